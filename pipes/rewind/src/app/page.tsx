@@ -14,12 +14,16 @@ import { useCurrentFrame } from "@/lib/hooks/use-current-frame";
 import { TimelineSlider } from "@/components/timeline/timeline";
 import { useTimelineStore } from "@/lib/hooks/use-timeline-store";
 import { hasFramesForDate } from "@/lib/actions/has-frames-date";
+import { getFrameTimestamp } from "@/lib/actions/get-frame-timestamp";
 import { CommandShortcut } from "@/components/ui/command";
 import { CurrentFrameTimeline } from "@/components/current-frame-timeline";
 
 export interface StreamTimeSeriesResponse {
 	timestamp: string;
 	devices: DeviceFrameResponse[];
+	// near-identical captures after this frame, shown until last_repeat_at
+	repeat_count?: number;
+	last_repeat_at?: string | null;
 }
 
 export interface DeviceFrameResponse {
@@ -39,6 +43,7 @@ export interface DeviceMetadata {
 }
 
 export interface AudioData {
+	transcription_id: number;
 	device_name: string;
 	is_input: boolean;
 	transcription: string;
@@ -52,6 +57,9 @@ export interface TimeRange {
 	end: Date;
 }
 
+// how close a loaded frame must be to a link's time to stop waiting for more
+const LINK_TARGET_TOLERANCE_MS = 60 * 1000;
+
 // Add this easing function at the top level
 const easeOutCubic = (x: number): number => {
 	return 1 - Math.pow(1 - x, 3);
@@ -80,6 +88,36 @@ export default function Timeline() {
 			setCurrentFrame(frame);
 		});
 
+	// screenpipe:// links open the timeline with ?frame_id= or ?timestamp=
+	const [linkTarget, setLinkTarget] = useState<Date | null>(null);
+
+	useEffect(() => {
+		const resolveLinkTarget = async () => {
+			const params = new URLSearchParams(window.location.search);
+			const frameId = params.get("frame_id");
+			const timestamp = params.get("timestamp");
+			if (frameId) {
+				const data = await getFrameTimestamp(Number(frameId));
+				if (!("error" in data)) {
+					setLinkTarget(data);
+				}
+			} else if (timestamp) {
+				const date = new Date(timestamp);
+				if (!isNaN(date.getTime())) {
+					setLinkTarget(date);
+				}
+			}
+		};
+
+		resolveLinkTarget();
+	}, []);
+
+	useEffect(() => {
+		if (linkTarget && !isSameDay(linkTarget, currentDate)) {
+			setCurrentDate(linkTarget);
+		}
+	}, [linkTarget]);
+
 	useEffect(() => {
 		const getStartDateAndSet = async () => {
 			const data = await getStartDate();
@@ -246,6 +284,26 @@ export default function Timeline() {
 		}
 	};
 
+	useEffect(() => {
+		if (!linkTarget || frames.length === 0) return;
+
+		// frames stream in, jump once one is near the target or the target's
+		// day is loaded
+		const target = linkTarget.getTime();
+		const closestDiff = frames.reduce(
+			(closest, frame) =>
+				Math.min(closest, Math.abs(new Date(frame.timestamp).getTime() - target)),
+			Infinity,
+		);
+		const dayLoaded =
+			!isLoading &&
+			frames.some((frame) => isSameDay(new Date(frame.timestamp), linkTarget));
+		if (closestDiff <= LINK_TARGET_TOLERANCE_MS || dayLoaded) {
+			jumpToTime(linkTarget);
+			setLinkTarget(null);
+		}
+	}, [frames, isLoading, linkTarget]);
+
 	const handleDateChange = async (newDate: Date) => {
 		const checkFramesForDate = await hasFramesForDate(newDate);
 
@@ -1,4 +1,4 @@
-import { useState, useRef, useMemo, useCallback } from "react";
+import { useState, useRef, useMemo, useCallback, useEffect } from "react";
 import { AudioData, StreamTimeSeriesResponse, TimeRange } from "@/app/page";
 import { Button } from "@/components/ui/button";
 import { Card } from "@/components/ui/card";
@@ -13,6 +13,14 @@ interface AudioGroup {
 	endTime: Date;
 }
 
+// timing of a word in seconds from the start of the audio file
+interface TranscriptWord {
+	word: string;
+	start_time: number;
+	end_time: number;
+	confidence: number;
+}
+
 interface AudioTranscriptProps {
 	frames: StreamTimeSeriesResponse[];
 	currentIndex: number;
@@ -62,7 +70,9 @@ export function AudioTranscript({
 	groupingWindowMs = 30000,
 	onClose,
 }: AudioTranscriptProps) {
-	const [playing, setPlaying] = useState<string | null>(null);
+	const [playing, setPlaying] = useState<number | null>(null);
+	const [words, setWords] = useState<TranscriptWord[]>([]);
+	const [playbackTime, setPlaybackTime] = useState<number | null>(null);
 	const [position, setPosition] = useState(() => ({
 		x: window.innerWidth - 320,
 		y: 100,
@@ -143,10 +153,32 @@ export function AudioTranscript({
 		[isDragging, dragOffset],
 	);
 
-	const handlePlay = useCallback((audioPath: string) => {
-		setPlaying((current) => (current === audioPath ? null : audioPath));
+	const handlePlay = useCallback((transcriptionId: number) => {
+		setPlaying((current) =>
+			current === transcriptionId ? null : transcriptionId,
+		);
 	}, []);
 
+	// word timings of the playing transcript, to follow along while it plays
+	useEffect(() => {
+		setWords([]);
+		setPlaybackTime(null);
+		if (playing === null) return;
+
+		let cancelled = false;
+		fetch(`http://localhost:3030/audio/transcriptions/${playing}/words`)
+			.then((response) => (response.ok ? response.json() : null))
+			.then((body) => {
+				if (!cancelled && body?.data) setWords(body.data);
+			})
+			.catch((error) =>
+				console.warn("failed to load transcript words:", error),
+			);
+		return () => {
+			cancelled = true;
+		};
+	}, [playing]);
+
 	const handlePanelMouseDown = (e: React.MouseEvent<HTMLDivElement>) => {
 		setIsDragging(true);
 		setDragOffset({
@@ -256,9 +288,9 @@ export function AudioTranscript({
 										variant="ghost"
 										size="sm"
 										className="h-6 w-6 p-0"
-										onClick={() => handlePlay(audio.audio_file_path)}
+										onClick={() => handlePlay(audio.transcription_id)}
 									>
-										{playing === audio.audio_file_path ? (
+										{playing === audio.transcription_id ? (
 											<Pause className="h-3 w-3" />
 										) : (
 											<Play className="h-3 w-3" />
@@ -272,15 +304,38 @@ export function AudioTranscript({
 									</div>
 								</div>
 
-								{audio.transcription && (
+								{playing === audio.transcription_id && words.length > 0 ? (
 									<div className="text-xs pl-8 text-muted-foreground">
-										{audio.transcription}
+										{words.map((word, wordIndex) => (
+											<span
+												key={wordIndex}
+												title={`confidence ${Math.round(word.confidence * 100)}%`}
+												className={
+													playbackTime !== null &&
+													playbackTime >= word.start_time &&
+													playbackTime < word.end_time
+														? "bg-primary/20 text-foreground rounded-sm"
+														: undefined
+												}
+											>
+												{word.word}{" "}
+											</span>
+										))}
 									</div>
+								) : (
+									audio.transcription && (
+										<div className="text-xs pl-8 text-muted-foreground">
+											{audio.transcription}
+										</div>
+									)
 								)}
 
-								{playing === audio.audio_file_path && (
+								{playing === audio.transcription_id && (
 									<div className="pl-8">
-										<VideoComponent filePath={audio.audio_file_path} />
+										<VideoComponent
+											filePath={audio.audio_file_path}
+											onTimeUpdate={setPlaybackTime}
+										/>
 									</div>
 								)}
 							</div>
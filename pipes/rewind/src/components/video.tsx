@@ -6,10 +6,12 @@ export const VideoComponent = memo(function VideoComponent({
   filePath,
   customDescription,
   className,
+  onTimeUpdate,
 }: {
   filePath: string;
   customDescription?: string;
   className?: string;
+  onTimeUpdate?: (seconds: number) => void;
 }) {
   const [mediaSrc, setMediaSrc] = useState<string | null>(null);
   const [error, setError] = useState<string | null>(null);
@@ -115,7 +117,11 @@ export const VideoComponent = memo(function VideoComponent({
     <div className={cn("w-full max-w-2xl text-center", className)}>
       {isAudio ? (
         <div className="bg-gray-100 p-4 rounded-md">
-          <audio controls className="w-full">
+          <audio
+            controls
+            className="w-full"
+            onTimeUpdate={(e) => onTimeUpdate?.(e.currentTarget.currentTime)}
+          >
             <source src={mediaSrc} type="audio/mpeg" />
             Your browser does not support the audio element.
           </audio>
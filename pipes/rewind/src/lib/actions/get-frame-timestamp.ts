@@ -0,0 +1,38 @@
+"use server";
+
+export async function getFrameTimestamp(frameId: number) {
+	try {
+		const query = `
+            SELECT f.timestamp
+            FROM frames f
+            WHERE f.id = ${Math.trunc(frameId)}
+            LIMIT 1
+        `;
+
+		const response = await fetch("http://localhost:3030/raw_sql", {
+			method: "POST",
+			headers: {
+				"Content-Type": "application/json",
+			},
+			body: JSON.stringify({ query }),
+		});
+
+		if (!response.ok) {
+			return {
+				error: "Error occurred while fetching frame",
+				details: await response.json(),
+			};
+		}
+
+		const result = await response.json();
+		if (!result[0]?.timestamp) {
+			return { error: `frame ${frameId} not found` };
+		}
+		return new Date(result[0].timestamp);
+	} catch (e) {
+		return {
+			error: "Error occurred while fetching frame",
+			details: e,
+		};
+	}
+}
@@ -10,6 +10,7 @@ import Onboarding from "@/components/onboarding";
 import { useOnboarding } from "@/lib/hooks/use-onboarding";
 import { ChangelogDialog } from "@/components/changelog-dialog";
 import { BreakingChangesInstructionsDialog } from "@/components/breaking-changes-instructions-dialog";
+import { CrashReportDialog } from "@/components/crash-report-dialog";
 import { useChangelogDialog } from "@/lib/hooks/use-changelog-dialog";
 import { useStatusDialog } from "@/lib/hooks/use-status-dialog";
 import { useSettingsDialog } from "@/lib/hooks/use-settings-dialog";
@@ -22,6 +23,12 @@ import { relaunch } from "@tauri-apps/plugin-process";
 import { PipeApi } from "@/lib/api";
 import localforage from "localforage";
 import { onOpenUrl } from "@tauri-apps/plugin-deep-link";
+import {
+  parseTimelineLink,
+  TimelineLink,
+  TIMELINE_PIPE_ID,
+  timelinePath,
+} from "@/lib/deep-link";
 import { LoginDialog } from "../components/login-dialog";
 import { ModelDownloadTracker } from "../components/model-download-tracker";
 
@@ -48,10 +55,36 @@ export default function Home() {
       return devices;
     };
 
+    const openTimelineLink = async (link: TimelineLink) => {
+      const pipeApi = new PipeApi();
+      const pipeList = await pipeApi.listPipes();
+      const pipe = pipeList.find((p) => p.id === TIMELINE_PIPE_ID);
+      if (!pipe?.enabled || !pipe.port) {
+        toast({
+          title: "timeline not available",
+          description: `enable the ${TIMELINE_PIPE_ID} pipe to open screenpipe links`,
+          variant: "destructive",
+        });
+        return;
+      }
+      await invoke("open_pipe_window", {
+        port: pipe.port,
+        title: pipe.id,
+        path: timelinePath(link),
+      });
+    };
+
     const setupDeepLink = async () => {
       const unsubscribeDeepLink = await onOpenUrl(async (urls) => {
         console.log("received deep link urls:", urls);
         for (const url of urls) {
+          // screenpipe://frame/12345 or screenpipe://time/2024-06-01T10:00
+          const timelineLink = parseTimelineLink(url);
+          if (timelineLink) {
+            await openTimelineLink(timelineLink);
+            continue;
+          }
+
           const parsedUrl = new URL(url);
 
           // Handle API key auth
@@ -236,6 +269,7 @@ export default function Home() {
       ) : (
         <>
           <ChangelogDialog />
+          <CrashReportDialog />
           {/* <BreakingChangesInstructionsDialog /> */}
           <Header />
           <div className=" w-full">
@@ -125,12 +125,24 @@ export function CliCommandDialog({ settings }: CliCommandDialogProps) {
     settings.includedWindows.forEach((window) =>
       args.push(`--included-windows "${window}"`)
     );
+    settings.captureRegions?.forEach((region) =>
+      args.push(`--capture-region "${region}"`)
+    );
+    if (settings.captureMode === "window") {
+      args.push("--capture-mode window");
+    }
+    if (settings.capturePrivateBrowsing) {
+      args.push("--capture-private-browsing");
+    }
     if (settings.deepgramApiKey && settings.deepgramApiKey !== "default") {
       args.push(`--deepgram-api-key "${settings.deepgramApiKey}"`);
     }
     if (settings.fps !== 0.2) {
       args.push(`--fps ${settings.fps}`);
     }
+    if (settings.adaptiveFps) {
+      args.push("--adaptive-fps");
+    }
     if (settings.vadSensitivity !== "high") {
       args.push(`--vad-sensitivity ${settings.vadSensitivity}`);
     }
@@ -0,0 +1,111 @@
+import React, { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogFooter,
+  DialogHeader,
+  DialogTitle,
+} from "@/components/ui/dialog";
+import { Button } from "@/components/ui/button";
+import { Bug, Loader2 } from "lucide-react";
+import { useToast } from "@/components/ui/use-toast";
+
+interface CrashReport {
+  id: string;
+  crashed_at: string;
+  version: string;
+  reason: string | null;
+  module: string | null;
+}
+
+// asks whether to send the reports of recorder crashes, nothing leaves the
+// computer without a click on "send report"
+export function CrashReportDialog() {
+  const { toast } = useToast();
+  const [reports, setReports] = useState<CrashReport[]>([]);
+  const [isSending, setIsSending] = useState(false);
+
+  useEffect(() => {
+    invoke<CrashReport[]>("get_crash_reports")
+      .then(setReports)
+      .catch((error) => console.error("failed to list crash reports:", error));
+  }, []);
+
+  const report = reports[0];
+  if (!report) return null;
+
+  const next = () => setReports((reports) => reports.slice(1));
+
+  const handleSend = async () => {
+    setIsSending(true);
+    try {
+      await invoke("send_crash_report", { id: report.id });
+      toast({
+        title: "crash report sent",
+        description: "thanks, it helps us fix it",
+      });
+      next();
+    } catch (error) {
+      toast({
+        title: "failed to send crash report",
+        description: String(error),
+        variant: "destructive",
+      });
+    } finally {
+      setIsSending(false);
+    }
+  };
+
+  const handleDismiss = async () => {
+    try {
+      await invoke("dismiss_crash_report", { id: report.id });
+    } catch (error) {
+      console.error("failed to dismiss crash report:", error);
+    }
+    next();
+  };
+
+  return (
+    <Dialog open={!!report} onOpenChange={(open) => !open && handleDismiss()}>
+      <DialogContent className="sm:max-w-[525px]">
+        <DialogHeader>
+          <DialogTitle className="flex gap-2 items-center">
+            <Bug className="h-5 w-5" />
+            screenpipe crashed, send report?
+          </DialogTitle>
+          <DialogDescription className="space-y-4">
+            <p>
+              the recorder (v{report.version}) crashed on{" "}
+              {new Date(report.crashed_at).toLocaleString()}
+              {report.reason ? ` with ${report.reason}` : ""}
+              {report.module ? ` in ${report.module}` : ""}.
+            </p>
+            <p className="text-sm text-muted-foreground">
+              the report has where it crashed, the modules that were loaded and
+              the last lines of the recorder log. the minidump, which holds
+              memory of the recorder, stays on this computer in the crashes
+              folder of your data directory.
+            </p>
+          </DialogDescription>
+        </DialogHeader>
+        <DialogFooter>
+          <Button variant="outline" onClick={handleDismiss} disabled={isSending}>
+            don&apos;t send
+          </Button>
+          <Button onClick={handleSend} disabled={isSending}>
+            {isSending ? (
+              <>
+                <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+                sending...
+              </>
+            ) : (
+              "send report"
+            )}
+          </Button>
+        </DialogFooter>
+      </DialogContent>
+    </Dialog>
+  );
+}
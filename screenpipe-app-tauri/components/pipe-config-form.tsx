@@ -12,7 +12,12 @@ import {
 } from "./ui/tooltip";
 import { Layers, Layout, RefreshCw } from "lucide-react";
 import { toast } from "./ui/use-toast";
-import { InstalledPipe, PipeWithStatus } from "./pipe-store/types";
+import {
+  InstalledPipe,
+  PipeWithStatus,
+  SettingsError,
+} from "./pipe-store/types";
+import { PipeSettingsForm } from "./pipe-settings-form";
 import { SqlAutocompleteInput } from "./sql-autocomplete-input";
 import {
   Select,
@@ -32,6 +37,7 @@ import { FolderOpen } from "lucide-react";
 type PipeConfigFormProps = {
   pipe: PipeWithStatus;
   onConfigSave: (config: Record<string, any>) => void;
+  settingsErrors?: SettingsError[];
 };
 
 type FieldConfig = {
@@ -45,6 +51,7 @@ type FieldConfig = {
 export const PipeConfigForm: React.FC<PipeConfigFormProps> = ({
   pipe,
   onConfigSave,
+  settingsErrors,
 }) => {
   const [config, setConfig] = useState(pipe.installed_config);
 
@@ -65,6 +72,9 @@ export const PipeConfigForm: React.FC<PipeConfigFormProps> = ({
     });
   };
 
+  // pipes with a settings schema get a form built from it instead
+  const fields = config?.settings_schema ? undefined : config?.fields;
+
   const renderConfigInput = (field: FieldConfig) => {
     const value = field?.value ?? field?.default;
 
@@ -454,7 +464,18 @@ export const PipeConfigForm: React.FC<PipeConfigFormProps> = ({
         </MemoizedReactMarkdown>
       </div>
 
-      {config?.fields?.map((field: FieldConfig) => (
+      {config?.settings_schema && (
+        <PipeSettingsForm
+          schema={config.settings_schema}
+          settings={config.settings ?? {}}
+          errors={settingsErrors}
+          onChange={(settings) =>
+            setConfig((prev) => (prev ? { ...prev, settings } : prev))
+          }
+        />
+      )}
+
+      {fields?.map((field: FieldConfig) => (
         <div key={field.name} className="space-y-2">
           <Label htmlFor={field.name} className="font-medium">
             {field.name} ({field.type})
@@ -0,0 +1,237 @@
+import React from "react";
+import { Input } from "./ui/input";
+import { Label } from "./ui/label";
+import { Checkbox } from "./ui/checkbox";
+import { Textarea } from "./ui/textarea";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "./ui/select";
+import { SettingsError, SettingsSchema } from "./pipe-store/types";
+
+type PipeSettingsFormProps = {
+  schema: SettingsSchema;
+  settings: Record<string, any>;
+  errors?: SettingsError[];
+  onChange: (settings: Record<string, any>) => void;
+};
+
+const schemaType = (schema: SettingsSchema): string | undefined =>
+  Array.isArray(schema.type)
+    ? schema.type.find((type) => type !== "null")
+    : schema.type;
+
+// form of the settings of a pipe, built from the json schema in its pipe.json
+export const PipeSettingsForm: React.FC<PipeSettingsFormProps> = ({
+  schema,
+  settings,
+  errors = [],
+  onChange,
+}) => {
+  const renderProperty = (
+    path: string,
+    name: string,
+    property: SettingsSchema,
+    value: any,
+    setValue: (value: any) => void,
+    required: boolean
+  ): React.ReactNode => {
+    const id = path.replace(/^\$\./, "");
+    const type = schemaType(property);
+    const propertyErrors = errors.filter(
+      (error) => error.path === path || error.path.startsWith(`${path}[`)
+    );
+
+    if (type === "object" && property.properties) {
+      const object = value ?? {};
+      return (
+        <fieldset key={path} className="space-y-4 border rounded-md p-4">
+          <legend className="px-1 font-medium">{property.title ?? name}</legend>
+          {property.description && (
+            <p className="text-sm text-muted-foreground">
+              {property.description}
+            </p>
+          )}
+          {Object.entries(property.properties).map(([key, child]) =>
+            renderProperty(
+              `${path}.${key}`,
+              key,
+              child,
+              object[key],
+              (childValue) => setValue({ ...object, [key]: childValue }),
+              property.required?.includes(key) ?? false
+            )
+          )}
+        </fieldset>
+      );
+    }
+
+    const current = value ?? property.default;
+    let input: React.ReactNode;
+    if (property.enum) {
+      input = (
+        <Select
+          value={current === undefined ? undefined : JSON.stringify(current)}
+          onValueChange={(selected) => setValue(JSON.parse(selected))}
+        >
+          <SelectTrigger id={id}>
+            <SelectValue placeholder={`select ${name}`} />
+          </SelectTrigger>
+          <SelectContent>
+            {property.enum.map((option) => (
+              <SelectItem
+                key={JSON.stringify(option)}
+                value={JSON.stringify(option)}
+              >
+                {String(option)}
+              </SelectItem>
+            ))}
+          </SelectContent>
+        </Select>
+      );
+    } else if (type === "boolean") {
+      input = (
+        <Checkbox
+          id={id}
+          checked={current ?? false}
+          onCheckedChange={(checked) => setValue(checked === true)}
+        />
+      );
+    } else if (type === "number" || type === "integer") {
+      input = (
+        <Input
+          id={id}
+          type="number"
+          value={current ?? ""}
+          min={property.minimum}
+          max={property.maximum}
+          step={type === "integer" ? 1 : "any"}
+          onChange={(e) =>
+            setValue(
+              e.target.value === ""
+                ? undefined
+                : type === "integer"
+                ? parseInt(e.target.value)
+                : parseFloat(e.target.value)
+            )
+          }
+          onWheel={(e) => e.preventDefault()}
+        />
+      );
+    } else if (
+      type === "array" &&
+      property.items &&
+      schemaType(property.items) === "string"
+    ) {
+      // one item per line
+      input = (
+        <Textarea
+          id={id}
+          value={(current ?? []).join("\n")}
+          onChange={(e) =>
+            setValue(
+              e.target.value
+                .split("\n")
+                .map((item) => item.trim())
+                .filter((item) => item !== "")
+            )
+          }
+        />
+      );
+    } else if (type === "string" || type === undefined) {
+      input = (
+        <Input
+          id={id}
+          type="text"
+          value={current ?? ""}
+          minLength={property.minLength}
+          maxLength={property.maxLength}
+          pattern={property.pattern}
+          onChange={(e) => setValue(e.target.value)}
+          autoCorrect="off"
+          spellCheck="false"
+        />
+      );
+    } else {
+      // anything else is edited as json
+      input = (
+        <Textarea
+          id={id}
+          className="font-mono"
+          defaultValue={
+            current === undefined ? "" : JSON.stringify(current, null, 2)
+          }
+          onBlur={(e) => {
+            try {
+              setValue(
+                e.target.value === "" ? undefined : JSON.parse(e.target.value)
+              );
+            } catch {
+              // invalid json is caught when saving
+            }
+          }}
+        />
+      );
+    }
+
+    return (
+      <div key={path} className="space-y-2">
+        <Label htmlFor={id} className="font-medium">
+          {property.title ?? name}
+          {required && " *"}
+        </Label>
+        {input}
+        {property.description && (
+          <p className="text-sm text-muted-foreground">
+            {property.description}
+          </p>
+        )}
+        {propertyErrors.map((error) => (
+          <p key={error.path + error.message} className="text-sm text-red-500">
+            {error.path.replace(/^\$\./, "")} {error.message}
+          </p>
+        ))}
+      </div>
+    );
+  };
+
+  const rootErrors = errors.filter(
+    (error) =>
+      error.path === "$" ||
+      !Object.keys(schema.properties ?? {}).some((name) =>
+        error.path.startsWith(`$.${name}`)
+      )
+  );
+
+  return (
+    <div className="space-y-4">
+      {Object.entries(schema.properties ?? {}).map(([name, property]) =>
+        renderProperty(
+          `$.${name}`,
+          name,
+          property,
+          settings[name],
+          (value) => {
+            const updated = { ...settings };
+            if (value === undefined) {
+              delete updated[name];
+            } else {
+              updated[name] = value;
+            }
+            onChange(updated);
+          },
+          schema.required?.includes(name) ?? false
+        )
+      )}
+      {rootErrors.map((error) => (
+        <p key={error.path + error.message} className="text-sm text-red-500">
+          {error.path === "$" ? "" : `${error.path.replace(/^\$\./, "")} `}
+          {error.message}
+        </p>
+      ))}
+    </div>
+  );
+};
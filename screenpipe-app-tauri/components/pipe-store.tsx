@@ -21,7 +21,11 @@ import {
 } from "@/lib/api/store";
 import { open as openUrl } from "@tauri-apps/plugin-shell";
 import { listen } from "@tauri-apps/api/event";
-import { InstalledPipe, PipeWithStatus } from "./pipe-store/types";
+import {
+  InstalledPipe,
+  PipeWithStatus,
+  SettingsError,
+} from "./pipe-store/types";
 import { PipeDetails } from "./pipe-store/pipe-details";
 import { PipeCard } from "./pipe-store/pipe-card";
 import { AddPipeForm } from "./pipe-store/add-pipe-form";
@@ -65,6 +69,7 @@ const corePipes: string[] = [];
 export const PipeStore: React.FC = () => {
   const { health } = useHealthCheck();
   const [selectedPipe, setSelectedPipe] = useState<PipeWithStatus | null>(null);
+  const [settingsErrors, setSettingsErrors] = useState<SettingsError[]>([]);
   const { settings, updateSettings } = useSettings();
   const [pipes, setPipes] = useState<PipeWithStatus[]>([]);
   const [installedPipes, setInstalledPipes] = useState<InstalledPipe[]>([]);
@@ -252,7 +257,10 @@ export const PipeStore: React.FC = () => {
     }
   };
 
-  const handleInstallSideload = async (url: string) => {
+  const handleInstallSideload = async (
+    url: string,
+    allowUnsigned: boolean = false,
+  ) => {
     posthog.capture("add_own_pipe", {
       newRepoUrl: url,
     });
@@ -289,13 +297,34 @@ export const PipeStore: React.FC = () => {
         headers: {
           "Content-Type": "application/json",
         },
-        body: JSON.stringify({ url: url }),
+        body: JSON.stringify({ url: url, allow_unsigned: allowUnsigned }),
       });
 
       const data = await response.json();
 
       clearInterval(progressInterval);
 
+      if (!data.success && data.error?.includes("allow_unsigned")) {
+        // unsigned, signed by a publisher the user doesn't trust, or by another
+        // publisher than the installed version
+        t.update({
+          id: t.id,
+          title: "pipe isn't verified",
+          description: data.error,
+          variant: "destructive",
+          duration: 10000,
+          action: (
+            <ToastAction
+              altText="install anyway"
+              onClick={() => handleInstallSideload(url, true)}
+            >
+              install anyway
+            </ToastAction>
+          ),
+        });
+        return;
+      }
+
       if (!data.success) {
         throw new Error(data.error || "Failed to download pipe");
       }
@@ -736,6 +765,16 @@ export const PipeStore: React.FC = () => {
         });
 
         const data = await response.json();
+        // settings not matching the schema of the pipe are shown in the form
+        setSettingsErrors(data.errors ?? []);
+        if (data.errors) {
+          toast({
+            title: "invalid settings",
+            description: "please fix the highlighted settings.",
+            variant: "destructive",
+          });
+          return;
+        }
         if (!data.success) {
           throw new Error(data.error || "Failed to update pipe configuration");
         }
@@ -1378,7 +1417,10 @@ export const PipeStore: React.FC = () => {
     return (
       <PipeDetails
         pipe={selectedPipe}
-        onClose={() => setSelectedPipe(null)}
+        onClose={() => {
+          setSelectedPipe(null);
+          setSettingsErrors([]);
+        }}
         onToggle={handleTogglePipe}
         onConfigSave={handleConfigSave}
         onDelete={handleDeletePipe}
@@ -1388,6 +1430,7 @@ export const PipeStore: React.FC = () => {
         onPurchase={handlePurchasePipe}
         isLoadingPurchase={loadingPurchases.has(selectedPipe.id)}
         isLoadingInstall={loadingInstalls.has(selectedPipe.id)}
+        settingsErrors={settingsErrors}
       />
     );
   }
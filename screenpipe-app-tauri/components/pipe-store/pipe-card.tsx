@@ -90,6 +90,7 @@ export const PipeCard: React.FC<PipeCardProps> = ({
 }) => {
   const [isLoading, setIsLoading] = useState(false);
   const { settings } = useSettings();
+  const provenance = pipe.installed_config?.provenance;
 
   const handleOpenWindow = async (e: React.MouseEvent) => {
     e.stopPropagation();
@@ -407,6 +408,24 @@ export const PipeCard: React.FC<PipeCardProps> = ({
                 local
               </span>
             )}
+            {provenance && !provenance.signed && (
+              <span
+                className="inline-flex items-center gap-1 px-2 py-0.5 rounded-full bg-muted font-mono text-xs"
+                title="installed without a signature"
+              >
+                unsigned
+              </span>
+            )}
+            {provenance?.signed &&
+              !provenance.publisher_pinned &&
+              !provenance.publisher_trusted && (
+                <span
+                  className="inline-flex items-center gap-1 px-2 py-0.5 rounded-full bg-muted font-mono text-xs"
+                  title="installed with allow_unsigned, the publisher key isn't one you trust"
+                >
+                  unverified publisher
+                </span>
+              )}
             <span className="inline-flex items-center gap-1 px-2 py-0.5 rounded-full bg-white/5 font-mono text-xs">
               {pipe.installed_config?.version && "v"}
               {pipe.installed_config?.version}
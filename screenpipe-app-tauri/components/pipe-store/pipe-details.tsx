@@ -11,7 +11,7 @@ import {
   Loader2,
 } from "lucide-react";
 import { PipeStoreMarkdown } from "@/components/pipe-store-markdown";
-import { PipeWithStatus } from "./types";
+import { PipeWithStatus, SettingsError } from "./types";
 import {
   Tooltip,
   TooltipContent,
@@ -38,6 +38,7 @@ interface PipeDetailsProps {
   onPurchase: (pipe: PipeWithStatus, onComplete: () => void) => void;
   isLoadingPurchase?: boolean;
   isLoadingInstall?: boolean;
+  settingsErrors?: SettingsError[];
 }
 
 const buildStatusNotAllows = ["in_progress", "not_started"];
@@ -71,6 +72,7 @@ export const PipeDetails: React.FC<PipeDetailsProps> = ({
   onPurchase,
   isLoadingPurchase,
   isLoadingInstall,
+  settingsErrors,
 }) => {
   const [isLoading, setIsLoading] = useState(false);
   return (
@@ -223,6 +225,7 @@ export const PipeDetails: React.FC<PipeDetailsProps> = ({
                     onConfigSave={(config) => {
                       onConfigSave(config, () => setIsLoading(false));
                     }}
+                    settingsErrors={settingsErrors}
                   />
                 </div>
               )}
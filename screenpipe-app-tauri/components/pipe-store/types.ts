@@ -9,6 +9,18 @@ export type BuildStatus =
       error?: string;
     };
 
+// what was checked when the pipe was installed
+export interface Provenance {
+  source: string;
+  signed: boolean;
+  publisher_key?: string;
+  // signed by the publisher of the version it replaced, false on first installs
+  publisher_pinned?: boolean;
+  // signed with a key the user trusts
+  publisher_trusted?: boolean;
+  installed_at: string;
+}
+
 export interface InstalledConfig {
   port?: number;
   enabled?: boolean;
@@ -16,6 +28,30 @@ export interface InstalledConfig {
   buildStatus?: BuildStatus;
   is_nextjs?: boolean;
   source?: string;
+  provenance?: Provenance;
+}
+
+// json schema of the settings of a pipe, the parts the settings form renders
+export interface SettingsSchema {
+  type?: string | string[];
+  title?: string;
+  description?: string;
+  default?: any;
+  enum?: any[];
+  minimum?: number;
+  maximum?: number;
+  minLength?: number;
+  maxLength?: number;
+  pattern?: string;
+  items?: SettingsSchema;
+  properties?: Record<string, SettingsSchema>;
+  required?: string[];
+}
+
+// where saved settings don't match the schema, `path` being like `$.model`
+export interface SettingsError {
+  path: string;
+  message: string;
 }
 
 export interface InstalledPipe {
@@ -30,6 +66,8 @@ export interface InstalledPipe {
       schedule: string;
     }[];
     fields?: Record<string, any>;
+    settings_schema?: SettingsSchema;
+    settings?: Record<string, any>;
     version?: string;
   };
   desc: string;
@@ -13,6 +13,7 @@ import {
   Check,
   HardDrive,
   FolderInput,
+  Webhook,
   Settings as SettingsIcon,
 } from "lucide-react";
 import { DialogHeader, DialogTitle } from "./ui/dialog";
@@ -36,6 +37,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { useProfiles } from "@/lib/hooks/use-profiles";
 import { toast } from "./ui/use-toast";
 import { DataImportSection } from "./settings/data-import-section";
+import { WebhooksSection } from "./settings/webhooks-section";
 import { Dialog, DialogContent } from "./ui/dialog";
 import { useSettingsDialog } from "@/lib/hooks/use-settings-dialog";
 import { RecordingSettings } from "./settings/recording-settings";
@@ -48,7 +50,8 @@ type SettingsSection =
   | "recording"
   | "account"
   | "diskUsage"
-  | "dataImport";
+  | "dataImport"
+  | "webhooks";
 
 export function Settings() {
   const { isOpen, setIsOpen: setSettingsOpen } = useSettingsDialog();
@@ -122,6 +125,8 @@ export function Settings() {
         return <DiskUsage />;
       case "dataImport":
         return <DataImportSection />;
+      case "webhooks":
+        return <WebhooksSection />;
     }
   };
 
@@ -256,6 +261,11 @@ export function Settings() {
                   label: "data import",
                   icon: <FolderInput className="h-4 w-4" />,
                 },
+                {
+                  id: "webhooks",
+                  label: "webhooks",
+                  icon: <Webhook className="h-4 w-4" />,
+                },
               ].map((section) => (
                 <button
                   key={section.id}
@@ -363,7 +363,7 @@ export function RecordingSettings() {
     }
   };
 
-  const handleAudioTranscriptionModelChange = (
+  const handleAudioTranscriptionModelChange = async (
     value: string,
     realtime = false
   ) => {
@@ -389,6 +389,31 @@ export function RecordingSettings() {
     const newSettings = realtime
       ? { realtimeAudioTranscriptionEngine: value }
       : { audioTranscriptionEngine: value };
+
+    // whisper models can be swapped while recording, no restart needed
+    if (!realtime && value.startsWith("whisper")) {
+      try {
+        const response = await fetch(
+          "http://localhost:3030/audio/transcription-engine",
+          {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify({ engine: value }),
+          }
+        );
+        if (response.ok) {
+          handleSettingsChange(newSettings, false);
+          toast({
+            title: "transcription model switched",
+            description: `now transcribing with ${value}`,
+          });
+          return;
+        }
+      } catch (error) {
+        console.error("failed to switch transcription model:", error);
+      }
+    }
+
     handleSettingsChange(newSettings, true);
   };
 
@@ -416,6 +441,10 @@ export function RecordingSettings() {
     handleSettingsChange({ usePiiRemoval: checked }, true);
   };
 
+  const handlePrivateBrowsingChange = (checked: boolean) => {
+    handleSettingsChange({ capturePrivateBrowsing: checked }, true);
+  };
+
   const handleDisableAudioChange = (checked: boolean) => {
     handleSettingsChange({ disableAudio: checked }, true);
   };
@@ -464,6 +493,7 @@ export function RecordingSettings() {
         {currentPlatform === "macos" && (
           <SelectItem value="apple-native">apple native</SelectItem>
         )}
+        <SelectItem value="paddle">paddle ocr</SelectItem>
       </>
     );
   };
@@ -861,6 +891,37 @@ export function RecordingSettings() {
                     </span>
                   </div>
                 </div>
+                <div className="flex items-center justify-between">
+                  <div className="space-y-1">
+                    <Label
+                      htmlFor="adaptiveFps"
+                      className="flex items-center space-x-2"
+                    >
+                      <span>adaptive frame rate</span>
+                      <TooltipProvider>
+                        <Tooltip>
+                          <TooltipTrigger>
+                            <HelpCircle className="h-4 w-4 cursor-default" />
+                          </TooltipTrigger>
+                          <TooltipContent side="right">
+                            <p>
+                              slow down capture when your screen does not
+                              change and go back to the fps above as soon as
+                              something happens. saves cpu and disk when idle
+                            </p>
+                          </TooltipContent>
+                        </Tooltip>
+                      </TooltipProvider>
+                    </Label>
+                  </div>
+                  <Switch
+                    id="adaptiveFps"
+                    checked={settings.adaptiveFps}
+                    onCheckedChange={(checked) =>
+                      handleSettingsChange({ adaptiveFps: checked }, true)
+                    }
+                  />
+                </div>
                 <div className="space-y-6">
                   <div className="flex flex-col space-y-2">
                     <Label
@@ -944,6 +1005,90 @@ export function RecordingSettings() {
                       validateCustomValue={(value) => value.length >= 2}
                     />
                   </div>
+
+                  <div className="flex items-center justify-between">
+                    <div className="space-y-1">
+                      <Label
+                        htmlFor="captureMode"
+                        className="flex items-center space-x-2"
+                      >
+                        <span>record windows only</span>
+                        <TooltipProvider>
+                          <Tooltip>
+                            <TooltipTrigger>
+                              <HelpCircle className="h-4 w-4 cursor-default" />
+                            </TooltipTrigger>
+                            <TooltipContent side="right">
+                              <p>
+                                only record the windows that pass the
+                                included/ignored filters instead of the whole
+                                monitor. ignored windows are always blanked
+                                out of the recording.
+                                <br />
+                                use &quot;process:name&quot; or
+                                &quot;title:regex&quot; in the lists above for
+                                exact process or regex title matching
+                              </p>
+                            </TooltipContent>
+                          </Tooltip>
+                        </TooltipProvider>
+                      </Label>
+                    </div>
+                    <Switch
+                      id="captureMode"
+                      checked={settings.captureMode === "window"}
+                      onCheckedChange={(checked) =>
+                        handleSettingsChange(
+                          { captureMode: checked ? "window" : "monitor" },
+                          true
+                        )
+                      }
+                    />
+                  </div>
+
+                  <div className="flex flex-col space-y-2">
+                    <Label
+                      htmlFor="captureRegions"
+                      className="flex items-center space-x-2"
+                    >
+                      <span>capture regions</span>
+                      <TooltipProvider>
+                        <Tooltip>
+                          <TooltipTrigger>
+                            <HelpCircle className="h-4 w-4 cursor-default" />
+                          </TooltipTrigger>
+                          <TooltipContent side="right">
+                            <p>
+                              only record parts of a monitor, in pixels:
+                              <br />
+                              monitor_id:x,y,width,height
+                              <br />- &quot;1:0,0,1280,720&quot; records the
+                              top-left 1280x720 area of monitor 1
+                              <br />
+                              monitors without a region are recorded in full
+                            </p>
+                          </TooltipContent>
+                        </Tooltip>
+                      </TooltipProvider>
+                    </Label>
+                    <MultiSelect
+                      options={(settings.captureRegions ?? []).map(
+                        (region) => ({ value: region, label: region })
+                      )}
+                      defaultValue={settings.captureRegions ?? []}
+                      onValueChange={(values) =>
+                        handleSettingsChange({ captureRegions: values }, true)
+                      }
+                      placeholder="add region, e.g. 1:0,0,1280,720"
+                      variant="default"
+                      modalPopover={true}
+                      animation={2}
+                      allowCustomValues={true}
+                      validateCustomValue={(value) =>
+                        /^\d+:\d+,\d+,\d+,\d+$/.test(value.trim())
+                      }
+                    />
+                  </div>
                 </div>
 
                 {/*  */}
@@ -1416,6 +1561,21 @@ export function RecordingSettings() {
               </div>
             </div>
 
+            <div className="flex items-center justify-between">
+              <div className="space-y-1">
+                <h4 className="font-medium">record private browsing</h4>
+                <p className="text-sm text-muted-foreground">
+                  capture pauses while a private or incognito browser window
+                  is focused, turn on to record them anyway
+                </p>
+              </div>
+              <Switch
+                id="private-browsing-toggle"
+                checked={settings.capturePrivateBrowsing}
+                onCheckedChange={handlePrivateBrowsingChange}
+              />
+            </div>
+
             <div className="flex items-center justify-between">
               <div className="space-y-1">
                 <h4 className="font-medium">enable telemetry</h4>
@@ -0,0 +1,296 @@
+import React, { useEffect, useState } from "react";
+import { Plus, RotateCw, Trash2, Webhook as WebhookIcon } from "lucide-react";
+import { Button } from "../ui/button";
+import { Input } from "../ui/input";
+import { Label } from "../ui/label";
+import { Badge } from "../ui/badge";
+import { Checkbox } from "../ui/checkbox";
+import { Card, CardContent } from "../ui/card";
+import { toast } from "../ui/use-toast";
+
+const API_URL = "http://localhost:3030";
+
+const EVENT_TYPES = [
+  { id: "transcript_indexed", label: "new transcript" },
+  { id: "frame_indexed", label: "new screen text" },
+  { id: "app_switched", label: "app opened" },
+  { id: "health_changed", label: "health changed" },
+  { id: "saved_search_matched", label: "saved search matched" },
+  { id: "audio_device_switched", label: "audio device switched" },
+];
+
+interface Webhook {
+  id: number;
+  url: string;
+  events: string;
+  keyword: string | null;
+  app_name: string | null;
+  secret: string;
+  created_at: string;
+}
+
+interface WebhookDeadLetter {
+  id: number;
+  webhook_id: number;
+  event: string;
+  error: string;
+  attempts: number;
+  created_at: string;
+}
+
+async function api<T>(path: string, init?: RequestInit): Promise<T> {
+  const response = await fetch(`${API_URL}${path}`, {
+    ...init,
+    headers: { "Content-Type": "application/json" },
+  });
+  const body = await response.json();
+  if (!response.ok) {
+    throw new Error(body.error ?? `request failed: ${response.status}`);
+  }
+  return body.data;
+}
+
+export function WebhooksSection() {
+  const [webhooks, setWebhooks] = useState<Webhook[]>([]);
+  const [deadLetters, setDeadLetters] = useState<WebhookDeadLetter[]>([]);
+  const [url, setUrl] = useState("");
+  const [events, setEvents] = useState<string[]>(["transcript_indexed"]);
+  const [keyword, setKeyword] = useState("");
+  const [appName, setAppName] = useState("");
+  const [isSaving, setIsSaving] = useState(false);
+
+  const load = async () => {
+    try {
+      setWebhooks(await api<Webhook[]>("/webhooks"));
+      setDeadLetters(
+        await api<WebhookDeadLetter[]>("/webhooks/dead-letters?limit=20")
+      );
+    } catch (error: any) {
+      console.error("failed to load webhooks:", error);
+    }
+  };
+
+  useEffect(() => {
+    load();
+  }, []);
+
+  const toggleEvent = (event: string, checked: boolean) => {
+    setEvents((prev) =>
+      checked ? [...prev, event] : prev.filter((e) => e !== event)
+    );
+  };
+
+  const handleAdd = async () => {
+    try {
+      setIsSaving(true);
+      const webhook = await api<Webhook>("/webhooks", {
+        method: "POST",
+        body: JSON.stringify({
+          url: url.trim(),
+          events,
+          keyword: keyword.trim() || null,
+          app_name: appName.trim() || null,
+        }),
+      });
+      setUrl("");
+      setKeyword("");
+      setAppName("");
+      toast({
+        title: "webhook added",
+        description: `payloads are signed with ${webhook.secret}`,
+      });
+      await load();
+    } catch (error: any) {
+      toast({
+        title: "failed to add webhook",
+        description: error.message,
+        variant: "destructive",
+      });
+    } finally {
+      setIsSaving(false);
+    }
+  };
+
+  const handleDelete = async (id: number) => {
+    try {
+      await api(`/webhooks/${id}`, { method: "DELETE" });
+      await load();
+    } catch (error: any) {
+      toast({
+        title: "failed to delete webhook",
+        description: error.message,
+        variant: "destructive",
+      });
+    }
+  };
+
+  const handleRetry = async (id: number) => {
+    try {
+      const { delivered } = await api<{ delivered: boolean }>(
+        `/webhooks/dead-letters/${id}/retry`,
+        { method: "POST" }
+      );
+      toast({
+        title: delivered ? "delivered" : "delivery failed again",
+        variant: delivered ? "default" : "destructive",
+      });
+      await load();
+    } catch (error: any) {
+      toast({
+        title: "failed to retry delivery",
+        description: error.message,
+        variant: "destructive",
+      });
+    }
+  };
+
+  return (
+    <div className="w-full space-y-6 py-4">
+      <div>
+        <h1 className="text-2xl font-bold">webhooks</h1>
+        <p className="text-sm text-gray-500">
+          post capture and search events to your own urls. payloads are signed
+          with the webhook secret in the X-Screenpipe-Signature header
+        </p>
+      </div>
+
+      <Card>
+        <CardContent className="pt-6 space-y-4">
+          <div className="space-y-2">
+            <Label>url</Label>
+            <Input
+              placeholder="https://example.com/screenpipe"
+              value={url}
+              onChange={(e) => setUrl(e.target.value)}
+              className="font-mono"
+              autoCorrect="off"
+              autoComplete="off"
+              autoCapitalize="off"
+            />
+          </div>
+          <div className="space-y-2">
+            <Label>events</Label>
+            <div className="flex flex-wrap gap-4">
+              {EVENT_TYPES.map((event) => (
+                <div key={event.id} className="flex items-center gap-2">
+                  <Checkbox
+                    id={`webhook-event-${event.id}`}
+                    checked={events.includes(event.id)}
+                    onCheckedChange={(checked) =>
+                      toggleEvent(event.id, checked === true)
+                    }
+                  />
+                  <Label
+                    htmlFor={`webhook-event-${event.id}`}
+                    className="text-sm"
+                  >
+                    {event.label}
+                  </Label>
+                </div>
+              ))}
+            </div>
+          </div>
+          <div className="grid grid-cols-2 gap-4">
+            <div className="space-y-2">
+              <Label>keyword (optional)</Label>
+              <Input
+                placeholder="only events mentioning this"
+                value={keyword}
+                onChange={(e) => setKeyword(e.target.value)}
+              />
+            </div>
+            <div className="space-y-2">
+              <Label>app (optional)</Label>
+              <Input
+                placeholder="only events of this app"
+                value={appName}
+                onChange={(e) => setAppName(e.target.value)}
+              />
+            </div>
+          </div>
+          <Button
+            onClick={handleAdd}
+            disabled={isSaving || !url.trim() || events.length === 0}
+          >
+            <Plus className="h-4 w-4 mr-2" />
+            add webhook
+          </Button>
+        </CardContent>
+      </Card>
+
+      <div className="space-y-2">
+        {webhooks.length === 0 && (
+          <p className="text-sm text-muted-foreground">no webhooks yet</p>
+        )}
+        {webhooks.map((webhook) => (
+          <div
+            key={webhook.id}
+            className="flex items-center justify-between border rounded-lg p-3"
+          >
+            <div className="space-y-1 min-w-0">
+              <div className="flex items-center gap-2">
+                <WebhookIcon className="h-4 w-4 shrink-0" />
+                <span className="font-mono text-sm truncate">
+                  {webhook.url}
+                </span>
+              </div>
+              <div className="flex flex-wrap gap-1">
+                {webhook.events.split(",").map((event) => (
+                  <Badge key={event} variant="outline">
+                    {event}
+                  </Badge>
+                ))}
+                {webhook.keyword && (
+                  <Badge variant="secondary">
+                    keyword: {webhook.keyword}
+                  </Badge>
+                )}
+                {webhook.app_name && (
+                  <Badge variant="secondary">app: {webhook.app_name}</Badge>
+                )}
+              </div>
+            </div>
+            <Button
+              variant="ghost"
+              size="icon"
+              onClick={() => handleDelete(webhook.id)}
+            >
+              <Trash2 className="h-4 w-4" />
+            </Button>
+          </div>
+        ))}
+      </div>
+
+      {deadLetters.length > 0 && (
+        <div className="space-y-2">
+          <h3 className="text-lg font-semibold">failed deliveries</h3>
+          {deadLetters.map((deadLetter) => (
+            <div
+              key={deadLetter.id}
+              className="flex items-center justify-between border rounded-lg p-3"
+            >
+              <div className="space-y-1 min-w-0">
+                <div className="text-sm">
+                  {deadLetter.event} to webhook {deadLetter.webhook_id},{" "}
+                  {deadLetter.attempts} attempts
+                </div>
+                <div className="text-xs text-muted-foreground truncate">
+                  {new Date(deadLetter.created_at).toLocaleString()}:{" "}
+                  {deadLetter.error}
+                </div>
+              </div>
+              <Button
+                variant="outline"
+                size="sm"
+                onClick={() => handleRetry(deadLetter.id)}
+              >
+                <RotateCw className="h-4 w-4 mr-2" />
+                retry
+              </Button>
+            </div>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
@@ -0,0 +1,32 @@
+// screenpipe://frame/12345 and screenpipe://time/2024-06-01T10:00 open the
+// timeline of the rewind pipe at that frame or moment
+export const TIMELINE_PIPE_ID = "rewind";
+
+export type TimelineLink =
+  | { kind: "frame"; frameId: number }
+  | { kind: "time"; timestamp: Date };
+
+export function parseTimelineLink(url: string): TimelineLink | null {
+  const match = url.match(/^screenpipe:\/\/(frame|time)\/([^?#]+?)\/?$/);
+  if (!match) return null;
+
+  const [, kind, value] = match;
+  if (kind === "frame") {
+    const frameId = Number(value);
+    return Number.isInteger(frameId) ? { kind: "frame", frameId } : null;
+  }
+
+  // times without an offset are local, like in exports
+  const timestamp = new Date(decodeURIComponent(value));
+  return isNaN(timestamp.getTime()) ? null : { kind: "time", timestamp };
+}
+
+// path of the pipe's page that jumps to the link
+export function timelinePath(link: TimelineLink): string {
+  const params = new URLSearchParams(
+    link.kind === "frame"
+      ? { frame_id: String(link.frameId) }
+      : { timestamp: link.timestamp.toISOString() }
+  );
+  return `/?${params.toString()}`;
+}
@@ -102,6 +102,10 @@ export type Settings = {
 	disableAudio: boolean;
 	ignoredWindows: string[];
 	includedWindows: string[];
+	captureRegions: string[];
+	captureMode: "monitor" | "window";
+	capturePrivateBrowsing: boolean;
+	adaptiveFps: boolean;
 	aiProviderType: AIProviderType;
 	aiUrl: string;
 	aiMaxContextChars: number;
@@ -168,6 +172,10 @@ const DEFAULT_SETTINGS: Settings = {
 	disableAudio: false,
 	ignoredWindows: [],
 	includedWindows: [],
+	captureRegions: [],
+	captureMode: "monitor",
+	capturePrivateBrowsing: false,
+	adaptiveFps: false,
 	aiProviderType: "openai",
 	aiUrl: "https://api.openai.com/v1",
 	aiMaxContextChars: 512000,
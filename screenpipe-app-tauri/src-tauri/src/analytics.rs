@@ -1,29 +1,299 @@
-use log::{error, warn};
+use log::{debug, error, info, warn};
 use reqwest::Client;
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 use serde_json::json;
+use std::future::Future;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use sysinfo::{System, SystemExt};
+use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
 use tokio::time::interval;
 
-pub struct AnalyticsManager {
+pub type SinkError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Destination for analytics events. `AnalyticsManager` collects the event
+/// properties (system info, disk usage, ...) and hands them to the sink, so
+/// self-hosted backends only need to implement delivery.
+pub trait AnalyticsSink: Send + Sync + 'static {
+    fn send_event(
+        &self,
+        event: &str,
+        properties: serde_json::Value,
+    ) -> impl Future<Output = Result<(), SinkError>> + Send;
+
+    fn flush(&self) -> impl Future<Output = Result<(), SinkError>> + Send;
+
+    fn shutdown(&self) -> impl Future<Output = Result<(), SinkError>> + Send;
+}
+
+pub struct PostHogSink {
+    client: Client,
+    api_key: String,
+    api_host: String,
+}
+
+impl PostHogSink {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            api_host: "https://eu.i.posthog.com".to_string(),
+        }
+    }
+}
+
+impl AnalyticsSink for PostHogSink {
+    async fn send_event(
+        &self,
+        event: &str,
+        properties: serde_json::Value,
+    ) -> Result<(), SinkError> {
+        let posthog_url = format!("{}/capture/", self.api_host);
+        let payload = json!({
+            "api_key": self.api_key,
+            "event": event,
+            "properties": properties,
+        });
+
+        let response = self.client.post(posthog_url).json(&payload).send().await?;
+
+        if !response.status().is_success() {
+            return Err(format!("PostHog API error: {}", response.status()).into());
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<(), SinkError> {
+        // events are sent immediately, nothing is buffered
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> Result<(), SinkError> {
+        Ok(())
+    }
+}
+
+/// Appends every event as one JSON line to a local file, useful for
+/// air-gapped setups or for piping into another collector.
+#[allow(dead_code)]
+pub struct JsonlFileSink {
+    path: PathBuf,
+    file: Mutex<Option<tokio::fs::File>>,
+}
+
+#[allow(dead_code)]
+impl JsonlFileSink {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: Mutex::new(None),
+        }
+    }
+}
+
+impl AnalyticsSink for JsonlFileSink {
+    async fn send_event(
+        &self,
+        event: &str,
+        properties: serde_json::Value,
+    ) -> Result<(), SinkError> {
+        let mut file = self.file.lock().await;
+        if file.is_none() {
+            *file = Some(
+                tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)
+                    .await?,
+            );
+        }
+
+        let mut line = serde_json::to_vec(&json!({
+            "event": event,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "properties": properties,
+        }))?;
+        line.push(b'\n');
+
+        file.as_mut().unwrap().write_all(&line).await?;
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<(), SinkError> {
+        if let Some(file) = self.file.lock().await.as_mut() {
+            file.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> Result<(), SinkError> {
+        if let Some(mut file) = self.file.lock().await.take() {
+            file.flush().await?;
+            file.sync_all().await?;
+        }
+        Ok(())
+    }
+}
+
+const OFFLINE_QUEUE_FILE: &str = "analytics_queue.jsonl";
+const MAX_QUEUED_EVENTS: usize = 10_000;
+const RETRY_INITIAL_BACKOFF: Duration = Duration::from_secs(30);
+const RETRY_MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize, Deserialize)]
+struct QueuedEvent {
+    event: String,
+    properties: serde_json::Value,
+}
+
+/// Append-only on-disk buffer for events the sink failed to deliver (e.g. the
+/// machine is offline). Events are replayed in order once the sink accepts
+/// them again.
+struct OfflineQueue {
+    path: PathBuf,
+    capacity: usize,
+    lock: Mutex<()>,
+}
+
+impl OfflineQueue {
+    fn new(path: PathBuf) -> Self {
+        Self::with_capacity(path, MAX_QUEUED_EVENTS)
+    }
+
+    fn with_capacity(path: PathBuf, capacity: usize) -> Self {
+        Self {
+            path,
+            capacity,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Queues an event, dropping the oldest ones once `capacity` is reached so
+    /// a long stretch offline can't grow the file without bound.
+    async fn push(&self, event: &str, properties: serde_json::Value) -> Result<(), SinkError> {
+        let _guard = self.lock.lock().await;
+
+        let queued = QueuedEvent {
+            event: event.to_string(),
+            properties,
+        };
+
+        let mut events = self.read_all().await?;
+        if events.len() >= self.capacity {
+            let overflow = events.len() + 1 - self.capacity;
+            warn!("analytics queue full, dropping {} oldest events", overflow);
+            events.drain(..overflow);
+            events.push(queued);
+            return self.rewrite(&events).await;
+        }
+
+        let mut line = serde_json::to_vec(&queued)?;
+        line.push(b'\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        Ok(())
+    }
+
+    async fn read_all(&self) -> Result<Vec<QueuedEvent>, SinkError> {
+        let content = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(event) => Some(event),
+                Err(e) => {
+                    warn!("dropping corrupted queued analytics event: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    async fn rewrite(&self, events: &[QueuedEvent]) -> Result<(), SinkError> {
+        if events.is_empty() {
+            match tokio::fs::remove_file(&self.path).await {
+                Ok(()) => return Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut content = Vec::new();
+        for event in events {
+            content.extend(serde_json::to_vec(event)?);
+            content.push(b'\n');
+        }
+
+        // write to a temp file first so a crash mid-write can't lose the queue
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        tokio::fs::write(&tmp_path, content).await?;
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+        Ok(())
+    }
+
+    /// Replays queued events through the sink. Stops at the first failure and
+    /// keeps the remaining events on disk. Returns the number of events sent.
+    async fn drain<S: AnalyticsSink>(&self, sink: &S) -> Result<usize, SinkError> {
+        let _guard = self.lock.lock().await;
+
+        let mut events = self.read_all().await?;
+        if events.len() > self.capacity {
+            let overflow = events.len() - self.capacity;
+            warn!("analytics queue full, dropping {} oldest events", overflow);
+            events.drain(..overflow);
+        }
+
+        let mut sent = 0;
+        let mut result = Ok(());
+        for queued in &events {
+            if let Err(e) = sink
+                .send_event(&queued.event, queued.properties.clone())
+                .await
+            {
+                result = Err(e);
+                break;
+            }
+            sent += 1;
+        }
+
+        self.rewrite(&events[sent..]).await?;
+        if sent > 0 {
+            sink.flush().await?;
+        }
+
+        result.map(|_| sent)
+    }
+}
+
+pub struct AnalyticsManager<S: AnalyticsSink = PostHogSink> {
     client: Client,
-    posthog_api_key: String,
+    sink: S,
+    queue: OfflineQueue,
     distinct_id: String,
     email: String,
     interval: Duration,
     enabled: Arc<Mutex<bool>>,
-    api_host: String,
     local_api_base_url: String,
     screenpipe_dir_path: PathBuf,
 }
 
-impl AnalyticsManager {
+impl<S: AnalyticsSink> AnalyticsManager<S> {
     pub fn new(
-        posthog_api_key: String,
+        sink: S,
         distinct_id: String,
         email: String,
         interval_hours: u64,
@@ -33,12 +303,12 @@ impl AnalyticsManager {
     ) -> Self {
         Self {
             client: Client::new(),
-            posthog_api_key,
+            sink,
+            queue: OfflineQueue::new(screenpipe_dir_path.join(OFFLINE_QUEUE_FILE)),
             distinct_id,
             email,
             interval: Duration::from_secs(interval_hours * 36),
             enabled: Arc::new(Mutex::new(analytics_enabled)),
-            api_host: "https://eu.i.posthog.com".to_string(),
             local_api_base_url,
             screenpipe_dir_path,
         }
@@ -53,12 +323,9 @@ impl AnalyticsManager {
             return Ok(());
         }
 
-        let posthog_url = format!("{}/capture/", self.api_host);
         let system = System::new_all();
 
         let mut payload = json!({
-            "api_key": self.posthog_api_key,
-            "event": event,
             "properties": {
                 "distinct_id": self.distinct_id,
                 "$lib": "rust-reqwest",
@@ -97,14 +364,63 @@ impl AnalyticsManager {
             }
         }
 
+        let properties = payload["properties"].take();
+        if let Err(e) = self.sink.send_event(event, properties.clone()).await {
+            warn!(
+                "failed to send analytics event '{}', queueing for retry: {}",
+                event, e
+            );
+            self.queue
+                .push(event, properties)
+                .await
+                .map_err(|e| e as Box<dyn std::error::Error>)?;
+        }
 
-        let response = self.client.post(posthog_url).json(&payload).send().await?;
+        Ok(())
+    }
 
-        if !response.status().is_success() {
-            return Err(format!("PostHog API error: {}", response.status()).into());
+    /// Periodically replays events that were queued while offline, backing off
+    /// exponentially while the sink keeps failing.
+    pub async fn start_retry_loop(&self) {
+        let mut backoff = RETRY_INITIAL_BACKOFF;
+
+        loop {
+            tokio::time::sleep(backoff).await;
+
+            if !*self.enabled.lock().await {
+                continue;
+            }
+
+            match self.queue.drain(&self.sink).await {
+                Ok(sent) => {
+                    if sent > 0 {
+                        info!("flushed {} queued analytics events", sent);
+                    }
+                    backoff = RETRY_INITIAL_BACKOFF;
+                }
+                Err(e) => {
+                    backoff = (backoff * 2).min(RETRY_MAX_BACKOFF);
+                    debug!(
+                        "analytics still unreachable ({}), retrying in {:?}",
+                        e, backoff
+                    );
+                }
+            }
         }
+    }
 
-        Ok(())
+    pub async fn flush(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.sink
+            .flush()
+            .await
+            .map_err(|e| e as Box<dyn std::error::Error>)
+    }
+
+    pub async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.sink
+            .shutdown()
+            .await
+            .map_err(|e| e as Box<dyn std::error::Error>)
     }
 
     pub async fn start_periodic_event(&self) {
@@ -133,7 +449,7 @@ impl AnalyticsManager {
                     .send_event("app_still_running", Some(health_status))
                     .await
                 {
-                    error!("failed to send periodic posthog event: {}", e);
+                    error!("failed to send periodic analytics event: {}", e);
                 }
 
                 // Track enabled pipes
@@ -167,16 +483,51 @@ impl AnalyticsManager {
         let audio_status = health["audio_status"].as_str().unwrap_or("unknown");
         let ui_status = health["ui_status"].as_str().unwrap_or("unknown");
 
-        // Consider healthy if all enabled systems are "ok"
+        // codes only, subsystems are named after the user's devices
+        let mut remediation_codes: Vec<&str> = health["remediations"]
+            .as_array()
+            .map(|remediations| {
+                remediations
+                    .iter()
+                    .filter_map(|remediation| remediation["code"].as_str())
+                    .collect()
+            })
+            .unwrap_or_default();
+        remediation_codes.sort();
+        remediation_codes.dedup();
+
+        // Consider healthy if all enabled systems are "ok" and nothing needs fixing
         let is_healthy = (frame_status == "ok" || frame_status == "disabled")
             && (audio_status == "ok" || audio_status == "disabled")
-            && (ui_status == "ok" || ui_status == "disabled");
+            && (ui_status == "ok" || ui_status == "disabled")
+            && remediation_codes.is_empty();
+
+        let subsystems = &health["subsystems"];
+        let count_stale = |devices: &serde_json::Value| {
+            devices.as_array().map(|devices| {
+                devices
+                    .iter()
+                    .filter(|device| device["status"] == "stale")
+                    .count()
+            })
+        };
 
         Ok(json!({
             "is_healthy": is_healthy,
             "frame_status": frame_status,
             "audio_status": audio_status,
-            "ui_status": ui_status
+            "ui_status": ui_status,
+            "remediation_codes": remediation_codes,
+            "monitor_count": subsystems["monitors"].as_array().map(Vec::len),
+            "stale_monitor_count": count_stale(&subsystems["monitors"]),
+            "audio_device_count": subsystems["audio_devices"].as_array().map(Vec::len),
+            "stale_audio_device_count": count_stale(&subsystems["audio_devices"]),
+            "ocr_queue_depth": subsystems["ocr"]["queue_depth"],
+            "deferred_ocr_pending": subsystems["ocr"]["deferred_pending"],
+            "db_writable": subsystems["database"]["writable"],
+            "disk_free_bytes": subsystems["disk"]["free_bytes"],
+            "transcription_model_loaded": subsystems["transcription_model_loaded"],
+            "watchdog_restarts": subsystems["restarts"].as_array().map(Vec::len)
         }))
     }
 
@@ -201,22 +552,22 @@ impl AnalyticsManager {
     }
 }
 
-pub fn start_analytics(
+pub fn start_analytics<S: AnalyticsSink>(
     unique_id: String,
     email: String,
-    posthog_api_key: String,
+    sink: S,
     interval_hours: u64,
     local_api_base_url: String,
     screenpipe_dir_path: PathBuf,
     analytics_enabled: bool,
-) -> Result<Arc<AnalyticsManager>, Box<dyn std::error::Error>> {
+) -> Result<Arc<AnalyticsManager<S>>, Box<dyn std::error::Error>> {
     let is_debug = std::env::var("TAURI_ENV_DEBUG").unwrap_or("false".to_string()) == "true";
     
     // Skip analytics in debug mode or when debug assertions are enabled
     let should_enable_analytics = analytics_enabled && !is_debug && !cfg!(debug_assertions);
 
     let analytics_manager = Arc::new(AnalyticsManager::new(
-        posthog_api_key,
+        sink,
         unique_id,
         email,
         interval_hours,
@@ -230,11 +581,19 @@ pub fn start_analytics(
         let analytics_manager = analytics_manager.clone();
         async move {
             if let Err(e) = analytics_manager.send_event("app_started", None).await {
-                error!("Failed to send initial PostHog event: {}", e);
+                error!("Failed to send initial analytics event: {}", e);
             }
         }
     });
 
+    // Replay events queued while offline
+    tokio::spawn({
+        let analytics_manager = analytics_manager.clone();
+        async move {
+            analytics_manager.start_retry_loop().await;
+        }
+    });
+
     // Start periodic events
     tokio::spawn({
         let analytics_manager = analytics_manager.clone();
@@ -258,3 +617,105 @@ struct PipeListResponse {
     #[allow(dead_code)]
     success: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Records the events it accepts, fails every send while offline.
+    struct FakeSink {
+        online: AtomicBool,
+        sent: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl FakeSink {
+        fn new(online: bool) -> Self {
+            Self {
+                online: AtomicBool::new(online),
+                sent: std::sync::Mutex::new(Vec::new()),
+            }
+        }
+
+        fn sent(&self) -> Vec<String> {
+            self.sent.lock().unwrap().clone()
+        }
+    }
+
+    impl AnalyticsSink for FakeSink {
+        async fn send_event(
+            &self,
+            event: &str,
+            _properties: serde_json::Value,
+        ) -> Result<(), SinkError> {
+            if !self.online.load(Ordering::SeqCst) {
+                return Err("offline".into());
+            }
+            self.sent.lock().unwrap().push(event.to_string());
+            Ok(())
+        }
+
+        async fn flush(&self) -> Result<(), SinkError> {
+            Ok(())
+        }
+
+        async fn shutdown(&self) -> Result<(), SinkError> {
+            Ok(())
+        }
+    }
+
+    fn queue_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "screenpipe-analytics-{}-{}.jsonl",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn test_push_drops_oldest_events_when_full() {
+        let path = queue_path("cap");
+        let queue = OfflineQueue::with_capacity(path.clone(), 3);
+
+        for i in 0..5 {
+            queue
+                .push(&format!("event_{}", i), json!({}))
+                .await
+                .unwrap();
+        }
+
+        let events: Vec<String> = queue
+            .read_all()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|queued| queued.event)
+            .collect();
+        assert_eq!(events, vec!["event_2", "event_3", "event_4"]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_queued_events_replay_in_order_after_reconnect() {
+        let path = queue_path("replay");
+        let queue = OfflineQueue::new(path.clone());
+        let sink = FakeSink::new(false);
+
+        queue.push("first", json!({})).await.unwrap();
+        queue.push("second", json!({})).await.unwrap();
+
+        // still offline, nothing is sent and nothing is lost
+        assert!(queue.drain(&sink).await.is_err());
+        assert!(sink.sent().is_empty());
+        assert_eq!(queue.read_all().await.unwrap().len(), 2);
+
+        sink.online.store(true, Ordering::SeqCst);
+        assert_eq!(queue.drain(&sink).await.unwrap(), 2);
+        assert_eq!(sink.sent(), vec!["first", "second"]);
+        assert!(queue.read_all().await.unwrap().is_empty());
+        assert!(!path.exists());
+    }
+}
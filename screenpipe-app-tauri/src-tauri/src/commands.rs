@@ -212,6 +212,7 @@ pub async fn open_pipe_window(
     app_handle: tauri::AppHandle<tauri::Wry>,
     port: u16,
     title: String,
+    path: Option<String>,
 ) -> Result<(), String> {
     // Close existing window if it exists
     if let Some(existing_window) = app_handle.get_webview_window(&title) {
@@ -224,7 +225,11 @@ pub async fn open_pipe_window(
     let window = match tauri::WebviewWindowBuilder::new(
         &app_handle,
         &title,
-        tauri::WebviewUrl::External(format!("http://localhost:{}", port).parse().unwrap()),
+        tauri::WebviewUrl::External(
+            format!("http://localhost:{}{}", port, path.unwrap_or_default())
+                .parse()
+                .map_err(|e| format!("invalid pipe path: {}", e))?,
+        ),
     )
     .title(title)
     .inner_size(1200.0, 850.0)
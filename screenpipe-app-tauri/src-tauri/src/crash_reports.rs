@@ -0,0 +1,104 @@
+use crate::analytics::AnalyticsManager;
+use crate::get_data_dir;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+/// Bytes of the end of the recorder log sent with a crash report.
+const LOG_TAIL_BYTES: usize = 32 * 1024;
+
+/// Directory of a crash report written by the recorder, see
+/// `screenpipe_server::crash`.
+fn report_dir(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    // ids are timestamps, anything else could point outside the crashes
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("invalid crash report id: {}", id));
+    }
+    let data_dir = get_data_dir(app).map_err(|e| e.to_string())?;
+    Ok(data_dir.join("crashes").join(id))
+}
+
+async fn read_report(dir: &Path) -> Result<Value, String> {
+    let content = tokio::fs::read(dir.join("report.json"))
+        .await
+        .map_err(|e| e.to_string())?;
+    serde_json::from_slice(&content).map_err(|e| e.to_string())
+}
+
+async fn set_status(dir: &Path, mut report: Value, status: &str) -> Result<(), String> {
+    report["status"] = json!(status);
+    let content = serde_json::to_vec_pretty(&report).map_err(|e| e.to_string())?;
+    tokio::fs::write(dir.join("report.json"), content)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Crash reports of the recorder the user hasn't sent or dismissed yet,
+/// newest first.
+#[tauri::command]
+pub async fn get_crash_reports(app: AppHandle) -> Result<Vec<Value>, String> {
+    let crashes_dir = get_data_dir(&app)
+        .map_err(|e| e.to_string())?
+        .join("crashes");
+    let mut reports = Vec::new();
+    let mut dir = match tokio::fs::read_dir(&crashes_dir).await {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(reports),
+        Err(e) => return Err(e.to_string()),
+    };
+    while let Some(entry) = dir.next_entry().await.map_err(|e| e.to_string())? {
+        // reports still being written have no report.json yet
+        if let Ok(report) = read_report(&entry.path()).await {
+            if report["status"] == "pending" {
+                reports.push(report);
+            }
+        }
+    }
+    reports.sort_by(|a, b| {
+        b["crashed_at"]
+            .as_str()
+            .unwrap_or_default()
+            .cmp(a["crashed_at"].as_str().unwrap_or_default())
+    });
+    Ok(reports)
+}
+
+/// Sends a crash report through analytics, once the user agreed to. The
+/// minidump stays on this computer, the report has what symbolication of
+/// the crash location needs.
+#[tauri::command]
+pub async fn send_crash_report(app: AppHandle, id: String) -> Result<(), String> {
+    let dir = report_dir(&app, &id)?;
+    let report = read_report(&dir).await?;
+    let analytics = app
+        .try_state::<Arc<AnalyticsManager>>()
+        .ok_or("analytics are disabled, enable them in settings to send crash reports")?
+        .inner()
+        .clone();
+
+    let log = tokio::fs::read(dir.join("screenpipe.log"))
+        .await
+        .unwrap_or_default();
+    let log_tail = String::from_utf8_lossy(&log[log.len().saturating_sub(LOG_TAIL_BYTES)..]);
+
+    let mut properties = report.clone();
+    properties["status"] = json!("sent");
+    properties["log"] = json!(log_tail);
+    analytics
+        .send_event("recorder_crashed", Some(properties))
+        .await
+        .map_err(|e| e.to_string())?;
+    info!("sent crash report {}", id);
+
+    set_status(&dir, report, "sent").await
+}
+
+/// Keeps a crash report on this computer without asking about it again.
+#[tauri::command]
+pub async fn dismiss_crash_report(app: AppHandle, id: String) -> Result<(), String> {
+    let dir = report_dir(&app, &id)?;
+    let report = read_report(&dir).await?;
+    set_status(&dir, report, "dismissed").await
+}
@@ -1,6 +1,8 @@
 use anyhow::Result;
 use dark_light::Mode;
+use once_cell::sync::Lazy;
 use serde::Deserialize;
+use std::sync::Mutex;
 use tauri::{path::BaseDirectory, Manager};
 use tokio::time::{interval, Duration};
 
@@ -20,6 +22,15 @@ struct HealthCheckResponse {
     message: String,
     #[serde(rename = "verbose_instructions")]
     verbose_instructions: Option<String>,
+    #[serde(default)]
+    capture_paused_by: Option<String>,
+}
+
+/// Privacy blocklist pattern pausing capture, as last reported by the sidecar
+static CAPTURE_PAUSED_BY: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn capture_paused_by() -> Option<String> {
+    CAPTURE_PAUSED_BY.lock().unwrap().clone()
 }
 
 /// Starts a background task that periodically checks the health of the sidecar
@@ -44,6 +55,26 @@ pub async fn start_health_check(app: tauri::AppHandle) -> Result<()> {
                 Err(_) => "error",
             };
 
+            let paused_by = health_result
+                .as_ref()
+                .ok()
+                .and_then(|health| health.capture_paused_by.clone());
+            let paused_changed = {
+                let mut last_paused_by = CAPTURE_PAUSED_BY.lock().unwrap();
+                let changed = *last_paused_by != paused_by;
+                *last_paused_by = paused_by.clone();
+                changed
+            };
+            if paused_changed {
+                if let Some(main_tray) = app.tray_by_id("screenpipe_main") {
+                    let tooltip = match &paused_by {
+                        Some(pattern) => format!("screenpipe - capture paused ({})", pattern),
+                        None => "screenpipe".to_string(),
+                    };
+                    let _ = main_tray.set_tooltip(Some(tooltip));
+                }
+            }
+
             // Update icon if either health status OR theme changes
             if current_status != last_status || theme != last_theme {
                 last_status = current_status.to_string();
@@ -30,9 +30,10 @@ use tracing_subscriber::EnvFilter;
 use updates::start_update_check;
 mod analytics;
 mod icons;
-use crate::analytics::start_analytics;
+use crate::analytics::{start_analytics, PostHogSink};
 
 mod commands;
+mod crash_reports;
 mod disk_usage;
 mod permissions;
 mod server;
@@ -686,6 +687,9 @@ async fn main() {
             upload_file_to_s3,
             update_global_shortcuts,
             get_env,
+            crash_reports::get_crash_reports,
+            crash_reports::send_crash_report,
+            crash_reports::dismiss_crash_report,
         ])
         .setup(|app| {
             //deep link register_all
@@ -854,7 +858,7 @@ async fn main() {
                 match start_analytics(
                     unique_id,
                     email,
-                    posthog_api_key,
+                    PostHogSink::new(posthog_api_key),
                     interval_hours,
                     "http://localhost:3030".to_string(),
                     base_dir.clone(),
@@ -926,6 +930,7 @@ async fn main() {
                             })),
                         )
                         .await;
+                    let _ = analytics.shutdown().await;
                 }
             });
 
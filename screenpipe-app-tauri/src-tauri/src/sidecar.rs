@@ -1,17 +1,33 @@
 use crate::get_store;
+use once_cell::sync::Lazy;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
+use std::time::Duration;
+use sysinfo::{Pid, PidExt, ProcessExt, Signal, System, SystemExt};
 use tauri::Emitter;
 use tauri::{Manager, State};
 use tauri_plugin_shell::process::{CommandChild, CommandEvent};
 use tauri_plugin_shell::ShellExt;
 use tauri_plugin_store::Store;
 use tokio::sync::Mutex;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 pub struct SidecarState(pub Arc<tokio::sync::Mutex<Option<SidecarManager>>>);
 
+/// Admin token screenpipe is started with, so the app can stop it through
+/// `/admin/shutdown` when it requires api tokens. It changes on every launch
+/// of the app and is never stored.
+static APP_TOKEN: Lazy<String> = Lazy::new(|| {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sp_app_{}", hex)
+});
+/// Where screenpipe reads the token from, see `screenpipe_server::auth`
+const APP_TOKEN_ENV: &str = "SCREENPIPE_APP_TOKEN";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UserCredits {
     #[serde(rename = "user.credits.amount")]
@@ -78,84 +94,157 @@ impl User {
 #[tauri::command]
 pub async fn stop_screenpipe(
     state: State<'_, SidecarState>,
-    _app: tauri::AppHandle,
+    app: tauri::AppHandle,
 ) -> Result<(), String> {
-    debug!("Killing screenpipe");
+    debug!("Stopping screenpipe");
 
-    #[cfg(target_os = "macos")]
-    {
+    let child = {
         let mut manager = state.0.lock().await;
-        if let Some(manager) = manager.as_mut() {
-            if let Some(child) = manager.child.take() {
-                if let Err(e) = child.kill() {
-                    error!("Failed to kill child process: {}", e);
-                }
-            }
+        manager.as_mut().and_then(|manager| manager.child.take())
+    };
+    let pids = screenpipe_pids(child.as_ref().map(|child| child.pid()));
+    if pids.is_empty() {
+        debug!("screenpipe isn't running");
+        return Ok(());
+    }
+
+    // screenpipe writes what it captured before exiting
+    let port = get_store(&app, None)
+        .ok()
+        .and_then(|store| store.get("port"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(3030);
+    let asked = request_shutdown(port).await || terminate(&pids);
+    if asked && wait_for_exit(&pids, SHUTDOWN_GRACE_PERIOD).await {
+        debug!("screenpipe exited");
+        return Ok(());
+    }
+
+    if asked {
+        warn!(
+            "screenpipe still running after {}s, killing it",
+            SHUTDOWN_GRACE_PERIOD.as_secs()
+        );
+    }
+    if let Some(child) = child {
+        if let Err(e) = child.kill() {
+            error!("Failed to kill child process: {}", e);
         }
-        match tokio::process::Command::new("pkill")
-            .arg("-9")
-            .arg("-f")
-            .arg("screenpipe")
-            .output()
-            .await
-        {
-            Ok(_) => {
-                debug!("Successfully killed screenpipe processes");
-                Ok(())
-            }
-            Err(e) => {
-                error!("Failed to kill screenpipe processes: {}", e);
-                Err(format!("Failed to kill screenpipe processes: {}", e))
-            }
+    }
+    kill(&pids).await
+}
+
+/// How long screenpipe gets to write what it captured once asked to quit,
+/// a bit more than its own shutdown timeout.
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(25);
+
+/// The sidecar and the screenpipe processes left over from an earlier run
+/// of the app.
+fn screenpipe_pids(child: Option<u32>) -> Vec<Pid> {
+    let mut system = System::new();
+    system.refresh_processes();
+    let own = Pid::from_u32(std::process::id());
+    let mut pids: Vec<Pid> = system
+        .processes()
+        .iter()
+        .filter(|(pid, process)| {
+            **pid != own && matches!(process.name(), "screenpipe" | "screenpipe.exe")
+        })
+        .map(|(pid, _)| *pid)
+        .collect();
+    if let Some(child) = child.map(Pid::from_u32) {
+        if !pids.contains(&child) && system.process(child).is_some() {
+            pids.push(child);
         }
     }
+    pids
+}
 
-    #[cfg(target_os = "windows")]
+/// Asks screenpipe to quit through its api, which works the same on every
+/// platform. False if it couldn't be reached, e.g. while starting.
+async fn request_shutdown(port: u64) -> bool {
+    match reqwest::Client::new()
+        .post(format!("http://localhost:{}/admin/shutdown", port))
+        .bearer_auth(APP_TOKEN.as_str())
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
     {
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-        match tokio::process::Command::new("powershell")
-            .arg("-NoProfile")
-            .arg("-WindowStyle")
-            .arg("hidden")
-            .arg("-Command")
-            .arg(r#"taskkill.exe /F /T /IM screenpipe.exe"#)
-            .creation_flags(CREATE_NO_WINDOW)
-            .output()
-            .await
-        {
-            Ok(_) => {
-                debug!("Successfully killed screenpipe processes");
-                Ok(())
-            }
-            Err(e) => {
-                error!("Failed to kill screenpipe processes: {}", e);
-                Err(format!("Failed to kill screenpipe processes: {}", e))
+        Ok(response) if response.status().is_success() => true,
+        Ok(response) => {
+            warn!("screenpipe refused to shut down: {}", response.status());
+            false
+        }
+        Err(e) => {
+            warn!("failed to ask screenpipe to shut down: {}", e);
+            false
+        }
+    }
+}
+
+/// Sends SIGTERM, which screenpipe handles like a request to its api. False
+/// if it couldn't be sent, always on windows, which has no equivalent.
+fn terminate(pids: &[Pid]) -> bool {
+    let mut system = System::new();
+    let mut sent = false;
+    for pid in pids {
+        if system.refresh_process(*pid) {
+            if let Some(process) = system.process(*pid) {
+                sent |= process.kill_with(Signal::Term).unwrap_or(false);
             }
         }
     }
+    sent
+}
 
-    #[cfg(target_os = "linux")]
-    {
-        // -15 from gnu man page
-        // ref: https://www.gnu.org/software/coreutils/manual/html_node/kill-invocation.html
-        let command = format!("pgrep -x screenpipe | xargs -I {{}} kill -15 {{}}",);
-        match tokio::process::Command::new("sh")
-            .arg("-c")
-            .arg(command)
-            .output()
-            .await
+/// Waits for the processes to exit, false if one is still running after
+/// `timeout`.
+async fn wait_for_exit(pids: &[Pid], timeout: Duration) -> bool {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut system = System::new();
+    loop {
+        // false once the process is gone
+        if !pids.iter().any(|pid| system.refresh_process(*pid)) {
+            return true;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// Kills the processes still running, with their children on windows, like
+/// the ffmpeg screenpipe encodes with.
+async fn kill(pids: &[Pid]) -> Result<(), String> {
+    let mut system = System::new();
+    for pid in pids {
+        if !system.refresh_process(*pid) {
+            continue;
+        }
+
+        #[cfg(target_os = "windows")]
         {
-            Ok(_) => {
-                debug!("Successfully killed screenpipe processes");
-                Ok(())
-            }
-            Err(e) => {
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            let output = tokio::process::Command::new("taskkill")
+                .args(["/F", "/T", "/PID", &pid.to_string()])
+                .creation_flags(CREATE_NO_WINDOW)
+                .output()
+                .await;
+            if let Err(e) = output {
                 error!("Failed to kill screenpipe processes: {}", e);
-                Err(format!("Failed to kill screenpipe processes: {}", e))
+                return Err(format!("Failed to kill screenpipe processes: {}", e));
             }
         }
+
+        #[cfg(not(target_os = "windows"))]
+        if system.process(*pid).is_some_and(|process| !process.kill()) {
+            error!("Failed to kill screenpipe process {}", pid);
+            return Err(format!("Failed to kill screenpipe process {}", pid));
+        }
     }
+    debug!("Successfully killed screenpipe processes");
+    Ok(())
 }
 
 #[tauri::command]
@@ -224,6 +313,21 @@ fn spawn_sidecar(
         .and_then(|v| v.as_array().cloned())
         .unwrap_or_default();
 
+    let capture_regions = store
+        .get("captureRegions")
+        .and_then(|v| v.as_array().cloned())
+        .unwrap_or_default();
+
+    let capture_mode = store
+        .get("captureMode")
+        .and_then(|v| v.as_str().map(String::from))
+        .unwrap_or(String::from("monitor"));
+
+    let capture_private_browsing = store
+        .get("capturePrivateBrowsing")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
     let deepgram_api_key = store
         .get("deepgramApiKey")
         .and_then(|v| v.as_str().map(String::from))
@@ -231,6 +335,11 @@ fn spawn_sidecar(
 
     let fps = store.get("fps").and_then(|v| v.as_f64()).unwrap_or(0.2);
 
+    let adaptive_fps = store
+        .get("adaptiveFps")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
     let dev_mode = store
         .get("devMode")
         .and_then(|v| v.as_bool())
@@ -308,6 +417,9 @@ fn spawn_sidecar(
         args.push("--fps");
         args.push(fps_str.as_str());
     }
+    if adaptive_fps {
+        args.push("--adaptive-fps");
+    }
 
     if audio_transcription_engine != "default" {
         args.push("--audio-transcription-engine");
@@ -373,6 +485,23 @@ fn spawn_sidecar(
             args.push(window.as_str().unwrap());
         }
     }
+
+    for region in &capture_regions {
+        if let Some(region) = region.as_str() {
+            args.push("--capture-region");
+            args.push(region);
+        }
+    }
+
+    if capture_mode == "window" {
+        args.push("--capture-mode");
+        args.push("window");
+    }
+
+    if capture_private_browsing {
+        args.push("--capture-private-browsing");
+    }
+
     let current_pid = std::process::id();
     let current_pid_str = current_pid.to_string();
     // Set auto-destruct PID if not in dev mode
@@ -462,6 +591,7 @@ fn spawn_sidecar(
         }
 
         c = c.env("SENTRY_RELEASE_NAME_APPEND", "tauri");
+        c = c.env(APP_TOKEN_ENV, APP_TOKEN.as_str());
 
         // only supports --enable-realtime-vision for now, avoid adding if already present
         if !args.contains(&"--enable-realtime-vision")
@@ -508,6 +638,7 @@ fn spawn_sidecar(
     }
 
     c = c.env("SENTRY_RELEASE_NAME_APPEND", "tauri");
+    c = c.env(APP_TOKEN_ENV, APP_TOKEN.as_str());
 
     // only supports --enable-realtime-vision for now, avoid adding if already present
     if !args.contains(&"--enable-realtime-vision")
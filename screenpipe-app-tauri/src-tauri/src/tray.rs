@@ -1,5 +1,6 @@
 use crate::commands::{open_pipe_window, show_main_window};
 use crate::get_pipe_port;
+use crate::health::capture_paused_by;
 use crate::store::get_store;
 use anyhow::Result;
 use once_cell::sync::Lazy;
@@ -23,6 +24,7 @@ static LAST_MENU_STATE: Lazy<Mutex<MenuState>> = Lazy::new(|| Mutex::new(MenuSta
 struct MenuState {
     pipes: Vec<String>,
     shortcuts: HashMap<String, String>,
+    capture_paused_by: Option<String>,
 }
 
 pub fn setup_tray(app: &AppHandle, update_item: &tauri::menu::MenuItem<Wry>) -> Result<()> {
@@ -85,6 +87,15 @@ fn create_dynamic_menu(
             .item(&MenuItemBuilder::with_id("stop_recording", "stop recording").build(app)?);
     }
 
+    // Tell why nothing is recorded while the privacy blocklist pauses capture
+    if let Some(pattern) = &state.capture_paused_by {
+        menu_builder = menu_builder.item(
+            &MenuItemBuilder::with_id("capture_paused", format!("capture paused: {}", pattern))
+                .enabled(false)
+                .build(app)?,
+        );
+    }
+
     // Add pipe submenu if there are active pipes
     if !state.pipes.is_empty() {
         menu_builder = menu_builder.item(&PredefinedMenuItem::separator(app)?);
@@ -193,6 +204,7 @@ async fn update_menu_if_needed(
     let new_state = MenuState {
         pipes: get_active_pipes().await?,
         shortcuts: get_current_shortcuts(app)?,
+        capture_paused_by: capture_paused_by(),
     };
 
     // Compare with last state
@@ -332,7 +344,7 @@ fn handle_pipe_click(app: &AppHandle, menu_id: &str) {
     tauri::async_runtime::spawn(async move {
         match get_pipe_port(&pipe_id).await {
             Ok(port) => {
-                if let Err(e) = open_pipe_window(app_handle, port, pipe_id).await {
+                if let Err(e) = open_pipe_window(app_handle, port, pipe_id, None).await {
                     error!("Failed to open pipe window: {}", e);
                 }
             }
@@ -1,15 +1,21 @@
 use anyhow::Result;
 use std::{collections::HashSet, env, path::PathBuf, sync::Arc, time::Duration};
 
+use screenpipe_core::pii_removal::Redactor;
 use screenpipe_core::Language;
 use screenpipe_db::DatabaseManager;
 
 use crate::{
     core::{
+        denoise::NoiseSuppression,
         device::{default_input_device, default_output_device},
         engine::AudioTranscriptionEngine,
+        ChunkingConfig,
+    },
+    transcription::{
+        deepgram::CUSTOM_DEEPGRAM_API_TOKEN,
+        provider::{CloudSttProvider, OpenAiSttConfig},
     },
-    transcription::deepgram::CUSTOM_DEEPGRAM_API_TOKEN,
     vad::{VadEngineEnum, VadSensitivity},
 };
 
@@ -24,14 +30,39 @@ pub struct AudioManagerOptions {
     pub enable_diarization: bool,
     pub enable_realtime: bool,
     pub audio_chunk_duration: Duration,
+    /// Audio repeated at the start of each chunk from the end of the previous one.
+    pub audio_chunk_overlap: Duration,
     pub vad_sensitivity: VadSensitivity,
+    /// Speech shorter than this is ignored.
+    pub vad_min_speech_duration: Duration,
+    /// How long speech continues after the VAD stops detecting it.
+    pub vad_hangover: Duration,
     pub health_check_grace_period: u64,
     pub enabled_devices: HashSet<String>,
+    /// Devices recorded in order of preference, by name or part of it. Only the
+    /// first connected one of each type is recorded.
+    pub device_priority: Vec<String>,
     pub use_all_devices: bool,
     pub db_path: Option<String>,
     pub deepgram_url: Option<String>,
     pub deepgram_websocket_url: Option<String>,
     pub output_path: Option<PathBuf>,
+    /// Cloud provider used when the local model fails or falls behind real time.
+    pub stt_fallback: Option<CloudSttProvider>,
+    pub openai_stt: OpenAiSttConfig,
+    /// Never send audio to a cloud provider.
+    pub disable_cloud_stt: bool,
+    /// Mute system audio picked up again by microphones before transcription.
+    pub echo_cancellation: bool,
+    /// Devices whose background noise is removed before transcription.
+    pub noise_suppression: NoiseSuppression,
+    /// Store an English translation of transcripts in other languages.
+    pub translate_transcripts: bool,
+    /// Skip transcribing music, keeping its audio and when it played. Off by
+    /// default, speech can be taken for music.
+    pub media_detection: bool,
+    /// Removes personal information from transcripts before they are stored.
+    pub redactor: Option<Arc<Redactor>>,
 }
 
 impl Default for AudioManagerOptions {
@@ -49,13 +80,25 @@ impl Default for AudioManagerOptions {
             enable_diarization: true,
             enable_realtime: false,
             audio_chunk_duration: Duration::from_secs(30),
+            audio_chunk_overlap: Duration::from_secs(2),
             vad_sensitivity: VadSensitivity::High,
+            vad_min_speech_duration: Duration::ZERO,
+            vad_hangover: Duration::ZERO,
             health_check_grace_period: 15,
             enabled_devices,
+            device_priority: Vec::new(),
             use_all_devices: false,
             db_path: None,
             deepgram_url,
             deepgram_websocket_url,
+            stt_fallback: None,
+            openai_stt: OpenAiSttConfig::default(),
+            disable_cloud_stt: false,
+            echo_cancellation: true,
+            noise_suppression: NoiseSuppression::default(),
+            translate_transcripts: false,
+            media_detection: false,
+            redactor: None,
         }
     }
 }
@@ -107,11 +150,26 @@ impl AudioManagerBuilder {
         self
     }
 
+    pub fn audio_chunk_overlap(mut self, audio_chunk_overlap: Duration) -> Self {
+        self.options.audio_chunk_overlap = audio_chunk_overlap;
+        self
+    }
+
     pub fn vad_sensitivity(mut self, vad_sensitivity: VadSensitivity) -> Self {
         self.options.vad_sensitivity = vad_sensitivity;
         self
     }
 
+    pub fn vad_min_speech_duration(mut self, vad_min_speech_duration: Duration) -> Self {
+        self.options.vad_min_speech_duration = vad_min_speech_duration;
+        self
+    }
+
+    pub fn vad_hangover(mut self, vad_hangover: Duration) -> Self {
+        self.options.vad_hangover = vad_hangover;
+        self
+    }
+
     pub fn health_check_grace_period(mut self, health_check_grace_period: u64) -> Self {
         self.options.health_check_grace_period = health_check_grace_period;
         self
@@ -122,6 +180,11 @@ impl AudioManagerBuilder {
         self
     }
 
+    pub fn device_priority(mut self, device_priority: Vec<String>) -> Self {
+        self.options.device_priority = device_priority;
+        self
+    }
+
     pub fn use_all_devices(mut self, use_all_devices: bool) -> Self {
         self.options.use_all_devices = use_all_devices;
         self
@@ -137,6 +200,56 @@ impl AudioManagerBuilder {
         self
     }
 
+    pub fn stt_fallback(mut self, stt_fallback: Option<CloudSttProvider>) -> Self {
+        self.options.stt_fallback = stt_fallback;
+        self
+    }
+
+    pub fn openai_stt(mut self, openai_stt: OpenAiSttConfig) -> Self {
+        self.options.openai_stt = openai_stt;
+        self
+    }
+
+    pub fn disable_cloud_stt(mut self, disable_cloud_stt: bool) -> Self {
+        self.options.disable_cloud_stt = disable_cloud_stt;
+        self
+    }
+
+    pub fn echo_cancellation(mut self, echo_cancellation: bool) -> Self {
+        self.options.echo_cancellation = echo_cancellation;
+        self
+    }
+
+    pub fn translate_transcripts(mut self, translate_transcripts: bool) -> Self {
+        self.options.translate_transcripts = translate_transcripts;
+        self
+    }
+
+    /// Denoises every device, except those turned off at runtime.
+    pub fn noise_suppression(mut self, enabled: bool) -> Self {
+        self.options.noise_suppression.default = enabled;
+        self
+    }
+
+    /// Denoises these devices, by name.
+    pub fn noise_suppression_devices(mut self, devices: Vec<String>) -> Self {
+        self.options
+            .noise_suppression
+            .devices
+            .extend(devices.into_iter().map(|device| (device, true)));
+        self
+    }
+
+    pub fn media_detection(mut self, media_detection: bool) -> Self {
+        self.options.media_detection = media_detection;
+        self
+    }
+
+    pub fn redactor(mut self, redactor: Option<Arc<Redactor>>) -> Self {
+        self.options.redactor = redactor;
+        self
+    }
+
     pub async fn build(&mut self, db: Arc<DatabaseManager>) -> Result<AudioManager> {
         self.validate_options()?;
         let options = &mut self.options;
@@ -166,10 +279,34 @@ impl AudioManagerBuilder {
             ));
         }
 
+        if self.options.disable_cloud_stt
+            && (self.options.transcription_engine == Arc::new(AudioTranscriptionEngine::Deepgram)
+                || self.options.enable_realtime)
+        {
+            return Err(anyhow::anyhow!(
+                "Deepgram transcription sends audio to the cloud, which is disabled"
+            ));
+        }
+
+        if self.options.stt_fallback == Some(CloudSttProvider::OpenAi)
+            && !self.options.disable_cloud_stt
+            && self.options.openai_stt.api_key.is_none()
+        {
+            return Err(anyhow::anyhow!(
+                "OpenAI API key is required for the OpenAI transcription fallback"
+            ));
+        }
+
         if self.options.output_path.is_none() {
             return Err(anyhow::anyhow!("Output path is required for audio manager"));
         }
 
+        ChunkingConfig {
+            duration: self.options.audio_chunk_duration,
+            overlap: self.options.audio_chunk_overlap,
+        }
+        .validate()?;
+
         if self.options.enable_realtime
             && (self.options.deepgram_api_key.is_none() && CUSTOM_DEEPGRAM_API_TOKEN.is_empty())
         {
@@ -1,10 +1,18 @@
-use std::{collections::HashSet, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Result;
+use screenpipe_events::{send_event, AudioDeviceSwitchedEvent, AUDIO_DEVICE_SWITCHED_EVENT};
 use tokio::{sync::Mutex, task::JoinHandle, time::sleep};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::{core::device::parse_audio_device, device::device_manager::DeviceManager};
+use crate::{
+    core::device::{parse_audio_device, AudioDevice, DeviceType},
+    device::device_manager::DeviceManager,
+};
 
 use super::{AudioManager, AudioManagerStatus};
 
@@ -20,10 +28,14 @@ pub async fn start_device_monitor(
 
     *DEVICE_MONITOR.lock().await = Some(tokio::spawn(async move {
         let mut disconnected_devices: HashSet<String> = HashSet::new();
+        // preferred device recorded for each type, and the devices it replaced
+        let mut active_devices: HashMap<DeviceType, AudioDevice> = HashMap::new();
+        let mut replaced_devices: HashSet<AudioDevice> = HashSet::new();
         loop {
             if audio_manager.status().await == AudioManagerStatus::Running {
                 let currently_available_devices = device_manager.devices().await;
                 let enabled_devices = audio_manager.enabled_devices().await;
+                let priority = audio_manager.device_priority().await;
                 for device_name in disconnected_devices.clone() {
                     let device = match parse_audio_device(&device_name) {
                         Ok(device) => device,
@@ -34,6 +46,16 @@ pub async fn start_device_monitor(
                     };
 
                     if audio_manager.start_device(&device).await.is_ok() {
+                        info!("Device {device_name} reconnected");
+                        // switches between devices of the priority list are reported below
+                        if !is_prioritized(&priority, &device) {
+                            send_switch_event(
+                                &device.device_type,
+                                None,
+                                Some(&device),
+                                "reconnected",
+                            );
+                        }
                         disconnected_devices.remove(&device_name);
                     }
                 }
@@ -48,12 +70,20 @@ pub async fn start_device_monitor(
                     };
 
                     if device_manager.is_running(&device)
-                        && !currently_available_devices.contains(&device)
+                        && !currently_available_devices.contains(&device.capture_device())
                     {
                         info!("Device {device_name} disconnected");
 
                         let _ = audio_manager.stop_device(device_name).await;
                         disconnected_devices.insert(device_name.clone());
+                        if !is_prioritized(&priority, &device) {
+                            send_switch_event(
+                                &device.device_type,
+                                Some(&device),
+                                None,
+                                "disconnected",
+                            );
+                        }
                     } else {
                         if audio_manager.status().await != AudioManagerStatus::Running {
                             break;
@@ -78,6 +108,17 @@ pub async fn start_device_monitor(
                         }
                     }
                 }
+
+                if !priority.is_empty() {
+                    apply_device_priority(
+                        &audio_manager,
+                        &priority,
+                        &currently_available_devices,
+                        &mut active_devices,
+                        &mut replaced_devices,
+                    )
+                    .await;
+                }
             }
             sleep(Duration::from_secs(2)).await;
         }
@@ -92,3 +133,143 @@ pub async fn stop_device_monitor() -> Result<()> {
 
     Ok(())
 }
+
+/// Position of `device` in the priority list. Entries match the device name,
+/// e.g. `Jabra Evolve2 65 (input)`, in full or in part and case insensitively.
+fn priority_rank(priority: &[String], device: &AudioDevice) -> Option<usize> {
+    let name = device.to_string().to_lowercase();
+    priority.iter().position(|entry| {
+        let entry = entry.trim().to_lowercase();
+        !entry.is_empty() && name.contains(&entry)
+    })
+}
+
+pub fn is_prioritized(priority: &[String], device: &AudioDevice) -> bool {
+    priority_rank(priority, device).is_some()
+}
+
+/// The available device of `device_type` that comes first in the priority
+/// list, `None` if none of them is in it.
+pub fn preferred_device(
+    priority: &[String],
+    available: &[AudioDevice],
+    device_type: &DeviceType,
+) -> Option<AudioDevice> {
+    available
+        .iter()
+        .filter(|device| &device.device_type == device_type)
+        .filter_map(|device| priority_rank(priority, device).map(|rank| (rank, device)))
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, device)| device.clone())
+}
+
+/// Records the preferred device of each type instead of the others. The
+/// devices it replaced are recorded again once no device of the priority list
+/// is connected.
+async fn apply_device_priority(
+    audio_manager: &AudioManager,
+    priority: &[String],
+    available: &[AudioDevice],
+    active_devices: &mut HashMap<DeviceType, AudioDevice>,
+    replaced_devices: &mut HashSet<AudioDevice>,
+) {
+    for device_type in [DeviceType::Input, DeviceType::Output] {
+        let running: Vec<AudioDevice> = audio_manager
+            .current_devices()
+            .into_iter()
+            .filter(|device| device.device_type == device_type)
+            .collect();
+
+        let current = match preferred_device(priority, available, &device_type) {
+            Some(preferred) => {
+                if !running.contains(&preferred) {
+                    if let Err(e) = audio_manager.start_device(&preferred).await {
+                        error!("failed to start preferred device {}: {}", preferred, e);
+                        continue;
+                    }
+                }
+                // application and channel devices record a part of a device,
+                // the preferred one doesn't replace them
+                for device in running.iter().filter(|device| {
+                    **device != preferred
+                        && device.source_app().is_none()
+                        && device.source_channel().is_none()
+                }) {
+                    match audio_manager.stop_device(&device.to_string()).await {
+                        Ok(()) => {
+                            replaced_devices.insert(device.clone());
+                        }
+                        Err(e) => warn!("failed to stop {} for {}: {}", device, preferred, e),
+                    }
+                }
+                Some(preferred)
+            }
+            None => {
+                let restorable: Vec<AudioDevice> = replaced_devices
+                    .iter()
+                    .filter(|device| {
+                        device.device_type == device_type
+                            && available.contains(&device.capture_device())
+                    })
+                    .cloned()
+                    .collect();
+                let mut restored = None;
+                for device in restorable {
+                    match audio_manager.start_device(&device).await {
+                        Ok(()) => {
+                            replaced_devices.remove(&device);
+                            restored.get_or_insert(device);
+                        }
+                        Err(e) => warn!("failed to record {} again: {}", device, e),
+                    }
+                }
+                restored
+            }
+        };
+
+        let previous = active_devices.get(&device_type).cloned();
+        if previous == current {
+            continue;
+        }
+        let reason = match &previous {
+            Some(previous) if !available.contains(&previous.capture_device()) => "disconnected",
+            _ => "priority",
+        };
+        info!(
+            "recording {} instead of {} ({})",
+            current
+                .as_ref()
+                .map_or("nothing".to_string(), ToString::to_string),
+            previous
+                .as_ref()
+                .map_or("nothing".to_string(), ToString::to_string),
+            reason
+        );
+        send_switch_event(&device_type, previous.as_ref(), current.as_ref(), reason);
+        match current {
+            Some(device) => active_devices.insert(device_type, device),
+            None => active_devices.remove(&device_type),
+        };
+    }
+}
+
+fn send_switch_event(
+    device_type: &DeviceType,
+    previous_device: Option<&AudioDevice>,
+    device: Option<&AudioDevice>,
+    reason: &str,
+) {
+    let _ = send_event(
+        AUDIO_DEVICE_SWITCHED_EVENT,
+        AudioDeviceSwitchedEvent {
+            device_type: match device_type {
+                DeviceType::Input => "input",
+                DeviceType::Output => "output",
+            }
+            .to_string(),
+            previous_device: previous_device.map(ToString::to_string),
+            device: device.map(ToString::to_string),
+            reason: reason.to_string(),
+        },
+    );
+}
@@ -1,13 +1,18 @@
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use crossbeam::channel::RecvTimeoutError;
 use dashmap::DashMap;
 use std::{
     collections::HashSet,
-    path::PathBuf,
-    sync::{atomic::Ordering, Arc},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 use tokio::{
     join,
-    sync::{Mutex, RwLock},
+    sync::{watch, Mutex, RwLock},
     task::JoinHandle,
 };
 use tracing::{error, info, warn};
@@ -18,18 +23,28 @@ use screenpipe_db::DatabaseManager;
 use super::{start_device_monitor, stop_device_monitor, AudioManagerOptions};
 use crate::{
     core::{
+        denoise::NoiseSuppression,
         device::{parse_audio_device, AudioDevice},
-        record_and_transcribe,
+        engine::AudioTranscriptionEngine,
+        record_and_transcribe, update_device_capture_time, ChunkingConfig,
     },
     device::device_manager::DeviceManager,
     segmentation::segmentation_manager::SegmentationManager,
+    speaker::embedding::MIN_SAMPLE_SECONDS,
     transcription::{
         deepgram::streaming::stream_transcription_deepgram,
         handle_new_transcript,
-        stt::process_audio_input,
+        provider::SttRouter,
+        stt::{process_audio_input, SAMPLE_RATE},
+        translation::{Translator, WhisperTranslator},
+        vocabulary::Vocabulary,
         whisper::model::{create_whisper_context_parameters, download_whisper_model},
+        DRAIN_POLL_INTERVAL,
+    },
+    utils::audio::{normalize_v2, pcm_decode, resample},
+    vad::{
+        silero::SileroVad, webrtc::WebRtcVad, SpeechTiming, VadConfig, VadEngine, VadEngineEnum,
     },
-    vad::{silero::SileroVad, webrtc::WebRtcVad, VadEngine, VadEngineEnum},
     AudioInput, TranscriptionResult,
 };
 
@@ -42,6 +57,17 @@ pub enum AudioManagerStatus {
 
 type RecordingHandlesMap = DashMap<AudioDevice, Arc<Mutex<JoinHandle<Result<()>>>>>;
 
+/// Engine and whisper model used for new audio chunks. Chunks keep the model
+/// they started with, so swapping it never interrupts a transcription.
+#[derive(Clone)]
+struct ActiveTranscriber {
+    engine: Arc<AudioTranscriptionEngine>,
+    whisper_context: Arc<WhisperContext>,
+    model_path: PathBuf,
+    stt_router: Arc<SttRouter>,
+    translator: Option<Arc<dyn Translator>>,
+}
+
 #[derive(Clone)]
 pub struct AudioManager {
     options: Arc<RwLock<AudioManagerOptions>>,
@@ -50,6 +76,12 @@ pub struct AudioManager {
     status: Arc<RwLock<AudioManagerStatus>>,
     db: Arc<DatabaseManager>,
     vad_engine: Arc<Mutex<Box<dyn VadEngine + Send>>>,
+    /// Read by the recordings when they start a chunk
+    chunking: Arc<watch::Sender<ChunkingConfig>>,
+    /// Custom vocabulary, read for each chunk transcribed
+    vocabulary: Arc<watch::Sender<Arc<Vocabulary>>>,
+    /// Read by the recordings for each chunk
+    noise_suppression: Arc<watch::Sender<NoiseSuppression>>,
     recording_handles: Arc<RecordingHandlesMap>,
     recording_sender: Arc<crossbeam::channel::Sender<AudioInput>>,
     recording_receiver: Arc<crossbeam::channel::Receiver<AudioInput>>,
@@ -57,7 +89,14 @@ pub struct AudioManager {
     transcription_sender: Arc<crossbeam::channel::Sender<TranscriptionResult>>,
     transcription_receiver_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
     recording_receiver_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
+    /// Set by `drain`, the audio handler stops once no audio is left to
+    /// transcribe
+    draining_audio: Arc<AtomicBool>,
+    /// Set by `drain`, the transcript handler stops once every transcript
+    /// is stored
+    draining_transcripts: Arc<AtomicBool>,
     stt_model_path: PathBuf,
+    transcriber: Arc<RwLock<Option<ActiveTranscriber>>>,
 }
 
 impl AudioManager {
@@ -65,10 +104,18 @@ impl AudioManager {
         let device_manager = DeviceManager::new().await?;
         let segmentation_manager = Arc::new(SegmentationManager::new().await?);
         let status = RwLock::new(AudioManagerStatus::Stopped);
-        let vad_engine: Arc<Mutex<Box<dyn VadEngine + Send>>> = match options.vad_engine {
-            VadEngineEnum::Silero => Arc::new(Mutex::new(Box::new(SileroVad::new().await?))),
-            VadEngineEnum::WebRtc => Arc::new(Mutex::new(Box::new(WebRtcVad::new()))),
-        };
+        let mut vad_engine = new_vad_engine(&options.vad_engine).await?;
+        vad_engine.set_sensitivity(options.vad_sensitivity);
+        vad_engine.set_speech_timing(SpeechTiming {
+            min_speech_duration: options.vad_min_speech_duration,
+            hangover: options.vad_hangover,
+        });
+        let vad_engine = Arc::new(Mutex::new(vad_engine));
+        let (chunking, _) = watch::channel(ChunkingConfig {
+            duration: options.audio_chunk_duration,
+            overlap: options.audio_chunk_overlap,
+        });
+        let (noise_suppression, _) = watch::channel(options.noise_suppression.clone());
 
         let (recording_sender, recording_receiver) = crossbeam::channel::bounded(1000);
         let (transcription_sender, transcription_receiver) = crossbeam::channel::bounded(1000);
@@ -85,6 +132,9 @@ impl AudioManager {
             status: Arc::new(status),
             db,
             vad_engine,
+            chunking: Arc::new(chunking),
+            vocabulary: Arc::new(watch::channel(Arc::new(Vocabulary::default())).0),
+            noise_suppression: Arc::new(noise_suppression),
             recording_sender: Arc::new(recording_sender),
             recording_receiver: Arc::new(recording_receiver),
             transcription_receiver: Arc::new(transcription_receiver),
@@ -92,7 +142,10 @@ impl AudioManager {
             recording_handles: Arc::new(recording_handles),
             recording_receiver_handle: Arc::new(RwLock::new(None)),
             transcription_receiver_handle: Arc::new(RwLock::new(None)),
+            draining_audio: Arc::new(AtomicBool::new(false)),
+            draining_transcripts: Arc::new(AtomicBool::new(false)),
             stt_model_path,
+            transcriber: Arc::new(RwLock::new(None)),
         };
 
         Ok(manager)
@@ -109,6 +162,8 @@ impl AudioManager {
 
     async fn start_internal(&self) -> Result<()> {
         *self.status.write().await = AudioManagerStatus::Running;
+        self.draining_audio.store(false, Ordering::SeqCst);
+        self.draining_transcripts.store(false, Ordering::SeqCst);
         let mut transcription_receiver_handle = self.transcription_receiver_handle.write().await;
         *transcription_receiver_handle = Some(self.start_transcription_receiver_handler().await?);
 
@@ -195,6 +250,14 @@ impl AudioManager {
         Ok(())
     }
 
+    /// Stops the recording of a device and starts it again, with a new
+    /// stream, e.g. when it stopped delivering audio.
+    pub async fn restart_device(&self, device_name: &str) -> Result<()> {
+        let device = parse_audio_device(device_name)?;
+        self.stop_device(device_name).await?;
+        self.start_device(&device).await
+    }
+
     pub async fn status(&self) -> AudioManagerStatus {
         self.status.read().await.clone()
     }
@@ -237,20 +300,27 @@ impl AudioManager {
     async fn record_device(&self, device: &AudioDevice) -> Result<JoinHandle<Result<()>>> {
         let options = self.options.read().await;
         let stream = self.device_manager.stream(device).unwrap();
-        let audio_chunk_duration = options.audio_chunk_duration;
+        let chunking = self.chunking.subscribe();
+        let noise_suppression = self.noise_suppression.subscribe();
         let recording_sender = self.recording_sender.clone();
         let is_running = self.device_manager.is_running_mut(device).unwrap();
         let languages = options.languages.clone();
         let deepgram_api_key = options.deepgram_api_key.clone();
         let realtime_enabled = options.enable_realtime;
+        let echo_cancellation = options.echo_cancellation;
         let device_clone = device.clone();
+        let still_running = is_running.clone();
+        // a device recorded before has the time of its last audio back then
+        update_device_capture_time(&device.to_string());
 
         let recording_handle = tokio::spawn(async move {
             let record_and_transcribe_handle = tokio::spawn(record_and_transcribe(
                 stream.clone(),
-                audio_chunk_duration,
+                chunking,
                 recording_sender.clone(),
                 is_running.clone(),
+                echo_cancellation,
+                noise_suppression,
             ));
 
             let realtime_handle = if realtime_enabled {
@@ -288,10 +358,12 @@ impl AudioManager {
                 return Err(e);
             }
 
-            warn!(
-                "recording handle for device {} quit unexpectedly",
-                device_clone
-            );
+            if still_running.load(Ordering::Relaxed) {
+                warn!(
+                    "recording handle for device {} quit unexpectedly",
+                    device_clone
+                );
+            }
 
             Ok(())
         });
@@ -308,21 +380,52 @@ impl AudioManager {
         let options = self.options.read().await;
         let output_path = options.output_path.clone();
         let languages = options.languages.clone();
-        let deepgram_api_key = options.deepgram_api_key.clone();
+        let media_detection = options.media_detection;
         let audio_transcription_engine = options.transcription_engine.clone();
         let vad_engine = self.vad_engine.clone();
         let whisper_receiver = self.recording_receiver.clone();
-        let context_param = create_whisper_context_parameters(audio_transcription_engine.clone())?;
-
-        let quantized_path = self.stt_model_path.clone();
-        let whisper_context = Arc::new(
-            WhisperContext::new_with_params(&quantized_path.to_string_lossy(), context_param)
-                .expect("failed to load model"),
-        );
+        let vocabulary = self.vocabulary.subscribe();
+        let draining = self.draining_audio.clone();
+
+        // keep the model across restarts, it may have been swapped at runtime
+        if self.transcriber.read().await.is_none() {
+            let whisper_context = Arc::new(
+                load_whisper_context(&self.stt_model_path, audio_transcription_engine.clone())
+                    .expect("failed to load model"),
+            );
+            let stt_router = SttRouter::from_options(
+                &options,
+                &audio_transcription_engine,
+                whisper_context.clone(),
+            )?;
+            info!(
+                "speech to text providers: {}",
+                stt_router.providers().collect::<Vec<_>>().join(", ")
+            );
+            *self.transcriber.write().await = Some(ActiveTranscriber {
+                engine: audio_transcription_engine,
+                translator: new_translator(&options, &whisper_context),
+                whisper_context,
+                model_path: self.stt_model_path.clone(),
+                stt_router: Arc::new(stt_router),
+            });
+        }
+        let transcriber = self.transcriber.clone();
 
         Ok(tokio::spawn(async move {
-            while let Ok(audio) = whisper_receiver.recv() {
+            loop {
+                let audio = match whisper_receiver.recv_timeout(DRAIN_POLL_INTERVAL) {
+                    Ok(audio) => audio,
+                    // the recordings stopped and their audio is transcribed
+                    Err(RecvTimeoutError::Timeout) if draining.load(Ordering::SeqCst) => break,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
                 info!("Received audio from device: {:?}", audio.device.name);
+                let Some(active) = transcriber.read().await.clone() else {
+                    continue;
+                };
+                let vocabulary = vocabulary.borrow().clone();
                 if let Err(e) = process_audio_input(
                     audio.clone(),
                     vad_engine.clone(),
@@ -330,11 +433,13 @@ impl AudioManager {
                     embedding_manager.clone(),
                     embedding_extractor.clone(),
                     &output_path.clone().unwrap(),
-                    audio_transcription_engine.clone(),
-                    deepgram_api_key.clone(),
+                    active.engine,
+                    active.stt_router,
+                    active.translator,
                     languages.clone(),
+                    vocabulary,
+                    media_detection,
                     &transcription_sender.clone(),
-                    whisper_context.clone(),
                 )
                 .await
                 {
@@ -347,14 +452,318 @@ impl AudioManager {
     async fn start_transcription_receiver_handler(&self) -> Result<JoinHandle<()>> {
         let transcription_receiver = self.transcription_receiver.clone();
         let db = self.db.clone();
-        let transcription_engine = self.options.read().await.transcription_engine.clone();
+        let redactor = self.options.read().await.redactor.clone();
         Ok(tokio::spawn(handle_new_transcript(
             db,
             transcription_receiver,
-            transcription_engine,
+            redactor,
+            self.chunking.subscribe(),
+            self.draining_transcripts.clone(),
         )))
     }
 
+    pub async fn transcription_engine(&self) -> Arc<AudioTranscriptionEngine> {
+        self.options.read().await.transcription_engine.clone()
+    }
+
+    /// Whether the speech to text model is loaded, it is once audio
+    /// recording started.
+    pub async fn transcription_model_loaded(&self) -> bool {
+        self.transcriber.read().await.is_some()
+    }
+
+    /// Switches the transcription engine while recording. The new model is
+    /// downloaded and loaded first, the chunk being transcribed finishes with
+    /// the old one and every chunk after it uses the new one.
+    pub async fn set_transcription_engine(&self, engine: AudioTranscriptionEngine) -> Result<()> {
+        if engine == AudioTranscriptionEngine::Deepgram
+            && self.options.read().await.disable_cloud_stt
+        {
+            return Err(anyhow!("cloud transcription is disabled"));
+        }
+        let engine = Arc::new(engine);
+
+        let download_engine = engine.clone();
+        let model_path =
+            tokio::task::spawn_blocking(move || download_whisper_model(download_engine)).await??;
+
+        let current = self.transcriber.read().await.clone();
+        let whisper_context = match current {
+            // e.g. deepgram and the default model share the same file
+            Some(active) if active.model_path == model_path => active.whisper_context,
+            _ => {
+                let (path, load_engine) = (model_path.clone(), engine.clone());
+                let context =
+                    tokio::task::spawn_blocking(move || load_whisper_context(&path, load_engine))
+                        .await??;
+                Arc::new(context)
+            }
+        };
+
+        let options = self.options.read().await.clone();
+        let stt_router = SttRouter::from_options(&options, &engine, whisper_context.clone())?;
+        *self.transcriber.write().await = Some(ActiveTranscriber {
+            engine: engine.clone(),
+            translator: new_translator(&options, &whisper_context),
+            whisper_context,
+            model_path,
+            stt_router: Arc::new(stt_router),
+        });
+        self.options.write().await.transcription_engine = engine.clone();
+
+        info!("transcription engine switched to {}", engine);
+        Ok(())
+    }
+
+    pub async fn vad_config(&self) -> VadConfig {
+        let options = self.options.read().await;
+        VadConfig {
+            engine: options.vad_engine.clone(),
+            sensitivity: options.vad_sensitivity,
+            timing: SpeechTiming {
+                min_speech_duration: options.vad_min_speech_duration,
+                hangover: options.vad_hangover,
+            },
+        }
+    }
+
+    /// Changes voice activity detection while recording, the next chunk is
+    /// processed with the new settings.
+    pub async fn set_vad_config(&self, config: VadConfig) -> Result<()> {
+        let engine_changed = self.options.read().await.vad_engine != config.engine;
+        // load a new engine before taking the lock, silero may need a download
+        let new_engine = if engine_changed {
+            Some(new_vad_engine(&config.engine).await?)
+        } else {
+            None
+        };
+
+        {
+            let mut vad_engine = self.vad_engine.lock().await;
+            if let Some(new_engine) = new_engine {
+                *vad_engine = new_engine;
+            }
+            vad_engine.set_sensitivity(config.sensitivity);
+            vad_engine.set_speech_timing(config.timing);
+        }
+
+        let mut options = self.options.write().await;
+        options.vad_engine = config.engine.clone();
+        options.vad_sensitivity = config.sensitivity;
+        options.vad_min_speech_duration = config.timing.min_speech_duration;
+        options.vad_hangover = config.timing.hangover;
+
+        info!("vad config updated: {:?}", config);
+        Ok(())
+    }
+
+    pub fn chunking_config(&self) -> ChunkingConfig {
+        *self.chunking.borrow()
+    }
+
+    /// Changes how recordings are cut into chunks, each recording switches
+    /// when it starts its next chunk.
+    pub async fn set_chunking_config(&self, config: ChunkingConfig) -> Result<()> {
+        config.validate()?;
+        self.chunking.send_replace(config);
+
+        let mut options = self.options.write().await;
+        options.audio_chunk_duration = config.duration;
+        options.audio_chunk_overlap = config.overlap;
+
+        info!("audio chunking updated: {:?}", config);
+        Ok(())
+    }
+
+    pub fn noise_suppression(&self) -> NoiseSuppression {
+        self.noise_suppression.borrow().clone()
+    }
+
+    /// Turns noise suppression on or off for a device, from its next chunk.
+    pub async fn set_device_noise_suppression(&self, device: &str, enabled: bool) {
+        self.noise_suppression.send_modify(|config| {
+            config.devices.insert(device.to_string(), enabled);
+        });
+        self.options
+            .write()
+            .await
+            .noise_suppression
+            .devices
+            .insert(device.to_string(), enabled);
+        info!(
+            "noise suppression {} for {}",
+            if enabled { "enabled" } else { "disabled" },
+            device
+        );
+    }
+
+    pub fn vocabulary(&self) -> Arc<Vocabulary> {
+        self.vocabulary.borrow().clone()
+    }
+
+    /// Replaces the custom vocabulary, from the next chunk transcribed.
+    pub fn set_vocabulary(&self, vocabulary: Vocabulary) {
+        let terms = vocabulary.terms.len();
+        let changed = self.vocabulary.send_if_modified(|current| {
+            let changed = **current != vocabulary;
+            if changed {
+                *current = Arc::new(vocabulary);
+            }
+            changed
+        });
+        if changed {
+            info!("custom vocabulary updated: {} terms", terms);
+        }
+    }
+
+    /// Transcribes an audio file recorded elsewhere as if `device` had
+    /// recorded it, in overlapping chunks like a recording. Works without
+    /// recording any device, e.g. on a headless server. Transcripts are dated
+    /// from `recorded_at`, or when they're transcribed. Returns the number of
+    /// chunks queued.
+    pub async fn ingest_file(
+        &self,
+        path: &Path,
+        device: AudioDevice,
+        recorded_at: Option<DateTime<Utc>>,
+    ) -> Result<usize> {
+        self.queue_file(path, device, recorded_at, true).await
+    }
+
+    /// Transcribes audio `device` recorded at `recorded_at` that was kept
+    /// untranscribed as music, like [`Self::ingest_file`] but without media
+    /// detection. The file is read before this returns.
+    pub async fn transcribe_kept_file(
+        &self,
+        path: &Path,
+        device: AudioDevice,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<usize> {
+        self.queue_file(path, device, Some(recorded_at), false)
+            .await
+    }
+
+    async fn queue_file(
+        &self,
+        path: &Path,
+        device: AudioDevice,
+        recorded_at: Option<DateTime<Utc>>,
+        media_detection: bool,
+    ) -> Result<usize> {
+        {
+            let mut recording_receiver_handle = self.recording_receiver_handle.write().await;
+            if recording_receiver_handle.is_none() {
+                *self.transcription_receiver_handle.write().await =
+                    Some(self.start_transcription_receiver_handler().await?);
+                *recording_receiver_handle = Some(self.start_audio_receiver_handler().await?);
+            }
+        }
+
+        let ChunkingConfig { duration, overlap } = self.chunking_config();
+        let recording_sender = self.recording_sender.clone();
+        let device = Arc::new(device);
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let (samples, sample_rate) = pcm_decode(&path)?;
+            let step = (duration.as_secs_f64() * sample_rate as f64) as usize;
+            let overlap_len = (overlap.as_secs_f64() * sample_rate as f64) as usize;
+            let mut chunks = 0;
+            // each chunk starts with the end of the previous one
+            for start in (0..samples.len()).step_by(step.max(1)) {
+                let from = start.saturating_sub(overlap_len);
+                let chunk = &samples[from..(start + step).min(samples.len())];
+                let offset = from as f64 / sample_rate as f64;
+                recording_sender
+                    .send(AudioInput {
+                        data: Arc::new(chunk.to_vec()),
+                        sample_rate,
+                        channels: 1,
+                        device: device.clone(),
+                        recorded_at: recorded_at.map(|recorded_at| {
+                            recorded_at + chrono::Duration::milliseconds((offset * 1000.0) as i64)
+                        }),
+                        media_detection,
+                    })
+                    .map_err(|_| anyhow!("audio processing stopped"))?;
+                chunks += 1;
+            }
+            Ok(chunks)
+        })
+        .await?
+    }
+
+    /// Voice embeddings of the speaker talking in the audio file at `path`,
+    /// to enroll a voice profile with.
+    pub async fn voice_embeddings(&self, path: &Path) -> Result<Vec<Vec<f32>>> {
+        let embedding_extractor = self.segmentation_manager.embedding_extractor.clone();
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let (samples, sample_rate) = pcm_decode(&path)?;
+            let samples = if sample_rate != SAMPLE_RATE {
+                resample(&samples, sample_rate, SAMPLE_RATE)?
+            } else {
+                samples
+            };
+            let embeddings = embedding_extractor
+                .lock()
+                .map_err(|_| anyhow!("embedding extractor poisoned"))?
+                .compute_windows(&normalize_v2(&samples), SAMPLE_RATE)?;
+            if embeddings.is_empty() {
+                return Err(anyhow!(
+                    "the sample is too short, it needs at least {} seconds of speech",
+                    MIN_SAMPLE_SECONDS
+                ));
+            }
+            Ok(embeddings)
+        })
+        .await?
+    }
+
+    /// Stops recording for a graceful shutdown, returning once the audio
+    /// already recorded, the chunks being recorded included, is transcribed
+    /// and stored.
+    pub async fn drain(&self) -> Result<()> {
+        *self.status.write().await = AudioManagerStatus::Stopped;
+        let _ = stop_device_monitor().await;
+
+        // a recording sends the audio it collected so far once it's stopped
+        let recordings = self
+            .recording_handles
+            .iter()
+            .map(|pair| (pair.key().clone(), pair.value().clone()))
+            .collect::<Vec<_>>();
+        for (device, _) in &recordings {
+            if let Some(is_running) = self.device_manager.is_running_mut(device) {
+                is_running.store(false, Ordering::Relaxed);
+            }
+        }
+        for (device, recording) in recordings {
+            let mut recording = recording.lock().await;
+            if !recording.is_finished() {
+                let _ = (&mut *recording).await;
+            }
+            // the stream is needed until the recording got its last audio
+            if let Some(stream) = self.device_manager.stream(&device) {
+                let _ = stream.stop().await;
+            }
+        }
+        self.recording_handles.clear();
+
+        // transcripts are only complete once the audio handler is done
+        self.draining_audio.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.recording_receiver_handle.write().await.take() {
+            let _ = handle.await;
+        }
+        self.draining_transcripts.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.transcription_receiver_handle.write().await.take() {
+            let _ = handle.await;
+        }
+
+        self.device_manager.stop_all_devices().await?;
+        info!("audio manager drained");
+        Ok(())
+    }
+
     pub async fn shutdown(&self) -> Result<()> {
         self.stop().await?;
         let rec = self.recording_handles.clone();
@@ -386,6 +795,35 @@ impl AudioManager {
     pub async fn enabled_devices(&self) -> HashSet<String> {
         self.options.read().await.enabled_devices.clone()
     }
+
+    pub async fn device_priority(&self) -> Vec<String> {
+        self.options.read().await.device_priority.clone()
+    }
+}
+
+fn load_whisper_context(
+    model_path: &std::path::Path,
+    engine: Arc<AudioTranscriptionEngine>,
+) -> Result<WhisperContext> {
+    let context_param = create_whisper_context_parameters(engine)?;
+    WhisperContext::new_with_params(&model_path.to_string_lossy(), context_param)
+        .map_err(|e| anyhow!("failed to load whisper model {:?}: {}", model_path, e))
+}
+
+fn new_translator(
+    options: &AudioManagerOptions,
+    whisper_context: &Arc<WhisperContext>,
+) -> Option<Arc<dyn Translator>> {
+    options
+        .translate_transcripts
+        .then(|| Arc::new(WhisperTranslator::new(whisper_context.clone())) as Arc<dyn Translator>)
+}
+
+async fn new_vad_engine(engine: &VadEngineEnum) -> Result<Box<dyn VadEngine + Send>> {
+    Ok(match engine {
+        VadEngineEnum::Silero => Box::new(SileroVad::new().await?),
+        VadEngineEnum::WebRtc => Box::new(WebRtcVad::new()),
+    })
 }
 
 impl Drop for AudioManager {
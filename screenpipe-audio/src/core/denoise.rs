@@ -0,0 +1,137 @@
+//! Noise suppression with RNNoise before voice activity detection and
+//! transcription. Steady background noise like fans and keyboards is removed
+//! from the chunks of the devices it's enabled for. The signal to noise ratio
+//! of each chunk is measured before and after, so the effect shows in
+//! `/health`.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use nnnoiseless::DenoiseState;
+
+use crate::utils::audio::resample;
+
+/// RNNoise works on 48kHz audio.
+const RNNOISE_SAMPLE_RATE: u32 = 48000;
+/// RNNoise takes samples in the range of 16 bit integers.
+const I16_SCALE: f32 = 32767.0;
+/// Loudness is measured on frames of 10ms for the signal to noise ratio.
+const SNR_FRAME_SECONDS: f32 = 0.01;
+/// Share of the quietest and loudest frames taken as noise and signal.
+const SNR_PERCENTILE: f32 = 0.1;
+/// Frames quieter than this are digital silence, not noise.
+const MIN_FRAME_POWER: f32 = 1e-10;
+/// Weight of the latest chunk in the averaged ratios.
+const SNR_SMOOTHING: f32 = 0.2;
+
+/// Devices whose chunks are denoised.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NoiseSuppression {
+    /// Whether devices without their own setting are denoised
+    pub default: bool,
+    /// Setting of each device, by name, e.g. `MacBook Pro Microphone (input)`
+    pub devices: HashMap<String, bool>,
+}
+
+impl NoiseSuppression {
+    pub fn is_enabled(&self, device: &str) -> bool {
+        self.devices.get(device).copied().unwrap_or(self.default)
+    }
+}
+
+/// Signal to noise ratio of a device's chunks before and after denoising,
+/// averaged over the chunks denoised.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnrStats {
+    pub before_db: f32,
+    pub after_db: f32,
+    pub chunks: u64,
+}
+
+lazy_static! {
+    pub static ref DEVICE_SNR: DashMap<String, SnrStats> = DashMap::new();
+}
+
+/// Adds the ratios of a chunk of `device` to its averages.
+pub fn record_snr(device: &str, before_db: f32, after_db: f32) {
+    DEVICE_SNR
+        .entry(device.to_string())
+        .and_modify(|stats| {
+            stats.before_db += SNR_SMOOTHING * (before_db - stats.before_db);
+            stats.after_db += SNR_SMOOTHING * (after_db - stats.after_db);
+            stats.chunks += 1;
+        })
+        .or_insert(SnrStats {
+            before_db,
+            after_db,
+            chunks: 1,
+        });
+}
+
+/// Ratios of every device that had a chunk denoised.
+pub fn device_snr() -> Vec<(String, SnrStats)> {
+    let mut stats: Vec<_> = DEVICE_SNR
+        .iter()
+        .map(|entry| (entry.key().clone(), *entry.value()))
+        .collect();
+    stats.sort_by(|a, b| a.0.cmp(&b.0));
+    stats
+}
+
+/// Estimated signal to noise ratio of mono audio in dB: the power of its
+/// loudest frames over the power of its quietest ones, the background noise
+/// between words. `None` for silent or too short audio.
+pub fn snr_db(samples: &[f32], sample_rate: u32) -> Option<f32> {
+    let frame_len = ((sample_rate as f32 * SNR_FRAME_SECONDS) as usize).max(1);
+    let mut powers: Vec<f32> = samples
+        .chunks_exact(frame_len)
+        .map(|frame| frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32)
+        .collect();
+    let count = (powers.len() as f32 * SNR_PERCENTILE) as usize;
+    if count == 0 {
+        return None;
+    }
+    powers.sort_by(|a, b| a.total_cmp(b));
+
+    let mean = |frames: &[f32]| frames.iter().sum::<f32>() / frames.len() as f32;
+    let noise = mean(&powers[..count]).max(MIN_FRAME_POWER);
+    let signal = mean(&powers[powers.len() - count..]);
+    (signal > MIN_FRAME_POWER).then(|| 10.0 * (signal / noise).log10())
+}
+
+/// Removes background noise from mono audio, returned at the same sample
+/// rate and length.
+pub fn denoise(samples: &[f32], sample_rate: u32) -> Result<Vec<f32>> {
+    if samples.is_empty() {
+        return Ok(Vec::new());
+    }
+    let input = if sample_rate == RNNOISE_SAMPLE_RATE {
+        samples.to_vec()
+    } else {
+        resample(samples, sample_rate, RNNOISE_SAMPLE_RATE)?
+    };
+
+    let mut state = DenoiseState::new();
+    let mut frame_in = [0.0; DenoiseState::FRAME_SIZE];
+    let mut frame_out = [0.0; DenoiseState::FRAME_SIZE];
+    let mut output = Vec::with_capacity(input.len());
+    for frame in input.chunks(DenoiseState::FRAME_SIZE) {
+        // the last frame is padded with silence
+        frame_in.fill(0.0);
+        for (scaled, sample) in frame_in.iter_mut().zip(frame) {
+            *scaled = sample * I16_SCALE;
+        }
+        state.process_frame(&mut frame_out, &frame_in);
+        output.extend(frame_out[..frame.len()].iter().map(|s| s / I16_SCALE));
+    }
+
+    let mut output = if sample_rate == RNNOISE_SAMPLE_RATE {
+        output
+    } else {
+        resample(&output, RNNOISE_SAMPLE_RATE, sample_rate)?
+    };
+    output.resize(samples.len(), 0.0);
+    Ok(output)
+}
@@ -44,6 +44,13 @@ impl From<DeviceType> for Arc<screenpipe_db::DeviceType> {
     }
 }
 
+/// Prefix of devices that capture a single application, e.g. `app:Zoom.exe`.
+pub const APP_DEVICE_PREFIX: &str = "app:";
+
+/// Marks devices that capture a single channel of a multi-channel device,
+/// e.g. `Scarlett 4i4 [ch 2: guest]` for its second channel labelled `guest`.
+const CHANNEL_MARKER: &str = " [ch ";
+
 #[derive(Clone, Eq, PartialEq, Hash, Serialize, Debug)]
 pub struct AudioDevice {
     pub name: String,
@@ -64,6 +71,59 @@ impl AudioDevice {
         AudioDevice { name, device_type }
     }
 
+    /// Device capturing only the audio played by `process_name` (Windows only).
+    /// Process names are case insensitive, so they are stored lowercase.
+    pub fn app(process_name: &str) -> Self {
+        AudioDevice::new(
+            format!("{}{}", APP_DEVICE_PREFIX, process_name.trim().to_lowercase()),
+            DeviceType::Output,
+        )
+    }
+
+    /// Application this device captures, if it is an application device.
+    pub fn source_app(&self) -> Option<&str> {
+        self.name.strip_prefix(APP_DEVICE_PREFIX)
+    }
+
+    /// Device capturing only `channel` (counted from 1) of `device`. The label
+    /// is part of the name, so transcripts of the channel keep it.
+    pub fn channel(device: &AudioDevice, channel: u16, label: Option<&str>) -> Self {
+        let name = match label.map(str::trim).filter(|label| !label.is_empty()) {
+            Some(label) => format!("{}{}{}: {}]", device.name, CHANNEL_MARKER, channel, label),
+            None => format!("{}{}{}]", device.name, CHANNEL_MARKER, channel),
+        };
+        AudioDevice::new(name, device.device_type.clone())
+    }
+
+    /// Channel this device captures, if it is a channel device.
+    pub fn source_channel(&self) -> Option<u16> {
+        self.channel_parts().map(|(_, channel, _)| channel)
+    }
+
+    /// Label of the channel this device captures.
+    pub fn channel_label(&self) -> Option<&str> {
+        self.channel_parts().and_then(|(_, _, label)| label)
+    }
+
+    /// Device the audio is captured from: the multi-channel device of a
+    /// channel device, the device itself otherwise.
+    pub fn capture_device(&self) -> AudioDevice {
+        match self.channel_parts() {
+            Some((name, _, _)) => AudioDevice::new(name.to_string(), self.device_type.clone()),
+            None => self.clone(),
+        }
+    }
+
+    fn channel_parts(&self) -> Option<(&str, u16, Option<&str>)> {
+        let (name, spec) = self.name.strip_suffix(']')?.rsplit_once(CHANNEL_MARKER)?;
+        let (channel, label) = match spec.split_once(':') {
+            Some((channel, label)) => (channel, Some(label.trim())),
+            None => (spec, None),
+        };
+        let channel = channel.trim().parse().ok().filter(|channel| *channel > 0)?;
+        Some((name, channel, label))
+    }
+
     pub fn from_name(name: &str) -> Result<Self> {
         if name.trim().is_empty() {
             return Err(anyhow!("Device name cannot be empty"));
@@ -107,6 +167,36 @@ pub fn parse_audio_device(name: &str) -> Result<AudioDevice> {
     AudioDevice::from_name(name)
 }
 
+/// Parses a channel of a device as `<device>:<channel>`, optionally followed
+/// by `=<label>`, e.g. `Scarlett 4i4 (input):2=guest`.
+pub fn parse_audio_channel(spec: &str) -> Result<AudioDevice> {
+    let (device, label) = match spec.split_once('=') {
+        Some((device, label)) => (device, Some(label)),
+        None => (spec, None),
+    };
+    let (device, channel) = device
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("channel not specified in {}, e.g. \"mic (input):1\"", spec))?;
+    let channel: u16 = channel
+        .trim()
+        .parse()
+        .ok()
+        .filter(|channel| *channel > 0)
+        .ok_or_else(|| {
+            anyhow!(
+                "invalid channel {} in {}, channels start at 1",
+                channel,
+                spec
+            )
+        })?;
+
+    Ok(AudioDevice::channel(
+        &parse_audio_device(device.trim())?,
+        channel,
+        label,
+    ))
+}
+
 /// Attempts an operation with exponential backoff retry
 #[cfg(target_os = "macos")]
 async fn with_retry<T, F, Fut>(operation: F, max_retries: usize) -> Result<T>
@@ -161,6 +251,8 @@ async fn get_screen_capture_host() -> Result<cpal::Host> {
 pub async fn get_cpal_device_and_config(
     audio_device: &AudioDevice,
 ) -> Result<(cpal::Device, cpal::SupportedStreamConfig)> {
+    // channels are captured from their multi-channel device
+    let audio_device = &audio_device.capture_device();
     let host = cpal::default_host();
     let is_output_device = audio_device.device_type == DeviceType::Output;
     let is_display = audio_device.to_string().contains("Display");
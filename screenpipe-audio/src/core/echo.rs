@@ -0,0 +1,236 @@
+//! Echo suppression between microphones and system audio. During a call the
+//! remote side plays through the speakers and is picked up again by the mic,
+//! so the same speech would be transcribed twice. Output devices feed a short
+//! history of what was played, and mic chunks are compared against it before
+//! transcription: frames whose loudness follows the played audio are muted.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+/// Loudness envelope resolution, 10ms per value.
+const ENVELOPE_RATE: u32 = 100;
+/// Echo arrives at the mic within this delay of being played. Also covers
+/// the timing jitter between the two streams.
+const MAX_DELAY_FRAMES: usize = 50;
+/// Mic audio is muted in frames of this many envelope values (250ms).
+const FRAME_LEN: usize = 25;
+/// Each frame is judged on the second of audio around it, shorter windows
+/// hold too few syllables to tell speech apart.
+const CORRELATION_WINDOW: usize = 100;
+/// Correlation above which a frame is considered an echo.
+const ECHO_CORRELATION: f32 = 0.7;
+/// Chunks correlating less than this with the played audio as a whole hold
+/// no echo worth looking for.
+const MIN_CHUNK_CORRELATION: f32 = 0.3;
+/// Frames quieter than this on either side are left alone.
+const MIN_FRAME_RMS: f32 = 1e-3;
+/// How much played audio is kept.
+const REFERENCE_HISTORY: Duration = Duration::from_secs(90);
+
+struct ReferenceBlock {
+    device: String,
+    /// When the last sample of the block was captured.
+    captured_at: Instant,
+    sample_rate: u32,
+    samples: Vec<f32>,
+}
+
+/// Recently played audio, per output device.
+#[derive(Default)]
+pub struct EchoReference {
+    blocks: Mutex<VecDeque<ReferenceBlock>>,
+}
+
+lazy_static! {
+    pub static ref ECHO_REFERENCE: EchoReference = EchoReference::default();
+}
+
+impl EchoReference {
+    /// Records audio an output device just captured.
+    pub fn push(&self, device: &str, samples: &[f32], sample_rate: u32) {
+        let now = Instant::now();
+        let mut blocks = self.blocks.lock().unwrap();
+        blocks.push_back(ReferenceBlock {
+            device: device.to_string(),
+            captured_at: now,
+            sample_rate,
+            samples: samples.to_vec(),
+        });
+        while blocks
+            .front()
+            .is_some_and(|b| now.duration_since(b.captured_at) > REFERENCE_HISTORY)
+        {
+            blocks.pop_front();
+        }
+    }
+
+    /// Envelopes of what each output device played between `start` and `end`,
+    /// preceded by `MAX_DELAY_FRAMES` of earlier audio.
+    fn envelopes(&self, start: Instant, end: Instant) -> Vec<Vec<f32>> {
+        let from = start
+            .checked_sub(Duration::from_millis(
+                MAX_DELAY_FRAMES as u64 * 1000 / ENVELOPE_RATE as u64,
+            ))
+            .unwrap_or(start);
+        let len = ((end - from).as_secs_f64() * ENVELOPE_RATE as f64).round() as usize;
+
+        let blocks = self.blocks.lock().unwrap();
+        let mut devices: Vec<(&str, Vec<(f32, usize)>)> = Vec::new();
+        for block in blocks.iter() {
+            let duration =
+                Duration::from_secs_f64(block.samples.len() as f64 / block.sample_rate as f64);
+            let Some(block_start) = block.captured_at.checked_sub(duration) else {
+                continue;
+            };
+            if block.captured_at <= from || block_start >= end {
+                continue;
+            }
+
+            let index = match devices.iter().position(|(d, _)| *d == block.device) {
+                Some(index) => index,
+                None => {
+                    devices.push((&block.device, vec![(0.0, 0); len]));
+                    devices.len() - 1
+                }
+            };
+            let bins = &mut devices[index].1;
+
+            // accumulate squared samples into 10ms bins
+            let block_offset = if block_start >= from {
+                (block_start - from).as_secs_f64()
+            } else {
+                -(from - block_start).as_secs_f64()
+            };
+            for (i, sample) in block.samples.iter().enumerate() {
+                let at = block_offset + i as f64 / block.sample_rate as f64;
+                if at < 0.0 {
+                    continue;
+                }
+                match bins.get_mut((at * ENVELOPE_RATE as f64) as usize) {
+                    Some((sum, count)) => {
+                        *sum += sample * sample;
+                        *count += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        devices
+            .into_iter()
+            .map(|(_, bins)| {
+                bins.into_iter()
+                    .map(|(sum, count)| {
+                        if count == 0 {
+                            0.0
+                        } else {
+                            (sum / count as f32).sqrt()
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Mutes the parts of a mic chunk that ended at `captured_at` which echo
+    /// what an output device played. Returns the number of muted samples.
+    pub fn suppress(&self, mic: &mut [f32], sample_rate: u32, captured_at: Instant) -> usize {
+        let duration = Duration::from_secs_f64(mic.len() as f64 / sample_rate as f64);
+        let Some(start) = captured_at.checked_sub(duration) else {
+            return 0;
+        };
+
+        self.envelopes(start, captured_at)
+            .iter()
+            .map(|reference| suppress_echo(mic, sample_rate, reference))
+            .sum()
+    }
+}
+
+/// RMS of consecutive 10ms windows.
+pub fn envelope(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    let window = (sample_rate / ENVELOPE_RATE).max(1) as usize;
+    samples
+        .chunks(window)
+        .map(|chunk| (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt())
+        .collect()
+}
+
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let n = a.len().min(b.len());
+    if n < 2 {
+        return 0.0;
+    }
+    let (a, b) = (&a[..n], &b[..n]);
+    let mean_a = a.iter().sum::<f32>() / n as f32;
+    let mean_b = b.iter().sum::<f32>() / n as f32;
+
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        let (dx, dy) = (x - mean_a, y - mean_b);
+        cov += dx * dy;
+        var_a += dx * dx;
+        var_b += dy * dy;
+    }
+    if var_a <= f32::EPSILON || var_b <= f32::EPSILON {
+        return 0.0;
+    }
+    cov / (var_a * var_b).sqrt()
+}
+
+/// Mutes mic frames whose loudness follows `reference`, the envelope of the
+/// played audio starting `MAX_DELAY_FRAMES` before the mic audio. The delay
+/// is estimated once for the whole chunk, then every frame is checked at
+/// that delay. Returns the number of muted samples.
+pub fn suppress_echo(mic: &mut [f32], sample_rate: u32, reference: &[f32]) -> usize {
+    let mic_envelope = envelope(mic, sample_rate);
+    if mic_envelope.len() < FRAME_LEN || reference.len() < mic_envelope.len() {
+        return 0;
+    }
+
+    // reference[MAX_DELAY_FRAMES + i] is played at the same time as mic[i] is
+    // recorded, audio played `delay` earlier is at offset MAX_DELAY_FRAMES - delay
+    let max_offset = reference.len() - mic_envelope.len();
+    let Some((offset, best)) = (0..=max_offset)
+        .map(|offset| (offset, correlation(&mic_envelope, &reference[offset..])))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+    else {
+        return 0;
+    };
+    if best < MIN_CHUNK_CORRELATION {
+        return 0;
+    }
+
+    let window = (sample_rate / ENVELOPE_RATE).max(1) as usize;
+    let rms = |f: &[f32]| (f.iter().map(|v| v * v).sum::<f32>() / f.len() as f32).sqrt();
+    let mut muted = 0;
+    for frame_start in (0..mic_envelope.len()).step_by(FRAME_LEN) {
+        let frame_end = (frame_start + FRAME_LEN).min(mic_envelope.len());
+        if rms(&mic_envelope[frame_start..frame_end]) < MIN_FRAME_RMS
+            || rms(&reference[frame_start + offset..frame_end + offset]) < MIN_FRAME_RMS
+        {
+            continue;
+        }
+
+        // window centered on the frame, shifted to stay inside the chunk
+        let center = (frame_start + frame_end) / 2;
+        let end = (center + CORRELATION_WINDOW / 2).min(mic_envelope.len());
+        let start = end.saturating_sub(CORRELATION_WINDOW);
+        let end = (start + CORRELATION_WINDOW).min(mic_envelope.len());
+
+        if correlation(
+            &mic_envelope[start..end],
+            &reference[start + offset..end + offset],
+        ) >= ECHO_CORRELATION
+        {
+            let from = frame_start * window;
+            let to = (frame_end * window).min(mic.len());
+            mic[from..to].fill(0.0);
+            muted += to - from;
+        }
+    }
+    muted
+}
@@ -1,4 +1,4 @@
-use std::fmt;
+use std::{fmt, str::FromStr};
 
 #[derive(Clone, Debug, PartialEq, Default)]
 pub enum AudioTranscriptionEngine {
@@ -12,6 +12,19 @@ pub enum AudioTranscriptionEngine {
     WhisperLargeV3Quantized,
 }
 
+impl AudioTranscriptionEngine {
+    /// Smaller local model to use on battery, `None` if the engine is already
+    /// small or runs in the cloud.
+    pub fn low_power(&self) -> Option<AudioTranscriptionEngine> {
+        match self {
+            AudioTranscriptionEngine::Deepgram
+            | AudioTranscriptionEngine::WhisperTiny
+            | AudioTranscriptionEngine::WhisperTinyQuantized => None,
+            _ => Some(AudioTranscriptionEngine::WhisperTinyQuantized),
+        }
+    }
+}
+
 impl fmt::Display for AudioTranscriptionEngine {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -29,3 +42,25 @@ impl fmt::Display for AudioTranscriptionEngine {
         }
     }
 }
+
+impl FromStr for AudioTranscriptionEngine {
+    type Err = anyhow::Error;
+
+    /// Parses the names used by the cli, e.g. `whisper-large-v3-turbo-quantized`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "deepgram" => Ok(AudioTranscriptionEngine::Deepgram),
+            "whisper-tiny" => Ok(AudioTranscriptionEngine::WhisperTiny),
+            "whisper-tiny-quantized" => Ok(AudioTranscriptionEngine::WhisperTinyQuantized),
+            "whisper-large" | "whisper-large-v3" => Ok(AudioTranscriptionEngine::WhisperLargeV3),
+            "whisper-large-quantized" | "whisper-large-v3-quantized" => {
+                Ok(AudioTranscriptionEngine::WhisperLargeV3Quantized)
+            }
+            "whisper-large-v3-turbo" => Ok(AudioTranscriptionEngine::WhisperLargeV3Turbo),
+            "whisper-large-v3-turbo-quantized" => {
+                Ok(AudioTranscriptionEngine::WhisperLargeV3TurboQuantized)
+            }
+            _ => Err(anyhow::anyhow!("unknown transcription engine: {}", s)),
+        }
+    }
+}
@@ -1,10 +1,15 @@
+pub mod denoise;
 pub mod device;
+pub mod echo;
 pub mod engine;
+#[cfg(target_os = "windows")]
+pub mod process_loopback;
 mod run_record_and_transcribe;
 pub mod stream;
 use crate::transcription::deepgram::streaming::stream_transcription_deepgram;
 use crate::AudioInput;
 use anyhow::Result;
+use denoise::NoiseSuppression;
 use dashmap::DashMap;
 use lazy_static::lazy_static;
 use screenpipe_core::Language;
@@ -12,6 +17,7 @@ use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use stream::AudioStream;
+use tokio::sync::watch;
 use tracing::error;
 
 lazy_static! {
@@ -56,18 +62,56 @@ fn is_normal_shutdown(is_running: &Arc<AtomicBool>) -> bool {
     !is_running.load(Ordering::Relaxed)
 }
 
+/// How recordings are cut into chunks for transcription.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChunkingConfig {
+    pub duration: Duration,
+    /// Audio of the end of a chunk repeated at the start of the next one, so
+    /// words cut at the boundary are transcribed whole in one of them.
+    pub overlap: Duration,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(30),
+            overlap: Duration::from_secs(2),
+        }
+    }
+}
+
+impl ChunkingConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.duration.is_zero() {
+            return Err(anyhow::anyhow!("audio chunk duration must be positive"));
+        }
+        if self.overlap >= self.duration {
+            return Err(anyhow::anyhow!(
+                "audio chunk overlap must be shorter than the chunk duration"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Records `audio_stream` in chunks, cut as `chunking` says when each chunk
+/// starts.
 pub async fn record_and_transcribe(
     audio_stream: Arc<AudioStream>,
-    duration: Duration,
+    chunking: watch::Receiver<ChunkingConfig>,
     whisper_sender: Arc<crossbeam::channel::Sender<AudioInput>>,
     is_running: Arc<AtomicBool>,
+    echo_cancellation: bool,
+    noise_suppression: watch::Receiver<NoiseSuppression>,
 ) -> Result<()> {
     while is_running.load(Ordering::Relaxed) {
         match run_record_and_transcribe::run_record_and_transcribe(
             audio_stream.clone(),
-            duration,
+            chunking.clone(),
             whisper_sender.clone(),
             is_running.clone(),
+            echo_cancellation,
+            noise_suppression.clone(),
         )
         .await
         {
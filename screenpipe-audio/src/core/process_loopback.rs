@@ -0,0 +1,246 @@
+//! Captures the audio of a single application on Windows through the
+//! ApplicationLoopback API, so e.g. a call can be recorded without the music
+//! playing next to it.
+
+use std::collections::{HashMap, HashSet};
+use std::mem::{size_of, ManuallyDrop};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use tokio::sync::broadcast;
+use tracing::{error, info};
+use windows::core::{implement, IUnknown, Interface, HRESULT, PROPVARIANT};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::Media::Audio::{
+    ActivateAudioInterfaceAsync, IActivateAudioInterfaceAsyncOperation,
+    IActivateAudioInterfaceCompletionHandler, IActivateAudioInterfaceCompletionHandler_Impl,
+    IAudioCaptureClient, IAudioClient, AUDCLNT_BUFFERFLAGS_SILENT, AUDCLNT_SHAREMODE_SHARED,
+    AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM, AUDCLNT_STREAMFLAGS_EVENTCALLBACK,
+    AUDCLNT_STREAMFLAGS_LOOPBACK, AUDIOCLIENT_ACTIVATION_PARAMS, AUDIOCLIENT_ACTIVATION_PARAMS_0,
+    AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK, AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS,
+    PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE, VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+    WAVEFORMATEX,
+};
+use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED};
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+};
+use windows::Win32::System::Threading::{CreateEventW, WaitForSingleObject};
+use windows::Win32::System::Variant::VT_BLOB;
+
+use crate::utils::audio::audio_to_mono;
+
+pub const LOOPBACK_SAMPLE_RATE: u32 = 48000;
+pub const LOOPBACK_CHANNELS: u16 = 2;
+
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+/// 200ms, in 100ns units.
+const BUFFER_DURATION: i64 = 2_000_000;
+
+struct ProcessEntry {
+    pid: u32,
+    parent_pid: u32,
+    name: String,
+}
+
+fn list_processes() -> Result<Vec<ProcessEntry>> {
+    let mut processes = Vec::new();
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)?;
+        let mut entry = PROCESSENTRY32W {
+            dwSize: size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        if Process32FirstW(snapshot, &mut entry).is_ok() {
+            loop {
+                let len = entry
+                    .szExeFile
+                    .iter()
+                    .position(|c| *c == 0)
+                    .unwrap_or(entry.szExeFile.len());
+                processes.push(ProcessEntry {
+                    pid: entry.th32ProcessID,
+                    parent_pid: entry.th32ParentProcessID,
+                    name: String::from_utf16_lossy(&entry.szExeFile[..len]),
+                });
+                if Process32NextW(snapshot, &mut entry).is_err() {
+                    break;
+                }
+            }
+        }
+        let _ = CloseHandle(snapshot);
+    }
+    Ok(processes)
+}
+
+/// Names of all running executables, e.g. `Zoom.exe`.
+pub fn running_process_names() -> HashSet<String> {
+    list_processes()
+        .map(|processes| processes.into_iter().map(|p| p.name).collect())
+        .unwrap_or_default()
+}
+
+/// Finds the top-most process with the given executable name. Apps like Teams
+/// run many processes, capturing the root one includes all its children.
+pub fn find_process_id(process_name: &str) -> Result<u32> {
+    let processes = list_processes()?;
+    let matching: HashMap<u32, u32> = processes
+        .iter()
+        .filter(|p| p.name.eq_ignore_ascii_case(process_name))
+        .map(|p| (p.pid, p.parent_pid))
+        .collect();
+
+    matching
+        .iter()
+        .find(|(_, parent)| !matching.contains_key(parent))
+        .or_else(|| matching.iter().next())
+        .map(|(pid, _)| *pid)
+        .ok_or_else(|| anyhow!("process {} not running", process_name))
+}
+
+#[implement(IActivateAudioInterfaceCompletionHandler)]
+struct ActivationHandler(mpsc::Sender<()>);
+
+impl IActivateAudioInterfaceCompletionHandler_Impl for ActivationHandler_Impl {
+    fn ActivateCompleted(
+        &self,
+        _operation: Option<&IActivateAudioInterfaceAsyncOperation>,
+    ) -> windows::core::Result<()> {
+        let _ = self.0.send(());
+        Ok(())
+    }
+}
+
+unsafe fn activate_process_loopback(pid: u32) -> Result<IAudioClient> {
+    let mut params = AUDIOCLIENT_ACTIVATION_PARAMS {
+        ActivationType: AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK,
+        Anonymous: AUDIOCLIENT_ACTIVATION_PARAMS_0 {
+            ProcessLoopbackParams: AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS {
+                TargetProcessId: pid,
+                ProcessLoopbackMode: PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE,
+            },
+        },
+    };
+
+    // the variant only borrows the params, it must not free them
+    let variant = ManuallyDrop::new(PROPVARIANT::from_raw(windows::core::imp::PROPVARIANT {
+        Anonymous: windows::core::imp::PROPVARIANT_0 {
+            Anonymous: windows::core::imp::PROPVARIANT_0_0 {
+                vt: VT_BLOB.0,
+                wReserved1: 0,
+                wReserved2: 0,
+                wReserved3: 0,
+                Anonymous: windows::core::imp::PROPVARIANT_0_0_0 {
+                    blob: windows::core::imp::BLOB {
+                        cbSize: size_of::<AUDIOCLIENT_ACTIVATION_PARAMS>() as u32,
+                        pBlobData: &mut params as *mut _ as *mut u8,
+                    },
+                },
+            },
+        },
+    }));
+
+    let (tx, rx) = mpsc::channel();
+    let handler: IActivateAudioInterfaceCompletionHandler = ActivationHandler(tx).into();
+    let operation = ActivateAudioInterfaceAsync(
+        VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+        &IAudioClient::IID,
+        Some(&*variant as *const PROPVARIANT),
+        &handler,
+    )?;
+    rx.recv_timeout(Duration::from_secs(5))
+        .map_err(|_| anyhow!("timed out activating process loopback"))?;
+
+    let mut result = HRESULT::default();
+    let mut client: Option<IUnknown> = None;
+    operation.GetActivateResult(&mut result, &mut client)?;
+    result.ok()?;
+    client
+        .ok_or_else(|| anyhow!("process loopback returned no audio client"))?
+        .cast::<IAudioClient>()
+        .map_err(|e| anyhow!(e))
+}
+
+/// Captures the audio of `process_name` and its child processes until
+/// `stop` is set. Blocks, run it on a dedicated thread.
+pub fn capture_process_audio(
+    process_name: &str,
+    tx: broadcast::Sender<Vec<f32>>,
+    stop: Arc<AtomicBool>,
+) -> Result<()> {
+    let pid = find_process_id(process_name)?;
+    info!("capturing audio of {} (pid {})", process_name, pid);
+
+    unsafe {
+        CoInitializeEx(None, COINIT_MULTITHREADED).ok()?;
+        let result = run_capture(pid, &tx, &stop);
+        CoUninitialize();
+        result
+    }
+}
+
+unsafe fn run_capture(pid: u32, tx: &broadcast::Sender<Vec<f32>>, stop: &AtomicBool) -> Result<()> {
+    let client = activate_process_loopback(pid)?;
+
+    let block_align = LOOPBACK_CHANNELS * size_of::<f32>() as u16;
+    let format = WAVEFORMATEX {
+        wFormatTag: WAVE_FORMAT_IEEE_FLOAT,
+        nChannels: LOOPBACK_CHANNELS,
+        nSamplesPerSec: LOOPBACK_SAMPLE_RATE,
+        nAvgBytesPerSec: LOOPBACK_SAMPLE_RATE * block_align as u32,
+        nBlockAlign: block_align,
+        wBitsPerSample: 32,
+        cbSize: 0,
+    };
+    client.Initialize(
+        AUDCLNT_SHAREMODE_SHARED,
+        AUDCLNT_STREAMFLAGS_LOOPBACK
+            | AUDCLNT_STREAMFLAGS_EVENTCALLBACK
+            | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM,
+        BUFFER_DURATION,
+        0,
+        &format,
+        None,
+    )?;
+
+    let event: HANDLE = CreateEventW(None, false, false, None)?;
+    client.SetEventHandle(event)?;
+    let capture: IAudioCaptureClient = client.GetService()?;
+    client.Start()?;
+
+    let result = (|| -> Result<()> {
+        while !stop.load(Ordering::Relaxed) {
+            if WaitForSingleObject(event, 200) != WAIT_OBJECT_0 {
+                continue;
+            }
+
+            while capture.GetNextPacketSize()? > 0 {
+                let mut data: *mut u8 = std::ptr::null_mut();
+                let mut frames = 0u32;
+                let mut flags = 0u32;
+                capture.GetBuffer(&mut data, &mut frames, &mut flags, None, None)?;
+
+                let len = frames as usize * LOOPBACK_CHANNELS as usize;
+                let samples = if flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 != 0 || data.is_null()
+                {
+                    vec![0.0; len]
+                } else {
+                    std::slice::from_raw_parts(data as *const f32, len).to_vec()
+                };
+                capture.ReleaseBuffer(frames)?;
+
+                let _ = tx.send(audio_to_mono(&samples, LOOPBACK_CHANNELS));
+            }
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = client.Stop() {
+        error!("failed to stop process loopback: {}", e);
+    }
+    let _ = CloseHandle(event);
+    result
+}
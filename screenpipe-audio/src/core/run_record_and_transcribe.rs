@@ -3,46 +3,70 @@ use std::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, Result};
+use screenpipe_core::idle::is_audio_idle;
+use screenpipe_core::power::is_audio_paused_on_battery;
+use screenpipe_core::privacy::is_capture_blocked;
+use tokio::sync::watch;
 use tracing::{debug, error, info, warn};
 
-use crate::{core::update_device_capture_time, AudioInput};
+use crate::{
+    core::{
+        denoise::{denoise, record_snr, snr_db, NoiseSuppression},
+        device::DeviceType,
+        echo::ECHO_REFERENCE,
+        update_device_capture_time,
+    },
+    AudioInput,
+};
 
-use super::AudioStream;
+use super::{AudioStream, ChunkingConfig};
 
 pub async fn run_record_and_transcribe(
     audio_stream: Arc<AudioStream>,
-    duration: Duration,
+    chunking: watch::Receiver<ChunkingConfig>,
     whisper_sender: Arc<crossbeam::channel::Sender<AudioInput>>,
     is_running: Arc<AtomicBool>,
+    echo_cancellation: bool,
+    noise_suppression: watch::Receiver<NoiseSuppression>,
 ) -> Result<()> {
     let mut receiver = audio_stream.subscribe().await;
     let device_name = audio_stream.device.to_string();
 
     info!(
-        "starting continuous recording for {} ({}s segments)",
+        "starting continuous recording for {} ({:?})",
         device_name,
-        duration.as_secs()
+        *chunking.borrow()
     );
 
-    const OVERLAP_SECONDS: usize = 2;
     let mut collected_audio = Vec::new();
     let sample_rate = audio_stream.device_config.sample_rate().0 as usize;
-    let audio_samples_len = sample_rate * duration.as_secs() as usize;
-    let overlap_samples = OVERLAP_SECONDS * sample_rate;
-    let max_samples = audio_samples_len + overlap_samples;
+    let samples = |duration: Duration| (duration.as_secs_f64() * sample_rate as f64) as usize;
 
     while is_running.load(Ordering::Relaxed)
         && !audio_stream.is_disconnected.load(Ordering::Relaxed)
     {
+        // changes apply from the next chunk
+        let ChunkingConfig { duration, overlap } = *chunking.borrow();
+        let overlap_samples = samples(overlap);
+        let max_samples = samples(duration) + overlap_samples;
+
         while collected_audio.len() < max_samples && is_running.load(Ordering::Relaxed) {
             match receiver.recv().await {
                 Ok(chunk) => {
-                    collected_audio.extend(chunk);
                     update_device_capture_time(&device_name);
+                    // nothing is recorded while a blocked app or site is focused,
+                    // the user is away or the battery is low
+                    if is_capture_blocked() || is_audio_idle() || is_audio_paused_on_battery() {
+                        continue;
+                    }
+                    if echo_cancellation && audio_stream.device.device_type == DeviceType::Output {
+                        ECHO_REFERENCE.push(&device_name, &chunk, sample_rate as u32);
+                    }
+                    collected_audio.extend(chunk);
                 }
                 Err(e) => {
                     error!("error receiving audio data: {}", e);
@@ -52,12 +76,29 @@ pub async fn run_record_and_transcribe(
         }
 
         if !collected_audio.is_empty() {
+            let mut data = collected_audio.clone();
+            if echo_cancellation && audio_stream.device.device_type == DeviceType::Input {
+                let muted = ECHO_REFERENCE.suppress(&mut data, sample_rate as u32, Instant::now());
+                if muted > 0 {
+                    debug!(
+                        "muted {:.1}s of echoed system audio in {}",
+                        muted as f32 / sample_rate as f32,
+                        device_name
+                    );
+                }
+            }
+            if noise_suppression.borrow().is_enabled(&device_name) {
+                data = denoise_chunk(data, sample_rate as u32, &device_name).await;
+            }
+
             debug!("sending audio segment to audio model");
             match whisper_sender.try_send(AudioInput {
-                data: Arc::new(collected_audio.clone()),
+                data: Arc::new(data),
                 device: audio_stream.device.clone(),
                 sample_rate: audio_stream.device_config.sample_rate().0,
                 channels: audio_stream.device_config.channels(),
+                recorded_at: None,
+                media_detection: true,
             }) {
                 Ok(_) => {
                     debug!("sent audio segment to audio model");
@@ -82,3 +123,33 @@ pub async fn run_record_and_transcribe(
     info!("stopped recording for {}", device_name);
     Ok(())
 }
+
+/// Denoises a chunk off the async runtime and records how much it helped,
+/// the chunk is kept as it was if denoising fails.
+async fn denoise_chunk(data: Vec<f32>, sample_rate: u32, device_name: &str) -> Vec<f32> {
+    let input = Arc::new(data);
+    let samples = input.clone();
+    let denoised = tokio::task::spawn_blocking(move || denoise(&samples, sample_rate))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|result| result);
+
+    match denoised {
+        Ok(denoised) => {
+            if let (Some(before), Some(after)) =
+                (snr_db(&input, sample_rate), snr_db(&denoised, sample_rate))
+            {
+                debug!(
+                    "denoised {}: snr {:.1}dB -> {:.1}dB",
+                    device_name, before, after
+                );
+                record_snr(device_name, before, after);
+            }
+            denoised
+        }
+        Err(e) => {
+            warn!("failed to denoise audio of {}: {}", device_name, e);
+            Arc::unwrap_or_clone(input)
+        }
+    }
+}
@@ -8,7 +8,7 @@ use tokio::sync::{broadcast, oneshot};
 use tokio::task::LocalSet;
 use tracing::{error, warn};
 
-use crate::utils::audio::audio_to_mono;
+use crate::utils::audio::{audio_channel, audio_to_mono};
 
 use super::device::{get_cpal_device_and_config, AudioDevice};
 
@@ -31,10 +31,25 @@ impl AudioStream {
         device: Arc<AudioDevice>,
         is_running: Arc<AtomicBool>,
     ) -> Result<Self> {
+        #[cfg(target_os = "windows")]
+        if let Some(process_name) = device.source_app() {
+            let process_name = process_name.to_string();
+            return Self::from_app(device, process_name).await;
+        }
+
         let (tx, _) = broadcast::channel::<Vec<f32>>(1000);
         let tx_clone = tx.clone();
         let (cpal_audio_device, config) = get_cpal_device_and_config(&device).await?;
         let channels = config.channels();
+        let channel = device.source_channel();
+        if let Some(channel) = channel.filter(|channel| *channel > channels) {
+            return Err(anyhow!(
+                "{} has {} channels, channel {} can't be recorded",
+                device.capture_device(),
+                channels,
+                channel
+            ));
+        }
 
         let is_running_weak = Arc::downgrade(&is_running);
         let is_disconnected = Arc::new(AtomicBool::new(false));
@@ -46,6 +61,7 @@ impl AudioStream {
             tx,
             stream_control_rx,
             channels,
+            channel,
             is_running_weak,
             is_disconnected.clone(),
             stream_control_tx.clone(),
@@ -62,6 +78,54 @@ impl AudioStream {
         })
     }
 
+    /// Captures a single application instead of a device.
+    #[cfg(target_os = "windows")]
+    async fn from_app(device: Arc<AudioDevice>, process_name: String) -> Result<Self> {
+        use super::process_loopback::{
+            capture_process_audio, find_process_id, LOOPBACK_CHANNELS, LOOPBACK_SAMPLE_RATE,
+        };
+
+        // fail early, the device monitor retries once the app is running
+        find_process_id(&process_name)?;
+
+        let (tx, _) = broadcast::channel::<Vec<f32>>(1000);
+        let tx_clone = tx.clone();
+        let config = cpal::SupportedStreamConfig::new(
+            LOOPBACK_CHANNELS,
+            cpal::SampleRate(LOOPBACK_SAMPLE_RATE),
+            cpal::SupportedBufferSize::Unknown,
+            cpal::SampleFormat::F32,
+        );
+        let is_disconnected = Arc::new(AtomicBool::new(false));
+        let (stream_control_tx, stream_control_rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let capture_disconnected = is_disconnected.clone();
+        let stream_thread = tokio::task::spawn_blocking(move || {
+            let capture_stop = stop.clone();
+            std::thread::spawn(move || {
+                if let Ok(StreamControl::Stop(response)) = stream_control_rx.recv() {
+                    capture_stop.store(true, Ordering::Relaxed);
+                    response.send(()).ok();
+                }
+            });
+
+            if let Err(e) = capture_process_audio(&process_name, tx, stop) {
+                error!("failed to capture audio of {}: {}", process_name, e);
+                capture_disconnected.store(true, Ordering::Relaxed);
+            }
+        });
+
+        Ok(AudioStream {
+            device,
+            device_config: config,
+            transmitter: Arc::new(tx_clone),
+            stream_control: stream_control_tx,
+            stream_thread: Some(Arc::new(tokio::sync::Mutex::new(Some(stream_thread)))),
+            is_disconnected,
+        })
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn spawn_audio_thread(
         device: cpal::Device,
@@ -69,6 +133,7 @@ impl AudioStream {
         tx: broadcast::Sender<Vec<f32>>,
         stream_control_rx: mpsc::Receiver<StreamControl>,
         channels: u16,
+        channel: Option<u16>,
         is_running_weak: std::sync::Weak<AtomicBool>,
         is_disconnected: Arc<AtomicBool>,
         stream_control_tx: mpsc::Sender<StreamControl>,
@@ -83,7 +148,8 @@ impl AudioStream {
                 stream_control_tx,
             );
 
-            let stream = build_input_stream(&device, &config, channels, tx, error_callback);
+            let stream =
+                build_input_stream(&device, &config, channels, channel, tx, error_callback);
 
             match stream {
                 Ok(stream) => {
@@ -170,6 +236,7 @@ fn build_input_stream(
     device: &cpal::Device,
     config: &cpal::SupportedStreamConfig,
     channels: u16,
+    channel: Option<u16>,
     tx: broadcast::Sender<Vec<f32>>,
     error_callback: impl FnMut(StreamError) + Send + 'static,
 ) -> Result<cpal::Stream> {
@@ -178,7 +245,7 @@ fn build_input_stream(
             .build_input_stream(
                 &config.config(),
                 move |data: &[f32], _: &_| {
-                    let mono = audio_to_mono(data, channels);
+                    let mono = to_mono(data, channels, channel);
                     let _ = tx.send(mono);
                 },
                 error_callback,
@@ -189,7 +256,7 @@ fn build_input_stream(
             .build_input_stream(
                 &config.config(),
                 move |data: &[i16], _: &_| {
-                    let mono = audio_to_mono(bytemuck::cast_slice(data), channels);
+                    let mono = to_mono(bytemuck::cast_slice(data), channels, channel);
                     let _ = tx.send(mono);
                 },
                 error_callback,
@@ -200,7 +267,7 @@ fn build_input_stream(
             .build_input_stream(
                 &config.config(),
                 move |data: &[i32], _: &_| {
-                    let mono = audio_to_mono(bytemuck::cast_slice(data), channels);
+                    let mono = to_mono(bytemuck::cast_slice(data), channels, channel);
                     let _ = tx.send(mono);
                 },
                 error_callback,
@@ -211,7 +278,7 @@ fn build_input_stream(
             .build_input_stream(
                 &config.config(),
                 move |data: &[i8], _: &_| {
-                    let mono = audio_to_mono(bytemuck::cast_slice(data), channels);
+                    let mono = to_mono(bytemuck::cast_slice(data), channels, channel);
                     let _ = tx.send(mono);
                 },
                 error_callback,
@@ -225,6 +292,14 @@ fn build_input_stream(
     }
 }
 
+/// The recorded channel of the audio, or all of its channels mixed down.
+fn to_mono(data: &[f32], channels: u16, channel: Option<u16>) -> Vec<f32> {
+    match channel {
+        Some(channel) => audio_channel(data, channels, channel),
+        None => audio_to_mono(data, channels),
+    }
+}
+
 impl Drop for AudioStream {
     fn drop(&mut self) {
         let set = LocalSet::new();
@@ -24,11 +24,22 @@ impl DeviceManager {
     }
 
     pub async fn devices(&self) -> Vec<AudioDevice> {
-        list_audio_devices().await.unwrap_or_default()
+        #[allow(unused_mut)]
+        let mut devices = list_audio_devices().await.unwrap_or_default();
+
+        // every running application can be captured on its own
+        #[cfg(target_os = "windows")]
+        devices.extend(
+            crate::core::process_loopback::running_process_names()
+                .iter()
+                .map(|name| AudioDevice::app(name)),
+        );
+
+        devices
     }
 
     pub async fn start_device(&self, device: &AudioDevice) -> Result<()> {
-        if !self.devices().await.contains(device) {
+        if !self.devices().await.contains(&device.capture_device()) {
             return Err(anyhow!("device {device} not found"));
         }
 
@@ -1,4 +1,5 @@
 pub mod core;
+pub mod media_detection;
 mod utils;
 pub mod vad;
 pub use transcription::stt::stt;
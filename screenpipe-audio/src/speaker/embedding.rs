@@ -3,6 +3,11 @@ use ndarray::Array2;
 use ort::Session;
 use std::path::Path;
 
+/// Length of the windows a voice sample is cut into, each giving an embedding.
+const SAMPLE_WINDOW_SECONDS: usize = 10;
+/// Windows shorter than this, at the end of a sample, are left out.
+pub const MIN_SAMPLE_SECONDS: usize = 2;
+
 #[derive(Debug)]
 pub struct EmbeddingExtractor {
     session: Session,
@@ -33,4 +38,16 @@ impl EmbeddingExtractor {
         // Return an iterator over the Vec
         Ok(embeddings.into_iter())
     }
+
+    /// Embeddings of a voice sample, one per window of 10 seconds, so that a
+    /// speaker is recognized however they sound during the sample.
+    pub fn compute_windows(&mut self, samples: &[f32], sample_rate: u32) -> Result<Vec<Vec<f32>>> {
+        let window = SAMPLE_WINDOW_SECONDS * sample_rate as usize;
+        let min_window = MIN_SAMPLE_SECONDS * sample_rate as usize;
+        samples
+            .chunks(window)
+            .filter(|chunk| chunk.len() >= min_window)
+            .map(|chunk| Ok(self.compute(chunk)?.collect()))
+            .collect()
+    }
 }
@@ -1,10 +1,10 @@
 use super::segment::get_segments;
 use crate::{
     utils::audio::{average_noise_spectrum, normalize_v2, spectral_subtraction},
-    vad::VadEngine,
+    vad::{smooth_speech_frames, VadEngine},
 };
 use anyhow::Result;
-use std::{path::PathBuf, sync::Arc, sync::Mutex as StdMutex};
+use std::{path::PathBuf, sync::Arc, sync::Mutex as StdMutex, time::Duration};
 use tokio::sync::Mutex;
 use tracing::{error, info};
 use vad_rs::VadStatus;
@@ -31,22 +31,35 @@ pub async fn prepare_segments(
     let mut total_frames = 0;
     let mut speech_frame_count = 0;
 
-    for chunk in audio_data.chunks(frame_size) {
+    let mut statuses = Vec::new();
+    let timing = {
+        let mut vad_engine = vad_engine.lock().await;
+        for chunk in audio_data.chunks(frame_size) {
+            statuses.push(vad_engine.audio_type(chunk));
+        }
+        vad_engine.speech_timing()
+    };
+    let speech: Vec<bool> = statuses
+        .iter()
+        .map(|status| matches!(status, Ok(VadStatus::Speech)))
+        .collect();
+    let speech = smooth_speech_frames(
+        &speech,
+        Duration::from_secs_f64(frame_size as f64 / 16000.0),
+        timing,
+    );
+
+    for ((chunk, status), is_speech) in audio_data.chunks(frame_size).zip(statuses).zip(speech) {
         total_frames += 1;
 
         let mut new_chunk = chunk.to_vec();
-        let status = vad_engine.lock().await.audio_type(chunk);
-        match status {
-            Ok(VadStatus::Speech) => {
-                if let Ok(processed_audio) = spectral_subtraction(chunk, noise) {
-                    new_chunk = processed_audio;
-                    speech_frame_count += 1;
-                }
-            }
-            Ok(VadStatus::Unknown) => {
-                noise = average_noise_spectrum(chunk);
+        if is_speech {
+            if let Ok(processed_audio) = spectral_subtraction(chunk, noise) {
+                new_chunk = processed_audio;
+                speech_frame_count += 1;
             }
-            _ => {}
+        } else if let Ok(VadStatus::Unknown) = status {
+            noise = average_noise_spectrum(chunk);
         }
         audio_frames.extend(new_chunk);
     }
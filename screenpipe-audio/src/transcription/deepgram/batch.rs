@@ -7,6 +7,7 @@ use std::io::Cursor;
 use tracing::{debug, error, info};
 
 use crate::transcription::deepgram::{CUSTOM_DEEPGRAM_API_TOKEN, DEEPGRAM_API_URL};
+use crate::transcription::{TimedWord, Transcript};
 
 pub async fn transcribe_with_deepgram(
     api_key: &str,
@@ -14,7 +15,8 @@ pub async fn transcribe_with_deepgram(
     device: &str,
     sample_rate: u32,
     languages: Vec<Language>,
-) -> Result<String> {
+    keywords: Vec<String>,
+) -> Result<Transcript> {
     debug!("starting deepgram transcription");
 
     // Use token from env var
@@ -24,7 +26,7 @@ pub async fn transcribe_with_deepgram(
     // Create a WAV file in memory
     let wav_data = create_wav_file(audio_data, sample_rate)?;
 
-    let query_params = create_query_params(languages);
+    let query_params = create_query_params(&languages);
 
     // rationale: custom api key = custom AI proxy to use deepgram
     // no custom api key = use deepgram api key for real deepgram endpoint
@@ -36,10 +38,20 @@ pub async fn transcribe_with_deepgram(
 
     debug!("deepgram api key: {}", api_key_to_use);
 
-    let response =
-        get_deepgram_response(api_key_to_use, is_custom_endpoint, wav_data, query_params).await;
-
-    handle_deepgram_response(response, device).await
+    let response = get_deepgram_response(
+        api_key_to_use,
+        is_custom_endpoint,
+        wav_data,
+        query_params,
+        &keywords,
+    )
+    .await;
+
+    let mut transcript = handle_deepgram_response(response, device).await?;
+    if let [language] = languages.as_slice() {
+        transcript.language = Some(language.as_lang_code().to_string());
+    }
+    Ok(transcript)
 }
 
 fn create_wav_file(audio_data: &[f32], sample_rate: u32) -> Result<Vec<u8>> {
@@ -66,10 +78,12 @@ fn create_wav_file(audio_data: &[f32], sample_rate: u32) -> Result<Vec<u8>> {
     Ok(cursor.into_inner())
 }
 
-fn create_query_params(languages: Vec<Language>) -> String {
+fn create_query_params(languages: &[Language]) -> String {
     let mut query_params = String::from("model=nova-2&smart_format=true&sample_rate=16000");
 
-    if !languages.is_empty() {
+    if languages.is_empty() {
+        query_params.push_str("&detect_language=true");
+    } else {
         query_params = [
             query_params,
             "&".into(),
@@ -90,11 +104,18 @@ async fn get_deepgram_response(
     is_custom_endpoint: bool,
     wav_data: Vec<u8>,
     params: String,
+    keywords: &[String],
 ) -> Result<Response, reqwest::Error> {
     let client = Client::new();
 
     client
         .post(format!("{}?{}", *DEEPGRAM_API_URL, params))
+        .query(
+            &keywords
+                .iter()
+                .map(|keyword| ("keywords", keyword))
+                .collect::<Vec<_>>(),
+        )
         .header("Content-Type", "audio/wav")
         // Use Bearer format when using custom endpoint/proxy
         .header(
@@ -113,7 +134,7 @@ async fn get_deepgram_response(
 async fn handle_deepgram_response(
     response: Result<Response, reqwest::Error>,
     device: &str,
-) -> Result<String> {
+) -> Result<Transcript> {
     match response {
         Ok(resp) => {
             debug!("received response from deepgram api");
@@ -142,7 +163,19 @@ async fn handle_deepgram_response(
                         );
                     }
 
-                    Ok(transcription.to_string())
+                    let language = result["results"]["channels"][0]["detected_language"]
+                        .as_str()
+                        .map(str::to_string);
+                    let words = result["results"]["channels"][0]["alternatives"][0]["words"]
+                        .as_array()
+                        .map(|words| words.iter().filter_map(deepgram_word).collect())
+                        .unwrap_or_default();
+
+                    Ok(Transcript {
+                        text: transcription.to_string(),
+                        language,
+                        words,
+                    })
                 }
                 Err(e) => {
                     error!("Failed to parse JSON response: {:?}", e);
@@ -159,3 +192,15 @@ async fn handle_deepgram_response(
         }
     }
 }
+
+fn deepgram_word(word: &Value) -> Option<TimedWord> {
+    Some(TimedWord {
+        text: word["punctuated_word"]
+            .as_str()
+            .or_else(|| word["word"].as_str())?
+            .to_string(),
+        start: word["start"].as_f64()?,
+        end: word["end"].as_f64()?,
+        confidence: word["confidence"].as_f64().unwrap_or(1.0) as f32,
+    })
+}
@@ -9,6 +9,9 @@ use deepgram::common::options::Encoding;
 use deepgram::common::stream_response::StreamResponse;
 use futures::channel::mpsc::{self, Receiver as FuturesReceiver};
 use futures::{SinkExt, TryStreamExt};
+use screenpipe_core::idle::is_audio_idle;
+use screenpipe_core::power::is_audio_paused_on_battery;
+use screenpipe_core::privacy::is_capture_blocked;
 use screenpipe_core::Language;
 use screenpipe_events::send_event;
 use serde::{Deserialize, Serialize};
@@ -265,6 +268,14 @@ fn get_stream(
                 continue;
             }
 
+            // silence keeps the connection open while a blocked app is focused,
+            // the user is away or the battery is low
+            let data = if is_capture_blocked() || is_audio_idle() || is_audio_paused_on_battery() {
+                vec![0.0; data.len()]
+            } else {
+                data
+            };
+
             let mut bytes = BytesMut::with_capacity(data.len() * 2);
             for sample in data {
                 bytes.put_i16_le((sample * i16::MAX as f32) as i16);
@@ -1,19 +1,53 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::{core::engine::AudioTranscriptionEngine, transcription::process_transcription_result};
+use crate::core::ChunkingConfig;
+use crate::transcription::{process_media_result, process_transcription_result};
+use crossbeam::channel::RecvTimeoutError;
+use screenpipe_core::pii_removal::Redactor;
 use screenpipe_db::DatabaseManager;
-use tracing::{error, info};
+use tokio::sync::watch;
+use tracing::{error, info, info_span, Instrument};
 
 use super::TranscriptionResult;
 
+/// How long the handlers of audio and transcripts wait for more before
+/// checking whether they're draining.
+pub(crate) const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Last transcript stored for a device, its next chunk starts with the end
+/// of the same audio.
+#[derive(Default)]
+struct PreviousTranscript {
+    text: String,
+    id: Option<i64>,
+}
+
 pub async fn handle_new_transcript(
     db: Arc<DatabaseManager>,
     transcription_receiver: Arc<crossbeam::channel::Receiver<TranscriptionResult>>,
-    transcription_engine: Arc<AudioTranscriptionEngine>,
+    redactor: Option<Arc<Redactor>>,
+    chunking: watch::Receiver<ChunkingConfig>,
+    draining: Arc<AtomicBool>,
 ) {
-    let mut previous_transcript = "".to_string();
-    let mut previous_transcript_id: Option<i64> = None;
-    while let Ok(mut transcription) = transcription_receiver.recv() {
+    let mut previous_transcripts: HashMap<String, PreviousTranscript> = HashMap::new();
+    loop {
+        let mut transcription = match transcription_receiver.recv_timeout(DRAIN_POLL_INTERVAL) {
+            Ok(transcription) => transcription,
+            // every transcript is stored, nothing more comes
+            Err(RecvTimeoutError::Timeout) if draining.load(Ordering::SeqCst) => break,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if let Some(classification) = transcription.classification {
+            if let Err(e) = process_media_result(&db, &transcription, classification).await {
+                error!("Error storing media block: {}", e);
+            }
+            continue;
+        }
+
         if transcription
             .transcription
             .clone()
@@ -22,19 +56,30 @@ pub async fn handle_new_transcript(
             continue;
         }
 
+        if let Some(redactor) = &redactor {
+            transcription.transcription = transcription.transcription.map(|t| redactor.redact(&t));
+            transcription.translation = transcription.translation.map(|t| redactor.redact(&t));
+        }
+
         info!(
             "device {} received transcription {:?}",
             transcription.input.device, transcription.transcription
         );
 
+        // chunks of different devices overlap in time but not in audio
+        let previous_transcript = previous_transcripts
+            .entry(transcription.input.device.to_string())
+            .or_default();
+        let overlap = chunking.borrow().overlap;
+
         // Insert the new transcript after fetching
         let mut current_transcript: Option<String> = transcription.transcription.clone();
         let mut processed_previous: Option<String> = None;
         if let Some((previous, current)) =
-            transcription.cleanup_overlap(previous_transcript.clone())
+            transcription.cleanup_overlap(previous_transcript.text.clone(), overlap)
         {
             if !previous.is_empty() && !current.is_empty() {
-                if previous != previous_transcript {
+                if previous != previous_transcript.text {
                     processed_previous = Some(previous);
                 }
                 if current_transcript.is_some()
@@ -46,23 +91,27 @@ pub async fn handle_new_transcript(
         }
 
         transcription.transcription = current_transcript.clone();
-        if current_transcript.is_some() {
-            previous_transcript = current_transcript.unwrap();
-        } else {
-            continue;
+        match current_transcript {
+            Some(current) => previous_transcript.text = current,
+            None => continue,
         }
         // Process the transcription result
+        let db_span = info_span!(
+            parent: &transcription.span,
+            "db_write",
+            device = %transcription.input.device
+        );
         match process_transcription_result(
             &db,
             transcription,
-            transcription_engine.clone(),
             processed_previous,
-            previous_transcript_id,
+            previous_transcript.id,
         )
+        .instrument(db_span)
         .await
         {
             Err(e) => error!("Error processing audio result: {}", e),
-            Ok(id) => previous_transcript_id = id,
+            Ok(id) => previous_transcript.id = id,
         }
     }
 }
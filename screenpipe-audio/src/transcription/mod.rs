@@ -1,9 +1,14 @@
 use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
+
 use crate::core::device::AudioDevice;
 
 pub mod deepgram;
+pub mod provider;
 pub mod stt;
+pub mod translation;
+pub mod vocabulary;
 pub mod whisper;
 
 #[derive(Debug, Clone)]
@@ -12,13 +17,40 @@ pub struct AudioInput {
     pub sample_rate: u32,
     pub channels: u16,
     pub device: Arc<AudioDevice>,
+    /// When the audio was recorded, `None` for audio being recorded
+    pub recorded_at: Option<DateTime<Utc>>,
+    /// Whether it is left untranscribed if it sounds like music and media
+    /// detection is on, false for audio transcribed on request
+    pub media_detection: bool,
+}
+
+/// Text of a transcribed segment and the language it was spoken in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcript {
+    pub text: String,
+    /// ISO 639-1 code, e.g. `en`, when the provider reports it
+    pub language: Option<String>,
+    /// Words of `text` with their timing, when the provider reports them
+    pub words: Vec<TimedWord>,
+}
+
+/// A transcribed word, timed in seconds from the start of the segment.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimedWord {
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+    /// From 0 to 1
+    pub confidence: f32,
 }
 
 mod text_utils;
 
 mod transcription_result;
 
+pub use transcription_result::process_media_result;
 pub use transcription_result::process_transcription_result;
 pub use transcription_result::TranscriptionResult;
 mod handle_new_transcript;
 pub use handle_new_transcript::handle_new_transcript;
+pub(crate) use handle_new_transcript::DRAIN_POLL_INTERVAL;
@@ -0,0 +1,426 @@
+//! Speech to text providers. The local Whisper model and the cloud services
+//! share one interface, so chunks can move to a cloud provider when the local
+//! model fails or cannot keep up with real time.
+
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use hound::{WavSpec, WavWriter};
+use reqwest::{multipart, Client};
+use screenpipe_core::metrics;
+use screenpipe_core::Language;
+use serde_json::Value;
+use std::{
+    io::Cursor,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{debug, info_span, warn, Instrument};
+use whisper_rs::WhisperContext;
+
+use crate::audio_manager::AudioManagerOptions;
+use crate::core::engine::AudioTranscriptionEngine;
+use crate::transcription::deepgram::batch::transcribe_with_deepgram;
+use crate::transcription::vocabulary::Vocabulary;
+use crate::transcription::whisper::batch::process_with_whisper;
+use crate::transcription::Transcript;
+
+/// Local transcription slower than this, relative to the audio duration, is
+/// falling behind.
+pub const MAX_REALTIME_FACTOR: f64 = 1.0;
+/// How long chunks go to the cloud once the local model falls behind.
+pub const FAILOVER_COOLDOWN: Duration = Duration::from_secs(120);
+/// Weight of the latest chunk in the smoothed real time factor.
+const REALTIME_FACTOR_SMOOTHING: f64 = 0.5;
+
+pub const DEFAULT_OPENAI_STT_URL: &str = "https://api.openai.com/v1";
+pub const DEFAULT_OPENAI_STT_MODEL: &str = "whisper-1";
+
+pub trait SttProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Whether audio leaves the machine.
+    fn is_cloud(&self) -> bool;
+
+    fn transcribe<'a>(
+        &'a self,
+        audio: &'a [f32],
+        sample_rate: u32,
+        device: &'a str,
+        languages: &'a [Language],
+        vocabulary: &'a Vocabulary,
+    ) -> BoxFuture<'a, Result<Transcript>>;
+}
+
+/// Cloud provider used when the local model fails or falls behind.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum CloudSttProvider {
+    #[default]
+    Deepgram,
+    OpenAi,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpenAiSttConfig {
+    pub api_key: Option<String>,
+    pub model: String,
+    pub base_url: String,
+}
+
+impl Default for OpenAiSttConfig {
+    fn default() -> Self {
+        Self {
+            api_key: std::env::var("OPENAI_API_KEY").ok(),
+            model: DEFAULT_OPENAI_STT_MODEL.to_string(),
+            base_url: DEFAULT_OPENAI_STT_URL.to_string(),
+        }
+    }
+}
+
+pub struct WhisperProvider {
+    whisper_context: Arc<WhisperContext>,
+}
+
+impl WhisperProvider {
+    pub fn new(whisper_context: Arc<WhisperContext>) -> Self {
+        Self { whisper_context }
+    }
+}
+
+impl SttProvider for WhisperProvider {
+    fn name(&self) -> &'static str {
+        "whisper"
+    }
+
+    fn is_cloud(&self) -> bool {
+        false
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        audio: &'a [f32],
+        _sample_rate: u32,
+        _device: &'a str,
+        languages: &'a [Language],
+        vocabulary: &'a Vocabulary,
+    ) -> BoxFuture<'a, Result<Transcript>> {
+        Box::pin(process_with_whisper(
+            audio,
+            languages.to_vec(),
+            self.whisper_context.clone(),
+            vocabulary.prompt(),
+        ))
+    }
+}
+
+pub struct DeepgramProvider {
+    api_key: String,
+}
+
+impl DeepgramProvider {
+    pub fn new(api_key: Option<String>) -> Self {
+        Self {
+            api_key: api_key.unwrap_or_default(),
+        }
+    }
+}
+
+impl SttProvider for DeepgramProvider {
+    fn name(&self) -> &'static str {
+        "deepgram"
+    }
+
+    fn is_cloud(&self) -> bool {
+        true
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        audio: &'a [f32],
+        sample_rate: u32,
+        device: &'a str,
+        languages: &'a [Language],
+        vocabulary: &'a Vocabulary,
+    ) -> BoxFuture<'a, Result<Transcript>> {
+        Box::pin(transcribe_with_deepgram(
+            &self.api_key,
+            audio,
+            device,
+            sample_rate,
+            languages.to_vec(),
+            vocabulary.keywords(),
+        ))
+    }
+}
+
+/// OpenAI's `/audio/transcriptions` endpoint, or any server compatible with it.
+pub struct OpenAiProvider {
+    config: OpenAiSttConfig,
+    client: Client,
+}
+
+impl OpenAiProvider {
+    pub fn new(config: OpenAiSttConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+
+    async fn request(
+        &self,
+        audio: &[f32],
+        sample_rate: u32,
+        device: &str,
+        languages: &[Language],
+        vocabulary: &Vocabulary,
+    ) -> Result<Transcript> {
+        let api_key = self
+            .config
+            .api_key
+            .as_deref()
+            .ok_or_else(|| anyhow!("OpenAI API key is required for OpenAI transcription"))?;
+
+        let file = multipart::Part::bytes(create_wav_file(audio, sample_rate)?)
+            .file_name("audio.wav")
+            .mime_str("audio/wav")?;
+        let mut form = multipart::Form::new()
+            .part("file", file)
+            .text("model", self.config.model.clone())
+            .text("response_format", "json");
+        // the api takes a single language hint, the json response doesn't
+        // report the detected language
+        let language = match languages {
+            [language] => Some(language.as_lang_code().to_string()),
+            _ => None,
+        };
+        if let Some(language) = &language {
+            form = form.text("language", language.clone());
+        }
+        if let Some(prompt) = vocabulary.prompt() {
+            form = form.text("prompt", prompt);
+        }
+
+        let response = self
+            .client
+            .post(format!(
+                "{}/audio/transcriptions",
+                self.config.base_url.trim_end_matches('/')
+            ))
+            .bearer_auth(api_key)
+            .multipart(form)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body: Value = response.json().await?;
+        if !status.is_success() {
+            return Err(anyhow!("OpenAI API error {}: {}", status, body));
+        }
+
+        let transcription = body["text"].as_str().unwrap_or_default().trim().to_string();
+        debug!(
+            "device: {}, openai transcription length: {} characters",
+            device,
+            transcription.len()
+        );
+        Ok(Transcript {
+            text: transcription,
+            language,
+            words: Vec::new(),
+        })
+    }
+}
+
+impl SttProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn is_cloud(&self) -> bool {
+        true
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        audio: &'a [f32],
+        sample_rate: u32,
+        device: &'a str,
+        languages: &'a [Language],
+        vocabulary: &'a Vocabulary,
+    ) -> BoxFuture<'a, Result<Transcript>> {
+        Box::pin(self.request(audio, sample_rate, device, languages, vocabulary))
+    }
+}
+
+fn create_wav_file(audio: &[f32], sample_rate: u32) -> Result<Vec<u8>> {
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = WavWriter::new(&mut cursor, spec)?;
+        for &sample in audio {
+            writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+        }
+        writer.finalize()?;
+    }
+    Ok(cursor.into_inner())
+}
+
+#[derive(Default)]
+struct Lag {
+    realtime_factor: Option<f64>,
+    failover_until: Option<Instant>,
+}
+
+/// Tries providers in order until one succeeds. While the local providers
+/// are slower than real time, cloud providers are tried first.
+pub struct SttRouter {
+    providers: Vec<Arc<dyn SttProvider>>,
+    lag: Mutex<Lag>,
+}
+
+impl SttRouter {
+    /// With `disable_cloud` set, cloud providers are dropped, so audio never
+    /// leaves the machine.
+    pub fn new(providers: Vec<Arc<dyn SttProvider>>, disable_cloud: bool) -> Result<Self> {
+        let providers: Vec<_> = providers
+            .into_iter()
+            .filter(|p| !(disable_cloud && p.is_cloud()))
+            .collect();
+        if providers.is_empty() {
+            return Err(anyhow!("no speech to text provider available"));
+        }
+        Ok(Self {
+            providers,
+            lag: Mutex::new(Lag::default()),
+        })
+    }
+
+    /// Providers for the configured engine, followed by the cloud fallback.
+    pub fn from_options(
+        options: &AudioManagerOptions,
+        engine: &AudioTranscriptionEngine,
+        whisper_context: Arc<WhisperContext>,
+    ) -> Result<Self> {
+        let whisper: Arc<dyn SttProvider> = Arc::new(WhisperProvider::new(whisper_context));
+        let deepgram = || -> Arc<dyn SttProvider> {
+            Arc::new(DeepgramProvider::new(options.deepgram_api_key.clone()))
+        };
+
+        let mut providers = match engine {
+            AudioTranscriptionEngine::Deepgram => vec![deepgram(), whisper],
+            _ => vec![whisper],
+        };
+        match options.stt_fallback {
+            Some(CloudSttProvider::Deepgram) if *engine != AudioTranscriptionEngine::Deepgram => {
+                providers.push(deepgram())
+            }
+            Some(CloudSttProvider::OpenAi) => {
+                providers.push(Arc::new(OpenAiProvider::new(options.openai_stt.clone())))
+            }
+            _ => {}
+        }
+
+        Self::new(providers, options.disable_cloud_stt)
+    }
+
+    pub fn providers(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.providers.iter().map(|p| p.name())
+    }
+
+    /// Whether chunks currently go to the cloud because the local model fell behind.
+    pub fn is_failed_over(&self) -> bool {
+        let lag = self.lag.lock().unwrap();
+        lag.failover_until
+            .is_some_and(|until| until > Instant::now())
+    }
+
+    fn ordered(&self) -> Vec<Arc<dyn SttProvider>> {
+        let mut providers = self.providers.clone();
+        if self.is_failed_over() {
+            // stable sort, so the preference among cloud providers is kept
+            providers.sort_by_key(|p| !p.is_cloud());
+        }
+        providers
+    }
+
+    fn record_local(&self, elapsed: Duration, audio_duration: Duration) {
+        if audio_duration.is_zero() {
+            return;
+        }
+        let factor = elapsed.as_secs_f64() / audio_duration.as_secs_f64();
+
+        let mut lag = self.lag.lock().unwrap();
+        let smoothed = match lag.realtime_factor {
+            Some(previous) => {
+                previous * (1.0 - REALTIME_FACTOR_SMOOTHING) + factor * REALTIME_FACTOR_SMOOTHING
+            }
+            None => factor,
+        };
+        lag.realtime_factor = Some(smoothed);
+
+        let has_cloud = self.providers.iter().any(|p| p.is_cloud());
+        if smoothed > MAX_REALTIME_FACTOR && has_cloud {
+            warn!(
+                "local transcription is behind real time ({:.2}x), using cloud transcription for {}s",
+                smoothed,
+                FAILOVER_COOLDOWN.as_secs()
+            );
+            lag.failover_until = Some(Instant::now() + FAILOVER_COOLDOWN);
+            // measure again from scratch once the cooldown is over
+            lag.realtime_factor = None;
+        }
+    }
+
+    /// Transcribes with the first provider that succeeds, then replaces what
+    /// it heard instead of the terms of `vocabulary`.
+    pub async fn transcribe(
+        &self,
+        audio: &[f32],
+        sample_rate: u32,
+        device: &str,
+        languages: &[Language],
+        vocabulary: &Vocabulary,
+    ) -> Result<Transcript> {
+        let audio_duration = Duration::from_secs_f64(audio.len() as f64 / sample_rate as f64);
+
+        let mut last_error = None;
+        for provider in self.ordered() {
+            let started = Instant::now();
+            match provider
+                .transcribe(audio, sample_rate, device, languages, vocabulary)
+                .instrument(info_span!("stt_provider", provider = provider.name()))
+                .await
+            {
+                Ok(mut transcript) => {
+                    let elapsed = started.elapsed();
+                    if !audio_duration.is_zero() {
+                        metrics::TRANSCRIPTION_REALTIME_FACTOR
+                            .with_labels(&[provider.name()])
+                            .observe(elapsed.as_secs_f64() / audio_duration.as_secs_f64());
+                    }
+                    if !provider.is_cloud() {
+                        self.record_local(elapsed, audio_duration);
+                    }
+                    transcript.text = vocabulary.correct(&transcript.text);
+                    return Ok(transcript);
+                }
+                Err(e) => {
+                    warn!(
+                        "device: {}, {} transcription failed, trying the next provider: {:?}",
+                        device,
+                        provider.name(),
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("no speech to text provider available")))
+    }
+}
@@ -1,11 +1,13 @@
 use crate::core::device::AudioDevice;
 use crate::core::engine::AudioTranscriptionEngine;
+use crate::media_detection::{classify_audio, AudioClassification};
 use crate::speaker::embedding::EmbeddingExtractor;
 use crate::speaker::embedding_manager::EmbeddingManager;
 use crate::speaker::prepare_segments;
 use crate::speaker::segment::SpeechSegment;
-use crate::transcription::deepgram::batch::transcribe_with_deepgram;
-use crate::transcription::whisper::batch::process_with_whisper;
+use crate::transcription::provider::{DeepgramProvider, SttProvider, SttRouter, WhisperProvider};
+use crate::transcription::translation::{translate_transcript, Translator};
+use crate::transcription::vocabulary::Vocabulary;
 use crate::utils::audio::resample;
 use crate::utils::ffmpeg::{get_new_file_path, write_audio_to_file};
 use crate::vad::VadEngine;
@@ -20,7 +22,7 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 use tokio::sync::Mutex;
-use tracing::error;
+use tracing::{debug, error, info_span, Instrument, Span};
 use whisper_rs::WhisperContext;
 
 use crate::{AudioInput, TranscriptionResult};
@@ -63,30 +65,25 @@ pub async fn stt(
     languages: Vec<Language>,
     whisper_context: Arc<WhisperContext>,
 ) -> Result<String> {
-    let transcription: Result<String> =
+    let whisper: Arc<dyn SttProvider> = Arc::new(WhisperProvider::new(whisper_context));
+    let providers: Vec<Arc<dyn SttProvider>> =
         if audio_transcription_engine == AudioTranscriptionEngine::Deepgram.into() {
-            // Deepgram implementation
-            let api_key = deepgram_api_key.unwrap_or_default();
-
-            match transcribe_with_deepgram(&api_key, audio, device, sample_rate, languages.clone())
-                .await
-            {
-                Ok(transcription) => Ok(transcription),
-                Err(e) => {
-                    error!(
-                        "device: {}, deepgram transcription failed, falling back to Whisper: {:?}",
-                        device, e
-                    );
-                    // Fallback to Whisper
-                    process_with_whisper(audio, languages.clone(), whisper_context).await
-                }
-            }
+            // whisper is the fallback when deepgram fails
+            vec![Arc::new(DeepgramProvider::new(deepgram_api_key)), whisper]
         } else {
-            // Existing Whisper implementation
-            process_with_whisper(audio, languages, whisper_context).await
+            vec![whisper]
         };
 
-    transcription
+    SttRouter::new(providers, false)?
+        .transcribe(
+            audio,
+            sample_rate,
+            device,
+            &languages,
+            &Vocabulary::default(),
+        )
+        .await
+        .map(|transcript| transcript.text)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -98,10 +95,12 @@ pub async fn process_audio_input(
     embedding_extractor: Arc<StdMutex<EmbeddingExtractor>>,
     output_path: &PathBuf,
     audio_transcription_engine: Arc<AudioTranscriptionEngine>,
-    deepgram_api_key: Option<String>,
+    stt_router: Arc<SttRouter>,
+    translator: Option<Arc<dyn Translator>>,
     languages: Vec<Language>,
+    vocabulary: Arc<Vocabulary>,
+    media_detection: bool,
     output_sender: &crossbeam::channel::Sender<TranscriptionResult>,
-    whisper_context: Arc<WhisperContext>,
 ) -> Result<()> {
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -119,6 +118,51 @@ pub async fn process_audio_input(
         sample_rate: SAMPLE_RATE,
         ..audio
     };
+    let chunk_span = info_span!(
+        "audio_chunk",
+        device = %audio.device,
+        samples = audio_data.len(),
+        seconds = audio_data.len() as f64 / SAMPLE_RATE as f64
+    );
+
+    // music passes the VAD as speech and comes out of whisper as gibberish
+    if let Some(classification) = (media_detection && audio.media_detection)
+        .then(|| classify_audio(&audio_data, SAMPLE_RATE))
+        .flatten()
+        .filter(AudioClassification::is_music)
+    {
+        debug!(
+            "device {} is playing {} ({:.2}), skipping transcription",
+            audio.device, classification.class, classification.music_probability
+        );
+        // kept so that speech taken for music can still be transcribed
+        let path = get_new_file_path(&audio.device.to_string(), output_path);
+        let path = match write_audio_to_file(&audio_data, SAMPLE_RATE, &PathBuf::from(&path), false)
+        {
+            Ok(()) => path,
+            Err(e) => {
+                error!("Error writing audio to file: {:?}", e);
+                String::new()
+            }
+        };
+        let _ = output_sender.send(TranscriptionResult {
+            path,
+            engine: audio_transcription_engine,
+            speaker_embedding: Vec::new(),
+            transcription: None,
+            language: None,
+            translation: None,
+            timestamp,
+            error: None,
+            start_time: 0.0,
+            end_time: audio_data.len() as f64 / SAMPLE_RATE as f64,
+            classification: Some(classification),
+            words: Vec::new(),
+            input: audio,
+            span: chunk_span,
+        });
+        return Ok(());
+    }
 
     let (mut segments, speech_ratio_ok) = prepare_segments(
         &audio_data,
@@ -128,6 +172,7 @@ pub async fn process_audio_input(
         embedding_extractor,
         &audio.device.to_string(),
     )
+    .instrument(info_span!(parent: &chunk_span, "vad"))
     .await?;
 
     if !speech_ratio_ok {
@@ -147,7 +192,15 @@ pub async fn process_audio_input(
 
     while let Some(segment) = segments.recv().await {
         let path = new_file_path.clone();
-        let transcription_result = if cfg!(target_os = "macos") {
+        let stt_span = info_span!(
+            parent: &chunk_span,
+            "stt",
+            engine = %audio_transcription_engine,
+            samples = segment.samples.len(),
+            start = segment.start,
+            end = segment.end
+        );
+        let mut transcription_result = if cfg!(target_os = "macos") {
             #[cfg(target_os = "macos")]
             {
                 let timestamp = timestamp + segment.start.round() as u64;
@@ -156,13 +209,15 @@ pub async fn process_audio_input(
                         segment,
                         audio.device.clone(),
                         audio_transcription_engine.clone(),
-                        deepgram_api_key.clone(),
+                        stt_router.clone(),
+                        translator.clone(),
                         languages.clone(),
+                        vocabulary.clone(),
                         path,
                         timestamp,
-                        whisper_context.clone(),
                     )
                 })
+                .instrument(stt_span)
                 .await?
             }
             #[cfg(not(target_os = "macos"))]
@@ -174,14 +229,21 @@ pub async fn process_audio_input(
                 segment,
                 audio.device.clone(),
                 audio_transcription_engine.clone(),
-                deepgram_api_key.clone(),
+                stt_router.clone(),
+                translator.clone(),
                 languages.clone(),
+                vocabulary.clone(),
                 path,
                 timestamp,
-                whisper_context.clone(),
             )
+            .instrument(stt_span)
             .await?
         };
+        // segments of recordings made earlier are dated from their start
+        transcription_result.input.recorded_at = audio.recorded_at.map(|recorded_at| {
+            recorded_at
+                + chrono::Duration::milliseconds((transcription_result.start_time * 1000.0) as i64)
+        });
 
         if output_sender.send(transcription_result).is_err() {
             break;
@@ -196,40 +258,63 @@ pub async fn run_stt(
     segment: SpeechSegment,
     device: Arc<AudioDevice>,
     audio_transcription_engine: Arc<AudioTranscriptionEngine>,
-    deepgram_api_key: Option<String>,
+    stt_router: Arc<SttRouter>,
+    translator: Option<Arc<dyn Translator>>,
     languages: Vec<Language>,
+    vocabulary: Arc<Vocabulary>,
     path: String,
     timestamp: u64,
-    whisper_context: Arc<WhisperContext>,
 ) -> Result<TranscriptionResult> {
     let audio = segment.samples.clone();
     let sample_rate = segment.sample_rate;
-    match stt_sync(
-        &audio,
-        sample_rate,
-        &device.to_string(),
-        audio_transcription_engine.clone(),
-        deepgram_api_key.clone(),
-        languages.clone(),
-        whisper_context,
-    )
-    .await
+    match stt_router
+        .transcribe(
+            &audio,
+            sample_rate,
+            &device.to_string(),
+            &languages,
+            &vocabulary,
+        )
+        .await
     {
-        Ok(transcription) => Ok(TranscriptionResult {
-            input: AudioInput {
-                data: Arc::new(audio),
-                sample_rate,
-                channels: 1,
-                device: device.clone(),
-            },
-            transcription: Some(transcription),
-            path,
-            timestamp,
-            error: None,
-            speaker_embedding: segment.embedding.clone(),
-            start_time: segment.start,
-            end_time: segment.end,
-        }),
+        Ok(transcript) => {
+            let translation = match &translator {
+                Some(translator) => {
+                    translate_transcript(
+                        translator.as_ref(),
+                        &audio,
+                        &transcript,
+                        &device.to_string(),
+                    )
+                    .await
+                }
+                None => None,
+            };
+
+            Ok(TranscriptionResult {
+                input: AudioInput {
+                    data: Arc::new(audio),
+                    sample_rate,
+                    channels: 1,
+                    device: device.clone(),
+                    recorded_at: None,
+                    media_detection: false,
+                },
+                transcription: Some(transcript.text),
+                words: transcript.words,
+                language: transcript.language,
+                translation,
+                path,
+                engine: audio_transcription_engine,
+                timestamp,
+                error: None,
+                speaker_embedding: segment.embedding.clone(),
+                start_time: segment.start,
+                end_time: segment.end,
+                classification: None,
+                span: Span::current(),
+            })
+        }
         Err(e) => {
             error!("STT error for input {}: {:?}", device, e);
             Ok(TranscriptionResult {
@@ -238,14 +323,22 @@ pub async fn run_stt(
                     sample_rate: segment.sample_rate,
                     channels: 1,
                     device: device.clone(),
+                    recorded_at: None,
+                    media_detection: false,
                 },
                 transcription: None,
+                language: None,
+                translation: None,
                 path,
+                engine: audio_transcription_engine,
                 timestamp,
                 error: Some(e.to_string()),
                 speaker_embedding: Vec::new(),
                 start_time: segment.start,
                 end_time: segment.end,
+                classification: None,
+                words: Vec::new(),
+                span: Span::current(),
             })
         }
     }
@@ -1,38 +1,55 @@
-pub fn longest_common_word_substring(s1: &str, s2: &str) -> Option<(usize, usize)> {
-  let s1 = s1.to_lowercase();
-  let s2 = s2.to_lowercase();
+/// Speech rarely goes faster than this, it bounds the words an overlap holds.
+const MAX_WORDS_PER_SECOND: f64 = 4.0;
+/// Shorter runs of shared words are too likely to be a coincidence, e.g. "the".
+const MIN_OVERLAP_WORDS: usize = 2;
 
-  let s1 = s1.replace(|c| char::is_ascii_punctuation(&c), "");
-  let s2 = s2.replace(|c| char::is_ascii_punctuation(&c), "");
-
-  let s1_words: Vec<&str> = s1.split_whitespace().collect();
-  let s2_words: Vec<&str> = s2.split_whitespace().collect();
-
-  let s1_len = s1_words.len();
-  let s2_len = s2_words.len();
+/// Words spoken in `seconds` at most.
+pub fn max_words_in(seconds: f64) -> usize {
+    (seconds * MAX_WORDS_PER_SECOND).ceil() as usize
+}
 
-  // Table to store lengths of longest common suffixes of word substrings
-  let mut dp = vec![vec![0; s2_len + 1]; s1_len + 1];
+/// Compares words ignoring case and punctuation.
+pub fn normalize_word(word: &str) -> String {
+    word.chars()
+        .filter(|c| !c.is_ascii_punctuation())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
 
-  let mut max_len = 0;
-  let mut max_index_s1 = None; // Store the starting word index of the longest substring in s1
-  let mut max_index_s2 = None; // Store the starting word index of the longest substring in s2
+/// Longest run of words shared by the last `max_words` words of `previous`
+/// and the first `max_words` of `current`, the audio both chunks hold.
+/// Returns the index of its first word in each, counting words separated by
+/// whitespace.
+pub fn overlapping_words(
+    previous: &str,
+    current: &str,
+    max_words: usize,
+) -> Option<(usize, usize)> {
+    let previous_words: Vec<String> = previous.split_whitespace().map(normalize_word).collect();
+    let current_words: Vec<String> = current
+        .split_whitespace()
+        .take(max_words)
+        .map(normalize_word)
+        .collect();
+    let previous_start = previous_words.len().saturating_sub(max_words);
+    let previous_words = &previous_words[previous_start..];
 
-  for i in 1..=s1_len {
-      for j in 1..=s2_len {
-          if s1_words[i - 1] == s2_words[j - 1] {
-              dp[i][j] = dp[i - 1][j - 1] + 1;
-              if dp[i][j] > max_len {
-                  max_len = dp[i][j];
-                  max_index_s1 = Some(i - max_len); // The start index of the match in s1
-                  max_index_s2 = Some(j - max_len); // The start index of the match in s2
-              }
-          }
-      }
-  }
+    // lengths of the runs of shared words ending at each pair of words
+    let mut run = vec![vec![0; current_words.len() + 1]; previous_words.len() + 1];
+    let mut longest: Option<(usize, usize, usize)> = None;
+    for i in 1..=previous_words.len() {
+        for j in 1..=current_words.len() {
+            if previous_words[i - 1].is_empty() || previous_words[i - 1] != current_words[j - 1] {
+                continue;
+            }
+            run[i][j] = run[i - 1][j - 1] + 1;
+            if run[i][j] > longest.map_or(0, |(len, _, _)| len) {
+                longest = Some((run[i][j], i - run[i][j], j - run[i][j]));
+            }
+        }
+    }
 
-  match (max_index_s1, max_index_s2) {
-      (Some(idx1), Some(idx2)) => Some((idx1, idx2)),
-      _ => None,
-  }
+    longest
+        .filter(|(len, _, _)| *len >= MIN_OVERLAP_WORDS)
+        .map(|(_, i, j)| (previous_start + i, j))
 }
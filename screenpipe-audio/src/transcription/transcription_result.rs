@@ -1,53 +1,101 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use screenpipe_db::{DatabaseManager, Speaker};
-use tracing::{debug, error, info};
+use screenpipe_core::metrics;
+use screenpipe_db::{DatabaseManager, Speaker, TranscriptWord};
+use screenpipe_events::{send_event, TranscriptIndexedEvent, TRANSCRIPT_INDEXED_EVENT};
+use tracing::{debug, error, info, Span};
 
-use crate::core::engine::AudioTranscriptionEngine;
+use crate::core::{device::DeviceType, engine::AudioTranscriptionEngine};
+use crate::media_detection::AudioClassification;
+use crate::transcription::deepgram::streaming::RealtimeTranscriptionEvent;
 
-use super::{text_utils::longest_common_word_substring, AudioInput};
+use super::{
+    text_utils::{max_words_in, overlapping_words},
+    AudioInput, TimedWord,
+};
 
 #[derive(Debug, Clone)]
 pub struct TranscriptionResult {
     pub path: String,
     pub input: AudioInput,
+    /// engine that produced the transcription, it can change at runtime
+    pub engine: Arc<AudioTranscriptionEngine>,
     pub speaker_embedding: Vec<f32>,
     pub transcription: Option<String>,
+    /// detected spoken language, e.g. `de`
+    pub language: Option<String>,
+    /// english translation, when enabled and spoken in another language
+    pub translation: Option<String>,
     pub timestamp: u64,
     pub error: Option<String>,
     pub start_time: f64,
     pub end_time: f64,
+    /// Set when the chunk was music and not transcribed
+    pub classification: Option<AudioClassification>,
+    /// Timings of the words of the transcription, when the engine gives them
+    pub words: Vec<TimedWord>,
+    /// Span of the transcription of the segment, which the writes to the
+    /// database continue
+    pub span: Span,
 }
 
 impl TranscriptionResult {
-    // TODO --optimize
-    pub fn cleanup_overlap(&mut self, previous_transcript: String) -> Option<(String, String)> {
-        if let Some(transcription) = &self.transcription {
-            let transcription = transcription.to_string();
-            if let Some((prev_idx, cur_idx)) =
-                longest_common_word_substring(previous_transcript.as_str(), transcription.as_str())
-            {
-                // strip old transcript from prev_idx word pos
-                let new_prev = previous_transcript
-                    .split_whitespace()
-                    .collect::<Vec<&str>>()[..prev_idx]
-                    .join(" ");
-                // strip new transcript before cur_idx word pos
-                let new_cur =
-                    transcription.split_whitespace().collect::<Vec<&str>>()[cur_idx..].join(" ");
-
-                return Some((new_prev, new_cur));
-            }
+    /// Removes the words transcribed twice because the chunk started with the
+    /// last `overlap` of audio of the previous one. Returns the previous
+    /// transcript without them and this one starting with them, `None` when
+    /// this segment didn't start in the overlap or no words are shared.
+    pub fn cleanup_overlap(
+        &mut self,
+        previous_transcript: String,
+        overlap: Duration,
+    ) -> Option<(String, String)> {
+        if self.start_time >= overlap.as_secs_f64() {
+            return None;
         }
+        let transcription = self.transcription.as_deref()?;
+        let max_words = max_words_in(overlap.as_secs_f64());
+        let (prev_idx, cur_idx) =
+            overlapping_words(&previous_transcript, transcription, max_words)?;
+        // strip old transcript from prev_idx word pos
+        let new_prev = previous_transcript
+            .split_whitespace()
+            .collect::<Vec<&str>>()[..prev_idx]
+            .join(" ");
+        // strip new transcript before cur_idx word pos
+        let new_cur = transcription.split_whitespace().collect::<Vec<&str>>()[cur_idx..].join(" ");
+        self.words.drain(..cur_idx.min(self.words.len()));
 
-        None
+        Some((new_prev, new_cur))
+    }
+
+    /// Timings of the words of `transcription`, the text as stored, taken
+    /// from the words the engine gave. Empty when they don't line up with it
+    /// anymore, e.g. after redaction merged words, so no text from before
+    /// redaction is kept. Times are moved from the segment to the chunk file.
+    pub fn transcript_words(&self, transcription: &str) -> Vec<TranscriptWord> {
+        let tokens: Vec<&str> = transcription.split_whitespace().collect();
+        if tokens.len() != self.words.len() {
+            return Vec::new();
+        }
+        tokens
+            .into_iter()
+            .zip(&self.words)
+            .map(|(token, word)| TranscriptWord {
+                word: token.to_string(),
+                start_time: self.start_time + word.start,
+                end_time: self.start_time + word.end,
+                confidence: word.confidence as f64,
+            })
+            .collect()
     }
 }
 
 pub async fn process_transcription_result(
     db: &DatabaseManager,
     result: TranscriptionResult,
-    audio_transcription_engine: Arc<AudioTranscriptionEngine>,
     previous_transcript: Option<String>,
     previous_transcript_id: Option<i64>,
 ) -> Result<Option<i64>, anyhow::Error> {
@@ -63,8 +111,9 @@ pub async fn process_transcription_result(
 
     info!("Detected speaker: {:?}", speaker);
 
-    let transcription = result.transcription.unwrap();
-    let transcription_engine = audio_transcription_engine.to_string();
+    let transcription = result.transcription.clone().unwrap();
+    let transcription_engine = result.engine.to_string();
+    let timestamp = result.input.recorded_at.unwrap_or_else(chrono::Utc::now);
     let mut chunk_id: Option<i64> = None;
 
     info!(
@@ -85,14 +134,15 @@ pub async fn process_transcription_result(
             }
         }
     }
-    match db.get_or_insert_audio_chunk(&result.path).await {
+    match db.get_or_insert_audio_chunk(&result.path, timestamp).await {
         Ok(audio_chunk_id) => {
             if transcription.is_empty() {
                 return Ok(Some(audio_chunk_id));
             }
 
-            if let Err(e) = db
-                .insert_audio_transcription(
+            let insert_started = Instant::now();
+            match db
+                .insert_audio_transcription_at(
                     audio_chunk_id,
                     &transcription,
                     0,
@@ -111,20 +161,64 @@ pub async fn process_transcription_result(
                     Some(speaker.id),
                     Some(result.start_time),
                     Some(result.end_time),
+                    result.language.as_deref(),
+                    result.translation.as_deref(),
+                    timestamp,
                 )
                 .await
             {
-                error!(
-                    "Failed to insert audio transcription for device {}: {}",
-                    result.input.device, e
-                );
-                return Ok(Some(audio_chunk_id));
-            } else {
-                debug!(
-                    "Inserted audio transcription for chunk {} from device {} using {}",
-                    audio_chunk_id, result.input.device, transcription_engine
-                );
-                chunk_id = Some(audio_chunk_id);
+                Err(e) => {
+                    error!(
+                        "Failed to insert audio transcription for device {}: {}",
+                        result.input.device, e
+                    );
+                    return Ok(Some(audio_chunk_id));
+                }
+                Ok(transcription_id) => {
+                    metrics::DB_WRITE_DURATION
+                        .with_labels(&["insert_audio_transcription"])
+                        .observe(insert_started.elapsed().as_secs_f64());
+                    debug!(
+                        "Inserted audio transcription for chunk {} from device {} using {}",
+                        audio_chunk_id, result.input.device, transcription_engine
+                    );
+                    chunk_id = Some(audio_chunk_id);
+
+                    let words = result.transcript_words(&transcription);
+                    if !words.is_empty() {
+                        if let Err(e) = db.insert_transcript_words(transcription_id, &words).await {
+                            error!(
+                                "Failed to insert word timings for device {}: {}",
+                                result.input.device, e
+                            );
+                        }
+                    }
+
+                    let _ = send_event(
+                        TRANSCRIPT_INDEXED_EVENT,
+                        TranscriptIndexedEvent {
+                            transcription_id,
+                            timestamp,
+                            device: result.input.device.to_string(),
+                            is_input: result.input.device.device_type == DeviceType::Input,
+                            speaker_id: Some(speaker.id),
+                            transcription: transcription.clone(),
+                        },
+                    );
+
+                    // stored transcripts are final, partial ones only come from realtime engines
+                    let _ = send_event(
+                        "transcription",
+                        RealtimeTranscriptionEvent {
+                            timestamp: chrono::Utc::now(),
+                            device: result.input.device.to_string(),
+                            transcription: transcription.clone(),
+                            is_final: true,
+                            is_input: result.input.device.device_type == DeviceType::Input,
+                            speaker: Some(speaker.id.to_string()),
+                        },
+                    );
+                }
             }
         }
         Err(e) => error!(
@@ -135,6 +229,50 @@ pub async fn process_transcription_result(
     Ok(chunk_id)
 }
 
+/// Stores a chunk that was not transcribed as a media block ending when it
+/// was recorded, keeping its audio file for the block.
+pub async fn process_media_result(
+    db: &DatabaseManager,
+    result: &TranscriptionResult,
+    classification: AudioClassification,
+) -> Result<i64, anyhow::Error> {
+    let duration = chrono::Duration::milliseconds((result.end_time * 1000.0) as i64);
+    let end_time = result
+        .input
+        .recorded_at
+        .map(|recorded_at| recorded_at + duration)
+        .unwrap_or_else(chrono::Utc::now);
+    let audio_chunk_id = if result.path.is_empty() {
+        None
+    } else {
+        Some(
+            db.insert_audio_chunk_at(&result.path, end_time - duration)
+                .await?,
+        )
+    };
+    let id = db
+        .record_media_block(
+            &screenpipe_db::AudioDevice {
+                name: result.input.device.name.clone(),
+                device_type: match result.input.device.device_type {
+                    DeviceType::Input => screenpipe_db::DeviceType::Input,
+                    DeviceType::Output => screenpipe_db::DeviceType::Output,
+                },
+            },
+            &classification.class.to_string(),
+            classification.music_probability as f64,
+            end_time - duration,
+            end_time,
+            audio_chunk_id,
+        )
+        .await?;
+    debug!(
+        "device {} playing {} in media block {}",
+        result.input.device, classification.class, id
+    );
+    Ok(id)
+}
+
 async fn get_or_create_speaker_from_embedding(
     db: &DatabaseManager,
     embedding: &[f32],
@@ -0,0 +1,96 @@
+//! Offline translation of transcripts, so speech in other languages is
+//! found by searching in English.
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use tracing::{debug, warn};
+use whisper_rs::WhisperContext;
+
+use crate::transcription::whisper::batch::translate_with_whisper;
+use crate::transcription::Transcript;
+
+/// Language transcripts are translated to.
+pub const TRANSLATION_LANGUAGE: &str = "en";
+
+pub trait Translator: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Translates `transcript`, spoken in `audio`, to `TRANSLATION_LANGUAGE`.
+    fn translate<'a>(
+        &'a self,
+        audio: &'a [f32],
+        transcript: &'a Transcript,
+    ) -> BoxFuture<'a, Result<String>>;
+}
+
+/// Translates with the local whisper model, which translates speech
+/// straight to English.
+pub struct WhisperTranslator {
+    whisper_context: Arc<WhisperContext>,
+}
+
+impl WhisperTranslator {
+    pub fn new(whisper_context: Arc<WhisperContext>) -> Self {
+        Self { whisper_context }
+    }
+}
+
+impl Translator for WhisperTranslator {
+    fn name(&self) -> &'static str {
+        "whisper"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        audio: &'a [f32],
+        transcript: &'a Transcript,
+    ) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let language = transcript
+                .language
+                .as_deref()
+                .unwrap_or(TRANSLATION_LANGUAGE);
+            translate_with_whisper(audio, language, self.whisper_context.clone()).await
+        })
+    }
+}
+
+/// Whether a transcript is in another language than `TRANSLATION_LANGUAGE`.
+/// Transcripts of unknown language are left alone.
+pub fn needs_translation(transcript: &Transcript) -> bool {
+    !transcript.text.trim().is_empty()
+        && transcript
+            .language
+            .as_deref()
+            .is_some_and(|language| language != TRANSLATION_LANGUAGE)
+}
+
+/// Translation of `transcript` if it needs one. Failures are logged and give
+/// no translation, the transcript is stored either way.
+pub async fn translate_transcript(
+    translator: &dyn Translator,
+    audio: &[f32],
+    transcript: &Transcript,
+    device: &str,
+) -> Option<String> {
+    if !needs_translation(transcript) {
+        return None;
+    }
+
+    match translator.translate(audio, transcript).await {
+        Ok(translation) => {
+            debug!(
+                "device: {}, translated {:?} transcript with {}",
+                device,
+                transcript.language,
+                translator.name()
+            );
+            Some(translation.trim().to_string()).filter(|t| !t.is_empty())
+        }
+        Err(e) => {
+            warn!("device: {}, translation failed: {:?}", device, e);
+            None
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Words the engines don't know, e.g. product names and jargon. They are
+//! given to the engine with each chunk, as the initial prompt of Whisper or
+//! keywords boosted by Deepgram, and what the engine heard instead of them
+//! is replaced in the transcript.
+
+use super::text_utils::normalize_word;
+
+/// Whisper keeps the last 224 tokens of its prompt, a few characters each.
+const MAX_PROMPT_CHARS: usize = 600;
+/// How much Deepgram favours a keyword, it mangles unrelated words above 10.
+const KEYWORD_BOOST: u32 = 2;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VocabularyTerm {
+    pub term: String,
+    /// What the engine hears instead, e.g. `screen pipe` for `screenpipe`
+    pub sounds_like: Option<String>,
+}
+
+impl VocabularyTerm {
+    pub fn new(term: impl Into<String>) -> Self {
+        Self {
+            term: term.into(),
+            sounds_like: None,
+        }
+    }
+
+    pub fn sounds_like(mut self, sounds_like: impl Into<String>) -> Self {
+        self.sounds_like = Some(sounds_like.into());
+        self
+    }
+}
+
+/// Terms used for the chunks being transcribed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vocabulary {
+    pub terms: Vec<VocabularyTerm>,
+}
+
+impl Vocabulary {
+    pub fn new(terms: Vec<VocabularyTerm>) -> Self {
+        Self { terms }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Prompt making Whisper spell the terms like they are written, as many
+    /// as fit in it. Whisper takes the prompt as the text said before.
+    pub fn prompt(&self) -> Option<String> {
+        let mut terms: Vec<&str> = Vec::new();
+        let mut len = 0;
+        for term in &self.terms {
+            len += term.term.len() + 2;
+            if len > MAX_PROMPT_CHARS {
+                break;
+            }
+            terms.push(&term.term);
+        }
+        (!terms.is_empty()).then(|| format!("Glossary: {}.", terms.join(", ")))
+    }
+
+    /// Keywords for Deepgram, e.g. `screenpipe:2`.
+    pub fn keywords(&self) -> Vec<String> {
+        self.terms
+            .iter()
+            .map(|term| format!("{}:{}", term.term, KEYWORD_BOOST))
+            .collect()
+    }
+
+    /// Replaces what the engine heard instead of a term, ignoring case and
+    /// punctuation, with the term. The longest match wins.
+    pub fn correct(&self, text: &str) -> String {
+        let mut hints: Vec<(Vec<String>, &str)> = self
+            .terms
+            .iter()
+            .filter_map(|term| {
+                let hint: Vec<String> = term
+                    .sounds_like
+                    .as_deref()?
+                    .split_whitespace()
+                    .map(normalize_word)
+                    .collect();
+                (!hint.is_empty() && hint.iter().all(|word| !word.is_empty()))
+                    .then_some((hint, term.term.as_str()))
+            })
+            .collect();
+        if hints.is_empty() {
+            return text.to_string();
+        }
+        hints.sort_by_key(|(hint, _)| std::cmp::Reverse(hint.len()));
+
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let words: Vec<String> = tokens.iter().map(|token| normalize_word(token)).collect();
+        let mut corrected = Vec::with_capacity(tokens.len());
+        let mut i = 0;
+        while i < tokens.len() {
+            match hints.iter().find(|(hint, _)| words[i..].starts_with(hint)) {
+                Some((hint, term)) => {
+                    // keep the punctuation ending the replaced words
+                    let last = tokens[i + hint.len() - 1];
+                    let end = last
+                        .trim_end_matches(|c: char| c.is_ascii_punctuation())
+                        .len();
+                    corrected.push(format!("{}{}", term, &last[end..]));
+                    i += hint.len();
+                }
+                None => {
+                    corrected.push(tokens[i].to_string());
+                    i += 1;
+                }
+            }
+        }
+        corrected.join(" ")
+    }
+}
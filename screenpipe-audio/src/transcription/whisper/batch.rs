@@ -1,28 +1,70 @@
 use super::detect_language;
+use crate::transcription::{TimedWord, Transcript};
 use anyhow::Result;
 use screenpipe_core::Language;
 use std::sync::Arc;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperState};
 /// Processes audio data using the Whisper model to generate transcriptions.
 ///
 /// # Returns
-/// A string containing the processed transcript
+/// The processed transcript and the language it was transcribed in
 pub async fn process_with_whisper(
     audio: &[f32],
     languages: Vec<Language>,
     whisper_context: Arc<WhisperContext>,
+    prompt: Option<String>,
+) -> Result<Transcript> {
+    let mut whisper_state = whisper_context
+        .create_state()
+        .expect("failed to create key");
+
+    let audio = pad_audio(audio);
+
+    whisper_state.pcm_to_mel(&audio, 2)?;
+    let (_, lang_probabilities) = whisper_state.lang_detect(0, 2)?;
+    let lang = detect_language(&lang_probabilities, &languages);
+
+    let (text, words) = run_whisper(&mut whisper_state, &audio, lang, false, prompt.as_deref())?;
+
+    Ok(Transcript {
+        text,
+        language: lang.map(str::to_string),
+        words,
+    })
+}
+
+/// Translates speech in `language` to English text with the Whisper model.
+pub async fn translate_with_whisper(
+    audio: &[f32],
+    language: &str,
+    whisper_context: Arc<WhisperContext>,
 ) -> Result<String> {
     let mut whisper_state = whisper_context
         .create_state()
         .expect("failed to create key");
 
-    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 0 });
+    let audio = pad_audio(audio);
 
+    run_whisper(&mut whisper_state, &audio, Some(language), true, None).map(|(text, _)| text)
+}
+
+fn pad_audio(audio: &[f32]) -> Vec<f32> {
     let mut audio = audio.to_vec();
 
     if audio.len() < 16000 {
         audio.resize(16000, 0.0);
     }
+    audio
+}
+
+fn run_whisper(
+    whisper_state: &mut WhisperState,
+    audio: &[f32],
+    language: Option<&str>,
+    translate: bool,
+    prompt: Option<&str>,
+) -> Result<(String, Vec<TimedWord>)> {
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 0 });
 
     // Edit params as needed.
     // Set the number of threads to use to 2.
@@ -34,16 +76,17 @@ pub async fn process_with_whisper(
     params.set_print_timestamps(false);
     // Enable token level timestamps
     params.set_token_timestamps(true);
-    whisper_state.pcm_to_mel(&audio, 2)?;
-    let (_, lang_tokens) = whisper_state.lang_detect(0, 2)?;
-    let lang = detect_language(lang_tokens, languages);
-    params.set_language(lang);
+    params.set_language(language);
     params.set_debug_mode(false);
     params.set_logprob_thold(-2.0);
-    params.set_translate(false);
+    params.set_translate(translate);
+    // spells the custom vocabulary like it's written
+    if let Some(prompt) = prompt {
+        params.set_initial_prompt(prompt);
+    }
 
     whisper_state
-        .full(params, &audio)
+        .full(params, audio)
         .expect("failed to run model");
 
     let num_segments = whisper_state
@@ -51,6 +94,7 @@ pub async fn process_with_whisper(
         .expect("failed to get number of segments");
 
     let mut transcript = String::new();
+    let mut words = Vec::new();
 
     for i in 0..num_segments {
         // Get the transcribed text and timestamps for the current segment.
@@ -59,7 +103,43 @@ pub async fn process_with_whisper(
             .expect("failed to get segment");
 
         transcript.push_str(&segment);
+        words.extend(segment_words(whisper_state, i)?);
+    }
+
+    Ok((transcript, words))
+}
+
+/// Words of a segment put together from its tokens, a token starting with
+/// whitespace starts a new word. The confidence of a word is the probability
+/// of its least likely token.
+fn segment_words(whisper_state: &WhisperState, segment: i32) -> Result<Vec<TimedWord>> {
+    let mut words: Vec<TimedWord> = Vec::new();
+
+    for token in 0..whisper_state.full_n_tokens(segment)? {
+        let text = whisper_state.full_get_token_text_lossy(segment, token)?;
+        // e.g. [_BEG_], [_TT_150] or <|endoftext|>
+        if text.starts_with("[_") || text.starts_with("<|") {
+            continue;
+        }
+        let data = whisper_state.full_get_token_data(segment, token)?;
+        // token timestamps are in hundredths of a second
+        let (start, end) = (data.t0 as f64 / 100.0, data.t1 as f64 / 100.0);
+
+        match words.last_mut() {
+            Some(word) if !text.starts_with(char::is_whitespace) => {
+                word.text.push_str(&text);
+                word.end = end;
+                word.confidence = word.confidence.min(data.p);
+            }
+            _ => words.push(TimedWord {
+                text: text.trim_start().to_string(),
+                start,
+                end,
+                confidence: data.p,
+            }),
+        }
     }
 
-    Ok(transcript)
+    words.retain(|word| !word.text.is_empty());
+    Ok(words)
 }
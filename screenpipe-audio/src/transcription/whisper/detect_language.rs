@@ -1,33 +1,27 @@
-use anyhow::anyhow;
-use clap::ValueEnum;
 use log::debug;
 use screenpipe_core::Language;
-use whisper_rs::{get_lang_str, get_lang_str_full};
+use whisper_rs::get_lang_str;
 
-pub fn detect_language<'a>(tokens: Vec<f32>, languages: Vec<Language>) -> Option<&'a str> {
-    if languages.len() == 1 {
-        return Some(languages.first().unwrap().as_lang_code());
+/// Picks the most likely spoken language from whisper's per language
+/// probabilities, indexed by whisper language id. Only `languages` are
+/// considered when any are given, a single one is used without detection.
+pub fn detect_language(probabilities: &[f32], languages: &[Language]) -> Option<&'static str> {
+    if let [language] = languages {
+        return Some(language.as_lang_code());
     }
-    for token in tokens {
-        let token = token as i32;
-        if let Some(lang) = get_lang_str_full(token) {
-            debug!("Detected language {lang}");
-            if languages.is_empty() {
-                return get_lang_str(token);
-            }
 
-            let l = match Language::from_str(lang, true)
-                .map_err(|_| anyhow!("language token not found"))
-            {
-                Ok(lang) => lang,
-                Err(_) => return None,
-            };
+    let detected = probabilities
+        .iter()
+        .enumerate()
+        .filter_map(|(id, probability)| {
+            let code = get_lang_str(id as i32)?;
+            let allowed =
+                languages.is_empty() || languages.iter().any(|l| l.as_lang_code() == code);
+            allowed.then_some((code, *probability))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(code, _)| code);
 
-            if languages.contains(&l) {
-                return get_lang_str(token);
-            }
-        }
-    }
-
-    None
+    debug!("Detected language {:?}", detected);
+    detected
 }
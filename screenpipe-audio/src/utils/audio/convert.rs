@@ -15,3 +15,13 @@ pub fn audio_to_mono(audio: &[f32], channels: u16) -> Vec<f32> {
 
     mono_samples
 }
+
+/// Samples of a single channel, counted from 1, of interleaved audio.
+pub fn audio_channel(audio: &[f32], channels: u16, channel: u16) -> Vec<f32> {
+    audio
+        .iter()
+        .skip(channel.saturating_sub(1) as usize)
+        .step_by(channels.max(1) as usize)
+        .copied()
+        .collect()
+}
@@ -4,7 +4,7 @@ mod pcm_decode;
 mod resample;
 mod spectral_subtraction;
 
-pub use convert::audio_to_mono;
+pub use convert::{audio_channel, audio_to_mono};
 pub use normalization::normalize_v2;
 pub use pcm_decode::pcm_decode;
 pub use resample::resample;
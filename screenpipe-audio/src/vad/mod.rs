@@ -5,12 +5,14 @@ use anyhow;
 use lazy_static::lazy_static;
 use silero::SileroVad;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::Once;
+use std::time::Duration;
 use tokio::sync::Mutex;
 use vad_rs::VadStatus;
 use webrtc::WebRtcVad;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum VadSensitivity {
     Low,
     #[default]
@@ -28,17 +30,100 @@ impl VadSensitivity {
     }
 }
 
-#[derive(Clone)]
+impl FromStr for VadSensitivity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "low" => Ok(VadSensitivity::Low),
+            "medium" => Ok(VadSensitivity::Medium),
+            "high" => Ok(VadSensitivity::High),
+            _ => Err(anyhow::anyhow!("unknown vad sensitivity: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum VadEngineEnum {
     WebRtc,
     Silero,
 }
 
+impl FromStr for VadEngineEnum {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "webrtc" | "web-rtc" => Ok(VadEngineEnum::WebRtc),
+            "silero" => Ok(VadEngineEnum::Silero),
+            _ => Err(anyhow::anyhow!("unknown vad engine: {}", s)),
+        }
+    }
+}
+
+/// How frame decisions are turned into speech. Both default to zero, which
+/// keeps the engine's decisions as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpeechTiming {
+    /// Speech shorter than this is treated as noise, e.g. clicks and coughs.
+    pub min_speech_duration: Duration,
+    /// Speech continues for this long after the engine stops detecting it,
+    /// so quiet endings of sentences are not cut off.
+    pub hangover: Duration,
+}
+
+/// Everything about voice activity detection that can change while recording.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VadConfig {
+    pub engine: VadEngineEnum,
+    pub sensitivity: VadSensitivity,
+    pub timing: SpeechTiming,
+}
+
 pub trait VadEngine: Send {
     fn is_voice_segment(&mut self, audio_chunk: &[f32]) -> anyhow::Result<bool>;
     fn set_sensitivity(&mut self, sensitivity: VadSensitivity);
     fn audio_type(&mut self, audio_chunk: &[f32]) -> anyhow::Result<VadStatus>;
     fn get_min_speech_ratio(&self) -> f32;
+    fn set_speech_timing(&mut self, timing: SpeechTiming);
+    fn speech_timing(&self) -> SpeechTiming;
+}
+
+/// Applies the minimum speech duration and the hangover to per frame speech
+/// decisions.
+pub fn smooth_speech_frames(
+    speech: &[bool],
+    frame_duration: Duration,
+    timing: SpeechTiming,
+) -> Vec<bool> {
+    let frames = |duration: Duration| {
+        if frame_duration.is_zero() {
+            0.0
+        } else {
+            duration.as_secs_f64() / frame_duration.as_secs_f64()
+        }
+        .ceil() as usize
+    };
+    let min_frames = frames(timing.min_speech_duration);
+    let hangover_frames = frames(timing.hangover);
+
+    let mut smoothed = vec![false; speech.len()];
+    let mut i = 0;
+    while i < speech.len() {
+        if !speech[i] {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < speech.len() && speech[i] {
+            i += 1;
+        }
+        if i - start >= min_frames {
+            let end = (i + hangover_frames).min(speech.len());
+            smoothed[start..end].fill(true);
+        }
+    }
+    smoothed
 }
 
 const FRAME_HISTORY: usize = 10; // Number of frames to consider for decision
@@ -52,7 +137,7 @@ lazy_static! {
 
 static DOWNLOAD_ONCE: Once = Once::new();
 
-pub async fn create_vad_engine(engine: VadEngineEnum) -> anyhow::Result<Box<dyn VadEngine>> {
+pub async fn create_vad_engine(engine: VadEngineEnum) -> anyhow::Result<Box<dyn VadEngine + Send>> {
     match engine {
         VadEngineEnum::WebRtc => Ok(Box::new(WebRtcVad::new())),
         VadEngineEnum::Silero => {
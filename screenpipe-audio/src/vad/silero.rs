@@ -8,7 +8,7 @@ use vad_rs::{Vad, VadStatus};
 use crate::vad::FRAME_HISTORY;
 
 use super::{
-    VadEngine, VadSensitivity, DOWNLOAD_ONCE, MODEL_PATH, SILENCE_THRESHOLD,
+    SpeechTiming, VadEngine, VadSensitivity, DOWNLOAD_ONCE, MODEL_PATH, SILENCE_THRESHOLD,
     SPEECH_FRAME_THRESHOLD, SPEECH_THRESHOLD,
 };
 
@@ -16,6 +16,7 @@ pub struct SileroVad {
     vad: Vad,
     prob_history: VecDeque<f32>,
     sensitivity: VadSensitivity,
+    speech_timing: SpeechTiming,
 }
 
 impl SileroVad {
@@ -32,6 +33,7 @@ impl SileroVad {
             vad,
             prob_history: VecDeque::with_capacity(FRAME_HISTORY),
             sensitivity: VadSensitivity::Medium,
+            speech_timing: SpeechTiming::default(),
         })
     }
 
@@ -177,4 +179,12 @@ impl VadEngine for SileroVad {
     fn get_min_speech_ratio(&self) -> f32 {
         self.sensitivity.min_speech_ratio()
     }
+
+    fn set_speech_timing(&mut self, timing: SpeechTiming) {
+        self.speech_timing = timing;
+    }
+
+    fn speech_timing(&self) -> SpeechTiming {
+        self.speech_timing
+    }
 }
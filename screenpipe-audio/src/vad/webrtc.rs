@@ -1,12 +1,13 @@
 use anyhow;
 use vad_rs::VadStatus;
 
-use super::{VadEngine, VadSensitivity};
+use super::{SpeechTiming, VadEngine, VadSensitivity};
 
 #[derive(Default)]
 pub struct WebRtcVad {
     vad: webrtc_vad::Vad,
     sensitivity: VadSensitivity,
+    speech_timing: SpeechTiming,
 }
 
 impl WebRtcVad {
@@ -15,6 +16,7 @@ impl WebRtcVad {
         Self {
             vad,
             sensitivity: VadSensitivity::Medium,
+            speech_timing: SpeechTiming::default(),
         }
     }
 }
@@ -69,4 +71,12 @@ impl VadEngine for WebRtcVad {
     fn get_min_speech_ratio(&self) -> f32 {
         self.sensitivity.min_speech_ratio()
     }
+
+    fn set_speech_timing(&mut self, timing: SpeechTiming) {
+        self.speech_timing = timing;
+    }
+
+    fn speech_timing(&self) -> SpeechTiming {
+        self.speech_timing
+    }
 }
@@ -0,0 +1,56 @@
+use screenpipe_audio::core::device::{
+    parse_audio_channel, parse_audio_device, AudioDevice, DeviceType,
+};
+
+fn interface() -> AudioDevice {
+    AudioDevice::new("Scarlett 4i4".to_string(), DeviceType::Input)
+}
+
+#[test]
+fn test_channel_device_keeps_its_label() {
+    let device = AudioDevice::channel(&interface(), 2, Some("guest"));
+
+    assert_eq!(device.to_string(), "Scarlett 4i4 [ch 2: guest] (input)");
+    assert_eq!(device.source_channel(), Some(2));
+    assert_eq!(device.channel_label(), Some("guest"));
+    assert_eq!(device.capture_device(), interface());
+
+    // the name is all that's stored with transcripts and passed to the api
+    let parsed = parse_audio_device(&device.to_string()).unwrap();
+    assert_eq!(parsed, device);
+    assert_eq!(parsed.source_channel(), Some(2));
+}
+
+#[test]
+fn test_channel_device_without_label() {
+    let device = AudioDevice::channel(&interface(), 1, Some("  "));
+
+    assert_eq!(device.to_string(), "Scarlett 4i4 [ch 1] (input)");
+    assert_eq!(device.source_channel(), Some(1));
+    assert_eq!(device.channel_label(), None);
+}
+
+#[test]
+fn test_regular_devices_are_not_channels() {
+    for name in ["Scarlett 4i4", "Mic [USB]", "Mic [ch x]", "Mic [ch 0]"] {
+        let device = AudioDevice::new(name.to_string(), DeviceType::Input);
+        assert_eq!(device.source_channel(), None, "{}", name);
+        assert_eq!(device.capture_device(), device);
+    }
+}
+
+#[test]
+fn test_parse_audio_channel() {
+    assert_eq!(
+        parse_audio_channel("Scarlett 4i4 (input):1=host").unwrap(),
+        AudioDevice::channel(&interface(), 1, Some("host"))
+    );
+    assert_eq!(
+        parse_audio_channel("Scarlett 4i4 (input):3").unwrap(),
+        AudioDevice::channel(&interface(), 3, None)
+    );
+
+    assert!(parse_audio_channel("Scarlett 4i4 (input)").is_err());
+    assert!(parse_audio_channel("Scarlett 4i4 (input):0").is_err());
+    assert!(parse_audio_channel("Scarlett 4i4:1").is_err());
+}
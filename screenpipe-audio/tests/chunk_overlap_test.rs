@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use screenpipe_audio::core::device::{AudioDevice, DeviceType};
+use screenpipe_audio::core::engine::AudioTranscriptionEngine;
+use screenpipe_audio::core::ChunkingConfig;
+use screenpipe_audio::{AudioInput, TranscriptionResult};
+
+const OVERLAP: Duration = Duration::from_secs(2);
+
+fn segment(text: &str, start_time: f64) -> TranscriptionResult {
+    TranscriptionResult {
+        path: String::new(),
+        input: AudioInput {
+            data: Arc::new(Vec::new()),
+            sample_rate: 16000,
+            channels: 1,
+            device: Arc::new(AudioDevice::new("mic".to_string(), DeviceType::Input)),
+            recorded_at: None,
+            media_detection: true,
+        },
+        engine: Arc::new(AudioTranscriptionEngine::default()),
+        speaker_embedding: Vec::new(),
+        transcription: Some(text.to_string()),
+        language: None,
+        translation: None,
+        timestamp: 0,
+        error: None,
+        start_time,
+        end_time: start_time + 5.0,
+        classification: None,
+        words: Vec::new(),
+        span: tracing::Span::none(),
+    }
+}
+
+#[test]
+fn test_overlapped_words_are_kept_once() {
+    let previous = "so the plan for next week is to ship the new onboar".to_string();
+    let mut current = segment("ship the new onboarding flow, then we look at pricing", 0.0);
+
+    let (previous, current) = current.cleanup_overlap(previous, OVERLAP).unwrap();
+    // the cut word is dropped from the previous chunk, transcribed whole in this one
+    assert_eq!(previous, "so the plan for next week is to");
+    assert_eq!(
+        current,
+        "ship the new onboarding flow, then we look at pricing"
+    );
+}
+
+#[test]
+fn test_words_outside_the_overlap_are_not_matched() {
+    // "the new" is shared, but said far before the end of the previous chunk
+    let previous = "we talked about the new logo and then everyone left for lunch".to_string();
+    let mut current = segment("okay the new hire starts monday", 0.0);
+    assert_eq!(current.cleanup_overlap(previous.clone(), OVERLAP), None);
+
+    // a single shared word could be a coincidence
+    let mut current = segment("lunch was great", 0.0);
+    assert_eq!(current.cleanup_overlap(previous.clone(), OVERLAP), None);
+
+    // a segment starting after the overlap holds no repeated audio
+    let mut current = segment("everyone left for lunch again", 12.0);
+    assert_eq!(current.cleanup_overlap(previous, OVERLAP), None);
+}
+
+#[test]
+fn test_no_overlap_disables_merging() {
+    let previous = "see you tomorrow".to_string();
+    let mut current = segment("see you tomorrow", 0.0);
+    assert_eq!(current.cleanup_overlap(previous, Duration::ZERO), None);
+}
+
+#[test]
+fn test_chunking_config_is_validated() {
+    assert!(ChunkingConfig::default().validate().is_ok());
+    assert!(ChunkingConfig {
+        duration: Duration::from_secs(10),
+        overlap: Duration::ZERO,
+    }
+    .validate()
+    .is_ok());
+    assert!(ChunkingConfig {
+        duration: Duration::from_secs(2),
+        overlap: Duration::from_secs(2),
+    }
+    .validate()
+    .is_err());
+    assert!(ChunkingConfig {
+        duration: Duration::ZERO,
+        overlap: Duration::ZERO,
+    }
+    .validate()
+    .is_err());
+}
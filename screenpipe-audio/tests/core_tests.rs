@@ -3,12 +3,14 @@ mod tests {
     use anyhow::anyhow;
     use chrono::Utc;
     use log::{debug, LevelFilter};
+    use screenpipe_audio::core::denoise::NoiseSuppression;
     use screenpipe_audio::core::device::{
         default_input_device, default_output_device, list_audio_devices, parse_audio_device,
+        AudioDevice, DeviceType,
     };
     use screenpipe_audio::core::engine::AudioTranscriptionEngine;
-    use screenpipe_audio::core::record_and_transcribe;
     use screenpipe_audio::core::stream::AudioStream;
+    use screenpipe_audio::core::{record_and_transcribe, ChunkingConfig};
     use screenpipe_audio::speaker::embedding::EmbeddingExtractor;
     use screenpipe_audio::speaker::embedding_manager::EmbeddingManager;
     use screenpipe_audio::speaker::prepare_segments;
@@ -24,7 +26,7 @@ mod tests {
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::Arc;
     use std::time::{Duration, Instant};
-    use tokio::sync::Mutex;
+    use tokio::sync::{watch, Mutex};
     use whisper_rs::WhisperContext;
 
     fn setup() {
@@ -51,6 +53,20 @@ mod tests {
         assert_eq!(spec.to_string(), "Test Device (input)");
     }
 
+    #[test]
+    fn test_app_device_round_trips() {
+        let device = AudioDevice::app("Zoom.exe");
+        assert_eq!(device.to_string(), "app:zoom.exe (output)");
+        assert_eq!(device.source_app(), Some("zoom.exe"));
+
+        let parsed = parse_audio_device(&device.to_string()).unwrap();
+        assert_eq!(parsed, device);
+        assert_eq!(parsed.device_type, DeviceType::Output);
+
+        let speakers = parse_audio_device("Speakers (output)").unwrap();
+        assert_eq!(speakers.source_app(), None);
+    }
+
     #[tokio::test]
     #[ignore] // Add this if you want to skip this test in regular test runs
     async fn test_record_and_transcribe() {
@@ -74,9 +90,15 @@ mod tests {
         println!("Starting record_and_transcribe");
         let result = record_and_transcribe(
             Arc::new(audio_stream),
-            duration,
+            watch::channel(ChunkingConfig {
+                duration,
+                ..Default::default()
+            })
+            .1,
             Arc::new(sender),
             is_running,
+            false,
+            watch::channel(NoiseSuppression::default()).1,
         )
         .await;
         println!("record_and_transcribe completed");
@@ -140,9 +162,15 @@ mod tests {
 
         record_and_transcribe(
             Arc::new(audio_stream),
-            duration,
+            watch::channel(ChunkingConfig {
+                duration,
+                ..Default::default()
+            })
+            .1,
             Arc::new(sender),
             is_running,
+            false,
+            watch::channel(NoiseSuppression::default()).1,
         )
         .await
         .unwrap();
@@ -234,6 +262,8 @@ mod tests {
             sample_rate: 44100, // hardcoded based on test data sample rate
             channels: 1,
             device: Arc::new(default_input_device().unwrap()),
+            recorded_at: None,
+            media_detection: true,
         };
 
         // Create the missing parameters
@@ -317,6 +347,8 @@ mod tests {
             sample_rate: 16000, // Adjust this based on your test audio
             channels: 1,
             device: Arc::new(default_output_device().await.unwrap()),
+            recorded_at: None,
+            media_detection: true,
         };
 
         let project_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -391,4 +423,21 @@ mod tests {
 
         debug!("Transcription completed in {:?}", elapsed_time);
     }
+
+    #[test]
+    fn test_parse_transcription_engine_names() {
+        assert_eq!(
+            AudioTranscriptionEngine::from_str("whisper-large-v3-turbo-quantized").unwrap(),
+            AudioTranscriptionEngine::WhisperLargeV3TurboQuantized
+        );
+        assert_eq!(
+            AudioTranscriptionEngine::from_str("whisper-large").unwrap(),
+            AudioTranscriptionEngine::WhisperLargeV3
+        );
+        assert_eq!(
+            AudioTranscriptionEngine::from_str("Deepgram").unwrap(),
+            AudioTranscriptionEngine::Deepgram
+        );
+        assert!(AudioTranscriptionEngine::from_str("whisper-huge").is_err());
+    }
 }
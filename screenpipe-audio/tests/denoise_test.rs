@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use screenpipe_audio::core::denoise::{denoise, snr_db, NoiseSuppression};
+
+const SAMPLE_RATE: u32 = 16000;
+
+/// Deterministic white noise, to keep the tests stable.
+fn noise(len: usize, amplitude: f32) -> Vec<f32> {
+    let mut seed: u32 = 42;
+    (0..len)
+        .map(|_| {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed as f32 / u32::MAX as f32 * 2.0 - 1.0) * amplitude
+        })
+        .collect()
+}
+
+/// A tone that's on every other 100ms, like speech with pauses.
+fn bursts(len: usize, amplitude: f32) -> Vec<f32> {
+    (0..len)
+        .map(|i| {
+            let on = (i / (SAMPLE_RATE as usize / 10)) % 2 == 0;
+            if on {
+                (2.0 * PI * 220.0 * i as f32 / SAMPLE_RATE as f32).sin() * amplitude
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+fn mix(a: &[f32], b: &[f32]) -> Vec<f32> {
+    a.iter().zip(b).map(|(a, b)| a + b).collect()
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+#[test]
+fn test_noise_suppression_per_device() {
+    let mut config = NoiseSuppression::default();
+    assert!(!config.is_enabled("mic (input)"));
+
+    config.devices = HashMap::from([("mic (input)".to_string(), true)]);
+    assert!(config.is_enabled("mic (input)"));
+    assert!(!config.is_enabled("speakers (output)"));
+
+    config.default = true;
+    config.devices.insert("mic (input)".to_string(), false);
+    assert!(!config.is_enabled("mic (input)"));
+    assert!(config.is_enabled("speakers (output)"));
+}
+
+#[test]
+fn test_snr_is_higher_for_cleaner_audio() {
+    let len = SAMPLE_RATE as usize * 2;
+    let clean = mix(&bursts(len, 0.5), &noise(len, 0.001));
+    let noisy = mix(&bursts(len, 0.5), &noise(len, 0.05));
+
+    let clean_snr = snr_db(&clean, SAMPLE_RATE).unwrap();
+    let noisy_snr = snr_db(&noisy, SAMPLE_RATE).unwrap();
+    assert!(
+        clean_snr > noisy_snr + 20.0,
+        "{} vs {}",
+        clean_snr,
+        noisy_snr
+    );
+}
+
+#[test]
+fn test_snr_of_silent_or_short_audio() {
+    assert_eq!(snr_db(&vec![0.0; SAMPLE_RATE as usize], SAMPLE_RATE), None);
+    assert_eq!(snr_db(&[0.1; 100], SAMPLE_RATE), None);
+}
+
+#[test]
+fn test_denoise_keeps_length_and_removes_noise() {
+    let len = SAMPLE_RATE as usize * 2 + 123;
+    let input = noise(len, 0.05);
+
+    let output = denoise(&input, SAMPLE_RATE).unwrap();
+    assert_eq!(output.len(), input.len());
+    assert!(
+        rms(&output) < rms(&input) / 2.0,
+        "{} vs {}",
+        rms(&output),
+        rms(&input)
+    );
+}
+
+#[test]
+fn test_denoise_empty_audio() {
+    assert!(denoise(&[], SAMPLE_RATE).unwrap().is_empty());
+}
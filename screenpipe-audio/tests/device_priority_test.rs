@@ -0,0 +1,67 @@
+use screenpipe_audio::audio_manager::{is_prioritized, preferred_device};
+use screenpipe_audio::core::device::{AudioDevice, DeviceType};
+
+fn input(name: &str) -> AudioDevice {
+    AudioDevice::new(name.to_string(), DeviceType::Input)
+}
+
+fn output(name: &str) -> AudioDevice {
+    AudioDevice::new(name.to_string(), DeviceType::Output)
+}
+
+fn priority(entries: &[&str]) -> Vec<String> {
+    entries.iter().map(|entry| entry.to_string()).collect()
+}
+
+#[test]
+fn test_preferred_device_follows_priority_order() {
+    let priority = priority(&["jabra", "MacBook Pro Microphone"]);
+    let docked = vec![
+        input("MacBook Pro Microphone"),
+        input("Jabra Evolve2 65"),
+        output("Jabra Evolve2 65"),
+    ];
+
+    assert_eq!(
+        preferred_device(&priority, &docked, &DeviceType::Input),
+        Some(input("Jabra Evolve2 65"))
+    );
+    assert_eq!(
+        preferred_device(&priority, &docked, &DeviceType::Output),
+        Some(output("Jabra Evolve2 65"))
+    );
+
+    // undocked, the headset is gone
+    let undocked = vec![
+        input("MacBook Pro Microphone"),
+        output("MacBook Pro Speakers"),
+    ];
+    assert_eq!(
+        preferred_device(&priority, &undocked, &DeviceType::Input),
+        Some(input("MacBook Pro Microphone"))
+    );
+    assert_eq!(
+        preferred_device(&priority, &undocked, &DeviceType::Output),
+        None
+    );
+}
+
+#[test]
+fn test_priority_entries_can_target_a_device_type() {
+    let priority = priority(&["Jabra Evolve2 65 (input)"]);
+    let devices = vec![input("Jabra Evolve2 65"), output("Jabra Evolve2 65")];
+
+    assert!(is_prioritized(&priority, &input("Jabra Evolve2 65")));
+    assert!(!is_prioritized(&priority, &output("Jabra Evolve2 65")));
+    assert_eq!(
+        preferred_device(&priority, &devices, &DeviceType::Output),
+        None
+    );
+}
+
+#[test]
+fn test_empty_priority_entries_match_nothing() {
+    let priority = priority(&["", "  "]);
+    assert!(!is_prioritized(&priority, &input("MacBook Pro Microphone")));
+    assert!(!is_prioritized(&[], &input("MacBook Pro Microphone")));
+}
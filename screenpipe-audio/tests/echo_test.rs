@@ -0,0 +1,63 @@
+use screenpipe_audio::core::echo::{envelope, suppress_echo};
+
+const SAMPLE_RATE: u32 = 16000;
+
+/// A tone whose loudness changes every 120ms, loosely like syllables.
+fn speech_like(seconds: usize, seed: u32) -> Vec<f32> {
+    let mut state = seed;
+    let mut gain = 0.0;
+    (0..seconds * SAMPLE_RATE as usize)
+        .map(|i| {
+            if i % 1920 == 0 {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                gain = (state >> 16) as f32 / 65536.0;
+            }
+            gain * (i as f32 * 0.17).sin()
+        })
+        .collect()
+}
+
+/// Envelope of `played`, with 500ms of what was played before the mic chunk.
+fn reference(played: &[f32]) -> Vec<f32> {
+    let mut reference = vec![0.0; 50];
+    reference.extend(envelope(played, SAMPLE_RATE));
+    reference
+}
+
+#[test]
+fn test_echo_of_played_audio_is_muted() {
+    let played = speech_like(4, 7);
+    // picked up 100ms later and much quieter
+    let delay = SAMPLE_RATE as usize / 10;
+    let mut mic: Vec<f32> = (0..played.len())
+        .map(|i| {
+            if i < delay {
+                0.0
+            } else {
+                played[i - delay] * 0.2
+            }
+        })
+        .collect();
+
+    let muted = suppress_echo(&mut mic, SAMPLE_RATE, &reference(&played));
+    assert!(muted > mic.len() * 3 / 4, "only muted {} samples", muted);
+}
+
+#[test]
+fn test_local_speech_is_kept() {
+    let played = speech_like(4, 7);
+    let mut mic = speech_like(4, 99);
+    let original = mic.clone();
+
+    let muted = suppress_echo(&mut mic, SAMPLE_RATE, &reference(&played));
+    assert!(muted < mic.len() / 4, "muted {} samples", muted);
+
+    // nothing played, nothing to suppress
+    let mut mic = original.clone();
+    let silence = vec![0.0; played.len()];
+    assert_eq!(
+        suppress_echo(&mut mic, SAMPLE_RATE, &reference(&silence)),
+        0
+    );
+    assert_eq!(mic, original);
+}
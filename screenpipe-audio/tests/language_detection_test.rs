@@ -0,0 +1,32 @@
+use screenpipe_audio::transcription::whisper::detect_language;
+use screenpipe_core::Language;
+
+/// Whisper's probabilities with German (id 2) most likely, then English (id 0).
+fn german_speech() -> Vec<f32> {
+    let mut probabilities = vec![0.001; 99];
+    probabilities[0] = 0.2;
+    probabilities[2] = 0.7;
+    probabilities
+}
+
+#[test]
+fn test_detects_most_likely_language() {
+    assert_eq!(detect_language(&german_speech(), &[]), Some("de"));
+    assert_eq!(
+        detect_language(&german_speech(), &[Language::English, Language::German]),
+        Some("de")
+    );
+}
+
+#[test]
+fn test_detection_is_limited_to_configured_languages() {
+    assert_eq!(
+        detect_language(&german_speech(), &[Language::English, Language::Spanish]),
+        Some("en")
+    );
+    // a single language is used as is
+    assert_eq!(
+        detect_language(&german_speech(), &[Language::Spanish]),
+        Some("es")
+    );
+}
@@ -0,0 +1,165 @@
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use screenpipe_audio::transcription::provider::{SttProvider, SttRouter};
+use screenpipe_audio::transcription::vocabulary::{Vocabulary, VocabularyTerm};
+use screenpipe_audio::transcription::Transcript;
+use screenpipe_core::Language;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::Duration;
+
+struct FakeProvider {
+    name: &'static str,
+    cloud: bool,
+    fail: bool,
+    delay: Duration,
+    calls: AtomicUsize,
+}
+
+impl FakeProvider {
+    fn new(name: &'static str, cloud: bool) -> Self {
+        Self {
+            name,
+            cloud,
+            fail: false,
+            delay: Duration::ZERO,
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl SttProvider for FakeProvider {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn is_cloud(&self) -> bool {
+        self.cloud
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        _audio: &'a [f32],
+        _sample_rate: u32,
+        _device: &'a str,
+        _languages: &'a [Language],
+        _vocabulary: &'a Vocabulary,
+    ) -> BoxFuture<'a, Result<Transcript>> {
+        Box::pin(async move {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            if self.fail {
+                Err(anyhow!("{} failed", self.name))
+            } else {
+                Ok(Transcript {
+                    text: format!("from {}", self.name),
+                    ..Default::default()
+                })
+            }
+        })
+    }
+}
+
+// 100ms of audio
+const AUDIO: [f32; 1600] = [0.0; 1600];
+const NO_VOCABULARY: Vocabulary = Vocabulary { terms: Vec::new() };
+
+#[tokio::test]
+async fn test_falls_back_when_a_provider_fails() {
+    let local = Arc::new(FakeProvider {
+        fail: true,
+        ..FakeProvider::new("local", false)
+    });
+    let cloud = Arc::new(FakeProvider::new("cloud", true));
+    let router = SttRouter::new(vec![local.clone(), cloud.clone()], false).unwrap();
+
+    let transcript = router
+        .transcribe(&AUDIO, 16000, "mic", &[], &NO_VOCABULARY)
+        .await
+        .unwrap();
+    assert_eq!(transcript.text, "from cloud");
+    assert_eq!(local.calls(), 1);
+}
+
+#[tokio::test]
+async fn test_disabled_cloud_is_never_used() {
+    let local = Arc::new(FakeProvider {
+        fail: true,
+        ..FakeProvider::new("local", false)
+    });
+    let cloud = Arc::new(FakeProvider::new("cloud", true));
+    let router = SttRouter::new(vec![local, cloud.clone()], true).unwrap();
+
+    assert_eq!(router.providers().collect::<Vec<_>>(), vec!["local"]);
+    assert!(router
+        .transcribe(&AUDIO, 16000, "mic", &[], &NO_VOCABULARY)
+        .await
+        .is_err());
+    assert_eq!(cloud.calls(), 0);
+
+    let cloud_only: Vec<Arc<dyn SttProvider>> = vec![Arc::new(FakeProvider::new("cloud", true))];
+    assert!(SttRouter::new(cloud_only, true).is_err());
+}
+
+#[tokio::test]
+async fn test_slow_local_model_fails_over_to_cloud() {
+    let local = Arc::new(FakeProvider {
+        delay: Duration::from_millis(300),
+        ..FakeProvider::new("local", false)
+    });
+    let cloud = Arc::new(FakeProvider::new("cloud", true));
+    let router = SttRouter::new(vec![local.clone(), cloud.clone()], false).unwrap();
+
+    // transcribing 100ms of audio in 300ms falls behind real time
+    let first = router
+        .transcribe(&AUDIO, 16000, "mic", &[], &NO_VOCABULARY)
+        .await
+        .unwrap();
+    assert_eq!(first.text, "from local");
+    assert!(router.is_failed_over());
+
+    let second = router
+        .transcribe(&AUDIO, 16000, "mic", &[], &NO_VOCABULARY)
+        .await
+        .unwrap();
+    assert_eq!(second.text, "from cloud");
+    assert_eq!(local.calls(), 1);
+}
+
+#[tokio::test]
+async fn test_fast_local_model_stays_local() {
+    let local = Arc::new(FakeProvider::new("local", false));
+    let cloud = Arc::new(FakeProvider::new("cloud", true));
+    let router = SttRouter::new(vec![local.clone(), cloud.clone()], false).unwrap();
+
+    for _ in 0..3 {
+        let transcript = router
+            .transcribe(&AUDIO, 16000, "mic", &[], &NO_VOCABULARY)
+            .await
+            .unwrap();
+        assert_eq!(transcript.text, "from local");
+    }
+    assert!(!router.is_failed_over());
+    assert_eq!(cloud.calls(), 0);
+}
+
+#[tokio::test]
+async fn test_vocabulary_corrects_transcripts() {
+    let local: Arc<dyn SttProvider> = Arc::new(FakeProvider::new("screen pipe", false));
+    let router = SttRouter::new(vec![local], false).unwrap();
+    let vocabulary = Vocabulary::new(vec![
+        VocabularyTerm::new("screenpipe").sounds_like("screen pipe")
+    ]);
+
+    let transcript = router
+        .transcribe(&AUDIO, 16000, "mic", &[], &vocabulary)
+        .await
+        .unwrap();
+    assert_eq!(transcript.text, "from screenpipe");
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use screenpipe_audio::core::device::{AudioDevice, DeviceType};
+use screenpipe_audio::core::engine::AudioTranscriptionEngine;
+use screenpipe_audio::transcription::TimedWord;
+use screenpipe_audio::{AudioInput, TranscriptionResult};
+
+fn timed(text: &str, start: f64) -> TimedWord {
+    TimedWord {
+        text: text.to_string(),
+        start,
+        end: start + 0.4,
+        confidence: 0.9,
+    }
+}
+
+fn segment(text: &str, words: Vec<TimedWord>) -> TranscriptionResult {
+    TranscriptionResult {
+        path: String::new(),
+        input: AudioInput {
+            data: Arc::new(Vec::new()),
+            sample_rate: 16000,
+            channels: 1,
+            device: Arc::new(AudioDevice::new("mic".to_string(), DeviceType::Input)),
+            recorded_at: None,
+            media_detection: true,
+        },
+        engine: Arc::new(AudioTranscriptionEngine::default()),
+        speaker_embedding: Vec::new(),
+        transcription: Some(text.to_string()),
+        language: None,
+        translation: None,
+        timestamp: 0,
+        error: None,
+        start_time: 1.5,
+        end_time: 4.0,
+        classification: None,
+        words,
+        span: tracing::Span::none(),
+    }
+}
+
+#[test]
+fn test_words_are_timed_from_the_chunk_file() {
+    let result = segment(
+        "call me at noon",
+        vec![
+            timed("call", 0.0),
+            timed("me", 0.4),
+            timed("at", 0.8),
+            timed("noon", 1.2),
+        ],
+    );
+
+    let words = result.transcript_words("call me at noon");
+    assert_eq!(words.len(), 4);
+    assert_eq!(words[3].word, "noon");
+    assert_eq!(words[3].start_time, 2.7);
+    assert!((words[3].confidence - 0.9).abs() < 1e-6);
+}
+
+#[test]
+fn test_words_follow_the_stored_text() {
+    let words = vec![
+        timed("mail", 0.0),
+        timed("jane@example.com", 0.4),
+        timed("today", 1.2),
+    ];
+    let result = segment("mail jane@example.com today", words);
+
+    // redacted in place, the timings are kept for the redacted text
+    let stored = result.transcript_words("mail [EMAIL] today");
+    assert_eq!(stored[1].word, "[EMAIL]");
+    assert!(stored.iter().all(|word| word.word != "jane@example.com"));
+
+    // no longer lined up with the text, nothing is kept
+    assert!(result.transcript_words("mail [REDACTED]").is_empty());
+}
+
+#[test]
+fn test_overlapped_words_lose_their_timings() {
+    // the chunk starts in the middle of "week"
+    let mut result = segment(
+        "ek next week ship it",
+        vec![
+            timed("ek", 0.0),
+            timed("next", 0.2),
+            timed("week", 0.6),
+            timed("ship", 1.0),
+            timed("it", 1.4),
+        ],
+    );
+    result.start_time = 0.0;
+
+    let (_, current) = result
+        .cleanup_overlap("the plan for next week".to_string(), Duration::from_secs(2))
+        .unwrap();
+    assert_eq!(current, "next week ship it");
+    let words = result.transcript_words(&current);
+    assert_eq!(words.len(), 4);
+    assert_eq!(words[0].word, "next");
+    assert_eq!(words[0].start_time, 0.2);
+}
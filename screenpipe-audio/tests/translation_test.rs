@@ -0,0 +1,77 @@
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use screenpipe_audio::transcription::translation::{translate_transcript, Translator};
+use screenpipe_audio::transcription::Transcript;
+
+struct FakeTranslator {
+    fail: bool,
+}
+
+impl Translator for FakeTranslator {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        _audio: &'a [f32],
+        transcript: &'a Transcript,
+    ) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            if self.fail {
+                Err(anyhow!("translation failed"))
+            } else {
+                Ok(format!(" english for {} ", transcript.text))
+            }
+        })
+    }
+}
+
+fn transcript(text: &str, language: Option<&str>) -> Transcript {
+    Transcript {
+        text: text.to_string(),
+        language: language.map(str::to_string),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_other_languages_are_translated() {
+    let translator = FakeTranslator { fail: false };
+    let translation = translate_transcript(
+        &translator,
+        &[],
+        &transcript("hallo zusammen", Some("de")),
+        "mic",
+    )
+    .await;
+    assert_eq!(translation.as_deref(), Some("english for hallo zusammen"));
+}
+
+#[tokio::test]
+async fn test_english_and_unknown_languages_are_not_translated() {
+    let translator = FakeTranslator { fail: false };
+    for transcript in [
+        transcript("hello there", Some("en")),
+        transcript("hello there", None),
+        transcript(" ", Some("de")),
+    ] {
+        assert_eq!(
+            translate_transcript(&translator, &[], &transcript, "mic").await,
+            None
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_failed_translation_is_skipped() {
+    let translator = FakeTranslator { fail: true };
+    let translation = translate_transcript(
+        &translator,
+        &[],
+        &transcript("hallo zusammen", Some("de")),
+        "mic",
+    )
+    .await;
+    assert_eq!(translation, None);
+}
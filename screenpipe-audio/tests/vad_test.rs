@@ -0,0 +1,59 @@
+use screenpipe_audio::vad::{smooth_speech_frames, SpeechTiming, VadEngineEnum, VadSensitivity};
+use std::str::FromStr;
+use std::time::Duration;
+
+const FRAME: Duration = Duration::from_millis(100);
+
+fn frames(pattern: &str) -> Vec<bool> {
+    pattern.chars().map(|c| c == '#').collect()
+}
+
+#[test]
+fn test_default_timing_keeps_frames() {
+    let speech = frames("..#..###.#");
+    assert_eq!(
+        smooth_speech_frames(&speech, FRAME, SpeechTiming::default()),
+        speech
+    );
+}
+
+#[test]
+fn test_short_speech_is_dropped() {
+    let timing = SpeechTiming {
+        min_speech_duration: Duration::from_millis(300),
+        ..Default::default()
+    };
+    assert_eq!(
+        smooth_speech_frames(&frames("#..###.##."), FRAME, timing),
+        frames("...###....")
+    );
+}
+
+#[test]
+fn test_hangover_extends_speech() {
+    let timing = SpeechTiming {
+        hangover: Duration::from_millis(200),
+        ..Default::default()
+    };
+    assert_eq!(
+        smooth_speech_frames(&frames(".##....#."), FRAME, timing),
+        frames(".####..##")
+    );
+}
+
+#[test]
+fn test_vad_settings_parse() {
+    assert_eq!(
+        VadEngineEnum::from_str("Silero").unwrap(),
+        VadEngineEnum::Silero
+    );
+    assert_eq!(
+        VadEngineEnum::from_str("webrtc").unwrap(),
+        VadEngineEnum::WebRtc
+    );
+    assert_eq!(
+        VadSensitivity::from_str("low").unwrap(),
+        VadSensitivity::Low
+    );
+    assert!(VadSensitivity::from_str("loud").is_err());
+}
@@ -0,0 +1,48 @@
+use screenpipe_audio::transcription::vocabulary::{Vocabulary, VocabularyTerm};
+
+fn vocabulary() -> Vocabulary {
+    Vocabulary::new(vec![
+        VocabularyTerm::new("screenpipe").sounds_like("screen pipe"),
+        VocabularyTerm::new("Kubernetes").sounds_like("cooper netties"),
+        VocabularyTerm::new("k8s"),
+    ])
+}
+
+#[test]
+fn test_terms_are_in_the_prompt_and_keywords() {
+    let vocabulary = vocabulary();
+    assert_eq!(
+        vocabulary.prompt().as_deref(),
+        Some("Glossary: screenpipe, Kubernetes, k8s.")
+    );
+    assert_eq!(
+        vocabulary.keywords(),
+        vec!["screenpipe:2", "Kubernetes:2", "k8s:2"]
+    );
+    assert_eq!(Vocabulary::default().prompt(), None);
+}
+
+#[test]
+fn test_long_vocabularies_are_cut_to_fit_the_prompt() {
+    let terms = (0..500)
+        .map(|i| VocabularyTerm::new(format!("term{}", i)))
+        .collect();
+    let prompt = Vocabulary::new(terms).prompt().unwrap();
+    assert!(prompt.len() <= 620);
+    assert!(prompt.starts_with("Glossary: term0, term1"));
+}
+
+#[test]
+fn test_misheard_terms_are_corrected() {
+    let vocabulary = vocabulary();
+    assert_eq!(
+        vocabulary.correct("We deployed Screen Pipe on cooper netties."),
+        "We deployed screenpipe on Kubernetes."
+    );
+    // words that only start like a hint are kept
+    assert_eq!(
+        vocabulary.correct("the screen pipeline is fine"),
+        "the screen pipeline is fine"
+    );
+    assert_eq!(Vocabulary::default().correct("screen pipe"), "screen pipe");
+}
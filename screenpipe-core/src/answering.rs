@@ -0,0 +1,119 @@
+//! Answers to questions about the recordings written by a local LLM from
+//! excerpts found for the question, citing the excerpts they rely on by
+//! number.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::llm_client::{json_object, LlmClient};
+
+pub const DEFAULT_QUERY_MODEL: &str = "llama3.2";
+
+/// Characters of excerpts given to the model.
+const DEFAULT_MAX_INPUT_CHARS: usize = 16_000;
+
+/// Recorded text an answer can rely on.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Excerpt {
+    /// Where and when it was recorded, e.g. "screen, Slack, Tuesday 22 April
+    /// 2025 14:03"
+    pub source: String,
+    pub text: String,
+}
+
+/// What the model answered, `citations` are the numbers of the excerpts it
+/// relies on, from 1.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnswerContent {
+    pub answer: String,
+    pub citations: Vec<usize>,
+}
+
+/// Answers questions with a local LLM.
+pub struct Answerer {
+    llm: LlmClient,
+    max_input_chars: usize,
+}
+
+impl Answerer {
+    pub fn new(llm: LlmClient) -> Self {
+        Answerer {
+            llm,
+            max_input_chars: DEFAULT_MAX_INPUT_CHARS,
+        }
+    }
+
+    pub fn with_max_input_chars(mut self, max_input_chars: usize) -> Self {
+        self.max_input_chars = max_input_chars;
+        self
+    }
+
+    /// Name of the model, returned with the answers.
+    pub fn model(&self) -> String {
+        self.llm.model()
+    }
+
+    pub async fn answer(&self, question: &str, excerpts: &[Excerpt]) -> Result<AnswerContent> {
+        let prompt = answer_prompt(question, excerpts, self.max_input_chars);
+        let response = self.llm.complete_json(prompt).await?;
+        parse_answer(&response, excerpts.len())
+    }
+}
+
+/// Instructions, numbered excerpts and question for the model, each excerpt
+/// getting an equal share of `max_chars`.
+pub fn answer_prompt(question: &str, excerpts: &[Excerpt], max_chars: usize) -> String {
+    let mut prompt = String::from(
+        "You answer questions about what a person saw on their screen and heard, from excerpts of what their computer recorded.\n\
+         Answer with JSON only, in this shape:\n\
+         {\"answer\": \"the answer, citing excerpts like [2]\", \"citations\": [2]}\n\
+         Rely only on the excerpts. citations are the numbers of the excerpts the answer relies on. \
+         If the excerpts don't answer the question, say so and leave citations empty.\n\n## Excerpts\n",
+    );
+    let budget = max_chars / excerpts.len().max(1);
+    for (i, excerpt) in excerpts.iter().enumerate() {
+        let text: String = excerpt
+            .text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(budget)
+            .collect();
+        prompt.push_str(&format!("[{}] ({}) {}\n", i + 1, excerpt.source, text));
+    }
+    prompt.push_str(&format!("\n## Question\n{}\n", question.trim()));
+    prompt
+}
+
+/// The answer in a model's answer, keeping the citations of the
+/// `excerpt_count` excerpts once each.
+pub fn parse_answer(response: &str, excerpt_count: usize) -> Result<AnswerContent> {
+    #[derive(Deserialize)]
+    struct RawAnswer {
+        answer: String,
+        #[serde(default)]
+        citations: Vec<serde_json::Value>,
+    }
+
+    let raw: RawAnswer = serde_json::from_str(json_object(response)?)
+        .context("the model's answer isn't an answer")?;
+    let mut citations = Vec::new();
+    // small models cite as 2, "2" or "[2]"
+    for citation in raw.citations {
+        let number = match &citation {
+            serde_json::Value::Number(n) => n.as_u64().map(|n| n as usize),
+            serde_json::Value::String(s) => s.trim_matches(|c| c == '[' || c == ']').parse().ok(),
+            _ => None,
+        };
+        if let Some(number) = number {
+            if (1..=excerpt_count).contains(&number) && !citations.contains(&number) {
+                citations.push(number);
+            }
+        }
+    }
+    Ok(AnswerContent {
+        answer: raw.answer.trim().to_string(),
+        citations,
+    })
+}
@@ -0,0 +1,254 @@
+//! At-rest encryption of recordings. Finished video and audio chunks are
+//! encrypted in place with AES-256-GCM, and decrypted to a temporary file
+//! in a private directory under the data dir when they are read. The key
+//! lives in the OS keychain and the same key unlocks the SQLCipher database.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::OnceCell;
+use tempfile::NamedTempFile;
+use tracing::info;
+
+/// Keychain entry holding the key.
+const KEYCHAIN_SERVICE: &str = "screenpipe";
+const KEYCHAIN_ACCOUNT: &str = "at-rest-encryption-key";
+
+/// Encrypted files start with this header, followed by the nonce and the
+/// ciphertext.
+const MAGIC: &[u8; 8] = b"SPENC\x00\x00\x01";
+const NONCE_LEN: usize = 12;
+
+/// Directory under the data dir decrypted recordings are written to.
+const DECRYPTED_DIR: &str = "decrypted";
+
+static MEDIA_CIPHER: OnceCell<MediaCipher> = OnceCell::new();
+static DECRYPT_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// 256-bit key used for both the database and the media files.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    pub fn generate() -> Self {
+        Self(Aes256Gcm::generate_key(OsRng).into())
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let bytes = hex::decode(hex.trim()).context("encryption key is not valid hex")?;
+        let key: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| anyhow!("encryption key must be 32 bytes"))?;
+        Ok(Self(key))
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// Reads the key from the OS keychain, creating and storing a new one on
+/// first use.
+pub fn load_or_create_key() -> Result<EncryptionKey> {
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)
+        .context("failed to open the keychain entry")?;
+    match entry.get_password() {
+        Ok(hex) => EncryptionKey::from_hex(&hex),
+        Err(keyring::Error::NoEntry) => {
+            info!("no encryption key in the keychain, creating one");
+            let key = EncryptionKey::generate();
+            entry
+                .set_password(&key.to_hex())
+                .context("failed to store the encryption key in the keychain")?;
+            Ok(key)
+        }
+        Err(e) => Err(anyhow!(
+            "failed to read the encryption key from the keychain: {}",
+            e
+        )),
+    }
+}
+
+/// Whether `data` starts like an encrypted file.
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Whether the file at `path` is encrypted.
+pub fn is_encrypted_file(path: &Path) -> Result<bool> {
+    use std::io::Read;
+
+    let mut header = [0u8; MAGIC.len()];
+    let mut file = std::fs::File::open(path)?;
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(is_encrypted(&header)),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub struct MediaCipher {
+    cipher: Aes256Gcm,
+}
+
+impl MediaCipher {
+    pub fn new(key: &EncryptionKey) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.0)),
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| anyhow!("failed to encrypt data"))?;
+
+        let mut data = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+        Ok(data)
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if !is_encrypted(data) || data.len() < MAGIC.len() + NONCE_LEN {
+            return Err(anyhow!("data is not encrypted"));
+        }
+        let (nonce, ciphertext) = data[MAGIC.len()..].split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("failed to decrypt data, wrong key or corrupted file"))
+    }
+
+    /// Encrypts the file at `path` in place. The encrypted file is written
+    /// next to it and renamed over it, so readers never see a partial file.
+    /// Returns false if it was already encrypted.
+    pub fn encrypt_file(&self, path: &Path) -> Result<bool> {
+        let plaintext = std::fs::read(path)?;
+        if is_encrypted(&plaintext) {
+            return Ok(false);
+        }
+        let data = self.encrypt(&plaintext)?;
+
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".enc");
+        let tmp_path = PathBuf::from(tmp_path);
+        std::fs::write(&tmp_path, data)?;
+        if let Err(e) = std::fs::rename(&tmp_path, path) {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+        Ok(true)
+    }
+
+    /// Decrypts the file at `path` to a temporary file in `dir` with the same
+    /// extension, removed when dropped.
+    pub fn decrypt_to_temp(&self, path: &Path, dir: &Path) -> Result<NamedTempFile> {
+        let plaintext = self.decrypt(&std::fs::read(path)?)?;
+        let suffix = path
+            .extension()
+            .map(|extension| format!(".{}", extension.to_string_lossy()))
+            .unwrap_or_default();
+        let mut file = tempfile::Builder::new()
+            .prefix("screenpipe-decrypted-")
+            .suffix(&suffix)
+            .tempfile_in(dir)?;
+        std::io::Write::write_all(&mut file, &plaintext)?;
+        Ok(file)
+    }
+}
+
+/// Creates the directory recordings are decrypted to under `data_dir`,
+/// readable by the current user only. Files left there by a previous run
+/// that didn't exit cleanly are removed.
+pub fn prepare_decrypt_dir(data_dir: &Path) -> Result<PathBuf> {
+    let dir = data_dir.join(DECRYPTED_DIR);
+    match std::fs::remove_dir_all(&dir) {
+        Ok(()) => info!("removed recordings left decrypted by a previous run"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e).with_context(|| format!("failed to clear {}", dir.display()));
+        }
+    }
+
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder
+        .create(&dir)
+        .with_context(|| format!("failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Makes `key` the key used to read encrypted recordings in this process,
+/// decrypting them to `decrypt_dir`. Only the first call has an effect.
+pub fn unlock_media(key: &EncryptionKey, decrypt_dir: PathBuf) {
+    if DECRYPT_DIR.set(decrypt_dir).is_err() || MEDIA_CIPHER.set(MediaCipher::new(key)).is_err() {
+        info!("media encryption key was already unlocked");
+    }
+}
+
+/// The cipher of the unlocked key, if any.
+pub fn media_cipher() -> Option<&'static MediaCipher> {
+    MEDIA_CIPHER.get()
+}
+
+/// A recording ready to be read by path, decrypted to a temporary file in
+/// the decrypt dir if it is encrypted.
+pub enum ReadableMedia {
+    Plain(PathBuf),
+    Decrypted(NamedTempFile),
+}
+
+impl ReadableMedia {
+    pub fn path(&self) -> &Path {
+        match self {
+            ReadableMedia::Plain(path) => path,
+            ReadableMedia::Decrypted(file) => file.path(),
+        }
+    }
+
+    /// The path as a string, for passing to ffmpeg.
+    pub fn path_str(&self) -> String {
+        self.path().to_string_lossy().into_owned()
+    }
+}
+
+/// Gives a readable version of the recording at `path`. Plain files are
+/// used as is, encrypted ones need the key to be unlocked.
+pub async fn readable_media(path: impl AsRef<Path>) -> Result<ReadableMedia> {
+    let path = path.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        if !is_encrypted_file(&path)? {
+            return Ok(ReadableMedia::Plain(path));
+        }
+        let cipher = media_cipher().ok_or_else(|| {
+            anyhow!(
+                "{} is encrypted but no encryption key is unlocked",
+                path.display()
+            )
+        })?;
+        let dir = DECRYPT_DIR
+            .get()
+            .ok_or_else(|| anyhow!("no directory to decrypt recordings to"))?;
+        Ok(ReadableMedia::Decrypted(
+            cipher.decrypt_to_temp(&path, dir)?,
+        ))
+    })
+    .await?
+}
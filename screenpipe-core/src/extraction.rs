@@ -0,0 +1,223 @@
+//! Records extracted from recordings by a local LLM, shaped by a JSON schema
+//! the user gives, e.g. the attendees, decisions and action items of a
+//! meeting. Only the part of JSON schema a record needs is checked: `type`,
+//! `properties`, `required`, `items` and `enum`.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::llm_client::{format_sections, json_object, LlmClient};
+
+pub const DEFAULT_EXTRACTION_MODEL: &str = "llama3.2";
+
+/// Characters of recordings given to the model.
+const DEFAULT_MAX_INPUT_CHARS: usize = 24_000;
+/// Times the model is asked before giving up on answers not matching the
+/// schema.
+const MAX_ATTEMPTS: usize = 2;
+
+const TYPES: &[&str] = &[
+    "object", "array", "string", "number", "integer", "boolean", "null",
+];
+
+/// What a record is extracted from, under a heading each.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtractionInput {
+    /// What was recorded, e.g. "a Zoom session from 14:00 to 14:45"
+    pub context: String,
+    pub sections: Vec<(String, Vec<String>)>,
+}
+
+impl ExtractionInput {
+    pub fn is_empty(&self) -> bool {
+        self.sections.iter().all(|(_, lines)| lines.is_empty())
+    }
+}
+
+/// Extracts records with a local LLM.
+pub struct Extractor {
+    llm: LlmClient,
+    max_input_chars: usize,
+}
+
+impl Extractor {
+    pub fn new(llm: LlmClient) -> Self {
+        Extractor {
+            llm,
+            max_input_chars: DEFAULT_MAX_INPUT_CHARS,
+        }
+    }
+
+    pub fn with_max_input_chars(mut self, max_input_chars: usize) -> Self {
+        self.max_input_chars = max_input_chars;
+        self
+    }
+
+    /// Name of the model, stored with the records.
+    pub fn model(&self) -> String {
+        self.llm.model()
+    }
+
+    /// The record following `instructions` and matching `schema`, or `None`
+    /// when the model's answers don't match it.
+    pub async fn extract(
+        &self,
+        instructions: &str,
+        schema: &Value,
+        input: &ExtractionInput,
+    ) -> Result<Option<Value>> {
+        let prompt = extraction_prompt(instructions, schema, input, self.max_input_chars);
+        for attempt in 1..=MAX_ATTEMPTS {
+            let response = self.llm.complete_json(prompt.clone()).await?;
+            match parse_extraction(&response, schema) {
+                Ok(record) => return Ok(Some(record)),
+                Err(e) => warn!(
+                    "extraction answer {}/{} rejected: {}",
+                    attempt, MAX_ATTEMPTS, e
+                ),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Instructions, schema and recordings for the model, each section getting
+/// an equal share of `max_chars`.
+pub fn extraction_prompt(
+    instructions: &str,
+    schema: &Value,
+    input: &ExtractionInput,
+    max_chars: usize,
+) -> String {
+    let mut prompt = format!(
+        "You extract structured data from what a person's computer recorded during {}.\n\
+         {}\n\
+         Answer with a JSON object only, matching this JSON schema:\n{}\n\
+         Use only what the recordings say, leave lists empty and omit optional fields rather than guessing.\n",
+        input.context,
+        instructions.trim(),
+        schema
+    );
+    prompt.push_str(&format_sections(&input.sections, max_chars));
+    prompt
+}
+
+/// The record in a model's answer, checked against `schema`.
+pub fn parse_extraction(response: &str, schema: &Value) -> Result<Value> {
+    let record: Value =
+        serde_json::from_str(json_object(response)?).context("the model's answer isn't JSON")?;
+    validate(&record, schema).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(record)
+}
+
+/// Checks that `schema` describes an object, with known types throughout.
+pub fn check_schema(schema: &Value) -> Result<(), String> {
+    if schema.get("type").and_then(Value::as_str) != Some("object") {
+        return Err("schema must have \"type\": \"object\"".to_string());
+    }
+    check_subschema(schema, "$")
+}
+
+fn check_subschema(schema: &Value, path: &str) -> Result<(), String> {
+    let schema = schema
+        .as_object()
+        .ok_or_else(|| format!("{}: schema must be an object", path))?;
+    let types: Vec<&Value> = match schema.get("type") {
+        None => vec![],
+        Some(Value::Array(types)) => types.iter().collect(),
+        Some(t) => vec![t],
+    };
+    for t in types {
+        if !t.as_str().is_some_and(|t| TYPES.contains(&t)) {
+            return Err(format!("{}: unknown type {}", path, t));
+        }
+    }
+    if let Some(properties) = schema.get("properties") {
+        let properties = properties
+            .as_object()
+            .ok_or_else(|| format!("{}: properties must be an object", path))?;
+        for (name, property) in properties {
+            check_subschema(property, &format!("{}.{}", path, name))?;
+        }
+    }
+    if let Some(required) = schema.get("required") {
+        if !required
+            .as_array()
+            .is_some_and(|required| required.iter().all(Value::is_string))
+        {
+            return Err(format!("{}: required must be a list of names", path));
+        }
+    }
+    if let Some(items) = schema.get("items") {
+        check_subschema(items, &format!("{}[]", path))?;
+    }
+    if let Some(values) = schema.get("enum") {
+        if !values.is_array() {
+            return Err(format!("{}: enum must be a list", path));
+        }
+    }
+    Ok(())
+}
+
+/// Checks `value` against `schema`, the error saying where it doesn't match.
+pub fn validate(value: &Value, schema: &Value) -> Result<(), String> {
+    validate_at(value, schema, "$")
+}
+
+fn validate_at(value: &Value, schema: &Value, path: &str) -> Result<(), String> {
+    if let Some(t) = schema.get("type") {
+        let types: Vec<&str> = match t {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            t => t.as_str().into_iter().collect(),
+        };
+        if !types.iter().any(|t| has_type(value, t)) {
+            return Err(format!("{}: expected {}", path, types.join(" or ")));
+        }
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        if !values.contains(value) {
+            return Err(format!(
+                "{}: {} is not one of {}",
+                path,
+                value,
+                Value::from(values.clone())
+            ));
+        }
+    }
+    if let Value::Object(object) = value {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    return Err(format!("{}: missing {}", path, name));
+                }
+            }
+        }
+        if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+            for (name, property) in properties {
+                if let Some(value) = object.get(name) {
+                    validate_at(value, property, &format!("{}.{}", path, name))?;
+                }
+            }
+        }
+    }
+    if let (Value::Array(items), Some(schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            validate_at(item, schema, &format!("{}[{}]", path, i))?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn has_type(value: &Value, t: &str) -> bool {
+    match t {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
@@ -0,0 +1,153 @@
+//! Whether the user is away from the computer, shared between the capture
+//! pipelines. The server checks it periodically, the screen isn't captured
+//! while the user is away and audio isn't recorded while the screen is
+//! locked or the display sleeps.
+
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleReason {
+    /// No keyboard or mouse input for longer than the idle timeout
+    NoInput,
+    ScreenLocked,
+    DisplayAsleep,
+}
+
+impl IdleReason {
+    /// Whether audio is paused too. A call can go on without any input, so
+    /// audio is only paused when the user can't be at the computer.
+    pub fn pauses_audio(&self) -> bool {
+        !matches!(self, IdleReason::NoInput)
+    }
+}
+
+impl fmt::Display for IdleReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdleReason::NoInput => write!(f, "no_input"),
+            IdleReason::ScreenLocked => write!(f, "screen_locked"),
+            IdleReason::DisplayAsleep => write!(f, "display_asleep"),
+        }
+    }
+}
+
+static IDLE: Lazy<Mutex<Option<IdleReason>>> = Lazy::new(|| Mutex::new(None));
+
+/// Records whether, and why, the user is away.
+pub fn set_idle(reason: Option<IdleReason>) {
+    *IDLE.lock().unwrap_or_else(|e| e.into_inner()) = reason;
+}
+
+/// Why the user is away, `None` while they are active.
+pub fn idle_reason() -> Option<IdleReason> {
+    *IDLE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether the screen isn't captured because the user is away.
+pub fn is_idle() -> bool {
+    idle_reason().is_some()
+}
+
+/// Whether audio isn't recorded because the user is away.
+pub fn is_audio_idle() -> bool {
+    idle_reason().is_some_and(|reason| reason.pauses_audio())
+}
+
+/// Checks whether the user is away now: the screen is locked, the display
+/// sleeps, or there was no input for `timeout`. Blocks while the system is
+/// queried.
+pub fn detect_idle(timeout: Duration) -> Option<IdleReason> {
+    if platform::is_screen_locked() {
+        return Some(IdleReason::ScreenLocked);
+    }
+    if platform::is_display_asleep() {
+        return Some(IdleReason::DisplayAsleep);
+    }
+    match user_idle::UserIdle::get_time() {
+        Ok(idle) if idle.duration() >= timeout => Some(IdleReason::NoInput),
+        Ok(_) => None,
+        Err(e) => {
+            tracing::debug!("failed to get the time since the last input: {:?}", e);
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use core_foundation::base::TCFType;
+    use core_foundation::boolean::CFBoolean;
+    use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+    use core_foundation::string::CFString;
+    use core_graphics::display::CGDisplay;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGSessionCopyCurrentDictionary() -> CFDictionaryRef;
+    }
+
+    pub fn is_screen_locked() -> bool {
+        let session = unsafe { CGSessionCopyCurrentDictionary() };
+        if session.is_null() {
+            return false;
+        }
+        let session: CFDictionary<CFString> =
+            unsafe { CFDictionary::wrap_under_create_rule(session) };
+        session
+            .find(&CFString::from_static_string("CGSSessionScreenIsLocked"))
+            .map(|value| unsafe { CFBoolean::wrap_under_get_rule(*value as _) })
+            .is_some_and(bool::from)
+    }
+
+    pub fn is_display_asleep() -> bool {
+        CGDisplay::main().is_asleep()
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows::Win32::System::StationsAndDesktops::{
+        CloseDesktop, OpenInputDesktop, DESKTOP_CONTROL_FLAGS, DESKTOP_SWITCHDESKTOP,
+    };
+
+    /// The input desktop can't be opened while the lock screen shows.
+    pub fn is_screen_locked() -> bool {
+        match unsafe { OpenInputDesktop(DESKTOP_CONTROL_FLAGS(0), false, DESKTOP_SWITCHDESKTOP) } {
+            Ok(desktop) => {
+                let _ = unsafe { CloseDesktop(desktop) };
+                false
+            }
+            Err(_) => true,
+        }
+    }
+
+    pub fn is_display_asleep() -> bool {
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::Command;
+
+    /// Lock state of the session as reported by logind.
+    pub fn is_screen_locked() -> bool {
+        let Ok(session) = std::env::var("XDG_SESSION_ID") else {
+            return false;
+        };
+        Command::new("loginctl")
+            .args(["show-session", &session, "--property=LockedHint", "--value"])
+            .output()
+            .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "yes")
+    }
+
+    pub fn is_display_asleep() -> bool {
+        false
+    }
+}
@@ -0,0 +1,533 @@
+//! Built-in inference of quantized GGUF models, the format of llama.cpp, so
+//! pipes can use a local LLM without running Ollama. Models come from a small
+//! catalog of instruction tuned Llama models, are downloaded from Hugging
+//! Face in the chosen quantization and run on the CPU or a GPU. Requests and
+//! responses follow OpenAI's chat completions.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Error as E, Result};
+use candle::quantized::gguf_file;
+use candle::{Device, Tensor};
+use candle_transformers::generation::{LogitsProcessor, Sampling};
+use candle_transformers::models::quantized_llama::ModelWeights;
+use hf_hub::api::sync::Api;
+use hf_hub::Cache;
+use serde::{Deserialize, Serialize};
+use tokenizers::Tokenizer;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+pub const DEFAULT_LOCAL_MODEL: &str = "llama3.2-1b";
+
+const DEFAULT_MAX_TOKENS: usize = 1024;
+const DEFAULT_TEMPERATURE: f64 = 0.7;
+const DEFAULT_SEED: u64 = 299792458;
+const REPEAT_PENALTY: f32 = 1.1;
+const REPEAT_LAST_N: usize = 64;
+const EOS_TOKENS: [&str; 2] = ["<|eot_id|>", "<|end_of_text|>"];
+
+/// A model of the catalog, its GGUF files named `{gguf_file}-{QUANT}.gguf`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalModel {
+    pub id: &'static str,
+    pub gguf_repo: &'static str,
+    pub gguf_file: &'static str,
+    /// Repo of the tokenizer, which GGUF repos don't ship as tokenizer.json
+    pub tokenizer_repo: &'static str,
+    /// Tokens of prompt and completion the model handles
+    pub context_length: usize,
+}
+
+pub const LOCAL_MODELS: &[LocalModel] = &[
+    LocalModel {
+        id: "llama3.2-1b",
+        gguf_repo: "bartowski/Llama-3.2-1B-Instruct-GGUF",
+        gguf_file: "Llama-3.2-1B-Instruct",
+        tokenizer_repo: "unsloth/Llama-3.2-1B-Instruct",
+        context_length: 8192,
+    },
+    LocalModel {
+        id: "llama3.2-3b",
+        gguf_repo: "bartowski/Llama-3.2-3B-Instruct-GGUF",
+        gguf_file: "Llama-3.2-3B-Instruct",
+        tokenizer_repo: "unsloth/Llama-3.2-3B-Instruct",
+        context_length: 8192,
+    },
+    LocalModel {
+        id: "llama3.1-8b",
+        gguf_repo: "bartowski/Meta-Llama-3.1-8B-Instruct-GGUF",
+        gguf_file: "Meta-Llama-3.1-8B-Instruct",
+        tokenizer_repo: "unsloth/Meta-Llama-3.1-8B-Instruct",
+        context_length: 8192,
+    },
+];
+
+pub fn local_model(id: &str) -> Option<&'static LocalModel> {
+    LOCAL_MODELS.iter().find(|model| model.id == id)
+}
+
+/// Quantizations of llama.cpp, smaller ones are faster and less accurate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quantization {
+    Q4_0,
+    #[serde(rename = "q4_k_m")]
+    Q4KM,
+    #[serde(rename = "q5_k_m")]
+    Q5KM,
+    Q6K,
+    Q8_0,
+}
+
+pub const QUANTIZATIONS: [Quantization; 5] = [
+    Quantization::Q4_0,
+    Quantization::Q4KM,
+    Quantization::Q5KM,
+    Quantization::Q6K,
+    Quantization::Q8_0,
+];
+
+impl Quantization {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Quantization::Q4_0 => "q4_0",
+            Quantization::Q4KM => "q4_k_m",
+            Quantization::Q5KM => "q5_k_m",
+            Quantization::Q6K => "q6_k",
+            Quantization::Q8_0 => "q8_0",
+        }
+    }
+}
+
+impl FromStr for Quantization {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        QUANTIZATIONS
+            .into_iter()
+            .find(|quantization| quantization.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| anyhow!("unknown quantization: {}", s))
+    }
+}
+
+impl LocalModel {
+    pub fn gguf_filename(&self, quantization: Quantization) -> String {
+        format!(
+            "{}-{}.gguf",
+            self.gguf_file,
+            quantization.as_str().to_uppercase()
+        )
+    }
+
+    /// Whether the model was downloaded in `quantization`.
+    pub fn is_downloaded(&self, quantization: Quantization) -> bool {
+        let cache = Cache::default();
+        cache
+            .model(self.gguf_repo.to_string())
+            .get(&self.gguf_filename(quantization))
+            .is_some()
+            && cache
+                .model(self.tokenizer_repo.to_string())
+                .get("tokenizer.json")
+                .is_some()
+    }
+
+    /// Paths of the weights and the tokenizer, downloaded unless they are
+    /// already. Blocks until the download is done.
+    pub fn download(&self, quantization: Quantization) -> Result<(PathBuf, PathBuf)> {
+        let api = Api::new()?;
+        let weights = api
+            .model(self.gguf_repo.to_string())
+            .get(&self.gguf_filename(quantization))
+            .with_context(|| format!("failed to download {}", self.id))?;
+        let tokenizer = api
+            .model(self.tokenizer_repo.to_string())
+            .get("tokenizer.json")
+            .with_context(|| format!("failed to download the tokenizer of {}", self.id))?;
+        Ok((weights, tokenizer))
+    }
+}
+
+/// Where the model runs. On a GPU the whole model is offloaded to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InferenceDevice {
+    /// The first GPU when screenpipe is built with `metal` or `cuda`, else
+    /// the CPU
+    #[default]
+    Auto,
+    Cpu,
+    /// The GPU with this index, failing without `metal` or `cuda`
+    Gpu(usize),
+}
+
+impl InferenceDevice {
+    fn device(&self) -> Result<Device> {
+        match self {
+            InferenceDevice::Cpu => Ok(Device::Cpu),
+            InferenceDevice::Auto => InferenceDevice::Gpu(0).device().or(Ok(Device::Cpu)),
+            InferenceDevice::Gpu(index) => {
+                if candle::utils::metal_is_available() {
+                    Ok(Device::new_metal(*index)?)
+                } else if candle::utils::cuda_is_available() {
+                    Ok(Device::new_cuda(*index)?)
+                } else {
+                    bail!("no gpu support, build screenpipe with the `metal` or `cuda` feature")
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatCompletionMessage {
+    pub role: String,
+    pub content: String,
+}
+
+/// An OpenAI chat completion request, fields the built-in models don't
+/// support are ignored.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChatCompletionRequest {
+    #[serde(default)]
+    pub model: Option<String>,
+    pub messages: Vec<ChatCompletionMessage>,
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    #[serde(default)]
+    pub max_completion_tokens: Option<usize>,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub top_p: Option<f64>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl ChatCompletionRequest {
+    /// Why the request can't be answered, if it can't.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.messages.is_empty() {
+            return Err("messages can't be empty".to_string());
+        }
+        if let Some(message) = self
+            .messages
+            .iter()
+            .find(|message| !matches!(message.role.as_str(), "system" | "user" | "assistant"))
+        {
+            return Err(format!("unsupported role: {}", message.role));
+        }
+        if let Some(temperature) = self.temperature.filter(|t| !(0.0..=2.0).contains(t)) {
+            return Err(format!(
+                "temperature must be between 0 and 2, got {}",
+                temperature
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChatCompletionChoice {
+    pub index: usize,
+    pub message: ChatCompletionMessage,
+    pub finish_reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChatCompletionUsage {
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub total_tokens: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChatCompletion {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub model: String,
+    pub choices: Vec<ChatCompletionChoice>,
+    pub usage: ChatCompletionUsage,
+}
+
+/// The Llama 3 chat template, ending where the assistant answers.
+pub fn llama3_prompt(messages: &[ChatCompletionMessage]) -> String {
+    let mut prompt = "<|begin_of_text|>".to_string();
+    for message in messages {
+        prompt.push_str(&format!(
+            "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
+            message.role,
+            message.content.trim()
+        ));
+    }
+    prompt.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+    prompt
+}
+
+fn unix_time() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// A model loaded in memory, answering one request at a time.
+pub struct InferenceEngine {
+    model: &'static LocalModel,
+    quantization: Quantization,
+    weights: Mutex<ModelWeights>,
+    tokenizer: Tokenizer,
+    device: Device,
+    eos_tokens: Vec<u32>,
+}
+
+impl InferenceEngine {
+    /// Loads `model`, downloading it first if needed. Blocks for as long.
+    pub fn load(
+        model: &'static LocalModel,
+        quantization: Quantization,
+        device: InferenceDevice,
+    ) -> Result<Self> {
+        let (weights_path, tokenizer_path) = model.download(quantization)?;
+        let device = device.device()?;
+
+        let mut file = std::fs::File::open(&weights_path)?;
+        let content =
+            gguf_file::Content::read(&mut file).map_err(|e| e.with_path(&weights_path))?;
+        let weights = ModelWeights::from_gguf(content, &mut file, &device)?;
+        let tokenizer = Tokenizer::from_file(tokenizer_path).map_err(E::msg)?;
+        let eos_tokens = EOS_TOKENS
+            .iter()
+            .filter_map(|token| tokenizer.token_to_id(token))
+            .collect();
+
+        Ok(InferenceEngine {
+            model,
+            quantization,
+            weights: Mutex::new(weights),
+            tokenizer,
+            device,
+            eos_tokens,
+        })
+    }
+
+    pub fn model(&self) -> &'static LocalModel {
+        self.model
+    }
+
+    pub fn quantization(&self) -> Quantization {
+        self.quantization
+    }
+
+    /// Answers `request`, calling `on_text` with each piece of the answer as
+    /// it is generated, which stops early by returning false, e.g. when the
+    /// client is gone. Blocks until the answer is complete.
+    pub fn complete(
+        &self,
+        request: &ChatCompletionRequest,
+        mut on_text: impl FnMut(&str) -> bool,
+    ) -> Result<ChatCompletion> {
+        let prompt = llama3_prompt(&request.messages);
+        let mut tokens = self
+            .tokenizer
+            .encode(prompt, false)
+            .map_err(E::msg)?
+            .get_ids()
+            .to_vec();
+        let prompt_tokens = tokens.len();
+        if prompt_tokens >= self.model.context_length {
+            bail!(
+                "the prompt is {} tokens, {} handles {}",
+                prompt_tokens,
+                self.model.id,
+                self.model.context_length
+            );
+        }
+        let max_tokens = request
+            .max_completion_tokens
+            .or(request.max_tokens)
+            .unwrap_or(DEFAULT_MAX_TOKENS)
+            .min(self.model.context_length - prompt_tokens);
+
+        let temperature = request.temperature.unwrap_or(DEFAULT_TEMPERATURE);
+        let sampling = match request.top_p {
+            _ if temperature <= 0.0 => Sampling::ArgMax,
+            Some(p) => Sampling::TopP { p, temperature },
+            None => Sampling::All { temperature },
+        };
+        let mut logits_processor =
+            LogitsProcessor::from_sampling(request.seed.unwrap_or(DEFAULT_SEED), sampling);
+
+        let mut weights = self
+            .weights
+            .lock()
+            .map_err(|_| anyhow!("the model crashed on a previous request"))?;
+        let mut answer = String::new();
+        let mut finish_reason = "length";
+        let mut index_pos = 0;
+        for _ in 0..max_tokens {
+            // the whole prompt first, then a token at a time, the rest is in
+            // the kv cache, which starting at 0 clears
+            let context = &tokens[index_pos..];
+            let input = Tensor::new(context, &self.device)?.unsqueeze(0)?;
+            let logits = weights.forward(&input, index_pos)?.squeeze(0)?;
+            index_pos += context.len();
+            let start_at = tokens.len().saturating_sub(REPEAT_LAST_N);
+            let logits = candle_transformers::utils::apply_repeat_penalty(
+                &logits,
+                REPEAT_PENALTY,
+                &tokens[start_at..],
+            )?;
+
+            let next_token = logits_processor.sample(&logits)?;
+            if self.eos_tokens.contains(&next_token) {
+                finish_reason = "stop";
+                break;
+            }
+            tokens.push(next_token);
+
+            let text = self
+                .tokenizer
+                .decode(&tokens[prompt_tokens..], true)
+                .map_err(E::msg)?;
+            // a character can span tokens, wait for all of them
+            if text.len() > answer.len() && !text.ends_with('\u{FFFD}') {
+                let more = on_text(&text[answer.len()..]);
+                answer = text;
+                if !more {
+                    finish_reason = "stop";
+                    break;
+                }
+            }
+        }
+
+        let completion_tokens = tokens.len() - prompt_tokens;
+        let now = unix_time();
+        Ok(ChatCompletion {
+            id: format!("chatcmpl-{:x}", now.as_nanos()),
+            object: "chat.completion".to_string(),
+            created: now.as_secs(),
+            model: self.model.id.to_string(),
+            choices: vec![ChatCompletionChoice {
+                index: 0,
+                message: ChatCompletionMessage {
+                    role: "assistant".to_string(),
+                    content: answer,
+                },
+                finish_reason: finish_reason.to_string(),
+            }],
+            usage: ChatCompletionUsage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            },
+        })
+    }
+}
+
+/// What the built-in inference is doing.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum InferenceStatus {
+    Idle,
+    Loading {
+        model: String,
+        quantization: Quantization,
+    },
+    Ready {
+        model: String,
+        quantization: Quantization,
+    },
+    Failed {
+        model: String,
+        quantization: Quantization,
+        error: String,
+    },
+}
+
+/// The model served to pipes, which can be switched while running.
+pub struct LocalInference {
+    device: InferenceDevice,
+    engine: RwLock<Option<Arc<InferenceEngine>>>,
+    status: Mutex<InferenceStatus>,
+}
+
+impl LocalInference {
+    pub fn new(device: InferenceDevice) -> Self {
+        LocalInference {
+            device,
+            engine: RwLock::new(None),
+            status: Mutex::new(InferenceStatus::Idle),
+        }
+    }
+
+    pub fn status(&self) -> InferenceStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// The model answering requests, `None` until one is loaded.
+    pub fn engine(&self) -> Option<Arc<InferenceEngine>> {
+        self.engine.read().unwrap().clone()
+    }
+
+    /// Starts downloading and loading `model` in `quantization` in the
+    /// background, replacing the model served once it's ready. Fails while
+    /// another model is loading.
+    pub fn load(
+        self: &Arc<Self>,
+        model: &'static LocalModel,
+        quantization: Quantization,
+    ) -> Result<JoinHandle<()>> {
+        {
+            let mut status = self.status.lock().unwrap();
+            if let InferenceStatus::Loading { model, .. } = &*status {
+                bail!("{} is already loading", model);
+            }
+            *status = InferenceStatus::Loading {
+                model: model.id.to_string(),
+                quantization,
+            };
+        }
+        info!(
+            "loading {} ({}) for the local llm api",
+            model.id,
+            quantization.as_str()
+        );
+
+        let inference = self.clone();
+        Ok(tokio::spawn(async move {
+            let device = inference.device;
+            let result = tokio::task::spawn_blocking(move || {
+                InferenceEngine::load(model, quantization, device)
+            })
+            .await
+            .map_err(E::from)
+            .and_then(|result| result);
+
+            let status = match result {
+                Ok(engine) => {
+                    *inference.engine.write().unwrap() = Some(Arc::new(engine));
+                    info!("local llm api is serving {}", model.id);
+                    InferenceStatus::Ready {
+                        model: model.id.to_string(),
+                        quantization,
+                    }
+                }
+                Err(e) => {
+                    error!("failed to load {}: {}", model.id, e);
+                    InferenceStatus::Failed {
+                        model: model.id.to_string(),
+                        quantization,
+                        error: e.to_string(),
+                    }
+                }
+            };
+            *inference.status.lock().unwrap() = status;
+        }))
+    }
+}
@@ -22,9 +22,19 @@ pub mod llama;
 pub use llama::*;
 pub mod pipes;
 pub use pipes::*;
+pub mod wasm_pipe;
+pub mod pipe_bus;
+pub mod pipe_schedule;
+pub mod pipe_limits;
+pub mod pipe_logs;
+pub mod pipe_settings;
+pub mod pipe_signing;
+pub mod pipe_subscriptions;
 mod language;
 #[cfg(feature = "security")]
 pub mod pii_removal;
+#[cfg(feature = "security")]
+pub mod ner;
 
 #[cfg(feature = "mkl")]
 extern crate intel_mkl_src;
@@ -41,3 +51,25 @@ pub use embedding::*;
 
 pub mod operator;
 pub use operator::*;
+
+pub mod encryption;
+
+pub mod idle;
+
+pub mod ocr_mode;
+
+pub mod power;
+
+pub mod privacy;
+
+pub mod llm_client;
+
+pub mod summarization;
+
+pub mod extraction;
+
+pub mod answering;
+
+pub mod inference;
+
+pub mod metrics;
@@ -0,0 +1,162 @@
+//! A local LLM asked for JSON, either a model served by Ollama or, with the
+//! `llm` feature, the built-in one. Used by summaries and extractions.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+
+/// Tokens the built-in model writes at most.
+#[cfg(feature = "llm")]
+const MAX_COMPLETION_TOKENS: usize = 1024;
+
+enum Backend {
+    Ollama {
+        url: String,
+        model: String,
+    },
+    #[cfg(feature = "llm")]
+    Builtin(std::sync::Arc<crate::LLM>),
+}
+
+pub struct LlmClient {
+    backend: Backend,
+    client: reqwest::Client,
+}
+
+impl LlmClient {
+    /// Completes with `model` served by Ollama at `url`.
+    pub fn ollama(url: &str, model: &str) -> Self {
+        LlmClient {
+            backend: Backend::Ollama {
+                url: url.trim_end_matches('/').to_string(),
+                model: model.to_string(),
+            },
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Completes with the built-in model, on a blocking thread.
+    #[cfg(feature = "llm")]
+    pub fn builtin(llm: crate::LLM) -> Self {
+        LlmClient {
+            backend: Backend::Builtin(std::sync::Arc::new(llm)),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Name of the model, stored with what it wrote.
+    pub fn model(&self) -> String {
+        match &self.backend {
+            Backend::Ollama { model, .. } => model.clone(),
+            #[cfg(feature = "llm")]
+            Backend::Builtin(_) => "builtin".to_string(),
+        }
+    }
+
+    /// The model's answer to `prompt`, which asks for JSON.
+    pub async fn complete_json(&self, prompt: String) -> Result<String> {
+        match &self.backend {
+            Backend::Ollama { url, model } => {
+                #[derive(Deserialize)]
+                struct GenerateResponse {
+                    response: String,
+                }
+
+                let response: GenerateResponse = self
+                    .client
+                    .post(format!("{}/api/generate", url))
+                    .json(&json!({
+                        "model": model,
+                        "prompt": prompt,
+                        "format": "json",
+                        "stream": false,
+                    }))
+                    .send()
+                    .await
+                    .with_context(|| format!("ollama is not reachable at {}", url))?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                Ok(response.response)
+            }
+            #[cfg(feature = "llm")]
+            Backend::Builtin(llm) => {
+                let llm = llm.clone();
+                let request = crate::ChatRequest {
+                    messages: vec![crate::ChatMessage {
+                        role: "user".to_string(),
+                        content: prompt,
+                    }],
+                    stream: false,
+                    max_completion_tokens: Some(MAX_COMPLETION_TOKENS),
+                    temperature: Some(0.2),
+                    top_p: None,
+                    top_k: None,
+                    seed: None,
+                };
+                let response = tokio::task::spawn_blocking(move || llm.chat(request)).await??;
+                response
+                    .choices
+                    .into_iter()
+                    .next()
+                    .map(|choice| choice.message.content)
+                    .context("the model gave no answer")
+            }
+        }
+    }
+}
+
+/// The JSON object in a model's answer, which may wrap it in prose or a
+/// code block.
+pub fn json_object(response: &str) -> Result<&str> {
+    let start = response
+        .find('{')
+        .context("no JSON in the model's answer")?;
+    let end = response
+        .rfind('}')
+        .filter(|end| *end > start)
+        .context("no JSON in the model's answer")?;
+    Ok(&response[start..=end])
+}
+
+/// Lines of `lines` fitting in `budget` characters, spread evenly over them
+/// so a long recording isn't cut after its start.
+pub fn sample_lines(lines: &[String], budget: usize) -> Vec<&str> {
+    let total: usize = lines.iter().map(|line| line.len() + 1).sum();
+    if total <= budget {
+        return lines.iter().map(String::as_str).collect();
+    }
+    let step = total.div_ceil(budget.max(1));
+    lines
+        .iter()
+        .step_by(step)
+        .map(String::as_str)
+        .scan(0, |used, line| {
+            *used += line.len() + 1;
+            Some((*used, line))
+        })
+        .take_while(|(used, _)| *used <= budget)
+        .map(|(_, line)| line)
+        .collect()
+}
+
+/// Recordings under a heading each, each non empty section getting an equal
+/// share of `max_chars`.
+pub fn format_sections(sections: &[(String, Vec<String>)], max_chars: usize) -> String {
+    let sections: Vec<&(String, Vec<String>)> = sections
+        .iter()
+        .filter(|(_, lines)| !lines.is_empty())
+        .collect();
+    let budget = max_chars / sections.len().max(1);
+    let mut text = String::new();
+    for (heading, lines) in sections {
+        text.push_str(&format!("\n## {}\n", heading));
+        for line in sample_lines(lines, budget) {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    text
+}
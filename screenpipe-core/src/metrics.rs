@@ -0,0 +1,302 @@
+//! Health of the recorder, served in the Prometheus text format on
+//! `/metrics`. The capture, OCR, transcription and database code update the
+//! metrics below as they go, the server renders them when scraped.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+
+/// Latest observations summaries compute their quantiles from.
+const SUMMARY_WINDOW: usize = 1024;
+/// Quantiles of every summary.
+const QUANTILES: [f64; 3] = [0.5, 0.9, 0.99];
+
+pub static FRAMES_CAPTURED: Lazy<Family<Counter>> = Lazy::new(|| {
+    Family::new(
+        "screenpipe_frames_captured_total",
+        "Frames captured from the screen",
+        &["monitor"],
+    )
+});
+
+pub static CAPTURE_FPS: Lazy<Family<Gauge>> = Lazy::new(|| {
+    Family::new(
+        "screenpipe_capture_fps",
+        "Frames captured per second over the last 30 seconds",
+        &["monitor"],
+    )
+});
+
+pub static FRAMES_DROPPED: Lazy<Family<Counter>> = Lazy::new(|| {
+    Family::new(
+        "screenpipe_frames_dropped_total",
+        "Frames dropped because the video or OCR queue was full",
+        &["monitor", "queue"],
+    )
+});
+
+pub static OCR_QUEUE_DEPTH: Lazy<Family<Gauge>> = Lazy::new(|| {
+    Family::new(
+        "screenpipe_ocr_queue_depth",
+        "Frames waiting to be written to the database after OCR",
+        &["monitor"],
+    )
+});
+
+pub static DEFERRED_OCR_PENDING: Lazy<Family<Gauge>> = Lazy::new(|| {
+    Family::new(
+        "screenpipe_deferred_ocr_pending",
+        "Windows waiting for deferred OCR",
+        &[],
+    )
+});
+
+pub static OCR_DURATION: Lazy<Family<Summary>> = Lazy::new(|| {
+    Family::new(
+        "screenpipe_ocr_duration_seconds",
+        "Time taken to OCR a window",
+        &["engine"],
+    )
+});
+
+pub static TRANSCRIPTION_REALTIME_FACTOR: Lazy<Family<Summary>> = Lazy::new(|| {
+    Family::new(
+        "screenpipe_transcription_realtime_factor",
+        "Time taken to transcribe a chunk divided by its duration, above 1 is behind real time",
+        &["provider"],
+    )
+});
+
+pub static DB_WRITE_DURATION: Lazy<Family<Summary>> = Lazy::new(|| {
+    Family::new(
+        "screenpipe_db_write_duration_seconds",
+        "Time taken by writes of the capture pipelines to the database",
+        &["operation"],
+    )
+});
+
+pub static DISK_USAGE: Lazy<Family<Gauge>> = Lazy::new(|| {
+    Family::new(
+        "screenpipe_disk_usage_bytes",
+        "Size of the screenpipe directory",
+        &[],
+    )
+});
+
+/// Every metric in the Prometheus text format.
+pub fn render() -> String {
+    let mut out = String::new();
+    FRAMES_CAPTURED.encode(&mut out);
+    CAPTURE_FPS.encode(&mut out);
+    FRAMES_DROPPED.encode(&mut out);
+    OCR_QUEUE_DEPTH.encode(&mut out);
+    DEFERRED_OCR_PENDING.encode(&mut out);
+    OCR_DURATION.encode(&mut out);
+    TRANSCRIPTION_REALTIME_FACTOR.encode(&mut out);
+    DB_WRITE_DURATION.encode(&mut out);
+    DISK_USAGE.encode(&mut out);
+    out
+}
+
+/// A metric as written on `/metrics`.
+pub trait Encode: Default {
+    const TYPE: &'static str;
+
+    /// Writes the samples of the metric named `name`, `labels` are its
+    /// labels already formatted, e.g. `monitor="1"`.
+    fn encode(&self, name: &str, labels: &str, out: &mut String);
+}
+
+/// A value that only goes up.
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    pub fn inc_by(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Encode for Counter {
+    const TYPE: &'static str = "counter";
+
+    fn encode(&self, name: &str, labels: &str, out: &mut String) {
+        let _ = writeln!(out, "{}{} {}", name, braced(labels), self.get());
+    }
+}
+
+/// A value that goes up and down.
+#[derive(Debug, Default)]
+pub struct Gauge(AtomicU64);
+
+impl Gauge {
+    pub fn set(&self, value: f64) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+impl Encode for Gauge {
+    const TYPE: &'static str = "gauge";
+
+    fn encode(&self, name: &str, labels: &str, out: &mut String) {
+        let _ = writeln!(out, "{}{} {}", name, braced(labels), self.get());
+    }
+}
+
+/// Count and sum of observations, and quantiles of the latest ones.
+#[derive(Debug, Default)]
+pub struct Summary(Mutex<SummaryWindow>);
+
+#[derive(Debug, Default)]
+struct SummaryWindow {
+    count: u64,
+    sum: f64,
+    latest: VecDeque<f64>,
+}
+
+impl Summary {
+    pub fn observe(&self, value: f64) {
+        let mut window = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        window.count += 1;
+        window.sum += value;
+        if window.latest.len() == SUMMARY_WINDOW {
+            window.latest.pop_front();
+        }
+        window.latest.push_back(value);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).count
+    }
+
+    /// The value `quantile` of the latest observations are below, `None`
+    /// before the first one.
+    pub fn quantile(&self, quantile: f64) -> Option<f64> {
+        let window = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let mut latest: Vec<f64> = window.latest.iter().copied().collect();
+        latest.sort_by(f64::total_cmp);
+        quantile_of(&latest, quantile)
+    }
+}
+
+fn quantile_of(sorted: &[f64], quantile: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+impl Encode for Summary {
+    const TYPE: &'static str = "summary";
+
+    fn encode(&self, name: &str, labels: &str, out: &mut String) {
+        let (count, sum, mut latest) = {
+            let window = self.0.lock().unwrap_or_else(|e| e.into_inner());
+            let latest: Vec<f64> = window.latest.iter().copied().collect();
+            (window.count, window.sum, latest)
+        };
+        latest.sort_by(f64::total_cmp);
+        let separator = if labels.is_empty() { "" } else { "," };
+        for quantile in QUANTILES {
+            let value = quantile_of(&latest, quantile).unwrap_or(f64::NAN);
+            let _ = writeln!(
+                out,
+                "{}{{{}{}quantile=\"{}\"}} {}",
+                name, labels, separator, quantile, value
+            );
+        }
+        let _ = writeln!(out, "{}_sum{} {}", name, braced(labels), sum);
+        let _ = writeln!(out, "{}_count{} {}", name, braced(labels), count);
+    }
+}
+
+/// Metrics of the same name, one per combination of label values.
+pub struct Family<M> {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    metrics: Mutex<BTreeMap<Vec<String>, Arc<M>>>,
+}
+
+impl<M: Encode> Family<M> {
+    pub fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Family {
+            name,
+            help,
+            labels,
+            metrics: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// The metric with these values of the labels of the family, in order.
+    pub fn with_labels(&self, values: &[&str]) -> Arc<M> {
+        debug_assert_eq!(values.len(), self.labels.len(), "{}", self.name);
+        let key: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        self.metrics
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key)
+            .or_default()
+            .clone()
+    }
+
+    /// Every metric of the family, with the values of its labels.
+    pub fn metrics(&self) -> Vec<(Vec<String>, Arc<M>)> {
+        self.metrics
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(values, metric)| (values.clone(), metric.clone()))
+            .collect()
+    }
+
+    pub fn encode(&self, out: &mut String) {
+        let metrics = self.metrics();
+        if metrics.is_empty() {
+            return;
+        }
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} {}", self.name, M::TYPE);
+        for (values, metric) in metrics {
+            let labels = self
+                .labels
+                .iter()
+                .zip(&values)
+                .map(|(label, value)| format!("{}=\"{}\"", label, escape_label_value(value)))
+                .collect::<Vec<_>>()
+                .join(",");
+            metric.encode(self.name, &labels, out);
+        }
+    }
+}
+
+fn braced(labels: &str) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels)
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,229 @@
+//! Named entity recognition with a local BERT model, to find names of
+//! people, organizations and places that no regular expression catches.
+
+use anyhow::{anyhow, Error as E, Result};
+use candle::{DType, Device, Module, Tensor, D};
+use candle_nn::{Linear, VarBuilder};
+use candle_transformers::models::bert::{BertModel, Config};
+use hf_hub::{api::sync::Api, Repo, RepoType};
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+use tracing::warn;
+
+use crate::pii_removal::{PiiDetector, PiiKind, PiiMatch};
+
+/// Model used when no local files are given, trained on CoNLL-2003.
+const NER_MODEL_REPO: &str = "dslim/bert-base-NER";
+/// Longest input of the model, longer lines are cut.
+const MAX_TOKENS: usize = 512;
+
+pub struct NerDetector {
+    model: BertModel,
+    classifier: Linear,
+    tokenizer: Tokenizer,
+    /// Entity kind of each label of the model, `None` for the others
+    labels: Vec<Option<(PiiKind, bool)>>,
+    device: Device,
+}
+
+impl NerDetector {
+    /// Loads the model from `model_dir`, holding `config.json`,
+    /// `tokenizer.json` and `model.safetensors`, or downloads the default one.
+    pub fn new(model_dir: Option<std::path::PathBuf>) -> Result<Self> {
+        let device = Device::Cpu;
+
+        let (config_path, tokenizer_path, weights_path) = match model_dir {
+            Some(dir) => (
+                dir.join("config.json"),
+                dir.join("tokenizer.json"),
+                dir.join("model.safetensors"),
+            ),
+            None => {
+                let api = Api::new()?;
+                let repo = api.repo(Repo::new(NER_MODEL_REPO.to_string(), RepoType::Model));
+                (
+                    repo.get("config.json")?,
+                    repo.get("tokenizer.json")?,
+                    repo.get("model.safetensors")?,
+                )
+            }
+        };
+
+        let config_json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+        let config: Config = serde_json::from_value(config_json.clone())?;
+        let labels = parse_labels(&config_json)?;
+
+        let mut tokenizer = Tokenizer::from_file(tokenizer_path).map_err(E::msg)?;
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            ..Default::default()
+        }));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_TOKENS,
+                ..Default::default()
+            }))
+            .map_err(E::msg)?;
+
+        let vb =
+            unsafe { VarBuilder::from_mmaped_safetensors(&[weights_path], DType::F32, &device)? };
+        let model = BertModel::load(vb.clone(), &config)?;
+        let hidden_size = config_json["hidden_size"]
+            .as_u64()
+            .ok_or_else(|| anyhow!("model config has no hidden_size"))?;
+        let classifier =
+            candle_nn::linear(hidden_size as usize, labels.len(), vb.pp("classifier"))?;
+
+        Ok(Self {
+            model,
+            classifier,
+            tokenizer,
+            labels,
+            device,
+        })
+    }
+
+    /// Entities in `text`, which is split in lines so long OCR output fits in
+    /// the model.
+    pub fn entities(&self, text: &str) -> Result<Vec<PiiMatch>> {
+        let mut lines = Vec::new();
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            if !line.trim().is_empty() {
+                lines.push((offset, line));
+            }
+            offset += line.len();
+        }
+        if lines.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let encodings = self
+            .tokenizer
+            .encode_batch(
+                lines.iter().map(|(_, line)| *line).collect::<Vec<_>>(),
+                true,
+            )
+            .map_err(E::msg)?;
+        let ids = encodings
+            .iter()
+            .map(|encoding| Tensor::new(encoding.get_ids(), &self.device))
+            .collect::<candle::Result<Vec<_>>>()?;
+        let attention_mask = encodings
+            .iter()
+            .map(|encoding| Tensor::new(encoding.get_attention_mask(), &self.device))
+            .collect::<candle::Result<Vec<_>>>()?;
+        let ids = Tensor::stack(&ids, 0)?;
+        let attention_mask = Tensor::stack(&attention_mask, 0)?;
+        let token_type_ids = ids.zeros_like()?;
+
+        let hidden = self
+            .model
+            .forward(&ids, &token_type_ids, Some(&attention_mask))?;
+        let predictions = self
+            .classifier
+            .forward(&hidden)?
+            .argmax(D::Minus1)?
+            .to_vec2::<u32>()?;
+
+        let mut entities = Vec::new();
+        for ((line_offset, _), (encoding, labels)) in
+            lines.iter().zip(encodings.iter().zip(predictions))
+        {
+            entities.extend(self.merge_tokens(
+                *line_offset,
+                encoding.get_word_ids(),
+                encoding.get_offsets(),
+                &labels,
+            ));
+        }
+        Ok(entities)
+    }
+
+    /// Turns per token labels into entity spans. The first token of a word
+    /// decides its label, the others only extend it.
+    fn merge_tokens(
+        &self,
+        line_offset: usize,
+        word_ids: &[Option<u32>],
+        offsets: &[(usize, usize)],
+        labels: &[u32],
+    ) -> Vec<PiiMatch> {
+        let mut entities: Vec<PiiMatch> = Vec::new();
+        let mut previous_word = None;
+        let mut in_entity = false;
+
+        for ((word, (start, end)), label) in word_ids.iter().zip(offsets).zip(labels) {
+            let Some(word) = word else {
+                previous_word = None;
+                in_entity = false;
+                continue;
+            };
+            let (start, end) = (line_offset + start, line_offset + end);
+
+            if previous_word == Some(*word) {
+                if let (true, Some(last)) = (in_entity, entities.last_mut()) {
+                    last.range.end = end;
+                }
+                continue;
+            }
+            previous_word = Some(*word);
+
+            match self.labels.get(*label as usize).copied().flatten() {
+                Some((kind, inside)) => {
+                    match entities.last_mut() {
+                        Some(last) if in_entity && inside && last.kind == kind => {
+                            last.range.end = end
+                        }
+                        _ => entities.push(PiiMatch {
+                            kind,
+                            range: start..end,
+                        }),
+                    }
+                    in_entity = true;
+                }
+                None => in_entity = false,
+            }
+        }
+        entities
+    }
+}
+
+impl PiiDetector for NerDetector {
+    fn detect(&self, text: &str) -> Vec<PiiMatch> {
+        self.entities(text).unwrap_or_else(|e| {
+            warn!("named entity recognition failed: {}", e);
+            Vec::new()
+        })
+    }
+}
+
+/// Maps the `id2label` of the model config, e.g. `B-PER`, to entity kinds
+/// and whether the label continues an entity.
+fn parse_labels(config: &serde_json::Value) -> Result<Vec<Option<(PiiKind, bool)>>> {
+    let id2label = config
+        .get("id2label")
+        .and_then(|labels| labels.as_object())
+        .ok_or_else(|| anyhow!("model config has no id2label"))?;
+
+    let mut labels = vec![None; id2label.len()];
+    for (id, label) in id2label {
+        let id: usize = id.parse()?;
+        let label = label.as_str().unwrap_or_default();
+        let (inside, entity) = match label.split_once('-') {
+            Some(("B", entity)) => (false, entity),
+            Some(("I", entity)) => (true, entity),
+            _ => continue,
+        };
+        let kind = match entity {
+            "PER" => PiiKind::Person,
+            "ORG" => PiiKind::Organization,
+            "LOC" => PiiKind::Location,
+            _ => continue,
+        };
+        if let Some(slot) = labels.get_mut(id) {
+            *slot = Some((kind, inside));
+        }
+    }
+    Ok(labels)
+}
@@ -0,0 +1,44 @@
+//! Whether windows are OCR'd while capturing or queued for a background
+//! worker, shared between the vision pipeline and the server. Queued windows
+//! are OCR'd once the user is idle or the laptop is plugged in.
+
+use std::fmt;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::power::is_ocr_deferred;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrMode {
+    #[default]
+    Realtime,
+    Deferred,
+}
+
+impl fmt::Display for OcrMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrMode::Realtime => write!(f, "realtime"),
+            OcrMode::Deferred => write!(f, "deferred"),
+        }
+    }
+}
+
+static OCR_MODE: Lazy<Mutex<OcrMode>> = Lazy::new(|| Mutex::new(OcrMode::default()));
+
+pub fn set_ocr_mode(mode: OcrMode) {
+    *OCR_MODE.lock().unwrap_or_else(|e| e.into_inner()) = mode;
+}
+
+pub fn ocr_mode() -> OcrMode {
+    *OCR_MODE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether windows captured now are queued instead of OCR'd, in deferred
+/// mode or while saving battery.
+pub fn should_defer_ocr() -> bool {
+    ocr_mode() == OcrMode::Deferred || is_ocr_deferred()
+}
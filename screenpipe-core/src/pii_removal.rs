@@ -1,21 +1,155 @@
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Kind of personal information a detector finds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Email,
+    CreditCard,
+    Ssn,
+    ApiKey,
+    Person,
+    Organization,
+    Location,
+}
+
+impl PiiKind {
+    /// Text the match is replaced with.
+    pub fn placeholder(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "[EMAIL]",
+            PiiKind::CreditCard => "[CREDIT_CARD]",
+            PiiKind::Ssn => "[SSN]",
+            PiiKind::ApiKey => "[API_KEY]",
+            PiiKind::Person => "[PERSON]",
+            PiiKind::Organization => "[ORGANIZATION]",
+            PiiKind::Location => "[LOCATION]",
+        }
+    }
+}
+
+/// Byte range of `text` holding personal information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PiiMatch {
+    pub kind: PiiKind,
+    pub range: Range<usize>,
+}
+
+pub trait PiiDetector: Send + Sync {
+    fn detect(&self, text: &str) -> Vec<PiiMatch>;
+}
 
 lazy_static! {
-    static ref PII_PATTERNS: Vec<(Regex, &'static str)> = vec![
-        (Regex::new(r"\b(?:\d{4}[-\s]?){3}\d{4}\b").unwrap(), "[CREDIT_CARD]"),
-        (Regex::new(r"\b\d{3}-\d{2}-\d{4}\b").unwrap(), "[SSN]"),
-        (Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Z|a-z]{2,}\b").unwrap(), "[EMAIL]"),
-        // add more patterns as needed
+    static ref PII_PATTERNS: Vec<(PiiKind, Regex)> = vec![
+        (PiiKind::CreditCard, Regex::new(r"\b(?:\d{4}[-\s]?){3}\d{4}\b").unwrap()),
+        (PiiKind::Ssn, Regex::new(r"\b\d{3}-\d{2}-\d{4}\b").unwrap()),
+        (PiiKind::Email, Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Z|a-z]{2,}\b").unwrap()),
+        // openai and anthropic
+        (PiiKind::ApiKey, Regex::new(r"\bsk-(?:proj-|ant-)?[A-Za-z0-9_-]{20,}").unwrap()),
+        // aws access key ids
+        (PiiKind::ApiKey, Regex::new(r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b").unwrap()),
+        // github
+        (PiiKind::ApiKey, Regex::new(r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})\b").unwrap()),
+        // slack
+        (PiiKind::ApiKey, Regex::new(r"\bxox[abprs]-[A-Za-z0-9-]{10,}").unwrap()),
+        // google
+        (PiiKind::ApiKey, Regex::new(r"\bAIza[0-9A-Za-z_-]{35}").unwrap()),
+        // stripe
+        (PiiKind::ApiKey, Regex::new(r"\b[spr]k_(?:live|test)_[A-Za-z0-9]{16,}\b").unwrap()),
+        // json web tokens
+        (PiiKind::ApiKey, Regex::new(r"\beyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}").unwrap()),
     ];
+    static ref DEFAULT_REDACTOR: Redactor = Redactor::new(vec![Box::new(RegexDetector::new(&[
+        PiiKind::CreditCard,
+        PiiKind::Ssn,
+        PiiKind::Email,
+    ]))]);
 }
 
-pub fn remove_pii(text: &str) -> String {
-    let mut sanitized = text.to_string();
-    for (pattern, replacement) in PII_PATTERNS.iter() {
-        sanitized = pattern.replace_all(&sanitized, *replacement).to_string();
+/// Finds emails, credit card numbers, SSNs and API keys with regular
+/// expressions.
+pub struct RegexDetector {
+    kinds: Vec<PiiKind>,
+}
+
+impl RegexDetector {
+    pub fn new(kinds: &[PiiKind]) -> Self {
+        Self {
+            kinds: kinds.to_vec(),
+        }
+    }
+}
+
+impl PiiDetector for RegexDetector {
+    fn detect(&self, text: &str) -> Vec<PiiMatch> {
+        PII_PATTERNS
+            .iter()
+            .filter(|(kind, _)| self.kinds.contains(kind))
+            .flat_map(|(kind, pattern)| {
+                pattern.find_iter(text).map(|m| PiiMatch {
+                    kind: *kind,
+                    range: m.range(),
+                })
+            })
+            .collect()
     }
-    sanitized
+}
+
+/// Runs a set of detectors and replaces what they find with placeholders.
+pub struct Redactor {
+    detectors: Vec<Box<dyn PiiDetector>>,
+}
+
+impl Redactor {
+    pub fn new(detectors: Vec<Box<dyn PiiDetector>>) -> Self {
+        Self { detectors }
+    }
+
+    /// Matches of all detectors, sorted and without overlaps. Of two
+    /// overlapping matches the one starting first, or the longer one, wins.
+    pub fn find(&self, text: &str) -> Vec<PiiMatch> {
+        let mut matches: Vec<PiiMatch> = self
+            .detectors
+            .iter()
+            .flat_map(|detector| detector.detect(text))
+            .filter(|m| m.range.start < m.range.end && m.range.end <= text.len())
+            .collect();
+        matches.sort_by_key(|m| (m.range.start, std::cmp::Reverse(m.range.end)));
+
+        let mut kept: Vec<PiiMatch> = Vec::with_capacity(matches.len());
+        for m in matches {
+            if kept
+                .last()
+                .is_none_or(|last| m.range.start >= last.range.end)
+            {
+                kept.push(m);
+            }
+        }
+        kept
+    }
+
+    pub fn contains_pii(&self, text: &str) -> bool {
+        !self.find(text).is_empty()
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut redacted = String::with_capacity(text.len());
+        let mut last = 0;
+        for m in self.find(text) {
+            redacted.push_str(&text[last..m.range.start]);
+            redacted.push_str(m.kind.placeholder());
+            last = m.range.end;
+        }
+        redacted.push_str(&text[last..]);
+        redacted
+    }
+}
+
+pub fn remove_pii(text: &str) -> String {
+    DEFAULT_REDACTOR.redact(text)
 }
 
 #[cfg(test)]
@@ -0,0 +1,153 @@
+//! A bus pipes publish messages on for other pipes, so that they compose: a
+//! meeting detector publishes `meeting-detector/ended`, a notes writer
+//! subscribes to it. Topics are namespaced by the id of a pipe, which can
+//! always publish and subscribe in its own namespace, and elsewhere only on
+//! the topics listed under `bus` in its pipe.json:
+//!
+//! ```json
+//! {
+//!   "bus": {
+//!     "publish": ["notes/*"],
+//!     "subscribe": ["meeting-detector/*"]
+//!   }
+//! }
+//! ```
+//!
+//! A topic ending with `*` covers all the topics starting like it. Pipes
+//! prove which one they are with the token given to them in
+//! `SCREENPIPE_PIPE_TOKEN` when they start.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tokio::sync::broadcast;
+
+pub const PIPE_TOKEN_ENV: &str = "SCREENPIPE_PIPE_TOKEN";
+
+/// Messages kept for subscribers that fall behind.
+const BUS_CAPACITY: usize = 1000;
+
+static BUS_SENDER: Lazy<broadcast::Sender<BusMessage>> =
+    Lazy::new(|| broadcast::channel(BUS_CAPACITY).0);
+
+/// Pipe of each token.
+static PIPE_TOKENS: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(Default::default);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BusMessage {
+    pub topic: String,
+    /// Id of the pipe that published it
+    pub from: String,
+    pub payload: Value,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Topics a pipe may use outside of its own namespace.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BusPermissions {
+    #[serde(default)]
+    pub publish: Vec<String>,
+    #[serde(default)]
+    pub subscribe: Vec<String>,
+}
+
+impl BusPermissions {
+    /// The permissions in `config`, none when it has no `bus`.
+    pub fn from_config(config: &Value) -> Result<Self> {
+        match config.get("bus") {
+            Some(bus) if !bus.is_null() => {
+                let permissions: BusPermissions =
+                    serde_json::from_value(bus.clone()).context("invalid bus permissions")?;
+                for topic in permissions.publish.iter().chain(&permissions.subscribe) {
+                    check_topic(topic, true)?;
+                }
+                Ok(permissions)
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn can_publish(&self, pipe: &str, topic: &str) -> bool {
+        covers(&format!("{}/*", pipe), topic)
+            || self.publish.iter().any(|allowed| covers(allowed, topic))
+    }
+
+    /// Whether `pipe` may subscribe to `topic`, which can end with `*`.
+    pub fn can_subscribe(&self, pipe: &str, topic: &str) -> bool {
+        covers(&format!("{}/*", pipe), topic)
+            || self.subscribe.iter().any(|allowed| covers(allowed, topic))
+    }
+}
+
+/// Checks that `topic` looks like `namespace/name`. When `wildcard`, it can
+/// instead end with `*`, `*` alone covering all the topics.
+pub fn check_topic(topic: &str, wildcard: bool) -> Result<()> {
+    let valid_chars = |s: &str| {
+        s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+    };
+    let valid = match topic.strip_suffix('*') {
+        Some(prefix) if wildcard => valid_chars(prefix) && !prefix.starts_with('/'),
+        _ => {
+            valid_chars(topic)
+                && topic
+                    .split_once('/')
+                    .is_some_and(|(namespace, name)| !namespace.is_empty() && !name.is_empty())
+        }
+    };
+    if !valid || topic.contains("//") || topic.ends_with('/') {
+        anyhow::bail!(
+            "invalid topic \"{}\", it should look like namespace/name",
+            topic
+        );
+    }
+    Ok(())
+}
+
+/// Whether `pattern` covers `topic`: they are equal, or `pattern` ends with
+/// `*` and `topic` starts like it.
+pub fn covers(pattern: &str, topic: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => topic.starts_with(prefix),
+        None => pattern == topic,
+    }
+}
+
+/// A new token for `pipe`, replacing the previous one.
+pub fn issue_token(pipe: &str) -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+    let mut tokens = PIPE_TOKENS.write().unwrap();
+    tokens.retain(|_, owner| owner != pipe);
+    tokens.insert(token.clone(), pipe.to_string());
+    token
+}
+
+/// The pipe `token` was given to.
+pub fn pipe_of_token(token: &str) -> Option<String> {
+    PIPE_TOKENS.read().unwrap().get(token).cloned()
+}
+
+/// Sends `payload` on `topic` from `pipe`, returning how many subscribers
+/// may receive it.
+pub fn publish(pipe: &str, topic: &str, payload: Value) -> usize {
+    BUS_SENDER
+        .send(BusMessage {
+            topic: topic.to_string(),
+            from: pipe.to_string(),
+            payload,
+            timestamp: Utc::now(),
+        })
+        .unwrap_or(0)
+}
+
+/// Receives the messages of all topics as they are published.
+pub fn subscribe() -> broadcast::Receiver<BusMessage> {
+    BUS_SENDER.subscribe()
+}
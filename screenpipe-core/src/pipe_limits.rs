@@ -0,0 +1,83 @@
+//! Resources a pipe may use, from `limits` in its pipe.json, and how pipes
+//! that crash are restarted:
+//!
+//! ```json
+//! {
+//!   "limits": { "max_memory_mb": 512, "max_cpu_percent": 80 }
+//! }
+//! ```
+//!
+//! A pipe over its memory, or over its CPU for a while, is stopped and not
+//! restarted. A pipe that crashes is restarted after
+//! [`restart_backoff`], up to [`MAX_RESTARTS`] times in a row.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+/// Crashes in a row after which a pipe isn't restarted anymore.
+pub const MAX_RESTARTS: u32 = 5;
+
+/// Time a pipe must run for its crashes to stop counting as in a row.
+pub const RESTART_STABLE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PipeLimits {
+    /// Resident memory of the pipe and its child processes
+    #[serde(default)]
+    pub max_memory_mb: Option<u64>,
+    /// CPU of the pipe and its child processes, 100 being a full core
+    #[serde(default)]
+    pub max_cpu_percent: Option<f32>,
+}
+
+impl PipeLimits {
+    /// The limits in `config`, none when it has no `limits`.
+    pub fn from_config(config: &Value) -> Result<Self> {
+        match config.get("limits") {
+            Some(limits) if !limits.is_null() => {
+                serde_json::from_value(limits.clone()).context("invalid pipe limits")
+            }
+            _ => Ok(PipeLimits::default()),
+        }
+    }
+
+    pub fn max_memory_bytes(&self) -> Option<u64> {
+        self.max_memory_mb.map(|mb| mb * 1024 * 1024)
+    }
+
+    /// Why `memory_bytes` is over the limit, if it is.
+    pub fn memory_exceeded(&self, memory_bytes: u64) -> Option<String> {
+        let max = self.max_memory_bytes()?;
+        (memory_bytes > max).then(|| {
+            format!(
+                "used {} MB of memory, over its limit of {} MB",
+                memory_bytes / (1024 * 1024),
+                max / (1024 * 1024)
+            )
+        })
+    }
+
+    /// Why `cpu_percent` is over the limit, if it is.
+    pub fn cpu_exceeded(&self, cpu_percent: f32) -> Option<String> {
+        let max = self.max_cpu_percent?;
+        (cpu_percent > max).then(|| {
+            format!(
+                "used {:.0}% of CPU, over its limit of {:.0}%",
+                cpu_percent, max
+            )
+        })
+    }
+}
+
+/// Wait before restarting a pipe that crashed after `restarts` restarts in a
+/// row: doubling from a second, up to five minutes.
+pub fn restart_backoff(restarts: u32) -> Duration {
+    FIRST_BACKOFF
+        .checked_mul(2u32.saturating_pow(restarts))
+        .map_or(MAX_BACKOFF, |backoff| backoff.min(MAX_BACKOFF))
+}
@@ -0,0 +1,314 @@
+//! Logs of each pipe, kept apart from the screenpipe logs in rotating JSON
+//! lines files at `<screenpipe_dir>/logs/pipes/<pipe>.log`. Lines a pipe
+//! prints as JSON, like pino or bunyan do, keep their level, time and fields.
+//! New entries are also broadcast, see [`subscribe`].
+
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+/// Size after which a log file is rotated.
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Rotated files kept besides the current one.
+const ROTATED_FILES: usize = 2;
+
+static LOG_SENDER: Lazy<broadcast::Sender<PipeLogEntry>> = Lazy::new(|| broadcast::channel(1000).0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PipeLogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for PipeLogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            PipeLogLevel::Debug => "debug",
+            PipeLogLevel::Info => "info",
+            PipeLogLevel::Warn => "warn",
+            PipeLogLevel::Error => "error",
+        })
+    }
+}
+
+impl FromStr for PipeLogLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "trace" | "debug" => Ok(PipeLogLevel::Debug),
+            "info" | "log" => Ok(PipeLogLevel::Info),
+            "warn" | "warning" => Ok(PipeLogLevel::Warn),
+            "error" | "fatal" | "critical" => Ok(PipeLogLevel::Error),
+            _ => anyhow::bail!("unknown log level {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipeLogEntry {
+    pub pipe: String,
+    pub timestamp: DateTime<Utc>,
+    pub level: PipeLogLevel,
+    /// `stdout`, `stderr` or `host` for what a wasm pipe logs
+    pub stream: String,
+    pub message: String,
+    /// Other fields of a JSON line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Value>,
+}
+
+/// Level of a plain line a pipe wrote to stderr, which bun and next.js also
+/// use for progress and warnings.
+pub fn stderr_level(line: &str) -> PipeLogLevel {
+    const INFO_PATTERNS: &[&str] = &[
+        // development related
+        "download",
+        "task dev",
+        "$ next dev",
+        "ready started server",
+        "local:",
+        "webpack is configured",
+        "see instructions",
+        "https://nextjs.org",
+        "⚠ see instructions",
+        "$ next start",
+        "[bun install]",
+        "saved lockfile",
+        "resolved, downloaded",
+        "installing",
+        "successfully installed",
+        "packages installed",
+        "fetching",
+        "resolving",
+        // frontend console patterns
+        "[log]",
+        "console.log",
+        "] ",
+        "›",
+        "<",
+        "warning:",
+        "render@",
+        "webpack",
+        "hmr",
+        "[hmr]",
+    ];
+    const ERROR_PATTERNS: &[&str] = &[
+        "typeerror:",
+        "referenceerror:",
+        "syntaxerror:",
+        "error:",
+        "uncaught",
+        "failed to compile",
+        "enoent",
+        "fatal",
+    ];
+
+    if line.trim().is_empty() || line.contains("console.") {
+        return PipeLogLevel::Info;
+    }
+    let line = line.to_lowercase();
+    if ERROR_PATTERNS.iter().any(|pattern| line.contains(pattern)) {
+        PipeLogLevel::Error
+    } else if INFO_PATTERNS.iter().any(|pattern| line.contains(pattern)) {
+        PipeLogLevel::Info
+    } else {
+        PipeLogLevel::Warn
+    }
+}
+
+/// Level of a JSON line: a name, or a pino number.
+fn json_level(level: &Value) -> Option<PipeLogLevel> {
+    match level {
+        Value::String(name) => name.parse().ok(),
+        Value::Number(n) => Some(match n.as_u64()? {
+            0..=20 => PipeLogLevel::Debug,
+            21..=30 => PipeLogLevel::Info,
+            31..=40 => PipeLogLevel::Warn,
+            _ => PipeLogLevel::Error,
+        }),
+        _ => None,
+    }
+}
+
+/// Time of a JSON line: milliseconds since the epoch or RFC 3339.
+fn json_time(time: &Value) -> Option<DateTime<Utc>> {
+    match time {
+        Value::Number(n) => Utc.timestamp_millis_opt(n.as_i64()?).single(),
+        Value::String(s) => DateTime::parse_from_rfc3339(s)
+            .ok()
+            .map(|time| time.with_timezone(&Utc)),
+        _ => None,
+    }
+}
+
+/// Entry of a line `pipe` wrote to `stream`, logged at `now`.
+pub fn parse_log_line(pipe: &str, stream: &str, line: &str, now: DateTime<Utc>) -> PipeLogEntry {
+    let plain = || PipeLogEntry {
+        pipe: pipe.to_string(),
+        timestamp: now,
+        level: if stream == "stderr" {
+            stderr_level(line)
+        } else {
+            PipeLogLevel::Info
+        },
+        stream: stream.to_string(),
+        message: line.to_string(),
+        fields: None,
+    };
+
+    let Ok(Value::Object(mut object)) = serde_json::from_str::<Value>(line.trim()) else {
+        return plain();
+    };
+    let Some(message) = ["msg", "message"]
+        .iter()
+        .find_map(|key| object.remove(*key))
+    else {
+        return plain();
+    };
+    let level = object.remove("level");
+    let time = ["time", "timestamp"]
+        .iter()
+        .find_map(|key| object.remove(*key));
+    // pino's own fields
+    for key in ["pid", "hostname", "v"] {
+        object.remove(key);
+    }
+    PipeLogEntry {
+        pipe: pipe.to_string(),
+        timestamp: time.as_ref().and_then(json_time).unwrap_or(now),
+        level: level
+            .as_ref()
+            .and_then(json_level)
+            .unwrap_or_else(|| plain().level),
+        stream: stream.to_string(),
+        message: match message {
+            Value::String(message) => message,
+            other => other.to_string(),
+        },
+        fields: (!object.is_empty()).then_some(Value::Object(object)),
+    }
+}
+
+pub fn log_dir(screenpipe_dir: &Path) -> PathBuf {
+    screenpipe_dir.join("logs").join("pipes")
+}
+
+/// Log files of `pipe`, the oldest first.
+fn log_files(screenpipe_dir: &Path, pipe: &str) -> Vec<PathBuf> {
+    let dir = log_dir(screenpipe_dir);
+    let mut files: Vec<PathBuf> = (1..=ROTATED_FILES)
+        .rev()
+        .map(|n| dir.join(format!("{}.{}.log", pipe, n)))
+        .collect();
+    files.push(dir.join(format!("{}.log", pipe)));
+    files
+}
+
+/// Writes the log file of a pipe, rotating it when it gets big.
+pub struct PipeLogger {
+    pipe: String,
+    files: Vec<PathBuf>,
+    file: Mutex<Option<File>>,
+}
+
+impl PipeLogger {
+    pub fn new(screenpipe_dir: &Path, pipe: &str) -> Self {
+        PipeLogger {
+            pipe: pipe.to_string(),
+            files: log_files(screenpipe_dir, pipe),
+            file: Mutex::new(None),
+        }
+    }
+
+    /// Logs a line the pipe wrote to `stream`, returning its entry.
+    pub fn log_line(&self, stream: &str, line: &str) -> PipeLogEntry {
+        let entry = parse_log_line(&self.pipe, stream, line, Utc::now());
+        self.log(&entry);
+        entry
+    }
+
+    pub fn log(&self, entry: &PipeLogEntry) {
+        if let Err(e) = self.write(entry) {
+            warn!("failed to write the log of pipe {}: {}", self.pipe, e);
+        }
+        // nobody listening isn't an error
+        let _ = LOG_SENDER.send(entry.clone());
+    }
+
+    fn write(&self, entry: &PipeLogEntry) -> Result<()> {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let current = self.files.last().expect("a current log file");
+        if file.is_none() {
+            if let Some(dir) = current.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            *file = Some(OpenOptions::new().create(true).append(true).open(current)?);
+        }
+        if file.as_ref().expect("an open log file").metadata()?.len() >= MAX_LOG_BYTES {
+            *file = None;
+            // the oldest is dropped
+            for pair in self.files.windows(2) {
+                if pair[1].exists() {
+                    std::fs::rename(&pair[1], &pair[0])?;
+                }
+            }
+            *file = Some(OpenOptions::new().create(true).append(true).open(current)?);
+        }
+        let file = file.as_mut().expect("an open log file");
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+}
+
+/// Entries of `pipe` logged after `since`, at `min_level` or above, the
+/// latest `limit` of them, oldest first.
+pub fn read_logs(
+    screenpipe_dir: &Path,
+    pipe: &str,
+    since: Option<DateTime<Utc>>,
+    min_level: Option<PipeLogLevel>,
+    limit: usize,
+) -> Result<Vec<PipeLogEntry>> {
+    let mut entries = Vec::new();
+    for path in log_files(screenpipe_dir, pipe) {
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for line in BufReader::new(file).lines() {
+            // a line cut by a crash is skipped
+            let Ok(entry) = serde_json::from_str::<PipeLogEntry>(&line?) else {
+                continue;
+            };
+            if since.is_some_and(|since| entry.timestamp <= since)
+                || min_level.is_some_and(|level| entry.level < level)
+            {
+                continue;
+            }
+            entries.push(entry);
+        }
+    }
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.split_off(skip))
+}
+
+/// Receives the entries of all pipes as they are logged.
+pub fn subscribe() -> broadcast::Receiver<PipeLogEntry> {
+    LOG_SENDER.subscribe()
+}
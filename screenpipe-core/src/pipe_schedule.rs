@@ -0,0 +1,126 @@
+//! When a pipe runs, from its pipe.json. Pipes without a `schedule` or
+//! `triggers` run all the time, the others are started when one of them
+//! fires and run until they exit, or for at most `timeout_secs`:
+//!
+//! ```json
+//! {
+//!   "schedule": "0 18 * * *",
+//!   "triggers": [
+//!     { "type": "meeting_ended" },
+//!     { "type": "keyword", "keyword": "invoice", "app_name": "Mail" }
+//!   ],
+//!   "timeout_secs": 600
+//! }
+//! ```
+//!
+//! `schedule` is a cron expression in local time, with an optional leading
+//! seconds field.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// Event sent when a meeting is detected to start, see
+/// `screenpipe_events::poll_meetings_events`.
+pub const MEETING_STARTED_EVENT: &str = "meeting_started";
+pub const MEETING_ENDED_EVENT: &str = "meeting_ended";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PipeTrigger {
+    MeetingStarted,
+    MeetingEnded,
+    /// Something recorded contains `keyword`, in `app_name` when given
+    Keyword {
+        keyword: String,
+        #[serde(default)]
+        app_name: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PipeSchedule {
+    #[serde(default)]
+    pub schedule: Option<String>,
+    #[serde(default)]
+    pub triggers: Vec<PipeTrigger>,
+    /// Seconds after which a run is stopped
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+impl PipeSchedule {
+    /// The schedule in `config`, `None` for pipes that run all the time.
+    pub fn from_config(config: &Value) -> Result<Option<Self>> {
+        if !is_scheduled(config) {
+            return Ok(None);
+        }
+        let schedule: PipeSchedule =
+            serde_json::from_value(config.clone()).context("invalid pipe schedule")?;
+        if let Some(expression) = &schedule.schedule {
+            parse_cron(expression)?;
+        }
+        if schedule.triggers.iter().any(|trigger| {
+            matches!(trigger, PipeTrigger::Keyword { keyword, .. } if keyword.trim().is_empty())
+        }) {
+            anyhow::bail!("keyword triggers need a keyword");
+        }
+        Ok(Some(schedule))
+    }
+
+    /// Whether `schedule` had a time in `(since, until]`.
+    pub fn is_due<Tz: TimeZone>(&self, since: &DateTime<Tz>, until: &DateTime<Tz>) -> bool {
+        let Some(schedule) = self.schedule.as_deref().and_then(|s| parse_cron(s).ok()) else {
+            return false;
+        };
+        let since = since.with_timezone(&Local);
+        let until = until.with_timezone(&Local);
+        schedule
+            .after(&since)
+            .next()
+            .is_some_and(|next| next <= until)
+    }
+
+    /// Whether one of the triggers fires on the event `name`.
+    pub fn is_triggered_by(&self, name: &str) -> bool {
+        self.triggers.iter().any(|trigger| match trigger {
+            PipeTrigger::MeetingStarted => name == MEETING_STARTED_EVENT,
+            PipeTrigger::MeetingEnded => name == MEETING_ENDED_EVENT,
+            PipeTrigger::Keyword { .. } => false,
+        })
+    }
+
+    /// The `(keyword, app_name)` of the keyword triggers.
+    pub fn keywords(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.triggers.iter().filter_map(|trigger| match trigger {
+            PipeTrigger::Keyword { keyword, app_name } => {
+                Some((keyword.as_str(), app_name.as_deref()))
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Whether the pipe of `config` runs on a schedule or triggers rather than
+/// all the time.
+pub fn is_scheduled(config: &Value) -> bool {
+    config.get("schedule").is_some_and(|s| !s.is_null())
+        || config
+            .get("triggers")
+            .and_then(Value::as_array)
+            .is_some_and(|triggers| !triggers.is_empty())
+}
+
+/// Parses a cron expression, adding the seconds field to the usual five.
+pub fn parse_cron(expression: &str) -> Result<cron::Schedule> {
+    let expression = expression.trim();
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+    cron::Schedule::from_str(&expression)
+        .with_context(|| format!("invalid cron schedule \"{}\"", expression))
+}
@@ -0,0 +1,224 @@
+//! Settings of a pipe described by a JSON schema in its pipe.json, from
+//! which the app builds the settings form:
+//!
+//! ```json
+//! {
+//!   "settings_schema": {
+//!     "type": "object",
+//!     "properties": {
+//!       "interval_minutes": { "type": "integer", "minimum": 1, "default": 5 },
+//!       "model": { "type": "string", "enum": ["llama3.2", "mistral"] }
+//!     },
+//!     "required": ["model"]
+//!   },
+//!   "settings": { "model": "llama3.2" }
+//! }
+//! ```
+//!
+//! Settings are checked against the schema when saved, with all the places
+//! they don't match it. Besides what [`check_schema`] knows, `default`,
+//! `minimum`, `maximum`, `minLength`, `maxLength`, `pattern` and
+//! `additionalProperties: false` are supported.
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+
+use crate::extraction::{check_schema, has_type};
+
+/// Where settings don't match the schema, `path` being like
+/// `$.interval_minutes`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SettingsError {
+    pub path: String,
+    pub message: String,
+}
+
+/// Settings not matching the schema of their pipe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSettings(pub Vec<SettingsError>);
+
+impl fmt::Display for InvalidSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors: Vec<String> = self
+            .0
+            .iter()
+            .map(|e| format!("{}: {}", e.path, e.message))
+            .collect();
+        write!(f, "invalid settings: {}", errors.join(", "))
+    }
+}
+
+impl std::error::Error for InvalidSettings {}
+
+/// The settings schema of `config`, if it has a valid one.
+pub fn settings_schema(config: &Value) -> Result<Option<&Value>, String> {
+    match config.get("settings_schema") {
+        Some(schema) if !schema.is_null() => {
+            check_schema(schema).map_err(|e| format!("invalid settings schema: {}", e))?;
+            Ok(Some(schema))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// `settings` with the defaults of `schema` for what they don't set.
+pub fn with_defaults(settings: &Value, schema: &Value) -> Value {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return settings.clone();
+    };
+    let mut settings = match settings {
+        Value::Object(settings) => settings.clone(),
+        Value::Null => Map::new(),
+        other => return other.clone(),
+    };
+    for (name, property) in properties {
+        match settings.get(name) {
+            Some(value) => {
+                let value = with_defaults(value, property);
+                settings.insert(name.clone(), value);
+            }
+            None => {
+                if let Some(default) = property.get("default") {
+                    settings.insert(name.clone(), default.clone());
+                } else if property.get("properties").is_some() {
+                    let nested = with_defaults(&Value::Null, property);
+                    if nested.as_object().is_some_and(|nested| !nested.is_empty()) {
+                        settings.insert(name.clone(), nested);
+                    }
+                }
+            }
+        }
+    }
+    Value::Object(settings)
+}
+
+/// The settings of `config` with their defaults, checked against its
+/// settings schema. `None` when the pipe has no schema.
+pub fn check_settings(config: &Value) -> Result<Option<Value>, InvalidSettings> {
+    let schema = match settings_schema(config) {
+        Ok(Some(schema)) => schema,
+        Ok(None) => return Ok(None),
+        Err(message) => {
+            return Err(InvalidSettings(vec![SettingsError {
+                path: "$".to_string(),
+                message,
+            }]))
+        }
+    };
+    let settings = with_defaults(config.get("settings").unwrap_or(&Value::Null), schema);
+    let errors = validate_settings(&settings, schema);
+    if errors.is_empty() {
+        Ok(Some(settings))
+    } else {
+        Err(InvalidSettings(errors))
+    }
+}
+
+/// All the places `settings` don't match `schema`.
+pub fn validate_settings(settings: &Value, schema: &Value) -> Vec<SettingsError> {
+    let mut errors = Vec::new();
+    validate_at(settings, schema, "$", &mut errors);
+    errors
+}
+
+fn validate_at(value: &Value, schema: &Value, path: &str, errors: &mut Vec<SettingsError>) {
+    let mut error = |message: String| {
+        errors.push(SettingsError {
+            path: path.to_string(),
+            message,
+        })
+    };
+
+    if let Some(t) = schema.get("type") {
+        let types: Vec<&str> = match t {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            t => t.as_str().into_iter().collect(),
+        };
+        if !types.iter().any(|t| has_type(value, t)) {
+            error(format!("expected {}", types.join(" or ")));
+            // the other keywords would only repeat it
+            return;
+        }
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        if !values.contains(value) {
+            error(format!(
+                "{} is not one of {}",
+                value,
+                Value::from(values.clone())
+            ));
+        }
+    }
+
+    match value {
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < minimum {
+                    error(format!("must be at least {}", minimum));
+                }
+            }
+            if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > maximum {
+                    error(format!("must be at most {}", maximum));
+                }
+            }
+        }
+        Value::String(s) => {
+            let chars = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if chars < min {
+                    error(format!("must be at least {} characters", min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if chars > max {
+                    error(format!("must be at most {} characters", max));
+                }
+            }
+            if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                match Regex::new(pattern) {
+                    Ok(re) if !re.is_match(s) => error(format!("must match {}", pattern)),
+                    Ok(_) => {}
+                    Err(_) => error(format!("invalid pattern {} in the schema", pattern)),
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(item, schema, &format!("{}[{}]", path, i), errors);
+                }
+            }
+        }
+        Value::Object(object) => {
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                for name in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(name) {
+                        errors.push(SettingsError {
+                            path: format!("{}.{}", path, name),
+                            message: "is required".to_string(),
+                        });
+                    }
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, value) in object {
+                let path = format!("{}.{}", path, name);
+                match properties.and_then(|properties| properties.get(name)) {
+                    Some(property) => validate_at(value, property, &path, errors),
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        errors.push(SettingsError {
+                            path,
+                            message: "is not a setting of this pipe".to_string(),
+                        })
+                    }
+                    None => {}
+                }
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,332 @@
+//! Signed pipe packages. A publisher signs the SHA-256 of every file of a
+//! pipe with an ed25519 key, into a `pipe.sig` file at its root:
+//!
+//! ```json
+//! {
+//!   "publisher_key": "<hex ed25519 public key>",
+//!   "files": { "package.json": "<hex sha256>", "src/index.ts": "<hex sha256>" },
+//!   "signature": "<hex ed25519 signature>"
+//! }
+//! ```
+//!
+//! The signed message lists the files like `sha256sum` does, sorted by path.
+//! Files [`should_ignore`] skips when copying a pipe, like `node_modules`,
+//! aren't signed, and aren't extracted from zipped pipes either. Pipes from
+//! URLs or the store must be signed unless installed with `allow_unsigned`.
+//! A first install must be signed with a key the user trusts, listed in
+//! `trusted_publishers.json`, as anyone can generate a key and sign a pipe
+//! with it. Updates must be signed by the publisher of the installed
+//! version. What was checked is recorded under `provenance` in pipe.json.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use ring::digest::{digest, SHA256};
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+
+use crate::pipes::should_ignore;
+
+pub const SIGNATURE_FILE: &str = "pipe.sig";
+/// Hex keys of the publishers the user trusts, in the screenpipe dir
+pub const TRUSTED_PUBLISHERS_FILE: &str = "trusted_publishers.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageSignature {
+    pub publisher_key: String,
+    /// Hex SHA-256 of each file, by path relative to the pipe with `/`
+    pub files: BTreeMap<String, String>,
+    pub signature: String,
+}
+
+/// What was checked when a pipe was installed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub source: String,
+    pub signed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publisher_key: Option<String>,
+    /// Whether the package was signed by the publisher of the installed
+    /// version. False on first installs, which trust the key of the package.
+    #[serde(default)]
+    pub publisher_pinned: bool,
+    /// Whether the package was signed with a key the user trusts.
+    #[serde(default)]
+    pub publisher_trusted: bool,
+    pub installed_at: DateTime<Utc>,
+}
+
+/// A new signing key: the secret seed and the public key, in hex.
+pub fn generate_key() -> Result<(String, String)> {
+    let mut seed = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut seed);
+    let key_pair = Ed25519KeyPair::from_seed_unchecked(&seed)
+        .map_err(|e| anyhow::anyhow!("invalid key: {}", e))?;
+    Ok((hex::encode(seed), hex::encode(key_pair.public_key())))
+}
+
+fn signed_message(files: &BTreeMap<String, String>) -> String {
+    files
+        .iter()
+        .map(|(path, sha256)| format!("{}  {}\n", sha256, path))
+        .collect()
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    hex::encode(digest(&SHA256, bytes))
+}
+
+/// SHA-256 of the files of the pipe at `dir`, without its signature.
+pub fn digest_dir(dir: &Path) -> Result<BTreeMap<String, String>> {
+    fn walk(dir: &Path, prefix: &str, files: &mut BTreeMap<String, String>) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if should_ignore(&entry.file_name()) {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = format!("{}{}", prefix, name);
+            if entry.file_type()?.is_dir() {
+                walk(&entry.path(), &format!("{}/", path), files)?;
+            } else if path != SIGNATURE_FILE {
+                files.insert(path, sha256(&std::fs::read(entry.path())?));
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = BTreeMap::new();
+    walk(dir, "", &mut files)?;
+    Ok(files)
+}
+
+/// The signature of the pipe at `dir`, if it has one.
+pub fn read_signature(dir: &Path) -> Result<Option<PackageSignature>> {
+    let path = dir.join(SIGNATURE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let signature = std::fs::read_to_string(&path)?;
+    Ok(Some(
+        serde_json::from_str(&signature).context("invalid pipe signature")?,
+    ))
+}
+
+/// Path within the pipe of the zip entry `name`, or `None` for the files
+/// [`should_ignore`] skips, which are neither signed nor extracted.
+pub fn zip_entry_path(name: &str) -> Option<&str> {
+    let path = name.trim_start_matches("./");
+    if path
+        .split('/')
+        .any(|component| should_ignore(std::ffi::OsStr::new(component)))
+    {
+        return None;
+    }
+    Some(path)
+}
+
+/// SHA-256 of the files of a zipped pipe, and its signature if it has one.
+pub fn digest_zip(zip_path: &Path) -> Result<(BTreeMap<String, String>, Option<PackageSignature>)> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(zip_path)?)?;
+    let mut files = BTreeMap::new();
+    let mut signature = None;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if file.enclosed_name().is_none() {
+            anyhow::bail!("{} is outside of the pipe", file.name());
+        }
+        if file.is_dir() {
+            continue;
+        }
+        let Some(path) = zip_entry_path(file.name()).map(str::to_string) else {
+            continue;
+        };
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        if path == SIGNATURE_FILE {
+            signature = Some(serde_json::from_slice(&bytes).context("invalid pipe signature")?);
+        } else {
+            files.insert(path, sha256(&bytes));
+        }
+    }
+    Ok((files, signature))
+}
+
+/// Signs `files` with the hex secret `seed` of the publisher.
+pub fn sign_files(files: BTreeMap<String, String>, seed: &str) -> Result<PackageSignature> {
+    let seed = hex::decode(seed.trim()).context("the signing key isn't hex")?;
+    let key_pair = Ed25519KeyPair::from_seed_unchecked(&seed)
+        .map_err(|e| anyhow::anyhow!("invalid signing key: {}", e))?;
+    let signature = key_pair.sign(signed_message(&files).as_bytes());
+    Ok(PackageSignature {
+        publisher_key: hex::encode(key_pair.public_key()),
+        files,
+        signature: hex::encode(signature),
+    })
+}
+
+/// Signs the pipe at `dir`, writing its `pipe.sig`.
+pub fn sign_package(dir: &Path, seed: &str) -> Result<PackageSignature> {
+    let signature = sign_files(digest_dir(dir)?, seed)?;
+    std::fs::write(
+        dir.join(SIGNATURE_FILE),
+        serde_json::to_string_pretty(&signature)?,
+    )?;
+    Ok(signature)
+}
+
+/// Checks that `signature` is valid and covers exactly `files`.
+pub fn verify_files(files: &BTreeMap<String, String>, signature: &PackageSignature) -> Result<()> {
+    let key = hex::decode(&signature.publisher_key).context("invalid publisher key")?;
+    let signed = hex::decode(&signature.signature).context("invalid signature")?;
+    UnparsedPublicKey::new(&ED25519, key)
+        .verify(signed_message(&signature.files).as_bytes(), &signed)
+        .map_err(|_| {
+            anyhow::anyhow!("the signature of the pipe doesn't match its publisher key")
+        })?;
+
+    for (path, sha256) in files {
+        match signature.files.get(path) {
+            Some(signed) if signed == sha256 => {}
+            Some(_) => anyhow::bail!("{} was modified after the pipe was signed", path),
+            None => anyhow::bail!("{} isn't signed", path),
+        }
+    }
+    if let Some(path) = signature
+        .files
+        .keys()
+        .find(|path| !files.contains_key(*path))
+    {
+        anyhow::bail!("{} is missing from the pipe", path);
+    }
+    Ok(())
+}
+
+/// Publisher key of the installed version of a pipe, from its pipe.json.
+pub fn installed_publisher_key(config: &Value) -> Option<String> {
+    config
+        .get("provenance")?
+        .get("publisher_key")?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Publisher keys the user trusts, from the `trusted_publishers.json` of
+/// `screenpipe_dir`.
+pub fn trusted_publisher_keys(screenpipe_dir: &Path) -> Result<Vec<String>> {
+    let path = screenpipe_dir.join(TRUSTED_PUBLISHERS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(&std::fs::read_to_string(&path)?).context("invalid trusted publishers")
+}
+
+/// Adds the hex public `key` to the publisher keys the user trusts.
+pub fn trust_publisher(screenpipe_dir: &Path, key: &str) -> Result<()> {
+    let key = key.trim().to_lowercase();
+    if hex::decode(&key).map(|bytes| bytes.len()).ok() != Some(32) {
+        anyhow::bail!("{} isn't an ed25519 public key", key);
+    }
+    let mut keys = trusted_publisher_keys(screenpipe_dir)?;
+    if !keys.contains(&key) {
+        keys.push(key);
+        std::fs::create_dir_all(screenpipe_dir)?;
+        std::fs::write(
+            screenpipe_dir.join(TRUSTED_PUBLISHERS_FILE),
+            serde_json::to_string_pretty(&keys)?,
+        )?;
+    }
+    Ok(())
+}
+
+/// Checks a package about to be installed from `source`. Pipes from URLs or
+/// the store must be signed, by the publisher of the installed version if
+/// there is one and with one of `trusted_keys` otherwise, unless
+/// `allow_unsigned`.
+pub fn check_package(
+    source: &str,
+    files: &BTreeMap<String, String>,
+    signature: Option<&PackageSignature>,
+    requires_signature: bool,
+    installed_key: Option<&str>,
+    trusted_keys: &[String],
+    allow_unsigned: bool,
+) -> Result<Provenance> {
+    let publisher_key = match signature {
+        Some(signature) => {
+            // a signature that doesn't match is refused even when unsigned
+            // pipes are allowed
+            verify_files(files, signature)?;
+            Some(signature.publisher_key.clone())
+        }
+        None => None,
+    };
+
+    check_publisher(
+        installed_key,
+        publisher_key.as_deref(),
+        trusted_keys,
+        requires_signature,
+        allow_unsigned,
+    )?;
+
+    Ok(Provenance {
+        source: source.to_string(),
+        signed: publisher_key.is_some(),
+        publisher_pinned: publisher_key.is_some() && publisher_key.as_deref() == installed_key,
+        publisher_trusted: publisher_key
+            .as_ref()
+            .is_some_and(|key| trusted_keys.contains(key)),
+        publisher_key,
+        installed_at: Utc::now(),
+    })
+}
+
+/// Checks that a package signed by `publisher_key` may replace the version
+/// signed by `installed_key`. Without an installed version, the package is
+/// treated as unsigned unless its key is one of `trusted_keys`.
+pub fn check_publisher(
+    installed_key: Option<&str>,
+    publisher_key: Option<&str>,
+    trusted_keys: &[String],
+    requires_signature: bool,
+    allow_unsigned: bool,
+) -> Result<()> {
+    if allow_unsigned {
+        return Ok(());
+    }
+    match (installed_key, publisher_key) {
+        (Some(installed), Some(key)) if installed != key => anyhow::bail!(
+            "the pipe is signed by another publisher than the installed version, install it with allow_unsigned to override"
+        ),
+        (Some(_), None) => anyhow::bail!(
+            "the installed version of the pipe is signed but this one isn't, install it with allow_unsigned to override"
+        ),
+        (None, Some(key)) if requires_signature && !trusted_keys.iter().any(|trusted| trusted == key) => {
+            anyhow::bail!(
+                "the pipe is signed by a publisher you don't trust, trust its key with `screenpipe pipe trust` or install it with allow_unsigned to override"
+            )
+        }
+        (None, None) if requires_signature => {
+            anyhow::bail!("the pipe isn't signed, install it with allow_unsigned to override")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Records `provenance` under `provenance` in the pipe.json of `pipe_dir`.
+pub async fn record_provenance(pipe_dir: &Path, provenance: &Provenance) -> Result<()> {
+    let pipe_json_path = pipe_dir.join("pipe.json");
+    let mut config: Value = if pipe_json_path.exists() {
+        serde_json::from_str(&tokio::fs::read_to_string(&pipe_json_path).await?)?
+    } else {
+        serde_json::json!({})
+    };
+    config["provenance"] = serde_json::to_value(provenance)?;
+    tokio::fs::write(&pipe_json_path, serde_json::to_string_pretty(&config)?).await?;
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Data a pipe subscribes to in its pipe.json, pushed to it as it's
+//! recorded instead of the pipe polling `/search`:
+//!
+//! ```json
+//! {
+//!   "subscriptions": [
+//!     { "type": "transcript", "pattern": "(?i)invoice|receipt" },
+//!     { "type": "frame", "app_name": "Slack", "window_name": "general" }
+//!   ]
+//! }
+//! ```
+//!
+//! A pipe with subscriptions gets the path of a local socket in
+//! `SCREENPIPE_EVENTS_SOCKET` (a named pipe on Windows). Each matching event
+//! is written to every connection to it as a line of JSON, like
+//! `{"name":"transcript_indexed","data":{...}}`.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Event sent when a frame is stored, see
+/// `screenpipe_events::FRAME_INDEXED_EVENT`.
+pub const FRAME_INDEXED_EVENT: &str = "frame_indexed";
+pub const TRANSCRIPT_INDEXED_EVENT: &str = "transcript_indexed";
+
+pub const EVENTS_SOCKET_ENV: &str = "SCREENPIPE_EVENTS_SOCKET";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PipeSubscription {
+    /// Transcripts matching the regex `pattern`
+    Transcript { pattern: String },
+    /// Frames of `app_name`, whose window name contains `window_name` when
+    /// given
+    Frame {
+        app_name: String,
+        #[serde(default)]
+        window_name: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Transcript(Regex),
+    Frame {
+        app_name: String,
+        window_name: Option<String>,
+    },
+}
+
+/// The subscriptions of a pipe, ready to match events.
+#[derive(Debug, Clone)]
+pub struct PipeSubscriptions {
+    matchers: Vec<Matcher>,
+}
+
+impl PipeSubscriptions {
+    /// The subscriptions in `config`, `None` for pipes without any.
+    pub fn from_config(config: &Value) -> Result<Option<Self>> {
+        let Some(subscriptions) = config.get("subscriptions").filter(|s| !s.is_null()) else {
+            return Ok(None);
+        };
+        let subscriptions: Vec<PipeSubscription> =
+            serde_json::from_value(subscriptions.clone()).context("invalid pipe subscriptions")?;
+        if subscriptions.is_empty() {
+            return Ok(None);
+        }
+
+        let matchers = subscriptions
+            .into_iter()
+            .map(|subscription| match subscription {
+                PipeSubscription::Transcript { pattern } => Regex::new(&pattern)
+                    .map(Matcher::Transcript)
+                    .with_context(|| format!("invalid transcript pattern \"{}\"", pattern)),
+                PipeSubscription::Frame {
+                    app_name,
+                    window_name,
+                } => {
+                    if app_name.trim().is_empty() {
+                        anyhow::bail!("frame subscriptions need an app_name");
+                    }
+                    Ok(Matcher::Frame {
+                        app_name,
+                        window_name: window_name.map(|name| name.to_lowercase()),
+                    })
+                }
+            })
+            .collect::<Result<_>>()?;
+        Ok(Some(Self { matchers }))
+    }
+
+    /// Whether the event `name` with `data` is one the pipe subscribed to.
+    pub fn matches(&self, name: &str, data: &Value) -> bool {
+        let field = |key: &str| data.get(key).and_then(Value::as_str).unwrap_or_default();
+        self.matchers.iter().any(|matcher| match matcher {
+            Matcher::Transcript(pattern) => {
+                name == TRANSCRIPT_INDEXED_EVENT && pattern.is_match(field("transcription"))
+            }
+            Matcher::Frame {
+                app_name,
+                window_name,
+            } => {
+                name == FRAME_INDEXED_EVENT
+                    && field("app_name").eq_ignore_ascii_case(app_name)
+                    && window_name
+                        .as_ref()
+                        .is_none_or(|window| field("window_name").to_lowercase().contains(window))
+            }
+        })
+    }
+}
+
+/// Where the events of `pipe` are served: a socket in the pipe's folder, or
+/// a named pipe on Windows.
+pub fn events_socket_path(screenpipe_dir: &Path, pipe: &str) -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(format!(r"\\.\pipe\screenpipe-{}-events", pipe))
+    } else {
+        screenpipe_dir.join("pipes").join(pipe).join(".events.sock")
+    }
+}
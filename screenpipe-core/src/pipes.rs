@@ -13,7 +13,7 @@ use tokio::sync::watch;
 use anyhow::Result;
 use std::fs;
 use std::path::Path;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tracing::{debug, error, info, warn};
 use url::Url;
 use which::which;
@@ -24,6 +24,14 @@ use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 
 use crate::pick_unused_port;
+use crate::pipe_bus::{issue_token, PIPE_TOKEN_ENV};
+use crate::pipe_logs::{stderr_level, PipeLogLevel, PipeLogger};
+use crate::pipe_settings::check_settings;
+use crate::pipe_signing::{
+    check_package, digest_dir, digest_zip, installed_publisher_key, read_signature,
+    record_provenance, zip_entry_path,
+};
+use crate::pipe_subscriptions::{events_socket_path, PipeSubscriptions, EVENTS_SOCKET_ENV};
 use once_cell::sync::Lazy;
 
 // Add near other imports
@@ -33,13 +41,16 @@ use rand::{thread_rng, Rng};
 use reqwest_middleware::reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
 use reqwest_middleware::reqwest::Client;
 use reqwest_middleware::ClientBuilder;
-use std::collections::HashSet;
 use std::str::FromStr;
+use std::sync::Arc;
 
 // Add at top of file with other imports
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
 
+/// Set to `1` for pipes run by `screenpipe pipe dev`.
+pub const DEV_ENV: &str = "SCREENPIPE_DEV";
+
 #[derive(Clone, Debug, Copy)]
 pub enum PipeState {
     Port(u16),
@@ -374,6 +385,7 @@ pub async fn run_pipe(
     })?;
     let pipe_dir = screenpipe_dir.join("pipes").join(pipe);
     let pipe_json_path = pipe_dir.join("pipe.json");
+    let logger = Arc::new(PipeLogger::new(&screenpipe_dir, pipe));
     let package_json_path = pipe_dir.join("package.json");
 
     debug!(
@@ -394,6 +406,9 @@ pub async fn run_pipe(
     };
 
     // Check if pipe is still enabled
+    let mut settings = None;
+    let mut subscribed = false;
+    let mut dev = false;
     if pipe_json_path.exists() {
         debug!("checking if pipe is enabled from: {:?}", pipe_json_path);
         let pipe_json = tokio::fs::read_to_string(&pipe_json_path).await?;
@@ -408,6 +423,19 @@ pub async fn run_pipe(
             anyhow::bail!("pipe is disabled");
         }
         debug!("pipe {} is enabled, continuing", pipe);
+
+        settings = match check_settings(&pipe_config) {
+            Ok(settings) => settings,
+            Err(e) => {
+                warn!("[{}] {}", pipe, e);
+                None
+            }
+        };
+        subscribed = matches!(PipeSubscriptions::from_config(&pipe_config), Ok(Some(_)));
+        dev = pipe_config
+            .get("dev")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
     }
 
     // Prepare environment variables
@@ -418,10 +446,26 @@ pub async fn run_pipe(
         screenpipe_dir.to_str().unwrap().to_string(),
     ));
     env_vars.push(("PIPE_ID".to_string(), pipe.to_string()));
+    env_vars.push((PIPE_TOKEN_ENV.to_string(), issue_token(pipe)));
     env_vars.push((
         "PIPE_DIR".to_string(),
         pipe_dir.to_str().unwrap().to_string(),
     ));
+    if let Some(settings) = settings {
+        env_vars.push(("PIPE_SETTINGS".to_string(), settings.to_string()));
+    }
+    if dev {
+        // run by `screenpipe pipe dev`, pipes can log more
+        env_vars.push((DEV_ENV.to_string(), "1".to_string()));
+    }
+    if subscribed {
+        env_vars.push((
+            EVENTS_SOCKET_ENV.to_string(),
+            events_socket_path(&screenpipe_dir, pipe)
+                .to_string_lossy()
+                .into_owned(),
+        ));
+    }
 
     if is_nextjs {
         debug!(
@@ -556,8 +600,12 @@ pub async fn run_pipe(
             env_vars.push(("PORT".to_string(), port.to_string()));
         }
 
-        // Try to build the Next.js project
-        let build_status = try_build_nextjs(&pipe_dir, &bun_path).await?;
+        // Try to build the Next.js project, dev mode reloads on its own
+        let build_status = if dev {
+            BuildStatus::NotStarted
+        } else {
+            try_build_nextjs(&pipe_dir, &bun_path).await?
+        };
         let build_success = matches!(build_status, BuildStatus::Success);
 
         if pipe_json_path.exists() {
@@ -602,7 +650,9 @@ pub async fn run_pipe(
         if build_success {
             command.arg("start");
         } else {
-            info!("[{}] falling back to dev mode due to build failure", pipe);
+            if !dev {
+                info!("[{}] falling back to dev mode due to build failure", pipe);
+            }
             command.arg("dev");
         }
 
@@ -617,7 +667,7 @@ pub async fn run_pipe(
         let mut child = command.spawn()?;
 
         debug!("[{}] streaming logs for next.js pipe", pipe);
-        stream_logs(pipe, &mut child).await?;
+        stream_logs(pipe, &mut child, Some(logger.clone())).await?;
 
         let child_pid = child.id().expect("Failed to get child PID") as u32;
         let parent_pid = std::process::id();
@@ -659,117 +709,54 @@ pub async fn run_pipe(
         .spawn()?;
 
     // Stream logs
-    stream_logs(pipe, &mut child).await?;
+    stream_logs(pipe, &mut child, Some(logger.clone())).await?;
 
     let child_id = child.id().unwrap();
     Ok((child, PipeState::Pid(child_id as i32))) // Return 0 or handle port differently for non-Next.js projects
 }
 
-async fn stream_logs(pipe: &str, child: &mut tokio::process::Child) -> Result<()> {
+async fn stream_logs(
+    pipe: &str,
+    child: &mut tokio::process::Child,
+    logger: Option<Arc<PipeLogger>>,
+) -> Result<()> {
     let stdout = child.stdout.take().expect("failed to get stdout");
     let stderr = child.stderr.take().expect("failed to get stderr");
 
-    let pipe_clone = pipe.to_string();
-
-    // Spawn tasks to handle stdout and stderr
-    let _stdout_handle = tokio::spawn(async move {
-        let reader = BufReader::new(stdout);
-        let mut lines = reader.lines();
-        while let Ok(Some(line)) = lines.next_line().await {
-            info!("[{}] {}", pipe_clone, line);
-        }
-    });
-
-    let pipe_clone = pipe.to_string();
-
-    let _stderr_handle = tokio::spawn(async move {
-        // Create static HashMaps for efficient lookups
-        static INFO_PATTERNS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
-            let mut set = HashSet::new();
-            // Development related
-            set.insert("Download");
-            set.insert("Task dev");
-            set.insert("$ next dev");
-            set.insert("ready started server");
-            set.insert("Local:");
-            set.insert("Webpack is configured");
-            set.insert("See instructions");
-            set.insert("https://nextjs.org");
-            set.insert("⚠ See instructions");
-            set.insert("$ next start");
-            set.insert("[bun install]");
-            set.insert("Saved lockfile");
-            set.insert("Resolved, downloaded");
-            set.insert("Installing");
-            set.insert("Successfully installed");
-            set.insert("packages installed");
-            set.insert("Fetching");
-            set.insert("Resolving");
-            // Frontend console patterns
-            set.insert("[LOG]");
-            set.insert("console.log");
-            set.insert("] ");
-            set.insert("›");
-            set.insert("<");
-            set.insert("Warning:");
-            set.insert("render@");
-            set.insert("webpack");
-            set.insert("HMR");
-            set.insert("[HMR]");
-            set
-        });
-
-        static ERROR_PATTERNS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
-            let mut set = HashSet::new();
-            set.insert("TypeError:");
-            set.insert("ReferenceError:");
-            set.insert("SyntaxError:");
-            set.insert("Error:");
-            set.insert("Uncaught");
-            set.insert("Failed to compile");
-            set.insert("ENOENT");
-            set.insert("FATAL");
-            set
-        });
-
-        let reader = BufReader::new(stderr);
-        let mut lines = reader.lines();
-
-        while let Ok(Some(line)) = lines.next_line().await {
-            let line_lower = line.to_lowercase(); // Convert once for case-insensitive matching
-
-            // Quick checks first
-            if line.trim().is_empty() || line.contains("console.") {
-                info!("[{}] {}", pipe_clone, line);
-                continue;
-            }
-
-            // Check for error patterns first (higher priority)
-            if ERROR_PATTERNS
-                .iter()
-                .any(|&pattern| line_lower.contains(&pattern.to_lowercase()))
-            {
-                error!("[{}] {}", pipe_clone, line);
-                sentry::capture_message(
-                    &format!("[{}] {}", pipe_clone, line),
-                    sentry::Level::Error,
-                );
-                continue;
-            }
-
-            // Then check for info patterns
-            if INFO_PATTERNS
-                .iter()
-                .any(|&pattern| line_lower.contains(&pattern.to_lowercase()))
-            {
-                info!("[{}] {}", pipe_clone, line);
-                continue;
+    for (stream, reader) in [
+        (
+            "stdout",
+            Box::new(stdout) as Box<dyn AsyncRead + Send + Unpin>,
+        ),
+        ("stderr", Box::new(stderr)),
+    ] {
+        let pipe = pipe.to_string();
+        let logger = logger.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let level = match &logger {
+                    Some(logger) => logger.log_line(stream, &line).level,
+                    None if stream == "stderr" => stderr_level(&line),
+                    None => PipeLogLevel::Info,
+                };
+                match level {
+                    PipeLogLevel::Debug => debug!("[{}] {}", pipe, line),
+                    PipeLogLevel::Info => info!("[{}] {}", pipe, line),
+                    PipeLogLevel::Warn => warn!("[{}] {}", pipe, line),
+                    PipeLogLevel::Error => {
+                        error!("[{}] {}", pipe, line);
+                        if stream == "stderr" {
+                            sentry::capture_message(
+                                &format!("[{}] {}", pipe, line),
+                                sentry::Level::Error,
+                            );
+                        }
+                    }
+                }
             }
-
-            // Default to warning for unknown patterns
-            warn!("[{}] {}", pipe_clone, line);
-        }
-    });
+        });
+    }
 
     info!("pipe execution completed successfully [{}]", pipe);
     Ok(())
@@ -789,7 +776,7 @@ async fn retry_install(bun_path: &Path, dest_dir: &Path, max_retries: u32) -> Re
             .spawn()?;
 
         // Stream logs for npm install
-        if let Ok(()) = stream_logs("bun install", &mut install_child).await {
+        if let Ok(()) = stream_logs("bun install", &mut install_child, None).await {
             let status = install_child.wait().await?;
             if status.success() {
                 return Ok(());
@@ -813,7 +800,15 @@ async fn retry_install(bun_path: &Path, dest_dir: &Path, max_retries: u32) -> Re
     Err(last_error.unwrap_or_else(|| anyhow::anyhow!("installation failed")))
 }
 
-pub async fn download_pipe(source: &str, screenpipe_dir: PathBuf) -> anyhow::Result<PathBuf> {
+/// Installs the pipe at `source`, a GitHub URL or a local folder. Pipes from
+/// URLs must be signed with one of `trusted_keys` or by the publisher of the
+/// installed version unless `allow_unsigned`, see [`crate::pipe_signing`].
+pub async fn download_pipe(
+    source: &str,
+    screenpipe_dir: PathBuf,
+    trusted_keys: &[String],
+    allow_unsigned: bool,
+) -> anyhow::Result<PathBuf> {
     info!("Processing pipe from source: {}", source);
 
     let pipe_name = sanitize_pipe_name(Path::new(source).to_str().unwrap());
@@ -868,6 +863,29 @@ pub async fn download_pipe(source: &str, screenpipe_dir: PathBuf) -> anyhow::Res
         error!("Failed to download pipe: {}", e);
     }
 
+    let requires_signature = Url::parse(source).is_ok_and(|url| url.host_str().is_some());
+    let installed_key = existing_config.as_ref().and_then(installed_publisher_key);
+    let provenance = match read_signature(&temp_dir).and_then(|signature| {
+        check_package(
+            source,
+            &digest_dir(&temp_dir)?,
+            signature.as_ref(),
+            requires_signature,
+            installed_key.as_deref(),
+            trusted_keys,
+            allow_unsigned,
+        )
+    }) {
+        Ok(provenance) => provenance,
+        Err(e) => {
+            if temp_dir.exists() {
+                tokio::fs::remove_dir_all(&temp_dir).await?;
+            }
+            error!("refusing to install pipe from {}: {}", source, e);
+            return Err(e.context("failed to verify pipe"));
+        }
+    };
+
     // If download successful, move temp dir to final location
     if dest_dir.exists() {
         tokio::fs::remove_dir_all(&dest_dir).await?;
@@ -960,6 +978,8 @@ pub async fn download_pipe(source: &str, screenpipe_dir: PathBuf) -> anyhow::Res
         }
     }
 
+    record_provenance(&dest_dir, &provenance).await?;
+
     info!("pipe copied successfully to: {:?}", dest_dir);
     Ok(dest_dir)
 }
@@ -1007,7 +1027,7 @@ fn copy_dir_all_boxed(
     Box::pin(copy_dir_all(src, dst))
 }
 
-fn should_ignore(file_name: &std::ffi::OsStr) -> bool {
+pub(crate) fn should_ignore(file_name: &std::ffi::OsStr) -> bool {
     let ignore_list = [
         "node_modules",
         ".git",
@@ -1617,10 +1637,15 @@ fn is_port_available(port: u16) -> bool {
     TcpListener::bind(("127.0.0.1", port)).is_ok()
 }
 
+/// Installs a zipped pipe from the store, which must be signed with one of
+/// `trusted_keys` or by the publisher of the installed version unless
+/// `allow_unsigned`, see [`crate::pipe_signing`].
 pub async fn download_pipe_private(
     pipe_name: &str,
     source: &str,
     screenpipe_dir: PathBuf,
+    trusted_keys: &[String],
+    allow_unsigned: bool,
 ) -> anyhow::Result<PathBuf> {
     info!("processing private pipe from zip: {}", source);
 
@@ -1719,8 +1744,36 @@ pub async fn download_pipe_private(
         return Err(anyhow::anyhow!(err_msg));
     }
 
-    // Update status before extraction
+    // Verify the package before extracting it
     let temp_pipe_json = temp_dir.join("pipe.json");
+    let installed_key = match tokio::fs::read_to_string(dest_dir.join("pipe.json")).await {
+        Ok(config) => serde_json::from_str::<Value>(&config)
+            .ok()
+            .and_then(|config| installed_publisher_key(&config)),
+        Err(_) => None,
+    };
+    let provenance = match digest_zip(&temp_zip).and_then(|(files, signature)| {
+        check_package(
+            source,
+            &files,
+            signature.as_ref(),
+            true,
+            installed_key.as_deref(),
+            trusted_keys,
+            allow_unsigned,
+        )
+    }) {
+        Ok(provenance) => provenance,
+        Err(e) => {
+            let err_msg = format!("Failed to verify pipe: {}", e);
+            error!("{}", err_msg);
+            update_build_status(&temp_pipe_json, "error", "verifying", Some(&err_msg)).await?;
+            cleanup_temp(&temp_dir, &temp_zip).await?;
+            return Err(anyhow::anyhow!(err_msg));
+        }
+    };
+
+    // Update status before extraction
     update_build_status(&temp_pipe_json, "in_progress", "extracting", None).await?;
 
     // Unzip the file
@@ -1737,14 +1790,18 @@ pub async fn download_pipe_private(
             let mut file = archive.by_index(i)?;
             let name = file.name().to_string();
 
-            // Check for zip slip vulnerability
-            if name.contains("..") {
+            // Check for zip slip vulnerability, absolute names included
+            if name.contains("..") || file.enclosed_name().is_none() {
                 return Err(anyhow::anyhow!(
                     "Invalid zip file: potential path traversal attack"
                 ));
             }
 
-            let outpath = temp_dir_path.join(&name);
+            // only what was verified is extracted
+            let Some(path) = zip_entry_path(&name) else {
+                continue;
+            };
+            let outpath = temp_dir_path.join(path);
             if name.ends_with('/') {
                 std::fs::create_dir_all(&outpath)?;
             } else {
@@ -1845,6 +1902,8 @@ pub async fn download_pipe_private(
         update_build_status(&final_pipe_json, "success", "completed", None).await?;
     }
 
+    record_provenance(&dest_dir, &provenance).await?;
+
     info!("pipe downloaded and set up successfully at: {:?}", dest_dir);
     Ok(dest_dir)
 }
@@ -0,0 +1,122 @@
+//! Power profile shared between the capture pipelines. On battery, the
+//! screen is captured less often, OCR waits until the laptop is plugged in
+//! and a smaller transcription model is used. Audio can also be paused when
+//! the battery runs low.
+
+use std::fmt;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerProfile {
+    #[default]
+    Normal,
+    LowPower,
+}
+
+impl fmt::Display for PowerProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PowerProfile::Normal => write!(f, "normal"),
+            PowerProfile::LowPower => write!(f, "low_power"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    /// Charge of the battery, `None` without a battery
+    pub battery_percent: Option<f32>,
+    pub profile: PowerProfile,
+    /// Whether OCR waits until the computer is plugged in
+    pub ocr_deferred: bool,
+    /// Whether audio isn't recorded because the battery is low
+    pub audio_paused: bool,
+}
+
+/// When to save power. Thresholds are the battery charge, in percent, at or
+/// below which a saving applies while on battery, 0 never applies it and 100
+/// always does on battery.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerSettings {
+    pub low_power_percent: u8,
+    pub pause_audio_percent: u8,
+    /// Whether the low power profile may defer OCR. Frames must be OCR'd
+    /// right away to be blurred, and images waiting for OCR aren't encrypted.
+    pub defer_ocr: bool,
+}
+
+impl PowerSettings {
+    pub fn status(&self, on_battery: bool, battery_percent: Option<f32>) -> PowerStatus {
+        // an unknown charge only counts as low for thresholds of 100
+        let percent = battery_percent.unwrap_or(100.0);
+        let below = |threshold: u8| on_battery && threshold > 0 && percent <= threshold as f32;
+        let low_power = below(self.low_power_percent);
+        PowerStatus {
+            on_battery,
+            battery_percent,
+            profile: if low_power {
+                PowerProfile::LowPower
+            } else {
+                PowerProfile::Normal
+            },
+            ocr_deferred: low_power && self.defer_ocr,
+            audio_paused: below(self.pause_audio_percent),
+        }
+    }
+}
+
+static POWER: Lazy<Mutex<PowerStatus>> = Lazy::new(|| Mutex::new(PowerStatus::default()));
+
+pub fn set_power_status(status: PowerStatus) {
+    *POWER.lock().unwrap_or_else(|e| e.into_inner()) = status;
+}
+
+pub fn power_status() -> PowerStatus {
+    *POWER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether capture runs with the low power profile.
+pub fn is_low_power() -> bool {
+    power_status().profile == PowerProfile::LowPower
+}
+
+/// Whether OCR is skipped until the computer is plugged in.
+pub fn is_ocr_deferred() -> bool {
+    power_status().ocr_deferred
+}
+
+/// Whether audio isn't recorded because the battery is low.
+pub fn is_audio_paused_on_battery() -> bool {
+    power_status().audio_paused
+}
+
+/// Whether the computer runs on battery, with the charge in percent. `None`
+/// if the batteries can't be read, e.g. on desktops.
+pub fn read_battery() -> Option<(bool, Option<f32>)> {
+    use starship_battery::units::ratio::percent;
+    use starship_battery::{Manager, State};
+
+    let batteries: Vec<_> = Manager::new()
+        .ok()?
+        .batteries()
+        .ok()?
+        .filter_map(Result::ok)
+        .collect();
+    if batteries.is_empty() {
+        return None;
+    }
+    let on_battery = batteries
+        .iter()
+        .any(|battery| battery.state() == State::Discharging);
+    let charge = batteries
+        .iter()
+        .map(|battery| battery.state_of_charge().get::<percent>())
+        .sum::<f32>()
+        / batteries.len() as f32;
+    Some((on_battery, Some(charge)))
+}
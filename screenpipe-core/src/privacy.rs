@@ -0,0 +1,56 @@
+//! State of the privacy blocklist shared between the capture pipelines. The
+//! vision pipeline reports which monitors show a blocked app or site, audio
+//! is not recorded while any of them does.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+/// Name of the event sent when the blocklist starts or stops hiding a window.
+pub const PRIVACY_BLOCK_EVENT: &str = "privacy_block";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrivacyBlockEvent {
+    pub monitor_id: u32,
+    /// `true` when capture stops, `false` when it resumes
+    pub blocked: bool,
+    /// Focused app when capture stopped
+    pub app_name: String,
+    /// Blocklist pattern that matched
+    pub pattern: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Monitors showing a blocked window, with the pattern that matched it.
+static BLOCKED_MONITORS: Lazy<Mutex<HashMap<u32, String>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Records which pattern, if any, blocks the focused window on a monitor.
+pub fn set_capture_blocked(monitor_id: u32, pattern: Option<String>) {
+    let mut monitors = BLOCKED_MONITORS.lock().unwrap_or_else(|e| e.into_inner());
+    match pattern {
+        Some(pattern) => monitors.insert(monitor_id, pattern),
+        None => monitors.remove(&monitor_id),
+    };
+}
+
+/// Whether a blocked app or site is focused on any monitor.
+pub fn is_capture_blocked() -> bool {
+    !BLOCKED_MONITORS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_empty()
+}
+
+/// The pattern blocking capture, on the lowest monitor id if several are.
+pub fn capture_blocked_by() -> Option<String> {
+    BLOCKED_MONITORS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .min_by_key(|(monitor_id, _)| **monitor_id)
+        .map(|(_, pattern)| pattern.clone())
+}
@@ -0,0 +1,124 @@
+//! Summaries of a day or a week of recordings written by a local LLM, either
+//! a model served by Ollama or, with the `llm` feature, the built-in one. The
+//! model is asked for JSON with a short summary, the topics, the action items
+//! and the people mentioned.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::llm_client::{format_sections, json_object, LlmClient};
+
+pub use crate::llm_client::DEFAULT_OLLAMA_URL;
+pub const DEFAULT_SUMMARY_MODEL: &str = "llama3.2";
+
+/// Characters of recordings given to the model, small local models have a
+/// context of a few thousand tokens.
+const DEFAULT_MAX_INPUT_CHARS: usize = 24_000;
+
+/// What a summary is made of, under a heading each, e.g. the transcripts
+/// and the screen text of a day, or the summaries of the days of a week.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SummaryInput {
+    /// The period summarized, e.g. "Tuesday 22 April 2025"
+    pub period: String,
+    pub sections: Vec<(String, Vec<String>)>,
+}
+
+impl SummaryInput {
+    pub fn is_empty(&self) -> bool {
+        self.sections.iter().all(|(_, lines)| lines.is_empty())
+    }
+}
+
+/// What the model made of a period.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SummaryContent {
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    #[serde(default)]
+    pub action_items: Vec<String>,
+    #[serde(default)]
+    pub people: Vec<String>,
+}
+
+/// Writes summaries with a local LLM.
+pub struct Summarizer {
+    llm: LlmClient,
+    max_input_chars: usize,
+}
+
+impl Summarizer {
+    /// Summarizes with `model` served by Ollama at `url`.
+    pub fn ollama(url: &str, model: &str) -> Self {
+        Summarizer::new(LlmClient::ollama(url, model))
+    }
+
+    /// Summarizes with the built-in model, on a blocking thread.
+    #[cfg(feature = "llm")]
+    pub fn builtin(llm: crate::LLM) -> Self {
+        Summarizer::new(LlmClient::builtin(llm))
+    }
+
+    pub fn new(llm: LlmClient) -> Self {
+        Summarizer {
+            llm,
+            max_input_chars: DEFAULT_MAX_INPUT_CHARS,
+        }
+    }
+
+    pub fn with_max_input_chars(mut self, max_input_chars: usize) -> Self {
+        self.max_input_chars = max_input_chars;
+        self
+    }
+
+    /// Name of the model, stored with the summaries.
+    pub fn model(&self) -> String {
+        self.llm.model()
+    }
+
+    pub async fn summarize(&self, input: &SummaryInput) -> Result<SummaryContent> {
+        let prompt = summary_prompt(input, self.max_input_chars);
+        let response = self.llm.complete_json(prompt).await?;
+        parse_summary(&response)
+    }
+}
+
+/// Instructions and recordings for the model, each section getting an equal
+/// share of `max_chars`.
+pub fn summary_prompt(input: &SummaryInput, max_chars: usize) -> String {
+    let mut prompt = format!(
+        "You summarize what a person did and discussed during {}, from what their computer recorded.\n\
+         Answer with JSON only, in this shape:\n\
+         {{\"summary\": \"a few sentences\", \"topics\": [\"...\"], \"action_items\": [\"...\"], \"people\": [\"...\"]}}\n\
+         action_items are tasks someone committed to or was asked to do. people are the names of people mentioned. \
+         Leave a list empty rather than guessing.\n",
+        input.period
+    );
+    prompt.push_str(&format_sections(&input.sections, max_chars));
+    prompt
+}
+
+/// The summary in a model's answer, which may wrap the JSON in prose or a
+/// code block.
+pub fn parse_summary(response: &str) -> Result<SummaryContent> {
+    let mut content: SummaryContent = serde_json::from_str(json_object(response)?)
+        .context("the model's answer isn't a summary")?;
+
+    content.summary = content.summary.trim().to_string();
+    for list in [
+        &mut content.topics,
+        &mut content.action_items,
+        &mut content.people,
+    ] {
+        let mut items: Vec<String> = Vec::new();
+        for item in list.iter().map(|item| item.trim()) {
+            if !item.is_empty() && !items.iter().any(|i| i.eq_ignore_ascii_case(item)) {
+                items.push(item.to_string());
+            }
+        }
+        *list = items;
+    }
+    Ok(content)
+}
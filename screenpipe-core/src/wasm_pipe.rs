@@ -0,0 +1,423 @@
+//! Pipes compiled to WebAssembly components, run in-process by wasmtime
+//! instead of as bun processes. A wasm pipe only reaches what its pipe.json
+//! grants it:
+//!
+//! ```json
+//! {
+//!   "enabled": true,
+//!   "runtime": "wasm",
+//!   "module": "pipe.wasm",
+//!   "capabilities": {
+//!     "search": true,
+//!     "notifications": true,
+//!     "network": ["api.openai.com", "*.slack.com"]
+//!   }
+//! }
+//! ```
+//!
+//! The component imports the screenpipe api of `wit/pipe.wit`, stable within
+//! a version of the `screenpipe:pipe` package, and the host checks every call
+//! against the capabilities. Beyond them the pipe gets stdout and stderr,
+//! no files, environment or sockets. Running them needs the `wasm` feature.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Component, Path, PathBuf};
+use url::Url;
+
+use crate::pipe_limits::PipeLimits;
+
+pub const DEFAULT_WASM_MODULE: &str = "pipe.wasm";
+
+/// What a wasm pipe may use, all denied unless declared.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PipeCapabilities {
+    /// Search the recordings through `/search`
+    #[serde(default)]
+    pub search: bool,
+    /// Show desktop notifications
+    #[serde(default)]
+    pub notifications: bool,
+    /// Hosts it can make http requests to, `*.example.com` matches the
+    /// subdomains of example.com
+    #[serde(default)]
+    pub network: Vec<String>,
+}
+
+impl PipeCapabilities {
+    pub fn allows_host(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_lowercase();
+        self.network.iter().any(|allowed| {
+            let allowed = allowed.trim().to_lowercase();
+            match allowed.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+                None => host == allowed,
+            }
+        })
+    }
+
+    /// Whether the pipe can make an http request to `url`.
+    pub fn allows_url(&self, url: &Url) -> bool {
+        matches!(url.scheme(), "http" | "https")
+            && url.host_str().is_some_and(|host| self.allows_host(host))
+    }
+
+    /// Parses `url`, checking the pipe can make a request to it.
+    pub fn check_url(&self, url: &str) -> Result<Url, String> {
+        let parsed = Url::parse(url).map_err(|e| format!("invalid url {}: {}", url, e))?;
+        if self.allows_url(&parsed) {
+            Ok(parsed)
+        } else {
+            Err(format!(
+                "{} isn't in the network capabilities of the pipe",
+                parsed.host_str().unwrap_or(url)
+            ))
+        }
+    }
+}
+
+/// Wasm runtime part of a pipe.json.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WasmPipeManifest {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Component to run, relative to the pipe directory
+    #[serde(default = "default_module")]
+    pub module: String,
+    #[serde(default)]
+    pub capabilities: PipeCapabilities,
+    /// Only `max_memory_mb` applies, to the memories of the component
+    #[serde(default)]
+    pub limits: PipeLimits,
+}
+
+fn default_module() -> String {
+    DEFAULT_WASM_MODULE.to_string()
+}
+
+impl WasmPipeManifest {
+    /// The wasm runtime part of `config`, `None` for pipes run by bun.
+    pub fn from_config(config: &Value) -> Result<Option<Self>> {
+        if !is_wasm_pipe(config) {
+            return Ok(None);
+        }
+        let manifest: WasmPipeManifest =
+            serde_json::from_value(config.clone()).context("invalid wasm pipe manifest")?;
+        Ok(Some(manifest))
+    }
+
+    /// Path of the component, which must be inside `pipe_dir`.
+    pub fn module_path(&self, pipe_dir: &Path) -> Result<PathBuf> {
+        let module = Path::new(&self.module);
+        if !module
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            anyhow::bail!("module {} must be a path inside the pipe", self.module);
+        }
+        Ok(pipe_dir.join(module))
+    }
+}
+
+pub fn is_wasm_pipe(config: &Value) -> bool {
+    config.get("runtime").and_then(Value::as_str) == Some("wasm")
+}
+
+/// The wasm manifest in the pipe.json of `pipe_dir`, `None` when it has none
+/// or the pipe is run by bun.
+pub async fn read_manifest(pipe_dir: &Path) -> Result<Option<WasmPipeManifest>> {
+    let config_path = pipe_dir.join("pipe.json");
+    if !config_path.exists() {
+        return Ok(None);
+    }
+    let config: Value = serde_json::from_str(&tokio::fs::read_to_string(&config_path).await?)?;
+    WasmPipeManifest::from_config(&config)
+}
+
+/// Where the host sends the calls of wasm pipes.
+#[derive(Debug, Clone)]
+pub struct WasmPipeHost {
+    /// Screenpipe api, e.g. http://localhost:3030
+    pub api_url: String,
+    /// Notification server of the app, e.g. http://localhost:11435/notify
+    pub notification_url: String,
+}
+
+/// A running wasm pipe.
+pub struct WasmPipe {
+    #[cfg(feature = "wasm")]
+    engine: wasmtime::Engine,
+    task: tokio::task::JoinHandle<Result<()>>,
+}
+
+impl WasmPipe {
+    /// Interrupts the pipe, [`WasmPipe::wait`] then returns an error.
+    pub fn stop(&self) {
+        #[cfg(feature = "wasm")]
+        self.engine.increment_epoch();
+    }
+
+    /// Waits for `run` of the pipe to return.
+    pub async fn wait(&mut self) -> Result<()> {
+        (&mut self.task).await?
+    }
+}
+
+/// Starts the wasm pipe `pipe` of `screenpipe_dir` described by `manifest`.
+#[cfg(feature = "wasm")]
+pub fn run_wasm_pipe(
+    pipe: &str,
+    screenpipe_dir: &Path,
+    manifest: WasmPipeManifest,
+    host: WasmPipeHost,
+) -> Result<WasmPipe> {
+    if !manifest.enabled {
+        anyhow::bail!("pipe is disabled");
+    }
+    let module_path = manifest.module_path(&screenpipe_dir.join("pipes").join(pipe))?;
+    runtime::start(
+        pipe,
+        module_path,
+        manifest.capabilities,
+        manifest.limits,
+        host,
+        crate::pipe_logs::PipeLogger::new(screenpipe_dir, pipe),
+    )
+}
+
+#[cfg(not(feature = "wasm"))]
+pub fn run_wasm_pipe(
+    pipe: &str,
+    _screenpipe_dir: &Path,
+    _manifest: WasmPipeManifest,
+    _host: WasmPipeHost,
+) -> Result<WasmPipe> {
+    anyhow::bail!(
+        "pipe {} is a wasm pipe, which needs a build with the `wasm` feature",
+        pipe
+    )
+}
+
+#[cfg(feature = "wasm")]
+mod runtime {
+    use super::{PipeCapabilities, PipeLimits, WasmPipe, WasmPipeHost};
+    use crate::pipe_logs::{PipeLogEntry, PipeLogLevel, PipeLogger};
+    use anyhow::Result;
+    use chrono::Utc;
+    use std::path::PathBuf;
+    use tracing::{debug, error, info, warn};
+    use wasmtime::component::{Component, Linker, ResourceTable};
+    use wasmtime::{Config, Engine, Store, StoreLimits, StoreLimitsBuilder};
+    use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiView};
+
+    wasmtime::component::bindgen!({
+        path: "wit",
+        world: "pipe",
+    });
+
+    use screenpipe::pipe::host::{self, HttpRequest, HttpResponse, LogLevel, SearchQuery};
+
+    struct HostState {
+        pipe: String,
+        capabilities: PipeCapabilities,
+        host: WasmPipeHost,
+        client: reqwest::Client,
+        runtime: tokio::runtime::Handle,
+        wasi: WasiCtx,
+        table: ResourceTable,
+        limits: StoreLimits,
+        logger: PipeLogger,
+    }
+
+    impl WasiView for HostState {
+        fn table(&mut self) -> &mut ResourceTable {
+            &mut self.table
+        }
+
+        fn ctx(&mut self) -> &mut WasiCtx {
+            &mut self.wasi
+        }
+    }
+
+    fn failed(e: impl std::fmt::Display) -> host::Error {
+        host::Error::Failed(e.to_string())
+    }
+
+    impl host::Host for HostState {
+        fn search(&mut self, query: SearchQuery) -> Result<String, host::Error> {
+            if !self.capabilities.search {
+                return Err(host::Error::Denied(
+                    "the pipe has no search capability".to_string(),
+                ));
+            }
+            let params: Vec<(&str, String)> = [
+                ("q", query.q),
+                ("content_type", query.content_type),
+                ("app_name", query.app_name),
+                ("window_name", query.window_name),
+                ("start_time", query.start_time),
+                ("end_time", query.end_time),
+                ("limit", query.limit.map(|limit| limit.to_string())),
+                ("offset", query.offset.map(|offset| offset.to_string())),
+            ]
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .collect();
+            let request = self
+                .client
+                .get(format!("{}/search", self.host.api_url))
+                .query(&params);
+            self.runtime
+                .block_on(async { request.send().await?.error_for_status()?.text().await })
+                .map_err(failed)
+        }
+
+        fn notify(&mut self, title: String, body: String) -> Result<(), host::Error> {
+            if !self.capabilities.notifications {
+                return Err(host::Error::Denied(
+                    "the pipe has no notifications capability".to_string(),
+                ));
+            }
+            let request = self
+                .client
+                .post(&self.host.notification_url)
+                .json(&serde_json::json!({ "title": title, "body": body }));
+            self.runtime
+                .block_on(async { request.send().await?.error_for_status() })
+                .map(|_| ())
+                .map_err(failed)
+        }
+
+        fn fetch(&mut self, request: HttpRequest) -> Result<HttpResponse, host::Error> {
+            let url = self
+                .capabilities
+                .check_url(&request.url)
+                .map_err(host::Error::Denied)?;
+            let method = reqwest::Method::from_bytes(request.method.as_bytes())
+                .map_err(|_| failed(format!("invalid method {}", request.method)))?;
+            let mut builder = self.client.request(method, url);
+            for (name, value) in request.headers {
+                builder = builder.header(name, value);
+            }
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+            self.runtime
+                .block_on(async {
+                    let response = builder.send().await?;
+                    let status = response.status().as_u16();
+                    let headers = response
+                        .headers()
+                        .iter()
+                        .filter_map(|(name, value)| {
+                            Some((name.to_string(), value.to_str().ok()?.to_string()))
+                        })
+                        .collect();
+                    let body = response.bytes().await?.to_vec();
+                    Ok::<_, reqwest::Error>(HttpResponse {
+                        status,
+                        headers,
+                        body,
+                    })
+                })
+                .map_err(failed)
+        }
+
+        fn log(&mut self, level: LogLevel, message: String) {
+            let level = match level {
+                LogLevel::Debug => PipeLogLevel::Debug,
+                LogLevel::Info => PipeLogLevel::Info,
+                LogLevel::Warn => PipeLogLevel::Warn,
+                LogLevel::Error => PipeLogLevel::Error,
+            };
+            match level {
+                PipeLogLevel::Debug => debug!("[{}] {}", self.pipe, message),
+                PipeLogLevel::Info => info!("[{}] {}", self.pipe, message),
+                PipeLogLevel::Warn => warn!("[{}] {}", self.pipe, message),
+                PipeLogLevel::Error => error!("[{}] {}", self.pipe, message),
+            }
+            self.logger.log(&PipeLogEntry {
+                pipe: self.pipe.clone(),
+                timestamp: Utc::now(),
+                level,
+                stream: "host".to_string(),
+                message,
+                fields: None,
+            });
+        }
+    }
+
+    /// Client for the calls of the pipe, following redirects only to hosts
+    /// it may reach.
+    fn client(capabilities: &PipeCapabilities) -> Result<reqwest::Client> {
+        let capabilities = capabilities.clone();
+        Ok(reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= 10 {
+                    attempt.error("too many redirects")
+                } else if capabilities.allows_url(attempt.url()) {
+                    attempt.follow()
+                } else {
+                    attempt.stop()
+                }
+            }))
+            .build()?)
+    }
+
+    pub(super) fn start(
+        pipe: &str,
+        module_path: PathBuf,
+        capabilities: PipeCapabilities,
+        limits: PipeLimits,
+        host: WasmPipeHost,
+        logger: PipeLogger,
+    ) -> Result<WasmPipe> {
+        let mut config = Config::new();
+        config.wasm_component_model(true).epoch_interruption(true);
+        // an engine per pipe, so stopping one interrupts only it
+        let engine = Engine::new(&config)?;
+
+        let state = HostState {
+            pipe: pipe.to_string(),
+            client: client(&capabilities)?,
+            capabilities,
+            host,
+            runtime: tokio::runtime::Handle::current(),
+            wasi: WasiCtxBuilder::new()
+                .inherit_stdout()
+                .inherit_stderr()
+                .build(),
+            table: ResourceTable::new(),
+            limits: match limits.max_memory_bytes() {
+                Some(max) => StoreLimitsBuilder::new()
+                    .memory_size(max as usize)
+                    .trap_on_grow_failure(true)
+                    .build(),
+                None => StoreLimits::default(),
+            },
+            logger,
+        };
+        let pipe = pipe.to_string();
+        let task_engine = engine.clone();
+        // the guest and the host calls it makes block, keep them off the
+        // async workers
+        let task = tokio::task::spawn_blocking(move || {
+            let component = Component::from_file(&task_engine, &module_path)?;
+            let mut linker = Linker::new(&task_engine);
+            wasmtime_wasi::add_to_linker_sync(&mut linker)?;
+            Pipe::add_to_linker(&mut linker, |state: &mut HostState| state)?;
+
+            let mut store = Store::new(&task_engine, state);
+            store.limiter(|state| &mut state.limits);
+            store.set_epoch_deadline(1);
+            let bindings = Pipe::instantiate(&mut store, &component, &linker)?;
+            bindings
+                .call_run(&mut store)?
+                .map_err(|e| anyhow::anyhow!("pipe {} failed: {}", pipe, e))
+        });
+        Ok(WasmPipe { engine, task })
+    }
+}
@@ -0,0 +1,41 @@
+use screenpipe_core::answering::{answer_prompt, parse_answer, Excerpt};
+
+#[test]
+fn test_answer_prompt() {
+    let excerpts = vec![
+        Excerpt {
+            source: "screen, Slack, Tuesday 22 April 2025 14:03".to_string(),
+            text: "launch   moved\n to friday".to_string(),
+        },
+        Excerpt {
+            source: "audio, Ana, Tuesday 22 April 2025 15:10".to_string(),
+            text: "x".repeat(5_000),
+        },
+    ];
+    let prompt = answer_prompt("  when is the launch?  ", &excerpts, 2_000);
+    assert!(prompt
+        .contains("[1] (screen, Slack, Tuesday 22 April 2025 14:03) launch moved to friday\n"));
+    assert!(prompt.contains("[2] (audio, Ana, Tuesday 22 April 2025 15:10) "));
+    // each excerpt gets an equal share
+    assert!(prompt.contains(&format!("{}\n", "x".repeat(1_000))));
+    assert!(!prompt.contains(&"x".repeat(1_001)));
+    assert!(prompt.ends_with("## Question\nwhen is the launch?\n"));
+}
+
+#[test]
+fn test_parse_answer() {
+    let content = parse_answer(
+        "```json\n{\"answer\": \" The launch moved to friday [1][3]. \", \"citations\": [1, \"3\", \"[1]\", 7, 0, \"two\"]}\n```",
+        3,
+    )
+    .unwrap();
+    assert_eq!(content.answer, "The launch moved to friday [1][3].");
+    assert_eq!(content.citations, vec![1, 3]);
+
+    // no citations when nothing answers the question
+    let content = parse_answer(r#"{"answer": "Nothing recorded says."}"#, 3).unwrap();
+    assert!(content.citations.is_empty());
+
+    assert!(parse_answer("I don't know.", 3).is_err());
+    assert!(parse_answer(r#"{"citations": [1]}"#, 3).is_err());
+}
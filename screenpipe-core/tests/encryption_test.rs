@@ -0,0 +1,73 @@
+use screenpipe_core::encryption::{
+    is_encrypted, is_encrypted_file, prepare_decrypt_dir, readable_media, EncryptionKey,
+    MediaCipher, ReadableMedia,
+};
+
+#[test]
+fn test_encrypt_decrypt_round_trip() {
+    let cipher = MediaCipher::new(&EncryptionKey::generate());
+    let plaintext = b"some recorded audio".to_vec();
+
+    let data = cipher.encrypt(&plaintext).unwrap();
+    assert!(is_encrypted(&data));
+    assert_ne!(&data[data.len() - plaintext.len()..], &plaintext[..]);
+    assert_eq!(cipher.decrypt(&data).unwrap(), plaintext);
+
+    let other = MediaCipher::new(&EncryptionKey::generate());
+    assert!(other.decrypt(&data).is_err());
+}
+
+#[test]
+fn test_key_hex_round_trip() {
+    let key = EncryptionKey::generate();
+    assert_eq!(EncryptionKey::from_hex(&key.to_hex()).unwrap(), key);
+    assert!(EncryptionKey::from_hex("abcd").is_err());
+    assert!(!format!("{:?}", key).contains(&key.to_hex()));
+}
+
+#[tokio::test]
+async fn test_encrypt_file_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("monitor_1_2025-03-20_10-00-00.mp4");
+    std::fs::write(&path, b"fake video data").unwrap();
+
+    // plain files are read as they are
+    let readable = readable_media(&path).await.unwrap();
+    assert!(matches!(readable, ReadableMedia::Plain(_)));
+
+    let cipher = MediaCipher::new(&EncryptionKey::generate());
+    assert!(cipher.encrypt_file(&path).unwrap());
+    assert!(!cipher.encrypt_file(&path).unwrap());
+    assert!(is_encrypted_file(&path).unwrap());
+    assert!(!dir
+        .path()
+        .join("monitor_1_2025-03-20_10-00-00.mp4.enc")
+        .exists());
+
+    let decrypted = cipher.decrypt_to_temp(&path, dir.path()).unwrap();
+    assert_eq!(decrypted.path().parent().unwrap(), dir.path());
+    assert_eq!(decrypted.path().extension().unwrap(), "mp4");
+    assert_eq!(std::fs::read(decrypted.path()).unwrap(), b"fake video data");
+
+    // without an unlocked key the recording can't be read
+    assert!(readable_media(&path).await.is_err());
+}
+
+#[test]
+fn test_prepare_decrypt_dir_clears_leftovers() {
+    let data_dir = tempfile::tempdir().unwrap();
+    let dir = prepare_decrypt_dir(data_dir.path()).unwrap();
+    assert!(dir.starts_with(data_dir.path()));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
+    // a crash left a decrypted recording behind
+    std::fs::write(dir.join("screenpipe-decrypted-1.mp4"), b"plaintext").unwrap();
+    let dir = prepare_decrypt_dir(data_dir.path()).unwrap();
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+}
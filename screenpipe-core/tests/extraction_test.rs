@@ -0,0 +1,135 @@
+use screenpipe_core::extraction::{
+    check_schema, extraction_prompt, parse_extraction, validate, ExtractionInput,
+};
+use serde_json::json;
+
+fn meeting_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "attendees": {"type": "array", "items": {"type": "string"}},
+            "decisions": {"type": "array", "items": {"type": "string"}},
+            "action_items": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "owner": {"type": ["string", "null"]},
+                        "task": {"type": "string"},
+                        "priority": {"enum": ["low", "high"]}
+                    },
+                    "required": ["task"]
+                }
+            },
+            "duration_minutes": {"type": "integer"}
+        },
+        "required": ["attendees", "decisions"]
+    })
+}
+
+#[test]
+fn test_check_schema() {
+    assert!(check_schema(&meeting_schema()).is_ok());
+    assert!(check_schema(&json!({"type": "array"})).is_err());
+    assert!(check_schema(&json!("object")).is_err());
+    assert_eq!(
+        check_schema(&json!({
+            "type": "object",
+            "properties": {"when": {"type": "date"}}
+        })),
+        Err("$.when: unknown type \"date\"".to_string())
+    );
+    assert!(check_schema(&json!({"type": "object", "required": "name"})).is_err());
+    assert!(check_schema(&json!({
+        "type": "object",
+        "properties": {"tags": {"type": "array", "items": 3}}
+    }))
+    .is_err());
+}
+
+#[test]
+fn test_validate() {
+    let schema = meeting_schema();
+    assert!(validate(
+        &json!({
+            "attendees": ["Ana", "Bob"],
+            "decisions": ["ship on friday"],
+            "action_items": [{"owner": null, "task": "write the notes", "priority": "low"}],
+            "duration_minutes": 45,
+            "extra": true
+        }),
+        &schema
+    )
+    .is_ok());
+
+    assert_eq!(
+        validate(&json!({"attendees": []}), &schema),
+        Err("$: missing decisions".to_string())
+    );
+    assert_eq!(
+        validate(&json!({"attendees": ["Ana", 2], "decisions": []}), &schema),
+        Err("$.attendees[1]: expected string".to_string())
+    );
+    assert_eq!(
+        validate(
+            &json!({"attendees": [], "decisions": [], "action_items": [{"owner": "Ana"}]}),
+            &schema
+        ),
+        Err("$.action_items[0]: missing task".to_string())
+    );
+    assert!(validate(
+        &json!({"attendees": [], "decisions": [], "action_items": [{"task": "x", "priority": "urgent"}]}),
+        &schema
+    )
+    .is_err());
+    assert!(validate(
+        &json!({"attendees": [], "decisions": [], "duration_minutes": 4.5}),
+        &schema
+    )
+    .is_err());
+    assert!(validate(&json!([]), &schema).is_err());
+}
+
+#[test]
+fn test_parse_extraction() {
+    let schema = meeting_schema();
+    let record = parse_extraction(
+        "Here you go:\n```json\n{\"attendees\": [\"Ana\"], \"decisions\": []}\n```",
+        &schema,
+    )
+    .unwrap();
+    assert_eq!(record, json!({"attendees": ["Ana"], "decisions": []}));
+
+    assert!(parse_extraction("no meeting today", &schema).is_err());
+    assert!(parse_extraction(r#"{"attendees": "Ana", "decisions": []}"#, &schema).is_err());
+}
+
+#[test]
+fn test_extraction_prompt() {
+    let input = ExtractionInput {
+        context: "a zoom.us session on Tuesday 22 April 2025 from 14:00 to 14:45".to_string(),
+        sections: vec![
+            (
+                "What was said".to_string(),
+                vec!["ana: let's ship on friday".to_string()],
+            ),
+            ("What zoom.us showed".to_string(), vec![]),
+        ],
+    };
+    assert!(!input.is_empty());
+
+    let prompt = extraction_prompt(
+        "  Extract the attendees and the decisions.  ",
+        &meeting_schema(),
+        &input,
+        2_000,
+    );
+    assert!(prompt.contains("a zoom.us session on Tuesday 22 April 2025 from 14:00 to 14:45"));
+    assert!(prompt.contains("\nExtract the attendees and the decisions.\n"));
+    assert!(prompt.contains("\"required\":[\"attendees\",\"decisions\"]"));
+    assert!(prompt.contains("## What was said\nana: let's ship on friday\n"));
+    // empty sections are left out
+    assert!(!prompt.contains("What zoom.us showed"));
+
+    assert!(ExtractionInput::default().is_empty());
+}
@@ -0,0 +1,18 @@
+use screenpipe_core::idle::{idle_reason, is_audio_idle, is_idle, set_idle, IdleReason};
+
+#[test]
+fn test_idle_pauses_audio_only_when_away_from_the_computer() {
+    set_idle(Some(IdleReason::NoInput));
+    assert!(is_idle());
+    // a call can go on without any input
+    assert!(!is_audio_idle());
+
+    set_idle(Some(IdleReason::ScreenLocked));
+    assert!(is_idle());
+    assert!(is_audio_idle());
+    assert_eq!(idle_reason().unwrap().to_string(), "screen_locked");
+
+    set_idle(None);
+    assert!(!is_idle());
+    assert!(!is_audio_idle());
+}
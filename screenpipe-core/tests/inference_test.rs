@@ -0,0 +1,90 @@
+use screenpipe_core::inference::{
+    llama3_prompt, local_model, ChatCompletionMessage, ChatCompletionRequest, Quantization,
+    DEFAULT_LOCAL_MODEL, LOCAL_MODELS,
+};
+
+fn message(role: &str, content: &str) -> ChatCompletionMessage {
+    ChatCompletionMessage {
+        role: role.to_string(),
+        content: content.to_string(),
+    }
+}
+
+#[test]
+fn test_local_models() {
+    assert!(local_model(DEFAULT_LOCAL_MODEL).is_some());
+    assert!(local_model("gpt-4").is_none());
+    assert!(LOCAL_MODELS
+        .iter()
+        .all(|model| local_model(model.id) == Some(model)));
+
+    let model = local_model("llama3.2-1b").unwrap();
+    assert_eq!(
+        model.gguf_filename(Quantization::Q4KM),
+        "Llama-3.2-1B-Instruct-Q4_K_M.gguf"
+    );
+    assert_eq!(
+        model.gguf_filename(Quantization::Q8_0),
+        "Llama-3.2-1B-Instruct-Q8_0.gguf"
+    );
+}
+
+#[test]
+fn test_quantization() {
+    assert_eq!(
+        "q5_k_m".parse::<Quantization>().unwrap(),
+        Quantization::Q5KM
+    );
+    assert_eq!("Q6_K".parse::<Quantization>().unwrap(), Quantization::Q6K);
+    assert!("q3".parse::<Quantization>().is_err());
+    assert_eq!(
+        serde_json::to_string(&Quantization::Q4KM).unwrap(),
+        "\"q4_k_m\""
+    );
+    assert_eq!(
+        serde_json::from_str::<Quantization>("\"q4_0\"").unwrap(),
+        Quantization::Q4_0
+    );
+}
+
+#[test]
+fn test_llama3_prompt() {
+    let prompt = llama3_prompt(&[
+        message("system", "Answer briefly."),
+        message("user", " What did I work on today?\n"),
+    ]);
+    assert_eq!(
+        prompt,
+        "<|begin_of_text|>\
+         <|start_header_id|>system<|end_header_id|>\n\nAnswer briefly.<|eot_id|>\
+         <|start_header_id|>user<|end_header_id|>\n\nWhat did I work on today?<|eot_id|>\
+         <|start_header_id|>assistant<|end_header_id|>\n\n"
+    );
+}
+
+#[test]
+fn test_validate_chat_completion_request() {
+    let request: ChatCompletionRequest = serde_json::from_str(
+        r#"{"model": "llama3.2-1b", "messages": [{"role": "user", "content": "hi"}], "max_tokens": 16, "presence_penalty": 1}"#,
+    )
+    .unwrap();
+    assert_eq!(request.max_tokens, Some(16));
+    assert!(!request.stream);
+    assert!(request.validate().is_ok());
+
+    let empty = ChatCompletionRequest::default();
+    assert!(empty.validate().is_err());
+
+    let tool = ChatCompletionRequest {
+        messages: vec![message("tool", "{}")],
+        ..Default::default()
+    };
+    assert_eq!(tool.validate().unwrap_err(), "unsupported role: tool");
+
+    let hot = ChatCompletionRequest {
+        messages: vec![message("user", "hi")],
+        temperature: Some(3.0),
+        ..Default::default()
+    };
+    assert!(hot.validate().is_err());
+}
@@ -0,0 +1,63 @@
+use screenpipe_core::metrics::{render, Counter, Family, Gauge, Summary, FRAMES_DROPPED};
+
+#[test]
+fn test_summary_quantiles() {
+    let summary = Summary::default();
+    assert_eq!(summary.quantile(0.5), None);
+    for value in 1..=100 {
+        summary.observe(value as f64);
+    }
+    assert_eq!(summary.count(), 100);
+    assert_eq!(summary.quantile(0.5), Some(50.0));
+    assert_eq!(summary.quantile(0.99), Some(99.0));
+    assert_eq!(summary.quantile(1.0), Some(100.0));
+
+    // quantiles only look at the latest observations
+    for _ in 0..2000 {
+        summary.observe(1.0);
+    }
+    assert_eq!(summary.quantile(0.99), Some(1.0));
+    assert_eq!(summary.count(), 2100);
+}
+
+#[test]
+fn test_family_encoding() {
+    let counters: Family<Counter> = Family::new("test_total", "Things counted", &["kind"]);
+    let mut out = String::new();
+    counters.encode(&mut out);
+    // nothing is written before the first metric
+    assert!(out.is_empty());
+
+    counters.with_labels(&["a \"quoted\" kind"]).inc_by(3);
+    counters.with_labels(&["plain"]).inc();
+    counters.encode(&mut out);
+    assert_eq!(
+        out,
+        "# HELP test_total Things counted\n\
+         # TYPE test_total counter\n\
+         test_total{kind=\"a \\\"quoted\\\" kind\"} 3\n\
+         test_total{kind=\"plain\"} 1\n"
+    );
+
+    let gauges: Family<Gauge> = Family::new("test_bytes", "Bytes", &[]);
+    gauges.with_labels(&[]).set(1.5);
+    let mut out = String::new();
+    gauges.encode(&mut out);
+    assert!(out.ends_with("test_bytes 1.5\n"));
+
+    let summaries: Family<Summary> = Family::new("test_seconds", "Durations", &["step"]);
+    summaries.with_labels(&["ocr"]).observe(0.25);
+    let mut out = String::new();
+    summaries.encode(&mut out);
+    assert!(out.contains("test_seconds{step=\"ocr\",quantile=\"0.5\"} 0.25\n"));
+    assert!(out.contains("test_seconds_sum{step=\"ocr\"} 0.25\n"));
+    assert!(out.contains("test_seconds_count{step=\"ocr\"} 1\n"));
+}
+
+#[test]
+fn test_render() {
+    FRAMES_DROPPED.with_labels(&["1", "ocr"]).inc();
+    let out = render();
+    assert!(out.contains("# TYPE screenpipe_frames_dropped_total counter\n"));
+    assert!(out.contains("screenpipe_frames_dropped_total{monitor=\"1\",queue=\"ocr\"} 1\n"));
+}
@@ -0,0 +1,20 @@
+use screenpipe_core::ocr_mode::{ocr_mode, set_ocr_mode, should_defer_ocr, OcrMode};
+use screenpipe_core::power::{set_power_status, PowerStatus};
+
+#[test]
+fn test_ocr_is_deferred_in_deferred_mode_or_on_battery() {
+    assert_eq!(ocr_mode(), OcrMode::Realtime);
+    assert!(!should_defer_ocr());
+
+    set_power_status(PowerStatus {
+        ocr_deferred: true,
+        ..Default::default()
+    });
+    assert!(should_defer_ocr());
+    set_power_status(PowerStatus::default());
+
+    set_ocr_mode(OcrMode::Deferred);
+    assert!(should_defer_ocr());
+    assert_eq!(ocr_mode().to_string(), "deferred");
+    set_ocr_mode(OcrMode::Realtime);
+}
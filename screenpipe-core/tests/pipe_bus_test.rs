@@ -0,0 +1,78 @@
+use screenpipe_core::pipe_bus::{
+    check_topic, covers, issue_token, pipe_of_token, publish, subscribe, BusPermissions,
+};
+use serde_json::json;
+
+#[test]
+fn test_topics() {
+    assert!(check_topic("meeting-detector/ended", false).is_ok());
+    assert!(check_topic("meeting-detector/summary.v2", false).is_ok());
+    assert!(check_topic("meeting-detector", false).is_err());
+    assert!(check_topic("/ended", false).is_err());
+    assert!(check_topic("meeting-detector/", false).is_err());
+    assert!(check_topic("meeting detector/ended", false).is_err());
+    assert!(check_topic("meeting-detector/*", false).is_err());
+
+    assert!(check_topic("meeting-detector/*", true).is_ok());
+    assert!(check_topic("*", true).is_ok());
+    assert!(check_topic("meeting-*", true).is_ok());
+    assert!(check_topic("meeting-detector/*/x", true).is_err());
+
+    assert!(covers("meeting-detector/*", "meeting-detector/ended"));
+    assert!(covers("meeting-detector/ended", "meeting-detector/ended"));
+    assert!(!covers(
+        "meeting-detector/ended",
+        "meeting-detector/started"
+    ));
+    assert!(!covers("meeting-detector/*", "meeting-detector-2/ended"));
+}
+
+#[test]
+fn test_permissions() {
+    let none = BusPermissions::from_config(&json!({"enabled": true})).unwrap();
+    assert!(none.can_publish("notes", "notes/created"));
+    assert!(none.can_subscribe("notes", "notes/*"));
+    assert!(!none.can_publish("notes", "meeting-detector/ended"));
+    assert!(!none.can_subscribe("notes", "meeting-detector/ended"));
+    assert!(!none.can_subscribe("notes", "*"));
+
+    let permissions = BusPermissions::from_config(&json!({
+        "bus": {"publish": ["reminders/new"], "subscribe": ["meeting-detector/*"]}
+    }))
+    .unwrap();
+    assert!(permissions.can_publish("notes", "reminders/new"));
+    assert!(!permissions.can_publish("notes", "reminders/other"));
+    assert!(permissions.can_subscribe("notes", "meeting-detector/ended"));
+    assert!(permissions.can_subscribe("notes", "meeting-detector/*"));
+    assert!(!permissions.can_subscribe("notes", "meeting-*"));
+
+    assert!(BusPermissions::from_config(&json!({"bus": {"subscribe": ["bad topic"]}})).is_err());
+    assert!(BusPermissions::from_config(&json!({"bus": {"publish": "notes/*"}})).is_err());
+}
+
+#[test]
+fn test_tokens() {
+    let first = issue_token("token-test-pipe");
+    assert_eq!(pipe_of_token(&first).as_deref(), Some("token-test-pipe"));
+
+    let second = issue_token("token-test-pipe");
+    assert_ne!(first, second);
+    assert_eq!(pipe_of_token(&first), None);
+    assert_eq!(pipe_of_token(&second).as_deref(), Some("token-test-pipe"));
+    assert_eq!(pipe_of_token("unknown"), None);
+}
+
+#[tokio::test]
+async fn test_publish() {
+    let mut messages = subscribe();
+    publish(
+        "meeting-detector",
+        "meeting-detector/ended",
+        json!({"id": 1}),
+    );
+
+    let message = messages.recv().await.unwrap();
+    assert_eq!(message.topic, "meeting-detector/ended");
+    assert_eq!(message.from, "meeting-detector");
+    assert_eq!(message.payload, json!({"id": 1}));
+}
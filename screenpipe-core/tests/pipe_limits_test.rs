@@ -0,0 +1,52 @@
+use screenpipe_core::pipe_limits::{restart_backoff, PipeLimits};
+use serde_json::json;
+use std::time::Duration;
+
+#[test]
+fn test_from_config() {
+    assert_eq!(
+        PipeLimits::from_config(&json!({"enabled": true})).unwrap(),
+        PipeLimits::default()
+    );
+    assert_eq!(
+        PipeLimits::from_config(&json!({"limits": {"max_memory_mb": 512, "max_cpu_percent": 80}}))
+            .unwrap(),
+        PipeLimits {
+            max_memory_mb: Some(512),
+            max_cpu_percent: Some(80.0),
+        }
+    );
+    assert!(PipeLimits::from_config(&json!({"limits": {"max_memory_mb": "512"}})).is_err());
+}
+
+#[test]
+fn test_exceeded() {
+    let limits = PipeLimits {
+        max_memory_mb: Some(512),
+        max_cpu_percent: Some(80.0),
+    };
+    assert_eq!(limits.max_memory_bytes(), Some(512 * 1024 * 1024));
+    assert_eq!(limits.memory_exceeded(100 * 1024 * 1024), None);
+    assert_eq!(
+        limits.memory_exceeded(600 * 1024 * 1024),
+        Some("used 600 MB of memory, over its limit of 512 MB".to_string())
+    );
+    assert_eq!(limits.cpu_exceeded(80.0), None);
+    assert_eq!(
+        limits.cpu_exceeded(150.0),
+        Some("used 150% of CPU, over its limit of 80%".to_string())
+    );
+
+    // no limits
+    assert_eq!(PipeLimits::default().memory_exceeded(u64::MAX), None);
+    assert_eq!(PipeLimits::default().cpu_exceeded(800.0), None);
+}
+
+#[test]
+fn test_restart_backoff() {
+    assert_eq!(restart_backoff(0), Duration::from_secs(1));
+    assert_eq!(restart_backoff(1), Duration::from_secs(2));
+    assert_eq!(restart_backoff(4), Duration::from_secs(16));
+    assert_eq!(restart_backoff(10), Duration::from_secs(5 * 60));
+    assert_eq!(restart_backoff(u32::MAX), Duration::from_secs(5 * 60));
+}
@@ -0,0 +1,112 @@
+use chrono::{Duration, TimeZone, Utc};
+use screenpipe_core::pipe_logs::{
+    log_dir, parse_log_line, read_logs, stderr_level, subscribe, PipeLogLevel, PipeLogger,
+};
+use serde_json::json;
+use tempfile::tempdir;
+
+#[test]
+fn test_plain_lines() {
+    let now = Utc::now();
+    let entry = parse_log_line("digest", "stdout", "summarized 12 frames", now);
+    assert_eq!(entry.pipe, "digest");
+    assert_eq!(entry.stream, "stdout");
+    assert_eq!(entry.level, PipeLogLevel::Info);
+    assert_eq!(entry.message, "summarized 12 frames");
+    assert_eq!(entry.timestamp, now);
+    assert_eq!(entry.fields, None);
+
+    let entry = parse_log_line("digest", "stderr", "TypeError: x is undefined", now);
+    assert_eq!(entry.level, PipeLogLevel::Error);
+    // json without a message is kept as is
+    let entry = parse_log_line("digest", "stdout", r#"{"count": 3}"#, now);
+    assert_eq!(entry.message, r#"{"count": 3}"#);
+    assert_eq!(entry.fields, None);
+}
+
+#[test]
+fn test_json_lines() {
+    let now = Utc::now();
+    let line = json!({
+        "level": 40,
+        "time": 1_700_000_000_000i64,
+        "pid": 4242,
+        "hostname": "laptop",
+        "msg": "rate limited",
+        "retry_in": 30
+    })
+    .to_string();
+    let entry = parse_log_line("digest", "stdout", &line, now);
+    assert_eq!(entry.level, PipeLogLevel::Warn);
+    assert_eq!(entry.message, "rate limited");
+    assert_eq!(
+        entry.timestamp,
+        Utc.timestamp_millis_opt(1_700_000_000_000).unwrap()
+    );
+    assert_eq!(entry.fields, Some(json!({"retry_in": 30})));
+
+    let line =
+        r#"{"level": "error", "message": "sync failed", "timestamp": "2024-05-01T10:00:00Z"}"#;
+    let entry = parse_log_line("digest", "stdout", line, now);
+    assert_eq!(entry.level, PipeLogLevel::Error);
+    assert_eq!(entry.message, "sync failed");
+    assert_eq!(entry.timestamp.to_rfc3339(), "2024-05-01T10:00:00+00:00");
+    assert_eq!(entry.fields, None);
+}
+
+#[test]
+fn test_stderr_level() {
+    assert_eq!(stderr_level("Saved lockfile"), PipeLogLevel::Info);
+    assert_eq!(stderr_level("console.log('hi')"), PipeLogLevel::Info);
+    assert_eq!(stderr_level("Failed to compile"), PipeLogLevel::Error);
+    assert_eq!(stderr_level("something odd happened"), PipeLogLevel::Warn);
+    assert_eq!(
+        "WARNING".parse::<PipeLogLevel>().unwrap(),
+        PipeLogLevel::Warn
+    );
+    assert!("loud".parse::<PipeLogLevel>().is_err());
+}
+
+#[tokio::test]
+async fn test_write_and_read_logs() {
+    let dir = tempdir().unwrap();
+    let mut logs = subscribe();
+    let logger = PipeLogger::new(dir.path(), "digest");
+
+    let first = logger.log_line("stdout", r#"{"level": 20, "msg": "polling"}"#);
+    let second = logger.log_line("stderr", "error: search failed");
+    let third = logger.log_line("stdout", "done");
+    assert!(log_dir(dir.path()).join("digest.log").exists());
+
+    let all = read_logs(dir.path(), "digest", None, None, 100).unwrap();
+    assert_eq!(all, vec![first.clone(), second.clone(), third.clone()]);
+    assert_eq!(
+        read_logs(dir.path(), "digest", None, Some(PipeLogLevel::Info), 100).unwrap(),
+        vec![second.clone(), third.clone()]
+    );
+    assert_eq!(
+        read_logs(dir.path(), "digest", None, None, 1).unwrap(),
+        vec![third.clone()]
+    );
+    assert!(read_logs(
+        dir.path(),
+        "digest",
+        Some(third.timestamp + Duration::seconds(1)),
+        None,
+        100
+    )
+    .unwrap()
+    .is_empty());
+    assert!(read_logs(dir.path(), "other", None, None, 100)
+        .unwrap()
+        .is_empty());
+
+    // other tests log too
+    let mut received = Vec::new();
+    while let Ok(entry) = logs.try_recv() {
+        if entry.pipe == "digest" {
+            received.push(entry);
+        }
+    }
+    assert_eq!(received, vec![first, second, third]);
+}
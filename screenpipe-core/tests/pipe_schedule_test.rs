@@ -0,0 +1,105 @@
+use chrono::{Duration, Local, TimeZone};
+use screenpipe_core::pipe_schedule::{
+    is_scheduled, parse_cron, PipeSchedule, PipeTrigger, MEETING_ENDED_EVENT, MEETING_STARTED_EVENT,
+};
+use serde_json::json;
+
+#[test]
+fn test_from_config() {
+    // pipes without a schedule or triggers run all the time
+    assert!(!is_scheduled(&json!({"enabled": true, "crons": []})));
+    assert!(!is_scheduled(&json!({"triggers": []})));
+    assert_eq!(
+        PipeSchedule::from_config(&json!({"enabled": true})).unwrap(),
+        None
+    );
+
+    let schedule = PipeSchedule::from_config(&json!({
+        "enabled": true,
+        "schedule": "0 18 * * *",
+        "triggers": [
+            {"type": "meeting_ended"},
+            {"type": "keyword", "keyword": "invoice", "app_name": "Mail"}
+        ],
+        "timeout_secs": 600
+    }))
+    .unwrap()
+    .unwrap();
+    assert_eq!(schedule.schedule.as_deref(), Some("0 18 * * *"));
+    assert_eq!(
+        schedule.triggers,
+        vec![
+            PipeTrigger::MeetingEnded,
+            PipeTrigger::Keyword {
+                keyword: "invoice".to_string(),
+                app_name: Some("Mail".to_string()),
+            }
+        ]
+    );
+    assert_eq!(schedule.timeout_secs, Some(600));
+    assert_eq!(
+        schedule.keywords().collect::<Vec<_>>(),
+        vec![("invoice", Some("Mail"))]
+    );
+
+    assert!(PipeSchedule::from_config(&json!({"schedule": "every evening"})).is_err());
+    assert!(PipeSchedule::from_config(&json!({"triggers": [{"type": "lunch"}]})).is_err());
+    assert!(
+        PipeSchedule::from_config(&json!({"triggers": [{"type": "keyword", "keyword": " "}]}))
+            .is_err()
+    );
+}
+
+#[test]
+fn test_parse_cron() {
+    // five fields or with seconds
+    assert!(parse_cron("0 18 * * *").is_ok());
+    assert!(parse_cron("30 0 18 * * *").is_ok());
+    assert!(parse_cron("").is_err());
+}
+
+#[test]
+fn test_is_due() {
+    let schedule = PipeSchedule {
+        schedule: Some("0 18 * * *".to_string()),
+        triggers: vec![],
+        timeout_secs: None,
+    };
+    let evening = Local.with_ymd_and_hms(2025, 4, 22, 18, 0, 0).unwrap();
+    assert!(schedule.is_due(&(evening - Duration::seconds(30)), &evening));
+    assert!(!schedule.is_due(&evening, &(evening + Duration::seconds(30))));
+    assert!(!schedule.is_due(
+        &(evening - Duration::hours(2)),
+        &(evening - Duration::hours(1))
+    ));
+    // a time missed while asleep is caught up once
+    assert!(schedule.is_due(
+        &(evening - Duration::hours(1)),
+        &(evening + Duration::hours(3))
+    ));
+
+    let triggers_only = PipeSchedule {
+        schedule: None,
+        triggers: vec![PipeTrigger::MeetingEnded],
+        timeout_secs: None,
+    };
+    assert!(!triggers_only.is_due(&(evening - Duration::days(1)), &evening));
+}
+
+#[test]
+fn test_is_triggered_by() {
+    let schedule = PipeSchedule {
+        schedule: None,
+        triggers: vec![
+            PipeTrigger::MeetingEnded,
+            PipeTrigger::Keyword {
+                keyword: "invoice".to_string(),
+                app_name: None,
+            },
+        ],
+        timeout_secs: None,
+    };
+    assert!(schedule.is_triggered_by(MEETING_ENDED_EVENT));
+    assert!(!schedule.is_triggered_by(MEETING_STARTED_EVENT));
+    assert!(!schedule.is_triggered_by("frame_indexed"));
+}
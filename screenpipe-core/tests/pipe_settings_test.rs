@@ -0,0 +1,116 @@
+use screenpipe_core::pipe_settings::{
+    check_settings, settings_schema, validate_settings, with_defaults, SettingsError,
+};
+use serde_json::{json, Value};
+
+fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "interval_minutes": {"type": "integer", "minimum": 1, "maximum": 60, "default": 5},
+            "model": {"type": "string", "enum": ["llama3.2", "mistral"]},
+            "webhook": {"type": "string", "pattern": "^https://"},
+            "apps": {"type": "array", "items": {"type": "string", "minLength": 1}},
+            "notify": {
+                "type": "object",
+                "properties": {"sound": {"type": "boolean", "default": true}}
+            }
+        },
+        "required": ["model"],
+        "additionalProperties": false
+    })
+}
+
+fn error(path: &str, message: &str) -> SettingsError {
+    SettingsError {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[test]
+fn test_with_defaults() {
+    assert_eq!(
+        with_defaults(&json!({"model": "mistral"}), &schema()),
+        json!({
+            "model": "mistral",
+            "interval_minutes": 5,
+            "notify": {"sound": true}
+        })
+    );
+    assert_eq!(
+        with_defaults(
+            &json!({"interval_minutes": 10, "notify": {"sound": false}}),
+            &schema()
+        ),
+        json!({"interval_minutes": 10, "notify": {"sound": false}})
+    );
+}
+
+#[test]
+fn test_validate_settings() {
+    assert!(validate_settings(
+        &json!({"model": "llama3.2", "apps": ["Slack"], "webhook": "https://example.com"}),
+        &schema()
+    )
+    .is_empty());
+
+    // every mismatch is reported
+    assert_eq!(
+        validate_settings(
+            &json!({
+                "interval_minutes": 0,
+                "webhook": "http://example.com",
+                "apps": ["Slack", ""],
+                "notify": {"sound": "yes"},
+                "theme": "dark"
+            }),
+            &schema()
+        ),
+        vec![
+            error("$.model", "is required"),
+            error("$.apps[1]", "must be at least 1 characters"),
+            error("$.interval_minutes", "must be at least 1"),
+            error("$.notify.sound", "expected boolean"),
+            error("$.theme", "is not a setting of this pipe"),
+            error("$.webhook", "must match ^https://"),
+        ]
+    );
+    assert_eq!(
+        validate_settings(
+            &json!({"model": "gpt-4", "interval_minutes": 2.5}),
+            &schema()
+        ),
+        vec![
+            error("$.interval_minutes", "expected integer"),
+            error(
+                "$.model",
+                "\"gpt-4\" is not one of [\"llama3.2\",\"mistral\"]"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_check_settings() {
+    assert_eq!(check_settings(&json!({"enabled": true})).unwrap(), None);
+    assert_eq!(
+        check_settings(&json!({"settings_schema": schema(), "settings": {"model": "mistral"}}))
+            .unwrap(),
+        Some(json!({
+            "model": "mistral",
+            "interval_minutes": 5,
+            "notify": {"sound": true}
+        }))
+    );
+
+    let invalid = check_settings(&json!({"settings_schema": schema()})).unwrap_err();
+    assert_eq!(invalid.0, vec![error("$.model", "is required")]);
+    assert_eq!(
+        invalid.to_string(),
+        "invalid settings: $.model: is required"
+    );
+
+    assert!(settings_schema(&json!({"settings_schema": {"type": "list"}})).is_err());
+    assert!(check_settings(&json!({"settings_schema": {"type": "list"}})).is_err());
+}
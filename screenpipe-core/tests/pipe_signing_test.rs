@@ -0,0 +1,266 @@
+use screenpipe_core::pipe_signing::{
+    check_package, digest_dir, digest_zip, generate_key, installed_publisher_key, read_signature,
+    sign_package, trust_publisher, trusted_publisher_keys, verify_files, zip_entry_path,
+    SIGNATURE_FILE,
+};
+use serde_json::json;
+use std::io::Write;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write_pipe(dir: &Path) {
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::create_dir_all(dir.join("node_modules").join("left-pad")).unwrap();
+    std::fs::write(dir.join("package.json"), r#"{"name": "digest"}"#).unwrap();
+    std::fs::write(dir.join("src").join("index.ts"), "console.log('hi')").unwrap();
+    std::fs::write(
+        dir.join("node_modules").join("left-pad").join("index.js"),
+        "module.exports = 1",
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_sign_and_verify() {
+    let dir = tempdir().unwrap();
+    write_pipe(dir.path());
+    let (seed, public_key) = generate_key().unwrap();
+
+    let signature = sign_package(dir.path(), &seed).unwrap();
+    assert_eq!(signature.publisher_key, public_key);
+    assert_eq!(
+        signature.files.keys().collect::<Vec<_>>(),
+        vec!["package.json", "src/index.ts"]
+    );
+    assert_eq!(read_signature(dir.path()).unwrap(), Some(signature.clone()));
+    verify_files(&digest_dir(dir.path()).unwrap(), &signature).unwrap();
+
+    // node_modules isn't signed, so installing dependencies keeps it valid
+    std::fs::write(dir.path().join("node_modules").join("extra.js"), "1").unwrap();
+    verify_files(&digest_dir(dir.path()).unwrap(), &signature).unwrap();
+
+    std::fs::write(dir.path().join("src").join("index.ts"), "steal()").unwrap();
+    let err = verify_files(&digest_dir(dir.path()).unwrap(), &signature).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "src/index.ts was modified after the pipe was signed"
+    );
+
+    std::fs::write(dir.path().join("src").join("index.ts"), "console.log('hi')").unwrap();
+    std::fs::write(dir.path().join("src").join("extra.ts"), "steal()").unwrap();
+    let err = verify_files(&digest_dir(dir.path()).unwrap(), &signature).unwrap_err();
+    assert_eq!(err.to_string(), "src/extra.ts isn't signed");
+
+    std::fs::remove_file(dir.path().join("src").join("extra.ts")).unwrap();
+    std::fs::remove_file(dir.path().join("package.json")).unwrap();
+    let err = verify_files(&digest_dir(dir.path()).unwrap(), &signature).unwrap_err();
+    assert_eq!(err.to_string(), "package.json is missing from the pipe");
+}
+
+#[test]
+fn test_tampered_signature() {
+    let dir = tempdir().unwrap();
+    write_pipe(dir.path());
+    let (seed, _) = generate_key().unwrap();
+    let (_, other_key) = generate_key().unwrap();
+    let mut signature = sign_package(dir.path(), &seed).unwrap();
+
+    signature.publisher_key = other_key;
+    let files = digest_dir(dir.path()).unwrap();
+    let err = verify_files(&files, &signature).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "the signature of the pipe doesn't match its publisher key"
+    );
+    // even when unsigned pipes are allowed
+    assert!(check_package("url", &files, Some(&signature), true, None, &[], true).is_err());
+}
+
+#[test]
+fn test_check_package() {
+    let dir = tempdir().unwrap();
+    write_pipe(dir.path());
+    let (seed, public_key) = generate_key().unwrap();
+    let (_, other_key) = generate_key().unwrap();
+    let files = digest_dir(dir.path()).unwrap();
+
+    // unsigned pipes are only installed from local folders or when allowed
+    let err = check_package(
+        "https://example.com/pipe",
+        &files,
+        None,
+        true,
+        None,
+        &[],
+        false,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "the pipe isn't signed, install it with allow_unsigned to override"
+    );
+    let provenance = check_package("./pipe", &files, None, false, None, &[], false).unwrap();
+    assert!(!provenance.signed);
+    assert_eq!(provenance.publisher_key, None);
+    assert!(check_package(
+        "https://example.com/pipe",
+        &files,
+        None,
+        true,
+        None,
+        &[],
+        true
+    )
+    .is_ok());
+
+    // anyone can sign a pipe, so a first install needs a trusted key
+    let signature = sign_package(dir.path(), &seed).unwrap();
+    let err = check_package(
+        "https://example.com/pipe",
+        &files,
+        Some(&signature),
+        true,
+        None,
+        std::slice::from_ref(&other_key),
+        false,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("a publisher you don't trust"));
+    let untrusted = check_package(
+        "https://example.com/pipe",
+        &files,
+        Some(&signature),
+        true,
+        None,
+        &[],
+        true,
+    )
+    .unwrap();
+    assert!(untrusted.signed);
+    assert!(!untrusted.publisher_trusted);
+    let provenance = check_package(
+        "https://example.com/pipe",
+        &files,
+        Some(&signature),
+        true,
+        None,
+        std::slice::from_ref(&public_key),
+        false,
+    )
+    .unwrap();
+    assert!(provenance.signed);
+    assert!(provenance.publisher_trusted);
+    assert!(!provenance.publisher_pinned);
+    assert_eq!(provenance.source, "https://example.com/pipe");
+    assert_eq!(
+        provenance.publisher_key.as_deref(),
+        Some(public_key.as_str())
+    );
+
+    // updates must come from the publisher of the installed version
+    let update = check_package(
+        "https://example.com/pipe",
+        &files,
+        Some(&signature),
+        true,
+        Some(&public_key),
+        &[],
+        false,
+    )
+    .unwrap();
+    assert!(update.publisher_pinned);
+    let err = check_package(
+        "https://example.com/pipe",
+        &files,
+        Some(&signature),
+        true,
+        Some(&other_key),
+        std::slice::from_ref(&public_key),
+        false,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("another publisher"));
+    let err =
+        check_package("./pipe", &files, None, false, Some(&public_key), &[], false).unwrap_err();
+    assert!(err.to_string().contains("is signed but this one isn't"));
+    let overridden = check_package(
+        "https://example.com/pipe",
+        &files,
+        Some(&signature),
+        true,
+        Some(&other_key),
+        &[],
+        true,
+    )
+    .unwrap();
+    assert!(!overridden.publisher_pinned);
+
+    let config = json!({"provenance": serde_json::to_value(&provenance).unwrap()});
+    assert_eq!(installed_publisher_key(&config), Some(public_key));
+    assert_eq!(installed_publisher_key(&json!({"enabled": true})), None);
+}
+
+#[test]
+fn test_digest_zip() {
+    let dir = tempdir().unwrap();
+    write_pipe(dir.path());
+    let (seed, _) = generate_key().unwrap();
+    let signature = sign_package(dir.path(), &seed).unwrap();
+
+    let zip_path = dir.path().join("pipe.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+    let options = zip::write::FileOptions::default();
+    for path in [
+        "package.json",
+        "src/index.ts",
+        "node_modules/left-pad/index.js",
+        SIGNATURE_FILE,
+    ] {
+        zip.start_file(path, options).unwrap();
+        zip.write_all(&std::fs::read(dir.path().join(path)).unwrap())
+            .unwrap();
+    }
+    zip.finish().unwrap();
+
+    let (files, zipped_signature) = digest_zip(&zip_path).unwrap();
+    assert_eq!(files, signature.files);
+    assert_eq!(zipped_signature, Some(signature.clone()));
+    verify_files(&files, &signature).unwrap();
+
+    // entries that would be extracted out of the pipe are refused
+    let evil_path = dir.path().join("evil.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&evil_path).unwrap());
+    zip.start_file("/tmp/evil.sh", options).unwrap();
+    zip.write_all(b"steal()").unwrap();
+    zip.finish().unwrap();
+    let err = digest_zip(&evil_path).unwrap_err();
+    assert_eq!(err.to_string(), "/tmp/evil.sh is outside of the pipe");
+}
+
+#[test]
+fn test_trusted_publishers() {
+    let dir = tempdir().unwrap();
+    let (_, public_key) = generate_key().unwrap();
+    assert!(trusted_publisher_keys(dir.path()).unwrap().is_empty());
+
+    trust_publisher(dir.path(), &public_key.to_uppercase()).unwrap();
+    trust_publisher(dir.path(), &public_key).unwrap();
+    assert_eq!(
+        trusted_publisher_keys(dir.path()).unwrap(),
+        vec![public_key]
+    );
+
+    let err = trust_publisher(dir.path(), "not a key").unwrap_err();
+    assert_eq!(err.to_string(), "not a key isn't an ed25519 public key");
+}
+
+#[test]
+fn test_zip_entry_path() {
+    assert_eq!(zip_entry_path("./src/index.ts"), Some("src/index.ts"));
+    assert_eq!(zip_entry_path("src/"), Some("src/"));
+    // the files that aren't signed aren't extracted
+    assert_eq!(zip_entry_path("node_modules/left-pad/index.js"), None);
+    assert_eq!(zip_entry_path("src/.env"), None);
+    assert_eq!(zip_entry_path(".git/hooks/post-checkout"), None);
+    assert_eq!(zip_entry_path("dist/index.js"), None);
+}
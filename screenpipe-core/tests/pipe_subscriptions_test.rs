@@ -0,0 +1,74 @@
+use screenpipe_core::pipe_subscriptions::{
+    PipeSubscriptions, FRAME_INDEXED_EVENT, TRANSCRIPT_INDEXED_EVENT,
+};
+use serde_json::json;
+
+fn subscriptions() -> PipeSubscriptions {
+    PipeSubscriptions::from_config(&json!({
+        "enabled": true,
+        "subscriptions": [
+            {"type": "transcript", "pattern": "(?i)invoice|receipt"},
+            {"type": "frame", "app_name": "Slack", "window_name": "General"}
+        ]
+    }))
+    .unwrap()
+    .unwrap()
+}
+
+#[test]
+fn test_from_config() {
+    assert!(PipeSubscriptions::from_config(&json!({"enabled": true}))
+        .unwrap()
+        .is_none());
+    assert!(
+        PipeSubscriptions::from_config(&json!({"subscriptions": []}))
+            .unwrap()
+            .is_none()
+    );
+
+    assert!(PipeSubscriptions::from_config(&json!({
+        "subscriptions": [{"type": "transcript", "pattern": "(unclosed"}]
+    }))
+    .is_err());
+    assert!(PipeSubscriptions::from_config(&json!({
+        "subscriptions": [{"type": "frame", "app_name": " "}]
+    }))
+    .is_err());
+    assert!(PipeSubscriptions::from_config(&json!({
+        "subscriptions": [{"type": "clipboard"}]
+    }))
+    .is_err());
+}
+
+#[test]
+fn test_matches() {
+    let subscriptions = subscriptions();
+
+    assert!(subscriptions.matches(
+        TRANSCRIPT_INDEXED_EVENT,
+        &json!({"transcription": "can you send me the Invoice?", "device": "mic"})
+    ));
+    assert!(!subscriptions.matches(
+        TRANSCRIPT_INDEXED_EVENT,
+        &json!({"transcription": "see you tomorrow"})
+    ));
+
+    assert!(subscriptions.matches(
+        FRAME_INDEXED_EVENT,
+        &json!({"app_name": "slack", "window_name": "#general - Acme"})
+    ));
+    assert!(!subscriptions.matches(
+        FRAME_INDEXED_EVENT,
+        &json!({"app_name": "Slack", "window_name": "#random - Acme"})
+    ));
+    assert!(!subscriptions.matches(
+        FRAME_INDEXED_EVENT,
+        &json!({"app_name": "Chrome", "window_name": "general"})
+    ));
+
+    // other events, even with matching fields, aren't pushed
+    assert!(!subscriptions.matches(
+        "app_switched",
+        &json!({"app_name": "Slack", "window_name": "general", "transcription": "invoice"})
+    ));
+}
@@ -41,7 +41,7 @@ mod tests {
         let screenpipe_dir = temp_dir.path().to_path_buf();
 
         let invalid_url = "https://example.com/invalid/url";
-        let result = download_pipe(invalid_url, screenpipe_dir.clone()).await;
+        let result = download_pipe(invalid_url, screenpipe_dir.clone(), &[], true).await;
 
         assert!(result.is_err(), "Expected an error for invalid URL");
     }
@@ -290,7 +290,7 @@ mod tests {
 
         let source_dir = temp_dir.path().join("source_pipe");
         let result = download_pipe(&source_dir.to_str().expect("failed bathbuf to str"),
-            screenpipe_dir.clone()).await;
+            screenpipe_dir.clone(), &[], false).await;
        
         assert!(result.is_err(), "test failed for non existence local pipe: {:?}", result.err());
     }
@@ -315,7 +315,9 @@ mod tests {
 
         // Try to download the pipe using the Windows path
         let result = download_pipe(&source_dir.to_str().expect("failed to convert to str"),
-            screenpipe_dir.clone()
+            screenpipe_dir.clone(),
+            &[],
+            false
         ).await;
 
 
@@ -341,7 +343,7 @@ mod tests {
         ];
 
         for url in urls {
-            let result = download_pipe(url, screenpipe_dir.clone()).await;
+            let result = download_pipe(url, screenpipe_dir.clone(), &[], true).await;
             assert!(result.is_ok(), "Failed to download pipe from URL: {}", url);
 
             let pipe_name = sanitize_pipe_name(url);
@@ -443,7 +445,7 @@ mod tests {
 
         let pipe_name = "data-table";
         let source = "https://raw.githubusercontent.com/tribhuwan-kumar/anime/master/0.1.8.zip";
-        let result = download_pipe_private("data-table", source, screenpipe_dir.clone()).await;
+        let result = download_pipe_private("data-table", source, screenpipe_dir.clone(), &[], true).await;
 
         assert!(
             result.is_ok(),
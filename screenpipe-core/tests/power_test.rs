@@ -0,0 +1,55 @@
+use screenpipe_core::power::{PowerProfile, PowerSettings};
+
+fn settings(low_power_percent: u8, pause_audio_percent: u8) -> PowerSettings {
+    PowerSettings {
+        low_power_percent,
+        pause_audio_percent,
+        defer_ocr: true,
+    }
+}
+
+#[test]
+fn test_power_status_follows_thresholds_on_battery() {
+    let settings = settings(30, 10);
+
+    let status = settings.status(true, Some(50.0));
+    assert_eq!(status.profile, PowerProfile::Normal);
+    assert!(!status.ocr_deferred);
+    assert!(!status.audio_paused);
+
+    let status = settings.status(true, Some(30.0));
+    assert_eq!(status.profile, PowerProfile::LowPower);
+    assert!(status.ocr_deferred);
+    assert!(!status.audio_paused);
+
+    let status = settings.status(true, Some(5.0));
+    assert!(status.audio_paused);
+    assert_eq!(status.profile.to_string(), "low_power");
+}
+
+#[test]
+fn test_power_status_is_normal_when_plugged_in() {
+    let status = settings(100, 100).status(false, Some(5.0));
+    assert_eq!(status.profile, PowerProfile::Normal);
+    assert!(!status.audio_paused);
+}
+
+#[test]
+fn test_power_status_thresholds() {
+    // 0 never applies a saving
+    let status = settings(0, 0).status(true, Some(1.0));
+    assert_eq!(status.profile, PowerProfile::Normal);
+    assert!(!status.audio_paused);
+
+    // 100 always does on battery, even when the charge is unknown
+    let status = settings(100, 0).status(true, None);
+    assert_eq!(status.profile, PowerProfile::LowPower);
+
+    let status = PowerSettings {
+        defer_ocr: false,
+        ..settings(100, 0)
+    }
+    .status(true, Some(80.0));
+    assert_eq!(status.profile, PowerProfile::LowPower);
+    assert!(!status.ocr_deferred);
+}
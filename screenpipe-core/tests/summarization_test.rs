@@ -0,0 +1,69 @@
+use screenpipe_core::summarization::{parse_summary, summary_prompt, SummaryInput};
+
+#[test]
+fn test_parse_summary() {
+    let response = r#"Sure! Here is the summary:
+```json
+{
+  "summary": "  Planned the launch with Ana.  ",
+  "topics": ["launch", "Launch", " pricing ", ""],
+  "action_items": ["send the deck to Ana"],
+  "people": ["Ana", "ana", "Bob"]
+}
+```"#;
+    let content = parse_summary(response).unwrap();
+    assert_eq!(content.summary, "Planned the launch with Ana.");
+    assert_eq!(content.topics, vec!["launch", "pricing"]);
+    assert_eq!(content.action_items, vec!["send the deck to Ana"]);
+    assert_eq!(content.people, vec!["Ana", "Bob"]);
+
+    // missing lists are empty
+    let content = parse_summary(r#"{"summary": "A quiet day."}"#).unwrap();
+    assert_eq!(content.summary, "A quiet day.");
+    assert!(content.topics.is_empty());
+    assert!(content.people.is_empty());
+
+    assert!(parse_summary("I can't summarize this.").is_err());
+    assert!(parse_summary("} oops {").is_err());
+    assert!(parse_summary(r#"{"summary": ["not", "a", "string"]}"#).is_err());
+}
+
+#[test]
+fn test_summary_prompt() {
+    let input = SummaryInput {
+        period: "Tuesday 22 April 2025".to_string(),
+        sections: vec![
+            (
+                "What was said".to_string(),
+                (0..1000).map(|i| format!("line {:04}", i)).collect(),
+            ),
+            ("What was on screen".to_string(), vec![]),
+            ("Time per app".to_string(), vec!["- Code: 2h".to_string()]),
+        ],
+    };
+    assert!(!input.is_empty());
+
+    let prompt = summary_prompt(&input, 2_000);
+    assert!(prompt.contains("Tuesday 22 April 2025"));
+    assert!(prompt.contains("## What was said\n"));
+    assert!(prompt.contains("## Time per app\n- Code: 2h\n"));
+    // empty sections are left out
+    assert!(!prompt.contains("## What was on screen"));
+    // long sections are sampled across the whole period, within their share
+    let said = prompt
+        .split("## What was said\n")
+        .nth(1)
+        .unwrap()
+        .split("\n## ")
+        .next()
+        .unwrap();
+    assert!(said.len() <= 1_000);
+    assert!(said.starts_with("line 0000\n"));
+    assert!(said.contains("line 09"));
+
+    let empty = SummaryInput {
+        period: "Wednesday 23 April 2025".to_string(),
+        sections: vec![("What was said".to_string(), vec![])],
+    };
+    assert!(empty.is_empty());
+}
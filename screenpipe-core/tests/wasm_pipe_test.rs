@@ -0,0 +1,115 @@
+use screenpipe_core::pipe_limits::PipeLimits;
+use screenpipe_core::wasm_pipe::{
+    is_wasm_pipe, read_manifest, PipeCapabilities, WasmPipeManifest, DEFAULT_WASM_MODULE,
+};
+use serde_json::json;
+use std::path::Path;
+use tempfile::tempdir;
+
+#[test]
+fn test_allows_host() {
+    let capabilities = PipeCapabilities {
+        network: vec!["api.openai.com".to_string(), "*.Slack.com".to_string()],
+        ..Default::default()
+    };
+    assert!(capabilities.allows_host("api.openai.com"));
+    assert!(capabilities.allows_host("API.openai.com."));
+    assert!(!capabilities.allows_host("openai.com"));
+    assert!(!capabilities.allows_host("evil-api.openai.com"));
+    assert!(capabilities.allows_host("hooks.slack.com"));
+    assert!(capabilities.allows_host("a.b.slack.com"));
+    // the wildcard only matches subdomains
+    assert!(!capabilities.allows_host("slack.com"));
+    assert!(!capabilities.allows_host("evilslack.com"));
+    assert!(!PipeCapabilities::default().allows_host("api.openai.com"));
+}
+
+#[test]
+fn test_check_url() {
+    let capabilities = PipeCapabilities {
+        network: vec!["api.openai.com".to_string()],
+        ..Default::default()
+    };
+    assert!(capabilities
+        .check_url("https://api.openai.com/v1/chat/completions")
+        .is_ok());
+    assert_eq!(
+        capabilities.check_url("https://example.com/"),
+        Err("example.com isn't in the network capabilities of the pipe".to_string())
+    );
+    assert!(capabilities.check_url("file:///etc/passwd").is_err());
+    assert!(capabilities.check_url("not a url").is_err());
+}
+
+#[test]
+fn test_manifest() {
+    assert!(!is_wasm_pipe(&json!({"enabled": true, "crons": []})));
+    assert_eq!(
+        WasmPipeManifest::from_config(&json!({"enabled": true})).unwrap(),
+        None
+    );
+
+    let manifest = WasmPipeManifest::from_config(&json!({
+        "enabled": true,
+        "runtime": "wasm",
+        "capabilities": {"search": true, "network": ["api.openai.com"]}
+    }))
+    .unwrap()
+    .unwrap();
+    assert!(manifest.enabled);
+    assert_eq!(manifest.module, DEFAULT_WASM_MODULE);
+    assert_eq!(
+        manifest.capabilities,
+        PipeCapabilities {
+            search: true,
+            notifications: false,
+            network: vec!["api.openai.com".to_string()],
+        }
+    );
+
+    // capabilities are denied unless declared
+    let manifest = WasmPipeManifest::from_config(&json!({"runtime": "wasm"}))
+        .unwrap()
+        .unwrap();
+    assert!(!manifest.enabled);
+    assert_eq!(manifest.capabilities, PipeCapabilities::default());
+
+    assert!(WasmPipeManifest::from_config(&json!({
+        "runtime": "wasm",
+        "capabilities": {"network": "api.openai.com"}
+    }))
+    .is_err());
+}
+
+#[test]
+fn test_module_path() {
+    let pipe_dir = Path::new("/pipes/reminders");
+    let manifest = |module: &str| WasmPipeManifest {
+        enabled: true,
+        module: module.to_string(),
+        capabilities: PipeCapabilities::default(),
+        limits: PipeLimits::default(),
+    };
+    assert_eq!(
+        manifest("target/pipe.wasm").module_path(pipe_dir).unwrap(),
+        pipe_dir.join("target/pipe.wasm")
+    );
+    assert!(manifest("../other/pipe.wasm")
+        .module_path(pipe_dir)
+        .is_err());
+    assert!(manifest("/tmp/pipe.wasm").module_path(pipe_dir).is_err());
+}
+
+#[tokio::test]
+async fn test_read_manifest() {
+    let dir = tempdir().unwrap();
+    assert_eq!(read_manifest(dir.path()).await.unwrap(), None);
+
+    std::fs::write(
+        dir.path().join("pipe.json"),
+        json!({"enabled": true, "runtime": "wasm", "module": "reminders.wasm"}).to_string(),
+    )
+    .unwrap();
+    let manifest = read_manifest(dir.path()).await.unwrap().unwrap();
+    assert_eq!(manifest.module, "reminders.wasm");
+}
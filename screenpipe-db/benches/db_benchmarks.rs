@@ -2,7 +2,7 @@
 
 use criterion::{criterion_group, criterion_main, Criterion};
 use rand::Rng;
-use screenpipe_db::{AudioDevice, ContentType, DatabaseManager, DeviceType, OcrEngine};
+use screenpipe_db::{AudioDevice, ContentType, DatabaseManager, DeviceType, OcrEngine, SearchSort};
 use std::sync::Arc;
 use tokio::runtime::Runtime;
 
@@ -44,6 +44,8 @@ async fn setup_large_db(size: usize) -> DatabaseManager {
             None,
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -86,6 +88,10 @@ fn bench_search(c: &mut Criterion) {
                                 None,
                                 None,
                                 None,
+                                None,
+                                None,
+                                None,
+                                SearchSort::Time,
                             )
                             .await
                             .unwrap()
@@ -1,10 +1,14 @@
 use chrono::{DateTime, Utc};
 use image::DynamicImage;
-use libsqlite3_sys::sqlite3_auto_extension;
+use libsqlite3_sys::{self as ffi, sqlite3_auto_extension};
 use sqlite_vec::sqlite3_vec_init;
 use sqlx::migrate::MigrateDatabase;
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteSynchronous,
+};
 use sqlx::Column;
+use sqlx::ConnectOptions;
+use sqlx::Connection;
 use sqlx::Error as SqlxError;
 use sqlx::Row;
 use sqlx::TypeInfo;
@@ -13,25 +17,62 @@ use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, error, warn};
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::{CStr, CString};
+use std::hash::Hash;
+use std::path::Path;
+use std::ptr;
+use std::str::FromStr;
 
 use zerocopy::AsBytes;
 
-use futures::future::try_join_all;
+use futures::{future::join_all, TryStreamExt};
+
+use crate::schema::{backup_before_migrating, backup_version, migrator, schema_status};
+use crate::write_queue::WriteQueue;
 
 use crate::{
-    AudioChunksResponse, AudioDevice, AudioEntry, AudioResult, AudioResultRaw, ContentType,
-    DeviceType, FrameData, FrameRow, OCREntry, OCRResult, OCRResultRaw, OcrEngine, OcrTextBlock,
-    Order, SearchMatch, SearchResult, Speaker, TagContentType, TextBounds, TextPosition,
-    TimeSeriesChunk, UiContent, VideoMetadata,
+    AccessibilityNode, ActivitySegment, ActivitySummary, Annotation, AnnotationRaw,
+    AnnotationTarget, ApiToken, AppUsage, AppUsageBucket, AudioChunksResponse, AudioDevice,
+    AudioEntry, AudioResult, AudioResultRaw, BrowserContext, CalendarEvent, CalendarEventRaw,
+    ClipboardEntry, CompanionDevice, CompanionUpload, CompanionUploadRaw, ContentType,
+    ContextSwitchBucket, DeletedRecords, DeviceType, ExportTranscript, ExtractedTable, Extraction,
+    ExtractionRaw, ExtractionRule, ExtractionRuleRaw, FocusEvent, FocusInterruption, FocusStreak,
+    FrameData, FrameEntry, FramePreview, FrameRow, IdleGap, InputEvent, MediaBlock, MediaChunk,
+    MediaType, MeetingParticipant, MeetingSession, OCREntry, OCRResult, OCRResultRaw, OcrBlockRow,
+    OcrEngine, OcrLayoutBlock, OcrLayoutLine, OcrLayoutParagraph, OcrTextBlock, Order,
+    PrivacyAuditEntry, SavedSearch, SearchCursor, SearchMatch, SearchResult, SearchResultKind,
+    SearchSort, SemanticSearchResult, Speaker, SpeakingTimeBucket, Summary, SummaryRaw, SyncBatch,
+    SyncCursor, TableResult, TableResultRaw, TagContentType, TextBounds, TextPosition, TextSource,
+    TimeSeriesChunk, TimelineAppSwitch, TimelineCursor, TimelineEvent, TimelineEventKind,
+    TimelineFrame, TimelinePage, TimelineTranscript, TranscriptWord, UiContent, UnembeddedText,
+    UsageBucket, VideoChunkSpan, VideoMetadata, VocabularyEntry, VoiceProfile, WatchdogIncident,
+    Webhook, WebhookDeadLetter, WordCountBucket,
 };
 
+/// Prepared statements kept per connection, enough for the queries of the
+/// capture pipeline and the API to skip parsing.
+const STATEMENT_CACHE_CAPACITY: usize = 512;
+
 pub struct DatabaseManager {
     pub pool: SqlitePool,
+    /// Inserts of the capture pipeline, committed in batches
+    writes: WriteQueue,
 }
 
 impl DatabaseManager {
     pub async fn new(database_path: &str) -> Result<Self, sqlx::Error> {
+        Self::open(database_path, None).await
+    }
+
+    /// Opens a database encrypted with SQLCipher using the hex encoded
+    /// 256-bit `key`. An existing unencrypted database is encrypted first.
+    /// Needs screenpipe-db to be built with the `sqlcipher` feature.
+    pub async fn new_encrypted(database_path: &str, key: &str) -> Result<Self, sqlx::Error> {
+        Self::open(database_path, Some(key)).await
+    }
+
+    async fn open(database_path: &str, key: Option<&str>) -> Result<Self, sqlx::Error> {
         debug!(
             "Initializing DatabaseManager with database path: {}",
             database_path
@@ -51,50 +92,100 @@ impl DatabaseManager {
             sqlx::Sqlite::create_database(&connection_string).await?;
         }
 
+        // pragmas are set on every connection of the pool, not only the first
+        let mut options = SqliteConnectOptions::from_str(&connection_string)?
+            .journal_mode(SqliteJournalMode::Wal)
+            // durable across crashes of the app in WAL mode, only a power loss
+            // may lose the last commits
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(Duration::from_secs(5))
+            .statement_cache_capacity(STATEMENT_CACHE_CAPACITY)
+            .pragma("cache_size", "-64000")
+            .pragma("temp_store", "MEMORY")
+            .pragma("mmap_size", "268435456")
+            .pragma("wal_autocheckpoint", "1000");
+        if let Some(key) = key {
+            if is_plaintext_database(database_path) {
+                encrypt_plaintext_database(database_path, key).await?;
+            }
+            // sqlx sends the key pragma before any other one
+            options = options.pragma("key", sqlcipher_key(key));
+        }
+
         let pool = SqlitePoolOptions::new()
             .max_connections(50)
             .min_connections(3) // Minimum number of idle connections
             .acquire_timeout(Duration::from_secs(10))
-            .connect(&connection_string)
-            .await?;
-
-        // Enable WAL mode
-        sqlx::query("PRAGMA journal_mode = WAL;")
-            .execute(&pool)
+            .connect_with(options)
             .await?;
 
-        // Enable SQLite's query result caching
-        // PRAGMA cache_size = -2000; -- Set cache size to 2MB
-        // PRAGMA temp_store = MEMORY; -- Store temporary tables and indices in memory
-        sqlx::query("PRAGMA cache_size = -2000;")
-            .execute(&pool)
-            .await?;
-        sqlx::query("PRAGMA temp_store = MEMORY;")
-            .execute(&pool)
-            .await?;
-
-        let db_manager = DatabaseManager { pool };
+        if key.is_some() {
+            // without SQLCipher the key pragma is silently ignored
+            let cipher_version: Option<(String,)> = sqlx::query_as("PRAGMA cipher_version;")
+                .fetch_optional(&pool)
+                .await?;
+            if cipher_version.is_none() {
+                return Err(sqlx::Error::Configuration(
+                    "database encryption needs screenpipe to be built with SQLCipher".into(),
+                ));
+            }
+        }
 
         // Run migrations after establishing the connection
-        Self::run_migrations(&db_manager.pool).await?;
+        Self::run_migrations(&pool, database_path, key).await?;
 
-        Ok(db_manager)
+        let writes = WriteQueue::start(pool.clone()).map_err(sqlx::Error::Io)?;
+        Ok(DatabaseManager { pool, writes })
     }
 
-    async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
-        let mut migrator = sqlx::migrate!("./src/migrations");
-        migrator.set_ignore_missing(true);
-        match migrator.run(pool).await {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e.into()),
+    /// Applies the pending migrations, after copying the database if it
+    /// already holds data, see [`crate::migrate_database`].
+    async fn run_migrations(
+        pool: &SqlitePool,
+        database_path: &str,
+        key: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let mut conn = pool.acquire().await?;
+        let status = schema_status(&mut conn).await?;
+        // in-memory databases have nothing to lose
+        if let Some(version) =
+            backup_version(&status).filter(|_| Path::new(database_path).is_file())
+        {
+            backup_before_migrating(&mut conn, Path::new(database_path), key, version)
+                .await
+                .map_err(|e| {
+                    sqlx::Error::Io(std::io::Error::other(format!(
+                        "failed to back up the database before migrating: {}",
+                        e
+                    )))
+                })?;
         }
+        migrator().run(&mut *conn).await?;
+        Ok(())
+    }
+
+    /// Commits the queued writes and closes the connections, for a clean
+    /// shutdown. Writes fail afterwards. Waits for the connections in use to
+    /// be given back, the transactions left open on them are rolled back.
+    pub async fn close(&self) {
+        self.writes.close().await;
+        self.pool.close().await;
     }
 
     pub async fn insert_audio_chunk(&self, file_path: &str) -> Result<i64, sqlx::Error> {
+        self.insert_audio_chunk_at(file_path, Utc::now()).await
+    }
+
+    /// Inserts an audio chunk recorded at `timestamp`, e.g. uploaded later.
+    pub async fn insert_audio_chunk_at(
+        &self,
+        file_path: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
         let mut tx = self.pool.begin().await?;
         let id = sqlx::query("INSERT INTO audio_chunks (file_path, timestamp) VALUES (?1, ?2)")
             .bind(file_path)
-            .bind(Utc::now())
+            .bind(timestamp)
             .execute(&mut *tx)
             .await?
             .last_insert_rowid();
@@ -110,10 +201,16 @@ impl DatabaseManager {
         Ok(id.unwrap_or(0))
     }
 
-    pub async fn get_or_insert_audio_chunk(&self, file_path: &str) -> Result<i64, sqlx::Error> {
+    /// Id of the audio chunk at `file_path`, inserted as recorded at
+    /// `timestamp` if it's new.
+    pub async fn get_or_insert_audio_chunk(
+        &self,
+        file_path: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
         let mut id = self.get_audio_chunk_id(file_path).await?;
         if id == 0 {
-            id = self.insert_audio_chunk(file_path).await?;
+            id = self.insert_audio_chunk_at(file_path, timestamp).await?;
         }
         Ok(id)
     }
@@ -142,33 +239,76 @@ impl DatabaseManager {
         speaker_id: Option<i64>,
         start_time: Option<f64>,
         end_time: Option<f64>,
+        language: Option<&str>,
+        translated_text: Option<&str>,
     ) -> Result<i64, sqlx::Error> {
-        let text_length = transcription.len() as i64;
-        let mut tx = self.pool.begin().await?;
-
-        // Insert the full transcription
-        let id = sqlx::query(
-            "INSERT INTO audio_transcriptions (audio_chunk_id, transcription, offset_index, timestamp, transcription_engine, device, is_input_device, speaker_id, start_time, end_time, text_length) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        self.insert_audio_transcription_at(
+            audio_chunk_id,
+            transcription,
+            offset_index,
+            transcription_engine,
+            device,
+            speaker_id,
+            start_time,
+            end_time,
+            language,
+            translated_text,
+            Utc::now(),
         )
-        .bind(audio_chunk_id)
-        .bind(transcription)
-        .bind(offset_index)
-        .bind(Utc::now())
-        .bind(transcription_engine)
-        .bind(&device.name)
-        .bind(device.device_type == DeviceType::Input)
-        .bind(speaker_id)
-        .bind(start_time)
-        .bind(end_time)
-        .bind(text_length)
-        .execute(&mut *tx)
-        .await?
-        .last_insert_rowid();
-
-        // Commit the transaction for the full transcription
-        tx.commit().await?;
+        .await
+    }
 
-        Ok(id)
+    /// Inserts a transcription of audio spoken at `timestamp`, e.g. in a
+    /// recording uploaded later.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn insert_audio_transcription_at(
+        &self,
+        audio_chunk_id: i64,
+        transcription: &str,
+        offset_index: i64,
+        transcription_engine: &str,
+        device: &AudioDevice,
+        speaker_id: Option<i64>,
+        start_time: Option<f64>,
+        end_time: Option<f64>,
+        language: Option<&str>,
+        translated_text: Option<&str>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        let text_length = transcription.len() as i64;
+        let transcription = transcription.to_string();
+        let transcription_engine = transcription_engine.to_string();
+        let device_name = device.name.clone();
+        let is_input_device = device.device_type == DeviceType::Input;
+        let language = language.map(str::to_string);
+        let translated_text = translated_text.map(str::to_string);
+
+        self.writes
+            .write(move |conn| {
+                Box::pin(async move {
+                    let id = sqlx::query(
+                        "INSERT INTO audio_transcriptions (audio_chunk_id, transcription, offset_index, timestamp, transcription_engine, device, is_input_device, speaker_id, start_time, end_time, text_length, language, translated_text) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                    )
+                    .bind(audio_chunk_id)
+                    .bind(transcription)
+                    .bind(offset_index)
+                    .bind(timestamp)
+                    .bind(transcription_engine)
+                    .bind(device_name)
+                    .bind(is_input_device)
+                    .bind(speaker_id)
+                    .bind(start_time)
+                    .bind(end_time)
+                    .bind(text_length)
+                    .bind(language)
+                    .bind(translated_text)
+                    .execute(&mut *conn)
+                    .await?
+                    .last_insert_rowid();
+                    Ok(id)
+                })
+            })
+            .await
     }
 
     pub async fn update_audio_transcription(
@@ -190,11 +330,61 @@ impl DatabaseManager {
         .await?
         .rows_affected();
 
+        // the transcription is only ever shortened, drop the timings of the removed words
+        sqlx::query(
+            "DELETE FROM transcript_words WHERE audio_transcription_id IN (SELECT id FROM audio_transcriptions WHERE audio_chunk_id = ?1) AND word_index >= ?2",
+        )
+        .bind(audio_chunk_id)
+        .bind(transcription.split_whitespace().count() as i64)
+        .execute(&mut *tx)
+        .await?;
+
         // Commit the transaction for the full transcription
         tx.commit().await?;
         Ok(affected as i64)
     }
 
+    /// Stores the timings of the words of a transcription, in order.
+    pub async fn insert_transcript_words(
+        &self,
+        audio_transcription_id: i64,
+        words: &[TranscriptWord],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        for (index, word) in words.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO transcript_words (audio_transcription_id, word_index, word, start_time, end_time, confidence) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .bind(audio_transcription_id)
+            .bind(index as i64)
+            .bind(&word.word)
+            .bind(word.start_time)
+            .bind(word.end_time)
+            .bind(word.confidence)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_transcript_words(
+        &self,
+        audio_transcription_id: i64,
+    ) -> Result<Vec<TranscriptWord>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT word, start_time, end_time, confidence
+            FROM transcript_words
+            WHERE audio_transcription_id = ?1
+            ORDER BY word_index ASC
+            "#,
+        )
+        .bind(audio_transcription_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
     pub async fn insert_speaker(&self, embedding: &[f32]) -> Result<Speaker, SqlxError> {
         let mut tx = self.pool.begin().await?;
 
@@ -250,15 +440,17 @@ impl DatabaseManager {
         let speaker_threshold = 0.5;
         let bytes: &[u8] = embedding.as_bytes();
 
-        // Using subquery with LIMIT 1 instead of JOIN
+        // Using subquery with LIMIT 1 instead of JOIN, enrolled voice
+        // profiles win over the speakers diarization found on its own
         let speaker = sqlx::query_as(
             "SELECT id, name, metadata
              FROM speakers
              WHERE id = (
-                 SELECT speaker_id
+                 SELECT speaker_embeddings.speaker_id
                  FROM speaker_embeddings
+                 JOIN speakers ON speakers.id = speaker_embeddings.speaker_id
                  WHERE vec_distance_cosine(embedding, vec_f32(?1)) < ?2
-                 ORDER BY vec_distance_cosine(embedding, vec_f32(?1))
+                 ORDER BY speakers.enrolled DESC, vec_distance_cosine(embedding, vec_f32(?1))
                  LIMIT 1
              )",
         )
@@ -301,66 +493,132 @@ impl DatabaseManager {
         &self,
         device_name: &str,
         timestamp: Option<DateTime<Utc>>,
-        browser_url: Option<&str>,
+        browser_context: Option<&BrowserContext>,
         app_name: Option<&str>,
         window_name: Option<&str>,
         focused: bool,
     ) -> Result<i64, sqlx::Error> {
-        let mut tx = self.pool.begin().await?;
-        debug!("insert_frame Transaction started");
+        let device_name = device_name.to_string();
+        let browser_url = browser_context.map(|c| c.url.clone());
+        let browser_context = browser_context.and_then(|c| serde_json::to_string(c).ok());
+        let app_name = app_name.map(str::to_string);
+        let window_name = window_name.map(str::to_string);
+        let timestamp = timestamp.unwrap_or_else(Utc::now);
 
-        // Get the most recent video_chunk_id and file_path
-        let video_chunk: Option<(i64, String)> = sqlx::query_as(
-            "SELECT id, file_path FROM video_chunks WHERE device_name = ?1 ORDER BY id DESC LIMIT 1",
-        )
-        .bind(device_name)
-        .fetch_optional(&mut *tx)
-        .await?;
-        debug!("Fetched most recent video_chunk: {:?}", video_chunk);
+        self.writes
+            .write(move |conn| {
+                Box::pin(async move {
+                    // Get the most recent video_chunk_id and file_path
+                    let video_chunk: Option<(i64, String)> = sqlx::query_as(
+                        "SELECT id, file_path FROM video_chunks WHERE device_name = ?1 ORDER BY id DESC LIMIT 1",
+                    )
+                    .bind(&device_name)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+                    debug!("Fetched most recent video_chunk: {:?}", video_chunk);
 
-        // If no video chunk is found, return 0
-        let (video_chunk_id, file_path) = match video_chunk {
-            Some((id, path)) => (id, path),
-            None => {
-                debug!("No video chunk found, rolling back transaction");
-                tx.rollback().await?;
-                return Ok(0);
-            }
-        };
+                    // If no video chunk is found, return 0
+                    let Some((video_chunk_id, file_path)) = video_chunk else {
+                        debug!("No video chunk found, no frame inserted");
+                        return Ok(0);
+                    };
 
-        // Calculate the offset_index
-        let offset_index: i64 = sqlx::query_scalar(
-            "SELECT COALESCE(MAX(offset_index), -1) + 1 FROM frames WHERE video_chunk_id = ?1",
-        )
-        .bind(video_chunk_id)
-        .fetch_one(&mut *tx)
-        .await?;
-        debug!("insert_frame Calculated offset_index: {}", offset_index);
+                    // Calculate the offset_index
+                    let offset_index: i64 = sqlx::query_scalar(
+                        "SELECT COALESCE(MAX(offset_index), -1) + 1 FROM frames WHERE video_chunk_id = ?1",
+                    )
+                    .bind(video_chunk_id)
+                    .fetch_one(&mut *conn)
+                    .await?;
+                    debug!("insert_frame Calculated offset_index: {}", offset_index);
 
-        let timestamp = timestamp.unwrap_or_else(Utc::now);
+                    // Insert the new frame with file_path as name and app/window metadata
+                    let id = sqlx::query(
+                        "INSERT INTO frames (video_chunk_id, offset_index, timestamp, name, browser_url, browser_context, app_name, window_name, focused, device_name) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    )
+                    .bind(video_chunk_id)
+                    .bind(offset_index)
+                    .bind(timestamp)
+                    .bind(file_path)
+                    .bind(browser_url)
+                    .bind(browser_context)
+                    .bind(app_name)
+                    .bind(window_name)
+                    .bind(focused)
+                    .bind(device_name)
+                    .execute(&mut *conn)
+                    .await?
+                    .last_insert_rowid();
+                    debug!("insert_frame Inserted new frame with id: {}", id);
 
-        // Insert the new frame with file_path as name and app/window metadata
-        let id = sqlx::query(
-            "INSERT INTO frames (video_chunk_id, offset_index, timestamp, name, browser_url, app_name, window_name, focused, device_name) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        )
-        .bind(video_chunk_id)
-        .bind(offset_index)
-        .bind(timestamp)
-        .bind(file_path)
-        .bind(browser_url)
-        .bind(app_name)
-        .bind(window_name)
-        .bind(focused)
-        .bind(device_name)
-        .execute(&mut *tx)
-        .await?
-        .last_insert_rowid();
-        debug!("insert_frame Inserted new frame with id: {}", id);
+                    Ok(id)
+                })
+            })
+            .await
+    }
 
-        // Commit the transaction
-        tx.commit().await?;
+    /// Stores the accessibility tree of the focused window of a frame.
+    pub async fn insert_frame_accessibility_tree(
+        &self,
+        frame_id: i64,
+        tree: &AccessibilityNode,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE frames SET accessibility_tree = ?1 WHERE id = ?2")
+            .bind(serde_json::to_string(tree).ok())
+            .bind(frame_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
 
-        Ok(id)
+    /// Stores the perceptual hash of the screen a frame was captured from.
+    pub async fn set_frame_phash(&self, frame_id: i64, phash: u64) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE frames SET phash = ?1 WHERE id = ?2")
+            // stored as the same 64 bits, sqlite integers are signed
+            .bind(phash as i64)
+            .bind(frame_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Counts a near-identical capture of the screen shown in `frame_ids`,
+    /// which stay on screen until `timestamp`.
+    pub async fn record_frame_repeat(
+        &self,
+        frame_ids: &[i64],
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        if frame_ids.is_empty() {
+            return Ok(());
+        }
+
+        let placeholders = vec!["?"; frame_ids.len()].join(",");
+        let sql = format!(
+            "UPDATE frames SET repeat_count = repeat_count + 1, last_repeat_at = ? WHERE id IN ({})",
+            placeholders
+        );
+        let mut query = sqlx::query(&sql).bind(timestamp);
+        for id in frame_ids {
+            query = query.bind(id);
+        }
+        query.execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Accessibility tree stored with a frame, `None` if the frame doesn't
+    /// exist or has none.
+    pub async fn get_frame_accessibility_tree(
+        &self,
+        frame_id: i64,
+    ) -> Result<Option<AccessibilityNode>, sqlx::Error> {
+        let tree: Option<String> =
+            sqlx::query_scalar("SELECT accessibility_tree FROM frames WHERE id = ?1")
+                .bind(frame_id)
+                .fetch_optional(&self.pool)
+                .await?
+                .flatten();
+        Ok(tree.and_then(|tree| serde_json::from_str(&tree).ok()))
     }
 
     pub async fn insert_ocr_text(
@@ -371,55 +629,409 @@ impl DatabaseManager {
         ocr_engine: Arc<OcrEngine>,
     ) -> Result<(), sqlx::Error> {
         let text_length = text.len() as i64;
+        let text = text.to_string();
+        let text_json = text_json.to_string();
+        let ocr_engine = format!("{:?}", *ocr_engine);
+        self.writes
+            .write(move |conn| {
+                Box::pin(async move {
+                    sqlx::query("INSERT INTO ocr_text (frame_id, text, text_json, ocr_engine, text_length) VALUES (?1, ?2, ?3, ?4, ?5)")
+                        .bind(frame_id)
+                        .bind(text)
+                        .bind(text_json)
+                        .bind(ocr_engine)
+                        .bind(text_length)
+                        .execute(&mut *conn)
+                        .await?;
+                    Ok(())
+                })
+            })
+            .await?;
+        debug!("OCR text inserted into db successfully");
+        Ok(())
+    }
+
+    /// Replaces the OCR text of a frame, e.g. once OCR deferred on battery
+    /// ran. Returns `false` if the frame was deleted in the meantime.
+    pub async fn replace_ocr_text(
+        &self,
+        frame_id: i64,
+        text: &str,
+        text_json: &str,
+        ocr_engine: Arc<OcrEngine>,
+    ) -> Result<bool, sqlx::Error> {
         let mut tx = self.pool.begin().await?;
-        sqlx::query("INSERT INTO ocr_text (frame_id, text, text_json, ocr_engine, text_length) VALUES (?1, ?2, ?3, ?4, ?5)")
+        // deleted and inserted again, the fts update trigger skips empty text
+        sqlx::query("DELETE FROM ocr_text WHERE frame_id = ?1")
             .bind(frame_id)
-            .bind(text)
-            .bind(text_json)
-            .bind(format!("{:?}", *ocr_engine))
-            .bind(text_length)
             .execute(&mut *tx)
             .await?;
+        let inserted = sqlx::query(
+            r#"INSERT INTO ocr_text (frame_id, text, text_json, ocr_engine, text_length)
+               SELECT ?1, ?2, ?3, ?4, ?5
+               WHERE EXISTS (SELECT 1 FROM frames WHERE id = ?1)"#,
+        )
+        .bind(frame_id)
+        .bind(text)
+        .bind(text_json)
+        .bind(format!("{:?}", *ocr_engine))
+        .bind(text.len() as i64)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+        tx.commit().await?;
+        Ok(inserted)
+    }
+
+    /// Stores the layout of a frame's OCR text. Blocks are expected in reading
+    /// order.
+    pub async fn insert_ocr_blocks(
+        &self,
+        frame_id: i64,
+        blocks: &[OcrLayoutBlock],
+    ) -> Result<(), sqlx::Error> {
+        if blocks.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self.pool.begin().await?;
+        for (block_index, block) in blocks.iter().enumerate() {
+            insert_ocr_block_row(
+                &mut tx,
+                frame_id,
+                "block",
+                (block_index as i64, None, None),
+                &block.text(),
+                None,
+                &block.bounds,
+                None,
+            )
+            .await?;
+
+            for (paragraph_index, paragraph) in block.paragraphs.iter().enumerate() {
+                insert_ocr_block_row(
+                    &mut tx,
+                    frame_id,
+                    "paragraph",
+                    (block_index as i64, Some(paragraph_index as i64), None),
+                    &paragraph.text(),
+                    None,
+                    &paragraph.bounds,
+                    None,
+                )
+                .await?;
 
+                for (line_index, line) in paragraph.lines.iter().enumerate() {
+                    insert_ocr_block_row(
+                        &mut tx,
+                        frame_id,
+                        "line",
+                        (
+                            block_index as i64,
+                            Some(paragraph_index as i64),
+                            Some(line_index as i64),
+                        ),
+                        &line.text,
+                        Some(line.confidence),
+                        &line.bounds,
+                        Some(line.source),
+                    )
+                    .await?;
+                }
+            }
+        }
         tx.commit().await?;
-        debug!("OCR text inserted into db successfully");
+        debug!(
+            "inserted {} ocr blocks for frame {}",
+            blocks.len(),
+            frame_id
+        );
         Ok(())
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub async fn search(
+    /// Returns the OCR layout of the given frames, keyed by frame id. Frames
+    /// without layout data are left out.
+    pub async fn get_ocr_blocks(
         &self,
-        query: &str,
-        mut content_type: ContentType,
-        limit: u32,
-        offset: u32,
-        start_time: Option<DateTime<Utc>>,
-        end_time: Option<DateTime<Utc>>,
-        app_name: Option<&str>,
-        window_name: Option<&str>,
-        min_length: Option<usize>,
-        max_length: Option<usize>,
-        speaker_ids: Option<Vec<i64>>,
-        frame_name: Option<&str>,
-        browser_url: Option<&str>,
-        focused: Option<bool>,
-    ) -> Result<Vec<SearchResult>, sqlx::Error> {
-        let mut results = Vec::new();
+        frame_ids: &[i64],
+    ) -> Result<HashMap<i64, Vec<OcrLayoutBlock>>, sqlx::Error> {
+        let mut result: HashMap<i64, Vec<OcrLayoutBlock>> = HashMap::new();
+        if frame_ids.is_empty() {
+            return Ok(result);
+        }
 
-        // if focused or browser_url is present, we run only on OCR
-        if focused.is_some() || browser_url.is_some() {
-            content_type = ContentType::OCR;
+        let placeholders = vec!["?"; frame_ids.len()].join(",");
+        let sql = format!(
+            r#"
+            SELECT frame_id, level, block_index, text, confidence, left, top, width, height, source
+            FROM ocr_blocks
+            WHERE frame_id IN ({})
+            -- nulls sort first, so a block comes before its paragraphs and lines
+            ORDER BY frame_id, block_index, paragraph_index, line_index
+            "#,
+            placeholders
+        );
+
+        let mut query = sqlx::query_as::<_, OcrBlockRow>(&sql);
+        for id in frame_ids {
+            query = query.bind(id);
+        }
+        let rows = query.fetch_all(&self.pool).await?;
+
+        // rows are sorted so parents always come before their children
+        for row in rows {
+            let bounds = TextBounds {
+                left: row.left as f32,
+                top: row.top as f32,
+                width: row.width as f32,
+                height: row.height as f32,
+            };
+            let blocks = result.entry(row.frame_id).or_default();
+            match row.level.as_str() {
+                "block" => blocks.push(OcrLayoutBlock {
+                    reading_order: row.block_index as u32,
+                    bounds,
+                    paragraphs: Vec::new(),
+                }),
+                "paragraph" => {
+                    if let Some(block) = blocks.last_mut() {
+                        block.paragraphs.push(OcrLayoutParagraph {
+                            bounds,
+                            lines: Vec::new(),
+                        });
+                    }
+                }
+                "line" => {
+                    if let Some(paragraph) = blocks
+                        .last_mut()
+                        .and_then(|block| block.paragraphs.last_mut())
+                    {
+                        paragraph.lines.push(OcrLayoutLine {
+                            text: row.text,
+                            confidence: row.confidence.unwrap_or(0.0) as f32,
+                            bounds,
+                            source: match row.source.as_deref() {
+                                Some("accessibility") => TextSource::Accessibility,
+                                _ => TextSource::Ocr,
+                            },
+                        });
+                    }
+                }
+                other => warn!("unknown ocr block level: {}", other),
+            }
         }
 
-        match content_type {
-            ContentType::All => {
-                let (ocr_results, audio_results, ui_results) =
-                    if app_name.is_none() && window_name.is_none() && frame_name.is_none() {
-                        // Run all three queries in parallel
-                        let (ocr, audio, ui) = tokio::try_join!(
-                            self.search_ocr(
-                                query,
-                                limit,
+        result.retain(|_, blocks| !blocks.is_empty());
+        Ok(result)
+    }
+
+    /// Stores the tables detected in a frame.
+    pub async fn insert_ocr_tables(
+        &self,
+        frame_id: i64,
+        tables: &[ExtractedTable],
+    ) -> Result<(), sqlx::Error> {
+        if tables.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self.pool.begin().await?;
+        for (table_index, table) in tables.iter().enumerate() {
+            let cells = serde_json::to_string(&table.rows).unwrap_or_else(|_| "[]".to_string());
+            sqlx::query(
+                "INSERT INTO ocr_tables (frame_id, table_index, row_count, column_count, cells, text, left, top, width, height) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )
+            .bind(frame_id)
+            .bind(table_index as i64)
+            .bind(table.rows.len() as i64)
+            .bind(table.column_count() as i64)
+            .bind(cells)
+            .bind(table.text())
+            .bind(table.bounds.left)
+            .bind(table.bounds.top)
+            .bind(table.bounds.width)
+            .bind(table.bounds.height)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Rewrites `query` so that each of its words also matches the indexed
+    /// words at most `edit_distance` typos away, for text the ocr got
+    /// slightly wrong like "rnicrosoft". Phrases, prefixes, excluded words
+    /// and words of up to twice `edit_distance` letters are left as they are.
+    pub async fn fuzzy_query(
+        &self,
+        query: &str,
+        edit_distance: u32,
+    ) -> Result<String, sqlx::Error> {
+        let edit_distance = edit_distance.min(MAX_EDIT_DISTANCE) as usize;
+        let mut tokens = Vec::new();
+        let mut negated = false;
+        for token in fts_tokens(query) {
+            match token {
+                FtsToken::Term {
+                    text,
+                    quoted: false,
+                    prefix: false,
+                } if !negated && text.chars().count() > edit_distance * 2 => {
+                    let variants = self.similar_fts_words(&text, edit_distance).await?;
+                    tokens.push(FtsToken::Open);
+                    for variant in std::iter::once(text).chain(variants) {
+                        if !matches!(tokens.last(), Some(FtsToken::Open)) {
+                            tokens.push(FtsToken::Or);
+                        }
+                        tokens.push(FtsToken::Term {
+                            text: variant,
+                            quoted: false,
+                            prefix: false,
+                        });
+                    }
+                    tokens.push(FtsToken::Close);
+                }
+                token => {
+                    negated = matches!(token, FtsToken::Not);
+                    tokens.push(token);
+                }
+            }
+        }
+        Ok(fts_expression(tokens).unwrap_or_default())
+    }
+
+    /// Indexed words at most `edit_distance` typos away from `word`, closest
+    /// and most frequent first.
+    async fn similar_fts_words(
+        &self,
+        word: &str,
+        edit_distance: usize,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        let word = word.to_lowercase();
+        let length = word.chars().count();
+        let terms: Vec<(String, i64)> = sqlx::query_as(
+            r#"
+            SELECT term, SUM(doc) AS docs
+            FROM (
+                SELECT term, doc FROM ocr_text_fts_vocab
+                UNION ALL
+                SELECT term, doc FROM audio_transcriptions_fts_vocab
+                UNION ALL
+                SELECT term, doc FROM ui_monitoring_fts_vocab
+            )
+            WHERE length(term) BETWEEN ?1 AND ?2 AND term != ?3
+            GROUP BY term
+            "#,
+        )
+        .bind(length.saturating_sub(edit_distance) as i64)
+        .bind((length + edit_distance) as i64)
+        .bind(&word)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut similar: Vec<(usize, i64, String)> = terms
+            .into_iter()
+            .filter_map(|(term, docs)| {
+                let distance = strsim::levenshtein(&word, &term);
+                (distance <= edit_distance).then_some((distance, docs, term))
+            })
+            .collect();
+        similar.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        similar.truncate(MAX_FUZZY_VARIANTS);
+        Ok(similar.into_iter().map(|(_, _, term)| term).collect())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search(
+        &self,
+        query: &str,
+        content_type: ContentType,
+        limit: u32,
+        offset: u32,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<Vec<i64>>,
+        frame_name: Option<&str>,
+        browser_url: Option<&str>,
+        focused: Option<bool>,
+        language: Option<&str>,
+        tag: Option<&str>,
+        meeting: Option<&str>,
+        sort: SearchSort,
+    ) -> Result<Vec<SearchResult>, sqlx::Error> {
+        self.search_after(
+            query,
+            content_type,
+            limit,
+            offset,
+            start_time,
+            end_time,
+            app_name,
+            window_name,
+            min_length,
+            max_length,
+            speaker_ids,
+            frame_name,
+            browser_url,
+            focused,
+            language,
+            tag,
+            meeting,
+            sort,
+            None,
+        )
+        .await
+    }
+
+    /// Search results after `cursor`, in the order of [`SearchCursor`] unless
+    /// sorted by relevance. Paging with the cursor of the last result stays
+    /// fast however deep it goes, unlike `offset`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_after(
+        &self,
+        query: &str,
+        mut content_type: ContentType,
+        limit: u32,
+        offset: u32,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<Vec<i64>>,
+        frame_name: Option<&str>,
+        browser_url: Option<&str>,
+        focused: Option<bool>,
+        language: Option<&str>,
+        tag: Option<&str>,
+        meeting: Option<&str>,
+        sort: SearchSort,
+        cursor: Option<SearchCursor>,
+    ) -> Result<Vec<SearchResult>, sqlx::Error> {
+        let cursor = cursor.as_ref();
+        let mut results = Vec::new();
+
+        // if focused or browser_url is present, we run only on OCR
+        if focused.is_some() || browser_url.is_some() {
+            content_type = ContentType::OCR;
+        }
+
+        match content_type {
+            ContentType::All => {
+                let (ocr_results, audio_results, ui_results) =
+                    if app_name.is_none() && window_name.is_none() && frame_name.is_none() {
+                        // Run all three queries in parallel
+                        let (ocr, audio, ui) = tokio::try_join!(
+                            self.search_ocr(
+                                query,
+                                limit,
                                 offset,
                                 start_time,
                                 end_time,
@@ -430,6 +1042,10 @@ impl DatabaseManager {
                                 frame_name,
                                 browser_url,
                                 focused,
+                                tag,
+                                meeting,
+                                sort,
+                                cursor,
                             ),
                             self.search_audio(
                                 query,
@@ -439,7 +1055,12 @@ impl DatabaseManager {
                                 end_time,
                                 min_length,
                                 max_length,
-                                speaker_ids
+                                speaker_ids,
+                                language,
+                                tag,
+                                meeting,
+                                sort,
+                                cursor,
                             ),
                             self.search_ui_monitoring(
                                 query,
@@ -449,6 +1070,10 @@ impl DatabaseManager {
                                 end_time,
                                 limit,
                                 offset,
+                                tag,
+                                meeting,
+                                sort,
+                                cursor,
                             )
                         )?;
                         (ocr, Some(audio), ui)
@@ -468,6 +1093,10 @@ impl DatabaseManager {
                                 frame_name,
                                 browser_url,
                                 focused,
+                                tag,
+                                meeting,
+                                sort,
+                                cursor,
                             ),
                             self.search_ui_monitoring(
                                 query,
@@ -477,6 +1106,10 @@ impl DatabaseManager {
                                 end_time,
                                 limit,
                                 offset,
+                                tag,
+                                meeting,
+                                sort,
+                                cursor,
                             )
                         )?;
                         (ocr, None, ui)
@@ -503,6 +1136,10 @@ impl DatabaseManager {
                         frame_name,
                         browser_url,
                         focused,
+                        tag,
+                        meeting,
+                        sort,
+                        cursor,
                     )
                     .await?;
                 results.extend(ocr_results.into_iter().map(SearchResult::OCR));
@@ -519,6 +1156,11 @@ impl DatabaseManager {
                             min_length,
                             max_length,
                             speaker_ids,
+                            language,
+                            tag,
+                            meeting,
+                            sort,
+                            cursor,
                         )
                         .await?;
                     results.extend(audio_results.into_iter().map(SearchResult::Audio));
@@ -534,6 +1176,10 @@ impl DatabaseManager {
                         end_time,
                         limit,
                         offset,
+                        tag,
+                        meeting,
+                        sort,
+                        cursor,
                     )
                     .await?;
                 results.extend(ui_results.into_iter().map(SearchResult::UI));
@@ -549,6 +1195,11 @@ impl DatabaseManager {
                         min_length,
                         max_length,
                         speaker_ids,
+                        language,
+                        tag,
+                        meeting,
+                        sort,
+                        cursor,
                     )
                     .await?;
                 let ui_results = self
@@ -560,6 +1211,10 @@ impl DatabaseManager {
                         end_time,
                         limit / 2,
                         offset,
+                        tag,
+                        meeting,
+                        sort,
+                        cursor,
                     )
                     .await?;
 
@@ -581,6 +1236,10 @@ impl DatabaseManager {
                         frame_name,
                         browser_url,
                         focused,
+                        tag,
+                        meeting,
+                        sort,
+                        cursor,
                     )
                     .await?;
                 let ui_results = self
@@ -592,6 +1251,10 @@ impl DatabaseManager {
                         end_time,
                         limit / 2,
                         offset,
+                        tag,
+                        meeting,
+                        sort,
+                        cursor,
                     )
                     .await?;
 
@@ -609,6 +1272,11 @@ impl DatabaseManager {
                         min_length,
                         max_length,
                         speaker_ids,
+                        language,
+                        tag,
+                        meeting,
+                        sort,
+                        cursor,
                     )
                     .await?;
                 let ocr_results = self
@@ -625,27 +1293,63 @@ impl DatabaseManager {
                         frame_name,
                         browser_url,
                         focused,
+                        tag,
+                        meeting,
+                        sort,
+                        cursor,
                     )
                     .await?;
 
                 results.extend(audio_results.into_iter().map(SearchResult::Audio));
                 results.extend(ocr_results.into_iter().map(SearchResult::OCR));
             }
+            // tables and clipboard entries can't be tagged or placed in meetings
+            ContentType::Table | ContentType::Clipboard if tag.is_some() || meeting.is_some() => {}
+            ContentType::Table => {
+                let table_results = self
+                    .search_tables(
+                        query,
+                        limit,
+                        offset,
+                        start_time,
+                        end_time,
+                        app_name,
+                        window_name,
+                        cursor,
+                    )
+                    .await?;
+                results.extend(table_results.into_iter().map(SearchResult::Table));
+            }
+            ContentType::Clipboard => {
+                let clipboard_results = self
+                    .search_clipboard(
+                        query,
+                        limit,
+                        offset,
+                        start_time,
+                        end_time,
+                        app_name,
+                        window_name,
+                        cursor,
+                    )
+                    .await?;
+                results.extend(clipboard_results.into_iter().map(SearchResult::Clipboard));
+            }
         }
 
-        // Sort results by timestamp in descending order
+        // Sort results by timestamp in descending order, or by rank first
+        // for relevance, results without one last
         results.sort_by(|a, b| {
-            let timestamp_a = match a {
-                SearchResult::OCR(ocr) => ocr.timestamp,
-                SearchResult::Audio(audio) => audio.timestamp,
-                SearchResult::UI(ui) => ui.timestamp,
+            let by_rank = match (sort, a.rank(), b.rank()) {
+                (SearchSort::Relevance, Some(rank_a), Some(rank_b)) => rank_a.total_cmp(&rank_b),
+                (SearchSort::Relevance, Some(_), None) => std::cmp::Ordering::Less,
+                (SearchSort::Relevance, None, Some(_)) => std::cmp::Ordering::Greater,
+                _ => std::cmp::Ordering::Equal,
             };
-            let timestamp_b = match b {
-                SearchResult::OCR(ocr) => ocr.timestamp,
-                SearchResult::Audio(audio) => audio.timestamp,
-                SearchResult::UI(ui) => ui.timestamp,
-            };
-            timestamp_b.cmp(&timestamp_a)
+            by_rank
+                .then_with(|| b.timestamp().cmp(&a.timestamp()))
+                .then_with(|| a.kind().cmp(&b.kind()))
+                .then_with(|| b.cursor().id.cmp(&a.cursor().id))
         });
 
         // Apply offset and limit after sorting
@@ -673,7 +1377,12 @@ impl DatabaseManager {
         frame_name: Option<&str>,
         browser_url: Option<&str>,
         focused: Option<bool>,
+        tag: Option<&str>,
+        meeting: Option<&str>,
+        sort: SearchSort,
+        cursor: Option<&SearchCursor>,
     ) -> Result<Vec<OCRResult>, sqlx::Error> {
+        let query = fts_query(query).unwrap_or_default();
         let mut frame_fts_parts = Vec::new();
 
         if let Some(app) = app_name {
@@ -688,7 +1397,7 @@ impl DatabaseManager {
         }
         if let Some(browser) = browser_url {
             if !browser.is_empty() {
-                frame_fts_parts.push(format!("browser_url:{}", browser));
+                frame_fts_parts.push(format!("browser_url:{}", fts_phrase(browser)));
             }
         }
         if let Some(is_focused) = focused {
@@ -718,7 +1427,9 @@ impl DatabaseManager {
             video_chunks.device_name,
             GROUP_CONCAT(tags.name, ',') as tags,
             frames.browser_url,
-            frames.focused
+            frames.browser_context,
+            frames.focused,
+            {rank_columns}
         FROM frames
         JOIN video_chunks ON frames.video_chunk_id = video_chunks.id
         JOIN ocr_text ON frames.id = ocr_text.frame_id
@@ -733,10 +1444,26 @@ impl DatabaseManager {
             AND (?3 IS NULL OR frames.timestamp <= ?3)
             AND (?4 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) >= ?4)
             AND (?5 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) <= ?5)
+            AND (?9 IS NULL OR {tag_condition})
+            AND (?10 IS NULL OR {meeting_condition})
+            AND {after_cursor}
         GROUP BY frames.id
-        ORDER BY frames.timestamp DESC
+        ORDER BY {order_by}
         LIMIT ?7 OFFSET ?8
         "#,
+            tag_condition = tag_condition(TaggedContent::Frame, "?9"),
+            meeting_condition = meeting_condition("frames.timestamp", "?10"),
+            after_cursor = after_search_cursor("frames.timestamp", "frames.id", "?11", "?12"),
+            rank_columns = if query.is_empty() {
+                "NULL as rank, NULL as snippet".to_string()
+            } else {
+                fts_rank_columns("ocr_text_fts", OCR_FTS_WEIGHTS)
+            },
+            order_by = if sort == SearchSort::Relevance && !query.is_empty() {
+                "rank, frames.timestamp DESC, frames.id DESC"
+            } else {
+                "frames.timestamp DESC, frames.id DESC"
+            },
             frame_fts_join = if frame_query.trim().is_empty() {
                 ""
             } else {
@@ -771,38 +1498,17 @@ impl DatabaseManager {
             .bind(end_time)
             .bind(min_length.map(|l| l as i64))
             .bind(max_length.map(|l| l as i64))
-            .bind(if query.trim().is_empty() {
-                None
-            } else {
-                Some(query)
-            })
+            .bind(if query.is_empty() { None } else { Some(&query) })
             .bind(limit)
             .bind(offset)
+            .bind(tag)
+            .bind(meeting)
+            .bind(cursor.map(|cursor| cursor.timestamp))
+            .bind(cursor.map(|cursor| cursor.id_bound(SearchResultKind::Ocr)))
             .fetch_all(&self.pool)
             .await?;
 
-        Ok(raw_results
-            .into_iter()
-            .map(|raw| OCRResult {
-                frame_id: raw.frame_id,
-                ocr_text: raw.ocr_text,
-                text_json: raw.text_json,
-                timestamp: raw.timestamp,
-                frame_name: raw.frame_name,
-                file_path: raw.file_path,
-                offset_index: raw.offset_index,
-                app_name: raw.app_name,
-                ocr_engine: raw.ocr_engine,
-                window_name: raw.window_name,
-                device_name: raw.device_name,
-                tags: raw
-                    .tags
-                    .map(|t| t.split(',').map(String::from).collect())
-                    .unwrap_or_default(),
-                browser_url: raw.browser_url,
-                focused: raw.focused,
-            })
-            .collect())
+        Ok(raw_results.into_iter().map(ocr_result_from_raw).collect())
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -816,10 +1522,18 @@ impl DatabaseManager {
         min_length: Option<usize>,
         max_length: Option<usize>,
         speaker_ids: Option<Vec<i64>>,
+        language: Option<&str>,
+        tag: Option<&str>,
+        meeting: Option<&str>,
+        sort: SearchSort,
+        cursor: Option<&SearchCursor>,
     ) -> Result<Vec<AudioResult>, sqlx::Error> {
+        let query = fts_query(query).unwrap_or_default();
+        let query = query.as_str();
         // base query for audio search
-        let mut base_sql = String::from(
+        let mut base_sql = format!(
             "SELECT
+                audio_transcriptions.id as transcription_id,
                 audio_transcriptions.audio_chunk_id,
                 audio_transcriptions.transcription,
                 audio_transcriptions.timestamp,
@@ -831,12 +1545,20 @@ impl DatabaseManager {
                 audio_transcriptions.is_input_device,
                 audio_transcriptions.speaker_id,
                 audio_transcriptions.start_time,
-                audio_transcriptions.end_time
+                audio_transcriptions.end_time,
+                audio_transcriptions.language,
+                audio_transcriptions.translated_text,
+                {}
              FROM audio_transcriptions
              JOIN audio_chunks ON audio_transcriptions.audio_chunk_id = audio_chunks.id
              LEFT JOIN speakers ON audio_transcriptions.speaker_id = speakers.id
              LEFT JOIN audio_tags ON audio_chunks.id = audio_tags.audio_chunk_id
              LEFT JOIN tags ON audio_tags.tag_id = tags.id",
+            if query.is_empty() {
+                "NULL as rank, NULL as snippet".to_string()
+            } else {
+                fts_rank_columns("audio_transcriptions_fts", AUDIO_FTS_WEIGHTS)
+            }
         );
         // if query is provided, join the corresponding fts table
         if !query.is_empty() {
@@ -864,6 +1586,22 @@ impl DatabaseManager {
         if speaker_ids.is_some() {
             conditions.push("(json_array_length(?) = 0 OR audio_transcriptions.speaker_id IN (SELECT value FROM json_each(?)))");
         }
+        if language.is_some() {
+            conditions.push("audio_transcriptions.language = ?");
+        }
+        let tagged = tag_condition(TaggedContent::Transcription, "?");
+        if tag.is_some() {
+            conditions.push(&tagged);
+        }
+        let in_meeting = meeting_condition("audio_transcriptions.timestamp", "?");
+        if meeting.is_some() {
+            conditions.push(&in_meeting);
+        }
+        if cursor.is_some() {
+            conditions.push(
+                "(audio_transcriptions.timestamp < ? OR (audio_transcriptions.timestamp = ? AND audio_transcriptions.id < ?))",
+            );
+        }
 
         let where_clause = if conditions.is_empty() {
             "WHERE 1=1".to_owned()
@@ -872,9 +1610,14 @@ impl DatabaseManager {
         };
 
         // complete sql with group, order, limit and offset
+        let order_by = if sort == SearchSort::Relevance && !query.is_empty() {
+            "rank, audio_transcriptions.timestamp DESC, audio_transcriptions.id DESC"
+        } else {
+            "audio_transcriptions.timestamp DESC, audio_transcriptions.id DESC"
+        };
         let sql = format!(
-            "{} {} GROUP BY audio_transcriptions.audio_chunk_id, audio_transcriptions.offset_index ORDER BY audio_transcriptions.timestamp DESC LIMIT ? OFFSET ?",
-            base_sql, where_clause
+            "{} {} GROUP BY audio_transcriptions.audio_chunk_id, audio_transcriptions.offset_index ORDER BY {} LIMIT ? OFFSET ?",
+            base_sql, where_clause, order_by
         );
 
         // prepare binding for speaker_ids (if any)
@@ -906,6 +1649,22 @@ impl DatabaseManager {
                 .bind(&speaker_ids_json)
                 .bind(&speaker_ids_json);
         }
+        if let Some(language) = language {
+            query_builder = query_builder.bind(language);
+        }
+        if let Some(tag) = tag {
+            // the condition names the tag twice
+            query_builder = query_builder.bind(tag).bind(tag);
+        }
+        if let Some(meeting) = meeting {
+            query_builder = query_builder.bind(meeting);
+        }
+        if let Some(cursor) = cursor {
+            query_builder = query_builder
+                .bind(cursor.timestamp)
+                .bind(cursor.timestamp)
+                .bind(cursor.id_bound(SearchResultKind::Audio));
+        }
         query_builder = query_builder.bind(limit as i64).bind(offset as i64);
 
         let results_raw: Vec<AudioResultRaw> = query_builder.fetch_all(&self.pool).await?;
@@ -913,44 +1672,50 @@ impl DatabaseManager {
         // map raw results into audio result type
         let futures: Vec<_> = results_raw
             .into_iter()
-            .map(|raw| async move {
-                let speaker = match raw.speaker_id {
-                    Some(id) => match self.get_speaker_by_id(id).await {
-                        Ok(speaker) => Some(speaker),
-                        Err(_) => None,
-                    },
-                    None => None,
-                };
-
-                Ok::<AudioResult, sqlx::Error>(AudioResult {
-                    audio_chunk_id: raw.audio_chunk_id,
-                    transcription: raw.transcription,
-                    timestamp: raw.timestamp,
-                    file_path: raw.file_path,
-                    offset_index: raw.offset_index,
-                    transcription_engine: raw.transcription_engine,
-                    tags: raw
-                        .tags
-                        .map(|s| s.split(',').map(|s| s.to_owned()).collect())
-                        .unwrap_or_default(),
-                    device_name: raw.device_name,
-                    device_type: if raw.is_input_device {
-                        DeviceType::Input
-                    } else {
-                        DeviceType::Output
-                    },
-                    speaker,
-                    start_time: raw.start_time,
-                    end_time: raw.end_time,
-                })
-            })
+            .map(|raw| self.audio_result_from_raw(raw))
             .collect();
 
-        Ok(try_join_all(futures).await?.into_iter().collect())
+        Ok(join_all(futures).await)
+    }
+
+    pub(crate) async fn audio_result_from_raw(&self, raw: AudioResultRaw) -> AudioResult {
+        let speaker = match raw.speaker_id {
+            Some(id) => self.get_speaker_by_id(id).await.ok(),
+            None => None,
+        };
+
+        AudioResult {
+            transcription_id: raw.transcription_id,
+            audio_chunk_id: raw.audio_chunk_id,
+            transcription: raw.transcription,
+            timestamp: raw.timestamp,
+            file_path: raw.file_path,
+            offset_index: raw.offset_index,
+            transcription_engine: raw.transcription_engine,
+            tags: raw
+                .tags
+                .map(|s| s.split(',').map(|s| s.to_owned()).collect())
+                .unwrap_or_default(),
+            device_name: raw.device_name,
+            device_type: if raw.is_input_device {
+                DeviceType::Input
+            } else {
+                DeviceType::Output
+            },
+            speaker,
+            start_time: raw.start_time,
+            end_time: raw.end_time,
+            language: raw.language,
+            translated_text: raw.translated_text,
+            rank: raw.rank,
+            snippet: raw.snippet,
+        }
     }
 
+    /// Video chunk file and offset of the frame, which may have been moved
+    /// to a partition.
     pub async fn get_frame(&self, frame_id: i64) -> Result<Option<(String, i64)>, sqlx::Error> {
-        sqlx::query_as::<_, (String, i64)>(
+        let frame = sqlx::query_as::<_, (String, i64)>(
             r#"
             SELECT
                 video_chunks.file_path,
@@ -965,6 +1730,109 @@ impl DatabaseManager {
         )
         .bind(frame_id)
         .fetch_optional(&self.pool)
+        .await?;
+        match frame {
+            Some(frame) => Ok(Some(frame)),
+            None => self.get_archived_frame(frame_id).await,
+        }
+    }
+
+    /// Up to `count` frames spread evenly over the time range, oldest first.
+    /// Every frame is returned when there are fewer.
+    pub async fn sample_frames(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        device_name: Option<&str>,
+        count: u32,
+    ) -> Result<Vec<FramePreview>, sqlx::Error> {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        // the frames are split in `count` equal runs, the first of each is kept
+        sqlx::query_as(
+            r#"
+            WITH ranged AS (
+                SELECT
+                    f.id,
+                    f.timestamp,
+                    f.device_name,
+                    vc.file_path,
+                    f.offset_index,
+                    ROW_NUMBER() OVER (ORDER BY f.timestamp, f.id) - 1 AS position,
+                    COUNT(*) OVER () AS total
+                FROM frames f
+                JOIN video_chunks vc ON f.video_chunk_id = vc.id
+                WHERE f.timestamp >= ?1
+                    AND f.timestamp <= ?2
+                    AND (?3 IS NULL OR f.device_name = ?3)
+            )
+            SELECT id, timestamp, device_name, file_path, offset_index
+            FROM ranged
+            WHERE position = 0 OR (position * ?4) / total > ((position - 1) * ?4) / total
+            ORDER BY position
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(device_name)
+        .bind(count as i64)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Video chunks with frames in the time range, oldest first.
+    pub async fn get_video_chunk_spans(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        device_name: Option<&str>,
+    ) -> Result<Vec<VideoChunkSpan>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                vc.id,
+                vc.file_path,
+                vc.device_name,
+                MIN(f.timestamp) AS start_time,
+                MAX(f.timestamp) AS end_time,
+                COUNT(f.id) AS frame_count
+            FROM video_chunks vc
+            JOIN frames f ON f.video_chunk_id = vc.id
+            WHERE (?3 IS NULL OR vc.device_name = ?3)
+            GROUP BY vc.id
+            HAVING MAX(f.timestamp) >= ?1 AND MIN(f.timestamp) <= ?2
+            ORDER BY start_time, vc.id
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(device_name)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn get_video_chunk_span(
+        &self,
+        video_chunk_id: i64,
+    ) -> Result<Option<VideoChunkSpan>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                vc.id,
+                vc.file_path,
+                vc.device_name,
+                MIN(f.timestamp) AS start_time,
+                MAX(f.timestamp) AS end_time,
+                COUNT(f.id) AS frame_count
+            FROM video_chunks vc
+            JOIN frames f ON f.video_chunk_id = vc.id
+            WHERE vc.id = ?1
+            GROUP BY vc.id
+            "#,
+        )
+        .bind(video_chunk_id)
+        .fetch_optional(&self.pool)
         .await
     }
 
@@ -983,11 +1851,22 @@ impl DatabaseManager {
         frame_name: Option<&str>,
         browser_url: Option<&str>,
         focused: Option<bool>,
+        language: Option<&str>,
+        tag: Option<&str>,
+        meeting: Option<&str>,
     ) -> Result<usize, sqlx::Error> {
         // if focused or browser_url is present, we run only on OCR
         if focused.is_some() || browser_url.is_some() {
             content_type = ContentType::OCR;
         }
+        // tables and clipboard entries can't be tagged or placed in meetings
+        if (tag.is_some() || meeting.is_some())
+            && matches!(content_type, ContentType::Table | ContentType::Clipboard)
+        {
+            return Ok(0);
+        }
+        let query = fts_query(query).unwrap_or_default();
+        let query = query.as_str();
 
         if content_type == ContentType::All {
             // Create boxed futures to avoid infinite size issues with recursion
@@ -1004,6 +1883,9 @@ impl DatabaseManager {
                 frame_name,
                 browser_url,
                 focused,
+                None,
+                tag,
+                meeting,
             ));
 
             let ui_future = Box::pin(self.count_search_results(
@@ -1019,6 +1901,9 @@ impl DatabaseManager {
                 None,
                 None,
                 None,
+                None,
+                tag,
+                meeting,
             ));
 
             if app_name.is_none() && window_name.is_none() {
@@ -1035,6 +1920,9 @@ impl DatabaseManager {
                     None,
                     None,
                     None,
+                    language,
+                    tag,
+                    meeting,
                 ));
 
                 let (ocr_count, audio_count, ui_count) =
@@ -1063,7 +1951,7 @@ impl DatabaseManager {
         // Split query parts between frame metadata and OCR content
         if !query.is_empty() {
             ocr_fts_parts.push(query.to_owned()); // Just use the query directly
-            ui_fts_parts.push(query.to_owned());
+            ui_fts_parts.push(format!("({})", query));
         }
         if let Some(app) = app_name {
             if !app.is_empty() {
@@ -1079,7 +1967,7 @@ impl DatabaseManager {
         }
         if let Some(browser) = browser_url {
             if !browser.is_empty() {
-                frame_fts_parts.push(format!("browser_url:{}", browser));
+                frame_fts_parts.push(format!("browser_url:{}", fts_phrase(browser)));
             }
         }
         if let Some(is_focused) = focused {
@@ -1099,7 +1987,11 @@ impl DatabaseManager {
                        AND (?3 IS NULL OR frames.timestamp <= ?3)
                        AND (?4 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) >= ?4)
                        AND (?5 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) <= ?5)
-                       AND (?6 IS NULL OR frames.name LIKE '%' || ?6 || '%')"#,
+                       AND (?6 IS NULL OR frames.name LIKE '%' || ?6 || '%')
+                       AND (?7 IS NULL OR {tag_condition})
+                       AND (?8 IS NULL OR {meeting_condition})"#,
+                tag_condition = tag_condition(TaggedContent::Frame, "?7"),
+                meeting_condition = meeting_condition("frames.timestamp", "?8"),
                 base_table = if ocr_query.is_empty() {
                     "frames
                      JOIN ocr_text ON frames.id = ocr_text.frame_id"
@@ -1121,7 +2013,11 @@ impl DatabaseManager {
                        AND (?2 IS NULL OR timestamp >= ?2)
                        AND (?3 IS NULL OR timestamp <= ?3)
                        AND (?4 IS NULL OR COALESCE(text_length, LENGTH(ui_monitoring.text_output)) >= ?4)
-                       AND (?5 IS NULL OR COALESCE(text_length, LENGTH(ui_monitoring.text_output)) <= ?5)"#,
+                       AND (?5 IS NULL OR COALESCE(text_length, LENGTH(ui_monitoring.text_output)) <= ?5)
+                       AND (?6 IS NULL OR {tag_condition})
+                       AND (?7 IS NULL OR {meeting_condition})"#,
+                tag_condition = tag_condition(TaggedContent::Ui, "?6"),
+                meeting_condition = meeting_condition("ui_monitoring.timestamp", "?7"),
                 table = if ui_query.is_empty() {
                     "ui_monitoring"
                 } else {
@@ -1142,7 +2038,12 @@ impl DatabaseManager {
                        AND (?4 IS NULL OR COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) >= ?4)
                        AND (?5 IS NULL OR COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) <= ?5)
                        AND (json_array_length(?6) = 0 OR audio_transcriptions.speaker_id IN (SELECT value FROM json_each(?6)))
+                       AND (?7 IS NULL OR audio_transcriptions.language = ?7)
+                       AND (?8 IS NULL OR {tag_condition})
+                       AND (?9 IS NULL OR {meeting_condition})
                 "#,
+                tag_condition = tag_condition(TaggedContent::Transcription, "?8"),
+                meeting_condition = meeting_condition("audio_transcriptions.timestamp", "?9"),
                 table = if query.is_empty() {
                     "audio_transcriptions"
                 } else {
@@ -1154,6 +2055,45 @@ impl DatabaseManager {
                     "audio_transcriptions_fts MATCH ?1"
                 }
             ),
+            ContentType::Table => format!(
+                r#"SELECT COUNT(DISTINCT ocr_tables.id)
+                   FROM {table}
+                   JOIN frames ON ocr_tables.frame_id = frames.id
+                   WHERE {match_condition}
+                       AND (?2 IS NULL OR frames.timestamp >= ?2)
+                       AND (?3 IS NULL OR frames.timestamp <= ?3)
+                       AND (?4 IS NULL OR frames.app_name LIKE '%' || ?4 || '%')
+                       AND (?5 IS NULL OR frames.window_name LIKE '%' || ?5 || '%')"#,
+                table = if query.is_empty() {
+                    "ocr_tables"
+                } else {
+                    "ocr_tables_fts JOIN ocr_tables ON ocr_tables_fts.table_id = ocr_tables.id"
+                },
+                match_condition = if query.is_empty() {
+                    "1=1"
+                } else {
+                    "ocr_tables_fts MATCH ?1"
+                }
+            ),
+            ContentType::Clipboard => format!(
+                r#"SELECT COUNT(DISTINCT clipboard_history.id)
+                   FROM {table}
+                   WHERE {match_condition}
+                       AND (?2 IS NULL OR clipboard_history.timestamp >= ?2)
+                       AND (?3 IS NULL OR clipboard_history.timestamp <= ?3)
+                       AND (?4 IS NULL OR clipboard_history.app_name LIKE '%' || ?4 || '%')
+                       AND (?5 IS NULL OR clipboard_history.window_name LIKE '%' || ?5 || '%')"#,
+                table = if query.is_empty() {
+                    "clipboard_history"
+                } else {
+                    "clipboard_history_fts JOIN clipboard_history ON clipboard_history_fts.clipboard_id = clipboard_history.id"
+                },
+                match_condition = if query.is_empty() {
+                    "1=1"
+                } else {
+                    "clipboard_history_fts MATCH ?1"
+                }
+            ),
             _ => return Ok(0),
         };
 
@@ -1172,6 +2112,8 @@ impl DatabaseManager {
                     .bind(min_length.map(|l| l as i64))
                     .bind(max_length.map(|l| l as i64))
                     .bind(frame_name)
+                    .bind(tag)
+                    .bind(meeting)
                     .fetch_one(&self.pool)
                     .await?
             }
@@ -1182,6 +2124,8 @@ impl DatabaseManager {
                     .bind(end_time)
                     .bind(min_length.map(|l| l as i64))
                     .bind(max_length.map(|l| l as i64))
+                    .bind(tag)
+                    .bind(meeting)
                     .fetch_one(&self.pool)
                     .await?
             }
@@ -1193,6 +2137,19 @@ impl DatabaseManager {
                     .bind(min_length.map(|l| l as i64))
                     .bind(max_length.map(|l| l as i64))
                     .bind(json_array)
+                    .bind(language)
+                    .bind(tag)
+                    .bind(meeting)
+                    .fetch_one(&self.pool)
+                    .await?
+            }
+            ContentType::Table | ContentType::Clipboard => {
+                sqlx::query_scalar(&sql)
+                    .bind(if query.is_empty() { "*" } else { query })
+                    .bind(start_time)
+                    .bind(end_time)
+                    .bind(app_name)
+                    .bind(window_name)
                     .fetch_one(&self.pool)
                     .await?
             }
@@ -1259,6 +2216,16 @@ impl DatabaseManager {
         ))
     }
 
+    /// Takes the write lock and releases it right away, fails when the
+    /// database can't be written to, e.g. a read only file or another
+    /// process holding the lock longer than the busy timeout.
+    pub async fn check_writable(&self) -> Result<(), sqlx::Error> {
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await?;
+        sqlx::query("ROLLBACK").execute(&mut *conn).await?;
+        Ok(())
+    }
+
     pub async fn add_tags(
         &self,
         id: i64,
@@ -1472,14 +2439,16 @@ impl DatabaseManager {
             f.id,
             f.timestamp,
             f.offset_index,
+            f.repeat_count,
+            f.last_repeat_at,
             ot.text,
             COALESCE(f.app_name, ot.app_name) as app_name,
             COALESCE(f.window_name, ot.window_name) as window_name,
             vc.device_name as screen_device,
             vc.file_path as video_path
-        FROM frames f
-        JOIN video_chunks vc ON f.video_chunk_id = vc.id
-        LEFT JOIN ocr_text ot ON f.id = ot.frame_id
+        FROM {schema}.frames f
+        JOIN {schema}.video_chunks vc ON f.video_chunk_id = vc.id
+        LEFT JOIN {schema}.ocr_text ot ON f.id = ot.frame_id
         WHERE f.timestamp >= ?1 AND f.timestamp <= ?2
         ORDER BY f.timestamp DESC, f.offset_index DESC
     "#;
@@ -1487,6 +2456,7 @@ impl DatabaseManager {
         // Get audio data with proper time windows for synchronization
         let audio_query = r#"
         SELECT
+            at.id,
             at.timestamp,
             at.transcription,
             at.device as audio_device,
@@ -1497,23 +2467,30 @@ impl DatabaseManager {
             CAST((julianday(datetime(at.timestamp, '+' || at.end_time || ' seconds')) -
                   julianday(datetime(at.timestamp, '+' || at.start_time || ' seconds'))) * 86400
                  as REAL) as duration_secs
-        FROM audio_transcriptions at
-        JOIN audio_chunks ac ON at.audio_chunk_id = ac.id
+        FROM {schema}.audio_transcriptions at
+        JOIN {schema}.audio_chunks ac ON at.audio_chunk_id = ac.id
         WHERE at.timestamp >= ?1 AND at.timestamp <= ?2
         ORDER BY at.timestamp DESC
         "#;
 
-        // Execute queries in parallel
-        let (frame_rows, audio_rows) = tokio::try_join!(
-            sqlx::query(frames_query)
+        // Execute queries in parallel, months moved to partitions included
+        let frames_main = frames_query.replace("{schema}", "main");
+        let audio_main = audio_query.replace("{schema}", "main");
+        let archived_queries = [frames_query, audio_query];
+        let (mut frame_rows, mut audio_rows, archived) = tokio::try_join!(
+            sqlx::query(&frames_main)
                 .bind(start)
                 .bind(end)
                 .fetch_all(&self.pool),
-            sqlx::query(audio_query)
+            sqlx::query(&audio_main)
                 .bind(start)
                 .bind(end)
-                .fetch_all(&self.pool)
+                .fetch_all(&self.pool),
+            self.query_partitions(&archived_queries, start, end)
         )?;
+        let mut archived = archived.into_iter();
+        frame_rows.extend(archived.next().unwrap_or_default());
+        audio_rows.extend(archived.next().unwrap_or_default());
 
         // Process into structured data with device-aware grouping
         let mut frames_map: BTreeMap<(DateTime<Utc>, i64), FrameData> = BTreeMap::new();
@@ -1528,6 +2505,8 @@ impl DatabaseManager {
                 frame_id: row.get("id"),
                 timestamp,
                 offset_index,
+                repeat_count: row.get("repeat_count"),
+                last_repeat_at: row.get("last_repeat_at"),
                 ocr_entries: Vec::new(),
                 audio_entries: Vec::new(),
             });
@@ -1555,6 +2534,7 @@ impl DatabaseManager {
             {
                 if let Some(frame_data) = frames_map.get_mut(&key) {
                     frame_data.audio_entries.push(AudioEntry {
+                        transcription_id: row.get("id"),
                         transcription: row.get("transcription"),
                         device_name: row.get("audio_device"),
                         is_input: row.get("is_input_device"),
@@ -1573,20 +2553,185 @@ impl DatabaseManager {
     }
 
     #[allow(clippy::too_many_arguments)]
-    pub async fn search_ui_monitoring(
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_tables(
         &self,
         query: &str,
-        app_name: Option<&str>,
-        window_name: Option<&str>,
+        limit: u32,
+        offset: u32,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        cursor: Option<&SearchCursor>,
+    ) -> Result<Vec<TableResult>, sqlx::Error> {
+        let query = fts_query(query).unwrap_or_default();
+        let query = query.as_str();
+        let (base_sql, where_clause) = if query.is_empty() {
+            ("ocr_tables", "WHERE 1=1")
+        } else {
+            (
+                "ocr_tables_fts JOIN ocr_tables ON ocr_tables_fts.table_id = ocr_tables.id",
+                "WHERE ocr_tables_fts MATCH ?1",
+            )
+        };
+
+        let sql = format!(
+            r#"
+            SELECT
+                ocr_tables.id as table_id,
+                ocr_tables.frame_id,
+                ocr_tables.table_index,
+                ocr_tables.cells,
+                ocr_tables.left,
+                ocr_tables.top,
+                ocr_tables.width,
+                ocr_tables.height,
+                frames.timestamp,
+                video_chunks.file_path,
+                frames.offset_index,
+                frames.app_name,
+                frames.window_name,
+                video_chunks.device_name,
+                frames.browser_url
+            FROM {}
+            JOIN frames ON ocr_tables.frame_id = frames.id
+            JOIN video_chunks ON frames.video_chunk_id = video_chunks.id
+            {}
+                AND (?2 IS NULL OR frames.timestamp >= ?2)
+                AND (?3 IS NULL OR frames.timestamp <= ?3)
+                AND (?4 IS NULL OR frames.app_name LIKE '%' || ?4 || '%')
+                AND (?5 IS NULL OR frames.window_name LIKE '%' || ?5 || '%')
+                AND {}
+            ORDER BY frames.timestamp DESC, ocr_tables.id DESC
+            LIMIT ?6 OFFSET ?7
+            "#,
+            base_sql,
+            where_clause,
+            after_search_cursor("frames.timestamp", "ocr_tables.id", "?8", "?9")
+        );
+
+        let rows: Vec<TableResultRaw> = sqlx::query_as(&sql)
+            .bind(if query.is_empty() { "*" } else { query })
+            .bind(start_time)
+            .bind(end_time)
+            .bind(app_name)
+            .bind(window_name)
+            .bind(limit)
+            .bind(offset)
+            .bind(cursor.map(|cursor| cursor.timestamp))
+            .bind(cursor.map(|cursor| cursor.id_bound(SearchResultKind::Table)))
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|raw| TableResult {
+                table_id: raw.table_id,
+                frame_id: raw.frame_id,
+                table_index: raw.table_index,
+                rows: serde_json::from_str(&raw.cells).unwrap_or_default(),
+                bounds: TextBounds {
+                    left: raw.left as f32,
+                    top: raw.top as f32,
+                    width: raw.width as f32,
+                    height: raw.height as f32,
+                },
+                timestamp: raw.timestamp,
+                file_path: raw.file_path,
+                offset_index: raw.offset_index,
+                app_name: raw.app_name.unwrap_or_default(),
+                window_name: raw.window_name.unwrap_or_default(),
+                device_name: raw.device_name.unwrap_or_default(),
+                browser_url: raw.browser_url,
+            })
+            .collect())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_clipboard(
+        &self,
+        query: &str,
+        limit: u32,
+        offset: u32,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        cursor: Option<&SearchCursor>,
+    ) -> Result<Vec<ClipboardEntry>, sqlx::Error> {
+        let query = fts_query(query).unwrap_or_default();
+        let query = query.as_str();
+        let (base_sql, where_clause) = if query.is_empty() {
+            ("clipboard_history", "WHERE 1=1")
+        } else {
+            (
+                "clipboard_history_fts JOIN clipboard_history ON clipboard_history_fts.clipboard_id = clipboard_history.id",
+                "WHERE clipboard_history_fts MATCH ?1",
+            )
+        };
+
+        let sql = format!(
+            r#"
+            SELECT
+                clipboard_history.id,
+                clipboard_history.timestamp,
+                clipboard_history.app_name,
+                clipboard_history.window_name,
+                clipboard_history.text
+            FROM {}
+            {}
+                AND (?2 IS NULL OR clipboard_history.timestamp >= ?2)
+                AND (?3 IS NULL OR clipboard_history.timestamp <= ?3)
+                AND (?4 IS NULL OR clipboard_history.app_name LIKE '%' || ?4 || '%')
+                AND (?5 IS NULL OR clipboard_history.window_name LIKE '%' || ?5 || '%')
+                AND {}
+            ORDER BY clipboard_history.timestamp DESC, clipboard_history.id DESC
+            LIMIT ?6 OFFSET ?7
+            "#,
+            base_sql,
+            where_clause,
+            after_search_cursor(
+                "clipboard_history.timestamp",
+                "clipboard_history.id",
+                "?8",
+                "?9"
+            )
+        );
+
+        sqlx::query_as(&sql)
+            .bind(if query.is_empty() { "*" } else { query })
+            .bind(start_time)
+            .bind(end_time)
+            .bind(app_name)
+            .bind(window_name)
+            .bind(limit)
+            .bind(offset)
+            .bind(cursor.map(|cursor| cursor.timestamp))
+            .bind(cursor.map(|cursor| cursor.id_bound(SearchResultKind::Clipboard)))
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn search_ui_monitoring(
+        &self,
+        query: &str,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
         start_time: Option<DateTime<Utc>>,
         end_time: Option<DateTime<Utc>>,
         limit: u32,
         offset: u32,
+        tag: Option<&str>,
+        meeting: Option<&str>,
+        sort: SearchSort,
+        cursor: Option<&SearchCursor>,
     ) -> Result<Vec<UiContent>, sqlx::Error> {
+        let query = fts_query(query).unwrap_or_default();
         // combine search aspects into single fts query
         let mut fts_parts = Vec::new();
         if !query.is_empty() {
-            fts_parts.push(query.to_owned());
+            fts_parts.push(format!("({})", query));
         }
         if let Some(app) = app_name {
             fts_parts.push(format!("app:{}", app));
@@ -1620,7 +2765,8 @@ impl DatabaseManager {
                 video_chunks.file_path,
                 frames.offset_index,
                 frames.name as frame_name,
-                frames.browser_url
+                frames.browser_url,
+                {}
             FROM {}
             LEFT JOIN frames ON
                 frames.timestamp BETWEEN
@@ -1630,11 +2776,29 @@ impl DatabaseManager {
             {}
                 AND (?2 IS NULL OR ui_monitoring.timestamp >= ?2)
                 AND (?3 IS NULL OR ui_monitoring.timestamp <= ?3)
+                AND (?6 IS NULL OR {})
+                AND (?7 IS NULL OR {})
+                AND {}
             GROUP BY ui_monitoring.id
-            ORDER BY ui_monitoring.timestamp DESC
+            ORDER BY {}
             LIMIT ?4 OFFSET ?5
             "#,
-            base_sql, where_clause
+            // app and window filters alone aren't ranked
+            if query.is_empty() {
+                "NULL as rank, NULL as snippet".to_string()
+            } else {
+                fts_rank_columns("ui_monitoring_fts", UI_FTS_WEIGHTS)
+            },
+            base_sql,
+            where_clause,
+            tag_condition(TaggedContent::Ui, "?6"),
+            meeting_condition("ui_monitoring.timestamp", "?7"),
+            after_search_cursor("ui_monitoring.timestamp", "ui_monitoring.id", "?8", "?9"),
+            if sort == SearchSort::Relevance && !query.is_empty() {
+                "rank, ui_monitoring.timestamp DESC, ui_monitoring.id DESC"
+            } else {
+                "ui_monitoring.timestamp DESC, ui_monitoring.id DESC"
+            }
         );
 
         sqlx::query_as(&sql)
@@ -1647,6 +2811,10 @@ impl DatabaseManager {
             .bind(end_time)
             .bind(limit)
             .bind(offset)
+            .bind(tag)
+            .bind(meeting)
+            .bind(cursor.map(|cursor| cursor.timestamp))
+            .bind(cursor.map(|cursor| cursor.id_bound(SearchResultKind::Ui)))
             .fetch_all(&self.pool)
             .await
     }
@@ -1822,6 +2990,15 @@ impl DatabaseManager {
             .execute(&mut *tx)
             .await?;
 
+        // the merged speaker stays a voice profile if either was one
+        sqlx::query(
+            "UPDATE speakers SET enrolled = TRUE WHERE id = ?1 AND EXISTS (SELECT 1 FROM speakers WHERE id = ?2 AND enrolled = TRUE)",
+        )
+        .bind(speaker_to_keep_id)
+        .bind(speaker_to_merge_id)
+        .execute(&mut *tx)
+        .await?;
+
         // delete the speaker to merge
         sqlx::query("DELETE FROM speakers WHERE id = ?")
             .bind(speaker_to_merge_id)
@@ -1833,6 +3010,90 @@ impl DatabaseManager {
         self.get_speaker_by_id(speaker_to_keep_id).await
     }
 
+    /// Adds the voice embeddings of samples to the voice profile named
+    /// `name`, creating it if needed. A speaker already named so, e.g. by
+    /// renaming one diarization found, becomes the profile.
+    pub async fn enroll_speaker(
+        &self,
+        name: &str,
+        embeddings: &[Vec<f32>],
+    ) -> Result<Speaker, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let existing: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM speakers
+            WHERE name = ?1 COLLATE NOCASE AND COALESCE(hallucination, FALSE) = FALSE
+            ORDER BY enrolled DESC, id ASC
+            LIMIT 1
+            "#,
+        )
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let id = match existing {
+            Some(id) => {
+                sqlx::query("UPDATE speakers SET enrolled = TRUE WHERE id = ?1")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                id
+            }
+            None => sqlx::query("INSERT INTO speakers (name, enrolled) VALUES (?1, TRUE)")
+                .bind(name)
+                .execute(&mut *tx)
+                .await?
+                .last_insert_rowid(),
+        };
+
+        for embedding in embeddings {
+            let bytes: &[u8] = embedding.as_bytes();
+            sqlx::query(
+                "INSERT INTO speaker_embeddings (embedding, speaker_id) VALUES (vec_f32(?1), ?2)",
+            )
+            .bind(bytes)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        self.get_speaker_by_id(id).await
+    }
+
+    /// Enrolled voice profiles by name.
+    pub async fn list_voice_profiles(&self) -> Result<Vec<VoiceProfile>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                s.id,
+                COALESCE(s.name, '') AS name,
+                (SELECT COUNT(*) FROM speaker_embeddings WHERE speaker_id = s.id) AS embedding_count,
+                (SELECT COUNT(*) FROM audio_transcriptions WHERE speaker_id = s.id) AS transcript_count
+            FROM speakers s
+            WHERE s.enrolled = TRUE
+            ORDER BY s.name COLLATE NOCASE ASC, s.id ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Speakers named `name`, whatever its case. Several speakers can share
+    /// a name until they're merged.
+    pub async fn get_speaker_ids_by_name(&self, name: &str) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT id FROM speakers
+            WHERE name = ?1 COLLATE NOCASE AND COALESCE(hallucination, FALSE) = FALSE
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(name.trim())
+        .fetch_all(&self.pool)
+        .await
+    }
+
     pub async fn search_speakers(&self, name_prefix: &str) -> Result<Vec<Speaker>, sqlx::Error> {
         sqlx::query_as::<_, Speaker>(
             "SELECT DISTINCT * FROM speakers WHERE name LIKE ? || '%' AND hallucination = 0",
@@ -2062,7 +3323,8 @@ impl DatabaseManager {
                 ocr_text.ocr_engine,
                 frames.window_name,
                 GROUP_CONCAT(tags.name, ',') as tags,
-                frames.browser_url
+                frames.browser_url,
+                frames.browser_context
             FROM embedding_matches
             JOIN ocr_text ON embedding_matches.frame_id = ocr_text.frame_id
             JOIN frames ON ocr_text.frame_id = frames.id
@@ -2101,95 +3363,3303 @@ impl DatabaseManager {
                     .map(|t| t.split(',').map(String::from).collect())
                     .unwrap_or_default(),
                 browser_url: raw.browser_url,
+                browser_context: raw
+                    .browser_context
+                    .and_then(|c| serde_json::from_str(&c).ok()),
                 focused: raw.focused,
+                rank: raw.rank,
+                snippet: raw.snippet,
             })
             .collect())
     }
 
-    // Add method to update frame names
-    pub async fn update_frame_name(&self, frame_id: i64, name: &str) -> Result<(), sqlx::Error> {
-        sqlx::query("UPDATE frames SET name = ?1 WHERE id = ?2")
-            .bind(name)
-            .bind(frame_id)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
+    /// OCR text of frames that have no embedding yet, newest first.
+    pub async fn get_ocr_text_without_embeddings(
+        &self,
+        limit: u32,
+    ) -> Result<Vec<UnembeddedText>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT ocr_text.frame_id as id, ocr_text.text
+            FROM ocr_text
+            LEFT JOIN ocr_text_embeddings ON ocr_text.frame_id = ocr_text_embeddings.frame_id
+            WHERE ocr_text_embeddings.id IS NULL
+                AND LENGTH(TRIM(ocr_text.text)) > 0
+            GROUP BY ocr_text.frame_id
+            ORDER BY ocr_text.frame_id DESC
+            LIMIT ?1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
     }
 
-    // Add method to update all frames in a video chunk
-    pub async fn update_video_chunk_frames_names(
+    /// Audio transcriptions that have no embedding yet, newest first. The
+    /// English translation is returned instead of the transcription when
+    /// there is one.
+    pub async fn get_audio_transcriptions_without_embeddings(
         &self,
-        video_chunk_id: i64,
-        name: &str,
+        limit: u32,
+    ) -> Result<Vec<UnembeddedText>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                audio_transcriptions.id,
+                COALESCE(NULLIF(audio_transcriptions.translated_text, ''), audio_transcriptions.transcription) as text
+            FROM audio_transcriptions
+            LEFT JOIN audio_transcription_embeddings
+                ON audio_transcriptions.id = audio_transcription_embeddings.audio_transcription_id
+            WHERE audio_transcription_embeddings.id IS NULL
+                AND LENGTH(TRIM(audio_transcriptions.transcription)) > 0
+            ORDER BY audio_transcriptions.id DESC
+            LIMIT ?1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn insert_audio_transcription_embedding(
+        &self,
+        audio_transcription_id: i64,
+        embedding: &[f32],
     ) -> Result<(), sqlx::Error> {
-        sqlx::query("UPDATE frames SET name = ?1 WHERE video_chunk_id = ?2")
-            .bind(name)
-            .bind(video_chunk_id)
-            .execute(&self.pool)
-            .await?;
+        let bytes: &[u8] = embedding.as_bytes();
+        sqlx::query(
+            "INSERT INTO audio_transcription_embeddings (audio_transcription_id, embedding) VALUES (?1, ?2)",
+        )
+        .bind(audio_transcription_id)
+        .bind(bytes)
+        .execute(&self.pool)
+        .await?;
         Ok(())
     }
 
-    pub async fn repair_database(&self) -> Result<(), anyhow::Error> {
-        debug!("starting aggressive database repair process");
-
-        // Force close any pending transactions
-        let emergency_steps = [
-            "PRAGMA locking_mode = EXCLUSIVE;",
-            "ROLLBACK;",                    // Force rollback any stuck transactions
-            "PRAGMA busy_timeout = 60000;", // Increase timeout to 60s
-        ];
-
-        for step in emergency_steps {
-            if let Err(e) = sqlx::query(step).execute(&self.pool).await {
-                warn!("emergency step failed (continuing anyway): {}", e);
+    /// Hybrid search over OCR text and audio transcriptions. Rows are ranked
+    /// once by cosine distance of their embedding to `embedding` and once by
+    /// bm25 for any of the words of `query`, and the rankings are merged with
+    /// reciprocal rank fusion. Content types other than OCR and audio give no
+    /// results.
+    pub async fn search_semantic(
+        &self,
+        query: &str,
+        embedding: &[f32],
+        content_type: ContentType,
+        limit: u32,
+    ) -> Result<Vec<SemanticSearchResult>, sqlx::Error> {
+        let (search_ocr, search_audio) = match content_type {
+            ContentType::All | ContentType::AudioAndOcr => (true, true),
+            ContentType::OCR | ContentType::OcrAndUi => (true, false),
+            ContentType::Audio | ContentType::AudioAndUi => (false, true),
+            ContentType::UI | ContentType::Table | ContentType::Clipboard => (false, false),
+        };
+        let candidates = limit.saturating_mul(SEMANTIC_CANDIDATES_PER_RESULT);
+        let bytes: &[u8] = embedding.as_bytes();
+        let fts_query = fts_any_word_query(query);
+
+        // OCR and audio candidates are merged into one ranking by distance
+        // and one by bm25, so both kinds compete for the top spots
+        let mut by_distance: Vec<(SemanticHit, f64)> = Vec::new();
+        let mut by_bm25: Vec<(SemanticHit, f64)> = Vec::new();
+        if search_ocr {
+            let rows: Vec<(i64, f64)> = sqlx::query_as(
+                "SELECT frame_id, MIN(vec_distance_cosine(embedding, vec_f32(?1))) as distance
+                 FROM ocr_text_embeddings
+                 GROUP BY frame_id
+                 ORDER BY distance
+                 LIMIT ?2",
+            )
+            .bind(bytes)
+            .bind(candidates)
+            .fetch_all(&self.pool)
+            .await?;
+            by_distance.extend(rows.into_iter().map(|(id, d)| (SemanticHit::Ocr(id), d)));
+
+            if let Some(fts_query) = &fts_query {
+                let rows: Vec<(i64, f64)> = sqlx::query_as(
+                    "SELECT frame_id, bm25(ocr_text_fts) as rank
+                     FROM ocr_text_fts
+                     WHERE ocr_text_fts MATCH ?1
+                     ORDER BY rank
+                     LIMIT ?2",
+                )
+                .bind(fts_query)
+                .bind(candidates)
+                .fetch_all(&self.pool)
+                .await?;
+                by_bm25.extend(rows.into_iter().map(|(id, r)| (SemanticHit::Ocr(id), r)));
             }
         }
-
-        // Force checkpoint and cleanup WAL files
-        let wal_cleanup = [
-            "PRAGMA wal_checkpoint(TRUNCATE);",
-            "PRAGMA journal_mode = DELETE;", // Temporarily disable WAL
-            "PRAGMA journal_size_limit = 0;", // Clear journal
-        ];
-
-        for step in wal_cleanup {
-            if let Err(e) = sqlx::query(step).execute(&self.pool).await {
-                warn!("wal cleanup failed (continuing anyway): {}", e);
+        if search_audio {
+            let rows: Vec<(i64, f64)> = sqlx::query_as(
+                "SELECT audio_transcription_id, MIN(vec_distance_cosine(embedding, vec_f32(?1))) as distance
+                 FROM audio_transcription_embeddings
+                 GROUP BY audio_transcription_id
+                 ORDER BY distance
+                 LIMIT ?2",
+            )
+            .bind(bytes)
+            .bind(candidates)
+            .fetch_all(&self.pool)
+            .await?;
+            by_distance.extend(rows.into_iter().map(|(id, d)| (SemanticHit::Audio(id), d)));
+
+            if let Some(fts_query) = &fts_query {
+                let rows: Vec<(i64, f64)> = sqlx::query_as(
+                    "SELECT audio_transcriptions.id, bm25(audio_transcriptions_fts) as rank
+                     FROM audio_transcriptions_fts
+                     JOIN audio_transcriptions
+                        ON audio_transcriptions.audio_chunk_id = audio_transcriptions_fts.audio_chunk_id
+                     WHERE audio_transcriptions_fts MATCH ?1
+                     ORDER BY rank
+                     LIMIT ?2",
+                )
+                .bind(fts_query)
+                .bind(candidates)
+                .fetch_all(&self.pool)
+                .await?;
+                by_bm25.extend(rows.into_iter().map(|(id, r)| (SemanticHit::Audio(id), r)));
             }
         }
 
-        // Aggressive recovery steps
-        let recovery_steps = [
-            ("PRAGMA synchronous = OFF;", "disable synchronous"),
-            ("PRAGMA cache_size = -2000000;", "increase cache"), // 2GB cache
-            ("VACUUM;", "vacuum database"),
-            ("PRAGMA integrity_check;", "check integrity"),
-            ("PRAGMA foreign_key_check;", "check foreign keys"),
-            ("REINDEX;", "rebuild indexes"),
-            ("ANALYZE;", "update statistics"),
-            ("VACUUM;", "final vacuum"), // Second vacuum after reindex
-        ];
-
-        for (query, step) in recovery_steps {
-            debug!("running aggressive recovery step: {}", step);
-            match sqlx::query(query).execute(&self.pool).await {
-                Ok(_) => debug!("recovery step '{}' succeeded", step),
-                Err(e) => warn!("recovery step '{}' failed: {}", step, e),
-            }
-        }
+        // lower is better for both
+        let rankings: Vec<Vec<SemanticHit>> = [by_distance, by_bm25]
+            .into_iter()
+            .map(|mut ranking| {
+                ranking.sort_by(|a, b| a.1.total_cmp(&b.1));
+                ranking.into_iter().map(|(hit, _)| hit).collect()
+            })
+            .collect();
 
-        // Restore safe settings
-        let restore_steps = [
-            "PRAGMA synchronous = NORMAL;",
-            "PRAGMA journal_mode = WAL;",
-            "PRAGMA wal_autocheckpoint = 1000;",
-            "PRAGMA cache_size = -2000;", // Back to 2MB cache
-            "PRAGMA locking_mode = NORMAL;",
-            "PRAGMA busy_timeout = 5000;", // Back to 5s timeout
-        ];
+        let mut fused = reciprocal_rank_fusion(&rankings, RRF_K);
+        fused.truncate(limit as usize);
 
-        for step in restore_steps {
+        let frame_ids: Vec<i64> = fused
+            .iter()
+            .filter_map(|(hit, _)| match hit {
+                SemanticHit::Ocr(id) => Some(*id),
+                SemanticHit::Audio(_) => None,
+            })
+            .collect();
+        let transcription_ids: Vec<i64> = fused
+            .iter()
+            .filter_map(|(hit, _)| match hit {
+                SemanticHit::Audio(id) => Some(*id),
+                SemanticHit::Ocr(_) => None,
+            })
+            .collect();
+        let mut ocr = self.get_ocr_results_by_frame_ids(&frame_ids).await?;
+        let mut audio = self
+            .get_audio_results_by_transcription_ids(&transcription_ids)
+            .await?;
+
+        // rows can disappear between ranking and fetching, skip them
+        Ok(fused
+            .into_iter()
+            .filter_map(|(hit, score)| {
+                let result = match hit {
+                    SemanticHit::Ocr(id) => SearchResult::OCR(ocr.remove(&id)?),
+                    SemanticHit::Audio(id) => SearchResult::Audio(audio.remove(&id)?),
+                };
+                Some(SemanticSearchResult { result, score })
+            })
+            .collect())
+    }
+
+    async fn get_ocr_results_by_frame_ids(
+        &self,
+        frame_ids: &[i64],
+    ) -> Result<HashMap<i64, OCRResult>, sqlx::Error> {
+        if frame_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let raw_results: Vec<OCRResultRaw> = sqlx::query_as(
+            r#"
+            SELECT
+                ocr_text.frame_id,
+                ocr_text.text as ocr_text,
+                ocr_text.text_json,
+                frames.timestamp,
+                frames.name as frame_name,
+                video_chunks.file_path,
+                frames.offset_index,
+                frames.app_name,
+                ocr_text.ocr_engine,
+                frames.window_name,
+                video_chunks.device_name,
+                GROUP_CONCAT(tags.name, ',') as tags,
+                frames.browser_url,
+                frames.browser_context,
+                frames.focused
+            FROM frames
+            JOIN video_chunks ON frames.video_chunk_id = video_chunks.id
+            JOIN ocr_text ON frames.id = ocr_text.frame_id
+            LEFT JOIN vision_tags ON frames.id = vision_tags.vision_id
+            LEFT JOIN tags ON vision_tags.tag_id = tags.id
+            WHERE frames.id IN (SELECT value FROM json_each(?1))
+            GROUP BY frames.id
+            "#,
+        )
+        .bind(serde_json::to_string(frame_ids).unwrap_or_else(|_| "[]".to_string()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(raw_results
+            .into_iter()
+            .map(|raw| {
+                (
+                    raw.frame_id,
+                    OCRResult {
+                        frame_id: raw.frame_id,
+                        ocr_text: raw.ocr_text,
+                        text_json: raw.text_json,
+                        timestamp: raw.timestamp,
+                        file_path: raw.file_path,
+                        offset_index: raw.offset_index,
+                        app_name: raw.app_name,
+                        ocr_engine: raw.ocr_engine,
+                        window_name: raw.window_name,
+                        frame_name: raw.frame_name,
+                        device_name: raw.device_name,
+                        tags: raw
+                            .tags
+                            .map(|t| t.split(',').map(String::from).collect())
+                            .unwrap_or_default(),
+                        browser_url: raw.browser_url,
+                        browser_context: raw
+                            .browser_context
+                            .and_then(|c| serde_json::from_str(&c).ok()),
+                        focused: raw.focused,
+                        rank: raw.rank,
+                        snippet: raw.snippet,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    async fn get_audio_results_by_transcription_ids(
+        &self,
+        transcription_ids: &[i64],
+    ) -> Result<HashMap<i64, AudioResult>, sqlx::Error> {
+        if transcription_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let raw_results: Vec<AudioResultRaw> = sqlx::query_as(
+            r#"
+            SELECT
+                audio_transcriptions.id as transcription_id,
+                audio_transcriptions.audio_chunk_id,
+                audio_transcriptions.transcription,
+                audio_transcriptions.timestamp,
+                audio_chunks.file_path,
+                audio_transcriptions.offset_index,
+                audio_transcriptions.transcription_engine,
+                GROUP_CONCAT(tags.name, ',') as tags,
+                audio_transcriptions.device as device_name,
+                audio_transcriptions.is_input_device,
+                audio_transcriptions.speaker_id,
+                audio_transcriptions.start_time,
+                audio_transcriptions.end_time,
+                audio_transcriptions.language,
+                audio_transcriptions.translated_text
+            FROM audio_transcriptions
+            JOIN audio_chunks ON audio_transcriptions.audio_chunk_id = audio_chunks.id
+            LEFT JOIN speakers ON audio_transcriptions.speaker_id = speakers.id
+            LEFT JOIN audio_tags ON audio_chunks.id = audio_tags.audio_chunk_id
+            LEFT JOIN tags ON audio_tags.tag_id = tags.id
+            WHERE audio_transcriptions.id IN (SELECT value FROM json_each(?1))
+                AND (speakers.id IS NULL OR speakers.hallucination = 0)
+            GROUP BY audio_transcriptions.id
+            "#,
+        )
+        .bind(serde_json::to_string(transcription_ids).unwrap_or_else(|_| "[]".to_string()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let futures: Vec<_> = raw_results
+            .into_iter()
+            .map(|raw| async move { (raw.transcription_id, self.audio_result_from_raw(raw).await) })
+            .collect();
+
+        Ok(join_all(futures).await.into_iter().collect())
+    }
+
+    // Add method to update frame names
+    pub async fn update_frame_name(&self, frame_id: i64, name: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE frames SET name = ?1 WHERE id = ?2")
+            .bind(name)
+            .bind(frame_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // Add method to update all frames in a video chunk
+    pub async fn update_video_chunk_frames_names(
+        &self,
+        video_chunk_id: i64,
+        name: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE frames SET name = ?1 WHERE video_chunk_id = ?2")
+            .bind(name)
+            .bind(video_chunk_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Media chunks whose last recording is older than `before` and whose
+    /// file was not deleted yet, oldest first. At most `limit` chunks if set.
+    pub async fn get_media_chunks_before(
+        &self,
+        media_type: MediaType,
+        before: DateTime<Utc>,
+        limit: Option<u32>,
+    ) -> Result<Vec<MediaChunk>, sqlx::Error> {
+        sqlx::query_as(media_chunks_before_sql(media_type))
+            .bind(before)
+            // a negative limit means no limit in sqlite
+            .bind(limit.map_or(-1, i64::from))
+            .bind(false)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Chunks whose media file is not encrypted yet and that were last
+    /// recorded to before `before`, oldest first.
+    pub async fn get_unencrypted_media_chunks(
+        &self,
+        media_type: MediaType,
+        before: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<MediaChunk>, sqlx::Error> {
+        sqlx::query_as(media_chunks_before_sql(media_type))
+            .bind(before)
+            .bind(limit)
+            .bind(true)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Records that the files of these chunks were encrypted.
+    pub async fn mark_media_chunks_encrypted(
+        &self,
+        media_type: MediaType,
+        ids: &[i64],
+    ) -> Result<(), sqlx::Error> {
+        let sql = match media_type {
+            MediaType::Video => {
+                "UPDATE video_chunks SET encrypted_at = ?1 WHERE id IN (SELECT value FROM json_each(?2))"
+            }
+            MediaType::Audio => {
+                "UPDATE audio_chunks SET encrypted_at = ?1 WHERE id IN (SELECT value FROM json_each(?2))"
+            }
+        };
+
+        sqlx::query(sql)
+            .bind(Utc::now())
+            .bind(serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Chunks whose media file is still on disk, neither deleted nor
+    /// offloaded, and that were last recorded to before `before`, oldest
+    /// first.
+    pub async fn get_local_media_chunks_before(
+        &self,
+        media_type: MediaType,
+        before: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<MediaChunk>, sqlx::Error> {
+        sqlx::query_as(local_media_chunks_sql(media_type))
+            .bind(before)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Records that the files of these chunks were moved to object storage.
+    pub async fn mark_media_chunks_offloaded(
+        &self,
+        media_type: MediaType,
+        ids: &[i64],
+    ) -> Result<(), sqlx::Error> {
+        let sql = match media_type {
+            MediaType::Video => {
+                "UPDATE video_chunks SET offloaded_at = ?1 WHERE id IN (SELECT value FROM json_each(?2))"
+            }
+            MediaType::Audio => {
+                "UPDATE audio_chunks SET offloaded_at = ?1 WHERE id IN (SELECT value FROM json_each(?2))"
+            }
+        };
+
+        sqlx::query(sql)
+            .bind(Utc::now())
+            .bind(serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Records that the files of these chunks were deleted. Their rows and
+    /// text stay searchable.
+    pub async fn mark_media_chunks_deleted(
+        &self,
+        media_type: MediaType,
+        ids: &[i64],
+    ) -> Result<(), sqlx::Error> {
+        let sql = match media_type {
+            MediaType::Video => {
+                "UPDATE video_chunks SET media_deleted_at = ?1 WHERE id IN (SELECT value FROM json_each(?2))"
+            }
+            MediaType::Audio => {
+                "UPDATE audio_chunks SET media_deleted_at = ?1 WHERE id IN (SELECT value FROM json_each(?2))"
+            }
+        };
+
+        sqlx::query(sql)
+            .bind(Utc::now())
+            .bind(serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes media chunks with everything recorded in them: frames and their
+    /// OCR text for video, transcriptions for audio. Returns the number of
+    /// deleted frames or transcriptions. The files are left to the caller.
+    pub async fn delete_media_chunks(
+        &self,
+        media_type: MediaType,
+        ids: &[i64],
+    ) -> Result<u64, sqlx::Error> {
+        let ids = serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string());
+        let (deletions, rows_sql, chunks_sql) = match media_type {
+            MediaType::Video => (
+                [
+                    "DELETE FROM ocr_text WHERE frame_id IN (SELECT id FROM frames WHERE video_chunk_id IN (SELECT value FROM json_each(?1)))",
+                    "DELETE FROM chunked_text_entries WHERE frame_id IN (SELECT id FROM frames WHERE video_chunk_id IN (SELECT value FROM json_each(?1)))",
+                ],
+                "DELETE FROM frames WHERE video_chunk_id IN (SELECT value FROM json_each(?1))",
+                "DELETE FROM video_chunks WHERE id IN (SELECT value FROM json_each(?1))",
+            ),
+            MediaType::Audio => (
+                [
+                    "DELETE FROM chunked_text_entries WHERE audio_chunk_id IN (SELECT value FROM json_each(?1))",
+                    "DELETE FROM audio_tags WHERE audio_chunk_id IN (SELECT value FROM json_each(?1))",
+                ],
+                "DELETE FROM audio_transcriptions WHERE audio_chunk_id IN (SELECT value FROM json_each(?1))",
+                "DELETE FROM audio_chunks WHERE id IN (SELECT value FROM json_each(?1))",
+            ),
+        };
+
+        let mut tx = self.pool.begin().await?;
+        for sql in deletions {
+            sqlx::query(sql).bind(&ids).execute(&mut *tx).await?;
+        }
+        let rows = sqlx::query(rows_sql)
+            .bind(&ids)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        sqlx::query(chunks_sql).bind(&ids).execute(&mut *tx).await?;
+        tx.commit().await?;
+
+        Ok(rows)
+    }
+
+    pub async fn count_frames_before(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM frames WHERE timestamp < ?1")
+            .bind(before)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as u64)
+    }
+
+    pub async fn count_audio_transcriptions_before(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<u64, sqlx::Error> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM audio_transcriptions WHERE timestamp < ?1")
+                .bind(before)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(count as u64)
+    }
+
+    /// Deletes frames older than `before` with their OCR text. Video chunks
+    /// left without frames are deleted too, their files are returned.
+    pub async fn delete_frames_before(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<DeletedRecords, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let chunk_ids: Vec<i64> =
+            sqlx::query_scalar("SELECT DISTINCT video_chunk_id FROM frames WHERE timestamp < ?1")
+                .bind(before)
+                .fetch_all(&mut *tx)
+                .await?;
+        // chunks last recorded to before the cutoff lose all their frames
+        let mut media_chunks: Vec<MediaChunk> =
+            sqlx::query_as(media_chunks_before_sql(MediaType::Video))
+                .bind(before)
+                .bind(-1)
+                .bind(false)
+                .fetch_all(&mut *tx)
+                .await?;
+        media_chunks.retain(|chunk| chunk_ids.contains(&chunk.id));
+
+        for sql in [
+            "DELETE FROM ocr_text WHERE frame_id IN (SELECT id FROM frames WHERE timestamp < ?1)",
+            "DELETE FROM chunked_text_entries WHERE frame_id IN (SELECT id FROM frames WHERE timestamp < ?1)",
+        ] {
+            sqlx::query(sql).bind(before).execute(&mut *tx).await?;
+        }
+        let rows = sqlx::query("DELETE FROM frames WHERE timestamp < ?1")
+            .bind(before)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        sqlx::query(
+            r#"
+            DELETE FROM video_chunks
+            WHERE id IN (SELECT value FROM json_each(?1))
+                AND NOT EXISTS (SELECT 1 FROM frames WHERE frames.video_chunk_id = video_chunks.id)
+            "#,
+        )
+        .bind(serde_json::to_string(&chunk_ids).unwrap_or_else(|_| "[]".to_string()))
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        debug!("deleted {} frames older than {}", rows, before);
+        Ok(DeletedRecords { rows, media_chunks })
+    }
+
+    /// Deletes audio transcriptions older than `before`. Audio chunks left
+    /// without transcriptions are deleted too, their files are returned.
+    pub async fn delete_audio_transcriptions_before(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<DeletedRecords, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let chunk_ids: Vec<i64> = sqlx::query_scalar(
+            "SELECT DISTINCT audio_chunk_id FROM audio_transcriptions WHERE timestamp < ?1",
+        )
+        .bind(before)
+        .fetch_all(&mut *tx)
+        .await?;
+        // chunks last recorded to before the cutoff lose all their transcriptions
+        let mut media_chunks: Vec<MediaChunk> =
+            sqlx::query_as(media_chunks_before_sql(MediaType::Audio))
+                .bind(before)
+                .bind(-1)
+                .bind(false)
+                .fetch_all(&mut *tx)
+                .await?;
+        media_chunks.retain(|chunk| chunk_ids.contains(&chunk.id));
+
+        let rows = sqlx::query("DELETE FROM audio_transcriptions WHERE timestamp < ?1")
+            .bind(before)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        let orphaned = r#"
+            id IN (SELECT value FROM json_each(?1))
+            AND NOT EXISTS (
+                SELECT 1 FROM audio_transcriptions
+                WHERE audio_transcriptions.audio_chunk_id = audio_chunks.id
+            )
+        "#;
+        let chunk_ids = serde_json::to_string(&chunk_ids).unwrap_or_else(|_| "[]".to_string());
+        for sql in [
+            format!(
+                "DELETE FROM chunked_text_entries WHERE audio_chunk_id IN (SELECT id FROM audio_chunks WHERE {})",
+                orphaned
+            ),
+            format!("DELETE FROM audio_chunks WHERE {}", orphaned),
+        ] {
+            sqlx::query(&sql).bind(&chunk_ids).execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        debug!(
+            "deleted {} audio transcriptions older than {}",
+            rows, before
+        );
+        Ok(DeletedRecords { rows, media_chunks })
+    }
+
+    pub async fn insert_privacy_audit_entry(
+        &self,
+        timestamp: DateTime<Utc>,
+        monitor_id: u32,
+        blocked: bool,
+        app_name: &str,
+        pattern: &str,
+    ) -> Result<i64, sqlx::Error> {
+        let id = sqlx::query(
+            "INSERT INTO privacy_audit_log (timestamp, monitor_id, blocked, app_name, pattern) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .bind(timestamp)
+        .bind(monitor_id)
+        .bind(blocked)
+        .bind(app_name)
+        .bind(pattern)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    /// Privacy blocklist entries in the time range, newest first.
+    pub async fn get_privacy_audit_log(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<PrivacyAuditEntry>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, timestamp, monitor_id, blocked, app_name, pattern
+            FROM privacy_audit_log
+            WHERE (?1 IS NULL OR timestamp >= ?1)
+                AND (?2 IS NULL OR timestamp <= ?2)
+            ORDER BY timestamp DESC, id DESC
+            LIMIT ?3 OFFSET ?4
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn insert_watchdog_incident(
+        &self,
+        timestamp: DateTime<Utc>,
+        subsystem: &str,
+        target: &str,
+        stalled_secs: i64,
+        restarted: bool,
+        error: Option<&str>,
+    ) -> Result<i64, sqlx::Error> {
+        let id = sqlx::query(
+            "INSERT INTO watchdog_incidents (timestamp, subsystem, target, stalled_secs, restarted, error) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )
+        .bind(timestamp)
+        .bind(subsystem)
+        .bind(target)
+        .bind(stalled_secs)
+        .bind(restarted)
+        .bind(error)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    /// Watchdog incidents since `since`, newest first.
+    pub async fn get_watchdog_incidents(
+        &self,
+        since: Option<DateTime<Utc>>,
+        limit: u32,
+    ) -> Result<Vec<WatchdogIncident>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, timestamp, subsystem, target, stalled_secs, restarted, error
+            FROM watchdog_incidents
+            WHERE ?1 IS NULL OR timestamp >= ?1
+            ORDER BY timestamp DESC, id DESC
+            LIMIT ?2
+            "#,
+        )
+        .bind(since)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn insert_input_event(
+        &self,
+        timestamp: DateTime<Utc>,
+        duration_ms: i64,
+        app_name: &str,
+        window_name: &str,
+        keystrokes: i64,
+        clicks: i64,
+        scrolls: i64,
+        typed_text: Option<&str>,
+    ) -> Result<i64, sqlx::Error> {
+        let id = sqlx::query(
+            "INSERT INTO input_events (timestamp, duration_ms, app_name, window_name, keystrokes, clicks, scrolls, typed_text) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )
+        .bind(timestamp)
+        .bind(duration_ms)
+        .bind(app_name)
+        .bind(window_name)
+        .bind(keystrokes)
+        .bind(clicks)
+        .bind(scrolls)
+        .bind(typed_text)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    /// Input activity in the time range, optionally of one app, oldest first.
+    pub async fn get_input_events(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<InputEvent>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, timestamp, duration_ms, app_name, window_name, keystrokes, clicks, scrolls, typed_text
+            FROM input_events
+            WHERE (?1 IS NULL OR timestamp >= ?1)
+                AND (?2 IS NULL OR timestamp <= ?2)
+                AND (?3 IS NULL OR app_name = ?3 COLLATE NOCASE)
+            ORDER BY timestamp ASC, id ASC
+            LIMIT ?4 OFFSET ?5
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(app_name)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn insert_clipboard_entry(
+        &self,
+        timestamp: DateTime<Utc>,
+        app_name: &str,
+        window_name: &str,
+        text: &str,
+    ) -> Result<i64, sqlx::Error> {
+        let id = sqlx::query(
+            "INSERT INTO clipboard_history (timestamp, app_name, window_name, text) VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(timestamp)
+        .bind(app_name)
+        .bind(window_name)
+        .bind(text)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    pub async fn insert_idle_gap(
+        &self,
+        start_time: DateTime<Utc>,
+        reason: &str,
+    ) -> Result<i64, sqlx::Error> {
+        let id = sqlx::query("INSERT INTO idle_gaps (start_time, reason) VALUES (?1, ?2)")
+            .bind(start_time)
+            .bind(reason)
+            .execute(&self.pool)
+            .await?
+            .last_insert_rowid();
+        Ok(id)
+    }
+
+    pub async fn end_idle_gap(&self, id: i64, end_time: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE idle_gaps SET end_time = ?1 WHERE id = ?2")
+            .bind(end_time)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Idle gaps overlapping the time range, oldest first.
+    pub async fn get_idle_gaps(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<IdleGap>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, start_time, end_time, reason
+            FROM idle_gaps
+            WHERE (?1 IS NULL OR end_time IS NULL OR end_time >= ?1)
+                AND (?2 IS NULL OR start_time <= ?2)
+            ORDER BY start_time ASC
+            LIMIT ?3 OFFSET ?4
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Records that `classification` was playing on a device between
+    /// `start_time` and `end_time`. The latest block of the device is extended
+    /// when it ended less than `MEDIA_BLOCK_MAX_GAP` before. The audio chunk
+    /// kept for the time is added to the block.
+    pub async fn record_media_block(
+        &self,
+        device: &AudioDevice,
+        classification: &str,
+        confidence: f64,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        audio_chunk_id: Option<i64>,
+    ) -> Result<i64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let latest: Option<(i64, String, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+            SELECT id, classification, end_time
+            FROM media_blocks
+            WHERE device = ?1 AND is_input_device = ?2
+            ORDER BY end_time DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(&device.name)
+        .bind(device.device_type == DeviceType::Input)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let id = match latest {
+            Some((id, latest_classification, latest_end))
+                if latest_classification == classification
+                    && latest_end >= start_time - MEDIA_BLOCK_MAX_GAP =>
+            {
+                sqlx::query(
+                    r#"
+                    UPDATE media_blocks
+                    SET end_time = ?1, confidence = MAX(confidence, ?2)
+                    WHERE id = ?3
+                    "#,
+                )
+                .bind(end_time.max(latest_end))
+                .bind(confidence)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+                id
+            }
+            _ => sqlx::query(
+                r#"
+                INSERT INTO media_blocks
+                    (start_time, end_time, device, is_input_device, classification, confidence)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+            )
+            .bind(start_time)
+            .bind(end_time)
+            .bind(&device.name)
+            .bind(device.device_type == DeviceType::Input)
+            .bind(classification)
+            .bind(confidence)
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid(),
+        };
+        if let Some(audio_chunk_id) = audio_chunk_id {
+            sqlx::query(
+                "INSERT OR IGNORE INTO media_block_chunks (media_block_id, audio_chunk_id) VALUES (?1, ?2)",
+            )
+            .bind(id)
+            .bind(audio_chunk_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    pub async fn get_media_block(&self, id: i64) -> Result<Option<MediaBlock>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                id,
+                start_time,
+                end_time,
+                device AS device_name,
+                is_input_device AS is_input,
+                classification,
+                confidence
+            FROM media_blocks
+            WHERE id = ?1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Audio chunks kept for the media block, oldest first.
+    pub async fn get_media_block_chunks(&self, id: i64) -> Result<Vec<MediaChunk>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT audio_chunks.id, audio_chunks.file_path, audio_chunks.timestamp
+            FROM media_block_chunks
+            JOIN audio_chunks ON audio_chunks.id = media_block_chunks.audio_chunk_id
+            WHERE media_block_chunks.media_block_id = ?1
+            ORDER BY audio_chunks.timestamp ASC
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Deletes the media block along with the audio chunks kept for it,
+    /// returning them so their files can be removed.
+    pub async fn delete_media_block(&self, id: i64) -> Result<Vec<MediaChunk>, sqlx::Error> {
+        let chunks = self.get_media_block_chunks(id).await?;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            DELETE FROM audio_chunks
+            WHERE id IN (SELECT audio_chunk_id FROM media_block_chunks WHERE media_block_id = ?1)
+                AND NOT EXISTS (
+                    SELECT 1 FROM audio_transcriptions
+                    WHERE audio_transcriptions.audio_chunk_id = audio_chunks.id
+                )
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM media_blocks WHERE id = ?1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(chunks)
+    }
+
+    /// Media blocks overlapping the time range, oldest first.
+    pub async fn get_media_blocks(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<MediaBlock>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                id,
+                start_time,
+                end_time,
+                device AS device_name,
+                is_input_device AS is_input,
+                classification,
+                confidence
+            FROM media_blocks
+            WHERE (?1 IS NULL OR end_time >= ?1)
+                AND (?2 IS NULL OR start_time <= ?2)
+            ORDER BY start_time ASC
+            LIMIT ?3 OFFSET ?4
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn insert_activity_segment(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        category: &str,
+        app_name: Option<&str>,
+        frame_count: i64,
+        has_audio: bool,
+    ) -> Result<i64, sqlx::Error> {
+        let id = sqlx::query(
+            "INSERT INTO activity_segments (start_time, end_time, category, app_name, frame_count, has_audio) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(category)
+        .bind(app_name)
+        .bind(frame_count)
+        .bind(has_audio)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    /// End of the last classified time block.
+    pub async fn get_last_activity_segment_end(
+        &self,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        sqlx::query_scalar("SELECT MAX(end_time) FROM activity_segments")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// Time of the first frame or transcript at or after `after`, or of the
+    /// first one recorded.
+    pub async fn get_next_recording_time(
+        &self,
+        after: Option<DateTime<Utc>>,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT MIN(timestamp) FROM (
+                SELECT MIN(timestamp) AS timestamp FROM frames
+                WHERE ?1 IS NULL OR timestamp >= ?1
+                UNION ALL
+                SELECT MIN(timestamp) AS timestamp FROM audio_transcriptions
+                WHERE ?1 IS NULL OR timestamp >= ?1
+            )
+            "#,
+        )
+        .bind(after)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Activity segments starting in the time range, oldest first.
+    pub async fn list_activity_segments(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        category: Option<&str>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ActivitySegment>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, start_time, end_time, category, app_name, frame_count, has_audio
+            FROM activity_segments
+            WHERE (?1 IS NULL OR start_time >= ?1)
+                AND (?2 IS NULL OR start_time <= ?2)
+                AND (?3 IS NULL OR category = ?3)
+            ORDER BY start_time ASC
+            LIMIT ?4 OFFSET ?5
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(category)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Time spent in each activity category in segments starting in the
+    /// time range, the longest first.
+    pub async fn get_activity_summary(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> Result<Vec<ActivitySummary>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                category,
+                CAST(ROUND(SUM(julianday(end_time) - julianday(start_time)) * 86400) AS INTEGER) AS seconds,
+                COUNT(*) AS segment_count
+            FROM activity_segments
+            WHERE (?1 IS NULL OR start_time >= ?1)
+                AND (?2 IS NULL OR start_time <= ?2)
+            GROUP BY category
+            ORDER BY seconds DESC, category ASC
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn insert_meeting_session(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        app_name: Option<&str>,
+    ) -> Result<MeetingSession, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            INSERT INTO meeting_sessions (start_time, end_time, app_name)
+            VALUES (?1, ?2, ?3)
+            RETURNING id, start_time, end_time, app_name, ended
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(app_name)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Moves the end of a meeting still going on forward to `end_time`.
+    pub async fn extend_meeting_session(
+        &self,
+        id: i64,
+        end_time: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE meeting_sessions SET end_time = ?2 WHERE id = ?1 AND ended = FALSE AND end_time < ?2",
+        )
+        .bind(id)
+        .bind(end_time)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn end_meeting_session(&self, id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE meeting_sessions SET ended = TRUE WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// The meeting going on, if any.
+    pub async fn get_open_meeting_session(&self) -> Result<Option<MeetingSession>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, start_time, end_time, app_name, ended
+            FROM meeting_sessions
+            WHERE ended = FALSE
+            ORDER BY start_time DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn get_meeting_session(
+        &self,
+        id: i64,
+    ) -> Result<Option<MeetingSession>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT id, start_time, end_time, app_name, ended FROM meeting_sessions WHERE id = ?1",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Meetings overlapping the time range, the latest first.
+    pub async fn list_meeting_sessions(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<MeetingSession>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, start_time, end_time, app_name, ended
+            FROM meeting_sessions
+            WHERE (?1 IS NULL OR end_time >= ?1)
+                AND (?2 IS NULL OR start_time <= ?2)
+            ORDER BY start_time DESC, id DESC
+            LIMIT ?3 OFFSET ?4
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Speakers transcribed in the time range, by when they first spoke.
+    /// Speakers marked as hallucinations are left out.
+    pub async fn get_meeting_participants(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<MeetingParticipant>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                at.speaker_id AS speaker_id,
+                NULLIF(s.name, '') AS name,
+                COUNT(*) AS transcript_count,
+                MIN(at.timestamp) AS first_spoke_at
+            FROM audio_transcriptions at
+            JOIN speakers s ON s.id = at.speaker_id
+            WHERE at.timestamp >= ?1 AND at.timestamp <= ?2
+                AND s.hallucination = FALSE
+            GROUP BY at.speaker_id
+            ORDER BY first_spoke_at ASC, at.speaker_id ASC
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Stores the summary of a period, replacing the one already written for
+    /// it.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn upsert_summary(
+        &self,
+        period: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        summary: &str,
+        topics: &[String],
+        action_items: &[String],
+        people: &[String],
+        model: &str,
+    ) -> Result<Summary, sqlx::Error> {
+        let to_json = |items: &[String]| serde_json::to_string(items).unwrap_or_default();
+        let raw: SummaryRaw = sqlx::query_as(
+            r#"
+            INSERT INTO summaries (period, start_time, end_time, summary, topics, action_items, people, model)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT(period, start_time) DO UPDATE SET
+                end_time = excluded.end_time,
+                summary = excluded.summary,
+                topics = excluded.topics,
+                action_items = excluded.action_items,
+                people = excluded.people,
+                model = excluded.model,
+                created_at = CURRENT_TIMESTAMP
+            RETURNING *
+            "#,
+        )
+        .bind(period)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(summary)
+        .bind(to_json(topics))
+        .bind(to_json(action_items))
+        .bind(to_json(people))
+        .bind(model)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(summary_from_raw(raw))
+    }
+
+    pub async fn get_summary(
+        &self,
+        period: &str,
+        start_time: DateTime<Utc>,
+    ) -> Result<Option<Summary>, sqlx::Error> {
+        let raw: Option<SummaryRaw> =
+            sqlx::query_as("SELECT * FROM summaries WHERE period = ?1 AND start_time = ?2")
+                .bind(period)
+                .bind(start_time)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(raw.map(summary_from_raw))
+    }
+
+    /// Summaries of periods starting in the time range, the latest first.
+    pub async fn list_summaries(
+        &self,
+        period: Option<&str>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Summary>, sqlx::Error> {
+        let raws: Vec<SummaryRaw> = sqlx::query_as(
+            r#"
+            SELECT * FROM summaries
+            WHERE (?1 IS NULL OR period = ?1)
+                AND (?2 IS NULL OR start_time >= ?2)
+                AND (?3 IS NULL OR start_time < ?3)
+            ORDER BY start_time DESC, id DESC
+            LIMIT ?4 OFFSET ?5
+            "#,
+        )
+        .bind(period)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(raws.into_iter().map(summary_from_raw).collect())
+    }
+
+    pub async fn insert_extraction_rule(
+        &self,
+        name: &str,
+        app_name: &str,
+        window_name: Option<&str>,
+        instructions: &str,
+        schema: &serde_json::Value,
+    ) -> Result<ExtractionRule, sqlx::Error> {
+        let raw: ExtractionRuleRaw = sqlx::query_as(
+            r#"
+            INSERT INTO extraction_rules (name, app_name, window_name, instructions, schema, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            RETURNING *
+            "#,
+        )
+        .bind(name)
+        .bind(app_name)
+        .bind(window_name)
+        .bind(instructions)
+        .bind(schema.to_string())
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(extraction_rule_from_raw(raw))
+    }
+
+    pub async fn list_extraction_rules(&self) -> Result<Vec<ExtractionRule>, sqlx::Error> {
+        let raws: Vec<ExtractionRuleRaw> =
+            sqlx::query_as("SELECT * FROM extraction_rules ORDER BY id ASC")
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(raws.into_iter().map(extraction_rule_from_raw).collect())
+    }
+
+    /// Returns whether an extraction rule was deleted, with its records.
+    pub async fn delete_extraction_rule(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM extractions WHERE rule_id = ?1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query("DELETE FROM extraction_rules WHERE id = ?1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Records that the sessions of an extraction rule ended before
+    /// `processed_until` were extracted.
+    pub async fn mark_extraction_rule_processed(
+        &self,
+        id: i64,
+        processed_until: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE extraction_rules SET processed_until = ?1 WHERE id = ?2")
+            .bind(processed_until)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Timestamps of the frames of an app, and of a window of it, recorded
+    /// after `after` and until `until`, oldest first.
+    pub async fn get_app_frame_times(
+        &self,
+        app_name: &str,
+        window_name: Option<&str>,
+        after: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<DateTime<Utc>>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT f.timestamp
+            FROM frames f
+            LEFT JOIN ocr_text ot ON ot.frame_id = f.id
+            WHERE f.timestamp > ?1
+                AND f.timestamp <= ?2
+                AND COALESCE(f.app_name, ot.app_name) LIKE '%' || ?3 || '%' COLLATE NOCASE
+                AND (?4 IS NULL OR COALESCE(f.window_name, ot.window_name) LIKE '%' || ?4 || '%' COLLATE NOCASE)
+            GROUP BY f.id
+            ORDER BY f.timestamp ASC
+            "#,
+        )
+        .bind(after)
+        .bind(until)
+        .bind(app_name)
+        .bind(window_name)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn insert_extraction(
+        &self,
+        rule_id: i64,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        data: &serde_json::Value,
+        model: &str,
+    ) -> Result<Extraction, sqlx::Error> {
+        let raw: ExtractionRaw = sqlx::query_as(
+            r#"
+            INSERT INTO extractions (rule_id, start_time, end_time, data, model, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            RETURNING *
+            "#,
+        )
+        .bind(rule_id)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(data.to_string())
+        .bind(model)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(extraction_from_raw(raw))
+    }
+
+    /// Records of sessions starting in the time range, the latest first.
+    pub async fn list_extractions(
+        &self,
+        rule_id: Option<i64>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Extraction>, sqlx::Error> {
+        let raws: Vec<ExtractionRaw> = sqlx::query_as(
+            r#"
+            SELECT * FROM extractions
+            WHERE (?1 IS NULL OR rule_id = ?1)
+                AND (?2 IS NULL OR start_time >= ?2)
+                AND (?3 IS NULL OR start_time < ?3)
+            ORDER BY start_time DESC, id DESC
+            LIMIT ?4 OFFSET ?5
+            "#,
+        )
+        .bind(rule_id)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(raws.into_iter().map(extraction_from_raw).collect())
+    }
+
+    pub async fn insert_api_token(
+        &self,
+        name: Option<&str>,
+        scope: &str,
+        token_hash: &str,
+    ) -> Result<ApiToken, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            INSERT INTO api_tokens (name, scope, token_hash, created_at)
+            VALUES (?1, ?2, ?3, ?4)
+            RETURNING id, name, scope, created_at, last_used_at
+            "#,
+        )
+        .bind(name)
+        .bind(scope)
+        .bind(token_hash)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn list_api_tokens(&self) -> Result<Vec<ApiToken>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT id, name, scope, created_at, last_used_at FROM api_tokens ORDER BY id ASC",
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Looks up the token with this hash and records that it was used.
+    pub async fn use_api_token(&self, token_hash: &str) -> Result<Option<ApiToken>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            UPDATE api_tokens SET last_used_at = ?1
+            WHERE token_hash = ?2
+            RETURNING id, name, scope, created_at, last_used_at
+            "#,
+        )
+        .bind(Utc::now())
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Returns whether a token was revoked.
+    pub async fn delete_api_token(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Inserts an event, or updates the one with the same `source` and `uid`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn upsert_calendar_event(
+        &self,
+        source: &str,
+        uid: &str,
+        title: &str,
+        attendees: &[String],
+        location: Option<&str>,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<CalendarEvent, sqlx::Error> {
+        let raw: CalendarEventRaw = sqlx::query_as(
+            r#"
+            INSERT INTO calendar_events
+                (source, uid, title, attendees, location, start_time, end_time, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT (source, uid) DO UPDATE SET
+                title = excluded.title,
+                attendees = excluded.attendees,
+                location = excluded.location,
+                start_time = excluded.start_time,
+                end_time = excluded.end_time,
+                updated_at = excluded.updated_at
+            RETURNING *
+            "#,
+        )
+        .bind(source)
+        .bind(uid)
+        .bind(title)
+        .bind(serde_json::to_string(attendees).unwrap_or_else(|_| "[]".to_string()))
+        .bind(location)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(calendar_event_from_raw(raw))
+    }
+
+    /// Deletes the events of `source` starting in the time range that aren't
+    /// in `uids`, e.g. cancelled or moved out of it, returning how many were.
+    pub async fn delete_calendar_events_except(
+        &self,
+        source: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        uids: &[String],
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM calendar_events
+            WHERE source = ?1 AND start_time >= ?2 AND start_time < ?3
+                AND uid NOT IN (SELECT value FROM json_each(?4))
+            "#,
+        )
+        .bind(source)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(serde_json::to_string(uids).unwrap_or_else(|_| "[]".to_string()))
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Events overlapping the time range whose title contains `title`,
+    /// earliest first.
+    pub async fn list_calendar_events(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        title: Option<&str>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<CalendarEvent>, sqlx::Error> {
+        let raws: Vec<CalendarEventRaw> = sqlx::query_as(
+            r#"
+            SELECT * FROM calendar_events
+            WHERE (?1 IS NULL OR end_time >= ?1)
+                AND (?2 IS NULL OR start_time <= ?2)
+                AND (?3 IS NULL OR title LIKE '%' || ?3 || '%')
+            ORDER BY start_time ASC, id ASC
+            LIMIT ?4 OFFSET ?5
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(title)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(raws.into_iter().map(calendar_event_from_raw).collect())
+    }
+
+    pub async fn insert_companion_device(
+        &self,
+        name: &str,
+        token_hash: &str,
+    ) -> Result<CompanionDevice, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            INSERT INTO companion_devices (name, token_hash, created_at)
+            VALUES (?1, ?2, ?3)
+            RETURNING id, name, created_at, last_seen_at
+            "#,
+        )
+        .bind(name)
+        .bind(token_hash)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn list_companion_devices(&self) -> Result<Vec<CompanionDevice>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT id, name, created_at, last_seen_at FROM companion_devices ORDER BY id ASC",
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// The device paired with the token of hash `token_hash`, marking it as
+    /// seen.
+    pub async fn use_companion_device(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<CompanionDevice>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            UPDATE companion_devices SET last_seen_at = ?1
+            WHERE token_hash = ?2
+            RETURNING id, name, created_at, last_seen_at
+            "#,
+        )
+        .bind(Utc::now())
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Returns whether a device was unpaired. What it uploaded is kept.
+    pub async fn delete_companion_device(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM companion_devices WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn insert_companion_upload(
+        &self,
+        id: &str,
+        device_id: i64,
+        kind: &str,
+        content_type: &str,
+        size: i64,
+        sha256: &str,
+        timestamp: Option<DateTime<Utc>>,
+        metadata: Option<&serde_json::Value>,
+    ) -> Result<CompanionUpload, sqlx::Error> {
+        let raw: CompanionUploadRaw = sqlx::query_as(
+            r#"
+            INSERT INTO companion_uploads
+                (id, device_id, kind, content_type, size, sha256, timestamp, metadata, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(device_id)
+        .bind(kind)
+        .bind(content_type)
+        .bind(size)
+        .bind(sha256)
+        .bind(timestamp)
+        .bind(metadata.map(|metadata| metadata.to_string()))
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(companion_upload_from_raw(raw))
+    }
+
+    pub async fn get_companion_upload(
+        &self,
+        id: &str,
+    ) -> Result<Option<CompanionUpload>, sqlx::Error> {
+        let raw: Option<CompanionUploadRaw> =
+            sqlx::query_as("SELECT * FROM companion_uploads WHERE id = ?1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(raw.map(companion_upload_from_raw))
+    }
+
+    /// An upload of the file of hash `sha256`: a completed one from any
+    /// device, else one `device_id` hasn't finished.
+    pub async fn find_companion_upload(
+        &self,
+        sha256: &str,
+        device_id: i64,
+    ) -> Result<Option<CompanionUpload>, sqlx::Error> {
+        let raw: Option<CompanionUploadRaw> = sqlx::query_as(
+            r#"
+            SELECT * FROM companion_uploads
+            WHERE sha256 = ?1 AND (completed_at IS NOT NULL OR device_id = ?2)
+            ORDER BY completed_at IS NULL, created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(sha256)
+        .bind(device_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(raw.map(companion_upload_from_raw))
+    }
+
+    pub async fn complete_companion_upload(
+        &self,
+        id: &str,
+        ingest_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE companion_uploads SET ingest_id = ?1, completed_at = ?2 WHERE id = ?3")
+            .bind(ingest_id)
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the uploads not completed and started before `before`,
+    /// returning their ids.
+    pub async fn delete_stale_companion_uploads(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            DELETE FROM companion_uploads
+            WHERE completed_at IS NULL AND created_at < ?1
+            RETURNING id
+            "#,
+        )
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn insert_saved_search(
+        &self,
+        name: &str,
+        query: &str,
+        content_type: &str,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        speaker_name: Option<&str>,
+        notify: bool,
+        webhook_url: Option<&str>,
+    ) -> Result<SavedSearch, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            INSERT INTO saved_searches (
+                name, query, content_type, app_name, window_name, speaker_name, notify,
+                webhook_url, created_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            RETURNING *
+            "#,
+        )
+        .bind(name)
+        .bind(query)
+        .bind(content_type)
+        .bind(app_name)
+        .bind(window_name)
+        .bind(speaker_name)
+        .bind(notify)
+        .bind(webhook_url)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn list_saved_searches(&self) -> Result<Vec<SavedSearch>, sqlx::Error> {
+        sqlx::query_as("SELECT * FROM saved_searches ORDER BY id ASC")
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Returns whether a saved search was deleted.
+    pub async fn delete_saved_search(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM saved_searches WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Records that a saved search was checked against what was recorded
+    /// until `checked_at`, and whether it matched.
+    pub async fn mark_saved_search_checked(
+        &self,
+        id: i64,
+        checked_at: DateTime<Utc>,
+        matched: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE saved_searches
+            SET last_checked_at = ?1,
+                last_matched_at = CASE WHEN ?2 THEN ?1 ELSE last_matched_at END
+            WHERE id = ?3
+            "#,
+        )
+        .bind(checked_at)
+        .bind(matched)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn insert_vocabulary_term(
+        &self,
+        term: &str,
+        sounds_like: Option<&str>,
+        app_name: Option<&str>,
+    ) -> Result<VocabularyEntry, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            INSERT INTO vocabulary_terms (term, sounds_like, app_name, created_at)
+            VALUES (?1, ?2, ?3, ?4)
+            RETURNING *
+            "#,
+        )
+        .bind(term)
+        .bind(sounds_like)
+        .bind(app_name)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Every term, or with `app_name` the terms used while it is focused:
+    /// those without an app and those of the app, ignoring case.
+    pub async fn list_vocabulary_terms(
+        &self,
+        app_name: Option<&str>,
+    ) -> Result<Vec<VocabularyEntry>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM vocabulary_terms
+            WHERE ?1 IS NULL OR app_name IS NULL OR app_name = ?1 COLLATE NOCASE
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(app_name)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Returns whether a term was deleted.
+    pub async fn delete_vocabulary_term(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM vocabulary_terms WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn insert_webhook(
+        &self,
+        url: &str,
+        events: &str,
+        keyword: Option<&str>,
+        app_name: Option<&str>,
+        secret: &str,
+    ) -> Result<Webhook, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            INSERT INTO webhooks (url, events, keyword, app_name, secret, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            RETURNING *
+            "#,
+        )
+        .bind(url)
+        .bind(events)
+        .bind(keyword)
+        .bind(app_name)
+        .bind(secret)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn list_webhooks(&self) -> Result<Vec<Webhook>, sqlx::Error> {
+        sqlx::query_as("SELECT * FROM webhooks ORDER BY id ASC")
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn get_webhook(&self, id: i64) -> Result<Option<Webhook>, sqlx::Error> {
+        sqlx::query_as("SELECT * FROM webhooks WHERE id = ?1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Returns whether a webhook was deleted, its dead letters go with it.
+    pub async fn delete_webhook(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM webhook_dead_letters WHERE webhook_id = ?1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query("DELETE FROM webhooks WHERE id = ?1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn insert_webhook_dead_letter(
+        &self,
+        webhook_id: i64,
+        event: &str,
+        payload: &str,
+        error: &str,
+        attempts: i64,
+    ) -> Result<WebhookDeadLetter, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            INSERT INTO webhook_dead_letters (webhook_id, event, payload, error, attempts, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            RETURNING *
+            "#,
+        )
+        .bind(webhook_id)
+        .bind(event)
+        .bind(payload)
+        .bind(error)
+        .bind(attempts)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Newest dead letters first.
+    pub async fn list_webhook_dead_letters(
+        &self,
+        limit: u32,
+    ) -> Result<Vec<WebhookDeadLetter>, sqlx::Error> {
+        sqlx::query_as("SELECT * FROM webhook_dead_letters ORDER BY id DESC LIMIT ?1")
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn get_webhook_dead_letter(
+        &self,
+        id: i64,
+    ) -> Result<Option<WebhookDeadLetter>, sqlx::Error> {
+        sqlx::query_as("SELECT * FROM webhook_dead_letters WHERE id = ?1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Records another failed attempt to deliver a dead letter.
+    pub async fn update_webhook_dead_letter(
+        &self,
+        id: i64,
+        error: &str,
+        attempts: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE webhook_dead_letters SET error = ?1, attempts = ?2 WHERE id = ?3")
+            .bind(error)
+            .bind(attempts)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_webhook_dead_letter(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM webhook_dead_letters WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns `None` when the frame or transcript segment annotated doesn't
+    /// exist.
+    pub async fn insert_annotation(
+        &self,
+        target: AnnotationTarget,
+        note: Option<&str>,
+        tags: &[String],
+    ) -> Result<Option<Annotation>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let (frame_id, audio_transcription_id, start_time, end_time) = match target {
+            AnnotationTarget::Frame(id) => {
+                let timestamp: Option<DateTime<Utc>> =
+                    sqlx::query_scalar("SELECT timestamp FROM frames WHERE id = ?1")
+                        .bind(id)
+                        .fetch_optional(&mut *tx)
+                        .await?;
+                match timestamp {
+                    Some(timestamp) => (Some(id), None, timestamp, timestamp),
+                    None => return Ok(None),
+                }
+            }
+            AnnotationTarget::Transcription(id) => {
+                let timestamp: Option<DateTime<Utc>> =
+                    sqlx::query_scalar("SELECT timestamp FROM audio_transcriptions WHERE id = ?1")
+                        .bind(id)
+                        .fetch_optional(&mut *tx)
+                        .await?;
+                match timestamp {
+                    Some(timestamp) => (None, Some(id), timestamp, timestamp),
+                    None => return Ok(None),
+                }
+            }
+            AnnotationTarget::TimeRange(start_time, end_time) => (None, None, start_time, end_time),
+        };
+
+        let now = Utc::now();
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO annotations (
+                frame_id, audio_transcription_id, start_time, end_time, note, created_at,
+                updated_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+            RETURNING id
+            "#,
+        )
+        .bind(frame_id)
+        .bind(audio_transcription_id)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(note)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+        Self::set_annotation_tags(&mut tx, id, tags).await?;
+        tx.commit().await?;
+
+        self.get_annotation(id).await
+    }
+
+    async fn set_annotation_tags(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        annotation_id: i64,
+        tags: &[String],
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM annotation_tags WHERE annotation_id = ?1")
+            .bind(annotation_id)
+            .execute(&mut **tx)
+            .await?;
+        for tag in tags {
+            let tag_id: i64 = sqlx::query_scalar(
+                "INSERT INTO tags (name) VALUES (?) ON CONFLICT(name) DO UPDATE SET name=name RETURNING id",
+            )
+            .bind(tag)
+            .fetch_one(&mut **tx)
+            .await?;
+            sqlx::query(
+                "INSERT INTO annotation_tags (annotation_id, tag_id) VALUES (?1, ?2) ON CONFLICT DO NOTHING",
+            )
+            .bind(annotation_id)
+            .bind(tag_id)
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_annotation(&self, id: i64) -> Result<Option<Annotation>, sqlx::Error> {
+        let raw: Option<AnnotationRaw> = sqlx::query_as(&format!(
+            "{} WHERE annotations.id = ?1 GROUP BY annotations.id",
+            ANNOTATION_SELECT
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(raw.map(annotation_from_raw))
+    }
+
+    /// Annotations overlapping the time range, oldest first.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn list_annotations(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        frame_id: Option<i64>,
+        audio_transcription_id: Option<i64>,
+        tag: Option<&str>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Annotation>, sqlx::Error> {
+        let raw: Vec<AnnotationRaw> = sqlx::query_as(&format!(
+            r#"
+            {}
+            WHERE (?1 IS NULL OR annotations.end_time >= ?1)
+                AND (?2 IS NULL OR annotations.start_time <= ?2)
+                AND (?3 IS NULL OR annotations.frame_id = ?3)
+                AND (?4 IS NULL OR annotations.audio_transcription_id = ?4)
+                AND (?5 IS NULL OR annotations.id IN (
+                    SELECT annotation_tags.annotation_id FROM annotation_tags
+                    JOIN tags ON annotation_tags.tag_id = tags.id
+                    WHERE tags.name = ?5))
+            GROUP BY annotations.id
+            ORDER BY annotations.start_time ASC, annotations.id ASC
+            LIMIT ?6 OFFSET ?7
+            "#,
+            ANNOTATION_SELECT
+        ))
+        .bind(start_time)
+        .bind(end_time)
+        .bind(frame_id)
+        .bind(audio_transcription_id)
+        .bind(tag)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(raw.into_iter().map(annotation_from_raw).collect())
+    }
+
+    /// Replaces the note, `Some("")` clearing it, and the tags of an
+    /// annotation when given. Returns `None` when it doesn't exist.
+    pub async fn update_annotation(
+        &self,
+        id: i64,
+        note: Option<&str>,
+        tags: Option<&[String]>,
+    ) -> Result<Option<Annotation>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE annotations
+            SET note = CASE WHEN ?1 THEN NULLIF(?2, '') ELSE note END,
+                updated_at = ?3
+            WHERE id = ?4
+            "#,
+        )
+        .bind(note.is_some())
+        .bind(note)
+        .bind(Utc::now())
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+        if let Some(tags) = tags {
+            Self::set_annotation_tags(&mut tx, id, tags).await?;
+        }
+        tx.commit().await?;
+
+        self.get_annotation(id).await
+    }
+
+    /// Returns whether an annotation was deleted.
+    pub async fn delete_annotation(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM annotation_tags WHERE annotation_id = ?1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query("DELETE FROM annotations WHERE id = ?1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Id of this device for sync, `candidate` is kept on first use.
+    pub async fn sync_device_id(&self, candidate: &str) -> Result<String, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let existing: Option<String> = sqlx::query_scalar("SELECT id FROM sync_device LIMIT 1")
+            .fetch_optional(&mut *tx)
+            .await?;
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                sqlx::query("INSERT INTO sync_device (id) VALUES (?1)")
+                    .bind(candidate)
+                    .execute(&mut *tx)
+                    .await?;
+                candidate.to_string()
+            }
+        };
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    /// Frames captured on this device after the frame `after_id`, oldest
+    /// first. Frames are only sent once they have OCR text, imported frames
+    /// never are.
+    pub async fn get_sync_frames(
+        &self,
+        after_id: i64,
+        limit: u32,
+    ) -> Result<Vec<FrameEntry>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                f.id,
+                f.timestamp,
+                f.device_name,
+                COALESCE(f.app_name, ot.app_name) AS app_name,
+                COALESCE(f.window_name, ot.window_name) AS window_name,
+                f.browser_url,
+                COALESCE(f.focused, ot.focused, 0) AS focused,
+                ot.text AS ocr_text
+            FROM frames f
+            JOIN ocr_text ot ON ot.frame_id = f.id
+            WHERE f.id > ?1
+                AND NOT EXISTS (
+                    SELECT 1 FROM sync_imports si
+                    WHERE si.kind = 'frame' AND si.local_id = f.id
+                )
+            GROUP BY f.id
+            ORDER BY f.id ASC
+            LIMIT ?2
+            "#,
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Transcripts recorded on this device after the transcript `after_id`,
+    /// oldest first.
+    pub async fn get_sync_transcriptions(
+        &self,
+        after_id: i64,
+        limit: u32,
+    ) -> Result<Vec<TimelineTranscript>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                at.id,
+                at.timestamp,
+                at.device AS device_name,
+                at.is_input_device AS is_input,
+                NULL AS speaker_id,
+                at.transcription,
+                at.start_time,
+                at.end_time
+            FROM audio_transcriptions at
+            WHERE at.id > ?1
+                AND NOT EXISTS (
+                    SELECT 1 FROM sync_imports si
+                    WHERE si.kind = 'transcription' AND si.local_id = at.id
+                )
+            ORDER BY at.id ASC
+            LIMIT ?2
+            "#,
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn get_sync_cursor(&self, peer_url: &str) -> Result<SyncCursor, sqlx::Error> {
+        let cursor = sqlx::query_as(
+            "SELECT frame_cursor, transcription_cursor FROM sync_peers WHERE url = ?1",
+        )
+        .bind(peer_url)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(cursor.unwrap_or_default())
+    }
+
+    /// Imports a batch pulled from `peer_url` and moves its cursor, in one
+    /// transaction. Records already imported, from this peer or through
+    /// another one, are skipped, so batches can be pulled again safely.
+    /// Returns how many records were new.
+    pub async fn import_sync_batch(
+        &self,
+        peer_url: &str,
+        batch: &SyncBatch,
+    ) -> Result<usize, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let chunk_path = format!("sync://{}", batch.device_id);
+        let mut imported = 0;
+
+        for frame in &batch.frames {
+            if sync_imported(&mut tx, &batch.device_id, "frame", frame.id).await? {
+                continue;
+            }
+            let device_name = format!("{}/{}", batch.host, frame.device_name);
+            let video_chunk_id = match sqlx::query_scalar::<_, i64>(
+                "SELECT id FROM video_chunks WHERE file_path = ?1 AND device_name = ?2",
+            )
+            .bind(&chunk_path)
+            .bind(&device_name)
+            .fetch_optional(&mut *tx)
+            .await?
+            {
+                Some(id) => id,
+                None => {
+                    sqlx::query("INSERT INTO video_chunks (file_path, device_name) VALUES (?1, ?2)")
+                        .bind(&chunk_path)
+                        .bind(&device_name)
+                        .execute(&mut *tx)
+                        .await?
+                        .last_insert_rowid()
+                }
+            };
+            let frame_id = sqlx::query(
+                "INSERT INTO frames (video_chunk_id, offset_index, timestamp, browser_url, app_name, window_name, focused, device_name) VALUES (?1, 0, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )
+            .bind(video_chunk_id)
+            .bind(frame.timestamp)
+            .bind(&frame.browser_url)
+            .bind(&frame.app_name)
+            .bind(&frame.window_name)
+            .bind(frame.focused)
+            .bind(&device_name)
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+            let text = frame.ocr_text.as_deref().unwrap_or_default();
+            sqlx::query(
+                "INSERT INTO ocr_text (frame_id, text, text_json, ocr_engine, text_length) VALUES (?1, ?2, '', 'Sync', ?3)",
+            )
+            .bind(frame_id)
+            .bind(text)
+            .bind(text.len() as i64)
+            .execute(&mut *tx)
+            .await?;
+            record_sync_import(&mut tx, &batch.device_id, "frame", frame.id, frame_id).await?;
+            imported += 1;
+        }
+
+        for transcript in &batch.transcripts {
+            if sync_imported(&mut tx, &batch.device_id, "transcription", transcript.id).await? {
+                continue;
+            }
+            let audio_chunk_id = match sqlx::query_scalar::<_, i64>(
+                "SELECT id FROM audio_chunks WHERE file_path = ?1",
+            )
+            .bind(&chunk_path)
+            .fetch_optional(&mut *tx)
+            .await?
+            {
+                Some(id) => id,
+                None => {
+                    sqlx::query("INSERT INTO audio_chunks (file_path, timestamp) VALUES (?1, ?2)")
+                        .bind(&chunk_path)
+                        .bind(Utc::now())
+                        .execute(&mut *tx)
+                        .await?
+                        .last_insert_rowid()
+                }
+            };
+            let transcription_id = sqlx::query(
+                "INSERT INTO audio_transcriptions (audio_chunk_id, transcription, offset_index, timestamp, transcription_engine, device, is_input_device, start_time, end_time, text_length) VALUES (?1, ?2, 0, ?3, 'Sync', ?4, ?5, ?6, ?7, ?8)",
+            )
+            .bind(audio_chunk_id)
+            .bind(&transcript.transcription)
+            .bind(transcript.timestamp)
+            .bind(format!("{}/{}", batch.host, transcript.device_name))
+            .bind(transcript.is_input)
+            .bind(transcript.start_time)
+            .bind(transcript.end_time)
+            .bind(transcript.transcription.len() as i64)
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+            record_sync_import(
+                &mut tx,
+                &batch.device_id,
+                "transcription",
+                transcript.id,
+                transcription_id,
+            )
+            .await?;
+            imported += 1;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO sync_peers (url, device_id, frame_cursor, transcription_cursor, last_synced_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(url) DO UPDATE SET
+                device_id = excluded.device_id,
+                frame_cursor = excluded.frame_cursor,
+                transcription_cursor = excluded.transcription_cursor,
+                last_synced_at = excluded.last_synced_at
+            "#,
+        )
+        .bind(peer_url)
+        .bind(&batch.device_id)
+        .bind(batch.next_cursor.frame_cursor)
+        .bind(batch.next_cursor.transcription_cursor)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(imported)
+    }
+
+    /// Frames with their OCR text in the time range, newest first.
+    pub async fn get_frames(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<FrameEntry>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                f.id,
+                f.timestamp,
+                f.device_name,
+                COALESCE(f.app_name, ot.app_name) AS app_name,
+                COALESCE(f.window_name, ot.window_name) AS window_name,
+                f.browser_url,
+                COALESCE(f.focused, ot.focused, 0) AS focused,
+                ot.text AS ocr_text
+            FROM frames f
+            LEFT JOIN ocr_text ot ON ot.frame_id = f.id
+            WHERE (?1 IS NULL OR f.timestamp >= ?1)
+                AND (?2 IS NULL OR f.timestamp <= ?2)
+                AND (?3 IS NULL OR COALESCE(f.app_name, ot.app_name) LIKE '%' || ?3 || '%' COLLATE NOCASE)
+            GROUP BY f.id
+            ORDER BY f.timestamp DESC, f.id DESC
+            LIMIT ?4 OFFSET ?5
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(app_name)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Audio transcripts in the time range, oldest first.
+    pub async fn get_audio_transcriptions(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        speaker_id: Option<i64>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<TimelineTranscript>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                id,
+                timestamp,
+                device AS device_name,
+                is_input_device AS is_input,
+                speaker_id,
+                transcription,
+                start_time,
+                end_time
+            FROM audio_transcriptions
+            WHERE (?1 IS NULL OR timestamp >= ?1)
+                AND (?2 IS NULL OR timestamp <= ?2)
+                AND (?3 IS NULL OR speaker_id = ?3)
+            ORDER BY timestamp ASC, id ASC
+            LIMIT ?4 OFFSET ?5
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(speaker_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Frames with their text in the time range, oldest first, optionally
+    /// only those of an app or with a tag.
+    pub async fn get_export_frames(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        app_name: Option<&str>,
+        tag: Option<&str>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<FrameEntry>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                f.id,
+                f.timestamp,
+                f.device_name,
+                COALESCE(f.app_name, ot.app_name) AS app_name,
+                COALESCE(f.window_name, ot.window_name) AS window_name,
+                f.browser_url,
+                COALESCE(f.focused, ot.focused, 0) AS focused,
+                ot.text AS ocr_text
+            FROM frames f
+            LEFT JOIN ocr_text ot ON ot.frame_id = f.id
+            WHERE f.timestamp >= ?1 AND f.timestamp <= ?2
+                AND (?3 IS NULL OR COALESCE(f.app_name, ot.app_name) LIKE '%' || ?3 || '%' COLLATE NOCASE)
+                AND (?4 IS NULL OR EXISTS (
+                    SELECT 1 FROM vision_tags
+                    JOIN tags ON tags.id = vision_tags.tag_id
+                    WHERE vision_tags.vision_id = f.id AND tags.name = ?4
+                ))
+            GROUP BY f.id
+            ORDER BY f.timestamp ASC, f.id ASC
+            LIMIT ?5 OFFSET ?6
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(app_name)
+        .bind(tag)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Audio transcripts in the time range with their speaker, oldest first,
+    /// optionally only those of a speaker, by name, or whose chunk has a tag.
+    pub async fn get_export_transcripts(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        speaker: Option<&str>,
+        tag: Option<&str>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ExportTranscript>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                at.id,
+                at.timestamp,
+                at.device AS device_name,
+                at.is_input_device AS is_input,
+                at.speaker_id,
+                NULLIF(s.name, '') AS speaker_name,
+                at.transcription,
+                at.start_time,
+                at.end_time
+            FROM audio_transcriptions at
+            LEFT JOIN speakers s ON s.id = at.speaker_id
+            WHERE at.timestamp >= ?1 AND at.timestamp <= ?2
+                AND LENGTH(TRIM(at.transcription)) > 0
+                AND (?3 IS NULL OR s.name = ?3 COLLATE NOCASE)
+                AND (?4 IS NULL OR EXISTS (
+                    SELECT 1 FROM audio_tags
+                    JOIN tags ON tags.id = audio_tags.tag_id
+                    WHERE audio_tags.audio_chunk_id = at.audio_chunk_id AND tags.name = ?4
+                ))
+            ORDER BY at.timestamp ASC, at.start_time ASC, at.id ASC
+            LIMIT ?5 OFFSET ?6
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(speaker)
+        .bind(tag)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Apps seen in the time range, most captured first.
+    pub async fn get_app_usage(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: u32,
+    ) -> Result<Vec<AppUsage>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                app_name,
+                COUNT(*) AS frame_count,
+                MIN(timestamp) AS first_seen,
+                MAX(timestamp) AS last_seen
+            FROM frames
+            WHERE app_name IS NOT NULL AND app_name != ''
+                AND (?1 IS NULL OR timestamp >= ?1)
+                AND (?2 IS NULL OR timestamp <= ?2)
+            GROUP BY app_name
+            ORDER BY frame_count DESC
+            LIMIT ?3
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Time each app was focused per bucket. Buckets start at multiples of
+    /// their length shifted by `utc_offset` seconds, so days start at local
+    /// midnight.
+    pub async fn get_app_usage_buckets(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        bucket: UsageBucket,
+        utc_offset: i32,
+    ) -> Result<Vec<AppUsageBucket>, sqlx::Error> {
+        let query = format!(
+            r#"
+            SELECT
+                {bucket_start} AS bucket_start,
+                app_name,
+                CAST(ROUND(SUM(MIN(
+                    COALESCE((julianday(next_timestamp) - julianday(timestamp)) * 86400, 0),
+                    ?4
+                ))) AS INTEGER) AS seconds
+            FROM (
+                SELECT
+                    timestamp,
+                    app_name,
+                    LEAD(timestamp) OVER (ORDER BY timestamp, id) AS next_timestamp
+                FROM frames
+                WHERE timestamp >= ?1 AND timestamp <= ?2
+                    AND focused = 1 AND app_name IS NOT NULL AND app_name != ''
+            )
+            GROUP BY bucket_start, app_name
+            ORDER BY bucket_start ASC, seconds DESC, app_name ASC
+            "#,
+            bucket_start = usage_bucket_start("timestamp", bucket, "?3"),
+        );
+        sqlx::query_as(&query)
+            .bind(start_time)
+            .bind(end_time)
+            .bind(utc_offset)
+            .bind(USAGE_MAX_FRAME_SECONDS)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Time each speaker talked per bucket, from the segments of their
+    /// transcripts, see [`Self::get_app_usage_buckets`] for the buckets.
+    pub async fn get_speaking_time_buckets(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        bucket: UsageBucket,
+        utc_offset: i32,
+    ) -> Result<Vec<SpeakingTimeBucket>, sqlx::Error> {
+        let query = format!(
+            r#"
+            SELECT
+                {bucket_start} AS bucket_start,
+                audio_transcriptions.speaker_id,
+                speakers.name AS speaker_name,
+                CAST(ROUND(SUM(MAX(
+                    COALESCE(audio_transcriptions.end_time - audio_transcriptions.start_time, 0),
+                    0
+                ))) AS INTEGER) AS seconds
+            FROM audio_transcriptions
+            LEFT JOIN speakers ON audio_transcriptions.speaker_id = speakers.id
+            WHERE audio_transcriptions.timestamp >= ?1 AND audio_transcriptions.timestamp <= ?2
+                AND (speakers.id IS NULL OR speakers.hallucination = 0)
+            GROUP BY bucket_start, audio_transcriptions.speaker_id
+            ORDER BY bucket_start ASC, seconds DESC, audio_transcriptions.speaker_id ASC
+            "#,
+            bucket_start = usage_bucket_start("audio_transcriptions.timestamp", bucket, "?3"),
+        );
+        sqlx::query_as(&query)
+            .bind(start_time)
+            .bind(end_time)
+            .bind(utc_offset)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Words of the screen text per app and bucket, see
+    /// [`Self::get_app_usage_buckets`] for the buckets. Text on screen for a
+    /// while is counted in every frame of it.
+    pub async fn get_word_count_buckets(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        bucket: UsageBucket,
+        utc_offset: i32,
+    ) -> Result<Vec<WordCountBucket>, sqlx::Error> {
+        // sqlite can't split words, the text is streamed and counted here
+        let query = format!(
+            r#"
+            SELECT
+                {bucket_start} AS bucket_start,
+                COALESCE(frames.app_name, '') AS app_name,
+                COALESCE(ocr_text.text, '') AS text
+            FROM ocr_text
+            JOIN frames ON ocr_text.frame_id = frames.id
+            WHERE frames.timestamp >= ?1 AND frames.timestamp <= ?2
+            "#,
+            bucket_start = usage_bucket_start("frames.timestamp", bucket, "?3"),
+        );
+        let mut rows = sqlx::query_as::<_, (DateTime<Utc>, String, String)>(&query)
+            .bind(start_time)
+            .bind(end_time)
+            .bind(utc_offset)
+            .fetch(&self.pool);
+        let mut words: BTreeMap<(DateTime<Utc>, String), i64> = BTreeMap::new();
+        while let Some((bucket_start, app_name, text)) = rows.try_next().await? {
+            *words.entry((bucket_start, app_name)).or_default() +=
+                text.split_whitespace().count() as i64;
+        }
+
+        let mut buckets: Vec<WordCountBucket> = words
+            .into_iter()
+            .map(|((bucket_start, app_name), words)| WordCountBucket {
+                bucket_start,
+                app_name,
+                words,
+            })
+            .collect();
+        buckets.sort_by(|a, b| {
+            a.bucket_start
+                .cmp(&b.bucket_start)
+                .then_with(|| b.words.cmp(&a.words))
+                .then_with(|| a.app_name.cmp(&b.app_name))
+        });
+        Ok(buckets)
+    }
+
+    /// Times the focus moved to another app per bucket, see
+    /// [`Self::get_app_usage_buckets`] for the buckets. Buckets without
+    /// switches are left out.
+    pub async fn get_context_switch_buckets(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        bucket: UsageBucket,
+        utc_offset: i32,
+    ) -> Result<Vec<ContextSwitchBucket>, sqlx::Error> {
+        let query = format!(
+            r#"
+            SELECT {bucket_start} AS bucket_start, COUNT(*) AS switches
+            FROM (
+                SELECT
+                    timestamp,
+                    app_name,
+                    LAG(app_name) OVER (ORDER BY timestamp, id) AS previous_app_name
+                FROM frames
+                WHERE timestamp >= ?1 AND timestamp <= ?2
+                    AND focused = 1 AND app_name IS NOT NULL AND app_name != ''
+            )
+            WHERE previous_app_name IS NOT NULL AND previous_app_name != app_name
+            GROUP BY bucket_start
+            ORDER BY bucket_start ASC
+            "#,
+            bucket_start = usage_bucket_start("timestamp", bucket, "?3"),
+        );
+        sqlx::query_as(&query)
+            .bind(start_time)
+            .bind(end_time)
+            .bind(utc_offset)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Records that the window had the focus at `timestamp`. Extends the
+    /// latest focus event when it's the same window, otherwise ends it there
+    /// and starts a new one. Nothing seen for longer than `max_gap` starts
+    /// afresh, leaving the latest event ending when it was last seen.
+    pub async fn record_focus(
+        &self,
+        app_name: &str,
+        window_name: &str,
+        timestamp: DateTime<Utc>,
+        max_gap: Duration,
+    ) -> Result<i64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let latest: Option<(i64, String, String, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT id, app_name, window_name, end_time FROM focus_events
+             ORDER BY end_time DESC, id DESC LIMIT 1",
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let latest = latest.filter(|(_, _, _, end_time)| {
+            timestamp >= *end_time
+                && (timestamp - *end_time)
+                    .to_std()
+                    .is_ok_and(|gap| gap <= max_gap)
+        });
+        if let Some((id, latest_app, latest_window, _)) = latest {
+            sqlx::query("UPDATE focus_events SET end_time = ?1 WHERE id = ?2")
+                .bind(timestamp)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            if latest_app == app_name && latest_window == window_name {
+                tx.commit().await?;
+                return Ok(id);
+            }
+        }
+
+        let id = sqlx::query(
+            "INSERT INTO focus_events (app_name, window_name, start_time, end_time)
+             VALUES (?1, ?2, ?3, ?3)",
+        )
+        .bind(app_name)
+        .bind(window_name)
+        .bind(timestamp)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    /// Focus events overlapping the time range, oldest first.
+    pub async fn list_focus_events(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        app_name: Option<&str>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<FocusEvent>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT
+                id,
+                app_name,
+                window_name,
+                start_time,
+                end_time,
+                CAST(ROUND((julianday(end_time) - julianday(start_time)) * 86400) AS INTEGER) AS seconds
+            FROM focus_events
+            WHERE end_time >= ?1 AND start_time <= ?2
+                AND (?3 IS NULL OR app_name = ?3)
+            ORDER BY start_time ASC, id ASC
+            LIMIT ?4 OFFSET ?5
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(app_name)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Longest times spent in one app without the focus leaving it, among the
+    /// focus events overlapping the time range.
+    pub async fn get_focus_streaks(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<FocusStreak>, sqlx::Error> {
+        let query = format!(
+            r#"
+            {FOCUS_STREAKS}
+            SELECT app_name, start_time, end_time, seconds, windows
+            FROM streaks
+            ORDER BY seconds DESC, start_time ASC
+            LIMIT ?3
+            "#
+        );
+        sqlx::query_as(&query)
+            .bind(start_time)
+            .bind(end_time)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Times the focus moved straight to another app per bucket, from the
+    /// focus events, see [`Self::get_app_usage_buckets`] for the buckets.
+    /// Moving to an app after the UI monitor went quiet isn't a switch.
+    pub async fn get_focus_switch_buckets(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        bucket: UsageBucket,
+        utc_offset: i32,
+    ) -> Result<Vec<ContextSwitchBucket>, sqlx::Error> {
+        let query = format!(
+            r#"
+            {FOCUS_STREAKS}
+            SELECT {bucket_start} AS bucket_start, COUNT(*) AS switches
+            FROM (
+                SELECT
+                    start_time,
+                    app_name,
+                    LAG(app_name) OVER (ORDER BY start_time, streak) AS previous_app_name,
+                    LAG(end_time) OVER (ORDER BY start_time, streak) AS previous_end_time
+                FROM streaks
+            )
+            WHERE previous_end_time = start_time AND previous_app_name != app_name
+                AND start_time >= ?1 AND start_time <= ?2
+            GROUP BY bucket_start
+            ORDER BY bucket_start ASC
+            "#,
+            bucket_start = usage_bucket_start("start_time", bucket, "?3"),
+        );
+        sqlx::query_as(&query)
+            .bind(start_time)
+            .bind(end_time)
+            .bind(utc_offset)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Apps the focus moved to for at most `max_seconds` before going straight
+    /// back to the app it came from, most interrupting first.
+    pub async fn get_focus_interruptions(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        max_seconds: i64,
+        limit: u32,
+    ) -> Result<Vec<FocusInterruption>, sqlx::Error> {
+        let query = format!(
+            r#"
+            {FOCUS_STREAKS}
+            SELECT app_name, COUNT(*) AS interruptions, SUM(seconds) AS seconds
+            FROM (
+                SELECT
+                    app_name,
+                    start_time,
+                    end_time,
+                    seconds,
+                    LAG(app_name) OVER w AS previous_app_name,
+                    LAG(end_time) OVER w AS previous_end_time,
+                    LEAD(app_name) OVER w AS next_app_name,
+                    LEAD(start_time) OVER w AS next_start_time
+                FROM streaks
+                WINDOW w AS (ORDER BY start_time, streak)
+            )
+            WHERE previous_app_name = next_app_name AND previous_app_name != app_name
+                AND previous_end_time = start_time AND next_start_time = end_time
+                AND seconds <= ?3
+            GROUP BY app_name
+            ORDER BY interruptions DESC, seconds DESC, app_name ASC
+            LIMIT ?4
+            "#
+        );
+        sqlx::query_as(&query)
+            .bind(start_time)
+            .bind(end_time)
+            .bind(max_seconds)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Frames, transcripts, app switches, idle gaps and media blocks in the
+    /// time range as a single stream ordered by time. Continues after `cursor`
+    /// when given, an idle gap is placed at its start.
+    pub async fn get_timeline(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        kinds: &[TimelineEventKind],
+        cursor: Option<TimelineCursor>,
+        limit: u32,
+    ) -> Result<TimelinePage, sqlx::Error> {
+        let cursor = cursor
+            .filter(|cursor| cursor.timestamp >= start_time)
+            .unwrap_or(TimelineCursor {
+                timestamp: start_time,
+                kind: TimelineEventKind::IdleGap,
+                id: i64::MIN,
+            });
+        // events at the cursor's time come after it if their kind sorts later
+        let after_cursor = |kind: TimelineEventKind, timestamp: &str, id: &str| {
+            let tie = match kind.cmp(&cursor.kind) {
+                std::cmp::Ordering::Less => "0".to_string(),
+                std::cmp::Ordering::Equal => format!("{} > ?3", id),
+                std::cmp::Ordering::Greater => "1".to_string(),
+            };
+            format!("({0} > ?2 OR ({0} = ?2 AND {1}))", timestamp, tie)
+        };
+
+        let mut events = Vec::new();
+        let mut has_more = false;
+
+        if kinds.contains(&TimelineEventKind::Frame) {
+            let query = format!(
+                r#"
+                SELECT
+                    f.id AS frame_id,
+                    f.timestamp,
+                    f.device_name,
+                    f.app_name,
+                    f.window_name,
+                    COALESCE(f.focused, 0) AS focused,
+                    (SELECT SUBSTR(ot.text, 1, {preview}) FROM ocr_text ot WHERE ot.frame_id = f.id LIMIT 1) AS text_preview,
+                    f.repeat_count,
+                    f.last_repeat_at
+                FROM frames f
+                WHERE f.timestamp <= ?1 AND {after}
+                ORDER BY f.timestamp ASC, f.id ASC
+                LIMIT ?4
+                "#,
+                preview = TIMELINE_TEXT_PREVIEW_CHARS,
+                after = after_cursor(TimelineEventKind::Frame, "f.timestamp", "f.id"),
+            );
+            let rows: Vec<TimelineFrame> = sqlx::query_as(&query)
+                .bind(end_time)
+                .bind(cursor.timestamp)
+                .bind(cursor.id)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?;
+            has_more |= rows.len() >= limit as usize;
+            events.extend(rows.into_iter().map(TimelineEvent::Frame));
+        }
+
+        if kinds.contains(&TimelineEventKind::Transcript) {
+            let query = format!(
+                r#"
+                SELECT
+                    at.id,
+                    at.timestamp,
+                    at.device AS device_name,
+                    at.is_input_device AS is_input,
+                    at.speaker_id,
+                    at.transcription,
+                    at.start_time,
+                    at.end_time
+                FROM audio_transcriptions at
+                WHERE at.timestamp <= ?1 AND {after}
+                ORDER BY at.timestamp ASC, at.id ASC
+                LIMIT ?4
+                "#,
+                after = after_cursor(TimelineEventKind::Transcript, "at.timestamp", "at.id"),
+            );
+            let rows: Vec<TimelineTranscript> = sqlx::query_as(&query)
+                .bind(end_time)
+                .bind(cursor.timestamp)
+                .bind(cursor.id)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?;
+            has_more |= rows.len() >= limit as usize;
+            events.extend(rows.into_iter().map(TimelineEvent::Transcript));
+        }
+
+        if kinds.contains(&TimelineEventKind::AppSwitch) {
+            // the app of each focused frame is compared with the previous one of
+            // the same monitor, looking back a little for the first frames
+            let query = format!(
+                r#"
+                SELECT frame_id, timestamp, device_name, app_name, window_name, previous_app_name
+                FROM (
+                    SELECT
+                        f.id AS frame_id,
+                        f.timestamp,
+                        f.device_name,
+                        f.app_name,
+                        f.window_name,
+                        LAG(f.app_name) OVER (
+                            PARTITION BY f.device_name ORDER BY f.timestamp, f.id
+                        ) AS previous_app_name
+                    FROM frames f
+                    WHERE f.timestamp >= ?5 AND f.timestamp <= ?1
+                        AND f.focused = 1 AND f.app_name IS NOT NULL AND f.app_name != ''
+                )
+                WHERE previous_app_name IS NOT NULL AND previous_app_name != app_name
+                    AND {after}
+                ORDER BY timestamp ASC, frame_id ASC
+                LIMIT ?4
+                "#,
+                after = after_cursor(TimelineEventKind::AppSwitch, "timestamp", "frame_id"),
+            );
+            let rows: Vec<TimelineAppSwitch> = sqlx::query_as(&query)
+                .bind(end_time)
+                .bind(cursor.timestamp)
+                .bind(cursor.id)
+                .bind(limit)
+                .bind(cursor.timestamp - TIMELINE_APP_SWITCH_LOOKBACK)
+                .fetch_all(&self.pool)
+                .await?;
+            has_more |= rows.len() >= limit as usize;
+            events.extend(rows.into_iter().map(TimelineEvent::AppSwitch));
+        }
+
+        if kinds.contains(&TimelineEventKind::IdleGap) {
+            let query = format!(
+                r#"
+                SELECT id, start_time, end_time, reason
+                FROM idle_gaps
+                WHERE start_time <= ?1 AND {after}
+                ORDER BY start_time ASC, id ASC
+                LIMIT ?4
+                "#,
+                after = after_cursor(TimelineEventKind::IdleGap, "start_time", "id"),
+            );
+            let rows: Vec<IdleGap> = sqlx::query_as(&query)
+                .bind(end_time)
+                .bind(cursor.timestamp)
+                .bind(cursor.id)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?;
+            has_more |= rows.len() >= limit as usize;
+            events.extend(rows.into_iter().map(TimelineEvent::IdleGap));
+        }
+
+        if kinds.contains(&TimelineEventKind::MediaBlock) {
+            let query = format!(
+                r#"
+                SELECT
+                    id,
+                    start_time,
+                    end_time,
+                    device AS device_name,
+                    is_input_device AS is_input,
+                    classification,
+                    confidence
+                FROM media_blocks
+                WHERE start_time <= ?1 AND {after}
+                ORDER BY start_time ASC, id ASC
+                LIMIT ?4
+                "#,
+                after = after_cursor(TimelineEventKind::MediaBlock, "start_time", "id"),
+            );
+            let rows: Vec<MediaBlock> = sqlx::query_as(&query)
+                .bind(end_time)
+                .bind(cursor.timestamp)
+                .bind(cursor.id)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?;
+            has_more |= rows.len() >= limit as usize;
+            events.extend(rows.into_iter().map(TimelineEvent::MediaBlock));
+        }
+
+        events.sort_by_key(|event| event.cursor());
+        has_more |= events.len() > limit as usize;
+        events.truncate(limit as usize);
+
+        let next_cursor = if has_more {
+            events.last().map(|event| event.cursor().to_string())
+        } else {
+            None
+        };
+        Ok(TimelinePage {
+            events,
+            next_cursor,
+        })
+    }
+
+    /// Copies the database to `dest` with SQLite's online backup API while it
+    /// keeps being written to. An encrypted database is copied encrypted with
+    /// the same hex encoded `key`.
+    pub async fn backup_to(&self, dest: &Path, key: Option<&str>) -> Result<(), anyhow::Error> {
+        match std::fs::remove_file(dest) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        let mut conn = self.pool.acquire().await?;
+        let mut handle = conn.lock_handle().await?;
+        let source = handle.as_raw_handle().as_ptr();
+        // SAFETY: the source connection is locked for the whole copy and the
+        // destination is opened and closed by backup_database
+        unsafe { backup_database(source, dest, key) }
+    }
+
+    pub async fn repair_database(&self) -> Result<(), anyhow::Error> {
+        debug!("starting aggressive database repair process");
+
+        // Force close any pending transactions
+        let emergency_steps = [
+            "PRAGMA locking_mode = EXCLUSIVE;",
+            "ROLLBACK;",                    // Force rollback any stuck transactions
+            "PRAGMA busy_timeout = 60000;", // Increase timeout to 60s
+        ];
+
+        for step in emergency_steps {
+            if let Err(e) = sqlx::query(step).execute(&self.pool).await {
+                warn!("emergency step failed (continuing anyway): {}", e);
+            }
+        }
+
+        // Force checkpoint and cleanup WAL files
+        let wal_cleanup = [
+            "PRAGMA wal_checkpoint(TRUNCATE);",
+            "PRAGMA journal_mode = DELETE;", // Temporarily disable WAL
+            "PRAGMA journal_size_limit = 0;", // Clear journal
+        ];
+
+        for step in wal_cleanup {
+            if let Err(e) = sqlx::query(step).execute(&self.pool).await {
+                warn!("wal cleanup failed (continuing anyway): {}", e);
+            }
+        }
+
+        // Aggressive recovery steps
+        let recovery_steps = [
+            ("PRAGMA synchronous = OFF;", "disable synchronous"),
+            ("PRAGMA cache_size = -2000000;", "increase cache"), // 2GB cache
+            ("VACUUM;", "vacuum database"),
+            ("PRAGMA integrity_check;", "check integrity"),
+            ("PRAGMA foreign_key_check;", "check foreign keys"),
+            ("REINDEX;", "rebuild indexes"),
+            ("ANALYZE;", "update statistics"),
+            ("VACUUM;", "final vacuum"), // Second vacuum after reindex
+        ];
+
+        for (query, step) in recovery_steps {
+            debug!("running aggressive recovery step: {}", step);
+            match sqlx::query(query).execute(&self.pool).await {
+                Ok(_) => debug!("recovery step '{}' succeeded", step),
+                Err(e) => warn!("recovery step '{}' failed: {}", step, e),
+            }
+        }
+
+        // Restore safe settings
+        let restore_steps = [
+            "PRAGMA synchronous = NORMAL;",
+            "PRAGMA journal_mode = WAL;",
+            "PRAGMA wal_autocheckpoint = 1000;",
+            "PRAGMA cache_size = -2000;", // Back to 2MB cache
+            "PRAGMA locking_mode = NORMAL;",
+            "PRAGMA busy_timeout = 5000;", // Back to 5s timeout
+        ];
+
+        for step in restore_steps {
             if let Err(e) = sqlx::query(step).execute(&self.pool).await {
                 warn!("restore step failed: {}", e);
             }
@@ -2350,6 +6820,840 @@ LIMIT ? OFFSET ?
     }
 }
 
+async fn sync_imported(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    device_id: &str,
+    kind: &str,
+    remote_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let found: Option<i64> = sqlx::query_scalar(
+        "SELECT local_id FROM sync_imports WHERE device_id = ?1 AND kind = ?2 AND remote_id = ?3",
+    )
+    .bind(device_id)
+    .bind(kind)
+    .bind(remote_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+    Ok(found.is_some())
+}
+
+async fn record_sync_import(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    device_id: &str,
+    kind: &str,
+    remote_id: i64,
+    local_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO sync_imports (device_id, kind, remote_id, local_id) VALUES (?1, ?2, ?3, ?4)",
+    )
+    .bind(device_id)
+    .bind(kind)
+    .bind(remote_id)
+    .bind(local_id)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn insert_ocr_block_row(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    frame_id: i64,
+    level: &str,
+    (block_index, paragraph_index, line_index): (i64, Option<i64>, Option<i64>),
+    text: &str,
+    confidence: Option<f32>,
+    bounds: &TextBounds,
+    source: Option<TextSource>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO ocr_blocks (frame_id, level, block_index, paragraph_index, line_index, text, confidence, left, top, width, height, source) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+    )
+    .bind(frame_id)
+    .bind(level)
+    .bind(block_index)
+    .bind(paragraph_index)
+    .bind(line_index)
+    .bind(text)
+    .bind(confidence)
+    .bind(bounds.left)
+    .bind(bounds.top)
+    .bind(bounds.width)
+    .bind(bounds.height)
+    .bind(source.map(|source| source.to_string()))
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Query behind `get_media_chunks_before`, binds the cutoff and a limit.
+fn media_chunks_before_sql(media_type: MediaType) -> &'static str {
+    match media_type {
+        MediaType::Video => {
+            r#"
+            SELECT
+                video_chunks.id,
+                video_chunks.file_path,
+                MAX(frames.timestamp) as timestamp
+            FROM video_chunks
+            JOIN frames ON frames.video_chunk_id = video_chunks.id
+            WHERE video_chunks.media_deleted_at IS NULL
+                AND (?3 = 0 OR video_chunks.encrypted_at IS NULL)
+            GROUP BY video_chunks.id
+            HAVING timestamp < ?1
+            ORDER BY timestamp
+            LIMIT ?2
+            "#
+        }
+        MediaType::Audio => {
+            r#"
+            SELECT
+                audio_chunks.id,
+                audio_chunks.file_path,
+                COALESCE(MAX(audio_transcriptions.timestamp), audio_chunks.timestamp) as timestamp
+            FROM audio_chunks
+            LEFT JOIN audio_transcriptions ON audio_transcriptions.audio_chunk_id = audio_chunks.id
+            WHERE audio_chunks.media_deleted_at IS NULL
+                AND (?3 = 0 OR audio_chunks.encrypted_at IS NULL)
+            GROUP BY audio_chunks.id
+            HAVING COALESCE(MAX(audio_transcriptions.timestamp), audio_chunks.timestamp) < ?1
+            ORDER BY timestamp
+            LIMIT ?2
+            "#
+        }
+    }
+}
+
+/// Query behind `get_local_media_chunks_before`, binds the cutoff and a limit.
+fn local_media_chunks_sql(media_type: MediaType) -> &'static str {
+    match media_type {
+        MediaType::Video => {
+            r#"
+            SELECT
+                video_chunks.id,
+                video_chunks.file_path,
+                MAX(frames.timestamp) as timestamp
+            FROM video_chunks
+            JOIN frames ON frames.video_chunk_id = video_chunks.id
+            WHERE video_chunks.media_deleted_at IS NULL
+                AND video_chunks.offloaded_at IS NULL
+            GROUP BY video_chunks.id
+            HAVING timestamp < ?1
+            ORDER BY timestamp
+            LIMIT ?2
+            "#
+        }
+        MediaType::Audio => {
+            r#"
+            SELECT
+                audio_chunks.id,
+                audio_chunks.file_path,
+                COALESCE(MAX(audio_transcriptions.timestamp), audio_chunks.timestamp) as timestamp
+            FROM audio_chunks
+            LEFT JOIN audio_transcriptions ON audio_transcriptions.audio_chunk_id = audio_chunks.id
+            WHERE audio_chunks.media_deleted_at IS NULL
+                AND audio_chunks.offloaded_at IS NULL
+            GROUP BY audio_chunks.id
+            HAVING COALESCE(MAX(audio_transcriptions.timestamp), audio_chunks.timestamp) < ?1
+            ORDER BY timestamp
+            LIMIT ?2
+            "#
+        }
+    }
+}
+
+/// Smoothing constant of reciprocal rank fusion, 60 is the usual choice.
+const RRF_K: f32 = 60.0;
+/// Candidates taken from each ranking per requested semantic search result.
+const SEMANTIC_CANDIDATES_PER_RESULT: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SemanticHit {
+    Ocr(i64),
+    Audio(i64),
+}
+
+/// Characters of OCR text returned with each frame of the timeline.
+const TIMELINE_TEXT_PREVIEW_CHARS: usize = 200;
+/// How far before the range frames are read to detect the first app switch.
+const TIMELINE_APP_SWITCH_LOOKBACK: chrono::Duration = chrono::Duration::minutes(10);
+/// Chunks of music less than this apart belong to the same media block,
+/// chunks overlap but are stored a while after each other.
+const MEDIA_BLOCK_MAX_GAP: chrono::Duration = chrono::Duration::seconds(30);
+/// Longest time a focused frame counts towards app usage, longer gaps to the
+/// next frame are time away or not recording.
+const USAGE_MAX_FRAME_SECONDS: i64 = 60;
+
+/// Focus events overlapping ?1..?2 merged into `streaks`, the time spent in
+/// an app until the focus left it or the UI monitor went quiet.
+const FOCUS_STREAKS: &str = r#"
+WITH marked AS (
+    SELECT
+        id,
+        app_name,
+        window_name,
+        start_time,
+        end_time,
+        CASE
+            WHEN LAG(app_name) OVER w = app_name AND LAG(end_time) OVER w = start_time THEN 0
+            ELSE 1
+        END AS starts_streak
+    FROM focus_events
+    WHERE end_time >= ?1 AND start_time <= ?2
+    WINDOW w AS (ORDER BY start_time, id)
+),
+numbered AS (
+    SELECT *, SUM(starts_streak) OVER (ORDER BY start_time, id) AS streak
+    FROM marked
+),
+streaks AS (
+    SELECT
+        streak,
+        app_name,
+        MIN(start_time) AS start_time,
+        MAX(end_time) AS end_time,
+        CAST(ROUND((julianday(MAX(end_time)) - julianday(MIN(start_time))) * 86400) AS INTEGER) AS seconds,
+        COUNT(DISTINCT window_name) AS windows
+    FROM numbered
+    GROUP BY streak, app_name
+)
+"#;
+
+/// Header every unencrypted SQLite database file starts with.
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Value of the SQLCipher key pragma for a hex encoded raw key.
+pub(crate) fn sqlcipher_key(key: &str) -> String {
+    format!("\"x'{}'\"", key)
+}
+
+fn is_plaintext_database(database_path: &str) -> bool {
+    use std::io::Read;
+
+    let mut header = [0u8; SQLITE_HEADER.len()];
+    std::fs::File::open(database_path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| &header == SQLITE_HEADER)
+}
+
+/// Copies the database open on `source` into a new database at `dest`.
+pub(crate) unsafe fn backup_database(
+    source: *mut ffi::sqlite3,
+    dest: &Path,
+    key: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    let path = CString::new(dest.to_string_lossy().as_bytes())?;
+    let mut dest_db = ptr::null_mut();
+    let rc = ffi::sqlite3_open_v2(
+        path.as_ptr(),
+        &mut dest_db,
+        ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE,
+        ptr::null(),
+    );
+    let result = if rc == ffi::SQLITE_OK {
+        copy_database(source, dest_db, key)
+    } else {
+        Err(anyhow::anyhow!(
+            "failed to create {}: {}",
+            dest.display(),
+            sqlite_error(dest_db)
+        ))
+    };
+    ffi::sqlite3_close(dest_db);
+    result
+}
+
+unsafe fn copy_database(
+    source: *mut ffi::sqlite3,
+    dest_db: *mut ffi::sqlite3,
+    key: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    if let Some(key) = key {
+        sqlite_exec(dest_db, &format!("PRAGMA key = {};", sqlcipher_key(key)))?;
+    }
+    let main = c"main".as_ptr();
+    let backup = ffi::sqlite3_backup_init(dest_db, main, source, main);
+    if backup.is_null() {
+        anyhow::bail!("failed to start the backup: {}", sqlite_error(dest_db));
+    }
+    // copied in a single step: the snapshot stays consistent and, the
+    // database being in wal mode, doesn't block writers. a backup made in
+    // several steps restarts whenever another connection writes
+    while let ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED = ffi::sqlite3_backup_step(backup, -1) {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    if ffi::sqlite3_backup_finish(backup) != ffi::SQLITE_OK {
+        anyhow::bail!("backup failed: {}", sqlite_error(dest_db));
+    }
+    // a single file, readable without the wal and shm files
+    sqlite_exec(dest_db, "PRAGMA journal_mode = DELETE;")
+}
+
+unsafe fn sqlite_exec(db: *mut ffi::sqlite3, sql: &str) -> Result<(), anyhow::Error> {
+    let sql = CString::new(sql)?;
+    let rc = ffi::sqlite3_exec(db, sql.as_ptr(), None, ptr::null_mut(), ptr::null_mut());
+    if rc != ffi::SQLITE_OK {
+        anyhow::bail!("{}", sqlite_error(db));
+    }
+    Ok(())
+}
+
+unsafe fn sqlite_error(db: *mut ffi::sqlite3) -> String {
+    if db.is_null() {
+        return "out of memory".to_string();
+    }
+    CStr::from_ptr(ffi::sqlite3_errmsg(db))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Runs SQLite's integrity check on the database at `database_path`, e.g. a
+/// backup, without migrating it.
+pub async fn check_database_integrity(
+    database_path: &Path,
+    key: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    let mut options = SqliteConnectOptions::new()
+        .filename(database_path)
+        .read_only(true);
+    if let Some(key) = key {
+        options = options.pragma("key", sqlcipher_key(key));
+    }
+    let mut conn = options.connect().await?;
+    let problems: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check;")
+        .fetch_all(&mut conn)
+        .await?;
+    conn.close().await?;
+    if problems != ["ok"] {
+        anyhow::bail!("database is corrupted: {}", problems.join("; "));
+    }
+    Ok(())
+}
+
+/// Replaces the unencrypted database at `database_path` with an encrypted
+/// copy made by SQLCipher.
+async fn encrypt_plaintext_database(database_path: &str, key: &str) -> Result<(), sqlx::Error> {
+    warn!("encrypting existing database {}", database_path);
+    let encrypted_path = format!("{}.encrypted", database_path);
+    let _ = std::fs::remove_file(&encrypted_path);
+
+    let mut conn = SqliteConnectOptions::from_str(&format!("sqlite:{}", database_path))?
+        .connect()
+        .await?;
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE);")
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("ATTACH DATABASE ?1 AS encrypted KEY ?2;")
+        .bind(&encrypted_path)
+        .bind(format!("x'{}'", key))
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("SELECT sqlcipher_export('encrypted');")
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("DETACH DATABASE encrypted;")
+        .execute(&mut conn)
+        .await?;
+    conn.close().await?;
+
+    std::fs::rename(&encrypted_path, database_path)?;
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", database_path, suffix));
+    }
+    Ok(())
+}
+
+/// Merges rankings with reciprocal rank fusion: an item scores
+/// `1 / (k + rank)` in every ranking it appears in, ranks starting at 1.
+/// Returns the items by descending score, equal scores keep the order the
+/// items were first seen in.
+pub fn reciprocal_rank_fusion<T: Eq + Hash + Clone>(rankings: &[Vec<T>], k: f32) -> Vec<(T, f32)> {
+    let mut fused: Vec<(T, f32)> = Vec::new();
+    let mut positions: HashMap<T, usize> = HashMap::new();
+
+    for ranking in rankings {
+        // an item listed twice in a ranking only counts at its best rank
+        let mut seen = HashSet::new();
+        for (rank, item) in ranking.iter().enumerate() {
+            if !seen.insert(item) {
+                continue;
+            }
+            let score = 1.0 / (k + rank as f32 + 1.0);
+            match positions.get(item) {
+                Some(&position) => fused[position].1 += score,
+                None => {
+                    positions.insert(item.clone(), fused.len());
+                    fused.push((item.clone(), score));
+                }
+            }
+        }
+    }
+
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1));
+    fused
+}
+
+/// Weights of the `text`, `app_name` and `window_name` columns of
+/// `ocr_text_fts` in BM25, a word of the window title says more about what a
+/// frame is about than one anywhere on screen.
+const OCR_FTS_WEIGHTS: &str = "1.0, 2.0, 4.0";
+/// Weights of the `text_output`, `app` and `window` columns of
+/// `ui_monitoring_fts`.
+const UI_FTS_WEIGHTS: &str = "1.0, 2.0, 4.0";
+/// Weights of the `transcription`, `translated_text` and `device` columns of
+/// `audio_transcriptions_fts`.
+const AUDIO_FTS_WEIGHTS: &str = "1.0, 1.0, 0.2";
+/// Words of context around the matches in search snippets.
+const SNIPPET_TOKENS: u32 = 16;
+/// Typos a fuzzy search tolerates at most, beyond two most short words
+/// match each other.
+const MAX_EDIT_DISTANCE: u32 = 2;
+/// Indexed words a word of a fuzzy search matches at most, besides itself.
+const MAX_FUZZY_VARIANTS: usize = 10;
+
+/// `rank` and `snippet` columns of a query matching `table`: its BM25 rank,
+/// lower is better, and the best matching part of it with the matches
+/// between `<mark>` and `</mark>`.
+fn fts_rank_columns(table: &str, weights: &str) -> String {
+    format!(
+        "bm25({table}, {weights}) as rank, \
+         snippet({table}, -1, '<mark>', '</mark>', '…', {tokens}) as snippet",
+        table = table,
+        weights = weights,
+        tokens = SNIPPET_TOKENS
+    )
+}
+
+enum FtsToken {
+    Term {
+        text: String,
+        quoted: bool,
+        prefix: bool,
+    },
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+/// Splits a search box query into terms and operators. Parentheses that
+/// don't close an open group are dropped.
+fn fts_tokens(query: &str) -> Vec<FtsToken> {
+    let mut tokens = Vec::new();
+    let mut depth = 0;
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if chars.next_if_eq(&'(').is_some() {
+            depth += 1;
+            tokens.push(FtsToken::Open);
+            continue;
+        }
+        if chars.next_if_eq(&')').is_some() {
+            if depth > 0 {
+                depth -= 1;
+                tokens.push(FtsToken::Close);
+            }
+            continue;
+        }
+        let negated = chars.next_if_eq(&'-').is_some();
+        if negated && chars.peek() == Some(&'(') {
+            tokens.push(FtsToken::Not);
+            continue;
+        }
+        let (text, quoted) = if chars.next_if_eq(&'"').is_some() {
+            let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            (phrase, true)
+        } else {
+            let mut word = String::new();
+            while let Some(c) =
+                chars.next_if(|&c| !c.is_whitespace() && !matches!(c, '(' | ')' | '"'))
+            {
+                word.push(c);
+            }
+            (word, false)
+        };
+        let prefix = chars.next_if_eq(&'*').is_some() || (!quoted && text.ends_with('*'));
+        let text = if quoted {
+            text.trim().to_string()
+        } else {
+            text.trim_end_matches('*').to_string()
+        };
+        if text.is_empty() {
+            continue;
+        }
+
+        if negated {
+            tokens.push(FtsToken::Not);
+        } else if !quoted {
+            match text.as_str() {
+                "AND" => {
+                    tokens.push(FtsToken::And);
+                    continue;
+                }
+                "OR" => {
+                    tokens.push(FtsToken::Or);
+                    continue;
+                }
+                "NOT" => {
+                    tokens.push(FtsToken::Not);
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        tokens.push(FtsToken::Term {
+            text,
+            quoted,
+            prefix,
+        });
+    }
+    tokens
+}
+
+/// Builds the FTS5 query of `tokens`. Operators without a term on both sides
+/// and empty groups are dropped, and excluded terms are taken out of the
+/// whole query rather than the term before.
+fn fts_expression(tokens: Vec<FtsToken>) -> Option<String> {
+    struct Group {
+        // where the group and the operator joining it start
+        start: usize,
+        // where its terms start
+        inner: usize,
+        negated: bool,
+    }
+
+    fn close(parts: &mut Vec<String>, excluded: &mut Vec<String>, group: Group) {
+        let inner = parts.split_off(group.inner);
+        if inner.is_empty() {
+            parts.truncate(group.start);
+            return;
+        }
+        let expression = if inner.len() == 1 {
+            inner.join(" ")
+        } else {
+            format!("({})", inner.join(" "))
+        };
+        if group.negated {
+            parts.truncate(group.start);
+            excluded.push(expression);
+        } else {
+            parts.truncate(group.inner - 1);
+            parts.push(expression);
+        }
+    }
+
+    let mut parts: Vec<String> = Vec::new();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut excluded = Vec::new();
+    let mut operator = None;
+    let mut negate = false;
+    for token in tokens {
+        let has_terms = parts.len() > groups.last().map_or(0, |group| group.inner);
+        match token {
+            FtsToken::And => operator = Some("AND"),
+            FtsToken::Or => operator = Some("OR"),
+            FtsToken::Not => negate = true,
+            FtsToken::Open => {
+                let start = parts.len();
+                if has_terms && !negate {
+                    parts.push(operator.unwrap_or("AND").to_string());
+                }
+                parts.push("(".to_string());
+                groups.push(Group {
+                    start,
+                    inner: parts.len(),
+                    negated: negate,
+                });
+                operator = None;
+                negate = false;
+            }
+            FtsToken::Close => {
+                if let Some(group) = groups.pop() {
+                    close(&mut parts, &mut excluded, group);
+                }
+                operator = None;
+            }
+            FtsToken::Term { text, prefix, .. } => {
+                let term = format!("\"{}\"{}", text, if prefix { "*" } else { "" });
+                if negate {
+                    excluded.push(term);
+                    negate = false;
+                    continue;
+                }
+                if has_terms {
+                    parts.push(operator.unwrap_or("AND").to_string());
+                }
+                parts.push(term);
+                operator = None;
+            }
+        }
+    }
+    while let Some(group) = groups.pop() {
+        close(&mut parts, &mut excluded, group);
+    }
+
+    if parts.is_empty() {
+        return None;
+    }
+    let expression = parts.join(" ");
+    if excluded.is_empty() {
+        Some(expression)
+    } else if parts.len() == 1 {
+        Some(format!("{} NOT {}", expression, excluded.join(" NOT ")))
+    } else {
+        Some(format!("({}) NOT {}", expression, excluded.join(" NOT ")))
+    }
+}
+
+/// Turns a search box query into an FTS5 query. Words and `"quoted phrases"`
+/// must all match unless separated by `OR`, `-word` or `NOT word` leaves out
+/// what matches the word, `word*` matches words starting with it and
+/// parentheses group terms. Every word is quoted so punctuation in user
+/// input can't break the query syntax. `None` if nothing is left to match.
+pub fn fts_query(query: &str) -> Option<String> {
+    fts_expression(fts_tokens(query))
+}
+
+/// FTS5 query matching any word of `query`, each word quoted so user input
+/// can't break the query syntax. `None` if there are no words.
+fn fts_any_word_query(query: &str) -> Option<String> {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .map(|word| word.replace('"', ""))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"", word))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" OR "))
+}
+
+/// FTS5 phrase of `text`, so that urls and other punctuated input match as a
+/// sequence of words instead of being parsed as query syntax.
+fn fts_phrase(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// Annotations with their tags, to be completed with a `WHERE` and a
+/// `GROUP BY annotations.id`.
+const ANNOTATION_SELECT: &str = r#"
+    SELECT
+        annotations.id,
+        annotations.frame_id,
+        annotations.audio_transcription_id,
+        annotations.start_time,
+        annotations.end_time,
+        annotations.note,
+        GROUP_CONCAT(tags.name, ',') as tags,
+        annotations.created_at,
+        annotations.updated_at
+    FROM annotations
+    LEFT JOIN annotation_tags ON annotations.id = annotation_tags.annotation_id
+    LEFT JOIN tags ON annotation_tags.tag_id = tags.id"#;
+
+fn annotation_from_raw(raw: AnnotationRaw) -> Annotation {
+    Annotation {
+        id: raw.id,
+        frame_id: raw.frame_id,
+        audio_transcription_id: raw.audio_transcription_id,
+        start_time: raw.start_time,
+        end_time: raw.end_time,
+        note: raw.note,
+        tags: raw
+            .tags
+            .map(|t| t.split(',').map(String::from).collect())
+            .unwrap_or_default(),
+        created_at: raw.created_at,
+        updated_at: raw.updated_at,
+    }
+}
+
+fn summary_from_raw(raw: SummaryRaw) -> Summary {
+    let from_json = |items: &str| serde_json::from_str(items).unwrap_or_default();
+    Summary {
+        id: raw.id,
+        period: raw.period,
+        start_time: raw.start_time,
+        end_time: raw.end_time,
+        summary: raw.summary,
+        topics: from_json(&raw.topics),
+        action_items: from_json(&raw.action_items),
+        people: from_json(&raw.people),
+        model: raw.model,
+        created_at: raw.created_at,
+    }
+}
+
+fn extraction_rule_from_raw(raw: ExtractionRuleRaw) -> ExtractionRule {
+    ExtractionRule {
+        id: raw.id,
+        name: raw.name,
+        app_name: raw.app_name,
+        window_name: raw.window_name,
+        instructions: raw.instructions,
+        schema: serde_json::from_str(&raw.schema).unwrap_or_default(),
+        created_at: raw.created_at,
+        processed_until: raw.processed_until,
+    }
+}
+
+fn calendar_event_from_raw(raw: CalendarEventRaw) -> CalendarEvent {
+    CalendarEvent {
+        id: raw.id,
+        source: raw.source,
+        uid: raw.uid,
+        title: raw.title,
+        attendees: serde_json::from_str(&raw.attendees).unwrap_or_default(),
+        location: raw.location,
+        start_time: raw.start_time,
+        end_time: raw.end_time,
+        updated_at: raw.updated_at,
+    }
+}
+
+fn companion_upload_from_raw(raw: CompanionUploadRaw) -> CompanionUpload {
+    CompanionUpload {
+        id: raw.id,
+        device_id: raw.device_id,
+        kind: raw.kind,
+        content_type: raw.content_type,
+        size: raw.size,
+        sha256: raw.sha256,
+        timestamp: raw.timestamp,
+        metadata: raw
+            .metadata
+            .and_then(|metadata| serde_json::from_str(&metadata).ok()),
+        ingest_id: raw.ingest_id,
+        created_at: raw.created_at,
+        completed_at: raw.completed_at,
+    }
+}
+
+fn extraction_from_raw(raw: ExtractionRaw) -> Extraction {
+    Extraction {
+        id: raw.id,
+        rule_id: raw.rule_id,
+        start_time: raw.start_time,
+        end_time: raw.end_time,
+        data: serde_json::from_str(&raw.data).unwrap_or_default(),
+        model: raw.model,
+        created_at: raw.created_at,
+    }
+}
+
+/// Search results a tag filter applies to.
+#[derive(Clone, Copy)]
+enum TaggedContent {
+    Frame,
+    Transcription,
+    Ui,
+}
+
+/// SQL condition keeping the rows tagged with the tag bound to `param`,
+/// through `/tags` or an annotation attached to them or to a time range
+/// they fall in. UI text can only be tagged through time ranges.
+fn tag_condition(content: TaggedContent, param: &str) -> String {
+    let (timestamp, attached, tagged) = match content {
+        TaggedContent::Frame => (
+            "frames.timestamp",
+            "annotation.frame_id = frames.id OR ",
+            format!(
+                "frames.id IN (
+                    SELECT vision_tags.vision_id FROM vision_tags
+                    JOIN tags AS legacy_tag ON vision_tags.tag_id = legacy_tag.id
+                    WHERE legacy_tag.name = {param}) OR "
+            ),
+        ),
+        TaggedContent::Transcription => (
+            "audio_transcriptions.timestamp",
+            "annotation.audio_transcription_id = audio_transcriptions.id OR ",
+            format!(
+                "audio_transcriptions.audio_chunk_id IN (
+                    SELECT audio_tags.audio_chunk_id FROM audio_tags
+                    JOIN tags AS legacy_tag ON audio_tags.tag_id = legacy_tag.id
+                    WHERE legacy_tag.name = {param}) OR "
+            ),
+        ),
+        TaggedContent::Ui => ("ui_monitoring.timestamp", "", String::new()),
+    };
+    format!(
+        "({tagged}EXISTS (
+            SELECT 1 FROM annotations AS annotation
+            JOIN annotation_tags ON annotation_tags.annotation_id = annotation.id
+            JOIN tags AS annotation_tag ON annotation_tags.tag_id = annotation_tag.id
+            WHERE annotation_tag.name = {param}
+                AND ({attached}(annotation.frame_id IS NULL
+                    AND annotation.audio_transcription_id IS NULL
+                    AND {timestamp} BETWEEN annotation.start_time AND annotation.end_time))))"
+    )
+}
+
+/// SQL condition keeping the rows captured during a calendar event whose
+/// title contains the text bound to `param`, `timestamp` being their time.
+fn meeting_condition(timestamp: &str, param: &str) -> String {
+    format!(
+        "EXISTS (
+            SELECT 1 FROM calendar_events AS meeting
+            WHERE meeting.title LIKE '%' || {param} || '%'
+                AND {timestamp} BETWEEN meeting.start_time AND meeting.end_time)"
+    )
+}
+
+/// SQL start of the usage bucket of `timestamp`, with buckets shifted by the
+/// UTC offset in seconds bound to `utc_offset_param`.
+fn usage_bucket_start(timestamp: &str, bucket: UsageBucket, utc_offset_param: &str) -> String {
+    let size = bucket.seconds();
+    format!(
+        "datetime((CAST(strftime('%s', {timestamp}) AS INTEGER) + {utc_offset_param}) / {size} * {size} - {utc_offset_param}, 'unixepoch')"
+    )
+}
+
+/// SQL condition keeping the rows after the search cursor whose time and id
+/// bound are bound to `timestamp_param` and `id_param`, see
+/// [`SearchCursor::id_bound`]. Keeps every row when they're NULL.
+pub(crate) fn after_search_cursor(
+    timestamp: &str,
+    id: &str,
+    timestamp_param: &str,
+    id_param: &str,
+) -> String {
+    format!(
+        "({timestamp_param} IS NULL OR {timestamp} < {timestamp_param}
+            OR ({timestamp} = {timestamp_param} AND {id} < {id_param}))"
+    )
+}
+
+pub(crate) fn ocr_result_from_raw(raw: OCRResultRaw) -> OCRResult {
+    OCRResult {
+        frame_id: raw.frame_id,
+        ocr_text: raw.ocr_text,
+        text_json: raw.text_json,
+        timestamp: raw.timestamp,
+        frame_name: raw.frame_name,
+        file_path: raw.file_path,
+        offset_index: raw.offset_index,
+        app_name: raw.app_name,
+        ocr_engine: raw.ocr_engine,
+        window_name: raw.window_name,
+        device_name: raw.device_name,
+        tags: raw
+            .tags
+            .map(|t| t.split(',').map(String::from).collect())
+            .unwrap_or_default(),
+        browser_url: raw.browser_url,
+        browser_context: raw
+            .browser_context
+            .and_then(|c| serde_json::from_str(&c).ok()),
+        focused: raw.focused,
+        rank: raw.rank,
+        snippet: raw.snippet,
+    }
+}
+
 pub fn find_matching_positions(blocks: &[OcrTextBlock], query: &str) -> Vec<TextPosition> {
     let query_lower = query.to_lowercase();
     let query_words: Vec<&str> = query_lower.split_whitespace().collect();
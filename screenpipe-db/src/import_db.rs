@@ -0,0 +1,230 @@
+//! History imported from other recording tools by `screenpipe import`. Their
+//! media isn't available, so frames and transcripts are stored under a chunk
+//! standing for the import, marked as having no file, and keep only their
+//! text. Importing the same history again skips what's already there.
+
+use chrono::Utc;
+use sqlx::{Sqlite, Transaction};
+
+use crate::{DatabaseManager, ImportedFrame, ImportedTranscript, ImportedWindowEvent};
+
+impl DatabaseManager {
+    /// Stores frames of `device_name` under the chunk standing for `source`,
+    /// with their text as OCR text of `engine`. Frames the chunk already has
+    /// at the same time are skipped. Returns the number of frames inserted.
+    pub async fn insert_imported_frames(
+        &self,
+        device_name: &str,
+        source: &str,
+        engine: &str,
+        frames: &[ImportedFrame],
+    ) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let chunk_id = imported_video_chunk(&mut tx, device_name, source).await?;
+        let mut offset_index: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(offset_index), -1) + 1 FROM frames WHERE video_chunk_id = ?1",
+        )
+        .bind(chunk_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut inserted = 0;
+        for frame in frames {
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM frames WHERE video_chunk_id = ?1 AND timestamp = ?2)",
+            )
+            .bind(chunk_id)
+            .bind(frame.timestamp)
+            .fetch_one(&mut *tx)
+            .await?;
+            if exists {
+                continue;
+            }
+
+            let frame_id = sqlx::query(
+                "INSERT INTO frames (video_chunk_id, offset_index, timestamp, name, browser_url, app_name, window_name, focused, device_name) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 1, ?8)",
+            )
+            .bind(chunk_id)
+            .bind(offset_index)
+            .bind(frame.timestamp)
+            .bind(source)
+            .bind(&frame.browser_url)
+            .bind(&frame.app_name)
+            .bind(&frame.window_name)
+            .bind(device_name)
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+            offset_index += 1;
+
+            sqlx::query(
+                "INSERT INTO ocr_text (frame_id, text, text_json, ocr_engine, text_length) VALUES (?1, ?2, '', ?3, ?4)",
+            )
+            .bind(frame_id)
+            .bind(&frame.text)
+            .bind(engine)
+            .bind(frame.text.len() as i64)
+            .execute(&mut *tx)
+            .await?;
+            inserted += 1;
+        }
+        tx.commit().await?;
+        Ok(inserted)
+    }
+
+    /// Stores transcripts of `device_name` under the chunk standing for
+    /// `source`, as transcribed by `engine`. Transcripts the chunk already
+    /// has at the same time are skipped. Returns the number inserted.
+    pub async fn insert_imported_transcripts(
+        &self,
+        device_name: &str,
+        source: &str,
+        engine: &str,
+        transcripts: &[ImportedTranscript],
+    ) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let chunk_id = imported_audio_chunk(&mut tx, source, transcripts).await?;
+        let mut offset_index: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(offset_index), -1) + 1 FROM audio_transcriptions WHERE audio_chunk_id = ?1",
+        )
+        .bind(chunk_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut inserted = 0;
+        for transcript in transcripts {
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM audio_transcriptions WHERE audio_chunk_id = ?1 AND timestamp = ?2)",
+            )
+            .bind(chunk_id)
+            .bind(transcript.timestamp)
+            .fetch_one(&mut *tx)
+            .await?;
+            if exists {
+                continue;
+            }
+
+            sqlx::query(
+                "INSERT INTO audio_transcriptions (audio_chunk_id, transcription, offset_index, timestamp, transcription_engine, device, is_input_device, text_length) VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7)",
+            )
+            .bind(chunk_id)
+            .bind(&transcript.text)
+            .bind(offset_index)
+            .bind(transcript.timestamp)
+            .bind(engine)
+            .bind(device_name)
+            .bind(transcript.text.len() as i64)
+            .execute(&mut *tx)
+            .await?;
+            offset_index += 1;
+            inserted += 1;
+        }
+        tx.commit().await?;
+        Ok(inserted)
+    }
+
+    /// Stores windows that had the focus as focus events, and their titles
+    /// in the UI monitoring table so they're searched like the ones the UI
+    /// monitor reports. Events already stored with the same window and start
+    /// are skipped. Returns the number of events inserted.
+    pub async fn insert_imported_window_events(
+        &self,
+        events: &[ImportedWindowEvent],
+    ) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut inserted = 0;
+        for event in events {
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM focus_events WHERE app_name = ?1 AND window_name = ?2 AND start_time = ?3)",
+            )
+            .bind(&event.app_name)
+            .bind(&event.window_name)
+            .bind(event.start_time)
+            .fetch_one(&mut *tx)
+            .await?;
+            if exists {
+                continue;
+            }
+
+            sqlx::query(
+                "INSERT INTO focus_events (app_name, window_name, start_time, end_time) VALUES (?1, ?2, ?3, ?4)",
+            )
+            .bind(&event.app_name)
+            .bind(&event.window_name)
+            .bind(event.start_time)
+            .bind(event.end_time.max(event.start_time))
+            .execute(&mut *tx)
+            .await?;
+            if !event.window_name.is_empty() {
+                sqlx::query(
+                    "INSERT INTO ui_monitoring (text_output, timestamp, app, window, initial_traversal_at) VALUES (?1, ?2, ?3, ?1, ?2)",
+                )
+                .bind(&event.window_name)
+                .bind(event.start_time)
+                .bind(&event.app_name)
+                .execute(&mut *tx)
+                .await?;
+            }
+            inserted += 1;
+        }
+        tx.commit().await?;
+        Ok(inserted)
+    }
+}
+
+/// Id of the video chunk standing for `source`, inserted without media if
+/// it's new.
+async fn imported_video_chunk(
+    tx: &mut Transaction<'_, Sqlite>,
+    device_name: &str,
+    source: &str,
+) -> Result<i64, sqlx::Error> {
+    let id: Option<i64> =
+        sqlx::query_scalar("SELECT id FROM video_chunks WHERE file_path = ?1 AND device_name = ?2")
+            .bind(source)
+            .bind(device_name)
+            .fetch_optional(&mut **tx)
+            .await?;
+    if let Some(id) = id {
+        return Ok(id);
+    }
+    Ok(sqlx::query(
+        "INSERT INTO video_chunks (file_path, device_name, media_deleted_at) VALUES (?1, ?2, ?3)",
+    )
+    .bind(source)
+    .bind(device_name)
+    .bind(Utc::now())
+    .execute(&mut **tx)
+    .await?
+    .last_insert_rowid())
+}
+
+/// Id of the audio chunk standing for `source`, inserted without media if
+/// it's new, recorded when the first of `transcripts` was spoken.
+async fn imported_audio_chunk(
+    tx: &mut Transaction<'_, Sqlite>,
+    source: &str,
+    transcripts: &[ImportedTranscript],
+) -> Result<i64, sqlx::Error> {
+    let id: Option<i64> = sqlx::query_scalar("SELECT id FROM audio_chunks WHERE file_path = ?1")
+        .bind(source)
+        .fetch_optional(&mut **tx)
+        .await?;
+    if let Some(id) = id {
+        return Ok(id);
+    }
+    let timestamp = transcripts
+        .iter()
+        .map(|transcript| transcript.timestamp)
+        .min()
+        .unwrap_or_else(Utc::now);
+    Ok(sqlx::query(
+        "INSERT INTO audio_chunks (file_path, timestamp, media_deleted_at) VALUES (?1, ?2, ?3)",
+    )
+    .bind(source)
+    .bind(timestamp)
+    .bind(Utc::now())
+    .execute(&mut **tx)
+    .await?
+    .last_insert_rowid())
+}
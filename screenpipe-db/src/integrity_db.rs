@@ -0,0 +1,261 @@
+//! Consistency checks of the database run by `screenpipe doctor`, and their
+//! repairs. Rows whose parent is gone are deleted, full text indexes that
+//! drifted from their table are filled again from it.
+
+use std::collections::BTreeMap;
+
+use sqlx::Row;
+use tracing::{debug, warn};
+
+use crate::{DatabaseManager, IntegrityIssue};
+
+/// Rows pointing at a parent row, checked even where no foreign key says so.
+const MISSING_PARENTS: [(&str, &str, &str); 3] = [
+    (
+        "frames",
+        "video chunk",
+        "video_chunk_id IS NOT NULL AND video_chunk_id NOT IN (SELECT id FROM video_chunks)",
+    ),
+    (
+        "ocr_text",
+        "frame",
+        "frame_id IS NOT NULL AND frame_id NOT IN (SELECT id FROM frames)",
+    ),
+    (
+        "audio_transcriptions",
+        "audio chunk",
+        "audio_chunk_id IS NOT NULL AND audio_chunk_id NOT IN (SELECT id FROM audio_chunks)",
+    ),
+];
+
+/// A full text index kept in sync with its table by triggers.
+struct FtsIndex {
+    name: &'static str,
+    /// Rows the index should hold
+    expected: &'static str,
+    /// Fills the emptied index from its table, like the insert trigger does
+    fill: &'static str,
+}
+
+const FTS_INDEXES: [FtsIndex; 3] = [
+    FtsIndex {
+        name: "ocr_text_fts",
+        expected: "SELECT COUNT(*) FROM ocr_text WHERE text IS NOT NULL AND text != '' AND frame_id IS NOT NULL",
+        fill: r#"
+            INSERT INTO ocr_text_fts(frame_id, text, app_name, window_name)
+            SELECT
+                ocr_text.frame_id,
+                ocr_text.text,
+                COALESCE(frames.app_name, ocr_text.app_name, ''),
+                COALESCE(frames.window_name, ocr_text.window_name, '')
+            FROM ocr_text
+            LEFT JOIN frames ON frames.id = ocr_text.frame_id
+            WHERE ocr_text.text IS NOT NULL AND ocr_text.text != '' AND ocr_text.frame_id IS NOT NULL
+        "#,
+    },
+    FtsIndex {
+        name: "audio_transcriptions_fts",
+        expected: "SELECT COUNT(*) FROM audio_transcriptions WHERE transcription IS NOT NULL AND transcription != '' AND audio_chunk_id IS NOT NULL",
+        fill: r#"
+            INSERT INTO audio_transcriptions_fts(transcription, translated_text, device, audio_chunk_id, speaker_id, start_time, end_time)
+            SELECT
+                transcription,
+                COALESCE(translated_text, ''),
+                COALESCE(device, ''),
+                audio_chunk_id,
+                speaker_id,
+                start_time,
+                end_time
+            FROM audio_transcriptions
+            WHERE transcription IS NOT NULL AND transcription != '' AND audio_chunk_id IS NOT NULL
+        "#,
+    },
+    FtsIndex {
+        name: "frames_fts",
+        expected: "SELECT COUNT(*) FROM frames",
+        fill: r#"
+            INSERT INTO frames_fts(id, name, browser_url, app_name, window_name, focused)
+            SELECT
+                id,
+                COALESCE(name, ''),
+                COALESCE(browser_url, ''),
+                COALESCE(app_name, ''),
+                COALESCE(window_name, ''),
+                COALESCE(focused, 0)
+            FROM frames
+        "#,
+    },
+];
+
+impl DatabaseManager {
+    /// Problems SQLite's quick check finds in the file itself. They can't be
+    /// repaired in place, only by restoring a backup.
+    pub async fn check_sqlite_integrity(&self) -> Result<Vec<IntegrityIssue>, sqlx::Error> {
+        let problems: Vec<String> = sqlx::query_scalar("PRAGMA quick_check;")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(problems
+            .into_iter()
+            .filter(|problem| problem != "ok")
+            .map(|problem| IntegrityIssue::new("sqlite_integrity", problem, 0))
+            .collect())
+    }
+
+    /// Frames, screen text and transcripts whose chunk or frame is gone,
+    /// deleted with `repair`.
+    pub async fn check_missing_parents(
+        &self,
+        repair: bool,
+    ) -> Result<Vec<IntegrityIssue>, sqlx::Error> {
+        let mut issues = Vec::new();
+        for (table, parent, orphaned) in MISSING_PARENTS {
+            let count: i64 = sqlx::query_scalar(&format!(
+                "SELECT COUNT(*) FROM {} WHERE {}",
+                table, orphaned
+            ))
+            .fetch_one(&self.pool)
+            .await?;
+            if count == 0 {
+                continue;
+            }
+            let mut issue = IntegrityIssue::new(
+                "missing_parents",
+                format!("{} rows refer to a {} that doesn't exist", table, parent),
+                count as u64,
+            );
+            if repair {
+                sqlx::query(&format!("DELETE FROM {} WHERE {}", table, orphaned))
+                    .execute(&self.pool)
+                    .await?;
+                debug!("deleted {} {} rows without a {}", count, table, parent);
+                issue.repaired = true;
+            }
+            issues.push(issue);
+        }
+        Ok(issues)
+    }
+
+    /// Rows violating a foreign key, per table, deleted with `repair`.
+    pub async fn check_foreign_keys(
+        &self,
+        repair: bool,
+    ) -> Result<Vec<IntegrityIssue>, sqlx::Error> {
+        let violations = sqlx::query("PRAGMA foreign_key_check;")
+            .fetch_all(&self.pool)
+            .await?;
+        let mut rows: BTreeMap<(String, String), Vec<i64>> = BTreeMap::new();
+        for violation in violations {
+            let table: String = violation.try_get("table")?;
+            let parent: String = violation.try_get("parent")?;
+            // tables without rowid have none to delete by
+            let rowid: Option<i64> = violation.try_get("rowid")?;
+            rows.entry((table, parent)).or_default().extend(rowid);
+        }
+
+        let mut issues = Vec::new();
+        for ((table, parent), rowids) in rows {
+            let mut issue = IntegrityIssue::new(
+                "foreign_keys",
+                format!("{} rows refer to missing {} rows", table, parent),
+                rowids.len() as u64,
+            );
+            if repair && !rowids.is_empty() {
+                sqlx::query(&format!(
+                    "DELETE FROM \"{}\" WHERE rowid IN (SELECT value FROM json_each(?1))",
+                    table.replace('"', "\"\"")
+                ))
+                .bind(serde_json::to_string(&rowids).unwrap_or_else(|_| "[]".to_string()))
+                .execute(&self.pool)
+                .await?;
+                issue.repaired = true;
+            }
+            issues.push(issue);
+        }
+        Ok(issues)
+    }
+
+    /// Full text indexes that are corrupted or out of sync with their table,
+    /// rebuilt from it with `repair`.
+    pub async fn check_fts_indexes(
+        &self,
+        repair: bool,
+    ) -> Result<Vec<IntegrityIssue>, sqlx::Error> {
+        let mut issues = Vec::new();
+        for index in FTS_INDEXES {
+            let mut issue = match sqlx::query(&format!(
+                "INSERT INTO {0}({0}) VALUES ('integrity-check')",
+                index.name
+            ))
+            .execute(&self.pool)
+            .await
+            {
+                Err(e) => Some(IntegrityIssue::new(
+                    "fts_index",
+                    format!("{} is corrupted: {}", index.name, e),
+                    0,
+                )),
+                Ok(_) => {
+                    let expected: i64 = sqlx::query_scalar(index.expected)
+                        .fetch_one(&self.pool)
+                        .await?;
+                    let indexed: i64 =
+                        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", index.name))
+                            .fetch_one(&self.pool)
+                            .await?;
+                    (indexed != expected).then(|| {
+                        IntegrityIssue::new(
+                            "fts_index",
+                            format!(
+                                "{} holds {} rows instead of {}",
+                                index.name, indexed, expected
+                            ),
+                            expected.abs_diff(indexed),
+                        )
+                    })
+                }
+            };
+            if let Some(issue) = issue.as_mut().filter(|_| repair) {
+                self.rebuild_fts_index(&index).await?;
+                issue.repaired = true;
+            }
+            issues.extend(issue);
+        }
+        Ok(issues)
+    }
+
+    async fn rebuild_fts_index(&self, index: &FtsIndex) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(&format!("DELETE FROM {}", index.name))
+            .execute(&mut *tx)
+            .await?;
+        let filled = sqlx::query(index.fill).execute(&mut *tx).await?;
+        tx.commit().await?;
+        if let Err(e) = sqlx::query(&format!(
+            "INSERT INTO {0}({0}) VALUES ('optimize')",
+            index.name
+        ))
+        .execute(&self.pool)
+        .await
+        {
+            warn!("failed to optimize {}: {}", index.name, e);
+        }
+        debug!(
+            "rebuilt {} with {} rows",
+            index.name,
+            filled.rows_affected()
+        );
+        Ok(())
+    }
+
+    /// Media files of every video and audio chunk, recorded to or not,
+    /// partitions included.
+    pub async fn media_chunk_paths(&self) -> Result<Vec<String>, sqlx::Error> {
+        let mut paths: Vec<String> = sqlx::query_scalar(
+            "SELECT file_path FROM video_chunks UNION SELECT file_path FROM audio_chunks",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        paths.extend(self.partition_media_paths().await?);
+        Ok(paths)
+    }
+}
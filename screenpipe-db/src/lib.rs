@@ -1,11 +1,18 @@
 mod db;
+mod import_db;
+mod integrity_db;
 mod migration_worker;
+mod partition_db;
+mod schema;
+mod snapshot_db;
 mod types;
 mod video_db;
+mod write_queue;
 
-pub use db::DatabaseManager;
+pub use db::{check_database_integrity, fts_query, reciprocal_rank_fusion, DatabaseManager};
 pub use migration_worker::{
     create_migration_worker, MigrationCommand, MigrationConfig, MigrationResponse, MigrationStatus,
     MigrationWorker,
 };
+pub use schema::{database_schema_status, migrate_database, rollback_database, schema_status};
 pub use types::*;
@@ -0,0 +1,20 @@
+-- Layout of the OCR'd text of a frame. One row per block, paragraph and line,
+-- blocks are numbered in reading order. Bounds are relative to the captured
+-- window image, from 0 to 1 with the origin at the top left.
+CREATE TABLE IF NOT EXISTS ocr_blocks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    frame_id INTEGER NOT NULL,
+    level TEXT NOT NULL CHECK (level IN ('block', 'paragraph', 'line')),
+    block_index INTEGER NOT NULL,
+    paragraph_index INTEGER,
+    line_index INTEGER,
+    text TEXT NOT NULL,
+    confidence REAL,
+    left REAL NOT NULL,
+    top REAL NOT NULL,
+    width REAL NOT NULL,
+    height REAL NOT NULL,
+    FOREIGN KEY (frame_id) REFERENCES frames(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_ocr_blocks_frame_id ON ocr_blocks(frame_id);
@@ -0,0 +1,37 @@
+-- Tables detected in the OCR'd text of a frame. Cells are stored as a JSON
+-- array of rows, text holds the cell values for full text search.
+CREATE TABLE IF NOT EXISTS ocr_tables (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    frame_id INTEGER NOT NULL,
+    table_index INTEGER NOT NULL,
+    row_count INTEGER NOT NULL,
+    column_count INTEGER NOT NULL,
+    cells TEXT NOT NULL,
+    text TEXT NOT NULL,
+    left REAL NOT NULL,
+    top REAL NOT NULL,
+    width REAL NOT NULL,
+    height REAL NOT NULL,
+    FOREIGN KEY (frame_id) REFERENCES frames(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_ocr_tables_frame_id ON ocr_tables(frame_id);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS ocr_tables_fts USING fts5(
+    text,
+    table_id UNINDEXED,
+    tokenize='unicode61'
+);
+
+CREATE TRIGGER IF NOT EXISTS ocr_tables_ai AFTER INSERT ON ocr_tables
+WHEN NEW.text IS NOT NULL AND NEW.text != ''
+BEGIN
+    INSERT OR IGNORE INTO ocr_tables_fts(table_id, text)
+    VALUES (NEW.id, NEW.text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS ocr_tables_delete AFTER DELETE ON ocr_tables
+BEGIN
+    DELETE FROM ocr_tables_fts
+    WHERE table_id = OLD.id;
+END;
@@ -0,0 +1,4 @@
+-- Spoken language detected for each transcription, e.g. 'en' or 'de'
+ALTER TABLE audio_transcriptions ADD COLUMN language TEXT DEFAULT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_audio_transcriptions_language ON audio_transcriptions(language);
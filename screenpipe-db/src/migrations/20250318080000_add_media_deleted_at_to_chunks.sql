@@ -0,0 +1,4 @@
+-- When the media file of a chunk was deleted to free disk space. The rows
+-- and the text extracted from them are kept.
+ALTER TABLE video_chunks ADD COLUMN media_deleted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE audio_chunks ADD COLUMN media_deleted_at TIMESTAMP DEFAULT NULL;
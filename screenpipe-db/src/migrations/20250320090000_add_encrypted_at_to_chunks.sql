@@ -0,0 +1,3 @@
+-- When the media file of a chunk was encrypted at rest.
+ALTER TABLE video_chunks ADD COLUMN encrypted_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE audio_chunks ADD COLUMN encrypted_at TIMESTAMP DEFAULT NULL;
@@ -0,0 +1,11 @@
+-- When the privacy blocklist stopped and resumed capture.
+CREATE TABLE IF NOT EXISTS privacy_audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp TIMESTAMP NOT NULL,
+    monitor_id INTEGER NOT NULL,
+    blocked BOOLEAN NOT NULL,
+    app_name TEXT NOT NULL,
+    pattern TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_privacy_audit_log_timestamp ON privacy_audit_log(timestamp);
@@ -0,0 +1,2 @@
+-- Active browser tab of a frame as json: url, tab title and browser
+ALTER TABLE frames ADD COLUMN browser_context TEXT DEFAULT NULL;
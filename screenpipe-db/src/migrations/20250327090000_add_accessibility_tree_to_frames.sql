@@ -0,0 +1,2 @@
+-- Accessibility tree of the focused window of a frame as json
+ALTER TABLE frames ADD COLUMN accessibility_tree TEXT DEFAULT NULL;
@@ -0,0 +1,3 @@
+-- Which source produced the text of a line: 'ocr', or 'accessibility' for
+-- text read from the app's accessibility API. NULL for blocks and paragraphs.
+ALTER TABLE ocr_blocks ADD COLUMN source TEXT DEFAULT NULL;
@@ -0,0 +1,17 @@
+-- Keyboard and mouse activity, recorded when input capture is enabled. One
+-- row per span of time spent in a focused window, spans end when the focus
+-- changes or are split after a while. Typed text is only kept if enabled.
+CREATE TABLE IF NOT EXISTS input_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp TIMESTAMP NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    app_name TEXT NOT NULL,
+    window_name TEXT NOT NULL,
+    keystrokes INTEGER NOT NULL DEFAULT 0,
+    clicks INTEGER NOT NULL DEFAULT 0,
+    scrolls INTEGER NOT NULL DEFAULT 0,
+    typed_text TEXT DEFAULT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_input_events_timestamp ON input_events(timestamp);
+CREATE INDEX IF NOT EXISTS idx_input_events_app_name ON input_events(app_name);
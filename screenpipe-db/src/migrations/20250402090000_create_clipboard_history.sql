@@ -0,0 +1,30 @@
+-- Text copied to the clipboard, with the app and window that had the focus
+-- when it was copied.
+CREATE TABLE IF NOT EXISTS clipboard_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp TIMESTAMP NOT NULL,
+    app_name TEXT NOT NULL,
+    window_name TEXT NOT NULL,
+    text TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_clipboard_history_timestamp ON clipboard_history(timestamp);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS clipboard_history_fts USING fts5(
+    text,
+    clipboard_id UNINDEXED,
+    tokenize='unicode61'
+);
+
+CREATE TRIGGER IF NOT EXISTS clipboard_history_ai AFTER INSERT ON clipboard_history
+WHEN NEW.text IS NOT NULL AND NEW.text != ''
+BEGIN
+    INSERT OR IGNORE INTO clipboard_history_fts(clipboard_id, text)
+    VALUES (NEW.id, NEW.text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS clipboard_history_delete AFTER DELETE ON clipboard_history
+BEGIN
+    DELETE FROM clipboard_history_fts
+    WHERE clipboard_id = OLD.id;
+END;
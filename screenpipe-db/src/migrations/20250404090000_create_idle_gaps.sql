@@ -0,0 +1,10 @@
+-- Times the user was away and capture was suspended. end_time stays NULL
+-- until activity resumes.
+CREATE TABLE IF NOT EXISTS idle_gaps (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    start_time TIMESTAMP NOT NULL,
+    end_time TIMESTAMP DEFAULT NULL,
+    reason TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_idle_gaps_start_time ON idle_gaps(start_time);
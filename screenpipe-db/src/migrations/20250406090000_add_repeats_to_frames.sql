@@ -0,0 +1,6 @@
+-- Perceptual hash of the screen, and how often a near-identical screen was
+-- captured again after the frame. Repeats aren't stored as frames, the frame
+-- stays on screen until last_repeat_at.
+ALTER TABLE frames ADD COLUMN phash INTEGER DEFAULT NULL;
+ALTER TABLE frames ADD COLUMN repeat_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE frames ADD COLUMN last_repeat_at TIMESTAMP DEFAULT NULL;
@@ -0,0 +1,27 @@
+-- Sync of indexed text between devices. This instance's id, sent with every
+-- batch so peers can tell devices apart.
+CREATE TABLE IF NOT EXISTS sync_device (
+    id TEXT NOT NULL
+);
+
+-- Records imported from other devices, keyed by the device they were
+-- captured on and their id there, so each is imported once and never sent
+-- back out.
+CREATE TABLE IF NOT EXISTS sync_imports (
+    device_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    remote_id INTEGER NOT NULL,
+    local_id INTEGER NOT NULL,
+    PRIMARY KEY (device_id, kind, remote_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_imports_local ON sync_imports(kind, local_id);
+
+-- Where pulling from each peer stopped.
+CREATE TABLE IF NOT EXISTS sync_peers (
+    url TEXT PRIMARY KEY,
+    device_id TEXT DEFAULT NULL,
+    frame_cursor INTEGER NOT NULL DEFAULT 0,
+    transcription_cursor INTEGER NOT NULL DEFAULT 0,
+    last_synced_at TIMESTAMP DEFAULT NULL
+);
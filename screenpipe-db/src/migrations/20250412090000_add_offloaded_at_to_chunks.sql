@@ -0,0 +1,3 @@
+-- When the media file of a chunk was moved to object storage.
+ALTER TABLE video_chunks ADD COLUMN offloaded_at TIMESTAMP DEFAULT NULL;
+ALTER TABLE audio_chunks ADD COLUMN offloaded_at TIMESTAMP DEFAULT NULL;
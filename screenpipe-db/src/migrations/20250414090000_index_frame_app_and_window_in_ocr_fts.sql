@@ -0,0 +1,36 @@
+-- The app and window of a frame are indexed next to its text so search can
+-- rank window title matches above the text on screen. They moved from
+-- ocr_text to frames, so they are now taken from the frame.
+PRAGMA foreign_keys = OFF;
+
+DROP TRIGGER IF EXISTS ocr_text_ai;
+DROP TRIGGER IF EXISTS ocr_text_update;
+
+CREATE TRIGGER IF NOT EXISTS ocr_text_ai AFTER INSERT ON ocr_text
+WHEN NEW.text IS NOT NULL AND NEW.text != '' AND NEW.frame_id IS NOT NULL
+BEGIN
+    INSERT OR IGNORE INTO ocr_text_fts(frame_id, text, app_name, window_name)
+    VALUES (
+        NEW.frame_id,
+        NEW.text,
+        COALESCE((SELECT app_name FROM frames WHERE id = NEW.frame_id), NEW.app_name, ''),
+        COALESCE((SELECT window_name FROM frames WHERE id = NEW.frame_id), NEW.window_name, '')
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS ocr_text_update AFTER UPDATE ON ocr_text
+WHEN NEW.text IS NOT NULL AND NEW.text != '' AND OLD.frame_id IS NOT NULL
+BEGIN
+    UPDATE ocr_text_fts
+    SET text = NEW.text,
+        app_name = COALESCE((SELECT app_name FROM frames WHERE id = NEW.frame_id), NEW.app_name, ''),
+        window_name = COALESCE((SELECT window_name FROM frames WHERE id = NEW.frame_id), NEW.window_name, '')
+    WHERE frame_id = OLD.frame_id;
+END;
+
+UPDATE ocr_text_fts
+SET app_name = COALESCE((SELECT frames.app_name FROM frames WHERE frames.id = ocr_text_fts.frame_id), app_name),
+    window_name = COALESCE((SELECT frames.window_name FROM frames WHERE frames.id = ocr_text_fts.frame_id), window_name)
+WHERE app_name = '' AND window_name = '';
+
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,5 @@
+-- Words indexed for search, fuzzy search looks up the ones a few typos away
+-- from a searched word in them.
+CREATE VIRTUAL TABLE IF NOT EXISTS ocr_text_fts_vocab USING fts5vocab(ocr_text_fts, row);
+CREATE VIRTUAL TABLE IF NOT EXISTS audio_transcriptions_fts_vocab USING fts5vocab(audio_transcriptions_fts, row);
+CREATE VIRTUAL TABLE IF NOT EXISTS ui_monitoring_fts_vocab USING fts5vocab(ui_monitoring_fts, row);
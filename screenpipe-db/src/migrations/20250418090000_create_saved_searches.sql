@@ -0,0 +1,15 @@
+-- Searches checked against new recordings, notifying when they match.
+CREATE TABLE IF NOT EXISTS saved_searches (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    query TEXT NOT NULL DEFAULT '',
+    content_type TEXT NOT NULL DEFAULT 'all',
+    app_name TEXT DEFAULT NULL,
+    window_name TEXT DEFAULT NULL,
+    speaker_name TEXT DEFAULT NULL,
+    notify BOOLEAN NOT NULL DEFAULT TRUE,
+    webhook_url TEXT DEFAULT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_checked_at TIMESTAMP DEFAULT NULL,
+    last_matched_at TIMESTAMP DEFAULT NULL
+);
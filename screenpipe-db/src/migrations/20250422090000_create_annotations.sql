@@ -0,0 +1,24 @@
+-- Notes and tags attached to a frame, a transcript segment, or a time range
+-- when both are NULL. `start_time` and `end_time` are the time of the frame
+-- or segment for those.
+CREATE TABLE IF NOT EXISTS annotations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    frame_id INTEGER DEFAULT NULL REFERENCES frames(id) ON DELETE CASCADE,
+    audio_transcription_id INTEGER DEFAULT NULL REFERENCES audio_transcriptions(id) ON DELETE CASCADE,
+    start_time TIMESTAMP NOT NULL,
+    end_time TIMESTAMP NOT NULL,
+    note TEXT DEFAULT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS annotation_tags (
+    annotation_id INTEGER NOT NULL REFERENCES annotations(id) ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (annotation_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_annotations_frame_id ON annotations(frame_id);
+CREATE INDEX IF NOT EXISTS idx_annotations_audio_transcription_id ON annotations(audio_transcription_id);
+CREATE INDEX IF NOT EXISTS idx_annotations_time ON annotations(start_time, end_time);
+CREATE INDEX IF NOT EXISTS idx_annotation_tags_tag_id ON annotation_tags(tag_id);
@@ -0,0 +1,17 @@
+-- Activity category of each recorded time block, `meeting`, `coding`,
+-- `browsing`, `writing` or `other`. Blocks without any frame or transcript
+-- have no segment.
+CREATE TABLE IF NOT EXISTS activity_segments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    start_time TIMESTAMP NOT NULL,
+    end_time TIMESTAMP NOT NULL,
+    category TEXT NOT NULL,
+    -- app on screen the most during the block
+    app_name TEXT DEFAULT NULL,
+    frame_count INTEGER NOT NULL DEFAULT 0,
+    has_audio BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_activity_segments_start_time ON activity_segments(start_time);
+CREATE INDEX IF NOT EXISTS idx_activity_segments_category ON activity_segments(category);
@@ -0,0 +1,17 @@
+-- Summaries of a local day or week written by an LLM. topics, action_items
+-- and people are JSON arrays of strings.
+CREATE TABLE IF NOT EXISTS summaries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    period TEXT NOT NULL,
+    start_time TIMESTAMP NOT NULL,
+    end_time TIMESTAMP NOT NULL,
+    summary TEXT NOT NULL,
+    topics TEXT NOT NULL DEFAULT '[]',
+    action_items TEXT NOT NULL DEFAULT '[]',
+    people TEXT NOT NULL DEFAULT '[]',
+    model TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (period, start_time)
+);
+
+CREATE INDEX IF NOT EXISTS idx_summaries_start_time ON summaries(start_time);
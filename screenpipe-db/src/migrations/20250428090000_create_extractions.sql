@@ -0,0 +1,26 @@
+-- Rules extracting a record matching a JSON schema from each session of an
+-- app, e.g. attendees and decisions from each Zoom meeting.
+CREATE TABLE IF NOT EXISTS extraction_rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    app_name TEXT NOT NULL,
+    window_name TEXT DEFAULT NULL,
+    instructions TEXT NOT NULL DEFAULT '',
+    schema TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    processed_until TIMESTAMP DEFAULT NULL
+);
+
+-- Records extracted by a rule, data is a JSON object matching its schema.
+CREATE TABLE IF NOT EXISTS extractions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    rule_id INTEGER NOT NULL REFERENCES extraction_rules(id) ON DELETE CASCADE,
+    start_time TIMESTAMP NOT NULL,
+    end_time TIMESTAMP NOT NULL,
+    data TEXT NOT NULL,
+    model TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_extractions_rule_id_start_time ON extractions(rule_id, start_time);
+CREATE INDEX IF NOT EXISTS idx_extractions_start_time ON extractions(start_time);
@@ -0,0 +1,28 @@
+-- Phones and other devices pushing what they capture with a companion app.
+-- Each is paired with its own token, only a SHA-256 hash of it is kept.
+CREATE TABLE IF NOT EXISTS companion_devices (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMP DEFAULT NULL
+);
+
+-- Media uploaded by companion devices in chunks. completed_at is set once
+-- the whole file was received and queued for ingestion, the SHA-256 of
+-- completed uploads is what duplicates are recognized by.
+CREATE TABLE IF NOT EXISTS companion_uploads (
+    id TEXT PRIMARY KEY,
+    device_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    content_type TEXT NOT NULL DEFAULT '',
+    size INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    timestamp TIMESTAMP DEFAULT NULL,
+    metadata TEXT DEFAULT NULL,
+    ingest_id TEXT DEFAULT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_companion_uploads_sha256 ON companion_uploads(sha256);
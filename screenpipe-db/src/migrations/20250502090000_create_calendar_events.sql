@@ -0,0 +1,17 @@
+-- Events pulled from the user's calendars. What was captured between the
+-- start and end of an event happened during that meeting. attendees is a
+-- JSON array of names, or emails when the calendar has no name.
+CREATE TABLE IF NOT EXISTS calendar_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    source TEXT NOT NULL,
+    uid TEXT NOT NULL,
+    title TEXT NOT NULL,
+    attendees TEXT NOT NULL DEFAULT '[]',
+    location TEXT DEFAULT NULL,
+    start_time TIMESTAMP NOT NULL,
+    end_time TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (source, uid)
+);
+
+CREATE INDEX IF NOT EXISTS idx_calendar_events_time ON calendar_events(start_time, end_time);
@@ -0,0 +1,14 @@
+-- Meetings detected from a meeting app in the foreground with sustained
+-- speech. `end_time` moves forward while the meeting goes on, `ended` is set
+-- once it's over. Participants are the speakers transcribed in between.
+CREATE TABLE IF NOT EXISTS meeting_sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    start_time TIMESTAMP NOT NULL,
+    end_time TIMESTAMP NOT NULL,
+    -- meeting app or browser the meeting was in
+    app_name TEXT DEFAULT NULL,
+    ended BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_meeting_sessions_start_time ON meeting_sessions(start_time);
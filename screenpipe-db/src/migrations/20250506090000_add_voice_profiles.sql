@@ -0,0 +1,3 @@
+-- Speakers enrolled from voice samples under a name, matched before the
+-- speakers diarization found on its own.
+ALTER TABLE speakers ADD COLUMN enrolled BOOLEAN NOT NULL DEFAULT FALSE;
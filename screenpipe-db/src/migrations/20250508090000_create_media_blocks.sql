@@ -0,0 +1,15 @@
+-- Time music was playing on an audio device. It is not transcribed, the
+-- block is kept so the timeline still shows it. Consecutive chunks of the
+-- same device extend one block.
+CREATE TABLE IF NOT EXISTS media_blocks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    start_time TIMESTAMP NOT NULL,
+    end_time TIMESTAMP NOT NULL,
+    device TEXT NOT NULL,
+    is_input_device BOOLEAN NOT NULL,
+    classification TEXT NOT NULL,
+    confidence REAL NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_blocks_start_time ON media_blocks(start_time);
+CREATE INDEX IF NOT EXISTS idx_media_blocks_device ON media_blocks(device, end_time);
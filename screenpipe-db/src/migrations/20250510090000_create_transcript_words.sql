@@ -0,0 +1,14 @@
+-- Timing and confidence of each word of a transcription, from engines that
+-- give them. Times are seconds from the start of the audio chunk file.
+CREATE TABLE IF NOT EXISTS transcript_words (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    audio_transcription_id INTEGER NOT NULL,
+    word_index INTEGER NOT NULL,
+    word TEXT NOT NULL,
+    start_time REAL NOT NULL,
+    end_time REAL NOT NULL,
+    confidence REAL NOT NULL,
+    FOREIGN KEY (audio_transcription_id) REFERENCES audio_transcriptions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_transcript_words_transcription ON transcript_words(audio_transcription_id, word_index);
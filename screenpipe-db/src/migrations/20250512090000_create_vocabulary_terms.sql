@@ -0,0 +1,11 @@
+-- Custom vocabulary given to the transcription engines. Terms without an
+-- app are used for every chunk, the others while their app is focused.
+CREATE TABLE IF NOT EXISTS vocabulary_terms (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    term TEXT NOT NULL,
+    sounds_like TEXT DEFAULT NULL,
+    app_name TEXT DEFAULT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_vocabulary_terms_app_name ON vocabulary_terms(app_name);
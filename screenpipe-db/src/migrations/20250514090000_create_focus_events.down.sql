@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_focus_events_app_name;
+DROP INDEX IF EXISTS idx_focus_events_end_time;
+DROP INDEX IF EXISTS idx_focus_events_start_time;
+DROP TABLE IF EXISTS focus_events;
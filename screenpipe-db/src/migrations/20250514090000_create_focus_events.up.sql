@@ -0,0 +1,13 @@
+-- Spans an app window had the focus for, from the UI monitor. A span ends
+-- when another window gets the focus, or when the monitor goes quiet.
+CREATE TABLE IF NOT EXISTS focus_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    app_name TEXT NOT NULL,
+    window_name TEXT NOT NULL DEFAULT '',
+    start_time TIMESTAMP NOT NULL,
+    end_time TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_focus_events_start_time ON focus_events(start_time);
+CREATE INDEX IF NOT EXISTS idx_focus_events_end_time ON focus_events(end_time);
+CREATE INDEX IF NOT EXISTS idx_focus_events_app_name ON focus_events(app_name);
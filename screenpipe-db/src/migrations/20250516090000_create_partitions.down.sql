@@ -0,0 +1,3 @@
+-- the partition files are left in place, their months stay out of the main
+-- database
+DROP TABLE IF EXISTS partitions;
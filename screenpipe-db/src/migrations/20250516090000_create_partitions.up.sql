@@ -0,0 +1,9 @@
+-- Months of recordings moved out of the main database into their own file,
+-- attached to a connection while they're queried.
+CREATE TABLE IF NOT EXISTS partitions (
+    month TEXT PRIMARY KEY,
+    file_path TEXT NOT NULL,
+    frames INTEGER NOT NULL DEFAULT 0,
+    transcriptions INTEGER NOT NULL DEFAULT 0,
+    archived_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
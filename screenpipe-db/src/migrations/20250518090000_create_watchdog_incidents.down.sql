@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_watchdog_incidents_timestamp;
+DROP TABLE IF EXISTS watchdog_incidents;
@@ -0,0 +1,13 @@
+-- Capture, OCR and audio loops the watchdog found without progress and
+-- restarted. `target` is the monitor id or the audio device name.
+CREATE TABLE IF NOT EXISTS watchdog_incidents (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp TIMESTAMP NOT NULL,
+    subsystem TEXT NOT NULL,
+    target TEXT NOT NULL,
+    stalled_secs INTEGER NOT NULL,
+    restarted BOOLEAN NOT NULL,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_watchdog_incidents_timestamp ON watchdog_incidents(timestamp);
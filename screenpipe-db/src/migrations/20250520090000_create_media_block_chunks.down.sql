@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_media_block_chunks_audio_chunk_id;
+DROP TABLE IF EXISTS media_block_chunks;
@@ -0,0 +1,10 @@
+-- Audio chunks of a media block. They are kept on disk rather than
+-- transcribed, so the music detection mistaking speech for music can be
+-- undone by transcribing them on request.
+CREATE TABLE IF NOT EXISTS media_block_chunks (
+    media_block_id INTEGER NOT NULL REFERENCES media_blocks(id) ON DELETE CASCADE,
+    audio_chunk_id INTEGER NOT NULL REFERENCES audio_chunks(id) ON DELETE CASCADE,
+    PRIMARY KEY (media_block_id, audio_chunk_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_block_chunks_audio_chunk_id ON media_block_chunks(audio_chunk_id);
@@ -0,0 +1,892 @@
+//! Months of recordings moved out of the main database into a file each, so
+//! the main one stays small enough to vacuum and back up. A partition holds
+//! the frames, screen text, transcripts and chunks of a month with what's
+//! tied to them, like tags, layouts and annotations, and is only attached to
+//! a connection while it's queried, dropping it deletes a file.
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use sqlx::pool::PoolConnection;
+use sqlx::sqlite::{SqliteArguments, SqliteRow};
+use sqlx::{Arguments, Connection, Row, Sqlite, SqliteConnection};
+use tracing::{debug, warn};
+
+use crate::db::{after_search_cursor, ocr_result_from_raw};
+use crate::{
+    AudioResultRaw, ContentType, DatabaseManager, DroppedPartition, MediaChunk, OCRResultRaw,
+    Partition, PartitionMatch, PartitionMonth, SearchCursor, SearchResult, SearchResultKind,
+};
+
+/// Frames of the month being moved.
+const MONTH_FRAMES: &str = "SELECT id FROM main.frames WHERE timestamp >= ?1 AND timestamp < ?2";
+/// Transcripts of the month being moved.
+const MONTH_TRANSCRIPTIONS: &str =
+    "SELECT id FROM main.audio_transcriptions WHERE timestamp >= ?1 AND timestamp < ?2";
+
+/// Moves the rows of the month, keeping their ids, table by table. Chunks
+/// recorded to across the end of the month are copied, the main database
+/// keeps them too. Rows already in the partition are skipped: with the main
+/// database in WAL mode a crash may commit the copy without the delete. Tags
+/// and speakers stay in the main database, the partition refers to them.
+const MOVE_TO_PARTITION: [(&str, &str); 15] = [
+    (
+        "video_chunks",
+        "INSERT INTO archived.video_chunks ({columns}) SELECT {columns} FROM main.video_chunks
+         WHERE id IN (SELECT video_chunk_id FROM main.frames WHERE timestamp >= ?1 AND timestamp < ?2)
+            AND id NOT IN (SELECT id FROM archived.video_chunks)",
+    ),
+    (
+        "frames",
+        "INSERT INTO archived.frames ({columns}) SELECT {columns} FROM main.frames
+         WHERE timestamp >= ?1 AND timestamp < ?2
+            AND id NOT IN (SELECT id FROM archived.frames)",
+    ),
+    (
+        "ocr_text",
+        "INSERT INTO archived.ocr_text ({columns}) SELECT {columns} FROM main.ocr_text
+         WHERE frame_id IN ({month_frames})
+            AND frame_id NOT IN (SELECT frame_id FROM archived.ocr_text)",
+    ),
+    (
+        "ocr_blocks",
+        "INSERT INTO archived.ocr_blocks ({columns}) SELECT {columns} FROM main.ocr_blocks
+         WHERE frame_id IN ({month_frames})
+            AND id NOT IN (SELECT id FROM archived.ocr_blocks)",
+    ),
+    (
+        "ocr_tables",
+        "INSERT INTO archived.ocr_tables ({columns}) SELECT {columns} FROM main.ocr_tables
+         WHERE frame_id IN ({month_frames})
+            AND id NOT IN (SELECT id FROM archived.ocr_tables)",
+    ),
+    (
+        "ocr_text_embeddings",
+        "INSERT INTO archived.ocr_text_embeddings ({columns}) SELECT {columns} FROM main.ocr_text_embeddings
+         WHERE frame_id IN ({month_frames})
+            AND id NOT IN (SELECT id FROM archived.ocr_text_embeddings)",
+    ),
+    (
+        "vision_tags",
+        "INSERT INTO archived.vision_tags ({columns}) SELECT {columns} FROM main.vision_tags AS moved
+         WHERE vision_id IN ({month_frames})
+            AND NOT EXISTS (SELECT 1 FROM archived.vision_tags AS copied
+                WHERE copied.vision_id = moved.vision_id AND copied.tag_id = moved.tag_id)",
+    ),
+    (
+        "chunked_text_entries",
+        "INSERT INTO archived.chunked_text_entries ({columns}) SELECT {columns} FROM main.chunked_text_entries AS moved
+         WHERE frame_id IN ({month_frames})
+            AND NOT EXISTS (SELECT 1 FROM archived.chunked_text_entries AS copied
+                WHERE copied.frame_id = moved.frame_id AND copied.text_id = moved.text_id)",
+    ),
+    (
+        "audio_chunks",
+        "INSERT INTO archived.audio_chunks ({columns}) SELECT {columns} FROM main.audio_chunks
+         WHERE id IN (SELECT audio_chunk_id FROM main.audio_transcriptions WHERE timestamp >= ?1 AND timestamp < ?2)
+            AND id NOT IN (SELECT id FROM archived.audio_chunks)",
+    ),
+    (
+        "audio_tags",
+        "INSERT INTO archived.audio_tags ({columns}) SELECT {columns} FROM main.audio_tags AS moved
+         WHERE audio_chunk_id IN (SELECT id FROM archived.audio_chunks)
+            AND NOT EXISTS (SELECT 1 FROM archived.audio_tags AS copied
+                WHERE copied.audio_chunk_id = moved.audio_chunk_id AND copied.tag_id = moved.tag_id)",
+    ),
+    (
+        "audio_transcriptions",
+        "INSERT INTO archived.audio_transcriptions ({columns}) SELECT {columns} FROM main.audio_transcriptions
+         WHERE timestamp >= ?1 AND timestamp < ?2
+            AND id NOT IN (SELECT id FROM archived.audio_transcriptions)",
+    ),
+    (
+        "audio_transcription_embeddings",
+        "INSERT INTO archived.audio_transcription_embeddings ({columns}) SELECT {columns} FROM main.audio_transcription_embeddings
+         WHERE audio_transcription_id IN ({month_transcriptions})
+            AND id NOT IN (SELECT id FROM archived.audio_transcription_embeddings)",
+    ),
+    (
+        "transcript_words",
+        "INSERT INTO archived.transcript_words ({columns}) SELECT {columns} FROM main.transcript_words
+         WHERE audio_transcription_id IN ({month_transcriptions})
+            AND id NOT IN (SELECT id FROM archived.transcript_words)",
+    ),
+    (
+        "annotations",
+        "INSERT INTO archived.annotations ({columns}) SELECT {columns} FROM main.annotations
+         WHERE (frame_id IN ({month_frames}) OR audio_transcription_id IN ({month_transcriptions}))
+            AND id NOT IN (SELECT id FROM archived.annotations)",
+    ),
+    (
+        "annotation_tags",
+        "INSERT INTO archived.annotation_tags ({columns}) SELECT {columns} FROM main.annotation_tags AS moved
+         WHERE annotation_id IN (SELECT id FROM archived.annotations)
+            AND NOT EXISTS (SELECT 1 FROM archived.annotation_tags AS copied
+                WHERE copied.annotation_id = moved.annotation_id AND copied.tag_id = moved.tag_id)",
+    ),
+];
+
+/// Deletes what was moved, the rows tied to a frame or transcript before it.
+const DELETE_FROM_MAIN: [&str; 15] = [
+    "DELETE FROM main.ocr_text WHERE frame_id IN ({month_frames})",
+    "DELETE FROM main.ocr_blocks WHERE frame_id IN ({month_frames})",
+    "DELETE FROM main.ocr_tables WHERE frame_id IN ({month_frames})",
+    "DELETE FROM main.ocr_text_embeddings WHERE frame_id IN ({month_frames})",
+    "DELETE FROM main.vision_tags WHERE vision_id IN ({month_frames})",
+    "DELETE FROM main.chunked_text_entries WHERE frame_id IN ({month_frames})",
+    "DELETE FROM main.annotation_tags WHERE annotation_id IN (SELECT id FROM main.annotations
+        WHERE frame_id IN ({month_frames}) OR audio_transcription_id IN ({month_transcriptions}))",
+    "DELETE FROM main.annotations
+        WHERE frame_id IN ({month_frames}) OR audio_transcription_id IN ({month_transcriptions})",
+    "DELETE FROM main.audio_transcription_embeddings WHERE audio_transcription_id IN ({month_transcriptions})",
+    "DELETE FROM main.transcript_words WHERE audio_transcription_id IN ({month_transcriptions})",
+    "DELETE FROM main.frames WHERE timestamp >= ?1 AND timestamp < ?2",
+    "DELETE FROM main.audio_transcriptions WHERE timestamp >= ?1 AND timestamp < ?2",
+    "DELETE FROM main.video_chunks WHERE id IN (SELECT id FROM archived.video_chunks)
+        AND NOT EXISTS (SELECT 1 FROM main.frames WHERE frames.video_chunk_id = video_chunks.id)",
+    "DELETE FROM main.audio_tags WHERE audio_chunk_id IN (SELECT id FROM archived.audio_chunks)
+        AND NOT EXISTS (SELECT 1 FROM main.audio_transcriptions WHERE audio_transcriptions.audio_chunk_id = audio_tags.audio_chunk_id)",
+    "DELETE FROM main.audio_chunks WHERE id IN (SELECT id FROM archived.audio_chunks)
+        AND NOT EXISTS (SELECT 1 FROM main.audio_transcriptions WHERE audio_transcriptions.audio_chunk_id = audio_chunks.id)",
+];
+
+const PARTITION_INDEXES: [&str; 7] = [
+    "CREATE INDEX IF NOT EXISTS archived.idx_video_chunks_id ON video_chunks(id)",
+    "CREATE INDEX IF NOT EXISTS archived.idx_frames_id ON frames(id)",
+    "CREATE INDEX IF NOT EXISTS archived.idx_frames_timestamp ON frames(timestamp)",
+    "CREATE INDEX IF NOT EXISTS archived.idx_ocr_text_frame_id ON ocr_text(frame_id)",
+    "CREATE INDEX IF NOT EXISTS archived.idx_vision_tags_vision_id ON vision_tags(vision_id)",
+    "CREATE INDEX IF NOT EXISTS archived.idx_audio_chunks_id ON audio_chunks(id)",
+    "CREATE INDEX IF NOT EXISTS archived.idx_audio_transcriptions_timestamp ON audio_transcriptions(timestamp)",
+];
+
+const SEARCH_PARTITION: &str = r#"
+SELECT * FROM (
+    SELECT
+        ?5 AS month,
+        'ocr' AS kind,
+        ocr_text.frame_id AS id,
+        frames.timestamp AS timestamp,
+        ocr_text.text AS text,
+        frames.app_name AS app_name,
+        frames.window_name AS window_name,
+        frames.device_name AS device_name
+    FROM archived.ocr_text
+    JOIN archived.frames ON ocr_text.frame_id = frames.id
+    WHERE ocr_text.text LIKE '%' || ?1 || '%'
+        AND (?2 IS NULL OR frames.timestamp >= ?2)
+        AND (?3 IS NULL OR frames.timestamp <= ?3)
+    UNION ALL
+    SELECT
+        ?5,
+        'audio',
+        id,
+        timestamp,
+        transcription,
+        NULL,
+        NULL,
+        device
+    FROM archived.audio_transcriptions
+    WHERE transcription LIKE '%' || ?1 || '%'
+        AND (?2 IS NULL OR timestamp >= ?2)
+        AND (?3 IS NULL OR timestamp <= ?3)
+)
+ORDER BY timestamp DESC, id DESC
+LIMIT ?4
+"#;
+
+/// Columns of screen text found in a partition, as [`OCRResultRaw`].
+const ARCHIVED_OCR_COLUMNS: &str = r#"
+SELECT
+    ocr_text.frame_id,
+    ocr_text.text AS ocr_text,
+    ocr_text.text_json,
+    frames.timestamp,
+    frames.name AS frame_name,
+    video_chunks.file_path,
+    frames.offset_index,
+    frames.app_name,
+    ocr_text.ocr_engine,
+    frames.window_name,
+    video_chunks.device_name,
+    (SELECT GROUP_CONCAT(tags.name, ',') FROM archived.vision_tags
+        JOIN main.tags ON vision_tags.tag_id = tags.id
+        WHERE vision_tags.vision_id = frames.id) AS tags,
+    frames.browser_url,
+    frames.browser_context,
+    frames.focused
+"#;
+
+/// Screen text of the attached partition matching a search: `?1` is part of
+/// the text, `?2` and `?3` bound the time range, `?4` and `?5` are part of
+/// the app and window names, `?6` and `?7` bound the length of the text.
+/// All but `?1` may be NULL.
+const ARCHIVED_OCR: &str = r#"
+FROM archived.frames
+JOIN archived.video_chunks ON frames.video_chunk_id = video_chunks.id
+JOIN archived.ocr_text ON frames.id = ocr_text.frame_id
+WHERE ocr_text.text LIKE '%' || ?1 || '%'
+    AND (?2 IS NULL OR frames.timestamp >= ?2)
+    AND (?3 IS NULL OR frames.timestamp <= ?3)
+    AND (?4 IS NULL OR frames.app_name LIKE '%' || ?4 || '%')
+    AND (?5 IS NULL OR frames.window_name LIKE '%' || ?5 || '%')
+    AND (?6 IS NULL OR LENGTH(ocr_text.text) >= ?6)
+    AND (?7 IS NULL OR LENGTH(ocr_text.text) <= ?7)
+"#;
+
+/// Columns of a transcript found in a partition, as [`AudioResultRaw`].
+const ARCHIVED_AUDIO_COLUMNS: &str = r#"
+SELECT
+    audio_transcriptions.id AS transcription_id,
+    audio_transcriptions.audio_chunk_id,
+    audio_transcriptions.transcription,
+    audio_transcriptions.timestamp,
+    audio_chunks.file_path,
+    audio_transcriptions.offset_index,
+    audio_transcriptions.transcription_engine,
+    (SELECT GROUP_CONCAT(tags.name, ',') FROM archived.audio_tags
+        JOIN main.tags ON audio_tags.tag_id = tags.id
+        WHERE audio_tags.audio_chunk_id = audio_chunks.id) AS tags,
+    audio_transcriptions.device AS device_name,
+    audio_transcriptions.is_input_device,
+    audio_transcriptions.speaker_id,
+    audio_transcriptions.start_time,
+    audio_transcriptions.end_time,
+    audio_transcriptions.language,
+    audio_transcriptions.translated_text
+"#;
+
+/// Transcripts of the attached partition matching a search, with the
+/// parameters of [`ARCHIVED_OCR`], `?8` a JSON array of speaker ids and
+/// `?9` a language, either may be NULL.
+const ARCHIVED_AUDIO: &str = r#"
+FROM archived.audio_transcriptions
+JOIN archived.audio_chunks ON audio_transcriptions.audio_chunk_id = audio_chunks.id
+LEFT JOIN main.speakers ON audio_transcriptions.speaker_id = speakers.id
+WHERE audio_transcriptions.transcription LIKE '%' || ?1 || '%'
+    AND (?2 IS NULL OR audio_transcriptions.timestamp >= ?2)
+    AND (?3 IS NULL OR audio_transcriptions.timestamp <= ?3)
+    AND (?6 IS NULL OR LENGTH(audio_transcriptions.transcription) >= ?6)
+    AND (?7 IS NULL OR LENGTH(audio_transcriptions.transcription) <= ?7)
+    AND (speakers.id IS NULL OR speakers.hallucination = 0)
+    AND (?8 IS NULL OR audio_transcriptions.speaker_id IN (SELECT value FROM json_each(?8)))
+    AND (?9 IS NULL OR audio_transcriptions.language = ?9)
+"#;
+
+/// Attaches the partition as `archived`, creating the tables a partition
+/// archived before they were moved lacks, so it's queried like a new one.
+pub(crate) async fn attach_partition(
+    conn: &mut SqliteConnection,
+    file_path: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("ATTACH DATABASE ?1 AS archived")
+        .bind(file_path)
+        .execute(&mut *conn)
+        .await?;
+    // without constraints, the rows keep their ids from the main database
+    for (table, _) in MOVE_TO_PARTITION {
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS archived.{table} AS SELECT * FROM main.{table} WHERE 0"
+        ))
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Connections go back to the pool, the partition mustn't stay attached:
+/// one it can't be detached from is closed instead.
+pub(crate) async fn detach_partition(mut conn: PoolConnection<Sqlite>) {
+    if let Err(e) = sqlx::query("DETACH DATABASE archived")
+        .execute(&mut *conn)
+        .await
+    {
+        warn!("failed to detach partition, closing the connection: {}", e);
+        if let Err(e) = conn.detach().close().await {
+            warn!("failed to close connection: {}", e);
+        }
+    }
+}
+
+/// Quoted columns of the partition's copy of `table`. Columns added to the
+/// main table after the partition was created are left out.
+async fn partition_columns(
+    conn: &mut SqliteConnection,
+    table: &str,
+) -> Result<String, sqlx::Error> {
+    let rows = sqlx::query(&format!("PRAGMA archived.table_info({})", table))
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| format!("\"{}\"", row.get::<String, _>("name")))
+        .collect::<Vec<_>>()
+        .join(", "))
+}
+
+/// `sql` with the frames and transcripts of the month substituted.
+fn month_sql(sql: &str) -> String {
+    sql.replace("{month_frames}", MONTH_FRAMES)
+        .replace("{month_transcriptions}", MONTH_TRANSCRIPTIONS)
+}
+
+async fn move_month(
+    conn: &mut SqliteConnection,
+    month: PartitionMonth,
+    file_path: &str,
+) -> Result<Partition, sqlx::Error> {
+    for sql in PARTITION_INDEXES {
+        sqlx::query(sql).execute(&mut *conn).await?;
+    }
+    let mut moves = Vec::with_capacity(MOVE_TO_PARTITION.len());
+    for (table, sql) in MOVE_TO_PARTITION {
+        let columns = partition_columns(conn, table).await?;
+        moves.push(month_sql(sql).replace("{columns}", &columns));
+    }
+
+    let mut tx = conn.begin().await?;
+    for sql in &moves {
+        sqlx::query(sql)
+            .bind(month.start())
+            .bind(month.end())
+            .execute(&mut *tx)
+            .await?;
+    }
+    for sql in DELETE_FROM_MAIN {
+        sqlx::query(&month_sql(sql))
+            .bind(month.start())
+            .bind(month.end())
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let (frames, transcriptions): (i64, i64) = sqlx::query_as(
+        "SELECT (SELECT COUNT(*) FROM archived.frames), (SELECT COUNT(*) FROM archived.audio_transcriptions)",
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let partition = Partition {
+        month: month.to_string(),
+        file_path: file_path.to_string(),
+        frames,
+        transcriptions,
+        archived_at: Utc::now(),
+    };
+    sqlx::query(
+        r#"
+        INSERT INTO main.partitions (month, file_path, frames, transcriptions, archived_at)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT(month) DO UPDATE SET
+            file_path = excluded.file_path,
+            frames = excluded.frames,
+            transcriptions = excluded.transcriptions,
+            archived_at = excluded.archived_at
+        "#,
+    )
+    .bind(&partition.month)
+    .bind(&partition.file_path)
+    .bind(partition.frames)
+    .bind(partition.transcriptions)
+    .bind(partition.archived_at)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(partition)
+}
+
+/// Parameters `?1` to `?9` of [`ARCHIVED_OCR`] and [`ARCHIVED_AUDIO`].
+#[allow(clippy::too_many_arguments)]
+fn archived_search_arguments<'q>(
+    query: &'q str,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    app_name: Option<&'q str>,
+    window_name: Option<&'q str>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    speaker_ids: Option<&[i64]>,
+    language: Option<&'q str>,
+) -> SqliteArguments<'q> {
+    let mut arguments = SqliteArguments::default();
+    arguments.add(query);
+    arguments.add(start_time);
+    arguments.add(end_time);
+    arguments.add(app_name);
+    arguments.add(window_name);
+    arguments.add(min_length.map(|l| l as i64));
+    arguments.add(max_length.map(|l| l as i64));
+    // an empty list of speakers doesn't filter, as in the main database
+    arguments.add(
+        speaker_ids
+            .filter(|ids| !ids.is_empty())
+            .map(|ids| serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string())),
+    );
+    arguments.add(language);
+    arguments
+}
+
+/// Whether a search for `content_type` includes screen text and transcripts.
+fn archived_kinds(content_type: &ContentType) -> (bool, bool) {
+    match content_type {
+        ContentType::All | ContentType::AudioAndOcr => (true, true),
+        ContentType::OCR | ContentType::OcrAndUi => (true, false),
+        ContentType::Audio | ContentType::AudioAndUi => (false, true),
+        _ => (false, false),
+    }
+}
+
+impl DatabaseManager {
+    /// Moves the recordings of the month to `<dir>/<month>.sqlite`, adding
+    /// to the partition if the month was archived before. `None` when the
+    /// main database has nothing of the month. The rows tied to the moved
+    /// frames and transcripts, like tags, layouts and annotations, move with
+    /// them.
+    pub async fn archive_partition(
+        &self,
+        month: PartitionMonth,
+        dir: &Path,
+    ) -> Result<Option<Partition>, sqlx::Error> {
+        let recorded: bool = sqlx::query_scalar(
+            r#"SELECT EXISTS (SELECT 1 FROM frames WHERE timestamp >= ?1 AND timestamp < ?2)
+                OR EXISTS (SELECT 1 FROM audio_transcriptions WHERE timestamp >= ?1 AND timestamp < ?2)"#,
+        )
+        .bind(month.start())
+        .bind(month.end())
+        .fetch_one(&self.pool)
+        .await?;
+        if !recorded {
+            return Ok(None);
+        }
+
+        std::fs::create_dir_all(dir).map_err(sqlx::Error::Io)?;
+        let file_path = dir
+            .join(format!("{}.sqlite", month))
+            .to_string_lossy()
+            .to_string();
+        let mut conn = self.pool.acquire().await?;
+        attach_partition(&mut conn, &file_path).await?;
+        let partition = move_month(&mut conn, month, &file_path).await;
+        detach_partition(conn).await;
+        let partition = partition?;
+        debug!(
+            "archived {} to {}: {} frames, {} transcriptions",
+            month, file_path, partition.frames, partition.transcriptions
+        );
+        Ok(Some(partition))
+    }
+
+    /// Partitions, oldest month first.
+    pub async fn list_partitions(&self) -> Result<Vec<Partition>, sqlx::Error> {
+        sqlx::query_as("SELECT * FROM partitions ORDER BY month ASC")
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Partitions overlapping the time range whose file exists, newest month
+    /// first.
+    async fn overlapping_partitions(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Partition>, sqlx::Error> {
+        let mut partitions = self.list_partitions().await?;
+        partitions.retain(|partition| {
+            let overlaps = partition
+                .month
+                .parse::<PartitionMonth>()
+                .is_ok_and(|month| month.overlaps(start_time, end_time));
+            let exists = Path::new(&partition.file_path).exists();
+            if overlaps && !exists {
+                warn!(
+                    "partition {} is missing: {}",
+                    partition.month, partition.file_path
+                );
+            }
+            overlaps && exists
+        });
+        partitions.reverse();
+        Ok(partitions)
+    }
+
+    /// Month of the oldest frame or transcript still in the main database.
+    pub async fn oldest_unpartitioned_month(&self) -> Result<Option<PartitionMonth>, sqlx::Error> {
+        let oldest: Option<DateTime<Utc>> = sqlx::query_scalar(
+            r#"SELECT MIN(timestamp) FROM (
+                SELECT MIN(timestamp) AS timestamp FROM frames
+                UNION ALL
+                SELECT MIN(timestamp) FROM audio_transcriptions
+            )"#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(oldest.map(PartitionMonth::of))
+    }
+
+    /// Screen text and transcripts containing `query` in the partitions
+    /// overlapping the time range, newest first. Partitions are attached one
+    /// at a time, SQLite only attaches a few databases at once.
+    pub async fn search_partitions(
+        &self,
+        query: &str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: u32,
+    ) -> Result<Vec<PartitionMatch>, sqlx::Error> {
+        let mut matches = Vec::new();
+        // months don't overlap, newer ones are searched first
+        for partition in self.overlapping_partitions(start_time, end_time).await? {
+            if matches.len() >= limit as usize {
+                break;
+            }
+            let mut conn = self.pool.acquire().await?;
+            attach_partition(&mut conn, &partition.file_path).await?;
+            let found: Result<Vec<PartitionMatch>, sqlx::Error> = sqlx::query_as(SEARCH_PARTITION)
+                .bind(query)
+                .bind(start_time)
+                .bind(end_time)
+                .bind(limit - matches.len() as u32)
+                .bind(&partition.month)
+                .fetch_all(&mut *conn)
+                .await;
+            detach_partition(conn).await;
+            matches.extend(found?);
+        }
+        Ok(matches)
+    }
+
+    /// Screen text and transcripts of the partitions matching a search, as
+    /// `/search` returns them, newest first. Partitions have no full text
+    /// index: `query` matches text containing it. The results come after
+    /// `cursor`, or after the first `offset` ones.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_archived(
+        &self,
+        query: &str,
+        content_type: ContentType,
+        limit: u32,
+        offset: u32,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<&[i64]>,
+        language: Option<&str>,
+        cursor: Option<&SearchCursor>,
+    ) -> Result<Vec<SearchResult>, sqlx::Error> {
+        let (ocr, audio) = archived_kinds(&content_type);
+        // transcripts have no app or window
+        let audio = audio && app_name.is_none() && window_name.is_none();
+        let arguments = archived_search_arguments(
+            query,
+            start_time,
+            end_time,
+            app_name,
+            window_name,
+            min_length,
+            max_length,
+            speaker_ids,
+            language,
+        );
+        let ocr_sql = format!(
+            "{} {} AND {} ORDER BY frames.timestamp DESC, frames.id DESC LIMIT ?10",
+            ARCHIVED_OCR_COLUMNS,
+            ARCHIVED_OCR,
+            after_search_cursor("frames.timestamp", "frames.id", "?11", "?12")
+        );
+        let audio_sql = format!(
+            "{} {} AND {} ORDER BY audio_transcriptions.timestamp DESC, audio_transcriptions.id DESC LIMIT ?10",
+            ARCHIVED_AUDIO_COLUMNS,
+            ARCHIVED_AUDIO,
+            after_search_cursor(
+                "audio_transcriptions.timestamp",
+                "audio_transcriptions.id",
+                "?11",
+                "?12"
+            )
+        );
+        let page_end = offset as usize + limit as usize;
+
+        let mut results = Vec::new();
+        // months don't overlap, the results of newer ones come first
+        for partition in self.overlapping_partitions(start_time, end_time).await? {
+            let wanted = page_end.saturating_sub(results.len());
+            if wanted == 0 {
+                break;
+            }
+            let with_page = |kind: SearchResultKind| {
+                let mut arguments = arguments.clone();
+                arguments.add(wanted as i64);
+                arguments.add(cursor.map(|cursor| cursor.timestamp));
+                arguments.add(cursor.map(|cursor| cursor.id_bound(kind)));
+                arguments
+            };
+
+            let mut conn = self.pool.acquire().await?;
+            attach_partition(&mut conn, &partition.file_path).await?;
+            let found = async {
+                let frames: Vec<OCRResultRaw> = if ocr {
+                    sqlx::query_as_with(&ocr_sql, with_page(SearchResultKind::Ocr))
+                        .fetch_all(&mut *conn)
+                        .await?
+                } else {
+                    Vec::new()
+                };
+                let transcripts: Vec<AudioResultRaw> = if audio {
+                    sqlx::query_as_with(&audio_sql, with_page(SearchResultKind::Audio))
+                        .fetch_all(&mut *conn)
+                        .await?
+                } else {
+                    Vec::new()
+                };
+                Ok::<_, sqlx::Error>((frames, transcripts))
+            }
+            .await;
+            detach_partition(conn).await;
+            let (frames, transcripts) = found?;
+
+            let mut month: Vec<SearchResult> = frames
+                .into_iter()
+                .map(|raw| SearchResult::OCR(ocr_result_from_raw(raw)))
+                .collect();
+            month.extend(
+                join_all(
+                    transcripts
+                        .into_iter()
+                        .map(|raw| self.audio_result_from_raw(raw)),
+                )
+                .await
+                .into_iter()
+                .map(SearchResult::Audio),
+            );
+            month.sort_by(|a, b| {
+                b.timestamp()
+                    .cmp(&a.timestamp())
+                    .then_with(|| a.kind().cmp(&b.kind()))
+                    .then_with(|| b.cursor().id.cmp(&a.cursor().id))
+            });
+            month.truncate(wanted);
+            results.extend(month);
+        }
+        Ok(results
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect())
+    }
+
+    /// Number of results of [`Self::search_archived`] with the same filters,
+    /// over all pages.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn count_archived(
+        &self,
+        query: &str,
+        content_type: ContentType,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<&[i64]>,
+        language: Option<&str>,
+    ) -> Result<usize, sqlx::Error> {
+        let (ocr, audio) = archived_kinds(&content_type);
+        let audio = audio && app_name.is_none() && window_name.is_none();
+        let arguments = archived_search_arguments(
+            query,
+            start_time,
+            end_time,
+            app_name,
+            window_name,
+            min_length,
+            max_length,
+            speaker_ids,
+            language,
+        );
+        let mut counts = Vec::new();
+        if ocr {
+            counts.push(format!("(SELECT COUNT(*) {})", ARCHIVED_OCR));
+        }
+        if audio {
+            counts.push(format!("(SELECT COUNT(*) {})", ARCHIVED_AUDIO));
+        }
+        if counts.is_empty() {
+            return Ok(0);
+        }
+        let sql = format!("SELECT {}", counts.join(" + "));
+
+        let mut total = 0;
+        for partition in self.overlapping_partitions(start_time, end_time).await? {
+            let mut conn = self.pool.acquire().await?;
+            attach_partition(&mut conn, &partition.file_path).await?;
+            let found: Result<i64, sqlx::Error> = sqlx::query_scalar_with(&sql, arguments.clone())
+                .fetch_one(&mut *conn)
+                .await;
+            detach_partition(conn).await;
+            total += found? as usize;
+        }
+        Ok(total)
+    }
+
+    /// Video chunk file and offset of a frame moved to a partition, newer
+    /// partitions first. See [`Self::get_frame`].
+    pub(crate) async fn get_archived_frame(
+        &self,
+        frame_id: i64,
+    ) -> Result<Option<(String, i64)>, sqlx::Error> {
+        for partition in self.overlapping_partitions(None, None).await? {
+            let mut conn = self.pool.acquire().await?;
+            attach_partition(&mut conn, &partition.file_path).await?;
+            let found: Result<Option<(String, i64)>, sqlx::Error> = sqlx::query_as(
+                r#"
+                SELECT video_chunks.file_path, frames.offset_index
+                FROM archived.frames
+                JOIN archived.video_chunks ON frames.video_chunk_id = video_chunks.id
+                WHERE frames.id = ?1
+                "#,
+            )
+            .bind(frame_id)
+            .fetch_optional(&mut *conn)
+            .await;
+            detach_partition(conn).await;
+            if let Some(frame) = found? {
+                return Ok(Some(frame));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Rows of each query on the partitions overlapping the time range,
+    /// newer partitions first. `{schema}` in a query names the partition,
+    /// `?1` and `?2` are the start and end of the range.
+    pub(crate) async fn query_partitions(
+        &self,
+        queries: &[&str],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Vec<SqliteRow>>, sqlx::Error> {
+        let mut rows: Vec<Vec<SqliteRow>> = queries.iter().map(|_| Vec::new()).collect();
+        for partition in self.overlapping_partitions(Some(start), Some(end)).await? {
+            let mut conn = self.pool.acquire().await?;
+            attach_partition(&mut conn, &partition.file_path).await?;
+            let found = async {
+                for (query, rows) in queries.iter().zip(rows.iter_mut()) {
+                    rows.extend(
+                        sqlx::query(&query.replace("{schema}", "archived"))
+                            .bind(start)
+                            .bind(end)
+                            .fetch_all(&mut *conn)
+                            .await?,
+                    );
+                }
+                Ok::<_, sqlx::Error>(())
+            }
+            .await;
+            detach_partition(conn).await;
+            found?;
+        }
+        Ok(rows)
+    }
+
+    /// Media files of the video and audio chunks kept in partitions.
+    pub async fn partition_media_paths(&self) -> Result<Vec<String>, sqlx::Error> {
+        let mut paths = Vec::new();
+        for partition in self.list_partitions().await? {
+            if !Path::new(&partition.file_path).exists() {
+                continue;
+            }
+            let mut conn = self.pool.acquire().await?;
+            attach_partition(&mut conn, &partition.file_path).await?;
+            let found: Result<Vec<String>, sqlx::Error> = sqlx::query_scalar(
+                "SELECT file_path FROM archived.video_chunks UNION SELECT file_path FROM archived.audio_chunks",
+            )
+            .fetch_all(&mut *conn)
+            .await;
+            detach_partition(conn).await;
+            paths.extend(found?);
+        }
+        Ok(paths)
+    }
+
+    /// Forgets the partition of the month and deletes its file. `None` if
+    /// the month isn't partitioned.
+    pub async fn drop_partition(
+        &self,
+        month: PartitionMonth,
+    ) -> Result<Option<DroppedPartition>, sqlx::Error> {
+        let partition: Option<Partition> =
+            sqlx::query_as("SELECT * FROM partitions WHERE month = ?1")
+                .bind(month.to_string())
+                .fetch_optional(&self.pool)
+                .await?;
+        let Some(partition) = partition else {
+            return Ok(None);
+        };
+
+        let mut video_chunks = Vec::new();
+        let mut audio_chunks = Vec::new();
+        if Path::new(&partition.file_path).exists() {
+            let mut conn = self.pool.acquire().await?;
+            attach_partition(&mut conn, &partition.file_path).await?;
+            let chunks = partition_media_chunks(&mut conn, month).await;
+            detach_partition(conn).await;
+            (video_chunks, audio_chunks) = chunks?;
+        }
+
+        sqlx::query("DELETE FROM partitions WHERE month = ?1")
+            .bind(&partition.month)
+            .execute(&self.pool)
+            .await?;
+        for suffix in ["", "-wal", "-shm"] {
+            let path = format!("{}{}", partition.file_path, suffix);
+            match std::fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => warn!("failed to delete partition file {}: {}", path, e),
+            }
+        }
+        debug!("dropped partition {}", partition.month);
+        Ok(Some(DroppedPartition {
+            partition,
+            video_chunks,
+            audio_chunks,
+        }))
+    }
+}
+
+/// Video and audio chunks of the attached partition the main database
+/// doesn't refer to.
+async fn partition_media_chunks(
+    conn: &mut SqliteConnection,
+    month: PartitionMonth,
+) -> Result<(Vec<MediaChunk>, Vec<MediaChunk>), sqlx::Error> {
+    let video_chunks = sqlx::query_as(
+        r#"
+        SELECT
+            id,
+            file_path,
+            COALESCE(
+                (SELECT MAX(timestamp) FROM archived.frames WHERE frames.video_chunk_id = video_chunks.id),
+                ?1
+            ) AS timestamp
+        FROM archived.video_chunks
+        WHERE id NOT IN (SELECT id FROM main.video_chunks)
+        "#,
+    )
+    .bind(month.end())
+    .fetch_all(&mut *conn)
+    .await?;
+    let audio_chunks = sqlx::query_as(
+        r#"
+        SELECT id, file_path, timestamp
+        FROM archived.audio_chunks
+        WHERE id NOT IN (SELECT id FROM main.audio_chunks)
+        "#,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok((video_chunks, audio_chunks))
+}
@@ -0,0 +1,286 @@
+//! Versioned schema migrations, the SQL files of `src/migrations`. Each one
+//! is applied once, in its own transaction, and its checksum is recorded so
+//! that a migration edited after it ran is refused instead of leaving the
+//! schema half way. Migrations with a `.down.sql` counterpart can be rolled
+//! back. The database is copied next to itself before pending migrations
+//! touch it.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, SqliteConnection};
+use tracing::{info, warn};
+
+use crate::db::{backup_database, sqlcipher_key};
+use crate::{MigrationReport, SchemaMigration, SchemaMigrationState};
+
+pub(crate) fn migrator() -> Migrator {
+    let mut migrator = sqlx::migrate!("./src/migrations");
+    // a database migrated by a newer version still opens after a downgrade
+    migrator.set_ignore_missing(true);
+    migrator
+}
+
+#[derive(sqlx::FromRow)]
+struct AppliedMigration {
+    version: i64,
+    description: String,
+    installed_on: DateTime<Utc>,
+    success: bool,
+    checksum: Vec<u8>,
+}
+
+async fn applied_migrations(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<AppliedMigration>, sqlx::Error> {
+    // a new database has no migrations table yet
+    let migrated: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if !migrated {
+        return Ok(Vec::new());
+    }
+    sqlx::query_as(
+        "SELECT version, description, installed_on, success, checksum FROM _sqlx_migrations ORDER BY version",
+    )
+    .fetch_all(&mut *conn)
+    .await
+}
+
+/// Every migration of this version of screenpipe and every one applied to
+/// the database, oldest first.
+pub async fn schema_status(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<SchemaMigration>, sqlx::Error> {
+    let migrator = migrator();
+    let mut applied = applied_migrations(conn).await?;
+    let mut status = Vec::new();
+
+    for migration in migrator
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+    {
+        let found = applied
+            .iter()
+            .position(|applied| applied.version == migration.version)
+            .map(|position| applied.remove(position));
+        let state = match &found {
+            None => SchemaMigrationState::Pending,
+            Some(found) if !found.success => SchemaMigrationState::Failed,
+            Some(found) if found.checksum != *migration.checksum => SchemaMigrationState::Modified,
+            Some(_) => SchemaMigrationState::Applied,
+        };
+        status.push(SchemaMigration {
+            version: migration.version,
+            description: migration.description.to_string(),
+            state,
+            reversible: migrator.iter().any(|down| {
+                down.version == migration.version && down.migration_type.is_down_migration()
+            }),
+            applied_at: found.map(|found| found.installed_on),
+        });
+    }
+    status.extend(applied.into_iter().map(|applied| SchemaMigration {
+        version: applied.version,
+        description: applied.description,
+        state: SchemaMigrationState::Unknown,
+        reversible: false,
+        applied_at: Some(applied.installed_on),
+    }));
+    status.sort_by_key(|migration| migration.version);
+    Ok(status)
+}
+
+/// Fails on migrations that left the database in a state no migration can
+/// safely be applied on.
+fn check_applied(status: &[SchemaMigration]) -> Result<(), anyhow::Error> {
+    for migration in status {
+        match migration.state {
+            SchemaMigrationState::Failed => anyhow::bail!(
+                "migration {} ({}) didn't complete, restore the database from a backup",
+                migration.version,
+                migration.description
+            ),
+            SchemaMigrationState::Modified => anyhow::bail!(
+                "migration {} ({}) changed since it was applied",
+                migration.version,
+                migration.description
+            ),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Copies the database at `database_path` next to it as
+/// `<name>.pre-migration-<version>`, `version` being the latest migration
+/// applied, and removes the copies made before earlier migrations.
+pub(crate) async fn backup_before_migrating(
+    conn: &mut SqliteConnection,
+    database_path: &Path,
+    key: Option<&str>,
+    version: i64,
+) -> Result<PathBuf, anyhow::Error> {
+    let file_name = database_path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("{} isn't a file", database_path.display()))?
+        .to_string_lossy()
+        .to_string();
+    let prefix = format!("{}.pre-migration-", file_name);
+    let dest = database_path.with_file_name(format!("{}{}", prefix, version));
+    match std::fs::remove_file(&dest) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+
+    let mut handle = conn.lock_handle().await?;
+    let source = handle.as_raw_handle().as_ptr();
+    // SAFETY: the source connection is locked for the whole copy and the
+    // destination is opened and closed by backup_database
+    unsafe { backup_database(source, &dest, key)? };
+    drop(handle);
+    info!(
+        "backed up the database to {} before migrating",
+        dest.display()
+    );
+
+    let dir = dest.parent().unwrap_or(Path::new("."));
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with(&prefix) && entry.path() != dest {
+            if let Err(e) = std::fs::remove_file(entry.path()) {
+                warn!("failed to remove {}: {}", entry.path().display(), e);
+            }
+        }
+    }
+    Ok(dest)
+}
+
+/// Latest migration applied, when some are and others are pending: the
+/// database then holds data worth backing up before migrating it.
+pub(crate) fn backup_version(status: &[SchemaMigration]) -> Option<i64> {
+    let pending = status
+        .iter()
+        .any(|migration| migration.state == SchemaMigrationState::Pending);
+    status
+        .iter()
+        .filter(|migration| migration.state == SchemaMigrationState::Applied)
+        .map(|migration| migration.version)
+        .max()
+        .filter(|_| pending)
+}
+
+async fn connect(database_path: &Path, key: Option<&str>) -> Result<SqliteConnection, sqlx::Error> {
+    let mut options = SqliteConnectOptions::new().filename(database_path);
+    if let Some(key) = key {
+        options = options.pragma("key", sqlcipher_key(key));
+    }
+    options.connect().await
+}
+
+/// Schema migrations of the database at `database_path`, without migrating
+/// it.
+pub async fn database_schema_status(
+    database_path: &Path,
+    key: Option<&str>,
+) -> Result<Vec<SchemaMigration>, anyhow::Error> {
+    let mut conn = connect(database_path, key).await?;
+    let status = schema_status(&mut conn).await;
+    conn.close().await?;
+    Ok(status?)
+}
+
+/// Applies the pending migrations to the database at `database_path`,
+/// backing it up first. With `dry_run` nothing is changed and the report
+/// lists what would be applied.
+pub async fn migrate_database(
+    database_path: &Path,
+    key: Option<&str>,
+    dry_run: bool,
+) -> Result<MigrationReport, anyhow::Error> {
+    let mut conn = connect(database_path, key).await?;
+    let status = schema_status(&mut conn).await?;
+    check_applied(&status)?;
+
+    let mut report = MigrationReport {
+        dry_run,
+        migrations: status
+            .iter()
+            .filter(|migration| migration.state == SchemaMigrationState::Pending)
+            .cloned()
+            .collect(),
+        backup: None,
+    };
+    if dry_run || report.migrations.is_empty() {
+        conn.close().await?;
+        return Ok(report);
+    }
+
+    if let Some(version) = backup_version(&status) {
+        report.backup =
+            Some(backup_before_migrating(&mut conn, database_path, key, version).await?);
+    }
+    migrator().run(&mut conn).await?;
+    conn.close().await?;
+    Ok(report)
+}
+
+/// Rolls the database at `database_path` back to migration `version` with
+/// the down migrations of the ones applied after it, newest first, backing
+/// it up first. Fails without changing anything when one of them has no down
+/// migration. With `dry_run` the report lists what would be rolled back.
+pub async fn rollback_database(
+    database_path: &Path,
+    key: Option<&str>,
+    version: i64,
+    dry_run: bool,
+) -> Result<MigrationReport, anyhow::Error> {
+    let mut conn = connect(database_path, key).await?;
+    let status = schema_status(&mut conn).await?;
+    check_applied(&status)?;
+
+    let mut rolled_back: Vec<SchemaMigration> = status
+        .iter()
+        .filter(|migration| {
+            migration.version > version && migration.state != SchemaMigrationState::Pending
+        })
+        .cloned()
+        .collect();
+    rolled_back.reverse();
+    for migration in &rolled_back {
+        if migration.state == SchemaMigrationState::Unknown {
+            anyhow::bail!(
+                "migration {} ({}) was applied by a newer version of screenpipe, roll back with it",
+                migration.version,
+                migration.description
+            );
+        }
+        if !migration.reversible {
+            anyhow::bail!(
+                "migration {} ({}) can't be rolled back",
+                migration.version,
+                migration.description
+            );
+        }
+    }
+
+    let mut report = MigrationReport {
+        dry_run,
+        migrations: rolled_back,
+        backup: None,
+    };
+    if dry_run || report.migrations.is_empty() {
+        conn.close().await?;
+        return Ok(report);
+    }
+
+    let latest = report.migrations[0].version;
+    report.backup = Some(backup_before_migrating(&mut conn, database_path, key, latest).await?);
+    migrator().undo(&mut conn, version).await?;
+    conn.close().await?;
+    Ok(report)
+}
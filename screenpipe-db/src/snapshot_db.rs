@@ -0,0 +1,135 @@
+//! Changes made to the copy of a database that goes into a snapshot of the
+//! instance, and to the database restored from one on another machine.
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use tracing::{debug, warn};
+
+use crate::partition_db::{attach_partition, detach_partition};
+use crate::{DatabaseManager, PartitionMonth};
+
+/// Deletes the recordings outside the time range: `?1` is its start, `?2`
+/// its end, both included, either may be NULL.
+const DELETE_OUTSIDE_RANGE: [&str; 8] = [
+    "DELETE FROM ocr_text WHERE frame_id IN (SELECT id FROM frames WHERE (?1 IS NOT NULL AND timestamp < ?1) OR (?2 IS NOT NULL AND timestamp > ?2))",
+    "DELETE FROM chunked_text_entries WHERE frame_id IN (SELECT id FROM frames WHERE (?1 IS NOT NULL AND timestamp < ?1) OR (?2 IS NOT NULL AND timestamp > ?2))",
+    "DELETE FROM frames WHERE (?1 IS NOT NULL AND timestamp < ?1) OR (?2 IS NOT NULL AND timestamp > ?2)",
+    "DELETE FROM audio_transcriptions WHERE (?1 IS NOT NULL AND timestamp < ?1) OR (?2 IS NOT NULL AND timestamp > ?2)",
+    "DELETE FROM video_chunks WHERE NOT EXISTS (SELECT 1 FROM frames WHERE frames.video_chunk_id = video_chunks.id)",
+    "DELETE FROM audio_chunks WHERE NOT EXISTS (SELECT 1 FROM audio_transcriptions WHERE audio_transcriptions.audio_chunk_id = audio_chunks.id)",
+    "DELETE FROM ui_monitoring WHERE (?1 IS NOT NULL AND timestamp < ?1) OR (?2 IS NOT NULL AND timestamp > ?2)",
+    "DELETE FROM focus_events WHERE (?1 IS NOT NULL AND end_time < ?1) OR (?2 IS NOT NULL AND start_time > ?2)",
+];
+
+/// Columns holding the path of a file in the data directory.
+const DATA_DIR_PATHS: [(&str, &str); 3] = [
+    ("video_chunks", "file_path"),
+    ("audio_chunks", "file_path"),
+    ("frames", "name"),
+];
+
+impl DatabaseManager {
+    /// Deletes the frames, transcripts, chunks, UI monitoring entries and
+    /// focus events outside the time range, and forgets the partitions of
+    /// the months outside it. Returns the number of rows deleted.
+    pub async fn delete_outside_time_range(
+        &self,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut deleted = 0;
+        for sql in DELETE_OUTSIDE_RANGE {
+            deleted += sqlx::query(sql)
+                .bind(start)
+                .bind(end)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+        tx.commit().await?;
+
+        for partition in self.list_partitions().await? {
+            let overlaps = partition
+                .month
+                .parse::<PartitionMonth>()
+                .is_ok_and(|month| month.overlaps(start, end));
+            if !overlaps {
+                sqlx::query("DELETE FROM partitions WHERE month = ?1")
+                    .bind(&partition.month)
+                    .execute(&self.pool)
+                    .await?;
+                deleted += 1;
+            }
+        }
+        debug!("deleted {} rows outside {:?} - {:?}", deleted, start, end);
+        Ok(deleted)
+    }
+
+    /// Points the paths of chunk files and partitions under `from` to the
+    /// same files under `to`, once the data directory moved, e.g. to another
+    /// machine. Returns the number of rows changed.
+    pub async fn relocate_data_dir(&self, from: &Path, to: &Path) -> Result<u64, sqlx::Error> {
+        let from = from.to_string_lossy().to_string();
+        let to = to.to_string_lossy().to_string();
+        if from == to {
+            return Ok(0);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let mut relocated = 0;
+        for (table, column) in DATA_DIR_PATHS
+            .into_iter()
+            .chain([("partitions", "file_path")])
+        {
+            relocated += sqlx::query(&relocate_sql("main", table, column))
+                .bind(&from)
+                .bind(&to)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+        tx.commit().await?;
+
+        for partition in self.list_partitions().await? {
+            if !Path::new(&partition.file_path).exists() {
+                warn!(
+                    "partition {} is missing: {}",
+                    partition.month, partition.file_path
+                );
+                continue;
+            }
+            let mut conn = self.pool.acquire().await?;
+            attach_partition(&mut conn, &partition.file_path).await?;
+            let mut result = Ok(());
+            for (table, column) in DATA_DIR_PATHS {
+                match sqlx::query(&relocate_sql("archived", table, column))
+                    .bind(&from)
+                    .bind(&to)
+                    .execute(&mut *conn)
+                    .await
+                {
+                    Ok(done) => relocated += done.rows_affected(),
+                    Err(e) => {
+                        result = Err(e);
+                        break;
+                    }
+                }
+            }
+            detach_partition(conn).await;
+            result?;
+        }
+        debug!("relocated {} paths from {} to {}", relocated, from, to);
+        Ok(relocated)
+    }
+}
+
+fn relocate_sql(schema: &str, table: &str, column: &str) -> String {
+    format!(
+        "UPDATE {schema}.{table} SET {column} = ?2 || substr({column}, length(?1) + 1) WHERE substr({column}, 1, length(?1)) = ?1",
+        schema = schema,
+        table = table,
+        column = column
+    )
+}
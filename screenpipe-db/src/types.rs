@@ -1,9 +1,10 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, TimeZone, Utc};
 use oasgen::OaSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use std::error::Error as StdError;
 use std::fmt::{self, Display};
+use std::str::FromStr;
 
 #[derive(OaSchema, Debug)]
 pub struct DatabaseError(pub String);
@@ -21,6 +22,167 @@ pub enum SearchResult {
     OCR(OCRResult),
     Audio(AudioResult),
     UI(UiContent),
+    Table(TableResult),
+    Clipboard(ClipboardEntry),
+}
+
+impl SearchResult {
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        match self {
+            SearchResult::OCR(ocr) => ocr.timestamp,
+            SearchResult::Audio(audio) => audio.timestamp,
+            SearchResult::UI(ui) => ui.timestamp,
+            SearchResult::Table(table) => table.timestamp,
+            SearchResult::Clipboard(entry) => entry.timestamp,
+        }
+    }
+
+    /// BM25 rank of the match, lower is better. `None` without a query or
+    /// for results that aren't ranked.
+    pub fn rank(&self) -> Option<f64> {
+        match self {
+            SearchResult::OCR(ocr) => ocr.rank,
+            SearchResult::Audio(audio) => audio.rank,
+            SearchResult::UI(ui) => ui.rank,
+            SearchResult::Table(_) | SearchResult::Clipboard(_) => None,
+        }
+    }
+
+    pub fn kind(&self) -> SearchResultKind {
+        match self {
+            SearchResult::OCR(_) => SearchResultKind::Ocr,
+            SearchResult::Audio(_) => SearchResultKind::Audio,
+            SearchResult::UI(_) => SearchResultKind::Ui,
+            SearchResult::Table(_) => SearchResultKind::Table,
+            SearchResult::Clipboard(_) => SearchResultKind::Clipboard,
+        }
+    }
+
+    /// Position of the result in the search results, to continue after it.
+    pub fn cursor(&self) -> SearchCursor {
+        let id = match self {
+            SearchResult::OCR(ocr) => ocr.frame_id,
+            SearchResult::Audio(audio) => audio.transcription_id,
+            SearchResult::UI(ui) => ui.id,
+            SearchResult::Table(table) => table.table_id,
+            SearchResult::Clipboard(entry) => entry.id,
+        };
+        SearchCursor {
+            timestamp: self.timestamp(),
+            kind: self.kind(),
+            id,
+        }
+    }
+}
+
+/// Kind of a search result. Results at the same time are ordered by kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SearchResultKind {
+    Ocr,
+    Audio,
+    Ui,
+    Table,
+    Clipboard,
+}
+
+impl SearchResultKind {
+    pub const ALL: [SearchResultKind; 5] = [
+        SearchResultKind::Ocr,
+        SearchResultKind::Audio,
+        SearchResultKind::Ui,
+        SearchResultKind::Table,
+        SearchResultKind::Clipboard,
+    ];
+}
+
+impl Display for SearchResultKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchResultKind::Ocr => write!(f, "ocr"),
+            SearchResultKind::Audio => write!(f, "audio"),
+            SearchResultKind::Ui => write!(f, "ui"),
+            SearchResultKind::Table => write!(f, "table"),
+            SearchResultKind::Clipboard => write!(f, "clipboard"),
+        }
+    }
+}
+
+impl FromStr for SearchResultKind {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SearchResultKind::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == s)
+            .ok_or_else(|| DatabaseError(format!("unknown search result type: {}", s)))
+    }
+}
+
+/// Position in search results ordered by time, newest first, then by kind
+/// and newest id first. Passed around as an opaque string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchCursor {
+    pub timestamp: DateTime<Utc>,
+    pub kind: SearchResultKind,
+    pub id: i64,
+}
+
+impl SearchCursor {
+    /// Results of `kind` come after the cursor when they're older, or at its
+    /// time with an id below this bound.
+    pub(crate) fn id_bound(&self, kind: SearchResultKind) -> i64 {
+        match kind.cmp(&self.kind) {
+            std::cmp::Ordering::Less => i64::MIN,
+            std::cmp::Ordering::Equal => self.id,
+            std::cmp::Ordering::Greater => i64::MAX,
+        }
+    }
+}
+
+impl Display for SearchCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}~{}~{}",
+            self.timestamp
+                .to_rfc3339_opts(chrono::SecondsFormat::Nanos, true),
+            self.kind,
+            self.id
+        )
+    }
+}
+
+impl FromStr for SearchCursor {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DatabaseError(format!("invalid search cursor: {}", s));
+        let mut parts = s.split('~');
+        let (Some(timestamp), Some(kind), Some(id), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        Ok(SearchCursor {
+            timestamp: DateTime::parse_from_rfc3339(timestamp)
+                .map_err(|_| invalid())?
+                .with_timezone(&Utc),
+            kind: kind.parse()?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Order of search results.
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSort {
+    /// Newest first
+    #[default]
+    Time,
+    /// Best match of the query first, by BM25 with window titles weighing
+    /// more than the text on screen
+    Relevance,
 }
 
 #[derive(FromRow, Debug)]
@@ -45,8 +207,14 @@ pub struct OCRResultRaw {
     pub window_name: String,
     pub tags: Option<String>,
     pub browser_url: Option<String>,
+    #[sqlx(default)]
+    pub browser_context: Option<String>,
     pub focused: Option<bool>,
     pub device_name: String,
+    #[sqlx(default)]
+    pub rank: Option<f64>,
+    #[sqlx(default)]
+    pub snippet: Option<String>,
 }
 
 #[derive(OaSchema, Debug, Serialize, Deserialize)]
@@ -63,11 +231,62 @@ pub struct OCRResult {
     pub window_name: String,
     pub tags: Vec<String>,
     pub browser_url: Option<String>,
+    pub browser_context: Option<BrowserContext>,
     pub focused: Option<bool>,
     pub device_name: String,
+    /// BM25 rank of the match, lower is better
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f64>,
+    /// Matching part of the text, matches between `<mark>` and `</mark>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+/// Element of the accessibility tree of the focused window, stored per
+/// frame as json in `frames.accessibility_tree`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessibilityNode {
+    /// e.g. `AXButton` or `AXTextField`
+    pub role: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subrole: Option<String>,
+    /// Title or description of the element
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Value or text content of the element
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Whether the element has the keyboard focus
+    #[serde(default)]
+    pub focused: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<AccessibilityNode>,
 }
 
-#[derive(OaSchema, Debug, Deserialize, PartialEq, Default, Clone)]
+impl AccessibilityNode {
+    /// The element with the keyboard focus, searched depth first.
+    pub fn focused_element(&self) -> Option<&AccessibilityNode> {
+        if self.focused {
+            return Some(self);
+        }
+        self.children
+            .iter()
+            .find_map(|child| child.focused_element())
+    }
+}
+
+/// Active browser tab when a frame was captured, stored as json in
+/// `frames.browser_context`.
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BrowserContext {
+    pub url: String,
+    /// Title of the tab, from the browser or its window title
+    pub title: Option<String>,
+    /// App name of the browser
+    pub browser: String,
+}
+
+#[derive(OaSchema, Debug, Serialize, Deserialize, PartialEq, Default, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum ContentType {
     #[default]
@@ -84,10 +303,13 @@ pub enum ContentType {
     #[serde(rename = "audio+ocr")]
     #[serde(alias = "audio ocr")]
     AudioAndOcr,
+    Table,
+    Clipboard,
 }
 
 #[derive(FromRow)]
 pub struct AudioResultRaw {
+    pub transcription_id: i64,
     pub audio_chunk_id: i64,
     pub transcription: String,
     pub timestamp: DateTime<Utc>,
@@ -100,6 +322,967 @@ pub struct AudioResultRaw {
     pub speaker_id: Option<i64>,
     pub start_time: Option<f64>,
     pub end_time: Option<f64>,
+    pub language: Option<String>,
+    pub translated_text: Option<String>,
+    #[sqlx(default)]
+    pub rank: Option<f64>,
+    #[sqlx(default)]
+    pub snippet: Option<String>,
+}
+
+/// A semantic search hit, `score` is its reciprocal rank fusion score.
+#[derive(OaSchema, Debug, Serialize, Deserialize)]
+pub struct SemanticSearchResult {
+    pub result: SearchResult,
+    pub score: f32,
+}
+
+/// Text that has no embedding yet, `id` is the frame id for OCR text and the
+/// transcription id for audio.
+#[derive(FromRow, Debug, Clone)]
+pub struct UnembeddedText {
+    pub id: i64,
+    pub text: String,
+}
+
+/// Kinds of recorded media files.
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    Video,
+    Audio,
+}
+
+/// A recorded media file, `id` is its video or audio chunk id and
+/// `timestamp` when it was last recorded to.
+#[derive(OaSchema, FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct MediaChunk {
+    pub id: i64,
+    pub file_path: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Capture stopping (`blocked`) or resuming because the focused app or site
+/// matched the privacy blocklist.
+#[derive(OaSchema, FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyAuditEntry {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub monitor_id: i64,
+    pub blocked: bool,
+    pub app_name: String,
+    pub pattern: String,
+}
+
+/// A capture, OCR or audio loop the watchdog found without progress for
+/// `stalled_secs`, and whether restarting it worked.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchdogIncident {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    /// "vision", "ocr" or "audio"
+    pub subsystem: String,
+    /// Monitor id or audio device name
+    pub target: String,
+    pub stalled_secs: i64,
+    pub restarted: bool,
+    pub error: Option<String>,
+}
+
+/// Keyboard and mouse activity while a window had the focus, starting at
+/// `timestamp` and lasting `duration_ms`.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputEvent {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub duration_ms: i64,
+    pub app_name: String,
+    pub window_name: String,
+    pub keystrokes: i64,
+    pub clicks: i64,
+    pub scrolls: i64,
+    /// What was typed, only recorded if enabled
+    pub typed_text: Option<String>,
+}
+
+/// Text copied to the clipboard while `app_name` had the focus.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClipboardEntry {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub app_name: String,
+    pub window_name: String,
+    pub text: String,
+}
+
+/// Time the user was away and capture was suspended, `end_time` is `None`
+/// while they still are.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdleGap {
+    pub id: i64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// `no_input`, `screen_locked` or `display_asleep`
+    pub reason: String,
+}
+
+/// Time music was playing on an audio device instead of being transcribed.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaBlock {
+    pub id: i64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub device_name: String,
+    pub is_input: bool,
+    /// `music`
+    pub classification: String,
+    /// Highest probability of the chunks in the block being `classification`
+    pub confidence: f64,
+}
+
+/// A word of a transcription with its timing in the audio chunk file.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptWord {
+    pub word: String,
+    /// Seconds from the start of the audio chunk file
+    pub start_time: f64,
+    pub end_time: f64,
+    /// From 0 to 1, as given by the transcription engine
+    pub confidence: f64,
+}
+
+/// Term of the custom vocabulary of the transcription engines.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VocabularyEntry {
+    pub id: i64,
+    pub term: String,
+    /// What the engines hear instead, replaced with `term` in transcripts
+    pub sounds_like: Option<String>,
+    /// Only used while this app is focused, every chunk when `None`
+    pub app_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Token for the local API, without the token itself.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: Option<String>,
+    /// `search`, `pipes` or `admin`
+    pub scope: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Event of a calendar, the captures between its start and end are in that
+/// meeting.
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalendarEvent {
+    pub id: i64,
+    /// Calendar it was pulled from, `caldav` or `google`
+    pub source: String,
+    /// Id of the event in its calendar
+    pub uid: String,
+    pub title: String,
+    /// Names of the attendees, or their emails when the calendar has no name
+    pub attendees: Vec<String>,
+    pub location: Option<String>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(FromRow, Debug)]
+pub struct CalendarEventRaw {
+    pub id: i64,
+    pub source: String,
+    pub uid: String,
+    pub title: String,
+    pub attendees: String,
+    pub location: Option<String>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Phone or other device pushing what it captures with a companion app,
+/// without its token.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompanionDevice {
+    pub id: i64,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: Option<DateTime<Utc>>,
+}
+
+/// Media a companion device uploads in chunks.
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompanionUpload {
+    pub id: String,
+    pub device_id: i64,
+    /// `image`, `video` or `audio`
+    pub kind: String,
+    pub content_type: String,
+    /// Size of the whole file in bytes
+    pub size: i64,
+    /// Hex SHA-256 of the whole file
+    pub sha256: String,
+    /// When the media was captured
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Anything else the device sent with it, as a JSON object
+    pub metadata: Option<serde_json::Value>,
+    /// Id of the ingest job processing the upload once it's complete
+    pub ingest_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(FromRow, Debug)]
+pub struct CompanionUploadRaw {
+    pub id: String,
+    pub device_id: i64,
+    pub kind: String,
+    pub content_type: String,
+    pub size: i64,
+    pub sha256: String,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub metadata: Option<String>,
+    pub ingest_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Search checked against new recordings, firing a desktop notification or
+/// a webhook when it matches.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: i64,
+    pub name: String,
+    pub query: String,
+    /// Content type searched, like the `content_type` of `/search`
+    pub content_type: String,
+    pub app_name: Option<String>,
+    pub window_name: Option<String>,
+    /// Start of the name of the speakers searched
+    pub speaker_name: Option<String>,
+    /// Whether matches fire a desktop notification
+    pub notify: bool,
+    pub webhook_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Matches are searched among what was recorded after this, or after
+    /// `created_at` when it wasn't checked yet
+    pub last_checked_at: Option<DateTime<Utc>>,
+    pub last_matched_at: Option<DateTime<Utc>>,
+}
+
+/// Url events are posted to, signed with `secret`.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    /// Comma separated names of the events posted, like the `types` of
+    /// `/events/stream`
+    pub events: String,
+    /// Only events with a text field containing this are posted
+    pub keyword: Option<String>,
+    /// Only events of this app are posted
+    pub app_name: Option<String>,
+    /// Key of the HMAC-SHA256 signature of the payloads
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Delivery of an event to a webhook that failed after every retry.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookDeadLetter {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: String,
+    /// JSON body that was posted
+    pub payload: String,
+    /// Error of the last attempt
+    pub error: String,
+    pub attempts: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What an annotation is attached to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationTarget {
+    Frame(i64),
+    /// A transcript segment, by the id of its `audio_transcriptions` row
+    Transcription(i64),
+    TimeRange(DateTime<Utc>, DateTime<Utc>),
+}
+
+/// Note and tags attached to a frame, a transcript segment, or a time range
+/// when neither `frame_id` nor `audio_transcription_id` is set.
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub id: i64,
+    pub frame_id: Option<i64>,
+    pub audio_transcription_id: Option<i64>,
+    /// Time of the frame or transcript segment, or start of the range
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(FromRow, Debug)]
+pub struct AnnotationRaw {
+    pub id: i64,
+    pub frame_id: Option<i64>,
+    pub audio_transcription_id: Option<i64>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub note: Option<String>,
+    pub tags: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What the user was doing during a recorded time block.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivitySegment {
+    pub id: i64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// `meeting`, `coding`, `browsing`, `writing` or `other`
+    pub category: String,
+    /// App on screen the most during the block
+    pub app_name: Option<String>,
+    pub frame_count: i64,
+    pub has_audio: bool,
+}
+
+/// A local day or week summarized by an LLM.
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub id: i64,
+    /// `day` or `week`
+    pub period: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub summary: String,
+    pub topics: Vec<String>,
+    pub action_items: Vec<String>,
+    /// People mentioned
+    pub people: Vec<String>,
+    /// LLM that wrote it
+    pub model: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow, Debug)]
+pub struct SummaryRaw {
+    pub id: i64,
+    pub period: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub summary: String,
+    pub topics: String,
+    pub action_items: String,
+    pub people: String,
+    pub model: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Rule extracting a record matching `schema` from each session of an app,
+/// with a local LLM.
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtractionRule {
+    pub id: i64,
+    pub name: String,
+    /// Part of the name of the app whose sessions records are extracted from
+    pub app_name: String,
+    /// Part of the window name, to only extract from some windows of the app
+    pub window_name: Option<String>,
+    /// What to extract, given to the LLM with the schema
+    pub instructions: String,
+    /// JSON schema of the records
+    pub schema: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    /// Sessions ended before this were extracted, or before `created_at`
+    /// when none was yet
+    pub processed_until: Option<DateTime<Utc>>,
+}
+
+#[derive(FromRow, Debug)]
+pub struct ExtractionRuleRaw {
+    pub id: i64,
+    pub name: String,
+    pub app_name: String,
+    pub window_name: Option<String>,
+    pub instructions: String,
+    pub schema: String,
+    pub created_at: DateTime<Utc>,
+    pub processed_until: Option<DateTime<Utc>>,
+}
+
+/// Record extracted by a rule from a session of its app.
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Extraction {
+    pub id: i64,
+    pub rule_id: i64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// JSON object matching the schema of the rule
+    pub data: serde_json::Value,
+    /// LLM that extracted it
+    pub model: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow, Debug)]
+pub struct ExtractionRaw {
+    pub id: i64,
+    pub rule_id: i64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub data: String,
+    pub model: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Time spent in an activity category in a time range.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivitySummary {
+    pub category: String,
+    pub seconds: i64,
+    pub segment_count: i64,
+}
+
+/// A meeting, from when a meeting app was in the foreground with people
+/// talking until they stopped.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeetingSession {
+    pub id: i64,
+    pub start_time: DateTime<Utc>,
+    /// Last time the meeting was seen going on, while it isn't `ended`
+    pub end_time: DateTime<Utc>,
+    /// Meeting app or browser the meeting was in
+    pub app_name: Option<String>,
+    pub ended: bool,
+}
+
+/// A speaker told apart by diarization during a meeting.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeetingParticipant {
+    pub speaker_id: i64,
+    /// `None` until the speaker is named
+    pub name: Option<String>,
+    pub transcript_count: i64,
+    pub first_spoke_at: DateTime<Utc>,
+}
+
+/// A video chunk and the time its frames were captured in.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VideoChunkSpan {
+    pub id: i64,
+    pub file_path: String,
+    pub device_name: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub frame_count: i64,
+}
+
+/// A frame picked for a preview of a time range, with where to decode it from.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FramePreview {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub device_name: String,
+    #[serde(skip)]
+    pub file_path: String,
+    #[serde(skip)]
+    pub offset_index: i64,
+}
+
+/// A frame with its app context and OCR text.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameEntry {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub device_name: String,
+    pub app_name: Option<String>,
+    pub window_name: Option<String>,
+    pub browser_url: Option<String>,
+    pub focused: bool,
+    pub ocr_text: Option<String>,
+}
+
+/// How much an app was on screen in a time range.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppUsage {
+    pub app_name: String,
+    pub frame_count: i64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Length of the time buckets of usage analytics.
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageBucket {
+    Hour,
+    Day,
+}
+
+impl UsageBucket {
+    pub fn seconds(&self) -> i64 {
+        match self {
+            UsageBucket::Hour => 3600,
+            UsageBucket::Day => 86400,
+        }
+    }
+}
+
+/// Time an app was focused during a bucket.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppUsageBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub app_name: String,
+    pub seconds: i64,
+}
+
+/// Time a speaker talked during a bucket.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeakingTimeBucket {
+    pub bucket_start: DateTime<Utc>,
+    /// `None` for speech no speaker was recognized in
+    pub speaker_id: Option<i64>,
+    pub speaker_name: Option<String>,
+    pub seconds: i64,
+}
+
+/// Words read on screen in an app during a bucket.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WordCountBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub app_name: String,
+    pub words: i64,
+}
+
+/// Times the focus moved to another app during a bucket.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextSwitchBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub switches: i64,
+}
+
+/// A span an app window had the focus for.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusEvent {
+    pub id: i64,
+    pub app_name: String,
+    pub window_name: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub seconds: i64,
+}
+
+/// Uninterrupted time in one app, across its windows.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusStreak {
+    pub app_name: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub seconds: i64,
+    /// Distinct windows of the app focused during the streak
+    pub windows: i64,
+}
+
+/// An app the focus briefly moved to before going back to the app it left.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusInterruption {
+    pub app_name: String,
+    pub interruptions: i64,
+    /// Time spent in the app during its interruptions
+    pub seconds: i64,
+}
+
+/// Where pulling from a sync peer stopped, the last id of each table sent.
+#[derive(FromRow, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncCursor {
+    pub frame_cursor: i64,
+    pub transcription_cursor: i64,
+}
+
+/// Indexed text captured on one device, sent to the others. Media isn't
+/// synced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncBatch {
+    pub device_id: String,
+    /// Name of the machine, shown with the monitor or audio device
+    pub host: String,
+    pub frames: Vec<FrameEntry>,
+    pub transcripts: Vec<TimelineTranscript>,
+    pub next_cursor: SyncCursor,
+}
+
+/// Kind of a timeline event. Events at the same time are ordered by kind.
+#[derive(
+    OaSchema, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    IdleGap,
+    AppSwitch,
+    Frame,
+    Transcript,
+    MediaBlock,
+}
+
+impl TimelineEventKind {
+    pub const ALL: [TimelineEventKind; 5] = [
+        TimelineEventKind::IdleGap,
+        TimelineEventKind::AppSwitch,
+        TimelineEventKind::Frame,
+        TimelineEventKind::Transcript,
+        TimelineEventKind::MediaBlock,
+    ];
+}
+
+impl Display for TimelineEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimelineEventKind::IdleGap => write!(f, "idle_gap"),
+            TimelineEventKind::AppSwitch => write!(f, "app_switch"),
+            TimelineEventKind::Frame => write!(f, "frame"),
+            TimelineEventKind::Transcript => write!(f, "transcript"),
+            TimelineEventKind::MediaBlock => write!(f, "media_block"),
+        }
+    }
+}
+
+impl FromStr for TimelineEventKind {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TimelineEventKind::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == s)
+            .ok_or_else(|| DatabaseError(format!("unknown timeline event type: {}", s)))
+    }
+}
+
+/// A captured frame, with the start of its OCR text.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineFrame {
+    pub frame_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub device_name: String,
+    pub app_name: Option<String>,
+    pub window_name: Option<String>,
+    pub focused: bool,
+    pub text_preview: Option<String>,
+    pub repeat_count: i64,
+    pub last_repeat_at: Option<DateTime<Utc>>,
+}
+
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineTranscript {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub device_name: String,
+    pub is_input: bool,
+    pub speaker_id: Option<i64>,
+    pub transcription: String,
+    pub start_time: Option<f64>,
+    pub end_time: Option<f64>,
+}
+
+/// An audio transcript with the name of its speaker, for export.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportTranscript {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub device_name: String,
+    pub is_input: bool,
+    pub speaker_id: Option<i64>,
+    pub speaker_name: Option<String>,
+    pub transcription: String,
+    /// Offsets in seconds from the start of the audio chunk
+    pub start_time: Option<f64>,
+    pub end_time: Option<f64>,
+}
+
+/// The focused app changed, detected from the frames of a monitor.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineAppSwitch {
+    /// First frame of the new app
+    pub frame_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub device_name: String,
+    pub app_name: String,
+    pub window_name: Option<String>,
+    pub previous_app_name: String,
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "content", rename_all = "snake_case")]
+pub enum TimelineEvent {
+    IdleGap(IdleGap),
+    AppSwitch(TimelineAppSwitch),
+    Frame(TimelineFrame),
+    Transcript(TimelineTranscript),
+    MediaBlock(MediaBlock),
+}
+
+impl TimelineEvent {
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        match self {
+            TimelineEvent::IdleGap(gap) => gap.start_time,
+            TimelineEvent::AppSwitch(switch) => switch.timestamp,
+            TimelineEvent::Frame(frame) => frame.timestamp,
+            TimelineEvent::Transcript(transcript) => transcript.timestamp,
+            TimelineEvent::MediaBlock(block) => block.start_time,
+        }
+    }
+
+    pub fn kind(&self) -> TimelineEventKind {
+        match self {
+            TimelineEvent::IdleGap(_) => TimelineEventKind::IdleGap,
+            TimelineEvent::AppSwitch(_) => TimelineEventKind::AppSwitch,
+            TimelineEvent::Frame(_) => TimelineEventKind::Frame,
+            TimelineEvent::Transcript(_) => TimelineEventKind::Transcript,
+            TimelineEvent::MediaBlock(_) => TimelineEventKind::MediaBlock,
+        }
+    }
+
+    /// Position of the event in the timeline, to continue after it.
+    pub fn cursor(&self) -> TimelineCursor {
+        let id = match self {
+            TimelineEvent::IdleGap(gap) => gap.id,
+            TimelineEvent::AppSwitch(switch) => switch.frame_id,
+            TimelineEvent::Frame(frame) => frame.frame_id,
+            TimelineEvent::Transcript(transcript) => transcript.id,
+            TimelineEvent::MediaBlock(block) => block.id,
+        };
+        TimelineCursor {
+            timestamp: self.timestamp(),
+            kind: self.kind(),
+            id,
+        }
+    }
+}
+
+/// Position in the timeline, events are ordered by time, kind and id.
+/// Passed around as an opaque string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimelineCursor {
+    pub timestamp: DateTime<Utc>,
+    pub kind: TimelineEventKind,
+    pub id: i64,
+}
+
+impl Display for TimelineCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}~{}~{}",
+            self.timestamp
+                .to_rfc3339_opts(chrono::SecondsFormat::Nanos, true),
+            self.kind,
+            self.id
+        )
+    }
+}
+
+impl FromStr for TimelineCursor {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DatabaseError(format!("invalid timeline cursor: {}", s));
+        let mut parts = s.split('~');
+        let (Some(timestamp), Some(kind), Some(id), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        Ok(TimelineCursor {
+            timestamp: DateTime::parse_from_rfc3339(timestamp)
+                .map_err(|_| invalid())?
+                .with_timezone(&Utc),
+            kind: kind.parse()?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct TimelinePage {
+    pub events: Vec<TimelineEvent>,
+    /// Pass as `cursor` to get the next events, `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// A calendar month in UTC, like `2025-01`, the unit recordings are
+/// partitioned by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PartitionMonth {
+    pub year: i32,
+    /// 1 to 12
+    pub month: u32,
+}
+
+impl PartitionMonth {
+    pub fn of(timestamp: DateTime<Utc>) -> Self {
+        PartitionMonth {
+            year: timestamp.year(),
+            month: timestamp.month(),
+        }
+    }
+
+    pub fn start(&self) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(self.year, self.month, 1, 0, 0, 0)
+            .unwrap()
+    }
+
+    /// Start of the next month, the end of this one.
+    pub fn end(&self) -> DateTime<Utc> {
+        self.next().start()
+    }
+
+    pub fn next(&self) -> Self {
+        match self.month {
+            12 => PartitionMonth {
+                year: self.year + 1,
+                month: 1,
+            },
+            month => PartitionMonth {
+                year: self.year,
+                month: month + 1,
+            },
+        }
+    }
+
+    pub fn overlaps(&self, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> bool {
+        start.is_none_or(|start| start < self.end()) && end.is_none_or(|end| self.start() <= end)
+    }
+}
+
+impl fmt::Display for PartitionMonth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+impl FromStr for PartitionMonth {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DatabaseError(format!("invalid month: {}, expected like 2025-01", s));
+        let (year, month) = s.split_once('-').ok_or_else(invalid)?;
+        let year: i32 = year.parse().map_err(|_| invalid())?;
+        let month: u32 = month.parse().map_err(|_| invalid())?;
+        if !(1..=12).contains(&month) || !(1..=9999).contains(&year) {
+            return Err(invalid());
+        }
+        Ok(PartitionMonth { year, month })
+    }
+}
+
+/// A month of frames, screen text, transcripts and their chunks moved out
+/// of the main database into its own file.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Partition {
+    /// Like `2025-01`
+    pub month: String,
+    pub file_path: String,
+    pub frames: i64,
+    pub transcriptions: i64,
+    pub archived_at: DateTime<Utc>,
+}
+
+/// A partition dropped from the database, with the media files only it
+/// referred to, which can be deleted from disk.
+#[derive(Debug)]
+pub struct DroppedPartition {
+    pub partition: Partition,
+    pub video_chunks: Vec<MediaChunk>,
+    pub audio_chunks: Vec<MediaChunk>,
+}
+
+/// Screen text or a transcript found in a partition.
+#[derive(OaSchema, FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartitionMatch {
+    pub month: String,
+    /// `ocr` or `audio`
+    pub kind: String,
+    /// Frame id of screen text, transcription id of a transcript
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub text: String,
+    pub app_name: Option<String>,
+    pub window_name: Option<String>,
+    pub device_name: Option<String>,
+}
+
+/// An inconsistency found by `screenpipe doctor`.
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    /// Check that found it, like `foreign_keys` or `fts_index`
+    pub check: String,
+    pub description: String,
+    /// Rows or files concerned, 0 when they can't be counted
+    pub count: u64,
+    pub repaired: bool,
+}
+
+impl IntegrityIssue {
+    pub fn new(check: &str, description: impl Into<String>, count: u64) -> Self {
+        IntegrityIssue {
+            check: check.to_string(),
+            description: description.into(),
+            count,
+            repaired: false,
+        }
+    }
+}
+
+/// Where a schema migration stands in a database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaMigrationState {
+    Applied,
+    Pending,
+    /// Started but didn't complete, the database needs to be restored
+    Failed,
+    /// Applied, but its SQL changed since
+    Modified,
+    /// Applied by a newer version of screenpipe
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaMigration {
+    pub version: i64,
+    pub description: String,
+    pub state: SchemaMigrationState,
+    /// Whether it has a down migration to roll it back with
+    pub reversible: bool,
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+/// Migrations applied or rolled back, or that would be on a dry run.
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationReport {
+    pub dry_run: bool,
+    pub migrations: Vec<SchemaMigration>,
+    /// Copy of the database made before changing it
+    pub backup: Option<std::path::PathBuf>,
+}
+
+/// Rows removed by a retention cleanup, with the media files that no row
+/// refers to anymore and that can be deleted from disk.
+#[derive(Debug, Default)]
+pub struct DeletedRecords {
+    pub rows: u64,
+    pub media_chunks: Vec<MediaChunk>,
 }
 
 #[derive(OaSchema, Debug, Serialize, Deserialize, FromRow, Clone)]
@@ -109,6 +1292,17 @@ pub struct Speaker {
     pub metadata: String,
 }
 
+/// A named speaker enrolled from voice samples.
+#[derive(OaSchema, Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+pub struct VoiceProfile {
+    pub id: i64,
+    pub name: String,
+    /// Voice embeddings transcripts are matched against, one per 10 seconds
+    /// of the samples and of the speakers merged into the profile
+    pub embedding_count: i64,
+    pub transcript_count: i64,
+}
+
 #[derive(OaSchema, Clone, Eq, PartialEq, Hash, Serialize, Debug, Deserialize)]
 pub enum DeviceType {
     Input,
@@ -117,6 +1311,8 @@ pub enum DeviceType {
 
 #[derive(OaSchema, Debug, Serialize, Deserialize)]
 pub struct AudioResult {
+    /// Id of the transcript segment, to annotate it
+    pub transcription_id: i64,
     pub audio_chunk_id: i64,
     pub transcription: String,
     pub timestamp: DateTime<Utc>,
@@ -129,6 +1325,17 @@ pub struct AudioResult {
     pub speaker: Option<Speaker>,
     pub start_time: Option<f64>,
     pub end_time: Option<f64>,
+    /// Detected spoken language, e.g. `en`
+    pub language: Option<String>,
+    /// English translation, for transcriptions in other languages
+    pub translated_text: Option<String>,
+    /// BM25 rank of the match, lower is better
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f64>,
+    /// Matching part of the transcription, matches between `<mark>` and
+    /// `</mark>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
 }
 
 #[derive(OaSchema, Debug, Deserialize, PartialEq)]
@@ -153,6 +1360,14 @@ pub struct UiContent {
     pub offset_index: i64,
     pub frame_name: Option<String>,
     pub browser_url: Option<String>,
+    /// BM25 rank of the match, lower is better
+    #[sqlx(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f64>,
+    /// Matching part of the text, matches between `<mark>` and `</mark>`
+    #[sqlx(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
 }
 
 #[derive(OaSchema, Debug, Clone)]
@@ -160,6 +1375,10 @@ pub struct FrameData {
     pub frame_id: i64,
     pub timestamp: DateTime<Utc>,
     pub offset_index: i64,
+    /// Near-identical captures after this one, which weren't stored
+    pub repeat_count: i64,
+    /// Until when the screen looked the same, `None` without repeats
+    pub last_repeat_at: Option<DateTime<Utc>>,
     pub ocr_entries: Vec<OCREntry>,
     pub audio_entries: Vec<AudioEntry>,
 }
@@ -175,6 +1394,7 @@ pub struct OCREntry {
 
 #[derive(OaSchema, Debug, Clone)]
 pub struct AudioEntry {
+    pub transcription_id: i64,
     pub transcription: String,
     pub device_name: String,
     pub is_input: bool,
@@ -243,7 +1463,7 @@ pub struct TextPosition {
     pub bounds: TextBounds,
 }
 
-#[derive(OaSchema, Debug, Serialize, Clone)]
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
 pub struct TextBounds {
     pub left: f32,
     pub top: f32,
@@ -251,6 +1471,165 @@ pub struct TextBounds {
     pub height: f32,
 }
 
+impl TextBounds {
+    pub fn right(&self) -> f32 {
+        self.left + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.top + self.height
+    }
+
+    /// Smallest bounds containing both.
+    pub fn union(&self, other: &TextBounds) -> TextBounds {
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        TextBounds {
+            left,
+            top,
+            width: self.right().max(other.right()) - left,
+            height: self.bottom().max(other.bottom()) - top,
+        }
+    }
+}
+
+/// Where the text of a line came from.
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TextSource {
+    #[default]
+    Ocr,
+    /// Read from the app through the accessibility API, e.g. UI Automation
+    Accessibility,
+}
+
+impl Display for TextSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextSource::Ocr => write!(f, "ocr"),
+            TextSource::Accessibility => write!(f, "accessibility"),
+        }
+    }
+}
+
+/// A line of OCR'd text. Bounds are relative to the window image, from 0 to 1
+/// with the origin at the top left.
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OcrLayoutLine {
+    pub text: String,
+    pub confidence: f32,
+    pub bounds: TextBounds,
+    #[serde(default)]
+    pub source: TextSource,
+}
+
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OcrLayoutParagraph {
+    pub bounds: TextBounds,
+    pub lines: Vec<OcrLayoutLine>,
+}
+
+impl OcrLayoutParagraph {
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|l| l.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// A group of paragraphs, e.g. a column or a sidebar. Blocks are returned in
+/// reading order.
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OcrLayoutBlock {
+    pub reading_order: u32,
+    pub bounds: TextBounds,
+    pub paragraphs: Vec<OcrLayoutParagraph>,
+}
+
+impl OcrLayoutBlock {
+    pub fn text(&self) -> String {
+        self.paragraphs
+            .iter()
+            .map(|p| p.text())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(FromRow, Debug)]
+pub(crate) struct OcrBlockRow {
+    pub frame_id: i64,
+    pub level: String,
+    pub block_index: i64,
+    pub text: String,
+    pub confidence: Option<f64>,
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+    pub source: Option<String>,
+}
+
+/// A table detected in a frame. Rows are ordered top to bottom and all have
+/// the same number of cells, empty cells are empty strings.
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExtractedTable {
+    pub bounds: TextBounds,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl ExtractedTable {
+    pub fn column_count(&self) -> usize {
+        self.rows.first().map_or(0, |r| r.len())
+    }
+
+    /// Cell values as tab separated rows.
+    pub fn text(&self) -> String {
+        self.rows
+            .iter()
+            .map(|r| r.join("\t"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(OaSchema, Debug, Serialize, Deserialize)]
+pub struct TableResult {
+    pub table_id: i64,
+    pub frame_id: i64,
+    pub table_index: i64,
+    pub rows: Vec<Vec<String>>,
+    pub bounds: TextBounds,
+    pub timestamp: DateTime<Utc>,
+    pub file_path: String,
+    pub offset_index: i64,
+    pub app_name: String,
+    pub window_name: String,
+    pub device_name: String,
+    pub browser_url: Option<String>,
+}
+
+#[derive(FromRow, Debug)]
+pub(crate) struct TableResultRaw {
+    pub table_id: i64,
+    pub frame_id: i64,
+    pub table_index: i64,
+    pub cells: String,
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+    pub timestamp: DateTime<Utc>,
+    pub file_path: String,
+    pub offset_index: i64,
+    pub app_name: Option<String>,
+    pub window_name: Option<String>,
+    pub device_name: Option<String>,
+    pub browser_url: Option<String>,
+}
+
 #[derive(OaSchema, Serialize)]
 pub struct SearchMatch {
     pub frame_id: i64,
@@ -306,6 +1685,7 @@ pub enum OcrEngine {
     Tesseract,
     WindowsNative,
     AppleNative,
+    Paddle,
     Custom(CustomOcrConfig),
 }
 
@@ -325,3 +1705,31 @@ impl Default for CustomOcrConfig {
         }
     }
 }
+
+/// A frame recorded by another tool, like Rewind, imported without its
+/// media: only its text and window are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedFrame {
+    pub timestamp: DateTime<Utc>,
+    pub app_name: Option<String>,
+    pub window_name: Option<String>,
+    pub browser_url: Option<String>,
+    pub text: String,
+}
+
+/// A window that had the focus according to another tool, like
+/// ActivityWatch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedWindowEvent {
+    pub app_name: String,
+    pub window_name: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
+
+/// Speech transcribed by another tool, imported without its audio.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedTranscript {
+    pub timestamp: DateTime<Utc>,
+    pub text: String,
+}
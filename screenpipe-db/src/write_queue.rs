@@ -0,0 +1,242 @@
+//! Writes of the capture pipeline go through a single writer, which runs
+//! those arriving within a flush interval in one transaction. Under sustained
+//! capture this makes one commit, and one fsync, out of many small inserts,
+//! and keeps them from fighting over SQLite's write lock.
+
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use sqlx::{SqliteConnection, SqlitePool};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use tracing::{debug, error, warn};
+
+/// How long writes are gathered before they're committed together.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(20);
+/// Most writes committed in one transaction.
+const MAX_BATCH_SIZE: usize = 256;
+/// Writes waiting for the writer before callers have to wait to queue more.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// A write waiting in the queue.
+trait QueuedWrite: Send {
+    fn run<'c>(self: Box<Self>, conn: &'c mut SqliteConnection)
+        -> BoxFuture<'c, Box<dyn RanWrite>>;
+
+    /// The batch failed before the write could run.
+    fn fail(self: Box<Self>, error: &sqlx::Error);
+}
+
+/// A write that ran, its result held back until the batch is committed.
+trait RanWrite: Send {
+    fn succeeded(&self) -> bool;
+
+    fn reply(self: Box<Self>, committed: Result<(), &sqlx::Error>);
+}
+
+struct Write<T, F> {
+    write: F,
+    reply: oneshot::Sender<Result<T, sqlx::Error>>,
+}
+
+struct Ran<T> {
+    result: Result<T, sqlx::Error>,
+    reply: oneshot::Sender<Result<T, sqlx::Error>>,
+}
+
+impl<T, F> QueuedWrite for Write<T, F>
+where
+    T: Send + 'static,
+    F: for<'c> FnOnce(&'c mut SqliteConnection) -> BoxFuture<'c, Result<T, sqlx::Error>>
+        + Send
+        + 'static,
+{
+    fn run<'c>(
+        self: Box<Self>,
+        conn: &'c mut SqliteConnection,
+    ) -> BoxFuture<'c, Box<dyn RanWrite>> {
+        Box::pin(async move {
+            let result = (self.write)(conn).await;
+            Box::new(Ran {
+                result,
+                reply: self.reply,
+            }) as Box<dyn RanWrite>
+        })
+    }
+
+    fn fail(self: Box<Self>, error: &sqlx::Error) {
+        let _ = self.reply.send(Err(batch_error(error)));
+    }
+}
+
+impl<T: Send> RanWrite for Ran<T> {
+    fn succeeded(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    fn reply(self: Box<Self>, committed: Result<(), &sqlx::Error>) {
+        let result = match (self.result, committed) {
+            (Ok(_), Err(e)) => Err(batch_error(e)),
+            (result, _) => result,
+        };
+        let _ = self.reply.send(result);
+    }
+}
+
+/// sqlx errors can't be cloned, every write of a failed batch gets its own.
+fn batch_error(error: &sqlx::Error) -> sqlx::Error {
+    sqlx::Error::Protocol(format!("batched write failed: {}", error))
+}
+
+pub(crate) struct WriteQueue {
+    writes: mpsc::Sender<Box<dyn QueuedWrite>>,
+    /// Asks the writer to stop, replying once the queue is written
+    close: mpsc::Sender<oneshot::Sender<()>>,
+}
+
+impl WriteQueue {
+    /// Starts the writer on its own thread, so it doesn't depend on the
+    /// runtime the database was opened in. It stops once the queue is
+    /// dropped, after writing what's left.
+    pub(crate) fn start(pool: SqlitePool) -> std::io::Result<Self> {
+        let (writes, queue) = mpsc::channel(QUEUE_CAPACITY);
+        let (close, close_requests) = mpsc::channel(1);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        std::thread::Builder::new()
+            .name("screenpipe-db-writer".to_string())
+            .spawn(move || runtime.block_on(run_writer(pool, queue, close_requests)))?;
+        Ok(WriteQueue { writes, close })
+    }
+
+    /// Refuses new writes and returns once the queued ones are committed.
+    pub(crate) async fn close(&self) {
+        let (done, closed) = oneshot::channel();
+        if self.close.send(done).await.is_ok() {
+            let _ = closed.await;
+        }
+    }
+
+    /// Runs `write` in the next batch, returning once the batch is
+    /// committed. A failing write is rolled back alone, the rest of its
+    /// batch is still committed.
+    pub(crate) async fn write<T, F>(&self, write: F) -> Result<T, sqlx::Error>
+    where
+        T: Send + 'static,
+        F: for<'c> FnOnce(&'c mut SqliteConnection) -> BoxFuture<'c, Result<T, sqlx::Error>>
+            + Send
+            + 'static,
+    {
+        let (reply, result) = oneshot::channel();
+        self.writes
+            .send(Box::new(Write { write, reply }))
+            .await
+            .map_err(|_| sqlx::Error::PoolClosed)?;
+        result.await.map_err(|_| sqlx::Error::PoolClosed)?
+    }
+}
+
+async fn run_writer(
+    pool: SqlitePool,
+    mut queue: mpsc::Receiver<Box<dyn QueuedWrite>>,
+    mut close_requests: mpsc::Receiver<oneshot::Sender<()>>,
+) {
+    let mut closing = false;
+    let mut closed = None;
+    loop {
+        let first = tokio::select! {
+            write = queue.recv() => write,
+            done = close_requests.recv(), if !closing => {
+                // the writes already queued are still written
+                queue.close();
+                closing = true;
+                closed = done;
+                continue;
+            }
+        };
+        let Some(first) = first else {
+            break;
+        };
+        let mut batch = vec![first];
+        let deadline = Instant::now() + FLUSH_INTERVAL;
+        while batch.len() < MAX_BATCH_SIZE {
+            match tokio::time::timeout_at(deadline, queue.recv()).await {
+                Ok(Some(write)) => batch.push(write),
+                _ => break,
+            }
+        }
+        write_batch(&pool, batch).await;
+    }
+    debug!("write queue closed, stopping the database writer");
+    if let Some(done) = closed {
+        let _ = done.send(());
+    }
+}
+
+async fn write_batch(pool: &SqlitePool, batch: Vec<Box<dyn QueuedWrite>>) {
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            error!(
+                "failed to get a connection for {} writes: {}",
+                batch.len(),
+                e
+            );
+            batch.into_iter().for_each(|write| write.fail(&e));
+            return;
+        }
+    };
+    // takes the write lock now, a deferred transaction could fail to upgrade
+    if let Err(e) = sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await {
+        error!(
+            "failed to start a transaction for {} writes: {}",
+            batch.len(),
+            e
+        );
+        batch.into_iter().for_each(|write| write.fail(&e));
+        return;
+    }
+
+    let size = batch.len();
+    let mut ran = Vec::with_capacity(size);
+    for write in batch {
+        if let Err(e) = sqlx::query("SAVEPOINT queued_write")
+            .execute(&mut *conn)
+            .await
+        {
+            write.fail(&e);
+            continue;
+        }
+        let write = write.run(&mut conn).await;
+        if !write.succeeded() {
+            if let Err(e) = sqlx::query("ROLLBACK TO queued_write")
+                .execute(&mut *conn)
+                .await
+            {
+                warn!("failed to roll back a queued write: {}", e);
+            }
+        }
+        if let Err(e) = sqlx::query("RELEASE queued_write")
+            .execute(&mut *conn)
+            .await
+        {
+            warn!("failed to release a queued write: {}", e);
+        }
+        ran.push(write);
+    }
+
+    let committed = sqlx::query("COMMIT").execute(&mut *conn).await.map(|_| ());
+    match &committed {
+        Ok(()) => debug!("committed {} queued writes", size),
+        Err(e) => {
+            error!("failed to commit {} queued writes: {}", size, e);
+            if let Err(e) = sqlx::query("ROLLBACK").execute(&mut *conn).await {
+                warn!("failed to roll back queued writes: {}", e);
+            }
+        }
+    }
+    for write in ran {
+        write.reply(committed.as_ref().map(|_| ()));
+    }
+}
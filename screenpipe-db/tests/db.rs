@@ -2,9 +2,14 @@
 mod tests {
     use std::sync::Arc;
 
-    use chrono::Utc;
+    use chrono::{TimeZone, Utc};
     use screenpipe_db::{
-        AudioDevice, ContentType, DatabaseManager, DeviceType, Frame, OcrEngine, SearchResult,
+        database_schema_status, fts_query, migrate_database, reciprocal_rank_fusion,
+        rollback_database, AccessibilityNode, AnnotationTarget, AudioDevice, BrowserContext,
+        ContentType, DatabaseManager, DeviceType, ExtractedTable, Frame, MediaType, OcrEngine,
+        OcrLayoutBlock, OcrLayoutLine, OcrLayoutParagraph, PartitionMonth, SchemaMigrationState,
+        SearchCursor, SearchResult, SearchSort, TagContentType, TextBounds, TextSource,
+        TimelineCursor, TimelineEvent, TimelineEventKind, TranscriptWord, UsageBucket,
     };
 
     async fn setup_test_db() -> DatabaseManager {
@@ -29,6 +34,128 @@ mod tests {
         db
     }
 
+    #[tokio::test]
+    async fn test_browser_context_is_stored_and_filterable_by_url() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let context = BrowserContext {
+            url: "https://github.com/mediar-ai/screenpipe/issues".to_string(),
+            title: Some("Issues · mediar-ai/screenpipe".to_string()),
+            browser: "Google Chrome".to_string(),
+        };
+        for browser_context in [Some(&context), None] {
+            let frame_id = db
+                .insert_frame(
+                    "test_device",
+                    None,
+                    browser_context,
+                    Some("Google Chrome"),
+                    Some("Issues"),
+                    true,
+                )
+                .await
+                .unwrap();
+            db.insert_ocr_text(frame_id, "open issues", "", Arc::new(OcrEngine::Tesseract))
+                .await
+                .unwrap();
+        }
+
+        let search = |url: &'static str| {
+            db.search(
+                "",
+                ContentType::OCR,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(url),
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
+            )
+        };
+
+        let results = search("github.com/mediar-ai").await.unwrap();
+        assert_eq!(results.len(), 1);
+        let SearchResult::OCR(ocr_result) = &results[0] else {
+            panic!("Expected OCR result");
+        };
+        assert_eq!(
+            ocr_result.browser_url.as_deref(),
+            Some(context.url.as_str())
+        );
+        assert_eq!(ocr_result.browser_context.as_ref(), Some(&context));
+
+        assert!(search("gitlab.com").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_accessibility_tree_roundtrip() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame(
+                "test_device",
+                None,
+                None,
+                Some("Mail"),
+                Some("New Message"),
+                true,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_frame_accessibility_tree(frame_id).await.unwrap(),
+            None
+        );
+
+        let node = |role: &str, label: &str, focused: bool| AccessibilityNode {
+            role: role.to_string(),
+            subrole: None,
+            label: Some(label.to_string()),
+            value: None,
+            focused,
+            children: vec![],
+        };
+        let mut tree = node("AXWindow", "New Message", false);
+        let mut toolbar = node("AXToolbar", "toolbar", false);
+        toolbar.children.push(node("AXButton", "Send", false));
+        tree.children.push(toolbar);
+        tree.children.push(AccessibilityNode {
+            value: Some("hi there".to_string()),
+            ..node("AXTextArea", "message body", true)
+        });
+
+        db.insert_frame_accessibility_tree(frame_id, &tree)
+            .await
+            .unwrap();
+
+        let stored = db
+            .get_frame_accessibility_tree(frame_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored, tree);
+        let focused = stored.focused_element().unwrap();
+        assert_eq!(focused.role, "AXTextArea");
+        assert_eq!(focused.value.as_deref(), Some("hi there"));
+        assert_eq!(node("AXButton", "Send", false).focused_element(), None);
+    }
+
     #[tokio::test]
     async fn test_insert_and_search_ocr() {
         let db = setup_test_db().await;
@@ -65,6 +192,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -77,6 +208,144 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_insert_audio_recorded_earlier() {
+        let db = setup_test_db().await;
+        let recorded_at = Utc::now() - chrono::Duration::days(3);
+        let audio_chunk_id = db
+            .get_or_insert_audio_chunk("call.wav", recorded_at)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_or_insert_audio_chunk("call.wav", Utc::now())
+                .await
+                .unwrap(),
+            audio_chunk_id
+        );
+        let transcription_id = db
+            .insert_audio_transcription_at(
+                audio_chunk_id,
+                "see you on monday",
+                0,
+                "",
+                &AudioDevice {
+                    name: "phone".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                None,
+                None,
+                None,
+                None,
+                None,
+                recorded_at,
+            )
+            .await
+            .unwrap();
+
+        let chunk_timestamp: chrono::DateTime<Utc> =
+            sqlx::query_scalar("SELECT timestamp FROM audio_chunks WHERE id = ?1")
+                .bind(audio_chunk_id)
+                .fetch_one(&db.pool)
+                .await
+                .unwrap();
+        let transcription_timestamp: chrono::DateTime<Utc> =
+            sqlx::query_scalar("SELECT timestamp FROM audio_transcriptions WHERE id = ?1")
+                .bind(transcription_id)
+                .fetch_one(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(chunk_timestamp, recorded_at);
+        assert_eq!(transcription_timestamp, recorded_at);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_inserts() {
+        let db = Arc::new(setup_test_db().await);
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+
+        let inserts = (0..50).map(|i| {
+            let db = db.clone();
+            tokio::spawn(async move {
+                let frame_id = db
+                    .insert_frame("test_device", None, None, Some("app"), None, true)
+                    .await
+                    .unwrap();
+                db.insert_ocr_text(
+                    frame_id,
+                    &format!("text {}", i),
+                    "",
+                    Arc::new(OcrEngine::Tesseract),
+                )
+                .await
+                .unwrap();
+                frame_id
+            })
+        });
+        let mut frame_ids = futures::future::join_all(inserts)
+            .await
+            .into_iter()
+            .map(|frame_id| frame_id.unwrap())
+            .collect::<Vec<_>>();
+        frame_ids.sort();
+        frame_ids.dedup();
+        assert_eq!(frame_ids.len(), 50);
+
+        // batched writes still see the ones before them
+        let offsets: Vec<i64> = sqlx::query_scalar("SELECT offset_index FROM frames ORDER BY id")
+            .fetch_all(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(offsets, (0..50).collect::<Vec<i64>>());
+        let texts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM ocr_text")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(texts, 50);
+    }
+
+    #[tokio::test]
+    async fn test_close_commits_queued_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.sqlite").to_string_lossy().into_owned();
+        let db = Arc::new(DatabaseManager::new(&path).await.unwrap());
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+
+        let inserts = (0..20)
+            .map(|_| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    db.insert_frame("test_device", None, None, Some("app"), None, true)
+                        .await
+                })
+            })
+            .collect::<Vec<_>>();
+        // let the inserts reach the queue
+        tokio::task::yield_now().await;
+        db.close().await;
+
+        let committed = futures::future::join_all(inserts)
+            .await
+            .into_iter()
+            .filter(|result| matches!(result, Ok(Ok(_))))
+            .count();
+        assert!(db
+            .insert_frame("test_device", None, None, Some("app"), None, true)
+            .await
+            .is_err());
+
+        let db = DatabaseManager::new(&path).await.unwrap();
+        let frames: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM frames")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        // the inserts that succeeded are stored, none is half written
+        assert_eq!(frames, committed as i64);
+    }
+
     #[tokio::test]
     async fn test_insert_and_search_audio() {
         let db = setup_test_db().await;
@@ -93,6 +362,8 @@ mod tests {
             None,
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -113,6 +384,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -134,6 +409,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -162,6 +441,8 @@ mod tests {
             None,
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -189,6 +470,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -210,6 +495,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -277,6 +566,8 @@ mod tests {
             None,
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -297,6 +588,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -318,6 +613,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -374,6 +673,8 @@ mod tests {
             None,
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -443,6 +744,8 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
             )
             .await;
         println!("Second audio insert result: {:?}", insert_result);
@@ -458,14 +761,42 @@ mod tests {
 
         // After inserting both audio transcriptions, let's check all audio entries
         let all_audio = db
-            .search_audio("", 100, 0, None, None, None, None, None)
+            .search_audio(
+                "",
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
+                None,
+            )
             .await
             .unwrap();
         println!("All audio entries: {:?}", all_audio);
 
         // Then try specific search
         let audio_results = db
-            .search_audio("2", 100, 0, None, None, None, None, None)
+            .search_audio(
+                "2",
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
+                None,
+            )
             .await
             .unwrap();
         println!("Audio results for '2': {:?}", audio_results);
@@ -489,6 +820,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -513,6 +848,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -536,6 +875,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -563,6 +906,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -585,6 +932,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -629,6 +980,8 @@ mod tests {
             None,
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -667,6 +1020,8 @@ mod tests {
             None,
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -690,6 +1045,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -716,6 +1075,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -814,6 +1176,8 @@ mod tests {
                     Some(speaker.id),
                     None,
                     None,
+                    None,
+                    None,
                 )
                 .await
                 .unwrap();
@@ -881,6 +1245,8 @@ mod tests {
                     Some(speaker.id),
                     None,
                     None,
+                    None,
+                    None,
                 )
                 .await
                 .unwrap();
@@ -940,6 +1306,8 @@ mod tests {
                     Some(speaker.id),
                     None,
                     None,
+                    None,
+                    None,
                 )
                 .await
                 .unwrap();
@@ -986,6 +1354,8 @@ mod tests {
             Some(speaker.id),
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -1033,6 +1403,8 @@ mod tests {
             Some(speaker.id),
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -1053,6 +1425,8 @@ mod tests {
             Some(speaker2.id),
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -1117,6 +1491,10 @@ mod tests {
                 Some("test_video"),
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -1144,6 +1522,10 @@ mod tests {
                 Some("non_existent"),
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -1171,6 +1553,10 @@ mod tests {
                 Some("test_video"),
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -1196,6 +1582,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -1245,6 +1634,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -1274,6 +1667,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -1296,6 +1693,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -1318,6 +1719,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -1360,6 +1765,8 @@ mod tests {
             None,
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -1400,6 +1807,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -1420,6 +1830,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -1440,9 +1853,2583 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
         assert_eq!(count, 0, "Should count zero results for non-matching query");
     }
+
+    #[tokio::test]
+    async fn test_insert_and_get_ocr_blocks() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("test"), Some(""), false)
+            .await
+            .unwrap();
+
+        let line = |text: &str, top: f32| OcrLayoutLine {
+            text: text.to_string(),
+            confidence: 0.9,
+            bounds: TextBounds {
+                left: 0.1,
+                top,
+                width: 0.3,
+                height: 0.02,
+            },
+            source: TextSource::Ocr,
+        };
+        let paragraph = |lines: Vec<OcrLayoutLine>| OcrLayoutParagraph {
+            bounds: lines
+                .iter()
+                .skip(1)
+                .fold(lines[0].bounds, |acc, l| acc.union(&l.bounds)),
+            lines,
+        };
+        let first = paragraph(vec![line("hello", 0.1), line("world", 0.12)]);
+        // read through the accessibility api, the source is kept
+        let second = paragraph(vec![OcrLayoutLine {
+            source: TextSource::Accessibility,
+            ..line("second block", 0.5)
+        }]);
+        let blocks = vec![
+            OcrLayoutBlock {
+                reading_order: 0,
+                bounds: first.bounds,
+                paragraphs: vec![first],
+            },
+            OcrLayoutBlock {
+                reading_order: 1,
+                bounds: second.bounds,
+                paragraphs: vec![second],
+            },
+        ];
+
+        db.insert_ocr_blocks(frame_id, &blocks).await.unwrap();
+
+        let stored = db.get_ocr_blocks(&[frame_id]).await.unwrap();
+        assert_eq!(stored.get(&frame_id), Some(&blocks));
+        assert_eq!(stored[&frame_id][0].text(), "hello world");
+
+        assert!(db.get_ocr_blocks(&[]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_insert_and_search_tables() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame(
+                "test_device",
+                None,
+                None,
+                Some("sheets"),
+                Some("budget"),
+                false,
+            )
+            .await
+            .unwrap();
+
+        let table = ExtractedTable {
+            bounds: TextBounds {
+                left: 0.1,
+                top: 0.1,
+                width: 0.5,
+                height: 0.2,
+            },
+            rows: vec![
+                vec!["item".to_string(), "cost".to_string()],
+                vec!["rent".to_string(), "1200".to_string()],
+            ],
+        };
+        db.insert_ocr_tables(frame_id, std::slice::from_ref(&table))
+            .await
+            .unwrap();
+
+        let results = db
+            .search(
+                "rent",
+                ContentType::Table,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        if let SearchResult::Table(result) = &results[0] {
+            assert_eq!(result.frame_id, frame_id);
+            assert_eq!(result.rows, table.rows);
+            assert_eq!(result.bounds, table.bounds);
+            assert_eq!(result.app_name, "sheets");
+        } else {
+            panic!("Expected table result");
+        }
+
+        let count = db
+            .count_search_results(
+                "1200",
+                ContentType::Table,
+                None,
+                None,
+                Some("sheets"),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let results = db
+            .search_tables("missing", 100, 0, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_audio_by_language() {
+        let db = setup_test_db().await;
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "test".to_string(),
+            device_type: DeviceType::Input,
+        };
+        for (offset, (text, language)) in [("hello there", "en"), ("hallo zusammen", "de")]
+            .into_iter()
+            .enumerate()
+        {
+            db.insert_audio_transcription(
+                audio_chunk_id,
+                text,
+                offset as i64,
+                "",
+                &device,
+                None,
+                None,
+                None,
+                Some(language),
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        let results = db
+            .search_audio(
+                "",
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some("de"),
+                None,
+                None,
+                SearchSort::Time,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].transcription, "hallo zusammen");
+        assert_eq!(results[0].language.as_deref(), Some("de"));
+
+        let count = db
+            .count_search_results(
+                "",
+                ContentType::Audio,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some("en"),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_finds_translated_audio() {
+        let db = setup_test_db().await;
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "guten morgen",
+            0,
+            "",
+            &AudioDevice {
+                name: "test".to_string(),
+                device_type: DeviceType::Input,
+            },
+            None,
+            None,
+            None,
+            Some("de"),
+            Some("good morning"),
+        )
+        .await
+        .unwrap();
+
+        for query in ["morgen", "morning"] {
+            let results = db
+                .search_audio(
+                    query,
+                    100,
+                    0,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    SearchSort::Time,
+                    None,
+                )
+                .await
+                .unwrap();
+            assert_eq!(results.len(), 1, "no result for {}", query);
+            assert_eq!(results[0].transcription, "guten morgen");
+            assert_eq!(results[0].translated_text.as_deref(), Some("good morning"));
+        }
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let fused = reciprocal_rank_fusion(&[vec!["a", "b", "c"], vec!["c", "b", "d"]], 60.0);
+        let order: Vec<&str> = fused.iter().map(|(item, _)| *item).collect();
+        // b and c are in both rankings, b ranks better on average
+        assert_eq!(order, vec!["b", "c", "a", "d"]);
+        assert!((fused[0].1 - (1.0 / 62.0 + 1.0 / 62.0)).abs() < 1e-6);
+
+        // duplicates within a ranking count once
+        let fused = reciprocal_rank_fusion(&[vec!["a", "a"]], 60.0);
+        assert_eq!(fused.len(), 1);
+        assert!((fused[0].1 - 1.0 / 61.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(
+            fts_query("rust async").as_deref(),
+            Some("\"rust\" AND \"async\"")
+        );
+        assert_eq!(
+            fts_query("\"pull request\" OR merge").as_deref(),
+            Some("\"pull request\" OR \"merge\"")
+        );
+        assert_eq!(
+            fts_query("deploy -staging NOT prod").as_deref(),
+            Some("\"deploy\" NOT \"staging\" NOT \"prod\"")
+        );
+        assert_eq!(
+            fts_query("(standup OR retro) -(sprint planning)").as_deref(),
+            Some("(\"standup\" OR \"retro\") NOT (\"sprint\" AND \"planning\")")
+        );
+        assert_eq!(fts_query("config*").as_deref(), Some("\"config\"*"));
+        // punctuation and dangling operators don't break the query
+        assert_eq!(
+            fts_query("OR github.com/foo (bar) AND").as_deref(),
+            Some("\"github.com/foo\" AND \"bar\"")
+        );
+        assert_eq!(fts_query("((a () OR").as_deref(), Some("\"a\""));
+        let query = fts_query("a OR \"b c\"* -d").unwrap();
+        assert_eq!(fts_query(&query), Some(query));
+        assert_eq!(fts_query("  "), None);
+        assert_eq!(fts_query("-only NOT excluded"), None);
+    }
+
+    #[tokio::test]
+    async fn test_search_by_relevance() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let in_title = db
+            .insert_frame(
+                "test_device",
+                None,
+                None,
+                Some("Mail"),
+                Some("invoice 1042"),
+                false,
+            )
+            .await
+            .unwrap();
+        db.insert_ocr_text(
+            in_title,
+            "attached is the invoice for march",
+            "",
+            Arc::new(OcrEngine::Tesseract),
+        )
+        .await
+        .unwrap();
+        let in_text = db
+            .insert_frame(
+                "test_device",
+                None,
+                None,
+                Some("Notes"),
+                Some("todo"),
+                false,
+            )
+            .await
+            .unwrap();
+        db.insert_ocr_text(
+            in_text,
+            "remember to review the invoice before friday",
+            "",
+            Arc::new(OcrEngine::Tesseract),
+        )
+        .await
+        .unwrap();
+
+        let results = db
+            .search(
+                "invoice",
+                ContentType::OCR,
+                10,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Relevance,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        // the window title match ranks first even though it was recorded first
+        let SearchResult::OCR(first) = &results[0] else {
+            panic!("expected an OCR result");
+        };
+        assert_eq!(first.frame_id, in_title);
+        assert!(first.rank.is_some());
+        assert!(first
+            .snippet
+            .as_deref()
+            .is_some_and(|snippet| snippet.contains("<mark>invoice</mark>")));
+
+        let results = db
+            .search(
+                "\"review the invoice\"",
+                ContentType::OCR,
+                10,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Relevance,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        let SearchResult::OCR(ocr) = &results[0] else {
+            panic!("expected an OCR result");
+        };
+        assert_eq!(ocr.frame_id, in_text);
+    }
+
+    #[tokio::test]
+    async fn test_search_after_cursor() {
+        let db = setup_test_db().await;
+        let now = chrono::DateTime::from_timestamp(Utc::now().timestamp() - 3600, 0).unwrap();
+        let earlier = now - chrono::Duration::minutes(1);
+
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        // several frames and transcriptions at the same time, so paging has
+        // to break ties on kind and id
+        for timestamp in [earlier, now, now, now, earlier] {
+            let frame_id = db
+                .insert_frame("test_device", Some(timestamp), None, None, None, false)
+                .await
+                .unwrap();
+            db.insert_ocr_text(
+                frame_id,
+                "quarterly report",
+                "",
+                Arc::new(OcrEngine::Tesseract),
+            )
+            .await
+            .unwrap();
+        }
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        for offset_index in 0..2 {
+            db.insert_audio_transcription_at(
+                audio_chunk_id,
+                "the quarterly report is late",
+                offset_index,
+                "",
+                &AudioDevice {
+                    name: "test".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                None,
+                None,
+                None,
+                None,
+                None,
+                now,
+            )
+            .await
+            .unwrap();
+        }
+
+        let search_page = |limit: u32, cursor: Option<SearchCursor>| {
+            db.search_after(
+                "quarterly",
+                ContentType::All,
+                limit,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
+                cursor,
+            )
+        };
+
+        let all: Vec<SearchCursor> = search_page(100, None)
+            .await
+            .unwrap()
+            .iter()
+            .map(SearchResult::cursor)
+            .collect();
+        assert_eq!(all.len(), 7);
+
+        let mut paged = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = search_page(2, cursor).await.unwrap();
+            paged.extend(page.iter().map(SearchResult::cursor));
+            if page.len() < 2 {
+                break;
+            }
+            cursor = page.last().map(SearchResult::cursor);
+        }
+        assert_eq!(paged, all);
+
+        let cursor: SearchCursor = all[3].to_string().parse().unwrap();
+        assert_eq!(cursor, all[3]);
+        assert!("2024-06-01T10:00:00Z~frame~1"
+            .parse::<SearchCursor>()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_search() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("Teams"), Some(""), false)
+            .await
+            .unwrap();
+        db.insert_ocr_text(
+            frame_id,
+            "Rnicrosoft Teams meeting notes",
+            "",
+            Arc::new(OcrEngine::Tesseract),
+        )
+        .await
+        .unwrap();
+
+        let search = |query: String| {
+            let db = &db;
+            async move {
+                db.search(
+                    &query,
+                    ContentType::OCR,
+                    10,
+                    0,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    SearchSort::Time,
+                )
+                .await
+                .unwrap()
+            }
+        };
+
+        assert!(search("microsoft meeting".to_string()).await.is_empty());
+        let query = db.fuzzy_query("microsoft meeting", 1).await.unwrap();
+        assert!(search(query).await.is_empty());
+        let query = db.fuzzy_query("microsoft meeting", 2).await.unwrap();
+        assert!(query.contains("\"rnicrosoft\""));
+        assert_eq!(search(query).await.len(), 1);
+
+        // phrases, excluded and short words are searched as typed
+        let query = db
+            .fuzzy_query("\"microsoft\" -rneeting tearns", 2)
+            .await
+            .unwrap();
+        assert_eq!(
+            query,
+            "(\"microsoft\" AND (\"tearns\" OR \"teams\")) NOT \"rneeting\""
+        );
+        assert_eq!(db.fuzzy_query("mtg", 2).await.unwrap(), "\"mtg\"");
+    }
+
+    #[tokio::test]
+    async fn test_search_semantic_combines_vectors_and_keywords() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("test"), Some(""), false)
+            .await
+            .unwrap();
+        db.insert_ocr_text(
+            frame_id,
+            "quarterly revenue report",
+            "",
+            Arc::new(OcrEngine::Tesseract),
+        )
+        .await
+        .unwrap();
+
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        let transcription_id = db
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "let's grab lunch",
+                0,
+                "",
+                &AudioDevice {
+                    name: "test".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let unembedded = db.get_ocr_text_without_embeddings(10).await.unwrap();
+        assert_eq!(unembedded.len(), 1);
+        assert_eq!(unembedded[0].id, frame_id);
+        let unembedded = db
+            .get_audio_transcriptions_without_embeddings(10)
+            .await
+            .unwrap();
+        assert_eq!(unembedded.len(), 1);
+        assert_eq!(unembedded[0].id, transcription_id);
+
+        db.insert_embeddings(frame_id, "[1.0, 0.0, 0.0]".to_string())
+            .await
+            .unwrap();
+        db.insert_audio_transcription_embedding(transcription_id, &[0.0, 1.0, 0.0])
+            .await
+            .unwrap();
+        assert!(db
+            .get_ocr_text_without_embeddings(10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(db
+            .get_audio_transcriptions_without_embeddings(10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // no shared words, the closest vector wins
+        let results = db
+            .search_semantic("food", &[0.1, 0.9, 0.0], ContentType::All, 10)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        match &results[0].result {
+            SearchResult::Audio(audio) => assert_eq!(audio.transcription, "let's grab lunch"),
+            _ => panic!("expected audio result first"),
+        }
+
+        // a keyword match outweighs a slightly closer vector
+        let results = db
+            .search_semantic("revenue \"", &[0.6, 0.8, 0.0], ContentType::All, 10)
+            .await
+            .unwrap();
+        match &results[0].result {
+            SearchResult::OCR(ocr) => assert_eq!(ocr.frame_id, frame_id),
+            _ => panic!("expected ocr result first"),
+        }
+        assert!(results[0].score > results[1].score);
+
+        let results = db
+            .search_semantic("revenue", &[0.6, 0.8, 0.0], ContentType::Audio, 10)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0].result, SearchResult::Audio(_)));
+    }
+
+    #[tokio::test]
+    async fn test_retention_of_frames_and_video_files() {
+        let db = setup_test_db().await;
+        let now = Utc::now();
+        let old = now - chrono::Duration::days(30);
+
+        db.insert_video_chunk("old.mp4", "test_device")
+            .await
+            .unwrap();
+        let old_frame = db
+            .insert_frame(
+                "test_device",
+                Some(old),
+                None,
+                Some("test"),
+                Some(""),
+                false,
+            )
+            .await
+            .unwrap();
+        db.insert_ocr_text(old_frame, "old text", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+        db.insert_video_chunk("new.mp4", "test_device")
+            .await
+            .unwrap();
+        let new_frame = db
+            .insert_frame(
+                "test_device",
+                Some(now),
+                None,
+                Some("test"),
+                Some(""),
+                false,
+            )
+            .await
+            .unwrap();
+        db.insert_ocr_text(new_frame, "new text", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+
+        let cutoff = now - chrono::Duration::days(7);
+        let expired = db
+            .get_media_chunks_before(MediaType::Video, cutoff, None)
+            .await
+            .unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].file_path, "old.mp4");
+
+        // deleting the file keeps the text
+        db.mark_media_chunks_deleted(MediaType::Video, &[expired[0].id])
+            .await
+            .unwrap();
+        assert!(db
+            .get_media_chunks_before(MediaType::Video, cutoff, None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(db.count_frames_before(cutoff).await.unwrap(), 1);
+
+        let deleted = db.delete_frames_before(cutoff).await.unwrap();
+        assert_eq!(deleted.rows, 1);
+        // the file was already deleted
+        assert!(deleted.media_chunks.is_empty());
+        assert_eq!(db.count_frames_before(cutoff).await.unwrap(), 0);
+
+        let results = db
+            .search(
+                "text",
+                ContentType::OCR,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retention_of_audio_transcriptions() {
+        let db = setup_test_db().await;
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "hello",
+            0,
+            "",
+            &AudioDevice {
+                name: "test".to_string(),
+                device_type: DeviceType::Input,
+            },
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let later = Utc::now() + chrono::Duration::minutes(1);
+        let expired = db
+            .get_media_chunks_before(MediaType::Audio, later, None)
+            .await
+            .unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(
+            db.count_audio_transcriptions_before(later).await.unwrap(),
+            1
+        );
+
+        let deleted = db.delete_audio_transcriptions_before(later).await.unwrap();
+        assert_eq!(deleted.rows, 1);
+        assert_eq!(deleted.media_chunks.len(), 1);
+        assert_eq!(deleted.media_chunks[0].file_path, "test_audio.mp4");
+        let chunks: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audio_chunks")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(chunks, 0);
+    }
+
+    #[tokio::test]
+    async fn test_partitions() {
+        let db = setup_test_db().await;
+        let dir = tempfile::tempdir().unwrap();
+        let january: PartitionMonth = "2025-01".parse().unwrap();
+
+        let mut frame_ids = Vec::new();
+        for (chunk, timestamp, text) in [
+            (
+                "jan.mp4",
+                Utc.with_ymd_and_hms(2025, 1, 15, 9, 0, 0).unwrap(),
+                "january invoice",
+            ),
+            (
+                "feb.mp4",
+                Utc.with_ymd_and_hms(2025, 2, 10, 9, 0, 0).unwrap(),
+                "february invoice",
+            ),
+        ] {
+            db.insert_video_chunk(chunk, "test_device").await.unwrap();
+            let frame_id = db
+                .insert_frame(
+                    "test_device",
+                    Some(timestamp),
+                    None,
+                    Some("mail"),
+                    None,
+                    true,
+                )
+                .await
+                .unwrap();
+            db.insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+                .await
+                .unwrap();
+            frame_ids.push(frame_id);
+        }
+        db.add_tags(
+            frame_ids[0],
+            TagContentType::Vision,
+            vec!["billing".to_string()],
+        )
+        .await
+        .unwrap();
+        let audio_chunk_id = db
+            .insert_audio_chunk_at(
+                "jan-audio.mp4",
+                Utc.with_ymd_and_hms(2025, 1, 20, 9, 0, 0).unwrap(),
+            )
+            .await
+            .unwrap();
+        db.insert_audio_transcription_at(
+            audio_chunk_id,
+            "call about the invoice",
+            0,
+            "",
+            &AudioDevice {
+                name: "mic".to_string(),
+                device_type: DeviceType::Input,
+            },
+            None,
+            None,
+            None,
+            None,
+            None,
+            Utc.with_ymd_and_hms(2025, 1, 20, 9, 0, 0).unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            db.oldest_unpartitioned_month().await.unwrap(),
+            Some(january)
+        );
+
+        let partition = db
+            .archive_partition(january, dir.path())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(partition.month, "2025-01");
+        assert_eq!((partition.frames, partition.transcriptions), (1, 1));
+        assert!(std::path::Path::new(&partition.file_path).exists());
+        assert_eq!(db.count_frames_before(january.end()).await.unwrap(), 0);
+        assert_eq!(
+            db.oldest_unpartitioned_month().await.unwrap(),
+            Some(january.next())
+        );
+        // nothing of the month is left
+        assert!(db
+            .archive_partition(january, dir.path())
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(db.list_partitions().await.unwrap(), vec![partition.clone()]);
+
+        let matches = db
+            .search_partitions("invoice", None, None, 10)
+            .await
+            .unwrap();
+        let matches: Vec<(&str, &str)> = matches
+            .iter()
+            .map(|found| (found.kind.as_str(), found.text.as_str()))
+            .collect();
+        assert_eq!(
+            matches,
+            vec![
+                ("audio", "call about the invoice"),
+                ("ocr", "january invoice")
+            ]
+        );
+        assert!(db
+            .search_partitions("invoice", Some(january.end()), None, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // the tags moved with the frame, and the regular read paths find it
+        let tagged: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM vision_tags")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(tagged, 0);
+        let (file_path, _) = db.get_frame(frame_ids[0]).await.unwrap().unwrap();
+        assert_eq!(file_path, "jan.mp4");
+        let archived = db
+            .search_archived(
+                "invoice",
+                ContentType::All,
+                10,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(archived.len(), 2);
+        assert!(
+            matches!(&archived[0], SearchResult::Audio(audio) if audio.transcription == "call about the invoice")
+        );
+        match &archived[1] {
+            SearchResult::OCR(ocr) => {
+                assert_eq!(ocr.ocr_text, "january invoice");
+                assert_eq!(ocr.tags, vec!["billing".to_string()]);
+            }
+            _ => panic!("expected screen text"),
+        }
+        assert_eq!(
+            db.count_archived(
+                "invoice",
+                ContentType::OCR,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None
+            )
+            .await
+            .unwrap(),
+            1
+        );
+        let timeline = db
+            .find_video_chunks(january.start(), january.end())
+            .await
+            .unwrap();
+        assert_eq!(timeline.frames.len(), 1);
+        assert_eq!(timeline.frames[0].ocr_entries[0].text, "january invoice");
+        assert_eq!(timeline.frames[0].audio_entries.len(), 1);
+
+        let dropped = db.drop_partition(january).await.unwrap().unwrap();
+        assert_eq!(dropped.partition, partition);
+        assert_eq!(dropped.video_chunks.len(), 1);
+        assert_eq!(dropped.video_chunks[0].file_path, "jan.mp4");
+        assert_eq!(dropped.audio_chunks.len(), 1);
+        assert!(!std::path::Path::new(&partition.file_path).exists());
+        assert!(db.list_partitions().await.unwrap().is_empty());
+        assert!(db.drop_partition(january).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_partition_months() {
+        let december: PartitionMonth = "2024-12".parse().unwrap();
+        assert_eq!(december.to_string(), "2024-12");
+        assert_eq!(december.next().to_string(), "2025-01");
+        assert_eq!(
+            december.end(),
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            PartitionMonth::of(Utc.with_ymd_and_hms(2024, 12, 31, 23, 0, 0).unwrap()),
+            december
+        );
+        for invalid in ["2024", "2024-13", "2024-00", "december"] {
+            assert!(invalid.parse::<PartitionMonth>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_schema_migrations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.sqlite");
+        let db = DatabaseManager::new(&path.to_string_lossy()).await.unwrap();
+        db.insert_video_chunk("kept.mp4", "test_device")
+            .await
+            .unwrap();
+        drop(db);
+
+        let status = database_schema_status(&path, None).await.unwrap();
+        assert!(status
+            .iter()
+            .all(|migration| migration.state == SchemaMigrationState::Applied));
+        let partitions = status.last().unwrap();
+        assert_eq!(partitions.version, 20250516090000);
+        assert_eq!(partitions.description, "create partitions");
+        assert!(partitions.reversible);
+        assert!(migrate_database(&path, None, false)
+            .await
+            .unwrap()
+            .migrations
+            .is_empty());
+
+        // the migrations before focus events have no down migration
+        let rolled_back = rollback_database(&path, None, 20250512090000, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            rolled_back
+                .migrations
+                .iter()
+                .map(|migration| migration.version)
+                .collect::<Vec<_>>(),
+            [20250516090000, 20250514090000]
+        );
+        assert!(rollback_database(&path, None, 20250510090000, false)
+            .await
+            .is_err());
+
+        let rolled_back = rollback_database(&path, None, 20250514090000, false)
+            .await
+            .unwrap();
+        assert_eq!(rolled_back.migrations.len(), 1);
+        let backup = rolled_back.backup.unwrap();
+        assert_eq!(
+            backup,
+            dir.path().join("db.sqlite.pre-migration-20250516090000")
+        );
+        assert!(backup.exists());
+        let status = database_schema_status(&path, None).await.unwrap();
+        assert_eq!(status.last().unwrap().state, SchemaMigrationState::Pending);
+
+        let dry_run = migrate_database(&path, None, true).await.unwrap();
+        assert_eq!(dry_run.migrations.len(), 1);
+        assert_eq!(dry_run.backup, None);
+
+        // reopening applies it again, after a backup replacing the older one
+        let db = DatabaseManager::new(&path.to_string_lossy()).await.unwrap();
+        assert!(db.list_partitions().await.unwrap().is_empty());
+        let chunks: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM video_chunks")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(chunks, 1);
+        assert!(dir
+            .path()
+            .join("db.sqlite.pre-migration-20250514090000")
+            .exists());
+        assert!(!backup.exists());
+
+        // an edited migration is refused
+        sqlx::query("UPDATE _sqlx_migrations SET checksum = x'00' WHERE version = 20250514090000")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        drop(db);
+        let status = database_schema_status(&path, None).await.unwrap();
+        assert!(status.iter().any(|migration| {
+            migration.version == 20250514090000 && migration.state == SchemaMigrationState::Modified
+        }));
+        assert!(migrate_database(&path, None, true).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_media_chunks_removes_their_text() {
+        let db = setup_test_db().await;
+        let video_chunk_id = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("test"), Some(""), false)
+            .await
+            .unwrap();
+        db.insert_ocr_text(frame_id, "evicted", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.delete_media_chunks(MediaType::Video, &[video_chunk_id])
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(db.get_frame(frame_id).await.unwrap().is_none());
+        let results = db
+            .search(
+                "evicted",
+                ContentType::OCR,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
+            )
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unencrypted_media_chunks() {
+        let db = setup_test_db().await;
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+
+        let later = Utc::now() + chrono::Duration::minutes(1);
+        let unencrypted = db
+            .get_unencrypted_media_chunks(MediaType::Audio, later, 10)
+            .await
+            .unwrap();
+        assert_eq!(unencrypted.len(), 1);
+        assert_eq!(unencrypted[0].id, audio_chunk_id);
+
+        db.mark_media_chunks_encrypted(MediaType::Audio, &[audio_chunk_id])
+            .await
+            .unwrap();
+        assert!(db
+            .get_unencrypted_media_chunks(MediaType::Audio, later, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        // encrypted chunks are still subject to retention
+        assert_eq!(
+            db.get_media_chunks_before(MediaType::Audio, later, None)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_privacy_audit_log() {
+        let db = setup_test_db().await;
+        let start = Utc::now() - chrono::Duration::minutes(10);
+        let end = start + chrono::Duration::minutes(5);
+
+        db.insert_privacy_audit_entry(start, 1, true, "1Password", "app:1password")
+            .await
+            .unwrap();
+        db.insert_privacy_audit_entry(end, 1, false, "1Password", "app:1password")
+            .await
+            .unwrap();
+
+        let log = db.get_privacy_audit_log(None, None, 10, 0).await.unwrap();
+        assert_eq!(log.len(), 2);
+        // newest first
+        assert!(!log[0].blocked);
+        assert!(log[1].blocked);
+        assert_eq!(log[1].app_name, "1Password");
+        assert_eq!(log[1].pattern, "app:1password");
+        assert_eq!(log[1].monitor_id, 1);
+
+        let log = db
+            .get_privacy_audit_log(None, Some(start + chrono::Duration::minutes(1)), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(log.len(), 1);
+        assert!(log[0].blocked);
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_incidents() {
+        let db = setup_test_db().await;
+        let start = Utc::now() - chrono::Duration::hours(2);
+
+        db.insert_watchdog_incident(start, "vision", "1", 130, true, None)
+            .await
+            .unwrap();
+        db.insert_watchdog_incident(
+            Utc::now(),
+            "audio",
+            "MacBook Pro Microphone (input)",
+            125,
+            false,
+            Some("Device MacBook Pro Microphone (input) not found"),
+        )
+        .await
+        .unwrap();
+
+        let incidents = db.get_watchdog_incidents(None, 10).await.unwrap();
+        assert_eq!(incidents.len(), 2);
+        // newest first
+        assert_eq!(incidents[0].subsystem, "audio");
+        assert!(!incidents[0].restarted);
+        assert!(incidents[0].error.is_some());
+        assert_eq!(incidents[1].target, "1");
+        assert_eq!(incidents[1].stalled_secs, 130);
+
+        let recent = db
+            .get_watchdog_incidents(Some(Utc::now() - chrono::Duration::hours(1)), 10)
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_input_events() {
+        let db = setup_test_db().await;
+        let start = Utc::now() - chrono::Duration::minutes(10);
+
+        db.insert_input_event(start, 60_000, "Code", "main.rs", 120, 4, 10, None)
+            .await
+            .unwrap();
+        db.insert_input_event(
+            start + chrono::Duration::minutes(1),
+            5_000,
+            "Slack",
+            "general",
+            12,
+            1,
+            0,
+            Some("on it"),
+        )
+        .await
+        .unwrap();
+
+        let events = db.get_input_events(None, None, None, 10, 0).await.unwrap();
+        assert_eq!(events.len(), 2);
+        // oldest first
+        assert_eq!(events[0].app_name, "Code");
+        assert_eq!(events[0].duration_ms, 60_000);
+        assert_eq!(events[0].keystrokes, 120);
+        assert_eq!(events[0].typed_text, None);
+        assert_eq!(events[1].typed_text.as_deref(), Some("on it"));
+
+        let events = db
+            .get_input_events(None, None, Some("slack"), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].window_name, "general");
+
+        let events = db
+            .get_input_events(
+                Some(start + chrono::Duration::seconds(30)),
+                None,
+                None,
+                10,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].app_name, "Slack");
+    }
+
+    #[tokio::test]
+    async fn test_search_clipboard() {
+        let db = setup_test_db().await;
+        let start = Utc::now() - chrono::Duration::minutes(10);
+
+        db.insert_clipboard_entry(start, "Safari", "Docs", "meeting notes for monday")
+            .await
+            .unwrap();
+        db.insert_clipboard_entry(
+            start + chrono::Duration::minutes(1),
+            "Terminal",
+            "zsh",
+            "cargo test --workspace",
+        )
+        .await
+        .unwrap();
+
+        let search = |query: &'static str, app_name: Option<&'static str>| {
+            db.search(
+                query,
+                ContentType::Clipboard,
+                10,
+                0,
+                None,
+                None,
+                app_name,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
+            )
+        };
+
+        let results = search("", None).await.unwrap();
+        assert_eq!(results.len(), 2);
+        // newest first
+        let SearchResult::Clipboard(entry) = &results[0] else {
+            panic!("expected a clipboard result");
+        };
+        assert_eq!(entry.app_name, "Terminal");
+        assert_eq!(entry.text, "cargo test --workspace");
+
+        let results = search("meeting", None).await.unwrap();
+        assert_eq!(results.len(), 1);
+        let SearchResult::Clipboard(entry) = &results[0] else {
+            panic!("expected a clipboard result");
+        };
+        assert_eq!(entry.app_name, "Safari");
+        assert_eq!(entry.window_name, "Docs");
+
+        let results = search("", Some("terminal")).await.unwrap();
+        assert_eq!(results.len(), 1);
+
+        let count = db
+            .count_search_results(
+                "meeting",
+                ContentType::Clipboard,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // clipboard history only shows up when asked for
+        let results = db
+            .search(
+                "meeting",
+                ContentType::All,
+                10,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
+            )
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_idle_gaps() {
+        let db = setup_test_db().await;
+        let start = Utc::now() - chrono::Duration::hours(2);
+
+        let locked = db.insert_idle_gap(start, "screen_locked").await.unwrap();
+        db.end_idle_gap(locked, start + chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        db.insert_idle_gap(start + chrono::Duration::minutes(90), "no_input")
+            .await
+            .unwrap();
+
+        let gaps = db.get_idle_gaps(None, None, 10, 0).await.unwrap();
+        assert_eq!(gaps.len(), 2);
+        assert_eq!(gaps[0].reason, "screen_locked");
+        assert_eq!(gaps[0].end_time, Some(start + chrono::Duration::hours(1)));
+        // still away
+        assert_eq!(gaps[1].end_time, None);
+
+        // the open gap overlaps any later range
+        let gaps = db
+            .get_idle_gaps(Some(start + chrono::Duration::minutes(100)), None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].reason, "no_input");
+    }
+
+    #[tokio::test]
+    async fn test_replace_ocr_text() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("test"), Some(""), false)
+            .await
+            .unwrap();
+        // ocr deferred while capturing
+        db.insert_ocr_text(frame_id, "", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+
+        let replaced = db
+            .replace_ocr_text(
+                frame_id,
+                "quarterly report",
+                "",
+                Arc::new(OcrEngine::Tesseract),
+            )
+            .await
+            .unwrap();
+        assert!(replaced);
+
+        let results = db
+            .search(
+                "quarterly",
+                ContentType::OCR,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        // the frame is gone
+        let replaced = db
+            .replace_ocr_text(frame_id + 1, "late", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+        assert!(!replaced);
+    }
+
+    #[tokio::test]
+    async fn test_record_frame_repeat() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("test"), Some(""), false)
+            .await
+            .unwrap();
+        db.set_frame_phash(frame_id, u64::MAX).await.unwrap();
+        db.insert_ocr_text(frame_id, "static page", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+
+        let repeated_until = Utc::now();
+        db.record_frame_repeat(&[frame_id], repeated_until - chrono::Duration::seconds(1))
+            .await
+            .unwrap();
+        db.record_frame_repeat(&[frame_id], repeated_until)
+            .await
+            .unwrap();
+
+        let chunks = db
+            .find_video_chunks(
+                Utc::now() - chrono::Duration::minutes(1),
+                Utc::now() + chrono::Duration::minutes(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(chunks.frames.len(), 1);
+        assert_eq!(chunks.frames[0].repeat_count, 2);
+        assert_eq!(chunks.frames[0].last_repeat_at, Some(repeated_until));
+    }
+
+    #[tokio::test]
+    async fn test_timeline() {
+        let db = setup_test_db().await;
+        let start = Utc::now() - chrono::Duration::minutes(1);
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let mut frame_ids = Vec::new();
+        for (i, app) in ["chrome", "chrome", "slack"].into_iter().enumerate() {
+            let frame_id = db
+                .insert_frame(
+                    "test_device",
+                    Some(start + chrono::Duration::seconds(i as i64)),
+                    None,
+                    Some(app),
+                    Some(""),
+                    true,
+                )
+                .await
+                .unwrap();
+            db.insert_ocr_text(
+                frame_id,
+                &format!("{} text", app),
+                "",
+                Arc::new(OcrEngine::Tesseract),
+            )
+            .await
+            .unwrap();
+            frame_ids.push(frame_id);
+        }
+        db.insert_idle_gap(start + chrono::Duration::milliseconds(1500), "no_input")
+            .await
+            .unwrap();
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "Hello from audio",
+            0,
+            "",
+            &AudioDevice {
+                name: "test".to_string(),
+                device_type: DeviceType::Input,
+            },
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let end = Utc::now() + chrono::Duration::minutes(1);
+        let first = db
+            .get_timeline(start, end, &TimelineEventKind::ALL, None, 4)
+            .await
+            .unwrap();
+        let kinds: Vec<_> = first.events.iter().map(|event| event.kind()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TimelineEventKind::Frame,
+                TimelineEventKind::Frame,
+                TimelineEventKind::IdleGap,
+                TimelineEventKind::AppSwitch,
+            ]
+        );
+        match &first.events[3] {
+            TimelineEvent::AppSwitch(switch) => {
+                assert_eq!(switch.frame_id, frame_ids[2]);
+                assert_eq!(switch.previous_app_name, "chrome");
+                assert_eq!(switch.app_name, "slack");
+            }
+            event => panic!("expected an app switch, got {:?}", event),
+        }
+
+        let cursor: TimelineCursor = first.next_cursor.unwrap().parse().unwrap();
+        let second = db
+            .get_timeline(start, end, &TimelineEventKind::ALL, Some(cursor), 4)
+            .await
+            .unwrap();
+        assert_eq!(second.events.len(), 2);
+        match &second.events[0] {
+            TimelineEvent::Frame(frame) => {
+                assert_eq!(frame.frame_id, frame_ids[2]);
+                assert_eq!(frame.text_preview.as_deref(), Some("slack text"));
+            }
+            event => panic!("expected a frame, got {:?}", event),
+        }
+        assert_eq!(second.events[1].kind(), TimelineEventKind::Transcript);
+        assert_eq!(second.next_cursor, None);
+
+        // only the requested types
+        let transcripts = db
+            .get_timeline(start, end, &[TimelineEventKind::Transcript], None, 10)
+            .await
+            .unwrap();
+        assert_eq!(transcripts.events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_annotations() {
+        let db = setup_test_db().await;
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let mut frame_ids = Vec::new();
+        for minutes in [0, 5, 20] {
+            let frame_id = db
+                .insert_frame(
+                    "test_device",
+                    Some(start + chrono::Duration::minutes(minutes)),
+                    None,
+                    Some("zoom"),
+                    Some("call"),
+                    false,
+                )
+                .await
+                .unwrap();
+            db.insert_ocr_text(frame_id, "deploy notes", "", Arc::new(OcrEngine::Tesseract))
+                .await
+                .unwrap();
+            frame_ids.push(frame_id);
+        }
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        let transcription_id = db
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "let's ship the deploy",
+                0,
+                "",
+                &AudioDevice {
+                    name: "mic".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let client = vec!["client x".to_string()];
+        let meeting = db
+            .insert_annotation(
+                AnnotationTarget::Frame(frame_ids[0]),
+                Some("meeting with client X"),
+                &client,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(meeting.start_time, start);
+        assert_eq!(meeting.tags, client);
+        let repro = db
+            .insert_annotation(
+                AnnotationTarget::TimeRange(
+                    start + chrono::Duration::minutes(4),
+                    start + chrono::Duration::minutes(6),
+                ),
+                None,
+                &["bug repro".to_string()],
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        db.insert_annotation(
+            AnnotationTarget::Transcription(transcription_id),
+            None,
+            &client,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(db
+            .insert_annotation(AnnotationTarget::Frame(999), Some("nothing"), &[])
+            .await
+            .unwrap()
+            .is_none());
+
+        let search = |content_type: ContentType, tag: &'static str| {
+            db.search(
+                "",
+                content_type,
+                10,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(tag),
+                None,
+                SearchSort::Time,
+            )
+        };
+        let frames = |results: Vec<SearchResult>| -> Vec<i64> {
+            results
+                .iter()
+                .filter_map(|result| match result {
+                    SearchResult::OCR(ocr) => Some(ocr.frame_id),
+                    _ => None,
+                })
+                .collect()
+        };
+        let results = search(ContentType::OCR, "client x").await.unwrap();
+        assert_eq!(frames(results), vec![frame_ids[0]]);
+        // the range covers the second frame only
+        let results = search(ContentType::OCR, "bug repro").await.unwrap();
+        assert_eq!(frames(results), vec![frame_ids[1]]);
+        let results = search(ContentType::Audio, "client x").await.unwrap();
+        assert_eq!(results.len(), 1);
+        let SearchResult::Audio(audio) = &results[0] else {
+            panic!("expected an audio result");
+        };
+        assert_eq!(audio.transcription_id, transcription_id);
+        assert_eq!(search(ContentType::All, "client x").await.unwrap().len(), 2);
+        assert!(search(ContentType::Clipboard, "client x")
+            .await
+            .unwrap()
+            .is_empty());
+        let count = db
+            .count_search_results(
+                "deploy",
+                ContentType::All,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some("client x"),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let list =
+            |tag: Option<&'static str>| db.list_annotations(None, None, None, None, tag, 10, 0);
+        let all = list(None).await.unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].id, meeting.id);
+        assert_eq!(all[1].id, repro.id);
+        assert_eq!(all[2].audio_transcription_id, Some(transcription_id));
+        assert_eq!(list(Some("client x")).await.unwrap().len(), 2);
+        let at_five = db
+            .list_annotations(
+                Some(start + chrono::Duration::minutes(5)),
+                Some(start + chrono::Duration::minutes(5)),
+                None,
+                None,
+                None,
+                10,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(at_five.len(), 1);
+        assert_eq!(at_five[0].id, repro.id);
+
+        let updated = db
+            .update_annotation(meeting.id, Some(""), Some(&["done".to_string()]))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.note, None);
+        assert_eq!(updated.tags, vec!["done".to_string()]);
+        assert_eq!(
+            frames(search(ContentType::OCR, "done").await.unwrap()),
+            vec![frame_ids[0]]
+        );
+        assert!(search(ContentType::OCR, "client x")
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(db
+            .update_annotation(999, Some("note"), None)
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(db.delete_annotation(repro.id).await.unwrap());
+        assert!(!db.delete_annotation(repro.id).await.unwrap());
+        assert!(search(ContentType::OCR, "bug repro")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_by_meeting() {
+        let db = setup_test_db().await;
+        let start = Utc::now() - chrono::Duration::hours(3);
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        // one capture during the meeting, one after it
+        for minutes in [10, 120] {
+            let timestamp = start + chrono::Duration::minutes(minutes);
+            let frame_id = db
+                .insert_frame("test_device", Some(timestamp), None, None, None, false)
+                .await
+                .unwrap();
+            db.insert_ocr_text(frame_id, "roadmap", "", Arc::new(OcrEngine::Tesseract))
+                .await
+                .unwrap();
+            let audio_chunk_id = db
+                .get_or_insert_audio_chunk(&format!("audio_{}.mp4", minutes), timestamp)
+                .await
+                .unwrap();
+            db.insert_audio_transcription_at(
+                audio_chunk_id,
+                "roadmap",
+                0,
+                "",
+                &AudioDevice {
+                    name: "mic".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                None,
+                None,
+                None,
+                None,
+                None,
+                timestamp,
+            )
+            .await
+            .unwrap();
+        }
+
+        let attendees = vec!["Jane Doe".to_string(), "bob@example.com".to_string()];
+        let event = db
+            .upsert_calendar_event(
+                "caldav",
+                "sprint-1",
+                "Sprint planning",
+                &attendees,
+                None,
+                start,
+                start + chrono::Duration::minutes(30),
+            )
+            .await
+            .unwrap();
+        assert_eq!(event.attendees, attendees);
+        // pulled again after being renamed
+        let event = db
+            .upsert_calendar_event(
+                "caldav",
+                "sprint-1",
+                "Sprint planning (week 2)",
+                &attendees,
+                Some("room 4"),
+                start,
+                start + chrono::Duration::minutes(30),
+            )
+            .await
+            .unwrap();
+        assert_eq!(event.title, "Sprint planning (week 2)");
+        assert_eq!(event.location.as_deref(), Some("room 4"));
+
+        let search = |meeting: &'static str| {
+            db.search(
+                "roadmap",
+                ContentType::All,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(meeting),
+                SearchSort::Time,
+            )
+        };
+        let results = search("sprint planning").await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results
+            .iter()
+            .any(|result| matches!(result, SearchResult::OCR(_))));
+        assert!(results
+            .iter()
+            .any(|result| matches!(result, SearchResult::Audio(_))));
+        assert!(search("standup").await.unwrap().is_empty());
+        let count = db
+            .count_search_results(
+                "roadmap",
+                ContentType::All,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some("Sprint"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let events = db
+            .list_calendar_events(Some(start), None, Some("sprint"), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+
+        // gone from the calendar
+        let deleted = db
+            .delete_calendar_events_except(
+                "caldav",
+                start - chrono::Duration::days(1),
+                start + chrono::Duration::days(1),
+                &[],
+            )
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert!(search("sprint").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_voice_profiles() {
+        let db = setup_test_db().await;
+        // voices far apart from each other
+        let voice = |speaker: usize, variation: f32| -> Vec<f32> {
+            (0..512)
+                .map(|i| match i % 4 {
+                    n if n == speaker => 1.0,
+                    n if n == (speaker + 1) % 4 => variation,
+                    _ => 0.0,
+                })
+                .collect()
+        };
+
+        // found by diarization before alice enrolled
+        let unnamed = db.insert_speaker(&voice(0, 0.0)).await.unwrap();
+        let alice = db.enroll_speaker("Alice", &[voice(0, 0.2)]).await.unwrap();
+        assert_ne!(alice.id, unnamed.id);
+        assert_eq!(alice.name, "Alice");
+        // the profile wins over the closer speaker diarization found
+        let matched = db
+            .get_speaker_from_embedding(&voice(0, 0.0))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(matched.id, alice.id);
+        assert!(db
+            .get_speaker_from_embedding(&voice(2, 0.0))
+            .await
+            .unwrap()
+            .is_none());
+
+        let again = db
+            .enroll_speaker("alice", &[voice(0, 0.1), voice(0, 0.3)])
+            .await
+            .unwrap();
+        assert_eq!(again.id, alice.id);
+        let profiles = db.list_voice_profiles().await.unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].embedding_count, 3);
+        assert_eq!(
+            db.get_speaker_ids_by_name("ALICE ").await.unwrap(),
+            vec![alice.id]
+        );
+
+        // a speaker named like the profile becomes it
+        let bob = db.insert_speaker(&voice(1, 0.0)).await.unwrap();
+        db.update_speaker_name(bob.id, "Bob").await.unwrap();
+        let enrolled_bob = db.enroll_speaker("bob", &[voice(1, 0.1)]).await.unwrap();
+        assert_eq!(enrolled_bob.id, bob.id);
+
+        // merging keeps the profile
+        db.merge_speakers(unnamed.id, alice.id).await.unwrap();
+        let profiles = db.list_voice_profiles().await.unwrap();
+        assert_eq!(
+            profiles
+                .iter()
+                .map(|profile| (profile.id, profile.embedding_count))
+                .collect::<Vec<_>>(),
+            vec![(bob.id, 2), (unnamed.id, 4)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_media_blocks() {
+        let db = setup_test_db().await;
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let at = |seconds: i64| start + chrono::Duration::seconds(seconds);
+        let speakers = AudioDevice {
+            name: "speakers".to_string(),
+            device_type: DeviceType::Output,
+        };
+        let mic = AudioDevice {
+            name: "mic".to_string(),
+            device_type: DeviceType::Input,
+        };
+
+        // overlapping chunks of the same song
+        let song = db
+            .record_media_block(&speakers, "music", 0.85, at(0), at(30), None)
+            .await
+            .unwrap();
+        let same = db
+            .record_media_block(&speakers, "music", 0.95, at(28), at(58), None)
+            .await
+            .unwrap();
+        assert_eq!(same, song);
+        // another device, then music again after a long pause
+        db.record_media_block(&mic, "music", 0.9, at(10), at(40), None)
+            .await
+            .unwrap();
+        let later = db
+            .record_media_block(&speakers, "music", 0.9, at(600), at(630), None)
+            .await
+            .unwrap();
+        assert_ne!(later, song);
+
+        let blocks = db.get_media_blocks(None, None, 10, 0).await.unwrap();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0].id, song);
+        assert_eq!((blocks[0].start_time, blocks[0].end_time), (at(0), at(58)));
+        assert_eq!(blocks[0].confidence, 0.95);
+        assert_eq!(blocks[0].classification, "music");
+        assert!(!blocks[0].is_input);
+        assert_eq!(blocks[1].device_name, "mic");
+
+        let blocks = db
+            .get_media_blocks(Some(at(100)), None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].id, later);
+
+        let page = db
+            .get_timeline(at(0), at(700), &TimelineEventKind::ALL, None, 10)
+            .await
+            .unwrap();
+        assert_eq!(page.events.len(), 3);
+        assert!(page
+            .events
+            .iter()
+            .all(|event| event.kind() == TimelineEventKind::MediaBlock));
+        match &page.events[2] {
+            TimelineEvent::MediaBlock(block) => assert_eq!(block.id, later),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_media_block_chunks() {
+        let db = setup_test_db().await;
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let at = |seconds: i64| start + chrono::Duration::seconds(seconds);
+        let speakers = AudioDevice {
+            name: "speakers".to_string(),
+            device_type: DeviceType::Output,
+        };
+
+        let first = db
+            .insert_audio_chunk_at("speakers_1.mp4", at(0))
+            .await
+            .unwrap();
+        let second = db
+            .insert_audio_chunk_at("speakers_2.mp4", at(28))
+            .await
+            .unwrap();
+        let block = db
+            .record_media_block(&speakers, "music", 0.9, at(0), at(30), Some(first))
+            .await
+            .unwrap();
+        db.record_media_block(&speakers, "music", 0.9, at(28), at(58), Some(second))
+            .await
+            .unwrap();
+
+        let chunks = db.get_media_block_chunks(block).await.unwrap();
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.id).collect::<Vec<_>>(),
+            vec![first, second]
+        );
+        assert_eq!(
+            db.get_media_block(block)
+                .await
+                .unwrap()
+                .unwrap()
+                .device_name,
+            "speakers"
+        );
+
+        let deleted = db.delete_media_block(block).await.unwrap();
+        assert_eq!(deleted.len(), 2);
+        assert!(db.get_media_block(block).await.unwrap().is_none());
+        assert!(db.get_media_block_chunks(block).await.unwrap().is_empty());
+        let kept: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audio_chunks")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(kept, 0);
+    }
+
+    #[tokio::test]
+    async fn test_transcript_words() {
+        let db = setup_test_db().await;
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        let transcription_id = db
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "see you tomorrow",
+                0,
+                "whisper-large",
+                &AudioDevice {
+                    name: "mic".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                None,
+                Some(2.0),
+                Some(4.0),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let word = |word: &str, start_time: f64, confidence: f64| TranscriptWord {
+            word: word.to_string(),
+            start_time,
+            end_time: start_time + 0.5,
+            confidence,
+        };
+        let words = vec![
+            word("see", 2.0, 0.9),
+            word("you", 2.5, 0.8),
+            word("tomorrow", 3.0, 0.4),
+        ];
+        db.insert_transcript_words(transcription_id, &words)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_transcript_words(transcription_id).await.unwrap(),
+            words
+        );
+        assert!(db
+            .get_transcript_words(transcription_id + 1)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // words removed as overlap of the next chunk lose their timings
+        db.update_audio_transcription(audio_chunk_id, "see you")
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_transcript_words(transcription_id).await.unwrap(),
+            words[..2]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vocabulary_terms() {
+        let db = setup_test_db().await;
+        let everywhere = db
+            .insert_vocabulary_term("screenpipe", Some("screen pipe"), None)
+            .await
+            .unwrap();
+        let in_slack = db
+            .insert_vocabulary_term("LGTM", None, Some("Slack"))
+            .await
+            .unwrap();
+        db.insert_vocabulary_term("Kubernetes", None, Some("Terminal"))
+            .await
+            .unwrap();
+        assert_eq!(everywhere.sounds_like.as_deref(), Some("screen pipe"));
+
+        assert_eq!(db.list_vocabulary_terms(None).await.unwrap().len(), 3);
+        let terms: Vec<String> = db
+            .list_vocabulary_terms(Some("slack"))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.term)
+            .collect();
+        assert_eq!(terms, vec!["screenpipe", "LGTM"]);
+
+        assert!(db.delete_vocabulary_term(in_slack.id).await.unwrap());
+        assert!(!db.delete_vocabulary_term(in_slack.id).await.unwrap());
+        assert_eq!(
+            db.list_vocabulary_terms(Some("Slack")).await.unwrap(),
+            vec![everywhere]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sample_frames() {
+        let db = setup_test_db().await;
+        db.insert_video_chunk("monitor_1.mp4", "monitor_1")
+            .await
+            .unwrap();
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let mut ids = Vec::new();
+        for minute in 0..10 {
+            let timestamp = start + chrono::Duration::minutes(minute);
+            ids.push(
+                db.insert_frame("monitor_1", Some(timestamp), None, None, None, false)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let end = start + chrono::Duration::minutes(9);
+
+        let sampled: Vec<i64> = db
+            .sample_frames(start, end, None, 4)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|frame| frame.id)
+            .collect();
+        assert_eq!(sampled, vec![ids[0], ids[3], ids[5], ids[8]]);
+
+        // fewer frames than asked for
+        let all = db.sample_frames(start, end, None, 50).await.unwrap();
+        assert_eq!(all.len(), 10);
+        assert_eq!(all[0].file_path, "monitor_1.mp4");
+        assert_eq!(all[9].offset_index, 9);
+
+        assert!(db
+            .sample_frames(start, end, Some("monitor_2"), 4)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(db
+            .sample_frames(start, end, None, 0)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_usage_buckets() {
+        let db = setup_test_db().await;
+        let day = Utc.with_ymd_and_hms(2025, 4, 22, 0, 0, 0).unwrap();
+        let at = |h: i64, m: i64, s: i64| {
+            day + chrono::Duration::hours(h)
+                + chrono::Duration::minutes(m)
+                + chrono::Duration::seconds(s)
+        };
+
+        db.insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        for (timestamp, app_name, focused, text) in [
+            (at(9, 0, 0), "Code", true, "fn main() {\n    println!"),
+            (at(9, 0, 30), "Code", true, ""),
+            (at(9, 0, 40), "Finder", false, "Downloads"),
+            (at(9, 1, 0), "Slack", true, "lunch?"),
+            (at(9, 1, 10), "Code", true, ""),
+            // long after, only counts up to a minute
+            (at(9, 10, 10), "Code", true, ""),
+            (at(23, 30, 0), "Slack", true, ""),
+        ] {
+            let frame_id = db
+                .insert_frame(
+                    "test_device",
+                    Some(timestamp),
+                    None,
+                    Some(app_name),
+                    Some(""),
+                    focused,
+                )
+                .await
+                .unwrap();
+            db.insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+                .await
+                .unwrap();
+        }
+        let end = day + chrono::Duration::days(2);
+
+        let usage = db
+            .get_app_usage_buckets(day, end, UsageBucket::Day, 0)
+            .await
+            .unwrap();
+        let usage: Vec<(&str, i64)> = usage
+            .iter()
+            .map(|bucket| {
+                assert_eq!(bucket.bucket_start, day);
+                (bucket.app_name.as_str(), bucket.seconds)
+            })
+            .collect();
+        // gaps to the next focused frame, long ones count for a minute
+        assert_eq!(usage, vec![("Code", 30 + 30 + 60 + 60), ("Slack", 10)]);
+
+        let switches = db
+            .get_context_switch_buckets(day, end, UsageBucket::Hour, 0)
+            .await
+            .unwrap();
+        assert_eq!(switches.len(), 2);
+        assert_eq!(switches[0].bucket_start, at(9, 0, 0));
+        assert_eq!(switches[0].switches, 2);
+        assert_eq!(switches[1].bucket_start, at(23, 0, 0));
+        assert_eq!(switches[1].switches, 1);
+
+        // a day starting an hour earlier has the late frame in the next day
+        let shifted = db
+            .get_context_switch_buckets(day, end, UsageBucket::Day, 3600)
+            .await
+            .unwrap();
+        assert_eq!(shifted.len(), 2);
+        assert_eq!(shifted[0].bucket_start, day - chrono::Duration::hours(1));
+        assert_eq!(shifted[1].bucket_start, at(23, 0, 0));
+
+        let words = db
+            .get_word_count_buckets(day, end, UsageBucket::Day, 0)
+            .await
+            .unwrap();
+        let words: Vec<(&str, i64)> = words
+            .iter()
+            .map(|bucket| (bucket.app_name.as_str(), bucket.words))
+            .collect();
+        assert_eq!(words, vec![("Code", 4), ("Finder", 1), ("Slack", 1)]);
+
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        for (start, end) in [(Some(0.0), Some(4.0)), (Some(5.0), Some(7.0)), (None, None)] {
+            db.insert_audio_transcription_at(
+                audio_chunk_id,
+                "hello",
+                0,
+                "",
+                &AudioDevice {
+                    name: "test".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                None,
+                start,
+                end,
+                None,
+                None,
+                at(10, 0, 0),
+            )
+            .await
+            .unwrap();
+        }
+        let speaking = db
+            .get_speaking_time_buckets(day, end, UsageBucket::Day, 0)
+            .await
+            .unwrap();
+        assert_eq!(speaking.len(), 1);
+        assert_eq!(speaking[0].speaker_id, None);
+        assert_eq!(speaking[0].seconds, 6);
+    }
+
+    #[tokio::test]
+    async fn test_focus_events() {
+        let db = setup_test_db().await;
+        let day = Utc.with_ymd_and_hms(2025, 4, 22, 0, 0, 0).unwrap();
+        let at = |h: i64, m: i64, s: i64| {
+            day + chrono::Duration::hours(h)
+                + chrono::Duration::minutes(m)
+                + chrono::Duration::seconds(s)
+        };
+        let max_gap = std::time::Duration::from_secs(300);
+
+        let mut ids = Vec::new();
+        for (timestamp, app_name, window_name) in [
+            (at(9, 0, 0), "Code", "main.rs"),
+            (at(9, 0, 30), "Code", "main.rs"),
+            (at(9, 1, 0), "Code", "lib.rs"),
+            (at(9, 2, 0), "Slack", "general"),
+            (at(9, 2, 20), "Code", "lib.rs"),
+            (at(9, 5, 0), "Code", "lib.rs"),
+            // the monitor went quiet, not a switch
+            (at(9, 20, 0), "Safari", "docs"),
+            (at(9, 20, 30), "Mail", "inbox"),
+            (at(9, 21, 0), "Safari", "docs"),
+        ] {
+            ids.push(
+                db.record_focus(app_name, window_name, timestamp, max_gap)
+                    .await
+                    .unwrap(),
+            );
+        }
+        assert_eq!(ids[0], ids[1]);
+        assert_eq!(ids[4], ids[5]);
+
+        let end = day + chrono::Duration::days(1);
+        let events = db.list_focus_events(day, end, None, 100, 0).await.unwrap();
+        let events: Vec<(&str, &str, i64)> = events
+            .iter()
+            .map(|event| {
+                (
+                    event.app_name.as_str(),
+                    event.window_name.as_str(),
+                    event.seconds,
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                ("Code", "main.rs", 60),
+                ("Code", "lib.rs", 60),
+                ("Slack", "general", 20),
+                ("Code", "lib.rs", 160),
+                ("Safari", "docs", 30),
+                ("Mail", "inbox", 30),
+                ("Safari", "docs", 0),
+            ]
+        );
+        let slack = db
+            .list_focus_events(day, end, Some("Slack"), 100, 0)
+            .await
+            .unwrap();
+        assert_eq!(slack.len(), 1);
+
+        let streaks = db.get_focus_streaks(day, end, 2).await.unwrap();
+        let streaks: Vec<(&str, i64, i64)> = streaks
+            .iter()
+            .map(|streak| (streak.app_name.as_str(), streak.seconds, streak.windows))
+            .collect();
+        assert_eq!(streaks, vec![("Code", 160, 1), ("Code", 120, 2)]);
+
+        let switches = db
+            .get_focus_switch_buckets(day, end, UsageBucket::Hour, 0)
+            .await
+            .unwrap();
+        assert_eq!(switches.len(), 1);
+        assert_eq!(switches[0].bucket_start, at(9, 0, 0));
+        assert_eq!(switches[0].switches, 4);
+
+        let interruptions = db.get_focus_interruptions(day, end, 120, 10).await.unwrap();
+        let interruptions: Vec<(&str, i64, i64)> = interruptions
+            .iter()
+            .map(|app| (app.app_name.as_str(), app.interruptions, app.seconds))
+            .collect();
+        assert_eq!(interruptions, vec![("Mail", 1, 30), ("Slack", 1, 20)]);
+        assert!(db
+            .get_focus_interruptions(day, end, 10, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_video_chunk_spans() {
+        let db = setup_test_db().await;
+        // whole seconds, so they read back the same
+        let start = chrono::DateTime::from_timestamp(Utc::now().timestamp() - 3600, 0).unwrap();
+        let minute = |m: i64| start + chrono::Duration::minutes(m);
+
+        db.insert_video_chunk("monitor_1_a.mp4", "monitor_1")
+            .await
+            .unwrap();
+        for m in 0..3 {
+            db.insert_frame("monitor_1", Some(minute(m)), None, None, None, false)
+                .await
+                .unwrap();
+        }
+        db.insert_video_chunk("monitor_2.mp4", "monitor_2")
+            .await
+            .unwrap();
+        db.insert_frame("monitor_2", Some(minute(4)), None, None, None, false)
+            .await
+            .unwrap();
+        db.insert_video_chunk("monitor_1_b.mp4", "monitor_1")
+            .await
+            .unwrap();
+        for m in 5..7 {
+            db.insert_frame("monitor_1", Some(minute(m)), None, None, None, false)
+                .await
+                .unwrap();
+        }
+
+        let spans = db
+            .get_video_chunk_spans(minute(1), minute(10), None)
+            .await
+            .unwrap();
+        let paths: Vec<&str> = spans.iter().map(|span| span.file_path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["monitor_1_a.mp4", "monitor_2.mp4", "monitor_1_b.mp4"]
+        );
+        assert_eq!(spans[0].start_time, minute(0));
+        assert_eq!(spans[0].end_time, minute(2));
+        assert_eq!(spans[0].frame_count, 3);
+
+        let spans = db
+            .get_video_chunk_spans(minute(3), minute(10), Some("monitor_1"))
+            .await
+            .unwrap();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].file_path, "monitor_1_b.mp4");
+
+        let span = db.get_video_chunk_span(spans[0].id).await.unwrap().unwrap();
+        assert_eq!(span, spans[0]);
+        assert!(db.get_video_chunk_span(-1).await.unwrap().is_none());
+    }
 }
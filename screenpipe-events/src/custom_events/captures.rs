@@ -0,0 +1,45 @@
+//! Notifications sent when a capture is stored, so that clients can react
+//! without polling the database.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A window of a screen capture was stored with its text.
+pub const FRAME_INDEXED_EVENT: &str = "frame_indexed";
+/// An audio transcript was stored.
+pub const TRANSCRIPT_INDEXED_EVENT: &str = "transcript_indexed";
+/// The focused app of a monitor changed.
+pub const APP_SWITCHED_EVENT: &str = "app_switched";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameIndexedEvent {
+    pub frame_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub device_name: String,
+    pub app_name: String,
+    pub window_name: String,
+    pub focused: bool,
+    /// Whether OCR waits in the background queue, the text is empty until then
+    pub ocr_deferred: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptIndexedEvent {
+    pub transcription_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub device: String,
+    pub is_input: bool,
+    pub speaker_id: Option<i64>,
+    pub transcription: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppSwitchedEvent {
+    /// First frame of the new app
+    pub frame_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub device_name: String,
+    pub app_name: String,
+    pub window_name: String,
+    pub previous_app_name: String,
+}
@@ -0,0 +1,19 @@
+//! Notifications about the audio devices being recorded.
+
+use serde::{Deserialize, Serialize};
+
+/// Recording moved from one audio device to another, e.g. a headset was
+/// plugged in or the laptop was undocked.
+pub const AUDIO_DEVICE_SWITCHED_EVENT: &str = "audio_device_switched";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioDeviceSwitchedEvent {
+    /// "input" or "output"
+    pub device_type: String,
+    /// Device recorded before the switch, if any
+    pub previous_device: Option<String>,
+    /// Device recorded from now on, `None` when no device is left
+    pub device: Option<String>,
+    /// "disconnected", "reconnected" or "priority"
+    pub reason: String,
+}
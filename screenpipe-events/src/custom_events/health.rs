@@ -0,0 +1,16 @@
+//! Notifications about the state of the recording.
+
+use serde::{Deserialize, Serialize};
+
+/// The health of the recording changed, e.g. audio stopped being recorded.
+pub const HEALTH_CHANGED_EVENT: &str = "health_changed";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthChangedEvent {
+    pub status: String,
+    pub frame_status: String,
+    pub audio_status: String,
+    pub ui_status: String,
+    /// Overall status before the change
+    pub previous_status: String,
+}
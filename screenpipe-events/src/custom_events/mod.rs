@@ -1 +1,5 @@
+pub mod captures;
+pub mod devices;
+pub mod health;
 pub mod meetings;
+pub mod searches;
@@ -0,0 +1,16 @@
+//! Notifications about searches run in the background.
+
+use serde::{Deserialize, Serialize};
+
+/// A saved search matched new recordings.
+pub const SAVED_SEARCH_MATCHED_EVENT: &str = "saved_search_matched";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearchMatchedEvent {
+    pub saved_search_id: i64,
+    pub name: String,
+    pub query: String,
+    pub matches: usize,
+    /// Start of the text of the newest match
+    pub preview: String,
+}
@@ -4,4 +4,8 @@ pub use events_manager::*;
 
 mod custom_events;
 
+pub use custom_events::captures::*;
+pub use custom_events::devices::*;
+pub use custom_events::health::*;
 pub use custom_events::meetings::*;
+pub use custom_events::searches::*;
@@ -0,0 +1,118 @@
+import type { Annotation } from "./types";
+
+type Result<T> = { success: true; data: T } | { success: false; error: any };
+
+export interface AnnotationParams {
+  /** Frame to annotate */
+  frameId?: number;
+  /** Transcript segment to annotate, the `transcriptionId` of audio results */
+  audioTranscriptionId?: number;
+  /** Time range to annotate when no frame or transcript segment is given */
+  startTime?: string;
+  endTime?: string;
+  note?: string;
+  tags?: string[];
+}
+
+export interface ListAnnotationsParams {
+  startTime?: string;
+  endTime?: string;
+  frameId?: number;
+  audioTranscriptionId?: number;
+  tag?: string;
+  limit?: number;
+  offset?: number;
+}
+
+export class AnnotationsManager {
+  private apiUrl = "http://localhost:3030";
+
+  private async request<T>(path: string, init: RequestInit): Promise<T> {
+    const response = await fetch(`${this.apiUrl}${path}`, {
+      ...init,
+      headers: { "Content-Type": "application/json" },
+    });
+    const body = await response.json();
+    if (!response.ok) {
+      throw new Error(body.error ?? `http error! status: ${response.status}`);
+    }
+    return body.data;
+  }
+
+  async list(params: ListAnnotationsParams = {}): Promise<Result<Annotation[]>> {
+    try {
+      const query = new URLSearchParams();
+      const fields: Record<string, string | number | undefined> = {
+        start_time: params.startTime,
+        end_time: params.endTime,
+        frame_id: params.frameId,
+        audio_transcription_id: params.audioTranscriptionId,
+        tag: params.tag,
+        limit: params.limit,
+        offset: params.offset,
+      };
+      for (const [key, value] of Object.entries(fields)) {
+        if (value !== undefined) {
+          query.append(key, value.toString());
+        }
+      }
+      const data = await this.request<Annotation[]>(`/annotations?${query}`, {
+        method: "GET",
+      });
+      return { success: true, data };
+    } catch (error) {
+      console.error("failed to list annotations:", error);
+      return { success: false, error: error };
+    }
+  }
+
+  async create(params: AnnotationParams): Promise<Result<Annotation>> {
+    try {
+      const data = await this.request<Annotation>("/annotations", {
+        method: "POST",
+        body: JSON.stringify({
+          frame_id: params.frameId,
+          audio_transcription_id: params.audioTranscriptionId,
+          start_time: params.startTime,
+          end_time: params.endTime,
+          note: params.note,
+          tags: params.tags ?? [],
+        }),
+      });
+      return { success: true, data };
+    } catch (error) {
+      console.error("failed to create annotation:", error);
+      return { success: false, error: error };
+    }
+  }
+
+  /** Replaces the note and/or tags, an empty note clears it */
+  async update(
+    id: number,
+    changes: { note?: string; tags?: string[] }
+  ): Promise<Result<Annotation>> {
+    try {
+      const data = await this.request<Annotation>(`/annotations/${id}`, {
+        method: "POST",
+        body: JSON.stringify(changes),
+      });
+      return { success: true, data };
+    } catch (error) {
+      console.error("failed to update annotation:", error);
+      return { success: false, error: error };
+    }
+  }
+
+  async delete(id: number): Promise<boolean> {
+    try {
+      const response = await fetch(`${this.apiUrl}/annotations/${id}`, {
+        method: "DELETE",
+        headers: { "Content-Type": "application/json" },
+      });
+      return response.ok;
+    } catch (error) {
+      console.error("failed to delete annotation:", error);
+      return false;
+    }
+  }
+}
@@ -10,7 +10,9 @@ export type ContentType =
   | "ui"
   | "audio+ui"
   | "ocr+ui"
-  | "audio+ocr";
+  | "audio+ocr"
+  | "table"
+  | "clipboard";
 
 /**
  * Parameters for querying Screenpipe.
@@ -57,6 +59,42 @@ export interface ScreenpipeQueryParams {
 
   /** Filter by browser URL (for web content) */
   browserUrl?: string;
+
+  /** Filter by a tag of an annotation (e.g. "client x") */
+  tag?: string;
+
+  /** Add the frames and transcripts this many seconds around each result to it, up to 300 */
+  contextSeconds?: number;
+
+  /** `nextCursor` of the previous page, for the results after it. Unlike `offset` it stays fast on deep pages */
+  cursor?: string;
+}
+
+/**
+ * Frames and transcripts around a search result, oldest first.
+ */
+export interface SearchContext {
+  frames: {
+    id: number;
+    timestamp: string;
+    deviceName: string;
+    appName?: string;
+    windowName?: string;
+    browserUrl?: string;
+    focused: boolean;
+    ocrText?: string;
+  }[];
+  transcripts: {
+    id: number;
+    timestamp: string;
+    deviceName: string;
+    isInput: boolean;
+    speakerId?: number;
+    speakerName?: string;
+    transcription: string;
+    startTime?: number;
+    endTime?: number;
+  }[];
 }
 
 /**
@@ -76,12 +114,16 @@ export interface OCRContent {
   browserUrl?: string;
   focused?: boolean;
   deviceName: string;
+  /** With `contextSeconds` */
+  context?: SearchContext;
 }
 
 /**
  * Structure of audio content.
  */
 export interface AudioContent {
+  /** Id of the transcript segment, to annotate it */
+  transcriptionId: number;
   chunkId: number;
   transcription: string;
   timestamp: string;
@@ -93,6 +135,8 @@ export interface AudioContent {
   speaker?: Speaker;
   startTime?: number;
   endTime?: number;
+  /** With `contextSeconds` */
+  context?: SearchContext;
 }
 
 /**
@@ -109,6 +153,40 @@ export interface UiContent {
   offsetIndex: number;
   frameName?: string;
   browserUrl?: string;
+  /** With `contextSeconds` */
+  context?: SearchContext;
+}
+
+/**
+ * Structure of a table detected in a frame.
+ */
+export interface TableContent {
+  tableId: number;
+  frameId: number;
+  /** Cell values, one array per row */
+  rows: string[][];
+  bounds: { left: number; top: number; width: number; height: number };
+  timestamp: string;
+  filePath: string;
+  offsetIndex: number;
+  appName: string;
+  windowName: string;
+  deviceName: string;
+  browserUrl?: string;
+  /** With `contextSeconds` */
+  context?: SearchContext;
+}
+
+/**
+ * Structure of text copied to the clipboard.
+ */
+export interface ClipboardContent {
+  id: number;
+  timestamp: string;
+  /** App that had the focus when the text was copied */
+  appName: string;
+  windowName: string;
+  text: string;
 }
 
 /**
@@ -126,7 +204,9 @@ export interface Speaker {
 export type ContentItem =
   | { type: "OCR"; content: OCRContent }
   | { type: "Audio"; content: AudioContent }
-  | { type: "UI"; content: UiContent };
+  | { type: "UI"; content: UiContent }
+  | { type: "Table"; content: TableContent }
+  | { type: "Clipboard"; content: ClipboardContent };
 
 /**
  * Pagination information for search results.
@@ -135,6 +215,8 @@ export interface PaginationInfo {
   limit: number;
   offset: number;
   total: number;
+  /** Cursor of the next page, when the page is full */
+  nextCursor?: string;
 }
 
 /**
@@ -392,3 +474,18 @@ export interface TypeTextRequest {
   selector: ElementSelector;
   text: string;
 }
+
+/**
+ * A note and tags attached to a frame, a transcript segment or a time range.
+ */
+export interface Annotation {
+  id: number;
+  frame_id: number | null;
+  audio_transcription_id: number | null;
+  start_time: string;
+  end_time: string;
+  note: string | null;
+  tags: string[];
+  created_at: string;
+  updated_at: string;
+}
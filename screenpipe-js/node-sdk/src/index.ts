@@ -7,6 +7,7 @@ import { toSnakeCase, convertObjectToCamelCase } from "../../common/utils";
 import { SettingsManager } from "./SettingsManager";
 import { InboxManager } from "./InboxManager";
 import { PipesManager } from "../../common/PipesManager";
+import { AnnotationsManager } from "../../common/AnnotationsManager";
 import {
   captureEvent,
   captureMainFeatureEvent,
@@ -27,6 +28,7 @@ class NodePipe {
   public settings = new SettingsManager();
   public inbox = new InboxManager();
   public pipes = new PipesManager();
+  public annotations = new AnnotationsManager();
   public operator = new Operator();
   public async sendDesktopNotification(
     options: NotificationOptions
@@ -0,0 +1,644 @@
+//! Labels each recorded time block with what the user was doing: in a
+//! meeting, coding, browsing, writing, or something else. The label comes
+//! from the focused app, the screen text and whether there was audio, and is
+//! stored in `activity_segments` for productivity dashboards.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::{DateTime, Duration, Utc};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_db::{ActivitySegment, DatabaseManager, FrameEntry, UsageBucket};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{debug, error, info};
+
+use crate::query_cache::QueryKey;
+use crate::server::{AppState, PaginationQuery};
+
+/// Length of a classified time block.
+pub const BLOCK: Duration = Duration::minutes(5);
+/// How long after a block ends it is classified, its OCR text and
+/// transcripts are stored a bit later than its frames.
+const SETTLE: Duration = Duration::minutes(2);
+/// Frames of a block looked at, a few monitors at 1 fps.
+const MAX_FRAMES: u32 = 2000;
+/// How long to wait for the next block once all are classified.
+const IDLE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+const MEETING_APPS: &[&str] = &["zoom", "teams", "webex", "facetime", "skype", "google meet"];
+const MEETING_SITES: &[&str] = &[
+    "meet.google.com",
+    "zoom.us/j",
+    "zoom.us/wc",
+    "teams.microsoft.com",
+    "teams.live.com",
+    "webex.com",
+    "whereby.com",
+];
+const CODING_APPS: &[&str] = &[
+    "code",
+    "visual studio",
+    "cursor",
+    "windsurf",
+    "xcode",
+    "intellij",
+    "pycharm",
+    "webstorm",
+    "goland",
+    "rustrover",
+    "clion",
+    "android studio",
+    "sublime text",
+    "zed",
+    "vim",
+    "nvim",
+    "emacs",
+    "terminal",
+    "iterm2",
+    "warp",
+    "alacritty",
+    "kitty",
+    "wezterm",
+    "ghostty",
+    "powershell",
+    "windowsterminal",
+];
+const CODING_SITES: &[&str] = &[
+    "github.com",
+    "gitlab.com",
+    "bitbucket.org",
+    "stackoverflow.com",
+    "localhost",
+    "127.0.0.1",
+];
+const WRITING_APPS: &[&str] = &[
+    "word",
+    "pages",
+    "notion",
+    "obsidian",
+    "notes",
+    "bear",
+    "ulysses",
+    "scrivener",
+    "typora",
+    "ia writer",
+    "libreoffice",
+    "google docs",
+];
+const WRITING_SITES: &[&str] = &["docs.google.com", "notion.so", "overleaf.com", "hackmd.io"];
+const BROWSERS: &[&str] = &[
+    "chrome", "chromium", "safari", "firefox", "arc", "edge", "msedge", "brave", "opera", "vivaldi",
+];
+/// Starts of lines of source code, in any of the common languages.
+const CODE_LINE_STARTS: &[&str] = &[
+    "fn ",
+    "pub ",
+    "use ",
+    "impl ",
+    "def ",
+    "class ",
+    "import ",
+    "from ",
+    "const ",
+    "let ",
+    "var ",
+    "func ",
+    "return ",
+    "if (",
+    "for (",
+    "#include",
+    "package ",
+    "function ",
+];
+
+/// What the user was doing during a time block.
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityCategory {
+    Meeting,
+    Coding,
+    Browsing,
+    Writing,
+    Other,
+}
+
+impl ActivityCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityCategory::Meeting => "meeting",
+            ActivityCategory::Coding => "coding",
+            ActivityCategory::Browsing => "browsing",
+            ActivityCategory::Writing => "writing",
+            ActivityCategory::Other => "other",
+        }
+    }
+
+    /// Which category wins when a block has as many frames of both, the
+    /// more deliberate activity first.
+    fn priority(&self) -> u8 {
+        match self {
+            ActivityCategory::Meeting => 4,
+            ActivityCategory::Coding => 3,
+            ActivityCategory::Writing => 2,
+            ActivityCategory::Browsing => 1,
+            ActivityCategory::Other => 0,
+        }
+    }
+}
+
+/// Whether `text` contains `keyword` as whole words, e.g. "code" in
+/// "Visual Studio Code" but not in "Xcode".
+fn mentions(text: &str, keyword: &str) -> bool {
+    text.match_indices(keyword).any(|(start, _)| {
+        let end = start + keyword.len();
+        let boundary_before = text[..start]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !c.is_alphanumeric());
+        let boundary_after = text[end..]
+            .chars()
+            .next()
+            .is_none_or(|c| !c.is_alphanumeric());
+        boundary_before && boundary_after
+    })
+}
+
+/// Whether most of the lines of some screen text look like source code.
+fn looks_like_code(text: &str) -> bool {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.len() < 5 {
+        return false;
+    }
+    let code_lines = lines
+        .iter()
+        .filter(|line| {
+            CODE_LINE_STARTS.iter().any(|start| line.starts_with(start))
+                || line.ends_with(['{', '}', ';'])
+                || line.contains("=>")
+                || line.contains("::")
+        })
+        .count();
+    code_lines * 3 >= lines.len()
+}
+
+/// Category of a single frame.
+pub fn frame_category(frame: &FrameEntry) -> ActivityCategory {
+    let app = frame.app_name.as_deref().unwrap_or_default().to_lowercase();
+    let window = frame
+        .window_name
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+    let url = frame
+        .browser_url
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+    let is_app = |apps: &[&str]| apps.iter().any(|name| mentions(&app, name));
+    let on_site = |sites: &[&str]| !url.is_empty() && sites.iter().any(|site| url.contains(site));
+
+    if is_app(MEETING_APPS) || on_site(MEETING_SITES) {
+        return ActivityCategory::Meeting;
+    }
+    if is_app(CODING_APPS) {
+        return ActivityCategory::Coding;
+    }
+    if is_app(WRITING_APPS) {
+        return ActivityCategory::Writing;
+    }
+    if is_app(BROWSERS) || !url.is_empty() {
+        return if on_site(CODING_SITES) {
+            ActivityCategory::Coding
+        } else if on_site(WRITING_SITES) || mentions(&window, "google docs") {
+            ActivityCategory::Writing
+        } else {
+            ActivityCategory::Browsing
+        };
+    }
+    if frame.ocr_text.as_deref().is_some_and(looks_like_code) {
+        return ActivityCategory::Coding;
+    }
+    ActivityCategory::Other
+}
+
+/// Category of a time block and the app on screen the most during it. Each
+/// focused frame votes for its category, frames of other monitors only count
+/// when none was focused. Audio with a meeting on any screen is a meeting
+/// even if other windows were in front most of the time, and so is audio
+/// with nothing on screen, e.g. with vision disabled.
+pub fn classify_block(
+    frames: &[FrameEntry],
+    has_audio: bool,
+) -> (ActivityCategory, Option<String>) {
+    if has_audio
+        && frames
+            .iter()
+            .any(|frame| frame_category(frame) == ActivityCategory::Meeting)
+    {
+        return (ActivityCategory::Meeting, top_app(frames.iter()));
+    }
+    let focused: Vec<&FrameEntry> = frames.iter().filter(|frame| frame.focused).collect();
+    let frames = if focused.is_empty() {
+        frames.iter().collect()
+    } else {
+        focused
+    };
+    if frames.is_empty() {
+        let category = if has_audio {
+            ActivityCategory::Meeting
+        } else {
+            ActivityCategory::Other
+        };
+        return (category, None);
+    }
+
+    let mut votes: HashMap<ActivityCategory, usize> = HashMap::new();
+    for frame in &frames {
+        *votes.entry(frame_category(frame)).or_default() += 1;
+    }
+    let category = votes
+        .into_iter()
+        .max_by_key(|(category, count)| (*count, category.priority()))
+        .map(|(category, _)| category)
+        .unwrap_or(ActivityCategory::Other);
+    (category, top_app(frames.into_iter()))
+}
+
+/// App with the most frames, the first one seen on ties.
+fn top_app<'a>(frames: impl Iterator<Item = &'a FrameEntry>) -> Option<String> {
+    let mut apps: Vec<(&str, usize)> = Vec::new();
+    for app in frames.filter_map(|frame| frame.app_name.as_deref()) {
+        if app.is_empty() {
+            continue;
+        }
+        match apps.iter_mut().find(|(name, _)| *name == app) {
+            Some((_, count)) => *count += 1,
+            None => apps.push((app, 1)),
+        }
+    }
+    apps.into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(app, _)| app.to_string())
+}
+
+/// Start of the time block `timestamp` is in.
+pub fn block_start(timestamp: DateTime<Utc>) -> DateTime<Utc> {
+    let seconds = timestamp.timestamp();
+    let start = seconds - seconds.rem_euclid(BLOCK.num_seconds());
+    DateTime::from_timestamp(start, 0).unwrap_or(timestamp)
+}
+
+/// Classifies the first time block with recordings after the last one
+/// classified. Returns `None` once the next block is still being recorded.
+pub async fn classify_next_block(
+    db: &DatabaseManager,
+    now: DateTime<Utc>,
+) -> Result<Option<ActivitySegment>> {
+    let after = db.get_last_activity_segment_end().await?;
+    let next = match db.get_next_recording_time(after).await? {
+        Some(next) => next,
+        None => return Ok(None),
+    };
+    let start_time = block_start(next);
+    let end_time = start_time + BLOCK;
+    if end_time + SETTLE > now {
+        return Ok(None);
+    }
+
+    let frames: Vec<FrameEntry> = db
+        .get_export_frames(start_time, end_time, None, None, MAX_FRAMES, 0)
+        .await?
+        .into_iter()
+        .filter(|frame| frame.timestamp < end_time)
+        .collect();
+    let has_audio = db
+        .get_audio_transcriptions(Some(start_time), Some(end_time), None, 1, 0)
+        .await?
+        .iter()
+        .any(|transcript| transcript.timestamp < end_time);
+    let (category, app_name) = classify_block(&frames, has_audio);
+
+    let id = db
+        .insert_activity_segment(
+            start_time,
+            end_time,
+            category.as_str(),
+            app_name.as_deref(),
+            frames.len() as i64,
+            has_audio,
+        )
+        .await?;
+    Ok(Some(ActivitySegment {
+        id,
+        start_time,
+        end_time,
+        category: category.as_str().to_string(),
+        app_name,
+        frame_count: frames.len() as i64,
+        has_audio,
+    }))
+}
+
+/// Keeps classifying time blocks as they are recorded until a shutdown is
+/// signalled. Blocks recorded before the first run are classified first.
+pub async fn run_activity_classifier(
+    db: Arc<DatabaseManager>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!("starting activity classification");
+
+    loop {
+        let wait = match classify_next_block(&db, Utc::now()).await {
+            Ok(Some(segment)) => {
+                debug!(
+                    "activity from {} to {}: {}",
+                    segment.start_time, segment.end_time, segment.category
+                );
+                std::time::Duration::ZERO
+            }
+            Ok(None) => IDLE_INTERVAL,
+            Err(e) => {
+                error!("activity classification failed: {}", e);
+                IDLE_INTERVAL
+            }
+        };
+
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping activity classification");
+                break;
+            }
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct ActivitySegmentsQuery {
+    #[serde(flatten)]
+    pagination: PaginationQuery,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    category: Option<ActivityCategory>,
+}
+
+/// Recorded time blocks labelled with what the user was doing, oldest
+/// first.
+#[oasgen]
+pub(crate) async fn get_activity_segments_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ActivitySegmentsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state
+        .db
+        .list_activity_segments(
+            query.start_time,
+            query.end_time,
+            query.category.as_ref().map(ActivityCategory::as_str),
+            query.pagination.limit,
+            query.pagination.offset,
+        )
+        .await
+    {
+        Ok(segments) => Ok(JsonResponse(json!({
+            "data": segments,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to read activity segments: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to read activity segments: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct ActivitySummaryQuery {
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+}
+
+/// Seconds spent in each activity category, the longest first.
+#[oasgen]
+pub(crate) async fn get_activity_summary_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ActivitySummaryQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let key = QueryKey::new("activity_summary")
+        .time("start_time", query.start_time)
+        .time("end_time", query.end_time);
+    match state
+        .query_cache
+        .get_or_try_insert(
+            key,
+            query.start_time,
+            query.end_time,
+            state
+                .db
+                .get_activity_summary(query.start_time, query.end_time),
+        )
+        .await
+    {
+        Ok(summary) => Ok(JsonResponse(json!({
+            "data": summary,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to summarize activity: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to summarize activity: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct UsageAnalyticsQuery {
+    pub(crate) start_time: DateTime<Utc>,
+    pub(crate) end_time: DateTime<Utc>,
+    /// `hour` or `day`
+    #[serde(default)]
+    pub(crate) bucket: Option<UsageBucket>,
+    /// Offset from UTC of the time zone buckets are aligned to, so days start
+    /// at its midnight. The server's by default
+    #[serde(default)]
+    pub(crate) utc_offset_minutes: Option<i32>,
+}
+
+/// Buckets of an analytics query over the range of `query`, cached like
+/// `/activity/summary`.
+pub(crate) async fn usage_analytics<T, F, Fut>(
+    state: &AppState,
+    name: &'static str,
+    query: &UsageAnalyticsQuery,
+    default_bucket: UsageBucket,
+    read: F,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce(DateTime<Utc>, DateTime<Utc>, UsageBucket, i32) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, sqlx::Error>>,
+{
+    let bad_request = |error: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": error, "success": false})),
+        )
+    };
+    if query.end_time < query.start_time {
+        return Err(bad_request("end_time must not be before start_time"));
+    }
+    let utc_offset_minutes = query
+        .utc_offset_minutes
+        .unwrap_or_else(|| chrono::Local::now().offset().local_minus_utc() / 60);
+    if utc_offset_minutes.abs() > 14 * 60 {
+        return Err(bad_request("utc_offset_minutes must be within 14 hours"));
+    }
+    let bucket = query.bucket.unwrap_or(default_bucket);
+
+    let key = QueryKey::new(name)
+        .time("start_time", Some(query.start_time))
+        .time("end_time", Some(query.end_time))
+        .param("bucket", Some(bucket.seconds()))
+        .param("utc_offset_minutes", Some(utc_offset_minutes));
+    match state
+        .query_cache
+        .get_or_try_insert(
+            key,
+            Some(query.start_time),
+            Some(query.end_time),
+            read(
+                query.start_time,
+                query.end_time,
+                bucket,
+                utc_offset_minutes * 60,
+            ),
+        )
+        .await
+    {
+        Ok(buckets) => Ok(JsonResponse(json!({
+            "data": buckets,
+            "bucket": bucket,
+            "utc_offset_minutes": utc_offset_minutes,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to compute {}: {}", name, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to compute {}: {}", name, e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
+/// Seconds each app was focused per bucket, days by default.
+#[oasgen]
+pub(crate) async fn get_app_usage_analytics_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<UsageAnalyticsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    usage_analytics(
+        &state,
+        "app usage",
+        &query,
+        UsageBucket::Day,
+        |start, end, bucket, utc_offset| {
+            state
+                .db
+                .get_app_usage_buckets(start, end, bucket, utc_offset)
+        },
+    )
+    .await
+}
+
+/// Seconds each speaker talked per bucket, days by default.
+#[oasgen]
+pub(crate) async fn get_speaking_time_analytics_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<UsageAnalyticsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    usage_analytics(
+        &state,
+        "speaking time",
+        &query,
+        UsageBucket::Day,
+        |start, end, bucket, utc_offset| {
+            state
+                .db
+                .get_speaking_time_buckets(start, end, bucket, utc_offset)
+        },
+    )
+    .await
+}
+
+/// Words of the screen text per app and bucket, days by default.
+#[oasgen]
+pub(crate) async fn get_word_count_analytics_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<UsageAnalyticsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    usage_analytics(
+        &state,
+        "word counts",
+        &query,
+        UsageBucket::Day,
+        |start, end, bucket, utc_offset| {
+            state
+                .db
+                .get_word_count_buckets(start, end, bucket, utc_offset)
+        },
+    )
+    .await
+}
+
+/// Times the focus moved to another app per bucket, hours by default.
+#[oasgen]
+pub(crate) async fn get_context_switch_analytics_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<UsageAnalyticsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    usage_analytics(
+        &state,
+        "context switches",
+        &query,
+        UsageBucket::Hour,
+        |start, end, bucket, utc_offset| {
+            state
+                .db
+                .get_context_switch_buckets(start, end, bucket, utc_offset)
+        },
+    )
+    .await
+}
@@ -3,6 +3,7 @@ use image::DynamicImage;
 use regex::Regex;
 use screenpipe_db::DatabaseManager;
 use screenpipe_vision::utils::{compare_with_previous_image, OcrEngine};
+use screenpipe_vision::perform_ocr_paddle;
 
 #[cfg(target_os = "macos")]
 use screenpipe_vision::perform_ocr_apple;
@@ -185,6 +186,10 @@ pub async fn handle_index_command(
                 OcrEngine::AppleNative => perform_ocr_apple(frame, &[]),
                 #[cfg(target_os = "windows")]
                 OcrEngine::WindowsNative => perform_ocr_windows(&frame).await.unwrap(),
+                OcrEngine::Paddle => perform_ocr_paddle(frame).await.unwrap_or_else(|e| {
+                    warn!("paddle ocr failed: {}", e);
+                    ("".to_string(), "".to_string(), None)
+                }),
                 _ => {
                     #[cfg(not(any(target_os = "macos", target_os = "windows")))]
                     {
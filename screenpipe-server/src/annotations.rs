@@ -0,0 +1,240 @@
+//! Notes and tags attached to a frame, a transcript segment or a time range,
+//! served at `/annotations`. Annotation tags are searchable like the tags of
+//! frames and transcripts.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::{DateTime, Utc};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_db::AnnotationTarget;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::server::{AppState, PaginationQuery};
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct AnnotationQuery {
+    /// Annotations ending after this
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    /// Annotations starting before this
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    frame_id: Option<i64>,
+    #[serde(default)]
+    audio_transcription_id: Option<i64>,
+    #[serde(default)]
+    tag: Option<String>,
+    #[serde(flatten)]
+    pagination: PaginationQuery,
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct AnnotationRequest {
+    #[serde(default)]
+    frame_id: Option<i64>,
+    /// Transcript segment, the `transcription_id` of audio search results
+    #[serde(default)]
+    audio_transcription_id: Option<i64>,
+    /// Time range annotated when no frame or transcript segment is given
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    note: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct UpdateAnnotationRequest {
+    /// Replaces the note, an empty one clears it
+    #[serde(default)]
+    note: Option<String>,
+    /// Replaces the tags
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+}
+
+fn annotation_error(status: StatusCode, error: String) -> (StatusCode, JsonResponse<Value>) {
+    (
+        status,
+        JsonResponse(json!({"error": error, "success": false})),
+    )
+}
+
+/// Trimmed tags without duplicates. Commas separate the tags of search
+/// results, so they can't be part of one.
+fn annotation_tags(tags: &[String]) -> Result<Vec<String>, (StatusCode, JsonResponse<Value>)> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags
+        .iter()
+        .map(|tag| tag.trim())
+        .filter(|tag| !tag.is_empty())
+    {
+        if tag.contains(',') {
+            return Err(annotation_error(
+                StatusCode::BAD_REQUEST,
+                format!("tags can't contain commas: {}", tag),
+            ));
+        }
+        if !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    Ok(normalized)
+}
+
+#[oasgen]
+pub(crate) async fn list_annotations_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AnnotationQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let annotations = state
+        .db
+        .list_annotations(
+            query.start_time,
+            query.end_time,
+            query.frame_id,
+            query.audio_transcription_id,
+            query.tag.as_deref(),
+            query.pagination.limit,
+            query.pagination.offset,
+        )
+        .await
+        .map_err(|e| {
+            error!("failed to list annotations: {}", e);
+            annotation_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to list annotations: {}", e),
+            )
+        })?;
+    Ok(JsonResponse(json!({
+        "data": annotations,
+        "success": true
+    })))
+}
+
+/// Attaches a note and tags to a frame, a transcript segment or a time
+/// range.
+#[oasgen]
+pub(crate) async fn create_annotation_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(request): JsonResponse<AnnotationRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let bad_request = |error: &str| annotation_error(StatusCode::BAD_REQUEST, error.to_string());
+    let target = match (
+        request.frame_id,
+        request.audio_transcription_id,
+        request.start_time,
+        request.end_time,
+    ) {
+        (Some(frame_id), None, None, None) => AnnotationTarget::Frame(frame_id),
+        (None, Some(id), None, None) => AnnotationTarget::Transcription(id),
+        (None, None, Some(start_time), Some(end_time)) if start_time <= end_time => {
+            AnnotationTarget::TimeRange(start_time, end_time)
+        }
+        (None, None, Some(_), Some(_)) => {
+            return Err(bad_request("start_time must be before end_time"))
+        }
+        _ => {
+            return Err(bad_request(
+                "an annotation needs one of frame_id, audio_transcription_id, or start_time and end_time",
+            ))
+        }
+    };
+    let note = request
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|note| !note.is_empty());
+    let tags = annotation_tags(&request.tags)?;
+    if note.is_none() && tags.is_empty() {
+        return Err(bad_request("an annotation needs a note or tags"));
+    }
+
+    match state
+        .db
+        .insert_annotation(target.clone(), note, &tags)
+        .await
+    {
+        Ok(Some(annotation)) => Ok(JsonResponse(json!({
+            "data": annotation,
+            "success": true
+        }))),
+        Ok(None) => Err(annotation_error(
+            StatusCode::NOT_FOUND,
+            match target {
+                AnnotationTarget::Frame(id) => format!("no frame {}", id),
+                AnnotationTarget::Transcription(id) => format!("no transcript segment {}", id),
+                AnnotationTarget::TimeRange(..) => "nothing to annotate".to_string(),
+            },
+        )),
+        Err(e) => {
+            error!("failed to create annotation: {}", e);
+            Err(annotation_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to create annotation: {}", e),
+            ))
+        }
+    }
+}
+
+#[oasgen]
+pub(crate) async fn update_annotation_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    JsonResponse(request): JsonResponse<UpdateAnnotationRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let tags = request.tags.as_deref().map(annotation_tags).transpose()?;
+    match state
+        .db
+        .update_annotation(id, request.note.as_deref().map(str::trim), tags.as_deref())
+        .await
+    {
+        Ok(Some(annotation)) => Ok(JsonResponse(json!({
+            "data": annotation,
+            "success": true
+        }))),
+        Ok(None) => Err(annotation_error(
+            StatusCode::NOT_FOUND,
+            format!("no annotation {}", id),
+        )),
+        Err(e) => {
+            error!("failed to update annotation {}: {}", id, e);
+            Err(annotation_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to update annotation: {}", e),
+            ))
+        }
+    }
+}
+
+#[oasgen]
+pub(crate) async fn delete_annotation_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.delete_annotation(id).await {
+        Ok(true) => Ok(JsonResponse(json!({"success": true}))),
+        Ok(false) => Err(annotation_error(
+            StatusCode::NOT_FOUND,
+            format!("no annotation {}", id),
+        )),
+        Err(e) => {
+            error!("failed to delete annotation {}: {}", id, e);
+            Err(annotation_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to delete annotation: {}", e),
+            ))
+        }
+    }
+}
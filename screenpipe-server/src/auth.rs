@@ -0,0 +1,191 @@
+//! Token authentication for the local API, enabled with `--api-auth`.
+//! Clients send `Authorization: Bearer <token>`, or a `token` query parameter
+//! where headers can't be set, e.g. websockets and `EventSource`. Only a hash
+//! of each token is stored.
+//!
+//! The app starts screenpipe with an admin token of its own in
+//! [`APP_TOKEN_ENV`], which it stops screenpipe with through
+//! `/admin/shutdown`. That token isn't stored and only works until screenpipe
+//! exits.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, Request, State},
+    http::{header::AUTHORIZATION, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use rand::{rngs::OsRng, RngCore};
+use screenpipe_db::{ApiToken, DatabaseManager};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+const TOKEN_PREFIX: &str = "sp_";
+const TOKEN_BYTES: usize = 32;
+
+/// Environment variable the app passes its admin token in. It is removed
+/// once read, so pipes and other child processes don't inherit it.
+pub const APP_TOKEN_ENV: &str = "SCREENPIPE_APP_TOKEN";
+
+/// What a token gives access to. Each scope includes the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TokenScope {
+    /// Read data: search, timeline, frames, streams, GraphQL and MCP
+    Search,
+    /// Install, configure and run pipes
+    Pipes,
+    /// Everything, including settings, raw SQL and device control
+    Admin,
+}
+
+impl fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenScope::Search => write!(f, "search"),
+            TokenScope::Pipes => write!(f, "pipes"),
+            TokenScope::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl FromStr for TokenScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "search" => Ok(TokenScope::Search),
+            "pipes" => Ok(TokenScope::Pipes),
+            "admin" => Ok(TokenScope::Admin),
+            _ => Err(anyhow::anyhow!("unknown token scope: {}", s)),
+        }
+    }
+}
+
+/// Scope needed to call an endpoint, `None` if it's open to anyone.
+pub fn required_scope(method: &Method, path: &str) -> Option<TokenScope> {
+    // sync batches are encrypted with the sync key, which is what peers
+    // prove they have
+    if path == "/health" || path == "/sync/changes" {
+        None
+    } else if path.starts_with("/bus/") {
+        // pipes authenticate with the token they're started with
+        None
+    } else if path.starts_with("/companion/uploads") {
+        // companion devices authenticate with the token they're paired with
+        None
+    } else if path.starts_with("/companion/") {
+        Some(TokenScope::Admin)
+    } else if path.starts_with("/pipes/") {
+        Some(TokenScope::Pipes)
+    } else if path.starts_with("/admin/") {
+        Some(TokenScope::Admin)
+    } else if path.starts_with("/webhooks") {
+        // webhooks hold the secrets their payloads are signed with
+        Some(TokenScope::Admin)
+    } else if path.starts_with("/annotations") && method != Method::GET {
+        // pipes annotate what they find
+        Some(TokenScope::Pipes)
+    } else if path == "/v1/chat/completions" {
+        // what the local llm is for
+        Some(TokenScope::Pipes)
+    } else if method == Method::GET || matches!(path, "/graphql" | "/mcp/messages" | "/query") {
+        // graphql, mcp and queries only read
+        Some(TokenScope::Search)
+    } else {
+        Some(TokenScope::Admin)
+    }
+}
+
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", TOKEN_PREFIX, hex)
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Creates a token, returning it with its record. The token can't be
+/// recovered later.
+pub async fn create_token(
+    db: &DatabaseManager,
+    name: Option<&str>,
+    scope: TokenScope,
+) -> Result<(String, ApiToken), sqlx::Error> {
+    let token = generate_token();
+    let record = db
+        .insert_api_token(name, &scope.to_string(), &hash_token(&token))
+        .await?;
+    Ok((token, record))
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+fn request_token(req: &Request) -> Option<String> {
+    let bearer = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+    bearer.or_else(|| {
+        Query::<TokenQuery>::try_from_uri(req.uri())
+            .ok()
+            .and_then(|query| query.0.token)
+    })
+}
+
+fn auth_error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({"error": message, "success": false}))).into_response()
+}
+
+#[derive(Clone)]
+pub struct AuthState {
+    pub db: Arc<DatabaseManager>,
+    /// Hash of the admin token of the app, see [`APP_TOKEN_ENV`]
+    pub app_token_hash: Option<String>,
+}
+
+/// Middleware rejecting requests without a token of the required scope.
+pub async fn require_token(State(auth): State<AuthState>, req: Request, next: Next) -> Response {
+    let Some(required) = required_scope(req.method(), req.uri().path()) else {
+        return next.run(req).await;
+    };
+    let Some(token) = request_token(&req) else {
+        return auth_error(StatusCode::UNAUTHORIZED, "missing api token");
+    };
+
+    let token_hash = hash_token(&token);
+    if auth.app_token_hash.as_deref() == Some(token_hash.as_str()) {
+        return next.run(req).await;
+    }
+    let record = match auth.db.use_api_token(&token_hash).await {
+        Ok(Some(record)) => record,
+        Ok(None) => return auth_error(StatusCode::UNAUTHORIZED, "invalid api token"),
+        Err(e) => {
+            warn!("failed to look up api token: {}", e);
+            return auth_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to check api token",
+            );
+        }
+    };
+    match record.scope.parse::<TokenScope>() {
+        Ok(scope) if scope >= required => next.run(req).await,
+        _ => auth_error(
+            StatusCode::FORBIDDEN,
+            &format!("this endpoint needs a token with the {} scope", required),
+        ),
+    }
+}
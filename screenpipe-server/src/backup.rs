@@ -0,0 +1,445 @@
+//! Backups of the database and optionally the recordings, made with
+//! `screenpipe backup create` or every `--backup-interval-hours` into
+//! `--backup-dir`. Each backup is a directory holding a snapshot of the
+//! database taken with SQLite's online backup API, the recordings, and a
+//! manifest with the checksum of every file.
+//!
+//! Recordings that didn't change since the previous backup are hard linked
+//! from it instead of copied, so later backups only take the space of new
+//! recordings while each one can still be restored or deleted on its own.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use screenpipe_db::{check_database_integrity, DatabaseManager};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+use walkdir::WalkDir;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+const DB_FILE: &str = "db.sqlite";
+/// Directory of the recordings, in the data directory and in backups.
+const MEDIA_DIR: &str = "data";
+/// Backups are written under this prefix and renamed once complete.
+const PARTIAL_PREFIX: &str = ".partial-";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFile {
+    /// Path relative to the backup, `/` separated
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub created_at: DateTime<Utc>,
+    pub version: String,
+    /// The database is encrypted with the key of the data directory
+    pub encrypted: bool,
+    /// Backup the unchanged recordings were linked from
+    pub base: Option<String>,
+    pub files: Vec<BackupFile>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BackupOptions {
+    pub include_media: bool,
+    /// Link the recordings unchanged since the latest backup instead of
+    /// copying them
+    pub incremental: bool,
+    /// Hex encoded key of an encrypted database
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupReport {
+    pub path: PathBuf,
+    pub manifest: BackupManifest,
+    /// Files copied, the database included
+    pub copied_files: usize,
+    pub copied_bytes: u64,
+    /// Recordings linked from the previous backup
+    pub linked_files: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreReport {
+    /// Where the database that was replaced was moved
+    pub previous_database: Option<PathBuf>,
+    pub restored_media: usize,
+}
+
+pub(crate) fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Copies `from` to `to`, returning the checksum of what was written.
+fn copy_with_checksum(from: &Path, to: &Path) -> Result<String> {
+    if let Some(dir) = to.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut reader = fs::File::open(from)?;
+    let mut writer = fs::File::create(to)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 20];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        writer.write_all(&buffer[..read])?;
+    }
+    writer.sync_all()?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub(crate) fn relative_path(path: &Path, root: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Some(parts.join("/"))
+}
+
+fn read_manifest(backup: &Path) -> Result<BackupManifest> {
+    let path = backup.join(MANIFEST_FILE);
+    let data =
+        fs::read(&path).with_context(|| format!("no backup manifest at {}", path.display()))?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// Backups in `root`, oldest first.
+pub fn list_backups(root: &Path) -> Result<Vec<(PathBuf, BackupManifest)>> {
+    let mut backups = Vec::new();
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(backups),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let partial = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with(PARTIAL_PREFIX));
+        if !path.is_dir() || partial {
+            continue;
+        }
+        match read_manifest(&path) {
+            Ok(manifest) => backups.push((path, manifest)),
+            Err(e) => warn!("skipping {}: {}", path.display(), e),
+        }
+    }
+    backups.sort_by_key(|(_, manifest)| manifest.created_at);
+    Ok(backups)
+}
+
+/// Copies or links the recordings of `data_dir` into `dest`.
+fn backup_media(
+    data_dir: &Path,
+    dest: &Path,
+    previous: Option<&(PathBuf, BackupManifest)>,
+    report: &mut BackupReport,
+) -> Result<()> {
+    let previous_files: HashMap<&str, &BackupFile> = previous
+        .map(|(_, manifest)| {
+            manifest
+                .files
+                .iter()
+                .map(|file| (file.path.as_str(), file))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for entry in WalkDir::new(data_dir.join(MEDIA_DIR)) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("skipping recording: {}", e);
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Some(path) = relative_path(entry.path(), data_dir) else {
+            continue;
+        };
+        let size = entry.metadata()?.len();
+        let target = dest.join(&path);
+
+        // recordings are never changed once finished, except when they get
+        // encrypted at rest, which changes their size
+        if let (Some((previous_dir, _)), Some(file)) = (previous, previous_files.get(&*path)) {
+            if file.size == size {
+                if let Some(dir) = target.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                if fs::hard_link(previous_dir.join(&path), &target).is_ok() {
+                    report.linked_files += 1;
+                    report.manifest.files.push((*file).clone());
+                    continue;
+                }
+            }
+        }
+
+        match copy_with_checksum(entry.path(), &target) {
+            Ok(sha256) => {
+                report.copied_files += 1;
+                report.copied_bytes += size;
+                report
+                    .manifest
+                    .files
+                    .push(BackupFile { path, size, sha256 });
+            }
+            // e.g. deleted by retention in the meantime
+            Err(e) => warn!("failed to back up {}: {}", entry.path().display(), e),
+        }
+    }
+    Ok(())
+}
+
+/// Writes the database, the recordings and the manifest of a backup to `dest`.
+async fn write_backup(
+    db: &DatabaseManager,
+    data_dir: &Path,
+    dest: &Path,
+    previous: Option<(PathBuf, BackupManifest)>,
+    options: &BackupOptions,
+    mut report: BackupReport,
+) -> Result<BackupReport> {
+    let key = options.key.as_deref();
+    let db_path = dest.join(DB_FILE);
+    db.backup_to(&db_path, key).await?;
+    check_database_integrity(&db_path, key).await?;
+    let size = fs::metadata(&db_path)?.len();
+    let sha256 = tokio::task::spawn_blocking(move || sha256_file(&db_path)).await??;
+    report.copied_files += 1;
+    report.copied_bytes += size;
+    report.manifest.files.push(BackupFile {
+        path: DB_FILE.to_string(),
+        size,
+        sha256,
+    });
+
+    if options.include_media {
+        let data_dir = data_dir.to_path_buf();
+        let dest = dest.to_path_buf();
+        report = tokio::task::spawn_blocking(move || {
+            backup_media(&data_dir, &dest, previous.as_ref(), &mut report)?;
+            Ok::<_, anyhow::Error>(report)
+        })
+        .await??;
+    }
+
+    fs::write(
+        dest.join(MANIFEST_FILE),
+        serde_json::to_vec_pretty(&report.manifest)?,
+    )?;
+    Ok(report)
+}
+
+/// Creates a backup of the data directory in a new directory of `root`,
+/// named after the time it was made. The backup is verified before it is
+/// returned.
+pub async fn create_backup(
+    db: &DatabaseManager,
+    data_dir: &Path,
+    root: &Path,
+    options: &BackupOptions,
+) -> Result<BackupReport> {
+    let created_at = Utc::now();
+    let name = created_at.format("%Y-%m-%dT%H-%M-%SZ").to_string();
+    let dest = root.join(&name);
+    let partial = root.join(format!("{}{}", PARTIAL_PREFIX, name));
+    if dest.exists() {
+        anyhow::bail!("backup {} already exists", dest.display());
+    }
+    let _ = fs::remove_dir_all(&partial);
+    fs::create_dir_all(&partial)?;
+
+    let previous = if options.include_media && options.incremental {
+        list_backups(root)?.pop()
+    } else {
+        None
+    };
+    let report = BackupReport {
+        path: dest.clone(),
+        manifest: BackupManifest {
+            created_at,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            encrypted: options.key.is_some(),
+            base: previous
+                .as_ref()
+                .and_then(|(path, _)| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned()),
+            files: Vec::new(),
+        },
+        copied_files: 0,
+        copied_bytes: 0,
+        linked_files: 0,
+    };
+
+    match write_backup(db, data_dir, &partial, previous, options, report).await {
+        Ok(report) => {
+            fs::rename(&partial, &dest)?;
+            Ok(report)
+        }
+        Err(e) => {
+            let _ = fs::remove_dir_all(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// Checks that every file of the backup is there with the size and checksum
+/// in its manifest and that its database is sound. `key` is needed for an
+/// encrypted database.
+pub async fn verify_backup(backup: &Path, key: Option<&str>) -> Result<BackupManifest> {
+    let manifest = read_manifest(backup)?;
+    if manifest.encrypted && key.is_none() {
+        anyhow::bail!("the backup is encrypted, run with --encrypt-data");
+    }
+
+    let files = manifest.files.clone();
+    let dir = backup.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        for file in files {
+            let path = dir.join(&file.path);
+            let size = fs::metadata(&path)
+                .with_context(|| format!("{} is missing", file.path))?
+                .len();
+            if size != file.size {
+                anyhow::bail!("{} is {} bytes, expected {}", file.path, size, file.size);
+            }
+            if sha256_file(&path)? != file.sha256 {
+                anyhow::bail!("{} doesn't match its checksum", file.path);
+            }
+        }
+        Ok(())
+    })
+    .await??;
+
+    check_database_integrity(&backup.join(DB_FILE), key.filter(|_| manifest.encrypted)).await?;
+    Ok(manifest)
+}
+
+/// Replaces the database of `data_dir` with the one of the backup and puts
+/// back the recordings that are missing. The replaced database is kept next
+/// to it. Screenpipe must not be running.
+pub async fn restore_backup(
+    backup: &Path,
+    data_dir: &Path,
+    key: Option<&str>,
+) -> Result<RestoreReport> {
+    let manifest = verify_backup(backup, key).await?;
+    let backup = backup.to_path_buf();
+    let data_dir = data_dir.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        fs::create_dir_all(&data_dir)?;
+        let db_path = data_dir.join(DB_FILE);
+        let previous_database = if db_path.exists() {
+            let previous = data_dir.join(format!(
+                "{}.before-restore-{}",
+                DB_FILE,
+                Utc::now().format("%Y-%m-%dT%H-%M-%SZ")
+            ));
+            fs::rename(&db_path, &previous)?;
+            Some(previous)
+        } else {
+            None
+        };
+        for suffix in ["-wal", "-shm"] {
+            let _ = fs::remove_file(data_dir.join(format!("{}{}", DB_FILE, suffix)));
+        }
+        fs::copy(backup.join(DB_FILE), &db_path)?;
+
+        let mut restored_media = 0;
+        for file in manifest.files.iter().filter(|file| file.path != DB_FILE) {
+            let target = data_dir.join(&file.path);
+            if fs::metadata(&target).is_ok_and(|metadata| metadata.len() == file.size) {
+                continue;
+            }
+            if let Some(dir) = target.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::copy(backup.join(&file.path), &target)?;
+            restored_media += 1;
+        }
+
+        Ok(RestoreReport {
+            previous_database,
+            restored_media,
+        })
+    })
+    .await?
+}
+
+/// Deletes the oldest backups of `root` beyond the `keep` latest. Returns
+/// how many were deleted.
+pub fn prune_backups(root: &Path, keep: usize) -> Result<usize> {
+    let backups = list_backups(root)?;
+    let excess = backups.len().saturating_sub(keep);
+    for (path, _) in &backups[..excess] {
+        fs::remove_dir_all(path)?;
+    }
+    Ok(excess)
+}
+
+/// Backs up every `interval` and keeps the `keep` latest backups until a
+/// shutdown is signalled.
+pub async fn run_backups(
+    db: Arc<DatabaseManager>,
+    data_dir: PathBuf,
+    root: PathBuf,
+    options: BackupOptions,
+    interval: Duration,
+    keep: usize,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!(
+        "backing up to {} every {} hours",
+        root.display(),
+        interval.as_secs() / 3600
+    );
+
+    // the first backup waits for the interval, restarts don't pile them up
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                match create_backup(&db, &data_dir, &root, &options).await {
+                    Ok(report) => info!(
+                        "backed up to {}: {} files copied ({} bytes), {} linked",
+                        report.path.display(),
+                        report.copied_files,
+                        report.copied_bytes,
+                        report.linked_files
+                    ),
+                    Err(e) => error!("failed to back up: {}", e),
+                }
+                if let Err(e) = prune_backups(&root, keep.max(1)) {
+                    warn!("failed to delete old backups: {}", e);
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping backups");
+                break;
+            }
+        }
+    }
+}
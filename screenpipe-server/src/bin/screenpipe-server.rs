@@ -8,36 +8,100 @@ use port_check::is_local_ipv4_port_free;
 use screenpipe_audio::{
     audio_manager::AudioManagerBuilder,
     core::device::{
-        default_input_device, default_output_device, list_audio_devices, parse_audio_device,
+        default_input_device, default_output_device, list_audio_devices, parse_audio_channel,
+        parse_audio_device, AudioDevice,
     },
+    transcription::provider::OpenAiSttConfig,
+};
+use screenpipe_core::encryption::{
+    load_or_create_key, media_cipher, prepare_decrypt_dir, unlock_media, EncryptionKey,
 };
 use screenpipe_core::find_ffmpeg_path;
+use screenpipe_core::ocr_mode::{ocr_mode, set_ocr_mode};
+use screenpipe_core::power::PowerSettings;
+use screenpipe_core::inference::{local_model, InferenceDevice, LocalInference, Quantization};
+use screenpipe_core::answering::Answerer;
+use screenpipe_core::extraction::Extractor;
+use screenpipe_core::llm_client::LlmClient;
+use screenpipe_core::summarization::Summarizer;
+use screenpipe_core::pipe_schedule::is_scheduled;
+use screenpipe_core::pipe_signing::{generate_key, sign_package, trust_publisher};
+use screenpipe_core::wasm_pipe::WasmPipeHost;
 use screenpipe_db::{
-    create_migration_worker, DatabaseManager, MigrationCommand, MigrationConfig, MigrationStatus,
+    create_migration_worker, database_schema_status, migrate_database, rollback_database,
+    DatabaseManager, MigrationCommand, MigrationConfig, MigrationReport, MigrationStatus,
+    SchemaMigration,
 };
 use screenpipe_server::{
+    activity::run_activity_classifier,
     cli::{
-        AudioCommand, Cli, CliAudioTranscriptionEngine, CliOcrEngine, Command, MigrationSubCommand,
-        OutputFormat, PipeCommand, VisionCommand, McpCommand,
+        AudioCommand, Cli, CliAudioTranscriptionEngine, CliOcrEngine, CliOcrMode, CliLlmDevice, CliLocalLlm, CliPiiDetector, Command,
+        LogCommand, LogFormat, MigrationSubCommand, OutputFormat, PipeCommand, VisionCommand, McpCommand, RemoteCommand,
+        SyncCommand, TokenCommand, BackupCommand, DbCommand,
+    },
+    auth::{create_token, TokenScope, APP_TOKEN_ENV},
+    calendar::{run_calendar_sync, CalendarSource},
+    backup::{
+        create_backup, list_backups, restore_backup, run_backups, verify_backup, BackupOptions,
     },
+    clipboard_capture::run_clipboard_capture,
+    crash::{run_crash_monitor, start_crash_reporter},
+    doctor::run_doctor,
+    export::{export, export_daily_notes, parse_export_time, ExportFilter, ExportFormat},
+    focus::run_focus_tracker,
     handle_index_command,
+    idle_monitor::run_idle_monitor,
+    import::import,
+    ingest::run_ingest,
+    input_capture::run_input_capture,
+    logging::{default_filter, install_log_levels, LogFileWriter, LogLevels},
+    mcp::{McpServer, McpTool},
+    media_encryption::run_media_encryption,
+    meetings::run_meeting_detector,
+    obsidian::{run_obsidian_export, ObsidianConfig},
+    ocr_queue::run_ocr_queue,
+    offload::{run_offload, set_object_store, ObjectStore, OffloadConfig},
+    partitions::{partitions_dir, run_partitioning},
+    pipe_dev::run_pipe_dev,
     pipe_manager::PipeInfo,
-    start_continuous_recording, watch_pid, PipeManager, ResourceMonitor, SCServer,
+    pipe_scheduler::run_pipe_scheduler,
+    pipe_supervisor::run_pipe_supervisor,
+    power_monitor::run_power_monitor,
+    privacy_audit::run_privacy_audit,
+    query_cache::QueryCache,
+    redaction::Redaction,
+    remote::{discover, RemoteConfig, TlsSource},
+    retention::{retention_days, run_retention, RetentionPolicy},
+    saved_search::{run_saved_search_watcher, SavedSearchNotifier},
+    shutdown::{drain_pipelines, shutdown_signal},
+    storage::{StorageBudget, StorageManager},
+    summaries::run_summaries,
+    extraction::run_extractions,
+    snapshot::{export_snapshot, import_snapshot, SnapshotOptions},
+    sync::{run_sync, SyncService},
+    start_continuous_recording,
+    video_encoder::{resolve_video_encoder, VideoEncoder},
+    vocabulary::run_vocabulary_updater,
+    watchdog::Watchdog,
+    webhooks::WebhookDispatcher,
+    run_semantic_indexer, watch_pid, PipeManager, ResourceMonitor, SCServer,
 };
+use screenpipe_vision::frame_rate::AdaptiveFpsConfig;
 use screenpipe_vision::monitor::list_monitors;
+use screenpipe_vision::paddle::set_ocr_device;
+use screenpipe_vision::PrivacyBlocklist;
 #[cfg(target_os = "macos")]
 use screenpipe_vision::run_ui;
 use serde_json::{json, Value};
 use std::{
-    env, fs, io::Write, net::SocketAddr, ops::Deref, path::PathBuf, sync::Arc, time::Duration,
+    env, fs, io::Write, net::SocketAddr, ops::Deref, path::PathBuf, sync::{Arc, RwLock}, time::Duration,
     net::{IpAddr, Ipv4Addr},
 };
 use tokio::{runtime::Runtime, signal, sync::broadcast};
 use tracing::{debug, error, info, warn};
 use tracing_appender::non_blocking::WorkerGuard;
-use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::{fmt, EnvFilter};
+use tracing_subscriber::{fmt, reload, EnvFilter};
 use tracing_subscriber::{prelude::__tracing_subscriber_SubscriberExt, Layer};
 use serde::Deserialize;
 use std::path::Path;
@@ -78,73 +142,54 @@ fn get_base_dir(custom_path: &Option<String>) -> anyhow::Result<PathBuf> {
     Ok(base_dir)
 }
 
+/// Client of the local LLM `llm` picked with `flag`.
+#[cfg_attr(feature = "llm", allow(unused_variables))]
+fn local_llm(
+    llm: &CliLocalLlm,
+    ollama_url: &str,
+    ollama_model: &str,
+    flag: &str,
+) -> anyhow::Result<LlmClient> {
+    match llm {
+        CliLocalLlm::Ollama => Ok(LlmClient::ollama(ollama_url, ollama_model)),
+        #[cfg(feature = "llm")]
+        CliLocalLlm::Builtin => Ok(LlmClient::builtin(screenpipe_core::LLM::new(
+            screenpipe_core::ModelName::Llama,
+        )?)),
+        #[cfg(not(feature = "llm"))]
+        CliLocalLlm::Builtin => Err(anyhow::anyhow!(
+            "{} builtin needs a build with the `llm` feature",
+            flag
+        )),
+    }
+}
+
 fn setup_logging(local_data_dir: &PathBuf, cli: &Cli) -> anyhow::Result<WorkerGuard> {
-    let file_appender = RollingFileAppender::builder()
-        .rotation(Rotation::DAILY)
-        .filename_prefix("screenpipe")
-        .filename_suffix("log")
-        .max_log_files(5)
-        .build(local_data_dir)?;
+    let file_appender = LogFileWriter::new(
+        local_data_dir,
+        cli.log_max_file_size_mb * 1024 * 1024,
+        cli.log_max_files,
+    )?;
 
     let (file_writer, guard) = tracing_appender::non_blocking(file_appender);
 
-    let make_env_filter = || {
-        let filter = EnvFilter::from_default_env()
-            .add_directive("tokio=debug".parse().unwrap())
-            .add_directive("runtime=debug".parse().unwrap())
-            .add_directive("info".parse().unwrap())
-            .add_directive("tokenizers=error".parse().unwrap())
-            .add_directive("rusty_tesseract=error".parse().unwrap())
-            .add_directive("symphonia=error".parse().unwrap())
-            .add_directive("hf_hub=error".parse().unwrap())
-            .add_directive("whisper_rs=error".parse().unwrap());
-
-        #[cfg(target_os = "windows")]
-        let filter = filter
-            .add_directive("xcap::platform::impl_window=off".parse().unwrap())
-            .add_directive("xcap::platform::impl_monitor=off".parse().unwrap())
-            .add_directive("xcap::platform::utils=off".parse().unwrap());
-
-        let filter = env::var("SCREENPIPE_LOG")
-            .unwrap_or_default()
-            .split(',')
-            .filter(|s| !s.is_empty())
-            .fold(filter, |filter, module_directive| {
-                match module_directive.parse() {
-                    Ok(directive) => filter.add_directive(directive),
-                    Err(e) => {
-                        eprintln!(
-                            "warning: invalid log directive '{}': {}",
-                            module_directive, e
-                        );
-                        filter
-                    }
-                }
-            });
+    let make_env_filter = || default_filter(cli.debug);
 
-        if cli.debug {
-            filter.add_directive("screenpipe=debug".parse().unwrap())
-        } else {
-            filter
-        }
-    };
-
-    let timer =
-        tracing_subscriber::fmt::time::ChronoLocal::new("%Y-%m-%dT%H:%M:%S%.6fZ".to_string());
+    // the filters of stdout and the log file are swapped by `screenpipe log set`
+    let (stdout_filter, stdout_filter_handle) = reload::Layer::new(make_env_filter());
+    let (file_filter, file_filter_handle) = reload::Layer::new(make_env_filter());
 
     let tracing_registry = tracing_subscriber::registry()
-        .with(
-            fmt::layer()
-                .with_writer(std::io::stdout)
-                .with_timer(timer.clone())
-                .with_filter(make_env_filter()),
-        )
-        .with(
-            fmt::layer()
-                .with_writer(file_writer)
-                .with_timer(timer)
-                .with_filter(make_env_filter()),
-        );
+        .with(fmt_layer(cli.log_format, std::io::stdout).with_filter(stdout_filter))
+        .with(fmt_layer(cli.log_format, file_writer).with_filter(file_filter));
+
+    install_log_levels(LogLevels::new(
+        cli.debug,
+        vec![
+            Box::new(move |filter| stdout_filter_handle.reload(filter)),
+            Box::new(move |filter| file_filter_handle.reload(filter)),
+        ],
+    ));
 
     #[cfg(feature = "debug-console")]
     let tracing_registry = tracing_registry.with(
@@ -155,6 +200,18 @@ fn setup_logging(local_data_dir: &PathBuf, cli: &Cli) -> anyhow::Result<WorkerGu
         ),
     );
 
+    #[cfg(feature = "otel")]
+    let tracing_registry = tracing_registry.with(match &cli.otlp_endpoint {
+        Some(endpoint) => Some(otlp_layer(endpoint)?.with_filter(make_env_filter())),
+        None => None,
+    });
+    #[cfg(not(feature = "otel"))]
+    if cli.otlp_endpoint.is_some() {
+        eprintln!(
+            "warning: --otlp-endpoint needs a build with the otel feature, no traces are exported"
+        );
+    }
+
     // Build the final registry with conditional Sentry layer
     if !cli.disable_telemetry {
         tracing_registry
@@ -167,11 +224,65 @@ fn setup_logging(local_data_dir: &PathBuf, cli: &Cli) -> anyhow::Result<WorkerGu
     Ok(guard)
 }
 
+/// Layer writing log lines to `writer`, as text or as one JSON object per
+/// line with the fields of the event and of its span.
+fn fmt_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+    W: for<'writer> fmt::MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let timer = fmt::time::ChronoLocal::new("%Y-%m-%dT%H:%M:%S%.6fZ".to_string());
+    let layer = fmt::layer().with_writer(writer).with_timer(timer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().flatten_event(true).boxed(),
+    }
+}
+
+/// Layer exporting spans to the OTLP collector at `endpoint`, in batches.
+#[cfg(feature = "otel")]
+fn otlp_layer<S>(endpoint: &str) -> anyhow::Result<impl Layer<S>>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_resource(opentelemetry_sdk::Resource::new([
+            opentelemetry::KeyValue::new("service.name", "screenpipe"),
+            opentelemetry::KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+        ]))
+        .build();
+    let tracer = provider.tracer("screenpipe");
+    // kept to flush the spans left on shutdown
+    opentelemetry::global::set_tracer_provider(provider);
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
 #[tokio::main]
 #[tracing::instrument]
 async fn main() -> anyhow::Result<()> {
     debug!("starting screenpipe server");
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    // read before anything is spawned, so that pipes don't inherit it
+    let app_token = env::var(APP_TOKEN_ENV).ok().filter(|token| !token.is_empty());
+    env::remove_var(APP_TOKEN_ENV);
+    // nothing is captured, media comes from /ingest
+    if cli.headless {
+        cli.disable_vision = true;
+        cli.disable_audio = true;
+        cli.enable_ui_monitoring = false;
+        cli.enable_input_capture = false;
+        cli.capture_typed_text = false;
+        cli.enable_clipboard_capture = false;
+        cli.idle_timeout_minutes = 0;
+    }
 
     // Initialize Sentry only if telemetry is enabled
     let _sentry_guard = if !cli.disable_telemetry {
@@ -225,6 +336,18 @@ async fn main() -> anyhow::Result<()> {
             output: OutputFormat::Text,
             ..
         }) => true,
+        // stdout carries the protocol
+        Some(Command::Mcp {
+            subcommand: McpCommand::Serve { .. },
+        }) => false,
+        Some(Command::Token { .. }) | Some(Command::Sync { .. }) => false,
+        Some(Command::Backup { .. }) | Some(Command::Export { .. }) => false,
+        Some(Command::Db { .. }) | Some(Command::Doctor { .. }) => false,
+        Some(Command::Log { .. }) | Some(Command::CrashMonitor { .. }) => false,
+        Some(Command::Import { .. }) => false,
+        Some(Command::Remote {
+            subcommand: RemoteCommand::Discover { output, .. },
+        }) => *output == OutputFormat::Text,
         _ => true,
     };
 
@@ -236,7 +359,12 @@ async fn main() -> anyhow::Result<()> {
     };
 
     let pipe_manager = if cli.enable_pipe_manager {
-        Arc::new(PipeManager::new(local_data_dir_clone.clone()))
+        Arc::new(
+            PipeManager::new(local_data_dir_clone.clone()).with_wasm_host(WasmPipeHost {
+                api_url: format!("http://localhost:{}", cli.port),
+                notification_url: cli.notification_url.clone(),
+            }),
+        )
     } else {
         Arc::new(PipeManager::new(PathBuf::from("")))
     };
@@ -319,6 +447,15 @@ async fn main() -> anyhow::Result<()> {
                 batch_delay_ms,
                 continue_on_error,
             } => {
+                if let Some(
+                    subcommand @ (MigrationSubCommand::Export { .. }
+                    | MigrationSubCommand::Import { .. }),
+                ) = subcommand
+                {
+                    handle_snapshot_command(subcommand, data_dir, output, cli.encrypt_data).await?;
+                    return Ok(());
+                }
+
                 // Initialize the database
                 let local_data_dir = get_base_dir(data_dir)?;
                 let db = Arc::new(
@@ -346,6 +483,11 @@ async fn main() -> anyhow::Result<()> {
                     Some(MigrationSubCommand::Pause) => MigrationCommand::Pause,
                     Some(MigrationSubCommand::Stop) => MigrationCommand::Stop,
                     Some(MigrationSubCommand::Status) | None => MigrationCommand::Status,
+                    Some(
+                        MigrationSubCommand::Export { .. } | MigrationSubCommand::Import { .. },
+                    ) => {
+                        unreachable!("snapshots are handled above")
+                    }
                 };
 
                 // Send the command to the worker
@@ -546,10 +688,184 @@ async fn main() -> anyhow::Result<()> {
                 .await?;
                 return Ok(());
             }
+            Command::Mcp {
+                subcommand: McpCommand::Serve { data_dir, tool },
+            } => {
+                let db = open_database(data_dir, cli.encrypt_data).await?;
+                let tools = tool.iter().cloned().map(McpTool::from).collect();
+                McpServer::new(Arc::new(db), tools).run_stdio().await?;
+                return Ok(());
+            }
             Command::Mcp { subcommand } => {
                 handle_mcp_command(subcommand, &local_data_dir_clone).await?;
                 return Ok(());
             }
+            Command::Token { subcommand } => {
+                handle_token_command(subcommand, cli.encrypt_data).await?;
+                return Ok(());
+            }
+            Command::Export {
+                format,
+                start,
+                end,
+                app,
+                tag,
+                speaker,
+                out,
+                data_dir,
+            } => {
+                let db = open_database(data_dir, cli.encrypt_data).await?;
+                let start = parse_export_time(start, false)?;
+                let end = match end {
+                    Some(end) => parse_export_time(end, true)?,
+                    None => chrono::Utc::now(),
+                };
+                let filter = ExportFilter {
+                    app_name: app.clone(),
+                    tag: tag.clone(),
+                    speaker: speaker.clone(),
+                };
+                let format = ExportFormat::from(format.clone());
+                match out {
+                    // a note per day, e.g. into an obsidian vault
+                    Some(dir) if format == ExportFormat::Markdown && dir.is_dir() => {
+                        let notes = export_daily_notes(&db, start, end, &filter, None).await?;
+                        for (day, note) in &notes {
+                            let path = dir.join(format!("{}.md", day.format("%Y-%m-%d")));
+                            fs::write(path, note.render())?;
+                        }
+                        eprintln!("wrote {} notes to {}", notes.len(), dir.display());
+                    }
+                    Some(path) => {
+                        fs::write(path, export(&db, format, start, end, &filter).await?)?
+                    }
+                    None => print!("{}", export(&db, format, start, end, &filter).await?),
+                }
+                return Ok(());
+            }
+            Command::Backup { subcommand } => {
+                handle_backup_command(subcommand, cli.encrypt_data).await?;
+                return Ok(());
+            }
+            Command::Db { subcommand } => {
+                handle_db_command(subcommand, cli.encrypt_data).await?;
+                return Ok(());
+            }
+            Command::CrashMonitor { socket, data_dir } => {
+                let screenpipe_dir = get_base_dir(data_dir)?;
+                let socket = socket.clone();
+                tokio::task::spawn_blocking(move || run_crash_monitor(&socket, &screenpipe_dir))
+                    .await??;
+                return Ok(());
+            }
+            Command::Log { subcommand } => {
+                handle_log_command(subcommand).await?;
+                return Ok(());
+            }
+            Command::Import {
+                source,
+                path,
+                key,
+                data_dir,
+                output,
+            } => {
+                let db = open_database(data_dir, cli.encrypt_data).await?;
+                let report = import(&db, source.clone().into(), path, key.as_deref()).await?;
+                match output {
+                    OutputFormat::Json => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&json!({
+                            "data": report,
+                            "success": true
+                        }))?
+                    ),
+                    OutputFormat::Text => {
+                        println!(
+                            "imported {} frames, {} transcripts and {} window events",
+                            report.frames, report.transcripts, report.window_events
+                        );
+                        for skipped in &report.skipped {
+                            println!("  skipped {}", skipped);
+                        }
+                    }
+                }
+                return Ok(());
+            }
+            Command::Doctor {
+                repair,
+                data_dir,
+                output,
+            } => {
+                let db = open_database(data_dir, cli.encrypt_data).await?;
+                let report = run_doctor(&db, &get_base_dir(data_dir)?, *repair).await?;
+                match output {
+                    OutputFormat::Json => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&json!({
+                            "data": report,
+                            "success": true
+                        }))?
+                    ),
+                    OutputFormat::Text => {
+                        if report.issues.is_empty() {
+                            println!("no issues found");
+                        }
+                        for issue in &report.issues {
+                            println!(
+                                "  [{}] {}{}",
+                                issue.check,
+                                issue.description,
+                                if issue.repaired { " (repaired)" } else { "" }
+                            );
+                        }
+                        if !report.repair && !report.issues.is_empty() {
+                            println!("run with --repair to repair them");
+                        }
+                    }
+                }
+                if report.unrepaired() > 0 {
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+            Command::Sync {
+                subcommand: SyncCommand::Key,
+            } => {
+                println!("{}", EncryptionKey::generate().to_hex());
+                return Ok(());
+            }
+            Command::Remote {
+                subcommand: RemoteCommand::Discover { timeout, output },
+            } => {
+                let instances = discover(Duration::from_secs(*timeout)).await?;
+                match output {
+                    OutputFormat::Json => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&json!({
+                            "data": instances,
+                            "success": true
+                        }))?
+                    ),
+                    OutputFormat::Text => {
+                        if instances.is_empty() {
+                            println!("no screenpipe instance found on the network");
+                        }
+                        for instance in instances {
+                            let addresses: Vec<String> =
+                                instance.addresses.iter().map(|a| a.to_string()).collect();
+                            println!(
+                                "  {} ({}) port: {}, version: {}, fingerprint: {}",
+                                instance.name,
+                                addresses.join(", "),
+                                instance.port,
+                                instance.version.as_deref().unwrap_or("-"),
+                                instance.fingerprint.as_deref().unwrap_or("-")
+                            );
+                        }
+                    }
+                }
+                return Ok(());
+            }
         }
     }
 
@@ -571,20 +887,41 @@ async fn main() -> anyhow::Result<()> {
         return Err(anyhow::anyhow!("port already in use"));
     }
 
-    let all_monitors = list_monitors().await;
+    // the app asks before sending the reports of crashes anywhere
+    let _crash_reporter = if cli.disable_crash_reports {
+        None
+    } else {
+        match start_crash_reporter(&local_data_dir) {
+            Ok(reporter) => Some(reporter),
+            Err(e) => {
+                warn!("crash reports are disabled: {}", e);
+                None
+            }
+        }
+    };
+
+    // without a display there's nothing to list
+    let all_monitors = if cli.disable_vision {
+        Vec::new()
+    } else {
+        list_monitors().await
+    };
 
     let mut audio_devices = Vec::new();
 
     let mut realtime_audio_devices = Vec::new();
 
     if !cli.disable_audio {
-        if cli.audio_device.is_empty() {
+        if cli.audio_device.is_empty() && cli.audio_channel.is_empty() {
             // Use default devices
             if let Ok(input_device) = default_input_device() {
                 audio_devices.push(input_device.to_string());
             }
-            if let Ok(output_device) = default_output_device().await {
-                audio_devices.push(output_device.to_string());
+            // applications replace the system output, so other apps are not recorded
+            if cli.audio_app.is_empty() || !cfg!(target_os = "windows") {
+                if let Ok(output_device) = default_output_device().await {
+                    audio_devices.push(output_device.to_string());
+                }
             }
         } else {
             // Use specified devices
@@ -592,6 +929,18 @@ async fn main() -> anyhow::Result<()> {
                 let device = parse_audio_device(d).expect("failed to parse audio device");
                 audio_devices.push(device.to_string());
             }
+            for c in &cli.audio_channel {
+                let device = parse_audio_channel(c).expect("failed to parse audio channel");
+                audio_devices.push(device.to_string());
+            }
+        }
+
+        if cfg!(target_os = "windows") {
+            for app in &cli.audio_app {
+                audio_devices.push(AudioDevice::app(app).to_string());
+            }
+        } else if !cli.audio_app.is_empty() {
+            warn!("--audio-app is only supported on windows, recording the system output instead");
         }
 
         if audio_devices.is_empty() {
@@ -624,13 +973,30 @@ async fn main() -> anyhow::Result<()> {
     let resource_monitor = ResourceMonitor::new(!cli.disable_telemetry);
     resource_monitor.start_monitoring(Duration::from_secs(30), Some(Duration::from_secs(60)));
 
+    // cleared even with encryption off, a crash may have left recordings
+    // decrypted while it was on
+    let decrypt_dir = prepare_decrypt_dir(&local_data_dir)?;
+    let encryption_key = if cli.encrypt_data {
+        let key = load_or_create_key().map_err(|e| {
+            eprintln!("failed to unlock the encryption key: {:?}", e);
+            e
+        })?;
+        unlock_media(&key, decrypt_dir);
+        Some(key)
+    } else {
+        None
+    };
+
+    let db_path = format!("{}/db.sqlite", local_data_dir.to_string_lossy());
     let db = Arc::new(
-        DatabaseManager::new(&format!("{}/db.sqlite", local_data_dir.to_string_lossy()))
-            .await
-            .map_err(|e| {
-                eprintln!("failed to initialize database: {:?}", e);
-                e
-            })?,
+        match &encryption_key {
+            Some(key) => DatabaseManager::new_encrypted(&db_path, &key.to_hex()).await,
+            None => DatabaseManager::new(&db_path).await,
+        }
+        .map_err(|e| {
+            eprintln!("failed to initialize database: {:?}", e);
+            e
+        })?,
     );
 
     let db_server = db.clone();
@@ -647,10 +1013,31 @@ async fn main() -> anyhow::Result<()> {
     let languages_clone = languages.clone();
 
     let ocr_engine_clone = cli.ocr_engine.clone();
+    let capture_region_clone = cli.capture_region.clone();
+    let capture_mode_clone = cli.capture_mode.clone();
     let vad_engine = cli.vad_engine.clone();
     let vad_engine_clone = vad_engine.clone();
     let vad_sensitivity_clone = cli.vad_sensitivity.clone();
     let (shutdown_tx, _) = broadcast::channel::<()>(1);
+    // subscribed before recording starts so that no block goes unrecorded
+    tokio::spawn(run_privacy_audit(db.clone(), shutdown_tx.subscribe()));
+    if cli.enable_input_capture {
+        tokio::spawn(run_input_capture(
+            db.clone(),
+            cli.capture_typed_text,
+            shutdown_tx.subscribe(),
+        ));
+    }
+    if cli.enable_ui_monitoring {
+        tokio::spawn(run_focus_tracker(db.clone(), shutdown_tx.subscribe()));
+    }
+    if cli.idle_timeout_minutes > 0 {
+        tokio::spawn(run_idle_monitor(
+            db.clone(),
+            Duration::from_secs(cli.idle_timeout_minutes * 60),
+            shutdown_tx.subscribe(),
+        ));
+    }
 
     let vision_runtime = Runtime::new().unwrap();
     let pipes_runtime = Runtime::new().unwrap();
@@ -673,17 +1060,96 @@ async fn main() -> anyhow::Result<()> {
         1.0
     };
 
+    if let Some(max_storage_gb) = cli.max_storage_gb {
+        if !max_storage_gb.is_finite() || max_storage_gb <= 0.0 {
+            return Err(anyhow::anyhow!(
+                "invalid --max-storage-gb value: {}",
+                max_storage_gb
+            ));
+        }
+    }
+
+    let adaptive_fps = Arc::new(RwLock::new(AdaptiveFpsConfig {
+        enabled: cli.adaptive_fps,
+        min_fps: cli.adaptive_fps_min,
+        ..Default::default()
+    }));
+    let adaptive_fps_server = adaptive_fps.clone();
+
+    let retention = Arc::new(RwLock::new(RetentionPolicy {
+        enabled: cli.enable_retention,
+        video_days: retention_days(cli.retention_video_days),
+        audio_days: retention_days(cli.retention_audio_days),
+        ocr_days: retention_days(cli.retention_ocr_days),
+        transcript_days: retention_days(cli.retention_transcript_days),
+    }));
+
+    let video_encoder = if cli.disable_vision {
+        VideoEncoder::default()
+    } else {
+        resolve_video_encoder(cli.video_encoder.clone().into()).await
+    };
+
+    let redaction = if cli.use_pii_removal {
+        let kinds: Vec<_> = cli
+            .pii_detector
+            .iter()
+            .filter_map(CliPiiDetector::regex_kind)
+            .collect();
+        let ner = cli.pii_detector.contains(&CliPiiDetector::Ner);
+        Some(
+            Redaction::new(&kinds, ner, cli.pii_blur_frames).map_err(|e| {
+                eprintln!("failed to set up pii removal: {:?}", e);
+                e
+            })?,
+        )
+    } else {
+        None
+    };
+
+    let privacy_blocklist = Arc::new(
+        PrivacyBlocklist::new(&cli.privacy_blocklist)
+            .block_private_browsing(!cli.capture_private_browsing),
+    );
+    if cli.enable_clipboard_capture {
+        tokio::spawn(run_clipboard_capture(
+            db.clone(),
+            privacy_blocklist.clone(),
+            shutdown_tx.subscribe(),
+        ));
+    }
+
     let audio_chunk_duration = Duration::from_secs(cli.audio_chunk_duration);
 
     let mut audio_manager_builder = AudioManagerBuilder::new()
         .audio_chunk_duration(audio_chunk_duration)
+        .audio_chunk_overlap(Duration::from_millis(cli.audio_chunk_overlap_ms))
         .vad_engine(vad_engine.into())
         .vad_sensitivity(cli.vad_sensitivity.into())
+        .vad_min_speech_duration(Duration::from_millis(cli.vad_min_speech_duration_ms))
+        .vad_hangover(Duration::from_millis(cli.vad_hangover_ms))
         .languages(languages.clone())
         .transcription_engine(cli.audio_transcription_engine.into())
         .realtime(cli.enable_realtime_audio_transcription)
         .enabled_devices(audio_devices)
+        .device_priority(cli.audio_device_priority.clone())
         .deepgram_api_key(cli.deepgram_api_key.clone())
+        .stt_fallback(cli.stt_fallback.clone().map(Into::into))
+        .openai_stt(OpenAiSttConfig {
+            api_key: cli
+                .openai_api_key
+                .clone()
+                .or_else(|| env::var("OPENAI_API_KEY").ok()),
+            model: cli.openai_stt_model.clone(),
+            base_url: cli.openai_stt_url.clone(),
+        })
+        .disable_cloud_stt(cli.disable_cloud_stt)
+        .echo_cancellation(!cli.disable_echo_cancellation)
+        .noise_suppression(cli.noise_suppression)
+        .noise_suppression_devices(cli.noise_suppression_device.clone())
+        .translate_transcripts(cli.translate_transcripts)
+        .media_detection(cli.enable_media_detection)
+        .redactor(redaction.as_ref().map(|r| r.redactor.clone()))
         .output_path(PathBuf::from(output_path_clone.clone().to_string()));
 
     let audio_manager = match audio_manager_builder.build(db.clone()).await {
@@ -694,11 +1160,46 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
-    let handle = {
+    set_ocr_device(cli.ocr_device.clone().into());
+    // blurring needs the text positions before the frame is recorded
+    let can_defer_ocr = !redaction.as_ref().is_some_and(|r| r.blur_frames);
+    if cli.ocr_mode == CliOcrMode::Deferred && !can_defer_ocr {
+        warn!("ocr can't be deferred with --pii-blur-frames, ocr runs in realtime");
+    } else {
+        set_ocr_mode(cli.ocr_mode.clone().into());
+    }
+    tokio::spawn(run_power_monitor(
+        (!cli.disable_audio).then(|| audio_manager.clone()),
+        PowerSettings {
+            low_power_percent: cli.battery_low_power_percent,
+            pause_audio_percent: cli.battery_pause_audio_percent,
+            defer_ocr: can_defer_ocr,
+        },
+        shutdown_tx.subscribe(),
+    ));
+    tokio::spawn(run_ocr_queue(
+        db.clone(),
+        Arc::new(cli.ocr_engine.clone().into()),
+        languages.clone(),
+        redaction.clone(),
+        output_path_clone.to_string(),
+        shutdown_tx.subscribe(),
+    ));
+    tokio::spawn(run_ingest(
+        db.clone(),
+        audio_manager.clone(),
+        Arc::new(cli.ocr_engine.clone().into()),
+        languages.clone(),
+        redaction.clone(),
+        output_path_clone.to_string(),
+        shutdown_tx.subscribe(),
+    ));
+
+    let mut handle = {
         let runtime = &tokio::runtime::Handle::current();
         runtime.spawn(async move {
+            let mut shutdown_rx = shutdown_tx_clone.subscribe();
             loop {
-                let mut shutdown_rx = shutdown_tx_clone.subscribe();
                 let recording_future = start_continuous_recording(
                     db_clone.clone(),
                     output_path_clone.clone(),
@@ -706,51 +1207,71 @@ async fn main() -> anyhow::Result<()> {
                     Duration::from_secs(cli.video_chunk_duration),
                     Arc::new(cli.ocr_engine.clone().into()),
                     monitor_ids_clone.clone(),
-                    cli.use_pii_removal,
+                    redaction.clone(),
                     cli.disable_vision,
                     &vision_handle,
                     &cli.ignored_windows,
                     &cli.included_windows,
+                    privacy_blocklist.clone(),
                     languages_clone.clone(),
                     cli.capture_unfocused_windows,
+                    cli.enable_ui_monitoring,
+                    !cli.disable_accessibility_text,
                     cli.enable_realtime_audio_transcription,
+                    &capture_region_clone,
+                    capture_mode_clone.clone().into(),
+                    adaptive_fps.clone(),
+                    video_encoder,
+                    shutdown_tx_clone.subscribe(),
                 );
 
-                let result = tokio::select! {
-                    result = recording_future => result,
-                    _ = shutdown_rx.recv() => {
-                        info!("received shutdown signal for recording");
-                        break;
-                    }
-                };
-
-                if let Err(e) = result {
+                // returns after a shutdown, once the frames captured are written
+                if let Err(e) = recording_future.await {
                     error!("continuous recording error: {:?}", e);
                 }
+                if !matches!(
+                    shutdown_rx.try_recv(),
+                    Err(broadcast::error::TryRecvError::Empty)
+                ) {
+                    info!("received shutdown signal for recording");
+                    break;
+                }
             }
         })
     };
 
     let local_data_dir_clone_2 = local_data_dir_clone.clone();
-    #[cfg(feature = "llm")]
-    debug!("LLM initializing");
-
-    #[cfg(feature = "llm")]
-    let _llm = {
-        match cli.enable_llm {
-            true => Some(screenpipe_core::LLM::new(
-                screenpipe_core::ModelName::Llama,
-            )?),
-            false => None,
-        }
+    // loaded in the background, the first start downloads the model
+    let inference = if cli.enable_llm {
+        let model = local_model(&cli.llm_model)
+            .ok_or_else(|| anyhow::anyhow!("unknown llm model: {}", cli.llm_model))?;
+        let device = match cli.llm_device {
+            CliLlmDevice::Auto => InferenceDevice::Auto,
+            CliLlmDevice::Cpu => InferenceDevice::Cpu,
+            CliLlmDevice::Gpu => InferenceDevice::Gpu(cli.llm_gpu_index),
+        };
+        let inference = Arc::new(LocalInference::new(device));
+        // failures are reported by /llm/models
+        inference.load(model, cli.llm_quantization.clone().into())?;
+        Some(inference)
+    } else {
+        None
     };
 
-    #[cfg(feature = "llm")]
-    debug!("LLM initialized");
-
+    let query_cache = Arc::new(QueryCache::new(Duration::from_secs(
+        cli.query_cache_ttl_secs,
+    )));
     let server = SCServer::new(
         db_server,
-        SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), cli.port),
+        // with remote access the lan goes through tls
+        SocketAddr::new(
+            IpAddr::V4(if cli.remote {
+                Ipv4Addr::LOCALHOST
+            } else {
+                Ipv4Addr::UNSPECIFIED
+            }),
+            cli.port,
+        ),
         local_data_dir_clone_2,
         pipe_manager.clone(),
         cli.disable_vision,
@@ -758,7 +1279,135 @@ async fn main() -> anyhow::Result<()> {
         cli.enable_ui_monitoring,
         audio_manager.clone(),
         cli.enable_pipe_manager,
-    );
+    )
+    .with_adaptive_fps(adaptive_fps_server)
+    .with_retention(retention.clone())
+    .with_query_cache(query_cache.clone())
+    .with_shutdown(shutdown_tx.clone());
+    let server = if cli.enable_mcp {
+        server.with_mcp(cli.mcp_tool.iter().cloned().map(McpTool::from).collect())
+    } else {
+        server
+    };
+    if (cli.api_auth || cli.remote)
+        && db.list_api_tokens().await.map_or(true, |tokens| tokens.is_empty())
+    {
+        warn!(
+            "api tokens are required but none exists, create one with `screenpipe token create`"
+        );
+    }
+    let server = if cli.api_auth {
+        server.with_api_auth()
+    } else {
+        server
+    };
+    let server = match app_token {
+        Some(token) => server.with_app_token(token),
+        None => server,
+    };
+    let sync = match &cli.sync_key {
+        Some(key) => {
+            let key = EncryptionKey::from_hex(key)?;
+            Some(Arc::new(SyncService::new(db.clone(), &key).await?))
+        }
+        None => None,
+    };
+    if let Some(sync) = &sync {
+        if !cli.sync_peer.is_empty() {
+            tokio::spawn(run_sync(
+                sync.clone(),
+                cli.sync_peer.clone(),
+                Duration::from_secs(cli.sync_interval.max(1)),
+                shutdown_tx.subscribe(),
+            ));
+        }
+    }
+    let server = match sync {
+        Some(sync) => server.with_sync(sync),
+        None => server,
+    };
+    let mut calendars = Vec::new();
+    if let Some(url) = &cli.caldav_url {
+        calendars.push(CalendarSource::CalDav {
+            url: url.clone(),
+            username: cli.caldav_username.clone(),
+            password: cli.caldav_password.clone(),
+        });
+    }
+    if let (Some(refresh_token), Some(client_id), Some(client_secret)) = (
+        &cli.google_calendar_refresh_token,
+        &cli.google_calendar_client_id,
+        &cli.google_calendar_client_secret,
+    ) {
+        calendars.push(CalendarSource::Google {
+            client_id: client_id.clone(),
+            client_secret: client_secret.clone(),
+            refresh_token: refresh_token.clone(),
+            calendar_id: cli.google_calendar_id.clone(),
+        });
+    }
+    if !calendars.is_empty() {
+        tokio::spawn(run_calendar_sync(
+            db.clone(),
+            calendars,
+            shutdown_tx.subscribe(),
+        ));
+    }
+    let summarizer = if cli.enable_summaries {
+        Some(Arc::new(Summarizer::new(local_llm(
+            &cli.summary_llm,
+            &cli.summary_ollama_url,
+            &cli.summary_model,
+            "--summary-llm",
+        )?)))
+    } else {
+        None
+    };
+    let extractor = if cli.enable_extractions {
+        Some(Arc::new(Extractor::new(local_llm(
+            &cli.extraction_llm,
+            &cli.extraction_ollama_url,
+            &cli.extraction_model,
+            "--extraction-llm",
+        )?)))
+    } else {
+        None
+    };
+    let server = if cli.enable_query {
+        server.with_answerer(Arc::new(Answerer::new(local_llm(
+            &cli.query_llm,
+            &cli.query_ollama_url,
+            &cli.query_model,
+            "--query-llm",
+        )?)))
+    } else {
+        server
+    };
+    let server = match &summarizer {
+        Some(summarizer) => server.with_summarizer(summarizer.clone()),
+        None => server,
+    };
+    let server = match inference {
+        Some(inference) => server.with_inference(inference),
+        None => server,
+    };
+    let server = if cli.remote {
+        let tls = match (&cli.tls_cert, &cli.tls_key) {
+            (Some(cert), Some(key)) => TlsSource::Provided {
+                cert: cert.clone(),
+                key: key.clone(),
+            },
+            _ => TlsSource::SelfSigned {
+                dir: local_data_dir.join("tls"),
+            },
+        };
+        server.with_remote(RemoteConfig {
+            port: cli.remote_port,
+            tls,
+        })
+    } else {
+        server
+    };
 
     // print screenpipe in gradient
     println!("\n\n{}", DISPLAY.truecolor(147, 112, 219).bold());
@@ -777,9 +1426,21 @@ async fn main() -> anyhow::Result<()> {
     println!("│ setting                │ value                              │");
     println!("├────────────────────────┼────────────────────────────────────┤");
     println!("│ fps                    │ {:<34} │", cli.fps);
+    println!(
+        "│ adaptive fps           │ {:<34} │",
+        if cli.adaptive_fps {
+            format!("enabled (min {})", cli.adaptive_fps_min)
+        } else {
+            "disabled".to_string()
+        }
+    );
+    println!("│ video encoder          │ {:<34} │", video_encoder);
     println!(
         "│ audio chunk duration   │ {:<34} │",
-        format!("{} seconds", cli.audio_chunk_duration)
+        format!(
+            "{} seconds, {}ms overlap",
+            cli.audio_chunk_duration, cli.audio_chunk_overlap_ms
+        )
     );
     println!(
         "│ video chunk duration   │ {:<34} │",
@@ -792,6 +1453,7 @@ async fn main() -> anyhow::Result<()> {
     );
     println!("│ audio disabled         │ {:<34} │", cli.disable_audio);
     println!("│ vision disabled        │ {:<34} │", cli.disable_vision);
+    println!("│ headless               │ {:<34} │", cli.headless);
     println!(
         "│ audio engine           │ {:<34} │",
         format!("{:?}", warning_audio_transcription_engine_clone)
@@ -808,6 +1470,13 @@ async fn main() -> anyhow::Result<()> {
         "│ vad sensitivity        │ {:<34} │",
         format!("{:?}", vad_sensitivity_clone)
     );
+    println!(
+        "│ vad min speech / hang  │ {:<34} │",
+        format!(
+            "{}ms / {}ms",
+            cli.vad_min_speech_duration_ms, cli.vad_hangover_ms
+        )
+    );
     println!(
         "│ data directory         │ {:<34} │",
         local_data_dir_clone.display()
@@ -817,9 +1486,27 @@ async fn main() -> anyhow::Result<()> {
         "│ telemetry              │ {:<34} │",
         !cli.disable_telemetry
     );
-    println!("│ local llm              │ {:<34} │", cli.enable_llm);
+    println!(
+        "│ local llm              │ {:<34} │",
+        if cli.enable_llm {
+            format!(
+                "{} {}",
+                cli.llm_model,
+                Quantization::from(cli.llm_quantization.clone()).as_str()
+            )
+        } else {
+            "disabled".to_string()
+        }
+    );
 
     println!("│ use pii removal        │ {:<34} │", cli.use_pii_removal);
+    if cli.use_pii_removal {
+        println!(
+            "│ pii detectors          │ {:<34} │",
+            format_cell(&format!("{:?}", &cli.pii_detector), VALUE_WIDTH)
+        );
+        println!("│ pii blur frames        │ {:<34} │", cli.pii_blur_frames);
+    }
     println!(
         "│ ignored windows        │ {:<34} │",
         format_cell(&format!("{:?}", &ignored_windows_clone), VALUE_WIDTH)
@@ -828,6 +1515,18 @@ async fn main() -> anyhow::Result<()> {
         "│ included windows       │ {:<34} │",
         format_cell(&format!("{:?}", &included_windows_clone), VALUE_WIDTH)
     );
+    println!(
+        "│ privacy blocklist      │ {:<34} │",
+        format_cell(&format!("{:?}", &cli.privacy_blocklist), VALUE_WIDTH)
+    );
+    println!(
+        "│ private browsing       │ {:<34} │",
+        if cli.capture_private_browsing {
+            "recorded"
+        } else {
+            "paused"
+        }
+    );
     println!(
         "│ ui monitoring          │ {:<34} │",
         cli.enable_ui_monitoring
@@ -840,6 +1539,69 @@ async fn main() -> anyhow::Result<()> {
         "│ capture unfocused wins │ {:<34} │",
         cli.capture_unfocused_windows
     );
+    #[cfg(target_os = "windows")]
+    println!(
+        "│ accessibility text     │ {:<34} │",
+        !cli.disable_accessibility_text
+    );
+    println!(
+        "│ input capture          │ {:<34} │",
+        match (cli.enable_input_capture, cli.capture_typed_text) {
+            (false, _) => "disabled",
+            (true, false) => "counts only",
+            (true, true) => "counts and typed text",
+        }
+    );
+    println!(
+        "│ clipboard capture      │ {:<34} │",
+        cli.enable_clipboard_capture
+    );
+    println!(
+        "│ idle timeout           │ {:<34} │",
+        match cli.idle_timeout_minutes {
+            0 => "disabled".to_string(),
+            minutes => format!("{} min", minutes),
+        }
+    );
+    if ocr_engine_clone == CliOcrEngine::Paddle {
+        println!(
+            "│ ocr device             │ {:<34} │",
+            format!("{:?}", cli.ocr_device)
+        );
+    }
+    println!(
+        "│ ocr mode               │ {:<34} │",
+        ocr_mode()
+    );
+    println!(
+        "│ battery low power      │ {:<34} │",
+        match cli.battery_low_power_percent {
+            0 => "disabled".to_string(),
+            percent => format!("at or below {}%", percent),
+        }
+    );
+    println!(
+        "│ battery pause audio    │ {:<34} │",
+        match cli.battery_pause_audio_percent {
+            0 => "disabled".to_string(),
+            percent => format!("at or below {}%", percent),
+        }
+    );
+    println!(
+        "│ capture mode           │ {:<34} │",
+        format!("{:?}", cli.capture_mode)
+    );
+    println!(
+        "│ capture regions        │ {:<34} │",
+        format_cell(
+            &cli.capture_region
+                .iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+            VALUE_WIDTH
+        )
+    );
     println!(
         "│ auto-destruct pid      │ {:<34} │",
         cli.auto_destruct_pid.unwrap_or(0)
@@ -853,6 +1615,75 @@ async fn main() -> anyhow::Result<()> {
             "not set"
         }
     );
+    println!(
+        "│ stt fallback           │ {:<34} │",
+        match (&cli.stt_fallback, cli.disable_cloud_stt) {
+            (_, true) => "cloud disabled".to_string(),
+            (Some(fallback), false) => format!("{:?}", fallback),
+            (None, false) => "none".to_string(),
+        }
+    );
+    println!("│ translate transcripts  │ {:<34} │", cli.translate_transcripts);
+    println!("│ skip music             │ {:<34} │", cli.enable_media_detection);
+    println!("│ semantic search        │ {:<34} │", cli.enable_semantic_search);
+    println!(
+        "│ activity labels        │ {:<34} │",
+        !cli.disable_activity_classification
+    );
+    println!(
+        "│ meeting detection      │ {:<34} │",
+        !cli.disable_meeting_detection
+    );
+    println!(
+        "│ summaries              │ {:<34} │",
+        if cli.enable_summaries {
+            format!("{:?} after {}:00", cli.summary_llm, cli.summary_hour)
+        } else {
+            "disabled".to_string()
+        }
+    );
+    println!(
+        "│ extractions            │ {:<34} │",
+        if cli.enable_extractions {
+            format!("{:?}", cli.extraction_llm)
+        } else {
+            "disabled".to_string()
+        }
+    );
+    println!(
+        "│ query                  │ {:<34} │",
+        if cli.enable_query {
+            format!("{:?}", cli.query_llm)
+        } else {
+            "disabled".to_string()
+        }
+    );
+    println!("│ encrypt data           │ {:<34} │", cli.encrypt_data);
+    println!(
+        "│ storage budget         │ {:<34} │",
+        match cli.max_storage_gb {
+            Some(gb) if cli.storage_evict_text => format!("{} GB, evicting text", gb),
+            Some(gb) => format!("{} GB", gb),
+            None => "unlimited".to_string(),
+        }
+    );
+    println!(
+        "│ retention              │ {:<34} │",
+        if cli.enable_retention {
+            format_cell(
+                &format!(
+                    "video {}d, audio {}d, ocr {}d, transcripts {}d",
+                    cli.retention_video_days,
+                    cli.retention_audio_days,
+                    cli.retention_ocr_days,
+                    cli.retention_transcript_days
+                ),
+                VALUE_WIDTH,
+            )
+        } else {
+            "disabled".to_string()
+        }
+    );
 
     const VALUE_WIDTH: usize = 34;
 
@@ -1063,6 +1894,10 @@ async fn main() -> anyhow::Result<()> {
             debug!("pipe {} is disabled, skipping", pipe.id);
             continue;
         }
+        if is_scheduled(&pipe.config) {
+            debug!("pipe {} runs on its schedule, skipping", pipe.id);
+            continue;
+        }
         match pipe_manager.start_pipe_task(pipe.id.clone()).await {
             Ok(future) => {
                 pipes_handle.spawn(future);
@@ -1073,6 +1908,141 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
+    if cli.enable_pipe_manager {
+        tokio::spawn(run_pipe_scheduler(pipe_manager.clone(), db.clone(), shutdown_tx.subscribe()));
+        tokio::spawn(run_pipe_supervisor(pipe_manager.clone(), shutdown_tx.subscribe()));
+    }
+    if cli.enable_semantic_search {
+        tokio::spawn(run_semantic_indexer(db.clone(), shutdown_tx.subscribe()));
+    }
+    if !cli.disable_activity_classification {
+        tokio::spawn(run_activity_classifier(db.clone(), shutdown_tx.subscribe()));
+    }
+    if !cli.disable_meeting_detection {
+        tokio::spawn(run_meeting_detector(db.clone(), shutdown_tx.subscribe()));
+    }
+    if !cli.disable_audio {
+        tokio::spawn(run_vocabulary_updater(
+            db.clone(),
+            audio_manager.clone(),
+            shutdown_tx.subscribe(),
+        ));
+    }
+    if let Some(summarizer) = &summarizer {
+        tokio::spawn(run_summaries(
+            db.clone(),
+            summarizer.clone(),
+            cli.summary_hour,
+            shutdown_tx.subscribe(),
+        ));
+    }
+    if let Some(extractor) = extractor {
+        tokio::spawn(run_extractions(
+            db.clone(),
+            extractor,
+            shutdown_tx.subscribe(),
+        ));
+    }
+    // set up before retention, which also deletes offloaded recordings
+    if let Some(bucket) = &cli.offload_bucket {
+        let config = OffloadConfig {
+            bucket: bucket.clone(),
+            endpoint: cli.offload_endpoint.clone(),
+            region: cli.offload_region.clone(),
+            prefix: cli.offload_prefix.clone(),
+            access_key: cli.offload_access_key.clone(),
+            secret_key: cli.offload_secret_key.clone(),
+        };
+        let store = set_object_store(ObjectStore::new(
+            &config,
+            local_data_dir.join("offload-cache"),
+        )?)?;
+        tokio::spawn(run_offload(
+            db.clone(),
+            store,
+            // chunks are recorded to for a few minutes
+            cli.offload_after_days.max(1),
+            shutdown_tx.subscribe(),
+        ));
+    }
+    tokio::spawn(run_retention(db.clone(), retention, shutdown_tx.subscribe()));
+    if cli.partition_after_months > 0 {
+        tokio::spawn(run_partitioning(
+            db.clone(),
+            partitions_dir(&local_data_dir),
+            cli.partition_after_months,
+            shutdown_tx.subscribe(),
+        ));
+    }
+    if let Some(max_storage_gb) = cli.max_storage_gb {
+        let storage_manager = StorageManager::new(
+            db.clone(),
+            local_data_dir.clone(),
+            StorageBudget {
+                max_bytes: (max_storage_gb * 1024.0 * 1024.0 * 1024.0) as u64,
+                keep_text: !cli.storage_evict_text,
+            },
+        );
+        tokio::spawn(storage_manager.run(shutdown_tx.subscribe()));
+    }
+    if cli.watchdog_timeout_secs > 0 {
+        let watchdog = Watchdog::new(
+            db.clone(),
+            audio_manager.clone(),
+            Duration::from_secs(cli.watchdog_timeout_secs),
+            cli.disable_vision,
+            cli.disable_audio,
+        );
+        tokio::spawn(watchdog.run(shutdown_tx.subscribe()));
+    }
+    if let Some(backup_dir) = &cli.backup_dir {
+        let options = BackupOptions {
+            include_media: cli.backup_include_media,
+            incremental: true,
+            key: backup_key(cli.encrypt_data)?,
+        };
+        tokio::spawn(run_backups(
+            db.clone(),
+            local_data_dir.clone(),
+            backup_dir.clone(),
+            options,
+            Duration::from_secs(cli.backup_interval_hours.max(1) * 60 * 60),
+            cli.backup_keep,
+            shutdown_tx.subscribe(),
+        ));
+    }
+    if let Some(vault) = &cli.obsidian_vault {
+        let mut config = ObsidianConfig::new(vault.clone())
+            .with_folder(cli.obsidian_folder.clone())
+            .with_frame_links(format!("http://localhost:{}", cli.port));
+        if let Some(template) = &cli.obsidian_template {
+            config = config.with_template(fs::read_to_string(template).map_err(|e| {
+                anyhow::anyhow!("failed to read {}: {}", template.display(), e)
+            })?);
+        }
+        tokio::spawn(run_obsidian_export(
+            db.clone(),
+            config,
+            Duration::from_secs(cli.obsidian_interval_minutes.max(1) * 60),
+            shutdown_tx.subscribe(),
+        ));
+    }
+    tokio::spawn(WebhookDispatcher::new(db.clone()).run(shutdown_tx.subscribe()));
+    tokio::spawn(query_cache.run(shutdown_tx.subscribe()));
+    tokio::spawn(run_saved_search_watcher(
+        db.clone(),
+        SavedSearchNotifier::new(cli.notification_url.clone()),
+        Duration::from_secs(cli.saved_search_interval_secs.max(1)),
+        shutdown_tx.subscribe(),
+    ));
+    if let Some(cipher) = media_cipher() {
+        tokio::spawn(run_media_encryption(
+            db.clone(),
+            cipher,
+            shutdown_tx.subscribe(),
+        ));
+    }
+
     let server_future = server.start(cli.enable_frame_cache);
     pin_mut!(server_future);
 
@@ -1115,8 +2085,10 @@ async fn main() -> anyhow::Result<()> {
         });
     }
 
-    let ctrl_c_future = signal::ctrl_c();
-    pin_mut!(ctrl_c_future);
+    let shutdown_future = shutdown_signal();
+    pin_mut!(shutdown_future);
+    // sent on by `/admin/shutdown` and the auto-destruct watcher
+    let mut shutdown_rx = shutdown_tx.subscribe();
 
     // Start the UI monitoring task
     #[cfg(target_os = "macos")]
@@ -1147,24 +2119,32 @@ async fn main() -> anyhow::Result<()> {
     }
 
     tokio::select! {
-        _ = handle => info!("recording completed"),
+        _ = &mut handle => info!("recording completed"),
         result = &mut server_future => {
             match result {
                 Ok(_) => info!("server stopped normally"),
                 Err(e) => error!("server stopped with error: {:?}", e),
             }
         }
-        _ = ctrl_c_future => {
-            info!("received ctrl+c, initiating shutdown");
-            audio_manager.shutdown().await?;
-            let _ = shutdown_tx.send(());
-        }
+        _ = shutdown_future => {}
+        _ = shutdown_rx.recv() => info!("shutdown requested, initiating shutdown"),
     }
 
+    let _ = shutdown_tx.send(());
+    drain_pipelines(
+        handle,
+        &audio_manager,
+        &db,
+        Duration::from_secs(cli.shutdown_timeout_secs),
+    )
+    .await;
+
     tokio::task::block_in_place(|| {
         drop(pipes_runtime);
         drop(vision_runtime);
         drop(audio_manager);
+        #[cfg(feature = "otel")]
+        opentelemetry::global::shutdown_tracer_provider();
     });
 
     info!("shutdown complete");
@@ -1172,6 +2152,435 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Opens the database of the data directory, for commands that run without
+/// the recorder.
+async fn open_database(
+    data_dir: &Option<String>,
+    encrypt: bool,
+) -> anyhow::Result<DatabaseManager> {
+    let local_data_dir = get_base_dir(data_dir)?;
+    let db_path = format!("{}/db.sqlite", local_data_dir.to_string_lossy());
+    let db = if encrypt {
+        let key = load_or_create_key()?;
+        DatabaseManager::new_encrypted(&db_path, &key.to_hex()).await
+    } else {
+        DatabaseManager::new(&db_path).await
+    }
+    .map_err(|e| {
+        eprintln!("failed to initialize database: {:?}", e);
+        e
+    })?;
+    Ok(db)
+}
+
+async fn handle_token_command(command: &TokenCommand, encrypt: bool) -> anyhow::Result<()> {
+    match command {
+        TokenCommand::Create {
+            scope,
+            name,
+            data_dir,
+            output,
+        } => {
+            let db = open_database(data_dir, encrypt).await?;
+            let (token, record) =
+                create_token(&db, name.as_deref(), TokenScope::from(scope.clone())).await?;
+            match output {
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&json!({
+                        "data": { "token": token, "info": record },
+                        "success": true
+                    }))?
+                ),
+                OutputFormat::Text => {
+                    println!("created {} token {}:", record.scope, record.id);
+                    println!("  {}", token);
+                    println!("it won't be shown again, send it as `Authorization: Bearer <token>`");
+                }
+            }
+        }
+        TokenCommand::List { data_dir, output } => {
+            let db = open_database(data_dir, encrypt).await?;
+            let tokens = db.list_api_tokens().await?;
+            match output {
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&json!({
+                        "data": tokens,
+                        "success": true
+                    }))?
+                ),
+                OutputFormat::Text => {
+                    println!("api tokens:");
+                    for token in tokens {
+                        println!(
+                            "  id: {}, scope: {}, name: {}, last used: {}",
+                            token.id,
+                            token.scope,
+                            token.name.as_deref().unwrap_or("-"),
+                            token
+                                .last_used_at
+                                .map_or("never".to_string(), |t| t.to_rfc3339())
+                        );
+                    }
+                }
+            }
+        }
+        TokenCommand::Revoke { id, data_dir } => {
+            let db = open_database(data_dir, encrypt).await?;
+            if db.delete_api_token(*id).await? {
+                println!("revoked token {}", id);
+            } else {
+                return Err(anyhow::anyhow!("no token with id {}", id));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Key of the encrypted database, to back it up and check backups.
+fn backup_key(encrypt: bool) -> anyhow::Result<Option<String>> {
+    Ok(if encrypt {
+        Some(load_or_create_key()?.to_hex())
+    } else {
+        None
+    })
+}
+
+async fn handle_snapshot_command(
+    command: &MigrationSubCommand,
+    data_dir: &Option<String>,
+    output: &OutputFormat,
+    encrypt: bool,
+) -> anyhow::Result<()> {
+    match command {
+        MigrationSubCommand::Export {
+            archive,
+            start,
+            end,
+        } => {
+            let db = open_database(data_dir, encrypt).await?;
+            let options = SnapshotOptions {
+                start: start
+                    .as_deref()
+                    .map(|start| parse_export_time(start, false))
+                    .transpose()?,
+                end: end
+                    .as_deref()
+                    .map(|end| parse_export_time(end, true))
+                    .transpose()?,
+                key: backup_key(encrypt)?,
+            };
+            let report = export_snapshot(&db, &get_base_dir(data_dir)?, archive, &options).await?;
+            match output {
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&json!({
+                        "data": report,
+                        "success": true
+                    }))?
+                ),
+                OutputFormat::Text => {
+                    println!("exported to {}", report.path.display());
+                    println!(
+                        "  {} files written ({} bytes), {} kept from the interrupted export",
+                        report.written_files, report.written_bytes, report.resumed_files
+                    );
+                }
+            }
+        }
+        MigrationSubCommand::Import { archive } => {
+            let report = import_snapshot(
+                archive,
+                &get_base_dir(data_dir)?,
+                backup_key(encrypt)?.as_deref(),
+            )
+            .await?;
+            match output {
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&json!({
+                        "data": report,
+                        "success": true
+                    }))?
+                ),
+                OutputFormat::Text => {
+                    println!(
+                        "imported and verified {} files",
+                        report.manifest.files.len()
+                    );
+                    println!(
+                        "  {} files extracted ({} bytes), {} kept from the interrupted import",
+                        report.imported_files, report.imported_bytes, report.resumed_files
+                    );
+                    if let Some(previous) = &report.previous_database {
+                        println!("  previous database moved to {}", previous.display());
+                    }
+                }
+            }
+        }
+        _ => unreachable!("only snapshots are handled here"),
+    }
+    Ok(())
+}
+
+async fn handle_backup_command(command: &BackupCommand, encrypt: bool) -> anyhow::Result<()> {
+    match command {
+        BackupCommand::Create {
+            dir,
+            include_media,
+            full,
+            data_dir,
+            output,
+        } => {
+            let db = open_database(data_dir, encrypt).await?;
+            let options = BackupOptions {
+                include_media: *include_media,
+                incremental: !full,
+                key: backup_key(encrypt)?,
+            };
+            let report = create_backup(&db, &get_base_dir(data_dir)?, dir, &options).await?;
+            match output {
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&json!({
+                        "data": report,
+                        "success": true
+                    }))?
+                ),
+                OutputFormat::Text => {
+                    println!("backed up to {}", report.path.display());
+                    println!(
+                        "  {} files copied ({} bytes), {} unchanged recordings linked",
+                        report.copied_files, report.copied_bytes, report.linked_files
+                    );
+                }
+            }
+        }
+        BackupCommand::List { dir, output } => {
+            let backups = list_backups(dir)?;
+            match output {
+                OutputFormat::Json => {
+                    let backups: Vec<Value> = backups
+                        .into_iter()
+                        .map(|(path, manifest)| json!({"path": path, "manifest": manifest}))
+                        .collect();
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&json!({
+                            "data": backups,
+                            "success": true
+                        }))?
+                    )
+                }
+                OutputFormat::Text => {
+                    println!("backups:");
+                    for (path, manifest) in backups {
+                        let bytes: u64 = manifest.files.iter().map(|file| file.size).sum();
+                        println!(
+                            "  {} created: {}, files: {}, bytes: {}, encrypted: {}",
+                            path.display(),
+                            manifest.created_at.to_rfc3339(),
+                            manifest.files.len(),
+                            bytes,
+                            manifest.encrypted
+                        );
+                    }
+                }
+            }
+        }
+        BackupCommand::Verify { backup } => {
+            let manifest = verify_backup(backup, backup_key(encrypt)?.as_deref()).await?;
+            println!(
+                "{} is intact, {} files checked",
+                backup.display(),
+                manifest.files.len()
+            );
+        }
+        BackupCommand::Restore { backup, data_dir } => {
+            let report = restore_backup(
+                backup,
+                &get_base_dir(data_dir)?,
+                backup_key(encrypt)?.as_deref(),
+            )
+            .await?;
+            println!("restored {}", backup.display());
+            if let Some(previous) = report.previous_database {
+                println!("  the replaced database was moved to {}", previous.display());
+            }
+            println!("  {} recordings put back", report.restored_media);
+        }
+    }
+    Ok(())
+}
+
+fn print_schema_migration(migration: &SchemaMigration) {
+    println!(
+        "  {} {} ({:?}{})",
+        migration.version,
+        migration.description,
+        migration.state,
+        if migration.reversible {
+            ", reversible"
+        } else {
+            ""
+        }
+    );
+}
+
+/// Prints the migrations of `report`, `done` telling what was done to them.
+fn print_migration_report(
+    report: &MigrationReport,
+    done: &str,
+    output: &OutputFormat,
+) -> anyhow::Result<()> {
+    match output {
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({
+                "data": report,
+                "success": true
+            }))?
+        ),
+        OutputFormat::Text => {
+            if report.migrations.is_empty() {
+                println!("no migrations {}", done);
+            } else if report.dry_run {
+                println!("migrations that would be {}:", done);
+            } else {
+                println!("migrations {}:", done);
+            }
+            for migration in &report.migrations {
+                print_schema_migration(migration);
+            }
+            if let Some(backup) = &report.backup {
+                println!("the database was backed up to {}", backup.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_db_command(command: &DbCommand, encrypt: bool) -> anyhow::Result<()> {
+    let db_path = |data_dir: &Option<String>| -> anyhow::Result<PathBuf> {
+        let path = get_base_dir(data_dir)?.join("db.sqlite");
+        if !path.exists() {
+            anyhow::bail!("no database at {}", path.display());
+        }
+        Ok(path)
+    };
+    match command {
+        DbCommand::Status { data_dir, output } => {
+            let status =
+                database_schema_status(&db_path(data_dir)?, backup_key(encrypt)?.as_deref())
+                    .await?;
+            match output {
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&json!({
+                        "data": status,
+                        "success": true
+                    }))?
+                ),
+                OutputFormat::Text => {
+                    println!("migrations:");
+                    for migration in &status {
+                        print_schema_migration(migration);
+                    }
+                }
+            }
+        }
+        DbCommand::Migrate {
+            dry_run,
+            data_dir,
+            output,
+        } => {
+            let report = migrate_database(
+                &db_path(data_dir)?,
+                backup_key(encrypt)?.as_deref(),
+                *dry_run,
+            )
+            .await?;
+            print_migration_report(&report, "applied", output)?;
+        }
+        DbCommand::Rollback {
+            version,
+            dry_run,
+            data_dir,
+            output,
+        } => {
+            let report = rollback_database(
+                &db_path(data_dir)?,
+                backup_key(encrypt)?.as_deref(),
+                *version,
+                *dry_run,
+            )
+            .await?;
+            print_migration_report(&report, "rolled back", output)?;
+        }
+    }
+    Ok(())
+}
+
+async fn handle_log_command(command: &LogCommand) -> anyhow::Result<()> {
+    let client = Client::new();
+    let url = |port: &u16| format!("http://localhost:{}/admin/log-levels", port);
+    let (request, token, output) = match command {
+        LogCommand::Set {
+            directives,
+            port,
+            token,
+        } => (
+            client
+                .post(url(port))
+                .json(&json!({ "directives": directives })),
+            token,
+            &OutputFormat::Text,
+        ),
+        LogCommand::Get {
+            port,
+            token,
+            output,
+        } => (client.get(url(port)), token, output),
+        LogCommand::Reset { port, token } => (client.delete(url(port)), token, &OutputFormat::Text),
+    };
+    let request = match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    };
+    let response = request
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to reach screenpipe, is it running? {}", e))?;
+    let status = response.status();
+    let body: Value = response.json().await?;
+    if !status.is_success() {
+        anyhow::bail!(
+            "{}",
+            body["error"]
+                .as_str()
+                .unwrap_or("failed to change log levels")
+        );
+    }
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&body)?),
+        OutputFormat::Text => {
+            println!("filter: {}", body["data"]["filter"].as_str().unwrap_or("-"));
+            let overrides = body["data"]["overrides"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+            if overrides.is_empty() {
+                println!("no levels set while running");
+            }
+            for directive in overrides {
+                println!("  {}", directive.as_str().unwrap_or_default());
+            }
+        }
+    }
+    Ok(())
+}
+
 async fn handle_pipe_command(
     command: &PipeCommand,
     pipe_manager: &Arc<PipeManager>,
@@ -1233,11 +2642,21 @@ async fn handle_pipe_command(
         }
 
         #[allow(deprecated)]
-        PipeCommand::Download { url, output, port }
-        | PipeCommand::Install { url, output, port } => {
+        PipeCommand::Download {
+            url,
+            output,
+            port,
+            allow_unsigned,
+        }
+        | PipeCommand::Install {
+            url,
+            output,
+            port,
+            allow_unsigned,
+        } => {
             match client
                 .post(format!("{}:{}/pipes/download", server_url, port))
-                .json(&json!({ "url": url }))
+                .json(&json!({ "url": url, "allow_unsigned": allow_unsigned }))
                 .send()
                 .await
             {
@@ -1251,7 +2670,7 @@ async fn handle_pipe_command(
                         ),
                     }
                 }
-                _ => match pipe_manager.download_pipe(url).await {
+                _ => match pipe_manager.download_pipe(url, *allow_unsigned).await {
                     Ok(pipe_id) => match output {
                         OutputFormat::Json => println!(
                             "{}",
@@ -1366,6 +2785,33 @@ async fn handle_pipe_command(
             }
         }
 
+        PipeCommand::Keygen { out } => {
+            let (seed, public_key) = generate_key()?;
+            std::fs::write(out, seed)?;
+            println!("secret key written to {}, keep it private", out);
+            println!("public key: {}", public_key);
+        }
+
+        PipeCommand::Sign { path, key } => {
+            let seed = std::fs::read_to_string(key)?;
+            let signature = sign_package(Path::new(path), &seed)?;
+            println!(
+                "signed {} files of {}, publisher key: {}",
+                signature.files.len(),
+                path,
+                signature.publisher_key
+            );
+        }
+
+        PipeCommand::Trust { key } => {
+            trust_publisher(pipe_manager.screenpipe_dir(), key)?;
+            println!("pipes signed by {} will install without --allow-unsigned", key.trim());
+        }
+
+        PipeCommand::Dev { path } => {
+            run_pipe_dev(pipe_manager.clone(), PathBuf::from(path), signal::ctrl_c()).await?;
+        }
+
         PipeCommand::Delete { id, yes, port } => {
             if !yes {
                 print!("are you sure you want to delete pipe '{}'? [y/N] ", id);
@@ -1604,6 +3050,8 @@ pub async fn handle_mcp_command(command: &McpCommand, local_data_dir: &PathBuf)
                 }
             }
         }
+        // runs in main, it needs the encryption flag
+        McpCommand::Serve { .. } => {}
     }
 
     Ok(())
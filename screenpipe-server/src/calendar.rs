@@ -0,0 +1,647 @@
+//! Calendar connector, enabled with `--caldav-url` or
+//! `--google-calendar-refresh-token`. The events of the last days and the
+//! next one are pulled every [`SYNC_INTERVAL`] into the database, where the
+//! audio, frames and UI text captured between the start and end of an
+//! event are in that meeting: search filters them with `meeting`, and
+//! `/calendar/events` lists the events with their attendees.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use oasgen::{oasgen, OaSchema};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, Method};
+use screenpipe_db::DatabaseManager;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{debug, error, info};
+
+use crate::server::{AppState, PaginationQuery};
+
+/// How often the calendars are pulled.
+pub const SYNC_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// Days before now whose events are pulled, for captures not yet matched.
+const SYNC_DAYS_BEFORE: i64 = 7;
+/// Days after now whose events are pulled.
+const SYNC_DAYS_AFTER: i64 = 1;
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_CALENDAR_URL: &str = "https://www.googleapis.com/calendar/v3/calendars";
+/// Title of events without one, as Google Calendar shows them.
+const UNTITLED: &str = "(no title)";
+
+/// A calendar events are pulled from.
+#[derive(Debug, Clone)]
+pub enum CalendarSource {
+    /// Calendar collection of a CalDAV server (Nextcloud, iCloud, Fastmail...)
+    CalDav {
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+    },
+    /// Google Calendar, through an OAuth client the user created
+    Google {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+        /// `primary` for the main calendar of the account
+        calendar_id: String,
+    },
+}
+
+impl CalendarSource {
+    /// Stored with the events, to tell them apart.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CalendarSource::CalDav { .. } => "caldav",
+            CalendarSource::Google { .. } => "google",
+        }
+    }
+}
+
+/// Event as read from a calendar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchedEvent {
+    pub uid: String,
+    pub title: String,
+    pub attendees: Vec<String>,
+    pub location: Option<String>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
+
+/// Pulls the calendars until a shutdown is signalled.
+pub async fn run_calendar_sync(
+    db: Arc<DatabaseManager>,
+    sources: Vec<CalendarSource>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let client = Client::new();
+    let mut interval = tokio::time::interval(SYNC_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                for source in &sources {
+                    match sync_calendar(&db, &client, source).await {
+                        Ok(count) => debug!("pulled {} events from {}", count, source.name()),
+                        Err(e) => error!("failed to pull {} calendar: {:#}", source.name(), e),
+                    }
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping calendar sync");
+                break;
+            }
+        }
+    }
+}
+
+/// Pulls the events of `source` around now, returning how many there are.
+pub async fn sync_calendar(
+    db: &DatabaseManager,
+    client: &Client,
+    source: &CalendarSource,
+) -> Result<usize> {
+    let now = Utc::now();
+    let start = now - chrono::Duration::days(SYNC_DAYS_BEFORE);
+    let end = now + chrono::Duration::days(SYNC_DAYS_AFTER);
+    let events = match source {
+        CalendarSource::CalDav {
+            url,
+            username,
+            password,
+        } => {
+            fetch_caldav(
+                client,
+                url,
+                username.as_deref(),
+                password.as_deref(),
+                start,
+                end,
+            )
+            .await?
+        }
+        CalendarSource::Google {
+            client_id,
+            client_secret,
+            refresh_token,
+            calendar_id,
+        } => {
+            let token = google_access_token(client, client_id, client_secret, refresh_token)
+                .await
+                .context("failed to refresh google access token")?;
+            fetch_google(client, &token, calendar_id, start, end).await?
+        }
+    };
+    store_events(db, source.name(), start, end, &events).await?;
+    Ok(events.len())
+}
+
+/// Saves the events of `source` starting in the time range, deleting the
+/// ones it no longer has.
+pub async fn store_events(
+    db: &DatabaseManager,
+    source: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    events: &[FetchedEvent],
+) -> Result<()> {
+    for event in events {
+        db.upsert_calendar_event(
+            source,
+            &event.uid,
+            &event.title,
+            &event.attendees,
+            event.location.as_deref(),
+            event.start_time,
+            event.end_time,
+        )
+        .await?;
+    }
+    let uids: Vec<String> = events.iter().map(|event| event.uid.clone()).collect();
+    let deleted = db
+        .delete_calendar_events_except(source, start, end, &uids)
+        .await?;
+    if deleted > 0 {
+        info!(
+            "removed {} events no longer in the {} calendar",
+            deleted, source
+        );
+    }
+    Ok(())
+}
+
+fn caldav_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+async fn fetch_caldav(
+    client: &Client,
+    url: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<FetchedEvent>> {
+    // recurring events are expanded by the server, to their instances in
+    // the range
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<C:calendar-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <D:prop>
+    <C:calendar-data>
+      <C:expand start="{start}" end="{end}"/>
+    </C:calendar-data>
+  </D:prop>
+  <C:filter>
+    <C:comp-filter name="VCALENDAR">
+      <C:comp-filter name="VEVENT">
+        <C:time-range start="{start}" end="{end}"/>
+      </C:comp-filter>
+    </C:comp-filter>
+  </C:filter>
+</C:calendar-query>"#,
+        start = caldav_time(start),
+        end = caldav_time(end),
+    );
+    let mut request = client
+        .request(Method::from_bytes(b"REPORT")?, url)
+        .header("Depth", "1")
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .body(body);
+    if let Some(username) = username {
+        request = request.basic_auth(username, password);
+    }
+    let response = request.send().await?.error_for_status()?;
+    let xml = response.text().await?;
+    Ok(calendar_data(&xml)
+        .iter()
+        .flat_map(|ics| parse_ics(ics))
+        .collect())
+}
+
+/// The iCalendar objects of a CalDAV multistatus response.
+pub fn calendar_data(xml: &str) -> Vec<String> {
+    let mut objects = Vec::new();
+    let mut rest = xml;
+    while let Some(open) = rest.find("calendar-data") {
+        // the element, whatever its namespace prefix
+        let Some(tag_start) = rest[..open].rfind('<') else {
+            break;
+        };
+        let Some(tag_end) = rest[open..].find('>').map(|i| open + i) else {
+            break;
+        };
+        let tag = &rest[tag_start..=tag_end];
+        rest = &rest[tag_end + 1..];
+        if tag.starts_with("</") || tag.ends_with("/>") {
+            continue;
+        }
+        let Some(close) = rest.find("</") else {
+            break;
+        };
+        let content = rest[..close].trim();
+        let content = content
+            .strip_prefix("<![CDATA[")
+            .and_then(|content| content.strip_suffix("]]>"))
+            .map(str::to_string)
+            .unwrap_or_else(|| unescape_xml(content));
+        if content.contains("BEGIN:VCALENDAR") {
+            objects.push(content);
+        }
+        rest = &rest[close..];
+    }
+    objects
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
+
+/// Events of an iCalendar object. All-day and cancelled events are left
+/// out, they aren't meetings.
+pub fn parse_ics(ics: &str) -> Vec<FetchedEvent> {
+    // lines starting with a space or tab continue the previous one
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continued), Some(last)) => last.push_str(continued),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut event: Option<IcsEvent> = None;
+    for line in &lines {
+        let Some((name, params, value)) = split_property(line) else {
+            continue;
+        };
+        match (name.as_str(), event.as_mut()) {
+            ("BEGIN", None) if value == "VEVENT" => event = Some(IcsEvent::default()),
+            ("END", Some(_)) if value == "VEVENT" => {
+                if let Some(parsed) = event.take().and_then(IcsEvent::finish) {
+                    events.push(parsed);
+                }
+            }
+            // alarms and other components inside the event
+            ("BEGIN", Some(current)) => current.nested += 1,
+            ("END", Some(current)) => current.nested = current.nested.saturating_sub(1),
+            (_, Some(current)) if current.nested == 0 => current.set(&name, &params, value),
+            _ => {}
+        }
+    }
+    events
+}
+
+#[derive(Default)]
+struct IcsEvent {
+    nested: usize,
+    uid: Option<String>,
+    recurrence_id: Option<String>,
+    title: Option<String>,
+    location: Option<String>,
+    start: Option<IcsTime>,
+    end: Option<IcsTime>,
+    duration: Option<chrono::Duration>,
+    attendees: Vec<String>,
+    cancelled: bool,
+}
+
+enum IcsTime {
+    DateTime(DateTime<Utc>),
+    Date,
+}
+
+impl IcsEvent {
+    fn set(&mut self, name: &str, params: &[(String, String)], value: &str) {
+        let param = |key: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v.as_str())
+        };
+        match name {
+            "UID" => self.uid = Some(value.to_string()),
+            "RECURRENCE-ID" => self.recurrence_id = Some(value.to_string()),
+            "SUMMARY" => self.title = Some(unescape_text(value)),
+            "LOCATION" => self.location = Some(unescape_text(value)).filter(|l| !l.is_empty()),
+            "DTSTART" => self.start = parse_ics_time(value, param("VALUE"), param("TZID")),
+            "DTEND" => self.end = parse_ics_time(value, param("VALUE"), param("TZID")),
+            "DURATION" => self.duration = parse_ics_duration(value),
+            "STATUS" => self.cancelled = value.eq_ignore_ascii_case("CANCELLED"),
+            "ATTENDEE" => {
+                let attendee = param("CN").map(str::to_string).unwrap_or_else(|| {
+                    let value = value.trim();
+                    value
+                        .strip_prefix("mailto:")
+                        .or_else(|| value.strip_prefix("MAILTO:"))
+                        .unwrap_or(value)
+                        .to_string()
+                });
+                if !attendee.is_empty() && !self.attendees.contains(&attendee) {
+                    self.attendees.push(attendee);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(self) -> Option<FetchedEvent> {
+        if self.cancelled {
+            return None;
+        }
+        let IcsTime::DateTime(start_time) = self.start? else {
+            return None;
+        };
+        let end_time = match (self.end, self.duration) {
+            (Some(IcsTime::DateTime(end)), _) => end,
+            (None, Some(duration)) => start_time + duration,
+            _ => return None,
+        };
+        // instances of a recurring event share its uid
+        let uid = match self.recurrence_id {
+            Some(recurrence_id) => format!("{}/{}", self.uid?, recurrence_id),
+            None => self.uid?,
+        };
+        Some(FetchedEvent {
+            uid,
+            title: self
+                .title
+                .filter(|title| !title.trim().is_empty())
+                .unwrap_or_else(|| UNTITLED.to_string()),
+            attendees: self.attendees,
+            location: self.location,
+            start_time,
+            end_time,
+        })
+    }
+}
+
+/// Name, parameters and value of a content line.
+type Property<'a> = (String, Vec<(String, String)>, &'a str);
+
+/// Splits a content line like `ATTENDEE;CN="Doe, Jane":mailto:jane@example.com`.
+fn split_property(line: &str) -> Option<Property<'_>> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(i),
+        _ => None,
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in head.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => parts.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    parts.push(current);
+
+    let mut parts = parts.into_iter();
+    let name = parts.next()?.to_ascii_uppercase();
+    let params = parts
+        .filter_map(|part| {
+            part.split_once('=')
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+        })
+        .collect();
+    Some((name, params, value))
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Times in UTC, or in a time zone or floating, which are read as local
+/// times as the zone database isn't shipped.
+fn parse_ics_time(value: &str, value_type: Option<&str>, tzid: Option<&str>) -> Option<IcsTime> {
+    let value = value.trim();
+    if value_type.is_some_and(|t| t.eq_ignore_ascii_case("DATE")) || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()
+            .map(|_| IcsTime::Date);
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(IcsTime::DateTime(Utc.from_utc_datetime(&time)));
+    }
+    let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    if tzid.is_some_and(|tz| matches!(tz, "UTC" | "Etc/UTC" | "GMT")) {
+        return Some(IcsTime::DateTime(Utc.from_utc_datetime(&time)));
+    }
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|time| IcsTime::DateTime(time.with_timezone(&Utc)))
+}
+
+/// Durations like `PT1H30M` or `P1D`.
+fn parse_ics_duration(value: &str) -> Option<chrono::Duration> {
+    let value = value.trim().strip_prefix('P')?;
+    let mut seconds = 0i64;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let n: i64 = std::mem::take(&mut number).parse().ok()?;
+                seconds += n * match unit {
+                    'W' => 7 * 24 * 3600,
+                    'D' => 24 * 3600,
+                    'H' => 3600,
+                    'M' => 60,
+                    'S' => 1,
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(chrono::Duration::seconds(seconds))
+}
+
+async fn google_access_token(
+    client: &Client,
+    client_id: &str,
+    client_secret: &str,
+    refresh_token: &str,
+) -> Result<String> {
+    let response: Value = client
+        .post(GOOGLE_TOKEN_URL)
+        .form(&[
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+            ("refresh_token", refresh_token),
+            ("grant_type", "refresh_token"),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    response["access_token"]
+        .as_str()
+        .map(str::to_string)
+        .context("no access token in the response")
+}
+
+async fn fetch_google(
+    client: &Client,
+    access_token: &str,
+    calendar_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<FetchedEvent>> {
+    let mut url = reqwest::Url::parse(GOOGLE_CALENDAR_URL)?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow::anyhow!("invalid calendar url"))?
+        .push(calendar_id)
+        .push("events");
+    let mut events = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut request = client.get(url.clone()).bearer_auth(access_token).query(&[
+            ("timeMin", start.to_rfc3339()),
+            ("timeMax", end.to_rfc3339()),
+            ("singleEvents", "true".to_string()),
+            ("maxResults", "2500".to_string()),
+        ]);
+        if let Some(page_token) = &page_token {
+            request = request.query(&[("pageToken", page_token)]);
+        }
+        let page: Value = request.send().await?.error_for_status()?.json().await?;
+        events.extend(parse_google_events(&page));
+        page_token = page["nextPageToken"].as_str().map(str::to_string);
+        if page_token.is_none() {
+            return Ok(events);
+        }
+    }
+}
+
+/// Events of a page of the Google Calendar events API. All-day and
+/// cancelled events are left out, they aren't meetings.
+pub fn parse_google_events(page: &Value) -> Vec<FetchedEvent> {
+    let time = |value: &Value| {
+        value["dateTime"]
+            .as_str()
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.with_timezone(&Utc))
+    };
+    page["items"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|item| item["status"] != "cancelled")
+        .filter_map(|item| {
+            let attendees = item["attendees"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                // meeting rooms
+                .filter(|attendee| attendee["resource"] != true)
+                .filter_map(|attendee| {
+                    attendee["displayName"]
+                        .as_str()
+                        .or_else(|| attendee["email"].as_str())
+                        .map(str::to_string)
+                })
+                .collect();
+            Some(FetchedEvent {
+                uid: item["id"].as_str()?.to_string(),
+                title: item["summary"]
+                    .as_str()
+                    .filter(|title| !title.trim().is_empty())
+                    .unwrap_or(UNTITLED)
+                    .to_string(),
+                attendees,
+                location: item["location"].as_str().map(str::to_string),
+                start_time: time(&item["start"])?,
+                end_time: time(&item["end"])?,
+            })
+        })
+        .collect()
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct CalendarEventsQuery {
+    #[serde(flatten)]
+    pagination: PaginationQuery,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    /// part of the title of the events
+    #[serde(default)]
+    title: Option<String>,
+}
+
+/// Meetings pulled from the configured calendars that overlap the range,
+/// oldest first.
+#[oasgen]
+pub(crate) async fn get_calendar_events_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CalendarEventsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state
+        .db
+        .list_calendar_events(
+            query.start_time,
+            query.end_time,
+            query.title.as_deref(),
+            query.pagination.limit,
+            query.pagination.offset,
+        )
+        .await
+    {
+        Ok(events) => Ok(JsonResponse(json!({
+            "data": events,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to read calendar events: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to read calendar events: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
@@ -3,12 +3,26 @@ use std::{path::PathBuf, sync::Arc};
 use clap::{Parser, Subcommand, ValueHint};
 use clap_complete::{generate, Shell};
 use clap::CommandFactory;
-use screenpipe_audio::{vad::{VadSensitivity, VadEngineEnum}, core::engine::AudioTranscriptionEngine as CoreAudioTranscriptionEngine};
-use screenpipe_vision::{custom_ocr::CustomOcrConfig, utils::OcrEngine as CoreOcrEngine};
+use screenpipe_audio::{vad::{VadSensitivity, VadEngineEnum}, core::engine::AudioTranscriptionEngine as CoreAudioTranscriptionEngine, transcription::provider::CloudSttProvider};
+use screenpipe_vision::{capture_screenshot_by_window::CaptureMode, custom_ocr::CustomOcrConfig, paddle::OcrDevice, utils::OcrEngine as CoreOcrEngine, CaptureRegion};
 use clap::ValueEnum;
 use screenpipe_core::Language;
+use screenpipe_core::ocr_mode::OcrMode;
+use screenpipe_core::pii_removal::PiiKind;
 use screenpipe_db::OcrEngine as DBOcrEngine;
 use screenpipe_db::CustomOcrConfig as DBCustomOcrConfig;
+
+use crate::auth::TokenScope;
+use crate::export::ExportFormat;
+use crate::import::ImportSource;
+use crate::mcp::McpTool;
+use crate::saved_search::DEFAULT_NOTIFICATION_URL;
+use screenpipe_core::answering::DEFAULT_QUERY_MODEL;
+use screenpipe_core::extraction::DEFAULT_EXTRACTION_MODEL;
+use screenpipe_core::llm_client::DEFAULT_OLLAMA_URL;
+use screenpipe_core::summarization::DEFAULT_SUMMARY_MODEL;
+use screenpipe_core::inference::{Quantization, DEFAULT_LOCAL_MODEL, LOCAL_MODELS};
+use crate::video_encoder::VideoEncoder;
 #[derive(Clone, Debug, ValueEnum, PartialEq)]
 pub enum CliAudioTranscriptionEngine {
     #[clap(name = "deepgram")]
@@ -54,6 +68,8 @@ pub enum CliOcrEngine {
     WindowsNative,
     #[cfg(target_os = "macos")]
     AppleNative,
+    /// PaddleOCR on ONNX Runtime, models are downloaded on first use
+    Paddle,
     Custom,
 }
 
@@ -67,6 +83,7 @@ impl From<CliOcrEngine> for Arc<DBOcrEngine> {
             CliOcrEngine::Tesseract => Arc::new(DBOcrEngine::Tesseract),
             #[cfg(target_os = "windows")]
             CliOcrEngine::WindowsNative => Arc::new(DBOcrEngine::WindowsNative),
+            CliOcrEngine::Paddle => Arc::new(DBOcrEngine::Paddle),
             CliOcrEngine::Custom => Arc::new(DBOcrEngine::Custom(DBCustomOcrConfig::default())),
         }
     }
@@ -82,6 +99,7 @@ impl From<CliOcrEngine> for CoreOcrEngine {
             CliOcrEngine::WindowsNative => CoreOcrEngine::WindowsNative,
             #[cfg(target_os = "macos")]
             CliOcrEngine::AppleNative => CoreOcrEngine::AppleNative,
+            CliOcrEngine::Paddle => CoreOcrEngine::Paddle,
             CliOcrEngine::Custom => {
                 // Try to read config from environment variable
                 if let Ok(config_str) = std::env::var("SCREENPIPE_CUSTOM_OCR_CONFIG") {
@@ -116,6 +134,265 @@ impl From<CliVadEngine> for VadEngineEnum {
     }
 }
 
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliOcrDevice {
+    /// The first available GPU, otherwise the CPU
+    #[clap(name = "auto")]
+    Auto,
+    #[clap(name = "cpu")]
+    Cpu,
+    /// NVIDIA GPUs, on Linux and Windows
+    #[clap(name = "cuda")]
+    Cuda,
+    /// Apple Neural Engine and GPU, on macOS
+    #[clap(name = "coreml")]
+    CoreMl,
+    /// Any DirectX 12 GPU, on Windows
+    #[clap(name = "directml")]
+    DirectMl,
+}
+
+impl From<CliOcrDevice> for OcrDevice {
+    fn from(cli_device: CliOcrDevice) -> Self {
+        match cli_device {
+            CliOcrDevice::Auto => OcrDevice::Auto,
+            CliOcrDevice::Cpu => OcrDevice::Cpu,
+            CliOcrDevice::Cuda => OcrDevice::Cuda,
+            CliOcrDevice::CoreMl => OcrDevice::CoreMl,
+            CliOcrDevice::DirectMl => OcrDevice::DirectMl,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliOcrMode {
+    /// OCR every window while capturing
+    #[clap(name = "realtime")]
+    Realtime,
+    /// Store frames right away and OCR them in the background while idle or plugged in
+    #[clap(name = "deferred")]
+    Deferred,
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliLlmQuantization {
+    #[clap(name = "q4_0")]
+    Q4_0,
+    /// A good tradeoff between size and quality
+    #[clap(name = "q4_k_m")]
+    Q4KM,
+    #[clap(name = "q5_k_m")]
+    Q5KM,
+    #[clap(name = "q6_k")]
+    Q6K,
+    /// Closest to the unquantized model, twice the size of q4
+    #[clap(name = "q8_0")]
+    Q8_0,
+}
+
+impl From<CliLlmQuantization> for Quantization {
+    fn from(cli_quantization: CliLlmQuantization) -> Self {
+        match cli_quantization {
+            CliLlmQuantization::Q4_0 => Quantization::Q4_0,
+            CliLlmQuantization::Q4KM => Quantization::Q4KM,
+            CliLlmQuantization::Q5KM => Quantization::Q5KM,
+            CliLlmQuantization::Q6K => Quantization::Q6K,
+            CliLlmQuantization::Q8_0 => Quantization::Q8_0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliLlmDevice {
+    /// The first GPU when built with `metal` or `cuda`, else the CPU
+    #[clap(name = "auto")]
+    Auto,
+    #[clap(name = "cpu")]
+    Cpu,
+    /// Offload the model to the GPU of --llm-gpu-index
+    #[clap(name = "gpu")]
+    Gpu,
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliLocalLlm {
+    /// A model served by Ollama
+    #[clap(name = "ollama")]
+    Ollama,
+    /// The model built into screenpipe, needs a build with the `llm` feature
+    #[clap(name = "builtin")]
+    Builtin,
+}
+
+impl From<CliOcrMode> for OcrMode {
+    fn from(cli_mode: CliOcrMode) -> Self {
+        match cli_mode {
+            CliOcrMode::Realtime => OcrMode::Realtime,
+            CliOcrMode::Deferred => OcrMode::Deferred,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliImportSource {
+    /// A Rewind database, decrypted or with --key
+    #[clap(name = "rewind")]
+    Rewind,
+    /// An ActivityWatch export of its buckets as JSON
+    #[clap(name = "activitywatch")]
+    ActivityWatch,
+}
+
+impl From<CliImportSource> for ImportSource {
+    fn from(cli_source: CliImportSource) -> Self {
+        match cli_source {
+            CliImportSource::Rewind => ImportSource::Rewind,
+            CliImportSource::ActivityWatch => ImportSource::ActivityWatch,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliMcpTool {
+    /// Search text that was on screen
+    #[clap(name = "search_screen_history")]
+    SearchScreenHistory,
+    /// Get audio transcripts
+    #[clap(name = "get_transcripts")]
+    GetTranscripts,
+    /// Get frames, transcripts, app switches and idle gaps of a time range
+    #[clap(name = "get_timeline")]
+    GetTimeline,
+}
+
+impl From<CliMcpTool> for McpTool {
+    fn from(cli_tool: CliMcpTool) -> Self {
+        match cli_tool {
+            CliMcpTool::SearchScreenHistory => McpTool::SearchScreenHistory,
+            CliMcpTool::GetTranscripts => McpTool::GetTranscripts,
+            CliMcpTool::GetTimeline => McpTool::GetTimeline,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliTokenScope {
+    /// Read-only access to search, timeline, frames, streams, GraphQL and MCP
+    #[clap(name = "search")]
+    Search,
+    /// Pipe management, includes search
+    #[clap(name = "pipes")]
+    Pipes,
+    /// Every endpoint
+    #[clap(name = "admin")]
+    Admin,
+}
+
+impl From<CliTokenScope> for TokenScope {
+    fn from(cli_scope: CliTokenScope) -> Self {
+        match cli_scope {
+            CliTokenScope::Search => TokenScope::Search,
+            CliTokenScope::Pipes => TokenScope::Pipes,
+            CliTokenScope::Admin => TokenScope::Admin,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliExportFormat {
+    /// SubRip subtitles of the transcripts
+    #[clap(name = "srt")]
+    Srt,
+    /// WebVTT subtitles of the transcripts, with speakers as voices
+    #[clap(name = "vtt")]
+    Vtt,
+    /// A note per day with app usage, screen activity and transcripts
+    #[clap(name = "markdown")]
+    Markdown,
+    /// A JSON object per screen text or transcript
+    #[clap(name = "jsonl")]
+    Jsonl,
+}
+
+impl From<CliExportFormat> for ExportFormat {
+    fn from(cli_format: CliExportFormat) -> Self {
+        match cli_format {
+            CliExportFormat::Srt => ExportFormat::Srt,
+            CliExportFormat::Vtt => ExportFormat::Vtt,
+            CliExportFormat::Markdown => ExportFormat::Markdown,
+            CliExportFormat::Jsonl => ExportFormat::Jsonl,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliCaptureMode {
+    /// Record whole monitors
+    #[clap(name = "monitor")]
+    Monitor,
+    /// Record only the windows matching the window filters
+    #[clap(name = "window")]
+    Window,
+}
+
+impl From<CliCaptureMode> for CaptureMode {
+    fn from(cli_mode: CliCaptureMode) -> Self {
+        match cli_mode {
+            CliCaptureMode::Monitor => CaptureMode::Monitor,
+            CliCaptureMode::Window => CaptureMode::Window,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliVideoEncoder {
+    /// Use the first hardware encoder that works, otherwise libx264
+    #[clap(name = "auto")]
+    Auto,
+    /// Apple VideoToolbox (macOS)
+    #[clap(name = "videotoolbox")]
+    VideoToolbox,
+    /// NVIDIA NVENC
+    #[clap(name = "nvenc")]
+    Nvenc,
+    /// Intel QuickSync
+    #[clap(name = "qsv")]
+    Qsv,
+    #[clap(name = "libx265")]
+    Libx265,
+    #[clap(name = "libx264")]
+    Libx264,
+}
+
+impl From<CliVideoEncoder> for VideoEncoder {
+    fn from(cli_encoder: CliVideoEncoder) -> Self {
+        match cli_encoder {
+            CliVideoEncoder::Auto => VideoEncoder::Auto,
+            CliVideoEncoder::VideoToolbox => VideoEncoder::VideoToolbox,
+            CliVideoEncoder::Nvenc => VideoEncoder::Nvenc,
+            CliVideoEncoder::Qsv => VideoEncoder::Qsv,
+            CliVideoEncoder::Libx265 => VideoEncoder::Libx265,
+            CliVideoEncoder::Libx264 => VideoEncoder::Libx264,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliSttFallback {
+    Deepgram,
+    #[clap(name = "openai")]
+    OpenAi,
+}
+
+impl From<CliSttFallback> for CloudSttProvider {
+    fn from(cli_fallback: CliSttFallback) -> Self {
+        match cli_fallback {
+            CliSttFallback::Deepgram => CloudSttProvider::Deepgram,
+            CliSttFallback::OpenAi => CloudSttProvider::OpenAi,
+        }
+    }
+}
+
 #[derive(Clone, Debug, ValueEnum, PartialEq)]
 pub enum CliVadSensitivity {
     Low,
@@ -133,6 +410,30 @@ impl From<CliVadSensitivity> for VadSensitivity {
     }
 }
 
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliPiiDetector {
+    Email,
+    CreditCard,
+    Ssn,
+    /// API keys and access tokens of common services
+    ApiKey,
+    /// Names of people, organizations and places, found with a local model
+    Ner,
+}
+
+impl CliPiiDetector {
+    /// Kind found with a regular expression, `None` for the model.
+    pub fn regex_kind(&self) -> Option<PiiKind> {
+        match self {
+            CliPiiDetector::Email => Some(PiiKind::Email),
+            CliPiiDetector::CreditCard => Some(PiiKind::CreditCard),
+            CliPiiDetector::Ssn => Some(PiiKind::Ssn),
+            CliPiiDetector::ApiKey => Some(PiiKind::ApiKey),
+            CliPiiDetector::Ner => None,
+        }
+    }
+}
+
 #[derive(Parser)]
 #[command(
     author, 
@@ -151,14 +452,36 @@ pub struct Cli {
     #[cfg_attr(target_os = "macos", arg(short, long, default_value_t = 0.5))] 
     pub fps: f64, // ! not crazy about this (inconsistent behaviour across platforms) see https://github.com/mediar-ai/screenpipe/issues/173
     
+    /// Lower the capture rate when the screen does not change and ramp back up on activity
+    #[arg(long, default_value_t = false)]
+    pub adaptive_fps: bool,
+
+    /// Lowest capture rate used by --adaptive-fps while the screen is idle
+    #[arg(long, default_value_t = 0.2)]
+    pub adaptive_fps_min: f64,
+
+    /// Video encoder for the recorded chunks. Hardware encoders that are not
+    /// available on this machine fall back to libx264
+    #[arg(long, value_enum, default_value_t = CliVideoEncoder::Auto)]
+    pub video_encoder: CliVideoEncoder,
+
     /// Audio chunk duration in seconds
     #[arg(short = 'd', long, default_value_t = 30)]
     pub audio_chunk_duration: u64,
 
+    /// Milliseconds of audio from the end of each chunk repeated at the start of the next,
+    /// so words cut at the boundary are transcribed whole. 0 disables the overlap
+    #[arg(long, default_value_t = 2000)]
+    pub audio_chunk_overlap_ms: u64,
+
     /// Port to run the server on
     #[arg(short = 'p', long, default_value_t = 3030)]
     pub port: u16,
 
+    /// Run without a display or audio devices, e.g. on a server or in a container: nothing is captured, media uploaded to /ingest is OCR'd and transcribed
+    #[arg(long, env = "SCREENPIPE_HEADLESS", default_value_t = false)]
+    pub headless: bool,
+
     /// Disable audio recording
     #[arg(long, default_value_t = false)]
     pub disable_audio: bool,
@@ -167,6 +490,26 @@ pub struct Cli {
     #[arg(short = 'i', long)]
     pub audio_device: Vec<String>,
 
+    /// Single channels of multi-channel audio interfaces to record and transcribe on their own,
+    /// as "<device>:<channel>", optionally with a label kept in the transcripts (can be specified
+    /// multiple times), example: --audio-channel "Scarlett 4i4 (input):1=host"
+    #[arg(long)]
+    pub audio_channel: Vec<String>,
+
+    /// Audio devices in order of preference, by name or part of it (can be specified multiple times).
+    /// The first connected device of each type is recorded instead of the others, switching when
+    /// devices are plugged in or removed, example:
+    /// --audio-device-priority "Jabra" --audio-device-priority "MacBook Pro Microphone"
+    #[arg(long)]
+    pub audio_device_priority: Vec<String>,
+
+    /// Only record the audio of these applications instead of the whole system output,
+    /// by process name (Windows only, can be specified multiple times), example:
+    /// --audio-app "Zoom.exe" --audio-app "ms-teams.exe"
+    /// Transcripts are tagged with the application through their device, e.g. "app:zoom.exe"
+    #[arg(long)]
+    pub audio_app: Vec<String>,
+
     // Audio devices to use for realtime audio transcription
     #[arg(short = 'r', long)]
     pub realtime_audio_device: Vec<String>,
@@ -179,6 +522,38 @@ pub struct Cli {
     #[arg(long)]
     pub debug: bool,
 
+    /// Format of the log lines, json writes one object per line with the fields of each event
+    #[arg(long, value_enum, env = "SCREENPIPE_LOG_FORMAT", default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Start a new log file when the current one reaches this size, in MB
+    #[arg(long, default_value_t = 20)]
+    pub log_max_file_size_mb: u64,
+
+    /// Number of log files to keep, the oldest are deleted
+    #[arg(long, default_value_t = 10)]
+    pub log_max_files: usize,
+
+    /// Don't write a minidump and the end of the log to <data-dir>/crashes when screenpipe
+    /// crashes. Reports are only sent from the app, when you agree to
+    #[arg(long, default_value_t = false)]
+    pub disable_crash_reports: bool,
+
+    /// Restart the screen capture or OCR of a monitor, or the recording of an audio device, after
+    /// this many seconds without progress. 0 never restarts them
+    #[arg(long, default_value_t = 120)]
+    pub watchdog_timeout_secs: u64,
+
+    /// On quit, how many seconds to wait for the frames and audio already captured to be written
+    /// before exiting without them
+    #[arg(long, default_value_t = 15)]
+    pub shutdown_timeout_secs: u64,
+
+    /// Export traces of the capture, OCR, transcription and database writes to this OTLP
+    /// collector over gRPC, e.g. http://localhost:4317. Needs a build with the `otel` feature
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+
     /// Audio transcription engine to use.
     /// Deepgram is a very high quality cloud-based transcription service (free of charge on us for now), recommended for high quality audio.
     /// WhisperTiny is a local, lightweight transcription model, recommended for high data privacy.
@@ -214,6 +589,14 @@ pub struct Cli {
     )]
     pub ocr_engine: CliOcrEngine,
 
+    /// When windows are OCR'd. Deferred OCR keeps the CPU free, e.g. during calls, and fills in the text later
+    #[arg(long, value_enum, default_value_t = CliOcrMode::Realtime)]
+    pub ocr_mode: CliOcrMode,
+
+    /// Hardware the paddle OCR models run on, falls back to the CPU when the device isn't available
+    #[arg(long, value_enum, default_value_t = CliOcrDevice::Auto)]
+    pub ocr_device: CliOcrDevice,
+
     /// Monitor IDs to use, these will be used to select the monitors to record
     #[arg(short = 'm', long)]
     pub monitor_id: Vec<u32>,
@@ -221,10 +604,23 @@ pub struct Cli {
     #[arg(short = 'l', long, value_enum)]
     pub language: Vec<Language>,
 
-    /// Enable PII removal from OCR text property that is saved to db and returned in search results
+    /// Enable PII removal from OCR text and transcripts before they are saved to db
     #[arg(long, default_value_t = false)]
     pub use_pii_removal: bool,
 
+    /// What PII removal looks for, repeat to use several detectors
+    #[arg(long, value_enum, default_values_t = [
+        CliPiiDetector::Email,
+        CliPiiDetector::CreditCard,
+        CliPiiDetector::Ssn,
+        CliPiiDetector::ApiKey,
+    ])]
+    pub pii_detector: Vec<CliPiiDetector>,
+
+    /// With PII removal, also blur the text it finds in the recorded video
+    #[arg(long, default_value_t = false)]
+    pub pii_blur_frames: bool,
+
     /// Disable vision recording
     #[arg(long, default_value_t = false)]
     pub disable_vision: bool,
@@ -236,15 +632,43 @@ pub struct Cli {
     /// List of windows to ignore (by title) for screen recording - we use contains to match, example:
     /// --ignored-windows "Spotify" --ignored-windows "Bit" will ignore both "Bitwarden" and "Bittorrent"
     /// --ignored-windows "x" will ignore "Home / X" and "SpaceX"
+    /// Prefix with "process:" to match the exact process name or "title:" to match the title with a regex:
+    /// --ignored-windows "process:1Password" --ignored-windows "title:^.*(bank|paypal).*$"
+    /// Ignored windows are never OCR'd and are blanked out of the recording.
     #[arg(long)]
     pub ignored_windows: Vec<String>,
 
     /// List of windows to include (by title) for screen recording - we use contains to match, example:
     /// --included-windows "Chrome" will include "Google Chrome"
     /// --included-windows "WhatsApp" will include "WhatsApp"
+    /// Supports the same "process:" and "title:" prefixes as --ignored-windows
     #[arg(long)]
     pub included_windows: Vec<String>,
 
+    /// Apps and sites that must never be recorded (can be specified multiple times). While the
+    /// focused window matches, screen frames are dropped before OCR and audio is not recorded.
+    /// Prefix with "app:" to match the exact app name or "url:" to match the browser URL with a regex,
+    /// anything else matches part of the app name or URL:
+    /// --privacy-blocklist "app:1Password" --privacy-blocklist "url:^https://([a-z]+\.)?mybank\.com"
+    #[arg(long)]
+    pub privacy_blocklist: Vec<String>,
+
+    /// Record private and incognito browser windows too. By default capture pauses like for
+    /// --privacy-blocklist while one is focused, and background ones are left out of OCR
+    #[arg(long, default_value_t = false)]
+    pub capture_private_browsing: bool,
+
+    /// What gets written to the video: whole monitors, or only the windows passing the window filters
+    #[arg(long, value_enum, default_value_t = CliCaptureMode::Monitor)]
+    pub capture_mode: CliCaptureMode,
+
+    /// Only capture, OCR and store rectangular regions of a monitor (can be specified multiple times).
+    /// Format: <monitor_id>:<x>,<y>,<width>,<height> in physical pixels, example:
+    /// --capture-region "1:0,0,1280,720" --capture-region "1:1280,0,640,360"
+    /// Monitors without a region are recorded in full.
+    #[arg(long)]
+    pub capture_region: Vec<CaptureRegion>,
+
     /// Video chunk duration in seconds
     #[arg(long, default_value_t = 60)]
     pub video_chunk_duration: u64,
@@ -253,6 +677,245 @@ pub struct Cli {
     #[arg(long = "deepgram-api-key")]
     pub deepgram_api_key: Option<String>,
 
+    /// Cloud transcription used when the local model fails or falls behind real time
+    #[arg(long, value_enum)]
+    pub stt_fallback: Option<CliSttFallback>,
+
+    /// OpenAI API key for the openai transcription fallback, defaults to OPENAI_API_KEY
+    #[arg(long = "openai-api-key")]
+    pub openai_api_key: Option<String>,
+
+    /// OpenAI transcription model
+    #[arg(long, default_value = "whisper-1")]
+    pub openai_stt_model: String,
+
+    /// Base url of the OpenAI compatible transcription api
+    #[arg(long, default_value = "https://api.openai.com/v1")]
+    pub openai_stt_url: String,
+
+    /// Never send audio to a cloud transcription provider
+    #[arg(long, default_value_t = false)]
+    pub disable_cloud_stt: bool,
+
+    /// Keep system audio picked up by microphones, by default it is muted in the
+    /// microphone recording so calls are not transcribed twice
+    #[arg(long, default_value_t = false)]
+    pub disable_echo_cancellation: bool,
+
+    /// Remove steady background noise (fans, keyboards) from all audio devices with
+    /// RNNoise before voice activity detection and transcription
+    #[arg(long, default_value_t = false)]
+    pub noise_suppression: bool,
+
+    /// Remove background noise only from these audio devices (can be specified multiple
+    /// times), example: --noise-suppression-device "MacBook Pro Microphone (input)"
+    #[arg(long)]
+    pub noise_suppression_device: Vec<String>,
+
+    /// Store an English translation of transcripts spoken in other languages, made offline
+    /// with the whisper model, so they are found by English searches
+    #[arg(long, default_value_t = false)]
+    pub translate_transcripts: bool,
+
+    /// Don't transcribe chunks that sound like music, they show up as media blocks in the
+    /// timeline instead. Experimental, speech can be taken for music: the audio is kept
+    /// and POST /audio/media-blocks/:id/transcribe transcribes it
+    #[arg(long, default_value_t = false)]
+    pub enable_media_detection: bool,
+
+    /// Embed new screen text and transcripts with a local model in the background, so
+    /// /search/semantic also finds them by meaning
+    #[arg(long, default_value_t = false)]
+    pub enable_semantic_search: bool,
+
+    /// Don't label recorded time blocks as meeting, coding, browsing or writing for
+    /// /activity/segments and /activity/summary
+    #[arg(long, default_value_t = false)]
+    pub disable_activity_classification: bool,
+
+    /// Don't detect meetings, from a meeting app in the foreground with people talking, for
+    /// /meetings
+    #[arg(long, default_value_t = false)]
+    pub disable_meeting_detection: bool,
+
+    /// Summarize each day at night, and each week on mondays, with a local LLM into topics,
+    /// action items and people mentioned, served at /summaries
+    #[arg(long, default_value_t = false)]
+    pub enable_summaries: bool,
+
+    /// LLM writing the summaries of --enable-summaries
+    #[arg(long, value_enum, default_value_t = CliLocalLlm::Ollama)]
+    pub summary_llm: CliLocalLlm,
+
+    /// Ollama model writing the summaries
+    #[arg(long, default_value = DEFAULT_SUMMARY_MODEL)]
+    pub summary_model: String,
+
+    /// Url of the Ollama server writing the summaries
+    #[arg(long, default_value = DEFAULT_OLLAMA_URL)]
+    pub summary_ollama_url: String,
+
+    /// Local hour after which the previous day is summarized
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(0..24))]
+    pub summary_hour: u32,
+
+    /// Run the extraction rules of /extractions/rules over each session of their app once it
+    /// ends, storing the records a local LLM extracts, served at /extractions
+    #[arg(long, default_value_t = false)]
+    pub enable_extractions: bool,
+
+    /// LLM extracting the records of --enable-extractions
+    #[arg(long, value_enum, default_value_t = CliLocalLlm::Ollama)]
+    pub extraction_llm: CliLocalLlm,
+
+    /// Ollama model extracting the records
+    #[arg(long, default_value = DEFAULT_EXTRACTION_MODEL)]
+    pub extraction_model: String,
+
+    /// Url of the Ollama server extracting the records
+    #[arg(long, default_value = DEFAULT_OLLAMA_URL)]
+    pub extraction_ollama_url: String,
+
+    /// Answer questions about the recordings at /query with a local LLM, from the text the
+    /// hybrid search of /search/semantic finds for them
+    #[arg(long, default_value_t = false)]
+    pub enable_query: bool,
+
+    /// LLM answering the questions of --enable-query
+    #[arg(long, value_enum, default_value_t = CliLocalLlm::Ollama)]
+    pub query_llm: CliLocalLlm,
+
+    /// Ollama model answering the questions
+    #[arg(long, default_value = DEFAULT_QUERY_MODEL)]
+    pub query_model: String,
+
+    /// Url of the Ollama server answering the questions
+    #[arg(long, default_value = DEFAULT_OLLAMA_URL)]
+    pub query_ollama_url: String,
+
+    /// Delete old recordings and text in the background according to the --retention-* days
+    #[arg(long, default_value_t = false)]
+    pub enable_retention: bool,
+
+    /// Days to keep screen recordings, 0 keeps them forever
+    #[arg(long, default_value_t = 7)]
+    pub retention_video_days: u32,
+
+    /// Days to keep audio recordings, 0 keeps them forever
+    #[arg(long, default_value_t = 3)]
+    pub retention_audio_days: u32,
+
+    /// Days to keep text read from the screen, 0 keeps it forever
+    #[arg(long, default_value_t = 90)]
+    pub retention_ocr_days: u32,
+
+    /// Days to keep audio transcripts, 0 keeps them forever
+    #[arg(long, default_value_t = 0)]
+    pub retention_transcript_days: u32,
+
+    /// Move each month of recordings older than this many months out of the main database
+    /// into its own file under partitions/, 0 keeps everything in the main database
+    #[arg(long, default_value_t = 0)]
+    pub partition_after_months: u32,
+
+    /// Cap on the size of the screenpipe data directory in GB. The oldest recordings are
+    /// evicted before it is exceeded
+    #[arg(long)]
+    pub max_storage_gb: Option<f64>,
+
+    /// Also delete the text read from recordings evicted by --max-storage-gb, by default it
+    /// stays searchable
+    #[arg(long, default_value_t = false)]
+    pub storage_evict_text: bool,
+
+    /// S3 compatible bucket to move old recordings to, they are fetched back when played.
+    /// Text stays in the local database
+    #[arg(long)]
+    pub offload_bucket: Option<String>,
+
+    /// Endpoint of a non-AWS object storage, e.g. http://localhost:9000 for MinIO or
+    /// https://s3.us-west-004.backblazeb2.com for B2
+    #[arg(long, requires = "offload_bucket")]
+    pub offload_endpoint: Option<String>,
+
+    /// Region of --offload-bucket
+    #[arg(long, default_value = "us-east-1")]
+    pub offload_region: String,
+
+    /// Prefix of the keys of offloaded recordings in --offload-bucket
+    #[arg(long, default_value = "screenpipe/")]
+    pub offload_prefix: String,
+
+    /// Access key of --offload-bucket, read from the AWS environment variables or config
+    /// files if not set
+    #[arg(long, env = "SCREENPIPE_OFFLOAD_ACCESS_KEY", requires = "offload_secret_key")]
+    pub offload_access_key: Option<String>,
+
+    /// Secret key of --offload-bucket
+    #[arg(
+        long,
+        env = "SCREENPIPE_OFFLOAD_SECRET_KEY",
+        hide_env_values = true,
+        requires = "offload_access_key"
+    )]
+    pub offload_secret_key: Option<String>,
+
+    /// Days recordings stay on disk before being moved to --offload-bucket
+    #[arg(long, default_value_t = 30)]
+    pub offload_after_days: u32,
+
+    /// Back up the database to a new directory of this one every --backup-interval-hours
+    #[arg(long, value_hint = ValueHint::DirPath)]
+    pub backup_dir: Option<PathBuf>,
+
+    /// Hours between two backups to --backup-dir
+    #[arg(long, default_value_t = 24)]
+    pub backup_interval_hours: u64,
+
+    /// Also back up the recordings to --backup-dir, only the new ones are copied
+    #[arg(long, default_value_t = false)]
+    pub backup_include_media: bool,
+
+    /// Number of backups kept in --backup-dir, older ones are deleted
+    #[arg(long, default_value_t = 7)]
+    pub backup_keep: usize,
+
+    /// Write a Markdown note per day of the apps used, what was on screen and what was said
+    /// into this Obsidian vault every --obsidian-interval-minutes
+    #[arg(long, value_hint = ValueHint::DirPath)]
+    pub obsidian_vault: Option<PathBuf>,
+
+    /// Folder of --obsidian-vault the daily notes are written to
+    #[arg(long, default_value = "screenpipe")]
+    pub obsidian_folder: String,
+
+    /// Template file of the daily notes, with {{date}}, {{yesterday}}, {{tomorrow}}, {{apps}},
+    /// {{screen}} and {{transcripts}} placeholders
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub obsidian_template: Option<PathBuf>,
+
+    /// Minutes between two updates of the daily notes in --obsidian-vault
+    #[arg(long, default_value_t = 60)]
+    pub obsidian_interval_minutes: u64,
+
+    /// Seconds between two checks of the saved searches of /search/saved against new recordings
+    #[arg(long, default_value_t = 60)]
+    pub saved_search_interval_secs: u64,
+
+    /// Seconds the totals of /search, /activity/summary and /analytics/usage are cached for, they're dropped sooner
+    /// when something is recorded in their time range. 0 disables the cache
+    #[arg(long, default_value_t = 60)]
+    pub query_cache_ttl_secs: u64,
+
+    /// Url of the desktop app's notifications, where matches of saved searches are reported
+    #[arg(long, default_value = DEFAULT_NOTIFICATION_URL)]
+    pub notification_url: String,
+
+    /// Encrypt the database with SQLCipher and finished recordings with AES-GCM, using a key
+    /// kept in the OS keychain. The database needs a build with the `sqlcipher` feature
+    #[arg(long, default_value_t = false)]
+    pub encrypt_data: bool,
+
     /// PID to watch for auto-destruction. If provided, screenpipe will stop when this PID is no longer running.
     #[arg(long)]
     pub auto_destruct_pid: Option<u32>,
@@ -261,15 +924,44 @@ pub struct Cli {
     #[arg(long, value_enum, default_value_t = CliVadSensitivity::High)]
     pub vad_sensitivity: CliVadSensitivity,
 
+    /// Ignore speech shorter than this many milliseconds, e.g. clicks and coughs
+    #[arg(long, default_value_t = 0)]
+    pub vad_min_speech_duration_ms: u64,
+
+    /// Keep treating audio as speech for this many milliseconds after the VAD stops detecting it
+    #[arg(long, default_value_t = 0)]
+    pub vad_hangover_ms: u64,
+
     /// Disable telemetry
     #[arg(long, default_value_t = false)]
     pub disable_telemetry: bool,
 
-    /// Enable Local LLM API
+    /// Serve a local LLM to pipes at /v1/chat/completions, compatible with OpenAI's api. The
+    /// model is downloaded on the first start
     #[arg(long, default_value_t = false)]
     pub enable_llm: bool,
 
-    /// Enable UI monitoring (macOS only)
+    /// Model of --enable-llm
+    #[arg(
+        long,
+        default_value = DEFAULT_LOCAL_MODEL,
+        value_parser = clap::builder::PossibleValuesParser::new(LOCAL_MODELS.iter().map(|model| model.id))
+    )]
+    pub llm_model: String,
+
+    /// Quantization of the --llm-model weights, smaller ones are faster and less accurate
+    #[arg(long, value_enum, default_value_t = CliLlmQuantization::Q4KM)]
+    pub llm_quantization: CliLlmQuantization,
+
+    /// Where the local LLM runs
+    #[arg(long, value_enum, default_value_t = CliLlmDevice::Auto)]
+    pub llm_device: CliLlmDevice,
+
+    /// Index of the GPU of --llm-device gpu
+    #[arg(long, default_value_t = 0)]
+    pub llm_gpu_index: usize,
+
+    /// Enable UI monitoring (macOS only), also stores the accessibility tree of the focused window with each frame and the windows focused over time, see /focus
     #[arg(long, default_value_t = false)]
     pub enable_ui_monitoring: bool,
     
@@ -281,6 +973,126 @@ pub struct Cli {
     #[arg(long, default_value_t = false)]
     pub capture_unfocused_windows: bool,
 
+    /// Only use OCR for screen text, don't read it through UI Automation (Windows only)
+    #[arg(long, default_value_t = false)]
+    pub disable_accessibility_text: bool,
+
+    /// Record keyboard and mouse activity: keystroke, click and scroll counts and time spent per window
+    #[arg(long, default_value_t = false)]
+    pub enable_input_capture: bool,
+
+    /// Also record the text typed, requires --enable-input-capture
+    #[arg(long, default_value_t = false, requires = "enable_input_capture")]
+    pub capture_typed_text: bool,
+
+    /// Record the text copied to the clipboard, with the app it was copied from
+    #[arg(long, default_value_t = false)]
+    pub enable_clipboard_capture: bool,
+
+    /// Suspend capture while the screen is locked or after this many minutes without input, 0 never suspends it
+    #[arg(long, default_value_t = 5)]
+    pub idle_timeout_minutes: u64,
+
+    /// On battery at or below this charge in percent, capture less often, OCR once plugged in and transcribe with a smaller model. 0 never does, 100 always does on battery
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub battery_low_power_percent: u8,
+
+    /// Pause audio recording on battery at or below this charge in percent, 0 never pauses it
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub battery_pause_audio_percent: u8,
+
+    /// Serve MCP clients over SSE at /mcp/sse
+    #[arg(long, default_value_t = false)]
+    pub enable_mcp: bool,
+
+    /// Tools MCP clients may use, all of them if not set. Can be repeated
+    #[arg(long, value_enum)]
+    pub mcp_tool: Vec<CliMcpTool>,
+
+    /// Require an API token on every endpoint except /health. Create tokens
+    /// with `screenpipe token create`
+    #[arg(long, default_value_t = false)]
+    pub api_auth: bool,
+
+    /// Serve other machines on the LAN over TLS at --remote-port, with API
+    /// tokens required, and advertise it over mDNS. The plain HTTP port then
+    /// only listens on localhost
+    #[arg(long, default_value_t = false)]
+    pub remote: bool,
+
+    /// Port of the remote access server
+    #[arg(long, default_value_t = 3031)]
+    pub remote_port: u16,
+
+    /// TLS certificate (PEM) for remote access, a self-signed one is
+    /// generated in the data directory if not set
+    #[arg(long, value_hint = ValueHint::FilePath, requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// Private key (PEM) of --tls-cert
+    #[arg(long, value_hint = ValueHint::FilePath, requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Key encrypting synced text, shared by all your devices. Create one
+    /// with `screenpipe sync key`. Setting it serves this device's text at
+    /// /sync/changes
+    #[arg(long, env = "SCREENPIPE_SYNC_KEY", hide_env_values = true)]
+    pub sync_key: Option<String>,
+
+    /// Another device to pull OCR text, transcripts and app context from,
+    /// e.g. https://laptop.local:3031. Can be repeated
+    #[arg(long, requires = "sync_key")]
+    pub sync_peer: Vec<String>,
+
+    /// How often to pull from the sync peers, in seconds
+    #[arg(long, default_value_t = 60)]
+    pub sync_interval: u64,
+
+    /// CalDAV calendar to pull meetings from, e.g.
+    /// https://cloud.example.com/remote.php/dav/calendars/me/personal/. Captures during an
+    /// event can be searched with its title
+    #[arg(long)]
+    pub caldav_url: Option<String>,
+
+    /// Username of --caldav-url
+    #[arg(long, env = "SCREENPIPE_CALDAV_USERNAME", requires = "caldav_url")]
+    pub caldav_username: Option<String>,
+
+    /// Password of --caldav-url, an app password for iCloud or Fastmail
+    #[arg(
+        long,
+        env = "SCREENPIPE_CALDAV_PASSWORD",
+        hide_env_values = true,
+        requires = "caldav_username"
+    )]
+    pub caldav_password: Option<String>,
+
+    /// OAuth refresh token of a Google account to pull meetings from its calendar, issued to
+    /// --google-calendar-client-id with the calendar.readonly scope
+    #[arg(
+        long,
+        env = "SCREENPIPE_GOOGLE_CALENDAR_REFRESH_TOKEN",
+        hide_env_values = true,
+        requires = "google_calendar_client_id"
+    )]
+    pub google_calendar_refresh_token: Option<String>,
+
+    /// OAuth client of --google-calendar-refresh-token
+    #[arg(
+        long,
+        env = "SCREENPIPE_GOOGLE_CALENDAR_CLIENT_ID",
+        requires = "google_calendar_client_secret"
+    )]
+    pub google_calendar_client_id: Option<String>,
+
+    /// Secret of --google-calendar-client-id
+    #[arg(long, env = "SCREENPIPE_GOOGLE_CALENDAR_CLIENT_SECRET", hide_env_values = true)]
+    pub google_calendar_client_secret: Option<String>,
+
+    /// Calendar of the Google account to pull, `primary` for its main one
+    #[arg(long, default_value = "primary")]
+    pub google_calendar_id: String,
+
     /// Enable pipe functionality (default: false)
     #[arg(long, default_value_t = false)]
     pub enable_pipe_manager: bool,
@@ -331,6 +1143,107 @@ pub enum Command {
         #[command(subcommand)]
         subcommand: McpCommand,
     },
+    /// API token management commands
+    Token {
+        #[command(subcommand)]
+        subcommand: TokenCommand,
+    },
+    /// Remote access commands
+    Remote {
+        #[command(subcommand)]
+        subcommand: RemoteCommand,
+    },
+    /// Multi-device sync commands
+    Sync {
+        #[command(subcommand)]
+        subcommand: SyncCommand,
+    },
+    /// Export a time range as subtitles, Markdown daily notes or JSONL
+    Export {
+        /// Format to export to
+        #[arg(value_enum)]
+        format: CliExportFormat,
+        /// Start of the range, RFC 3339 or a local date like 2025-04-14
+        #[arg(long)]
+        start: String,
+        /// End of the range, RFC 3339 or a local date, included. Default to now
+        #[arg(long)]
+        end: Option<String>,
+        /// Only screen text of apps whose name contains this
+        #[arg(long)]
+        app: Option<String>,
+        /// Only records with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only transcripts of the speaker with this name
+        #[arg(long)]
+        speaker: Option<String>,
+        /// File to write to, or for markdown a directory to write a note per day to.
+        /// Default to stdout
+        #[arg(long, value_hint = ValueHint::AnyPath)]
+        out: Option<PathBuf>,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+    },
+    /// Backup commands
+    Backup {
+        #[command(subcommand)]
+        subcommand: BackupCommand,
+    },
+    /// Check that the database and the recordings agree with each other
+    Doctor {
+        /// Repair what's found: delete rows whose parent is gone and recordings no chunk
+        /// refers to, rebuild the search index, mark chunks whose file is gone. Stop
+        /// screenpipe first
+        #[arg(long, default_value_t = false)]
+        repair: bool,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Import history recorded by another tool: Rewind's frames and transcripts, or
+    /// ActivityWatch's window events. Their media isn't imported
+    Import {
+        /// Tool the history was recorded by
+        #[arg(value_enum)]
+        source: CliImportSource,
+        /// Rewind database, or ActivityWatch export
+        #[arg(value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+        /// Key of an encrypted Rewind database, needs a build with the sqlcipher feature
+        #[arg(long)]
+        key: Option<String>,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Database schema commands
+    Db {
+        #[command(subcommand)]
+        subcommand: DbCommand,
+    },
+    /// Writes the crash reports of a recorder, started by it
+    #[command(hide = true)]
+    CrashMonitor {
+        /// Socket the recorder sends its crashes to
+        #[arg(long)]
+        socket: PathBuf,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+    },
+    /// Change the log levels of the running screenpipe, without restarting it
+    Log {
+        #[command(subcommand)]
+        subcommand: LogCommand,
+    },
     /// Add video files to existing screenpipe data (OCR only) - DOES NOT SUPPORT AUDIO
     Add {
         /// Path to folder containing video files
@@ -402,6 +1315,26 @@ pub enum MigrationSubCommand {
     Stop,
     /// Get migration status
     Status,
+    /// Write the database and the recordings to a single archive, to move them to
+    /// another machine. Run again to pick up an interrupted export
+    Export {
+        /// Archive to write
+        #[arg(value_hint = ValueHint::FilePath)]
+        archive: PathBuf,
+        /// Only the recordings from then on, RFC 3339 or a local date like 2025-04-14
+        #[arg(long)]
+        start: Option<String>,
+        /// Only the recordings until then, RFC 3339 or a local date
+        #[arg(long)]
+        end: Option<String>,
+    },
+    /// Import an archive made with `migrate export`, replacing the database. Run again to
+    /// pick up an interrupted import. Stop screenpipe first
+    Import {
+        /// Archive to import
+        #[arg(value_hint = ValueHint::FilePath)]
+        archive: PathBuf,
+    },
 }
 
 #[derive(Subcommand)]
@@ -446,6 +1379,9 @@ pub enum PipeCommand {
         /// Server port
         #[arg(short = 'p', long, default_value_t = 3030)]
         port: u16,
+        /// Install the pipe even if it isn't signed, is signed by a publisher you don't trust, or by another publisher than the installed version
+        #[arg(long)]
+        allow_unsigned: bool,
     },
     /// Install a new pipe
     Install {
@@ -457,6 +1393,9 @@ pub enum PipeCommand {
         /// Server port
         #[arg(short = 'p', long, default_value_t = 3030)]
         port: u16,
+        /// Install the pipe even if it isn't signed, is signed by a publisher you don't trust, or by another publisher than the installed version
+        #[arg(long)]
+        allow_unsigned: bool,
     },
     /// Get info for a specific pipe
     Info {
@@ -504,6 +1443,32 @@ pub enum PipeCommand {
         #[arg(short = 'p', long, default_value_t = 3030)]
         port: u16,
     },
+    /// Generate a key to sign pipes with
+    Keygen {
+        /// File to write the secret key to
+        #[arg(value_hint = ValueHint::FilePath)]
+        out: String,
+    },
+    /// Sign a pipe, writing its pipe.sig
+    Sign {
+        /// Folder of the pipe
+        #[arg(value_hint = ValueHint::DirPath)]
+        path: String,
+        /// File with the secret key, from `pipe keygen`
+        #[arg(short, long, value_hint = ValueHint::FilePath)]
+        key: String,
+    },
+    /// Trust a publisher, so that the pipes it signs install without --allow-unsigned
+    Trust {
+        /// Public key of the publisher, in hex
+        key: String,
+    },
+    /// Run a pipe from its folder, reloading it when its files change
+    Dev {
+        /// Folder of the pipe
+        #[arg(value_hint = ValueHint::DirPath)]
+        path: String,
+    },
     /// Delete a pipe
     Delete {
         /// ID of the pipe to delete
@@ -537,6 +1502,195 @@ pub enum McpCommand {
         #[arg(long)]
         purge: bool,
     },
+    /// Run an MCP server on stdin and stdout, for clients like Claude Desktop
+    Serve {
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Tools the client may use, all of them if not set. Can be repeated
+        #[arg(long, value_enum)]
+        tool: Vec<CliMcpTool>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TokenCommand {
+    /// Create a token for the local API, it's only shown once
+    Create {
+        /// What the token gives access to
+        #[arg(long, value_enum)]
+        scope: CliTokenScope,
+        /// Name to recognize the token by
+        #[arg(long)]
+        name: Option<String>,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// List tokens
+    List {
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Revoke a token
+    Revoke {
+        /// ID of the token, see `screenpipe token list`
+        id: i64,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RemoteCommand {
+    /// List screenpipe instances on the LAN that allow remote access
+    Discover {
+        /// How long to listen for instances, in seconds
+        #[arg(long, default_value_t = 3)]
+        timeout: u64,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SyncCommand {
+    /// Create a sync key, pass it with --sync-key on every device
+    Key,
+}
+
+#[derive(Subcommand)]
+pub enum BackupCommand {
+    /// Back up the database, and optionally the recordings, to a new directory of DIR
+    Create {
+        /// Directory the backups are kept in
+        #[arg(value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+        /// Also back up the recordings
+        #[arg(long, default_value_t = false)]
+        include_media: bool,
+        /// Copy every recording instead of linking the ones unchanged since the latest backup
+        #[arg(long, default_value_t = false)]
+        full: bool,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// List the backups of DIR
+    List {
+        /// Directory the backups are kept in
+        #[arg(value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Check the checksums and the database of a backup
+    Verify {
+        /// Directory of the backup
+        #[arg(value_hint = ValueHint::DirPath)]
+        backup: PathBuf,
+    },
+    /// Replace the database with a backup and put back missing recordings. Stop screenpipe
+    /// first
+    Restore {
+        /// Directory of the backup
+        #[arg(value_hint = ValueHint::DirPath)]
+        backup: PathBuf,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// List the schema migrations and whether they are applied
+    Status {
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Apply the pending schema migrations, after backing up the database. Stop screenpipe
+    /// first
+    Migrate {
+        /// Only list the migrations that would be applied
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Roll the schema back to a migration with the down migrations of the later ones,
+    /// after backing up the database. Stop screenpipe first
+    Rollback {
+        /// Version of the migration to roll back to, it stays applied
+        version: i64,
+        /// Only list the migrations that would be rolled back
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum LogCommand {
+    /// Set log levels per module, e.g. `screenpipe_audio=debug,screenpipe_vision=warn`.
+    /// A level without a module changes the default one
+    Set {
+        /// Comma separated directives, in the format of RUST_LOG
+        directives: String,
+        /// Server port
+        #[arg(short = 'p', long, default_value_t = 3030)]
+        port: u16,
+        /// API token, when screenpipe runs with --api-auth
+        #[arg(long, env = "SCREENPIPE_API_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+    /// Show the log filter and the levels set while running
+    Get {
+        /// Server port
+        #[arg(short = 'p', long, default_value_t = 3030)]
+        port: u16,
+        /// API token, when screenpipe runs with --api-auth
+        #[arg(long, env = "SCREENPIPE_API_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Go back to the log levels screenpipe started with
+    Reset {
+        /// Server port
+        #[arg(short = 'p', long, default_value_t = 3030)]
+        port: u16,
+        /// API token, when screenpipe runs with --api-auth
+        #[arg(long, env = "SCREENPIPE_API_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
 }
 
 #[derive(Clone, Debug, ValueEnum, PartialEq)]
@@ -544,3 +1698,9 @@ pub enum OutputFormat {
     Text,
     Json,
 }
+
+#[derive(Clone, Copy, Debug, ValueEnum, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
@@ -0,0 +1,112 @@
+//! Opt-in history of the text copied to the clipboard, attributed to the app
+//! and window that had the focus when it was copied. Nothing is stored while
+//! the privacy blocklist pauses capture or when the focused app is blocked.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use screenpipe_core::privacy::is_capture_blocked;
+use screenpipe_db::DatabaseManager;
+use screenpipe_vision::capture_screenshot_by_window::focused_window;
+use screenpipe_vision::PrivacyBlocklist;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error, info, warn};
+
+/// How often the clipboard is checked for new text.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Longest text stored for one copy, longer copies are cut.
+const MAX_TEXT_CHARS: usize = 10_000;
+
+/// Text to store for a copy, `None` if there is nothing worth storing.
+pub fn clipboard_text(text: &str) -> Option<String> {
+    if text.trim().is_empty() {
+        return None;
+    }
+    Some(text.chars().take(MAX_TEXT_CHARS).collect())
+}
+
+/// Records copied text until a shutdown is signalled.
+pub async fn run_clipboard_capture(
+    db: Arc<DatabaseManager>,
+    privacy_blocklist: Arc<PrivacyBlocklist>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let (text_tx, mut text_rx) = mpsc::unbounded_channel();
+
+    // the clipboard handle is tied to the thread that opened it
+    std::thread::spawn(move || {
+        let mut clipboard = match arboard::Clipboard::new() {
+            Ok(clipboard) => clipboard,
+            Err(e) => {
+                error!("failed to open the clipboard: {}", e);
+                return;
+            }
+        };
+        // text copied before startup isn't attributed to whatever is focused now
+        let mut last = clipboard.get_text().ok();
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let Ok(text) = clipboard.get_text() else {
+                continue;
+            };
+            if last.as_ref() == Some(&text) {
+                continue;
+            }
+            last = Some(text.clone());
+            if text_tx.send(text).is_err() {
+                break;
+            }
+        }
+    });
+
+    info!("recording clipboard history");
+
+    loop {
+        tokio::select! {
+            text = text_rx.recv() => {
+                let Some(text) = text else {
+                    warn!("clipboard monitor stopped, no more copied text is recorded");
+                    break;
+                };
+                store_copy(&db, &privacy_blocklist, &text).await;
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping clipboard capture");
+                break;
+            }
+        }
+    }
+}
+
+async fn store_copy(db: &DatabaseManager, privacy_blocklist: &PrivacyBlocklist, text: &str) {
+    let Some(text) = clipboard_text(text) else {
+        return;
+    };
+    if is_capture_blocked() {
+        debug!("capture is paused, clipboard change not recorded");
+        return;
+    }
+
+    let focused = tokio::task::spawn_blocking(focused_window)
+        .await
+        .ok()
+        .flatten();
+    let (app_name, window_name) = focused
+        .map(|f| (f.app_name, f.window_name))
+        .unwrap_or_default();
+    if privacy_blocklist
+        .matching_pattern(&app_name, None)
+        .is_some()
+    {
+        debug!("{} is blocked, clipboard change not recorded", app_name);
+        return;
+    }
+
+    if let Err(e) = db
+        .insert_clipboard_entry(Utc::now(), &app_name, &window_name, &text)
+        .await
+    {
+        error!("failed to store clipboard entry: {}", e);
+    }
+}
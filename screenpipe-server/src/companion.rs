@@ -0,0 +1,667 @@
+//! Protocol the mobile companion apps push screenshots, audio and their
+//! metadata with. A device is paired with `POST /companion/devices`, which
+//! returns the token it then sends as `Authorization: Bearer <token>`, and
+//! what it uploads is attributed to it. Files are uploaded in chunks so that
+//! they survive flaky connections:
+//!
+//! 1. `POST /companion/uploads` with the kind, size and SHA-256 of a file
+//!    starts an upload. The upload the device started earlier for the same
+//!    file is returned instead, to resume it, and a file already received
+//!    from any device is reported as a duplicate.
+//! 2. `PUT /companion/uploads/:id` appends a chunk at the offset in the
+//!    `Upload-Offset` header. Chunks at another offset are refused with the
+//!    one to resume from, which `GET /companion/uploads/:id` also returns.
+//! 3. Once the whole file is received and its SHA-256 checked, it's queued
+//!    for [`crate::ingest`] under the name of the device.
+//!
+//! New uploads are refused with a `Retry-After` while too many wait to be
+//! processed.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::{
+        header::{AUTHORIZATION, RETRY_AFTER},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Json as JsonResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use oasgen::{oasgen, OaSchema};
+use once_cell::sync::Lazy;
+use screenpipe_db::{CompanionDevice, CompanionUpload, DatabaseManager};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::auth::{generate_token, hash_token};
+use crate::ingest::{pending_count, queue_file, IngestKind};
+use crate::server::AppState;
+
+/// Header with the offset a chunk starts at.
+pub const OFFSET_HEADER: &str = "upload-offset";
+/// Largest chunk accepted, clients split files in chunks up to this size.
+pub const MAX_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+/// Largest file accepted.
+pub const MAX_UPLOAD_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+/// Uploads waiting to be processed above which new ones are refused.
+pub const MAX_PENDING: usize = 32;
+/// When devices are told to try again while too many uploads are pending.
+pub const RETRY_AFTER_DELAY: Duration = Duration::from_secs(60);
+/// Uploads not completed after this long are deleted.
+const UPLOAD_EXPIRY_DAYS: i64 = 7;
+
+/// Uploads a chunk is being written to.
+static WRITING: Lazy<Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+/// Upload of a file a device starts.
+#[derive(OaSchema, Deserialize, Debug, Clone)]
+pub struct UploadRequest {
+    pub kind: IngestKind,
+    /// e.g. `image/heic` or `audio/mp4`, the content is probed anyway
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// Hex SHA-256 of the file
+    pub sha256: String,
+    /// When the media was captured
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Anything else the device knows about the media, as a JSON object
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+#[derive(OaSchema, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadStatus {
+    /// Chunks are expected from `offset`
+    Uploading,
+    /// Received and queued for ingestion
+    Complete,
+    /// The file was already received, nothing more to send
+    Duplicate,
+}
+
+/// Where an upload is at, as returned to the device.
+#[derive(OaSchema, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UploadState {
+    pub id: String,
+    pub status: UploadStatus,
+    /// Bytes received, where the next chunk starts
+    pub offset: u64,
+    pub size: u64,
+    /// Ingest job processing the file, see `GET /ingest/:id`
+    pub ingest_id: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum UploadError {
+    /// The device isn't paired
+    Unauthorized,
+    NotFound,
+    /// Too many uploads wait to be processed
+    Busy,
+    Invalid(String),
+    /// The chunk doesn't start where the upload is at, the offset given
+    WrongOffset(u64),
+    /// Another chunk of the upload is being written
+    InProgress,
+    ChunkTooLarge,
+    /// The file received doesn't have the SHA-256 given, it's to be sent
+    /// again from the start
+    ChecksumMismatch,
+    Internal(anyhow::Error),
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UploadError::Unauthorized => write!(f, "missing or invalid device token"),
+            UploadError::NotFound => write!(f, "upload not found"),
+            UploadError::Busy => write!(f, "too many uploads are waiting to be processed"),
+            UploadError::Invalid(message) => write!(f, "{}", message),
+            UploadError::WrongOffset(offset) => {
+                write!(f, "chunk isn't at the offset of the upload, {}", offset)
+            }
+            UploadError::InProgress => write!(f, "a chunk of this upload is being written"),
+            UploadError::ChunkTooLarge => {
+                write!(f, "chunks can't be larger than {} bytes", MAX_CHUNK_SIZE)
+            }
+            UploadError::ChecksumMismatch => {
+                write!(f, "file doesn't match its sha256, upload it again")
+            }
+            UploadError::Internal(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for UploadError {
+    fn from(e: sqlx::Error) -> Self {
+        UploadError::Internal(e.into())
+    }
+}
+
+impl From<io::Error> for UploadError {
+    fn from(e: io::Error) -> Self {
+        UploadError::Internal(e.into())
+    }
+}
+
+impl From<anyhow::Error> for UploadError {
+    fn from(e: anyhow::Error) -> Self {
+        UploadError::Internal(e)
+    }
+}
+
+impl IntoResponse for UploadError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            UploadError::Unauthorized => StatusCode::UNAUTHORIZED,
+            UploadError::NotFound => StatusCode::NOT_FOUND,
+            UploadError::Busy => StatusCode::SERVICE_UNAVAILABLE,
+            UploadError::Invalid(_) => StatusCode::BAD_REQUEST,
+            UploadError::WrongOffset(_) | UploadError::InProgress => StatusCode::CONFLICT,
+            UploadError::ChunkTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::ChecksumMismatch => StatusCode::UNPROCESSABLE_ENTITY,
+            UploadError::Internal(ref e) => {
+                warn!("companion upload failed: {:#}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        let mut body = json!({"error": self.to_string(), "success": false});
+        if let UploadError::WrongOffset(offset) = self {
+            body["offset"] = json!(offset);
+        }
+        let mut response = (status, Json(body)).into_response();
+        if status == StatusCode::SERVICE_UNAVAILABLE {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, RETRY_AFTER_DELAY.as_secs().into());
+        }
+        response
+    }
+}
+
+/// Where uploads are received.
+pub fn companion_dir(output_path: &str) -> PathBuf {
+    Path::new(output_path).join("companion")
+}
+
+fn part_path(output_path: &str, id: &str) -> PathBuf {
+    companion_dir(output_path).join(format!("{}.part", id))
+}
+
+fn kind_name(kind: IngestKind) -> &'static str {
+    match kind {
+        IngestKind::Image => "image",
+        IngestKind::Video => "video",
+        IngestKind::Audio => "audio",
+    }
+}
+
+fn parse_kind(kind: &str) -> Option<IngestKind> {
+    match kind {
+        "image" => Some(IngestKind::Image),
+        "video" => Some(IngestKind::Video),
+        "audio" => Some(IngestKind::Audio),
+        _ => None,
+    }
+}
+
+/// Pairs a device, returning its token with its record. The token can't
+/// be recovered later.
+pub async fn pair_device(
+    db: &DatabaseManager,
+    name: &str,
+) -> Result<(String, CompanionDevice), sqlx::Error> {
+    let token = generate_token();
+    let device = db
+        .insert_companion_device(name, &hash_token(&token))
+        .await?;
+    Ok((token, device))
+}
+
+/// The device sending a request, from its token.
+pub async fn authenticate(
+    db: &DatabaseManager,
+    headers: &HeaderMap,
+) -> Result<CompanionDevice, UploadError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .ok_or(UploadError::Unauthorized)?;
+    db.use_companion_device(&hash_token(token))
+        .await?
+        .ok_or(UploadError::Unauthorized)
+}
+
+async fn state_of(output_path: &str, upload: &CompanionUpload) -> UploadState {
+    let (status, offset) = if upload.completed_at.is_some() {
+        (UploadStatus::Complete, upload.size as u64)
+    } else {
+        let received = tokio::fs::metadata(part_path(output_path, &upload.id))
+            .await
+            .map_or(0, |metadata| metadata.len());
+        (UploadStatus::Uploading, received)
+    };
+    UploadState {
+        id: upload.id.clone(),
+        status,
+        offset,
+        size: upload.size as u64,
+        ingest_id: upload.ingest_id.clone(),
+    }
+}
+
+fn duplicate_of(upload: &CompanionUpload) -> UploadState {
+    UploadState {
+        id: upload.id.clone(),
+        status: UploadStatus::Duplicate,
+        offset: upload.size as u64,
+        size: upload.size as u64,
+        ingest_id: upload.ingest_id.clone(),
+    }
+}
+
+/// Deletes the uploads not completed in time, with what was received.
+async fn expire_uploads(db: &DatabaseManager, output_path: &str) -> Result<(), UploadError> {
+    let before = Utc::now() - chrono::Duration::days(UPLOAD_EXPIRY_DAYS);
+    for id in db.delete_stale_companion_uploads(before).await? {
+        let _ = tokio::fs::remove_file(part_path(output_path, &id)).await;
+    }
+    Ok(())
+}
+
+/// Starts the upload of a file, or returns the one to resume.
+pub async fn start_upload(
+    db: &DatabaseManager,
+    output_path: &str,
+    device: &CompanionDevice,
+    request: UploadRequest,
+) -> Result<UploadState, UploadError> {
+    let sha256 = request.sha256.to_ascii_lowercase();
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(UploadError::Invalid(
+            "sha256 must be 64 hex characters".to_string(),
+        ));
+    }
+    if request.size == 0 || request.size > MAX_UPLOAD_SIZE {
+        return Err(UploadError::Invalid(format!(
+            "size must be between 1 and {} bytes",
+            MAX_UPLOAD_SIZE
+        )));
+    }
+    if request
+        .metadata
+        .as_ref()
+        .is_some_and(|metadata| !metadata.is_object())
+    {
+        return Err(UploadError::Invalid(
+            "metadata must be a JSON object".to_string(),
+        ));
+    }
+    let content_type = request.content_type.unwrap_or_default();
+    if IngestKind::of_upload(Some(request.kind), &content_type).is_none() {
+        return Err(UploadError::Invalid(format!(
+            "{} isn't a content type of {}",
+            content_type,
+            kind_name(request.kind)
+        )));
+    }
+
+    expire_uploads(db, output_path).await?;
+    if let Some(upload) = db.find_companion_upload(&sha256, device.id).await? {
+        return Ok(if upload.completed_at.is_some() {
+            duplicate_of(&upload)
+        } else {
+            state_of(output_path, &upload).await
+        });
+    }
+    if pending_count(output_path) >= MAX_PENDING {
+        return Err(UploadError::Busy);
+    }
+
+    let id = Uuid::new_v4().to_string();
+    tokio::fs::create_dir_all(companion_dir(output_path)).await?;
+    tokio::fs::File::create(part_path(output_path, &id)).await?;
+    let upload = db
+        .insert_companion_upload(
+            &id,
+            device.id,
+            kind_name(request.kind),
+            &content_type,
+            request.size as i64,
+            &sha256,
+            request.timestamp,
+            request.metadata.as_ref(),
+        )
+        .await?;
+    info!(
+        "companion device {} started upload {} of {} bytes",
+        device.name, id, upload.size
+    );
+    Ok(state_of(output_path, &upload).await)
+}
+
+async fn own_upload(
+    db: &DatabaseManager,
+    device: &CompanionDevice,
+    id: &str,
+) -> Result<CompanionUpload, UploadError> {
+    db.get_companion_upload(id)
+        .await?
+        .filter(|upload| upload.device_id == device.id)
+        .ok_or(UploadError::NotFound)
+}
+
+/// Where the upload `id` of `device` is at.
+pub async fn upload_state(
+    db: &DatabaseManager,
+    output_path: &str,
+    device: &CompanionDevice,
+    id: &str,
+) -> Result<UploadState, UploadError> {
+    let upload = own_upload(db, device, id).await?;
+    Ok(state_of(output_path, &upload).await)
+}
+
+/// Removes the upload from [`WRITING`] when the chunk is written or fails.
+struct WritingGuard(String);
+
+impl Drop for WritingGuard {
+    fn drop(&mut self) {
+        WRITING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.0);
+    }
+}
+
+/// Appends the chunk starting at `offset` to the upload `id`, queueing the
+/// file for ingestion once it's all received.
+pub async fn append_chunk(
+    db: &DatabaseManager,
+    output_path: &str,
+    device: &CompanionDevice,
+    id: &str,
+    offset: u64,
+    chunk: Bytes,
+) -> Result<UploadState, UploadError> {
+    let upload = own_upload(db, device, id).await?;
+    if upload.completed_at.is_some() {
+        return Ok(state_of(output_path, &upload).await);
+    }
+    if !WRITING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(upload.id.clone())
+    {
+        return Err(UploadError::InProgress);
+    }
+    let _guard = WritingGuard(upload.id.clone());
+
+    let part = part_path(output_path, &upload.id);
+    let received = tokio::fs::metadata(&part).await?.len();
+    if offset != received {
+        return Err(UploadError::WrongOffset(received));
+    }
+    let size = upload.size as u64;
+    if offset + chunk.len() as u64 > size {
+        return Err(UploadError::Invalid(
+            "chunk goes past the end of the file".to_string(),
+        ));
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .append(true)
+        .open(&part)
+        .await?;
+    file.write_all(&chunk).await?;
+    file.flush().await?;
+    drop(file);
+
+    let received = offset + chunk.len() as u64;
+    if received < size {
+        return Ok(UploadState {
+            id: upload.id,
+            status: UploadStatus::Uploading,
+            offset: received,
+            size,
+            ingest_id: None,
+        });
+    }
+    complete_upload(db, output_path, device, upload, &part).await
+}
+
+async fn complete_upload(
+    db: &DatabaseManager,
+    output_path: &str,
+    device: &CompanionDevice,
+    upload: CompanionUpload,
+    part: &Path,
+) -> Result<UploadState, UploadError> {
+    let path = part.to_path_buf();
+    let sha256 = tokio::task::spawn_blocking(move || -> io::Result<String> {
+        let mut hasher = Sha256::new();
+        io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+        Ok(format!("{:x}", hasher.finalize()))
+    })
+    .await
+    .map_err(anyhow::Error::from)??;
+    if sha256 != upload.sha256 {
+        tokio::fs::File::create(part).await?;
+        return Err(UploadError::ChecksumMismatch);
+    }
+
+    // another device may have sent the same file meanwhile
+    if let Some(other) = db
+        .find_companion_upload(&upload.sha256, device.id)
+        .await?
+        .filter(|other| other.completed_at.is_some())
+    {
+        tokio::fs::remove_file(part).await?;
+        db.complete_companion_upload(&upload.id, other.ingest_id.as_deref().unwrap_or_default())
+            .await?;
+        return Ok(UploadState {
+            ingest_id: other.ingest_id.clone(),
+            ..duplicate_of(&upload)
+        });
+    }
+
+    let kind = parse_kind(&upload.kind)
+        .ok_or_else(|| anyhow::anyhow!("unknown upload kind {}", upload.kind))?;
+    let (_, extension) = IngestKind::of_upload(Some(kind), &upload.content_type)
+        .unwrap_or((kind, "bin".to_string()));
+    let job = queue_file(
+        output_path,
+        kind,
+        &extension,
+        Some(device.name.clone()),
+        upload.timestamp,
+        part,
+    )
+    .await?;
+    db.complete_companion_upload(&upload.id, &job.id).await?;
+    info!(
+        "companion device {} completed upload {}, queued as {}",
+        device.name, upload.id, job.id
+    );
+    Ok(UploadState {
+        id: upload.id,
+        status: UploadStatus::Complete,
+        offset: upload.size as u64,
+        size: upload.size as u64,
+        ingest_id: Some(job.id),
+    })
+}
+
+fn companion_error(status: StatusCode, error: String) -> (StatusCode, JsonResponse<Value>) {
+    (
+        status,
+        JsonResponse(json!({"error": error, "success": false})),
+    )
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct PairDeviceRequest {
+    /// Name the media of the device is attributed to, e.g. `iphone`
+    name: String,
+}
+
+/// Companion devices paired to push media.
+#[oasgen]
+pub(crate) async fn list_companion_devices_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let devices = state.db.list_companion_devices().await.map_err(|e| {
+        error!("failed to list companion devices: {}", e);
+        companion_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to list companion devices: {}", e),
+        )
+    })?;
+    Ok(JsonResponse(json!({
+        "data": devices,
+        "success": true
+    })))
+}
+
+/// Pairs a companion device. The response has the token it uploads with,
+/// which is only shown once.
+#[oasgen]
+pub(crate) async fn pair_companion_device_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(request): JsonResponse<PairDeviceRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err(companion_error(
+            StatusCode::BAD_REQUEST,
+            "name must not be empty".to_string(),
+        ));
+    }
+    let devices = state.db.list_companion_devices().await.map_err(|e| {
+        companion_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to list companion devices: {}", e),
+        )
+    })?;
+    if devices.iter().any(|device| device.name == name) {
+        return Err(companion_error(
+            StatusCode::CONFLICT,
+            format!("a device is already paired as {}", name),
+        ));
+    }
+
+    let (token, device) = pair_device(&state.db, name).await.map_err(|e| {
+        error!("failed to pair companion device: {}", e);
+        companion_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to pair companion device: {}", e),
+        )
+    })?;
+    Ok(JsonResponse(json!({
+        "data": {"device": device, "token": token},
+        "success": true
+    })))
+}
+
+/// Unpairs a companion device, what it uploaded is kept.
+#[oasgen]
+pub(crate) async fn delete_companion_device_handler(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.delete_companion_device(id).await {
+        Ok(true) => Ok(JsonResponse(json!({"success": true}))),
+        Ok(false) => Err(companion_error(
+            StatusCode::NOT_FOUND,
+            format!("no companion device {}", id),
+        )),
+        Err(e) => {
+            error!("failed to delete companion device {}: {}", id, e);
+            Err(companion_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to delete companion device: {}", e),
+            ))
+        }
+    }
+}
+
+/// Starts the upload of a file by a companion device, or returns the one to
+/// resume, see [`crate::companion`].
+pub(crate) async fn start_companion_upload_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<UploadRequest>,
+) -> Result<JsonResponse<Value>, UploadError> {
+    let device = authenticate(&state.db, &headers).await?;
+    let output_path = state.screenpipe_dir.join("data");
+    let upload = start_upload(&state.db, &output_path.to_string_lossy(), &device, request).await?;
+    Ok(JsonResponse(json!({
+        "data": upload,
+        "success": true
+    })))
+}
+
+/// Where an upload of a companion device is at.
+pub(crate) async fn get_companion_upload_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<JsonResponse<Value>, UploadError> {
+    let device = authenticate(&state.db, &headers).await?;
+    let output_path = state.screenpipe_dir.join("data");
+    let upload = upload_state(&state.db, &output_path.to_string_lossy(), &device, &id).await?;
+    Ok(JsonResponse(json!({
+        "data": upload,
+        "success": true
+    })))
+}
+
+/// Appends a chunk to an upload of a companion device, at the offset in
+/// the `Upload-Offset` header.
+pub(crate) async fn put_companion_chunk_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    body: Body,
+) -> Result<JsonResponse<Value>, UploadError> {
+    let device = authenticate(&state.db, &headers).await?;
+    let offset = headers
+        .get(OFFSET_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .ok_or_else(|| UploadError::Invalid("missing or invalid Upload-Offset".to_string()))?;
+    let chunk = axum::body::to_bytes(body, MAX_CHUNK_SIZE)
+        .await
+        .map_err(|_| UploadError::ChunkTooLarge)?;
+    let output_path = state.screenpipe_dir.join("data");
+    let upload = append_chunk(
+        &state.db,
+        &output_path.to_string_lossy(),
+        &device,
+        &id,
+        offset,
+        chunk,
+    )
+    .await?;
+    Ok(JsonResponse(json!({
+        "data": upload,
+        "success": true
+    })))
+}
@@ -1,17 +1,31 @@
+use crate::ocr_queue::save_deferred_ocr;
+use crate::redaction::Redaction;
+use crate::video_encoder::VideoEncoder;
+use crate::watchdog::{monitor_restart_signal, record_ocr_progress};
 use crate::VideoCapture;
 use anyhow::Result;
+use chrono::Utc;
 use futures::future::join_all;
-use screenpipe_core::pii_removal::remove_pii;
+use screenpipe_core::metrics;
 use screenpipe_core::Language;
 use screenpipe_db::{DatabaseManager, Speaker};
-use screenpipe_events::{poll_meetings_events, send_event};
+use screenpipe_events::{
+    poll_meetings_events, send_event, AppSwitchedEvent, FrameIndexedEvent, APP_SWITCHED_EVENT,
+    FRAME_INDEXED_EVENT,
+};
+use screenpipe_vision::capture_screenshot_by_window::CaptureMode;
 use screenpipe_vision::core::WindowOcr;
-use screenpipe_vision::OcrEngine;
+use screenpipe_vision::frame_rate::SharedAdaptiveFpsConfig;
+use screenpipe_vision::region::regions_for_monitor;
+use screenpipe_vision::{CaptureRegion, OcrEngine, PrivacyBlocklist};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::runtime::Handle;
-use tracing::{debug, error, info, warn};
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
+/// Records the monitors until a shutdown is signalled, then returns once the
+/// frames already captured are written to the videos and the database.
 #[allow(clippy::too_many_arguments)]
 pub async fn start_continuous_recording(
     db: Arc<DatabaseManager>,
@@ -20,14 +34,22 @@ pub async fn start_continuous_recording(
     video_chunk_duration: Duration,
     ocr_engine: Arc<OcrEngine>,
     monitor_ids: Vec<u32>,
-    use_pii_removal: bool,
+    redaction: Option<Redaction>,
     vision_disabled: bool,
     vision_handle: &Handle,
     ignored_windows: &[String],
     include_windows: &[String],
+    privacy_blocklist: Arc<PrivacyBlocklist>,
     languages: Vec<Language>,
     capture_unfocused_windows: bool,
+    capture_ui_tree: bool,
+    accessibility_text: bool,
     realtime_vision: bool,
+    capture_regions: &[CaptureRegion],
+    capture_mode: CaptureMode,
+    adaptive_fps: SharedAdaptiveFpsConfig,
+    video_encoder: VideoEncoder,
+    mut shutdown_rx: broadcast::Receiver<()>,
 ) -> Result<()> {
     info!("Starting video recording for monitors {:?}", monitor_ids);
     let video_tasks = if !vision_disabled {
@@ -39,34 +61,55 @@ pub async fn start_continuous_recording(
                 let ocr_engine = Arc::clone(&ocr_engine);
                 let ignored_windows_video = ignored_windows.to_vec();
                 let include_windows_video = include_windows.to_vec();
+                let privacy_blocklist = Arc::clone(&privacy_blocklist);
+                let capture_regions_video = regions_for_monitor(capture_regions, monitor_id);
 
                 let languages = languages.clone();
+                let adaptive_fps = adaptive_fps.clone();
+                let redaction = redaction.clone();
+                let mut shutdown_rx = shutdown_rx.resubscribe();
 
                 info!("Starting video recording for monitor {}", monitor_id);
+                let restart = monitor_restart_signal(monitor_id);
                 vision_handle.spawn(async move {
                     // Wrap in a loop with recovery logic
                     loop {
                         info!("Starting/restarting vision capture for monitor {}", monitor_id);
-                        match record_video(
+                        let recording = record_video(
                             db_manager_video.clone(),
                             output_path_video.clone(),
                             fps,
                             ocr_engine.clone(),
                             monitor_id,
-                            use_pii_removal,
+                            redaction.clone(),
                             &ignored_windows_video,
                             &include_windows_video,
+                            privacy_blocklist.clone(),
                             video_chunk_duration,
                             languages.clone(),
                             capture_unfocused_windows,
+                            capture_ui_tree,
+                            accessibility_text,
                             realtime_vision,
-                        )
-                        .await
-                        {
+                            capture_regions_video.clone(),
+                            capture_mode,
+                            adaptive_fps.clone(),
+                            video_encoder,
+                            &mut shutdown_rx,
+                        );
+                        let result = tokio::select! {
+                            result = recording => result,
+                            // dropping the recording stops its capture tasks
+                            _ = restart.notified() => {
+                                warn!("restarting stalled vision pipeline of monitor {}", monitor_id);
+                                continue;
+                            }
+                        };
+                        match result {
+                            // only returns after a shutdown
                             Ok(_) => {
-                                warn!("record_video for monitor {} completed unexpectedly but without error", monitor_id);
-                                // Short delay before restarting to prevent CPU spinning
-                                tokio::time::sleep(Duration::from_secs(5)).await;
+                                info!("record_video for monitor {} stopped", monitor_id);
+                                break Ok::<(), anyhow::Error>(());
                             }
                             Err(e) => {
                                 error!("record_video for monitor {} failed with error: {}", monitor_id, e);
@@ -80,7 +123,7 @@ pub async fn start_continuous_recording(
             .collect::<Vec<_>>()
     } else {
         vec![vision_handle.spawn(async move {
-            tokio::time::sleep(Duration::from_secs(60)).await;
+            let _ = shutdown_rx.recv().await;
             Ok::<(), anyhow::Error>(())
         })]
     };
@@ -117,13 +160,21 @@ async fn record_video(
     fps: f64,
     ocr_engine: Arc<OcrEngine>,
     monitor_id: u32,
-    use_pii_removal: bool,
+    redaction: Option<Redaction>,
     ignored_windows: &[String],
     include_windows: &[String],
+    privacy_blocklist: Arc<PrivacyBlocklist>,
     video_chunk_duration: Duration,
     languages: Vec<Language>,
     capture_unfocused_windows: bool,
+    capture_ui_tree: bool,
+    accessibility_text: bool,
     realtime_vision: bool,
+    capture_regions: Vec<CaptureRegion>,
+    capture_mode: CaptureMode,
+    adaptive_fps: SharedAdaptiveFpsConfig,
+    video_encoder: VideoEncoder,
+    shutdown_rx: &mut broadcast::Receiver<()>,
 ) -> Result<()> {
     info!("record_video: Starting for monitor {}", monitor_id);
     let device_name = Arc::new(format!("monitor_{}", monitor_id));
@@ -159,7 +210,7 @@ async fn record_video(
     };
 
     info!("Creating VideoCapture for monitor {}", monitor_id);
-    let video_capture = VideoCapture::new(
+    let mut video_capture = VideoCapture::new(
         &output_path,
         fps,
         video_chunk_duration,
@@ -168,8 +219,16 @@ async fn record_video(
         monitor_id,
         ignored_windows,
         include_windows,
+        privacy_blocklist,
         languages,
         capture_unfocused_windows,
+        capture_ui_tree,
+        accessibility_text,
+        capture_regions,
+        capture_mode,
+        adaptive_fps,
+        video_encoder,
+        redaction,
     );
 
     info!(
@@ -178,12 +237,33 @@ async fn record_video(
     );
     let mut last_frame_time = std::time::Instant::now();
     let mut frames_processed = 0;
+    // frames of the last capture, counted again on repeats
+    let mut last_frame_ids: Vec<i64> = Vec::new();
+    // app focused on the last capture, to notice switches
+    let mut last_focused_app: Option<String> = None;
+
+    let ocr_queue_depth = metrics::OCR_QUEUE_DEPTH.with_labels(&[&monitor_id.to_string()]);
+    let insert_frame_duration = metrics::DB_WRITE_DURATION.with_labels(&["insert_frame"]);
+    let insert_ocr_text_duration = metrics::DB_WRITE_DURATION.with_labels(&["insert_ocr_text"]);
 
     // Keep count of consecutive errors to detect unhealthy state
     let mut consecutive_db_errors = 0;
     const MAX_CONSECUTIVE_DB_ERRORS: u32 = 100; // Threshold before reporting unhealthy state
 
+    record_ocr_progress(monitor_id);
+    // after a shutdown, the frames left in the OCR queue are written
+    let mut draining = false;
+
     loop {
+        if !draining && !matches!(shutdown_rx.try_recv(), Err(TryRecvError::Empty)) {
+            info!(
+                "record_video: stopping capture of monitor {}, writing the frames left",
+                monitor_id
+            );
+            video_capture.stop().await;
+            draining = true;
+        }
+
         // Increment and check heartbeat
         heartbeat_counter += 1;
         if heartbeat_counter % heartbeat_interval == 0 {
@@ -208,7 +288,7 @@ async fn record_video(
         }
 
         // In the try-catch block inside the loop, add health checks
-        if heartbeat_counter % health_check_interval == 0 {
+        if !draining && heartbeat_counter % health_check_interval == 0 {
             debug!(
                 "Checking VideoCapture task health for monitor {}",
                 monitor_id
@@ -224,6 +304,14 @@ async fn record_video(
         }
 
         if let Some(frame) = video_capture.ocr_frame_queue.pop() {
+            ocr_queue_depth.set(video_capture.ocr_frame_queue.len() as f64);
+            record_ocr_progress(monitor_id);
+            let db_span = info_span!(
+                parent: &frame.span,
+                "db_write",
+                device = %device_name,
+                windows = frame.window_ocr_results.len()
+            );
             let time_since_last_frame = last_frame_time.elapsed();
             last_frame_time = std::time::Instant::now();
             frames_processed += 1;
@@ -235,20 +323,35 @@ async fn record_video(
                 time_since_last_frame.as_millis()
             );
 
+            // a repeat has no windows, the frames stored last stay on screen
+            if frame.repeat_of_previous {
+                if let Err(e) = db
+                    .record_frame_repeat(&last_frame_ids, Utc::now())
+                    .instrument(db_span.clone())
+                    .await
+                {
+                    warn!("Failed to record repeated frame: {}", e);
+                }
+            } else {
+                last_frame_ids.clear();
+            }
+
             for window_result in &frame.window_ocr_results {
                 let insert_frame_start = std::time::Instant::now();
                 let result = db
                     .insert_frame(
                         &device_name,
                         None,
-                        window_result.browser_url.as_deref(),
+                        window_result.browser_context.as_ref(),
                         Some(window_result.app_name.as_str()),
                         Some(window_result.window_name.as_str()),
                         window_result.focused,
                     )
+                    .instrument(db_span.clone())
                     .await;
 
                 let insert_duration = insert_frame_start.elapsed();
+                insert_frame_duration.observe(insert_duration.as_secs_f64());
                 if insert_duration.as_millis() > 100 {
                     warn!(
                         "Slow DB insert_frame operation: {}ms",
@@ -258,6 +361,10 @@ async fn record_video(
 
                 match result {
                     Ok(frame_id) => {
+                        last_frame_ids.push(frame_id);
+                        if let Err(e) = db.set_frame_phash(frame_id, frame.phash).await {
+                            warn!("Failed to store hash of frame {}: {}", frame_id, e);
+                        }
                         debug!(
                             "Successfully inserted frame {} in {}ms",
                             frame_id,
@@ -266,11 +373,10 @@ async fn record_video(
                         let text_json =
                             serde_json::to_string(&window_result.text_json).unwrap_or_default();
 
-                        let text = if use_pii_removal {
-                            &remove_pii(&window_result.text)
-                        } else {
-                            &window_result.text
-                        };
+                        // already redacted by the capture queue, if enabled
+                        let text = &window_result.text;
+                        let layout = &window_result.layout;
+                        let tables = &window_result.tables;
 
                         if realtime_vision {
                             let send_event_start = std::time::Instant::now();
@@ -280,12 +386,17 @@ async fn record_video(
                                     image: Some(frame.image.clone()),
                                     text: text.clone(),
                                     text_json: window_result.text_json.clone(),
+                                    layout: layout.clone(),
+                                    tables: tables.clone(),
                                     app_name: window_result.app_name.clone(),
                                     window_name: window_result.window_name.clone(),
                                     focused: window_result.focused,
                                     confidence: window_result.confidence,
                                     timestamp: frame.timestamp,
-                                    browser_url: window_result.browser_url.clone(),
+                                    browser_url: window_result
+                                        .browser_context
+                                        .as_ref()
+                                        .map(|context| context.url.clone()),
                                 },
                             ) {
                                 Ok(_) => {
@@ -309,6 +420,7 @@ async fn record_video(
                                 &text_json,
                                 Arc::new((*ocr_engine).clone().into()),
                             )
+                            .instrument(db_span.clone())
                             .await
                         {
                             error!(
@@ -319,6 +431,7 @@ async fn record_video(
                             continue;
                         } else {
                             let ocr_insert_duration = insert_ocr_start.elapsed();
+                            insert_ocr_text_duration.observe(ocr_insert_duration.as_secs_f64());
                             if ocr_insert_duration.as_millis() > 100 {
                                 warn!(
                                     "Slow DB insert_ocr_text operation: {}ms",
@@ -332,6 +445,68 @@ async fn record_video(
                                 ocr_insert_duration.as_millis()
                             );
                         }
+
+                        let _ = send_event(
+                            FRAME_INDEXED_EVENT,
+                            FrameIndexedEvent {
+                                frame_id,
+                                timestamp: Utc::now(),
+                                device_name: device_name.to_string(),
+                                app_name: window_result.app_name.clone(),
+                                window_name: window_result.window_name.clone(),
+                                focused: window_result.focused,
+                                ocr_deferred: window_result.ocr_deferred,
+                            },
+                        );
+                        if window_result.focused && !window_result.app_name.is_empty() {
+                            let previous = last_focused_app.replace(window_result.app_name.clone());
+                            if let Some(previous_app_name) =
+                                previous.filter(|previous| *previous != window_result.app_name)
+                            {
+                                let _ = send_event(
+                                    APP_SWITCHED_EVENT,
+                                    AppSwitchedEvent {
+                                        frame_id,
+                                        timestamp: Utc::now(),
+                                        device_name: device_name.to_string(),
+                                        app_name: window_result.app_name.clone(),
+                                        window_name: window_result.window_name.clone(),
+                                        previous_app_name,
+                                    },
+                                );
+                            }
+                        }
+
+                        if window_result.ocr_deferred && text.trim().is_empty() {
+                            if let Err(e) =
+                                save_deferred_ocr(&output_path, frame_id, &window_result.image)
+                                    .await
+                            {
+                                warn!("Failed to save frame {} for later OCR: {}", frame_id, e);
+                            }
+                        }
+
+                        if !layout.is_empty() {
+                            if let Err(e) = db.insert_ocr_blocks(frame_id, layout).await {
+                                warn!("Failed to insert OCR blocks for frame {}: {}", frame_id, e);
+                            }
+                        }
+
+                        if !tables.is_empty() {
+                            if let Err(e) = db.insert_ocr_tables(frame_id, tables).await {
+                                warn!("Failed to insert OCR tables for frame {}: {}", frame_id, e);
+                            }
+                        }
+
+                        if let Some(tree) = &window_result.accessibility_tree {
+                            if let Err(e) = db.insert_frame_accessibility_tree(frame_id, tree).await
+                            {
+                                warn!(
+                                    "Failed to insert accessibility tree for frame {}: {}",
+                                    frame_id, e
+                                );
+                            }
+                        }
                     }
                     Err(e) => {
                         warn!("Failed to insert frame: {}", e);
@@ -341,6 +516,12 @@ async fn record_video(
                     }
                 }
             }
+        } else if draining {
+            info!(
+                "record_video: wrote the frames left for monitor {}",
+                monitor_id
+            );
+            return Ok(());
         } else {
             // Log when frame queue is empty
             if heartbeat_counter % 10 == 0 {
@@ -362,7 +543,9 @@ async fn record_video(
         }
 
         // Sleep for the frame interval
-        tokio::time::sleep(Duration::from_secs_f64(1.0 / fps)).await;
+        if !draining {
+            tokio::time::sleep(Duration::from_secs_f64(1.0 / fps)).await;
+        }
     }
 }
 
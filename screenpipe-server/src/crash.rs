@@ -0,0 +1,283 @@
+//! Crash reports of the recorder. A monitor process started with the
+//! recorder writes a minidump when it crashes, with the end of its log, the
+//! crash location as a module offset and the debug identifiers of the loaded
+//! modules, which is what symbolication against the debug files of a release
+//! needs. Reports stay in `<screenpipe_dir>/crashes` until the app sends
+//! them, only if the user agrees, or they are dismissed.
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use minidump::{Minidump, MinidumpException, MinidumpModuleList, MinidumpSystemInfo, Module};
+use minidumper::{LoopAction, MinidumpBinary, ServerHandler};
+use serde::{Deserialize, Serialize};
+
+use crate::logging::log_files;
+
+/// Lines of the log kept with a crash report.
+pub const CRASH_LOG_LINES: usize = 500;
+
+const REPORT_FILE: &str = "report.json";
+const MINIDUMP_FILE: &str = "minidump.dmp";
+const LOG_FILE: &str = "screenpipe.log";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashReportStatus {
+    /// Waiting for the user to send or dismiss it.
+    Pending,
+    Sent,
+    Dismissed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// Name of the directory of the report.
+    pub id: String,
+    pub crashed_at: DateTime<Utc>,
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    /// e.g. `SIGSEGV / SEGV_MAPERR`, `None` when the minidump has no
+    /// exception.
+    pub reason: Option<String>,
+    pub address: Option<u64>,
+    /// Module the crash address is in.
+    pub module: Option<String>,
+    /// Offset of the crash address in `module`.
+    pub module_offset: Option<u64>,
+    /// Modules loaded when it crashed.
+    pub modules: Vec<CrashModule>,
+    pub status: CrashReportStatus,
+}
+
+/// A loaded module, identified the way symbol servers and `dump_syms`
+/// identify debug files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashModule {
+    pub name: String,
+    pub base_address: u64,
+    pub size: u64,
+    pub debug_file: Option<String>,
+    pub debug_id: Option<String>,
+    pub code_id: Option<String>,
+}
+
+pub fn crashes_dir(screenpipe_dir: &Path) -> PathBuf {
+    screenpipe_dir.join("crashes")
+}
+
+/// Crash reports, newest first.
+pub fn list_crash_reports(screenpipe_dir: &Path) -> anyhow::Result<Vec<CrashReport>> {
+    let dir = crashes_dir(screenpipe_dir);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut reports = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        // the monitor may still be writing it
+        if let Ok(report) = read_crash_report(&entry?.path()) {
+            reports.push(report);
+        }
+    }
+    reports.sort_by_key(|report| std::cmp::Reverse(report.crashed_at));
+    Ok(reports)
+}
+
+fn read_crash_report(dir: &Path) -> anyhow::Result<CrashReport> {
+    Ok(serde_json::from_slice(&fs::read(dir.join(REPORT_FILE))?)?)
+}
+
+/// Keeps the recorder's crashes, until dropped.
+pub struct CrashReporter {
+    _handler: crash_handler::CrashHandler,
+    _monitor: Child,
+}
+
+/// Starts the monitor process writing the crash reports of this process,
+/// it exits with it.
+pub fn start_crash_reporter(screenpipe_dir: &Path) -> anyhow::Result<CrashReporter> {
+    let socket = std::env::temp_dir().join(format!("screenpipe-crash-{}.sock", std::process::id()));
+    let monitor = Command::new(std::env::current_exe()?)
+        .arg("crash-monitor")
+        .arg("--socket")
+        .arg(&socket)
+        .arg("--data-dir")
+        .arg(screenpipe_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()
+        .context("failed to start the crash monitor")?;
+
+    let client = connect(&socket)?;
+    // SAFETY: the event runs in the signal or exception handler, all it does
+    // is hand the crash context to the monitor
+    let handler = crash_handler::CrashHandler::attach(unsafe {
+        crash_handler::make_crash_event(move |context: &crash_handler::CrashContext| {
+            crash_handler::CrashEventResult::Handled(client.request_dump(context).is_ok())
+        })
+    })?;
+    // the monitor reads the memory of this process when it crashes
+    #[cfg(target_os = "linux")]
+    handler.set_ptracer(Some(monitor.id()));
+
+    Ok(CrashReporter {
+        _handler: handler,
+        _monitor: monitor,
+    })
+}
+
+fn connect(socket: &Path) -> anyhow::Result<minidumper::Client> {
+    let mut attempts = 0;
+    loop {
+        match minidumper::Client::with_name(socket) {
+            Ok(client) => return Ok(client),
+            // the monitor takes a moment to listen
+            Err(_) if attempts < 50 => {
+                attempts += 1;
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) => return Err(anyhow::anyhow!("crash monitor isn't listening: {}", e)),
+        }
+    }
+}
+
+/// Runs the monitor started by `start_crash_reporter` until the recorder
+/// exits or crashes.
+pub fn run_crash_monitor(socket: &Path, screenpipe_dir: &Path) -> anyhow::Result<()> {
+    // left behind by a process with the same id
+    let _ = fs::remove_file(socket);
+    let mut server = minidumper::Server::with_name(socket)?;
+    let monitor = CrashMonitor {
+        screenpipe_dir: screenpipe_dir.to_path_buf(),
+    };
+    let result = server.run(Box::new(monitor), &AtomicBool::new(false), None);
+    let _ = fs::remove_file(socket);
+    Ok(result?)
+}
+
+struct CrashMonitor {
+    screenpipe_dir: PathBuf,
+}
+
+impl ServerHandler for CrashMonitor {
+    fn create_minidump_file(&self) -> Result<(File, PathBuf), io::Error> {
+        let id = Utc::now().format("%Y-%m-%dT%H-%M-%S").to_string();
+        let dir = crashes_dir(&self.screenpipe_dir).join(id);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(MINIDUMP_FILE);
+        Ok((File::create(&path)?, path))
+    }
+
+    fn on_minidump_created(&self, result: Result<MinidumpBinary, minidumper::Error>) -> LoopAction {
+        match result {
+            Ok(binary) => {
+                if let Err(e) = write_crash_report(&self.screenpipe_dir, &binary.path) {
+                    eprintln!("failed to write crash report: {}", e);
+                }
+            }
+            Err(e) => eprintln!("failed to write minidump: {}", e),
+        }
+        LoopAction::Exit
+    }
+
+    fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {}
+
+    fn on_client_disconnected(&self, num_clients: usize) -> LoopAction {
+        // the recorder exited without crashing
+        if num_clients == 0 {
+            LoopAction::Exit
+        } else {
+            LoopAction::Continue
+        }
+    }
+}
+
+/// Writes the report of the crash `minidump` was written for, next to it,
+/// with the end of the latest log file.
+pub fn write_crash_report(screenpipe_dir: &Path, minidump: &Path) -> anyhow::Result<CrashReport> {
+    let dir = minidump
+        .parent()
+        .context("minidump isn't in a report directory")?;
+    if let Some(log) = log_files(screenpipe_dir)?.first() {
+        fs::write(dir.join(LOG_FILE), last_lines(log, CRASH_LOG_LINES)?)?;
+    }
+
+    let mut report = CrashReport {
+        id: dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        crashed_at: Utc::now(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        reason: None,
+        address: None,
+        module: None,
+        module_offset: None,
+        modules: Vec::new(),
+        status: CrashReportStatus::Pending,
+    };
+    // a report without the crash location still has the log
+    if let Err(e) = read_minidump(minidump, &mut report) {
+        eprintln!("failed to read minidump: {}", e);
+    }
+
+    // the app lists the reports while they're written
+    let tmp = dir.join(format!("{}.tmp", REPORT_FILE));
+    fs::write(&tmp, serde_json::to_vec_pretty(&report)?)?;
+    fs::rename(tmp, dir.join(REPORT_FILE))?;
+    Ok(report)
+}
+
+fn read_minidump(path: &Path, report: &mut CrashReport) -> anyhow::Result<()> {
+    let dump = Minidump::read_path(path)?;
+    let modules = dump.get_stream::<MinidumpModuleList>()?;
+    report.modules = modules
+        .iter()
+        .map(|module| CrashModule {
+            name: module.code_file().into_owned(),
+            base_address: module.base_address(),
+            size: module.size(),
+            debug_file: module.debug_file().map(|file| file.into_owned()),
+            debug_id: module
+                .debug_identifier()
+                .map(|id| id.breakpad().to_string()),
+            code_id: module.code_identifier().map(|id| id.to_string()),
+        })
+        .collect();
+
+    let system_info = dump.get_stream::<MinidumpSystemInfo>()?;
+    let exception = dump.get_stream::<MinidumpException>()?;
+    let address = exception.get_crash_address(system_info.os, system_info.cpu);
+    report.reason = Some(
+        exception
+            .get_crash_reason(system_info.os, system_info.cpu)
+            .to_string(),
+    );
+    report.address = Some(address);
+    if let Some(module) = modules.module_at_address(address) {
+        report.module = Some(module.code_file().into_owned());
+        report.module_offset = Some(address - module.base_address());
+    }
+    Ok(())
+}
+
+fn last_lines(path: &Path, count: usize) -> io::Result<String> {
+    let mut lines = VecDeque::with_capacity(count);
+    for line in BufReader::new(File::open(path)?).split(b'\n') {
+        if lines.len() == count {
+            lines.pop_front();
+        }
+        lines.push_back(String::from_utf8_lossy(&line?).into_owned());
+    }
+    Ok(lines.into_iter().collect::<Vec<_>>().join("\n") + "\n")
+}
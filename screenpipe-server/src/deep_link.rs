@@ -0,0 +1,82 @@
+//! `screenpipe://` links opening the timeline of the app at a frame or a
+//! moment, so notes exported to Obsidian or Notion can jump back to what was
+//! recorded.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+
+pub const DEEP_LINK_SCHEME: &str = "screenpipe";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeepLink {
+    /// `screenpipe://frame/12345`
+    Frame(i64),
+    /// `screenpipe://time/2024-06-01T10:00:00Z`
+    Time(DateTime<Utc>),
+}
+
+pub fn frame_link(frame_id: i64) -> String {
+    DeepLink::Frame(frame_id).to_string()
+}
+
+pub fn time_link(timestamp: DateTime<Utc>) -> String {
+    DeepLink::Time(timestamp).to_string()
+}
+
+impl fmt::Display for DeepLink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeepLink::Frame(frame_id) => write!(f, "{}://frame/{}", DEEP_LINK_SCHEME, frame_id),
+            DeepLink::Time(timestamp) => write!(
+                f,
+                "{}://time/{}",
+                DEEP_LINK_SCHEME,
+                timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+            ),
+        }
+    }
+}
+
+/// Times without an offset, like `2024-06-01T10:00`, are local.
+fn parse_link_time(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M"))
+        .map_err(|_| anyhow::anyhow!("invalid time in link: {}", value))?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .ok_or_else(|| anyhow::anyhow!("time doesn't exist locally: {}", value))
+}
+
+impl FromStr for DeepLink {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix(DEEP_LINK_SCHEME)
+            .and_then(|rest| rest.strip_prefix("://"))
+            .ok_or_else(|| anyhow::anyhow!("not a screenpipe link: {}", s))?;
+        let (kind, value) = rest
+            .trim_end_matches('/')
+            .split_once('/')
+            .ok_or_else(|| anyhow::anyhow!("invalid screenpipe link: {}", s))?;
+        match kind {
+            "frame" => value
+                .parse()
+                .map(DeepLink::Frame)
+                .map_err(|_| anyhow::anyhow!("invalid frame id in link: {}", value)),
+            "time" => parse_link_time(value).map(DeepLink::Time),
+            _ => Err(anyhow::anyhow!(
+                "unknown screenpipe link: {}, expected frame or time",
+                s
+            )),
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! `screenpipe doctor`: checks that the database and the recordings on disk
+//! agree with each other, and repairs what can be. Also served on
+//! `/admin/doctor`.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use axum::{extract::State, http::StatusCode, response::Json as JsonResponse};
+use chrono::{DateTime, Utc};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_db::{DatabaseManager, IntegrityIssue, MediaType};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::{error, info, warn};
+use walkdir::WalkDir;
+
+use crate::server::AppState;
+
+/// Chunks and files this recent may still be written, they're left alone.
+const MIN_MEDIA_AGE: Duration = Duration::from_secs(5 * 60);
+/// Extensions of the recordings kept under `data/`.
+const MEDIA_EXTENSIONS: [&str; 5] = ["mp4", "mp3", "wav", "m4a", "webm"];
+
+#[derive(OaSchema, Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub repair: bool,
+    pub issues: Vec<IntegrityIssue>,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl DoctorReport {
+    /// Issues left after the run, all of them unless repairing.
+    pub fn unrepaired(&self) -> usize {
+        self.issues.iter().filter(|issue| !issue.repaired).count()
+    }
+}
+
+/// Runs every check on the database and the recordings under `data_dir`,
+/// repairing what's found with `repair`:
+///
+/// - rows whose chunk, frame or foreign key parent is gone are deleted
+/// - full text indexes out of sync with their table are rebuilt
+/// - chunks whose file is gone are marked deleted, their text stays
+/// - recordings no chunk refers to are deleted
+///
+/// Corruption found by SQLite's own check is only reported, restoring a
+/// backup is the way out.
+pub async fn run_doctor(
+    db: &DatabaseManager,
+    data_dir: &Path,
+    repair: bool,
+) -> Result<DoctorReport> {
+    let mut issues = db.check_sqlite_integrity().await?;
+    issues.extend(db.check_missing_parents(repair).await?);
+    issues.extend(db.check_foreign_keys(repair).await?);
+    issues.extend(db.check_fts_indexes(repair).await?);
+    issues.extend(check_missing_media(db, repair).await?);
+    issues.extend(check_orphaned_media(db, &data_dir.join("data"), repair).await?);
+
+    let report = DoctorReport {
+        repair,
+        issues,
+        checked_at: Utc::now(),
+    };
+    info!(
+        "doctor found {} issues, {} left unrepaired",
+        report.issues.len(),
+        report.unrepaired()
+    );
+    Ok(report)
+}
+
+/// Chunks on local disk according to the database whose file is gone.
+async fn check_missing_media(db: &DatabaseManager, repair: bool) -> Result<Vec<IntegrityIssue>> {
+    let before = Utc::now() - chrono::Duration::from_std(MIN_MEDIA_AGE)?;
+    let mut issues = Vec::new();
+    for (media_type, name) in [(MediaType::Video, "video"), (MediaType::Audio, "audio")] {
+        let missing: Vec<i64> = db
+            .get_local_media_chunks_before(media_type, before, u32::MAX)
+            .await?
+            .into_iter()
+            .filter(|chunk| !Path::new(&chunk.file_path).exists())
+            .map(|chunk| chunk.id)
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+        let mut issue = IntegrityIssue::new(
+            "missing_media",
+            format!("{} chunks refer to a file that doesn't exist", name),
+            missing.len() as u64,
+        );
+        if repair {
+            db.mark_media_chunks_deleted(media_type, &missing).await?;
+            issue.repaired = true;
+        }
+        issues.push(issue);
+    }
+    Ok(issues)
+}
+
+/// Recordings under `media_dir` no chunk refers to. Files are matched by
+/// name, so a data directory that moved isn't taken for orphans.
+async fn check_orphaned_media(
+    db: &DatabaseManager,
+    media_dir: &Path,
+    repair: bool,
+) -> Result<Vec<IntegrityIssue>> {
+    let referenced: HashSet<String> = db
+        .media_chunk_paths()
+        .await?
+        .iter()
+        .filter_map(|path| Path::new(path).file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .collect();
+
+    let orphaned: Vec<_> = WalkDir::new(media_dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .is_some_and(|extension| MEDIA_EXTENSIONS.contains(&&*extension.to_string_lossy()))
+        })
+        .filter(|entry| !referenced.contains(&*entry.file_name().to_string_lossy()))
+        .filter(|entry| {
+            entry
+                .metadata()
+                .ok()
+                .and_then(|metadata| metadata.modified().ok())
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age >= MIN_MEDIA_AGE)
+        })
+        .collect();
+    if orphaned.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut issue = IntegrityIssue::new(
+        "orphaned_media",
+        format!(
+            "{} recordings in {} aren't referred to by any chunk",
+            orphaned.len(),
+            media_dir.display()
+        ),
+        orphaned.len() as u64,
+    );
+    if repair {
+        for entry in &orphaned {
+            if let Err(e) = tokio::fs::remove_file(entry.path()).await {
+                warn!("failed to delete {}: {}", entry.path().display(), e);
+            }
+        }
+        issue.repaired = true;
+    }
+    Ok(vec![issue])
+}
+
+async fn doctor(
+    state: &AppState,
+    repair: bool,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match run_doctor(&state.db, &state.screenpipe_dir, repair).await {
+        Ok(report) => Ok(JsonResponse(json!({
+            "data": report,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("doctor failed: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("doctor failed: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
+/// Checks that the database and the recordings agree with each other,
+/// changing nothing.
+#[oasgen]
+pub(crate) async fn doctor_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    doctor(&state, false).await
+}
+
+/// Runs the doctor's checks and repairs what they find, see
+/// `screenpipe doctor --repair`.
+#[oasgen]
+pub(crate) async fn doctor_repair_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    doctor(&state, true).await
+}
@@ -0,0 +1,644 @@
+//! Export of a time range to standard formats: SRT and WebVTT subtitles of
+//! the audio transcripts, Markdown daily notes of the screen activity and
+//! what was said, and JSONL of every record for downstream pipelines. Used
+//! by `screenpipe export` and `/export`.
+//!
+//! Each filter applies to the records it makes sense for, records of a kind
+//! it can't match are left out: with an app only screen text is exported,
+//! with a speaker only transcripts.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Json as JsonResponse, Response},
+};
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_db::{DatabaseManager, ExportTranscript, FrameEntry};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::deep_link::{frame_link, time_link};
+use crate::server::AppState;
+
+/// Rows fetched per query.
+const PAGE_SIZE: u32 = 1000;
+/// Cues without an end last this long, or until the next one starts.
+const DEFAULT_CUE_SECONDS: f64 = 5.0;
+/// Time between two frames beyond which the user is considered away.
+const MAX_FRAME_GAP: Duration = Duration::minutes(5);
+/// Characters of screen text quoted for each activity in daily notes.
+const TEXT_EXCERPT_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Srt,
+    Vtt,
+    Markdown,
+    Jsonl,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Srt => "application/x-subrip",
+            ExportFormat::Vtt => "text/vtt",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ExportFormat::Jsonl => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Srt => "srt",
+            ExportFormat::Vtt => "vtt",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Jsonl => "jsonl",
+        }
+    }
+
+    /// Subtitles only carry what was said.
+    fn includes_screen(&self) -> bool {
+        matches!(self, ExportFormat::Markdown | ExportFormat::Jsonl)
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportFormat::Srt => write!(f, "srt"),
+            ExportFormat::Vtt => write!(f, "vtt"),
+            ExportFormat::Markdown => write!(f, "markdown"),
+            ExportFormat::Jsonl => write!(f, "jsonl"),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "srt" => Ok(ExportFormat::Srt),
+            "vtt" | "webvtt" => Ok(ExportFormat::Vtt),
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            "jsonl" => Ok(ExportFormat::Jsonl),
+            _ => Err(anyhow::anyhow!(
+                "unknown export format: {}, expected srt, vtt, markdown or jsonl",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// Part of the app name, screen text only
+    pub app_name: Option<String>,
+    pub tag: Option<String>,
+    /// Name of the speaker, transcripts only
+    pub speaker: Option<String>,
+}
+
+/// A line of a JSONL export, with a `screenpipe://` link to its moment.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExportRecord<'a> {
+    Ocr {
+        #[serde(flatten)]
+        frame: &'a FrameEntry,
+        deep_link: String,
+    },
+    Audio {
+        #[serde(flatten)]
+        transcript: &'a ExportTranscript,
+        deep_link: String,
+    },
+}
+
+/// Parses the bound of a range, RFC 3339 or a local date. A date starts the
+/// range at its first moment and ends it at its last when `end` is set.
+pub fn parse_export_time(value: &str, end: bool) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        anyhow::anyhow!(
+            "invalid time {}, expected RFC 3339 or a date like 2025-04-14",
+            value
+        )
+    })?;
+    if end {
+        Ok(local_day_start(date.succ_opt().unwrap_or(date))? - Duration::milliseconds(1))
+    } else {
+        local_day_start(date)
+    }
+}
+
+/// First moment of a day in the local time zone.
+pub fn local_day_start(date: NaiveDate) -> Result<DateTime<Utc>> {
+    let midnight = Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight exists"))
+        .earliest()
+        .ok_or_else(|| anyhow::anyhow!("{} has no midnight in the local time zone", date))?;
+    Ok(midnight.with_timezone(&Utc))
+}
+
+async fn fetch_frames(
+    db: &DatabaseManager,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    filter: &ExportFilter,
+) -> Result<Vec<FrameEntry>> {
+    let mut frames = Vec::new();
+    loop {
+        let page = db
+            .get_export_frames(
+                start,
+                end,
+                filter.app_name.as_deref(),
+                filter.tag.as_deref(),
+                PAGE_SIZE,
+                frames.len() as u32,
+            )
+            .await?;
+        let done = page.len() < PAGE_SIZE as usize;
+        frames.extend(page);
+        if done {
+            return Ok(frames);
+        }
+    }
+}
+
+async fn fetch_transcripts(
+    db: &DatabaseManager,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    filter: &ExportFilter,
+) -> Result<Vec<ExportTranscript>> {
+    let mut transcripts = Vec::new();
+    loop {
+        let page = db
+            .get_export_transcripts(
+                start,
+                end,
+                filter.speaker.as_deref(),
+                filter.tag.as_deref(),
+                PAGE_SIZE,
+                transcripts.len() as u32,
+            )
+            .await?;
+        let done = page.len() < PAGE_SIZE as usize;
+        transcripts.extend(page);
+        if done {
+            return Ok(transcripts);
+        }
+    }
+}
+
+/// Screen text and transcripts between `start` and `end` matching
+/// `filter`, those `format` carries.
+async fn fetch(
+    db: &DatabaseManager,
+    format: ExportFormat,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    filter: &ExportFilter,
+) -> Result<(Vec<FrameEntry>, Vec<ExportTranscript>)> {
+    let frames = if format.includes_screen() && filter.speaker.is_none() {
+        fetch_frames(db, start, end, filter).await?
+    } else {
+        Vec::new()
+    };
+    let transcripts = if filter.app_name.is_none() {
+        fetch_transcripts(db, start, end, filter).await?
+    } else {
+        Vec::new()
+    };
+    Ok((frames, transcripts))
+}
+
+/// Everything recorded between `start` and `end` matching `filter`, daily
+/// notes following each other for markdown.
+pub async fn export(
+    db: &DatabaseManager,
+    format: ExportFormat,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    filter: &ExportFilter,
+) -> Result<String> {
+    let (frames, transcripts) = fetch(db, format, start, end, filter).await?;
+    Ok(match format {
+        ExportFormat::Srt => to_srt(&transcripts, start),
+        ExportFormat::Vtt => to_vtt(&transcripts, start),
+        ExportFormat::Markdown => daily_notes(&frames, &transcripts, None)
+            .values()
+            .map(DailyNote::render)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ExportFormat::Jsonl => to_jsonl(&frames, &transcripts)?,
+    })
+}
+
+/// The markdown export as a note per local day, see [`daily_notes`].
+pub async fn export_daily_notes(
+    db: &DatabaseManager,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    filter: &ExportFilter,
+    frame_links: Option<&str>,
+) -> Result<BTreeMap<NaiveDate, DailyNote>> {
+    let (frames, transcripts) = fetch(db, ExportFormat::Markdown, start, end, filter).await?;
+    Ok(daily_notes(&frames, &transcripts, frame_links))
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Start and end of each cue in seconds from `origin`.
+fn cue_times(transcripts: &[ExportTranscript], origin: DateTime<Utc>) -> Vec<(f64, f64)> {
+    let starts: Vec<f64> = transcripts
+        .iter()
+        .map(|t| {
+            let chunk_start = (t.timestamp - origin).num_milliseconds() as f64 / 1000.0;
+            (chunk_start + t.start_time.unwrap_or(0.0)).max(0.0)
+        })
+        .collect();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = match transcripts[i].end_time.zip(transcripts[i].start_time) {
+                Some((end, offset)) if end > offset => start + end - offset,
+                _ => {
+                    let next = starts.get(i + 1).copied().filter(|&next| next > start);
+                    next.map_or(start + DEFAULT_CUE_SECONDS, |next| {
+                        next.min(start + DEFAULT_CUE_SECONDS)
+                    })
+                }
+            };
+            (start, end)
+        })
+        .collect()
+}
+
+fn cue_timestamp(seconds: f64, separator: char) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+/// SRT subtitles of the transcripts, timed from `origin`.
+pub fn to_srt(transcripts: &[ExportTranscript], origin: DateTime<Utc>) -> String {
+    let mut srt = String::new();
+    for (i, (t, (start, end))) in transcripts
+        .iter()
+        .zip(cue_times(transcripts, origin))
+        .enumerate()
+    {
+        let text = single_line(&t.transcription);
+        let text = match &t.speaker_name {
+            Some(name) => format!("{}: {}", name, text),
+            None => text,
+        };
+        let _ = write!(
+            srt,
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            cue_timestamp(start, ','),
+            cue_timestamp(end, ','),
+            text
+        );
+    }
+    srt
+}
+
+fn escape_vtt(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// WebVTT subtitles of the transcripts, timed from `origin`. Speakers are
+/// set as cue voices.
+pub fn to_vtt(transcripts: &[ExportTranscript], origin: DateTime<Utc>) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+    for (t, (start, end)) in transcripts.iter().zip(cue_times(transcripts, origin)) {
+        let text = escape_vtt(&single_line(&t.transcription));
+        let text = match &t.speaker_name {
+            Some(name) => format!("<v {}>{}", escape_vtt(name), text),
+            None => text,
+        };
+        let _ = write!(
+            vtt,
+            "{} --> {}\n{}\n\n",
+            cue_timestamp(start, '.'),
+            cue_timestamp(end, '.'),
+            text
+        );
+    }
+    vtt
+}
+
+/// One JSON object per line, screen text and transcripts by time.
+pub fn to_jsonl(frames: &[FrameEntry], transcripts: &[ExportTranscript]) -> Result<String> {
+    let mut records: Vec<(DateTime<Utc>, ExportRecord)> = frames
+        .iter()
+        .map(|f| {
+            let record = ExportRecord::Ocr {
+                frame: f,
+                deep_link: frame_link(f.id),
+            };
+            (f.timestamp, record)
+        })
+        .chain(transcripts.iter().map(|t| {
+            let record = ExportRecord::Audio {
+                transcript: t,
+                deep_link: time_link(t.timestamp),
+            };
+            (t.timestamp, record)
+        }))
+        .collect();
+    // stable, records of the same time keep their order
+    records.sort_by_key(|(timestamp, _)| *timestamp);
+
+    let mut jsonl = String::new();
+    for (_, record) in records {
+        jsonl.push_str(&serde_json::to_string(&record)?);
+        jsonl.push('\n');
+    }
+    Ok(jsonl)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    match minutes {
+        0 => "<1m".to_string(),
+        m if m < 60 => format!("{}m", m),
+        m => format!("{}h {}m", m / 60, m % 60),
+    }
+}
+
+fn local_time(timestamp: DateTime<Utc>) -> String {
+    timestamp.with_timezone(&Local).format("%H:%M").to_string()
+}
+
+/// Consecutive frames of the same app and window.
+struct Activity<'a> {
+    frame_id: i64,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    app_name: &'a str,
+    window_name: Option<&'a str>,
+    text: Option<&'a str>,
+}
+
+fn activities<'a>(frames: &[&'a FrameEntry]) -> Vec<Activity<'a>> {
+    let mut activities: Vec<Activity> = Vec::new();
+    for frame in frames {
+        let app_name = frame.app_name.as_deref().unwrap_or("unknown app");
+        let window_name = frame.window_name.as_deref();
+        let text = frame.ocr_text.as_deref().filter(|t| !t.trim().is_empty());
+        match activities.last_mut() {
+            Some(last)
+                if last.app_name == app_name
+                    && last.window_name == window_name
+                    && frame.timestamp - last.end <= MAX_FRAME_GAP =>
+            {
+                last.end = frame.timestamp;
+                last.text = last.text.or(text);
+            }
+            _ => activities.push(Activity {
+                frame_id: frame.id,
+                start: frame.timestamp,
+                end: frame.timestamp,
+                app_name,
+                window_name,
+                text,
+            }),
+        }
+    }
+    activities
+}
+
+/// Time on screen of each app, counting the time to the next frame unless
+/// the user was away.
+fn app_time<'a>(frames: &[&'a FrameEntry]) -> Vec<(&'a str, Duration)> {
+    let mut totals: BTreeMap<&str, Duration> = BTreeMap::new();
+    for pair in frames.windows(2) {
+        let gap = pair[1].timestamp - pair[0].timestamp;
+        if gap > MAX_FRAME_GAP {
+            continue;
+        }
+        if let Some(app_name) = pair[0].app_name.as_deref() {
+            *totals.entry(app_name).or_insert_with(Duration::zero) += gap;
+        }
+    }
+    let mut totals: Vec<(&str, Duration)> = totals.into_iter().collect();
+    totals.sort_by_key(|&(_, duration)| std::cmp::Reverse(duration));
+    totals
+}
+
+/// A day of activity, each section a Markdown list, empty when there was
+/// nothing to list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyNote {
+    pub date: NaiveDate,
+    /// Time spent in each app
+    pub apps: String,
+    /// What was on screen, app by app
+    pub screen: String,
+    /// What was said
+    pub transcripts: String,
+}
+
+impl DailyNote {
+    /// The note with a heading per non empty section.
+    pub fn render(&self) -> String {
+        let mut note = format!("# {}\n", self.date.format("%Y-%m-%d"));
+        for (heading, section) in [
+            ("apps", &self.apps),
+            ("screen", &self.screen),
+            ("transcripts", &self.transcripts),
+        ] {
+            if !section.is_empty() {
+                let _ = write!(note, "\n## {}\n\n{}", heading, section);
+            }
+        }
+        note
+    }
+}
+
+/// A note per local day: time spent in each app, what was on screen and
+/// what was said. Times are `screenpipe://` links opening the app there.
+/// With `frame_links`, the base url of the api, each screen activity also
+/// links to the image of its first frame.
+pub fn daily_notes(
+    frames: &[FrameEntry],
+    transcripts: &[ExportTranscript],
+    frame_links: Option<&str>,
+) -> BTreeMap<NaiveDate, DailyNote> {
+    let mut days: BTreeMap<NaiveDate, (Vec<&FrameEntry>, Vec<&ExportTranscript>)> = BTreeMap::new();
+    for frame in frames {
+        let day = frame.timestamp.with_timezone(&Local).date_naive();
+        days.entry(day).or_default().0.push(frame);
+    }
+    for transcript in transcripts {
+        let day = transcript.timestamp.with_timezone(&Local).date_naive();
+        days.entry(day).or_default().1.push(transcript);
+    }
+
+    days.into_iter()
+        .map(|(date, (frames, transcripts))| {
+            let mut apps = String::new();
+            for (app_name, time) in app_time(&frames) {
+                let _ = writeln!(apps, "- **{}**: {}", app_name, format_duration(time));
+            }
+
+            let mut screen = String::new();
+            for activity in activities(&frames) {
+                let _ = write!(
+                    screen,
+                    "- [{}–{}]({}) **{}**",
+                    local_time(activity.start),
+                    local_time(activity.end),
+                    frame_link(activity.frame_id),
+                    activity.app_name
+                );
+                if let Some(window_name) = activity.window_name.filter(|w| !w.is_empty()) {
+                    let _ = write!(screen, " {}", window_name);
+                }
+                if let Some(base) = frame_links {
+                    let _ = write!(
+                        screen,
+                        " [frame]({}/frames/{})",
+                        base.trim_end_matches('/'),
+                        activity.frame_id
+                    );
+                }
+                screen.push('\n');
+                if let Some(text) = activity.text {
+                    let excerpt: String =
+                        single_line(text).chars().take(TEXT_EXCERPT_CHARS).collect();
+                    let _ = writeln!(screen, "  > {}", excerpt);
+                }
+            }
+
+            let mut said = String::new();
+            for t in transcripts {
+                let who = match &t.speaker_name {
+                    Some(name) => format!("**{}**", name),
+                    None => format!("_{}_", t.device_name),
+                };
+                let _ = writeln!(
+                    said,
+                    "- [{}]({}) {}: {}",
+                    local_time(t.timestamp),
+                    time_link(t.timestamp),
+                    who,
+                    single_line(&t.transcription)
+                );
+            }
+
+            let note = DailyNote {
+                date,
+                apps,
+                screen,
+                transcripts: said,
+            };
+            (date, note)
+        })
+        .collect()
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct ExportQuery {
+    /// srt, vtt, markdown or jsonl
+    format: String,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    /// Only screen text of apps whose name contains this
+    #[serde(default)]
+    app_name: Option<String>,
+    #[serde(default)]
+    tag: Option<String>,
+    /// Only transcripts of the speaker with this name
+    #[serde(default)]
+    speaker: Option<String>,
+}
+
+/// Exports a time range as SRT or WebVTT subtitles of the transcripts,
+/// Markdown daily notes or JSONL.
+#[oasgen]
+pub(crate) async fn export_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response<Body>, (StatusCode, JsonResponse<Value>)> {
+    let bad_request = |error: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": error, "success": false})),
+        )
+    };
+    let format: ExportFormat = query
+        .format
+        .parse()
+        .map_err(|e: anyhow::Error| bad_request(e.to_string()))?;
+    if query.end_time < query.start_time {
+        return Err(bad_request(
+            "end_time must not be before start_time".to_string(),
+        ));
+    }
+    let filter = ExportFilter {
+        app_name: query.app_name,
+        tag: query.tag,
+        speaker: query.speaker,
+    };
+
+    let body = export(&state.db, format, query.start_time, query.end_time, &filter)
+        .await
+        .map_err(|e| {
+            error!("failed to export: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to export: {}", e), "success": false}),
+                ),
+            )
+        })?;
+
+    Response::builder()
+        .header("content-type", format.content_type())
+        .header(
+            "content-disposition",
+            format!(
+                "attachment; filename=\"screenpipe-{}.{}\"",
+                query.start_time.format("%Y-%m-%d"),
+                format.extension()
+            ),
+        )
+        .body(Body::from(body))
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to create response: {}", e), "success": false}),
+                ),
+            )
+        })
+}
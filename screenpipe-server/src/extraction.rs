@@ -0,0 +1,348 @@
+//! Records extracted by the extraction rules from each session of an app by
+//! a local LLM, see [`screenpipe_core::extraction`]. A session is a run of
+//! frames of the app with no gap longer than [`SESSION_GAP`], extracted once
+//! it's over from what was said and what the app showed meanwhile. They are
+//! served at `/extractions`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::{DateTime, Local, Utc};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_core::extraction::{check_schema, ExtractionInput, Extractor};
+use screenpipe_db::{DatabaseManager, Extraction, ExtractionRule};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::export::{export_daily_notes, ExportFilter};
+use crate::server::{AppState, PaginationQuery};
+
+/// How often ended sessions are looked for.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Time without a frame of the app after which its session is over.
+pub const SESSION_GAP: Duration = Duration::from_secs(2 * 60);
+
+/// Runs of `times` with no gap longer than `gap`, as their first and last
+/// time. `times` are sorted.
+pub fn split_sessions(
+    times: &[DateTime<Utc>],
+    gap: Duration,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let gap = chrono::Duration::from_std(gap).unwrap_or(chrono::Duration::MAX);
+    let mut sessions: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+    for &time in times {
+        match sessions.last_mut() {
+            Some((_, end)) if time - *end <= gap => *end = time,
+            _ => sessions.push((time, time)),
+        }
+    }
+    sessions
+}
+
+fn lines(markdown: &str) -> Vec<String> {
+    markdown
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+async fn session_input(
+    db: &DatabaseManager,
+    rule: &ExtractionRule,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<ExtractionInput> {
+    let filter = ExportFilter {
+        app_name: Some(rule.app_name.clone()),
+        ..Default::default()
+    };
+    let notes = export_daily_notes(db, start, end, &filter, None).await?;
+    let mut said = Vec::new();
+    let mut shown = Vec::new();
+    for note in notes.values() {
+        said.extend(lines(&note.transcripts));
+        shown.extend(lines(&note.screen));
+    }
+
+    let (start, end) = (start.with_timezone(&Local), end.with_timezone(&Local));
+    Ok(ExtractionInput {
+        context: format!(
+            "a {} session on {} from {} to {}",
+            rule.app_name,
+            start.format("%A %-d %B %Y"),
+            start.format("%H:%M"),
+            end.format("%H:%M")
+        ),
+        sections: vec![
+            ("What was said".to_string(), said),
+            (format!("What {} showed", rule.app_name), shown),
+        ],
+    })
+}
+
+/// Extracts the record of a session of the app of `rule`. Returns `None`
+/// when nothing was recorded or the model's answers didn't match the
+/// schema.
+pub async fn extract_session(
+    db: &DatabaseManager,
+    extractor: &Extractor,
+    rule: &ExtractionRule,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Option<Extraction>> {
+    let input = session_input(db, rule, start, end).await?;
+    if input.is_empty() {
+        return Ok(None);
+    }
+
+    let Some(data) = extractor
+        .extract(&rule.instructions, &rule.schema, &input)
+        .await?
+    else {
+        warn!(
+            "extraction rule {} got no record matching its schema for the session from {}",
+            rule.name, start
+        );
+        return Ok(None);
+    };
+    let extraction = db
+        .insert_extraction(rule.id, start, end, &data, &extractor.model())
+        .await?;
+    Ok(Some(extraction))
+}
+
+/// Extracts the sessions of the app of `rule` that ended by `now` since it
+/// last ran. Returns how many records were stored.
+pub async fn process_rule(
+    db: &DatabaseManager,
+    extractor: &Extractor,
+    rule: &ExtractionRule,
+    now: DateTime<Utc>,
+) -> Result<usize> {
+    let since = rule.processed_until.unwrap_or(rule.created_at);
+    let times = db
+        .get_app_frame_times(&rule.app_name, rule.window_name.as_deref(), since, now)
+        .await?;
+    let gap = chrono::Duration::from_std(SESSION_GAP)?;
+    let mut written = 0;
+    for (start, end) in split_sessions(&times, SESSION_GAP) {
+        if now - end < gap {
+            // still going
+            return Ok(written);
+        }
+        if extract_session(db, extractor, rule, start, end)
+            .await?
+            .is_some()
+        {
+            written += 1;
+        }
+        db.mark_extraction_rule_processed(rule.id, end).await?;
+    }
+    // a frame recorded before now - gap would have ended a session
+    db.mark_extraction_rule_processed(rule.id, since.max(now - gap))
+        .await?;
+    Ok(written)
+}
+
+/// Extracts the sessions that ended by `now` for every rule. Returns how
+/// many records were stored.
+pub async fn extract_ended_sessions(
+    db: &DatabaseManager,
+    extractor: &Extractor,
+    now: DateTime<Utc>,
+) -> Result<usize> {
+    let mut written = 0;
+    for rule in db.list_extraction_rules().await? {
+        written += process_rule(db, extractor, &rule, now).await?;
+    }
+    Ok(written)
+}
+
+/// Extracts the sessions of the apps of the extraction rules as they end
+/// until a shutdown is signalled.
+pub async fn run_extractions(
+    db: Arc<DatabaseManager>,
+    extractor: Arc<Extractor>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!("extracting records with {}", extractor.model());
+
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                match extract_ended_sessions(&db, &extractor, Utc::now()).await {
+                    Ok(0) => {}
+                    Ok(written) => info!("extracted {} records", written),
+                    Err(e) => error!("failed to extract records: {}", e),
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping extractions");
+                break;
+            }
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct ExtractionRuleRequest {
+    name: String,
+    /// Part of the name of the app whose sessions records are extracted from
+    app_name: String,
+    /// Part of the window name, to only extract from some windows of the app
+    #[serde(default)]
+    window_name: Option<String>,
+    /// What to extract, e.g. "the attendees, the decisions and the action
+    /// items of the meeting"
+    #[serde(default)]
+    instructions: String,
+    /// JSON schema of the records, an object
+    schema: Value,
+}
+
+#[oasgen]
+pub(crate) async fn list_extraction_rules_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let rules = state.db.list_extraction_rules().await.map_err(|e| {
+        error!("failed to list extraction rules: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(
+                json!({"error": format!("failed to list extraction rules: {}", e), "success": false}),
+            ),
+        )
+    })?;
+    Ok(JsonResponse(json!({
+        "data": rules,
+        "success": true
+    })))
+}
+
+/// Adds a rule extracting a record matching a JSON schema from each session
+/// of an app once it ends.
+#[oasgen]
+pub(crate) async fn create_extraction_rule_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(request): JsonResponse<ExtractionRuleRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let bad_request = |error: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": error, "success": false})),
+        )
+    };
+    if request.name.trim().is_empty() {
+        return Err(bad_request("name must not be empty"));
+    }
+    if request.app_name.trim().is_empty() {
+        return Err(bad_request("app_name must not be empty"));
+    }
+    check_schema(&request.schema).map_err(|e| bad_request(&format!("invalid schema: {}", e)))?;
+
+    let rule = state
+        .db
+        .insert_extraction_rule(
+            request.name.trim(),
+            request.app_name.trim(),
+            request.window_name.as_deref(),
+            request.instructions.trim(),
+            &request.schema,
+        )
+        .await
+        .map_err(|e| {
+            error!("failed to add extraction rule: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to add extraction rule: {}", e), "success": false}),
+                ),
+            )
+        })?;
+    Ok(JsonResponse(json!({
+        "data": rule,
+        "success": true
+    })))
+}
+
+/// Deletes an extraction rule with the records it extracted.
+#[oasgen]
+pub(crate) async fn delete_extraction_rule_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.delete_extraction_rule(id).await {
+        Ok(true) => Ok(JsonResponse(json!({"success": true}))),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("no extraction rule {}", id), "success": false})),
+        )),
+        Err(e) => {
+            error!("failed to delete extraction rule {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to delete extraction rule: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct ExtractionsQuery {
+    #[serde(flatten)]
+    pagination: PaginationQuery,
+    #[serde(default)]
+    rule_id: Option<i64>,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+}
+
+/// Records extracted from sessions starting in a time range, the latest
+/// first.
+#[oasgen]
+pub(crate) async fn get_extractions_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ExtractionsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state
+        .db
+        .list_extractions(
+            query.rule_id,
+            query.start_time,
+            query.end_time,
+            query.pagination.limit,
+            query.pagination.offset,
+        )
+        .await
+    {
+        Ok(extractions) => Ok(JsonResponse(json!({
+            "data": extractions,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to read extractions: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to read extractions: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
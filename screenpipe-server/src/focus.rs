@@ -0,0 +1,245 @@
+//! Turns the windows the UI monitor reports into focus events, the spans an
+//! app window had the focus for, behind `/focus` and its streaks, switches
+//! and interruptions.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use oasgen::{oasgen, OaSchema};
+use screenpipe_core::idle::is_idle;
+use screenpipe_db::{DatabaseManager, UsageBucket};
+use screenpipe_events::subscribe_to_event;
+use screenpipe_vision::UIFrame;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+use crate::activity::{usage_analytics, UsageAnalyticsQuery};
+use crate::server::{AppState, PaginationQuery};
+
+/// Event the UI monitor reports the focused window with, when the focus
+/// moves to it and when its content changes.
+const UI_FRAME_EVENT: &str = "ui_frame";
+/// Longest a focus event may go without being extended before the focus
+/// counts as lost, the user was away or the monitor stopped.
+pub const FOCUS_MAX_GAP: Duration = Duration::from_secs(120);
+/// How often the focus event going on is extended while the user is around.
+const EXTEND_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Records focus events until a shutdown is signalled.
+pub async fn run_focus_tracker(db: Arc<DatabaseManager>, mut shutdown_rx: broadcast::Receiver<()>) {
+    let mut events = subscribe_to_event::<UIFrame>(UI_FRAME_EVENT);
+    let mut interval = tokio::time::interval(EXTEND_INTERVAL);
+    // window of the latest focus event and when it was last stored
+    let mut focused: Option<(String, String, Instant)> = None;
+
+    loop {
+        let (app, window) = tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else { break };
+                let frame = event.data;
+                let unchanged = focused.as_ref().is_some_and(|(app, window, at)| {
+                    *app == frame.app && *window == frame.window && at.elapsed() < EXTEND_INTERVAL
+                });
+                if frame.app.is_empty() || unchanged {
+                    continue;
+                }
+                (frame.app, frame.window)
+            }
+            _ = interval.tick() => {
+                // the monitor only reports changes, the window keeps the focus meanwhile
+                match &focused {
+                    Some((app, window, at)) if !is_idle() && at.elapsed() >= EXTEND_INTERVAL => {
+                        (app.clone(), window.clone())
+                    }
+                    _ => continue,
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping focus tracking");
+                break;
+            }
+        };
+
+        match db
+            .record_focus(&app, &window, Utc::now(), FOCUS_MAX_GAP)
+            .await
+        {
+            Ok(_) => focused = Some((app, window, Instant::now())),
+            Err(e) => error!("failed to store focus event: {}", e),
+        }
+    }
+}
+
+/// Longest a focus may last to count as an interruption, by default.
+const DEFAULT_INTERRUPTION_SECONDS: i64 = 120;
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct FocusEventsQuery {
+    #[serde(flatten)]
+    pagination: PaginationQuery,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    #[serde(default)]
+    app_name: Option<String>,
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct FocusStreaksQuery {
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    /// 10 by default
+    #[serde(default)]
+    limit: Option<u32>,
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct FocusInterruptionsQuery {
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    /// Longest time in an app that counts as an interruption, 120 by default
+    #[serde(default)]
+    max_seconds: Option<i64>,
+    /// 10 by default
+    #[serde(default)]
+    limit: Option<u32>,
+}
+
+fn focus_error(status: StatusCode, error: String) -> (StatusCode, JsonResponse<Value>) {
+    (
+        status,
+        JsonResponse(json!({"error": error, "success": false})),
+    )
+}
+
+fn check_focus_range(
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<(), (StatusCode, JsonResponse<Value>)> {
+    if end_time < start_time {
+        return Err(focus_error(
+            StatusCode::BAD_REQUEST,
+            "end_time must not be before start_time".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Spans app windows had the focus for in the time range, oldest first.
+#[oasgen]
+pub(crate) async fn list_focus_events_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FocusEventsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    check_focus_range(query.start_time, query.end_time)?;
+    match state
+        .db
+        .list_focus_events(
+            query.start_time,
+            query.end_time,
+            query.app_name.as_deref(),
+            query.pagination.limit,
+            query.pagination.offset,
+        )
+        .await
+    {
+        Ok(events) => Ok(JsonResponse(json!({
+            "data": events,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to list focus events: {}", e);
+            Err(focus_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to list focus events: {}", e),
+            ))
+        }
+    }
+}
+
+/// Longest times spent in one app without the focus leaving it.
+#[oasgen]
+pub(crate) async fn get_focus_streaks_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FocusStreaksQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    check_focus_range(query.start_time, query.end_time)?;
+    match state
+        .db
+        .get_focus_streaks(query.start_time, query.end_time, query.limit.unwrap_or(10))
+        .await
+    {
+        Ok(streaks) => Ok(JsonResponse(json!({
+            "data": streaks,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to compute focus streaks: {}", e);
+            Err(focus_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to compute focus streaks: {}", e),
+            ))
+        }
+    }
+}
+
+/// Times the focus moved straight to another app per bucket, hours by
+/// default.
+#[oasgen]
+pub(crate) async fn get_focus_switches_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<UsageAnalyticsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    usage_analytics(
+        &state,
+        "focus switches",
+        &query,
+        UsageBucket::Hour,
+        |start, end, bucket, utc_offset| {
+            state
+                .db
+                .get_focus_switch_buckets(start, end, bucket, utc_offset)
+        },
+    )
+    .await
+}
+
+/// Apps the focus briefly moved to before going back to the app it left,
+/// most interrupting first.
+#[oasgen]
+pub(crate) async fn get_focus_interruptions_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FocusInterruptionsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    check_focus_range(query.start_time, query.end_time)?;
+    match state
+        .db
+        .get_focus_interruptions(
+            query.start_time,
+            query.end_time,
+            query.max_seconds.unwrap_or(DEFAULT_INTERRUPTION_SECONDS),
+            query.limit.unwrap_or(10),
+        )
+        .await
+    {
+        Ok(apps) => Ok(JsonResponse(json!({
+            "data": apps,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to compute focus interruptions: {}", e);
+            Err(focus_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to compute focus interruptions: {}", e),
+            ))
+        }
+    }
+}
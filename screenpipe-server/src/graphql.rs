@@ -0,0 +1,285 @@
+//! GraphQL API over the database, for pipes that need frames with their
+//! transcripts and app context in a single request. Served at `/graphql`,
+//! opening it in a browser shows an explorer.
+
+use std::sync::Arc;
+
+use async_graphql::{
+    http::GraphiQLSource, Context, EmptyMutation, EmptySubscription, Object, Result, Schema,
+    SimpleObject,
+};
+use axum::{extract::State, response::Html, Json};
+use chrono::{DateTime, Duration, Utc};
+use screenpipe_db::{
+    AppUsage, DatabaseManager, FrameEntry, Speaker as DbSpeaker, TagContentType, TimelineTranscript,
+};
+
+use crate::server::AppState;
+
+pub type ScreenpipeSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 500;
+/// Nested lists multiply the queries, deeper queries are rejected.
+const MAX_DEPTH: usize = 6;
+/// Transcripts within this long of a frame are returned with it.
+const FRAME_TRANSCRIPT_WINDOW: Duration = Duration::seconds(30);
+
+pub fn build_schema(db: Arc<DatabaseManager>) -> ScreenpipeSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(db)
+        .limit_depth(MAX_DEPTH)
+        .finish()
+}
+
+/// Page of the explorer, querying `endpoint`.
+pub fn graphiql(endpoint: &str) -> String {
+    GraphiQLSource::build().endpoint(endpoint).finish()
+}
+
+fn limit(limit: Option<u32>) -> u32 {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+fn db<'a>(ctx: &Context<'a>) -> &'a Arc<DatabaseManager> {
+    ctx.data_unchecked::<Arc<DatabaseManager>>()
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Frames captured in the time range, newest first. `app_name` matches
+    /// part of the name, ignoring case.
+    async fn frames(
+        &self,
+        ctx: &Context<'_>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<String>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Vec<Frame>> {
+        let frames = db(ctx)
+            .get_frames(
+                start_time,
+                end_time,
+                app_name.as_deref(),
+                self::limit(limit),
+                offset.unwrap_or(0),
+            )
+            .await?;
+        Ok(frames.into_iter().map(Frame).collect())
+    }
+
+    /// Audio transcripts in the time range, oldest first.
+    async fn transcripts(
+        &self,
+        ctx: &Context<'_>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        speaker_id: Option<i64>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Vec<Transcript>> {
+        let transcripts = db(ctx)
+            .get_audio_transcriptions(
+                start_time,
+                end_time,
+                speaker_id,
+                self::limit(limit),
+                offset.unwrap_or(0),
+            )
+            .await?;
+        Ok(transcripts.into_iter().map(Transcript).collect())
+    }
+
+    /// Apps seen in the time range, most captured first.
+    async fn apps(
+        &self,
+        ctx: &Context<'_>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: Option<u32>,
+    ) -> Result<Vec<App>> {
+        let apps = db(ctx)
+            .get_app_usage(start_time, end_time, self::limit(limit))
+            .await?;
+        Ok(apps.into_iter().map(App::from).collect())
+    }
+
+    /// Speakers whose name starts with `name_prefix`, all of them when not set.
+    async fn speakers(
+        &self,
+        ctx: &Context<'_>,
+        name_prefix: Option<String>,
+    ) -> Result<Vec<Speaker>> {
+        let speakers = db(ctx)
+            .search_speakers(name_prefix.as_deref().unwrap_or_default())
+            .await?;
+        Ok(speakers.into_iter().map(Speaker).collect())
+    }
+}
+
+pub struct Frame(FrameEntry);
+
+#[Object]
+impl Frame {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn timestamp(&self) -> DateTime<Utc> {
+        self.0.timestamp
+    }
+
+    /// Monitor the frame was captured on
+    async fn device_name(&self) -> &str {
+        &self.0.device_name
+    }
+
+    async fn app_name(&self) -> Option<&str> {
+        self.0.app_name.as_deref()
+    }
+
+    async fn window_name(&self) -> Option<&str> {
+        self.0.window_name.as_deref()
+    }
+
+    async fn browser_url(&self) -> Option<&str> {
+        self.0.browser_url.as_deref()
+    }
+
+    async fn focused(&self) -> bool {
+        self.0.focused
+    }
+
+    /// Text read with OCR, empty while OCR is deferred
+    async fn text(&self) -> Option<&str> {
+        self.0.ocr_text.as_deref()
+    }
+
+    async fn tags(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+        Ok(db(ctx).get_tags(self.0.id, TagContentType::Vision).await?)
+    }
+
+    /// Transcripts recorded around the time of the frame
+    async fn transcripts(&self, ctx: &Context<'_>, limit: Option<u32>) -> Result<Vec<Transcript>> {
+        let transcripts = db(ctx)
+            .get_audio_transcriptions(
+                Some(self.0.timestamp - FRAME_TRANSCRIPT_WINDOW),
+                Some(self.0.timestamp + FRAME_TRANSCRIPT_WINDOW),
+                None,
+                self::limit(limit),
+                0,
+            )
+            .await?;
+        Ok(transcripts.into_iter().map(Transcript).collect())
+    }
+}
+
+pub struct Transcript(TimelineTranscript);
+
+#[Object]
+impl Transcript {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn timestamp(&self) -> DateTime<Utc> {
+        self.0.timestamp
+    }
+
+    async fn device_name(&self) -> &str {
+        &self.0.device_name
+    }
+
+    /// Whether it was recorded from a microphone rather than the speakers
+    async fn is_input(&self) -> bool {
+        self.0.is_input
+    }
+
+    async fn transcription(&self) -> &str {
+        &self.0.transcription
+    }
+
+    /// Seconds from the start of the audio chunk
+    async fn start_time(&self) -> Option<f64> {
+        self.0.start_time
+    }
+
+    async fn end_time(&self) -> Option<f64> {
+        self.0.end_time
+    }
+
+    async fn speaker(&self, ctx: &Context<'_>) -> Result<Option<Speaker>> {
+        let Some(speaker_id) = self.0.speaker_id else {
+            return Ok(None);
+        };
+        match db(ctx).get_speaker_by_id(speaker_id).await {
+            Ok(speaker) => Ok(Some(Speaker(speaker))),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn tags(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+        Ok(db(ctx).get_tags(self.0.id, TagContentType::Audio).await?)
+    }
+}
+
+pub struct Speaker(DbSpeaker);
+
+#[Object]
+impl Speaker {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn transcripts(
+        &self,
+        ctx: &Context<'_>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        limit: Option<u32>,
+    ) -> Result<Vec<Transcript>> {
+        let transcripts = db(ctx)
+            .get_audio_transcriptions(start_time, end_time, Some(self.0.id), self::limit(limit), 0)
+            .await?;
+        Ok(transcripts.into_iter().map(Transcript).collect())
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct App {
+    name: String,
+    frame_count: i64,
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+}
+
+impl From<AppUsage> for App {
+    fn from(usage: AppUsage) -> Self {
+        App {
+            name: usage.app_name,
+            frame_count: usage.frame_count,
+            first_seen: usage.first_seen,
+            last_seen: usage.last_seen,
+        }
+    }
+}
+
+pub(crate) async fn graphql_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    Json(state.graphql.execute(request).await)
+}
+
+pub(crate) async fn graphiql_handler() -> Html<String> {
+    Html(graphiql("/graphql"))
+}
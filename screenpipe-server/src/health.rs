@@ -0,0 +1,721 @@
+//! Per-subsystem detail of `/health`: each monitor and audio device being
+//! recorded, the OCR backlog, the database, the disk and the transcription
+//! model, with remediation codes apps and scripts can act on without parsing
+//! the messages.
+
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::{Json as JsonResponse, Response},
+};
+use chrono::{DateTime, TimeZone, Utc};
+use oasgen::{oasgen, OaSchema};
+use once_cell::sync::Lazy;
+use screenpipe_audio::audio_manager::AudioManager;
+use screenpipe_core::idle::{idle_reason, is_idle};
+use screenpipe_core::metrics::OCR_QUEUE_DEPTH;
+use screenpipe_core::power::power_status;
+use screenpipe_core::privacy::capture_blocked_by;
+use screenpipe_db::{DatabaseManager, WatchdogIncident};
+use screenpipe_events::{send_event, HealthChangedEvent, HEALTH_CHANGED_EVENT};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+use crate::ocr_queue::queue_status;
+use crate::server::AppState;
+use crate::storage::available_space;
+use crate::watchdog::recent_incidents;
+
+/// Nothing is reported missing this long after startup, capture and the
+/// model take a moment to start.
+pub const STARTUP_GRACE_PERIOD: Duration = Duration::from_secs(120);
+/// A monitor without a screenshot for this long is stale.
+pub const MONITOR_STALE_AFTER: Duration = Duration::from_secs(60);
+/// An audio device without data for this long is stale.
+pub const AUDIO_DEVICE_STALE_AFTER: Duration = Duration::from_secs(30);
+/// Frames waiting for OCR on a monitor from which OCR is falling behind, the
+/// queue drops frames at 30.
+pub const OCR_QUEUE_BACKLOG: u64 = 24;
+/// Windows waiting for deferred OCR from which it is falling behind.
+pub const DEFERRED_OCR_BACKLOG: usize = 5000;
+/// Free space on the disk of the data directory below which it's low.
+pub const LOW_DISK_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// How long the database and disk checks are reused for, `/health` is
+/// polled every second by the app.
+const SLOW_CHECKS_TTL: Duration = Duration::from_secs(10);
+
+static SLOW_CHECKS: Lazy<Mutex<Option<(Instant, DatabaseHealth, DiskHealth)>>> =
+    Lazy::new(|| Mutex::new(None));
+
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct SubsystemHealth {
+    /// Within the grace period after startup, nothing is reported missing
+    pub starting: bool,
+    pub monitors: Vec<MonitorHealth>,
+    pub audio_devices: Vec<AudioDeviceHealth>,
+    pub ocr: OcrHealth,
+    pub database: DatabaseHealth,
+    pub disk: DiskHealth,
+    /// `None` when audio is disabled
+    pub transcription_model_loaded: Option<bool>,
+    /// Loops the watchdog restarted in the last hour, newest first
+    pub restarts: Vec<WatchdogIncident>,
+}
+
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorHealth {
+    pub monitor_id: u32,
+    pub last_frame_timestamp: Option<DateTime<Utc>>,
+    /// "ok", "stale", or "idle" while the user is away
+    pub status: String,
+    /// Frames waiting for OCR
+    pub ocr_queue_depth: u64,
+}
+
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct AudioDeviceHealth {
+    pub device: String,
+    pub last_chunk_timestamp: Option<DateTime<Utc>>,
+    /// "ok" or "stale"
+    pub status: String,
+}
+
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct OcrHealth {
+    /// Frames waiting for OCR on every monitor
+    pub queue_depth: u64,
+    /// Windows waiting for deferred OCR
+    pub deferred_pending: usize,
+}
+
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseHealth {
+    pub writable: bool,
+    pub error: Option<String>,
+}
+
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct DiskHealth {
+    /// Free space on the disk of the data directory, `None` when unknown
+    pub free_bytes: Option<u64>,
+}
+
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemediationCode {
+    MonitorStale,
+    AudioDeviceStale,
+    OcrBacklog,
+    DatabaseNotWritable,
+    DiskLow,
+    ModelNotLoaded,
+}
+
+/// Something wrong with a subsystem and what to do about it.
+#[derive(OaSchema, Debug, Clone, Serialize, Deserialize)]
+pub struct Remediation {
+    pub code: RemediationCode,
+    /// e.g. "monitor 1", "audio device MacBook Pro Microphone (input)"
+    pub subsystem: String,
+    pub message: String,
+}
+
+/// Checks every subsystem being recorded.
+pub async fn subsystem_health(
+    db: &DatabaseManager,
+    audio_manager: &AudioManager,
+    screenpipe_dir: &Path,
+    audio_disabled: bool,
+    started_at: DateTime<Utc>,
+) -> SubsystemHealth {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let starting = (Utc::now() - started_at)
+        .to_std()
+        .map_or(true, |uptime| uptime < STARTUP_GRACE_PERIOD);
+    let stale =
+        |last: u64, after: Duration| !starting && now.saturating_sub(last) > after.as_secs();
+
+    let queue_depths = OCR_QUEUE_DEPTH.metrics();
+    let monitors: Vec<MonitorHealth> = screenpipe_vision::core::monitor_capture_times()
+        .into_iter()
+        .map(|(monitor_id, last_capture)| {
+            let label = monitor_id.to_string();
+            let ocr_queue_depth = queue_depths
+                .iter()
+                .find(|(values, _)| values.first() == Some(&label))
+                .map_or(0, |(_, depth)| depth.get() as u64);
+            let status = if is_idle() {
+                "idle"
+            } else if stale(last_capture, MONITOR_STALE_AFTER) {
+                "stale"
+            } else {
+                "ok"
+            };
+            MonitorHealth {
+                monitor_id,
+                last_frame_timestamp: timestamp(last_capture),
+                status: status.to_string(),
+                ocr_queue_depth,
+            }
+        })
+        .collect();
+
+    let mut audio_devices: Vec<AudioDeviceHealth> = audio_manager
+        .current_devices()
+        .iter()
+        .map(|device| {
+            let device = device.to_string();
+            let last_capture = screenpipe_audio::core::get_device_capture_time(&device);
+            let status = if stale(last_capture, AUDIO_DEVICE_STALE_AFTER) {
+                "stale"
+            } else {
+                "ok"
+            };
+            AudioDeviceHealth {
+                last_chunk_timestamp: timestamp(last_capture),
+                status: status.to_string(),
+                device,
+            }
+        })
+        .collect();
+    audio_devices.sort_by(|a, b| a.device.cmp(&b.device));
+
+    let ocr = OcrHealth {
+        queue_depth: monitors.iter().map(|monitor| monitor.ocr_queue_depth).sum(),
+        deferred_pending: queue_status(&screenpipe_dir.join("data").to_string_lossy()).pending,
+    };
+    let (database, disk) = slow_checks(db, screenpipe_dir).await;
+    let transcription_model_loaded = if audio_disabled {
+        None
+    } else {
+        Some(audio_manager.transcription_model_loaded().await)
+    };
+
+    SubsystemHealth {
+        starting,
+        monitors,
+        audio_devices,
+        ocr,
+        database,
+        disk,
+        transcription_model_loaded,
+        restarts: recent_incidents(),
+    }
+}
+
+async fn slow_checks(db: &DatabaseManager, screenpipe_dir: &Path) -> (DatabaseHealth, DiskHealth) {
+    if let Some((checked_at, database, disk)) =
+        &*SLOW_CHECKS.lock().unwrap_or_else(|e| e.into_inner())
+    {
+        if checked_at.elapsed() < SLOW_CHECKS_TTL {
+            return (database.clone(), disk.clone());
+        }
+    }
+
+    let database = match db.check_writable().await {
+        Ok(()) => DatabaseHealth {
+            writable: true,
+            error: None,
+        },
+        Err(e) => DatabaseHealth {
+            writable: false,
+            error: Some(e.to_string()),
+        },
+    };
+    let dir = screenpipe_dir.to_path_buf();
+    let disk = DiskHealth {
+        free_bytes: tokio::task::spawn_blocking(move || available_space(&dir))
+            .await
+            .ok()
+            .flatten(),
+    };
+    *SLOW_CHECKS.lock().unwrap_or_else(|e| e.into_inner()) =
+        Some((Instant::now(), database.clone(), disk.clone()));
+    (database, disk)
+}
+
+fn timestamp(secs: u64) -> Option<DateTime<Utc>> {
+    (secs > 0)
+        .then(|| Utc.timestamp_opt(secs as i64, 0).single())
+        .flatten()
+}
+
+/// What to do about the subsystems that aren't healthy, none when they all
+/// are.
+pub fn remediations(health: &SubsystemHealth) -> Vec<Remediation> {
+    let mut remediations = Vec::new();
+    let mut push = |code, subsystem: String, message: String| {
+        remediations.push(Remediation {
+            code,
+            subsystem,
+            message,
+        })
+    };
+
+    for monitor in &health.monitors {
+        let subsystem = format!("monitor {}", monitor.monitor_id);
+        if monitor.status == "stale" {
+            push(
+                RemediationCode::MonitorStale,
+                subsystem.clone(),
+                "no screenshot of this monitor for a while, check screen recording permissions and that it's still connected".to_string(),
+            );
+        }
+        if monitor.ocr_queue_depth >= OCR_QUEUE_BACKLOG {
+            push(
+                RemediationCode::OcrBacklog,
+                subsystem,
+                format!(
+                    "{} frames waiting for OCR, frames get dropped: lower the fps or use a faster OCR engine",
+                    monitor.ocr_queue_depth
+                ),
+            );
+        }
+    }
+    for device in &health.audio_devices {
+        if device.status == "stale" {
+            push(
+                RemediationCode::AudioDeviceStale,
+                format!("audio device {}", device.device),
+                "no audio from this device for a while, check microphone permissions and that it's still connected".to_string(),
+            );
+        }
+    }
+    if health.ocr.deferred_pending >= DEFERRED_OCR_BACKLOG {
+        push(
+            RemediationCode::OcrBacklog,
+            "deferred ocr".to_string(),
+            format!(
+                "{} windows waiting for deferred OCR, plug in the computer or switch OCR to realtime",
+                health.ocr.deferred_pending
+            ),
+        );
+    }
+    if !health.database.writable {
+        push(
+            RemediationCode::DatabaseNotWritable,
+            "database".to_string(),
+            format!(
+                "the database can't be written to ({}), check that the disk isn't full and no other screenpipe is running",
+                health.database.error.as_deref().unwrap_or("unknown error")
+            ),
+        );
+    }
+    if let Some(free_bytes) = health.disk.free_bytes.filter(|free| *free < LOW_DISK_BYTES) {
+        push(
+            RemediationCode::DiskLow,
+            "disk".to_string(),
+            format!(
+                "{} MB left on the disk of the data directory, free some space or set --max-storage-gb",
+                free_bytes / (1024 * 1024)
+            ),
+        );
+    }
+    if health.transcription_model_loaded == Some(false) && !health.starting {
+        push(
+            RemediationCode::ModelNotLoaded,
+            "transcription".to_string(),
+            "the transcription model isn't loaded, audio isn't transcribed: check the logs for download errors".to_string(),
+        );
+    }
+    remediations
+}
+
+#[derive(Serialize, OaSchema, Deserialize)]
+pub struct HealthCheckResponse {
+    pub status: String,
+    pub status_code: u16,
+    pub last_frame_timestamp: Option<DateTime<Utc>>,
+    pub last_audio_timestamp: Option<DateTime<Utc>>,
+    pub last_ui_timestamp: Option<DateTime<Utc>>,
+    pub frame_status: String,
+    pub audio_status: String,
+    pub ui_status: String,
+    pub message: String,
+    pub verbose_instructions: Option<String>,
+    pub device_status_details: Option<String>,
+    /// Audio devices being recorded
+    pub active_audio_devices: Vec<String>,
+    /// Privacy blocklist pattern that currently pauses capture
+    pub capture_paused_by: Option<String>,
+    /// Why capture is suspended because the user is away
+    pub idle_reason: Option<String>,
+    /// "normal", or "low_power" while saving battery
+    pub power_profile: String,
+    pub on_battery: bool,
+    pub battery_percent: Option<f32>,
+    pub noise_suppression: Vec<NoiseSuppressionStatus>,
+    /// Each monitor, audio device, the OCR backlog, database, disk and
+    /// transcription model
+    pub subsystems: SubsystemHealth,
+    /// What to do about the subsystems that aren't healthy
+    pub remediations: Vec<Remediation>,
+}
+
+#[derive(OaSchema, Serialize, Deserialize)]
+pub struct NoiseSuppressionStatus {
+    pub device: String,
+    pub enabled: bool,
+    /// Signal to noise ratio of the device's chunks before denoising, in dB
+    pub snr_before_db: Option<f32>,
+    /// Signal to noise ratio after denoising, in dB
+    pub snr_after_db: Option<f32>,
+    /// Chunks denoised since startup
+    pub chunks: u64,
+}
+
+fn get_verbose_instructions(unhealthy_systems: &[&str]) -> String {
+    let mut instructions = String::new();
+
+    if unhealthy_systems.contains(&"vision") {
+        instructions.push_str("Vision system is not working properly. Check if screen recording permissions are enabled.\n");
+    }
+
+    if unhealthy_systems.contains(&"audio") {
+        instructions.push_str("Audio system is not working properly. Check if microphone permissions are enabled and devices are connected.\n");
+    }
+
+    if unhealthy_systems.contains(&"ui") {
+        instructions.push_str("UI monitoring is not working properly. Check if accessibility permissions are enabled.\n");
+    }
+
+    if instructions.is_empty() {
+        instructions =
+            "If you're experiencing issues, please try contacting us on Discord.".to_string();
+    }
+
+    instructions
+}
+
+#[oasgen]
+pub async fn health_check(State(state): State<Arc<AppState>>) -> JsonResponse<HealthCheckResponse> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let app_uptime = (now as i64) - (state.app_start_time.timestamp());
+    let grace_period = 120; // 2 minutes in seconds
+
+    // Get the status of all devices
+    let audio_devices = state.audio_manager.current_devices();
+    let mut device_statuses = Vec::new();
+    let mut global_audio_active = false;
+    let mut most_recent_audio_timestamp = 0; // Track the most recent timestamp
+
+    // Check each device
+    for device in &audio_devices {
+        let device_name = device.to_string();
+        let last_capture = screenpipe_audio::core::get_device_capture_time(&device_name);
+
+        // Update the most recent timestamp
+        most_recent_audio_timestamp = most_recent_audio_timestamp.max(last_capture);
+
+        let device_active = if app_uptime < grace_period {
+            true // Consider active during grace period
+        } else {
+            now - last_capture < 5 // Consider active if captured in last 5 seconds
+        };
+
+        // Track if any device is active
+        if device_active {
+            global_audio_active = true;
+        }
+        debug!(target: "server", "device status: {} {}", device_name, device_active);
+
+        device_statuses.push((device_name, device_active, last_capture));
+    }
+
+    let mut active_audio_devices: Vec<String> =
+        audio_devices.iter().map(ToString::to_string).collect();
+    active_audio_devices.sort();
+
+    // Fallback to global timestamp if no devices are detected
+    if audio_devices.is_empty() {
+        let last_capture = screenpipe_audio::core::LAST_AUDIO_CAPTURE.load(Ordering::Relaxed);
+        global_audio_active = if app_uptime < grace_period {
+            true // Consider active during grace period
+        } else {
+            now - last_capture < 5 // Consider active if captured in last 5 seconds
+        };
+    }
+
+    let (last_frame, audio, last_ui) = match state.db.get_latest_timestamps().await {
+        Ok((frame, audio, ui)) => (frame, audio, ui),
+        Err(e) => {
+            error!("failed to get latest timestamps: {}", e);
+            (None, None, None)
+        }
+    };
+
+    let now = Utc::now();
+    let threshold = Duration::from_secs(1800); // 30 minutes
+
+    let frame_status = if state.vision_disabled {
+        "disabled"
+    } else if is_idle() {
+        "idle"
+    } else {
+        match last_frame {
+            Some(timestamp)
+                if now.signed_duration_since(timestamp)
+                    < chrono::Duration::from_std(threshold).unwrap() =>
+            {
+                "ok"
+            }
+            Some(_) => "stale",
+            None => "not_started",
+        }
+    };
+
+    let audio_status = if state.audio_disabled {
+        "disabled".to_string()
+    } else if global_audio_active {
+        "ok".to_string()
+    } else {
+        match audio {
+            Some(timestamp)
+                if now.signed_duration_since(timestamp)
+                    < chrono::Duration::from_std(threshold).unwrap() =>
+            {
+                "stale".to_string()
+            }
+            Some(_) => "stale".to_string(),
+            None => "not_started".to_string(),
+        }
+    };
+
+    // Format device statuses as a string for a more detailed view
+    let device_status_details = if !device_statuses.is_empty() {
+        let now_secs = now.timestamp() as u64;
+        let device_details: Vec<String> = device_statuses
+            .iter()
+            .map(|(name, active, last_capture)| {
+                format!(
+                    "{}: {} (last activity: {}s ago)",
+                    name,
+                    if *active { "active" } else { "inactive" },
+                    now_secs.saturating_sub(*last_capture)
+                )
+            })
+            .collect();
+
+        Some(device_details.join(", "))
+    } else {
+        None
+    };
+
+    let ui_status = if !state.ui_monitoring_enabled {
+        "disabled"
+    } else {
+        match last_ui {
+            Some(timestamp)
+                if now.signed_duration_since(timestamp)
+                    < chrono::Duration::from_std(threshold).unwrap() =>
+            {
+                "ok"
+            }
+            Some(_) => "stale",
+            None => "not_started",
+        }
+    };
+
+    let subsystems = subsystem_health(
+        &state.db,
+        &state.audio_manager,
+        &state.screenpipe_dir,
+        state.audio_disabled,
+        state.app_start_time,
+    )
+    .await;
+    let remediations = remediations(&subsystems);
+
+    // frames stop while the user is away, that's expected
+    let frame_ok = matches!(frame_status, "ok" | "disabled" | "idle");
+    let systems_ok = frame_ok
+        && (audio_status == "ok" || audio_status == "disabled")
+        && (ui_status == "ok" || ui_status == "disabled");
+    let healthy = systems_ok && remediations.is_empty();
+    let (overall_status, message, verbose_instructions, status_code) = if healthy {
+        (
+            "healthy",
+            "all systems are functioning normally.".to_string(),
+            None,
+            200,
+        )
+    } else if systems_ok {
+        // recording, but something is about to stop it or lose data
+        let subsystems: Vec<&str> = remediations
+            .iter()
+            .map(|remediation| remediation.subsystem.as_str())
+            .collect();
+        let instructions: Vec<&str> = remediations
+            .iter()
+            .map(|remediation| remediation.message.as_str())
+            .collect();
+        (
+            "degraded",
+            format!("some subsystems are not healthy: {}", subsystems.join(", ")),
+            Some(instructions.join("\n")),
+            503,
+        )
+    } else {
+        let mut unhealthy_systems = Vec::new();
+        if !frame_ok {
+            unhealthy_systems.push("vision");
+        }
+        if audio_status != "ok" && audio_status != "disabled" {
+            unhealthy_systems.push("audio");
+        }
+        if ui_status != "ok" && ui_status != "disabled" {
+            unhealthy_systems.push("ui");
+        }
+
+        let systems_str = unhealthy_systems.join(", ");
+        (
+            "degraded",
+            format!("some systems are not healthy: {}", systems_str),
+            Some(get_verbose_instructions(&unhealthy_systems)),
+            503,
+        )
+    };
+
+    let noise_suppression = noise_suppression_statuses(&state);
+    let power = power_status();
+    JsonResponse(HealthCheckResponse {
+        status: overall_status.to_string(),
+        status_code,
+        last_frame_timestamp: last_frame,
+        last_audio_timestamp: if most_recent_audio_timestamp > 0 {
+            Some(
+                Utc.timestamp_opt(most_recent_audio_timestamp as i64, 0)
+                    .unwrap(),
+            )
+        } else {
+            None
+        },
+        last_ui_timestamp: last_ui,
+        frame_status: frame_status.to_string(),
+        audio_status,
+        ui_status: ui_status.to_string(),
+        message,
+        verbose_instructions,
+        device_status_details,
+        active_audio_devices,
+        capture_paused_by: capture_blocked_by(),
+        idle_reason: idle_reason().map(|reason| reason.to_string()),
+        power_profile: power.profile.to_string(),
+        on_battery: power.on_battery,
+        battery_percent: power.battery_percent,
+        noise_suppression,
+        subsystems,
+        remediations,
+    })
+}
+
+/// Noise suppression of the devices recording, with the signal to noise
+/// ratios measured on the chunks denoised.
+pub(crate) fn noise_suppression_statuses(state: &AppState) -> Vec<NoiseSuppressionStatus> {
+    let config = state.audio_manager.noise_suppression();
+    state
+        .audio_manager
+        .current_devices()
+        .iter()
+        .map(|device| {
+            let device = device.to_string();
+            let snr = screenpipe_audio::core::denoise::DEVICE_SNR
+                .get(&device)
+                .map(|stats| *stats);
+            NoiseSuppressionStatus {
+                enabled: config.is_enabled(&device),
+                snr_before_db: snr.map(|stats| stats.before_db),
+                snr_after_db: snr.map(|stats| stats.after_db),
+                chunks: snr.map_or(0, |stats| stats.chunks),
+                device,
+            }
+        })
+        .collect()
+}
+
+const HEALTH_CHANGE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Checks the health every few seconds and sends a `health_changed` event
+/// when a status changed, for the event stream and webhooks.
+pub(crate) async fn publish_health_changes(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(HEALTH_CHANGE_INTERVAL);
+    // overall, frame, audio and ui status of the last check
+    let mut last_health: Option<[String; 4]> = None;
+
+    loop {
+        interval.tick().await;
+        let health = health_check(State(state.clone())).await.0;
+        let current = [
+            health.status,
+            health.frame_status,
+            health.audio_status,
+            health.ui_status,
+        ];
+        let Some(previous) = last_health.replace(current.clone()) else {
+            continue;
+        };
+        if previous == current {
+            continue;
+        }
+        let [status, frame_status, audio_status, ui_status] = current;
+        let [previous_status, ..] = previous;
+        if let Err(e) = send_event(
+            HEALTH_CHANGED_EVENT,
+            HealthChangedEvent {
+                status,
+                frame_status,
+                audio_status,
+                ui_status,
+                previous_status,
+            },
+        ) {
+            error!("failed to send health change: {}", e);
+        }
+    }
+}
+
+pub(crate) async fn ws_health_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    ws.on_upgrade(move |socket| handle_health_socket(socket, state))
+}
+
+async fn handle_health_socket(mut socket: WebSocket, state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(5));
+
+    loop {
+        tokio::select! {
+        _ = interval.tick() => {
+            let health_response = health_check(State(state.clone())).await;
+            let health_status = serde_json::to_string(&health_response.0).unwrap_or_default();
+            if let Err(e) = socket.send(Message::Text(health_status)).await {
+                error!("Failed to send health status: {}", e);
+                break;
+            }
+        }
+            result = socket.recv() => {
+                if result.is_none() {
+                    break;
+                }
+            }
+        }
+    }
+
+    debug!("WebSocket connection closed gracefully");
+}
@@ -0,0 +1,359 @@
+//! HLS playlists of recorded video, so players can stream a time range chunk
+//! by chunk instead of downloading whole mp4 files. Each video chunk is one
+//! segment, remuxed to MPEG-TS the first time it is asked for.
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Json as JsonResponse, Response},
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use lru::LruCache;
+use oasgen::{oasgen, OaSchema};
+use screenpipe_core::find_ffmpeg_path;
+use screenpipe_db::{DatabaseManager, VideoChunkSpan};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::process::Command;
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+use crate::server::AppState;
+use crate::{offload::readable_media, video_utils::get_video_duration};
+
+pub const PLAYLIST_CONTENT_TYPE: &str = "application/vnd.apple.mpegurl";
+pub const SEGMENT_CONTENT_TYPE: &str = "video/mp2t";
+/// Remuxed segments kept on disk.
+const SEGMENT_CACHE_CAPACITY: usize = 100;
+
+/// A video chunk in a playlist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HlsSegment {
+    pub chunk_id: i64,
+    pub start_time: DateTime<Utc>,
+    /// Seconds
+    pub duration: f64,
+}
+
+/// Device with the most frames in the chunks, the first one on a tie.
+pub fn busiest_device(chunks: &[VideoChunkSpan]) -> Option<String> {
+    let mut frames: Vec<(&str, i64)> = Vec::new();
+    for chunk in chunks {
+        match frames
+            .iter_mut()
+            .find(|(name, _)| *name == chunk.device_name)
+        {
+            Some((_, count)) => *count += chunk.frame_count,
+            None => frames.push((&chunk.device_name, chunk.frame_count)),
+        }
+    }
+    frames
+        .iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(name, _)| name.to_string())
+}
+
+/// VOD playlist of the segments. Segment URIs are relative to the playlist
+/// and carry `token` so players can fetch them from an authenticated server.
+pub fn build_playlist(segments: &[HlsSegment], token: Option<&str>) -> String {
+    let target_duration = segments
+        .iter()
+        .map(|segment| segment.duration.ceil() as u64)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let mut playlist = String::new();
+    playlist.push_str("#EXTM3U\n");
+    playlist.push_str("#EXT-X-VERSION:3\n");
+    let _ = writeln!(playlist, "#EXT-X-TARGETDURATION:{}", target_duration);
+    playlist.push_str("#EXT-X-MEDIA-SEQUENCE:0\n");
+    playlist.push_str("#EXT-X-PLAYLIST-TYPE:VOD\n");
+
+    for (i, segment) in segments.iter().enumerate() {
+        // chunks are encoded separately, so their timestamps start over
+        if i > 0 {
+            playlist.push_str("#EXT-X-DISCONTINUITY\n");
+        }
+        let _ = writeln!(
+            playlist,
+            "#EXT-X-PROGRAM-DATE-TIME:{}",
+            segment
+                .start_time
+                .to_rfc3339_opts(SecondsFormat::Millis, true)
+        );
+        let _ = writeln!(playlist, "#EXTINF:{:.3},", segment.duration);
+        match token {
+            Some(token) => {
+                let _ = writeln!(
+                    playlist,
+                    "hls/segments/{}.ts?token={}",
+                    segment.chunk_id, token
+                );
+            }
+            None => {
+                let _ = writeln!(playlist, "hls/segments/{}.ts", segment.chunk_id);
+            }
+        }
+    }
+
+    playlist.push_str("#EXT-X-ENDLIST\n");
+    playlist
+}
+
+/// Segment durations and remuxed segments of video chunks.
+pub struct HlsStreamer {
+    dir: PathBuf,
+    durations: Mutex<HashMap<i64, f64>>,
+    segments: Mutex<LruCache<i64, PathBuf>>,
+}
+
+impl Default for HlsStreamer {
+    fn default() -> Self {
+        HlsStreamer::new(std::env::temp_dir().join("screenpipe_hls"))
+    }
+}
+
+impl HlsStreamer {
+    pub fn new(dir: PathBuf) -> Self {
+        HlsStreamer {
+            dir,
+            durations: Mutex::new(HashMap::new()),
+            segments: Mutex::new(LruCache::new(
+                NonZeroUsize::new(SEGMENT_CACHE_CAPACITY).unwrap(),
+            )),
+        }
+    }
+
+    /// Segments of the chunks. Chunks that can't be probed, like the one
+    /// still being recorded, are left out.
+    pub async fn segments(&self, chunks: &[VideoChunkSpan]) -> Vec<HlsSegment> {
+        let mut segments = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            match self.duration(chunk).await {
+                Ok(duration) if duration > 0.0 => segments.push(HlsSegment {
+                    chunk_id: chunk.id,
+                    start_time: chunk.start_time,
+                    duration,
+                }),
+                Ok(_) => debug!("skipping empty video chunk {}", chunk.id),
+                Err(e) => debug!("skipping video chunk {}: {}", chunk.id, e),
+            }
+        }
+        segments
+    }
+
+    async fn duration(&self, chunk: &VideoChunkSpan) -> Result<f64> {
+        if let Some(duration) = self.durations.lock().unwrap().get(&chunk.id) {
+            return Ok(*duration);
+        }
+        let duration = get_video_duration(&chunk.file_path).await?;
+        if duration > 0.0 {
+            self.durations.lock().unwrap().insert(chunk.id, duration);
+        }
+        Ok(duration)
+    }
+
+    /// MPEG-TS file of the video chunk, `None` if there is no such chunk.
+    pub async fn segment(&self, db: &DatabaseManager, chunk_id: i64) -> Result<Option<PathBuf>> {
+        let cached = self.segments.lock().unwrap().get(&chunk_id).cloned();
+        if let Some(path) = cached {
+            if path.exists() {
+                return Ok(Some(path));
+            }
+        }
+
+        let Some(chunk) = db.get_video_chunk_span(chunk_id).await? else {
+            return Ok(None);
+        };
+        let path = self.remux(&chunk).await?;
+
+        let evicted = self.segments.lock().unwrap().push(chunk_id, path.clone());
+        if let Some((evicted_id, evicted_path)) = evicted {
+            if evicted_id != chunk_id {
+                let _ = tokio::fs::remove_file(evicted_path).await;
+            }
+        }
+        Ok(Some(path))
+    }
+
+    async fn remux(&self, chunk: &VideoChunkSpan) -> Result<PathBuf> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        // encrypted recordings are decrypted to a temporary file for ffmpeg
+        let media = readable_media(&chunk.file_path).await?;
+        let output_path = self.dir.join(format!("{}.ts", chunk.id));
+        // written aside first so a concurrent request never serves half a file
+        let partial_path = self
+            .dir
+            .join(format!("{}_{}.ts.part", chunk.id, Uuid::new_v4()));
+
+        let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
+        let output = Command::new(ffmpeg_path)
+            .args(["-v", "error", "-y", "-i"])
+            .arg(media.path())
+            .args(["-map", "0:v:0", "-c", "copy", "-f", "mpegts"])
+            .arg(&partial_path)
+            .output()
+            .await?;
+        if !output.status.success() {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            let error = String::from_utf8_lossy(&output.stderr);
+            warn!("failed to remux video chunk {}: {}", chunk.id, error);
+            return Err(anyhow::anyhow!("ffmpeg failed: {}", error));
+        }
+
+        tokio::fs::rename(&partial_path, &output_path).await?;
+        Ok(output_path)
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub struct HlsPlaylistQuery {
+    #[serde(alias = "start_time")]
+    start: DateTime<Utc>,
+    #[serde(alias = "end_time")]
+    end: DateTime<Utc>,
+    /// Monitor to play, the one with the most frames in the range by default
+    device_name: Option<String>,
+    /// API token, passed on to the segment URIs
+    token: Option<String>,
+}
+
+/// HLS playlist of the video recorded in a time range, one segment per video
+/// chunk, for native video players to scrub the timeline.
+#[oasgen]
+pub async fn get_hls_playlist_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HlsPlaylistQuery>,
+) -> Result<Response<Body>, (StatusCode, JsonResponse<Value>)> {
+    if query.end <= query.start {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "end must be after start", "success": false})),
+        ));
+    }
+
+    let chunks = state
+        .db
+        .get_video_chunk_spans(query.start, query.end, query.device_name.as_deref())
+        .await
+        .map_err(|e| {
+            error!("failed to get video chunks for hls playlist: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to get video chunks: {}", e),
+                    "success": false
+                })),
+            )
+        })?;
+    let chunks = match query.device_name {
+        Some(_) => chunks,
+        None => {
+            let device_name = busiest_device(&chunks);
+            chunks
+                .into_iter()
+                .filter(|chunk| Some(&chunk.device_name) == device_name.as_ref())
+                .collect()
+        }
+    };
+
+    let segments = state.hls.segments(&chunks).await;
+    if segments.is_empty() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": "no video in this time range", "success": false})),
+        ));
+    }
+
+    Response::builder()
+        .header("content-type", PLAYLIST_CONTENT_TYPE)
+        .header("cache-control", "no-cache")
+        .body(Body::from(build_playlist(
+            &segments,
+            query.token.as_deref(),
+        )))
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to create response: {}", e), "success": false}),
+                ),
+            )
+        })
+}
+
+/// Video chunk of an HLS playlist remuxed to MPEG-TS.
+#[oasgen]
+pub async fn get_hls_segment_handler(
+    State(state): State<Arc<AppState>>,
+    Path(segment): Path<String>,
+) -> Result<Response<Body>, (StatusCode, JsonResponse<Value>)> {
+    let Some(chunk_id) = segment
+        .strip_suffix(".ts")
+        .and_then(|id| id.parse::<i64>().ok())
+    else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": "segment not found", "success": false})),
+        ));
+    };
+
+    let path = match state.hls.segment(&state.db, chunk_id).await {
+        Ok(Some(path)) => path,
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                JsonResponse(json!({
+                    "error": "video chunk not found",
+                    "chunk_id": chunk_id,
+                    "success": false
+                })),
+            ))
+        }
+        Err(e) => {
+            error!("failed to remux video chunk {}: {}", chunk_id, e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to remux video chunk: {}", e),
+                    "chunk_id": chunk_id,
+                    "success": false
+                })),
+            ));
+        }
+    };
+    let data = tokio::fs::read(&path).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({
+                "error": format!("failed to read segment: {}", e),
+                "success": false
+            })),
+        )
+    })?;
+
+    Response::builder()
+        .header("content-type", SEGMENT_CONTENT_TYPE)
+        .header("cache-control", "public, max-age=604800")
+        .body(Body::from(data))
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to create response: {}", e), "success": false}),
+                ),
+            )
+        })
+}
@@ -0,0 +1,144 @@
+//! Suspends capture while the user is away and records the gap, so that a
+//! locked screen isn't recorded for hours and the timeline shows why nothing
+//! was captured. Capture resumes on the first check after activity.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::{DateTime, Utc};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_core::idle::{detect_idle, set_idle, IdleReason};
+use screenpipe_db::DatabaseManager;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{debug, error, info};
+
+use crate::server::{AppState, PaginationQuery};
+
+/// How often the user's activity is checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Checks whether the user is away until a shutdown is signalled.
+/// `idle_timeout` is how long without input counts as away.
+pub async fn run_idle_monitor(
+    db: Arc<DatabaseManager>,
+    idle_timeout: Duration,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    // reason and row id of the current gap
+    let mut gap: Option<(IdleReason, Option<i64>)> = None;
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let detected = tokio::task::spawn_blocking(move || detect_idle(idle_timeout)).await;
+                let reason = match detected {
+                    Ok(reason) => reason,
+                    Err(e) => {
+                        error!("failed to check for idle: {}", e);
+                        continue;
+                    }
+                };
+                if reason == gap.as_ref().map(|(reason, _)| *reason) {
+                    continue;
+                }
+
+                set_idle(reason);
+                match (gap.take(), reason) {
+                    (None, Some(reason)) => {
+                        info!("user is away ({}), suspending capture", reason);
+                        gap = Some((reason, start_gap(&db, reason).await));
+                    }
+                    (Some((_, id)), None) => {
+                        info!("activity resumed, resuming capture");
+                        end_gap(&db, id).await;
+                    }
+                    // the screen locks after a while without input, the gap goes on
+                    (Some((_, id)), Some(reason)) => {
+                        debug!("still away, now {}", reason);
+                        gap = Some((reason, id));
+                    }
+                    (None, None) => {}
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping idle detection");
+                break;
+            }
+        }
+    }
+
+    if let Some((_, id)) = gap {
+        set_idle(None);
+        end_gap(&db, id).await;
+    }
+}
+
+async fn start_gap(db: &DatabaseManager, reason: IdleReason) -> Option<i64> {
+    match db.insert_idle_gap(Utc::now(), &reason.to_string()).await {
+        Ok(id) => Some(id),
+        Err(e) => {
+            error!("failed to store idle gap: {}", e);
+            None
+        }
+    }
+}
+
+async fn end_gap(db: &DatabaseManager, id: Option<i64>) {
+    let Some(id) = id else {
+        return;
+    };
+    if let Err(e) = db.end_idle_gap(id, Utc::now()).await {
+        error!("failed to end idle gap: {}", e);
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct IdleGapsQuery {
+    #[serde(flatten)]
+    pagination: PaginationQuery,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+}
+
+/// Times the user was away and capture was suspended, oldest first.
+#[oasgen]
+pub(crate) async fn get_idle_gaps_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<IdleGapsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state
+        .db
+        .get_idle_gaps(
+            query.start_time,
+            query.end_time,
+            query.pagination.limit,
+            query.pagination.offset,
+        )
+        .await
+    {
+        Ok(gaps) => Ok(JsonResponse(json!({
+            "data": gaps,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to read idle gaps: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to read idle gaps: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
@@ -0,0 +1,414 @@
+//! `screenpipe import`: history recorded by other lifelogging tools, for
+//! those moving to screenpipe. Their recordings can't be brought along, what
+//! they extracted from them is:
+//!
+//! - Rewind: the frames of its database with their text and window, and its
+//!   transcripts, stored under a chunk without media
+//! - ActivityWatch: the window events of an export, stored as focus events
+//!   and UI monitoring entries
+//!
+//! Importing the same history twice skips what's already imported.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use oasgen::OaSchema;
+use screenpipe_db::{DatabaseManager, ImportedFrame, ImportedTranscript, ImportedWindowEvent};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, Row, SqliteConnection};
+use tracing::{info, warn};
+
+/// Device, chunk and engine name of what's imported from Rewind.
+pub const REWIND_DEVICE_NAME: &str = "rewind";
+/// Rows read and stored at a time.
+const IMPORT_BATCH_SIZE: usize = 1000;
+/// ActivityWatch bucket type of the window watcher.
+const ACTIVITYWATCH_WINDOW_BUCKET: &str = "currentwindow";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportSource {
+    /// A Rewind database, decrypted or with its key
+    Rewind,
+    /// An ActivityWatch export of its buckets as JSON
+    ActivityWatch,
+}
+
+#[derive(OaSchema, Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub frames: u64,
+    pub transcripts: u64,
+    pub window_events: u64,
+    /// What was found but isn't imported, and why
+    pub skipped: Vec<String>,
+}
+
+/// Imports the history at `path`. `key` opens an encrypted Rewind database,
+/// which needs a build with the `sqlcipher` feature.
+pub async fn import(
+    db: &DatabaseManager,
+    source: ImportSource,
+    path: &Path,
+    key: Option<&str>,
+) -> Result<ImportReport> {
+    let report = match source {
+        ImportSource::Rewind => import_rewind(db, path, key).await?,
+        ImportSource::ActivityWatch => import_activitywatch(db, path).await?,
+    };
+    info!(
+        "imported {} frames, {} transcripts and {} window events from {}",
+        report.frames,
+        report.transcripts,
+        report.window_events,
+        path.display()
+    );
+    Ok(report)
+}
+
+#[derive(Deserialize)]
+struct ActivityWatchExport {
+    buckets: HashMap<String, ActivityWatchBucket>,
+}
+
+#[derive(Deserialize)]
+struct ActivityWatchBucket {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    events: Vec<ActivityWatchEvent>,
+}
+
+#[derive(Deserialize)]
+struct ActivityWatchEvent {
+    timestamp: DateTime<Utc>,
+    /// Seconds
+    #[serde(default)]
+    duration: f64,
+    #[serde(default)]
+    data: HashMap<String, serde_json::Value>,
+}
+
+impl ActivityWatchEvent {
+    fn data(&self, key: &str) -> Option<String> {
+        self.data
+            .get(key)
+            .and_then(|value| value.as_str())
+            .map(str::to_string)
+    }
+
+    fn window_event(&self) -> Option<ImportedWindowEvent> {
+        let app_name = self.data("app").filter(|app| !app.is_empty())?;
+        let duration = chrono::Duration::milliseconds((self.duration.max(0.0) * 1000.0) as i64);
+        Some(ImportedWindowEvent {
+            app_name,
+            window_name: self.data("title").unwrap_or_default(),
+            start_time: self.timestamp,
+            end_time: self.timestamp + duration,
+        })
+    }
+}
+
+/// Imports the window watcher buckets of an ActivityWatch export, as made
+/// from its raw data page or `/api/0/export`. Other buckets are skipped.
+pub async fn import_activitywatch(db: &DatabaseManager, path: &Path) -> Result<ImportReport> {
+    let export: ActivityWatchExport = serde_json::from_slice(&tokio::fs::read(path).await?)
+        .with_context(|| format!("{} isn't an ActivityWatch export", path.display()))?;
+
+    let mut report = ImportReport::default();
+    let buckets: BTreeMap<_, _> = export.buckets.into_iter().collect();
+    for (id, bucket) in buckets {
+        if bucket.kind != ACTIVITYWATCH_WINDOW_BUCKET {
+            report.skipped.push(format!(
+                "bucket {}: {} events of type {} aren't imported",
+                id,
+                bucket.events.len(),
+                bucket.kind
+            ));
+            continue;
+        }
+        let mut events: Vec<_> = bucket
+            .events
+            .iter()
+            .filter_map(ActivityWatchEvent::window_event)
+            .collect();
+        let without_app = bucket.events.len() - events.len();
+        if without_app > 0 {
+            report.skipped.push(format!(
+                "bucket {}: {} events without an app",
+                id, without_app
+            ));
+        }
+        events.sort_by_key(|event| event.start_time);
+        for batch in events.chunks(IMPORT_BATCH_SIZE) {
+            report.window_events += db.insert_imported_window_events(batch).await?;
+        }
+    }
+    Ok(report)
+}
+
+/// Imports the frames and transcripts of a Rewind database. Its schema isn't
+/// documented, so the tables and columns used are looked up first and what's
+/// missing is skipped.
+pub async fn import_rewind(
+    db: &DatabaseManager,
+    path: &Path,
+    key: Option<&str>,
+) -> Result<ImportReport> {
+    let mut options = SqliteConnectOptions::new().filename(path).read_only(true);
+    if let Some(key) = key {
+        options = options
+            .pragma("key", format!("'{}'", key.replace('\'', "''")))
+            .pragma("cipher_compatibility", "4");
+    }
+    let mut conn = options
+        .connect()
+        .await
+        .with_context(|| format!("failed to open the Rewind database {}", path.display()))?;
+    sqlx::query("SELECT COUNT(*) FROM sqlite_master")
+        .execute(&mut conn)
+        .await
+        .with_context(|| {
+            format!(
+                "{} can't be read, decrypt it first or pass its key",
+                path.display()
+            )
+        })?;
+
+    let mut report = ImportReport::default();
+    let result = import_rewind_frames(db, &mut conn, &mut report).await;
+    let result = match result {
+        Ok(()) => import_rewind_transcripts(db, &mut conn, &mut report).await,
+        Err(e) => Err(e),
+    };
+    conn.close().await?;
+    result.map(|_| report)
+}
+
+async fn columns(conn: &mut SqliteConnection, table: &str) -> Result<HashSet<String>> {
+    let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?1)")
+        .bind(table)
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(columns.into_iter().collect())
+}
+
+async fn import_rewind_frames(
+    db: &DatabaseManager,
+    conn: &mut SqliteConnection,
+    report: &mut ImportReport,
+) -> Result<()> {
+    let frame = columns(conn, "frame").await?;
+    if !["id", "createdAt", "segmentId"]
+        .iter()
+        .all(|column| frame.contains(*column))
+    {
+        report
+            .skipped
+            .push("frames: no frame table with createdAt and segmentId".to_string());
+        return Ok(());
+    }
+    // frames keep their time without the segment telling their window
+    let segment = columns(conn, "segment").await?;
+    let joined = segment.contains("id");
+    let segment_column = |column: &str| {
+        if joined && segment.contains(column) {
+            format!("segment.{}", column)
+        } else {
+            "NULL".to_string()
+        }
+    };
+    let content = columns(conn, "searchRanking_content").await?;
+    let doc_segment = columns(conn, "doc_segment").await?;
+    let text = if content.contains("id")
+        && content.contains("c0")
+        && doc_segment.contains("docid")
+        && doc_segment.contains("frameId")
+    {
+        r#"(SELECT group_concat(searchRanking_content.c0, ' ')
+            FROM doc_segment
+            JOIN searchRanking_content ON searchRanking_content.id = doc_segment.docid
+            WHERE doc_segment.frameId = frame.id)"#
+    } else {
+        report
+            .skipped
+            .push("frame text: no searchRanking_content and doc_segment tables".to_string());
+        "NULL"
+    };
+    let sql = format!(
+        r#"SELECT
+            frame.id,
+            CAST(frame.createdAt AS TEXT) AS created_at,
+            {} AS bundle_id,
+            {} AS window_name,
+            {} AS browser_url,
+            {} AS text
+        FROM frame
+        {}
+        WHERE frame.id > ?1
+        ORDER BY frame.id
+        LIMIT ?2"#,
+        segment_column("bundleID"),
+        segment_column("windowName"),
+        segment_column("browserUrl"),
+        text,
+        if joined {
+            "LEFT JOIN segment ON segment.id = frame.segmentId"
+        } else {
+            ""
+        },
+    );
+
+    let mut last_id = 0i64;
+    let mut unparsed = 0;
+    loop {
+        let rows = sqlx::query(&sql)
+            .bind(last_id)
+            .bind(IMPORT_BATCH_SIZE as i64)
+            .fetch_all(&mut *conn)
+            .await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        last_id = last.try_get("id")?;
+
+        let mut frames = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let created_at: Option<String> = row.try_get("created_at")?;
+            let Some(timestamp) = created_at.as_deref().and_then(parse_rewind_timestamp) else {
+                unparsed += 1;
+                continue;
+            };
+            let bundle_id: Option<String> = row.try_get("bundle_id")?;
+            frames.push(ImportedFrame {
+                timestamp,
+                app_name: bundle_id.as_deref().map(app_name_of_bundle),
+                window_name: row.try_get("window_name")?,
+                browser_url: row.try_get("browser_url")?,
+                text: row
+                    .try_get::<Option<String>, _>("text")?
+                    .unwrap_or_default(),
+            });
+        }
+        report.frames += db
+            .insert_imported_frames(
+                REWIND_DEVICE_NAME,
+                REWIND_DEVICE_NAME,
+                REWIND_DEVICE_NAME,
+                &frames,
+            )
+            .await?;
+    }
+    if unparsed > 0 {
+        warn!("skipped {} Rewind frames without a valid time", unparsed);
+        report
+            .skipped
+            .push(format!("frames: {} without a valid time", unparsed));
+    }
+    Ok(())
+}
+
+async fn import_rewind_transcripts(
+    db: &DatabaseManager,
+    conn: &mut SqliteConnection,
+    report: &mut ImportReport,
+) -> Result<()> {
+    let words = columns(conn, "transcript_word").await?;
+    let segment = columns(conn, "segment").await?;
+    if !(words.contains("segmentId")
+        && words.contains("word")
+        && segment.contains("id")
+        && segment.contains("startDate"))
+    {
+        report.skipped.push(
+            "transcripts: no transcript_word table with segmentId and word, or segment without startDate"
+                .to_string(),
+        );
+        return Ok(());
+    }
+    let order = if words.contains("timeOffset") {
+        "segmentId, timeOffset, rowid"
+    } else {
+        "segmentId, rowid"
+    };
+    let sql = format!(
+        r#"SELECT
+            words.segmentId AS segment_id,
+            CAST(segment.startDate AS TEXT) AS start_date,
+            group_concat(words.word, ' ') AS text
+        FROM (SELECT segmentId, word FROM transcript_word ORDER BY {}) AS words
+        JOIN segment ON segment.id = words.segmentId
+        WHERE words.segmentId > ?1
+        GROUP BY words.segmentId
+        ORDER BY words.segmentId
+        LIMIT ?2"#,
+        order
+    );
+
+    let mut last_segment = i64::MIN;
+    loop {
+        let rows = sqlx::query(&sql)
+            .bind(last_segment)
+            .bind(IMPORT_BATCH_SIZE as i64)
+            .fetch_all(&mut *conn)
+            .await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        last_segment = last.try_get("segment_id")?;
+
+        let mut transcripts = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let start_date: Option<String> = row.try_get("start_date")?;
+            let text: Option<String> = row.try_get("text")?;
+            if let (Some(timestamp), Some(text)) = (
+                start_date.as_deref().and_then(parse_rewind_timestamp),
+                text.filter(|text| !text.trim().is_empty()),
+            ) {
+                transcripts.push(ImportedTranscript { timestamp, text });
+            }
+        }
+        report.transcripts += db
+            .insert_imported_transcripts(
+                REWIND_DEVICE_NAME,
+                REWIND_DEVICE_NAME,
+                REWIND_DEVICE_NAME,
+                &transcripts,
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Times in a Rewind database: ISO 8601 in UTC, with or without an offset,
+/// or seconds or milliseconds since the epoch.
+pub fn parse_rewind_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+        if let Ok(timestamp) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(timestamp.and_utc());
+        }
+    }
+    let number: f64 = value.parse().ok()?;
+    let millis = if number.abs() >= 1e11 {
+        number
+    } else {
+        number * 1000.0
+    };
+    Utc.timestamp_millis_opt(millis as i64).single()
+}
+
+/// App name of a macOS bundle id, like `Safari` for `com.apple.Safari`.
+pub fn app_name_of_bundle(bundle_id: &str) -> String {
+    bundle_id
+        .rsplit('.')
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or(bundle_id)
+        .to_string()
+}
@@ -0,0 +1,577 @@
+//! Media captured elsewhere and uploaded to `/ingest`, e.g. to a screenpipe
+//! running with `--headless` on a server. Uploads are saved in
+//! `data/ingest` and processed one at a time by [`run_ingest`]: frames of
+//! images and videos are OCR'd, audio is transcribed.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json as JsonResponse,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use image::DynamicImage;
+use oasgen::{oasgen, OaSchema};
+use once_cell::sync::Lazy;
+use screenpipe_audio::audio_manager::AudioManager;
+use screenpipe_audio::core::device::{AudioDevice, DeviceType};
+use screenpipe_core::Language;
+use screenpipe_db::{DatabaseManager, OcrEngine as DBOcrEngine, VideoMetadata};
+use screenpipe_vision::core::ocr_deferred_image;
+use screenpipe_vision::utils::compare_with_previous_image;
+use screenpipe_vision::OcrEngine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::redaction::{redact_deferred_ocr, Redaction};
+use crate::server::AppState;
+use crate::video_utils::{extract_frames_from_video, get_video_metadata};
+
+/// How often the worker checks for uploads.
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+/// Finished uploads whose status is kept.
+const FINISHED_CAPACITY: usize = 1000;
+/// Device of uploads that don't name one.
+pub const DEFAULT_DEVICE_NAME: &str = "ingest";
+/// Frames this similar to the previous one aren't OCR'd, as while capturing.
+const SIMILAR_FRAME_THRESHOLD: f64 = 0.006;
+
+static FINISHED: Lazy<Mutex<VecDeque<IngestJob>>> = Lazy::new(Default::default);
+static PROCESSING: Lazy<Mutex<Option<String>>> = Lazy::new(Default::default);
+
+#[derive(OaSchema, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestKind {
+    Image,
+    Video,
+    Audio,
+}
+
+#[derive(OaSchema, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestStatus {
+    Queued,
+    Processing,
+    Done,
+    Failed,
+}
+
+#[derive(OaSchema, Serialize, Deserialize, Debug, Clone)]
+pub struct IngestJob {
+    pub id: String,
+    pub kind: IngestKind,
+    /// Name of the uploaded file in the ingest dir
+    pub file: String,
+    pub device_name: String,
+    /// When the media was captured. Videos default to their creation time,
+    /// images to the upload time, and audio to when it's transcribed.
+    pub timestamp: Option<DateTime<Utc>>,
+    pub uploaded_at: DateTime<Utc>,
+    pub status: IngestStatus,
+    /// Frames OCR'd or audio chunks transcribed
+    pub processed: Option<usize>,
+    pub error: Option<String>,
+}
+
+impl IngestKind {
+    /// Kind and file extension of an upload of type `content_type`, like
+    /// `video/mp4`.
+    pub fn from_content_type(content_type: &str) -> Option<(Self, String)> {
+        let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+        let (kind, subtype) = mime.split_once('/')?;
+        let kind = match kind {
+            "image" => IngestKind::Image,
+            "video" => IngestKind::Video,
+            "audio" => IngestKind::Audio,
+            _ => return None,
+        };
+        // ffmpeg and the decoders look at the content, the extension is for
+        // whoever browses the data dir
+        let extension = match (kind, subtype) {
+            (_, "jpeg") => "jpg",
+            (_, "quicktime") => "mov",
+            (_, "x-matroska") => "mkv",
+            (IngestKind::Audio, "mpeg") => "mp3",
+            (_, "x-wav" | "wave" | "vnd.wave") => "wav",
+            // `.json` is taken by the job
+            (_, subtype)
+                if subtype != "json" && subtype.chars().all(|c| c.is_ascii_alphanumeric()) =>
+            {
+                subtype
+            }
+            _ => "bin",
+        };
+        Some((kind, extension.to_string()))
+    }
+
+    /// Kind and file extension of an upload of type `content_type` to the
+    /// endpoint of `expected`, or to `/ingest` when `None`. Uploads without
+    /// a type are accepted by the endpoints of a kind, the content is probed
+    /// anyway.
+    pub fn of_upload(expected: Option<Self>, content_type: &str) -> Option<(Self, String)> {
+        let untyped =
+            content_type.is_empty() || content_type.starts_with("application/octet-stream");
+        match (expected, IngestKind::from_content_type(content_type)) {
+            (None, detected) => detected,
+            (Some(expected), Some((kind, extension))) if kind == expected => {
+                Some((kind, extension))
+            }
+            (Some(expected), None) if untyped => Some((expected, "bin".to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// Where uploads wait to be processed.
+pub fn ingest_dir(output_path: &str) -> PathBuf {
+    Path::new(output_path).join("ingest")
+}
+
+fn new_job(
+    kind: IngestKind,
+    extension: &str,
+    device_name: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+) -> IngestJob {
+    let id = Uuid::new_v4().to_string();
+    IngestJob {
+        file: format!("{}.{}", id, extension),
+        id,
+        kind,
+        device_name: device_name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_DEVICE_NAME.to_string()),
+        timestamp,
+        uploaded_at: Utc::now(),
+        status: IngestStatus::Queued,
+        processed: None,
+        error: None,
+    }
+}
+
+/// Written after the file, the worker only picks up uploads with their job.
+async fn write_job(dir: &Path, job: &IngestJob) -> anyhow::Result<()> {
+    tokio::fs::write(
+        dir.join(format!("{}.json", job.id)),
+        serde_json::to_vec(job)?,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Saves an upload for the worker, streaming `body` to disk.
+pub async fn save_upload(
+    output_path: &str,
+    kind: IngestKind,
+    extension: &str,
+    device_name: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    body: Body,
+) -> anyhow::Result<IngestJob> {
+    let dir = ingest_dir(output_path);
+    tokio::fs::create_dir_all(&dir).await?;
+    let job = new_job(kind, extension, device_name, timestamp);
+
+    let path = dir.join(&job.file);
+    let written = async {
+        let mut file = tokio::fs::File::create(&path).await?;
+        let mut stream = body.into_data_stream();
+        while let Some(chunk) = stream.next().await {
+            file.write_all(&chunk?).await?;
+        }
+        file.flush().await?;
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(e) = written {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(e);
+    }
+    write_job(&dir, &job).await?;
+    Ok(job)
+}
+
+/// Queues a file received otherwise for the worker, moving it to the ingest
+/// dir.
+pub async fn queue_file(
+    output_path: &str,
+    kind: IngestKind,
+    extension: &str,
+    device_name: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    file: &Path,
+) -> anyhow::Result<IngestJob> {
+    let dir = ingest_dir(output_path);
+    tokio::fs::create_dir_all(&dir).await?;
+    let job = new_job(kind, extension, device_name, timestamp);
+    tokio::fs::rename(file, dir.join(&job.file)).await?;
+    write_job(&dir, &job).await?;
+    Ok(job)
+}
+
+/// Number of uploads waiting to be processed.
+pub fn pending_count(output_path: &str) -> usize {
+    std::fs::read_dir(ingest_dir(output_path)).map_or(0, |entries| {
+        entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .count()
+    })
+}
+
+/// Status of the upload `id`, `None` if it's unknown or finished long ago.
+pub fn ingest_status(output_path: &str, id: &str) -> Option<IngestJob> {
+    // ids are uuids, anything else could point out of the ingest dir
+    Uuid::parse_str(id).ok()?;
+    if let Some(job) = FINISHED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|job| job.id == id)
+    {
+        return Some(job.clone());
+    }
+    let mut job = read_job(&ingest_dir(output_path).join(format!("{}.json", id))).ok()?;
+    if PROCESSING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_deref()
+        == Some(id)
+    {
+        job.status = IngestStatus::Processing;
+    }
+    Some(job)
+}
+
+fn read_job(path: &Path) -> anyhow::Result<IngestJob> {
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+/// Uploads waiting in the ingest dir, oldest first.
+fn pending_jobs(output_path: &str) -> Vec<IngestJob> {
+    let Ok(entries) = std::fs::read_dir(ingest_dir(output_path)) else {
+        return Vec::new();
+    };
+    let mut jobs: Vec<IngestJob> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| match read_job(&path) {
+            Ok(job) => Some(job),
+            Err(e) => {
+                warn!("skipping invalid upload {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+    jobs.sort_by_key(|job| job.uploaded_at);
+    jobs
+}
+
+/// Processes uploads until a shutdown is signalled.
+pub async fn run_ingest(
+    db: Arc<DatabaseManager>,
+    audio_manager: Arc<AudioManager>,
+    ocr_engine: Arc<OcrEngine>,
+    languages: Vec<Language>,
+    redaction: Option<Redaction>,
+    output_path: String,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                for job in pending_jobs(&output_path) {
+                    process_job(
+                        &db,
+                        &audio_manager,
+                        &ocr_engine,
+                        &languages,
+                        redaction.as_ref(),
+                        &output_path,
+                        job,
+                    )
+                    .await;
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping ingest");
+                break;
+            }
+        }
+    }
+}
+
+async fn process_job(
+    db: &DatabaseManager,
+    audio_manager: &AudioManager,
+    ocr_engine: &Arc<OcrEngine>,
+    languages: &[Language],
+    redaction: Option<&Redaction>,
+    output_path: &str,
+    mut job: IngestJob,
+) {
+    let dir = ingest_dir(output_path);
+    let upload = dir.join(&job.file);
+    *PROCESSING.lock().unwrap_or_else(|e| e.into_inner()) = Some(job.id.clone());
+    info!("ingesting {:?} upload {}", job.kind, job.id);
+
+    let result = match job.kind {
+        IngestKind::Audio => {
+            let device = AudioDevice::new(job.device_name.clone(), DeviceType::Input);
+            let result = audio_manager
+                .ingest_file(&upload, device, job.timestamp)
+                .await;
+            // transcribed chunks are saved on their own
+            let _ = tokio::fs::remove_file(&upload).await;
+            result
+        }
+        IngestKind::Image | IngestKind::Video => {
+            // kept next to the recordings, frames are served from it
+            let media = Path::new(output_path).join(&job.file);
+            match tokio::fs::rename(&upload, &media).await {
+                Ok(()) => {
+                    let result =
+                        ingest_frames(db, ocr_engine, languages, redaction, &job, &media).await;
+                    if result.is_err() {
+                        let _ = tokio::fs::remove_file(&media).await;
+                    }
+                    result
+                }
+                Err(e) => Err(e.into()),
+            }
+        }
+    };
+
+    match result {
+        Ok(processed) => {
+            job.status = IngestStatus::Done;
+            job.processed = Some(processed);
+        }
+        Err(e) => {
+            error!("failed to ingest {}: {}", job.id, e);
+            job.status = IngestStatus::Failed;
+            job.error = Some(e.to_string());
+            let _ = tokio::fs::remove_file(&upload).await;
+        }
+    }
+    if let Err(e) = tokio::fs::remove_file(dir.join(format!("{}.json", job.id))).await {
+        warn!("failed to remove upload {}: {}", job.id, e);
+    }
+
+    *PROCESSING.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    let mut finished = FINISHED.lock().unwrap_or_else(|e| e.into_inner());
+    if finished.len() == FINISHED_CAPACITY {
+        finished.pop_front();
+    }
+    finished.push_back(job);
+}
+
+/// Stores the frames of an image or video and OCRs them, returning how many
+/// were OCR'd.
+async fn ingest_frames(
+    db: &DatabaseManager,
+    ocr_engine: &Arc<OcrEngine>,
+    languages: &[Language],
+    redaction: Option<&Redaction>,
+    job: &IngestJob,
+    media: &Path,
+) -> anyhow::Result<usize> {
+    let (frames, metadata) = match job.kind {
+        IngestKind::Video => {
+            let mut metadata: VideoMetadata =
+                get_video_metadata(&media.to_string_lossy()).await?.into();
+            // frames of faster videos are extracted at 1 fps
+            if metadata.fps > 10.0 {
+                metadata.fps = 1.0;
+            }
+            if let Some(timestamp) = job.timestamp {
+                metadata.creation_time = timestamp;
+            }
+            (extract_frames_from_video(media, None).await?, metadata)
+        }
+        _ => {
+            let bytes = tokio::fs::read(media).await?;
+            let image =
+                tokio::task::spawn_blocking(move || image::load_from_memory(&bytes)).await??;
+            let metadata = VideoMetadata {
+                creation_time: job.timestamp.unwrap_or(job.uploaded_at),
+                fps: 1.0,
+                duration: 0.0,
+                device_name: None,
+                name: None,
+            };
+            (vec![image], metadata)
+        }
+    };
+    let metadata = VideoMetadata {
+        device_name: Some(job.device_name.clone()),
+        ..metadata
+    };
+
+    let frame_ids = db
+        .create_video_with_frames(&media.to_string_lossy(), frames.clone(), metadata)
+        .await?;
+
+    let engine: Arc<DBOcrEngine> = Arc::new((**ocr_engine).clone().into());
+    let mut previous: Option<&DynamicImage> = None;
+    let mut processed = 0;
+    for (frame, frame_id) in frames.iter().zip(frame_ids) {
+        if previous.is_some() {
+            let difference =
+                compare_with_previous_image(previous, frame, &mut None, 0, &mut 0.0).await?;
+            if difference < SIMILAR_FRAME_THRESHOLD {
+                continue;
+            }
+        }
+        previous = Some(frame);
+
+        let mut ocr = ocr_deferred_image(frame, ocr_engine, languages.to_vec())
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        if let Some(redaction) = redaction {
+            redact_deferred_ocr(&mut ocr, &redaction.redactor);
+        }
+        let text_json = serde_json::to_string(&ocr.text_json).unwrap_or_default();
+        db.insert_ocr_text(frame_id, &ocr.text, &text_json, engine.clone())
+            .await?;
+        db.insert_ocr_blocks(frame_id, &ocr.layout).await?;
+        db.insert_ocr_tables(frame_id, &ocr.tables).await?;
+        processed += 1;
+    }
+    Ok(processed)
+}
+
+#[derive(Deserialize)]
+pub(crate) struct IngestQuery {
+    /// Device the media was captured on, `ingest` by default
+    #[serde(default)]
+    device_name: Option<String>,
+    /// When the media was captured
+    #[serde(default)]
+    timestamp: Option<DateTime<Utc>>,
+}
+
+/// Queues an image, video or audio file sent as the body, its type taken
+/// from the content type, to be OCR'd or transcribed.
+pub(crate) async fn ingest_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<IngestQuery>,
+    body: Body,
+) -> Result<(StatusCode, JsonResponse<Value>), (StatusCode, JsonResponse<Value>)> {
+    ingest(&state, None, &headers, query, body).await
+}
+
+/// Queues a video sent as the body, e.g. a phone screen recording, to be
+/// OCR'd into the timeline.
+pub(crate) async fn ingest_video_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<IngestQuery>,
+    body: Body,
+) -> Result<(StatusCode, JsonResponse<Value>), (StatusCode, JsonResponse<Value>)> {
+    ingest(&state, Some(IngestKind::Video), &headers, query, body).await
+}
+
+/// Queues an audio file sent as the body, e.g. a call recording, to be
+/// transcribed into the timeline.
+pub(crate) async fn ingest_audio_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<IngestQuery>,
+    body: Body,
+) -> Result<(StatusCode, JsonResponse<Value>), (StatusCode, JsonResponse<Value>)> {
+    ingest(&state, Some(IngestKind::Audio), &headers, query, body).await
+}
+
+async fn ingest(
+    state: &AppState,
+    expected: Option<IngestKind>,
+    headers: &HeaderMap,
+    query: IngestQuery,
+    body: Body,
+) -> Result<(StatusCode, JsonResponse<Value>), (StatusCode, JsonResponse<Value>)> {
+    let content_type = headers
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let Some((kind, extension)) = IngestKind::of_upload(expected, content_type) else {
+        let error = match expected {
+            Some(IngestKind::Video) => "send a video file, e.g. video/mp4",
+            Some(IngestKind::Audio) => "send an audio file, e.g. audio/wav",
+            _ => "send an image, video or audio file with its content type, e.g. video/mp4",
+        };
+        return Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            JsonResponse(json!({
+                "error": error,
+                "success": false
+            })),
+        ));
+    };
+
+    let output_path = state.screenpipe_dir.join("data");
+    match save_upload(
+        &output_path.to_string_lossy(),
+        kind,
+        &extension,
+        query.device_name,
+        query.timestamp,
+        body,
+    )
+    .await
+    {
+        Ok(job) => Ok((
+            StatusCode::ACCEPTED,
+            JsonResponse(json!({
+                "data": job,
+                "success": true
+            })),
+        )),
+        Err(e) => {
+            error!("failed to save upload: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to save upload: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
+/// Status of an upload to `/ingest`.
+#[oasgen]
+pub(crate) async fn get_ingest_handler(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let output_path = state.screenpipe_dir.join("data");
+    match ingest_status(&output_path.to_string_lossy(), &id) {
+        Some(job) => Ok(JsonResponse(json!({
+            "data": job,
+            "success": true
+        }))),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({
+                "error": "upload not found",
+                "success": false
+            })),
+        )),
+    }
+}
@@ -0,0 +1,252 @@
+//! Opt-in recording of keyboard and mouse activity for focus metrics. Only
+//! keystroke, click and scroll counts and the time spent in each window are
+//! kept, typed text only if enabled. Nothing is recorded while the privacy
+//! blocklist pauses capture.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::{DateTime, Utc};
+use oasgen::{oasgen, OaSchema};
+use rdev::{EventType, Key};
+use screenpipe_core::privacy::is_capture_blocked;
+use screenpipe_db::DatabaseManager;
+use screenpipe_vision::capture_screenshot_by_window::focused_window;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{error, info, warn};
+
+use crate::server::{AppState, PaginationQuery};
+
+/// How often the focused window is checked.
+const FOCUS_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest span stored as one row, longer stays in a window are split.
+const MAX_SPAN: Duration = Duration::from_secs(60);
+
+/// A keyboard or mouse action, as sent by the input hook.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputAction {
+    /// A key press, with the text it typed if typed text is recorded
+    Key(Option<TypedKey>),
+    Click,
+    Scroll,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypedKey {
+    Text(String),
+    Backspace,
+}
+
+/// Activity while one window had the focus.
+#[derive(Debug, Clone)]
+pub struct InputSpan {
+    pub timestamp: DateTime<Utc>,
+    pub started: Instant,
+    pub app_name: String,
+    pub window_name: String,
+    pub keystrokes: i64,
+    pub clicks: i64,
+    pub scrolls: i64,
+    pub typed_text: String,
+}
+
+impl InputSpan {
+    pub fn new(app_name: String, window_name: String) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            started: Instant::now(),
+            app_name,
+            window_name,
+            keystrokes: 0,
+            clicks: 0,
+            scrolls: 0,
+            typed_text: String::new(),
+        }
+    }
+
+    pub fn record(&mut self, action: InputAction) {
+        match action {
+            InputAction::Key(typed) => {
+                self.keystrokes += 1;
+                match typed {
+                    Some(TypedKey::Text(text)) => self.typed_text.push_str(&text),
+                    Some(TypedKey::Backspace) => {
+                        self.typed_text.pop();
+                    }
+                    None => {}
+                }
+            }
+            InputAction::Click => self.clicks += 1,
+            InputAction::Scroll => self.scrolls += 1,
+        }
+    }
+}
+
+/// Records input until a shutdown is signalled. The hook needs the
+/// accessibility permission on macOS and X11 on Linux.
+pub async fn run_input_capture(
+    db: Arc<DatabaseManager>,
+    record_text: bool,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let (action_tx, mut action_rx) = mpsc::unbounded_channel();
+
+    // the hook blocks its thread for as long as it listens
+    std::thread::spawn(move || {
+        let result = rdev::listen(move |event| {
+            let action = match event.event_type {
+                EventType::KeyPress(key) if record_text => {
+                    InputAction::Key(typed_key(key, event.name.as_deref()))
+                }
+                EventType::KeyPress(_) => InputAction::Key(None),
+                EventType::ButtonPress(_) => InputAction::Click,
+                EventType::Wheel { .. } => InputAction::Scroll,
+                _ => return,
+            };
+            let _ = action_tx.send(action);
+        });
+        if let Err(e) = result {
+            error!("failed to listen to keyboard and mouse input: {:?}", e);
+        }
+    });
+
+    info!(
+        "recording keyboard and mouse activity{}",
+        if record_text { " with typed text" } else { "" }
+    );
+
+    let mut focus_interval = tokio::time::interval(FOCUS_INTERVAL);
+    let mut span: Option<InputSpan> = None;
+
+    loop {
+        tokio::select! {
+            action = action_rx.recv() => {
+                let Some(action) = action else {
+                    warn!("input hook stopped, no more keyboard and mouse activity is recorded");
+                    break;
+                };
+                if let Some(span) = span.as_mut() {
+                    span.record(action);
+                }
+            }
+            _ = focus_interval.tick() => {
+                if is_capture_blocked() {
+                    // activity in blocked apps is dropped, not stored
+                    span = None;
+                    continue;
+                }
+                let focused = tokio::task::spawn_blocking(focused_window)
+                    .await
+                    .ok()
+                    .flatten();
+                let same_window = match (&span, &focused) {
+                    (Some(span), Some(focused)) => {
+                        span.app_name == focused.app_name
+                            && span.window_name == focused.window_name
+                            && span.started.elapsed() < MAX_SPAN
+                    }
+                    _ => false,
+                };
+                if !same_window {
+                    if let Some(finished) = span.take() {
+                        store_span(&db, finished, record_text).await;
+                    }
+                    span = focused.map(|f| InputSpan::new(f.app_name, f.window_name));
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping input capture");
+                break;
+            }
+        }
+    }
+
+    if let Some(finished) = span.take() {
+        store_span(&db, finished, record_text).await;
+    }
+}
+
+/// Text typed by a key press, `None` for keys that type nothing.
+fn typed_key(key: Key, name: Option<&str>) -> Option<TypedKey> {
+    match key {
+        Key::Backspace => Some(TypedKey::Backspace),
+        Key::Return | Key::KpReturn => Some(TypedKey::Text("\n".to_string())),
+        Key::Tab => Some(TypedKey::Text("\t".to_string())),
+        _ => name
+            .filter(|name| !name.is_empty() && !name.chars().any(char::is_control))
+            .map(|name| TypedKey::Text(name.to_string())),
+    }
+}
+
+async fn store_span(db: &DatabaseManager, span: InputSpan, record_text: bool) {
+    let typed_text = (record_text && !span.typed_text.is_empty()).then_some(span.typed_text);
+    if let Err(e) = db
+        .insert_input_event(
+            span.timestamp,
+            span.started.elapsed().as_millis() as i64,
+            &span.app_name,
+            &span.window_name,
+            span.keystrokes,
+            span.clicks,
+            span.scrolls,
+            typed_text.as_deref(),
+        )
+        .await
+    {
+        error!("failed to store input activity: {}", e);
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct InputEventsQuery {
+    #[serde(flatten)]
+    pagination: PaginationQuery,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    app_name: Option<String>,
+}
+
+/// Keyboard and mouse activity per focused window, oldest first. Empty
+/// unless input capture is enabled.
+#[oasgen]
+pub(crate) async fn get_input_events_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<InputEventsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state
+        .db
+        .get_input_events(
+            query.start_time,
+            query.end_time,
+            query.app_name.as_deref(),
+            query.pagination.limit,
+            query.pagination.offset,
+        )
+        .await
+    {
+        Ok(events) => Ok(JsonResponse(json!({
+            "data": events,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to read input events: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to read input events: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
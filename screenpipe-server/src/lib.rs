@@ -1,16 +1,70 @@
+pub mod activity;
 mod add;
+pub mod annotations;
 mod auto_destruct;
+pub mod auth;
+pub mod backup;
+pub mod calendar;
 pub mod chunking;
 pub mod cli;
+pub mod clipboard_capture;
+pub mod companion;
+pub mod crash;
+pub mod deep_link;
+pub mod doctor;
 pub mod core;
+pub mod export;
+pub mod extraction;
 pub mod filtering;
+pub mod focus;
+pub mod graphql;
+pub mod health;
+pub mod hls;
+pub mod idle_monitor;
+pub mod import;
+pub mod ingest;
+pub mod input_capture;
+pub mod llm;
+pub mod logging;
+pub mod mcp;
+pub mod media_encryption;
+pub mod meetings;
+pub mod metrics;
+pub mod ocr_queue;
+pub mod obsidian;
+pub mod offload;
+pub mod partitions;
+pub mod pipe_bus;
+pub mod pipe_dev;
+pub mod pipe_events;
 pub mod pipe_manager;
+pub mod pipe_scheduler;
+pub mod pipe_supervisor;
+pub mod power_monitor;
+pub mod privacy_audit;
+pub mod query;
+pub mod query_cache;
+pub mod redaction;
+pub mod remote;
 mod resource_monitor;
+pub mod retention;
+pub mod saved_search;
+mod semantic_index;
 mod server;
+pub mod shutdown;
+pub mod snapshot;
+pub mod storage;
+pub mod summaries;
+pub mod sync;
 pub mod text_embeds;
+pub mod thumbnails;
 mod video;
 pub mod video_cache;
+pub mod video_encoder;
 pub mod video_utils;
+pub mod vocabulary;
+pub mod watchdog;
+pub mod webhooks;
 pub use add::handle_index_command;
 pub use auto_destruct::watch_pid;
 pub use axum::Json as JsonResponse;
@@ -19,10 +73,11 @@ pub use core::start_continuous_recording;
 pub use pipe_manager::PipeManager;
 pub use resource_monitor::{ResourceMonitor, RestartSignal};
 pub use screenpipe_core::Language;
-pub use server::health_check;
+pub use semantic_index::run_semantic_indexer;
+pub use health::health_check;
 pub use server::AppState;
 pub use server::ContentItem;
-pub use server::HealthCheckResponse;
+pub use health::HealthCheckResponse;
 pub use server::PaginatedResponse;
 pub use server::SCServer;
 pub use server::{api_list_monitors, MonitorInfo};
@@ -0,0 +1,212 @@
+//! Endpoints of the local LLM enabled with `--enable-llm`: OpenAI compatible
+//! chat completions at `/v1/chat/completions`, and the models it can serve,
+//! downloaded and loaded at `/llm/models`.
+
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse, Json as JsonResponse, Response,
+    },
+};
+use chrono::Utc;
+use oasgen::{oasgen, OaSchema};
+use screenpipe_core::inference::{
+    local_model, ChatCompletionRequest, Quantization, LOCAL_MODELS, QUANTIZATIONS,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tracing::error;
+
+use crate::server::AppState;
+
+/// The model served at `/v1/chat/completions`, in OpenAI's format.
+#[oasgen]
+pub(crate) async fn list_openai_models_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let inference = state.inference.clone().ok_or_else(llm_disabled)?;
+    let models: Vec<Value> = inference
+        .engine()
+        .map(|engine| {
+            json!({
+                "id": engine.model().id,
+                "object": "model",
+                "created": 0,
+                "owned_by": "screenpipe"
+            })
+        })
+        .into_iter()
+        .collect();
+    Ok(JsonResponse(json!({
+        "object": "list",
+        "data": models
+    })))
+}
+
+/// The models the local llm can serve, the quantizations each was
+/// downloaded in, and the model loaded.
+#[oasgen]
+pub(crate) async fn list_llm_models_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let inference = state.inference.clone().ok_or_else(llm_disabled)?;
+    let models: Vec<Value> = LOCAL_MODELS
+        .iter()
+        .map(|model| {
+            let downloaded: Vec<&str> = QUANTIZATIONS
+                .into_iter()
+                .filter(|quantization| model.is_downloaded(*quantization))
+                .map(|quantization| quantization.as_str())
+                .collect();
+            json!({
+                "id": model.id,
+                "repo": model.gguf_repo,
+                "context_length": model.context_length,
+                "downloaded": downloaded
+            })
+        })
+        .collect();
+    Ok(JsonResponse(json!({
+        "data": {
+            "status": inference.status(),
+            "quantizations": QUANTIZATIONS.map(|quantization| quantization.as_str()),
+            "models": models
+        },
+        "success": true
+    })))
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct LoadLlmModelRequest {
+    model: String,
+    /// e.g. `q4_k_m`, `q8_0`
+    quantization: String,
+}
+
+/// Downloads a model if needed and serves it once loaded, follow the
+/// progress at `/llm/models`.
+#[oasgen]
+pub(crate) async fn load_llm_model_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<LoadLlmModelRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let inference = state.inference.clone().ok_or_else(llm_disabled)?;
+    let model = local_model(&request.model).ok_or_else(|| {
+        llm_error(
+            StatusCode::BAD_REQUEST,
+            format!("unknown model: {}", request.model),
+        )
+    })?;
+    let quantization: Quantization = request
+        .quantization
+        .parse()
+        .map_err(|e: anyhow::Error| llm_error(StatusCode::BAD_REQUEST, e.to_string()))?;
+    inference
+        .load(model, quantization)
+        .map_err(|e| llm_error(StatusCode::CONFLICT, e.to_string()))?;
+    Ok(JsonResponse(json!({
+        "data": inference.status(),
+        "success": true
+    })))
+}
+
+fn llm_error(status: StatusCode, error: String) -> (StatusCode, JsonResponse<Value>) {
+    (
+        status,
+        JsonResponse(json!({
+            "error": error,
+            "success": false
+        })),
+    )
+}
+
+fn llm_disabled() -> (StatusCode, JsonResponse<Value>) {
+    llm_error(
+        StatusCode::NOT_FOUND,
+        "the local llm is disabled, start screenpipe with --enable-llm".to_string(),
+    )
+}
+
+// openai compatible chat completions on the built-in model, streamed as
+// server-sent events with `stream`
+pub(crate) async fn chat_completions_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ChatCompletionRequest>,
+) -> Result<Response, (StatusCode, JsonResponse<Value>)> {
+    let inference = state.inference.clone().ok_or_else(llm_disabled)?;
+    request
+        .validate()
+        .map_err(|e| llm_error(StatusCode::BAD_REQUEST, e))?;
+    let engine = inference.engine().ok_or_else(|| {
+        llm_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "no model is loaded yet, see /llm/models".to_string(),
+        )
+    })?;
+
+    if !request.stream {
+        let completion = tokio::task::spawn_blocking(move || engine.complete(&request, |_| true))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|completion| completion)
+            .map_err(|e| {
+                error!("failed to complete chat: {}", e);
+                llm_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            })?;
+        return Ok(JsonResponse(completion).into_response());
+    }
+
+    let (tx, rx) = mpsc::channel(100);
+    tokio::task::spawn_blocking(move || {
+        let id = format!(
+            "chatcmpl-{}",
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        );
+        let created = Utc::now().timestamp();
+        let model = engine.model().id;
+        let chunk = |delta: Value, finish_reason: Option<&str>| {
+            SseEvent::default().data(
+                json!({
+                    "id": id,
+                    "object": "chat.completion.chunk",
+                    "created": created,
+                    "model": model,
+                    "choices": [{
+                        "index": 0,
+                        "delta": delta,
+                        "finish_reason": finish_reason
+                    }]
+                })
+                .to_string(),
+            )
+        };
+
+        let _ = tx.blocking_send(chunk(json!({"role": "assistant", "content": ""}), None));
+        let last = match engine.complete(&request, |text| {
+            tx.blocking_send(chunk(json!({ "content": text }), None))
+                .is_ok()
+        }) {
+            Ok(completion) => chunk(json!({}), Some(&completion.choices[0].finish_reason)),
+            Err(e) => {
+                error!("failed to complete chat: {}", e);
+                SseEvent::default().data(json!({"error": {"message": e.to_string()}}).to_string())
+            }
+        };
+        let _ = tx.blocking_send(last);
+        let _ = tx.blocking_send(SseEvent::default().data("[DONE]"));
+    });
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv()
+            .await
+            .map(|event| (Ok::<_, Infallible>(event), rx))
+    });
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
@@ -0,0 +1,348 @@
+//! Logs of the recorder. The log files are rotated daily and when they grow
+//! past a size cap, and the log levels can be changed per module while
+//! running, see `screenpipe log set`.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use axum::{http::StatusCode, response::Json as JsonResponse};
+use chrono::{NaiveDate, Utc};
+use oasgen::{oasgen, OaSchema};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::info;
+use tracing_subscriber::filter::{Directive, EnvFilter, ParseError};
+use tracing_subscriber::reload;
+
+/// Name the log files start with, today's is `screenpipe.YYYY-MM-DD.log`.
+const LOG_FILE_PREFIX: &str = "screenpipe";
+
+/// Filter the recorder starts with: `RUST_LOG`, info for everything else,
+/// then the directives of `SCREENPIPE_LOG`, e.g.
+/// `screenpipe_audio=debug,screenpipe_vision=warn`.
+pub fn default_filter(debug: bool) -> EnvFilter {
+    let filter = EnvFilter::from_default_env()
+        .add_directive("tokio=debug".parse().unwrap())
+        .add_directive("runtime=debug".parse().unwrap())
+        .add_directive("info".parse().unwrap())
+        .add_directive("tokenizers=error".parse().unwrap())
+        .add_directive("rusty_tesseract=error".parse().unwrap())
+        .add_directive("symphonia=error".parse().unwrap())
+        .add_directive("hf_hub=error".parse().unwrap())
+        .add_directive("whisper_rs=error".parse().unwrap());
+
+    #[cfg(target_os = "windows")]
+    let filter = filter
+        .add_directive("xcap::platform::impl_window=off".parse().unwrap())
+        .add_directive("xcap::platform::impl_monitor=off".parse().unwrap())
+        .add_directive("xcap::platform::utils=off".parse().unwrap());
+
+    let filter = std::env::var("SCREENPIPE_LOG")
+        .unwrap_or_default()
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .fold(filter, |filter, module_directive| {
+            match module_directive.parse() {
+                Ok(directive) => filter.add_directive(directive),
+                Err(e) => {
+                    eprintln!(
+                        "warning: invalid log directive '{}': {}",
+                        module_directive, e
+                    );
+                    filter
+                }
+            }
+        });
+
+    if debug {
+        filter.add_directive("screenpipe=debug".parse().unwrap())
+    } else {
+        filter
+    }
+}
+
+/// Swaps the filter of one of the outputs of the logs.
+pub type Reload = Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>;
+
+/// Log levels set while running, on top of the filter the recorder started
+/// with.
+pub struct LogLevels {
+    debug: bool,
+    /// Directive per target, `""` for the default level.
+    overrides: Mutex<BTreeMap<String, Directive>>,
+    reloads: Vec<Reload>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogLevelsStatus {
+    /// Filter every log line goes through.
+    pub filter: String,
+    /// Directives set while running.
+    pub overrides: Vec<String>,
+}
+
+impl LogLevels {
+    /// `reloads` swap the filter of each output the logs go to.
+    pub fn new(debug: bool, reloads: Vec<Reload>) -> Self {
+        LogLevels {
+            debug,
+            overrides: Mutex::new(BTreeMap::new()),
+            reloads,
+        }
+    }
+
+    /// Applies comma separated `directives`, e.g. `screenpipe_audio=debug`.
+    /// A directive replaces the one set before for the same module, nothing
+    /// is applied when one of them is invalid.
+    pub fn set(&self, directives: &str) -> Result<LogLevelsStatus, ParseError> {
+        let parsed = parse_directives(directives)?;
+        let mut overrides = self.overrides.lock().unwrap_or_else(|e| e.into_inner());
+        for (target, directive) in parsed {
+            overrides.insert(target, directive);
+        }
+        self.apply(&overrides);
+        Ok(self.status(&overrides))
+    }
+
+    /// Drops every directive set while running.
+    pub fn reset(&self) -> LogLevelsStatus {
+        let mut overrides = self.overrides.lock().unwrap_or_else(|e| e.into_inner());
+        overrides.clear();
+        self.apply(&overrides);
+        self.status(&overrides)
+    }
+
+    pub fn current(&self) -> LogLevelsStatus {
+        let overrides = self.overrides.lock().unwrap_or_else(|e| e.into_inner());
+        self.status(&overrides)
+    }
+
+    fn filter(&self, overrides: &BTreeMap<String, Directive>) -> EnvFilter {
+        overrides
+            .values()
+            .fold(default_filter(self.debug), |filter, directive| {
+                filter.add_directive(directive.clone())
+            })
+    }
+
+    fn apply(&self, overrides: &BTreeMap<String, Directive>) {
+        for reload in &self.reloads {
+            if let Err(e) = reload(self.filter(overrides)) {
+                eprintln!("warning: failed to change log levels: {}", e);
+            }
+        }
+    }
+
+    fn status(&self, overrides: &BTreeMap<String, Directive>) -> LogLevelsStatus {
+        LogLevelsStatus {
+            filter: self.filter(overrides).to_string(),
+            overrides: overrides.values().map(|d| d.to_string()).collect(),
+        }
+    }
+}
+
+/// Parses comma separated directives, keyed by the module they apply to.
+pub fn parse_directives(directives: &str) -> Result<Vec<(String, Directive)>, ParseError> {
+    directives
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let target = s.rsplit_once('=').map_or("", |(target, _)| target);
+            Ok((target.to_string(), s.parse()?))
+        })
+        .collect()
+}
+
+static LOG_LEVELS: OnceCell<LogLevels> = OnceCell::new();
+
+/// Makes `levels` the ones `/admin/log-levels` changes, once per process.
+pub fn install_log_levels(levels: LogLevels) {
+    let _ = LOG_LEVELS.set(levels);
+}
+
+pub fn log_levels() -> Option<&'static LogLevels> {
+    LOG_LEVELS.get()
+}
+
+/// Log file writer starting a new file every day and when the current one
+/// reaches `max_file_size`. Full files are renamed
+/// `screenpipe.YYYY-MM-DD.N.log`, only the latest `max_files` files are
+/// kept.
+pub struct LogFileWriter {
+    dir: PathBuf,
+    max_file_size: u64,
+    max_files: usize,
+    date: NaiveDate,
+    file: File,
+    size: u64,
+}
+
+impl LogFileWriter {
+    pub fn new(dir: impl Into<PathBuf>, max_file_size: u64, max_files: usize) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let date = Utc::now().date_naive();
+        let (file, size) = open_log_file(&log_file_path(&dir, date))?;
+        let writer = LogFileWriter {
+            dir,
+            max_file_size,
+            max_files: max_files.max(1),
+            date,
+            file,
+            size,
+        };
+        writer.prune();
+        Ok(writer)
+    }
+
+    fn roll(&mut self) -> io::Result<()> {
+        let today = Utc::now().date_naive();
+        if today != self.date {
+            self.date = today;
+        } else {
+            // numbered after the latest one, the oldest ones may be gone
+            let prefix = format!("{}.{}.", LOG_FILE_PREFIX, self.date);
+            let latest = log_files(&self.dir)?
+                .iter()
+                .filter_map(|path| {
+                    let name = path.file_name()?.to_str()?;
+                    name.strip_prefix(&prefix)?
+                        .strip_suffix(".log")?
+                        .parse()
+                        .ok()
+                })
+                .max()
+                .unwrap_or(0u32);
+            let rotated = self.dir.join(format!("{}{}.log", prefix, latest + 1));
+            self.file.flush()?;
+            fs::rename(log_file_path(&self.dir, self.date), rotated)?;
+        }
+        (self.file, self.size) = open_log_file(&log_file_path(&self.dir, self.date))?;
+        self.prune();
+        Ok(())
+    }
+
+    fn prune(&self) {
+        let Ok(files) = log_files(&self.dir) else {
+            return;
+        };
+        let current = log_file_path(&self.dir, self.date);
+        for path in files
+            .into_iter()
+            .filter(|path| *path != current)
+            .skip(self.max_files - 1)
+        {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let full = self.size > 0 && self.size + buf.len() as u64 > self.max_file_size;
+        if full || Utc::now().date_naive() != self.date {
+            self.roll()?;
+        }
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Every log file in `dir`, newest first.
+pub fn log_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(&format!("{}.", LOG_FILE_PREFIX)) && name.ends_with(".log") {
+            let modified = entry.metadata()?.modified()?;
+            files.push((modified, entry.path()));
+        }
+    }
+    files.sort_by(|a, b| b.cmp(a));
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+fn log_file_path(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("{}.{}.log", LOG_FILE_PREFIX, date))
+}
+
+fn open_log_file(path: &Path) -> io::Result<(File, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok((file, size))
+}
+
+fn log_levels_unavailable() -> (StatusCode, JsonResponse<Value>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        JsonResponse(json!({
+            "error": "log levels can't be changed in this process",
+            "success": false
+        })),
+    )
+}
+
+/// The log filter, and the levels set with `POST /admin/log-levels`.
+#[oasgen]
+pub(crate) async fn get_log_levels_handler(
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let levels = log_levels().ok_or_else(log_levels_unavailable)?;
+    Ok(JsonResponse(json!({
+        "data": levels.current(),
+        "success": true
+    })))
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct SetLogLevelsRequest {
+    /// Comma separated directives, e.g. `screenpipe_audio=debug`
+    directives: String,
+}
+
+/// Changes log levels per module until the next restart, see
+/// `screenpipe log set`.
+#[oasgen]
+pub(crate) async fn set_log_levels_handler(
+    JsonResponse(payload): JsonResponse<SetLogLevelsRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let levels = log_levels().ok_or_else(log_levels_unavailable)?;
+    match levels.set(&payload.directives) {
+        Ok(status) => {
+            info!("log levels set to {}", payload.directives);
+            Ok(JsonResponse(json!({
+                "data": status,
+                "success": true
+            })))
+        }
+        Err(e) => Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({
+                "error": format!("invalid log directives: {}", e),
+                "success": false
+            })),
+        )),
+    }
+}
+
+/// Goes back to the log levels the recorder started with.
+#[oasgen]
+pub(crate) async fn reset_log_levels_handler(
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let levels = log_levels().ok_or_else(log_levels_unavailable)?;
+    Ok(JsonResponse(json!({
+        "data": levels.reset(),
+        "success": true
+    })))
+}
@@ -0,0 +1,478 @@
+//! Model Context Protocol server, so that MCP clients like Claude Desktop can
+//! search the recorded history. Speaks JSON-RPC over stdio with
+//! `screenpipe mcp serve`, or over server-sent events at `/mcp/sse` while
+//! recording. Clients only see the tools they are allowed to use.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use axum::{
+    extract::{Json, Query, State},
+    http::StatusCode,
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        Json as JsonResponse,
+    },
+};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use screenpipe_db::{
+    ContentType, DatabaseManager, SearchResult, SearchSort, TimelineCursor, TimelineEventKind,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use crate::server::AppState;
+
+/// Protocol revision implemented, the one with the SSE transport.
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+const DEFAULT_SEARCH_RESULTS: u32 = 10;
+const MAX_SEARCH_RESULTS: u32 = 50;
+const DEFAULT_TIMELINE_EVENTS: u32 = 100;
+const MAX_TIMELINE_EVENTS: u32 = 500;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum McpTool {
+    SearchScreenHistory,
+    GetTranscripts,
+    GetTimeline,
+}
+
+impl McpTool {
+    pub const ALL: [McpTool; 3] = [
+        McpTool::SearchScreenHistory,
+        McpTool::GetTranscripts,
+        McpTool::GetTimeline,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            McpTool::SearchScreenHistory => "search_screen_history",
+            McpTool::GetTranscripts => "get_transcripts",
+            McpTool::GetTimeline => "get_timeline",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            McpTool::SearchScreenHistory => {
+                "Search text that was on screen, read with OCR. Results have the time, app, window and text."
+            }
+            McpTool::GetTranscripts => {
+                "Get transcripts of recorded audio, from microphones and speakers, optionally matching a query."
+            }
+            McpTool::GetTimeline => {
+                "Get what happened in a time range: screen captures, transcripts, app switches and times the user was away, ordered by time."
+            }
+        }
+    }
+
+    fn input_schema(&self) -> Value {
+        let mut schema = match self {
+            McpTool::SearchScreenHistory => json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Words to search for" },
+                    "app_name": { "type": "string", "description": "Only this app, e.g. Chrome" },
+                    "window_name": { "type": "string", "description": "Only windows with this title" },
+                    "limit": { "type": "integer", "default": DEFAULT_SEARCH_RESULTS, "maximum": MAX_SEARCH_RESULTS }
+                }
+            }),
+            McpTool::GetTranscripts => json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Words to search for, all transcripts when empty" },
+                    "limit": { "type": "integer", "default": DEFAULT_SEARCH_RESULTS, "maximum": MAX_SEARCH_RESULTS }
+                }
+            }),
+            McpTool::GetTimeline => json!({
+                "type": "object",
+                "properties": {
+                    "types": {
+                        "type": "array",
+                        "items": { "type": "string", "enum": ["frame", "transcript", "app_switch", "idle_gap", "media_block"] },
+                        "description": "Event types to include, all when not set"
+                    },
+                    "cursor": { "type": "string", "description": "next_cursor of the previous page" },
+                    "limit": { "type": "integer", "default": DEFAULT_TIMELINE_EVENTS, "maximum": MAX_TIMELINE_EVENTS }
+                },
+                "required": ["start_time", "end_time"]
+            }),
+        };
+        schema["properties"]["start_time"] = json!({
+            "type": "string",
+            "format": "date-time",
+            "description": "Start of the time range, ISO 8601 in UTC"
+        });
+        schema["properties"]["end_time"] = json!({
+            "type": "string",
+            "format": "date-time",
+            "description": "End of the time range, ISO 8601 in UTC"
+        });
+        schema
+    }
+}
+
+impl fmt::Display for McpTool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchArgs {
+    #[serde(default)]
+    query: Option<String>,
+    #[serde(default)]
+    app_name: Option<String>,
+    #[serde(default)]
+    window_name: Option<String>,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct TimelineArgs {
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    #[serde(default)]
+    types: Option<Vec<String>>,
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    limit: Option<u32>,
+}
+
+pub struct McpServer {
+    db: Arc<DatabaseManager>,
+    tools: Vec<McpTool>,
+    /// SSE sessions, the responses for each client go to its stream
+    sessions: Mutex<HashMap<String, mpsc::Sender<Value>>>,
+}
+
+impl McpServer {
+    /// Offers `tools` to clients, all of them when empty.
+    pub fn new(db: Arc<DatabaseManager>, tools: Vec<McpTool>) -> Self {
+        let tools = if tools.is_empty() {
+            McpTool::ALL.to_vec()
+        } else {
+            tools
+        };
+        McpServer {
+            db,
+            tools,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn tools(&self) -> &[McpTool] {
+        &self.tools
+    }
+
+    /// Handles a JSON-RPC request and returns the response. Notifications
+    /// have no id and get no response.
+    pub async fn handle_message(&self, message: Value) -> Option<Value> {
+        let method = message
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        debug!("mcp request: {}", method);
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "screenpipe", "version": env!("CARGO_PKG_VERSION") }
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({
+                "tools": self
+                    .tools
+                    .iter()
+                    .map(|tool| {
+                        json!({
+                            "name": tool.name(),
+                            "description": tool.description(),
+                            "inputSchema": tool.input_schema()
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })),
+            "tools/call" => self.call_tool(params).await,
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method: {}", method))),
+        };
+
+        let id = message.get("id")?.clone();
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, message),
+        })
+    }
+
+    async fn call_tool(&self, params: Value) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let tool = self
+            .tools
+            .iter()
+            .find(|tool| tool.name() == name)
+            .ok_or_else(|| (INVALID_PARAMS, format!("unknown tool: {}", name)))?;
+        let arguments = params
+            .get("arguments")
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+
+        // failures are shown to the model so that it can fix its arguments
+        let (text, is_error) = match self.run_tool(*tool, arguments).await {
+            Ok(output) => (
+                serde_json::to_string_pretty(&output).unwrap_or_default(),
+                false,
+            ),
+            Err(e) => (e.to_string(), true),
+        };
+        Ok(json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": is_error
+        }))
+    }
+
+    async fn run_tool(&self, tool: McpTool, arguments: Value) -> Result<Value> {
+        match tool {
+            McpTool::SearchScreenHistory | McpTool::GetTranscripts => {
+                let args: SearchArgs = serde_json::from_value(arguments)?;
+                let content_type = if tool == McpTool::SearchScreenHistory {
+                    ContentType::OCR
+                } else {
+                    ContentType::Audio
+                };
+                let results = self
+                    .db
+                    .search(
+                        args.query.as_deref().unwrap_or_default(),
+                        content_type,
+                        args.limit
+                            .unwrap_or(DEFAULT_SEARCH_RESULTS)
+                            .clamp(1, MAX_SEARCH_RESULTS),
+                        0,
+                        args.start_time,
+                        args.end_time,
+                        args.app_name.as_deref(),
+                        args.window_name.as_deref(),
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        SearchSort::Time,
+                    )
+                    .await?;
+                Ok(Value::Array(
+                    results.iter().filter_map(summarize_result).collect(),
+                ))
+            }
+            McpTool::GetTimeline => {
+                let args: TimelineArgs = serde_json::from_value(arguments)?;
+                let kinds = match args.types {
+                    Some(types) => types
+                        .iter()
+                        .map(|kind| kind.parse::<TimelineEventKind>())
+                        .collect::<Result<Vec<_>, _>>()?,
+                    None => TimelineEventKind::ALL.to_vec(),
+                };
+                let cursor = args
+                    .cursor
+                    .as_deref()
+                    .map(str::parse::<TimelineCursor>)
+                    .transpose()?;
+                let page = self
+                    .db
+                    .get_timeline(
+                        args.start_time,
+                        args.end_time,
+                        &kinds,
+                        cursor,
+                        args.limit
+                            .unwrap_or(DEFAULT_TIMELINE_EVENTS)
+                            .clamp(1, MAX_TIMELINE_EVENTS),
+                    )
+                    .await?;
+                Ok(serde_json::to_value(page)?)
+            }
+        }
+    }
+
+    /// Serves a single client on stdin and stdout, one message per line,
+    /// until stdin is closed.
+    pub async fn run_stdio(&self) -> Result<()> {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        let mut stdout = tokio::io::stdout();
+
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle_message(message).await,
+                Err(e) => Some(error_response(Value::Null, PARSE_ERROR, e.to_string())),
+            };
+            if let Some(response) = response {
+                stdout
+                    .write_all(format!("{}\n", response).as_bytes())
+                    .await?;
+                stdout.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts an SSE session, the responses to the messages posted for it are
+    /// received on the returned channel.
+    pub fn open_session(&self) -> (String, mpsc::Receiver<Value>) {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let (tx, rx) = mpsc::channel(100);
+        self.sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(session_id.clone(), tx);
+        (session_id, rx)
+    }
+
+    /// Handles a message posted for an SSE session, `false` if the session
+    /// doesn't exist or was closed.
+    pub async fn handle_session_message(&self, session_id: &str, message: Value) -> bool {
+        let tx = self
+            .sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(session_id)
+            .cloned();
+        let Some(tx) = tx else {
+            return false;
+        };
+        if let Some(response) = self.handle_message(message).await {
+            if tx.send(response).await.is_err() {
+                self.close_session(session_id);
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn close_session(&self, session_id: &str) {
+        self.sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(session_id);
+    }
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message }
+    })
+}
+
+/// Fields of a search result useful to a model, without file paths and
+/// layout data.
+fn summarize_result(result: &SearchResult) -> Option<Value> {
+    match result {
+        SearchResult::OCR(ocr) => Some(json!({
+            "frame_id": ocr.frame_id,
+            "timestamp": ocr.timestamp,
+            "app_name": ocr.app_name,
+            "window_name": ocr.window_name,
+            "browser_url": ocr.browser_url,
+            "text": ocr.ocr_text
+        })),
+        SearchResult::Audio(audio) => Some(json!({
+            "timestamp": audio.timestamp,
+            "device_name": audio.device_name,
+            "speaker": audio.speaker.as_ref().map(|speaker| speaker.name.clone()),
+            "transcription": audio.transcription
+        })),
+        _ => None,
+    }
+}
+
+fn mcp_disabled() -> (StatusCode, JsonResponse<Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        JsonResponse(json!({
+            "error": "the mcp server is disabled, start screenpipe with --enable-mcp",
+            "success": false
+        })),
+    )
+}
+
+// mcp sse transport: the stream first tells the client where to post its
+// messages, the responses are sent on the stream
+pub(crate) async fn mcp_sse_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, (StatusCode, JsonResponse<Value>)>
+{
+    let mcp = state.mcp.clone().ok_or_else(mcp_disabled)?;
+    let (session_id, rx) = mcp.open_session();
+    debug!("mcp session {} opened", session_id);
+
+    let endpoint = SseEvent::default()
+        .event("endpoint")
+        .data(format!("/mcp/messages?session_id={}", session_id));
+    let messages = futures::stream::unfold(rx, |mut rx| async move {
+        let message = rx.recv().await?;
+        let event = SseEvent::default()
+            .event("message")
+            .data(message.to_string());
+        Some((Ok(event), rx))
+    });
+    let stream = futures::stream::once(async move { Ok(endpoint) }).chain(messages);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct McpMessageQuery {
+    session_id: String,
+}
+
+pub(crate) async fn mcp_messages_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<McpMessageQuery>,
+    Json(message): Json<Value>,
+) -> Result<StatusCode, (StatusCode, JsonResponse<Value>)> {
+    let mcp = state.mcp.clone().ok_or_else(mcp_disabled)?;
+    if mcp.handle_session_message(&query.session_id, message).await {
+        Ok(StatusCode::ACCEPTED)
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({
+                "error": format!("unknown mcp session: {}", query.session_id),
+                "success": false
+            })),
+        ))
+    }
+}
@@ -0,0 +1,91 @@
+//! Background encryption of finished recordings. Chunks that are no longer
+//! written to are encrypted in place with the unlocked key and marked in
+//! the database, reads decrypt them through `readable_media`.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use screenpipe_core::encryption::MediaCipher;
+use screenpipe_db::{DatabaseManager, MediaType};
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, warn};
+
+/// How often new chunks are looked for.
+const ENCRYPTION_INTERVAL: Duration = Duration::from_secs(60);
+/// Chunks recorded to more recently may still be written.
+const MIN_CHUNK_AGE: Duration = Duration::from_secs(5 * 60);
+/// Chunks encrypted per media type and round.
+const ENCRYPTION_BATCH: u32 = 20;
+
+/// Encrypts one batch of finished chunks of each media type. Returns how
+/// many files were encrypted.
+pub async fn encrypt_finished_chunks(
+    db: &DatabaseManager,
+    cipher: &'static MediaCipher,
+) -> Result<usize> {
+    let before = Utc::now() - chrono::Duration::from_std(MIN_CHUNK_AGE)?;
+    let mut encrypted = 0;
+
+    for media_type in [MediaType::Video, MediaType::Audio] {
+        let chunks = db
+            .get_unencrypted_media_chunks(media_type, before, ENCRYPTION_BATCH)
+            .await?;
+        let mut ids = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            let path = PathBuf::from(&chunk.file_path);
+            match tokio::task::spawn_blocking(move || cipher.encrypt_file(&path)).await? {
+                Ok(true) => encrypted += 1,
+                Ok(false) => debug!("{} was already encrypted", chunk.file_path),
+                Err(e) => {
+                    // a missing file has nothing left to encrypt
+                    if !std::path::Path::new(&chunk.file_path).exists() {
+                        debug!("{} no longer exists", chunk.file_path);
+                    } else {
+                        warn!("failed to encrypt {}: {}", chunk.file_path, e);
+                        continue;
+                    }
+                }
+            }
+            ids.push(chunk.id);
+        }
+        if !ids.is_empty() {
+            db.mark_media_chunks_encrypted(media_type, &ids).await?;
+        }
+    }
+
+    Ok(encrypted)
+}
+
+/// Keeps encrypting finished chunks until a shutdown is signalled.
+pub async fn run_media_encryption(
+    db: Arc<DatabaseManager>,
+    cipher: &'static MediaCipher,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!("starting encryption of recordings at rest");
+
+    loop {
+        let wait = match encrypt_finished_chunks(&db, cipher).await {
+            Ok(0) => ENCRYPTION_INTERVAL,
+            Ok(count) => {
+                debug!("encrypted {} recordings", count);
+                Duration::ZERO
+            }
+            Err(e) => {
+                error!("failed to encrypt recordings: {}", e);
+                ENCRYPTION_INTERVAL
+            }
+        };
+
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping encryption of recordings");
+                break;
+            }
+        }
+    }
+}
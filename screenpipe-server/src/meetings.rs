@@ -0,0 +1,328 @@
+//! Detects meetings as they happen: a session starts once a meeting app or
+//! site is in the foreground while people keep talking, and ends a few
+//! minutes after they stopped. Sessions are stored in `meeting_sessions`,
+//! their participants are the speakers diarization told apart in between.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Json as JsonResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_db::{DatabaseManager, FrameEntry, MeetingParticipant, MeetingSession};
+use screenpipe_events::send_event;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+use crate::activity::{frame_category, ActivityCategory};
+use crate::export::{export, ExportFilter, ExportFormat};
+use crate::server::{AppState, PaginationQuery};
+
+/// How often the latest frames and transcripts are looked at.
+pub const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+/// Time looked back at each check, transcripts are stored a while after
+/// their audio was recorded.
+const WINDOW: Duration = Duration::minutes(2);
+/// Transcripts in `WINDOW` for the speech to be sustained, audio is
+/// transcribed in chunks of about 30 seconds.
+const MIN_TRANSCRIPTS: usize = 2;
+/// A meeting ends once it hasn't been seen for this long, so that looking
+/// at notes or a silence doesn't split it.
+const END_AFTER: Duration = Duration::minutes(5);
+/// Frames looked at in `WINDOW`, a few monitors at 1 fps.
+const MAX_FRAMES: u32 = 1000;
+const MAX_TRANSCRIPTS: u32 = 200;
+
+/// What a check changed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeetingChange {
+    Started(MeetingSession),
+    /// Still going on, until the new `end_time`
+    Continued(MeetingSession),
+    Ended(MeetingSession),
+}
+
+/// A meeting with the speakers transcribed during it.
+#[derive(Debug, Clone, Serialize)]
+pub struct MeetingDetails {
+    #[serde(flatten)]
+    pub session: MeetingSession,
+    pub participants: Vec<MeetingParticipant>,
+}
+
+impl MeetingDetails {
+    pub async fn load(db: &DatabaseManager, session: MeetingSession) -> Result<Self> {
+        let participants = db
+            .get_meeting_participants(session.start_time, session.end_time)
+            .await?;
+        Ok(Self {
+            session,
+            participants,
+        })
+    }
+}
+
+/// Frames showing a meeting app or site, only the focused ones when
+/// `focused` is set.
+fn meeting_frames(frames: &[FrameEntry], focused: bool) -> Vec<&FrameEntry> {
+    frames
+        .iter()
+        .filter(|frame| !focused || frame.focused)
+        .filter(|frame| frame_category(frame) == ActivityCategory::Meeting)
+        .collect()
+}
+
+/// Looks at what was captured in the `WINDOW` before `now`: starts a
+/// session when a meeting app is in the foreground with sustained speech,
+/// moves the end of the open one forward while a meeting app is on any
+/// screen with speech, and ends it after `END_AFTER` without.
+pub async fn check_meeting(
+    db: &DatabaseManager,
+    now: DateTime<Utc>,
+) -> Result<Option<MeetingChange>> {
+    let since = now - WINDOW;
+    let frames: Vec<FrameEntry> = db
+        .get_export_frames(since, now, None, None, MAX_FRAMES, 0)
+        .await?;
+    let transcripts: Vec<_> = db
+        .get_audio_transcriptions(Some(since), Some(now), None, MAX_TRANSCRIPTS, 0)
+        .await?
+        .into_iter()
+        .filter(|transcript| !transcript.transcription.trim().is_empty())
+        .collect();
+    let speech = transcripts.len() >= MIN_TRANSCRIPTS;
+
+    match db.get_open_meeting_session().await? {
+        None => {
+            let in_front = meeting_frames(&frames, true);
+            let Some(first_frame) = in_front.first().filter(|_| speech) else {
+                return Ok(None);
+            };
+            let start_time = first_frame.timestamp.min(transcripts[0].timestamp);
+            let end_time = in_front
+                .iter()
+                .map(|frame| frame.timestamp)
+                .chain(transcripts.iter().map(|transcript| transcript.timestamp))
+                .max()
+                .unwrap_or(now);
+            let session = db
+                .insert_meeting_session(start_time, end_time, first_frame.app_name.as_deref())
+                .await?;
+            Ok(Some(MeetingChange::Started(session)))
+        }
+        Some(mut session) => {
+            let on_screen = meeting_frames(&frames, false);
+            if speech && !on_screen.is_empty() {
+                let end_time = on_screen
+                    .iter()
+                    .map(|frame| frame.timestamp)
+                    .chain(transcripts.iter().map(|transcript| transcript.timestamp))
+                    .max()
+                    .unwrap_or(now);
+                if end_time > session.end_time {
+                    db.extend_meeting_session(session.id, end_time).await?;
+                    session.end_time = end_time;
+                    return Ok(Some(MeetingChange::Continued(session)));
+                }
+            } else if now - session.end_time >= END_AFTER {
+                db.end_meeting_session(session.id).await?;
+                session.ended = true;
+                return Ok(Some(MeetingChange::Ended(session)));
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Keeps detecting meetings until a shutdown is signalled. `meeting_started`
+/// and `meeting_ended` events are sent with the session.
+pub async fn run_meeting_detector(
+    db: Arc<DatabaseManager>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!("starting meeting detection");
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                match check_meeting(&db, Utc::now()).await {
+                    Ok(Some(MeetingChange::Started(session))) => {
+                        info!(
+                            "meeting {} started in {}",
+                            session.id,
+                            session.app_name.as_deref().unwrap_or("an unknown app")
+                        );
+                        if let Err(e) = send_event("meeting_started", session) {
+                            error!("failed to send meeting started event: {}", e);
+                        }
+                    }
+                    Ok(Some(MeetingChange::Ended(session))) => {
+                        info!("meeting {} ended", session.id);
+                        if let Err(e) = send_event("meeting_ended", session) {
+                            error!("failed to send meeting ended event: {}", e);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("meeting detection failed: {}", e),
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping meeting detection");
+                break;
+            }
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct MeetingsQuery {
+    #[serde(flatten)]
+    pagination: PaginationQuery,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+}
+
+fn meeting_error(status: StatusCode, error: String) -> (StatusCode, JsonResponse<Value>) {
+    (
+        status,
+        JsonResponse(json!({"error": error, "success": false})),
+    )
+}
+
+/// The meeting with the id, with its participants.
+async fn find_meeting(
+    db: &DatabaseManager,
+    id: i64,
+) -> Result<MeetingDetails, (StatusCode, JsonResponse<Value>)> {
+    let internal = |e: anyhow::Error| {
+        error!("failed to read meeting {}: {}", id, e);
+        meeting_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to read meeting: {}", e),
+        )
+    };
+    let session = db
+        .get_meeting_session(id)
+        .await
+        .map_err(|e| internal(e.into()))?
+        .ok_or_else(|| meeting_error(StatusCode::NOT_FOUND, "meeting not found".to_string()))?;
+    MeetingDetails::load(db, session).await.map_err(internal)
+}
+
+/// Detected meetings overlapping a time range with their participants, the
+/// latest first. The one going on isn't `ended` yet.
+#[oasgen]
+pub(crate) async fn list_meetings_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<MeetingsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let internal = |e: anyhow::Error| {
+        error!("failed to list meetings: {}", e);
+        meeting_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to list meetings: {}", e),
+        )
+    };
+    let sessions = state
+        .db
+        .list_meeting_sessions(
+            query.start_time,
+            query.end_time,
+            query.pagination.limit,
+            query.pagination.offset,
+        )
+        .await
+        .map_err(|e| internal(e.into()))?;
+    let mut meetings = Vec::with_capacity(sessions.len());
+    for session in sessions {
+        meetings.push(
+            MeetingDetails::load(&state.db, session)
+                .await
+                .map_err(internal)?,
+        );
+    }
+    Ok(JsonResponse(json!({
+        "data": meetings,
+        "success": true
+    })))
+}
+
+#[oasgen]
+pub(crate) async fn get_meeting_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let meeting = find_meeting(&state.db, id).await?;
+    Ok(JsonResponse(json!({
+        "data": meeting,
+        "success": true
+    })))
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct MeetingTranscriptQuery {
+    /// markdown (the default), srt, vtt or jsonl
+    #[serde(default)]
+    format: Option<String>,
+}
+
+/// Everything said during a meeting, as exported by `/export` for its time
+/// range. Markdown lists each line with its time and speaker.
+#[oasgen]
+pub(crate) async fn get_meeting_transcript_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Query(query): Query<MeetingTranscriptQuery>,
+) -> Result<Response<Body>, (StatusCode, JsonResponse<Value>)> {
+    let format: ExportFormat = query
+        .format
+        .as_deref()
+        .unwrap_or("markdown")
+        .parse()
+        .map_err(|e: anyhow::Error| meeting_error(StatusCode::BAD_REQUEST, e.to_string()))?;
+    let meeting = find_meeting(&state.db, id).await?;
+
+    let body = export(
+        &state.db,
+        format,
+        meeting.session.start_time,
+        meeting.session.end_time,
+        &ExportFilter::default(),
+    )
+    .await
+    .map_err(|e| {
+        error!("failed to export meeting {}: {}", id, e);
+        meeting_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to export meeting: {}", e),
+        )
+    })?;
+
+    Response::builder()
+        .header("content-type", format.content_type())
+        .header(
+            "content-disposition",
+            format!(
+                "attachment; filename=\"screenpipe-meeting-{}.{}\"",
+                id,
+                format.extension()
+            ),
+        )
+        .body(Body::from(body))
+        .map_err(|e| {
+            meeting_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to create response: {}", e),
+            )
+        })
+}
@@ -0,0 +1,60 @@
+//! `/metrics` in the Prometheus text format. Most metrics are updated by the
+//! capture pipelines as they go, the size of the screenpipe directory and
+//! the deferred OCR backlog are measured when scraped.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use screenpipe_core::metrics::{self, DEFERRED_OCR_PENDING, DISK_USAGE};
+
+use crate::ocr_queue::queue_status;
+use crate::server::AppState;
+use crate::storage::directory_size;
+
+/// How long the size of the screenpipe directory is reused for, walking it
+/// on every scrape would be slow with months of recordings.
+const DISK_USAGE_TTL: Duration = Duration::from_secs(60);
+
+static LAST_DISK_USAGE: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+
+/// Every metric of the recorder, as served on `/metrics`.
+pub async fn render_metrics(screenpipe_dir: &Path) -> String {
+    let output_path = screenpipe_dir.join("data");
+    let pending = queue_status(&output_path.to_string_lossy()).pending;
+    DEFERRED_OCR_PENDING.with_labels(&[]).set(pending as f64);
+
+    let stale = {
+        let mut measured_at = LAST_DISK_USAGE.lock().unwrap_or_else(|e| e.into_inner());
+        let stale = !measured_at.is_some_and(|at| at.elapsed() < DISK_USAGE_TTL);
+        if stale {
+            *measured_at = Some(Instant::now());
+        }
+        stale
+    };
+    if stale {
+        let dir = screenpipe_dir.to_path_buf();
+        if let Ok(size) = tokio::task::spawn_blocking(move || directory_size(&dir)).await {
+            DISK_USAGE.with_labels(&[]).set(size as f64);
+        }
+    }
+
+    metrics::render()
+}
+
+// prometheus text format, which openapi can't describe
+pub(crate) async fn metrics_handler(State(state): State<Arc<AppState>>) -> Response {
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        render_metrics(&state.screenpipe_dir).await,
+    )
+        .into_response()
+}
@@ -0,0 +1,279 @@
+//! Daily notes written into an Obsidian vault, every
+//! `--obsidian-interval-minutes` when `--obsidian-vault` is set or on demand
+//! by pipes through `/pipes/obsidian/export`.
+//!
+//! A note is rendered from a template and written between two markers, so
+//! what the user adds to the note around them is kept when it's written
+//! again, and notes of the same day made by other plugins get the block
+//! appended.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use axum::{extract::State, http::StatusCode, response::Json as JsonResponse};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_db::DatabaseManager;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+use crate::export::{export_daily_notes, local_day_start, DailyNote, ExportFilter};
+use crate::server::AppState;
+
+const START_MARKER: &str = "<!-- screenpipe:start -->";
+const END_MARKER: &str = "<!-- screenpipe:end -->";
+
+/// Template of a note. `{{date}}`, `{{yesterday}}` and `{{tomorrow}}` are
+/// dates like 2025-04-14, the other placeholders Markdown lists.
+pub const DEFAULT_TEMPLATE: &str = "#screenpipe
+
+← [[{{yesterday}}]] · [[{{tomorrow}}]] →
+
+## apps
+
+{{apps}}
+## screen
+
+{{screen}}
+## transcripts
+
+{{transcripts}}";
+
+#[derive(Debug, Clone)]
+pub struct ObsidianConfig {
+    pub vault: PathBuf,
+    /// Folder of the vault notes are written to
+    pub folder: String,
+    pub template: String,
+    /// Base url of the api screen activities link their frame on
+    pub frame_links: Option<String>,
+}
+
+impl ObsidianConfig {
+    pub fn new(vault: PathBuf) -> Self {
+        ObsidianConfig {
+            vault,
+            folder: "screenpipe".to_string(),
+            template: DEFAULT_TEMPLATE.to_string(),
+            frame_links: None,
+        }
+    }
+
+    pub fn with_folder(mut self, folder: String) -> Self {
+        self.folder = folder;
+        self
+    }
+
+    pub fn with_template(mut self, template: String) -> Self {
+        self.template = template;
+        self
+    }
+
+    pub fn with_frame_links(mut self, base_url: String) -> Self {
+        self.frame_links = Some(base_url);
+        self
+    }
+
+    /// Where the note of `date` is written, named like Obsidian's daily notes.
+    pub fn note_path(&self, date: NaiveDate) -> PathBuf {
+        self.vault
+            .join(&self.folder)
+            .join(format!("{}.md", date.format("%Y-%m-%d")))
+    }
+}
+
+fn or_nothing(section: &str) -> &str {
+    if section.is_empty() {
+        "_nothing recorded_\n"
+    } else {
+        section
+    }
+}
+
+/// Fills the placeholders of `template` with the note.
+pub fn render_template(template: &str, note: &DailyNote) -> String {
+    let day = |date: Option<NaiveDate>| {
+        date.map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    };
+    template
+        .replace("{{date}}", &day(Some(note.date)))
+        .replace("{{yesterday}}", &day(note.date.pred_opt()))
+        .replace("{{tomorrow}}", &day(note.date.succ_opt()))
+        .replace("{{apps}}", or_nothing(&note.apps))
+        .replace("{{screen}}", or_nothing(&note.screen))
+        .replace("{{transcripts}}", or_nothing(&note.transcripts))
+}
+
+/// Puts `block` between the markers of `existing`, or after it when they
+/// aren't there.
+pub fn merge_note(existing: Option<&str>, block: &str) -> String {
+    let block = format!("{}\n{}\n{}", START_MARKER, block.trim_end(), END_MARKER);
+    let Some(existing) = existing else {
+        return block + "\n";
+    };
+    if let Some(start) = existing.find(START_MARKER) {
+        if let Some(end) = existing[start..].find(END_MARKER) {
+            let end = start + end + END_MARKER.len();
+            return format!("{}{}{}", &existing[..start], block, &existing[end..]);
+        }
+    }
+    format!("{}\n\n{}\n", existing.trim_end(), block)
+}
+
+fn write_note(path: &Path, block: &str) -> Result<()> {
+    let existing = match fs::read_to_string(path) {
+        Ok(existing) => Some(existing),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    fs::write(path, merge_note(existing.as_deref(), block))
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Writes the notes of the days between `start` and `end`, returning their
+/// paths.
+pub async fn write_notes(
+    db: &DatabaseManager,
+    config: &ObsidianConfig,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<PathBuf>> {
+    if !config.vault.is_dir() {
+        anyhow::bail!("vault {} is not a directory", config.vault.display());
+    }
+    let notes = export_daily_notes(
+        db,
+        start,
+        end,
+        &ExportFilter::default(),
+        config.frame_links.as_deref(),
+    )
+    .await?;
+    fs::create_dir_all(config.vault.join(&config.folder))?;
+
+    let mut paths = Vec::new();
+    for note in notes.values() {
+        let path = config.note_path(note.date);
+        write_note(&path, &render_template(&config.template, note))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+pub async fn run_obsidian_export(
+    db: Arc<DatabaseManager>,
+    config: ObsidianConfig,
+    interval: Duration,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!(
+        "writing daily notes to {} every {} minutes",
+        config.vault.join(&config.folder).display(),
+        interval.as_secs() / 60
+    );
+
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                // yesterday's note gets what was still processing at midnight
+                let today = Local::now().date_naive();
+                let start = match local_day_start(today.pred_opt().unwrap_or(today)) {
+                    Ok(start) => start,
+                    Err(e) => {
+                        error!("failed to write daily notes: {}", e);
+                        continue;
+                    }
+                };
+                match write_notes(&db, &config, start, Utc::now()).await {
+                    Ok(paths) => info!("wrote {} daily notes", paths.len()),
+                    Err(e) => error!("failed to write daily notes: {}", e),
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping daily notes");
+                break;
+            }
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct ObsidianExportRequest {
+    vault_path: String,
+    /// Folder of the vault, `screenpipe` by default
+    #[serde(default)]
+    folder: Option<String>,
+    /// Template of a note, see `obsidian::DEFAULT_TEMPLATE`
+    #[serde(default)]
+    template: Option<String>,
+    /// Start of today by default
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+}
+
+/// Writes the daily notes of a time range into an Obsidian vault, for pipes
+/// keeping one up to date.
+#[oasgen]
+pub(crate) async fn obsidian_export_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(request): JsonResponse<ObsidianExportRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let bad_request = |error: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": error, "success": false})),
+        )
+    };
+    let vault_path = PathBuf::from(&request.vault_path);
+    if !vault_path.is_dir() {
+        return Err(bad_request(format!(
+            "vault {} is not a directory",
+            vault_path.display()
+        )));
+    }
+    let start = match request.start_time {
+        Some(start) => start,
+        None => local_day_start(chrono::Local::now().date_naive())
+            .map_err(|e| bad_request(e.to_string()))?,
+    };
+    let end = request.end_time.unwrap_or_else(Utc::now);
+    if end < start {
+        return Err(bad_request(
+            "end_time must not be before start_time".to_string(),
+        ));
+    }
+
+    let mut config = ObsidianConfig::new(vault_path)
+        .with_frame_links(format!("http://localhost:{}", state.port));
+    if let Some(folder) = request.folder {
+        config = config.with_folder(folder);
+    }
+    if let Some(template) = request.template {
+        config = config.with_template(template);
+    }
+
+    let notes = write_notes(&state.db, &config, start, end)
+        .await
+        .map_err(|e| {
+            error!("failed to write daily notes: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to write daily notes: {}", e), "success": false}),
+                ),
+            )
+        })?;
+    Ok(JsonResponse(json!({
+        "data": { "notes": notes },
+        "success": true
+    })))
+}
@@ -0,0 +1,291 @@
+//! Queue of windows whose OCR was skipped while capturing, in deferred mode
+//! or on battery. Their images are saved next to the recordings, encrypted
+//! like them with `--encrypt-data`, and a background worker OCRs them in
+//! small batches when it's allowed to.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{extract::State, http::StatusCode, response::Json as JsonResponse};
+use chrono::{DateTime, Utc};
+use image::{DynamicImage, ImageFormat};
+use oasgen::{oasgen, OaSchema};
+use once_cell::sync::Lazy;
+use screenpipe_core::encryption::{media_cipher, readable_media};
+use screenpipe_core::idle::is_idle;
+use screenpipe_core::ocr_mode::{ocr_mode, OcrMode};
+use screenpipe_core::power::power_status;
+use screenpipe_core::Language;
+use screenpipe_db::DatabaseManager;
+use screenpipe_vision::core::ocr_deferred_image;
+use screenpipe_vision::OcrEngine;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, warn};
+
+use crate::redaction::{redact_deferred_ocr, Redaction};
+use crate::server::AppState;
+
+/// How often the worker checks for queued windows.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Windows OCR'd per check, so catching up doesn't spike the CPU.
+const BATCH_SIZE: usize = 20;
+/// Times a window is tried before it's dropped, when the OCR or the
+/// database keep failing.
+const MAX_ATTEMPTS: u32 = 3;
+
+#[derive(Default)]
+struct QueueStats {
+    processed: u64,
+    failed: u64,
+    busy: Duration,
+    last_processed_at: Option<DateTime<Utc>>,
+    /// Failed tries of the windows still queued, by frame id
+    attempts: HashMap<i64, u32>,
+}
+
+/// Why a queued window wasn't OCR'd.
+enum WindowError {
+    /// The saved image can't be decoded, trying again won't help
+    Unreadable(anyhow::Error),
+    Failed(anyhow::Error),
+}
+
+static STATS: Lazy<Mutex<QueueStats>> = Lazy::new(|| Mutex::new(QueueStats::default()));
+
+#[derive(OaSchema, Serialize, Debug, Clone)]
+pub struct OcrQueueStatus {
+    /// "realtime" or "deferred"
+    pub mode: String,
+    /// Windows waiting for OCR
+    pub pending: usize,
+    /// Whether the worker is allowed to OCR right now
+    pub processing: bool,
+    /// Windows OCR'd since startup
+    pub processed: u64,
+    pub failed: u64,
+    /// Windows OCR'd per minute of work, `None` before the first one
+    pub windows_per_minute: Option<f64>,
+    pub last_processed_at: Option<DateTime<Utc>>,
+}
+
+/// Where windows waiting for OCR are saved, named after their frame id.
+pub fn deferred_ocr_dir(output_path: &str) -> PathBuf {
+    Path::new(output_path).join("deferred_ocr")
+}
+
+/// Saves a window whose OCR was deferred, to be OCR'd by the worker. It is
+/// encrypted when a media key is unlocked.
+pub async fn save_deferred_ocr(
+    output_path: &str,
+    frame_id: i64,
+    image: &DynamicImage,
+) -> anyhow::Result<()> {
+    let dir = deferred_ocr_dir(output_path);
+    let image = image.clone();
+    tokio::task::spawn_blocking(move || {
+        std::fs::create_dir_all(&dir)?;
+        let mut jpeg = Vec::new();
+        image
+            .to_rgb8()
+            .write_to(&mut std::io::Cursor::new(&mut jpeg), ImageFormat::Jpeg)?;
+        let data = match media_cipher() {
+            Some(cipher) => cipher.encrypt(&jpeg)?,
+            None => jpeg,
+        };
+        std::fs::write(dir.join(format!("{}.jpg", frame_id)), data)?;
+        Ok(())
+    })
+    .await?
+}
+
+/// Whether queued windows can be OCR'd now. Nothing is OCR'd while saving
+/// battery, and in deferred mode only while the user is idle or a laptop is
+/// plugged in.
+pub fn can_process() -> bool {
+    let power = power_status();
+    if power.ocr_deferred {
+        return false;
+    }
+    match ocr_mode() {
+        OcrMode::Realtime => true,
+        OcrMode::Deferred => is_idle() || (!power.on_battery && power.battery_percent.is_some()),
+    }
+}
+
+/// Backlog and throughput of the queue.
+pub fn queue_status(output_path: &str) -> OcrQueueStatus {
+    let pending = std::fs::read_dir(deferred_ocr_dir(output_path))
+        .map(|entries| entries.filter_map(Result::ok).count())
+        .unwrap_or(0);
+    let stats = STATS.lock().unwrap_or_else(|e| e.into_inner());
+    OcrQueueStatus {
+        mode: ocr_mode().to_string(),
+        pending,
+        processing: can_process(),
+        processed: stats.processed,
+        failed: stats.failed,
+        windows_per_minute: (stats.processed > 0 && !stats.busy.is_zero())
+            .then(|| stats.processed as f64 / stats.busy.as_secs_f64() * 60.0),
+        last_processed_at: stats.last_processed_at,
+    }
+}
+
+/// OCRs queued windows until a shutdown is signalled.
+pub async fn run_ocr_queue(
+    db: Arc<DatabaseManager>,
+    ocr_engine: Arc<OcrEngine>,
+    languages: Vec<Language>,
+    redaction: Option<Redaction>,
+    output_path: String,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if can_process() {
+                    run_batch(&db, &ocr_engine, &languages, redaction.as_ref(), &output_path).await;
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping ocr queue");
+                break;
+            }
+        }
+    }
+}
+
+/// OCRs a batch of the queued windows.
+async fn run_batch(
+    db: &DatabaseManager,
+    ocr_engine: &Arc<OcrEngine>,
+    languages: &[Language],
+    redaction: Option<&Redaction>,
+    output_path: &str,
+) {
+    let dir = deferred_ocr_dir(output_path);
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return;
+    };
+    let pending: Vec<(i64, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let frame_id = path.file_stem()?.to_str()?.parse().ok()?;
+            Some((frame_id, path))
+        })
+        .take(BATCH_SIZE)
+        .collect();
+    if pending.is_empty() {
+        return;
+    }
+    debug!("running deferred ocr for {} windows", pending.len());
+
+    for (frame_id, path) in pending {
+        let start = Instant::now();
+        let result = ocr_window(db, ocr_engine, languages, redaction, frame_id, &path).await;
+        let done = {
+            let mut stats = STATS.lock().unwrap_or_else(|e| e.into_inner());
+            stats.busy += start.elapsed();
+            match result {
+                Ok(()) => {
+                    stats.attempts.remove(&frame_id);
+                    stats.processed += 1;
+                    stats.last_processed_at = Some(Utc::now());
+                    true
+                }
+                Err(WindowError::Unreadable(e)) => {
+                    error!("dropping frame {} from the ocr queue: {}", frame_id, e);
+                    stats.attempts.remove(&frame_id);
+                    stats.failed += 1;
+                    true
+                }
+                Err(WindowError::Failed(e)) => {
+                    let attempts = stats.attempts.entry(frame_id).or_default();
+                    *attempts += 1;
+                    if *attempts < MAX_ATTEMPTS {
+                        warn!(
+                            "deferred ocr failed for frame {}, will retry: {}",
+                            frame_id, e
+                        );
+                        false
+                    } else {
+                        error!(
+                            "deferred ocr failed {} times for frame {}, giving up: {}",
+                            MAX_ATTEMPTS, frame_id, e
+                        );
+                        stats.attempts.remove(&frame_id);
+                        stats.failed += 1;
+                        true
+                    }
+                }
+            }
+        };
+        if done {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("failed to remove {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+async fn ocr_window(
+    db: &DatabaseManager,
+    ocr_engine: &Arc<OcrEngine>,
+    languages: &[Language],
+    redaction: Option<&Redaction>,
+    frame_id: i64,
+    path: &Path,
+) -> Result<(), WindowError> {
+    let media = readable_media(path).await.map_err(WindowError::Failed)?;
+    let image = tokio::task::spawn_blocking(move || image::open(media.path()))
+        .await
+        .map_err(|e| WindowError::Failed(e.into()))?
+        .map_err(|e| WindowError::Unreadable(e.into()))?;
+    let mut ocr = ocr_deferred_image(&image, ocr_engine, languages.to_vec())
+        .await
+        .map_err(|e| WindowError::Failed(anyhow::anyhow!("{}", e)))?;
+    if let Some(redaction) = redaction {
+        redact_deferred_ocr(&mut ocr, &redaction.redactor);
+    }
+
+    let text_json = serde_json::to_string(&ocr.text_json).unwrap_or_default();
+    let stored = db
+        .replace_ocr_text(
+            frame_id,
+            &ocr.text,
+            &text_json,
+            Arc::new((**ocr_engine).clone().into()),
+        )
+        .await
+        .map_err(|e| WindowError::Failed(e.into()))?;
+    // the frame was deleted while waiting
+    if !stored {
+        return Ok(());
+    }
+    db.insert_ocr_blocks(frame_id, &ocr.layout)
+        .await
+        .map_err(|e| WindowError::Failed(e.into()))?;
+    db.insert_ocr_tables(frame_id, &ocr.tables)
+        .await
+        .map_err(|e| WindowError::Failed(e.into()))?;
+    Ok(())
+}
+
+/// Backlog and throughput of the deferred OCR queue.
+#[oasgen]
+pub(crate) async fn ocr_queue_status_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let output_path = state.screenpipe_dir.join("data");
+    let status = queue_status(&output_path.to_string_lossy());
+    Ok(JsonResponse(json!({
+        "data": status,
+        "success": true
+    })))
+}
@@ -0,0 +1,285 @@
+//! Offload of old recordings to S3 compatible object storage (AWS, MinIO,
+//! Backblaze B2...), enabled with `--offload-bucket`. Chunks last recorded
+//! to more than `--offload-after-days` ago are uploaded and removed from
+//! disk, their rows and text stay in the local database. Reads fetch them
+//! back into a local cache through [`readable_media`].
+//!
+//! Files are uploaded as they are on disk, so recordings encrypted at rest
+//! stay encrypted in the bucket.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use once_cell::sync::OnceCell;
+use s3::creds::Credentials;
+use s3::{Bucket, Region};
+use screenpipe_core::encryption::{self, is_encrypted_file, media_cipher, ReadableMedia};
+use screenpipe_db::{DatabaseManager, MediaType};
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, warn};
+
+static OBJECT_STORE: OnceCell<ObjectStore> = OnceCell::new();
+
+/// How often old chunks are looked for.
+const OFFLOAD_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// Chunks uploaded per media type and round.
+const OFFLOAD_BATCH: u32 = 20;
+/// Fetched recordings not read for this long are removed from the cache.
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone)]
+pub struct OffloadConfig {
+    pub bucket: String,
+    /// Endpoint of a non-AWS service, e.g. `http://localhost:9000` for MinIO
+    pub endpoint: Option<String>,
+    pub region: String,
+    /// Prepended to the file names of the recordings to get their keys
+    pub prefix: String,
+    /// Read from the environment or the AWS config files if not set
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+}
+
+/// The bucket recordings are offloaded to, with the cache they are fetched
+/// back into.
+pub struct ObjectStore {
+    bucket: Box<Bucket>,
+    prefix: String,
+    cache_dir: PathBuf,
+}
+
+impl ObjectStore {
+    pub fn new(config: &OffloadConfig, cache_dir: PathBuf) -> Result<Self> {
+        let region = match &config.endpoint {
+            Some(endpoint) => Region::Custom {
+                region: config.region.clone(),
+                endpoint: endpoint.trim_end_matches('/').to_string(),
+            },
+            None => config.region.parse()?,
+        };
+        let credentials = match (&config.access_key, &config.secret_key) {
+            (Some(access_key), Some(secret_key)) => Credentials::new(
+                Some(access_key.as_str()),
+                Some(secret_key.as_str()),
+                None,
+                None,
+                None,
+            )?,
+            _ => Credentials::default().context("no object storage credentials found")?,
+        };
+        let mut bucket = Bucket::new(&config.bucket, region, credentials)?;
+        if config.endpoint.is_some() {
+            // self-hosted services rarely have a wildcard dns for buckets
+            bucket = bucket.with_path_style();
+        }
+        Ok(Self {
+            bucket,
+            prefix: config.prefix.clone(),
+            cache_dir,
+        })
+    }
+
+    /// Key of the recording at `file_path`. File names are unique within a
+    /// data directory, they carry the device and the time of the chunk.
+    pub fn key(&self, file_path: impl AsRef<Path>) -> String {
+        let name = file_path
+            .as_ref()
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        format!("{}{}", self.prefix, name)
+    }
+
+    /// Where a fetched copy of the recording at `file_path` is kept.
+    pub fn cache_path(&self, file_path: impl AsRef<Path>) -> PathBuf {
+        self.cache_dir.join(self.key(file_path).replace('/', "_"))
+    }
+
+    pub async fn upload(&self, file_path: &Path) -> Result<()> {
+        let mut file = tokio::fs::File::open(file_path).await?;
+        self.bucket
+            .put_object_stream(&mut file, self.key(file_path))
+            .await?;
+        Ok(())
+    }
+
+    /// Downloads the recording at `file_path` into the cache unless it is
+    /// there already, returning the cached copy.
+    pub async fn fetch(&self, file_path: &Path) -> Result<PathBuf> {
+        let cached = self.cache_path(file_path);
+        if cached.exists() {
+            return Ok(cached);
+        }
+
+        tokio::fs::create_dir_all(&self.cache_dir).await?;
+        // downloaded next to the cached copy and renamed, so concurrent reads
+        // never see a partial file
+        let partial = cached.with_extension(format!("part-{}", uuid::Uuid::new_v4()));
+        let key = self.key(file_path);
+        let download = async {
+            let mut file = tokio::fs::File::create(&partial).await?;
+            let status = self.bucket.get_object_to_writer(&key, &mut file).await?;
+            if status != 200 {
+                anyhow::bail!("object storage returned {} for {}", status, key);
+            }
+            tokio::fs::rename(&partial, &cached).await?;
+            Ok(())
+        };
+        if let Err(e) = download.await {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+        debug!("fetched {} from object storage", key);
+        Ok(cached)
+    }
+
+    /// Deletes the recording at `file_path` from the bucket and the cache.
+    pub async fn delete(&self, file_path: &Path) -> Result<()> {
+        self.bucket.delete_object(self.key(file_path)).await?;
+        match tokio::fs::remove_file(self.cache_path(file_path)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Removes fetched recordings that weren't read for [`CACHE_TTL`].
+    pub async fn clean_cache(&self) -> Result<usize> {
+        let mut removed = 0;
+        let mut entries = match tokio::fs::read_dir(&self.cache_dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            let last_used = metadata.accessed().or_else(|_| metadata.modified())?;
+            let age = SystemTime::now()
+                .duration_since(last_used)
+                .unwrap_or_default();
+            if age > CACHE_TTL {
+                tokio::fs::remove_file(entry.path()).await?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Makes [`readable_media`] fetch offloaded recordings from `store`.
+pub fn set_object_store(store: ObjectStore) -> Result<&'static ObjectStore> {
+    OBJECT_STORE
+        .set(store)
+        .map_err(|_| anyhow::anyhow!("object storage is already set up"))?;
+    Ok(OBJECT_STORE.get().expect("object store was just set"))
+}
+
+pub fn object_store() -> Option<&'static ObjectStore> {
+    OBJECT_STORE.get()
+}
+
+/// Gives a readable version of the recording at `path` like
+/// [`encryption::readable_media`], fetching it from object storage first if
+/// it was offloaded.
+pub async fn readable_media(path: impl AsRef<Path>) -> Result<ReadableMedia> {
+    let path = path.as_ref();
+    match object_store() {
+        Some(store) if !path.exists() => {
+            let cached = store
+                .fetch(path)
+                .await
+                .with_context(|| format!("failed to fetch offloaded {}", path.display()))?;
+            encryption::readable_media(cached).await
+        }
+        _ => encryption::readable_media(path).await,
+    }
+}
+
+/// Uploads one batch of chunks last recorded to before `after` ago of each
+/// media type and removes them from disk. Returns how many files were
+/// offloaded.
+pub async fn offload_old_chunks(
+    db: &DatabaseManager,
+    store: &ObjectStore,
+    after: Duration,
+) -> Result<usize> {
+    let before = Utc::now() - chrono::Duration::from_std(after)?;
+    let mut offloaded = 0;
+
+    for media_type in [MediaType::Video, MediaType::Audio] {
+        let chunks = db
+            .get_local_media_chunks_before(media_type, before, OFFLOAD_BATCH)
+            .await?;
+        let mut ids = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            let path = PathBuf::from(&chunk.file_path);
+            if !path.exists() {
+                debug!("{} no longer exists", chunk.file_path);
+                ids.push(chunk.id);
+                continue;
+            }
+            // leave plain files to the encryption task rather than uploading
+            // them in the clear
+            if media_cipher().is_some() && !is_encrypted_file(&path).unwrap_or(false) {
+                debug!("{} is not encrypted yet", chunk.file_path);
+                continue;
+            }
+            if let Err(e) = store.upload(&path).await {
+                warn!("failed to offload {}: {}", chunk.file_path, e);
+                continue;
+            }
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                warn!("failed to delete offloaded {}: {}", chunk.file_path, e);
+            }
+            offloaded += 1;
+            ids.push(chunk.id);
+        }
+        if !ids.is_empty() {
+            db.mark_media_chunks_offloaded(media_type, &ids).await?;
+        }
+    }
+
+    Ok(offloaded)
+}
+
+/// Keeps offloading old chunks and cleaning the cache of fetched ones until
+/// a shutdown is signalled.
+pub async fn run_offload(
+    db: Arc<DatabaseManager>,
+    store: &'static ObjectStore,
+    after_days: u32,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!(
+        "offloading recordings older than {} days to object storage",
+        after_days
+    );
+    let after = Duration::from_secs(u64::from(after_days) * 24 * 60 * 60);
+
+    loop {
+        if let Err(e) = store.clean_cache().await {
+            warn!("failed to clean the cache of offloaded recordings: {}", e);
+        }
+        let wait = match offload_old_chunks(&db, store, after).await {
+            Ok(0) => OFFLOAD_INTERVAL,
+            Ok(count) => {
+                debug!("offloaded {} recordings", count);
+                Duration::ZERO
+            }
+            Err(e) => {
+                error!("failed to offload recordings: {}", e);
+                OFFLOAD_INTERVAL
+            }
+        };
+
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping offload of recordings");
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Moves months of recordings out of the main database once they're old
+//! enough, see [`DatabaseManager::archive_partition`]. Complete months are
+//! archived oldest first, the current one never is.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::{DateTime, Datelike, Utc};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_db::{DatabaseManager, Partition, PartitionMonth};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+use crate::retention::remove_media_files;
+use crate::server::AppState;
+
+/// How often old months are looked for.
+const PARTITION_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Where the partition files of the data directory are kept.
+pub fn partitions_dir(screenpipe_dir: &Path) -> PathBuf {
+    screenpipe_dir.join("partitions")
+}
+
+/// First month kept in the main database when months older than
+/// `after_months` before the one of `now` are archived.
+pub fn partition_cutoff(now: DateTime<Utc>, after_months: u32) -> PartitionMonth {
+    let months = now.year() * 12 + now.month0() as i32 - after_months as i32;
+    PartitionMonth {
+        year: months.div_euclid(12),
+        month: months.rem_euclid(12) as u32 + 1,
+    }
+}
+
+/// Archives the months before [`partition_cutoff`] still in the main
+/// database, returns the partitions written.
+pub async fn archive_old_months(
+    db: &DatabaseManager,
+    dir: &Path,
+    after_months: u32,
+    now: DateTime<Utc>,
+) -> Result<Vec<Partition>> {
+    // the current month is still recorded to
+    let cutoff = partition_cutoff(now, after_months.max(1));
+    let mut archived = Vec::new();
+    let Some(mut month) = db.oldest_unpartitioned_month().await? else {
+        return Ok(archived);
+    };
+    while month < cutoff {
+        if let Some(partition) = db.archive_partition(month, dir).await? {
+            info!(
+                "archived {}: {} frames, {} transcriptions",
+                partition.month, partition.frames, partition.transcriptions
+            );
+            archived.push(partition);
+        }
+        month = month.next();
+    }
+    Ok(archived)
+}
+
+/// Archives months older than `after_months` once a day, until a shutdown is
+/// signalled.
+pub async fn run_partitioning(
+    db: Arc<DatabaseManager>,
+    dir: PathBuf,
+    after_months: u32,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    loop {
+        if let Err(e) = archive_old_months(&db, &dir, after_months, Utc::now()).await {
+            error!("failed to archive old months: {}", e);
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(PARTITION_INTERVAL) => {}
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping partitioning");
+                break;
+            }
+        }
+    }
+}
+
+fn partition_error(status: StatusCode, error: String) -> (StatusCode, JsonResponse<Value>) {
+    (
+        status,
+        JsonResponse(json!({"error": error, "success": false})),
+    )
+}
+
+fn parse_partition_month(month: &str) -> Result<PartitionMonth, (StatusCode, JsonResponse<Value>)> {
+    month
+        .parse()
+        .map_err(|e| partition_error(StatusCode::BAD_REQUEST, format!("invalid month: {}", e)))
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct ArchivePartitionRequest {
+    /// Month to move out of the main database, like `2025-01`
+    month: String,
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct PartitionSearchQuery {
+    q: String,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    /// 20 by default
+    #[serde(default)]
+    limit: Option<u32>,
+}
+
+/// Months moved out of the main database, oldest first.
+#[oasgen]
+pub(crate) async fn list_partitions_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.list_partitions().await {
+        Ok(partitions) => Ok(JsonResponse(json!({
+            "data": partitions,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to list partitions: {}", e);
+            Err(partition_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to list partitions: {}", e),
+            ))
+        }
+    }
+}
+
+/// Moves a past month into its own database file, `null` when nothing was
+/// recorded in it.
+#[oasgen]
+pub(crate) async fn archive_partition_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<ArchivePartitionRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let month = parse_partition_month(&payload.month)?;
+    if month >= PartitionMonth::of(Utc::now()) {
+        return Err(partition_error(
+            StatusCode::BAD_REQUEST,
+            format!("{} is still being recorded", month),
+        ));
+    }
+
+    let dir = partitions_dir(&state.screenpipe_dir);
+    match state.db.archive_partition(month, &dir).await {
+        Ok(partition) => Ok(JsonResponse(json!({
+            "data": partition,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to archive {}: {}", month, e);
+            Err(partition_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to archive {}: {}", month, e),
+            ))
+        }
+    }
+}
+
+/// Deletes a partition with its recordings.
+#[oasgen]
+pub(crate) async fn drop_partition_handler(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(month): axum::extract::Path<String>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let month = parse_partition_month(&month)?;
+    match state.db.drop_partition(month).await {
+        Ok(Some(dropped)) => {
+            remove_media_files(&dropped.video_chunks, false).await;
+            remove_media_files(&dropped.audio_chunks, false).await;
+            Ok(JsonResponse(json!({
+                "data": dropped.partition,
+                "success": true
+            })))
+        }
+        Ok(None) => Err(partition_error(
+            StatusCode::NOT_FOUND,
+            format!("{} isn't partitioned", month),
+        )),
+        Err(e) => {
+            error!("failed to drop partition {}: {}", month, e);
+            Err(partition_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to drop partition {}: {}", month, e),
+            ))
+        }
+    }
+}
+
+/// Text matching `q` in the partitions overlapping the time range, newest
+/// month first. The main database is searched with `/search`.
+#[oasgen]
+pub(crate) async fn search_partitions_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PartitionSearchQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state
+        .db
+        .search_partitions(
+            &query.q,
+            query.start_time,
+            query.end_time,
+            query.limit.unwrap_or(20),
+        )
+        .await
+    {
+        Ok(matches) => Ok(JsonResponse(json!({
+            "data": matches,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to search partitions: {}", e);
+            Err(partition_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to search partitions: {}", e),
+            ))
+        }
+    }
+}
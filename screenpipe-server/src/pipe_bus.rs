@@ -0,0 +1,208 @@
+//! Endpoints of the message bus pipes publish to and subscribe on, see
+//! [`screenpipe_core::pipe_bus`]. Pipes authenticate with the token they
+//! were started with.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Json, Query, State,
+    },
+    http::{HeaderMap, StatusCode},
+    response::{Json as JsonResponse, Response},
+};
+use futures::{SinkExt, StreamExt};
+use screenpipe_core::pipe_bus::{
+    check_topic, covers, pipe_of_token, publish as publish_on_bus, subscribe as subscribe_to_bus,
+    BusPermissions,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+use crate::server::AppState;
+
+/// Header pipes send the token they were started with in, or the
+/// `pipe_token` query parameter.
+const PIPE_TOKEN_HEADER: &str = "x-pipe-token";
+
+#[derive(Deserialize)]
+pub(crate) struct BusPublishRequest {
+    topic: String,
+    #[serde(default)]
+    payload: Value,
+    pipe_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct BusSubscribeQuery {
+    /// Comma separated topics, which can end with `*`
+    topics: String,
+    pipe_token: Option<String>,
+}
+
+fn bus_error(status: StatusCode, message: String) -> (StatusCode, JsonResponse<Value>) {
+    (
+        status,
+        JsonResponse(json!({
+            "error": message,
+            "success": false
+        })),
+    )
+}
+
+/// The pipe sending a bus request, from its token, with the topics it may
+/// use outside of its namespace.
+async fn bus_pipe(
+    state: &AppState,
+    headers: &HeaderMap,
+    pipe_token: Option<String>,
+) -> Result<(String, BusPermissions), (StatusCode, JsonResponse<Value>)> {
+    if !state.enable_pipe_manager {
+        return Err(bus_error(
+            StatusCode::FORBIDDEN,
+            "pipe functionality is disabled".to_string(),
+        ));
+    }
+    let token = headers
+        .get(PIPE_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .or(pipe_token);
+    let Some(pipe) = token.as_deref().and_then(pipe_of_token) else {
+        return Err(bus_error(
+            StatusCode::UNAUTHORIZED,
+            "missing or invalid pipe token".to_string(),
+        ));
+    };
+    let Some(info) = state.pipe_manager.get_pipe_info(&pipe).await else {
+        return Err(bus_error(
+            StatusCode::NOT_FOUND,
+            "pipe not found".to_string(),
+        ));
+    };
+    let permissions = BusPermissions::from_config(&info.config)
+        .map_err(|e| bus_error(StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    Ok((pipe, permissions))
+}
+
+/// Publishes a message from a pipe to the pipes subscribed to its topic.
+pub(crate) async fn bus_publish_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<BusPublishRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let (pipe, permissions) = bus_pipe(&state, &headers, request.pipe_token).await?;
+    check_topic(&request.topic, false)
+        .map_err(|e| bus_error(StatusCode::BAD_REQUEST, e.to_string()))?;
+    if !permissions.can_publish(&pipe, &request.topic) {
+        return Err(bus_error(
+            StatusCode::FORBIDDEN,
+            format!(
+                "pipe {} isn't allowed to publish on {}",
+                pipe, request.topic
+            ),
+        ));
+    }
+
+    publish_on_bus(&pipe, &request.topic, request.payload);
+    Ok(JsonResponse(json!({
+        "success": true
+    })))
+}
+
+/// Streams the messages of the topics a pipe subscribes to over a
+/// websocket.
+pub(crate) async fn bus_subscribe_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<BusSubscribeQuery>,
+) -> Result<Response, (StatusCode, JsonResponse<Value>)> {
+    let (pipe, permissions) = bus_pipe(&state, &headers, query.pipe_token).await?;
+    let topics: Vec<String> = query
+        .topics
+        .split(',')
+        .map(str::trim)
+        .filter(|topic| !topic.is_empty())
+        .map(str::to_string)
+        .collect();
+    if topics.is_empty() {
+        return Err(bus_error(
+            StatusCode::BAD_REQUEST,
+            "no topics to subscribe to".to_string(),
+        ));
+    }
+    for topic in &topics {
+        check_topic(topic, true).map_err(|e| bus_error(StatusCode::BAD_REQUEST, e.to_string()))?;
+        if !permissions.can_subscribe(&pipe, topic) {
+            return Err(bus_error(
+                StatusCode::FORBIDDEN,
+                format!("pipe {} isn't allowed to subscribe to {}", pipe, topic),
+            ));
+        }
+    }
+
+    Ok(ws.on_upgrade(move |socket| handle_bus_socket(socket, pipe, topics)))
+}
+
+async fn handle_bus_socket(socket: WebSocket, pipe: String, topics: Vec<String>) {
+    let (mut sender, mut receiver) = socket.split();
+
+    // only used to notice when the client goes away
+    let incoming = tokio::spawn(async move {
+        while let Some(Ok(msg)) = receiver.next().await {
+            if let Message::Close(_) = msg {
+                break;
+            }
+        }
+    });
+
+    let subscriber = pipe.clone();
+    let outgoing = tokio::spawn(async move {
+        let mut messages = subscribe_to_bus();
+        let mut ping = tokio::time::interval(Duration::from_secs(30));
+        loop {
+            tokio::select! {
+                message = messages.recv() => {
+                    let message = match message {
+                        Ok(message) => message,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!(
+                                "bus subscription of pipe {} skipped {} messages",
+                                subscriber, skipped
+                            );
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    if !topics.iter().any(|topic| covers(topic, &message.topic)) {
+                        continue;
+                    }
+                    if let Err(e) = sender
+                        .send(Message::Text(serde_json::to_string(&message).unwrap_or_default()))
+                        .await
+                    {
+                        debug!("failed to send bus message: {}", e);
+                        break;
+                    }
+                }
+                _ = ping.tick() => {
+                    if sender.send(Message::Ping(vec![])).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    tokio::select! {
+        _ = incoming => {}
+        _ = outgoing => {}
+    }
+
+    debug!("bus subscription of pipe {} closed", pipe);
+}
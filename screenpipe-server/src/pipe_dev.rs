@@ -0,0 +1,153 @@
+//! `screenpipe pipe dev`: installs a pipe from a local folder, runs it with
+//! `SCREENPIPE_DEV=1` and its logs in the terminal, and reinstalls and
+//! restarts it whenever its files change.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use screenpipe_core::pipe_logs::{subscribe as subscribe_to_pipe_logs, PipeLogEntry};
+use screenpipe_core::pipe_signing::digest_dir;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::pipe_manager::PipeManager;
+
+/// How often the files of the pipe are checked for changes.
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Wait after a change for the other files saved with it.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Files of the pipe at `path`, with their SHA-256, to notice changes. The
+/// ones not installed, like `node_modules` or build output, are left out.
+pub async fn snapshot(path: &Path) -> Result<BTreeMap<String, String>> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || digest_dir(&path)).await?
+}
+
+/// Sets `enabled` and `dev` in the pipe.json of `id`, without starting or
+/// stopping it.
+pub async fn set_dev(pipe_manager: &PipeManager, id: &str, dev: bool) -> Result<()> {
+    let config_path = pipe_manager
+        .screenpipe_dir()
+        .join("pipes")
+        .join(id)
+        .join("pipe.json");
+    let mut config: Value = if config_path.exists() {
+        serde_json::from_str(&tokio::fs::read_to_string(&config_path).await?)?
+    } else {
+        serde_json::json!({ "id": id })
+    };
+    config["enabled"] = Value::Bool(dev);
+    config["dev"] = Value::Bool(dev);
+    tokio::fs::write(&config_path, serde_json::to_string_pretty(&config)?).await?;
+    Ok(())
+}
+
+/// A log entry as printed in the terminal.
+pub fn format_log_entry(entry: &PipeLogEntry) -> String {
+    let mut line = format!(
+        "{} {:<5} {}",
+        entry.timestamp.format("%H:%M:%S%.3f"),
+        entry.level,
+        entry.message
+    );
+    if let Some(fields) = &entry.fields {
+        line.push(' ');
+        line.push_str(&fields.to_string());
+    }
+    line
+}
+
+/// (Re)installs the pipe at `path` and starts it, returning its id.
+async fn install_and_start(
+    pipe_manager: &PipeManager,
+    path: &Path,
+    id: Option<&str>,
+) -> Result<String> {
+    if let Some(id) = id {
+        pipe_manager.stop_pipe(id).await?;
+    }
+    let id = pipe_manager
+        .download_pipe(&path.to_string_lossy(), false)
+        .await?;
+    set_dev(pipe_manager, &id, true).await?;
+    // scheduled pipes too, so that they can be tried right away
+    let future = pipe_manager.start_pipe_task(id.clone()).await?;
+    let pipe = id.clone();
+    tokio::spawn(async move {
+        if let Err(e) = future.await {
+            eprintln!("pipe {} stopped: {}", pipe, e);
+        }
+    });
+    Ok(id)
+}
+
+/// Runs the pipe at `path` until `shutdown`, reloading it when its files
+/// change, then disables it.
+pub async fn run_pipe_dev(
+    pipe_manager: Arc<PipeManager>,
+    path: PathBuf,
+    shutdown: impl Future,
+) -> Result<()> {
+    if !path.is_dir() {
+        anyhow::bail!("{} isn't a folder", path.display());
+    }
+    let path = path.canonicalize()?;
+    tokio::pin!(shutdown);
+
+    let mut logs = subscribe_to_pipe_logs();
+    let mut files = snapshot(&path).await?;
+    println!("installing {}", path.display());
+    let id = install_and_start(&pipe_manager, &path, None).await?;
+    println!(
+        "running pipe {}, watching {} for changes (ctrl+c to stop)",
+        id,
+        path.display()
+    );
+
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let changed = match snapshot(&path).await {
+                    Ok(current) => current != files,
+                    Err(e) => {
+                        eprintln!("failed to read {}: {}", path.display(), e);
+                        false
+                    }
+                };
+                if !changed {
+                    continue;
+                }
+                tokio::time::sleep(DEBOUNCE).await;
+                if let Ok(current) = snapshot(&path).await {
+                    files = current;
+                }
+                println!("change detected, reloading pipe {}", id);
+                match install_and_start(&pipe_manager, &path, Some(&id)).await {
+                    Ok(_) => println!("pipe {} reloaded", id),
+                    Err(e) => eprintln!("failed to reload pipe {}: {:#}", id, e),
+                }
+            }
+            entry = logs.recv() => match entry {
+                Ok(entry) if entry.pipe == id => println!("{}", format_log_entry(&entry)),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    eprintln!("skipped {} log lines", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => {}
+            },
+            _ = &mut shutdown => break,
+        }
+    }
+
+    println!("stopping pipe {}", id);
+    pipe_manager.stop_pipe(&id).await?;
+    set_dev(&pipe_manager, &id, false).await?;
+    Ok(())
+}
@@ -0,0 +1,166 @@
+//! Pushes the events the running pipes subscribed to in their pipe.json over
+//! a local socket, see [`screenpipe_core::pipe_subscriptions`]. Wasm pipes
+//! don't have subscriptions.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use futures::StreamExt;
+use screenpipe_core::pipe_subscriptions::{events_socket_path, PipeSubscriptions};
+use screenpipe_events::{subscribe_to_all_events, EventSubscription};
+use serde_json::Value;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+/// Events queued for a connection before the next ones are dropped, so that
+/// a pipe not reading them doesn't hold back the others.
+pub const CONNECTION_QUEUE: usize = 100;
+
+/// Serves the events of a pipe until dropped.
+pub struct PipeEvents {
+    path: PathBuf,
+    task: JoinHandle<()>,
+}
+
+impl PipeEvents {
+    /// Starts serving the events `config` subscribes to, `None` for pipes
+    /// without subscriptions.
+    pub fn serve(screenpipe_dir: &Path, id: &str, config: &Value) -> Result<Option<Self>> {
+        let Some(subscriptions) = PipeSubscriptions::from_config(config)? else {
+            return Ok(None);
+        };
+        let path = events_socket_path(screenpipe_dir, id);
+        let listener = EventsListener::bind(&path)?;
+        let events = subscribe_to_all_events();
+        debug!("serving the events of pipe {} at {:?}", id, path);
+        let task = tokio::spawn(forward_events(
+            listener,
+            events,
+            subscriptions,
+            id.to_string(),
+        ));
+        Ok(Some(Self { path, task }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PipeEvents {
+    fn drop(&mut self) {
+        self.task.abort();
+        #[cfg(unix)]
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn forward_events(
+    mut listener: EventsListener,
+    mut events: EventSubscription<Value>,
+    subscriptions: PipeSubscriptions,
+    id: String,
+) {
+    let mut connections: Vec<mpsc::Sender<String>> = Vec::new();
+    loop {
+        tokio::select! {
+            connection = listener.accept() => match connection {
+                Ok(connection) => {
+                    debug!("pipe {} connected to its events", id);
+                    connections.push(spawn_writer(connection));
+                }
+                Err(e) => {
+                    error!(
+                        "failed to accept a connection to the events of pipe {}: {}",
+                        id, e
+                    );
+                    return;
+                }
+            },
+            Some(event) = events.next() => {
+                if !subscriptions.matches(&event.name, &event.data) {
+                    continue;
+                }
+                let line = match serde_json::to_string(&event) {
+                    Ok(line) => line + "\n",
+                    Err(e) => {
+                        error!("failed to serialize event {}: {}", event.name, e);
+                        continue;
+                    }
+                };
+                connections.retain(|connection| match connection.try_send(line.clone()) {
+                    Ok(()) => true,
+                    Err(TrySendError::Full(_)) => {
+                        warn!(
+                            "pipe {} isn't reading its events, dropping a {} event",
+                            id, event.name
+                        );
+                        true
+                    }
+                    Err(TrySendError::Closed(_)) => false,
+                });
+            }
+        }
+    }
+}
+
+/// Writes the lines sent to it to `connection` until it's closed.
+fn spawn_writer(mut connection: Box<dyn AsyncWrite + Send + Unpin>) -> mpsc::Sender<String> {
+    let (tx, mut rx) = mpsc::channel::<String>(CONNECTION_QUEUE);
+    tokio::spawn(async move {
+        while let Some(line) = rx.recv().await {
+            if connection.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+    tx
+}
+
+#[cfg(unix)]
+struct EventsListener(tokio::net::UnixListener);
+
+#[cfg(unix)]
+impl EventsListener {
+    fn bind(path: &Path) -> io::Result<Self> {
+        // left behind by a server that didn't stop cleanly
+        let _ = std::fs::remove_file(path);
+        Ok(Self(tokio::net::UnixListener::bind(path)?))
+    }
+
+    async fn accept(&mut self) -> io::Result<Box<dyn AsyncWrite + Send + Unpin>> {
+        let (stream, _) = self.0.accept().await?;
+        Ok(Box::new(stream))
+    }
+}
+
+#[cfg(windows)]
+struct EventsListener {
+    name: String,
+    server: tokio::net::windows::named_pipe::NamedPipeServer,
+}
+
+#[cfg(windows)]
+impl EventsListener {
+    fn bind(path: &Path) -> io::Result<Self> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        let name = path.to_string_lossy().into_owned();
+        let server = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(&name)?;
+        Ok(Self { name, server })
+    }
+
+    async fn accept(&mut self) -> io::Result<Box<dyn AsyncWrite + Send + Unpin>> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        self.server.connect().await?;
+        // the next client connects to a new instance of the pipe
+        let next = ServerOptions::new().create(&self.name)?;
+        Ok(Box::new(std::mem::replace(&mut self.server, next)))
+    }
+}
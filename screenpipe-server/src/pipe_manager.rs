@@ -1,4 +1,14 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use screenpipe_core::pipe_limits::{
+    restart_backoff, PipeLimits, MAX_RESTARTS, RESTART_STABLE_AFTER,
+};
+use screenpipe_core::pipe_schedule::is_scheduled;
+use screenpipe_core::pipe_settings::check_settings;
+use screenpipe_core::pipe_signing::{
+    check_publisher, installed_publisher_key, trusted_publisher_keys,
+};
+use screenpipe_core::wasm_pipe::{read_manifest, run_wasm_pipe, WasmPipeHost};
 use screenpipe_core::{download_pipe, download_pipe_private, PipeState};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -6,12 +16,16 @@ use std::collections::HashMap;
 use std::future::Future;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
-use tokio::sync::mpsc::{self, Sender};
+use tokio::sync::mpsc::{self, Receiver, Sender};
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
+use crate::pipe_events::PipeEvents;
+use crate::saved_search::DEFAULT_NOTIFICATION_URL;
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct PipeInfo {
     pub id: String,
@@ -24,14 +38,58 @@ pub struct PipeInfo {
     pub build_status: Option<Value>,
 }
 
+/// Restarts and kills kept per pipe.
+const MAX_INCIDENTS: usize = 20;
+
+/// A crash or a kill of a pipe.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct PipeIncident {
+    pub at: DateTime<Utc>,
+    pub reason: String,
+}
+
+/// Resource usage and restart history of a pipe, served at
+/// `/pipes/:pipe_id/stats`.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct PipeStats {
+    pub running: bool,
+    /// Process of bun pipes, `None` for wasm pipes
+    pub pid: Option<u32>,
+    pub started_at: Option<DateTime<Utc>>,
+    /// Resident memory of the pipe and its child processes at the last check
+    pub memory_bytes: Option<u64>,
+    /// CPU of the pipe and its child processes at the last check, 100 being
+    /// a full core
+    pub cpu_percent: Option<f32>,
+    pub limits: PipeLimits,
+    /// Crashes it was restarted after, the latest last
+    pub restarts: Vec<PipeIncident>,
+    /// Last time it was stopped for exceeding its limits
+    pub killed: Option<PipeIncident>,
+    /// Checks in a row it was over its CPU limit
+    #[serde(skip)]
+    cpu_over_limit: u32,
+}
+
 struct PipeHandle {
-    state: PipeState,
+    /// `None` for wasm pipes, run in-process, and pipes waiting to restart
+    state: Option<PipeState>,
+    pid: Option<u32>,
     kill_tx: Sender<()>,
 }
 
+/// How a run of a pipe ended.
+enum PipeExit {
+    /// It exited fine or was stopped
+    Done,
+    Crashed(String),
+}
+
 pub struct PipeManager {
     screenpipe_dir: PathBuf,
     running_pipes: Arc<RwLock<HashMap<String, PipeHandle>>>,
+    stats: Arc<RwLock<HashMap<String, PipeStats>>>,
+    wasm_host: WasmPipeHost,
 }
 
 impl PipeManager {
@@ -39,9 +97,24 @@ impl PipeManager {
         PipeManager {
             screenpipe_dir,
             running_pipes: Arc::new(RwLock::new(HashMap::new())),
+            stats: Arc::new(RwLock::new(HashMap::new())),
+            wasm_host: WasmPipeHost {
+                api_url: "http://localhost:3030".to_string(),
+                notification_url: DEFAULT_NOTIFICATION_URL.to_string(),
+            },
         }
     }
 
+    /// Where the calls of wasm pipes go.
+    pub fn with_wasm_host(mut self, wasm_host: WasmPipeHost) -> Self {
+        self.wasm_host = wasm_host;
+        self
+    }
+
+    pub fn screenpipe_dir(&self) -> &Path {
+        &self.screenpipe_dir
+    }
+
     pub async fn update_config(&self, id: &str, new_config: Value) -> Result<()> {
         debug!("Updating config for pipe: {}", id);
         let pipe_dir = self.screenpipe_dir.join("pipes").join(id);
@@ -77,6 +150,8 @@ impl PipeManager {
         };
 
         let is_enabled = new_config.get("enabled").and_then(Value::as_bool);
+        let updates_settings =
+            new_config.get("settings").is_some() || new_config.get("settings_schema").is_some();
 
         debug!("is_enabled: {}", is_enabled.unwrap_or(false));
 
@@ -102,17 +177,28 @@ impl PipeManager {
             return Err(anyhow::anyhow!("existing configuration is not an object"));
         }
 
+        // settings are checked when changed, so that a pipe missing required
+        // settings can still be installed and enabled
+        if updates_settings {
+            check_settings(&config)?;
+        }
+
         let updated_config_str = serde_json::to_string_pretty(&config)?;
 
         let mut file = File::create(&config_path).await?;
         file.write_all(updated_config_str.as_bytes()).await?;
 
+        // scheduled pipes are started by the scheduler when they're due
+        let scheduled = is_scheduled(&config);
+
         // Handle pipe state changes
         if let Some(enabled) = is_enabled {
             match (was_enabled, enabled) {
                 (false, true) => {
-                    let future = self.start_pipe_task(id.to_string()).await?;
-                    tokio::spawn(future);
+                    if !scheduled {
+                        let future = self.start_pipe_task(id.to_string()).await?;
+                        tokio::spawn(future);
+                    }
 
                     info!("pipe {} enabled", id);
                 }
@@ -124,8 +210,10 @@ impl PipeManager {
                 (true, true) => {
                     self.stop_pipe(id).await?;
 
-                    let future = self.start_pipe_task(id.to_string()).await?;
-                    tokio::spawn(future);
+                    if !scheduled {
+                        let future = self.start_pipe_task(id.to_string()).await?;
+                        tokio::spawn(future);
+                    }
 
                     info!("pipe {} restarted", id);
                 }
@@ -136,6 +224,66 @@ impl PipeManager {
         Ok(())
     }
 
+    pub async fn is_running(&self, id: &str) -> bool {
+        self.running_pipes.read().await.contains_key(id)
+    }
+
+    pub async fn get_pipe_stats(&self, id: &str) -> PipeStats {
+        self.stats.read().await.get(id).cloned().unwrap_or_default()
+    }
+
+    /// Processes of the running bun pipes.
+    pub async fn running_pids(&self) -> Vec<(String, u32)> {
+        self.running_pipes
+            .read()
+            .await
+            .iter()
+            .filter_map(|(id, handle)| Some((id.clone(), handle.pid?)))
+            .collect()
+    }
+
+    /// Records what `id` uses, returning why it must be stopped when it's
+    /// over its memory limit, or over its CPU limit for `cpu_checks` checks
+    /// in a row.
+    pub async fn record_usage(
+        &self,
+        id: &str,
+        memory_bytes: u64,
+        cpu_percent: f32,
+        cpu_checks: u32,
+    ) -> Option<String> {
+        let mut stats = self.stats.write().await;
+        let stats = stats.get_mut(id)?;
+        stats.memory_bytes = Some(memory_bytes);
+        stats.cpu_percent = Some(cpu_percent);
+        if let Some(reason) = stats.limits.memory_exceeded(memory_bytes) {
+            return Some(reason);
+        }
+        match stats.limits.cpu_exceeded(cpu_percent) {
+            Some(reason) => {
+                stats.cpu_over_limit += 1;
+                (stats.cpu_over_limit >= cpu_checks).then_some(reason)
+            }
+            None => {
+                stats.cpu_over_limit = 0;
+                None
+            }
+        }
+    }
+
+    /// Stops `id` for exceeding its limits, it isn't restarted.
+    pub async fn kill_pipe(&self, id: &str, reason: String) -> Result<()> {
+        warn!("stopping pipe {}: {}", id, reason);
+        self.stop_pipe(id).await?;
+        if let Some(stats) = self.stats.write().await.get_mut(id) {
+            stats.killed = Some(PipeIncident {
+                at: Utc::now(),
+                reason,
+            });
+        }
+        Ok(())
+    }
+
     pub async fn get_pipe_info(&self, id: &str) -> Option<PipeInfo> {
         let pipes = self.list_pipes().await;
         pipes.iter().find(|pipe| pipe.id == id).cloned()
@@ -204,11 +352,20 @@ impl PipeManager {
         pipe_infos
     }
 
-    pub async fn download_pipe(&self, url: &str) -> Result<String> {
+    /// Installs the pipe at `url`, which must be signed by a trusted
+    /// publisher unless `allow_unsigned`.
+    pub async fn download_pipe(&self, url: &str, allow_unsigned: bool) -> Result<String> {
         // Remove any surrounding quotes and normalize backslashes
         let normalized_url = url.trim_matches('"').replace("\\", "/");
 
-        let pipe_dir = download_pipe(&normalized_url, self.screenpipe_dir.clone()).await?;
+        let trusted_keys = trusted_publisher_keys(&self.screenpipe_dir)?;
+        let pipe_dir = download_pipe(
+            &normalized_url,
+            self.screenpipe_dir.clone(),
+            &trusted_keys,
+            allow_unsigned,
+        )
+        .await?;
 
         // update the config with the source url
         self.update_config(
@@ -233,8 +390,17 @@ impl PipeManager {
         url: &str,
         pipe_name: &str,
         pipe_id: &str,
+        allow_unsigned: bool,
     ) -> Result<String> {
-        let pipe_dir = download_pipe_private(pipe_name, url, self.screenpipe_dir.clone()).await?;
+        let trusted_keys = trusted_publisher_keys(&self.screenpipe_dir)?;
+        let pipe_dir = download_pipe_private(
+            pipe_name,
+            url,
+            self.screenpipe_dir.clone(),
+            &trusted_keys,
+            allow_unsigned,
+        )
+        .await?;
 
         let package_json_path = pipe_dir.join("package.json");
         let version = if package_json_path.exists() {
@@ -391,7 +557,7 @@ impl PipeManager {
             }
 
             match handle.state {
-                PipeState::Port(port) => {
+                Some(PipeState::Port(port)) => {
                     tokio::task::spawn(async move {
                         // killport doesn't seems working
                         #[cfg(unix)]
@@ -484,7 +650,7 @@ impl PipeManager {
                     .await
                     .map_err(|e| anyhow::anyhow!("Failed to kill port: {}", e))?;
                 }
-                PipeState::Pid(pid) => {
+                Some(PipeState::Pid(pid)) => {
                     // Force kill the process if it's still running
                     #[cfg(unix)]
                     {
@@ -506,6 +672,8 @@ impl PipeManager {
                         }
                     }
                 }
+                // wasm pipes stop with their task
+                None => {}
             }
 
             // Clean up cron jobs
@@ -519,63 +687,99 @@ impl PipeManager {
     pub async fn start_pipe_task(&self, id: String) -> Result<impl Future<Output = Result<()>>> {
         let screenpipe_dir = self.screenpipe_dir.clone();
         let running_pipes = self.running_pipes.clone();
-        let id_for_map = id.clone();
+        let stats = self.stats.clone();
+        let wasm_host = self.wasm_host.clone();
 
         Ok(async move {
-            match screenpipe_core::run_pipe(&id, screenpipe_dir.clone()).await {
-                Ok((mut child, pipe_state)) => {
-                    let (kill_tx, mut kill_rx) = mpsc::channel::<()>(1);
-
-                    running_pipes.write().await.insert(
-                        id_for_map.clone(),
-                        PipeHandle {
-                            state: pipe_state,
-                            kill_tx: kill_tx.clone(),
-                        },
-                    );
-
-                    match pipe_state {
-                        PipeState::Port(port) => {
-                            info!("started pipe: {} on port {}", id, port);
-                        }
-                        PipeState::Pid(pid) => {
-                            info!("started pipe: {} on pid {}", id, pid);
-                        }
+            // one channel for all the runs, so that stopping the pipe also
+            // cancels a pending restart
+            let (kill_tx, mut kill_rx) = mpsc::channel::<()>(1);
+            let mut restarts = 0;
+            loop {
+                let started = Instant::now();
+                let exit = run_pipe_once(
+                    &id,
+                    &screenpipe_dir,
+                    &wasm_host,
+                    &running_pipes,
+                    &stats,
+                    &kill_tx,
+                    &mut kill_rx,
+                )
+                .await;
+                if let Some(stats) = stats.write().await.get_mut(&id) {
+                    stats.running = false;
+                    stats.pid = None;
+                }
+                let reason = match exit {
+                    Ok(PipeExit::Done) => {
+                        remove_handle(&running_pipes, &id, &kill_tx).await;
+                        return Ok(());
+                    }
+                    Ok(PipeExit::Crashed(reason)) => reason,
+                    Err(e) => {
+                        remove_handle(&running_pipes, &id, &kill_tx).await;
+                        return Err(e);
                     }
+                };
 
-                    tokio::select! {
-                        status = child.wait() => {
-                            match status {
-                                Ok(status) if !status.success() => {
-                                    println!("pipe {} exited with status: {}", id, status);
-                                    running_pipes.write().await.remove(&id_for_map);
-                                    anyhow::bail!("pipe exited with non-zero status: {}", status);
-                                }
-                                Err(e) => {
-                                    println!("error waiting for pipe {}: {}", id, e);
-                                    running_pipes.write().await.remove(&id_for_map);
-                                    anyhow::bail!("error waiting for pipe: {}", e);
-                                }
-                                Ok(_) => Ok(())
-                            }
-                        }
-                        _ = kill_rx.recv() => {
-                            // Kill received through channel
-                            let _ = child.kill().await;
-                            running_pipes.write().await.remove(&id_for_map);
-                            Ok(())
-                        }
+                if started.elapsed() >= RESTART_STABLE_AFTER {
+                    restarts = 0;
+                }
+                if restarts >= MAX_RESTARTS {
+                    remove_handle(&running_pipes, &id, &kill_tx).await;
+                    anyhow::bail!(
+                        "pipe {} crashed {} times in a row, not restarting it: {}",
+                        id,
+                        restarts + 1,
+                        reason
+                    );
+                }
+                let backoff = restart_backoff(restarts);
+                restarts += 1;
+                warn!(
+                    "pipe {} crashed ({}), restarting it in {} seconds",
+                    id,
+                    reason,
+                    backoff.as_secs()
+                );
+                {
+                    let mut stats = stats.write().await;
+                    let stats = stats.entry(id.clone()).or_default();
+                    stats.restarts.push(PipeIncident {
+                        at: Utc::now(),
+                        reason,
+                    });
+                    if stats.restarts.len() > MAX_INCIDENTS {
+                        stats.restarts.remove(0);
                     }
                 }
-                Err(e) => {
-                    error!("[{}] failed to start pipe {}:", id, e);
-                    Err(e)
+                running_pipes.write().await.insert(
+                    id.clone(),
+                    PipeHandle {
+                        state: None,
+                        pid: None,
+                        kill_tx: kill_tx.clone(),
+                    },
+                );
+
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = kill_rx.recv() => {
+                        remove_handle(&running_pipes, &id, &kill_tx).await;
+                        return Ok(());
+                    }
                 }
             }
         })
     }
 
-    pub async fn update_pipe_version(&self, id: &str, source: &str) -> Result<()> {
+    pub async fn update_pipe_version(
+        &self,
+        id: &str,
+        source: &str,
+        allow_unsigned: bool,
+    ) -> Result<()> {
         debug!("updating pipe: {}", id);
         let pipe_dir = self.screenpipe_dir.join("pipes").join(id);
 
@@ -621,8 +825,20 @@ impl PipeManager {
             tokio::fs::write(&pipe_json_path, updated_config).await?;
         }
 
-        // Download new version to temp directory
-        let tmp_pipe_dir = match download_pipe_private(id, source, tmp_dir.clone()).await {
+        // Download new version to temp directory. It isn't installed there,
+        // so the key of the installed version is passed as trusted, and
+        // checked to be the one the update is signed with below.
+        let trusted_keys = trusted_publisher_keys(&self.screenpipe_dir)?;
+        let installed_key = installed_publisher_key(&config);
+        let download_keys: Vec<String> = trusted_keys
+            .iter()
+            .cloned()
+            .chain(installed_key.clone())
+            .collect();
+        let download =
+            download_pipe_private(id, source, tmp_dir.clone(), &download_keys, allow_unsigned)
+                .await;
+        let tmp_pipe_dir = match download {
             Ok(dir) => {
                 debug!("downloaded new version to temp dir: {:?}", dir);
                 dir
@@ -668,6 +884,57 @@ impl PipeManager {
             ));
         }
 
+        // The new version must come from the publisher of the installed one
+        let mut provenance = tokio::fs::read_to_string(tmp_pipe_dir.join("pipe.json"))
+            .await
+            .ok()
+            .and_then(|config| serde_json::from_str::<Value>(&config).ok())
+            .and_then(|config| config.get("provenance").cloned());
+        let publisher_key = provenance
+            .as_ref()
+            .and_then(|provenance| provenance.get("publisher_key"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        if let Err(e) = check_publisher(
+            installed_key.as_deref(),
+            publisher_key.as_deref(),
+            &trusted_keys,
+            true,
+            allow_unsigned,
+        ) {
+            if let Some(obj) = config.as_object_mut() {
+                obj.insert(
+                    "buildStatus".to_string(),
+                    serde_json::json!({
+                        "status": "error",
+                        "step": "verification",
+                        "error": format!("Failed to verify the new version: {}", e)
+                    }),
+                );
+                let updated_config = serde_json::to_string_pretty(&config)?;
+                tokio::fs::write(&pipe_json_path, updated_config).await?;
+            }
+            tokio::fs::remove_dir_all(&tmp_dir).await?;
+            return Err(anyhow::anyhow!("failed to verify new version: {}", e));
+        }
+        if let Some(provenance) = provenance.as_mut().and_then(Value::as_object_mut) {
+            provenance.insert(
+                "publisher_pinned".to_string(),
+                Value::Bool(publisher_key.is_some() && publisher_key == installed_key),
+            );
+            provenance.insert(
+                "publisher_trusted".to_string(),
+                Value::Bool(
+                    publisher_key
+                        .as_ref()
+                        .is_some_and(|key| trusted_keys.contains(key)),
+                ),
+            );
+        }
+        if let (Some(obj), Some(provenance)) = (config.as_object_mut(), provenance) {
+            obj.insert("provenance".to_string(), provenance);
+        }
+
         // Update build status to indicate extracting version
         if let Some(obj) = config.as_object_mut() {
             obj.insert(
@@ -868,6 +1135,7 @@ impl PipeManager {
             .get("enabled")
             .and_then(Value::as_bool)
             .unwrap_or(false)
+            && !is_scheduled(&config)
         {
             match self.start_pipe_task(id.to_string()).await {
                 Ok(future) => {
@@ -927,6 +1195,152 @@ impl PipeManager {
 }
 
 // Helper function to recursively copy directories
+/// Removes the handle of `id` unless a new run replaced it.
+async fn remove_handle(
+    running_pipes: &RwLock<HashMap<String, PipeHandle>>,
+    id: &str,
+    kill_tx: &Sender<()>,
+) {
+    let mut pipes = running_pipes.write().await;
+    if pipes
+        .get(id)
+        .is_some_and(|handle| handle.kill_tx.same_channel(kill_tx))
+    {
+        pipes.remove(id);
+    }
+}
+
+async fn mark_started(
+    stats: &RwLock<HashMap<String, PipeStats>>,
+    id: &str,
+    pid: Option<u32>,
+    limits: PipeLimits,
+) {
+    let mut stats = stats.write().await;
+    let stats = stats.entry(id.to_string()).or_default();
+    stats.running = true;
+    stats.pid = pid;
+    stats.started_at = Some(Utc::now());
+    stats.memory_bytes = None;
+    stats.cpu_percent = None;
+    stats.limits = limits;
+    stats.cpu_over_limit = 0;
+}
+
+/// Runs `id` until it exits or `kill_rx` receives, with bun or, for wasm
+/// pipes, wasmtime.
+async fn run_pipe_once(
+    id: &str,
+    screenpipe_dir: &Path,
+    wasm_host: &WasmPipeHost,
+    running_pipes: &RwLock<HashMap<String, PipeHandle>>,
+    stats: &RwLock<HashMap<String, PipeStats>>,
+    kill_tx: &Sender<()>,
+    kill_rx: &mut Receiver<()>,
+) -> Result<PipeExit> {
+    let pipe_dir = screenpipe_dir.join("pipes").join(id);
+    let config = PipeManager::load_pipe_info(id.to_string(), pipe_dir.clone())
+        .await
+        .config;
+    let limits = PipeLimits::from_config(&config).unwrap_or_else(|e| {
+        warn!("ignoring the limits of pipe {}: {}", id, e);
+        PipeLimits::default()
+    });
+    match read_manifest(&pipe_dir).await {
+        Ok(Some(manifest)) => {
+            let mut pipe = match run_wasm_pipe(id, screenpipe_dir, manifest, wasm_host.clone()) {
+                Ok(pipe) => pipe,
+                Err(e) => {
+                    error!("[{}] failed to start pipe {}:", id, e);
+                    return Err(e);
+                }
+            };
+            running_pipes.write().await.insert(
+                id.to_string(),
+                PipeHandle {
+                    state: None,
+                    pid: None,
+                    kill_tx: kill_tx.clone(),
+                },
+            );
+            mark_started(stats, id, None, limits).await;
+            info!("started wasm pipe: {}", id);
+
+            return tokio::select! {
+                result = pipe.wait() => match result {
+                    Ok(()) => Ok(PipeExit::Done),
+                    Err(e) => Ok(PipeExit::Crashed(e.to_string())),
+                },
+                _ = kill_rx.recv() => {
+                    pipe.stop();
+                    // the interrupted pipe returns an error
+                    let _ = pipe.wait().await;
+                    Ok(PipeExit::Done)
+                }
+            };
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("[{}] failed to read the manifest of pipe {}:", id, e);
+            return Err(e);
+        }
+    }
+
+    // served until the pipe exits
+    let _events = PipeEvents::serve(screenpipe_dir, id, &config).unwrap_or_else(|e| {
+        warn!("not serving the events pipe {} subscribed to: {}", id, e);
+        None
+    });
+
+    match screenpipe_core::run_pipe(id, screenpipe_dir.to_path_buf()).await {
+        Ok((mut child, pipe_state)) => {
+            running_pipes.write().await.insert(
+                id.to_string(),
+                PipeHandle {
+                    state: Some(pipe_state),
+                    pid: child.id(),
+                    kill_tx: kill_tx.clone(),
+                },
+            );
+            mark_started(stats, id, child.id(), limits).await;
+
+            match pipe_state {
+                PipeState::Port(port) => {
+                    info!("started pipe: {} on port {}", id, port);
+                }
+                PipeState::Pid(pid) => {
+                    info!("started pipe: {} on pid {}", id, pid);
+                }
+            }
+
+            tokio::select! {
+                status = child.wait() => {
+                    match status {
+                        Ok(status) if !status.success() => {
+                            println!("pipe {} exited with status: {}", id, status);
+                            Ok(PipeExit::Crashed(format!("exited with status: {}", status)))
+                        }
+                        Err(e) => {
+                            println!("error waiting for pipe {}: {}", id, e);
+                            Ok(PipeExit::Crashed(format!("error waiting for pipe: {}", e)))
+                        }
+                        Ok(_) => Ok(PipeExit::Done)
+                    }
+                }
+                _ = kill_rx.recv() => {
+                    // Kill received through channel
+                    let _ = child.kill().await;
+                    Ok(PipeExit::Done)
+                }
+            }
+        }
+        Err(e) => {
+            error!("[{}] failed to start pipe {}:", id, e);
+            Err(e)
+        }
+    }
+}
+
 async fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
     let src = src.as_ref();
     let dst = dst.as_ref();
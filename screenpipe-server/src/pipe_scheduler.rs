@@ -0,0 +1,207 @@
+//! Starts the enabled pipes with a `schedule` or `triggers` in their
+//! pipe.json when they are due, see [`screenpipe_core::pipe_schedule`]:
+//! schedules and keywords are checked every [`CHECK_INTERVAL`], meetings
+//! start their pipes as soon as their event is sent.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use screenpipe_core::pipe_schedule::{PipeSchedule, MEETING_ENDED_EVENT, MEETING_STARTED_EVENT};
+use screenpipe_db::{ContentType, DatabaseManager, SearchSort};
+use screenpipe_events::subscribe_to_all_events;
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::pipe_manager::{PipeInfo, PipeManager};
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Schedules of the enabled pipes that have one.
+fn scheduled_pipes(pipes: Vec<PipeInfo>) -> Vec<(String, PipeSchedule)> {
+    pipes
+        .into_iter()
+        .filter(|pipe| pipe.enabled)
+        .filter_map(|pipe| match PipeSchedule::from_config(&pipe.config) {
+            Ok(schedule) => schedule.map(|schedule| (pipe.id, schedule)),
+            Err(e) => {
+                warn!("pipe {} has an invalid schedule: {}", pipe.id, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Whether something recorded in `(since, until]` contains one of the
+/// keywords of `schedule`.
+async fn keyword_seen(
+    db: &DatabaseManager,
+    schedule: &PipeSchedule,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<bool> {
+    for (keyword, app_name) in schedule.keywords() {
+        let matches = db
+            .search(
+                keyword,
+                ContentType::All,
+                1,
+                0,
+                Some(since),
+                Some(until),
+                app_name,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
+            )
+            .await?;
+        if !matches.is_empty() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Remembers when each scheduled pipe was last checked, so that schedules
+/// and keywords fire once.
+#[derive(Default)]
+pub struct PipeScheduler {
+    last_checked: HashMap<String, DateTime<Utc>>,
+}
+
+impl PipeScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pipes of `pipes` due at `now` from their schedule or keywords. Pipes
+    /// seen for the first time are due from then on.
+    pub async fn due(
+        &mut self,
+        db: &DatabaseManager,
+        pipes: Vec<PipeInfo>,
+        now: DateTime<Utc>,
+    ) -> Vec<String> {
+        let pipes = scheduled_pipes(pipes);
+        // pipes disabled in between start over when enabled again
+        self.last_checked
+            .retain(|id, _| pipes.iter().any(|(pipe, _)| pipe == id));
+
+        let mut due = Vec::new();
+        for (id, schedule) in pipes {
+            let Some(since) = self.last_checked.insert(id.clone(), now) else {
+                continue;
+            };
+            let keyword = match keyword_seen(db, &schedule, since, now).await {
+                Ok(seen) => seen,
+                Err(e) => {
+                    error!("failed to check the keywords of pipe {}: {}", id, e);
+                    false
+                }
+            };
+            if schedule.is_due(&since, &now) || keyword {
+                due.push(id);
+            }
+        }
+        due
+    }
+}
+
+/// Pipes of `pipes` triggered by the event `name`.
+pub fn triggered(pipes: Vec<PipeInfo>, name: &str) -> Vec<String> {
+    scheduled_pipes(pipes)
+        .into_iter()
+        .filter(|(_, schedule)| schedule.is_triggered_by(name))
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// Runs `id` unless it's already running, stopping it after its
+/// `timeout_secs`.
+async fn start(pipe_manager: &Arc<PipeManager>, id: String) {
+    if pipe_manager.is_running(&id).await {
+        return;
+    }
+    let timeout = pipe_manager
+        .get_pipe_info(&id)
+        .await
+        .and_then(|pipe| PipeSchedule::from_config(&pipe.config).ok().flatten())
+        .and_then(|schedule| schedule.timeout_secs)
+        .map(Duration::from_secs);
+    let future = match pipe_manager.start_pipe_task(id.clone()).await {
+        Ok(future) => future,
+        Err(e) => {
+            error!("failed to start scheduled pipe {}: {}", id, e);
+            return;
+        }
+    };
+    info!("starting scheduled pipe {}", id);
+
+    let pipe_manager = pipe_manager.clone();
+    tokio::spawn(async move {
+        tokio::pin!(future);
+        let result = match timeout {
+            Some(timeout) => {
+                tokio::select! {
+                    result = &mut future => result,
+                    _ = tokio::time::sleep(timeout) => {
+                        info!("scheduled pipe {} timed out, stopping it", id);
+                        if let Err(e) = pipe_manager.stop_pipe(&id).await {
+                            error!("failed to stop pipe {}: {}", id, e);
+                        }
+                        future.await
+                    }
+                }
+            }
+            None => future.await,
+        };
+        if let Err(e) = result {
+            warn!("scheduled pipe {} failed: {}", id, e);
+        }
+    });
+}
+
+pub async fn run_pipe_scheduler(
+    pipe_manager: Arc<PipeManager>,
+    db: Arc<DatabaseManager>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!("starting scheduled pipes when they're due");
+
+    let mut scheduler = PipeScheduler::new();
+    let mut events = subscribe_to_all_events();
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let pipes = pipe_manager.list_pipes().await;
+                for id in scheduler.due(&db, pipes, Utc::now()).await {
+                    start(&pipe_manager, id).await;
+                }
+            }
+            Some(event) = events.next() => {
+                if event.name == MEETING_STARTED_EVENT || event.name == MEETING_ENDED_EVENT {
+                    let pipes = pipe_manager.list_pipes().await;
+                    for id in triggered(pipes, &event.name) {
+                        start(&pipe_manager, id).await;
+                    }
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping the pipe scheduler");
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Watches the memory and CPU of the running bun pipes and their child
+//! processes, stopping the pipes over the `limits` of their pipe.json, see
+//! [`screenpipe_core::pipe_limits`]. Wasm pipes have their memory capped by
+//! wasmtime instead.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sysinfo::{Pid, PidExt, ProcessExt, System, SystemExt};
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+use crate::pipe_manager::PipeManager;
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Checks in a row a pipe can be over its CPU limit, so that short bursts
+/// don't stop it.
+pub const CPU_CHECKS: u32 = 3;
+
+/// Memory in bytes and CPU of `pid` and its descendants.
+pub fn process_tree_usage(sys: &System, pid: u32) -> Option<(u64, f32)> {
+    let root = sys.process(Pid::from_u32(pid))?;
+    let mut tree = HashSet::from([root.pid()]);
+    let mut memory = root.memory();
+    let mut cpu = root.cpu_usage();
+    // children can be listed before their parent
+    loop {
+        let children: Vec<_> = sys
+            .processes()
+            .values()
+            .filter(|process| {
+                !tree.contains(&process.pid())
+                    && process
+                        .parent()
+                        .is_some_and(|parent| tree.contains(&parent))
+            })
+            .collect();
+        if children.is_empty() {
+            break;
+        }
+        for child in children {
+            tree.insert(child.pid());
+            memory += child.memory();
+            cpu += child.cpu_usage();
+        }
+    }
+    Some((memory, cpu))
+}
+
+/// Records what the running pipes use, stopping those over their limits.
+pub async fn check_pipes(pipe_manager: &PipeManager, sys: &mut System) {
+    let pids = pipe_manager.running_pids().await;
+    if pids.is_empty() {
+        return;
+    }
+    sys.refresh_processes();
+    for (id, pid) in pids {
+        let Some((memory, cpu)) = process_tree_usage(sys, pid) else {
+            continue;
+        };
+        if let Some(reason) = pipe_manager
+            .record_usage(&id, memory, cpu, CPU_CHECKS)
+            .await
+        {
+            if let Err(e) = pipe_manager.kill_pipe(&id, reason).await {
+                error!("failed to stop pipe {}: {}", id, e);
+            }
+        }
+    }
+}
+
+pub async fn run_pipe_supervisor(
+    pipe_manager: Arc<PipeManager>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!("watching the resources of pipes");
+
+    let mut sys = System::new();
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => check_pipes(&pipe_manager, &mut sys).await,
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping the pipe supervisor");
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Switches to the low power profile on battery and back when plugged in.
+//! Windows whose OCR was deferred on battery wait in the OCR queue, see
+//! [`crate::ocr_queue`].
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use screenpipe_audio::audio_manager::AudioManager;
+use screenpipe_audio::core::engine::AudioTranscriptionEngine;
+use screenpipe_core::power::{
+    read_battery, set_power_status, PowerProfile, PowerSettings, PowerStatus,
+};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+/// How often the battery is checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Follows the battery until a shutdown is signalled. `audio_manager` is
+/// `None` when audio is disabled.
+pub async fn run_power_monitor(
+    audio_manager: Option<Arc<AudioManager>>,
+    settings: PowerSettings,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    let mut current = PowerStatus::default();
+    // engine to go back to when plugged in
+    let mut normal_engine: Option<AudioTranscriptionEngine> = None;
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let battery = tokio::task::spawn_blocking(read_battery).await.ok().flatten();
+                let status = match battery {
+                    Some((on_battery, percent)) => settings.status(on_battery, percent),
+                    None => PowerStatus::default(),
+                };
+                set_power_status(status);
+
+                if status.profile != current.profile {
+                    info!(
+                        "switching to the {} power profile (on battery: {}, charge: {:?})",
+                        status.profile, status.on_battery, status.battery_percent
+                    );
+                    if let Some(audio_manager) = &audio_manager {
+                        normal_engine =
+                            switch_transcription_engine(audio_manager, status, normal_engine).await;
+                    }
+                }
+                if status.audio_paused != current.audio_paused {
+                    info!(
+                        "audio recording {} on battery",
+                        if status.audio_paused { "paused" } else { "resumed" }
+                    );
+                }
+                current = status;
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping power monitor");
+                break;
+            }
+        }
+    }
+
+    if let (Some(audio_manager), Some(engine)) = (&audio_manager, normal_engine) {
+        if let Err(e) = audio_manager.set_transcription_engine(engine).await {
+            warn!("failed to restore the transcription engine: {}", e);
+        }
+    }
+}
+
+/// Uses the smaller model on low power, returns the engine to restore later.
+async fn switch_transcription_engine(
+    audio_manager: &AudioManager,
+    status: PowerStatus,
+    normal_engine: Option<AudioTranscriptionEngine>,
+) -> Option<AudioTranscriptionEngine> {
+    let current = (*audio_manager.transcription_engine().await).clone();
+    let (engine, normal_engine) = match (status.profile, normal_engine) {
+        (PowerProfile::LowPower, None) => match current.low_power() {
+            Some(engine) => (engine, Some(current)),
+            None => return None,
+        },
+        (PowerProfile::Normal, Some(engine)) => (engine, None),
+        (_, normal_engine) => return normal_engine,
+    };
+
+    info!("switching transcription engine to {}", engine);
+    match audio_manager.set_transcription_engine(engine).await {
+        Ok(()) => normal_engine,
+        Err(e) => {
+            warn!("failed to switch the transcription engine: {}", e);
+            None
+        }
+    }
+}
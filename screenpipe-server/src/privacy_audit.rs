@@ -0,0 +1,95 @@
+//! Persists when the privacy blocklist stopped and resumed capture, so users
+//! can check what was kept out of the recording and why.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use oasgen::{oasgen, OaSchema};
+use screenpipe_core::privacy::{PrivacyBlockEvent, PRIVACY_BLOCK_EVENT};
+use screenpipe_db::DatabaseManager;
+use screenpipe_events::subscribe_to_event;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+use crate::server::{AppState, PaginationQuery};
+
+/// Stores privacy block events until a shutdown is signalled.
+pub async fn run_privacy_audit(db: Arc<DatabaseManager>, mut shutdown_rx: broadcast::Receiver<()>) {
+    let mut events = subscribe_to_event::<PrivacyBlockEvent>(PRIVACY_BLOCK_EVENT);
+
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else { break };
+                let block = event.data;
+                if let Err(e) = db
+                    .insert_privacy_audit_entry(
+                        block.timestamp,
+                        block.monitor_id,
+                        block.blocked,
+                        &block.app_name,
+                        &block.pattern,
+                    )
+                    .await
+                {
+                    error!("failed to store privacy audit entry: {}", e);
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping privacy audit log");
+                break;
+            }
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct PrivacyAuditQuery {
+    #[serde(flatten)]
+    pagination: PaginationQuery,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+}
+
+/// Lists when the privacy blocklist stopped and resumed capture, newest first.
+#[oasgen]
+pub(crate) async fn get_privacy_audit_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PrivacyAuditQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state
+        .db
+        .get_privacy_audit_log(
+            query.start_time,
+            query.end_time,
+            query.pagination.limit,
+            query.pagination.offset,
+        )
+        .await
+    {
+        Ok(entries) => Ok(JsonResponse(json!({
+            "data": entries,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to read privacy audit log: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to read privacy audit log: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Answers to questions about the recordings, served at `/query`: the OCR
+//! text and transcripts found for the question by the hybrid search of
+//! `/search/semantic` are given to a local LLM, which answers citing them,
+//! see [`screenpipe_core::answering`].
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Json, Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::{DateTime, Local, Utc};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_core::answering::{Answerer, Excerpt};
+use screenpipe_db::{ContentType, DatabaseManager, SearchResult, SemanticSearchResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{debug, error};
+
+use crate::semantic_index::embed_texts;
+use crate::server::AppState;
+
+/// Characters of the text of a citation returned with an answer.
+const CITATION_TEXT_CHARS: usize = 300;
+
+/// Recording an answer relies on.
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    /// Number the answer cites it with, like `[2]`
+    pub number: usize,
+    /// `ocr` or `audio`
+    pub content_type: String,
+    /// Frame the text was on, for OCR text
+    pub frame_id: Option<i64>,
+    /// Transcript segment, for audio
+    pub transcription_id: Option<i64>,
+    pub timestamp: DateTime<Utc>,
+    pub app_name: Option<String>,
+    pub window_name: Option<String>,
+    pub device_name: String,
+    pub speaker_name: Option<String>,
+    /// Start of the cited text
+    pub text: String,
+}
+
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryAnswer {
+    pub answer: String,
+    /// Recordings the answer cites, in the order of their numbers
+    pub citations: Vec<Citation>,
+    /// LLM that answered
+    pub model: String,
+}
+
+/// Excerpt given to the model for a search result and its citation, for
+/// OCR text and transcripts.
+fn excerpt(result: &SearchResult, number: usize) -> Option<(Excerpt, Citation)> {
+    let (source, text, citation) = match result {
+        SearchResult::OCR(ocr) => (
+            format!("screen, {}", ocr.app_name),
+            &ocr.ocr_text,
+            Citation {
+                number,
+                content_type: "ocr".to_string(),
+                frame_id: Some(ocr.frame_id),
+                transcription_id: None,
+                timestamp: ocr.timestamp,
+                app_name: Some(ocr.app_name.clone()),
+                window_name: Some(ocr.window_name.clone()),
+                device_name: ocr.device_name.clone(),
+                speaker_name: None,
+                text: String::new(),
+            },
+        ),
+        SearchResult::Audio(audio) => {
+            let speaker_name = audio
+                .speaker
+                .as_ref()
+                .map(|speaker| speaker.name.clone())
+                .filter(|name| !name.is_empty());
+            (
+                match &speaker_name {
+                    Some(name) => format!("audio, {}", name),
+                    None => format!("audio, {}", audio.device_name),
+                },
+                &audio.transcription,
+                Citation {
+                    number,
+                    content_type: "audio".to_string(),
+                    frame_id: None,
+                    transcription_id: Some(audio.transcription_id),
+                    timestamp: audio.timestamp,
+                    app_name: None,
+                    window_name: None,
+                    device_name: audio.device_name.clone(),
+                    speaker_name,
+                    text: String::new(),
+                },
+            )
+        }
+        _ => return None,
+    };
+    if text.trim().is_empty() {
+        return None;
+    }
+    let source = format!(
+        "{}, {}",
+        source,
+        citation
+            .timestamp
+            .with_timezone(&Local)
+            .format("%A %-d %B %Y %H:%M")
+    );
+    Some((
+        Excerpt {
+            source,
+            text: text.clone(),
+        },
+        Citation {
+            text: text.chars().take(CITATION_TEXT_CHARS).collect(),
+            ..citation
+        },
+    ))
+}
+
+/// OCR text and transcripts relevant to `question`, the most relevant first.
+pub async fn retrieve(
+    db: &DatabaseManager,
+    question: &str,
+    content_type: ContentType,
+    limit: u32,
+) -> Result<Vec<SemanticSearchResult>> {
+    let embedding = embed_texts(vec![question.to_string()])
+        .await?
+        .into_iter()
+        .next()
+        .context("no embedding generated for the question")?;
+    Ok(db
+        .search_semantic(question, &embedding, content_type, limit)
+        .await?)
+}
+
+/// Answers `question` from `results`. Returns `None` when none of them has
+/// text to answer from.
+pub async fn answer(
+    answerer: &Answerer,
+    question: &str,
+    results: &[SemanticSearchResult],
+) -> Result<Option<QueryAnswer>> {
+    let mut excerpts = Vec::new();
+    let mut citations = Vec::new();
+    for hit in results {
+        if let Some((excerpt, citation)) = excerpt(&hit.result, citations.len() + 1) {
+            excerpts.push(excerpt);
+            citations.push(citation);
+        }
+    }
+    if excerpts.is_empty() {
+        return Ok(None);
+    }
+
+    let content = answerer.answer(question, &excerpts).await?;
+    let mut cited: Vec<Citation> = content
+        .citations
+        .iter()
+        .map(|number| citations[number - 1].clone())
+        .collect();
+    cited.sort_by_key(|citation| citation.number);
+    Ok(Some(QueryAnswer {
+        answer: content.answer,
+        citations: cited,
+        model: answerer.model(),
+    }))
+}
+
+#[derive(Debug, OaSchema, Deserialize)]
+pub(crate) struct HybridSearchQuery {
+    q: String,
+    #[serde(default = "default_hybrid_search_limit")]
+    limit: u32,
+    #[serde(default)]
+    content_type: ContentType,
+}
+
+fn default_hybrid_search_limit() -> u32 {
+    20
+}
+
+/// Hybrid search over OCR text and transcripts, combining embedding
+/// similarity to `q` computed with the local embedding model and bm25
+/// keyword ranking. Only content embedded by the semantic search indexer
+/// (`--enable-semantic-search`) is found by meaning.
+#[oasgen]
+pub(crate) async fn hybrid_search_handler(
+    Query(query): Query<HybridSearchQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Vec<SemanticSearchResult>>, (StatusCode, JsonResponse<Value>)> {
+    debug!("hybrid search for '{}' with limit {}", query.q, query.limit);
+
+    let embedding = match embed_texts(vec![query.q.clone()]).await {
+        Ok(mut embeddings) if !embeddings.is_empty() => embeddings.remove(0),
+        Ok(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": "no embedding generated for query"})),
+            ));
+        }
+        Err(e) => {
+            error!("failed to generate embedding: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to generate embedding: {}", e)})),
+            ));
+        }
+    };
+
+    match state
+        .db
+        .search_semantic(&query.q, &embedding, query.content_type, query.limit)
+        .await
+    {
+        Ok(results) => Ok(JsonResponse(results)),
+        Err(e) => {
+            error!("failed to run hybrid search: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to run hybrid search: {}", e)})),
+            ))
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct QueryRequest {
+    question: String,
+    /// Search results the answer is written from
+    #[serde(default = "default_query_limit")]
+    limit: u32,
+    #[serde(default)]
+    content_type: ContentType,
+}
+
+fn default_query_limit() -> u32 {
+    10
+}
+
+/// Answers a question about the recordings with a local LLM, from the OCR
+/// text and transcripts the hybrid search finds for it, citing the frames
+/// and transcript segments it relies on.
+#[oasgen]
+pub(crate) async fn query_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<QueryRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let failure = |status: StatusCode, error: String| {
+        (
+            status,
+            JsonResponse(json!({
+                "error": error,
+                "success": false
+            })),
+        )
+    };
+    let answerer = state.answerer.clone().ok_or_else(|| {
+        failure(
+            StatusCode::NOT_FOUND,
+            "queries are disabled, start screenpipe with --enable-query".to_string(),
+        )
+    })?;
+    let question = request.question.trim();
+    if question.is_empty() {
+        return Err(failure(
+            StatusCode::BAD_REQUEST,
+            "question must not be empty".to_string(),
+        ));
+    }
+
+    let results = retrieve(&state.db, question, request.content_type, request.limit)
+        .await
+        .map_err(|e| {
+            error!("failed to search for the question: {}", e);
+            failure(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to search for the question: {}", e),
+            )
+        })?;
+    match answer(&answerer, question, &results).await {
+        Ok(Some(answer)) => Ok(JsonResponse(json!({
+            "data": answer,
+            "success": true
+        }))),
+        Ok(None) => Err(failure(
+            StatusCode::NOT_FOUND,
+            "nothing recorded matches the question".to_string(),
+        )),
+        Err(e) => {
+            error!("failed to answer the question: {}", e);
+            Err(failure(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to answer the question: {}", e),
+            ))
+        }
+    }
+}
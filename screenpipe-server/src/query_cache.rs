@@ -0,0 +1,219 @@
+//! Results of aggregate queries kept for a while, so dashboards polling the
+//! same totals don't make SQLite count the same rows over and over. Entries
+//! expire after a TTL, and as soon as something is recorded in their time
+//! range.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    future::Future,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use lru::LruCache;
+use screenpipe_events::{subscribe_to_all_events, FRAME_INDEXED_EVENT, TRANSCRIPT_INDEXED_EVENT};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+/// How long results are kept when nothing is recorded in their range.
+pub const DEFAULT_QUERY_CACHE_TTL: Duration = Duration::from_secs(60);
+/// Query results kept in memory.
+const CACHE_CAPACITY: usize = 1000;
+/// Events of new data, all carrying the `timestamp` it was recorded at.
+const INGEST_EVENT_TYPES: [&str; 2] = [FRAME_INDEXED_EVENT, TRANSCRIPT_INDEXED_EVENT];
+
+/// A query and its parameters, the same however the request spelled them:
+/// parameters are sorted by name, unset ones left out, text trimmed and
+/// times in UTC.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryKey {
+    name: &'static str,
+    params: BTreeMap<&'static str, String>,
+}
+
+impl QueryKey {
+    pub fn new(name: &'static str) -> Self {
+        QueryKey {
+            name,
+            params: BTreeMap::new(),
+        }
+    }
+
+    pub fn param(mut self, name: &'static str, value: Option<impl ToString>) -> Self {
+        if let Some(value) = value {
+            let value = value.to_string();
+            let value = value.trim();
+            if !value.is_empty() {
+                self.params.insert(name, value.to_string());
+            }
+        }
+        self
+    }
+
+    pub fn time(self, name: &'static str, value: Option<DateTime<Utc>>) -> Self {
+        self.param(name, value.map(|time| time.timestamp_micros()))
+    }
+}
+
+impl fmt::Display for QueryKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        for (i, (name, value)) in self.params.iter().enumerate() {
+            write!(f, "{}{}={}", if i == 0 { '?' } else { '&' }, name, value)?;
+        }
+        Ok(())
+    }
+}
+
+struct CachedQuery {
+    value: Value,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    cached_at: Instant,
+}
+
+impl CachedQuery {
+    /// Whether data recorded at `timestamp` may change the result, open
+    /// ends cover everything on their side.
+    fn covers(&self, timestamp: DateTime<Utc>) -> bool {
+        self.start_time.is_none_or(|start| start <= timestamp)
+            && self.end_time.is_none_or(|end| timestamp <= end)
+    }
+}
+
+pub struct QueryCache {
+    ttl: Duration,
+    entries: Mutex<LruCache<QueryKey, CachedQuery>>,
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        QueryCache::new(DEFAULT_QUERY_CACHE_TTL)
+    }
+}
+
+impl QueryCache {
+    /// A zero `ttl` caches nothing.
+    pub fn new(ttl: Duration) -> Self {
+        QueryCache {
+            ttl,
+            entries: Mutex::new(LruCache::new(NonZeroUsize::new(CACHE_CAPACITY).unwrap())),
+        }
+    }
+
+    /// Cached result of the query over the time range, running `query` when
+    /// there is none or it expired. Failures aren't cached.
+    pub async fn get_or_try_insert<T, E, F>(
+        &self,
+        key: QueryKey,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        query: F,
+    ) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+        F: Future<Output = Result<T, E>>,
+    {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+        let value = query.await?;
+        self.insert(key, start_time, end_time, &value);
+        Ok(value)
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &QueryKey) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap();
+        let cached = entries.get(key)?;
+        if cached.cached_at.elapsed() >= self.ttl {
+            entries.pop(key);
+            return None;
+        }
+        serde_json::from_value(cached.value.clone()).ok()
+    }
+
+    pub fn insert<T: Serialize>(
+        &self,
+        key: QueryKey,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        value: &T,
+    ) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let value = match serde_json::to_value(value) {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("failed to cache {}: {}", key, e);
+                return;
+            }
+        };
+        self.entries.lock().unwrap().put(
+            key,
+            CachedQuery {
+                value,
+                start_time,
+                end_time,
+                cached_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Drops the results whose time range covers `timestamp`, returns how
+    /// many there were.
+    pub fn invalidate(&self, timestamp: DateTime<Utc>) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let stale: Vec<QueryKey> = entries
+            .iter()
+            .filter(|(_, cached)| cached.covers(timestamp))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &stale {
+            entries.pop(key);
+        }
+        stale.len()
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Drops the results covering what gets recorded until shutdown.
+    pub async fn run(self: Arc<Self>, mut shutdown_rx: broadcast::Receiver<()>) {
+        let mut events = subscribe_to_all_events();
+
+        loop {
+            let event = tokio::select! {
+                event = events.next() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+                _ = shutdown_rx.recv() => {
+                    info!("received shutdown signal, stopping query cache invalidation");
+                    break;
+                }
+            };
+            if !INGEST_EVENT_TYPES.contains(&event.name.as_str()) {
+                continue;
+            }
+            let Some(timestamp) = event
+                .data
+                .get("timestamp")
+                .and_then(|timestamp| serde_json::from_value(timestamp.clone()).ok())
+            else {
+                continue;
+            };
+            let dropped = self.invalidate(timestamp);
+            if dropped > 0 {
+                debug!("dropped {} cached queries after {}", dropped, event.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Removal of personal information from captured frames before anything is
+//! stored. OCR text is rewritten with placeholders and, when enabled, the
+//! areas of the frame showing it are blurred in the recorded video.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use image::{DynamicImage, GenericImage};
+use screenpipe_core::ner::NerDetector;
+use screenpipe_core::pii_removal::{PiiDetector, PiiKind, Redactor, RegexDetector};
+use screenpipe_db::{AccessibilityNode, ExtractedTable, OcrLayoutBlock, TextBounds};
+use screenpipe_vision::core::{DeferredOcr, WindowOcrResult};
+use screenpipe_vision::CaptureResult;
+
+/// Strength of the blur over redacted text, high enough to make it unreadable.
+const BLUR_SIGMA: f32 = 8.0;
+
+#[derive(Clone)]
+pub struct Redaction {
+    pub redactor: Arc<Redactor>,
+    /// Also blur the text in the stored frames.
+    pub blur_frames: bool,
+}
+
+impl Redaction {
+    /// Finds `kinds` with regular expressions and, if `ner` is set, names
+    /// with the named entity model, which is downloaded on first use.
+    pub fn new(kinds: &[PiiKind], ner: bool, blur_frames: bool) -> Result<Self> {
+        let mut detectors: Vec<Box<dyn PiiDetector>> = vec![Box::new(RegexDetector::new(kinds))];
+        if ner {
+            detectors.push(Box::new(NerDetector::new(None)?));
+        }
+        Ok(Self {
+            redactor: Arc::new(Redactor::new(detectors)),
+            blur_frames,
+        })
+    }
+}
+
+/// Replaces personal information in the text, layout and tables of a window.
+pub fn redact_window_result(window: &mut WindowOcrResult, redactor: &Redactor) {
+    redact_ocr(
+        &mut window.text,
+        &mut window.text_json,
+        &mut window.layout,
+        &mut window.tables,
+        redactor,
+    );
+    if let Some(tree) = window.accessibility_tree.as_mut() {
+        redact_accessibility_node(tree, redactor);
+    }
+}
+
+/// Replaces personal information in the text of a window OCR'd late.
+pub fn redact_deferred_ocr(ocr: &mut DeferredOcr, redactor: &Redactor) {
+    redact_ocr(
+        &mut ocr.text,
+        &mut ocr.text_json,
+        &mut ocr.layout,
+        &mut ocr.tables,
+        redactor,
+    );
+}
+
+fn redact_ocr(
+    text: &mut String,
+    text_json: &mut [HashMap<String, String>],
+    layout: &mut [OcrLayoutBlock],
+    tables: &mut [ExtractedTable],
+    redactor: &Redactor,
+) {
+    *text = redactor.redact(text);
+    for text in text_json
+        .iter_mut()
+        .filter_map(|entry| entry.get_mut("text"))
+    {
+        *text = redactor.redact(text);
+    }
+    for line in layout
+        .iter_mut()
+        .flat_map(|b| b.paragraphs.iter_mut())
+        .flat_map(|p| p.lines.iter_mut())
+    {
+        line.text = redactor.redact(&line.text);
+    }
+    for cell in tables
+        .iter_mut()
+        .flat_map(|t| t.rows.iter_mut())
+        .flat_map(|r| r.iter_mut())
+    {
+        *cell = redactor.redact(cell);
+    }
+}
+
+/// Replaces personal information in the labels and values of a UI tree.
+fn redact_accessibility_node(node: &mut AccessibilityNode, redactor: &Redactor) {
+    for text in [&mut node.label, &mut node.value].into_iter().flatten() {
+        *text = redactor.redact(text);
+    }
+    for child in node.children.iter_mut() {
+        redact_accessibility_node(child, redactor);
+    }
+}
+
+/// Redacts every window of a captured frame, blurring the lines holding
+/// personal information if configured.
+pub fn redact_capture(result: &mut CaptureResult, redaction: &Redaction) {
+    let mut frame_rects = Vec::new();
+
+    for window in result.window_ocr_results.iter_mut() {
+        if redaction.blur_frames {
+            let lines: Vec<TextBounds> = window
+                .layout
+                .iter()
+                .flat_map(|b| b.paragraphs.iter())
+                .flat_map(|p| p.lines.iter())
+                .filter(|line| redaction.redactor.contains_pii(&line.text))
+                .map(|line| line.bounds)
+                .collect();
+
+            let window_rect = (0, 0, window.image.width(), window.image.height());
+            let window_rects: Vec<_> = lines.iter().map(|b| to_rect(b, window_rect)).collect();
+            blur_rects(&mut window.image, &window_rects);
+
+            if let Some(frame_rect) = window.frame_rect {
+                frame_rects.extend(lines.iter().map(|b| to_rect(b, frame_rect)));
+            }
+        }
+        redact_window_result(window, &redaction.redactor);
+    }
+
+    blur_rects(&mut result.image, &frame_rects);
+}
+
+/// Maps bounds relative to a window to pixels of the image the window is in.
+fn to_rect(
+    bounds: &TextBounds,
+    (x, y, width, height): (u32, u32, u32, u32),
+) -> (u32, u32, u32, u32) {
+    let left = (bounds.left.clamp(0.0, 1.0) * width as f32).floor() as u32;
+    let top = (bounds.top.clamp(0.0, 1.0) * height as f32).floor() as u32;
+    let right = (bounds.right().clamp(0.0, 1.0) * width as f32).ceil() as u32;
+    let bottom = (bounds.bottom().clamp(0.0, 1.0) * height as f32).ceil() as u32;
+    (
+        x + left,
+        y + top,
+        right.saturating_sub(left),
+        bottom.saturating_sub(top),
+    )
+}
+
+/// Blurs the given `(x, y, width, height)` areas of the image.
+pub fn blur_rects(image: &mut DynamicImage, rects: &[(u32, u32, u32, u32)]) {
+    for &(x, y, width, height) in rects {
+        if x >= image.width() || y >= image.height() {
+            continue;
+        }
+        let width = width.min(image.width() - x);
+        let height = height.min(image.height() - y);
+        if width == 0 || height == 0 {
+            continue;
+        }
+        let blurred = image.crop_imm(x, y, width, height).blur(BLUR_SIGMA);
+        // the area lies within the image, checked above
+        let _ = image.copy_from(&blurred, x, y);
+    }
+}
@@ -0,0 +1,179 @@
+//! Remote access from other machines on the LAN, enabled with `--remote`.
+//! The API is served over TLS on its own port and always requires a token,
+//! see [`crate::auth`]. The instance is advertised over mDNS so other
+//! screenpipe instances can find it with [`discover`].
+
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use base64::{engine::general_purpose, Engine as _};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sysinfo::{System, SystemExt};
+use tracing::{debug, info};
+
+pub const SERVICE_TYPE: &str = "_screenpipe._tcp.local.";
+const CERT_FILE: &str = "cert.pem";
+const KEY_FILE: &str = "key.pem";
+
+#[derive(Debug, Clone)]
+pub enum TlsSource {
+    /// Certificate generated on first use and kept in `dir`, so clients can
+    /// keep trusting its fingerprint
+    SelfSigned {
+        dir: PathBuf,
+    },
+    Provided {
+        cert: PathBuf,
+        key: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoteConfig {
+    pub port: u16,
+    pub tls: TlsSource,
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    /// SHA-256 fingerprint of the certificate, for clients to pin
+    pub fingerprint: String,
+}
+
+impl TlsSource {
+    pub fn files(&self) -> anyhow::Result<TlsFiles> {
+        let (cert, key) = match self {
+            TlsSource::SelfSigned { dir } => {
+                let (cert, key) = (dir.join(CERT_FILE), dir.join(KEY_FILE));
+                if !cert.exists() || !key.exists() {
+                    generate_self_signed(dir, &cert, &key)?;
+                }
+                (cert, key)
+            }
+            TlsSource::Provided { cert, key } => (cert.clone(), key.clone()),
+        };
+        let pem = fs::read_to_string(&cert)
+            .with_context(|| format!("failed to read certificate {}", cert.display()))?;
+        Ok(TlsFiles {
+            fingerprint: cert_fingerprint(&pem)?,
+            cert,
+            key,
+        })
+    }
+}
+
+fn generate_self_signed(dir: &Path, cert: &Path, key: &Path) -> anyhow::Result<()> {
+    info!("generating a self-signed certificate in {}", dir.display());
+    let names = vec!["localhost".to_string(), format!("{}.local", host_name())];
+    let generated = rcgen::generate_simple_self_signed(names)?;
+    fs::create_dir_all(dir)?;
+    fs::write(cert, generated.cert.pem())?;
+    fs::write(key, generated.key_pair.serialize_pem())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(key, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+/// Fingerprint of the first certificate in a PEM file, as colon separated hex.
+pub fn cert_fingerprint(pem: &str) -> anyhow::Result<String> {
+    let body: String = pem
+        .lines()
+        .skip_while(|line| !line.starts_with("-----BEGIN CERTIFICATE-----"))
+        .skip(1)
+        .take_while(|line| !line.starts_with("-----END CERTIFICATE-----"))
+        .collect();
+    if body.is_empty() {
+        anyhow::bail!("no certificate found");
+    }
+    let der = general_purpose::STANDARD.decode(body.trim())?;
+    let fingerprint: Vec<String> = Sha256::digest(&der)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect();
+    Ok(fingerprint.join(":"))
+}
+
+pub fn host_name() -> String {
+    System::new()
+        .host_name()
+        .unwrap_or_else(|| "screenpipe".to_string())
+}
+
+/// Advertises the instance on the LAN for as long as the daemon runs.
+pub fn advertise(port: u16, fingerprint: &str) -> anyhow::Result<ServiceDaemon> {
+    let mdns = ServiceDaemon::new()?;
+    let host = host_name();
+    let properties = [
+        ("version", env!("CARGO_PKG_VERSION")),
+        ("tls", "1"),
+        ("fingerprint", fingerprint),
+    ];
+    let service = ServiceInfo::new(
+        SERVICE_TYPE,
+        &host,
+        &format!("{}.local.", host),
+        "",
+        port,
+        &properties[..],
+    )?
+    .enable_addr_auto();
+    mdns.register(service)?;
+    info!("advertising remote access over mdns as {}", host);
+    Ok(mdns)
+}
+
+/// A screenpipe instance found on the LAN.
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteInstance {
+    pub name: String,
+    pub host: String,
+    pub addresses: Vec<IpAddr>,
+    pub port: u16,
+    pub version: Option<String>,
+    pub fingerprint: Option<String>,
+}
+
+/// Instances advertising remote access, listening for `timeout`.
+pub async fn discover(timeout: Duration) -> anyhow::Result<Vec<RemoteInstance>> {
+    let mdns = ServiceDaemon::new()?;
+    let receiver = mdns.browse(SERVICE_TYPE)?;
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut instances: Vec<RemoteInstance> = Vec::new();
+
+    while let Ok(Ok(event)) = tokio::time::timeout_at(deadline, receiver.recv_async()).await {
+        if let ServiceEvent::ServiceResolved(info) = event {
+            debug!("resolved {}", info.get_fullname());
+            let name = info
+                .get_fullname()
+                .trim_end_matches(SERVICE_TYPE)
+                .trim_end_matches('.')
+                .to_string();
+            let mut addresses: Vec<IpAddr> = info.get_addresses().iter().copied().collect();
+            addresses.sort();
+            let instance = RemoteInstance {
+                name,
+                host: info.get_hostname().trim_end_matches('.').to_string(),
+                addresses,
+                port: info.get_port(),
+                version: info.get_property_val_str("version").map(str::to_string),
+                fingerprint: info.get_property_val_str("fingerprint").map(str::to_string),
+            };
+            instances.retain(|known| known.name != instance.name);
+            instances.push(instance);
+        }
+    }
+
+    let _ = mdns.shutdown();
+    instances.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(instances)
+}
@@ -0,0 +1,295 @@
+//! Retention policy: deletes recordings and extracted text once they are
+//! older than a configurable number of days, separately per kind of data.
+
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::{extract::State, http::StatusCode, response::Json as JsonResponse};
+use chrono::{DateTime, Utc};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_db::{DatabaseManager, MediaChunk, MediaType, PartitionMonth};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::offload::object_store;
+use crate::server::AppState;
+
+/// How often the policy is enforced.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Days each kind of data is kept, `None` keeps it forever. Shared behind a
+/// lock so it can be changed at runtime through `/settings/retention`.
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Whether the policy is enforced in the background
+    pub enabled: bool,
+    pub video_days: Option<u32>,
+    pub audio_days: Option<u32>,
+    pub ocr_days: Option<u32>,
+    pub transcript_days: Option<u32>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            video_days: Some(7),
+            audio_days: Some(3),
+            ocr_days: Some(90),
+            transcript_days: None,
+        }
+    }
+}
+
+pub type SharedRetentionPolicy = Arc<RwLock<RetentionPolicy>>;
+
+/// Converts a number of days from the CLI or API, where 0 keeps forever.
+pub fn retention_days(days: u32) -> Option<u32> {
+    (days > 0).then_some(days)
+}
+
+/// What a retention run deleted, or would delete on a dry run.
+#[derive(OaSchema, Debug, Default, Clone, Serialize)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    pub video_files: usize,
+    pub video_bytes: u64,
+    pub audio_files: usize,
+    pub audio_bytes: u64,
+    pub ocr_frames: u64,
+    pub transcripts: u64,
+    /// Monthly partitions dropped whole, their rows aren't counted above
+    pub partitions: usize,
+}
+
+fn cutoff(now: DateTime<Utc>, days: Option<u32>) -> Option<DateTime<Utc>> {
+    days.map(|days| now - chrono::Duration::days(days as i64))
+}
+
+/// Size of the files of `chunks`, deleting them unless `dry_run`. Files that
+/// are already gone count as deleted, offloaded ones are deleted from object
+/// storage.
+pub(crate) async fn remove_media_files(chunks: &[MediaChunk], dry_run: bool) -> (usize, u64) {
+    let mut bytes = 0;
+    for chunk in chunks {
+        let path = Path::new(&chunk.file_path);
+        bytes += tokio::fs::metadata(path)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        if dry_run {
+            continue;
+        }
+        match tokio::fs::remove_file(path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                // it may have been offloaded
+                if let Some(store) = object_store() {
+                    if let Err(e) = store.delete(path).await {
+                        warn!("failed to delete offloaded {}: {}", chunk.file_path, e);
+                    }
+                }
+            }
+            Err(e) => warn!("failed to delete {}: {}", chunk.file_path, e),
+        }
+    }
+    (chunks.len(), bytes)
+}
+
+/// Applies `policy` once. With `dry_run` nothing is deleted and the report
+/// tells what would be. Text is deleted before media, so files of chunks
+/// whose rows are all gone are removed along with them.
+pub async fn enforce_retention(
+    db: &DatabaseManager,
+    policy: &RetentionPolicy,
+    dry_run: bool,
+) -> Result<RetentionReport> {
+    let now = Utc::now();
+    let mut report = RetentionReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    if let Some(before) = cutoff(now, policy.ocr_days) {
+        if dry_run {
+            report.ocr_frames = db.count_frames_before(before).await?;
+        } else {
+            let deleted = db.delete_frames_before(before).await?;
+            let (files, bytes) = remove_media_files(&deleted.media_chunks, false).await;
+            report.ocr_frames = deleted.rows;
+            report.video_files += files;
+            report.video_bytes += bytes;
+        }
+    }
+
+    if let Some(before) = cutoff(now, policy.transcript_days) {
+        if dry_run {
+            report.transcripts = db.count_audio_transcriptions_before(before).await?;
+        } else {
+            let deleted = db.delete_audio_transcriptions_before(before).await?;
+            let (files, bytes) = remove_media_files(&deleted.media_chunks, false).await;
+            report.transcripts = deleted.rows;
+            report.audio_files += files;
+            report.audio_bytes += bytes;
+        }
+    }
+
+    // a partition holds both kinds of text, it goes once neither is kept
+    if let (Some(ocr_before), Some(transcripts_before)) = (
+        cutoff(now, policy.ocr_days),
+        cutoff(now, policy.transcript_days),
+    ) {
+        let before = ocr_before.min(transcripts_before);
+        for partition in db.list_partitions().await? {
+            let Ok(month) = partition.month.parse::<PartitionMonth>() else {
+                continue;
+            };
+            if month.end() > before {
+                continue;
+            }
+            report.partitions += 1;
+            if dry_run {
+                continue;
+            }
+            if let Some(dropped) = db.drop_partition(month).await? {
+                let (files, bytes) = remove_media_files(&dropped.video_chunks, false).await;
+                report.video_files += files;
+                report.video_bytes += bytes;
+                let (files, bytes) = remove_media_files(&dropped.audio_chunks, false).await;
+                report.audio_files += files;
+                report.audio_bytes += bytes;
+            }
+        }
+    }
+
+    for (media_type, days) in [
+        (MediaType::Video, policy.video_days),
+        (MediaType::Audio, policy.audio_days),
+    ] {
+        let Some(before) = cutoff(now, days) else {
+            continue;
+        };
+        let chunks = db.get_media_chunks_before(media_type, before, None).await?;
+        let (files, bytes) = remove_media_files(&chunks, dry_run).await;
+        if !dry_run {
+            let ids: Vec<i64> = chunks.iter().map(|chunk| chunk.id).collect();
+            db.mark_media_chunks_deleted(media_type, &ids).await?;
+        }
+        match media_type {
+            MediaType::Video => {
+                report.video_files += files;
+                report.video_bytes += bytes;
+            }
+            MediaType::Audio => {
+                report.audio_files += files;
+                report.audio_bytes += bytes;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Enforces the policy every hour while it is enabled, until a shutdown is
+/// signalled.
+pub async fn run_retention(
+    db: Arc<DatabaseManager>,
+    policy: SharedRetentionPolicy,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    loop {
+        let current = *policy.read().unwrap_or_else(|e| e.into_inner());
+        if current.enabled {
+            match enforce_retention(&db, &current, false).await {
+                Ok(report) => info!("retention cleanup done: {:?}", report),
+                Err(e) => error!("retention cleanup failed: {}", e),
+            }
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(RETENTION_INTERVAL) => {}
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping retention cleanup");
+                break;
+            }
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub struct RetentionUpdateRequest {
+    pub enabled: Option<bool>,
+    /// Days to keep each kind of data, 0 keeps it forever
+    pub video_days: Option<u32>,
+    pub audio_days: Option<u32>,
+    pub ocr_days: Option<u32>,
+    pub transcript_days: Option<u32>,
+}
+
+#[oasgen]
+pub(crate) async fn get_retention_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let policy = *state.retention.read().unwrap_or_else(|e| e.into_inner());
+    Ok(JsonResponse(json!({
+        "data": policy,
+        "success": true
+    })))
+}
+
+#[oasgen]
+pub(crate) async fn update_retention_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<RetentionUpdateRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let mut policy = state.retention.write().unwrap_or_else(|e| e.into_inner());
+
+    let updated = RetentionPolicy {
+        enabled: payload.enabled.unwrap_or(policy.enabled),
+        video_days: payload.video_days.map_or(policy.video_days, retention_days),
+        audio_days: payload.audio_days.map_or(policy.audio_days, retention_days),
+        ocr_days: payload.ocr_days.map_or(policy.ocr_days, retention_days),
+        transcript_days: payload
+            .transcript_days
+            .map_or(policy.transcript_days, retention_days),
+    };
+
+    *policy = updated;
+    info!("retention policy updated: {:?}", updated);
+
+    Ok(JsonResponse(json!({
+        "data": updated,
+        "success": true
+    })))
+}
+
+/// Reports what the current retention policy would delete, whether or not
+/// it is enabled.
+#[oasgen]
+pub(crate) async fn retention_dry_run_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let policy = *state.retention.read().unwrap_or_else(|e| e.into_inner());
+
+    match enforce_retention(&state.db, &policy, true).await {
+        Ok(report) => Ok(JsonResponse(json!({
+            "data": report,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("retention dry run failed: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("retention dry run failed: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! Saved searches registered through `/search/saved`. They are checked every
+//! `--saved-search-interval-secs` against what was recorded since they were
+//! last checked, and fire a desktop notification through the app, a webhook
+//! and a `saved_search_matched` event when something new matches.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::Utc;
+use oasgen::{oasgen, OaSchema};
+use screenpipe_db::{ContentType, DatabaseManager, SavedSearch, SearchResult, SearchSort};
+use screenpipe_events::{send_event, SavedSearchMatchedEvent, SAVED_SEARCH_MATCHED_EVENT};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::server::AppState;
+
+/// Where the desktop app shows notifications, see its `/notify` route.
+pub const DEFAULT_NOTIFICATION_URL: &str = "http://localhost:11435/notify";
+
+/// Matches sent to a webhook at most per check.
+const MAX_MATCHES: u32 = 20;
+
+/// Characters of the first match shown in a notification.
+const NOTIFICATION_PREVIEW_CHARS: usize = 120;
+
+/// Delivers the matches of saved searches.
+#[derive(Clone)]
+pub struct SavedSearchNotifier {
+    client: reqwest::Client,
+    notification_url: String,
+}
+
+impl SavedSearchNotifier {
+    pub fn new(notification_url: String) -> Self {
+        SavedSearchNotifier {
+            client: reqwest::Client::new(),
+            notification_url,
+        }
+    }
+
+    async fn notify(&self, search: &SavedSearch, matches: &[SearchResult]) -> Result<()> {
+        let mut body = format!("{} new matches", matches.len());
+        if let Some(first) = matches.first() {
+            body = format!("{}: {}", body, preview(first));
+        }
+        self.client
+            .post(&self.notification_url)
+            .json(&json!({
+                "title": format!("saved search \"{}\" matched", search.name),
+                "body": body,
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn call_webhook(
+        &self,
+        url: &str,
+        search: &SavedSearch,
+        matches: &[SearchResult],
+    ) -> Result<()> {
+        self.client
+            .post(url)
+            .json(&json!({
+                "saved_search": search,
+                "matches": matches,
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Start of the text of a match, on one line.
+fn preview(result: &SearchResult) -> String {
+    let text = match result {
+        SearchResult::OCR(ocr) => ocr.ocr_text.clone(),
+        SearchResult::Audio(audio) => audio.transcription.clone(),
+        SearchResult::UI(ui) => ui.text.clone(),
+        SearchResult::Table(table) => table
+            .rows
+            .iter()
+            .map(|row| row.join(" "))
+            .collect::<Vec<_>>()
+            .join(" "),
+        SearchResult::Clipboard(entry) => entry.text.clone(),
+    };
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(NOTIFICATION_PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+/// What recorded since `search` was last checked matches it, newest first.
+pub async fn find_new_matches(
+    db: &DatabaseManager,
+    search: &SavedSearch,
+    until: chrono::DateTime<Utc>,
+) -> Result<Vec<SearchResult>> {
+    let content_type: ContentType = serde_json::from_value(json!(search.content_type))
+        .with_context(|| format!("unknown content type {}", search.content_type))?;
+    let speaker_ids = match &search.speaker_name {
+        Some(name) => {
+            let ids: Vec<i64> = db
+                .search_speakers(name)
+                .await?
+                .into_iter()
+                .map(|speaker| speaker.id)
+                .collect();
+            // nobody was named like this yet
+            if ids.is_empty() {
+                return Ok(Vec::new());
+            }
+            Some(ids)
+        }
+        None => None,
+    };
+
+    let since = search.last_checked_at.unwrap_or(search.created_at);
+    let matches = db
+        .search(
+            &search.query,
+            content_type,
+            MAX_MATCHES,
+            0,
+            Some(since),
+            Some(until),
+            search.app_name.as_deref(),
+            search.window_name.as_deref(),
+            None,
+            None,
+            speaker_ids,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            SearchSort::Time,
+        )
+        .await?;
+    Ok(matches)
+}
+
+/// Checks every saved search once, returning how many of them matched.
+pub async fn check_saved_searches(
+    db: &DatabaseManager,
+    notifier: &SavedSearchNotifier,
+) -> Result<usize> {
+    let mut matched = 0;
+    for search in db.list_saved_searches().await? {
+        let until = Utc::now();
+        let matches = match find_new_matches(db, &search, until).await {
+            Ok(matches) => matches,
+            Err(e) => {
+                error!("failed to check saved search {}: {}", search.id, e);
+                continue;
+            }
+        };
+
+        // a failed delivery isn't retried, so the same matches aren't sent
+        // over and over while the app or webhook is down
+        if !matches.is_empty() {
+            matched += 1;
+            if let Err(e) = send_event(
+                SAVED_SEARCH_MATCHED_EVENT,
+                SavedSearchMatchedEvent {
+                    saved_search_id: search.id,
+                    name: search.name.clone(),
+                    query: search.query.clone(),
+                    matches: matches.len(),
+                    preview: preview(&matches[0]),
+                },
+            ) {
+                error!("failed to send saved search {} match: {}", search.id, e);
+            }
+            if search.notify {
+                if let Err(e) = notifier.notify(&search, &matches).await {
+                    warn!(
+                        "failed to notify of saved search {} matches: {}",
+                        search.id, e
+                    );
+                }
+            }
+            if let Some(url) = &search.webhook_url {
+                if let Err(e) = notifier.call_webhook(url, &search, &matches).await {
+                    warn!(
+                        "failed to call webhook of saved search {}: {}",
+                        search.id, e
+                    );
+                }
+            }
+        }
+        db.mark_saved_search_checked(search.id, until, !matches.is_empty())
+            .await?;
+    }
+    Ok(matched)
+}
+
+pub async fn run_saved_search_watcher(
+    db: Arc<DatabaseManager>,
+    notifier: SavedSearchNotifier,
+    interval: Duration,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!(
+        "checking saved searches every {} seconds",
+        interval.as_secs()
+    );
+
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                match check_saved_searches(&db, &notifier).await {
+                    Ok(0) => {}
+                    Ok(matched) => info!("{} saved searches matched", matched),
+                    Err(e) => error!("failed to check saved searches: {}", e),
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping saved searches");
+                break;
+            }
+        }
+    }
+}
+
+fn default_notify() -> bool {
+    true
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct SavedSearchRequest {
+    name: String,
+    /// Query like the `q` of `/search`
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    content_type: ContentType,
+    #[serde(default)]
+    app_name: Option<String>,
+    #[serde(default)]
+    window_name: Option<String>,
+    /// Start of the name of the speakers searched
+    #[serde(default)]
+    speaker_name: Option<String>,
+    /// Whether matches fire a desktop notification through the app
+    #[serde(default = "default_notify")]
+    notify: bool,
+    /// Url the matches are posted to
+    #[serde(default)]
+    webhook_url: Option<String>,
+}
+
+#[oasgen]
+pub(crate) async fn list_saved_searches_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let searches = state.db.list_saved_searches().await.map_err(|e| {
+        error!("failed to list saved searches: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(
+                json!({"error": format!("failed to list saved searches: {}", e), "success": false}),
+            ),
+        )
+    })?;
+    Ok(JsonResponse(json!({
+        "data": searches,
+        "success": true
+    })))
+}
+
+/// Saves a search that is checked against new recordings, firing a desktop
+/// notification or a webhook when it matches.
+#[oasgen]
+pub(crate) async fn create_saved_search_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(request): JsonResponse<SavedSearchRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let bad_request = |error: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": error, "success": false})),
+        )
+    };
+    if request.name.trim().is_empty() {
+        return Err(bad_request("name must not be empty"));
+    }
+    if request.q.trim().is_empty()
+        && request.app_name.is_none()
+        && request.window_name.is_none()
+        && request.speaker_name.is_none()
+    {
+        return Err(bad_request("a saved search needs a query or a filter"));
+    }
+    if let Some(url) = &request.webhook_url {
+        if !reqwest::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+            return Err(bad_request("webhook_url must be an http or https url"));
+        }
+    }
+    if !request.notify && request.webhook_url.is_none() {
+        return Err(bad_request(
+            "a saved search needs notify or a webhook_url to report matches",
+        ));
+    }
+
+    let content_type = serde_json::to_value(&request.content_type)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| "all".to_string());
+    let search = state
+        .db
+        .insert_saved_search(
+            request.name.trim(),
+            request.q.trim(),
+            &content_type,
+            request.app_name.as_deref(),
+            request.window_name.as_deref(),
+            request.speaker_name.as_deref(),
+            request.notify,
+            request.webhook_url.as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            error!("failed to save search: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to save search: {}", e), "success": false}),
+                ),
+            )
+        })?;
+    Ok(JsonResponse(json!({
+        "data": search,
+        "success": true
+    })))
+}
+
+#[oasgen]
+pub(crate) async fn delete_saved_search_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.delete_saved_search(id).await {
+        Ok(true) => Ok(JsonResponse(json!({"success": true}))),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("no saved search {}", id), "success": false})),
+        )),
+        Err(e) => {
+            error!("failed to delete saved search {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to delete saved search: {}", e), "success": false}),
+                ),
+            ))
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Background indexing for semantic search. New OCR text and transcripts
+//! are embedded with the local embedding model shortly after they are
+//! inserted, and the vectors are stored next to them in the database.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use screenpipe_db::DatabaseManager;
+use tokio::sync::broadcast;
+use tracing::{debug, error, info};
+
+use crate::embedding::embedding_endpoint::get_or_initialize_model;
+
+/// Rows of each kind embedded per batch.
+const BATCH_SIZE: u32 = 16;
+/// How long to wait for new rows once everything is embedded.
+const IDLE_INTERVAL: Duration = Duration::from_secs(10);
+/// Longer texts are cut before embedding, a frame full of text is mostly
+/// described by its start and the model gets slow on long inputs.
+const MAX_TEXT_CHARS: usize = 2000;
+
+/// Embeds `texts` with the local embedding model, off the async runtime.
+pub async fn embed_texts(texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+    let model = get_or_initialize_model().await?;
+    tokio::task::spawn_blocking(move || model.blocking_lock().generate_batch_embeddings(&texts))
+        .await?
+}
+
+/// Embeds one batch of rows that have no embedding yet. Returns how many
+/// rows were embedded.
+pub async fn index_batch(db: &DatabaseManager) -> Result<usize> {
+    let ocr = db.get_ocr_text_without_embeddings(BATCH_SIZE).await?;
+    let audio = db
+        .get_audio_transcriptions_without_embeddings(BATCH_SIZE)
+        .await?;
+    if ocr.is_empty() && audio.is_empty() {
+        return Ok(0);
+    }
+
+    let texts: Vec<String> = ocr
+        .iter()
+        .chain(audio.iter())
+        .map(|row| row.text.chars().take(MAX_TEXT_CHARS).collect())
+        .collect();
+    let embeddings = embed_texts(texts).await?;
+    let (ocr_embeddings, audio_embeddings) = embeddings.split_at(ocr.len());
+
+    for (row, embedding) in ocr.iter().zip(ocr_embeddings) {
+        db.insert_embeddings(row.id, serde_json::to_string(embedding)?)
+            .await?;
+    }
+    for (row, embedding) in audio.iter().zip(audio_embeddings) {
+        db.insert_audio_transcription_embedding(row.id, embedding)
+            .await?;
+    }
+
+    Ok(ocr.len() + audio.len())
+}
+
+/// Keeps embedding new rows until a shutdown is signalled.
+pub async fn run_semantic_indexer(
+    db: Arc<DatabaseManager>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!("starting semantic search indexer");
+
+    loop {
+        let wait = match index_batch(&db).await {
+            Ok(0) => IDLE_INTERVAL,
+            Ok(count) => {
+                debug!("embedded {} rows for semantic search", count);
+                Duration::ZERO
+            }
+            Err(e) => {
+                error!("semantic search indexing failed: {}", e);
+                IDLE_INTERVAL
+            }
+        };
+
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping semantic search indexer");
+                break;
+            }
+        }
+    }
+}
@@ -5,18 +5,29 @@ use axum::{
         Json, Path, Query, State,
     },
     http::StatusCode,
-    response::{IntoResponse, Json as JsonResponse, Response},
-    routing::get,
+    middleware,
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse, Json as JsonResponse, Response,
+    },
+    routing::{get, post},
     serve, Router,
 };
+use axum_server::tls_rustls::RustlsConfig;
 use oasgen::{oasgen, OaSchema, Server};
 
+use screenpipe_core::answering::Answerer;
+use screenpipe_core::inference::LocalInference;
+use screenpipe_core::pipe_logs::{read_logs, subscribe as subscribe_to_pipe_logs, PipeLogLevel};
+use screenpipe_core::pipe_settings::InvalidSettings;
+use screenpipe_core::summarization::Summarizer;
 use screenpipe_core::Desktop;
+use screenpipe_core::Language;
 
-use chrono::TimeZone;
 use screenpipe_db::{
-    ContentType, DatabaseManager, FrameData, Order, SearchMatch, SearchResult, Speaker,
-    TagContentType,
+    BrowserContext, ClipboardEntry, ContentType, DatabaseManager, ExportTranscript, FrameData,
+    FrameEntry, OcrLayoutBlock, Order, SearchCursor, SearchMatch, SearchResult, SearchSort,
+    Speaker, TagContentType, TextBounds, TimelineCursor, TimelineEventKind,
 };
 
 use tokio_util::io::ReaderStream;
@@ -25,19 +36,98 @@ use tokio::fs::File;
 
 use futures::{
     future::{try_join, try_join_all},
-    SinkExt, StreamExt,
+    SinkExt, Stream, StreamExt,
 };
 use image::ImageFormat::{self};
-use screenpipe_events::{send_event, subscribe_to_all_events, Event as ScreenpipeEvent};
+use screenpipe_events::{
+    send_event, subscribe_to_all_events, subscribe_to_event, Event as ScreenpipeEvent,
+    APP_SWITCHED_EVENT, AUDIO_DEVICE_SWITCHED_EVENT, FRAME_INDEXED_EVENT, HEALTH_CHANGED_EVENT,
+    SAVED_SEARCH_MATCHED_EVENT, TRANSCRIPT_INDEXED_EVENT,
+};
 
 use crate::{
+    activity::{
+        get_activity_segments_handler, get_activity_summary_handler,
+        get_app_usage_analytics_handler, get_context_switch_analytics_handler,
+        get_speaking_time_analytics_handler, get_word_count_analytics_handler,
+    },
+    annotations::{
+        create_annotation_handler, delete_annotation_handler, list_annotations_handler,
+        update_annotation_handler,
+    },
+    auth::{hash_token, require_token, AuthState},
+    calendar::get_calendar_events_handler,
+    companion::{
+        delete_companion_device_handler, get_companion_upload_handler,
+        list_companion_devices_handler, pair_companion_device_handler, put_companion_chunk_handler,
+        start_companion_upload_handler,
+    },
+    deep_link::{frame_link, time_link},
+    doctor::{doctor_handler, doctor_repair_handler},
     embedding::embedding_endpoint::create_embeddings,
+    export::export_handler,
+    extraction::{
+        create_extraction_rule_handler, delete_extraction_rule_handler, get_extractions_handler,
+        list_extraction_rules_handler,
+    },
+    focus::{
+        get_focus_interruptions_handler, get_focus_streaks_handler, get_focus_switches_handler,
+        list_focus_events_handler,
+    },
+    graphql::{build_schema, graphiql_handler, graphql_handler, ScreenpipeSchema},
+    health::{health_check, noise_suppression_statuses, publish_health_changes, ws_health_handler},
+    hls::{get_hls_playlist_handler, get_hls_segment_handler, HlsStreamer},
+    idle_monitor::get_idle_gaps_handler,
+    ingest::{get_ingest_handler, ingest_audio_handler, ingest_handler, ingest_video_handler},
+    input_capture::get_input_events_handler,
+    llm::{
+        chat_completions_handler, list_llm_models_handler, list_openai_models_handler,
+        load_llm_model_handler,
+    },
+    logging::{get_log_levels_handler, reset_log_levels_handler, set_log_levels_handler},
+    mcp::{mcp_messages_handler, mcp_sse_handler, McpServer, McpTool},
+    meetings::{get_meeting_handler, get_meeting_transcript_handler, list_meetings_handler},
+    metrics::metrics_handler,
+    obsidian::obsidian_export_handler,
+    ocr_queue::ocr_queue_status_handler,
+    offload::{object_store, readable_media},
+    partitions::{
+        archive_partition_handler, drop_partition_handler, list_partitions_handler,
+        search_partitions_handler,
+    },
+    pipe_bus::{bus_publish_handler, bus_subscribe_handler},
+    privacy_audit::get_privacy_audit_handler,
+    query::{hybrid_search_handler, query_handler},
+    query_cache::{QueryCache, QueryKey},
+    remote::{advertise, RemoteConfig},
+    retention::{
+        get_retention_handler, remove_media_files, retention_dry_run_handler,
+        update_retention_handler,
+    },
+    saved_search::{
+        create_saved_search_handler, delete_saved_search_handler, list_saved_searches_handler,
+    },
+    shutdown::shutdown_handler,
+    summaries::{get_summaries_handler, summarize_handler},
+    sync::{sync_changes_handler, SyncService},
+    thumbnails::{
+        get_frame_thumbnail_handler, get_sprite_index_handler, get_sprite_sheet_handler,
+        ThumbnailCache,
+    },
     video::{finish_ffmpeg_process, start_ffmpeg_process, write_frame_to_ffmpeg, MAX_FPS},
     video_cache::{AudioEntry, DeviceFrame, FrameCache, FrameMetadata, TimeSeriesFrame},
+    video_encoder::VideoEncoder,
     video_utils::{
         extract_frame, extract_frame_from_video, extract_high_quality_frame, merge_videos,
         validate_media, MergeVideosRequest, MergeVideosResponse, ValidateMediaParams,
     },
+    vocabulary::{
+        add_vocabulary_term_handler, delete_vocabulary_term_handler, list_vocabulary_handler,
+    },
+    webhooks::{
+        create_webhook_handler, delete_webhook_handler, list_webhook_dead_letters_handler,
+        list_webhooks_handler, retry_webhook_dead_letter_handler,
+    },
     PipeManager,
 };
 use chrono::{DateTime, Utc};
@@ -46,35 +136,45 @@ use screenpipe_audio::{
     core::device::{
         default_input_device, default_output_device, list_audio_devices, AudioDevice, DeviceType,
     },
+    core::engine::AudioTranscriptionEngine,
+    core::ChunkingConfig,
+    transcription::deepgram::streaming::RealtimeTranscriptionEvent,
+    vad::{VadConfig, VadEngineEnum, VadSensitivity},
 };
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
+use screenpipe_vision::frame_rate::{AdaptiveFpsConfig, SharedAdaptiveFpsConfig};
 use screenpipe_vision::monitor::{get_monitor_by_id, list_monitors};
 use screenpipe_vision::OcrEngine;
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::{json, Value};
 use std::{
-    net::SocketAddr,
+    convert::Infallible,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     num::NonZeroUsize,
     path::PathBuf,
-    sync::{atomic::Ordering, Arc},
+    sync::Arc,
     time::{Duration, Instant},
 };
 
 use lru::LruCache;
 
 use tokio::{
+    io::AsyncWriteExt,
     net::TcpListener,
-    sync::{mpsc, Mutex},
+    sync::{broadcast, mpsc, Mutex},
     time::timeout,
 };
 
 use tower_http::{cors::Any, trace::TraceLayer};
 use tower_http::{cors::CorsLayer, trace::DefaultMakeSpan};
 
+use clap::ValueEnum;
 use enigo::{Enigo, Key, Settings};
 use std::str::FromStr;
 
+use crate::retention::SharedRetentionPolicy;
+
 use crate::text_embeds::generate_embedding;
 
 use screenpipe_core::UIElement;
@@ -87,6 +187,8 @@ pub struct AppState {
     pub db: Arc<DatabaseManager>,
     pub audio_manager: Arc<AudioManager>,
     pub app_start_time: DateTime<Utc>,
+    /// Port of the api on localhost
+    pub port: u16,
     pub screenpipe_dir: PathBuf,
     pub pipe_manager: Arc<PipeManager>,
     pub vision_disabled: bool,
@@ -95,7 +197,26 @@ pub struct AppState {
     pub enable_pipe_manager: bool,
     pub frame_cache: Option<Arc<FrameCache>>,
     pub frame_image_cache: Option<Arc<Mutex<FrameImageCache>>>,
+    pub thumbnails: Arc<ThumbnailCache>,
+    pub hls: Arc<HlsStreamer>,
+    /// Totals of `/search`, `/activity/summary` and `/analytics/usage`
+    pub query_cache: Arc<QueryCache>,
     pub element_cache: Arc<Mutex<Option<(Vec<UIElement>, Instant, String)>>>,
+    pub adaptive_fps: SharedAdaptiveFpsConfig,
+    pub retention: SharedRetentionPolicy,
+    /// MCP server for clients connecting over SSE, `None` when disabled
+    pub mcp: Option<Arc<McpServer>>,
+    pub graphql: ScreenpipeSchema,
+    /// Serves indexed text to other devices, `None` when sync is disabled
+    pub sync: Option<Arc<SyncService>>,
+    /// Writes summaries on request, `None` when summaries are disabled
+    pub summarizer: Option<Arc<Summarizer>>,
+    /// The built-in LLM, `None` when disabled
+    pub inference: Option<Arc<LocalInference>>,
+    /// Answers `/query`, `None` when it's disabled
+    pub answerer: Option<Arc<Answerer>>,
+    /// Stops screenpipe on `/admin/shutdown`, `None` when it can't be
+    pub shutdown: Option<broadcast::Sender<()>>,
 }
 
 // Update the SearchQuery struct
@@ -123,24 +244,64 @@ pub(crate) struct SearchQuery {
     #[serde(default)]
     max_length: Option<usize>,
     #[serde(
+        alias = "speaker_id",
         deserialize_with = "from_comma_separated_array",
         default = "default_speaker_ids"
     )]
     speaker_ids: Option<Vec<i64>>,
     #[serde(default)]
     focused: Option<bool>,
-    #[serde(default)]
+    /// part of the active tab url, e.g. `github.com/mediar-ai`
+    #[serde(default, alias = "url")]
     browser_url: Option<String>,
+    #[serde(default)]
+    include_blocks: bool,
+    /// spoken language of audio results, e.g. `de` or `german`
+    #[serde(default)]
+    language: Option<String>,
+    /// tag of `/tags` or of an annotation, for frames, transcripts and ui
+    /// text tagged with it
+    #[serde(default)]
+    tag: Option<String>,
+    /// part of the title of a calendar event, e.g. `Sprint planning`, for
+    /// what was captured during it
+    #[serde(default)]
+    meeting: Option<String>,
+    /// name of a speaker, e.g. an enrolled voice profile, for their
+    /// transcripts like `speaker_ids`
+    #[serde(default)]
+    speaker_name: Option<String>,
+    /// `time` for the newest results first, `relevance` for the best matches
+    /// of `q` first
+    #[serde(default)]
+    sort: SearchSort,
+    /// words of `q` also match words this many typos away, up to 2, for
+    /// text the ocr got slightly wrong
+    #[serde(default, deserialize_with = "deserialize_number_from_string")]
+    edit_distance: u32,
+    /// adds the frames and transcripts this many seconds around each result
+    /// to it, up to 300
+    #[serde(default, deserialize_with = "deserialize_number_from_string")]
+    context_seconds: u32,
+    /// `next_cursor` of the previous page, for the results after it. Unlike
+    /// `offset` it stays fast however deep it goes, not with `sort=relevance`
+    #[serde(default)]
+    cursor: Option<String>,
 }
 
+/// Longest `context_seconds` of `/search`.
+const MAX_CONTEXT_SECONDS: u32 = 300;
+/// Most frames, and most transcripts, in the context of a result.
+const MAX_CONTEXT_ITEMS: u32 = 100;
+
 #[derive(OaSchema, Deserialize)]
 pub(crate) struct PaginationQuery {
     #[serde(default = "default_limit")]
     #[serde(deserialize_with = "deserialize_number_from_string")]
-    limit: u32,
+    pub(crate) limit: u32,
     #[serde(default)]
     #[serde(deserialize_with = "deserialize_number_from_string")]
-    offset: u32,
+    pub(crate) offset: u32,
 }
 
 fn deserialize_number_from_string<'de, D>(deserializer: D) -> Result<u32, D::Error>
@@ -163,6 +324,9 @@ pub struct PaginationInfo {
     pub limit: u32,
     pub offset: u32,
     pub total: i64,
+    /// `cursor` of the next page, when the page is full
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
@@ -193,6 +357,8 @@ pub enum ContentItem {
     OCR(OCRContent),
     Audio(AudioContent),
     UI(UiContent),
+    Table(TableContent),
+    Clipboard(ClipboardEntry),
 }
 
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
@@ -208,12 +374,55 @@ pub struct OCRContent {
     pub frame: Option<String>,
     pub frame_name: Option<String>,
     pub browser_url: Option<String>,
+    #[serde(default)]
+    pub browser_context: Option<BrowserContext>,
     pub focused: Option<bool>,
     pub device_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocks: Option<Vec<OcrLayoutBlock>>,
+    /// Part of the text matching `q`, matches between `<mark>` and `</mark>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// `screenpipe://` link opening the timeline of the app here
+    #[serde(default)]
+    pub deep_link: String,
+    /// What was on screen and said around it, with `context_seconds`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<SearchContext>,
+}
+
+/// Frames and transcripts around a search result, oldest first.
+#[derive(OaSchema, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SearchContext {
+    pub frames: Vec<FrameEntry>,
+    pub transcripts: Vec<ExportTranscript>,
+}
+
+#[derive(OaSchema, Serialize, Deserialize, Debug)]
+pub struct TableContent {
+    pub table_id: i64,
+    pub frame_id: i64,
+    pub rows: Vec<Vec<String>>,
+    pub bounds: TextBounds,
+    pub timestamp: DateTime<Utc>,
+    pub file_path: String,
+    pub offset_index: i64,
+    pub app_name: String,
+    pub window_name: String,
+    pub device_name: String,
+    pub browser_url: Option<String>,
+    /// `screenpipe://` link opening the timeline of the app here
+    #[serde(default)]
+    pub deep_link: String,
+    /// What was on screen and said around it, with `context_seconds`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<SearchContext>,
 }
 
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
 pub struct AudioContent {
+    /// Id of the transcript segment, to annotate it
+    pub transcription_id: i64,
     pub chunk_id: i64,
     pub transcription: String,
     pub timestamp: DateTime<Utc>,
@@ -225,6 +434,18 @@ pub struct AudioContent {
     pub speaker: Option<Speaker>,
     pub start_time: Option<f64>,
     pub end_time: Option<f64>,
+    pub language: Option<String>,
+    pub translated_text: Option<String>,
+    /// Part of the transcription matching `q`, matches between `<mark>` and
+    /// `</mark>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// `screenpipe://` link opening the timeline of the app here
+    #[serde(default)]
+    pub deep_link: String,
+    /// What was on screen and said around it, with `context_seconds`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<SearchContext>,
 }
 
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
@@ -239,6 +460,15 @@ pub struct UiContent {
     pub offset_index: i64,
     pub frame_name: Option<String>,
     pub browser_url: Option<String>,
+    /// Part of the text matching `q`, matches between `<mark>` and `</mark>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// `screenpipe://` link opening the timeline of the app here
+    #[serde(default)]
+    pub deep_link: String,
+    /// What was on screen and said around it, with `context_seconds`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<SearchContext>,
 }
 
 #[derive(OaSchema, Serialize)]
@@ -281,21 +511,6 @@ fn default_limit() -> u32 {
     20
 }
 
-#[derive(Serialize, OaSchema, Deserialize)]
-pub struct HealthCheckResponse {
-    pub status: String,
-    pub status_code: u16,
-    pub last_frame_timestamp: Option<DateTime<Utc>>,
-    pub last_audio_timestamp: Option<DateTime<Utc>>,
-    pub last_ui_timestamp: Option<DateTime<Utc>>,
-    pub frame_status: String,
-    pub audio_status: String,
-    pub ui_status: String,
-    pub message: String,
-    pub verbose_instructions: Option<String>,
-    pub device_status_details: Option<String>,
-}
-
 #[derive(OaSchema, Serialize, Deserialize)]
 pub struct SearchResponse {
     pub data: Vec<ContentItem>,
@@ -309,7 +524,7 @@ pub(crate) async fn search(
     State(state): State<Arc<AppState>>,
 ) -> Result<JsonResponse<SearchResponse>, (StatusCode, JsonResponse<serde_json::Value>)> {
     info!(
-        "received search request: query='{}', content_type={:?}, limit={}, offset={}, start_time={:?}, end_time={:?}, app_name={:?}, window_name={:?}, min_length={:?}, max_length={:?}, speaker_ids={:?}, frame_name={:?}, browser_url={:?}, focused={:?}",
+        "received search request: query='{}', content_type={:?}, limit={}, offset={}, start_time={:?}, end_time={:?}, app_name={:?}, window_name={:?}, min_length={:?}, max_length={:?}, speaker_ids={:?}, speaker_name={:?}, frame_name={:?}, browser_url={:?}, focused={:?}, language={:?}, tag={:?}, meeting={:?}, sort={:?}, edit_distance={}",
         query.q.as_deref().unwrap_or(""),
         query.content_type,
         query.pagination.limit,
@@ -321,18 +536,36 @@ pub(crate) async fn search(
         query.min_length,
         query.max_length,
         query.speaker_ids,
+        query.speaker_name,
         query.frame_name,
         query.browser_url,
         query.focused,
+        query.language,
+        query.tag,
+        query.meeting,
+        query.sort,
+        query.edit_distance,
     );
 
-    let query_str = query.q.as_deref().unwrap_or("");
+    let cursor = search_cursor(&query)?;
+    let Some(terms) = search_terms(&state.db, &query).await? else {
+        return Ok(JsonResponse(SearchResponse {
+            data: Vec::new(),
+            pagination: PaginationInfo {
+                limit: query.pagination.limit,
+                offset: query.pagination.offset,
+                total: 0,
+                next_cursor: None,
+            },
+        }));
+    };
 
     let content_type = query.content_type.clone();
+    let archived = searches_partitions(&query);
 
-    let (results, total) = try_join(
-        state.db.search(
-            query_str,
+    let (mut results, (total, archived_total)) = try_join(
+        state.db.search_after(
+            &terms.query,
             content_type.clone(),
             query.pagination.limit,
             query.pagination.offset,
@@ -342,24 +575,59 @@ pub(crate) async fn search(
             query.window_name.as_deref(),
             query.min_length,
             query.max_length,
-            query.speaker_ids.clone(),
+            terms.speaker_ids.clone(),
             query.frame_name.as_deref(),
             query.browser_url.as_deref(),
             query.focused,
+            terms.language.as_deref(),
+            query.tag.as_deref(),
+            query.meeting.as_deref(),
+            query.sort,
+            cursor,
         ),
-        state.db.count_search_results(
-            query_str,
-            content_type,
+        state.query_cache.get_or_try_insert(
+            search_count_key(&query, &terms),
             query.start_time,
             query.end_time,
-            query.app_name.as_deref(),
-            query.window_name.as_deref(),
-            query.min_length,
-            query.max_length,
-            query.speaker_ids.clone(),
-            query.frame_name.as_deref(),
-            query.browser_url.as_deref(),
-            query.focused,
+            try_join(
+                state.db.count_search_results(
+                    &terms.query,
+                    content_type.clone(),
+                    query.start_time,
+                    query.end_time,
+                    query.app_name.as_deref(),
+                    query.window_name.as_deref(),
+                    query.min_length,
+                    query.max_length,
+                    terms.speaker_ids.clone(),
+                    query.frame_name.as_deref(),
+                    query.browser_url.as_deref(),
+                    query.focused,
+                    terms.language.as_deref(),
+                    query.tag.as_deref(),
+                    query.meeting.as_deref(),
+                ),
+                async {
+                    if !archived {
+                        return Ok(0);
+                    }
+                    state
+                        .db
+                        .count_archived(
+                            query.q.as_deref().unwrap_or(""),
+                            content_type.clone(),
+                            query.start_time,
+                            query.end_time,
+                            query.app_name.as_deref(),
+                            query.window_name.as_deref(),
+                            query.min_length,
+                            query.max_length,
+                            terms.speaker_ids.as_deref(),
+                            terms.language.as_deref(),
+                        )
+                        .await
+                },
+            ),
         ),
     )
     .await
@@ -371,51 +639,48 @@ pub(crate) async fn search(
         )
     })?;
 
-    let mut content_items: Vec<ContentItem> = results
-        .iter()
-        .map(|result| match result {
-            SearchResult::OCR(ocr) => ContentItem::OCR(OCRContent {
-                frame_id: ocr.frame_id,
-                text: ocr.ocr_text.clone(),
-                timestamp: ocr.timestamp,
-                file_path: ocr.file_path.clone(),
-                offset_index: ocr.offset_index,
-                app_name: ocr.app_name.clone(),
-                window_name: ocr.window_name.clone(),
-                tags: ocr.tags.clone(),
-                frame: None,
-                frame_name: Some(ocr.frame_name.clone()),
-                browser_url: ocr.browser_url.clone(),
-                focused: ocr.focused,
-                device_name: ocr.device_name.clone(),
-            }),
-            SearchResult::Audio(audio) => ContentItem::Audio(AudioContent {
-                chunk_id: audio.audio_chunk_id,
-                transcription: audio.transcription.clone(),
-                timestamp: audio.timestamp,
-                file_path: audio.file_path.clone(),
-                offset_index: audio.offset_index,
-                tags: audio.tags.clone(),
-                device_name: audio.device_name.clone(),
-                device_type: audio.device_type.clone().into(),
-                speaker: audio.speaker.clone(),
-                start_time: audio.start_time,
-                end_time: audio.end_time,
-            }),
-            SearchResult::UI(ui) => ContentItem::UI(UiContent {
-                id: ui.id,
-                text: ui.text.clone(),
-                timestamp: ui.timestamp,
-                app_name: ui.app_name.clone(),
-                window_name: ui.window_name.clone(),
-                initial_traversal_at: ui.initial_traversal_at,
-                file_path: ui.file_path.clone(),
-                offset_index: ui.offset_index,
-                frame_name: ui.frame_name.clone(),
-                browser_url: ui.browser_url.clone(),
-            }),
-        })
-        .collect();
+    // months moved to partitions come after the main database, once its
+    // results run out
+    if archived && (results.len() as u32) < query.pagination.limit {
+        let found = state
+            .db
+            .search_archived(
+                query.q.as_deref().unwrap_or(""),
+                content_type,
+                query.pagination.limit - results.len() as u32,
+                query.pagination.offset.saturating_sub(total as u32),
+                query.start_time,
+                query.end_time,
+                query.app_name.as_deref(),
+                query.window_name.as_deref(),
+                query.min_length,
+                query.max_length,
+                terms.speaker_ids.as_deref(),
+                terms.language.as_deref(),
+                cursor.as_ref(),
+            )
+            .await
+            .map_err(|e| {
+                error!("failed to search partitions: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    JsonResponse(json!({"error": format!("failed to search partitions: {}", e)})),
+                )
+            })?;
+        results.extend(found);
+    }
+    let total = total + archived_total;
+
+    // ranks don't carry over pages, so there's no cursor by relevance
+    let next_cursor = match results.last() {
+        Some(last)
+            if query.sort == SearchSort::Time && results.len() as u32 == query.pagination.limit =>
+        {
+            Some(last.cursor().to_string())
+        }
+        _ => None,
+    };
+    let mut content_items: Vec<ContentItem> = results.iter().map(content_item).collect();
 
     if query.include_frames {
         debug!("extracting frames for ocr content");
@@ -442,6 +707,60 @@ pub(crate) async fn search(
         }
     }
 
+    if query.include_blocks {
+        let frame_ids: Vec<i64> = content_items
+            .iter()
+            .filter_map(|item| match item {
+                ContentItem::OCR(ocr_content) => Some(ocr_content.frame_id),
+                _ => None,
+            })
+            .collect();
+
+        let mut blocks = state.db.get_ocr_blocks(&frame_ids).await.map_err(|e| {
+            error!("failed to fetch ocr blocks: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to fetch ocr blocks: {}", e)})),
+            )
+        })?;
+
+        for item in content_items.iter_mut() {
+            if let ContentItem::OCR(ref mut ocr_content) = item {
+                ocr_content.blocks = Some(blocks.remove(&ocr_content.frame_id).unwrap_or_default());
+            }
+        }
+    }
+
+    if query.context_seconds > 0 {
+        let seconds = query.context_seconds.min(MAX_CONTEXT_SECONDS);
+        let contexts = try_join_all(content_items.iter().map(|item| async {
+            match item_timestamp(item) {
+                Some(timestamp) => search_context(&state.db, timestamp, seconds)
+                    .await
+                    .map(Some),
+                None => Ok(None),
+            }
+        }))
+        .await
+        .map_err(|e| {
+            error!("failed to fetch search result context: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to fetch context: {}", e)})),
+            )
+        })?;
+
+        for (item, context) in content_items.iter_mut().zip(contexts) {
+            match item {
+                ContentItem::OCR(content) => content.context = context,
+                ContentItem::Audio(content) => content.context = context,
+                ContentItem::UI(content) => content.context = context,
+                ContentItem::Table(content) => content.context = context,
+                ContentItem::Clipboard(_) => {}
+            }
+        }
+    }
+
     info!("search completed: found {} results", total);
     Ok(JsonResponse(SearchResponse {
         data: content_items,
@@ -449,10 +768,335 @@ pub(crate) async fn search(
             limit: query.pagination.limit,
             offset: query.pagination.offset,
             total: total as i64,
+            next_cursor,
         },
     }))
 }
 
+/// `q`, language and speakers of a search as the db takes them.
+struct SearchTerms {
+    query: String,
+    language: Option<String>,
+    speaker_ids: Option<Vec<i64>>,
+}
+
+/// Terms of the search, `None` when `speaker_name` names no speaker so
+/// nothing can match.
+async fn search_terms(
+    db: &DatabaseManager,
+    query: &SearchQuery,
+) -> Result<Option<SearchTerms>, (StatusCode, JsonResponse<Value>)> {
+    let query_str = match query.q.as_deref() {
+        Some(q) if query.edit_distance > 0 => {
+            db.fuzzy_query(q, query.edit_distance).await.map_err(|e| {
+                error!("failed to expand fuzzy query: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    JsonResponse(json!({"error": format!("failed to expand fuzzy query: {}", e)})),
+                )
+            })?
+        }
+        q => q.unwrap_or("").to_string(),
+    };
+    // transcriptions store language codes, names like `german` are accepted too
+    let language = query.language.as_deref().map(|language| {
+        Language::from_str(language, true)
+            .map(|l| l.as_lang_code().to_string())
+            .unwrap_or_else(|_| language.to_lowercase())
+    });
+
+    let mut speaker_ids = query.speaker_ids.clone();
+    if let Some(name) = query.speaker_name.as_deref() {
+        let named = db.get_speaker_ids_by_name(name).await.map_err(|e| {
+            error!("failed to find speaker {}: {}", name, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to find speaker: {}", e)})),
+            )
+        })?;
+        if named.is_empty() {
+            return Ok(None);
+        }
+        speaker_ids.get_or_insert_with(Vec::new).extend(named);
+    }
+
+    Ok(Some(SearchTerms {
+        query: query_str,
+        language,
+        speaker_ids,
+    }))
+}
+
+/// Whether the search also runs on the months moved to partitions, which
+/// take its text, time range, app, window, length, speaker and language
+/// filters but not the others.
+fn searches_partitions(query: &SearchQuery) -> bool {
+    query.frame_name.is_none()
+        && query.browser_url.is_none()
+        && query.focused.is_none()
+        && query.tag.is_none()
+        && query.meeting.is_none()
+}
+
+/// Key of the totals of the search, the same for every page of it.
+fn search_count_key(query: &SearchQuery, terms: &SearchTerms) -> QueryKey {
+    let mut speaker_ids = terms.speaker_ids.clone().unwrap_or_default();
+    speaker_ids.sort_unstable();
+    speaker_ids.dedup();
+    let speaker_ids: Vec<String> = speaker_ids.iter().map(i64::to_string).collect();
+    QueryKey::new("search_count")
+        .param("q", Some(&terms.query))
+        .param("content_type", Some(format!("{:?}", query.content_type)))
+        .time("start_time", query.start_time)
+        .time("end_time", query.end_time)
+        .param("app_name", query.app_name.as_ref())
+        .param("window_name", query.window_name.as_ref())
+        .param("min_length", query.min_length)
+        .param("max_length", query.max_length)
+        .param("speaker_ids", Some(speaker_ids.join(",")))
+        .param("frame_name", query.frame_name.as_ref())
+        .param("browser_url", query.browser_url.as_ref())
+        .param("focused", query.focused)
+        .param("language", terms.language.as_ref())
+        .param("tag", query.tag.as_ref())
+        .param("meeting", query.meeting.as_ref())
+}
+
+fn search_cursor(
+    query: &SearchQuery,
+) -> Result<Option<SearchCursor>, (StatusCode, JsonResponse<Value>)> {
+    let bad_request = |error: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": error, "success": false})),
+        )
+    };
+    if query.cursor.is_some() && query.sort == SearchSort::Relevance {
+        return Err(bad_request(
+            "cursor can't be used with sort=relevance".to_string(),
+        ));
+    }
+    query
+        .cursor
+        .as_deref()
+        .map(str::parse::<SearchCursor>)
+        .transpose()
+        .map_err(|e| bad_request(e.0))
+}
+
+fn content_item(result: &SearchResult) -> ContentItem {
+    match result {
+        SearchResult::OCR(ocr) => ContentItem::OCR(OCRContent {
+            frame_id: ocr.frame_id,
+            text: ocr.ocr_text.clone(),
+            timestamp: ocr.timestamp,
+            file_path: ocr.file_path.clone(),
+            offset_index: ocr.offset_index,
+            app_name: ocr.app_name.clone(),
+            window_name: ocr.window_name.clone(),
+            tags: ocr.tags.clone(),
+            frame: None,
+            frame_name: Some(ocr.frame_name.clone()),
+            browser_url: ocr.browser_url.clone(),
+            browser_context: ocr.browser_context.clone(),
+            focused: ocr.focused,
+            device_name: ocr.device_name.clone(),
+            blocks: None,
+            snippet: ocr.snippet.clone(),
+            deep_link: frame_link(ocr.frame_id),
+            context: None,
+        }),
+        SearchResult::Audio(audio) => ContentItem::Audio(AudioContent {
+            transcription_id: audio.transcription_id,
+            chunk_id: audio.audio_chunk_id,
+            transcription: audio.transcription.clone(),
+            timestamp: audio.timestamp,
+            file_path: audio.file_path.clone(),
+            offset_index: audio.offset_index,
+            tags: audio.tags.clone(),
+            device_name: audio.device_name.clone(),
+            device_type: audio.device_type.clone().into(),
+            speaker: audio.speaker.clone(),
+            start_time: audio.start_time,
+            end_time: audio.end_time,
+            language: audio.language.clone(),
+            translated_text: audio.translated_text.clone(),
+            snippet: audio.snippet.clone(),
+            deep_link: time_link(audio.timestamp),
+            context: None,
+        }),
+        SearchResult::UI(ui) => ContentItem::UI(UiContent {
+            id: ui.id,
+            text: ui.text.clone(),
+            timestamp: ui.timestamp,
+            app_name: ui.app_name.clone(),
+            window_name: ui.window_name.clone(),
+            initial_traversal_at: ui.initial_traversal_at,
+            file_path: ui.file_path.clone(),
+            offset_index: ui.offset_index,
+            frame_name: ui.frame_name.clone(),
+            browser_url: ui.browser_url.clone(),
+            snippet: ui.snippet.clone(),
+            deep_link: time_link(ui.timestamp),
+            context: None,
+        }),
+        SearchResult::Table(table) => ContentItem::Table(TableContent {
+            table_id: table.table_id,
+            frame_id: table.frame_id,
+            rows: table.rows.clone(),
+            bounds: table.bounds,
+            timestamp: table.timestamp,
+            file_path: table.file_path.clone(),
+            offset_index: table.offset_index,
+            app_name: table.app_name.clone(),
+            window_name: table.window_name.clone(),
+            device_name: table.device_name.clone(),
+            browser_url: table.browser_url.clone(),
+            deep_link: frame_link(table.frame_id),
+            context: None,
+        }),
+        SearchResult::Clipboard(entry) => ContentItem::Clipboard(entry.clone()),
+    }
+}
+
+/// Results of `/search/stream` read from the db at a time.
+const SEARCH_STREAM_PAGE_SIZE: u32 = 500;
+
+/// Every result of a search as newline delimited JSON, newest first. Takes
+/// the filters of `/search` and reads the results page by page, so exports
+/// of months of data neither time out nor sit in memory. `limit` and
+/// `offset` are ignored, `cursor` starts the stream after it.
+#[oasgen]
+async fn search_stream_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Response<Body>, (StatusCode, JsonResponse<Value>)> {
+    if query.sort == SearchSort::Relevance {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({
+                "error": "search results are streamed by time, not by relevance",
+                "success": false
+            })),
+        ));
+    }
+    let cursor = search_cursor(&query)?;
+    let terms = search_terms(&state.db, &query).await?;
+
+    let (tx, rx) = mpsc::channel(4);
+    if let Some(terms) = terms {
+        tokio::spawn(forward_search_results(state, query, terms, cursor, tx));
+    }
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|lines| (lines, rx))
+    });
+
+    Response::builder()
+        .header("content-type", "application/x-ndjson")
+        .body(Body::from_stream(stream))
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to create response: {}", e), "success": false}),
+                ),
+            )
+        })
+}
+
+async fn forward_search_results(
+    state: Arc<AppState>,
+    query: SearchQuery,
+    terms: SearchTerms,
+    mut cursor: Option<SearchCursor>,
+    tx: mpsc::Sender<Result<String, std::io::Error>>,
+) {
+    loop {
+        let page = match state
+            .db
+            .search_after(
+                &terms.query,
+                query.content_type.clone(),
+                SEARCH_STREAM_PAGE_SIZE,
+                0,
+                query.start_time,
+                query.end_time,
+                query.app_name.as_deref(),
+                query.window_name.as_deref(),
+                query.min_length,
+                query.max_length,
+                terms.speaker_ids.clone(),
+                query.frame_name.as_deref(),
+                query.browser_url.as_deref(),
+                query.focused,
+                terms.language.as_deref(),
+                query.tag.as_deref(),
+                query.meeting.as_deref(),
+                SearchSort::Time,
+                cursor,
+            )
+            .await
+        {
+            Ok(page) => page,
+            Err(e) => {
+                // the response has started, so the stream is cut short
+                error!("failed to stream search results: {}", e);
+                let _ = tx.send(Err(std::io::Error::other(e))).await;
+                return;
+            }
+        };
+
+        let mut lines = String::new();
+        for result in &page {
+            match serde_json::to_string(&content_item(result)) {
+                Ok(line) => {
+                    lines.push_str(&line);
+                    lines.push('\n');
+                }
+                Err(e) => error!("failed to encode search result: {}", e),
+            }
+        }
+        if !lines.is_empty() && tx.send(Ok(lines)).await.is_err() {
+            break;
+        }
+        if page.len() < SEARCH_STREAM_PAGE_SIZE as usize {
+            break;
+        }
+        cursor = page.last().map(SearchResult::cursor);
+    }
+
+    debug!("search stream closed");
+}
+
+/// Time of the result, `None` for clipboard entries which have no context.
+fn item_timestamp(item: &ContentItem) -> Option<DateTime<Utc>> {
+    match item {
+        ContentItem::OCR(content) => Some(content.timestamp),
+        ContentItem::Audio(content) => Some(content.timestamp),
+        ContentItem::UI(content) => Some(content.timestamp),
+        ContentItem::Table(content) => Some(content.timestamp),
+        ContentItem::Clipboard(_) => None,
+    }
+}
+
+async fn search_context(
+    db: &DatabaseManager,
+    timestamp: DateTime<Utc>,
+    seconds: u32,
+) -> Result<SearchContext, sqlx::Error> {
+    let start = timestamp - chrono::Duration::seconds(seconds as i64);
+    let end = timestamp + chrono::Duration::seconds(seconds as i64);
+    let (frames, transcripts) = try_join(
+        db.get_export_frames(start, end, None, None, MAX_CONTEXT_ITEMS, 0),
+        db.get_export_transcripts(start, end, None, None, MAX_CONTEXT_ITEMS, 0),
+    )
+    .await?;
+    Ok(SearchContext {
+        frames,
+        transcripts,
+    })
+}
+
 #[oasgen]
 pub(crate) async fn api_list_audio_devices(
     State(_state): State<Arc<AppState>>,
@@ -592,214 +1236,15 @@ pub(crate) async fn remove_tags(
     }
 }
 
-#[oasgen]
-pub async fn health_check(State(state): State<Arc<AppState>>) -> JsonResponse<HealthCheckResponse> {
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
-    let app_uptime = (now as i64) - (state.app_start_time.timestamp());
-    let grace_period = 120; // 2 minutes in seconds
-
-    // Get the status of all devices
-    let audio_devices = state.audio_manager.current_devices();
-    let mut device_statuses = Vec::new();
-    let mut global_audio_active = false;
-    let mut most_recent_audio_timestamp = 0; // Track the most recent timestamp
-
-    // Check each device
-    for device in &audio_devices {
-        let device_name = device.to_string();
-        let last_capture = screenpipe_audio::core::get_device_capture_time(&device_name);
-
-        // Update the most recent timestamp
-        most_recent_audio_timestamp = most_recent_audio_timestamp.max(last_capture);
-
-        let device_active = if app_uptime < grace_period {
-            true // Consider active during grace period
-        } else {
-            now - last_capture < 5 // Consider active if captured in last 5 seconds
-        };
-
-        // Track if any device is active
-        if device_active {
-            global_audio_active = true;
-        }
-        debug!(target: "server", "device status: {} {}", device_name, device_active);
-
-        device_statuses.push((device_name, device_active, last_capture));
-    }
-
-    // Fallback to global timestamp if no devices are detected
-    if audio_devices.is_empty() {
-        let last_capture = screenpipe_audio::core::LAST_AUDIO_CAPTURE.load(Ordering::Relaxed);
-        global_audio_active = if app_uptime < grace_period {
-            true // Consider active during grace period
-        } else {
-            now - last_capture < 5 // Consider active if captured in last 5 seconds
-        };
-    }
-
-    let (last_frame, audio, last_ui) = match state.db.get_latest_timestamps().await {
-        Ok((frame, audio, ui)) => (frame, audio, ui),
-        Err(e) => {
-            error!("failed to get latest timestamps: {}", e);
-            (None, None, None)
-        }
-    };
-
-    let now = Utc::now();
-    let threshold = Duration::from_secs(1800); // 30 minutes
-
-    let frame_status = if state.vision_disabled {
-        "disabled"
-    } else {
-        match last_frame {
-            Some(timestamp)
-                if now.signed_duration_since(timestamp)
-                    < chrono::Duration::from_std(threshold).unwrap() =>
-            {
-                "ok"
-            }
-            Some(_) => "stale",
-            None => "not_started",
-        }
-    };
-
-    let audio_status = if state.audio_disabled {
-        "disabled".to_string()
-    } else if global_audio_active {
-        "ok".to_string()
-    } else {
-        match audio {
-            Some(timestamp)
-                if now.signed_duration_since(timestamp)
-                    < chrono::Duration::from_std(threshold).unwrap() =>
-            {
-                "stale".to_string()
-            }
-            Some(_) => "stale".to_string(),
-            None => "not_started".to_string(),
-        }
-    };
-
-    // Format device statuses as a string for a more detailed view
-    let device_status_details = if !device_statuses.is_empty() {
-        let now_secs = now.timestamp() as u64;
-        let device_details: Vec<String> = device_statuses
-            .iter()
-            .map(|(name, active, last_capture)| {
-                format!(
-                    "{}: {} (last activity: {}s ago)",
-                    name,
-                    if *active { "active" } else { "inactive" },
-                    now_secs.saturating_sub(*last_capture)
-                )
-            })
-            .collect();
-
-        Some(device_details.join(", "))
-    } else {
-        None
-    };
-
-    let ui_status = if !state.ui_monitoring_enabled {
-        "disabled"
-    } else {
-        match last_ui {
-            Some(timestamp)
-                if now.signed_duration_since(timestamp)
-                    < chrono::Duration::from_std(threshold).unwrap() =>
-            {
-                "ok"
-            }
-            Some(_) => "stale",
-            None => "not_started",
-        }
-    };
-
-    let (overall_status, message, verbose_instructions, status_code) = if (frame_status == "ok"
-        || frame_status == "disabled")
-        && (audio_status == "ok" || audio_status == "disabled")
-        && (ui_status == "ok" || ui_status == "disabled")
-    {
-        (
-            "healthy",
-            "all systems are functioning normally.".to_string(),
-            None,
-            200,
-        )
-    } else {
-        let mut unhealthy_systems = Vec::new();
-        if frame_status != "ok" && frame_status != "disabled" {
-            unhealthy_systems.push("vision");
-        }
-        if audio_status != "ok" && audio_status != "disabled" {
-            unhealthy_systems.push("audio");
-        }
-        if ui_status != "ok" && ui_status != "disabled" {
-            unhealthy_systems.push("ui");
-        }
-
-        let systems_str = unhealthy_systems.join(", ");
-        (
-            "degraded",
-            format!("some systems are not healthy: {}", systems_str),
-            Some(get_verbose_instructions(&unhealthy_systems)),
-            503,
-        )
-    };
-
-    JsonResponse(HealthCheckResponse {
-        status: overall_status.to_string(),
-        status_code,
-        last_frame_timestamp: last_frame,
-        last_audio_timestamp: if most_recent_audio_timestamp > 0 {
-            Some(
-                Utc.timestamp_opt(most_recent_audio_timestamp as i64, 0)
-                    .unwrap(),
-            )
-        } else {
-            None
-        },
-        last_ui_timestamp: last_ui,
-        frame_status: frame_status.to_string(),
-        audio_status,
-        ui_status: ui_status.to_string(),
-        message,
-        verbose_instructions,
-        device_status_details,
-    })
-}
-
-fn get_verbose_instructions(unhealthy_systems: &[&str]) -> String {
-    let mut instructions = String::new();
-
-    if unhealthy_systems.contains(&"vision") {
-        instructions.push_str("Vision system is not working properly. Check if screen recording permissions are enabled.\n");
-    }
-
-    if unhealthy_systems.contains(&"audio") {
-        instructions.push_str("Audio system is not working properly. Check if microphone permissions are enabled and devices are connected.\n");
-    }
-
-    if unhealthy_systems.contains(&"ui") {
-        instructions.push_str("UI monitoring is not working properly. Check if accessibility permissions are enabled.\n");
-    }
-
-    if instructions.is_empty() {
-        instructions =
-            "If you're experiencing issues, please try contacting us on Discord.".to_string();
-    }
-
-    instructions
-}
-
 // Request and response structs
 #[derive(OaSchema, Deserialize)]
 struct DownloadPipeRequest {
     url: String,
+    /// Install the pipe even if it isn't signed, is signed by a publisher
+    /// the user doesn't trust, or by another publisher than the installed
+    /// version
+    #[serde(default)]
+    allow_unsigned: bool,
 }
 
 #[derive(OaSchema, Deserialize)]
@@ -807,6 +1252,8 @@ struct DownloadPipePrivateRequest {
     url: String,
     pipe_name: String,
     pipe_id: String,
+    #[serde(default)]
+    allow_unsigned: bool,
 }
 
 #[derive(OaSchema, Deserialize)]
@@ -824,6 +1271,8 @@ struct UpdatePipeConfigRequest {
 struct UpdatePipeVersionRequest {
     pipe_id: String,
     source: String,
+    #[serde(default)]
+    allow_unsigned: bool,
 }
 
 #[oasgen]
@@ -841,7 +1290,11 @@ async fn download_pipe_handler(
         ));
     }
     debug!("Downloading pipe: {}", payload.url);
-    match state.pipe_manager.download_pipe(&payload.url).await {
+    match state
+        .pipe_manager
+        .download_pipe(&payload.url, payload.allow_unsigned)
+        .await
+    {
         Ok(pipe_dir) => Ok(JsonResponse(json!({
             "data": {
                 "pipe_id": pipe_dir,
@@ -878,7 +1331,12 @@ async fn download_pipe_private_handler(
     }
     match state
         .pipe_manager
-        .download_pipe_private(&payload.url, &payload.pipe_name, &payload.pipe_id)
+        .download_pipe_private(
+            &payload.url,
+            &payload.pipe_name,
+            &payload.pipe_id,
+            payload.allow_unsigned,
+        )
         .await
     {
         Ok(pipe_dir) => Ok(JsonResponse(json!({
@@ -1015,10 +1473,18 @@ async fn update_pipe_config_handler(
         }))),
         Err(e) => Err((
             StatusCode::BAD_REQUEST,
-            JsonResponse(json!({
-                "error": format!("failed to update pipe config: {}", e),
-                "success": false
-            })),
+            JsonResponse(match e.downcast_ref::<InvalidSettings>() {
+                // where each setting doesn't match, for the settings form
+                Some(invalid) => json!({
+                    "error": format!("failed to update pipe config: {}", invalid),
+                    "errors": invalid.0,
+                    "success": false
+                }),
+                None => json!({
+                    "error": format!("failed to update pipe config: {}", e),
+                    "success": false
+                }),
+            }),
         )),
     }
 }
@@ -1040,7 +1506,7 @@ async fn update_pipe_version_handler(
     debug!("Updating pipe version: {}", payload.pipe_id);
     match state
         .pipe_manager
-        .update_pipe_version(&payload.pipe_id, &payload.source)
+        .update_pipe_version(&payload.pipe_id, &payload.source, payload.allow_unsigned)
         .await
     {
         Ok(_) => Ok(JsonResponse(json!({
@@ -1091,23 +1557,283 @@ async fn get_pipe_info_handler(
 }
 
 #[oasgen]
-async fn list_pipes_handler(State(state): State<Arc<AppState>>) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
-    if !state.enable_pipe_manager {
+async fn get_pipe_stats_handler(
+    State(state): State<Arc<AppState>>,
+    Path(pipe_id): Path<String>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    if !state.enable_pipe_manager {
+        return Err((
+            StatusCode::FORBIDDEN,
+            JsonResponse(json!({
+                "error": "pipe functionality is disabled",
+                "success": false
+            })),
+        ));
+    }
+    if state.pipe_manager.get_pipe_info(&pipe_id).await.is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({
+                "error": "pipe not found",
+                "success": false
+            })),
+        ));
+    }
+    Ok(JsonResponse(json!({
+        "data": state.pipe_manager.get_pipe_stats(&pipe_id).await,
+        "success": true
+    })))
+}
+
+#[derive(OaSchema, Deserialize)]
+struct PipeLogsQuery {
+    /// Only entries logged after this time
+    #[serde(default)]
+    since: Option<DateTime<Utc>>,
+    /// Lowest level of the entries: `debug`, `info`, `warn` or `error`
+    #[serde(default)]
+    level: Option<String>,
+    #[serde(default = "default_pipe_logs_limit")]
+    limit: usize,
+}
+
+fn default_pipe_logs_limit() -> usize {
+    200
+}
+
+/// Checks that pipes are enabled and `pipe_id` exists, and parses the lowest
+/// log level asked for.
+async fn pipe_logs_level(
+    state: &AppState,
+    pipe_id: &str,
+    level: Option<&str>,
+) -> Result<Option<PipeLogLevel>, (StatusCode, JsonResponse<Value>)> {
+    if !state.enable_pipe_manager {
+        return Err((
+            StatusCode::FORBIDDEN,
+            JsonResponse(json!({
+                "error": "pipe functionality is disabled",
+                "success": false
+            })),
+        ));
+    }
+    if state.pipe_manager.get_pipe_info(pipe_id).await.is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({
+                "error": "pipe not found",
+                "success": false
+            })),
+        ));
+    }
+    level
+        .map(str::parse::<PipeLogLevel>)
+        .transpose()
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                JsonResponse(json!({
+                    "error": e.to_string(),
+                    "success": false
+                })),
+            )
+        })
+}
+
+/// Latest log entries of a pipe, oldest first.
+#[oasgen]
+async fn get_pipe_logs_handler(
+    State(state): State<Arc<AppState>>,
+    Path(pipe_id): Path<String>,
+    Query(query): Query<PipeLogsQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let level = pipe_logs_level(&state, &pipe_id, query.level.as_deref()).await?;
+    let screenpipe_dir = state.screenpipe_dir.clone();
+    let logs = tokio::task::spawn_blocking(move || {
+        read_logs(&screenpipe_dir, &pipe_id, query.since, level, query.limit)
+    })
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|logs| logs);
+    match logs {
+        Ok(logs) => Ok(JsonResponse(json!({
+            "data": logs,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to read pipe logs: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to read pipe logs: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
+#[oasgen]
+async fn list_pipes_handler(State(state): State<Arc<AppState>>) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    if !state.enable_pipe_manager {
+        return Err((
+            StatusCode::FORBIDDEN,
+            JsonResponse(json!({
+                "error": "pipe functionality is disabled",
+                "success": false
+            })),
+        ));
+    }
+    let pipes = state.pipe_manager.list_pipes().await;
+    Ok(JsonResponse(json!({
+        "data": pipes,
+        "success": true
+    })))
+}
+
+#[derive(OaSchema, Deserialize)]
+pub struct AdaptiveFpsUpdateRequest {
+    pub enabled: Option<bool>,
+    pub min_fps: Option<f64>,
+    pub idle_threshold: Option<f64>,
+    pub activity_threshold: Option<f64>,
+    pub idle_frames_before_slowdown: Option<u32>,
+}
+
+#[oasgen]
+async fn get_adaptive_fps_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let config = *state.adaptive_fps.read().unwrap_or_else(|e| e.into_inner());
+    Ok(JsonResponse(json!({
+        "data": config,
+        "success": true
+    })))
+}
+
+#[oasgen]
+async fn update_adaptive_fps_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<AdaptiveFpsUpdateRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let mut config = state
+        .adaptive_fps
+        .write()
+        .unwrap_or_else(|e| e.into_inner());
+
+    let updated = AdaptiveFpsConfig {
+        enabled: payload.enabled.unwrap_or(config.enabled),
+        min_fps: payload.min_fps.unwrap_or(config.min_fps),
+        idle_threshold: payload.idle_threshold.unwrap_or(config.idle_threshold),
+        activity_threshold: payload
+            .activity_threshold
+            .unwrap_or(config.activity_threshold),
+        idle_frames_before_slowdown: payload
+            .idle_frames_before_slowdown
+            .unwrap_or(config.idle_frames_before_slowdown),
+    };
+
+    if let Err(e) = updated.validate() {
         return Err((
-            StatusCode::FORBIDDEN,
+            StatusCode::BAD_REQUEST,
             JsonResponse(json!({
-                "error": "pipe functionality is disabled",
+                "error": e,
                 "success": false
             })),
         ));
     }
-    let pipes = state.pipe_manager.list_pipes().await;
+
+    *config = updated;
+    info!("adaptive fps settings updated: {:?}", updated);
+
     Ok(JsonResponse(json!({
-        "data": pipes,
+        "data": updated,
         "success": true
     })))
 }
 
+#[derive(OaSchema, Deserialize)]
+struct TimelineQuery {
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    /// `next_cursor` of the previous page
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default = "default_timeline_limit")]
+    limit: u32,
+    /// Comma separated event types: `frame`, `transcript`, `app_switch`,
+    /// `idle_gap` and `media_block`, all when not set
+    #[serde(default)]
+    types: Option<String>,
+}
+
+fn default_timeline_limit() -> u32 {
+    100
+}
+
+const MAX_TIMELINE_LIMIT: u32 = 1000;
+
+fn timeline_bad_request(error: String) -> (StatusCode, JsonResponse<Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        JsonResponse(json!({
+            "error": error,
+            "success": false
+        })),
+    )
+}
+
+/// Frames, transcripts, app switches and idle gaps of a time range in one
+/// stream ordered by time, paginated with a cursor.
+#[oasgen]
+async fn get_timeline_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TimelineQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    if query.end_time < query.start_time {
+        return Err(timeline_bad_request(
+            "end_time must not be before start_time".to_string(),
+        ));
+    }
+    let kinds = match &query.types {
+        Some(types) => types
+            .split(',')
+            .map(|kind| kind.trim().parse::<TimelineEventKind>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| timeline_bad_request(e.0))?,
+        None => TimelineEventKind::ALL.to_vec(),
+    };
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(str::parse::<TimelineCursor>)
+        .transpose()
+        .map_err(|e| timeline_bad_request(e.0))?;
+    let limit = query.limit.clamp(1, MAX_TIMELINE_LIMIT);
+
+    match state
+        .db
+        .get_timeline(query.start_time, query.end_time, &kinds, cursor, limit)
+        .await
+    {
+        Ok(page) => Ok(JsonResponse(json!({
+            "data": page.events,
+            "next_cursor": page.next_cursor,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to read the timeline: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to read the timeline: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
 pub struct SCServer {
     db: Arc<DatabaseManager>,
     addr: SocketAddr,
@@ -1118,6 +1844,18 @@ pub struct SCServer {
     audio_disabled: bool,
     ui_monitoring_enabled: bool,
     enable_pipe: bool,
+    adaptive_fps: SharedAdaptiveFpsConfig,
+    retention: SharedRetentionPolicy,
+    mcp_tools: Option<Vec<McpTool>>,
+    api_auth: bool,
+    app_token: Option<String>,
+    remote: Option<RemoteConfig>,
+    sync: Option<Arc<SyncService>>,
+    summarizer: Option<Arc<Summarizer>>,
+    inference: Option<Arc<LocalInference>>,
+    answerer: Option<Arc<Answerer>>,
+    query_cache: Arc<QueryCache>,
+    shutdown: Option<broadcast::Sender<()>>,
 }
 
 impl SCServer {
@@ -1143,33 +1881,164 @@ impl SCServer {
             ui_monitoring_enabled,
             audio_manager,
             enable_pipe,
+            adaptive_fps: Default::default(),
+            retention: Default::default(),
+            mcp_tools: None,
+            api_auth: false,
+            app_token: None,
+            remote: None,
+            sync: None,
+            summarizer: None,
+            inference: None,
+            answerer: None,
+            query_cache: Default::default(),
+            shutdown: None,
         }
     }
 
+    /// Shares the adaptive fps settings with the recorder so they can be
+    /// changed through `/vision/adaptive-fps` while recording.
+    pub fn with_adaptive_fps(mut self, adaptive_fps: SharedAdaptiveFpsConfig) -> Self {
+        self.adaptive_fps = adaptive_fps;
+        self
+    }
+
+    /// Shares the retention policy with the background cleanup so it can be
+    /// changed through `/settings/retention`.
+    pub fn with_retention(mut self, retention: SharedRetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Serves MCP clients over SSE at `/mcp/sse`, offering `tools`, all of
+    /// them when empty.
+    pub fn with_mcp(mut self, tools: Vec<McpTool>) -> Self {
+        self.mcp_tools = Some(tools);
+        self
+    }
+
+    /// Requires an api token on every endpoint except `/health`, see
+    /// [`crate::auth`].
+    pub fn with_api_auth(mut self) -> Self {
+        self.api_auth = true;
+        self
+    }
+
+    /// Accepts `token` as an admin token without storing it, for the app
+    /// that started screenpipe, see [`crate::auth::APP_TOKEN_ENV`].
+    pub fn with_app_token(mut self, token: String) -> Self {
+        self.app_token = Some(token);
+        self
+    }
+
+    /// Also serves the LAN over TLS, with tokens required, and advertises it
+    /// over mDNS, see [`crate::remote`].
+    pub fn with_remote(mut self, remote: RemoteConfig) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    /// Serves what this device captured to its sync peers at
+    /// `/sync/changes`, see [`crate::sync`].
+    pub fn with_sync(mut self, sync: Arc<SyncService>) -> Self {
+        self.sync = Some(sync);
+        self
+    }
+
+    /// Lets `/summaries` write summaries on request, see
+    /// [`crate::summaries`].
+    pub fn with_summarizer(mut self, summarizer: Arc<Summarizer>) -> Self {
+        self.summarizer = Some(summarizer);
+        self
+    }
+
+    /// Serves the built-in LLM at `/v1/chat/completions` and its models at
+    /// `/llm/models`, see [`screenpipe_core::inference`].
+    pub fn with_inference(mut self, inference: Arc<LocalInference>) -> Self {
+        self.inference = Some(inference);
+        self
+    }
+
+    /// Answers questions about the recordings at `/query`, see
+    /// [`crate::query`].
+    pub fn with_answerer(mut self, answerer: Arc<Answerer>) -> Self {
+        self.answerer = Some(answerer);
+        self
+    }
+
+    /// Shares the query cache with its invalidation on new recordings, see
+    /// [`crate::query_cache`].
+    pub fn with_query_cache(mut self, query_cache: Arc<QueryCache>) -> Self {
+        self.query_cache = query_cache;
+        self
+    }
+
+    /// Lets `/admin/shutdown` stop screenpipe like ctrl+c does, by sending
+    /// on `shutdown`, see [`crate::shutdown`].
+    pub fn with_shutdown(mut self, shutdown: broadcast::Sender<()>) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
     pub async fn start(self, enable_frame_cache: bool) -> Result<(), std::io::Error> {
+        let app_state = self.app_state(enable_frame_cache).await;
+        tokio::spawn(publish_health_changes(app_state.clone()));
+
         // Create the OpenAPI server
-        let app = self.create_router(enable_frame_cache).await;
+        let routes = self.routes(app_state);
+        let app = self.with_layers(routes.clone(), self.api_auth);
 
         // Create the listener
         let listener = TcpListener::bind(&self.addr).await?;
         info!("Server listening on {}", self.addr);
 
         // Start serving
-        serve(
-            listener,
-            app.into_make_service_with_connect_info::<SocketAddr>(),
-        )
-        .await
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        let local = async {
+            serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+        };
 
+        let Some(remote) = &self.remote else {
+            return local.await;
+        };
+        // the lan always needs a token
+        let remote_app = self.with_layers(routes, true);
+        let tls = remote
+            .tls
+            .files()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        let tls_config = RustlsConfig::from_pem_file(&tls.cert, &tls.key).await?;
+        // kept until the server stops
+        let _mdns = advertise(remote.port, &tls.fingerprint)
+            .map_err(|e| warn!("failed to advertise over mdns: {}", e))
+            .ok();
+        let remote_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), remote.port);
+        info!(
+            "Remote access listening on https://{} (certificate fingerprint {})",
+            remote_addr, tls.fingerprint
+        );
+        let remote_server = axum_server::bind_rustls(remote_addr, tls_config)
+            .serve(remote_app.into_make_service_with_connect_info::<SocketAddr>());
+
+        tokio::try_join!(local, remote_server)?;
         Ok(())
     }
 
     pub async fn create_router(&self, enable_frame_cache: bool) -> Router {
-        let app_state = Arc::new(AppState {
+        let routes = self.routes(self.app_state(enable_frame_cache).await);
+        self.with_layers(routes, self.api_auth)
+    }
+
+    async fn app_state(&self, enable_frame_cache: bool) -> Arc<AppState> {
+        Arc::new(AppState {
             db: self.db.clone(),
             audio_manager: self.audio_manager.clone(),
             app_start_time: Utc::now(),
+            port: self.addr.port(),
             screenpipe_dir: self.screenpipe_dir.clone(),
             pipe_manager: self.pipe_manager.clone(),
             vision_disabled: self.vision_disabled,
@@ -1192,25 +2061,106 @@ impl SCServer {
             } else {
                 None
             },
+            thumbnails: Arc::new(ThumbnailCache::default()),
+            hls: Arc::new(HlsStreamer::default()),
+            query_cache: self.query_cache.clone(),
             element_cache: Arc::new(Mutex::new(None)),
-        });
+            adaptive_fps: self.adaptive_fps.clone(),
+            retention: self.retention.clone(),
+            mcp: self
+                .mcp_tools
+                .clone()
+                .map(|tools| Arc::new(McpServer::new(self.db.clone(), tools))),
+            graphql: build_schema(self.db.clone()),
+            sync: self.sync.clone(),
+            summarizer: self.summarizer.clone(),
+            inference: self.inference.clone(),
+            answerer: self.answerer.clone(),
+            shutdown: self.shutdown.clone(),
+        })
+    }
 
-        let cors = CorsLayer::new()
-            .allow_origin(Any)
-            .allow_methods(Any)
-            .allow_headers(Any)
-            .expose_headers([
-                axum::http::header::CONTENT_TYPE,
-                axum::http::header::CACHE_CONTROL,
-            ]);
+    fn routes(&self, app_state: Arc<AppState>) -> Router {
         let server = Server::axum()
             .get("/search", search)
+            .get("/search/stream", search_stream_handler)
+            .get("/search/saved", list_saved_searches_handler)
+            .post("/search/saved", create_saved_search_handler)
+            .delete("/search/saved/:id", delete_saved_search_handler)
+            .get("/vocabulary", list_vocabulary_handler)
+            .post("/vocabulary", add_vocabulary_term_handler)
+            .delete("/vocabulary/:id", delete_vocabulary_term_handler)
+            .get("/webhooks", list_webhooks_handler)
+            .post("/webhooks", create_webhook_handler)
+            .delete("/webhooks/:id", delete_webhook_handler)
+            .get("/webhooks/dead-letters", list_webhook_dead_letters_handler)
+            .post(
+                "/webhooks/dead-letters/:id/retry",
+                retry_webhook_dead_letter_handler,
+            )
             .get("/audio/list", api_list_audio_devices)
             .get("/vision/list", api_list_monitors)
+            .get("/vision/adaptive-fps", get_adaptive_fps_handler)
+            .post("/vision/adaptive-fps", update_adaptive_fps_handler)
+            .get("/settings/retention", get_retention_handler)
+            .post("/settings/retention", update_retention_handler)
+            .get("/settings/retention/dry-run", retention_dry_run_handler)
+            .get("/partitions", list_partitions_handler)
+            .post("/partitions", archive_partition_handler)
+            .get("/partitions/search", search_partitions_handler)
+            .delete("/partitions/:month", drop_partition_handler)
+            .get("/admin/doctor", doctor_handler)
+            .post("/admin/doctor/repair", doctor_repair_handler)
+            .get("/admin/log-levels", get_log_levels_handler)
+            .post("/admin/log-levels", set_log_levels_handler)
+            .delete("/admin/log-levels", reset_log_levels_handler)
+            .post("/admin/shutdown", shutdown_handler)
+            .get("/privacy/audit", get_privacy_audit_handler)
+            .get("/input/events", get_input_events_handler)
+            .get("/calendar/events", get_calendar_events_handler)
+            .get("/idle/gaps", get_idle_gaps_handler)
+            .get("/activity/segments", get_activity_segments_handler)
+            .get("/activity/summary", get_activity_summary_handler)
+            .get("/analytics/usage/apps", get_app_usage_analytics_handler)
+            .get(
+                "/analytics/usage/speakers",
+                get_speaking_time_analytics_handler,
+            )
+            .get("/analytics/usage/words", get_word_count_analytics_handler)
+            .get(
+                "/analytics/usage/context-switches",
+                get_context_switch_analytics_handler,
+            )
+            .get("/focus/events", list_focus_events_handler)
+            .get("/focus/streaks", get_focus_streaks_handler)
+            .get("/focus/switches", get_focus_switches_handler)
+            .get("/focus/interruptions", get_focus_interruptions_handler)
+            .get("/meetings", list_meetings_handler)
+            .get("/meetings/:id", get_meeting_handler)
+            .get("/meetings/:id/transcript", get_meeting_transcript_handler)
+            .get("/summaries", get_summaries_handler)
+            .post("/summaries", summarize_handler)
+            .get("/extractions", get_extractions_handler)
+            .get("/extractions/rules", list_extraction_rules_handler)
+            .post("/extractions/rules", create_extraction_rule_handler)
+            .delete("/extractions/rules/:id", delete_extraction_rule_handler)
+            .get("/timeline", get_timeline_handler)
+            .get("/export", export_handler)
+            .get("/ocr/queue", ocr_queue_status_handler)
+            .get("/ingest/:id", get_ingest_handler)
+            .get("/companion/devices", list_companion_devices_handler)
+            .post("/companion/devices", pair_companion_device_handler)
+            .delete("/companion/devices/:id", delete_companion_device_handler)
             .post("/tags/:content_type/:id", add_tags)
             .delete("/tags/:content_type/:id", remove_tags)
+            .get("/annotations", list_annotations_handler)
+            .post("/annotations", create_annotation_handler)
+            .post("/annotations/:id", update_annotation_handler)
+            .delete("/annotations/:id", delete_annotation_handler)
             .get("/pipes/info/:pipe_id", get_pipe_info_handler)
             .get("/pipes/list", list_pipes_handler)
+            .get("/pipes/:pipe_id/stats", get_pipe_stats_handler)
+            .get("/pipes/:pipe_id/logs", get_pipe_logs_handler)
             .post("/pipes/download", download_pipe_handler)
             .post("/pipes/download-private", download_pipe_private_handler)
             .post("/pipes/enable", run_pipe_handler)
@@ -1219,7 +2169,18 @@ impl SCServer {
             .post("/pipes/update-version", update_pipe_version_handler)
             .post("/pipes/delete", delete_pipe_handler)
             .post("/pipes/purge", purge_pipe_handler)
+            .post("/pipes/obsidian/export", obsidian_export_handler)
             .get("/frames/:frame_id", get_frame_data)
+            .get("/frames/:frame_id/thumbnail", get_frame_thumbnail_handler)
+            .get("/frames/sprite", get_sprite_sheet_handler)
+            .get("/frames/sprite/index", get_sprite_index_handler)
+            .get(
+                "/frames/:frame_id/accessibility",
+                get_frame_accessibility_handler,
+            )
+            .get("/media/file", get_media_file_handler)
+            .get("/stream/hls", get_hls_playlist_handler)
+            .get("/stream/hls/segments/:segment", get_hls_segment_handler)
             .get("/health", health_check)
             .post("/raw_sql", execute_raw_sql)
             .post("/add", add_to_database)
@@ -1230,6 +2191,7 @@ impl SCServer {
             .post("/speakers/hallucination", mark_as_hallucination_handler)
             .post("/speakers/merge", merge_speakers_handler)
             .get("/speakers/similar", get_similar_speakers_handler)
+            .get("/speakers/profiles", list_voice_profiles_handler)
             .post("/experimental/frames/merge", merge_frames_handler)
             .get("/experimental/validate/media", validate_media_handler)
             .post("/experimental/operator", find_elements_handler)
@@ -1263,11 +2225,32 @@ impl SCServer {
             .post("/audio/start", start_audio)
             .post("/audio/stop", stop_audio)
             .get("/semantic-search", semantic_search_handler)
+            .get("/search/semantic", hybrid_search_handler)
+            .post("/query", query_handler)
             .get("/pipes/build-status/:pipe_id", get_pipe_build_status)
             .get("/search/keyword", keyword_search_handler)
             .post("/v1/embeddings", create_embeddings)
+            .get("/v1/models", list_openai_models_handler)
+            .get("/llm/models", list_llm_models_handler)
+            .post("/llm/models/load", load_llm_model_handler)
             .post("/audio/device/start", start_audio_device)
             .post("/audio/device/stop", stop_audio_device)
+            .get("/audio/transcription-engine", get_transcription_engine)
+            .post("/audio/transcription-engine", set_transcription_engine)
+            .get("/audio/vad/config", get_vad_config)
+            .post("/audio/vad/config", set_vad_config)
+            .get("/audio/chunking", get_chunking_config)
+            .post("/audio/chunking", set_chunking_config)
+            .get("/audio/noise-suppression", get_noise_suppression)
+            .post("/audio/noise-suppression", set_noise_suppression)
+            .post(
+                "/audio/media-blocks/:id/transcribe",
+                transcribe_media_block_handler,
+            )
+            .get(
+                "/audio/transcriptions/:id/words",
+                get_transcript_words_handler,
+            )
             .route_yaml_spec("/openapi.yaml")
             .route_json_spec("/openapi.json")
             .freeze();
@@ -1277,10 +2260,56 @@ impl SCServer {
             .merge(server.into_router())
             // NOTE: websockerts and sse is not supported by openapi so we move it down here
             .route("/stream/frames", get(stream_frames_handler))
+            .route("/events/stream", get(events_stream_handler))
+            .route("/pipes/:pipe_id/logs/stream", get(pipe_logs_stream_handler))
+            .route("/mcp/sse", get(mcp_sse_handler))
+            .route("/mcp/messages", post(mcp_messages_handler))
+            .route("/graphql", get(graphiql_handler).post(graphql_handler))
+            .route("/metrics", get(metrics_handler))
+            .route("/sync/changes", get(sync_changes_handler))
+            .route("/v1/chat/completions", post(chat_completions_handler))
             .route("/ws/events", get(ws_events_handler))
             .route("/ws/health", get(ws_health_handler))
+            .route("/ws/transcriptions", get(ws_transcriptions_handler))
+            .route("/bus/publish", post(bus_publish_handler))
+            .route("/bus/subscribe", get(bus_subscribe_handler))
+            .route("/ingest", post(ingest_handler))
+            .route("/ingest/video", post(ingest_video_handler))
+            .route("/ingest/audio", post(ingest_audio_handler))
+            .route("/speakers/enroll", post(enroll_speaker_handler))
+            .route("/companion/uploads", post(start_companion_upload_handler))
+            .route(
+                "/companion/uploads/:id",
+                get(get_companion_upload_handler).put(put_companion_chunk_handler),
+            )
             .route("/frames/export", get(handle_video_export_ws))
             .with_state(app_state)
+    }
+
+    fn with_layers(&self, router: Router, require_auth: bool) -> Router {
+        let cors = CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any)
+            .expose_headers([
+                axum::http::header::CONTENT_TYPE,
+                axum::http::header::CACHE_CONTROL,
+            ]);
+
+        // inside cors so preflight requests don't need a token
+        let router = if require_auth {
+            router.layer(middleware::from_fn_with_state(
+                AuthState {
+                    db: self.db.clone(),
+                    app_token_hash: self.app_token.as_deref().map(hash_token),
+                },
+                require_token,
+            ))
+        } else {
+            router
+        };
+
+        router
             .layer(cors)
             .layer(TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::default()))
     }
@@ -1440,7 +2469,8 @@ async fn write_frames_to_video(
     video_file_path: &str,
     fps: f64,
 ) -> Result<(), anyhow::Error> {
-    let mut ffmpeg_child = start_ffmpeg_process(video_file_path, fps).await?;
+    let mut ffmpeg_child =
+        start_ffmpeg_process(video_file_path, fps, VideoEncoder::Libx265).await?;
     let mut ffmpeg_stdin = ffmpeg_child
         .stdin
         .take()
@@ -1479,6 +2509,8 @@ async fn add_transcription_to_db(
         None,
         None,
         None,
+        None,
+        None,
     )
     .await?;
 
@@ -1575,6 +2607,8 @@ pub(crate) async fn add_to_database(
             ));
         }
     }
+    // added content can be of any time
+    state.query_cache.clear();
 
     Ok(JsonResponse(AddContentResponse {
         success: true,
@@ -1678,6 +2712,10 @@ pub struct StreamFramesRequest {
 pub struct StreamTimeSeriesResponse {
     pub timestamp: DateTime<Utc>,
     pub devices: Vec<DeviceFrameResponse>,
+    /// Near-identical captures after this frame, which weren't stored
+    pub repeat_count: i64,
+    /// Until when the screen looked the same
+    pub last_repeat_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -1699,6 +2737,7 @@ pub struct DeviceMetadata {
 
 #[derive(Debug, Serialize)]
 pub struct AudioData {
+    pub transcription_id: i64,
     pub device_name: String,
     pub is_input: bool,
     pub transcription: String,
@@ -1711,6 +2750,8 @@ impl From<TimeSeriesFrame> for StreamTimeSeriesResponse {
     fn from(frame: TimeSeriesFrame) -> Self {
         StreamTimeSeriesResponse {
             timestamp: frame.timestamp,
+            repeat_count: frame.repeat_count,
+            last_repeat_at: frame.last_repeat_at,
             devices: frame
                 .frame_data
                 .into_iter()
@@ -1730,6 +2771,7 @@ impl From<TimeSeriesFrame> for StreamTimeSeriesResponse {
                             .into_iter()
                             .map(|audio| {
                                 AudioData {
+                                    transcription_id: audio.transcription_id,
                                     device_name: audio.device_name,
                                     is_input: audio.is_input,
                                     transcription: audio.transcription,
@@ -1917,6 +2959,118 @@ async fn mark_as_hallucination_handler(
     Ok(JsonResponse(json!({"success": true})))
 }
 
+#[derive(Deserialize)]
+struct EnrollSpeakerQuery {
+    /// Name of the voice profile, e.g. `me`
+    name: String,
+}
+
+/// Enrolls a voice sample sent as the body, e.g. 30 seconds of one person
+/// talking, into the voice profile with the name, creating it if needed.
+/// Transcripts are then matched to the profile first, more samples make it
+/// recognize the speaker better.
+async fn enroll_speaker_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EnrollSpeakerQuery>,
+    body: Body,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let fail = |status: StatusCode, error: String| {
+        (
+            status,
+            JsonResponse(json!({"error": error, "success": false})),
+        )
+    };
+    let name = query.name.trim();
+    if name.is_empty() {
+        return Err(fail(
+            StatusCode::BAD_REQUEST,
+            "name must not be empty".to_string(),
+        ));
+    }
+
+    let sample = tempfile::NamedTempFile::new().map_err(|e| {
+        fail(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to store the voice sample: {}", e),
+        )
+    })?;
+    let written = async {
+        let mut file = File::create(sample.path()).await?;
+        let mut stream = body.into_data_stream();
+        while let Some(chunk) = stream.next().await {
+            file.write_all(&chunk?).await?;
+        }
+        file.flush().await?;
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(e) = written {
+        return Err(fail(
+            StatusCode::BAD_REQUEST,
+            format!("failed to receive the voice sample: {}", e),
+        ));
+    }
+
+    let embeddings = state
+        .audio_manager
+        .voice_embeddings(sample.path())
+        .await
+        .map_err(|e| {
+            fail(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("failed to read the voice sample: {}", e),
+            )
+        })?;
+    let speaker = state
+        .db
+        .enroll_speaker(name, &embeddings)
+        .await
+        .map_err(|e| {
+            error!("failed to enroll speaker {}: {}", name, e);
+            fail(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to enroll speaker: {}", e),
+            )
+        })?;
+    info!(
+        "enrolled {} voice embeddings for speaker {} ({})",
+        embeddings.len(),
+        speaker.id,
+        name
+    );
+    Ok(JsonResponse(json!({
+        "data": {
+            "id": speaker.id,
+            "name": speaker.name,
+            "embeddings_added": embeddings.len()
+        },
+        "success": true
+    })))
+}
+
+/// Enrolled voice profiles, with how many transcripts were matched to each.
+#[oasgen]
+async fn list_voice_profiles_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.list_voice_profiles().await {
+        Ok(profiles) => Ok(JsonResponse(json!({
+            "data": profiles,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to list voice profiles: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to list voice profiles: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
 #[oasgen]
 async fn merge_speakers_handler(
     State(state): State<Arc<AppState>>,
@@ -2068,6 +3222,21 @@ struct EventsQuery {
     images: Option<bool>,
 }
 
+#[derive(Debug, Deserialize)]
+struct EventStreamQuery {
+    /// comma separated event types, all when not set
+    types: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptionStreamQuery {
+    /// only stream segments from devices whose name contains this
+    device: Option<String>,
+    /// skip partial segments from realtime engines
+    #[serde(default)]
+    final_only: bool,
+}
+
 #[derive(Debug, OaSchema, Deserialize)]
 struct SemanticSearchQuery {
     text: String,
@@ -2246,48 +3415,214 @@ async fn handle_socket(socket: WebSocket, query: Query<EventsQuery>) {
                         }
                     }
                 }
-                _ = tokio::time::sleep(Duration::from_secs(1)) => {
-                    let _ = sender.send(Message::Ping(vec![])).await;
+                _ = tokio::time::sleep(Duration::from_secs(1)) => {
+                    let _ = sender.send(Message::Ping(vec![])).await;
+                }
+            }
+        }
+    });
+
+    // Wait for either task to finish
+    tokio::select! {
+        _ = incoming => {}
+        _ = outgoing => {}
+    }
+
+    debug!("WebSocket connection closed");
+}
+
+async fn ws_transcriptions_handler(
+    ws: WebSocketUpgrade,
+    Query(query): Query<TranscriptionStreamQuery>,
+) -> Response {
+    ws.on_upgrade(|socket| handle_transcriptions_socket(socket, query))
+}
+
+async fn handle_transcriptions_socket(socket: WebSocket, query: TranscriptionStreamQuery) {
+    let (mut sender, mut receiver) = socket.split();
+
+    // only used to notice when the client goes away
+    let incoming = tokio::spawn(async move {
+        while let Some(Ok(msg)) = receiver.next().await {
+            if let Message::Close(_) = msg {
+                break;
+            }
+        }
+    });
+
+    let device_filter = query.device.map(|d| d.to_lowercase());
+    let outgoing = tokio::spawn(async move {
+        let mut stream = subscribe_to_event::<RealtimeTranscriptionEvent>("transcription");
+        let mut ping = tokio::time::interval(Duration::from_secs(30));
+        loop {
+            tokio::select! {
+                event = stream.next() => {
+                    let Some(event) = event else { break };
+                    let segment = event.data;
+                    if query.final_only && !segment.is_final {
+                        continue;
+                    }
+                    if device_filter
+                        .as_ref()
+                        .is_some_and(|d| !segment.device.to_lowercase().contains(d))
+                    {
+                        continue;
+                    }
+                    if let Err(e) = sender
+                        .send(Message::Text(serde_json::to_string(&segment).unwrap_or_default()))
+                        .await
+                    {
+                        debug!("failed to send transcription segment: {}", e);
+                        break;
+                    }
+                }
+                _ = ping.tick() => {
+                    if sender.send(Message::Ping(vec![])).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    tokio::select! {
+        _ = incoming => {}
+        _ = outgoing => {}
+    }
+
+    debug!("transcription websocket closed");
+}
+
+/// Events streamed at `/events/stream`.
+const STREAM_EVENT_TYPES: [&str; 6] = [
+    FRAME_INDEXED_EVENT,
+    TRANSCRIPT_INDEXED_EVENT,
+    APP_SWITCHED_EVENT,
+    HEALTH_CHANGED_EVENT,
+    SAVED_SEARCH_MATCHED_EVENT,
+    AUDIO_DEVICE_SWITCHED_EVENT,
+];
+
+// server-sent events of new captures, filtered by type
+async fn events_stream_handler(
+    Query(query): Query<EventStreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, (StatusCode, JsonResponse<Value>)>
+{
+    let types = match &query.types {
+        Some(types) => types
+            .split(',')
+            .map(|name| {
+                let name = name.trim();
+                STREAM_EVENT_TYPES
+                    .into_iter()
+                    .find(|event| *event == name)
+                    .ok_or_else(|| {
+                        (
+                            StatusCode::BAD_REQUEST,
+                            JsonResponse(json!({
+                                "error": format!("unknown event type: {}", name),
+                                "success": false
+                            })),
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => STREAM_EVENT_TYPES.to_vec(),
+    };
+
+    let (tx, rx) = mpsc::channel(100);
+    tokio::spawn(forward_stream_events(types, tx));
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (Ok(event), rx))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn forward_stream_events(types: Vec<&'static str>, tx: mpsc::Sender<SseEvent>) {
+    let mut events = subscribe_to_all_events();
+
+    loop {
+        let event = tokio::select! {
+            event = events.next() => match event {
+                Some(event) if types.iter().any(|name| *name == event.name) => {
+                    SseEvent::default().event(&event.name).json_data(&event.data)
+                }
+                Some(_) => continue,
+                None => break,
+            },
+            _ = tx.closed() => break,
+        };
+
+        match event {
+            Ok(event) => {
+                if tx.send(event).await.is_err() {
+                    break;
                 }
             }
+            Err(e) => error!("failed to encode streamed event: {}", e),
         }
-    });
-
-    // Wait for either task to finish
-    tokio::select! {
-        _ = incoming => {}
-        _ = outgoing => {}
     }
 
-    debug!("WebSocket connection closed");
+    debug!("event stream closed");
+}
+
+#[derive(Deserialize)]
+struct PipeLogStreamQuery {
+    #[serde(default)]
+    level: Option<String>,
 }
 
-async fn ws_health_handler(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> Response {
-    ws.on_upgrade(move |socket| handle_health_socket(socket, state))
+// server-sent events of the entries a pipe logs
+async fn pipe_logs_stream_handler(
+    State(state): State<Arc<AppState>>,
+    Path(pipe_id): Path<String>,
+    Query(query): Query<PipeLogStreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, (StatusCode, JsonResponse<Value>)>
+{
+    let level = pipe_logs_level(&state, &pipe_id, query.level.as_deref()).await?;
+
+    let (tx, rx) = mpsc::channel(100);
+    tokio::spawn(forward_pipe_logs(pipe_id, level, tx));
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (Ok(event), rx))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
 
-async fn handle_health_socket(mut socket: WebSocket, state: Arc<AppState>) {
-    let mut interval = tokio::time::interval(Duration::from_secs(5));
+async fn forward_pipe_logs(
+    pipe_id: String,
+    min_level: Option<PipeLogLevel>,
+    tx: mpsc::Sender<SseEvent>,
+) {
+    let mut logs = subscribe_to_pipe_logs();
 
     loop {
-        tokio::select! {
-        _ = interval.tick() => {
-            let health_response = health_check(State(state.clone())).await;
-            let health_status = serde_json::to_string(&health_response.0).unwrap_or_default();
-            if let Err(e) = socket.send(Message::Text(health_status)).await {
-                error!("Failed to send health status: {}", e);
-                break;
-            }
+        let entry = tokio::select! {
+            entry = logs.recv() => match entry {
+                Ok(entry) => entry,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("pipe log stream skipped {} entries", skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = tx.closed() => break,
+        };
+        if entry.pipe != pipe_id || min_level.is_some_and(|level| entry.level < level) {
+            continue;
         }
-            result = socket.recv() => {
-                if result.is_none() {
+
+        match SseEvent::default().event("log").json_data(&entry) {
+            Ok(event) => {
+                if tx.send(event).await.is_err() {
                     break;
                 }
             }
+            Err(e) => error!("failed to encode pipe log entry: {}", e),
         }
     }
 
-    debug!("WebSocket connection closed gracefully");
+    debug!("pipe log stream closed");
 }
 
 #[derive(Debug, Deserialize)]
@@ -2411,6 +3746,301 @@ async fn stop_audio(
     }
 }
 
+#[derive(OaSchema, Deserialize)]
+struct TranscriptionEngineRequest {
+    /// cli name of the engine, e.g. `whisper-large-v3-turbo-quantized`
+    engine: String,
+}
+
+#[oasgen]
+async fn get_transcription_engine(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let engine = state.audio_manager.transcription_engine().await;
+    Ok(JsonResponse(json!({
+        "engine": engine.to_string(),
+        "success": true
+    })))
+}
+
+#[oasgen]
+async fn set_transcription_engine(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<TranscriptionEngineRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let engine = AudioTranscriptionEngine::from_str(&payload.engine).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"success": false, "message": e.to_string()})),
+        )
+    })?;
+
+    if let Err(e) = state.audio_manager.set_transcription_engine(engine).await {
+        error!("failed to switch transcription engine: {}", e);
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({
+                "success": false,
+                "message": format!("failed to switch transcription engine: {}", e)
+            })),
+        ));
+    }
+
+    let engine = state.audio_manager.transcription_engine().await;
+    Ok(JsonResponse(json!({
+        "engine": engine.to_string(),
+        "success": true
+    })))
+}
+
+#[derive(OaSchema, Deserialize)]
+struct VadConfigRequest {
+    /// `silero` or `webrtc`
+    engine: Option<String>,
+    /// `low`, `medium` or `high`
+    sensitivity: Option<String>,
+    min_speech_duration_ms: Option<u64>,
+    hangover_ms: Option<u64>,
+}
+
+fn vad_config_json(config: &VadConfig) -> Value {
+    json!({
+        "engine": format!("{:?}", config.engine).to_lowercase(),
+        "sensitivity": format!("{:?}", config.sensitivity).to_lowercase(),
+        "min_speech_duration_ms": config.timing.min_speech_duration.as_millis() as u64,
+        "hangover_ms": config.timing.hangover.as_millis() as u64,
+        "success": true
+    })
+}
+
+#[oasgen]
+async fn get_vad_config(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let config = state.audio_manager.vad_config().await;
+    Ok(JsonResponse(vad_config_json(&config)))
+}
+
+#[oasgen]
+async fn set_vad_config(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<VadConfigRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let bad_request = |e: anyhow::Error| {
+        (
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"success": false, "message": e.to_string()})),
+        )
+    };
+
+    // fields left out keep their current value
+    let mut config = state.audio_manager.vad_config().await;
+    if let Some(engine) = payload.engine {
+        config.engine = VadEngineEnum::from_str(&engine).map_err(bad_request)?;
+    }
+    if let Some(sensitivity) = payload.sensitivity {
+        config.sensitivity = VadSensitivity::from_str(&sensitivity).map_err(bad_request)?;
+    }
+    if let Some(ms) = payload.min_speech_duration_ms {
+        config.timing.min_speech_duration = Duration::from_millis(ms);
+    }
+    if let Some(ms) = payload.hangover_ms {
+        config.timing.hangover = Duration::from_millis(ms);
+    }
+
+    if let Err(e) = state.audio_manager.set_vad_config(config).await {
+        error!("failed to update vad config: {}", e);
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({
+                "success": false,
+                "message": format!("failed to update vad config: {}", e)
+            })),
+        ));
+    }
+
+    let config = state.audio_manager.vad_config().await;
+    Ok(JsonResponse(vad_config_json(&config)))
+}
+
+#[derive(OaSchema, Deserialize)]
+struct ChunkingConfigRequest {
+    duration_secs: Option<u64>,
+    overlap_ms: Option<u64>,
+}
+
+fn chunking_config_json(config: &ChunkingConfig) -> Value {
+    json!({
+        "duration_secs": config.duration.as_secs(),
+        "overlap_ms": config.overlap.as_millis() as u64,
+        "success": true
+    })
+}
+
+#[oasgen]
+async fn get_chunking_config(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    Ok(JsonResponse(chunking_config_json(
+        &state.audio_manager.chunking_config(),
+    )))
+}
+
+#[oasgen]
+async fn set_chunking_config(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ChunkingConfigRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    // fields left out keep their current value
+    let mut config = state.audio_manager.chunking_config();
+    if let Some(secs) = payload.duration_secs {
+        config.duration = Duration::from_secs(secs);
+    }
+    if let Some(ms) = payload.overlap_ms {
+        config.overlap = Duration::from_millis(ms);
+    }
+
+    if let Err(e) = state.audio_manager.set_chunking_config(config).await {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"success": false, "message": e.to_string()})),
+        ));
+    }
+
+    Ok(JsonResponse(chunking_config_json(&config)))
+}
+
+#[derive(OaSchema, Deserialize)]
+struct NoiseSuppressionRequest {
+    device_name: String,
+    enabled: bool,
+}
+
+#[oasgen]
+async fn get_noise_suppression(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    Ok(JsonResponse(json!({
+        "data": noise_suppression_statuses(&state),
+        "success": true
+    })))
+}
+
+#[oasgen]
+async fn set_noise_suppression(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<NoiseSuppressionRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    // the device doesn't have to be recording, it's used once it starts
+    state
+        .audio_manager
+        .set_device_noise_suppression(&payload.device_name, payload.enabled)
+        .await;
+
+    Ok(JsonResponse(json!({
+        "data": noise_suppression_statuses(&state),
+        "success": true
+    })))
+}
+
+/// Transcribes the audio kept for a media block, when media detection took
+/// speech for music. The block is removed once its chunks are queued, their
+/// transcripts are stored like any other.
+#[oasgen]
+async fn transcribe_media_block_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let internal_error = |e: String| {
+        error!("failed to transcribe media block {}: {}", id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": e, "success": false})),
+        )
+    };
+    let not_found = |message: String| {
+        (
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": message, "success": false})),
+        )
+    };
+
+    let block = state
+        .db
+        .get_media_block(id)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?
+        .ok_or_else(|| not_found(format!("media block {} not found", id)))?;
+    let chunks = state
+        .db
+        .get_media_block_chunks(id)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    if chunks.is_empty() {
+        return Err(not_found(format!(
+            "no audio is kept for media block {}",
+            id
+        )));
+    }
+
+    let device = AudioDevice::new(
+        block.device_name,
+        if block.is_input {
+            DeviceType::Input
+        } else {
+            DeviceType::Output
+        },
+    );
+    for chunk in &chunks {
+        let media = readable_media(&chunk.file_path)
+            .await
+            .map_err(|e| internal_error(e.to_string()))?;
+        state
+            .audio_manager
+            .transcribe_kept_file(media.path(), device.clone(), chunk.timestamp)
+            .await
+            .map_err(|e| internal_error(e.to_string()))?;
+    }
+
+    // the queued audio is saved again with its transcripts
+    let chunks = state
+        .db
+        .delete_media_block(id)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    remove_media_files(&chunks, false).await;
+
+    Ok(JsonResponse(json!({
+        "chunks": chunks.len(),
+        "success": true
+    })))
+}
+
+/// Timings and confidence of the words of a transcription, in seconds from
+/// the start of its audio file. Empty for engines that don't give them.
+#[oasgen]
+async fn get_transcript_words_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.get_transcript_words(id).await {
+        Ok(words) => Ok(JsonResponse(json!({
+            "data": words,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to get words of transcription {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to get transcript words: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
 pub async fn handle_video_export_ws(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
@@ -2876,6 +4506,47 @@ pub async fn get_frame_data(
     }
 }
 
+/// Accessibility tree of the focused window stored with a frame, and the
+/// element that had the keyboard focus.
+#[oasgen]
+pub async fn get_frame_accessibility_handler(
+    State(state): State<Arc<AppState>>,
+    Path(frame_id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.get_frame_accessibility_tree(frame_id).await {
+        Ok(Some(tree)) => Ok(JsonResponse(json!({
+            "data": {
+                "frame_id": frame_id,
+                "focused_element": tree.focused_element(),
+                "tree": tree,
+            },
+            "success": true
+        }))),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({
+                "error": "no accessibility tree stored for this frame",
+                "frame_id": frame_id,
+                "success": false
+            })),
+        )),
+        Err(e) => {
+            error!(
+                "failed to read accessibility tree of frame {}: {}",
+                frame_id, e
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to read accessibility tree: {}", e),
+                    "frame_id": frame_id,
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
 async fn serve_file(path: &str) -> Result<Response, (StatusCode, JsonResponse<Value>)> {
     match File::open(path).await {
         Ok(file) => {
@@ -2902,6 +4573,85 @@ async fn serve_file(path: &str) -> Result<Response, (StatusCode, JsonResponse<Va
     }
 }
 
+#[derive(OaSchema, Deserialize)]
+pub struct MediaFileQuery {
+    path: String,
+}
+
+/// Canonical form of a recording path. Offloaded recordings are no longer on
+/// disk, their directory is resolved instead.
+async fn canonical_media_path(path: &std::path::Path) -> std::io::Result<PathBuf> {
+    match tokio::fs::canonicalize(path).await {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && object_store().is_some() => {
+            let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+                return Err(e);
+            };
+            Ok(tokio::fs::canonicalize(dir).await?.join(name))
+        }
+        result => result,
+    }
+}
+
+/// Serves a video or audio recording, decrypted if it is encrypted at rest and
+/// fetched back if it was offloaded. Only files inside the screenpipe
+/// directory are served.
+#[oasgen]
+pub async fn get_media_file_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<MediaFileQuery>,
+) -> Result<Response<Body>, (StatusCode, JsonResponse<Value>)> {
+    let inside_screenpipe_dir = match (
+        canonical_media_path(std::path::Path::new(&query.path)).await,
+        tokio::fs::canonicalize(&state.screenpipe_dir).await,
+    ) {
+        (Ok(path), Ok(dir)) => path.starts_with(dir),
+        _ => false,
+    };
+    if !inside_screenpipe_dir {
+        return Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": "media file not found", "success": false})),
+        ));
+    }
+
+    let data = async {
+        let media = readable_media(&query.path).await?;
+        Ok::<_, anyhow::Error>(tokio::fs::read(media.path()).await?)
+    }
+    .await
+    .map_err(|e| {
+        error!("failed to read media file {}: {}", query.path, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(
+                json!({"error": format!("failed to read media file: {}", e), "success": false}),
+            ),
+        )
+    })?;
+
+    let content_type = match std::path::Path::new(&query.path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("mp4") => "video/mp4",
+        Some("mp3") => "audio/mpeg",
+        Some("wav") => "audio/wav",
+        _ => "application/octet-stream",
+    };
+
+    Response::builder()
+        .header("content-type", content_type)
+        .body(Body::from(data))
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to create response: {}", e), "success": false}),
+                ),
+            )
+        })
+}
+
 // Add these new functions before stream_frames_handler
 async fn fetch_and_process_frames(
     db: Arc<DatabaseManager>,
@@ -2932,6 +4682,8 @@ async fn fetch_and_process_frames(
 fn create_time_series_frame(chunk: FrameData) -> TimeSeriesFrame {
     TimeSeriesFrame {
         timestamp: chunk.timestamp,
+        repeat_count: chunk.repeat_count,
+        last_repeat_at: chunk.last_repeat_at,
         frame_data: chunk
             .ocr_entries
             .into_iter()
@@ -2955,6 +4707,7 @@ fn create_time_series_frame(chunk: FrameData) -> TimeSeriesFrame {
                     .audio_entries
                     .iter()
                     .map(|a| AudioEntry {
+                        transcription_id: a.transcription_id,
                         transcription: a.transcription.clone(),
                         device_name: a.device_name.clone(),
                         is_input: a.is_input,
@@ -0,0 +1,109 @@
+//! Coordinated shutdown. Recording stops first, then the frames and audio
+//! already captured are written, the video chunks finished and the queued
+//! database writes committed before the connections close, so quitting
+//! doesn't lose what was recorded or leave a transaction half done.
+//! Started by ctrl+c, SIGTERM, or `POST /admin/shutdown`, which the desktop
+//! app uses as it works the same on every platform.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{extract::State, http::StatusCode, response::Json as JsonResponse};
+use oasgen::oasgen;
+use screenpipe_audio::audio_manager::AudioManager;
+use screenpipe_db::DatabaseManager;
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::server::AppState;
+
+/// How long the database gets to commit and close once recording stopped.
+const DATABASE_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Waits for the recording, told to stop by the shutdown signal, and the
+/// audio to write what they captured, for `timeout` at most, then closes the
+/// database.
+pub async fn drain_pipelines(
+    recording: JoinHandle<()>,
+    audio_manager: &AudioManager,
+    db: &DatabaseManager,
+    timeout: Duration,
+) {
+    info!(
+        "stopping recording, waiting up to {}s for what was captured to be written",
+        timeout.as_secs()
+    );
+    let vision = async {
+        if !recording.is_finished() {
+            let _ = recording.await;
+        }
+    };
+    let audio = async {
+        if let Err(e) = audio_manager.drain().await {
+            error!("failed to stop audio recording: {}", e);
+        }
+    };
+    if tokio::time::timeout(timeout, async { tokio::join!(vision, audio) })
+        .await
+        .is_err()
+    {
+        warn!(
+            "recording didn't finish writing within {}s, the frames and audio left are dropped",
+            timeout.as_secs()
+        );
+    }
+
+    if tokio::time::timeout(DATABASE_CLOSE_TIMEOUT, db.close())
+        .await
+        .is_err()
+    {
+        warn!(
+            "database still in use after {}s, closing it anyway",
+            DATABASE_CLOSE_TIMEOUT.as_secs()
+        );
+    }
+}
+
+/// Resolves on ctrl+c, or when the process is asked to terminate on unix.
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => info!("received ctrl+c, initiating shutdown"),
+                    _ = terminate.recv() => info!("received sigterm, initiating shutdown"),
+                }
+                return;
+            }
+            Err(e) => warn!("failed to listen for sigterm: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+    info!("received ctrl+c, initiating shutdown");
+}
+
+/// Stops screenpipe once what was captured is written, like ctrl+c. Returns
+/// right away, the process exits when done.
+#[oasgen]
+pub(crate) async fn shutdown_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let Some(shutdown) = &state.shutdown else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({
+                "error": "shutdown isn't available",
+                "success": false
+            })),
+        ));
+    };
+    info!("shutdown requested over the api");
+    // fails only when already shutting down
+    let _ = shutdown.send(());
+    Ok(JsonResponse(json!({
+        "success": true
+    })))
+}
@@ -0,0 +1,615 @@
+//! Snapshots of a whole instance, to move it to another machine with
+//! `screenpipe migrate export` and `screenpipe migrate import`. A snapshot is
+//! a single tar archive holding a manifest, a copy of the database, the
+//! partitions and the recordings, optionally only those of a time range. The
+//! manifest comes first and lists every other file with its checksum.
+//!
+//! Both ends pick up where they stopped when run again: an interrupted
+//! export keeps the files already in its partial archive, an interrupted
+//! import skips the files already in place. Every imported file is checked
+//! against its checksum before the database is swapped in, and the paths in
+//! the database are pointed to the new data directory.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use screenpipe_db::{check_database_integrity, DatabaseManager};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, warn};
+use walkdir::WalkDir;
+
+use crate::backup::{relative_path, sha256_file, BackupFile};
+
+pub const SNAPSHOT_MANIFEST: &str = "manifest.json";
+const DB_FILE: &str = "db.sqlite";
+/// Where the database of a snapshot is extracted to before it's swapped in.
+const DB_IMPORT_FILE: &str = "db.sqlite.importing";
+/// Directories of the recordings and the partitions, in the data directory
+/// and in snapshots.
+const SNAPSHOT_DIRS: [&str; 2] = ["data", "partitions"];
+/// Suffix of archives and files being written.
+const PARTIAL_SUFFIX: &str = ".partial";
+/// Suffix of the copy of the database an export keeps until it's done.
+const DB_COPY_SUFFIX: &str = ".db";
+const TAR_BLOCK_SIZE: u64 = 512;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub created_at: DateTime<Utc>,
+    pub version: String,
+    /// The database is encrypted with the key of the exported instance
+    pub encrypted: bool,
+    /// Data directory of the exported instance, the paths in its database
+    /// start with it
+    pub data_dir: PathBuf,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub files: Vec<BackupFile>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotOptions {
+    /// Only the recordings from then on
+    pub start: Option<DateTime<Utc>>,
+    /// Only the recordings until then
+    pub end: Option<DateTime<Utc>>,
+    /// Hex encoded key of an encrypted database
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotExportReport {
+    pub path: PathBuf,
+    pub manifest: SnapshotManifest,
+    /// Files written to the archive by this run, the manifest left out
+    pub written_files: usize,
+    pub written_bytes: u64,
+    /// Files kept from an interrupted run
+    pub resumed_files: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotImportReport {
+    pub manifest: SnapshotManifest,
+    /// Files extracted by this run
+    pub imported_files: usize,
+    pub imported_bytes: u64,
+    /// Files already in place from an interrupted run
+    pub resumed_files: usize,
+    /// Where the database that was replaced was moved
+    pub previous_database: Option<PathBuf>,
+    /// Paths in the database pointed to the new data directory
+    pub relocated_paths: u64,
+}
+
+/// Hashes what's read through it.
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        HashingReader {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn sha256(self) -> String {
+        format!("{:x}", self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn entry_path<R: Read>(entry: &tar::Entry<R>) -> String {
+    String::from_utf8_lossy(&entry.path_bytes()).into_owned()
+}
+
+fn append_entry<W: Write, R: Read>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    size: u64,
+    data: R,
+) -> std::io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    builder.append_data(&mut header, path, data.take(size))
+}
+
+/// Copies the database to `db_copy`, keeping only the time range of the
+/// options, and lists it with the files of the data directory that go along.
+async fn plan_snapshot(
+    db: &DatabaseManager,
+    data_dir: &Path,
+    db_copy: &Path,
+    options: &SnapshotOptions,
+) -> Result<SnapshotManifest> {
+    let key = options.key.as_deref();
+    db.backup_to(db_copy, key).await?;
+
+    let ranged = options.start.is_some() || options.end.is_some();
+    let paths: Vec<PathBuf> = if ranged {
+        let copy_path = db_copy.to_string_lossy();
+        let copy = match key {
+            Some(key) => DatabaseManager::new_encrypted(&copy_path, key).await?,
+            None => DatabaseManager::new(&copy_path).await?,
+        };
+        let files = async {
+            copy.delete_outside_time_range(options.start, options.end)
+                .await?;
+            sqlx::query("VACUUM").execute(&copy.pool).await?;
+            let mut files: Vec<PathBuf> = copy
+                .media_chunk_paths()
+                .await?
+                .into_iter()
+                .map(PathBuf::from)
+                .collect();
+            files.extend(
+                copy.list_partitions()
+                    .await?
+                    .into_iter()
+                    .map(|partition| PathBuf::from(partition.file_path)),
+            );
+            Ok::<_, sqlx::Error>(files)
+        }
+        .await;
+        copy.pool.close().await;
+        files?
+    } else {
+        SNAPSHOT_DIRS
+            .iter()
+            .flat_map(|dir| WalkDir::new(data_dir.join(dir)))
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .collect()
+    };
+    check_database_integrity(db_copy, key).await?;
+
+    let data_dir = data_dir.to_path_buf();
+    let db_copy = db_copy.to_path_buf();
+    let options = options.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut files = vec![BackupFile {
+            path: DB_FILE.to_string(),
+            size: fs::metadata(&db_copy)?.len(),
+            sha256: sha256_file(&db_copy)?,
+        }];
+        let mut outside = 0;
+        let mut relative_paths = BTreeSet::new();
+        for path in &paths {
+            match relative_path(path, &data_dir) {
+                Some(relative) => {
+                    relative_paths.insert((relative, path));
+                }
+                None => outside += 1,
+            }
+        }
+        if outside > 0 {
+            warn!(
+                "{} files are outside {} and left out of the snapshot",
+                outside,
+                data_dir.display()
+            );
+        }
+        for (relative, path) in relative_paths {
+            // e.g. deleted by retention, its chunk stays in the database
+            let Ok(metadata) = fs::metadata(path) else {
+                debug!("{} is gone, left out of the snapshot", path.display());
+                continue;
+            };
+            files.push(BackupFile {
+                path: relative,
+                size: metadata.len(),
+                sha256: sha256_file(path)?,
+            });
+        }
+
+        Ok(SnapshotManifest {
+            created_at: Utc::now(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            encrypted: options.key.is_some(),
+            data_dir,
+            start: options.start,
+            end: options.end,
+            files,
+        })
+    })
+    .await?
+}
+
+/// Manifest, files and length of what a previous run wrote completely to the
+/// partial archive. `None` when there's nothing to pick up.
+fn read_partial_archive(
+    partial: &Path,
+    db_copy: &Path,
+) -> Result<Option<(SnapshotManifest, HashSet<String>, u64)>> {
+    let file = match fs::File::open(partial) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let len = file.metadata()?.len();
+    let mut archive = tar::Archive::new(file);
+    let mut manifest = None;
+    let mut written = HashSet::new();
+    let mut end = 0;
+    for entry in archive.entries()? {
+        // the rest was cut off
+        let Ok(mut entry) = entry else {
+            break;
+        };
+        let entry_end =
+            entry.raw_file_position() + entry.size().div_ceil(TAR_BLOCK_SIZE) * TAR_BLOCK_SIZE;
+        if entry_end > len {
+            break;
+        }
+        let path = entry_path(&entry);
+        if manifest.is_none() {
+            if path != SNAPSHOT_MANIFEST {
+                return Ok(None);
+            }
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            manifest = Some(serde_json::from_slice::<SnapshotManifest>(&data)?);
+        } else {
+            written.insert(path);
+        }
+        end = entry_end;
+    }
+
+    let Some(manifest) = manifest else {
+        return Ok(None);
+    };
+    if !written.contains(DB_FILE) && !db_copy.exists() {
+        return Ok(None);
+    }
+    Ok(Some((manifest, written, end)))
+}
+
+/// Appends the file at `source` to the archive, `false` if it's not the one
+/// the manifest lists anymore.
+fn append_unchanged<W: Write>(
+    builder: &mut tar::Builder<W>,
+    file: &BackupFile,
+    source: &Path,
+) -> Result<bool> {
+    let reader = match fs::File::open(source) {
+        Ok(reader) => reader,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    if reader.metadata()?.len() != file.size {
+        return Ok(false);
+    }
+    let mut reader = HashingReader::new(reader);
+    append_entry(builder, &file.path, file.size, &mut reader)?;
+    Ok(reader.sha256() == file.sha256)
+}
+
+/// Appends the files of the manifest missing from the partial archive, cut
+/// to the `end` of what's complete, and finishes it.
+fn write_archive(
+    partial: &Path,
+    db_copy: &Path,
+    data_dir: &Path,
+    written: &HashSet<String>,
+    end: u64,
+    report: &mut SnapshotExportReport,
+) -> Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(partial)?;
+    file.set_len(end)?;
+    file.seek(SeekFrom::End(0))?;
+    let mut builder = tar::Builder::new(file);
+    if end == 0 {
+        let data = serde_json::to_vec_pretty(&report.manifest)?;
+        append_entry(
+            &mut builder,
+            SNAPSHOT_MANIFEST,
+            data.len() as u64,
+            &data[..],
+        )?;
+    }
+
+    let (mut written_files, mut written_bytes, mut resumed_files) = (0, 0, 0);
+    for file in &report.manifest.files {
+        if written.contains(&file.path) {
+            resumed_files += 1;
+            continue;
+        }
+        let source = if file.path == DB_FILE {
+            db_copy.to_path_buf()
+        } else {
+            data_dir.join(&file.path)
+        };
+        if !append_unchanged(&mut builder, file, &source)? {
+            drop(builder);
+            // what's written can't be picked up, the next run starts over
+            let _ = fs::remove_file(partial);
+            let _ = fs::remove_file(db_copy);
+            anyhow::bail!(
+                "{} changed since the export started, export again",
+                file.path
+            );
+        }
+        written_files += 1;
+        written_bytes += file.size;
+    }
+    builder.into_inner()?.sync_all()?;
+
+    report.written_files = written_files;
+    report.written_bytes = written_bytes;
+    report.resumed_files = resumed_files;
+    Ok(())
+}
+
+/// Writes a snapshot of the instance to `archive`. An interrupted export to
+/// the same archive is picked up where it stopped, with the database and
+/// the files it planned to export. A file that changed since starts the
+/// export over on the next run.
+pub async fn export_snapshot(
+    db: &DatabaseManager,
+    data_dir: &Path,
+    archive: &Path,
+    options: &SnapshotOptions,
+) -> Result<SnapshotExportReport> {
+    if archive.exists() {
+        anyhow::bail!("{} already exists", archive.display());
+    }
+    if let Some(dir) = archive.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = with_suffix(archive, PARTIAL_SUFFIX);
+    let db_copy = with_suffix(archive, DB_COPY_SUFFIX);
+
+    let resumed = {
+        let (partial, db_copy) = (partial.clone(), db_copy.clone());
+        tokio::task::spawn_blocking(move || read_partial_archive(&partial, &db_copy)).await?
+    };
+    let (manifest, written, end) = match resumed {
+        Ok(Some(resumed)) => {
+            info!(
+                "resuming the export to {}, {} files already written",
+                archive.display(),
+                resumed.1.len()
+            );
+            resumed
+        }
+        result => {
+            if let Err(e) = result {
+                warn!("starting the export over: {}", e);
+            }
+            let manifest = plan_snapshot(db, data_dir, &db_copy, options).await?;
+            (manifest, HashSet::new(), 0)
+        }
+    };
+
+    let mut report = SnapshotExportReport {
+        path: archive.to_path_buf(),
+        manifest,
+        written_files: 0,
+        written_bytes: 0,
+        resumed_files: 0,
+    };
+    let data_dir = data_dir.to_path_buf();
+    let (partial_path, db_copy_path) = (partial.clone(), db_copy.clone());
+    let report = tokio::task::spawn_blocking(move || {
+        write_archive(
+            &partial_path,
+            &db_copy_path,
+            &data_dir,
+            &written,
+            end,
+            &mut report,
+        )
+        .map(|_| report)
+    })
+    .await??;
+
+    fs::rename(&partial, archive)?;
+    if let Err(e) = fs::remove_file(&db_copy) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("failed to remove {}: {}", db_copy.display(), e);
+        }
+    }
+    Ok(report)
+}
+
+/// Where a file of a snapshot goes in `data_dir`. Paths leaving it are
+/// refused.
+fn import_target(data_dir: &Path, path: &str) -> Result<PathBuf> {
+    if path == DB_FILE {
+        return Ok(data_dir.join(DB_IMPORT_FILE));
+    }
+    let relative = Path::new(path);
+    let inside = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if path.is_empty() || !inside {
+        anyhow::bail!("{} isn't a path in the data directory", path);
+    }
+    Ok(data_dir.join(relative))
+}
+
+/// Extracts the files of the archive missing from `data_dir`, the database
+/// next to the one in place.
+fn extract_archive(archive: &Path, data_dir: &Path, has_key: bool) -> Result<SnapshotImportReport> {
+    let file =
+        fs::File::open(archive).with_context(|| format!("failed to open {}", archive.display()))?;
+    let mut archive = tar::Archive::new(file);
+    let mut entries = archive.entries()?;
+    let mut first = entries
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("the archive is empty"))??;
+    if entry_path(&first) != SNAPSHOT_MANIFEST {
+        anyhow::bail!("the archive isn't a screenpipe snapshot");
+    }
+    let mut data = Vec::new();
+    first.read_to_end(&mut data)?;
+    let manifest: SnapshotManifest = serde_json::from_slice(&data)?;
+    if manifest.encrypted && !has_key {
+        anyhow::bail!("the snapshot is encrypted, run with --encrypt-data");
+    }
+
+    let mut report = SnapshotImportReport {
+        manifest: manifest.clone(),
+        imported_files: 0,
+        imported_bytes: 0,
+        resumed_files: 0,
+        previous_database: None,
+        relocated_paths: 0,
+    };
+    let files: HashMap<&str, &BackupFile> = manifest
+        .files
+        .iter()
+        .map(|file| (file.path.as_str(), file))
+        .collect();
+    let mut extracted = HashSet::new();
+    fs::create_dir_all(data_dir)?;
+    for entry in entries {
+        let mut entry = entry?;
+        let path = entry_path(&entry);
+        let file = files
+            .get(path.as_str())
+            .ok_or_else(|| anyhow::anyhow!("{} isn't in the manifest", path))?;
+        let target = import_target(data_dir, &file.path)?;
+        extracted.insert(file.path.clone());
+
+        // checked against its checksum once everything is extracted
+        if fs::metadata(&target).is_ok_and(|metadata| metadata.len() == file.size) {
+            report.resumed_files += 1;
+            continue;
+        }
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let partial = with_suffix(&target, PARTIAL_SUFFIX);
+        let mut writer = fs::File::create(&partial)?;
+        let mut reader = HashingReader::new(&mut entry);
+        std::io::copy(&mut reader, &mut writer)?;
+        writer.sync_all()?;
+        if reader.sha256() != file.sha256 {
+            let _ = fs::remove_file(&partial);
+            anyhow::bail!("{} doesn't match its checksum", file.path);
+        }
+        fs::rename(&partial, &target)?;
+        report.imported_files += 1;
+        report.imported_bytes += file.size;
+    }
+
+    let missing = manifest
+        .files
+        .iter()
+        .filter(|file| !extracted.contains(&file.path))
+        .count();
+    if missing > 0 {
+        anyhow::bail!("the archive is incomplete, {} files are missing", missing);
+    }
+    Ok(report)
+}
+
+/// Checks every file of the snapshot in `data_dir` against its checksum and
+/// the soundness of its database.
+async fn verify_imported(
+    data_dir: &Path,
+    manifest: &SnapshotManifest,
+    key: Option<&str>,
+) -> Result<()> {
+    let files = manifest.files.clone();
+    let dir = data_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        for file in files {
+            let path = import_target(&dir, &file.path)?;
+            if sha256_file(&path)? != file.sha256 {
+                anyhow::bail!(
+                    "{} doesn't match its checksum, delete it and import again",
+                    path.display()
+                );
+            }
+        }
+        Ok(())
+    })
+    .await??;
+    check_database_integrity(
+        &data_dir.join(DB_IMPORT_FILE),
+        key.filter(|_| manifest.encrypted),
+    )
+    .await
+}
+
+/// Imports the snapshot at `archive` into `data_dir`: extracts what's
+/// missing, verifies it, replaces the database, keeping the one in place
+/// next to it, and points its paths to `data_dir`. An interrupted import is
+/// picked up where it stopped. Screenpipe must not be running.
+pub async fn import_snapshot(
+    archive: &Path,
+    data_dir: &Path,
+    key: Option<&str>,
+) -> Result<SnapshotImportReport> {
+    let (archive, dir) = (archive.to_path_buf(), data_dir.to_path_buf());
+    let has_key = key.is_some();
+    let mut report =
+        tokio::task::spawn_blocking(move || extract_archive(&archive, &dir, has_key)).await??;
+    verify_imported(data_dir, &report.manifest, key).await?;
+
+    let db_path = data_dir.join(DB_FILE);
+    if db_path.exists() {
+        let previous = data_dir.join(format!(
+            "{}.before-import-{}",
+            DB_FILE,
+            Utc::now().format("%Y-%m-%dT%H-%M-%SZ")
+        ));
+        fs::rename(&db_path, &previous)?;
+        report.previous_database = Some(previous);
+    }
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(data_dir.join(format!("{}{}", DB_FILE, suffix)));
+    }
+    fs::rename(data_dir.join(DB_IMPORT_FILE), &db_path)?;
+
+    if report.manifest.data_dir != data_dir {
+        let path = db_path.to_string_lossy();
+        let db = match key.filter(|_| report.manifest.encrypted) {
+            Some(key) => DatabaseManager::new_encrypted(&path, key).await?,
+            None => DatabaseManager::new(&path).await?,
+        };
+        let relocated = db
+            .relocate_data_dir(&report.manifest.data_dir, data_dir)
+            .await;
+        db.pool.close().await;
+        report.relocated_paths = relocated?;
+    }
+    info!(
+        "imported {} files ({} bytes) into {}, {} were already there",
+        report.imported_files,
+        report.imported_bytes,
+        data_dir.display(),
+        report.resumed_files
+    );
+    Ok(report)
+}
@@ -0,0 +1,223 @@
+//! Disk space budget for the screenpipe directory. When usage gets close to
+//! the cap, the oldest video and audio chunks are evicted until it is well
+//! below it again, and a `storage_eviction` event tells the app about it.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use screenpipe_db::{DatabaseManager, MediaChunk, MediaType};
+use screenpipe_events::send_event;
+use serde::Serialize;
+use sysinfo::{DiskExt, System, SystemExt};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+use walkdir::WalkDir;
+
+use crate::retention::remove_media_files;
+
+/// How often disk usage is checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Eviction starts once usage passes this share of the budget, so the cap
+/// is not exceeded between two checks.
+const EVICTION_THRESHOLD: f64 = 0.95;
+/// Eviction frees space until usage is below this share of the budget.
+const EVICTION_TARGET: f64 = 0.85;
+/// Chunks fetched per media type and eviction round.
+const EVICTION_BATCH: u32 = 20;
+/// Chunks recorded to more recently may still be written and are never
+/// evicted.
+const MIN_CHUNK_AGE: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy)]
+pub struct StorageBudget {
+    pub max_bytes: u64,
+    /// Keep the text extracted from evicted recordings searchable
+    pub keep_text: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EvictedChunk {
+    pub media_type: MediaType,
+    pub file_path: String,
+    pub timestamp: DateTime<Utc>,
+    pub bytes: u64,
+}
+
+/// Sent as `storage_eviction` after recordings were evicted.
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageEvictionEvent {
+    pub evicted: Vec<EvictedChunk>,
+    pub freed_bytes: u64,
+    pub usage_bytes: u64,
+    pub max_bytes: u64,
+    pub kept_text: bool,
+}
+
+/// Total size of the files under `path`.
+pub fn directory_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Space left on the disk `path` is on, `None` when no mounted disk holds it.
+pub fn available_space(path: &Path) -> Option<u64> {
+    let mut sys = System::new();
+    sys.refresh_disks_list();
+    // the deepest mount point holding the path, not `/` for every path
+    sys.disks()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().components().count())
+        .map(|disk| disk.available_space())
+}
+
+/// Merges video and audio chunks, each sorted oldest first, into one list
+/// sorted oldest first.
+pub fn oldest_first(
+    video: Vec<MediaChunk>,
+    audio: Vec<MediaChunk>,
+) -> Vec<(MediaType, MediaChunk)> {
+    let mut chunks: Vec<(MediaType, MediaChunk)> = video
+        .into_iter()
+        .map(|chunk| (MediaType::Video, chunk))
+        .chain(audio.into_iter().map(|chunk| (MediaType::Audio, chunk)))
+        .collect();
+    chunks.sort_by_key(|(_, chunk)| chunk.timestamp);
+    chunks
+}
+
+pub struct StorageManager {
+    db: Arc<DatabaseManager>,
+    dir: PathBuf,
+    budget: StorageBudget,
+}
+
+impl StorageManager {
+    pub fn new(db: Arc<DatabaseManager>, dir: PathBuf, budget: StorageBudget) -> Self {
+        Self { db, dir, budget }
+    }
+
+    async fn usage(&self) -> Result<u64> {
+        let dir = self.dir.clone();
+        Ok(tokio::task::spawn_blocking(move || directory_size(&dir)).await?)
+    }
+
+    /// Evicts the oldest recordings if usage is close to the budget. Returns
+    /// what was evicted, if anything.
+    pub async fn enforce(&self) -> Result<Option<StorageEvictionEvent>> {
+        let max_bytes = self.budget.max_bytes;
+        let mut usage = self.usage().await?;
+        if (usage as f64) < max_bytes as f64 * EVICTION_THRESHOLD {
+            return Ok(None);
+        }
+        let target = (max_bytes as f64 * EVICTION_TARGET) as u64;
+
+        let mut evicted = Vec::new();
+        let mut freed_bytes = 0;
+        while usage > target {
+            let before = Utc::now() - chrono::Duration::from_std(MIN_CHUNK_AGE)?;
+            let video = self
+                .db
+                .get_local_media_chunks_before(MediaType::Video, before, EVICTION_BATCH)
+                .await?;
+            let audio = self
+                .db
+                .get_local_media_chunks_before(MediaType::Audio, before, EVICTION_BATCH)
+                .await?;
+            let candidates = oldest_first(video, audio);
+            if candidates.is_empty() {
+                warn!(
+                    "storage usage {} bytes is over budget but there are no recordings left to evict",
+                    usage
+                );
+                break;
+            }
+
+            let mut round: Vec<(MediaType, MediaChunk)> = Vec::new();
+            for (media_type, chunk) in candidates {
+                if usage <= target {
+                    break;
+                }
+                let (_, bytes) = remove_media_files(std::slice::from_ref(&chunk), false).await;
+                usage = usage.saturating_sub(bytes);
+                freed_bytes += bytes;
+                evicted.push(EvictedChunk {
+                    media_type,
+                    file_path: chunk.file_path.clone(),
+                    timestamp: chunk.timestamp,
+                    bytes,
+                });
+                round.push((media_type, chunk));
+            }
+
+            for media_type in [MediaType::Video, MediaType::Audio] {
+                let ids: Vec<i64> = round
+                    .iter()
+                    .filter(|(t, _)| *t == media_type)
+                    .map(|(_, chunk)| chunk.id)
+                    .collect();
+                if ids.is_empty() {
+                    continue;
+                }
+                if self.budget.keep_text {
+                    self.db.mark_media_chunks_deleted(media_type, &ids).await?;
+                } else {
+                    self.db.delete_media_chunks(media_type, &ids).await?;
+                }
+            }
+        }
+
+        if evicted.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(StorageEvictionEvent {
+            evicted,
+            freed_bytes,
+            usage_bytes: usage,
+            max_bytes,
+            kept_text: self.budget.keep_text,
+        }))
+    }
+
+    /// Checks usage every minute until a shutdown is signalled.
+    pub async fn run(self, mut shutdown_rx: broadcast::Receiver<()>) {
+        info!(
+            "starting storage manager with a budget of {} bytes for {}",
+            self.budget.max_bytes,
+            self.dir.display()
+        );
+
+        loop {
+            match self.enforce().await {
+                Ok(Some(event)) => {
+                    info!(
+                        "evicted {} recordings, freed {} bytes",
+                        event.evicted.len(),
+                        event.freed_bytes
+                    );
+                    if let Err(e) = send_event("storage_eviction", event) {
+                        error!("failed to send storage eviction event: {}", e);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => error!("storage eviction failed: {}", e),
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+                _ = shutdown_rx.recv() => {
+                    info!("received shutdown signal, stopping storage manager");
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! Summaries of each local day, written at night, and of each week, written
+//! on mondays, by a local LLM, see [`screenpipe_core::summarization`]. A day
+//! is summarized from its daily note, a week from the summaries of its days.
+//! They are served at `/summaries`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::{
+    extract::{Json, Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::{DateTime, Datelike, Local, NaiveDate, Timelike, Utc, Weekday};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_core::summarization::{Summarizer, SummaryInput};
+use screenpipe_db::{DatabaseManager, Summary};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+use crate::export::{export_daily_notes, local_day_start, ExportFilter};
+use crate::server::{AppState, PaginationQuery};
+
+/// How often missing summaries are looked for.
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// Days before yesterday summarized when they were missed, e.g. because
+/// screenpipe wasn't running at night.
+const CATCH_UP_DAYS: i64 = 7;
+
+#[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryPeriod {
+    Day,
+    Week,
+}
+
+impl SummaryPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SummaryPeriod::Day => "day",
+            SummaryPeriod::Week => "week",
+        }
+    }
+
+    /// First day of the period `date` is in, weeks start on monday.
+    pub fn first_day(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            SummaryPeriod::Day => date,
+            SummaryPeriod::Week => {
+                date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64)
+            }
+        }
+    }
+
+    /// Local midnights starting and ending the period `date` is in.
+    pub fn bounds(&self, date: NaiveDate) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+        let first_day = self.first_day(date);
+        let days = match self {
+            SummaryPeriod::Day => 1,
+            SummaryPeriod::Week => 7,
+        };
+        Ok((
+            local_day_start(first_day)?,
+            local_day_start(first_day + chrono::Duration::days(days))?,
+        ))
+    }
+}
+
+fn lines(markdown: &str) -> Vec<String> {
+    markdown
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+async fn day_input(db: &DatabaseManager, date: NaiveDate) -> Result<SummaryInput> {
+    let (start, end) = SummaryPeriod::Day.bounds(date)?;
+    let notes = export_daily_notes(db, start, end, &ExportFilter::default(), None).await?;
+    let mut input = SummaryInput {
+        period: date.format("%A %-d %B %Y").to_string(),
+        sections: Vec::new(),
+    };
+    if let Some(note) = notes.get(&date) {
+        input.sections = vec![
+            ("What was said".to_string(), lines(&note.transcripts)),
+            ("What was on screen".to_string(), lines(&note.screen)),
+            ("Time per app".to_string(), lines(&note.apps)),
+        ];
+    }
+    Ok(input)
+}
+
+async fn week_input(db: &DatabaseManager, date: NaiveDate) -> Result<SummaryInput> {
+    let first_day = SummaryPeriod::Week.first_day(date);
+    let (start, end) = SummaryPeriod::Week.bounds(date)?;
+    let mut days = db
+        .list_summaries(
+            Some(SummaryPeriod::Day.as_str()),
+            Some(start),
+            Some(end),
+            7,
+            0,
+        )
+        .await?;
+    days.reverse();
+
+    let mut summaries = Vec::new();
+    for day in days {
+        let mut line = format!(
+            "{}: {}",
+            day.start_time.with_timezone(&Local).format("%A"),
+            day.summary
+        );
+        for (label, items) in [
+            ("Topics", &day.topics),
+            ("Action items", &day.action_items),
+            ("People", &day.people),
+        ] {
+            if !items.is_empty() {
+                line.push_str(&format!(" {}: {}.", label, items.join("; ")));
+            }
+        }
+        summaries.push(line);
+    }
+    Ok(SummaryInput {
+        period: format!("the week of {}", first_day.format("%A %-d %B %Y")),
+        sections: vec![("Summaries of the days".to_string(), summaries)],
+    })
+}
+
+/// Summarizes the period `date` is in, replacing its summary. Returns
+/// `None` when there is nothing to summarize, e.g. nothing was recorded.
+pub async fn summarize(
+    db: &DatabaseManager,
+    summarizer: &Summarizer,
+    period: SummaryPeriod,
+    date: NaiveDate,
+) -> Result<Option<Summary>> {
+    let input = match period {
+        SummaryPeriod::Day => day_input(db, date).await?,
+        SummaryPeriod::Week => week_input(db, date).await?,
+    };
+    if input.is_empty() {
+        return Ok(None);
+    }
+
+    let content = summarizer.summarize(&input).await?;
+    let (start, end) = period.bounds(date)?;
+    let summary = db
+        .upsert_summary(
+            period.as_str(),
+            start,
+            end,
+            &content.summary,
+            &content.topics,
+            &content.action_items,
+            &content.people,
+            &summarizer.model(),
+        )
+        .await?;
+    Ok(Some(summary))
+}
+
+/// Summarizes the days of the last week that have no summary yet, and the
+/// week before this one. Yesterday and last week wait until `hour` o'clock,
+/// when the user is likely done for the day. Returns how many summaries
+/// were written.
+pub async fn summarize_missing(
+    db: &DatabaseManager,
+    summarizer: &Summarizer,
+    hour: u32,
+    now: DateTime<Local>,
+) -> Result<usize> {
+    let today = now.date_naive();
+    let ready = now.hour() >= hour;
+    let mut written = 0;
+
+    for days_ago in (1..=CATCH_UP_DAYS).rev() {
+        if days_ago == 1 && !ready {
+            continue;
+        }
+        let date = today - chrono::Duration::days(days_ago);
+        let (start, _) = SummaryPeriod::Day.bounds(date)?;
+        if db
+            .get_summary(SummaryPeriod::Day.as_str(), start)
+            .await?
+            .is_some()
+        {
+            continue;
+        }
+        if summarize(db, summarizer, SummaryPeriod::Day, date)
+            .await?
+            .is_some()
+        {
+            written += 1;
+        }
+    }
+
+    if ready || today.weekday() != Weekday::Mon {
+        let last_week = today - chrono::Duration::days(7);
+        let (start, _) = SummaryPeriod::Week.bounds(last_week)?;
+        if db
+            .get_summary(SummaryPeriod::Week.as_str(), start)
+            .await?
+            .is_none()
+            && summarize(db, summarizer, SummaryPeriod::Week, last_week)
+                .await?
+                .is_some()
+        {
+            written += 1;
+        }
+    }
+    Ok(written)
+}
+
+/// Writes the summaries of past days and weeks as they end until a shutdown
+/// is signalled.
+pub async fn run_summaries(
+    db: Arc<DatabaseManager>,
+    summarizer: Arc<Summarizer>,
+    hour: u32,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!(
+        "summarizing days after {}:00 with {}",
+        hour,
+        summarizer.model()
+    );
+
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                match summarize_missing(&db, &summarizer, hour, Local::now()).await {
+                    Ok(0) => {}
+                    Ok(written) => info!("wrote {} summaries", written),
+                    Err(e) => error!("failed to summarize: {}", e),
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping summaries");
+                break;
+            }
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct SummariesQuery {
+    #[serde(flatten)]
+    pagination: PaginationQuery,
+    #[serde(default)]
+    period: Option<SummaryPeriod>,
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+}
+
+/// Summaries of days and weeks starting in a time range, the latest first.
+#[oasgen]
+pub(crate) async fn get_summaries_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SummariesQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state
+        .db
+        .list_summaries(
+            query.period.as_ref().map(SummaryPeriod::as_str),
+            query.start_time,
+            query.end_time,
+            query.pagination.limit,
+            query.pagination.offset,
+        )
+        .await
+    {
+        Ok(summaries) => Ok(JsonResponse(json!({
+            "data": summaries,
+            "success": true
+        }))),
+        Err(e) => {
+            error!("failed to read summaries: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to read summaries: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct SummarizeRequest {
+    period: SummaryPeriod,
+    /// A local day of the period as `YYYY-MM-DD`, yesterday or last week
+    /// when not set
+    #[serde(default)]
+    date: Option<String>,
+}
+
+/// Summarizes a day or a week now, replacing its summary.
+#[oasgen]
+pub(crate) async fn summarize_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SummarizeRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let failure = |status: StatusCode, error: String| {
+        (
+            status,
+            JsonResponse(json!({
+                "error": error,
+                "success": false
+            })),
+        )
+    };
+    let summarizer = state.summarizer.clone().ok_or_else(|| {
+        failure(
+            StatusCode::NOT_FOUND,
+            "summaries are disabled, start screenpipe with --enable-summaries".to_string(),
+        )
+    })?;
+    let date = match &request.date {
+        Some(date) => chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+            failure(
+                StatusCode::BAD_REQUEST,
+                format!("invalid date '{}', expected YYYY-MM-DD", date),
+            )
+        })?,
+        None => {
+            let days_ago = match request.period {
+                SummaryPeriod::Day => 1,
+                SummaryPeriod::Week => 7,
+            };
+            chrono::Local::now().date_naive() - chrono::Duration::days(days_ago)
+        }
+    };
+
+    match summarize(&state.db, &summarizer, request.period, date).await {
+        Ok(Some(summary)) => Ok(JsonResponse(json!({
+            "data": summary,
+            "success": true
+        }))),
+        Ok(None) => Err(failure(
+            StatusCode::NOT_FOUND,
+            format!(
+                "nothing to summarize for the {} of {}",
+                request.period.as_str(),
+                date
+            ),
+        )),
+        Err(e) => {
+            error!("failed to summarize: {}", e);
+            Err(failure(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to summarize: {}", e),
+            ))
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Sync of indexed text between devices, enabled with `--sync-peer`. Each
+//! instance serves the OCR text, transcripts and app context it captured at
+//! `/sync/changes`, encrypted with the sync key all the devices share, and
+//! pulls the same from its peers. Raw media stays on the device that
+//! recorded it.
+//!
+//! Records are keyed by the device they were captured on and their id there,
+//! so pulling twice or through several paths imports them once. Imported
+//! records aren't served again, every device pulls from every other one.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json as JsonResponse, Response},
+};
+use screenpipe_core::encryption::{EncryptionKey, MediaCipher};
+use screenpipe_db::{DatabaseManager, SyncBatch, SyncCursor};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::remote::host_name;
+use crate::server::AppState;
+
+/// Most records of each kind in one batch.
+pub const SYNC_BATCH_SIZE: u32 = 500;
+
+pub struct SyncService {
+    db: Arc<DatabaseManager>,
+    device_id: String,
+    host: String,
+    cipher: MediaCipher,
+}
+
+impl SyncService {
+    pub async fn new(db: Arc<DatabaseManager>, key: &EncryptionKey) -> anyhow::Result<Self> {
+        let device_id = db.sync_device_id(&Uuid::new_v4().to_string()).await?;
+        Ok(Self {
+            db,
+            device_id,
+            host: host_name(),
+            cipher: MediaCipher::new(key),
+        })
+    }
+
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    /// What this device captured after `cursor`, encrypted.
+    pub async fn changes(&self, cursor: SyncCursor, limit: u32) -> anyhow::Result<Vec<u8>> {
+        let frames = self.db.get_sync_frames(cursor.frame_cursor, limit).await?;
+        let transcripts = self
+            .db
+            .get_sync_transcriptions(cursor.transcription_cursor, limit)
+            .await?;
+        let next_cursor = SyncCursor {
+            frame_cursor: frames.last().map_or(cursor.frame_cursor, |f| f.id),
+            transcription_cursor: transcripts
+                .last()
+                .map_or(cursor.transcription_cursor, |t| t.id),
+        };
+        let batch = SyncBatch {
+            device_id: self.device_id.clone(),
+            host: self.host.clone(),
+            frames,
+            transcripts,
+            next_cursor,
+        };
+        self.cipher.encrypt(&serde_json::to_vec(&batch)?)
+    }
+
+    /// Decrypts a batch from a peer. Fails if it wasn't encrypted with the
+    /// same key or was tampered with.
+    pub fn open(&self, data: &[u8]) -> anyhow::Result<SyncBatch> {
+        let plaintext = self
+            .cipher
+            .decrypt(data)
+            .context("failed to decrypt, do the devices use the same sync key?")?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Pulls everything new from the peer at `url`, returns how many records
+    /// were imported.
+    pub async fn pull(&self, client: &reqwest::Client, url: &str) -> anyhow::Result<usize> {
+        let endpoint = format!("{}/sync/changes", url.trim_end_matches('/'));
+        let mut imported = 0;
+        loop {
+            let cursor = self.db.get_sync_cursor(url).await?;
+            let body = client
+                .get(&endpoint)
+                .query(&[
+                    ("frame_cursor", cursor.frame_cursor.to_string()),
+                    (
+                        "transcription_cursor",
+                        cursor.transcription_cursor.to_string(),
+                    ),
+                    ("limit", SYNC_BATCH_SIZE.to_string()),
+                ])
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?;
+            let batch = self.open(&body)?;
+            if batch.device_id == self.device_id {
+                anyhow::bail!("{} is this device", url);
+            }
+
+            let done = batch.frames.len() < SYNC_BATCH_SIZE as usize
+                && batch.transcripts.len() < SYNC_BATCH_SIZE as usize;
+            imported += self.db.import_sync_batch(url, &batch).await?;
+            if done {
+                return Ok(imported);
+            }
+        }
+    }
+}
+
+/// Pulls from every peer each `interval` until a shutdown is signalled.
+pub async fn run_sync(
+    service: Arc<SyncService>,
+    peers: Vec<String>,
+    interval: Duration,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let client = match reqwest::Client::builder()
+        // batches are encrypted and authenticated with the sync key, tls
+        // with the peers' self-signed certificates only hides the traffic
+        .danger_accept_invalid_certs(true)
+        .timeout(Duration::from_secs(60))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("failed to create the sync client: {}", e);
+            return;
+        }
+    };
+    info!(
+        "syncing device {} with {} peer(s)",
+        service.device_id(),
+        peers.len()
+    );
+
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                for peer in &peers {
+                    match service.pull(&client, peer).await {
+                        Ok(0) => debug!("nothing new from {}", peer),
+                        Ok(imported) => info!("synced {} records from {}", imported, peer),
+                        Err(e) => warn!("failed to sync with {}: {}", peer, e),
+                    }
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping sync");
+                break;
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SyncChangesQuery {
+    #[serde(default)]
+    frame_cursor: i64,
+    #[serde(default)]
+    transcription_cursor: i64,
+    #[serde(default = "default_sync_limit")]
+    limit: u32,
+}
+
+fn default_sync_limit() -> u32 {
+    SYNC_BATCH_SIZE
+}
+
+// the body is encrypted with the sync key, peers decrypt it
+pub(crate) async fn sync_changes_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SyncChangesQuery>,
+) -> Result<Response, (StatusCode, JsonResponse<Value>)> {
+    let sync = state.sync.clone().ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({
+                "error": "sync is disabled, start screenpipe with --sync-peer",
+                "success": false
+            })),
+        )
+    })?;
+    let cursor = SyncCursor {
+        frame_cursor: query.frame_cursor,
+        transcription_cursor: query.transcription_cursor,
+    };
+    match sync
+        .changes(cursor, query.limit.clamp(1, SYNC_BATCH_SIZE))
+        .await
+    {
+        Ok(body) => Ok((
+            [(axum::http::header::CONTENT_TYPE, "application/octet-stream")],
+            body,
+        )
+            .into_response()),
+        Err(e) => {
+            error!("failed to get sync changes: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": e.to_string(), "success": false})),
+            ))
+        }
+    }
+}
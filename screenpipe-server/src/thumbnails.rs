@@ -0,0 +1,407 @@
+//! Thumbnails of frames and sprite sheets of time ranges, so timelines can be
+//! scrubbed without decoding the video chunks on the client.
+
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Json as JsonResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use image::{codecs::jpeg::JpegEncoder, imageops, DynamicImage, RgbImage};
+use lru::LruCache;
+use oasgen::{oasgen, OaSchema};
+use screenpipe_db::{DatabaseManager, FramePreview};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, warn};
+
+use crate::server::AppState;
+use crate::video_utils::extract_frame_from_video;
+
+/// Width of thumbnails when no size is asked for.
+pub const DEFAULT_THUMBNAIL_WIDTH: u32 = 320;
+/// Width of sprite sheet tiles when no size is asked for, they are 16:9.
+pub const DEFAULT_TILE_WIDTH: u32 = 160;
+/// Largest width or height of a thumbnail or tile.
+pub const MAX_THUMBNAIL_SIZE: u32 = 1920;
+/// Most frames in a sprite sheet.
+pub const MAX_SPRITE_FRAMES: u32 = 100;
+const JPEG_QUALITY: u8 = 80;
+/// Thumbnails and sprite sheets kept in memory.
+const CACHE_CAPACITY: usize = 500;
+/// Frames decoded at once for a sprite sheet.
+const DECODE_CONCURRENCY: usize = 4;
+
+/// Box a thumbnail fits in, keeping the aspect ratio of its frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThumbnailSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ThumbnailSize {
+    /// A missing dimension doesn't bound the thumbnail, without both it is
+    /// [`DEFAULT_THUMBNAIL_WIDTH`] wide.
+    pub fn new(width: Option<u32>, height: Option<u32>) -> Self {
+        let width = match (width, height) {
+            (None, None) => Some(DEFAULT_THUMBNAIL_WIDTH),
+            _ => width,
+        };
+        ThumbnailSize {
+            width: clamp_size(width.unwrap_or(MAX_THUMBNAIL_SIZE)),
+            height: clamp_size(height.unwrap_or(MAX_THUMBNAIL_SIZE)),
+        }
+    }
+
+    /// Cell of a sprite sheet, 16:9 unless both dimensions are given.
+    pub fn tile(width: Option<u32>, height: Option<u32>) -> Self {
+        let width = clamp_size(width.unwrap_or(DEFAULT_TILE_WIDTH));
+        ThumbnailSize {
+            width,
+            height: clamp_size(height.unwrap_or(width * 9 / 16)),
+        }
+    }
+}
+
+fn clamp_size(size: u32) -> u32 {
+    size.clamp(1, MAX_THUMBNAIL_SIZE)
+}
+
+/// Scales the image down to fit in `size`. Smaller images are kept as they are.
+pub fn fit_thumbnail(image: &DynamicImage, size: ThumbnailSize) -> DynamicImage {
+    if image.width() <= size.width && image.height() <= size.height {
+        image.clone()
+    } else {
+        image.thumbnail(size.width, size.height)
+    }
+}
+
+/// Where a frame is in a sprite sheet.
+#[derive(OaSchema, Debug, Clone, PartialEq, Serialize)]
+pub struct SpriteTile {
+    pub frame_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub device_name: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Tiles of the frames, left to right then top to bottom.
+pub fn sprite_layout(
+    frames: &[FramePreview],
+    tile: ThumbnailSize,
+    columns: u32,
+) -> Vec<SpriteTile> {
+    let columns = columns.max(1);
+    frames
+        .iter()
+        .enumerate()
+        .map(|(i, frame)| SpriteTile {
+            frame_id: frame.id,
+            timestamp: frame.timestamp,
+            device_name: frame.device_name.clone(),
+            x: (i as u32 % columns) * tile.width,
+            y: (i as u32 / columns) * tile.height,
+            width: tile.width,
+            height: tile.height,
+        })
+        .collect()
+}
+
+/// Draws the images in a grid of `tile` sized cells, each centered in its
+/// cell. Missing images leave their cell black.
+pub fn draw_sprite_sheet(
+    images: &[Option<DynamicImage>],
+    tile: ThumbnailSize,
+    columns: u32,
+) -> RgbImage {
+    let count = images.len().max(1) as u32;
+    let columns = columns.clamp(1, count);
+    let rows = count.div_ceil(columns);
+    let mut sheet = RgbImage::new(columns * tile.width, rows * tile.height);
+
+    for (i, image) in images.iter().enumerate() {
+        let Some(image) = image else {
+            continue;
+        };
+        let thumbnail = fit_thumbnail(image, tile).to_rgb8();
+        let x = (i as u32 % columns) * tile.width + (tile.width - thumbnail.width()) / 2;
+        let y = (i as u32 / columns) * tile.height + (tile.height - thumbnail.height()) / 2;
+        imageops::overlay(&mut sheet, &thumbnail, x as i64, y as i64);
+    }
+    sheet
+}
+
+pub fn encode_jpeg(image: &RgbImage) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY).encode_image(image)?;
+    Ok(bytes)
+}
+
+/// Encoded thumbnails and sprite sheets, most recently used first.
+pub struct ThumbnailCache {
+    images: Mutex<LruCache<String, Arc<Vec<u8>>>>,
+}
+
+impl Default for ThumbnailCache {
+    fn default() -> Self {
+        ThumbnailCache {
+            images: Mutex::new(LruCache::new(NonZeroUsize::new(CACHE_CAPACITY).unwrap())),
+        }
+    }
+}
+
+impl ThumbnailCache {
+    fn get(&self, key: &str) -> Option<Arc<Vec<u8>>> {
+        self.images.lock().unwrap().get(key).cloned()
+    }
+
+    fn put(&self, key: String, image: Arc<Vec<u8>>) {
+        self.images.lock().unwrap().put(key, image);
+    }
+
+    /// JPEG thumbnail of a frame, `None` if there is no such frame.
+    pub async fn frame_thumbnail(
+        &self,
+        db: &DatabaseManager,
+        frame_id: i64,
+        size: ThumbnailSize,
+    ) -> Result<Option<Arc<Vec<u8>>>> {
+        let key = format!("frame:{}:{}x{}", frame_id, size.width, size.height);
+        if let Some(cached) = self.get(&key) {
+            return Ok(Some(cached));
+        }
+
+        let Some((file_path, offset_index)) = db.get_frame(frame_id).await? else {
+            return Ok(None);
+        };
+        let image = decode_frame(&file_path, offset_index).await?;
+        let thumbnail = Arc::new(
+            tokio::task::spawn_blocking(move || {
+                encode_jpeg(&fit_thumbnail(&image, size).to_rgb8())
+            })
+            .await??,
+        );
+
+        self.put(key, thumbnail.clone());
+        Ok(Some(thumbnail))
+    }
+
+    /// JPEG sprite sheet of the frames, laid out as in [`sprite_layout`].
+    pub async fn sprite_sheet(
+        &self,
+        frames: &[FramePreview],
+        tile: ThumbnailSize,
+        columns: u32,
+    ) -> Result<Arc<Vec<u8>>> {
+        let frame_ids: Vec<String> = frames.iter().map(|frame| frame.id.to_string()).collect();
+        let key = format!(
+            "sprite:{}x{}:{}:{}",
+            tile.width,
+            tile.height,
+            columns,
+            frame_ids.join(",")
+        );
+        if let Some(cached) = self.get(&key) {
+            return Ok(cached);
+        }
+
+        // a frame that can't be decoded leaves a blank tile, not a failed sheet
+        let images: Vec<Option<DynamicImage>> = stream::iter(frames.to_vec())
+            .map(|frame| async move {
+                decode_frame(&frame.file_path, frame.offset_index)
+                    .await
+                    .map_err(|e| warn!("failed to decode frame {}: {}", frame.id, e))
+                    .ok()
+            })
+            .buffered(DECODE_CONCURRENCY)
+            .collect()
+            .await;
+        let sheet = Arc::new(
+            tokio::task::spawn_blocking(move || {
+                encode_jpeg(&draw_sprite_sheet(&images, tile, columns))
+            })
+            .await??,
+        );
+
+        self.put(key, sheet.clone());
+        Ok(sheet)
+    }
+}
+
+async fn decode_frame(file_path: &str, offset_index: i64) -> Result<DynamicImage> {
+    let frame_path = extract_frame_from_video(file_path, offset_index).await?;
+    let image = tokio::task::spawn_blocking({
+        let frame_path = frame_path.clone();
+        move || image::open(frame_path)
+    })
+    .await?;
+    let _ = tokio::fs::remove_file(&frame_path).await;
+    Ok(image?)
+}
+
+#[derive(OaSchema, Deserialize)]
+pub struct ThumbnailQuery {
+    /// Largest width in pixels, the aspect ratio is kept
+    width: Option<u32>,
+    /// Largest height in pixels
+    height: Option<u32>,
+}
+
+/// Frame scaled down to fit the size asked for, 320 pixels wide by default.
+#[oasgen]
+pub async fn get_frame_thumbnail_handler(
+    State(state): State<Arc<AppState>>,
+    Path(frame_id): Path<i64>,
+    Query(query): Query<ThumbnailQuery>,
+) -> Result<Response<Body>, (StatusCode, JsonResponse<Value>)> {
+    let size = ThumbnailSize::new(query.width, query.height);
+    match state
+        .thumbnails
+        .frame_thumbnail(&state.db, frame_id, size)
+        .await
+    {
+        Ok(Some(thumbnail)) => jpeg_response(&thumbnail),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({
+                "error": "Frame not found",
+                "frame_id": frame_id
+            })),
+        )),
+        Err(e) => {
+            error!("failed to make thumbnail of frame {}: {}", frame_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to make thumbnail: {}", e),
+                    "frame_id": frame_id
+                })),
+            ))
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub struct SpriteSheetQuery {
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    /// Frames spread evenly over the range, at most 100
+    #[serde(default = "default_sprite_frames")]
+    count: u32,
+    /// Tiles per row, all in one strip by default
+    columns: Option<u32>,
+    /// Tile width in pixels, 160 by default
+    width: Option<u32>,
+    /// Tile height in pixels, 16:9 of the width by default
+    height: Option<u32>,
+    /// Only frames of this monitor
+    device_name: Option<String>,
+}
+
+fn default_sprite_frames() -> u32 {
+    20
+}
+
+/// Frames of the sprite sheet asked for, with its tile size and columns.
+async fn sprite_frames(
+    state: &AppState,
+    query: &SpriteSheetQuery,
+) -> Result<(Vec<FramePreview>, ThumbnailSize, u32), (StatusCode, JsonResponse<Value>)> {
+    if query.end_time <= query.start_time {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "end_time must be after start_time", "success": false})),
+        ));
+    }
+    let count = query.count.clamp(1, MAX_SPRITE_FRAMES);
+    let frames = state
+        .db
+        .sample_frames(
+            query.start_time,
+            query.end_time,
+            query.device_name.as_deref(),
+            count,
+        )
+        .await
+        .map_err(|e| {
+            error!("failed to get frames for sprite sheet: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to get frames: {}", e),
+                    "success": false
+                })),
+            )
+        })?;
+    let tile = ThumbnailSize::tile(query.width, query.height);
+    let columns = query.columns.unwrap_or(count).clamp(1, count);
+    Ok((frames, tile, columns))
+}
+
+/// Thumbnails of frames of a time range in one image, for timeline previews.
+/// `/frames/sprite/index` tells where each frame is.
+#[oasgen]
+pub async fn get_sprite_sheet_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SpriteSheetQuery>,
+) -> Result<Response<Body>, (StatusCode, JsonResponse<Value>)> {
+    let (frames, tile, columns) = sprite_frames(&state, &query).await?;
+    if frames.is_empty() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": "no frames in this time range", "success": false})),
+        ));
+    }
+
+    match state.thumbnails.sprite_sheet(&frames, tile, columns).await {
+        Ok(sheet) => jpeg_response(&sheet),
+        Err(e) => {
+            error!("failed to make sprite sheet: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({
+                    "error": format!("failed to make sprite sheet: {}", e),
+                    "success": false
+                })),
+            ))
+        }
+    }
+}
+
+/// Frames of the sprite sheet of the same query and where each one is in it.
+#[oasgen]
+pub async fn get_sprite_index_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SpriteSheetQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let (frames, tile, columns) = sprite_frames(&state, &query).await?;
+    Ok(JsonResponse(json!({
+        "data": sprite_layout(&frames, tile, columns),
+        "columns": columns,
+        "success": true
+    })))
+}
+
+fn jpeg_response(image: &[u8]) -> Result<Response<Body>, (StatusCode, JsonResponse<Value>)> {
+    Response::builder()
+        .header("content-type", "image/jpeg")
+        .header("cache-control", "public, max-age=604800")
+        .body(Body::from(image.to_vec()))
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("Failed to create response: {}", e)})),
+            )
+        })
+}
@@ -1,10 +1,14 @@
 use chrono::Utc;
 use crossbeam::queue::ArrayQueue;
 use image::ImageFormat::{self};
+use screenpipe_core::metrics::{self, Counter};
 use screenpipe_core::{find_ffmpeg_path, Language};
 use screenpipe_vision::monitor::get_monitor_by_id;
 use screenpipe_vision::{
-    capture_screenshot_by_window::WindowFilters, continuous_capture, CaptureResult, OcrEngine,
+    capture_screenshot_by_window::{CaptureMode, WindowFilters},
+    continuous_capture,
+    frame_rate::SharedAdaptiveFpsConfig,
+    CaptureRegion, CaptureResult, OcrEngine, PrivacyBlocklist,
 };
 use std::borrow::Cow;
 use std::path::PathBuf;
@@ -20,6 +24,9 @@ use tokio::sync::mpsc::channel;
 use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
+use crate::redaction::{redact_capture, Redaction};
+use crate::video_encoder::VideoEncoder;
+
 pub(crate) const MAX_FPS: f64 = 30.0; // Adjust based on your needs
 const MAX_QUEUE_SIZE: usize = 30; // Increased from 10 for more buffer room
 
@@ -34,6 +41,8 @@ pub struct VideoCapture {
     monitor_check_handle: tokio::task::JoinHandle<()>, // New handle for monitor check
     monitor_available: Arc<AtomicBool>,                // Flag to track monitor availability
     monitor_id: u32,                                   // Store monitor ID for availability checks
+    /// Set by `stop`, the video task finishes its chunk once its queue is empty
+    stopping: Arc<AtomicBool>,
 }
 
 impl VideoCapture {
@@ -47,8 +56,16 @@ impl VideoCapture {
         monitor_id: u32,
         ignore_list: &[String],
         include_list: &[String],
+        privacy_blocklist: Arc<PrivacyBlocklist>,
         languages: Vec<Language>,
         capture_unfocused_windows: bool,
+        capture_ui_tree: bool,
+        accessibility_text: bool,
+        capture_regions: Vec<CaptureRegion>,
+        capture_mode: CaptureMode,
+        adaptive_fps: SharedAdaptiveFpsConfig,
+        video_encoder: VideoEncoder,
+        redaction: Option<Redaction>,
     ) -> Self {
         let fps = if fps.is_finite() && fps > 0.0 {
             fps
@@ -63,6 +80,7 @@ impl VideoCapture {
         let new_chunk_callback_clone = Arc::clone(&new_chunk_callback);
         let monitor_available = Arc::new(AtomicBool::new(true));
         let monitor_available_clone = monitor_available.clone();
+        let stopping = Arc::new(AtomicBool::new(false));
 
         info!(
             "Starting VideoCapture for monitor {}, max queue size: {}, fps: {}",
@@ -82,6 +100,14 @@ impl VideoCapture {
         let capture_result_sender = result_sender.clone();
         let capture_interval = interval;
         let capture_unfocused = capture_unfocused_windows;
+        let capture_regions = Arc::new(capture_regions);
+        if !capture_regions.is_empty() {
+            info!(
+                "monitor {} limited to {} capture region(s)",
+                monitor_id,
+                capture_regions.len()
+            );
+        }
 
         // Store task handles for health monitoring
         let capture_thread = tokio::spawn(async move {
@@ -109,8 +135,14 @@ impl VideoCapture {
                     (*capture_ocr_engine).clone(),
                     monitor_id,
                     capture_window_filters.clone(),
+                    privacy_blocklist.clone(),
                     capture_languages.clone(),
                     capture_unfocused,
+                    capture_ui_tree,
+                    accessibility_text,
+                    capture_regions.clone(),
+                    capture_mode,
+                    adaptive_fps.clone(),
                 )
                 .await
                 {
@@ -137,12 +169,21 @@ impl VideoCapture {
             let start_time = std::time::Instant::now();
             let mut last_log_time = start_time;
             let log_interval = Duration::from_secs(30); // Log stats every 30 seconds
+            let mut processed_at_last_log = 0;
+
+            let monitor = monitor_id.to_string();
+            let frames_captured = metrics::FRAMES_CAPTURED.with_labels(&[&monitor]);
+            let capture_fps = metrics::CAPTURE_FPS.with_labels(&[&monitor]);
+            let ocr_queue_depth = metrics::OCR_QUEUE_DEPTH.with_labels(&[&monitor]);
+            let video_dropped = metrics::FRAMES_DROPPED.with_labels(&[&monitor, "video"]);
+            let ocr_dropped = metrics::FRAMES_DROPPED.with_labels(&[&monitor, "ocr"]);
 
             // Helper function to push to queue and handle errors
             fn push_to_queue(
                 queue: &ArrayQueue<Arc<CaptureResult>>,
                 result: &Arc<CaptureResult>,
                 queue_name: &str,
+                dropped: &Counter,
             ) -> bool {
                 if queue.push(Arc::clone(result)).is_err() {
                     if queue.len() >= queue.capacity() {
@@ -158,6 +199,7 @@ impl VideoCapture {
                         error!("{} queue is in an inconsistent state", queue_name);
                         return false;
                     }
+                    dropped.inc();
                     if queue.push(Arc::clone(result)).is_err() {
                         error!(
                             "Failed to push to {} queue after removing oldest frame",
@@ -178,6 +220,7 @@ impl VideoCapture {
             while let Some(result) = result_receiver.recv().await {
                 let frame_number = result.frame_number;
                 processed_count += 1;
+                frames_captured.inc();
 
                 // Periodically log stats
                 let now = std::time::Instant::now();
@@ -194,15 +237,55 @@ impl VideoCapture {
                         capture_video_frame_queue.len(), capture_video_frame_queue.capacity(),
                         capture_ocr_frame_queue.len(), capture_ocr_frame_queue.capacity()
                     );
+                    capture_fps.set(
+                        (processed_count - processed_at_last_log) as f64
+                            / now.duration_since(last_log_time).as_secs_f64(),
+                    );
+                    processed_at_last_log = processed_count;
                     last_log_time = now;
                 }
 
                 debug!("Received frame {} for queueing", frame_number);
 
+                // repeats aren't encoded again, the database counts them
+                if result.repeat_of_previous {
+                    push_to_queue(
+                        &capture_ocr_frame_queue,
+                        &Arc::new(result),
+                        "OCR",
+                        &ocr_dropped,
+                    );
+                    ocr_queue_depth.set(capture_ocr_frame_queue.len() as f64);
+                    continue;
+                }
+
+                // nothing unredacted may reach the video or the database
+                let result = match &redaction {
+                    Some(redaction) => {
+                        let redaction = redaction.clone();
+                        match tokio::task::spawn_blocking(move || {
+                            let mut result = result;
+                            redact_capture(&mut result, &redaction);
+                            result
+                        })
+                        .await
+                        {
+                            Ok(result) => result,
+                            Err(e) => {
+                                error!("Failed to redact frame {}: {}", frame_number, e);
+                                continue;
+                            }
+                        }
+                    }
+                    None => result,
+                };
                 let result = Arc::new(result);
 
-                let video_pushed = push_to_queue(&capture_video_frame_queue, &result, "Video");
-                let ocr_pushed = push_to_queue(&capture_ocr_frame_queue, &result, "OCR");
+                let video_pushed =
+                    push_to_queue(&capture_video_frame_queue, &result, "Video", &video_dropped);
+                let ocr_pushed =
+                    push_to_queue(&capture_ocr_frame_queue, &result, "OCR", &ocr_dropped);
+                ocr_queue_depth.set(capture_ocr_frame_queue.len() as f64);
 
                 if !video_pushed || !ocr_pushed {
                     error!(
@@ -229,6 +312,7 @@ impl VideoCapture {
         });
 
         let video_frame_queue_clone = video_frame_queue.clone();
+        let video_stopping = stopping.clone();
 
         let output_path = output_path.to_string();
         let video_thread = tokio::spawn(async move {
@@ -243,11 +327,13 @@ impl VideoCapture {
                 new_chunk_callback_clone,
                 monitor_id,
                 video_chunk_duration,
+                video_encoder,
+                &video_stopping,
             )
             .await
             {
-                Ok(_) => warn!(
-                    "save_frames_as_video task completed unexpectedly for monitor {}",
+                Ok(_) => info!(
+                    "save_frames_as_video task finished for monitor {}",
                     monitor_id
                 ),
                 Err(e) => error!(
@@ -255,10 +341,6 @@ impl VideoCapture {
                     monitor_id, e
                 ),
             }
-            warn!(
-                "save_frames_as_video task terminated for monitor {}",
-                monitor_id
-            );
         });
 
         // Add monitor availability check task
@@ -301,9 +383,23 @@ impl VideoCapture {
             monitor_check_handle,
             monitor_available,
             monitor_id,
+            stopping,
         }
     }
 
+    /// Stops capturing, queues the frames already captured and waits for
+    /// them to be written to the video, whose chunk is then finished. The
+    /// OCR queue is left for the caller to empty.
+    pub async fn stop(&mut self) {
+        self.capture_thread_handle.abort();
+        let _ = (&mut self.capture_thread_handle).await;
+        // runs until the frames sent by the capture are queued
+        let _ = (&mut self.queue_thread_handle).await;
+        self.stopping.store(true, Ordering::SeqCst);
+        let _ = (&mut self.video_thread_handle).await;
+        self.monitor_check_handle.abort();
+    }
+
     // Modify check_health to include monitor check task
     pub fn check_health(&self) -> bool {
         let capture_ok = !self.capture_thread_handle.is_finished();
@@ -337,7 +433,22 @@ impl VideoCapture {
     }
 }
 
-pub async fn start_ffmpeg_process(output_file: &str, fps: f64) -> Result<Child, anyhow::Error> {
+impl Drop for VideoCapture {
+    /// Stops the tasks of the capture, e.g. when the watchdog restarts it.
+    /// FFmpeg finishes the video chunk being written once its input closes.
+    fn drop(&mut self) {
+        self.capture_thread_handle.abort();
+        self.queue_thread_handle.abort();
+        self.video_thread_handle.abort();
+        self.monitor_check_handle.abort();
+    }
+}
+
+pub async fn start_ffmpeg_process(
+    output_file: &str,
+    fps: f64,
+    encoder: VideoEncoder,
+) -> Result<Child, anyhow::Error> {
     // Overriding fps with max fps if over the max and warning user
     let fps = if fps > MAX_FPS {
         warn!("Overriding FPS from {} to {}", fps, MAX_FPS);
@@ -346,7 +457,10 @@ pub async fn start_ffmpeg_process(output_file: &str, fps: f64) -> Result<Child,
         fps
     };
 
-    info!("Starting FFmpeg process for file: {}", output_file);
+    info!(
+        "Starting FFmpeg process for file: {} (encoder: {})",
+        output_file, encoder
+    );
     let fps_str = fps.to_string();
     let mut command = Command::new(find_ffmpeg_path().unwrap());
     let mut args = vec![
@@ -362,18 +476,9 @@ pub async fn start_ffmpeg_process(output_file: &str, fps: f64) -> Result<Child,
         "pad=width=ceil(iw/2)*2:height=ceil(ih/2)*2",
     ];
 
-    args.extend_from_slice(&[
-        "-vcodec",
-        "libx265",
-        "-tag:v",
-        "hvc1",
-        "-preset",
-        "ultrafast",
-        "-crf",
-        "23",
-    ]);
+    args.extend(encoder.ffmpeg_args());
 
-    args.extend_from_slice(&["-pix_fmt", "yuv420p", output_file]);
+    args.extend_from_slice(&["-pix_fmt", encoder.pix_fmt(), output_file]);
 
     command
         .args(&args)
@@ -412,6 +517,8 @@ async fn save_frames_as_video(
     new_chunk_callback: Arc<dyn Fn(&str) + Send + Sync>,
     monitor_id: u32,
     video_chunk_duration: Duration,
+    video_encoder: VideoEncoder,
+    stopping: &AtomicBool,
 ) -> Result<(), anyhow::Error> {
     info!(
         "Starting save_frames_as_video function for monitor {}",
@@ -421,6 +528,7 @@ async fn save_frames_as_video(
     let mut frame_count = 0;
     let mut current_ffmpeg: Option<Child> = None;
     let mut current_stdin: Option<ChildStdin> = None;
+    let mut video_encoder = video_encoder;
 
     // Track health metrics
     let start_time = std::time::Instant::now();
@@ -430,7 +538,8 @@ async fn save_frames_as_video(
     let stats_interval = Duration::from_secs(60);
 
     loop {
-        if frame_count >= frames_per_video || current_ffmpeg.is_none() {
+        let stopped = stopping.load(Ordering::SeqCst) && frame_queue.is_empty();
+        if frame_count >= frames_per_video || current_ffmpeg.is_none() || stopped {
             if let Some(child) = current_ffmpeg.take() {
                 info!(
                     "Finishing FFmpeg process for monitor {} after {} frames",
@@ -442,7 +551,9 @@ async fn save_frames_as_video(
 
             frame_count = 0;
             debug!("Waiting for first frame for monitor {}", monitor_id);
-            let first_frame = wait_for_first_frame(frame_queue).await;
+            let Some(first_frame) = wait_for_first_frame(frame_queue, stopping).await else {
+                break;
+            };
             let buffer = encode_frame(&first_frame);
             debug!("Got first frame for new chunk for monitor {}", monitor_id);
 
@@ -453,7 +564,7 @@ async fn save_frames_as_video(
             );
             new_chunk_callback(&output_file);
 
-            match start_ffmpeg_process(&output_file, fps).await {
+            match start_ffmpeg_process(&output_file, fps, video_encoder).await {
                 Ok(mut child) => {
                     let mut stdin = child.stdin.take().expect("Failed to open stdin");
                     spawn_ffmpeg_loggers(child.stderr.take(), child.stdout.take());
@@ -464,6 +575,7 @@ async fn save_frames_as_video(
                             "Failed to write first frame to ffmpeg for monitor {}: {}",
                             monitor_id, e
                         );
+                        fall_back_to_software_encoder(&mut video_encoder, monitor_id);
                         continue;
                     }
                     frame_count += 1;
@@ -481,6 +593,7 @@ async fn save_frames_as_video(
                         "Failed to start FFmpeg process for monitor {}: {}",
                         monitor_id, e
                     );
+                    fall_back_to_software_encoder(&mut video_encoder, monitor_id);
                     continue;
                 }
             }
@@ -511,6 +624,7 @@ async fn save_frames_as_video(
             &mut frame_count,
             frames_per_video,
             fps,
+            stopping,
         )
         .await;
 
@@ -520,18 +634,35 @@ async fn save_frames_as_video(
         tokio::task::yield_now().await;
     }
 
-    // This is unreachable, but we need to return a Result to match the function signature
-    #[allow(unreachable_code)]
     Ok(())
 }
 
+/// A hardware encoder can disappear at runtime (driver reset, eGPU unplugged),
+/// so switch to the software encoder for the remaining chunks.
+fn fall_back_to_software_encoder(video_encoder: &mut VideoEncoder, monitor_id: u32) {
+    if video_encoder.is_hardware() {
+        warn!(
+            "video encoder {} failed for monitor {}, falling back to {}",
+            video_encoder,
+            monitor_id,
+            VideoEncoder::FALLBACK
+        );
+        *video_encoder = VideoEncoder::FALLBACK;
+    }
+}
+
+/// Waits for a frame to start a chunk with, none once stopping.
 async fn wait_for_first_frame(
     frame_queue: &Arc<ArrayQueue<Arc<CaptureResult>>>,
-) -> Arc<CaptureResult> {
+    stopping: &AtomicBool,
+) -> Option<Arc<CaptureResult>> {
     loop {
         if let Some(result) = frame_queue.pop() {
             debug!("Got first frame for new chunk");
-            return result;
+            return Some(result);
+        }
+        if stopping.load(Ordering::SeqCst) {
+            return None;
         }
         tokio::time::sleep(Duration::from_millis(10)).await;
     }
@@ -571,6 +702,7 @@ async fn process_frames(
     frame_count: &mut usize,
     frames_per_video: usize,
     fps: f64,
+    stopping: &AtomicBool,
 ) {
     let write_timeout = Duration::from_secs_f64(1.0 / fps);
     while *frame_count < frames_per_video {
@@ -586,6 +718,8 @@ async fn process_frames(
 
                 flush_ffmpeg_input(stdin, *frame_count, fps).await;
             }
+        } else if stopping.load(Ordering::SeqCst) {
+            break;
         } else {
             tokio::time::sleep(write_timeout).await;
         }
@@ -1,7 +1,9 @@
+use crate::offload::readable_media;
 use anyhow::Result;
 use bincode;
 use chrono::{DateTime, Duration, Utc};
 use dirs::cache_dir;
+use screenpipe_core::encryption::{is_encrypted, media_cipher};
 use screenpipe_core::find_ffmpeg_path;
 use screenpipe_db::{DatabaseManager, FrameData, OCREntry};
 use serde::{Deserialize, Serialize};
@@ -23,6 +25,9 @@ pub struct TimeSeriesFrame {
     pub timestamp: DateTime<Utc>,
     pub frame_data: Vec<DeviceFrame>,
     pub error: Option<String>,
+    /// Near-identical captures after this frame, see [`FrameData`]
+    pub repeat_count: i64,
+    pub last_repeat_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone)]
@@ -36,6 +41,7 @@ pub struct DeviceFrame {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioEntry {
+    pub transcription_id: i64,
     pub transcription: String,
     pub device_name: String,
     pub is_input: bool,
@@ -46,6 +52,7 @@ pub struct AudioEntry {
 impl From<screenpipe_db::AudioEntry> for AudioEntry {
     fn from(db_entry: screenpipe_db::AudioEntry) -> Self {
         Self {
+            transcription_id: db_entry.transcription_id,
             transcription: db_entry.transcription,
             device_name: db_entry.device_name,
             is_input: db_entry.is_input,
@@ -165,7 +172,7 @@ impl FrameDiskCache {
 
     async fn load_index(&mut self) -> Result<()> {
         match fs::read(&self.index_path).await {
-            Ok(data) if !data.is_empty() => match bincode::deserialize::<Vec<CachedFrame>>(&data) {
+            Ok(data) if !data.is_empty() => match decode_index(data) {
                 Ok(frames) => {
                     for frame in frames {
                         let path = self.get_frame_path(&frame.timestamp, &frame.device_id);
@@ -183,7 +190,7 @@ impl FrameDiskCache {
                     }
                     debug!("loaded {} cached frames", self.entries.len());
                 }
-                Err(e) => error!("failed to deserialize cache index: {}", e),
+                Err(e) => error!("failed to decode cache index: {}", e),
             },
             Ok(_) => debug!("cache index is empty, starting fresh"),
             Err(e) => error!("failed to read cache index: {}", e),
@@ -200,7 +207,7 @@ impl FrameDiskCache {
             bincode::serialize(&frames)?
         };
 
-        fs::write(&temp_path, encoded).await?;
+        fs::write(&temp_path, seal(encoded)?).await?;
         fs::rename(&temp_path, &self.index_path).await?;
         Ok(())
     }
@@ -228,6 +235,7 @@ impl FrameDiskCache {
         let mut hasher = Sha256::new();
         hasher.update(frame_data);
         let checksum = format!("{:x}", hasher.finalize());
+        let sealed = seal(frame_data.to_vec())?;
 
         let cached_frame = CachedFrame {
             timestamp,
@@ -244,7 +252,7 @@ impl FrameDiskCache {
                     .join(" "),
                 ocr_text: device_data.text.clone(),
             },
-            frame_size: frame_data.len() as u64,
+            frame_size: sealed.len() as u64,
             compression: CompressionType::Jpeg {
                 quality: self.config.compression_quality,
             },
@@ -253,7 +261,7 @@ impl FrameDiskCache {
             audio_entries: audio_entries.to_vec(),
         };
 
-        fs::write(&frame_path, frame_data).await?;
+        fs::write(&frame_path, &sealed).await?;
 
         self.entries.insert(
             (timestamp, device_id.to_string()),
@@ -264,7 +272,7 @@ impl FrameDiskCache {
             },
         );
 
-        self.total_size += frame_data.len() as u64;
+        self.total_size += sealed.len() as u64;
         self.save_index().await?;
 
         Ok(())
@@ -304,7 +312,7 @@ impl FrameDiskCache {
 
             if should_verify {
                 debug!("verifying checksum for cached frame");
-                let frame_data = fs::read(&frame_path).await?;
+                let frame_data = unseal(fs::read(&frame_path).await?)?;
                 let mut hasher = Sha256::new();
                 hasher.update(&frame_data);
                 let checksum = format!("{:x}", hasher.finalize());
@@ -321,7 +329,7 @@ impl FrameDiskCache {
                 )))
             } else {
                 // Fast path - skip checksum verification
-                let frame_data = fs::read(&frame_path).await?;
+                let frame_data = unseal(fs::read(&frame_path).await?)?;
                 Ok(Some((
                     frame_data,
                     entry.frame.metadata.clone(),
@@ -489,6 +497,8 @@ impl FrameCache {
                 timestamp: chunk.timestamp,
                 frame_data: Vec::new(),
                 error: None,
+                repeat_count: chunk.repeat_count,
+                last_repeat_at: chunk.last_repeat_at,
             };
 
             for device_data in &chunk.ocr_entries {
@@ -515,6 +525,7 @@ impl FrameCache {
                                 .audio_entries
                                 .iter()
                                 .map(|a| AudioEntry {
+                                    transcription_id: a.transcription_id,
                                     transcription: a.transcription.clone(),
                                     device_name: a.device_name.clone(),
                                     is_input: a.is_input,
@@ -640,6 +651,28 @@ impl FrameCache {
     }
 }
 
+/// Encrypts data written to the cache when recordings are encrypted, so
+/// frames and their text don't end up on disk in the clear.
+fn seal(data: Vec<u8>) -> Result<Vec<u8>> {
+    match media_cipher() {
+        Some(cipher) => cipher.encrypt(&data),
+        None => Ok(data),
+    }
+}
+
+fn decode_index(data: Vec<u8>) -> Result<Vec<CachedFrame>> {
+    Ok(bincode::deserialize(&unseal(data)?)?)
+}
+
+fn unseal(data: Vec<u8>) -> Result<Vec<u8>> {
+    if !is_encrypted(&data) {
+        return Ok(data);
+    }
+    media_cipher()
+        .ok_or_else(|| anyhow::anyhow!("cached frame is encrypted but no key is unlocked"))?
+        .decrypt(&data)
+}
+
 async fn extract_frame(
     ffmpeg: PathBuf,
     video_file_path: String,
@@ -647,6 +680,10 @@ async fn extract_frame(
     frame_tx: FrameChannel,
     cache_tx: mpsc::Sender<CacheMessage>,
 ) -> Result<usize> {
+    // encrypted recordings are decrypted to a temporary file for ffmpeg
+    let media = readable_media(&video_file_path).await?;
+    let video_file_path = media.path_str();
+
     if !is_video_file_complete(&ffmpeg, &video_file_path).await? {
         debug!("skipping incomplete video file: {}", video_file_path);
         return Ok(0);
@@ -776,6 +813,8 @@ async fn extract_frame(
             .send(TimeSeriesFrame {
                 error: None,
                 timestamp: chunk.timestamp,
+                repeat_count: chunk.repeat_count,
+                last_repeat_at: chunk.last_repeat_at,
                 frame_data: vec![DeviceFrame {
                     frame_id: frame.frame_id,
                     device_id: device_data.device_name.clone(),
@@ -796,6 +835,7 @@ async fn extract_frame(
                         .audio_entries
                         .iter()
                         .map(|a| AudioEntry {
+                            transcription_id: a.transcription_id,
                             transcription: a.transcription.clone(),
                             device_name: a.device_name.clone(),
                             is_input: a.is_input,
@@ -0,0 +1,205 @@
+use screenpipe_core::find_ffmpeg_path;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::process::Stdio;
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+
+/// Encoder used by ffmpeg when writing video chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoEncoder {
+    /// Pick the first hardware encoder that works on this machine
+    #[default]
+    Auto,
+    /// Apple VideoToolbox (macOS)
+    VideoToolbox,
+    /// NVIDIA NVENC
+    Nvenc,
+    /// Intel QuickSync
+    Qsv,
+    /// Software HEVC encoder
+    Libx265,
+    /// Software H.264 encoder, used as fallback when hardware encoding fails
+    Libx264,
+}
+
+impl VideoEncoder {
+    pub const FALLBACK: VideoEncoder = VideoEncoder::Libx264;
+
+    /// Hardware encoders to try, in order, when `Auto` is selected.
+    pub fn hardware_candidates() -> &'static [VideoEncoder] {
+        if cfg!(target_os = "macos") {
+            &[VideoEncoder::VideoToolbox]
+        } else {
+            &[VideoEncoder::Nvenc, VideoEncoder::Qsv]
+        }
+    }
+
+    pub fn is_hardware(&self) -> bool {
+        matches!(
+            self,
+            VideoEncoder::VideoToolbox | VideoEncoder::Nvenc | VideoEncoder::Qsv
+        )
+    }
+
+    /// QuickSync only accepts nv12 input, everything else takes yuv420p.
+    pub fn pix_fmt(&self) -> &'static str {
+        match self {
+            VideoEncoder::Qsv => "nv12",
+            _ => "yuv420p",
+        }
+    }
+
+    /// Output codec arguments passed to ffmpeg after the input and filters.
+    pub fn ffmpeg_args(&self) -> Vec<&'static str> {
+        match self {
+            // auto is resolved before encoding, treat it like the fallback if it slips through
+            VideoEncoder::Auto | VideoEncoder::Libx264 => vec![
+                "-vcodec",
+                "libx264",
+                "-preset",
+                "ultrafast",
+                "-crf",
+                "23",
+            ],
+            VideoEncoder::Libx265 => vec![
+                "-vcodec",
+                "libx265",
+                "-tag:v",
+                "hvc1",
+                "-preset",
+                "ultrafast",
+                "-crf",
+                "23",
+            ],
+            VideoEncoder::VideoToolbox => vec![
+                "-vcodec",
+                "hevc_videotoolbox",
+                "-tag:v",
+                "hvc1",
+                "-q:v",
+                "50",
+                "-realtime",
+                "true",
+            ],
+            VideoEncoder::Nvenc => vec![
+                "-vcodec",
+                "hevc_nvenc",
+                "-tag:v",
+                "hvc1",
+                "-preset",
+                "p1",
+                "-rc",
+                "constqp",
+                "-qp",
+                "28",
+            ],
+            VideoEncoder::Qsv => vec![
+                "-vcodec",
+                "hevc_qsv",
+                "-tag:v",
+                "hvc1",
+                "-preset",
+                "veryfast",
+                "-global_quality",
+                "28",
+            ],
+        }
+    }
+}
+
+impl fmt::Display for VideoEncoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VideoEncoder::Auto => "auto",
+            VideoEncoder::VideoToolbox => "videotoolbox",
+            VideoEncoder::Nvenc => "nvenc",
+            VideoEncoder::Qsv => "qsv",
+            VideoEncoder::Libx265 => "libx265",
+            VideoEncoder::Libx264 => "libx264",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Encodes a few blank frames with the given encoder to check that both
+/// ffmpeg and the underlying hardware support it.
+pub async fn probe_encoder(encoder: VideoEncoder) -> bool {
+    let Some(ffmpeg_path) = find_ffmpeg_path() else {
+        warn!("ffmpeg not found, cannot probe video encoder {}", encoder);
+        return false;
+    };
+
+    let mut args = vec![
+        "-hide_banner",
+        "-loglevel",
+        "error",
+        "-f",
+        "lavfi",
+        "-i",
+        "color=c=black:s=256x256:r=1:d=1",
+    ];
+    args.extend(encoder.ffmpeg_args());
+    args.extend_from_slice(&["-pix_fmt", encoder.pix_fmt()]);
+    args.extend_from_slice(&["-f", "null", "-"]);
+
+    let result = Command::new(ffmpeg_path)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await;
+
+    match result {
+        Ok(output) if output.status.success() => true,
+        Ok(output) => {
+            debug!(
+                "video encoder {} not available: {}",
+                encoder,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            false
+        }
+        Err(e) => {
+            debug!("failed to run ffmpeg to probe {}: {}", encoder, e);
+            false
+        }
+    }
+}
+
+/// Turns the requested encoder into the one that will actually be used.
+/// `Auto` picks the first working hardware encoder, and any encoder that
+/// fails to probe falls back to libx264.
+pub async fn resolve_video_encoder(requested: VideoEncoder) -> VideoEncoder {
+    match requested {
+        VideoEncoder::Auto => {
+            for candidate in VideoEncoder::hardware_candidates() {
+                if probe_encoder(*candidate).await {
+                    info!("using hardware video encoder: {}", candidate);
+                    return *candidate;
+                }
+            }
+            info!(
+                "no hardware video encoder available, using {}",
+                VideoEncoder::FALLBACK
+            );
+            VideoEncoder::FALLBACK
+        }
+        encoder if encoder.is_hardware() => {
+            if probe_encoder(encoder).await {
+                info!("using hardware video encoder: {}", encoder);
+                encoder
+            } else {
+                warn!(
+                    "video encoder {} is not available on this machine, falling back to {}",
+                    encoder,
+                    VideoEncoder::FALLBACK
+                );
+                VideoEncoder::FALLBACK
+            }
+        }
+        encoder => encoder,
+    }
+}
@@ -1,3 +1,4 @@
+use crate::offload::readable_media;
 use anyhow::Result;
 use base64::{engine::general_purpose, Engine as _};
 use chrono::NaiveDateTime;
@@ -37,6 +38,10 @@ struct Stream {
 }
 
 pub async fn extract_frame(file_path: &str, offset_index: i64) -> Result<String> {
+    // encrypted recordings are decrypted to a temporary file for ffmpeg
+    let media = readable_media(file_path).await?;
+    let media_path = media.path_str();
+    let file_path = media_path.as_str();
     let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
 
     let offset_seconds = offset_index as f64 / 1000.0;
@@ -449,6 +454,15 @@ pub async fn get_video_metadata(video_path: &str) -> Result<VideoMetadata> {
     })
 }
 
+/// Length of the video in seconds, decrypting or fetching it first if needed.
+pub async fn get_video_duration(video_path: &str) -> Result<f64> {
+    let media = readable_media(video_path).await?;
+    let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
+    let ffprobe_path = ffmpeg_path.with_file_name("ffprobe");
+    let (_, duration) = get_video_technical_metadata(&ffprobe_path, &media.path_str()).await?;
+    Ok(duration)
+}
+
 // Helper function to get fps and duration
 async fn get_video_technical_metadata(ffprobe_path: &Path, video_path: &str) -> Result<(f64, f64)> {
     let output = Command::new(ffprobe_path)
@@ -555,6 +569,10 @@ impl VideoMetadataOverride {
 }
 
 pub async fn extract_frame_from_video(file_path: &str, offset_index: i64) -> Result<String> {
+    // encrypted recordings are decrypted to a temporary file for ffmpeg
+    let media = readable_media(file_path).await?;
+    let media_path = media.path_str();
+    let file_path = media_path.as_str();
     let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
 
     let source_fps = match get_video_fps(&ffmpeg_path, file_path).await {
@@ -659,6 +677,10 @@ pub async fn extract_high_quality_frame(
     offset_index: i64,
     output_dir: &Path,
 ) -> Result<String> {
+    // encrypted recordings are decrypted to a temporary file for ffmpeg
+    let media = readable_media(file_path).await?;
+    let media_path = media.path_str();
+    let file_path = media_path.as_str();
     let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
 
     let source_fps = match get_video_fps(&ffmpeg_path, file_path).await {
@@ -0,0 +1,212 @@
+//! Keeps the custom vocabulary of the transcription engines up to date: the
+//! terms of every app, and those of the app focused on screen while the
+//! audio is recorded.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::{Duration, Utc};
+use oasgen::{oasgen, OaSchema};
+use screenpipe_audio::audio_manager::AudioManager;
+use screenpipe_audio::transcription::vocabulary::{Vocabulary, VocabularyTerm};
+use screenpipe_db::DatabaseManager;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+use crate::server::AppState;
+
+/// How often the focused app is looked at, chunks are about 30 seconds.
+pub const REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+/// Time looked back at for the focused app.
+const FOCUS_WINDOW: Duration = Duration::minutes(1);
+/// Frames looked at in `FOCUS_WINDOW`, a few monitors at 1 fps.
+const MAX_FRAMES: u32 = 500;
+
+/// App of the latest focused frame in the last `FOCUS_WINDOW`.
+pub async fn focused_app(db: &DatabaseManager) -> Result<Option<String>> {
+    let now = Utc::now();
+    let frames = db
+        .get_export_frames(now - FOCUS_WINDOW, now, None, None, MAX_FRAMES, 0)
+        .await?;
+    Ok(frames
+        .into_iter()
+        .rev()
+        .find(|frame| frame.focused && frame.app_name.is_some())
+        .and_then(|frame| frame.app_name))
+}
+
+/// Terms used while `app_name` is focused, or only those of every app.
+pub async fn load_vocabulary(db: &DatabaseManager, app_name: Option<&str>) -> Result<Vocabulary> {
+    // no app is named "", only the terms of every app match it
+    let entries = db
+        .list_vocabulary_terms(Some(app_name.unwrap_or("")))
+        .await?;
+    Ok(Vocabulary::new(
+        entries
+            .into_iter()
+            .map(|entry| VocabularyTerm {
+                term: entry.term,
+                sounds_like: entry.sounds_like,
+            })
+            .collect(),
+    ))
+}
+
+/// Gives the audio manager the terms of the app focused now.
+pub async fn refresh_vocabulary(db: &DatabaseManager, audio_manager: &AudioManager) -> Result<()> {
+    let app_name = focused_app(db).await?;
+    audio_manager.set_vocabulary(load_vocabulary(db, app_name.as_deref()).await?);
+    Ok(())
+}
+
+/// Keeps refreshing the vocabulary until a shutdown is signalled.
+pub async fn run_vocabulary_updater(
+    db: Arc<DatabaseManager>,
+    audio_manager: Arc<AudioManager>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!("starting custom vocabulary updates");
+    let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Err(e) = refresh_vocabulary(&db, &audio_manager).await {
+                    error!("failed to refresh custom vocabulary: {}", e);
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                info!("received shutdown signal, stopping custom vocabulary updates");
+                break;
+            }
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct VocabularyQuery {
+    /// Lists the terms used while this app is focused instead of all of them
+    #[serde(default)]
+    app_name: Option<String>,
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct VocabularyTermRequest {
+    term: String,
+    /// What the engines hear instead, e.g. `screen pipe` for `screenpipe`
+    #[serde(default)]
+    sounds_like: Option<String>,
+    /// Only used while this app is focused
+    #[serde(default)]
+    app_name: Option<String>,
+}
+
+/// New terms are used from the next chunk transcribed.
+async fn apply_vocabulary(state: &AppState) {
+    if let Err(e) = refresh_vocabulary(&state.db, &state.audio_manager).await {
+        error!("failed to refresh custom vocabulary: {}", e);
+    }
+}
+
+#[oasgen]
+pub(crate) async fn list_vocabulary_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<VocabularyQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let terms = state
+        .db
+        .list_vocabulary_terms(query.app_name.as_deref())
+        .await
+        .map_err(|e| {
+            error!("failed to list vocabulary: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to list vocabulary: {}", e), "success": false}),
+                ),
+            )
+        })?;
+    Ok(JsonResponse(json!({
+        "data": terms,
+        "success": true
+    })))
+}
+
+/// Adds a term to the custom vocabulary of the transcription engines, for
+/// every app or only while `app_name` is focused.
+#[oasgen]
+pub(crate) async fn add_vocabulary_term_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(request): JsonResponse<VocabularyTermRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let term = request.term.trim();
+    if term.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "term must not be empty", "success": false})),
+        ));
+    }
+    let non_empty = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+
+    let entry = state
+        .db
+        .insert_vocabulary_term(
+            term,
+            non_empty(&request.sounds_like).as_deref(),
+            non_empty(&request.app_name).as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            error!("failed to add vocabulary term: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to add vocabulary term: {}", e), "success": false}),
+                ),
+            )
+        })?;
+    apply_vocabulary(&state).await;
+    Ok(JsonResponse(json!({
+        "data": entry,
+        "success": true
+    })))
+}
+
+#[oasgen]
+pub(crate) async fn delete_vocabulary_term_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.delete_vocabulary_term(id).await {
+        Ok(true) => {
+            apply_vocabulary(&state).await;
+            Ok(JsonResponse(json!({"success": true})))
+        }
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": format!("no vocabulary term {}", id), "success": false})),
+        )),
+        Err(e) => {
+            error!("failed to delete vocabulary term {}: {}", id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(
+                    json!({"error": format!("failed to delete vocabulary term: {}", e), "success": false}),
+                ),
+            ))
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//! Restarts screen capture, OCR and audio recording loops that stopped making
+//! progress. A hung loop keeps screenpipe running without recording anything
+//! until someone notices, the watchdog notices first. Each restart is stored
+//! in the database and the latest ones are listed by `/health`.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use screenpipe_audio::audio_manager::AudioManager;
+use screenpipe_core::idle::is_idle;
+use screenpipe_core::metrics::OCR_QUEUE_DEPTH;
+use screenpipe_db::{DatabaseManager, WatchdogIncident};
+use tokio::sync::{broadcast, Notify};
+use tracing::{error, info, warn};
+
+/// How often the loops are checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Incidents kept in memory for `/health`.
+const RECENT_INCIDENTS: usize = 50;
+/// How far back `/health` lists incidents.
+const RECENT_INCIDENTS_WINDOW: chrono::Duration = chrono::Duration::hours(1);
+
+/// Restart requests of the pipeline of each monitor, see
+/// `monitor_restart_signal`.
+static MONITOR_RESTARTS: Lazy<Mutex<HashMap<u32, Arc<Notify>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// When each monitor's pipeline last took a frame off its OCR queue.
+static OCR_PROGRESS: Lazy<Mutex<HashMap<u32, Instant>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static INCIDENTS: Lazy<Mutex<VecDeque<WatchdogIncident>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// Notified when the pipeline of a monitor, its capture and the writes of
+/// its OCR results, has to be restarted.
+pub fn monitor_restart_signal(monitor_id: u32) -> Arc<Notify> {
+    MONITOR_RESTARTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(monitor_id)
+        .or_default()
+        .clone()
+}
+
+/// Records that the pipeline of a monitor took a frame off its OCR queue,
+/// or started.
+pub fn record_ocr_progress(monitor_id: u32) {
+    OCR_PROGRESS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(monitor_id, Instant::now());
+}
+
+/// Incidents of the last hour, newest first.
+pub fn recent_incidents() -> Vec<WatchdogIncident> {
+    let since = Utc::now() - RECENT_INCIDENTS_WINDOW;
+    INCIDENTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .rev()
+        .filter(|incident| incident.timestamp >= since)
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Subsystem {
+    /// Screen capture of a monitor
+    Vision,
+    /// Writes of a monitor's OCR results
+    Ocr,
+    /// Recording of an audio device
+    Audio,
+}
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subsystem::Vision => write!(f, "vision"),
+            Subsystem::Ocr => write!(f, "ocr"),
+            Subsystem::Audio => write!(f, "audio"),
+        }
+    }
+}
+
+/// A loop the watchdog checks: a subsystem for a monitor id or an audio
+/// device name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LoopId {
+    pub subsystem: Subsystem,
+    pub target: String,
+}
+
+/// Finds the loops without progress for longer than the timeout. A loop
+/// found stalled gets as long again to make progress after its restart.
+pub struct StallDetector {
+    timeout: Duration,
+    restarted_at: HashMap<LoopId, Instant>,
+}
+
+impl StallDetector {
+    pub fn new(timeout: Duration) -> Self {
+        StallDetector {
+            timeout,
+            restarted_at: HashMap::new(),
+        }
+    }
+
+    /// The loops of `idle_for`, how long each went without progress, that
+    /// are stalled at `now`.
+    pub fn stalled(
+        &mut self,
+        idle_for: Vec<(LoopId, Duration)>,
+        now: Instant,
+    ) -> Vec<(LoopId, Duration)> {
+        let timeout = self.timeout;
+        self.restarted_at
+            .retain(|_, restarted_at| now.duration_since(*restarted_at) < timeout);
+        let stalled: Vec<(LoopId, Duration)> = idle_for
+            .into_iter()
+            .filter(|(id, idle_for)| *idle_for > timeout && !self.restarted_at.contains_key(id))
+            .collect();
+        for (id, _) in &stalled {
+            self.restarted_at.insert(id.clone(), now);
+        }
+        stalled
+    }
+}
+
+pub struct Watchdog {
+    db: Arc<DatabaseManager>,
+    audio_manager: Arc<AudioManager>,
+    vision_disabled: bool,
+    audio_disabled: bool,
+    detector: StallDetector,
+    /// When the user was last away or the computer asleep, loops get the
+    /// timeout from then to make progress again
+    paused_at: Option<Instant>,
+}
+
+impl Watchdog {
+    pub fn new(
+        db: Arc<DatabaseManager>,
+        audio_manager: Arc<AudioManager>,
+        timeout: Duration,
+        vision_disabled: bool,
+        audio_disabled: bool,
+    ) -> Self {
+        Watchdog {
+            db,
+            audio_manager,
+            vision_disabled,
+            audio_disabled,
+            detector: StallDetector::new(timeout),
+            paused_at: None,
+        }
+    }
+
+    /// Checks the loops every few seconds until a shutdown is signalled.
+    pub async fn run(mut self, mut shutdown_rx: broadcast::Receiver<()>) {
+        info!(
+            "starting watchdog, loops are restarted after {}s without progress",
+            self.detector.timeout.as_secs()
+        );
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        let mut last_check = SystemTime::now();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown_rx.recv() => {
+                    info!("received shutdown signal, stopping watchdog");
+                    break;
+                }
+            }
+
+            // a check this late means the computer was asleep
+            let asleep = last_check.elapsed().unwrap_or_default() > CHECK_INTERVAL * 3;
+            last_check = SystemTime::now();
+            if asleep || is_idle() {
+                self.paused_at = Some(Instant::now());
+            }
+
+            let idle_for = self.idle_for();
+            for (id, stalled_for) in self.detector.stalled(idle_for, Instant::now()) {
+                warn!(
+                    "{} loop of {} made no progress for {}s, restarting it",
+                    id.subsystem,
+                    id.target,
+                    stalled_for.as_secs()
+                );
+                let result = self.restart(&id).await;
+                if let Err(e) = &result {
+                    error!(
+                        "failed to restart {} loop of {}: {}",
+                        id.subsystem, id.target, e
+                    );
+                }
+                self.record_incident(&id, stalled_for, result.err()).await;
+            }
+        }
+    }
+
+    /// How long each loop being recorded went without progress, since the
+    /// last pause at most.
+    fn idle_for(&self) -> Vec<(LoopId, Duration)> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut idle_for = Vec::new();
+
+        if !self.vision_disabled {
+            for (monitor_id, last_capture) in screenpipe_vision::core::monitor_capture_times() {
+                idle_for.push((
+                    LoopId {
+                        subsystem: Subsystem::Vision,
+                        target: monitor_id.to_string(),
+                    },
+                    Duration::from_secs(now.saturating_sub(last_capture)),
+                ));
+            }
+
+            let progress = OCR_PROGRESS
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone();
+            for (labels, depth) in OCR_QUEUE_DEPTH.metrics() {
+                let Some(monitor_id) = labels.first().and_then(|id| id.parse::<u32>().ok()) else {
+                    continue;
+                };
+                // an empty queue has nothing to make progress on
+                if depth.get() < 1.0 {
+                    continue;
+                }
+                if let Some(last_progress) = progress.get(&monitor_id) {
+                    idle_for.push((
+                        LoopId {
+                            subsystem: Subsystem::Ocr,
+                            target: monitor_id.to_string(),
+                        },
+                        last_progress.elapsed(),
+                    ));
+                }
+            }
+        }
+
+        if !self.audio_disabled {
+            for device in self.audio_manager.current_devices() {
+                let device = device.to_string();
+                let last_capture = screenpipe_audio::core::get_device_capture_time(&device);
+                idle_for.push((
+                    LoopId {
+                        subsystem: Subsystem::Audio,
+                        target: device,
+                    },
+                    Duration::from_secs(now.saturating_sub(last_capture)),
+                ));
+            }
+        }
+
+        let since_pause = self.paused_at.map_or(Duration::MAX, |at| at.elapsed());
+        for (_, idle_for) in &mut idle_for {
+            *idle_for = (*idle_for).min(since_pause);
+        }
+        idle_for
+    }
+
+    async fn restart(&self, id: &LoopId) -> anyhow::Result<()> {
+        match id.subsystem {
+            Subsystem::Vision | Subsystem::Ocr => {
+                let monitor_id = id.target.parse::<u32>()?;
+                monitor_restart_signal(monitor_id).notify_one();
+                Ok(())
+            }
+            Subsystem::Audio => self.audio_manager.restart_device(&id.target).await,
+        }
+    }
+
+    async fn record_incident(
+        &self,
+        id: &LoopId,
+        stalled_for: Duration,
+        error: Option<anyhow::Error>,
+    ) {
+        let timestamp = Utc::now();
+        let subsystem = id.subsystem.to_string();
+        let stalled_secs = stalled_for.as_secs() as i64;
+        let error = error.map(|e| e.to_string());
+        let restarted = error.is_none();
+        let incident_id = match self
+            .db
+            .insert_watchdog_incident(
+                timestamp,
+                &subsystem,
+                &id.target,
+                stalled_secs,
+                restarted,
+                error.as_deref(),
+            )
+            .await
+        {
+            Ok(incident_id) => incident_id,
+            Err(e) => {
+                error!("failed to store watchdog incident: {}", e);
+                0
+            }
+        };
+
+        let mut incidents = INCIDENTS.lock().unwrap_or_else(|e| e.into_inner());
+        if incidents.len() == RECENT_INCIDENTS {
+            incidents.pop_front();
+        }
+        incidents.push_back(WatchdogIncident {
+            id: incident_id,
+            timestamp,
+            subsystem,
+            target: id.target.clone(),
+            stalled_secs,
+            restarted,
+            error,
+        });
+    }
+}
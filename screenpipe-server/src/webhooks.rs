@@ -0,0 +1,443 @@
+//! Webhooks registered through `/webhooks`: events of the event bus, like
+//! new transcripts, app switches, health changes and saved search matches,
+//! are posted to the urls whose filters they pass.
+//!
+//! Payloads are signed with the webhook's secret, in the
+//! `X-Screenpipe-Signature` header as `sha256=<hex HMAC-SHA256 of the body>`.
+//! Failed deliveries are retried a few times with backoff, then kept as dead
+//! letters that can be retried through `/webhooks/dead-letters/:id/retry`.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json as JsonResponse,
+};
+use chrono::Utc;
+use futures::StreamExt;
+use hmac::{Hmac, Mac};
+use oasgen::{oasgen, OaSchema};
+use rand::{rngs::OsRng, RngCore};
+use screenpipe_db::{DatabaseManager, Webhook, WebhookDeadLetter};
+use screenpipe_events::{
+    subscribe_to_all_events, Event, APP_SWITCHED_EVENT, AUDIO_DEVICE_SWITCHED_EVENT,
+    FRAME_INDEXED_EVENT, HEALTH_CHANGED_EVENT, SAVED_SEARCH_MATCHED_EVENT,
+    TRANSCRIPT_INDEXED_EVENT,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, warn};
+
+use crate::server::{AppState, PaginationQuery};
+
+/// Events webhooks can subscribe to.
+pub const WEBHOOK_EVENT_TYPES: [&str; 6] = [
+    FRAME_INDEXED_EVENT,
+    TRANSCRIPT_INDEXED_EVENT,
+    APP_SWITCHED_EVENT,
+    HEALTH_CHANGED_EVENT,
+    SAVED_SEARCH_MATCHED_EVENT,
+    AUDIO_DEVICE_SWITCHED_EVENT,
+];
+
+const SECRET_PREFIX: &str = "whsec_";
+const SECRET_BYTES: usize = 32;
+
+/// Attempts of a delivery before it's dead-lettered.
+const DELIVERY_ATTEMPTS: u32 = 4;
+/// Wait before the first retry, doubled before each of the next ones.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long the webhooks are cached between two reads of the database.
+const WEBHOOK_RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", SECRET_PREFIX, hex)
+}
+
+/// `X-Screenpipe-Signature` of `body`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any size");
+    mac.update(body);
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("sha256={}", hex)
+}
+
+/// Whether `webhook` subscribed to the event `name` with `data`. The keyword
+/// is looked for in the text fields of the event, the app in its
+/// `app_name`.
+pub fn accepts(webhook: &Webhook, name: &str, data: &Value) -> bool {
+    if !webhook.events.split(',').any(|event| event.trim() == name) {
+        return false;
+    }
+    if let Some(app_name) = &webhook.app_name {
+        let app_matches = data
+            .get("app_name")
+            .and_then(Value::as_str)
+            .is_some_and(|app| app.eq_ignore_ascii_case(app_name));
+        if !app_matches {
+            return false;
+        }
+    }
+    if let Some(keyword) = &webhook.keyword {
+        let keyword = keyword.to_lowercase();
+        let Some(fields) = data.as_object() else {
+            return false;
+        };
+        return fields.values().any(|value| {
+            value
+                .as_str()
+                .is_some_and(|text| text.to_lowercase().contains(&keyword))
+        });
+    }
+    true
+}
+
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    db: Arc<DatabaseManager>,
+    client: reqwest::Client,
+    retry_delay: Duration,
+}
+
+impl WebhookDispatcher {
+    pub fn new(db: Arc<DatabaseManager>) -> Self {
+        WebhookDispatcher {
+            db,
+            client: reqwest::Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            retry_delay: RETRY_DELAY,
+        }
+    }
+
+    pub fn with_retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    async fn post(&self, webhook: &Webhook, event: &str, body: &str) -> Result<()> {
+        self.client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Screenpipe-Event", event)
+            .header(
+                "X-Screenpipe-Signature",
+                sign(&webhook.secret, body.as_bytes()),
+            )
+            .body(body.to_string())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Posts an event to `webhook`, retrying with backoff and keeping it as
+    /// a dead letter when every attempt failed. Returns whether it was
+    /// delivered.
+    pub async fn deliver(&self, webhook: &Webhook, event: &Event) -> Result<bool> {
+        let body = json!({
+            "event": event.name,
+            "webhook_id": webhook.id,
+            "timestamp": Utc::now(),
+            "data": event.data,
+        })
+        .to_string();
+
+        let mut delay = self.retry_delay;
+        let mut last_error = String::new();
+        for attempt in 1..=DELIVERY_ATTEMPTS {
+            match self.post(webhook, &event.name, &body).await {
+                Ok(()) => return Ok(true),
+                Err(e) => {
+                    debug!(
+                        "attempt {} to post {} to webhook {} failed: {}",
+                        attempt, event.name, webhook.id, e
+                    );
+                    last_error = e.to_string();
+                }
+            }
+            if attempt < DELIVERY_ATTEMPTS {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+
+        warn!(
+            "failed to post {} to webhook {}, dead-lettering it: {}",
+            event.name, webhook.id, last_error
+        );
+        self.db
+            .insert_webhook_dead_letter(
+                webhook.id,
+                &event.name,
+                &body,
+                &last_error,
+                DELIVERY_ATTEMPTS as i64,
+            )
+            .await?;
+        Ok(false)
+    }
+
+    /// Posts a dead letter again, once. It's deleted when delivered.
+    pub async fn retry_dead_letter(&self, dead_letter: &WebhookDeadLetter) -> Result<bool> {
+        let webhook = self
+            .db
+            .get_webhook(dead_letter.webhook_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("webhook {} was deleted", dead_letter.webhook_id))?;
+        match self
+            .post(&webhook, &dead_letter.event, &dead_letter.payload)
+            .await
+        {
+            Ok(()) => {
+                self.db.delete_webhook_dead_letter(dead_letter.id).await?;
+                Ok(true)
+            }
+            Err(e) => {
+                self.db
+                    .update_webhook_dead_letter(
+                        dead_letter.id,
+                        &e.to_string(),
+                        dead_letter.attempts + 1,
+                    )
+                    .await?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Posts the events of the event bus to the webhooks accepting them
+    /// until shutdown.
+    pub async fn run(self, mut shutdown_rx: broadcast::Receiver<()>) {
+        info!("dispatching events to webhooks");
+        let mut events = subscribe_to_all_events();
+        let mut webhooks: Vec<Webhook> = Vec::new();
+        let mut loaded_at: Option<Instant> = None;
+
+        loop {
+            let event = tokio::select! {
+                event = events.next() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+                _ = shutdown_rx.recv() => {
+                    info!("received shutdown signal, stopping webhooks");
+                    break;
+                }
+            };
+            if !WEBHOOK_EVENT_TYPES.contains(&event.name.as_str()) {
+                continue;
+            }
+
+            if loaded_at.is_none_or(|at| at.elapsed() > WEBHOOK_RELOAD_INTERVAL) {
+                match self.db.list_webhooks().await {
+                    Ok(loaded) => {
+                        webhooks = loaded;
+                        loaded_at = Some(Instant::now());
+                    }
+                    Err(e) => error!("failed to load webhooks: {}", e),
+                }
+            }
+
+            for webhook in &webhooks {
+                if !accepts(webhook, &event.name, &event.data) {
+                    continue;
+                }
+                // a slow or failing url doesn't hold up the others
+                let dispatcher = self.clone();
+                let webhook = webhook.clone();
+                let event = event.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = dispatcher.deliver(&webhook, &event).await {
+                        error!("failed to deliver to webhook {}: {}", webhook.id, e);
+                    }
+                });
+            }
+        }
+    }
+}
+
+#[derive(OaSchema, Deserialize)]
+pub(crate) struct WebhookRequest {
+    url: String,
+    /// Names of the events posted, see `webhooks::WEBHOOK_EVENT_TYPES`
+    events: Vec<String>,
+    /// Only events with a text field containing this are posted
+    #[serde(default)]
+    keyword: Option<String>,
+    /// Only events of this app are posted
+    #[serde(default)]
+    app_name: Option<String>,
+}
+
+fn webhook_error(status: StatusCode, error: String) -> (StatusCode, JsonResponse<Value>) {
+    (
+        status,
+        JsonResponse(json!({"error": error, "success": false})),
+    )
+}
+
+#[oasgen]
+pub(crate) async fn list_webhooks_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let webhooks = state.db.list_webhooks().await.map_err(|e| {
+        error!("failed to list webhooks: {}", e);
+        webhook_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to list webhooks: {}", e),
+        )
+    })?;
+    Ok(JsonResponse(json!({
+        "data": webhooks,
+        "success": true
+    })))
+}
+
+/// Registers a url events are posted to. The response has the secret
+/// payloads are signed with.
+#[oasgen]
+pub(crate) async fn create_webhook_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(request): JsonResponse<WebhookRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    if !reqwest::Url::parse(&request.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+    {
+        return Err(webhook_error(
+            StatusCode::BAD_REQUEST,
+            "url must be an http or https url".to_string(),
+        ));
+    }
+    if request.events.is_empty() {
+        return Err(webhook_error(
+            StatusCode::BAD_REQUEST,
+            "events must not be empty".to_string(),
+        ));
+    }
+    if let Some(unknown) = request
+        .events
+        .iter()
+        .find(|event| !WEBHOOK_EVENT_TYPES.contains(&event.as_str()))
+    {
+        return Err(webhook_error(
+            StatusCode::BAD_REQUEST,
+            format!("unknown event type: {}", unknown),
+        ));
+    }
+
+    let webhook = state
+        .db
+        .insert_webhook(
+            &request.url,
+            &request.events.join(","),
+            request.keyword.as_deref().filter(|k| !k.is_empty()),
+            request.app_name.as_deref().filter(|a| !a.is_empty()),
+            &generate_secret(),
+        )
+        .await
+        .map_err(|e| {
+            error!("failed to create webhook: {}", e);
+            webhook_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to create webhook: {}", e),
+            )
+        })?;
+    Ok(JsonResponse(json!({
+        "data": webhook,
+        "success": true
+    })))
+}
+
+#[oasgen]
+pub(crate) async fn delete_webhook_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.delete_webhook(id).await {
+        Ok(true) => Ok(JsonResponse(json!({"success": true}))),
+        Ok(false) => Err(webhook_error(
+            StatusCode::NOT_FOUND,
+            format!("no webhook {}", id),
+        )),
+        Err(e) => {
+            error!("failed to delete webhook {}: {}", id, e);
+            Err(webhook_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to delete webhook: {}", e),
+            ))
+        }
+    }
+}
+
+/// Deliveries that failed after every retry, newest first.
+#[oasgen]
+pub(crate) async fn list_webhook_dead_letters_handler(
+    State(state): State<Arc<AppState>>,
+    Query(pagination): Query<PaginationQuery>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let dead_letters = state
+        .db
+        .list_webhook_dead_letters(pagination.limit)
+        .await
+        .map_err(|e| {
+            error!("failed to list webhook dead letters: {}", e);
+            webhook_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to list webhook dead letters: {}", e),
+            )
+        })?;
+    Ok(JsonResponse(json!({
+        "data": dead_letters,
+        "success": true
+    })))
+}
+
+/// Posts a dead letter again, it's deleted once delivered.
+#[oasgen]
+pub(crate) async fn retry_webhook_dead_letter_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let internal_error = |e: String| {
+        error!("failed to retry webhook dead letter {}: {}", id, e);
+        webhook_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to retry webhook dead letter: {}", e),
+        )
+    };
+    let dead_letter = state
+        .db
+        .get_webhook_dead_letter(id)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?
+        .ok_or_else(|| {
+            webhook_error(
+                StatusCode::NOT_FOUND,
+                format!("no webhook dead letter {}", id),
+            )
+        })?;
+    let delivered = WebhookDispatcher::new(state.db.clone())
+        .retry_dead_letter(&dead_letter)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    Ok(JsonResponse(json!({
+        "data": { "delivered": delivered },
+        "success": true
+    })))
+}
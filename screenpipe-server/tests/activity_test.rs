@@ -0,0 +1,226 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+
+use screenpipe_db::{AudioDevice, DeviceType, FrameEntry};
+use screenpipe_server::activity::{
+    block_start, classify_block, classify_next_block, frame_category, ActivityCategory, BLOCK,
+};
+
+mod common;
+use common::{request, setup_test_app, setup_test_db};
+
+fn frame(app_name: &str, browser_url: Option<&str>, focused: bool) -> FrameEntry {
+    FrameEntry {
+        id: 1,
+        timestamp: Utc::now(),
+        device_name: "monitor_1".to_string(),
+        app_name: Some(app_name.to_string()),
+        window_name: Some("window".to_string()),
+        browser_url: browser_url.map(str::to_string),
+        focused,
+        ocr_text: None,
+    }
+}
+
+#[test]
+fn test_frame_category() {
+    use ActivityCategory::*;
+
+    assert_eq!(frame_category(&frame("zoom.us", None, true)), Meeting);
+    assert_eq!(
+        frame_category(&frame("Microsoft Teams", None, true)),
+        Meeting
+    );
+    assert_eq!(
+        frame_category(&frame(
+            "Google Chrome",
+            Some("https://meet.google.com/abc-defg-hij"),
+            true
+        )),
+        Meeting
+    );
+    assert_eq!(frame_category(&frame("Code", None, true)), Coding);
+    assert_eq!(frame_category(&frame("iTerm2", None, true)), Coding);
+    assert_eq!(
+        frame_category(&frame(
+            "Arc",
+            Some("https://github.com/org/repo/pull/1"),
+            true
+        )),
+        Coding
+    );
+    assert_eq!(
+        frame_category(&frame("Microsoft Word", None, true)),
+        Writing
+    );
+    assert_eq!(
+        frame_category(&frame(
+            "Safari",
+            Some("https://docs.google.com/document/d/1"),
+            true
+        )),
+        Writing
+    );
+    assert_eq!(
+        frame_category(&frame(
+            "Firefox",
+            Some("https://news.ycombinator.com"),
+            true
+        )),
+        Browsing
+    );
+    // whole words only
+    assert_eq!(frame_category(&frame("Barcode Scanner", None, true)), Other);
+
+    let mut editor = frame("Unknown Editor", None, true);
+    editor.ocr_text = Some(
+        "use std::fs;\n\nfn main() {\n    let text = fs::read(\"a\");\n    println!(\"{:?}\", text);\n}\n"
+            .to_string(),
+    );
+    assert_eq!(frame_category(&editor), Coding);
+    editor.ocr_text = Some("Dear team,\nthanks\nfor\nthe\nlaunch".to_string());
+    assert_eq!(frame_category(&editor), Other);
+}
+
+#[test]
+fn test_classify_block() {
+    use ActivityCategory::*;
+
+    let frames = vec![
+        frame("Code", None, true),
+        frame("Code", None, true),
+        frame("Safari", Some("https://example.com"), true),
+        // a call on the other monitor
+        frame("zoom.us", None, false),
+    ];
+    assert_eq!(
+        classify_block(&frames, false),
+        (Coding, Some("Code".to_string()))
+    );
+    assert_eq!(
+        classify_block(&frames, true),
+        (Meeting, Some("Code".to_string()))
+    );
+
+    // ties go to the more deliberate activity
+    let frames = vec![frame("Safari", None, true), frame("Notion", None, true)];
+    assert_eq!(
+        classify_block(&frames, false),
+        (Writing, Some("Safari".to_string()))
+    );
+
+    assert_eq!(classify_block(&[], true), (Meeting, None));
+    assert_eq!(classify_block(&[], false), (Other, None));
+}
+
+#[test]
+fn test_block_start() {
+    let timestamp: DateTime<Utc> = "2025-04-22T10:07:31.500Z".parse().unwrap();
+    assert_eq!(
+        block_start(timestamp),
+        "2025-04-22T10:05:00Z".parse::<DateTime<Utc>>().unwrap()
+    );
+    assert_eq!(block_start(block_start(timestamp)), block_start(timestamp));
+}
+
+#[tokio::test]
+async fn test_activity_segments() {
+    let db = setup_test_db().await;
+    let start = block_start(Utc::now() - Duration::hours(2));
+
+    db.insert_video_chunk("test_video.mp4", "monitor_1")
+        .await
+        .unwrap();
+    for (minutes, app_name) in [(1, "Code"), (2, "Code"), (3, "Google Chrome")] {
+        db.insert_frame(
+            "monitor_1",
+            Some(start + Duration::minutes(minutes)),
+            None,
+            Some(app_name),
+            Some("window"),
+            true,
+        )
+        .await
+        .unwrap();
+    }
+    // nothing recorded for a block, then a call
+    db.insert_frame(
+        "monitor_1",
+        Some(start + Duration::minutes(12)),
+        None,
+        Some("zoom.us"),
+        Some("Zoom Meeting"),
+        true,
+    )
+    .await
+    .unwrap();
+    let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+    let transcription_id = db
+        .insert_audio_transcription(
+            audio_chunk_id,
+            "let's go over the roadmap",
+            0,
+            "",
+            &AudioDevice {
+                name: "mic".to_string(),
+                device_type: DeviceType::Input,
+            },
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    sqlx::query("UPDATE audio_transcriptions SET timestamp = ?1 WHERE id = ?2")
+        .bind(start + Duration::minutes(13))
+        .bind(transcription_id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+    let now = Utc::now();
+    let coding = classify_next_block(&db, now).await.unwrap().unwrap();
+    assert_eq!(coding.start_time, start);
+    assert_eq!(coding.end_time, start + BLOCK);
+    assert_eq!(coding.category, "coding");
+    assert_eq!(coding.app_name.as_deref(), Some("Code"));
+    assert_eq!(coding.frame_count, 3);
+    assert!(!coding.has_audio);
+
+    let meeting = classify_next_block(&db, now).await.unwrap().unwrap();
+    assert_eq!(meeting.start_time, start + Duration::minutes(10));
+    assert_eq!(meeting.category, "meeting");
+    assert!(meeting.has_audio);
+    assert!(classify_next_block(&db, now).await.unwrap().is_none());
+
+    // the block being recorded waits until it's over
+    db.insert_frame("monitor_1", Some(now), None, Some("Code"), None, true)
+        .await
+        .unwrap();
+    assert!(classify_next_block(&db, now).await.unwrap().is_none());
+
+    let app = setup_test_app(db.clone()).await;
+    let (status, body) = request(&app, "GET", "/activity/segments", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+    assert_eq!(body["data"][0]["category"], "coding");
+
+    let (_, body) = request(&app, "GET", "/activity/segments?category=meeting", None).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["app_name"], "zoom.us");
+    let (status, _) = request(&app, "GET", "/activity/segments?category=gaming", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = request(&app, "GET", "/activity/summary", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["data"],
+        json!([
+            {"category": "coding", "seconds": 300, "segment_count": 1},
+            {"category": "meeting", "seconds": 300, "segment_count": 1},
+        ])
+    );
+}
@@ -0,0 +1,107 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use serde_json::json;
+use std::sync::Arc;
+
+use screenpipe_db::OcrEngine;
+
+mod common;
+use common::{request, setup_test_app, setup_test_db};
+
+#[tokio::test]
+async fn test_annotation_api() {
+    let db = setup_test_db().await;
+    let app = setup_test_app(db.clone()).await;
+    db.insert_video_chunk("test_video.mp4", "monitor_1")
+        .await
+        .unwrap();
+    let frame_id = db
+        .insert_frame(
+            "monitor_1",
+            Some(Utc::now()),
+            None,
+            Some("Zoom"),
+            Some("call"),
+            true,
+        )
+        .await
+        .unwrap();
+    db.insert_ocr_text(
+        frame_id,
+        "quarterly review",
+        "",
+        Arc::new(OcrEngine::Tesseract),
+    )
+    .await
+    .unwrap();
+
+    let now = Utc::now();
+    for invalid in [
+        // no target
+        json!({"note": "meeting with client X"}),
+        // two targets
+        json!({"frame_id": frame_id, "audio_transcription_id": 1, "tags": ["x"]}),
+        // reversed range
+        json!({"start_time": now, "end_time": now - Duration::minutes(1), "tags": ["x"]}),
+        // nothing to attach
+        json!({"frame_id": frame_id, "note": " ", "tags": [""]}),
+        json!({"frame_id": frame_id, "tags": ["a,b"]}),
+    ] {
+        let (status, _) = request(&app, "POST", "/annotations", Some(invalid)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let (status, _) = request(
+        &app,
+        "POST",
+        "/annotations",
+        Some(json!({"frame_id": 999, "tags": ["x"]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = request(
+        &app,
+        "POST",
+        "/annotations",
+        Some(json!({
+            "frame_id": frame_id,
+            "note": "meeting with client X",
+            "tags": ["client x", " client x ", "meeting"]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let id = body["data"]["id"].as_i64().unwrap();
+    assert_eq!(body["data"]["tags"], json!(["client x", "meeting"]));
+
+    let (status, body) = request(&app, "GET", "/search?tag=meeting", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["content"]["frame_id"], frame_id);
+    assert_eq!(body["pagination"]["total"], 1);
+
+    let uri = format!("/annotations/{}", id);
+    let (status, body) = request(
+        &app,
+        "POST",
+        &uri,
+        Some(json!({"note": "bug repro", "tags": ["bug"]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["note"], "bug repro");
+    assert_eq!(body["data"]["tags"], json!(["bug"]));
+
+    let (status, body) = request(&app, "GET", "/annotations?tag=bug", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    let (_, body) = request(&app, "GET", "/search?tag=meeting", None).await;
+    assert!(body["data"].as_array().unwrap().is_empty());
+
+    let (status, _) = request(&app, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = request(&app, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = request(&app, "POST", &uri, Some(json!({"note": "gone"}))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,153 @@
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+    Router,
+};
+use tokio::sync::broadcast;
+
+use screenpipe_server::auth::{create_token, required_scope, TokenScope};
+
+mod common;
+use common::{send, setup_test_app_with, setup_test_db};
+
+async fn status(app: &Router, method: &str, uri: &str, token: Option<&str>) -> StatusCode {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    send(app, request.body(Body::empty()).unwrap()).await.0
+}
+
+#[test]
+fn test_required_scope() {
+    assert_eq!(required_scope(&Method::GET, "/health"), None);
+    // pipes authenticate with their own token
+    assert_eq!(required_scope(&Method::POST, "/bus/publish"), None);
+    assert_eq!(required_scope(&Method::PUT, "/companion/uploads/1"), None);
+    assert_eq!(
+        required_scope(&Method::GET, "/companion/devices"),
+        Some(TokenScope::Admin)
+    );
+    assert_eq!(
+        required_scope(&Method::GET, "/search"),
+        Some(TokenScope::Search)
+    );
+    assert_eq!(
+        required_scope(&Method::POST, "/graphql"),
+        Some(TokenScope::Search)
+    );
+    assert_eq!(
+        required_scope(&Method::POST, "/query"),
+        Some(TokenScope::Search)
+    );
+    assert_eq!(
+        required_scope(&Method::GET, "/pipes/list"),
+        Some(TokenScope::Pipes)
+    );
+    assert_eq!(
+        required_scope(&Method::GET, "/webhooks"),
+        Some(TokenScope::Admin)
+    );
+    assert_eq!(
+        required_scope(&Method::GET, "/admin/doctor"),
+        Some(TokenScope::Admin)
+    );
+    assert_eq!(
+        required_scope(&Method::GET, "/annotations"),
+        Some(TokenScope::Search)
+    );
+    assert_eq!(
+        required_scope(&Method::POST, "/annotations/1"),
+        Some(TokenScope::Pipes)
+    );
+    assert_eq!(
+        required_scope(&Method::POST, "/v1/chat/completions"),
+        Some(TokenScope::Pipes)
+    );
+    assert_eq!(
+        required_scope(&Method::POST, "/llm/models/load"),
+        Some(TokenScope::Admin)
+    );
+    assert_eq!(
+        required_scope(&Method::POST, "/raw_sql"),
+        Some(TokenScope::Admin)
+    );
+}
+
+#[tokio::test]
+async fn test_endpoints_need_a_token_of_the_right_scope() {
+    let db = setup_test_db().await;
+    let app = setup_test_app_with(db.clone(), |app| app.with_api_auth()).await;
+    let (search_token, _) = create_token(&db, Some("reader"), TokenScope::Search)
+        .await
+        .unwrap();
+
+    assert_ne!(
+        status(&app, "GET", "/health", None).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(&app, "GET", "/search", None).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(&app, "GET", "/search", Some("sp_wrong")).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(&app, "GET", "/search", Some(&search_token)).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(
+            &app,
+            "GET",
+            &format!("/search?token={}", search_token),
+            None
+        )
+        .await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(&app, "GET", "/pipes/list", Some(&search_token)).await,
+        StatusCode::FORBIDDEN
+    );
+
+    let tokens = db.list_api_tokens().await.unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].scope, "search");
+    assert!(tokens[0].last_used_at.is_some());
+
+    // revoked tokens stop working
+    assert!(db.delete_api_token(tokens[0].id).await.unwrap());
+    assert_eq!(
+        status(&app, "GET", "/search", Some(&search_token)).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_app_token_can_shut_down() {
+    let (shutdown_tx, mut shutdown_rx) = broadcast::channel::<()>(1);
+    let app = setup_test_app_with(setup_test_db().await, |app| {
+        app.with_api_auth()
+            .with_shutdown(shutdown_tx)
+            .with_app_token("sp_app_secret".to_string())
+    })
+    .await;
+
+    assert_eq!(
+        status(&app, "POST", "/admin/shutdown", None).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(&app, "POST", "/admin/shutdown", Some("sp_app_wrong")).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert!(shutdown_rx.try_recv().is_err());
+    assert_eq!(
+        status(&app, "POST", "/admin/shutdown", Some("sp_app_secret")).await,
+        StatusCode::OK
+    );
+    assert!(shutdown_rx.try_recv().is_ok());
+}
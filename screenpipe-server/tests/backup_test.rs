@@ -0,0 +1,73 @@
+use std::fs;
+
+use screenpipe_db::DatabaseManager;
+use screenpipe_server::backup::{
+    create_backup, list_backups, prune_backups, restore_backup, verify_backup, BackupOptions,
+};
+
+#[tokio::test]
+async fn test_backup_verify_and_restore() {
+    let data_dir = tempfile::tempdir().unwrap();
+    let backup_root = tempfile::tempdir().unwrap();
+    let db_path = data_dir.path().join("db.sqlite");
+    let db = DatabaseManager::new(db_path.to_str().unwrap())
+        .await
+        .unwrap();
+    db.insert_audio_chunk("first.mp4").await.unwrap();
+    fs::create_dir_all(data_dir.path().join("data")).unwrap();
+    fs::write(data_dir.path().join("data/first.mp4"), b"first recording").unwrap();
+
+    let options = BackupOptions {
+        include_media: true,
+        incremental: true,
+        key: None,
+    };
+    let first = create_backup(&db, data_dir.path(), backup_root.path(), &options)
+        .await
+        .unwrap();
+    assert_eq!(first.copied_files, 2);
+    assert_eq!(first.linked_files, 0);
+    assert!(first.manifest.base.is_none());
+    verify_backup(&first.path, None).await.unwrap();
+
+    // backups are named by the second they were made
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    fs::write(data_dir.path().join("data/second.mp4"), b"second recording").unwrap();
+    let second = create_backup(&db, data_dir.path(), backup_root.path(), &options)
+        .await
+        .unwrap();
+    // only the new recording is copied, with the database
+    assert_eq!(second.copied_files, 2);
+    assert_eq!(second.linked_files, 1);
+    assert_eq!(list_backups(backup_root.path()).unwrap().len(), 2);
+
+    // backups stand on their own once older ones are gone
+    assert_eq!(prune_backups(backup_root.path(), 1).unwrap(), 1);
+    verify_backup(&second.path, None).await.unwrap();
+
+    let restored_dir = tempfile::tempdir().unwrap();
+    let report = restore_backup(&second.path, restored_dir.path(), None)
+        .await
+        .unwrap();
+    assert_eq!(report.restored_media, 2);
+    assert!(report.previous_database.is_none());
+    assert_eq!(
+        fs::read(restored_dir.path().join("data/first.mp4")).unwrap(),
+        b"first recording"
+    );
+    let restored = DatabaseManager::new(restored_dir.path().join("db.sqlite").to_str().unwrap())
+        .await
+        .unwrap();
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audio_chunks")
+        .fetch_one(&restored.pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+
+    // a damaged backup is refused
+    fs::write(second.path.join("data/second.mp4"), b"second recordinG").unwrap();
+    assert!(verify_backup(&second.path, None).await.is_err());
+    assert!(restore_backup(&second.path, restored_dir.path(), None)
+        .await
+        .is_err());
+}
@@ -0,0 +1,139 @@
+use chrono::{TimeZone, Utc};
+use screenpipe_server::calendar::{calendar_data, parse_google_events, parse_ics};
+use serde_json::json;
+
+const ICS: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+UID:sprint@example.com\r
+SUMMARY:Sprint planning\\, Q3\r
+DTSTART:20250505T090000Z\r
+DTEND:20250505T100000Z\r
+ATTENDEE;CN=\"Doe, Jane\";ROLE=REQ-PARTICIPANT:mailto:jane@example.com\r
+ATTENDEE;ROLE=OPT-PARTICIPANT:mailto:bob@exam\r
+ ple.com\r
+LOCATION:Room 4\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+SUMMARY:Reminder\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:standup@example.com\r
+RECURRENCE-ID:20250506T083000Z\r
+SUMMARY:Standup\r
+DTSTART:20250506T083000Z\r
+DURATION:PT15M\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:holiday@example.com\r
+SUMMARY:Holiday\r
+DTSTART;VALUE=DATE:20250507\r
+DTEND;VALUE=DATE:20250508\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:cancelled@example.com\r
+SUMMARY:Retro\r
+STATUS:CANCELLED\r
+DTSTART:20250508T090000Z\r
+DTEND:20250508T100000Z\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+#[test]
+fn test_parse_ics() {
+    let events = parse_ics(ICS);
+    assert_eq!(events.len(), 2);
+
+    let sprint = &events[0];
+    assert_eq!(sprint.uid, "sprint@example.com");
+    assert_eq!(sprint.title, "Sprint planning, Q3");
+    assert_eq!(sprint.attendees, vec!["Doe, Jane", "bob@example.com"]);
+    assert_eq!(sprint.location.as_deref(), Some("Room 4"));
+    assert_eq!(
+        sprint.start_time,
+        Utc.with_ymd_and_hms(2025, 5, 5, 9, 0, 0).unwrap()
+    );
+    assert_eq!(
+        sprint.end_time,
+        Utc.with_ymd_and_hms(2025, 5, 5, 10, 0, 0).unwrap()
+    );
+
+    let standup = &events[1];
+    assert_eq!(standup.uid, "standup@example.com/20250506T083000Z");
+    assert_eq!(
+        standup.end_time,
+        Utc.with_ymd_and_hms(2025, 5, 6, 8, 45, 0).unwrap()
+    );
+}
+
+#[test]
+fn test_calendar_data() {
+    let xml = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/calendars/me/personal/sprint.ics</d:href>
+    <d:propstat>
+      <d:prop>
+        <cal:calendar-data>{}</cal:calendar-data>
+      </d:prop>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/calendars/me/personal/empty.ics</d:href>
+    <d:propstat>
+      <d:prop>
+        <cal:calendar-data/>
+      </d:prop>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#,
+        ICS.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('\r', "&#13;")
+    );
+    let objects = calendar_data(&xml);
+    assert_eq!(objects.len(), 1);
+    assert_eq!(parse_ics(&objects[0]).len(), 2);
+}
+
+#[test]
+fn test_parse_google_events() {
+    let page = json!({
+        "items": [
+            {
+                "id": "abc_20250505T090000Z",
+                "status": "confirmed",
+                "summary": "Sprint planning",
+                "start": { "dateTime": "2025-05-05T11:00:00+02:00" },
+                "end": { "dateTime": "2025-05-05T12:00:00+02:00" },
+                "attendees": [
+                    { "email": "jane@example.com", "displayName": "Jane Doe" },
+                    { "email": "bob@example.com" },
+                    { "email": "room-4@resource.calendar.google.com", "resource": true }
+                ]
+            },
+            {
+                "id": "holiday",
+                "status": "confirmed",
+                "summary": "Holiday",
+                "start": { "date": "2025-05-07" },
+                "end": { "date": "2025-05-08" }
+            },
+            {
+                "id": "retro",
+                "status": "cancelled"
+            }
+        ]
+    });
+    let events = parse_google_events(&page);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].uid, "abc_20250505T090000Z");
+    assert_eq!(events[0].attendees, vec!["Jane Doe", "bob@example.com"]);
+    assert_eq!(
+        events[0].start_time,
+        Utc.with_ymd_and_hms(2025, 5, 5, 9, 0, 0).unwrap()
+    );
+}
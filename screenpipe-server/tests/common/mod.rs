@@ -0,0 +1,117 @@
+//! Server fixtures shared by the integration tests. Every test binary only uses
+//! some of them.
+#![allow(dead_code)]
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    Router,
+};
+use screenpipe_audio::audio_manager::AudioManagerBuilder;
+use screenpipe_db::DatabaseManager;
+use screenpipe_server::{PipeManager, SCServer};
+use serde_json::Value;
+use std::{net::SocketAddr, path::Path, sync::Arc};
+use tower::ServiceExt;
+
+pub async fn setup_test_db() -> Arc<DatabaseManager> {
+    Arc::new(DatabaseManager::new("sqlite::memory:").await.unwrap())
+}
+
+/// A server on `db` with its pipes in `screenpipe_dir`, capturing nothing.
+pub async fn test_server(
+    db: Arc<DatabaseManager>,
+    addr: SocketAddr,
+    screenpipe_dir: &Path,
+) -> SCServer {
+    let audio_manager = Arc::new(
+        AudioManagerBuilder::new()
+            .output_path("/tmp/screenpipe".into())
+            .build(db.clone())
+            .await
+            .unwrap(),
+    );
+
+    SCServer::new(
+        db,
+        addr,
+        screenpipe_dir.to_path_buf(),
+        Arc::new(PipeManager::new(screenpipe_dir.to_path_buf())),
+        false,
+        false,
+        false,
+        audio_manager,
+        true,
+    )
+}
+
+pub async fn setup_test_app(db: Arc<DatabaseManager>) -> Router {
+    setup_test_app_with(db, |app| app).await
+}
+
+/// Like [`setup_test_app`], with the optional parts of the server, e.g. the
+/// summarizer or the api auth, set by `configure`.
+pub async fn setup_test_app_with(
+    db: Arc<DatabaseManager>,
+    configure: impl FnOnce(SCServer) -> SCServer,
+) -> Router {
+    let app = test_server(db, SocketAddr::from(([127, 0, 0, 1], 23948)), Path::new("")).await;
+    configure(app).create_router(false).await
+}
+
+/// Like [`setup_test_app`], for the pipe routes, with the pipes in `screenpipe_dir`.
+pub async fn setup_pipes_app(screenpipe_dir: &Path) -> Router {
+    let app = test_server(
+        setup_test_db().await,
+        SocketAddr::from(([127, 0, 0, 1], 23948)),
+        screenpipe_dir,
+    )
+    .await;
+    app.create_router(false).await
+}
+
+/// Serves a server on a free local port, for the tests that need a real
+/// connection, e.g. websockets or streamed uploads.
+pub async fn start_server(screenpipe_dir: &Path) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let router = test_server(setup_test_db().await, addr, screenpipe_dir)
+        .await
+        .create_router(false)
+        .await;
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    addr
+}
+
+/// Sends `request` to `app`, returning its status and its JSON body, or null
+/// when the body isn't JSON.
+pub async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+pub async fn request(
+    app: &Router,
+    method: impl AsRef<str>,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method.as_ref())
+        .uri(uri)
+        .header("Content-Type", "application/json");
+    let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+    send(app, request.body(body).unwrap()).await
+}
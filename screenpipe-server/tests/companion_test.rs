@@ -0,0 +1,154 @@
+use std::net::SocketAddr;
+
+use screenpipe_server::ingest::ingest_dir;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tempfile::tempdir;
+
+mod common;
+use common::start_server;
+
+async fn pair(addr: SocketAddr, name: &str) -> String {
+    let response = reqwest::Client::new()
+        .post(format!("http://{}/companion/devices", addr))
+        .json(&json!({"name": name}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    body["data"]["token"].as_str().unwrap().to_string()
+}
+
+async fn start_upload(addr: SocketAddr, token: &str, file: &[u8]) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("http://{}/companion/uploads", addr))
+        .bearer_auth(token)
+        .json(&json!({
+            "kind": "image",
+            "content_type": "image/png",
+            "size": file.len(),
+            "sha256": format!("{:x}", Sha256::digest(file)),
+            "timestamp": "2024-05-01T10:00:00Z",
+            "metadata": {"app": "camera"}
+        }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn put_chunk(
+    addr: SocketAddr,
+    token: &str,
+    id: &str,
+    offset: usize,
+    chunk: &[u8],
+) -> reqwest::Response {
+    reqwest::Client::new()
+        .put(format!("http://{}/companion/uploads/{}", addr, id))
+        .bearer_auth(token)
+        .header("upload-offset", offset.to_string())
+        .body(chunk.to_vec())
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_resumable_upload() {
+    let dir = tempdir().unwrap();
+    let addr = start_server(dir.path()).await;
+    let token = pair(addr, "iphone").await;
+    let file: Vec<u8> = (0..200u8).collect();
+
+    let response = start_upload(addr, "sp_unknown", &file).await;
+    assert_eq!(response.status(), 401);
+
+    let response = start_upload(addr, &token, &file).await;
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+    assert_eq!(body["data"]["status"], "uploading");
+    assert_eq!(body["data"]["offset"], 0);
+
+    let response = put_chunk(addr, &token, &id, 0, &file[..120]).await;
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["data"]["offset"], 120);
+
+    // the connection dropped: the chunk is sent again, then the upload is
+    // resumed where the server is at
+    let response = put_chunk(addr, &token, &id, 0, &file[..120]).await;
+    assert_eq!(response.status(), 409);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["offset"], 120);
+
+    let response = start_upload(addr, &token, &file).await;
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["data"]["id"], id.as_str());
+    assert_eq!(body["data"]["offset"], 120);
+
+    let response = put_chunk(addr, &token, &id, 120, &file[120..]).await;
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["data"]["status"], "complete");
+    let ingest_id = body["data"]["ingest_id"].as_str().unwrap().to_string();
+
+    let queued = ingest_dir(&dir.path().join("data").to_string_lossy());
+    assert_eq!(
+        std::fs::read(queued.join(format!("{}.png", ingest_id))).unwrap(),
+        file
+    );
+    let job: Value =
+        serde_json::from_slice(&std::fs::read(queued.join(format!("{}.json", ingest_id))).unwrap())
+            .unwrap();
+    assert_eq!(job["device_name"], "iphone");
+    assert_eq!(job["timestamp"], "2024-05-01T10:00:00Z");
+
+    // the same file from another device isn't ingested twice
+    let other = pair(addr, "ipad").await;
+    let response = start_upload(addr, &other, &file).await;
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["data"]["status"], "duplicate");
+    assert_eq!(body["data"]["ingest_id"], ingest_id.as_str());
+
+    // uploads are only visible to their device
+    let response = reqwest::Client::new()
+        .get(format!("http://{}/companion/uploads/{}", addr, id))
+        .bearer_auth(&other)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_corrupted_upload_restarts() {
+    let dir = tempdir().unwrap();
+    let addr = start_server(dir.path()).await;
+    let token = pair(addr, "pixel").await;
+    let file = vec![7u8; 64];
+
+    let response = start_upload(addr, &token, &file).await;
+    let body: Value = response.json().await.unwrap();
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+
+    let response = put_chunk(addr, &token, &id, 0, &[0u8; 65]).await;
+    assert_eq!(response.status(), 400);
+
+    let response = put_chunk(addr, &token, &id, 0, &[8u8; 64]).await;
+    assert_eq!(response.status(), 422);
+
+    let response = reqwest::Client::new()
+        .get(format!("http://{}/companion/uploads/{}", addr, id))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["data"]["status"], "uploading");
+    assert_eq!(body["data"]["offset"], 0);
+
+    let response = put_chunk(addr, &token, &id, 0, &file).await;
+    assert_eq!(response.status(), 200);
+}
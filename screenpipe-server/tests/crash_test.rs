@@ -0,0 +1,34 @@
+use std::fs;
+
+use screenpipe_server::crash::{
+    crashes_dir, list_crash_reports, write_crash_report, CrashReportStatus, CRASH_LOG_LINES,
+};
+
+#[test]
+fn test_crash_report_keeps_end_of_log() {
+    let screenpipe_dir = tempfile::tempdir().unwrap();
+    let log: String = (0..1000).map(|i| format!("line {}\n", i)).collect();
+    fs::write(screenpipe_dir.path().join("screenpipe.2025-04-14.log"), log).unwrap();
+    assert!(list_crash_reports(screenpipe_dir.path())
+        .unwrap()
+        .is_empty());
+
+    let dir = crashes_dir(screenpipe_dir.path()).join("2025-04-14T10-00-00");
+    fs::create_dir_all(&dir).unwrap();
+    let minidump = dir.join("minidump.dmp");
+    // cut short, the report still has the log
+    fs::write(&minidump, b"MDMP").unwrap();
+
+    let report = write_crash_report(screenpipe_dir.path(), &minidump).unwrap();
+    assert_eq!(report.id, "2025-04-14T10-00-00");
+    assert_eq!(report.status, CrashReportStatus::Pending);
+    assert!(report.reason.is_none());
+
+    let log = fs::read_to_string(dir.join("screenpipe.log")).unwrap();
+    assert_eq!(log.lines().count(), CRASH_LOG_LINES);
+    assert_eq!(log.lines().last(), Some("line 999"));
+
+    let reports = list_crash_reports(screenpipe_dir.path()).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].id, report.id);
+}
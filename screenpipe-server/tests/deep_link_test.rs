@@ -0,0 +1,60 @@
+use chrono::{Local, TimeZone, Utc};
+use screenpipe_server::deep_link::{frame_link, time_link, DeepLink};
+
+#[test]
+fn test_links() {
+    assert_eq!(frame_link(12345), "screenpipe://frame/12345");
+    assert_eq!(
+        time_link(Utc.with_ymd_and_hms(2024, 6, 1, 10, 0, 0).unwrap()),
+        "screenpipe://time/2024-06-01T10:00:00Z"
+    );
+}
+
+#[test]
+fn test_parse_links() {
+    assert_eq!(
+        "screenpipe://frame/12345".parse::<DeepLink>().unwrap(),
+        DeepLink::Frame(12345)
+    );
+    assert_eq!(
+        "screenpipe://time/2024-06-01T10:00:00Z/"
+            .parse::<DeepLink>()
+            .unwrap(),
+        DeepLink::Time(Utc.with_ymd_and_hms(2024, 6, 1, 10, 0, 0).unwrap())
+    );
+    // without an offset the time is local
+    assert_eq!(
+        "screenpipe://time/2024-06-01T10:00"
+            .parse::<DeepLink>()
+            .unwrap(),
+        DeepLink::Time(
+            Local
+                .with_ymd_and_hms(2024, 6, 1, 10, 0, 0)
+                .unwrap()
+                .with_timezone(&Utc)
+        )
+    );
+}
+
+#[test]
+fn test_links_round_trip() {
+    let link = DeepLink::Time(Utc.with_ymd_and_hms(2025, 1, 31, 23, 59, 59).unwrap());
+    assert_eq!(link.to_string().parse::<DeepLink>().unwrap(), link);
+    assert_eq!(
+        frame_link(7).parse::<DeepLink>().unwrap(),
+        DeepLink::Frame(7)
+    );
+}
+
+#[test]
+fn test_parse_invalid_links() {
+    for link in [
+        "https://screenpi.pe/frame/1",
+        "screenpipe://frame/abc",
+        "screenpipe://frame",
+        "screenpipe://time/yesterday",
+        "screenpipe://settings/1",
+    ] {
+        assert!(link.parse::<DeepLink>().is_err(), "{}", link);
+    }
+}
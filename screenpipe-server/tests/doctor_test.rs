@@ -0,0 +1,105 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use chrono::Utc;
+use screenpipe_db::{DatabaseManager, OcrEngine};
+use screenpipe_server::doctor::run_doctor;
+
+/// Makes the file look like it was last written an hour ago.
+fn age(path: &Path) {
+    std::fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(60 * 60))
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_doctor_finds_and_repairs_inconsistencies() {
+    let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let media_dir = dir.path().join("data");
+    std::fs::create_dir(&media_dir).unwrap();
+
+    let recorded = media_dir.join("recorded.mp4");
+    let lost = media_dir.join("lost.mp4");
+    std::fs::write(&recorded, b"video").unwrap();
+    age(&recorded);
+    for chunk in [&recorded, &lost] {
+        db.insert_video_chunk(&chunk.to_string_lossy(), "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame(
+                "test_device",
+                Some(Utc::now() - chrono::Duration::hours(1)),
+                None,
+                Some("mail"),
+                None,
+                true,
+            )
+            .await
+            .unwrap();
+        db.insert_ocr_text(frame_id, "invoice", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+    }
+
+    let orphaned = media_dir.join("orphaned.mp4");
+    std::fs::write(&orphaned, b"video").unwrap();
+    age(&orphaned);
+    // may still be recorded to
+    let fresh = media_dir.join("fresh.mp4");
+    std::fs::write(&fresh, b"video").unwrap();
+
+    sqlx::query(
+        "INSERT INTO ocr_text (frame_id, text, text_json, ocr_engine, text_length) VALUES (999, 'lost', '', 'Tesseract', 4)",
+    )
+    .execute(&db.pool)
+    .await
+    .unwrap();
+    sqlx::query("DELETE FROM frames_fts")
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+    let report = run_doctor(&db, dir.path(), false).await.unwrap();
+    let mut checks: Vec<&str> = report
+        .issues
+        .iter()
+        .map(|issue| issue.check.as_str())
+        .collect();
+    checks.sort();
+    assert_eq!(
+        checks,
+        [
+            "fts_index",
+            "missing_media",
+            "missing_parents",
+            "orphaned_media"
+        ]
+    );
+    assert_eq!(report.unrepaired(), 4);
+    assert!(orphaned.exists());
+
+    let report = run_doctor(&db, dir.path(), true).await.unwrap();
+    assert_eq!(report.issues.len(), 4);
+    assert_eq!(report.unrepaired(), 0);
+    assert!(!orphaned.exists());
+    assert!(fresh.exists());
+    assert!(recorded.exists());
+
+    // the lost chunk keeps its text
+    let texts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM ocr_text")
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+    assert_eq!(texts, 2);
+    assert!(run_doctor(&db, dir.path(), false)
+        .await
+        .unwrap()
+        .issues
+        .is_empty());
+}
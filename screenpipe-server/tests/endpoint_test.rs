@@ -7,7 +7,7 @@ mod tests {
     use chrono::DateTime;
     use chrono::{Duration, Utc};
     use screenpipe_audio::audio_manager::AudioManagerBuilder;
-    use screenpipe_db::{ContentType, DatabaseManager, SearchResult};
+    use screenpipe_db::{ContentType, DatabaseManager, SearchResult, SearchSort};
     use screenpipe_server::PipeManager;
     use screenpipe_server::SCServer;
     use screenpipe_server::{ContentItem, PaginatedResponse};
@@ -78,6 +78,8 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -95,6 +97,8 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -169,6 +173,282 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_search_audio_by_speaker() {
+        let (app, db) = setup_test_app().await;
+
+        let first_speaker = db.insert_speaker(&[0.1; 512]).await.unwrap();
+        let second_speaker = db.insert_speaker(&[-0.1; 512]).await.unwrap();
+
+        for (path, text, speaker_id) in [
+            ("test_audio1.wav", "first speaker", first_speaker.id),
+            ("test_audio2.wav", "second speaker", second_speaker.id),
+        ] {
+            let audio_chunk_id = db.insert_audio_chunk(path).await.unwrap();
+            db.insert_audio_transcription(
+                audio_chunk_id,
+                text,
+                0,
+                "",
+                &screenpipe_db::AudioDevice {
+                    name: "test".to_string(),
+                    device_type: screenpipe_db::DeviceType::Input,
+                },
+                Some(speaker_id),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        // both the singular and the plural parameter filter by speaker
+        for param in ["speaker_id", "speaker_ids"] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(format!(
+                            "/search?content_type=audio&{}={}",
+                            param, second_speaker.id
+                        ))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let search_response: PaginatedResponse<ContentItem> =
+                serde_json::from_slice(&body).unwrap();
+            assert_eq!(search_response.data.len(), 1);
+            if let ContentItem::Audio(audio_item) = &search_response.data[0] {
+                assert_eq!(audio_item.transcription, "second speaker");
+                assert_eq!(
+                    audio_item.speaker.as_ref().map(|s| s.id),
+                    Some(second_speaker.id)
+                );
+            } else {
+                panic!("Expected audio item");
+            }
+        }
+
+        // or by name, whatever its case
+        db.update_speaker_name(second_speaker.id, "Alice")
+            .await
+            .unwrap();
+        for (name, expected) in [("alice", 1), ("bob", 0)] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/search?content_type=audio&speaker_name={}", name))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let search_response: PaginatedResponse<ContentItem> =
+                serde_json::from_slice(&body).unwrap();
+            assert_eq!(search_response.data.len(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_context() {
+        let (app, db) = setup_test_app().await;
+
+        db.insert_video_chunk("test_video1.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, None, None, true)
+            .await
+            .unwrap();
+        db.insert_ocr_text(
+            frame_id,
+            "standup notes",
+            "",
+            Arc::new(OcrEngine::Tesseract.into()),
+        )
+        .await
+        .unwrap();
+        let audio_chunk_id = db.insert_audio_chunk("test_audio1.wav").await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "morning everyone",
+            0,
+            "",
+            &screenpipe_db::AudioDevice {
+                name: "test".to_string(),
+                device_type: screenpipe_db::DeviceType::Input,
+            },
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        for (uri, with_context) in [
+            ("/search?content_type=audio", false),
+            ("/search?content_type=audio&context_seconds=60", true),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let search_response: PaginatedResponse<ContentItem> =
+                serde_json::from_slice(&body).unwrap();
+            assert_eq!(search_response.data.len(), 1);
+            let ContentItem::Audio(audio_item) = &search_response.data[0] else {
+                panic!("Expected audio item");
+            };
+
+            match &audio_item.context {
+                Some(context) => {
+                    assert!(with_context);
+                    assert_eq!(context.frames.len(), 1);
+                    assert_eq!(context.frames[0].id, frame_id);
+                    assert_eq!(context.frames[0].ocr_text.as_deref(), Some("standup notes"));
+                    assert_eq!(context.transcripts.len(), 1);
+                    assert_eq!(context.transcripts[0].transcription, "morning everyone");
+                }
+                None => assert!(!with_context),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_cursor_and_stream() {
+        let (app, db) = setup_test_app().await;
+
+        db.insert_video_chunk("test_video1.mp4", "test_device")
+            .await
+            .unwrap();
+        for i in 0..5 {
+            let frame_id = db
+                .insert_frame("test_device", None, None, None, None, true)
+                .await
+                .unwrap();
+            db.insert_ocr_text(
+                frame_id,
+                &format!("release checklist {}", i),
+                "",
+                Arc::new(OcrEngine::Tesseract.into()),
+            )
+            .await
+            .unwrap();
+        }
+
+        let get = |uri: &str| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+
+        let mut paged = Vec::new();
+        let mut uri = "/search?q=checklist&limit=2".to_string();
+        loop {
+            let response = get(&uri).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let page: PaginatedResponse<ContentItem> = serde_json::from_slice(&body).unwrap();
+            assert_eq!(page.pagination.total, 5);
+            paged.extend(page.data.into_iter().map(|item| match item {
+                ContentItem::OCR(ocr) => ocr.frame_id,
+                _ => panic!("Expected OCR item"),
+            }));
+            match page.pagination.next_cursor {
+                Some(cursor) => uri = format!("/search?q=checklist&limit=2&cursor={}", cursor),
+                None => break,
+            }
+        }
+        assert_eq!(paged.len(), 5);
+
+        let response = get("/search/stream?q=checklist").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/x-ndjson"
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let streamed: Vec<i64> = String::from_utf8(body.to_vec())
+            .unwrap()
+            .lines()
+            .map(|line| match serde_json::from_str(line).unwrap() {
+                ContentItem::OCR(ocr) => ocr.frame_id,
+                _ => panic!("Expected OCR item"),
+            })
+            .collect();
+        assert_eq!(streamed, paged);
+
+        for uri in [
+            "/search?q=checklist&cursor=nonsense",
+            "/search?q=checklist&sort=relevance&cursor=2024-06-01T10:00:00Z~ocr~1",
+            "/search/stream?q=checklist&sort=relevance",
+        ] {
+            let response = get(uri).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_focus_endpoints() {
+        let (app, db) = setup_test_app().await;
+        let start = DateTime::parse_from_rfc3339("2025-04-22T09:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        for (seconds, app_name) in [(0, "Code"), (300, "Slack"), (330, "Code"), (420, "Code")] {
+            db.record_focus(
+                app_name,
+                "",
+                start + Duration::seconds(seconds),
+                std::time::Duration::from_secs(120),
+            )
+            .await
+            .unwrap();
+        }
+
+        let get = |uri: &str| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+        let range = "start_time=2025-04-22T00:00:00Z&end_time=2025-04-23T00:00:00Z";
+        let data = |uri: String| async move {
+            let response = get(&uri).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["data"].clone()
+        };
+
+        let events = data(format!("/focus/events?{}", range)).await;
+        assert_eq!(events.as_array().unwrap().len(), 3);
+        let streaks = data(format!("/focus/streaks?{}&limit=1", range)).await;
+        assert_eq!(streaks[0]["app_name"], "Code");
+        assert_eq!(streaks[0]["seconds"], 300);
+        let switches = data(format!("/focus/switches?{}&utc_offset_minutes=0", range)).await;
+        assert_eq!(switches[0]["switches"], 2);
+        let interruptions = data(format!("/focus/interruptions?{}", range)).await;
+        assert_eq!(interruptions[0]["app_name"], "Slack");
+        assert_eq!(interruptions[0]["interruptions"], 1);
+
+        let response =
+            get("/focus/streaks?start_time=2025-04-23T00:00:00Z&end_time=2025-04-22T00:00:00Z")
+                .await
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_count_search_results() {
@@ -219,6 +499,8 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -235,6 +517,8 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -254,6 +538,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -274,6 +561,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -294,6 +584,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -314,6 +607,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -334,6 +630,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -354,6 +653,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -374,6 +676,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -431,6 +736,8 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -458,6 +765,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -479,6 +790,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -501,6 +816,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -527,6 +846,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -552,6 +875,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -571,6 +897,9 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -656,6 +985,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -686,6 +1019,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -732,6 +1069,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -755,6 +1096,10 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                SearchSort::Time,
             )
             .await
             .unwrap();
@@ -0,0 +1,60 @@
+use std::path::Path;
+use std::time::Duration;
+
+use screenpipe_events::{send_event, AppSwitchedEvent, APP_SWITCHED_EVENT, FRAME_INDEXED_EVENT};
+
+mod common;
+use common::start_server;
+
+#[tokio::test]
+async fn test_event_stream_filters_types() {
+    let addr = start_server(Path::new("")).await;
+    let mut response = reqwest::get(format!("http://{}/events/stream?types=app_switched", addr))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // give the server time to subscribe before publishing
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    send_event(FRAME_INDEXED_EVENT, serde_json::json!({ "frame_id": 1 })).unwrap();
+    send_event(
+        APP_SWITCHED_EVENT,
+        AppSwitchedEvent {
+            frame_id: 2,
+            timestamp: chrono::Utc::now(),
+            device_name: "monitor_1".to_string(),
+            app_name: "slack".to_string(),
+            window_name: "general".to_string(),
+            previous_app_name: "chrome".to_string(),
+        },
+    )
+    .unwrap();
+
+    let body = tokio::time::timeout(Duration::from_secs(5), async {
+        let mut body = String::new();
+        while !body.contains("\n\n") {
+            let chunk = response.chunk().await.unwrap().expect("stream closed");
+            body.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        body
+    })
+    .await
+    .expect("no event received");
+
+    assert!(body.starts_with("event: app_switched\n"));
+    assert!(body.contains("\"previous_app_name\":\"chrome\""));
+    assert!(!body.contains("frame_indexed"));
+}
+
+#[tokio::test]
+async fn test_event_stream_rejects_unknown_types() {
+    let addr = start_server(Path::new("")).await;
+    let response = reqwest::get(format!(
+        "http://{}/events/stream?types=frame_indexed,nope",
+        addr
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 400);
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use chrono::{Duration, TimeZone, Utc};
+use screenpipe_db::{AudioDevice, DatabaseManager, DeviceType, OcrEngine, TagContentType};
+use screenpipe_server::export::{export, parse_export_time, ExportFilter, ExportFormat};
+use serde_json::Value;
+
+mod common;
+use common::setup_test_db;
+
+async fn insert_test_data(db: &DatabaseManager) {
+    db.insert_video_chunk("test_video.mp4", "monitor_1")
+        .await
+        .unwrap();
+    let frame_id = db
+        .insert_frame(
+            "monitor_1",
+            Some(Utc::now()),
+            None,
+            Some("Slack"),
+            Some("general"),
+            true,
+        )
+        .await
+        .unwrap();
+    db.insert_ocr_text(
+        frame_id,
+        "standup notes",
+        "",
+        Arc::new(OcrEngine::Tesseract),
+    )
+    .await
+    .unwrap();
+    db.add_tags(frame_id, TagContentType::Vision, vec!["work".to_string()])
+        .await
+        .unwrap();
+
+    let speaker = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+    db.update_speaker_name(speaker.id, "Alice").await.unwrap();
+    let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+    let mic = AudioDevice {
+        name: "mic".to_string(),
+        device_type: DeviceType::Input,
+    };
+    db.insert_audio_transcription(
+        audio_chunk_id,
+        "morning everyone",
+        0,
+        "",
+        &mic,
+        Some(speaker.id),
+        Some(1.0),
+        Some(3.5),
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    db.insert_audio_transcription(
+        audio_chunk_id,
+        "hi <all> & welcome",
+        1,
+        "",
+        &mic,
+        None,
+        Some(4.0),
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_export_formats_and_filters() {
+    let db = setup_test_db().await;
+    insert_test_data(&db).await;
+    let start = Utc::now() - Duration::hours(1);
+    let end = Utc::now() + Duration::hours(1);
+    let all = ExportFilter::default();
+
+    let srt = export(&db, ExportFormat::Srt, start, end, &all)
+        .await
+        .unwrap();
+    let cues: Vec<&str> = srt.split("\n\n").filter(|c| !c.is_empty()).collect();
+    assert_eq!(cues.len(), 2);
+    assert!(cues[0].starts_with("1\n"));
+    assert!(cues[0].ends_with("\nAlice: morning everyone"));
+    // the first cue lasts from its start to its end offset
+    let times: Vec<&str> = cues[0].lines().nth(1).unwrap().split(" --> ").collect();
+    assert_eq!(times.len(), 2);
+    assert!(times.iter().all(|t| t.contains(',')));
+
+    let vtt = export(&db, ExportFormat::Vtt, start, end, &all)
+        .await
+        .unwrap();
+    assert!(vtt.starts_with("WEBVTT\n\n"));
+    assert!(vtt.contains("\n<v Alice>morning everyone\n"));
+    assert!(vtt.contains("\nhi &lt;all&gt; &amp; welcome\n"));
+
+    let jsonl = export(&db, ExportFormat::Jsonl, start, end, &all)
+        .await
+        .unwrap();
+    let records: Vec<Value> = jsonl
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records.iter().filter(|r| r["type"] == "ocr").count(), 1);
+    assert!(records
+        .iter()
+        .any(|r| r["type"] == "audio" && r["speaker_name"] == "Alice"));
+    assert!(records.iter().all(|r| r["deep_link"]
+        .as_str()
+        .is_some_and(|link| link.starts_with("screenpipe://"))));
+
+    let markdown = export(&db, ExportFormat::Markdown, start, end, &all)
+        .await
+        .unwrap();
+    assert!(markdown.starts_with("# "));
+    assert!(markdown.contains("**Slack** general\n  > standup notes\n"));
+    assert!(markdown.contains(" **Alice**: morning everyone\n"));
+    assert!(markdown.contains("](screenpipe://frame/"));
+    assert!(markdown.contains("](screenpipe://time/"));
+
+    // an app filter leaves out the transcripts, a speaker one the screen text
+    let slack = ExportFilter {
+        app_name: Some("slack".to_string()),
+        ..Default::default()
+    };
+    let jsonl = export(&db, ExportFormat::Jsonl, start, end, &slack)
+        .await
+        .unwrap();
+    assert_eq!(jsonl.lines().count(), 1);
+    let alice = ExportFilter {
+        speaker: Some("alice".to_string()),
+        ..Default::default()
+    };
+    let jsonl = export(&db, ExportFormat::Jsonl, start, end, &alice)
+        .await
+        .unwrap();
+    assert_eq!(jsonl.lines().count(), 1);
+    let tagged = ExportFilter {
+        tag: Some("work".to_string()),
+        ..Default::default()
+    };
+    let jsonl = export(&db, ExportFormat::Jsonl, start, end, &tagged)
+        .await
+        .unwrap();
+    assert_eq!(jsonl.lines().count(), 1);
+}
+
+#[test]
+fn test_parse_export_time() {
+    assert_eq!(
+        parse_export_time("2025-04-14T09:30:00Z", false).unwrap(),
+        Utc.with_ymd_and_hms(2025, 4, 14, 9, 30, 0).unwrap()
+    );
+    let start = parse_export_time("2025-04-14", false).unwrap();
+    let end = parse_export_time("2025-04-14", true).unwrap();
+    assert!(end > start);
+    assert!(end - start < Duration::hours(26));
+    assert!(parse_export_time("yesterday", false).is_err());
+    assert!("srt".parse::<ExportFormat>().is_ok());
+    assert!("docx".parse::<ExportFormat>().is_err());
+}
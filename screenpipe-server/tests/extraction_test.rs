@@ -0,0 +1,280 @@
+use axum::{
+    extract::State,
+    http::{Method, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+use screenpipe_core::extraction::Extractor;
+use screenpipe_core::llm_client::LlmClient;
+use screenpipe_db::{AudioDevice, DatabaseManager, DeviceType, OcrEngine};
+use screenpipe_server::extraction::{process_rule, split_sessions, SESSION_GAP};
+
+mod common;
+use common::{request, setup_test_app, setup_test_db};
+
+/// Serves an Ollama answering every prompt with the same meeting, returning
+/// its url and the prompts it got.
+async fn serve_ollama() -> (String, Arc<Mutex<Vec<String>>>) {
+    let prompts = Arc::new(Mutex::new(Vec::new()));
+    let app = Router::new()
+        .route(
+            "/api/generate",
+            post(
+                |State(prompts): State<Arc<Mutex<Vec<String>>>>, Json(body): Json<Value>| async move {
+                    assert_eq!(body["format"], "json");
+                    prompts
+                        .lock()
+                        .unwrap()
+                        .push(body["prompt"].as_str().unwrap().to_string());
+                    Json(json!({
+                        "response": json!({
+                            "attendees": ["Ana", "Bob"],
+                            "decisions": ["ship on friday"],
+                        })
+                        .to_string()
+                    }))
+                },
+            ),
+        )
+        .with_state(prompts.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{}", addr), prompts)
+}
+
+fn meeting_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "attendees": {"type": "array", "items": {"type": "string"}},
+            "decisions": {"type": "array", "items": {"type": "string"}}
+        },
+        "required": ["attendees", "decisions"]
+    })
+}
+
+async fn record_frame(db: &DatabaseManager, at: DateTime<Utc>, app_name: &str, text: &str) {
+    let frame_id = db
+        .insert_frame("monitor_1", Some(at), None, Some(app_name), Some(""), true)
+        .await
+        .unwrap();
+    db.insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+        .await
+        .unwrap();
+}
+
+async fn record_transcript(db: &DatabaseManager, at: DateTime<Utc>, text: &str) {
+    let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+    let transcription_id = db
+        .insert_audio_transcription(
+            audio_chunk_id,
+            text,
+            0,
+            "",
+            &AudioDevice {
+                name: "mic".to_string(),
+                device_type: DeviceType::Input,
+            },
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    sqlx::query("UPDATE audio_transcriptions SET timestamp = ?1 WHERE id = ?2")
+        .bind(at)
+        .bind(transcription_id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+}
+
+#[test]
+fn test_split_sessions() {
+    let start = Utc::now();
+    let minutes = |m: i64| start + Duration::minutes(m);
+    assert!(split_sessions(&[], SESSION_GAP).is_empty());
+    assert_eq!(
+        split_sessions(
+            &[minutes(0), minutes(1), minutes(3), minutes(10), minutes(11)],
+            SESSION_GAP
+        ),
+        vec![(minutes(0), minutes(3)), (minutes(10), minutes(11))]
+    );
+    assert_eq!(
+        split_sessions(&[minutes(5)], SESSION_GAP),
+        vec![(minutes(5), minutes(5))]
+    );
+}
+
+#[tokio::test]
+async fn test_extractions() {
+    let db = setup_test_db().await;
+    let (url, prompts) = serve_ollama().await;
+    let extractor = Extractor::new(LlmClient::ollama(&url, "llama3.2"));
+    db.insert_video_chunk("test_video.mp4", "monitor_1")
+        .await
+        .unwrap();
+
+    let app = setup_test_app(db.clone()).await;
+    let (status, body) = request(
+        &app,
+        Method::POST,
+        "/extractions/rules",
+        Some(json!({
+            "name": "meetings",
+            "app_name": "zoom",
+            "schema": {"type": "object", "properties": {"when": {"type": "date"}}}
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["error"],
+        "invalid schema: $.when: unknown type \"date\""
+    );
+    let (status, body) = request(
+        &app,
+        Method::POST,
+        "/extractions/rules",
+        Some(json!({
+            "name": "meetings",
+            "app_name": "zoom",
+            "instructions": "Extract who attended and what was decided.",
+            "schema": meeting_schema()
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["schema"], meeting_schema());
+    let rule_id = body["data"]["id"].as_i64().unwrap();
+
+    // a call, some coding, then another call still going
+    let start = Utc::now() + Duration::seconds(1);
+    let minutes = |m: i64| start + Duration::minutes(m);
+    record_frame(&db, minutes(0), "zoom.us", "Q3 roadmap").await;
+    record_frame(&db, minutes(1), "zoom.us", "Q3 roadmap").await;
+    record_transcript(&db, minutes(1), "ana: let's ship on friday").await;
+    record_frame(&db, minutes(2), "zoom.us", "Q3 roadmap").await;
+    record_frame(&db, minutes(3), "Code", "fn main()").await;
+    record_frame(&db, minutes(10), "zoom.us", "standup").await;
+    record_frame(&db, minutes(11), "zoom.us", "standup").await;
+
+    let rule = db.list_extraction_rules().await.unwrap().remove(0);
+    assert_eq!(
+        process_rule(&db, &extractor, &rule, minutes(12))
+            .await
+            .unwrap(),
+        1
+    );
+    {
+        let prompts = prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("Extract who attended and what was decided."));
+        assert!(prompts[0].contains("ana: let's ship on friday"));
+        assert!(prompts[0].contains("Q3 roadmap"));
+        assert!(!prompts[0].contains("fn main()"));
+        assert!(!prompts[0].contains("standup"));
+    }
+    let extractions = db
+        .list_extractions(Some(rule_id), None, None, 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(extractions.len(), 1);
+    assert_eq!(extractions[0].start_time, minutes(0));
+    assert_eq!(extractions[0].end_time, minutes(2));
+    assert_eq!(extractions[0].data["attendees"], json!(["Ana", "Bob"]));
+    assert_eq!(extractions[0].model, "llama3.2");
+
+    // sessions are extracted once, the second once it's over
+    let rule = db.list_extraction_rules().await.unwrap().remove(0);
+    assert_eq!(rule.processed_until, Some(minutes(2)));
+    assert_eq!(
+        process_rule(&db, &extractor, &rule, minutes(12))
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(prompts.lock().unwrap().len(), 1);
+    assert_eq!(
+        process_rule(&db, &extractor, &rule, minutes(20))
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(prompts.lock().unwrap()[1].contains("standup"));
+    let rule = db.list_extraction_rules().await.unwrap().remove(0);
+    assert_eq!(rule.processed_until, Some(minutes(18)));
+
+    // answers not matching the schema are asked again, then dropped
+    let budget = db
+        .insert_extraction_rule(
+            "budget",
+            "zoom",
+            None,
+            "",
+            &json!({"type": "object", "required": ["budget"]}),
+        )
+        .await
+        .unwrap();
+    sqlx::query("UPDATE extraction_rules SET created_at = ?1 WHERE id = ?2")
+        .bind(minutes(-1))
+        .bind(budget.id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    let budget = db.list_extraction_rules().await.unwrap().remove(1);
+    assert_eq!(
+        process_rule(&db, &extractor, &budget, minutes(20))
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(prompts.lock().unwrap().len(), 6);
+
+    let (status, body) = request(
+        &app,
+        Method::GET,
+        &format!("/extractions?rule_id={}", rule_id),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+    // the latest start first
+    assert_eq!(
+        body["data"][0]["data"]["decisions"],
+        json!(["ship on friday"])
+    );
+    let (_, body) = request(&app, Method::GET, "/extractions/rules", None).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+
+    let (status, _) = request(
+        &app,
+        Method::DELETE,
+        &format!("/extractions/rules/{}", rule_id),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(db
+        .list_extractions(Some(rule_id), None, None, 10, 0)
+        .await
+        .unwrap()
+        .is_empty());
+    let (status, _) = request(
+        &app,
+        Method::DELETE,
+        &format!("/extractions/rules/{}", rule_id),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use screenpipe_db::{AudioDevice, DeviceType, OcrEngine, TagContentType};
+use screenpipe_server::graphql::build_schema;
+use serde_json::{json, Value};
+
+mod common;
+use common::setup_test_db;
+
+#[tokio::test]
+async fn test_frames_with_text_tags_and_transcripts() {
+    let db = setup_test_db().await;
+    db.insert_video_chunk("test_video.mp4", "monitor_1")
+        .await
+        .unwrap();
+    let frame_id = db
+        .insert_frame(
+            "monitor_1",
+            Some(Utc::now()),
+            None,
+            Some("Slack"),
+            Some("general"),
+            true,
+        )
+        .await
+        .unwrap();
+    db.insert_ocr_text(
+        frame_id,
+        "standup notes",
+        "",
+        Arc::new(OcrEngine::Tesseract),
+    )
+    .await
+    .unwrap();
+    db.add_tags(frame_id, TagContentType::Vision, vec!["work".to_string()])
+        .await
+        .unwrap();
+    let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+    db.insert_audio_transcription(
+        audio_chunk_id,
+        "morning everyone",
+        0,
+        "",
+        &AudioDevice {
+            name: "mic".to_string(),
+            device_type: DeviceType::Input,
+        },
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    let schema = build_schema(db);
+    let response = schema
+        .execute(
+            r#"{
+                frames(appName: "slack", limit: 10) {
+                    id appName windowName text tags
+                    transcripts { transcription isInput speaker { name } }
+                }
+                apps { name frameCount }
+            }"#,
+        )
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+    let data: Value = response.data.into_json().unwrap();
+    assert_eq!(
+        data["frames"],
+        json!([{
+            "id": frame_id,
+            "appName": "Slack",
+            "windowName": "general",
+            "text": "standup notes",
+            "tags": ["work"],
+            "transcripts": [{
+                "transcription": "morning everyone",
+                "isInput": true,
+                "speaker": null
+            }]
+        }])
+    );
+    assert_eq!(data["apps"], json!([{ "name": "Slack", "frameCount": 1 }]));
+}
+
+#[tokio::test]
+async fn test_time_range_and_depth_limit() {
+    let schema = build_schema(setup_test_db().await);
+
+    let response = schema
+        .execute(
+            r#"{
+                transcripts(startTime: "2024-01-01T00:00:00Z", endTime: "2024-01-02T00:00:00Z") {
+                    id
+                }
+            }"#,
+        )
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data.into_json().unwrap(),
+        json!({ "transcripts": [] })
+    );
+
+    // nesting back and forth is rejected before hitting the database
+    let response = schema
+        .execute(
+            "{ speakers { transcripts { speaker { transcripts { speaker { transcripts { id } } } } } } }",
+        )
+        .await;
+    assert!(!response.errors.is_empty());
+}
@@ -0,0 +1,92 @@
+use screenpipe_server::health::{
+    remediations, AudioDeviceHealth, DatabaseHealth, DiskHealth, MonitorHealth, OcrHealth,
+    RemediationCode, SubsystemHealth, LOW_DISK_BYTES, OCR_QUEUE_BACKLOG,
+};
+
+fn healthy() -> SubsystemHealth {
+    SubsystemHealth {
+        starting: false,
+        monitors: vec![MonitorHealth {
+            monitor_id: 1,
+            last_frame_timestamp: None,
+            status: "ok".to_string(),
+            ocr_queue_depth: 0,
+        }],
+        audio_devices: vec![AudioDeviceHealth {
+            device: "MacBook Pro Microphone (input)".to_string(),
+            last_chunk_timestamp: None,
+            status: "ok".to_string(),
+        }],
+        ocr: OcrHealth {
+            queue_depth: 0,
+            deferred_pending: 0,
+        },
+        database: DatabaseHealth {
+            writable: true,
+            error: None,
+        },
+        disk: DiskHealth {
+            free_bytes: Some(LOW_DISK_BYTES * 10),
+        },
+        transcription_model_loaded: Some(true),
+        restarts: Vec::new(),
+    }
+}
+
+fn codes(health: &SubsystemHealth) -> Vec<RemediationCode> {
+    remediations(health)
+        .into_iter()
+        .map(|remediation| remediation.code)
+        .collect()
+}
+
+#[test]
+fn test_no_remediations_when_healthy() {
+    assert!(remediations(&healthy()).is_empty());
+}
+
+#[test]
+fn test_remediations_of_each_subsystem() {
+    let mut health = healthy();
+    health.monitors[0].status = "stale".to_string();
+    health.monitors[0].ocr_queue_depth = OCR_QUEUE_BACKLOG;
+    health.audio_devices[0].status = "stale".to_string();
+    health.database = DatabaseHealth {
+        writable: false,
+        error: Some("attempt to write a readonly database".to_string()),
+    };
+    health.disk.free_bytes = Some(LOW_DISK_BYTES - 1);
+    health.transcription_model_loaded = Some(false);
+
+    assert_eq!(
+        codes(&health),
+        [
+            RemediationCode::MonitorStale,
+            RemediationCode::OcrBacklog,
+            RemediationCode::AudioDeviceStale,
+            RemediationCode::DatabaseNotWritable,
+            RemediationCode::DiskLow,
+            RemediationCode::ModelNotLoaded,
+        ]
+    );
+    let remediations = remediations(&health);
+    assert_eq!(remediations[0].subsystem, "monitor 1");
+    assert!(remediations[3].message.contains("readonly"));
+    assert_eq!(
+        serde_json::to_value(&remediations[3]).unwrap()["code"],
+        "database_not_writable"
+    );
+}
+
+#[test]
+fn test_model_not_reported_while_starting() {
+    let mut health = healthy();
+    health.transcription_model_loaded = Some(false);
+    health.starting = true;
+    assert!(codes(&health).is_empty());
+
+    // audio disabled
+    health.transcription_model_loaded = None;
+    health.starting = false;
+    assert!(codes(&health).is_empty());
+}
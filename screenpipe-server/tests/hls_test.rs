@@ -0,0 +1,79 @@
+use chrono::{TimeZone, Utc};
+use screenpipe_db::VideoChunkSpan;
+use screenpipe_server::hls::{build_playlist, busiest_device, HlsSegment};
+
+fn segment(chunk_id: i64, duration: f64) -> HlsSegment {
+    HlsSegment {
+        chunk_id,
+        start_time: Utc.timestamp_opt(1_700_000_000 + chunk_id * 60, 0).unwrap(),
+        duration,
+    }
+}
+
+fn chunk(id: i64, device_name: &str, frame_count: i64) -> VideoChunkSpan {
+    let start_time = Utc.timestamp_opt(1_700_000_000 + id * 60, 0).unwrap();
+    VideoChunkSpan {
+        id,
+        file_path: format!("{}_{}.mp4", device_name, id),
+        device_name: device_name.to_string(),
+        start_time,
+        end_time: start_time + chrono::Duration::seconds(59),
+        frame_count,
+    }
+}
+
+#[test]
+fn test_build_playlist() {
+    let playlist = build_playlist(&[segment(1, 59.5), segment(2, 30.0)], None);
+
+    assert_eq!(
+        playlist,
+        "#EXTM3U\n\
+         #EXT-X-VERSION:3\n\
+         #EXT-X-TARGETDURATION:60\n\
+         #EXT-X-MEDIA-SEQUENCE:0\n\
+         #EXT-X-PLAYLIST-TYPE:VOD\n\
+         #EXT-X-PROGRAM-DATE-TIME:2023-11-14T22:14:20.000Z\n\
+         #EXTINF:59.500,\n\
+         hls/segments/1.ts\n\
+         #EXT-X-DISCONTINUITY\n\
+         #EXT-X-PROGRAM-DATE-TIME:2023-11-14T22:15:20.000Z\n\
+         #EXTINF:30.000,\n\
+         hls/segments/2.ts\n\
+         #EXT-X-ENDLIST\n"
+    );
+}
+
+#[test]
+fn test_build_playlist_passes_token_to_segments() {
+    let playlist = build_playlist(&[segment(7, 2.0)], Some("sp_abc"));
+
+    assert!(playlist.contains("\nhls/segments/7.ts?token=sp_abc\n"));
+    assert!(!playlist.contains("#EXT-X-DISCONTINUITY"));
+}
+
+#[test]
+fn test_build_empty_playlist() {
+    let playlist = build_playlist(&[], None);
+
+    assert!(playlist.contains("#EXT-X-TARGETDURATION:1\n"));
+    assert!(playlist.ends_with("#EXT-X-ENDLIST\n"));
+}
+
+#[test]
+fn test_busiest_device() {
+    assert_eq!(busiest_device(&[]), None);
+    assert_eq!(
+        busiest_device(&[
+            chunk(1, "monitor_1", 30),
+            chunk(2, "monitor_2", 40),
+            chunk(3, "monitor_1", 20),
+        ]),
+        Some("monitor_1".to_string())
+    );
+    // the first device wins a tie
+    assert_eq!(
+        busiest_device(&[chunk(1, "monitor_2", 10), chunk(2, "monitor_1", 10)]),
+        Some("monitor_2".to_string())
+    );
+}
@@ -0,0 +1,175 @@
+use chrono::{TimeZone, Utc};
+use screenpipe_db::DatabaseManager;
+use screenpipe_server::import::{
+    app_name_of_bundle, import, parse_rewind_timestamp, ImportSource, REWIND_DEVICE_NAME,
+};
+use serde_json::json;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection};
+
+#[test]
+fn test_parse_rewind_timestamp() {
+    let expected = Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap();
+    for value in [
+        "2024-03-01T09:30:00.000",
+        "2024-03-01 09:30:00",
+        "2024-03-01T10:30:00+01:00",
+        "1709285400",
+        "1709285400000",
+    ] {
+        assert_eq!(parse_rewind_timestamp(value), Some(expected), "{}", value);
+    }
+    assert_eq!(parse_rewind_timestamp("yesterday"), None);
+    assert_eq!(app_name_of_bundle("com.apple.Safari"), "Safari");
+}
+
+#[tokio::test]
+async fn test_import_rewind() {
+    let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db-enc.sqlite3");
+
+    let mut rewind = SqliteConnectOptions::new()
+        .filename(&path)
+        .create_if_missing(true)
+        .connect()
+        .await
+        .unwrap();
+    for sql in [
+        "CREATE TABLE segment (id INTEGER PRIMARY KEY, bundleID TEXT, windowName TEXT, browserUrl TEXT, startDate TEXT, endDate TEXT)",
+        "CREATE TABLE frame (id INTEGER PRIMARY KEY, createdAt TEXT, segmentId INTEGER, imageFileName TEXT)",
+        "CREATE TABLE searchRanking_content (id INTEGER PRIMARY KEY, c0 TEXT, c1 TEXT, c2 TEXT)",
+        "CREATE TABLE doc_segment (docid INTEGER, segmentId INTEGER, frameId INTEGER)",
+        "CREATE TABLE transcript_word (id INTEGER PRIMARY KEY, segmentId INTEGER, speechSource TEXT, word TEXT, timeOffset INTEGER)",
+        "INSERT INTO segment VALUES (1, 'com.apple.Safari', 'Quarterly report', 'https://example.com/report', '2024-03-01T09:30:00.000', '2024-03-01T09:31:00.000')",
+        "INSERT INTO segment VALUES (2, 'us.zoom.xos', 'Zoom Meeting', NULL, '2024-03-01T10:00:00.000', '2024-03-01T10:30:00.000')",
+        "INSERT INTO frame VALUES (1, '2024-03-01T09:30:00.000', 1, 'a.jpg')",
+        "INSERT INTO frame VALUES (2, '2024-03-01T09:30:02.000', 1, 'b.jpg')",
+        "INSERT INTO frame VALUES (3, 'never', 1, 'c.jpg')",
+        "INSERT INTO searchRanking_content VALUES (1, 'revenue grew by ten percent', '', '')",
+        "INSERT INTO doc_segment VALUES (1, 1, 1)",
+        "INSERT INTO transcript_word VALUES (1, 2, 'me', 'budget', 1)",
+        "INSERT INTO transcript_word VALUES (2, 2, 'me', 'the', 0)",
+        "INSERT INTO transcript_word VALUES (3, 2, 'me', 'approved', 2)",
+    ] {
+        sqlx::query(sql).execute(&mut rewind).await.unwrap();
+    }
+    rewind.close().await.unwrap();
+
+    let report = import(&db, ImportSource::Rewind, &path, None)
+        .await
+        .unwrap();
+    assert_eq!(report.frames, 2);
+    assert_eq!(report.transcripts, 1);
+    assert_eq!(report.skipped, vec!["frames: 1 without a valid time"]);
+
+    let (app_name, window_name, browser_url, text): (String, String, String, String) =
+        sqlx::query_as(
+            "SELECT frames.app_name, frames.window_name, frames.browser_url, ocr_text.text FROM frames JOIN ocr_text ON ocr_text.frame_id = frames.id WHERE frames.device_name = ?1 ORDER BY frames.timestamp LIMIT 1",
+        )
+        .bind(REWIND_DEVICE_NAME)
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+    assert_eq!(app_name, "Safari");
+    assert_eq!(window_name, "Quarterly report");
+    assert_eq!(browser_url, "https://example.com/report");
+    assert_eq!(text, "revenue grew by ten percent");
+
+    let transcription: String =
+        sqlx::query_scalar("SELECT transcription FROM audio_transcriptions WHERE device = ?1")
+            .bind(REWIND_DEVICE_NAME)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+    assert_eq!(transcription, "the budget approved");
+
+    // the media isn't there, its chunks say so
+    let without_media: i64 = sqlx::query_scalar(
+        "SELECT (SELECT COUNT(*) FROM video_chunks WHERE media_deleted_at IS NOT NULL) + (SELECT COUNT(*) FROM audio_chunks WHERE media_deleted_at IS NOT NULL)",
+    )
+    .fetch_one(&db.pool)
+    .await
+    .unwrap();
+    assert_eq!(without_media, 2);
+
+    let again = import(&db, ImportSource::Rewind, &path, None)
+        .await
+        .unwrap();
+    assert_eq!((again.frames, again.transcripts), (0, 0));
+}
+
+#[tokio::test]
+async fn test_import_activitywatch() {
+    let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("aw-buckets-export.json");
+    let export = json!({
+        "buckets": {
+            "aw-watcher-window_laptop": {
+                "id": "aw-watcher-window_laptop",
+                "type": "currentwindow",
+                "hostname": "laptop",
+                "events": [
+                    {
+                        "timestamp": "2024-03-01T09:00:00+00:00",
+                        "duration": 90.5,
+                        "data": { "app": "Code", "title": "main.rs - screenpipe" }
+                    },
+                    {
+                        "timestamp": "2024-03-01T09:01:30.500000+00:00",
+                        "duration": 30.0,
+                        "data": { "app": "Firefox", "title": "Rust docs" }
+                    },
+                    {
+                        "timestamp": "2024-03-01T09:02:00+00:00",
+                        "duration": 5.0,
+                        "data": { "title": "unknown" }
+                    }
+                ]
+            },
+            "aw-watcher-afk_laptop": {
+                "id": "aw-watcher-afk_laptop",
+                "type": "afkstatus",
+                "hostname": "laptop",
+                "events": [
+                    {
+                        "timestamp": "2024-03-01T09:00:00+00:00",
+                        "duration": 120.0,
+                        "data": { "status": "not-afk" }
+                    }
+                ]
+            }
+        }
+    });
+    std::fs::write(&path, export.to_string()).unwrap();
+
+    let report = import(&db, ImportSource::ActivityWatch, &path, None)
+        .await
+        .unwrap();
+    assert_eq!(report.window_events, 2);
+    assert_eq!(report.skipped.len(), 2);
+
+    let start = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap();
+    let events = db.list_focus_events(start, end, None, 10, 0).await.unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].app_name, "Code");
+    assert_eq!(events[0].window_name, "main.rs - screenpipe");
+    assert_eq!(
+        events[0].end_time,
+        Utc.with_ymd_and_hms(2024, 3, 1, 9, 1, 30).unwrap() + chrono::Duration::milliseconds(500)
+    );
+
+    let titles: Vec<String> =
+        sqlx::query_scalar("SELECT text_output FROM ui_monitoring ORDER BY timestamp")
+            .fetch_all(&db.pool)
+            .await
+            .unwrap();
+    assert_eq!(titles, vec!["main.rs - screenpipe", "Rust docs"]);
+
+    let again = import(&db, ImportSource::ActivityWatch, &path, None)
+        .await
+        .unwrap();
+    assert_eq!(again.window_events, 0);
+}
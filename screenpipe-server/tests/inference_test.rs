@@ -0,0 +1,82 @@
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use screenpipe_core::inference::{InferenceDevice, LocalInference, LOCAL_MODELS};
+
+mod common;
+use common::{request, setup_test_app, setup_test_app_with, setup_test_db};
+
+fn chat(content: &str) -> Value {
+    json!({
+        "model": "llama3.2-1b",
+        "messages": [{"role": "user", "content": content}]
+    })
+}
+
+#[tokio::test]
+async fn test_local_llm_disabled() {
+    let app = setup_test_app(setup_test_db().await).await;
+
+    let (status, body) =
+        request(&app, Method::POST, "/v1/chat/completions", Some(chat("hi"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(body["error"].as_str().unwrap().contains("--enable-llm"));
+    let (status, _) = request(&app, Method::GET, "/v1/models", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = request(&app, Method::GET, "/llm/models", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_local_llm_without_model() {
+    let inference = Arc::new(LocalInference::new(InferenceDevice::Cpu));
+    let app = setup_test_app_with(setup_test_db().await, |app| app.with_inference(inference)).await;
+
+    let (status, body) = request(&app, Method::GET, "/llm/models", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["status"], json!({"state": "idle"}));
+    assert_eq!(
+        body["data"]["models"].as_array().unwrap().len(),
+        LOCAL_MODELS.len()
+    );
+    assert_eq!(body["data"]["models"][0]["id"], "llama3.2-1b");
+    assert!(body["data"]["quantizations"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("q4_k_m")));
+
+    let (status, body) = request(&app, Method::GET, "/v1/models", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({"object": "list", "data": []}));
+
+    let (status, _) = request(&app, Method::POST, "/v1/chat/completions", Some(chat("hi"))).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let (status, body) = request(
+        &app,
+        Method::POST,
+        "/v1/chat/completions",
+        Some(json!({"messages": []})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "messages can't be empty");
+
+    let (status, _) = request(
+        &app,
+        Method::POST,
+        "/llm/models/load",
+        Some(json!({"model": "gpt-4", "quantization": "q4_k_m"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, body) = request(
+        &app,
+        Method::POST,
+        "/llm/models/load",
+        Some(json!({"model": "llama3.2-1b", "quantization": "q3"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "unknown quantization: q3");
+}
@@ -0,0 +1,166 @@
+use screenpipe_server::ingest::{ingest_dir, IngestKind};
+use serde_json::Value;
+use tempfile::tempdir;
+
+mod common;
+use common::start_server;
+
+#[test]
+fn test_kind_from_content_type() {
+    assert_eq!(
+        IngestKind::from_content_type("video/mp4"),
+        Some((IngestKind::Video, "mp4".to_string()))
+    );
+    assert_eq!(
+        IngestKind::from_content_type("video/quicktime"),
+        Some((IngestKind::Video, "mov".to_string()))
+    );
+    assert_eq!(
+        IngestKind::from_content_type("image/jpeg"),
+        Some((IngestKind::Image, "jpg".to_string()))
+    );
+    assert_eq!(
+        IngestKind::from_content_type("Audio/MPEG; charset=binary"),
+        Some((IngestKind::Audio, "mp3".to_string()))
+    );
+    assert_eq!(
+        IngestKind::from_content_type("image/svg+xml"),
+        Some((IngestKind::Image, "bin".to_string()))
+    );
+    assert_eq!(IngestKind::from_content_type("application/json"), None);
+    assert_eq!(IngestKind::from_content_type("video"), None);
+}
+
+#[test]
+fn test_kind_of_upload() {
+    assert_eq!(
+        IngestKind::of_upload(Some(IngestKind::Video), "video/mp4"),
+        Some((IngestKind::Video, "mp4".to_string()))
+    );
+    assert_eq!(
+        IngestKind::of_upload(Some(IngestKind::Audio), "application/octet-stream"),
+        Some((IngestKind::Audio, "bin".to_string()))
+    );
+    assert_eq!(
+        IngestKind::of_upload(Some(IngestKind::Audio), ""),
+        Some((IngestKind::Audio, "bin".to_string()))
+    );
+    assert_eq!(
+        IngestKind::of_upload(Some(IngestKind::Video), "audio/wav"),
+        None
+    );
+    assert_eq!(
+        IngestKind::of_upload(Some(IngestKind::Audio), "application/pdf"),
+        None
+    );
+    assert_eq!(
+        IngestKind::of_upload(None, "application/octet-stream"),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_uploads_are_queued() {
+    let dir = tempdir().unwrap();
+    let addr = start_server(dir.path()).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("http://{}/ingest?device_name=phone", addr))
+        .header("content-type", "image/png")
+        .body(vec![0u8; 64])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    let body: Value = response.json().await.unwrap();
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+    assert_eq!(body["data"]["kind"], "image");
+    assert_eq!(body["data"]["device_name"], "phone");
+    assert_eq!(body["data"]["status"], "queued");
+
+    let queued = ingest_dir(&dir.path().join("data").to_string_lossy());
+    assert_eq!(
+        std::fs::read(queued.join(format!("{}.png", id))).unwrap(),
+        vec![0u8; 64]
+    );
+    assert!(queued.join(format!("{}.json", id)).exists());
+
+    let response = client
+        .get(format!("http://{}/ingest/{}", addr, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["data"]["status"], "queued");
+}
+
+#[tokio::test]
+async fn test_invalid_uploads_are_refused() {
+    let dir = tempdir().unwrap();
+    let addr = start_server(dir.path()).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("http://{}/ingest", addr))
+        .header("content-type", "application/pdf")
+        .body("%PDF")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 415);
+
+    for id in ["0b4f5c3e-4d4e-4c1a-9a53-3f1e4f1f2b10", "..%2F..%2Fdb"] {
+        let response = client
+            .get(format!("http://{}/ingest/{}", addr, id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+    }
+}
+
+#[tokio::test]
+async fn test_typed_uploads() {
+    let dir = tempdir().unwrap();
+    let addr = start_server(dir.path()).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!(
+            "http://{}/ingest/video?device_name=phone&timestamp=2024-05-01T10:00:00Z",
+            addr
+        ))
+        .header("content-type", "video/mp4")
+        .body(vec![0u8; 16])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["data"]["kind"], "video");
+    assert_eq!(body["data"]["timestamp"], "2024-05-01T10:00:00Z");
+
+    let response = client
+        .post(format!("http://{}/ingest/audio", addr))
+        .header("content-type", "video/mp4")
+        .body(vec![0u8; 16])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 415);
+
+    let response = client
+        .post(format!("http://{}/ingest/audio?device_name=calls", addr))
+        .body(vec![0u8; 16])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["data"]["kind"], "audio");
+    let id = body["data"]["id"].as_str().unwrap();
+    let queued = ingest_dir(&dir.path().join("data").to_string_lossy());
+    assert!(queued.join(format!("{}.bin", id)).exists());
+}
@@ -0,0 +1,44 @@
+use screenpipe_server::input_capture::{InputAction, InputSpan, TypedKey};
+
+fn key(text: &str) -> InputAction {
+    InputAction::Key(Some(TypedKey::Text(text.to_string())))
+}
+
+#[test]
+fn test_span_counts_actions() {
+    let mut span = InputSpan::new("Code".to_string(), "main.rs".to_string());
+    for action in [
+        InputAction::Key(None),
+        InputAction::Key(None),
+        InputAction::Click,
+        InputAction::Scroll,
+        InputAction::Scroll,
+        InputAction::Scroll,
+    ] {
+        span.record(action);
+    }
+
+    assert_eq!(span.keystrokes, 2);
+    assert_eq!(span.clicks, 1);
+    assert_eq!(span.scrolls, 3);
+    // keys without text are only counted
+    assert!(span.typed_text.is_empty());
+}
+
+#[test]
+fn test_span_applies_backspace_to_typed_text() {
+    let mut span = InputSpan::new("Notes".to_string(), "todo".to_string());
+    for action in [
+        key("h"),
+        key("e"),
+        key("y"),
+        InputAction::Key(Some(TypedKey::Backspace)),
+        key("!"),
+        key("\n"),
+    ] {
+        span.record(action);
+    }
+
+    assert_eq!(span.typed_text, "he!\n");
+    assert_eq!(span.keystrokes, 6);
+}
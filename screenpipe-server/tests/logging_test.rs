@@ -0,0 +1,83 @@
+use std::fs;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use screenpipe_server::logging::{log_files, parse_directives, LogFileWriter, LogLevels};
+
+#[test]
+fn test_log_file_rotation() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut writer = LogFileWriter::new(dir.path(), 100, 3).unwrap();
+    let line = [b'a'; 39];
+    for _ in 0..12 {
+        writer.write_all(&line).unwrap();
+        writer.write_all(b"\n").unwrap();
+    }
+    writer.flush().unwrap();
+
+    let files = log_files(dir.path()).unwrap();
+    assert_eq!(files.len(), 3);
+    for file in &files {
+        assert!(fs::metadata(file).unwrap().len() <= 100);
+    }
+    // the file the app opens is always the current one
+    let today = chrono::Utc::now().date_naive();
+    assert!(dir
+        .path()
+        .join(format!("screenpipe.{}.log", today))
+        .exists());
+}
+
+#[test]
+fn test_log_files_ignores_other_files() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("db.sqlite"), "").unwrap();
+    fs::write(dir.path().join("screenpipe.2024-01-01.log"), "old").unwrap();
+    LogFileWriter::new(dir.path(), 1024, 1).unwrap();
+    // only the current file is left
+    assert_eq!(log_files(dir.path()).unwrap().len(), 1);
+    assert!(dir.path().join("db.sqlite").exists());
+}
+
+#[test]
+fn test_parse_directives() {
+    let parsed =
+        parse_directives("screenpipe_audio=debug, warn,screenpipe_vision::core=trace").unwrap();
+    let targets: Vec<&str> = parsed.iter().map(|(target, _)| target.as_str()).collect();
+    assert_eq!(targets, ["screenpipe_audio", "", "screenpipe_vision::core"]);
+    assert!(parse_directives("screenpipe_audio=loud").is_err());
+}
+
+#[test]
+fn test_log_levels_set_and_reset() {
+    let applied = Arc::new(Mutex::new(Vec::new()));
+    let reload_applied = applied.clone();
+    let levels = LogLevels::new(
+        false,
+        vec![Box::new(move |filter| {
+            reload_applied.lock().unwrap().push(filter.to_string());
+            Ok(())
+        })],
+    );
+
+    let status = levels.set("screenpipe_audio=debug").unwrap();
+    assert_eq!(status.overrides, ["screenpipe_audio=debug"]);
+    assert!(status.filter.contains("screenpipe_audio=debug"));
+
+    // a later directive for the same module replaces the first one
+    let status = levels
+        .set("screenpipe_audio=warn,screenpipe_vision=trace")
+        .unwrap();
+    assert_eq!(
+        status.overrides,
+        ["screenpipe_audio=warn", "screenpipe_vision=trace"]
+    );
+    assert!(!status.filter.contains("screenpipe_audio=debug"));
+
+    // invalid directives change nothing
+    assert!(levels.set("screenpipe_audio=loud").is_err());
+    assert_eq!(levels.current().overrides.len(), 2);
+
+    assert!(levels.reset().overrides.is_empty());
+    assert_eq!(applied.lock().unwrap().len(), 3);
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use screenpipe_db::DatabaseManager;
+use screenpipe_server::mcp::{McpServer, McpTool, PROTOCOL_VERSION};
+use serde_json::{json, Value};
+
+async fn setup_server(tools: Vec<McpTool>) -> McpServer {
+    let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    McpServer::new(Arc::new(db), tools)
+}
+
+fn request(id: i64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+#[tokio::test]
+async fn test_initialize_and_notifications() {
+    let server = setup_server(Vec::new()).await;
+
+    let response = server
+        .handle_message(request(1, "initialize", json!({})))
+        .await
+        .unwrap();
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSION);
+
+    let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+    assert!(server.handle_message(notification).await.is_none());
+
+    let response = server
+        .handle_message(request(2, "resources/list", json!({})))
+        .await
+        .unwrap();
+    assert_eq!(response["error"]["code"], -32601);
+}
+
+#[tokio::test]
+async fn test_only_allowed_tools_are_offered() {
+    let server = setup_server(vec![McpTool::GetTimeline]).await;
+
+    let response = server
+        .handle_message(request(1, "tools/list", json!({})))
+        .await
+        .unwrap();
+    let tools = response["result"]["tools"].as_array().unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0]["name"], "get_timeline");
+
+    let response = server
+        .handle_message(request(
+            2,
+            "tools/call",
+            json!({ "name": "search_screen_history", "arguments": { "query": "hello" } }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response["error"]["code"], -32602);
+}
+
+#[tokio::test]
+async fn test_call_tool() {
+    let server = setup_server(Vec::new()).await;
+
+    let response = server
+        .handle_message(request(
+            1,
+            "tools/call",
+            json!({
+                "name": "get_timeline",
+                "arguments": {
+                    "start_time": "2024-01-01T00:00:00Z",
+                    "end_time": "2024-01-02T00:00:00Z"
+                }
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response["result"]["isError"], false);
+    let page: Value =
+        serde_json::from_str(response["result"]["content"][0]["text"].as_str().unwrap()).unwrap();
+    assert_eq!(page["events"], json!([]));
+
+    // bad arguments are reported to the model
+    let response = server
+        .handle_message(request(
+            2,
+            "tools/call",
+            json!({ "name": "get_timeline", "arguments": {} }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response["result"]["isError"], true);
+}
@@ -0,0 +1,46 @@
+use chrono::{Duration, Utc};
+use screenpipe_core::encryption::{is_encrypted_file, EncryptionKey, MediaCipher};
+use screenpipe_db::{DatabaseManager, MediaType};
+use screenpipe_server::media_encryption::encrypt_finished_chunks;
+
+#[tokio::test]
+async fn test_encrypt_finished_chunks() {
+    let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let finished = dir.path().join("finished.mp4");
+    let recording = dir.path().join("recording.mp4");
+    std::fs::write(&finished, b"finished audio").unwrap();
+    std::fs::write(&recording, b"audio being recorded").unwrap();
+
+    let finished_id = db
+        .insert_audio_chunk(finished.to_str().unwrap())
+        .await
+        .unwrap();
+    db.insert_audio_chunk(recording.to_str().unwrap())
+        .await
+        .unwrap();
+    sqlx::query("UPDATE audio_chunks SET timestamp = ?1 WHERE id = ?2")
+        .bind(Utc::now() - Duration::hours(1))
+        .bind(finished_id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+    let cipher: &'static MediaCipher =
+        Box::leak(Box::new(MediaCipher::new(&EncryptionKey::generate())));
+    assert_eq!(encrypt_finished_chunks(&db, cipher).await.unwrap(), 1);
+
+    assert!(is_encrypted_file(&finished).unwrap());
+    assert!(!is_encrypted_file(&recording).unwrap());
+    let decrypted = cipher.decrypt_to_temp(&finished, dir.path()).unwrap();
+    assert_eq!(std::fs::read(decrypted.path()).unwrap(), b"finished audio");
+
+    // encrypted chunks are not picked up again
+    assert_eq!(encrypt_finished_chunks(&db, cipher).await.unwrap(), 0);
+    assert!(db
+        .get_unencrypted_media_chunks(MediaType::Audio, Utc::now(), 10)
+        .await
+        .unwrap()
+        .iter()
+        .all(|chunk| chunk.id != finished_id));
+}
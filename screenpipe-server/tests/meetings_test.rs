@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use screenpipe_db::{AudioDevice, DatabaseManager, DeviceType};
+use screenpipe_server::export::{export, ExportFilter, ExportFormat};
+use screenpipe_server::meetings::{check_meeting, MeetingChange, MeetingDetails};
+
+#[tokio::test]
+async fn test_meeting_session() {
+    let db = Arc::new(DatabaseManager::new("sqlite::memory:").await.unwrap());
+    let start = Utc::now() - Duration::hours(1);
+    let ana = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+    db.update_speaker_name(ana.id, "Ana").await.unwrap();
+    let other = db.insert_speaker(&vec![0.9; 512]).await.unwrap();
+
+    db.insert_video_chunk("test_video.mp4", "monitor_1")
+        .await
+        .unwrap();
+    let device = AudioDevice {
+        name: "mic".to_string(),
+        device_type: DeviceType::Input,
+    };
+    // a podcast while coding, then a 10 minute call
+    db.insert_frame(
+        "monitor_1",
+        Some(start - Duration::minutes(5)),
+        None,
+        Some("Code"),
+        Some("main.rs"),
+        true,
+    )
+    .await
+    .unwrap();
+    let chunk_id = db
+        .get_or_insert_audio_chunk("podcast.mp4", start - Duration::minutes(5))
+        .await
+        .unwrap();
+    db.insert_audio_transcription_at(
+        chunk_id,
+        "welcome to the show",
+        0,
+        "",
+        &device,
+        None,
+        None,
+        None,
+        None,
+        None,
+        start - Duration::minutes(5),
+    )
+    .await
+    .unwrap();
+    for step in 0..=20 {
+        let timestamp = start + Duration::seconds(30 * step);
+        db.insert_frame(
+            "monitor_1",
+            Some(timestamp),
+            None,
+            Some("zoom.us"),
+            Some("Zoom Meeting"),
+            true,
+        )
+        .await
+        .unwrap();
+        if step == 0 {
+            continue;
+        }
+        let chunk_id = db
+            .get_or_insert_audio_chunk(&format!("call_{}.mp4", step), timestamp)
+            .await
+            .unwrap();
+        let speaker_id = if step % 2 == 1 { ana.id } else { other.id };
+        db.insert_audio_transcription_at(
+            chunk_id,
+            &format!("point number {}", step),
+            0,
+            "",
+            &device,
+            Some(speaker_id),
+            None,
+            None,
+            None,
+            None,
+            timestamp,
+        )
+        .await
+        .unwrap();
+    }
+
+    let check = |minutes: i64| check_meeting(&db, start + Duration::minutes(minutes));
+    assert_eq!(check(-4).await.unwrap(), None);
+
+    let Some(MeetingChange::Started(session)) = check(2).await.unwrap() else {
+        panic!("meeting not started");
+    };
+    assert_eq!(session.start_time, start);
+    assert_eq!(session.end_time, start + Duration::minutes(2));
+    assert_eq!(session.app_name.as_deref(), Some("zoom.us"));
+    assert!(!session.ended);
+
+    let Some(MeetingChange::Continued(continued)) = check(6).await.unwrap() else {
+        panic!("meeting not continued");
+    };
+    assert_eq!(continued.id, session.id);
+    assert_eq!(continued.end_time, start + Duration::minutes(6));
+    assert!(matches!(
+        check(10).await.unwrap(),
+        Some(MeetingChange::Continued(_))
+    ));
+    // silence for a bit isn't the end yet
+    assert_eq!(check(12).await.unwrap(), None);
+    let Some(MeetingChange::Ended(ended)) = check(15).await.unwrap() else {
+        panic!("meeting not ended");
+    };
+    assert_eq!(ended.end_time, start + Duration::minutes(10));
+    assert!(ended.ended);
+    assert_eq!(check(16).await.unwrap(), None);
+
+    let sessions = db.list_meeting_sessions(None, None, 10, 0).await.unwrap();
+    assert_eq!(sessions, vec![ended.clone()]);
+
+    let meeting = MeetingDetails::load(&db, ended).await.unwrap();
+    assert_eq!(meeting.participants.len(), 2);
+    assert_eq!(meeting.participants[0].speaker_id, ana.id);
+    assert_eq!(meeting.participants[0].name.as_deref(), Some("Ana"));
+    assert_eq!(meeting.participants[0].transcript_count, 10);
+    assert_eq!(meeting.participants[1].speaker_id, other.id);
+    assert_eq!(meeting.participants[1].transcript_count, 10);
+
+    let transcript = export(
+        &db,
+        ExportFormat::Srt,
+        meeting.session.start_time,
+        meeting.session.end_time,
+        &ExportFilter::default(),
+    )
+    .await
+    .unwrap();
+    assert!(transcript.contains("Ana: point number 1\n"));
+    assert!(transcript.contains("point number 20"));
+    assert!(!transcript.contains("welcome to the show"));
+}
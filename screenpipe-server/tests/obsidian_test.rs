@@ -0,0 +1,83 @@
+use std::fs;
+use std::sync::Arc;
+
+use chrono::{Duration, Local, Utc};
+use screenpipe_db::{DatabaseManager, OcrEngine};
+use screenpipe_server::obsidian::{merge_note, write_notes, ObsidianConfig};
+
+#[test]
+fn test_merge_note_keeps_user_text() {
+    let note = merge_note(None, "first\n");
+    assert_eq!(
+        note,
+        "<!-- screenpipe:start -->\nfirst\n<!-- screenpipe:end -->\n"
+    );
+
+    let edited = format!("# my day\n\n{}\nmet bob for lunch\n", note);
+    assert_eq!(
+        merge_note(Some(&edited), "second"),
+        "# my day\n\n<!-- screenpipe:start -->\nsecond\n<!-- screenpipe:end -->\nmet bob for lunch\n"
+    );
+
+    // notes made by other plugins get the block appended
+    assert_eq!(
+        merge_note(Some("- [ ] groceries\n"), "second"),
+        "- [ ] groceries\n\n<!-- screenpipe:start -->\nsecond\n<!-- screenpipe:end -->\n"
+    );
+}
+
+#[tokio::test]
+async fn test_write_notes_into_vault() {
+    let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    db.insert_video_chunk("test_video.mp4", "monitor_1")
+        .await
+        .unwrap();
+    let frame_id = db
+        .insert_frame(
+            "monitor_1",
+            Some(Utc::now()),
+            None,
+            Some("Figma"),
+            Some("landing page"),
+            true,
+        )
+        .await
+        .unwrap();
+    db.insert_ocr_text(frame_id, "hero section", "", Arc::new(OcrEngine::Tesseract))
+        .await
+        .unwrap();
+
+    let vault = tempfile::tempdir().unwrap();
+    let config = ObsidianConfig::new(vault.path().to_path_buf())
+        .with_folder("daily".to_string())
+        .with_frame_links("http://localhost:3030".to_string());
+    let start = Utc::now() - Duration::hours(1);
+    let end = Utc::now() + Duration::hours(1);
+    let paths = write_notes(&db, &config, start, end).await.unwrap();
+    assert_eq!(paths.len(), 1);
+
+    let today = Local::now().date_naive();
+    assert_eq!(paths[0], config.note_path(today));
+    assert!(paths[0].starts_with(vault.path().join("daily")));
+    let note = fs::read_to_string(&paths[0]).unwrap();
+    assert!(note.contains(&format!(
+        "[[{}]]",
+        today.pred_opt().unwrap().format("%Y-%m-%d")
+    )));
+    assert!(note.contains(&format!(
+        "**Figma** landing page [frame](http://localhost:3030/frames/{})\n",
+        frame_id
+    )));
+    assert!(note.contains("  > hero section\n"));
+    assert!(note.contains("## transcripts\n\n_nothing recorded_\n"));
+
+    // writing again replaces the block, not what the user added
+    fs::write(&paths[0], format!("{}\nfollow up with design\n", note)).unwrap();
+    write_notes(&db, &config, start, end).await.unwrap();
+    let rewritten = fs::read_to_string(&paths[0]).unwrap();
+    assert_eq!(rewritten.matches("<!-- screenpipe:start -->").count(), 1);
+    assert!(rewritten.ends_with("\nfollow up with design\n"));
+
+    let missing = ObsidianConfig::new(vault.path().join("missing"));
+    assert!(write_notes(&db, &missing, start, end).await.is_err());
+}
@@ -0,0 +1,50 @@
+use image::{DynamicImage, RgbImage};
+use screenpipe_core::encryption::{is_encrypted_file, readable_media, unlock_media, EncryptionKey};
+use screenpipe_core::ocr_mode::{set_ocr_mode, OcrMode};
+use screenpipe_server::ocr_queue::{
+    can_process, deferred_ocr_dir, queue_status, save_deferred_ocr,
+};
+
+#[tokio::test]
+async fn test_saved_windows_are_counted_as_pending() {
+    let dir = tempfile::tempdir().unwrap();
+    let output_path = dir.path().to_string_lossy().into_owned();
+    assert_eq!(queue_status(&output_path).pending, 0);
+
+    let image = DynamicImage::ImageRgb8(RgbImage::new(8, 8));
+    save_deferred_ocr(&output_path, 1, &image).await.unwrap();
+    save_deferred_ocr(&output_path, 2, &image).await.unwrap();
+
+    assert!(deferred_ocr_dir(&output_path).join("1.jpg").exists());
+    let status = queue_status(&output_path);
+    assert_eq!(status.pending, 2);
+    assert_eq!(status.processed, 0);
+    assert_eq!(status.windows_per_minute, None);
+}
+
+#[tokio::test]
+async fn test_saved_windows_are_encrypted_with_the_media_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let output_path = dir.path().to_string_lossy().into_owned();
+    unlock_media(
+        &EncryptionKey::generate(),
+        tempfile::tempdir().unwrap().into_path(),
+    );
+
+    let image = DynamicImage::ImageRgb8(RgbImage::new(8, 8));
+    save_deferred_ocr(&output_path, 1, &image).await.unwrap();
+
+    let path = deferred_ocr_dir(&output_path).join("1.jpg");
+    assert!(is_encrypted_file(&path).unwrap());
+    let media = readable_media(&path).await.unwrap();
+    assert_eq!(image::open(media.path()).unwrap().width(), 8);
+}
+
+#[test]
+fn test_deferred_mode_waits_for_idle_or_power() {
+    // without a battery and while the user is active
+    set_ocr_mode(OcrMode::Deferred);
+    assert!(!can_process());
+    set_ocr_mode(OcrMode::Realtime);
+    assert!(can_process());
+}
@@ -0,0 +1,82 @@
+use chrono::{Duration, Utc};
+use screenpipe_db::{DatabaseManager, MediaType};
+use screenpipe_server::offload::{readable_media, set_object_store, ObjectStore, OffloadConfig};
+
+fn test_config() -> OffloadConfig {
+    OffloadConfig {
+        bucket: "screenpipe".to_string(),
+        endpoint: Some("http://localhost:9000".to_string()),
+        region: "us-east-1".to_string(),
+        prefix: "laptop/".to_string(),
+        access_key: Some("minioadmin".to_string()),
+        secret_key: Some("minioadmin".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn test_offloaded_chunks_are_read_from_the_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache_dir = dir.path().join("offload-cache");
+    let store =
+        set_object_store(ObjectStore::new(&test_config(), cache_dir.clone()).unwrap()).unwrap();
+
+    let offloaded = dir.path().join("monitor_1_2024-01-01_10-00-00.mp4");
+    assert_eq!(
+        store.key(&offloaded),
+        "laptop/monitor_1_2024-01-01_10-00-00.mp4"
+    );
+    assert_eq!(
+        store.cache_path(&offloaded),
+        cache_dir.join("laptop_monitor_1_2024-01-01_10-00-00.mp4")
+    );
+
+    // a fetched copy is served without hitting the bucket
+    std::fs::create_dir_all(&cache_dir).unwrap();
+    std::fs::write(store.cache_path(&offloaded), b"offloaded video").unwrap();
+    let media = readable_media(&offloaded).await.unwrap();
+    assert_eq!(std::fs::read(media.path()).unwrap(), b"offloaded video");
+
+    // files still on disk are read in place
+    let local = dir.path().join("monitor_1_2024-01-02_10-00-00.mp4");
+    std::fs::write(&local, b"local video").unwrap();
+    let media = readable_media(&local).await.unwrap();
+    assert_eq!(media.path(), local.as_path());
+}
+
+#[tokio::test]
+async fn test_offloaded_chunks_are_not_local() {
+    let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    let old_id = db.insert_audio_chunk("old.mp4").await.unwrap();
+    db.insert_audio_chunk("new.mp4").await.unwrap();
+    sqlx::query("UPDATE audio_chunks SET timestamp = ?1 WHERE id = ?2")
+        .bind(Utc::now() - Duration::days(40))
+        .bind(old_id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+    let before = Utc::now() - Duration::days(30);
+    let chunks = db
+        .get_local_media_chunks_before(MediaType::Audio, before, 10)
+        .await
+        .unwrap();
+    let ids: Vec<i64> = chunks.iter().map(|chunk| chunk.id).collect();
+    assert_eq!(ids, vec![old_id]);
+
+    db.mark_media_chunks_offloaded(MediaType::Audio, &[old_id])
+        .await
+        .unwrap();
+    assert!(db
+        .get_local_media_chunks_before(MediaType::Audio, before, 10)
+        .await
+        .unwrap()
+        .is_empty());
+    // retention still sees them, to delete them from the bucket
+    assert_eq!(
+        db.get_media_chunks_before(MediaType::Audio, before, None)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use chrono::{TimeZone, Utc};
+use screenpipe_db::{DatabaseManager, OcrEngine, PartitionMonth};
+use screenpipe_server::partitions::{archive_old_months, partition_cutoff};
+use screenpipe_server::retention::{enforce_retention, RetentionPolicy};
+
+#[test]
+fn test_partition_cutoff() {
+    let now = Utc.with_ymd_and_hms(2025, 3, 10, 12, 0, 0).unwrap();
+    assert_eq!(
+        partition_cutoff(now, 1),
+        PartitionMonth {
+            year: 2025,
+            month: 2
+        }
+    );
+    assert_eq!(
+        partition_cutoff(now, 3),
+        PartitionMonth {
+            year: 2024,
+            month: 12
+        }
+    );
+    assert_eq!(partition_cutoff(now, 15).to_string(), "2023-12");
+}
+
+#[tokio::test]
+async fn test_old_months_are_archived_then_dropped_by_retention() {
+    let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+
+    for (month, day) in [(1, 15), (2, 10), (3, 5)] {
+        let video = dir.path().join(format!("2025-{:02}.mp4", month));
+        std::fs::write(&video, b"video").unwrap();
+        db.insert_video_chunk(&video.to_string_lossy(), "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame(
+                "test_device",
+                Some(Utc.with_ymd_and_hms(2025, month, day, 9, 0, 0).unwrap()),
+                None,
+                Some("mail"),
+                None,
+                true,
+            )
+            .await
+            .unwrap();
+        db.insert_ocr_text(frame_id, "invoice", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+    }
+
+    // march is still recorded to
+    let now = Utc.with_ymd_and_hms(2025, 3, 20, 12, 0, 0).unwrap();
+    let archived = archive_old_months(&db, &dir.path().join("partitions"), 1, now)
+        .await
+        .unwrap();
+    let months: Vec<&str> = archived
+        .iter()
+        .map(|partition| partition.month.as_str())
+        .collect();
+    assert_eq!(months, ["2025-01"]);
+    assert!(
+        archive_old_months(&db, &dir.path().join("partitions"), 1, now)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    let policy = RetentionPolicy {
+        enabled: true,
+        video_days: None,
+        audio_days: None,
+        ocr_days: Some(30),
+        transcript_days: Some(30),
+    };
+    // text kept forever keeps its partitions too
+    let keep_transcripts = RetentionPolicy {
+        transcript_days: None,
+        ..policy
+    };
+    let report = enforce_retention(&db, &keep_transcripts, true)
+        .await
+        .unwrap();
+    assert_eq!(report.partitions, 0);
+    let report = enforce_retention(&db, &policy, true).await.unwrap();
+    assert_eq!(report.partitions, 1);
+    assert!(dir.path().join("partitions/2025-01.sqlite").exists());
+
+    let report = enforce_retention(&db, &policy, false).await.unwrap();
+    assert_eq!(report.partitions, 1);
+    assert!(db.list_partitions().await.unwrap().is_empty());
+    assert!(!dir.path().join("partitions/2025-01.sqlite").exists());
+    assert!(!dir.path().join("2025-01.mp4").exists());
+}
@@ -0,0 +1,118 @@
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use futures::StreamExt;
+use screenpipe_core::pipe_bus::issue_token;
+use serde_json::{json, Value};
+use tempfile::tempdir;
+use tokio_tungstenite::tungstenite::Message;
+
+mod common;
+use common::start_server;
+
+fn write_pipe(screenpipe_dir: &Path, id: &str, config: Value) {
+    let pipe_dir = screenpipe_dir.join("pipes").join(id);
+    std::fs::create_dir_all(&pipe_dir).unwrap();
+    std::fs::write(pipe_dir.join("pipe.json"), config.to_string()).unwrap();
+}
+
+async fn publish(addr: SocketAddr, token: Option<&str>, topic: &str) -> reqwest::StatusCode {
+    let mut request = reqwest::Client::new()
+        .post(format!("http://{}/bus/publish", addr))
+        .json(&json!({"topic": topic, "payload": {"title": "standup"}}));
+    if let Some(token) = token {
+        request = request.header("x-pipe-token", token);
+    }
+    request.send().await.unwrap().status()
+}
+
+#[tokio::test]
+async fn test_pipes_publish_in_their_namespace() {
+    let dir = tempdir().unwrap();
+    write_pipe(dir.path(), "meeting-detector", json!({"enabled": true}));
+    let addr = start_server(dir.path()).await;
+    let token = issue_token("meeting-detector");
+
+    assert_eq!(publish(addr, None, "meeting-detector/ended").await, 401);
+    assert_eq!(
+        publish(addr, Some("not-a-token"), "meeting-detector/ended").await,
+        401
+    );
+    assert_eq!(
+        publish(addr, Some(&token), "meeting-detector/ended").await,
+        200
+    );
+    assert_eq!(publish(addr, Some(&token), "notes/created").await, 403);
+    assert_eq!(publish(addr, Some(&token), "no-namespace").await, 400);
+
+    // a restarted pipe gets a new token
+    let new_token = issue_token("meeting-detector");
+    assert_eq!(
+        publish(addr, Some(&token), "meeting-detector/ended").await,
+        401
+    );
+    assert_eq!(
+        publish(addr, Some(&new_token), "meeting-detector/ended").await,
+        200
+    );
+}
+
+#[tokio::test]
+async fn test_pipes_subscribe_to_allowed_topics() {
+    let dir = tempdir().unwrap();
+    write_pipe(dir.path(), "meeting-recorder", json!({"enabled": true}));
+    write_pipe(
+        dir.path(),
+        "notes-writer",
+        json!({"enabled": true, "bus": {"subscribe": ["meeting-recorder/*"]}}),
+    );
+    let addr = start_server(dir.path()).await;
+    let publisher = issue_token("meeting-recorder");
+    let subscriber = issue_token("notes-writer");
+
+    // topics outside of the permissions are refused before upgrading
+    let denied = format!(
+        "ws://{}/bus/subscribe?topics=other/*&pipe_token={}",
+        addr, subscriber
+    );
+    assert!(tokio_tungstenite::connect_async(denied).await.is_err());
+
+    let url = format!(
+        "ws://{}/bus/subscribe?topics=meeting-recorder/ended&pipe_token={}",
+        addr, subscriber
+    );
+    let (ws_stream, _) = tokio_tungstenite::connect_async(url)
+        .await
+        .expect("Failed to connect to websocket");
+    let (_write, mut read) = ws_stream.split();
+
+    // give the server time to subscribe before publishing
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    assert_eq!(
+        publish(addr, Some(&publisher), "meeting-recorder/started").await,
+        200
+    );
+    assert_eq!(
+        publish(addr, Some(&publisher), "meeting-recorder/ended").await,
+        200
+    );
+
+    let message = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            match read.next().await {
+                Some(Ok(Message::Text(text))) => {
+                    break serde_json::from_str::<Value>(&text).unwrap()
+                }
+                Some(Ok(_)) => continue,
+                other => panic!("websocket closed: {:?}", other),
+            }
+        }
+    })
+    .await
+    .expect("no message received");
+    assert_eq!(message["topic"], "meeting-recorder/ended");
+    assert_eq!(message["from"], "meeting-recorder");
+    assert_eq!(message["payload"], json!({"title": "standup"}));
+}
@@ -0,0 +1,72 @@
+use chrono::TimeZone;
+use screenpipe_core::pipe_logs::{PipeLogEntry, PipeLogLevel};
+use screenpipe_server::pipe_dev::{format_log_entry, set_dev, snapshot};
+use screenpipe_server::PipeManager;
+use serde_json::{json, Value};
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_set_dev_keeps_config() {
+    let dir = tempdir().unwrap();
+    let pipe_dir = dir.path().join("pipes").join("notes");
+    std::fs::create_dir_all(&pipe_dir).unwrap();
+    std::fs::write(
+        pipe_dir.join("pipe.json"),
+        json!({"enabled": false, "settings": {"folder": "~/notes"}}).to_string(),
+    )
+    .unwrap();
+    let pipe_manager = PipeManager::new(dir.path().to_path_buf());
+
+    set_dev(&pipe_manager, "notes", true).await.unwrap();
+    let config: Value =
+        serde_json::from_str(&std::fs::read_to_string(pipe_dir.join("pipe.json")).unwrap())
+            .unwrap();
+    assert_eq!(config["enabled"], true);
+    assert_eq!(config["dev"], true);
+    assert_eq!(config["settings"], json!({"folder": "~/notes"}));
+
+    set_dev(&pipe_manager, "notes", false).await.unwrap();
+    let config: Value =
+        serde_json::from_str(&std::fs::read_to_string(pipe_dir.join("pipe.json")).unwrap())
+            .unwrap();
+    assert_eq!(config["enabled"], false);
+    assert_eq!(config["dev"], false);
+}
+
+#[tokio::test]
+async fn test_snapshot_notices_changes() {
+    let dir = tempdir().unwrap();
+    std::fs::write(dir.path().join("pipe.ts"), "console.log(1)").unwrap();
+    let before = snapshot(dir.path()).await.unwrap();
+
+    // dependencies aren't watched
+    std::fs::create_dir_all(dir.path().join("node_modules").join("left-pad")).unwrap();
+    std::fs::write(
+        dir.path()
+            .join("node_modules")
+            .join("left-pad")
+            .join("index.js"),
+        "",
+    )
+    .unwrap();
+    assert_eq!(snapshot(dir.path()).await.unwrap(), before);
+
+    std::fs::write(dir.path().join("pipe.ts"), "console.log(2)").unwrap();
+    assert_ne!(snapshot(dir.path()).await.unwrap(), before);
+}
+
+#[test]
+fn test_format_log_entry() {
+    let entry = PipeLogEntry {
+        pipe: "notes".to_string(),
+        timestamp: chrono::Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap(),
+        level: PipeLogLevel::Warn,
+        stream: "stderr".to_string(),
+        message: "rate limited".to_string(),
+        fields: Some(json!({"retry_in": 30})),
+    };
+    assert_eq!(
+        format_log_entry(&entry),
+        r#"10:00:00.000 warn  rate limited {"retry_in":30}"#
+    );
+}
@@ -0,0 +1,66 @@
+#![cfg(unix)]
+
+use std::time::Duration;
+
+use screenpipe_events::{
+    send_event, TranscriptIndexedEvent, FRAME_INDEXED_EVENT, TRANSCRIPT_INDEXED_EVENT,
+};
+use screenpipe_server::pipe_events::PipeEvents;
+use serde_json::{json, Value};
+use tempfile::tempdir;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::UnixStream;
+
+fn transcript(transcription: &str) -> TranscriptIndexedEvent {
+    TranscriptIndexedEvent {
+        transcription_id: 1,
+        timestamp: chrono::Utc::now(),
+        device: "mic (input)".to_string(),
+        is_input: true,
+        speaker_id: None,
+        transcription: transcription.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_pipe_events_pushes_subscribed_events() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("pipes").join("invoices")).unwrap();
+
+    assert!(
+        PipeEvents::serve(dir.path(), "invoices", &json!({"enabled": true}))
+            .unwrap()
+            .is_none()
+    );
+
+    let events = PipeEvents::serve(
+        dir.path(),
+        "invoices",
+        &json!({"subscriptions": [{"type": "transcript", "pattern": "invoice"}]}),
+    )
+    .unwrap()
+    .unwrap();
+    let stream = UnixStream::connect(events.path()).await.unwrap();
+    let mut lines = BufReader::new(stream).lines();
+
+    // give the server time to accept the connection before publishing
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    send_event(TRANSCRIPT_INDEXED_EVENT, transcript("see you tomorrow")).unwrap();
+    send_event(FRAME_INDEXED_EVENT, json!({"app_name": "Mail"})).unwrap();
+    send_event(TRANSCRIPT_INDEXED_EVENT, transcript("the invoice is late")).unwrap();
+
+    let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line())
+        .await
+        .expect("no event received")
+        .unwrap()
+        .unwrap();
+    let event: Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(event["name"], TRANSCRIPT_INDEXED_EVENT);
+    assert_eq!(event["data"]["transcription"], "the invoice is late");
+
+    // the socket is removed when the pipe stops
+    let path = events.path().to_path_buf();
+    drop(events);
+    assert!(!path.exists());
+}
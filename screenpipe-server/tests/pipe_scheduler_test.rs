@@ -0,0 +1,114 @@
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use screenpipe_db::{DatabaseManager, OcrEngine};
+use screenpipe_server::pipe_manager::PipeInfo;
+use screenpipe_server::pipe_scheduler::{triggered, PipeScheduler};
+
+fn pipe(id: &str, enabled: bool, config: Value) -> PipeInfo {
+    PipeInfo {
+        id: id.to_string(),
+        enabled,
+        config,
+        source: String::new(),
+        port: None,
+        is_nextjs: false,
+        desc: String::new(),
+        build_status: None,
+    }
+}
+
+fn pipes() -> Vec<PipeInfo> {
+    vec![
+        pipe("always-on", true, json!({"enabled": true})),
+        pipe(
+            "invoices",
+            true,
+            json!({
+                "enabled": true,
+                "triggers": [{"type": "keyword", "keyword": "invoice", "app_name": "Mail"}]
+            }),
+        ),
+        pipe(
+            "meeting-notes",
+            true,
+            json!({"enabled": true, "triggers": [{"type": "meeting_ended"}]}),
+        ),
+        pipe(
+            "disabled",
+            false,
+            json!({"enabled": false, "triggers": [{"type": "meeting_ended"}]}),
+        ),
+    ]
+}
+
+async fn insert_frame_text(db: &DatabaseManager, app_name: &str, text: &str) {
+    let frame_id = db
+        .insert_frame(
+            "monitor_1",
+            Some(Utc::now()),
+            None,
+            Some(app_name),
+            Some(""),
+            true,
+        )
+        .await
+        .unwrap();
+    db.insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_keyword_triggers() {
+    let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    db.insert_video_chunk("test_video.mp4", "monitor_1")
+        .await
+        .unwrap();
+    let mut scheduler = PipeScheduler::new();
+
+    // nothing is due when first seen
+    let start = Utc::now() - Duration::seconds(1);
+    assert!(scheduler.due(&db, pipes(), start).await.is_empty());
+
+    insert_frame_text(&db, "Slack", "the invoice is overdue").await;
+    assert!(scheduler.due(&db, pipes(), Utc::now()).await.is_empty());
+
+    insert_frame_text(&db, "Mail", "invoice 1042 is overdue").await;
+    let now = Utc::now() + Duration::seconds(1);
+    assert_eq!(scheduler.due(&db, pipes(), now).await, vec!["invoices"]);
+    // fires once
+    assert!(scheduler
+        .due(&db, pipes(), now + Duration::seconds(30))
+        .await
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_schedules() {
+    let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    let mut scheduler = PipeScheduler::new();
+    let every_minute = || {
+        vec![pipe(
+            "digest",
+            true,
+            json!({"enabled": true, "schedule": "* * * * *"}),
+        )]
+    };
+
+    let now = Utc::now();
+    assert!(scheduler.due(&db, every_minute(), now).await.is_empty());
+    assert_eq!(
+        scheduler
+            .due(&db, every_minute(), now + Duration::minutes(1))
+            .await,
+        vec!["digest"]
+    );
+}
+
+#[test]
+fn test_triggered() {
+    assert_eq!(triggered(pipes(), "meeting_ended"), vec!["meeting-notes"]);
+    assert!(triggered(pipes(), "meeting_started").is_empty());
+}
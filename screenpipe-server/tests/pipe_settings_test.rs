@@ -0,0 +1,57 @@
+use axum::{http::StatusCode, Router};
+use serde_json::{json, Value};
+use tempfile::tempdir;
+
+mod common;
+use common::{request, setup_pipes_app};
+
+async fn update_config(app: &Router, config: Value) -> (StatusCode, Value) {
+    let body = json!({"pipe_id": "digest", "config": config});
+    request(app, "POST", "/pipes/update", Some(body)).await
+}
+
+#[tokio::test]
+async fn test_settings_are_checked_on_save() {
+    let dir = tempdir().unwrap();
+    let pipe_json = dir.path().join("pipes").join("digest").join("pipe.json");
+    std::fs::create_dir_all(pipe_json.parent().unwrap()).unwrap();
+    std::fs::write(
+        &pipe_json,
+        json!({
+            "enabled": false,
+            "settings_schema": {
+                "type": "object",
+                "properties": {
+                    "interval_minutes": {"type": "integer", "minimum": 1, "default": 5},
+                    "model": {"type": "string"}
+                },
+                "required": ["model"]
+            }
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let app = setup_pipes_app(dir.path()).await;
+
+    let (status, body) = update_config(
+        &app,
+        json!({"settings": {"interval_minutes": 0, "model": 3}}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["errors"],
+        json!([
+            {"path": "$.interval_minutes", "message": "must be at least 1"},
+            {"path": "$.model", "message": "expected string"}
+        ])
+    );
+    // nothing was saved
+    let saved: Value = serde_json::from_str(&std::fs::read_to_string(&pipe_json).unwrap()).unwrap();
+    assert!(saved.get("settings").is_none());
+
+    let (status, _) = update_config(&app, json!({"settings": {"model": "llama3.2"}})).await;
+    assert_eq!(status, StatusCode::OK);
+    let saved: Value = serde_json::from_str(&std::fs::read_to_string(&pipe_json).unwrap()).unwrap();
+    assert_eq!(saved["settings"], json!({"model": "llama3.2"}));
+}
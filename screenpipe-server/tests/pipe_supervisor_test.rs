@@ -0,0 +1,38 @@
+use axum::http::StatusCode;
+use sysinfo::{System, SystemExt};
+use tempfile::tempdir;
+
+use screenpipe_server::pipe_supervisor::process_tree_usage;
+
+mod common;
+use common::{request, setup_pipes_app};
+
+#[test]
+fn test_process_tree_usage() {
+    let mut sys = System::new();
+    sys.refresh_processes();
+    let (memory, _) = process_tree_usage(&sys, std::process::id()).unwrap();
+    assert!(memory > 0);
+    assert!(process_tree_usage(&sys, u32::MAX).is_none());
+}
+
+#[tokio::test]
+async fn test_pipe_stats_endpoint() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("pipes").join("reminders")).unwrap();
+    std::fs::write(
+        dir.path().join("pipes").join("reminders").join("pipe.json"),
+        r#"{"enabled": false, "limits": {"max_memory_mb": 256}}"#,
+    )
+    .unwrap();
+    let app = setup_pipes_app(dir.path()).await;
+
+    let (status, body) = request(&app, "GET", "/pipes/reminders/stats", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["running"], false);
+    assert_eq!(body["data"]["restarts"], serde_json::json!([]));
+    assert!(body["data"]["killed"].is_null());
+
+    let (status, _) = request(&app, "GET", "/pipes/missing/stats", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
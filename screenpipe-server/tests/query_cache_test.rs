@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use chrono::{FixedOffset, TimeZone, Utc};
+use screenpipe_server::query_cache::{QueryCache, QueryKey};
+
+#[test]
+fn test_query_key_normalization() {
+    let start = Utc.with_ymd_and_hms(2025, 4, 22, 8, 0, 0).unwrap();
+    let same_start = FixedOffset::east_opt(2 * 3600)
+        .unwrap()
+        .with_ymd_and_hms(2025, 4, 22, 10, 0, 0)
+        .unwrap()
+        .with_timezone(&Utc);
+
+    let key = QueryKey::new("search_count")
+        .param("q", Some(" invoice "))
+        .param("app_name", None::<&str>)
+        .time("start_time", Some(start));
+    let same = QueryKey::new("search_count")
+        .time("start_time", Some(same_start))
+        .param("app_name", Some(""))
+        .param("q", Some("invoice"));
+    assert_eq!(key, same);
+    assert_eq!(
+        key.to_string(),
+        format!(
+            "search_count?q=invoice&start_time={}",
+            start.timestamp_micros()
+        )
+    );
+
+    assert_ne!(
+        key,
+        QueryKey::new("search_count").param("q", Some("invoice"))
+    );
+    assert_ne!(
+        key,
+        QueryKey::new("activity_summary").param("q", Some("invoice"))
+    );
+}
+
+#[tokio::test]
+async fn test_cached_until_invalidated() {
+    let cache = QueryCache::new(Duration::from_secs(60));
+    let start = Utc.with_ymd_and_hms(2025, 4, 22, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2025, 4, 23, 0, 0, 0).unwrap();
+    let key = || QueryKey::new("count").time("start_time", Some(start));
+
+    let count: Result<i64, ()> = cache
+        .get_or_try_insert(key(), Some(start), Some(end), async { Ok(3) })
+        .await;
+    assert_eq!(count, Ok(3));
+    let count: Result<i64, ()> = cache
+        .get_or_try_insert(key(), Some(start), Some(end), async { Ok(4) })
+        .await;
+    assert_eq!(count, Ok(3));
+
+    // recorded outside the range
+    assert_eq!(cache.invalidate(end + chrono::Duration::hours(1)), 0);
+    assert_eq!(cache.get::<i64>(&key()), Some(3));
+
+    assert_eq!(cache.invalidate(start + chrono::Duration::hours(1)), 1);
+    assert_eq!(cache.get::<i64>(&key()), None);
+}
+
+#[test]
+fn test_open_ranges_are_invalidated_by_anything_on_their_side() {
+    let cache = QueryCache::new(Duration::from_secs(60));
+    let start = Utc.with_ymd_and_hms(2025, 4, 22, 0, 0, 0).unwrap();
+    cache.insert(QueryKey::new("all"), None, None, &1);
+    cache.insert(QueryKey::new("since"), Some(start), None, &2);
+
+    assert_eq!(cache.invalidate(start - chrono::Duration::days(1)), 1);
+    assert_eq!(cache.get::<i32>(&QueryKey::new("since")), Some(2));
+    assert_eq!(cache.invalidate(Utc::now()), 1);
+    assert_eq!(cache.get::<i32>(&QueryKey::new("since")), None);
+}
+
+#[tokio::test]
+async fn test_failures_and_zero_ttl_are_not_cached() {
+    let cache = QueryCache::new(Duration::from_secs(60));
+    let failed: Result<i64, &str> = cache
+        .get_or_try_insert(QueryKey::new("count"), None, None, async { Err("locked") })
+        .await;
+    assert_eq!(failed, Err("locked"));
+    assert_eq!(cache.get::<i64>(&QueryKey::new("count")), None);
+
+    let disabled = QueryCache::new(Duration::ZERO);
+    disabled.insert(QueryKey::new("count"), None, None, &1);
+    assert_eq!(disabled.get::<i64>(&QueryKey::new("count")), None);
+}
@@ -0,0 +1,173 @@
+use axum::{
+    extract::State,
+    http::{Method, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+use screenpipe_core::answering::Answerer;
+use screenpipe_core::llm_client::LlmClient;
+use screenpipe_db::{
+    AudioResult, DeviceType, OCRResult, SearchResult, SemanticSearchResult, Speaker,
+};
+use screenpipe_server::query::answer;
+
+mod common;
+use common::{request, setup_test_app, setup_test_app_with, setup_test_db};
+
+/// Serves an Ollama answering every question the same, citing the second
+/// and a missing excerpt, returning its url and the prompts it got.
+async fn serve_ollama() -> (String, Arc<Mutex<Vec<String>>>) {
+    let prompts = Arc::new(Mutex::new(Vec::new()));
+    let app = Router::new()
+        .route(
+            "/api/generate",
+            post(
+                |State(prompts): State<Arc<Mutex<Vec<String>>>>, Json(body): Json<Value>| async move {
+                    prompts
+                        .lock()
+                        .unwrap()
+                        .push(body["prompt"].as_str().unwrap().to_string());
+                    Json(json!({
+                        "response": json!({
+                            "answer": "Ana said the launch moved to friday [2].",
+                            "citations": [2, 9],
+                        })
+                        .to_string()
+                    }))
+                },
+            ),
+        )
+        .with_state(prompts.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{}", addr), prompts)
+}
+
+fn ocr_hit(frame_id: i64, app_name: &str, text: &str) -> SemanticSearchResult {
+    SemanticSearchResult {
+        result: SearchResult::OCR(OCRResult {
+            frame_id,
+            frame_name: String::new(),
+            ocr_text: text.to_string(),
+            text_json: String::new(),
+            timestamp: Utc.with_ymd_and_hms(2025, 4, 22, 12, 0, 0).unwrap(),
+            file_path: String::new(),
+            offset_index: 0,
+            app_name: app_name.to_string(),
+            ocr_engine: String::new(),
+            window_name: "general".to_string(),
+            tags: vec![],
+            browser_url: None,
+            browser_context: None,
+            focused: Some(true),
+            device_name: "monitor_1".to_string(),
+            rank: None,
+            snippet: None,
+        }),
+        score: 0.03,
+    }
+}
+
+fn audio_hit(transcription_id: i64, text: &str) -> SemanticSearchResult {
+    SemanticSearchResult {
+        result: SearchResult::Audio(AudioResult {
+            transcription_id,
+            audio_chunk_id: 1,
+            transcription: text.to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 4, 22, 13, 0, 0).unwrap(),
+            file_path: String::new(),
+            offset_index: 0,
+            transcription_engine: String::new(),
+            tags: vec![],
+            device_name: "mic".to_string(),
+            device_type: DeviceType::Input,
+            speaker: Some(Speaker {
+                id: 1,
+                name: "Ana".to_string(),
+                metadata: String::new(),
+            }),
+            start_time: None,
+            end_time: None,
+            language: None,
+            translated_text: None,
+            rank: None,
+            snippet: None,
+        }),
+        score: 0.02,
+    }
+}
+
+#[tokio::test]
+async fn test_answer() {
+    let (url, prompts) = serve_ollama().await;
+    let answerer = Answerer::new(LlmClient::ollama(&url, "llama3.2"));
+
+    let results = vec![
+        ocr_hit(7, "Slack", "launch plan draft"),
+        // no text to answer from
+        ocr_hit(8, "Slack", "  "),
+        audio_hit(42, "the launch moved to friday"),
+    ];
+    let response = answer(&answerer, "when is the launch?", &results)
+        .await
+        .unwrap()
+        .unwrap();
+    {
+        let prompts = prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("[1] (screen, Slack, "));
+        assert!(prompts[0].contains("[2] (audio, Ana, "));
+        assert!(prompts[0].contains("the launch moved to friday"));
+        assert!(prompts[0].contains("when is the launch?"));
+    }
+    assert_eq!(response.answer, "Ana said the launch moved to friday [2].");
+    assert_eq!(response.model, "llama3.2");
+    // citations of missing excerpts are dropped
+    assert_eq!(response.citations.len(), 1);
+    let citation = &response.citations[0];
+    assert_eq!(citation.number, 2);
+    assert_eq!(citation.content_type, "audio");
+    assert_eq!(citation.transcription_id, Some(42));
+    assert_eq!(citation.frame_id, None);
+    assert_eq!(citation.speaker_name.as_deref(), Some("Ana"));
+    assert_eq!(citation.text, "the launch moved to friday");
+
+    // nothing to answer from
+    assert!(answer(&answerer, "anything?", &[ocr_hit(9, "Code", "")])
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(prompts.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_query_endpoint() {
+    let app = setup_test_app(setup_test_db().await).await;
+    let (status, body) = request(
+        &app,
+        Method::POST,
+        "/query",
+        Some(json!({"question": "when is the launch?"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(body["error"].as_str().unwrap().contains("--enable-query"));
+
+    let (url, _) = serve_ollama().await;
+    let answerer = Arc::new(Answerer::new(LlmClient::ollama(&url, "llama3.2")));
+    let app = setup_test_app_with(setup_test_db().await, |app| app.with_answerer(answerer)).await;
+    let (status, body) = request(
+        &app,
+        Method::POST,
+        "/query",
+        Some(json!({"question": "  "})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "question must not be empty");
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use screenpipe_core::pii_removal::{PiiKind, Redactor, RegexDetector};
+use screenpipe_db::{
+    AccessibilityNode, ExtractedTable, OcrLayoutBlock, OcrLayoutLine, OcrLayoutParagraph,
+    TextBounds, TextSource,
+};
+use screenpipe_server::redaction::{redact_capture, Redaction};
+use screenpipe_vision::{core::WindowOcrResult, CaptureResult};
+
+/// Black and white stripes, so blurring visibly changes the pixels.
+fn striped_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, _| {
+        if x % 2 == 0 {
+            Rgba([0, 0, 0, 255])
+        } else {
+            Rgba([255, 255, 255, 255])
+        }
+    }))
+}
+
+fn line(text: &str, top: f32) -> OcrLayoutLine {
+    OcrLayoutLine {
+        text: text.to_string(),
+        confidence: 0.9,
+        bounds: TextBounds {
+            left: 0.0,
+            top,
+            width: 0.5,
+            height: 0.25,
+        },
+        source: TextSource::Ocr,
+    }
+}
+
+fn capture(frame_rect: Option<(u32, u32, u32, u32)>) -> CaptureResult {
+    let lines = vec![line("mail me at jane@example.com", 0.0), line("hello", 0.5)];
+    CaptureResult {
+        image: striped_image(200, 100),
+        frame_number: 1,
+        timestamp: Instant::now(),
+        window_ocr_results: vec![WindowOcrResult {
+            image: striped_image(100, 40),
+            window_name: "inbox".to_string(),
+            app_name: "mail".to_string(),
+            text: "mail me at jane@example.com hello".to_string(),
+            text_json: vec![HashMap::from([(
+                "text".to_string(),
+                "jane@example.com".to_string(),
+            )])],
+            layout: vec![OcrLayoutBlock {
+                reading_order: 0,
+                bounds: TextBounds::default(),
+                paragraphs: vec![OcrLayoutParagraph {
+                    bounds: TextBounds::default(),
+                    lines,
+                }],
+            }],
+            tables: vec![ExtractedTable {
+                bounds: TextBounds::default(),
+                rows: vec![vec!["ssn".to_string(), "123-45-6789".to_string()]],
+            }],
+            focused: true,
+            confidence: 0.9,
+            browser_context: None,
+            accessibility_tree: Some(AccessibilityNode {
+                role: "AXWindow".to_string(),
+                subrole: None,
+                label: Some("inbox".to_string()),
+                value: None,
+                focused: false,
+                children: vec![AccessibilityNode {
+                    role: "AXTextField".to_string(),
+                    subrole: None,
+                    label: Some("to".to_string()),
+                    value: Some("jane@example.com".to_string()),
+                    focused: true,
+                    children: vec![],
+                }],
+            }),
+            frame_rect,
+            ocr_deferred: false,
+        }],
+        phash: 0,
+        repeat_of_previous: false,
+        span: tracing::Span::none(),
+    }
+}
+
+fn redaction(blur_frames: bool) -> Redaction {
+    Redaction {
+        redactor: Arc::new(Redactor::new(vec![Box::new(RegexDetector::new(&[
+            PiiKind::Email,
+            PiiKind::Ssn,
+        ]))])),
+        blur_frames,
+    }
+}
+
+#[test]
+fn test_redact_capture_replaces_text() {
+    let mut result = capture(Some((100, 60, 100, 40)));
+    redact_capture(&mut result, &redaction(false));
+
+    let window = &result.window_ocr_results[0];
+    assert_eq!(window.text, "mail me at [EMAIL] hello");
+    assert_eq!(window.text_json[0]["text"], "[EMAIL]");
+    assert_eq!(
+        window.layout[0].paragraphs[0].lines[0].text,
+        "mail me at [EMAIL]"
+    );
+    assert_eq!(window.tables[0].rows[0][1], "[SSN]");
+    let tree = window.accessibility_tree.as_ref().unwrap();
+    assert_eq!(tree.children[0].value.as_deref(), Some("[EMAIL]"));
+    assert_eq!(tree.label.as_deref(), Some("inbox"));
+
+    // frames are left alone unless blurring is enabled
+    assert_eq!(result.image.to_rgba8(), striped_image(200, 100).to_rgba8());
+}
+
+#[test]
+fn test_redact_capture_blurs_lines_with_pii() {
+    let mut result = capture(Some((100, 60, 100, 40)));
+    redact_capture(&mut result, &redaction(true));
+
+    let original = striped_image(200, 100);
+    // the email line covers x 100..150, y 60..70 of the frame
+    assert_ne!(result.image.get_pixel(110, 65), original.get_pixel(110, 65));
+    // the line without pii and the rest of the frame are untouched
+    assert_eq!(result.image.get_pixel(110, 85), original.get_pixel(110, 85));
+    assert_eq!(result.image.get_pixel(10, 10), original.get_pixel(10, 10));
+
+    let window = &result.window_ocr_results[0].image;
+    let original = striped_image(100, 40);
+    assert_ne!(window.get_pixel(10, 5), original.get_pixel(10, 5));
+    assert_eq!(window.get_pixel(10, 25), original.get_pixel(10, 25));
+}
+
+#[test]
+fn test_redact_capture_without_frame_rect_keeps_frame() {
+    let mut result = capture(None);
+    redact_capture(&mut result, &redaction(true));
+
+    assert_eq!(result.image.to_rgba8(), striped_image(200, 100).to_rgba8());
+    assert_eq!(
+        result.window_ocr_results[0].text,
+        "mail me at [EMAIL] hello"
+    );
+}
@@ -0,0 +1,143 @@
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use screenpipe_db::{DatabaseManager, OcrEngine};
+use screenpipe_server::saved_search::{check_saved_searches, SavedSearchNotifier};
+
+mod common;
+use common::{request, setup_test_app, setup_test_db};
+
+/// Serves a webhook on a free port, returning its url and what it received.
+async fn serve_webhook() -> (String, Arc<Mutex<Vec<Value>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let app = Router::new()
+        .route(
+            "/hook",
+            post(
+                |State(received): State<Arc<Mutex<Vec<Value>>>>, Json(body): Json<Value>| async move {
+                    received.lock().unwrap().push(body);
+                    StatusCode::OK
+                },
+            ),
+        )
+        .with_state(received.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{}/hook", addr), received)
+}
+
+async fn insert_frame_text(db: &DatabaseManager, app_name: &str, text: &str) {
+    let frame_id = db
+        .insert_frame(
+            "monitor_1",
+            Some(Utc::now()),
+            None,
+            Some(app_name),
+            Some(""),
+            true,
+        )
+        .await
+        .unwrap();
+    db.insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_saved_search_api() {
+    let app = setup_test_app(setup_test_db().await).await;
+
+    let (status, _) = request(
+        &app,
+        "POST",
+        "/search/saved",
+        Some(json!({"name": "everything"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = request(
+        &app,
+        "POST",
+        "/search/saved",
+        Some(json!({"name": "invoices", "q": "invoice", "webhook_url": "ftp://example.com"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = request(
+        &app,
+        "POST",
+        "/search/saved",
+        Some(json!({
+            "name": "invoices",
+            "q": "invoice overdue",
+            "content_type": "ocr",
+            "app_name": "Slack",
+            "notify": false,
+            "webhook_url": "http://localhost:9999/hook"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let id = body["data"]["id"].as_i64().unwrap();
+    assert_eq!(body["data"]["content_type"], "ocr");
+    assert_eq!(body["data"]["notify"], false);
+
+    let (status, body) = request(&app, "GET", "/search/saved", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["app_name"], "Slack");
+
+    let uri = format!("/search/saved/{}", id);
+    let (status, _) = request(&app, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = request(&app, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_saved_search_fires_webhook_on_new_matches() {
+    let db = setup_test_db().await;
+    db.insert_video_chunk("test_video.mp4", "monitor_1")
+        .await
+        .unwrap();
+    let (webhook_url, received) = serve_webhook().await;
+    let search = db
+        .insert_saved_search(
+            "overdue invoices",
+            "invoice overdue",
+            "all",
+            Some("Slack"),
+            None,
+            None,
+            false,
+            Some(&webhook_url),
+        )
+        .await
+        .unwrap();
+    // nothing can be notified on this url, notify is off
+    let notifier = SavedSearchNotifier::new("http://127.0.0.1:9/notify".to_string());
+
+    assert_eq!(check_saved_searches(&db, &notifier).await.unwrap(), 0);
+    insert_frame_text(&db, "Mail", "invoice 1042 is overdue").await;
+    insert_frame_text(&db, "Slack", "the invoice is overdue, can you check").await;
+    assert_eq!(check_saved_searches(&db, &notifier).await.unwrap(), 1);
+    {
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0]["saved_search"]["id"], search.id);
+        let matches = received[0]["matches"].as_array().unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0]["OCR"]["app_name"], "Slack");
+    }
+
+    // what was already reported isn't reported again
+    assert_eq!(check_saved_searches(&db, &notifier).await.unwrap(), 0);
+    let searches = db.list_saved_searches().await.unwrap();
+    assert!(searches[0].last_checked_at.is_some());
+    assert!(searches[0].last_matched_at.is_some());
+    assert_eq!(received.lock().unwrap().len(), 1);
+}
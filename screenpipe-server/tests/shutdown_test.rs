@@ -0,0 +1,29 @@
+use axum::{
+    http::{Method, StatusCode},
+    Router,
+};
+use tokio::sync::broadcast;
+
+mod common;
+use common::{request, setup_test_app, setup_test_app_with, setup_test_db};
+
+async fn post_shutdown(app: &Router) -> StatusCode {
+    request(app, Method::POST, "/admin/shutdown", None).await.0
+}
+
+#[tokio::test]
+async fn test_shutdown_endpoint_signals_shutdown() {
+    let (shutdown_tx, mut shutdown_rx) = broadcast::channel::<()>(1);
+    let app =
+        setup_test_app_with(setup_test_db().await, |app| app.with_shutdown(shutdown_tx)).await;
+
+    assert_eq!(post_shutdown(&app).await, StatusCode::OK);
+    assert!(shutdown_rx.try_recv().is_ok());
+}
+
+#[tokio::test]
+async fn test_shutdown_endpoint_unavailable() {
+    let app = setup_test_app(setup_test_db().await).await;
+
+    assert_eq!(post_shutdown(&app).await, StatusCode::NOT_FOUND);
+}
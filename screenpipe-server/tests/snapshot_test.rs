@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{Duration, Utc};
+use screenpipe_db::DatabaseManager;
+use screenpipe_server::snapshot::{export_snapshot, import_snapshot, SnapshotOptions};
+
+/// An instance with a recording from ten days ago and one from now.
+async fn setup_instance(data_dir: &Path) -> DatabaseManager {
+    let db = DatabaseManager::new(data_dir.join("db.sqlite").to_str().unwrap())
+        .await
+        .unwrap();
+    fs::create_dir_all(data_dir.join("data")).unwrap();
+    for (name, timestamp) in [
+        ("old.mp4", Utc::now() - Duration::days(10)),
+        ("new.mp4", Utc::now()),
+    ] {
+        let path = data_dir.join("data").join(name);
+        fs::write(&path, format!("recording {}", name)).unwrap();
+        db.insert_video_chunk(path.to_str().unwrap(), "monitor_1")
+            .await
+            .unwrap();
+        db.insert_frame("monitor_1", Some(timestamp), None, Some("Code"), None, true)
+            .await
+            .unwrap();
+    }
+    db
+}
+
+async fn chunk_paths(data_dir: &Path) -> Vec<String> {
+    let db = DatabaseManager::new(data_dir.join("db.sqlite").to_str().unwrap())
+        .await
+        .unwrap();
+    let paths = sqlx::query_scalar("SELECT file_path FROM video_chunks ORDER BY id")
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+    db.pool.close().await;
+    paths
+}
+
+#[tokio::test]
+async fn test_snapshot_export_and_import() {
+    let source = tempfile::tempdir().unwrap();
+    let db = setup_instance(source.path()).await;
+    let archives = tempfile::tempdir().unwrap();
+    let archive = archives.path().join("snapshot.tar");
+
+    let export = export_snapshot(&db, source.path(), &archive, &SnapshotOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(export.manifest.files.len(), 3);
+    assert_eq!(export.resumed_files, 0);
+    assert!(archive.exists());
+    assert!(
+        export_snapshot(&db, source.path(), &archive, &SnapshotOptions::default())
+            .await
+            .is_err()
+    );
+
+    let target = tempfile::tempdir().unwrap();
+    let import = import_snapshot(&archive, target.path(), None)
+        .await
+        .unwrap();
+    assert_eq!(import.imported_files, 3);
+    assert!(import.previous_database.is_none());
+    assert_eq!(
+        fs::read(target.path().join("data/new.mp4")).unwrap(),
+        b"recording new.mp4"
+    );
+    // the chunks point to the files on the new machine
+    let paths = chunk_paths(target.path()).await;
+    assert_eq!(paths.len(), 2);
+    assert!(paths
+        .iter()
+        .all(|path| path.starts_with(target.path().to_str().unwrap())));
+
+    // importing again skips the recordings in place and keeps the database
+    // it replaces
+    let again = import_snapshot(&archive, target.path(), None)
+        .await
+        .unwrap();
+    assert_eq!((again.imported_files, again.resumed_files), (1, 2));
+    assert!(again.previous_database.unwrap().exists());
+}
+
+#[tokio::test]
+async fn test_snapshot_time_range() {
+    let source = tempfile::tempdir().unwrap();
+    let db = setup_instance(source.path()).await;
+    let archives = tempfile::tempdir().unwrap();
+    let archive = archives.path().join("recent.tar");
+
+    let options = SnapshotOptions {
+        start: Some(Utc::now() - Duration::days(1)),
+        ..Default::default()
+    };
+    let export = export_snapshot(&db, source.path(), &archive, &options)
+        .await
+        .unwrap();
+    assert_eq!(export.manifest.files.len(), 2);
+
+    let target = tempfile::tempdir().unwrap();
+    import_snapshot(&archive, target.path(), None)
+        .await
+        .unwrap();
+    assert!(target.path().join("data/new.mp4").exists());
+    assert!(!target.path().join("data/old.mp4").exists());
+    assert_eq!(chunk_paths(target.path()).await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_snapshot_resumes_interrupted_export() {
+    let source = tempfile::tempdir().unwrap();
+    let db = setup_instance(source.path()).await;
+    let archives = tempfile::tempdir().unwrap();
+    let complete = archives.path().join("complete.tar");
+    export_snapshot(&db, source.path(), &complete, &SnapshotOptions::default())
+        .await
+        .unwrap();
+
+    // cut in the middle of the last file, before the end of the archive
+    let bytes = fs::read(&complete).unwrap();
+    let archive = archives.path().join("snapshot.tar");
+    fs::write(
+        archives.path().join("snapshot.tar.partial"),
+        &bytes[..bytes.len() - 1024 - 256],
+    )
+    .unwrap();
+
+    let export = export_snapshot(&db, source.path(), &archive, &SnapshotOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(export.resumed_files, 2);
+    assert_eq!(export.written_files, 1);
+    assert!(!archives.path().join("snapshot.tar.partial").exists());
+
+    let target = tempfile::tempdir().unwrap();
+    let import = import_snapshot(&archive, target.path(), None)
+        .await
+        .unwrap();
+    assert_eq!(import.imported_files, 3);
+}
@@ -0,0 +1,42 @@
+use chrono::{Duration, TimeZone, Utc};
+use screenpipe_db::{MediaChunk, MediaType};
+use screenpipe_server::storage::{directory_size, oldest_first};
+
+fn chunk(id: i64, minutes: i64) -> MediaChunk {
+    MediaChunk {
+        id,
+        file_path: format!("{}.mp4", id),
+        timestamp: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap() + Duration::minutes(minutes),
+    }
+}
+
+#[test]
+fn test_oldest_first_interleaves_video_and_audio() {
+    let chunks = oldest_first(
+        vec![chunk(1, 0), chunk(2, 10)],
+        vec![chunk(3, 5), chunk(4, 20)],
+    );
+    let order: Vec<(MediaType, i64)> = chunks
+        .iter()
+        .map(|(media_type, chunk)| (*media_type, chunk.id))
+        .collect();
+    assert_eq!(
+        order,
+        vec![
+            (MediaType::Video, 1),
+            (MediaType::Audio, 3),
+            (MediaType::Video, 2),
+            (MediaType::Audio, 4),
+        ]
+    );
+}
+
+#[test]
+fn test_directory_size_counts_nested_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("db.sqlite"), vec![0u8; 100]).unwrap();
+    std::fs::create_dir(dir.path().join("data")).unwrap();
+    std::fs::write(dir.path().join("data").join("chunk.mp4"), vec![0u8; 250]).unwrap();
+
+    assert_eq!(directory_size(dir.path()), 350);
+}
@@ -0,0 +1,249 @@
+use axum::{
+    extract::State,
+    http::{Method, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+use screenpipe_core::summarization::Summarizer;
+use screenpipe_db::{AudioDevice, DatabaseManager, DeviceType, OcrEngine};
+use screenpipe_server::export::local_day_start;
+use screenpipe_server::summaries::{summarize, summarize_missing, SummaryPeriod};
+
+mod common;
+use common::{request, setup_test_app, setup_test_app_with, setup_test_db};
+
+/// Serves an Ollama answering every prompt with the same summary, returning
+/// its url and the prompts it got.
+async fn serve_ollama() -> (String, Arc<Mutex<Vec<String>>>) {
+    let prompts = Arc::new(Mutex::new(Vec::new()));
+    let app = Router::new()
+        .route(
+            "/api/generate",
+            post(
+                |State(prompts): State<Arc<Mutex<Vec<String>>>>, Json(body): Json<Value>| async move {
+                    assert_eq!(body["model"], "llama3.2");
+                    assert_eq!(body["format"], "json");
+                    prompts
+                        .lock()
+                        .unwrap()
+                        .push(body["prompt"].as_str().unwrap().to_string());
+                    Json(json!({
+                        "response": json!({
+                            "summary": "Reviewed the roadmap with Ana.",
+                            "topics": ["roadmap"],
+                            "action_items": ["send Ana the launch plan"],
+                            "people": ["Ana"],
+                        })
+                        .to_string()
+                    }))
+                },
+            ),
+        )
+        .with_state(prompts.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{}", addr), prompts)
+}
+
+fn date(value: &str) -> NaiveDate {
+    value.parse().unwrap()
+}
+
+fn local_noon(date: NaiveDate) -> DateTime<Local> {
+    local_day_start(date).unwrap().with_timezone(&Local) + Duration::hours(12)
+}
+
+/// Records a call on the afternoon of `day`.
+async fn record_day(db: &DatabaseManager, day: NaiveDate) {
+    let noon = local_noon(day).with_timezone(&Utc);
+    db.insert_video_chunk("test_video.mp4", "monitor_1")
+        .await
+        .unwrap();
+    let frame_id = db
+        .insert_frame(
+            "monitor_1",
+            Some(noon),
+            None,
+            Some("zoom.us"),
+            Some("Zoom Meeting"),
+            true,
+        )
+        .await
+        .unwrap();
+    db.insert_ocr_text(frame_id, "Q3 roadmap", "", Arc::new(OcrEngine::Tesseract))
+        .await
+        .unwrap();
+    let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+    let transcription_id = db
+        .insert_audio_transcription(
+            audio_chunk_id,
+            "ana, can you send me the launch plan",
+            0,
+            "",
+            &AudioDevice {
+                name: "mic".to_string(),
+                device_type: DeviceType::Input,
+            },
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    sqlx::query("UPDATE audio_transcriptions SET timestamp = ?1 WHERE id = ?2")
+        .bind(noon + Duration::minutes(5))
+        .bind(transcription_id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+}
+
+#[test]
+fn test_period_bounds() {
+    // a wednesday
+    let wednesday = date("2025-04-23");
+    assert_eq!(SummaryPeriod::Day.first_day(wednesday), wednesday);
+    assert_eq!(SummaryPeriod::Week.first_day(wednesday), date("2025-04-21"));
+    assert_eq!(
+        SummaryPeriod::Week.first_day(date("2025-04-27")),
+        date("2025-04-21")
+    );
+    assert_eq!(
+        SummaryPeriod::Week.bounds(wednesday).unwrap(),
+        (
+            local_day_start(date("2025-04-21")).unwrap(),
+            local_day_start(date("2025-04-28")).unwrap()
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_summaries() {
+    let db = setup_test_db().await;
+    let (url, prompts) = serve_ollama().await;
+    let summarizer = Arc::new(Summarizer::ollama(&url, "llama3.2"));
+    let wednesday = date("2025-04-23");
+    record_day(&db, wednesday).await;
+
+    // yesterday waits until the summary hour
+    let thursday_noon = local_noon(date("2025-04-24"));
+    assert_eq!(
+        summarize_missing(&db, &summarizer, 13, thursday_noon)
+            .await
+            .unwrap(),
+        0
+    );
+    assert!(prompts.lock().unwrap().is_empty());
+
+    assert_eq!(
+        summarize_missing(&db, &summarizer, 3, thursday_noon)
+            .await
+            .unwrap(),
+        1
+    );
+    {
+        let prompts = prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("Wednesday 23 April 2025"));
+        assert!(prompts[0].contains("ana, can you send me the launch plan"));
+        assert!(prompts[0].contains("Q3 roadmap"));
+    }
+    let (start, end) = SummaryPeriod::Day.bounds(wednesday).unwrap();
+    let day = db.get_summary("day", start).await.unwrap().unwrap();
+    assert_eq!(day.end_time, end);
+    assert_eq!(day.summary, "Reviewed the roadmap with Ana.");
+    assert_eq!(day.action_items, vec!["send Ana the launch plan"]);
+    assert_eq!(day.people, vec!["Ana"]);
+    assert_eq!(day.model, "llama3.2");
+
+    // summaries are written once
+    assert_eq!(
+        summarize_missing(&db, &summarizer, 3, thursday_noon)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(prompts.lock().unwrap().len(), 1);
+
+    // nothing recorded
+    assert!(
+        summarize(&db, &summarizer, SummaryPeriod::Day, date("2025-04-22"))
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // the week is summarized from its days on the next monday
+    let monday_noon = local_noon(date("2025-04-28"));
+    assert_eq!(
+        summarize_missing(&db, &summarizer, 3, monday_noon)
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(prompts.lock().unwrap()[1].contains("Wednesday: Reviewed the roadmap with Ana."));
+    let (start, _) = SummaryPeriod::Week.bounds(wednesday).unwrap();
+    assert!(db.get_summary("week", start).await.unwrap().is_some());
+
+    let app = setup_test_app_with(db.clone(), |app| app.with_summarizer(summarizer.clone())).await;
+    let (status, body) = request(&app, Method::GET, "/summaries", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+    // the latest start first
+    assert_eq!(body["data"][0]["period"], "day");
+    assert_eq!(body["data"][0]["topics"], json!(["roadmap"]));
+    assert_eq!(body["data"][1]["period"], "week");
+
+    let (_, body) = request(&app, Method::GET, "/summaries?period=day", None).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["id"], day.id);
+
+    let (status, body) = request(
+        &app,
+        Method::POST,
+        "/summaries",
+        Some(json!({"period": "day", "date": "2025-04-23"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["id"], day.id);
+    assert_eq!(prompts.lock().unwrap().len(), 3);
+
+    let (status, _) = request(
+        &app,
+        Method::POST,
+        "/summaries",
+        Some(json!({"period": "day", "date": "2025-04-22"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = request(
+        &app,
+        Method::POST,
+        "/summaries",
+        Some(json!({"period": "day", "date": "yesterday"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let app = setup_test_app(db.clone()).await;
+    let (status, body) = request(
+        &app,
+        Method::POST,
+        "/summaries",
+        Some(json!({"period": "day"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(body["error"]
+        .as_str()
+        .unwrap()
+        .contains("--enable-summaries"));
+}
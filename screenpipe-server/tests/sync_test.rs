@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use screenpipe_core::encryption::EncryptionKey;
+use screenpipe_db::{AudioDevice, DatabaseManager, DeviceType, OcrEngine, SyncCursor};
+use screenpipe_server::sync::{SyncService, SYNC_BATCH_SIZE};
+
+mod common;
+use common::setup_test_db;
+
+async fn insert_test_data(db: &DatabaseManager) {
+    db.insert_video_chunk("test_video.mp4", "monitor_1")
+        .await
+        .unwrap();
+    let frame_id = db
+        .insert_frame(
+            "monitor_1",
+            Some(Utc::now()),
+            None,
+            Some("Notes"),
+            Some("groceries"),
+            true,
+        )
+        .await
+        .unwrap();
+    db.insert_ocr_text(frame_id, "buy oat milk", "", Arc::new(OcrEngine::Tesseract))
+        .await
+        .unwrap();
+    let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+    db.insert_audio_transcription(
+        audio_chunk_id,
+        "remember the milk",
+        0,
+        "",
+        &AudioDevice {
+            name: "mic".to_string(),
+            device_type: DeviceType::Input,
+        },
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_pulled_records_are_imported_once() {
+    let key = EncryptionKey::generate();
+    let (desktop_db, laptop_db) = (setup_test_db().await, setup_test_db().await);
+    insert_test_data(&desktop_db).await;
+    let desktop = SyncService::new(desktop_db.clone(), &key).await.unwrap();
+    let laptop = SyncService::new(laptop_db.clone(), &key).await.unwrap();
+    assert_ne!(desktop.device_id(), laptop.device_id());
+
+    let body = desktop
+        .changes(SyncCursor::default(), SYNC_BATCH_SIZE)
+        .await
+        .unwrap();
+    let batch = laptop.open(&body).unwrap();
+    assert_eq!(batch.device_id, desktop.device_id());
+    assert_eq!(batch.frames.len(), 1);
+    assert_eq!(batch.transcripts.len(), 1);
+
+    let peer = "https://desktop.local:3031";
+    assert_eq!(laptop_db.import_sync_batch(peer, &batch).await.unwrap(), 2);
+    // pulling the same batch again changes nothing
+    assert_eq!(laptop_db.import_sync_batch(peer, &batch).await.unwrap(), 0);
+    assert_eq!(
+        laptop_db.get_sync_cursor(peer).await.unwrap(),
+        batch.next_cursor
+    );
+
+    let frames = laptop_db.get_frames(None, None, None, 10, 0).await.unwrap();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].ocr_text.as_deref(), Some("buy oat milk"));
+    assert_eq!(frames[0].device_name, format!("{}/monitor_1", batch.host));
+
+    // imported records aren't sent back
+    assert!(laptop_db.get_sync_frames(0, 10).await.unwrap().is_empty());
+    assert!(laptop_db
+        .get_sync_transcriptions(0, 10)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_batches_need_the_same_key() {
+    let db = setup_test_db().await;
+    insert_test_data(&db).await;
+    let desktop = SyncService::new(db, &EncryptionKey::generate())
+        .await
+        .unwrap();
+    let stranger = SyncService::new(setup_test_db().await, &EncryptionKey::generate())
+        .await
+        .unwrap();
+
+    let body = desktop
+        .changes(SyncCursor::default(), SYNC_BATCH_SIZE)
+        .await
+        .unwrap();
+    assert!(stranger.open(&body).is_err());
+}
@@ -149,7 +149,7 @@ async fn test_add_tags_and_search() {
                 assert!(audio.tags.contains(&"test".to_string()));
                 assert!(audio.tags.contains(&"audio".to_string()));
             }
-            ContentItem::UI(_) => {
+            ContentItem::UI(_) | ContentItem::Table(_) | ContentItem::Clipboard(_) => {
                 unreachable!()
             }
         }
@@ -352,6 +352,12 @@ async fn test_search_by_multiple_tags() {
             ContentItem::UI(_) => {
                 panic!("UI content should not be included in the results");
             }
+            ContentItem::Table(_) => {
+                panic!("Table content should not be included in the results");
+            }
+            ContentItem::Clipboard(_) => {
+                panic!("Clipboard content should not be included in the results");
+            }
         }
     }
 }
@@ -402,6 +408,8 @@ async fn insert_test_data(db: &Arc<DatabaseManager>) {
         None,
         None,
         None,
+        None,
+        None,
     )
     .await
     .unwrap();
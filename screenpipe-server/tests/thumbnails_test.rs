@@ -0,0 +1,128 @@
+use chrono::{TimeZone, Utc};
+use image::{DynamicImage, Rgb, RgbImage};
+use screenpipe_db::FramePreview;
+use screenpipe_server::thumbnails::{
+    draw_sprite_sheet, encode_jpeg, fit_thumbnail, sprite_layout, ThumbnailSize,
+    DEFAULT_THUMBNAIL_WIDTH, MAX_THUMBNAIL_SIZE,
+};
+
+fn screen(width: u32, height: u32, color: [u8; 3]) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb(color)))
+}
+
+fn frame(id: i64) -> FramePreview {
+    FramePreview {
+        id,
+        timestamp: Utc.timestamp_opt(1_700_000_000 + id, 0).unwrap(),
+        device_name: "monitor_1".to_string(),
+        file_path: "monitor_1.mp4".to_string(),
+        offset_index: id,
+    }
+}
+
+#[test]
+fn test_thumbnail_size() {
+    assert_eq!(
+        ThumbnailSize::new(None, None),
+        ThumbnailSize {
+            width: DEFAULT_THUMBNAIL_WIDTH,
+            height: MAX_THUMBNAIL_SIZE
+        }
+    );
+    assert_eq!(
+        ThumbnailSize::new(None, Some(90)),
+        ThumbnailSize {
+            width: MAX_THUMBNAIL_SIZE,
+            height: 90
+        }
+    );
+    assert_eq!(ThumbnailSize::new(Some(0), Some(100_000)).width, 1);
+    assert_eq!(
+        ThumbnailSize::new(Some(0), Some(100_000)).height,
+        MAX_THUMBNAIL_SIZE
+    );
+
+    assert_eq!(
+        ThumbnailSize::tile(None, None),
+        ThumbnailSize {
+            width: 160,
+            height: 90
+        }
+    );
+    assert_eq!(ThumbnailSize::tile(Some(1), None).height, 1);
+}
+
+#[test]
+fn test_fit_thumbnail_keeps_aspect_ratio() {
+    let thumbnail = fit_thumbnail(
+        &screen(1920, 1080, [0, 0, 0]),
+        ThumbnailSize::new(Some(320), None),
+    );
+    assert_eq!((thumbnail.width(), thumbnail.height()), (320, 180));
+
+    let thumbnail = fit_thumbnail(
+        &screen(1920, 1080, [0, 0, 0]),
+        ThumbnailSize::new(None, Some(90)),
+    );
+    assert_eq!((thumbnail.width(), thumbnail.height()), (160, 90));
+
+    // never scaled up
+    let thumbnail = fit_thumbnail(
+        &screen(100, 50, [0, 0, 0]),
+        ThumbnailSize::new(Some(320), None),
+    );
+    assert_eq!((thumbnail.width(), thumbnail.height()), (100, 50));
+}
+
+#[test]
+fn test_sprite_layout() {
+    let frames: Vec<FramePreview> = (1..=5).map(frame).collect();
+    let tiles = sprite_layout(&frames, ThumbnailSize::tile(Some(160), None), 2);
+
+    let positions: Vec<(i64, u32, u32)> = tiles
+        .iter()
+        .map(|tile| (tile.frame_id, tile.x, tile.y))
+        .collect();
+    assert_eq!(
+        positions,
+        vec![
+            (1, 0, 0),
+            (2, 160, 0),
+            (3, 0, 90),
+            (4, 160, 90),
+            (5, 0, 180)
+        ]
+    );
+    assert!(tiles
+        .iter()
+        .all(|tile| tile.width == 160 && tile.height == 90));
+}
+
+#[test]
+fn test_draw_sprite_sheet() {
+    let tile = ThumbnailSize::tile(Some(160), None);
+    let images = vec![
+        Some(screen(1920, 1080, [255, 0, 0])),
+        None,
+        // a portrait screen is centered in its tile
+        Some(screen(1080, 1920, [0, 0, 255])),
+    ];
+    let sheet = draw_sprite_sheet(&images, tile, 2);
+
+    assert_eq!((sheet.width(), sheet.height()), (320, 180));
+    assert_eq!(sheet.get_pixel(80, 45), &Rgb([255, 0, 0]));
+    assert_eq!(sheet.get_pixel(240, 45), &Rgb([0, 0, 0]));
+    assert_eq!(sheet.get_pixel(80, 135), &Rgb([0, 0, 255]));
+    assert_eq!(sheet.get_pixel(5, 135), &Rgb([0, 0, 0]));
+
+    // a strip when there are fewer frames than columns
+    let strip = draw_sprite_sheet(&images[..1], tile, 20);
+    assert_eq!((strip.width(), strip.height()), (160, 90));
+}
+
+#[test]
+fn test_encode_jpeg() {
+    let bytes = encode_jpeg(&screen(64, 32, [0, 128, 0]).to_rgb8()).unwrap();
+    let decoded = image::load_from_memory(&bytes).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (64, 32));
+}
@@ -0,0 +1,64 @@
+use screenpipe_server::cli::CliVideoEncoder;
+use screenpipe_server::video_encoder::{resolve_video_encoder, VideoEncoder};
+
+#[test]
+fn test_encoder_args_select_codec() {
+    let cases = [
+        (VideoEncoder::Libx264, "libx264"),
+        (VideoEncoder::Libx265, "libx265"),
+        (VideoEncoder::VideoToolbox, "hevc_videotoolbox"),
+        (VideoEncoder::Nvenc, "hevc_nvenc"),
+        (VideoEncoder::Qsv, "hevc_qsv"),
+    ];
+
+    for (encoder, codec) in cases {
+        let args = encoder.ffmpeg_args();
+        let position = args.iter().position(|a| *a == "-vcodec").unwrap();
+        assert_eq!(args[position + 1], codec, "wrong codec for {}", encoder);
+    }
+}
+
+#[test]
+fn test_unresolved_auto_encodes_with_fallback() {
+    assert_eq!(
+        VideoEncoder::Auto.ffmpeg_args(),
+        VideoEncoder::FALLBACK.ffmpeg_args()
+    );
+    assert!(!VideoEncoder::FALLBACK.is_hardware());
+}
+
+#[test]
+fn test_hardware_candidates_are_hardware() {
+    assert!(!VideoEncoder::hardware_candidates().is_empty());
+    assert!(VideoEncoder::hardware_candidates()
+        .iter()
+        .all(|e| e.is_hardware()));
+}
+
+#[test]
+fn test_cli_video_encoder_conversion() {
+    assert_eq!(VideoEncoder::from(CliVideoEncoder::Auto), VideoEncoder::Auto);
+    assert_eq!(VideoEncoder::from(CliVideoEncoder::Nvenc), VideoEncoder::Nvenc);
+    assert_eq!(
+        VideoEncoder::from(CliVideoEncoder::Libx264),
+        VideoEncoder::Libx264
+    );
+}
+
+#[tokio::test]
+async fn test_software_encoders_are_not_probed() {
+    assert_eq!(
+        resolve_video_encoder(VideoEncoder::Libx265).await,
+        VideoEncoder::Libx265
+    );
+    assert_eq!(
+        resolve_video_encoder(VideoEncoder::Libx264).await,
+        VideoEncoder::Libx264
+    );
+}
+
+#[tokio::test]
+async fn test_auto_never_resolves_to_auto() {
+    let resolved = resolve_video_encoder(VideoEncoder::Auto).await;
+    assert_ne!(resolved, VideoEncoder::Auto);
+}
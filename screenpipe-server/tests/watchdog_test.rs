@@ -0,0 +1,41 @@
+use std::time::{Duration, Instant};
+
+use screenpipe_server::watchdog::{LoopId, StallDetector, Subsystem};
+
+fn monitor(subsystem: Subsystem) -> LoopId {
+    LoopId {
+        subsystem,
+        target: "1".to_string(),
+    }
+}
+
+#[test]
+fn test_stalled_loops_past_timeout() {
+    let mut detector = StallDetector::new(Duration::from_secs(120));
+    let stalled = detector.stalled(
+        vec![
+            (monitor(Subsystem::Vision), Duration::from_secs(121)),
+            (monitor(Subsystem::Ocr), Duration::from_secs(30)),
+        ],
+        Instant::now(),
+    );
+    assert_eq!(stalled.len(), 1);
+    assert_eq!(stalled[0].0, monitor(Subsystem::Vision));
+    assert_eq!(stalled[0].1, Duration::from_secs(121));
+}
+
+#[test]
+fn test_restarted_loop_gets_the_timeout_again() {
+    let timeout = Duration::from_secs(120);
+    let mut detector = StallDetector::new(timeout);
+    let start = Instant::now();
+    let idle = || vec![(monitor(Subsystem::Vision), Duration::from_secs(500))];
+
+    assert_eq!(detector.stalled(idle(), start).len(), 1);
+    // still without progress right after its restart
+    assert!(detector
+        .stalled(idle(), start + Duration::from_secs(10))
+        .is_empty());
+    // the restart didn't help
+    assert_eq!(detector.stalled(idle(), start + timeout).len(), 1);
+}
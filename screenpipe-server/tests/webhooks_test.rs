@@ -0,0 +1,227 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use screenpipe_db::Webhook;
+use screenpipe_events::Event;
+use screenpipe_server::webhooks::{accepts, generate_secret, sign, WebhookDispatcher};
+
+mod common;
+use common::{request, setup_test_app, setup_test_db};
+
+#[derive(Clone, Default)]
+struct Receiver {
+    /// Requests failed before the next one succeeds
+    failures: Arc<AtomicUsize>,
+    received: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+}
+
+/// Serves a webhook on a free port, returning its url.
+async fn serve_webhook(receiver: Receiver) -> String {
+    let app = Router::new()
+        .route(
+            "/hook",
+            post(
+                |State(receiver): State<Receiver>, headers: HeaderMap, body: String| async move {
+                    if receiver
+                        .failures
+                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                        .is_ok()
+                    {
+                        return StatusCode::INTERNAL_SERVER_ERROR;
+                    }
+                    receiver.received.lock().unwrap().push((headers, body));
+                    StatusCode::OK
+                },
+            ),
+        )
+        .with_state(receiver);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}/hook", addr)
+}
+
+fn webhook(events: &str, keyword: Option<&str>, app_name: Option<&str>) -> Webhook {
+    Webhook {
+        id: 1,
+        url: "http://localhost/hook".to_string(),
+        events: events.to_string(),
+        keyword: keyword.map(str::to_string),
+        app_name: app_name.map(str::to_string),
+        secret: generate_secret(),
+        created_at: Utc::now(),
+    }
+}
+
+fn transcript_event(transcription: &str) -> Event {
+    Event {
+        name: "transcript_indexed".to_string(),
+        data: json!({
+            "transcription_id": 1,
+            "timestamp": Utc::now(),
+            "device": "mic",
+            "is_input": true,
+            "speaker_id": null,
+            "transcription": transcription,
+        }),
+    }
+}
+
+#[test]
+fn test_webhook_filters() {
+    let transcript = json!({"transcription": "the Invoice is overdue"});
+    let switch = json!({"app_name": "Slack", "window_name": "general"});
+
+    let all = webhook("transcript_indexed,app_switched", None, None);
+    assert!(accepts(&all, "transcript_indexed", &transcript));
+    assert!(accepts(&all, "app_switched", &switch));
+    assert!(!accepts(&all, "health_changed", &json!({})));
+
+    let keyword = webhook("transcript_indexed", Some("invoice"), None);
+    assert!(accepts(&keyword, "transcript_indexed", &transcript));
+    assert!(!accepts(
+        &keyword,
+        "transcript_indexed",
+        &json!({"transcription": "lunch?"})
+    ));
+
+    let slack = webhook("app_switched,transcript_indexed", None, Some("slack"));
+    assert!(accepts(&slack, "app_switched", &switch));
+    assert!(!accepts(
+        &slack,
+        "app_switched",
+        &json!({"app_name": "Mail"})
+    ));
+    // transcripts have no app
+    assert!(!accepts(&slack, "transcript_indexed", &transcript));
+}
+
+#[test]
+fn test_sign() {
+    assert_eq!(
+        sign("key", b"The quick brown fox jumps over the lazy dog"),
+        "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+    );
+    assert!(generate_secret().starts_with("whsec_"));
+    assert_ne!(generate_secret(), generate_secret());
+}
+
+#[tokio::test]
+async fn test_delivery_retries_and_dead_letters() {
+    let db = setup_test_db().await;
+    let dispatcher = WebhookDispatcher::new(db.clone()).with_retry_delay(Duration::from_millis(10));
+    let receiver = Receiver::default();
+    let url = serve_webhook(receiver.clone()).await;
+    let webhook = db
+        .insert_webhook(&url, "transcript_indexed", None, None, &generate_secret())
+        .await
+        .unwrap();
+
+    // delivered on the third attempt, signed
+    receiver.failures.store(2, Ordering::SeqCst);
+    let event = transcript_event("invoice overdue");
+    assert!(dispatcher.deliver(&webhook, &event).await.unwrap());
+    {
+        let received = receiver.received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (headers, body) = &received[0];
+        assert_eq!(headers["x-screenpipe-event"], "transcript_indexed");
+        assert_eq!(
+            headers["x-screenpipe-signature"],
+            sign(&webhook.secret, body.as_bytes()).as_str()
+        );
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["event"], "transcript_indexed");
+        assert_eq!(body["data"]["transcription"], "invoice overdue");
+    }
+
+    // every attempt failed
+    receiver.failures.store(100, Ordering::SeqCst);
+    assert!(!dispatcher.deliver(&webhook, &event).await.unwrap());
+    let dead_letters = db.list_webhook_dead_letters(10).await.unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].webhook_id, webhook.id);
+    assert!(dead_letters[0].error.contains("500"));
+
+    assert!(!dispatcher
+        .retry_dead_letter(&dead_letters[0])
+        .await
+        .unwrap());
+    let dead_letter = db
+        .get_webhook_dead_letter(dead_letters[0].id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(dead_letter.attempts, dead_letters[0].attempts + 1);
+
+    receiver.failures.store(0, Ordering::SeqCst);
+    assert!(dispatcher.retry_dead_letter(&dead_letter).await.unwrap());
+    assert!(db.list_webhook_dead_letters(10).await.unwrap().is_empty());
+    assert_eq!(receiver.received.lock().unwrap().len(), 2);
+
+    assert!(db.delete_webhook(webhook.id).await.unwrap());
+    assert!(db.list_webhooks().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_webhook_api() {
+    let app = setup_test_app(setup_test_db().await).await;
+
+    for invalid in [
+        json!({"url": "ftp://example.com", "events": ["app_switched"]}),
+        json!({"url": "http://localhost:9999/hook", "events": []}),
+        json!({"url": "http://localhost:9999/hook", "events": ["app_opened"]}),
+    ] {
+        let (status, _) = request(&app, "POST", "/webhooks", Some(invalid)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    let (status, body) = request(
+        &app,
+        "POST",
+        "/webhooks",
+        Some(json!({
+            "url": "http://localhost:9999/hook",
+            "events": ["transcript_indexed", "health_changed"],
+            "keyword": "invoice"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let id = body["data"]["id"].as_i64().unwrap();
+    assert_eq!(body["data"]["events"], "transcript_indexed,health_changed");
+    assert!(body["data"]["secret"]
+        .as_str()
+        .unwrap()
+        .starts_with("whsec_"));
+
+    let (status, body) = request(&app, "GET", "/webhooks", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["keyword"], "invoice");
+
+    let (status, body) = request(&app, "GET", "/webhooks/dead-letters", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["data"].as_array().unwrap().is_empty());
+    let (status, _) = request(&app, "POST", "/webhooks/dead-letters/1/retry", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let uri = format!("/webhooks/{}", id);
+    let (status, _) = request(&app, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = request(&app, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -30,3 +30,88 @@ async fn send_test_event() {
         }
     }
 }
+
+#[tokio::test]
+async fn test_transcription_stream() {
+    use screenpipe_audio::audio_manager::AudioManagerBuilder;
+    use screenpipe_audio::transcription::deepgram::streaming::RealtimeTranscriptionEvent;
+    use screenpipe_db::DatabaseManager;
+    use screenpipe_events::send_event;
+    use screenpipe_server::{PipeManager, SCServer};
+    use std::net::SocketAddr;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let db = Arc::new(DatabaseManager::new("sqlite::memory:").await.unwrap());
+    let audio_manager = Arc::new(
+        AudioManagerBuilder::new()
+            .output_path("/tmp/screenpipe".into())
+            .build(db.clone())
+            .await
+            .unwrap(),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = SCServer::new(
+        db,
+        addr,
+        PathBuf::from(""),
+        Arc::new(PipeManager::new(PathBuf::from(""))),
+        false,
+        false,
+        false,
+        audio_manager,
+        true,
+    );
+    let router = server.create_router(false).await;
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+
+    let url = format!("ws://{}/ws/transcriptions?final_only=true&device=mic", addr);
+    let (ws_stream, _) = tokio_tungstenite::connect_async(url)
+        .await
+        .expect("Failed to connect to websocket");
+    let (_write, mut read) = ws_stream.split();
+
+    // give the server time to subscribe before publishing
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let segment = |device: &str, text: &str, is_final: bool| RealtimeTranscriptionEvent {
+        timestamp: chrono::Utc::now(),
+        device: device.to_string(),
+        transcription: text.to_string(),
+        is_final,
+        is_input: true,
+        speaker: None,
+    };
+    send_event("transcription", segment("mic (input)", "partial", false)).unwrap();
+    send_event(
+        "transcription",
+        segment("speakers (output)", "other device", true),
+    )
+    .unwrap();
+    send_event("transcription", segment("mic (input)", "hello world", true)).unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            match read.next().await {
+                Some(Ok(tokio_tungstenite::tungstenite::Message::Text(text))) => return text,
+                Some(Ok(_)) => continue,
+                other => panic!("websocket closed: {:?}", other),
+            }
+        }
+    })
+    .await
+    .expect("no transcription received");
+
+    let received: RealtimeTranscriptionEvent = serde_json::from_str(&message).unwrap();
+    assert_eq!(received.transcription, "hello world");
+    assert!(received.is_final);
+}
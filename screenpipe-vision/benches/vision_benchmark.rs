@@ -3,12 +3,13 @@
 // cargo bench --bench vision_benchmark
 // ! not very useful bench
 
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use screenpipe_vision::capture_screenshot_by_window::WindowFilters;
+use screenpipe_vision::capture_screenshot_by_window::{CaptureMode, WindowFilters};
+use screenpipe_vision::frame_rate::AdaptiveFpsConfig;
 use screenpipe_vision::monitor::get_default_monitor;
-use screenpipe_vision::{continuous_capture, OcrEngine};
+use screenpipe_vision::{continuous_capture, OcrEngine, PrivacyBlocklist};
 use tokio::sync::mpsc;
 use tokio::time::Duration;
 
@@ -23,8 +24,14 @@ async fn benchmark_continuous_capture(duration_secs: u64) -> f64 {
             OcrEngine::Tesseract,
             get_default_monitor().await.id(),
             window_filters,
+            Arc::new(PrivacyBlocklist::default()),
             vec![],
             false,
+            false,
+            false,
+            Arc::new(vec![]),
+            CaptureMode::Monitor,
+            Arc::new(RwLock::new(AdaptiveFpsConfig::default())),
         )
         .await
         {
@@ -1,9 +1,15 @@
 use clap::Parser;
 use screenpipe_core::Language;
 use screenpipe_vision::{
-    capture_screenshot_by_window::WindowFilters, continuous_capture, OcrEngine,
+    capture_screenshot_by_window::{CaptureMode, WindowFilters},
+    continuous_capture,
+    frame_rate::AdaptiveFpsConfig,
+    OcrEngine, PrivacyBlocklist,
+};
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
 };
-use std::{sync::Arc, time::Duration};
 use tokio::sync::mpsc::channel;
 use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
 use xcap::Monitor;
@@ -47,8 +53,14 @@ async fn main() {
         OcrEngine::AppleNative,
         monitor_id.unwrap(),
         window_filters,
+        Arc::new(PrivacyBlocklist::default()),
         languages.clone(),
         false,
+        false,
+        false,
+        Arc::new(vec![]),
+        CaptureMode::Monitor,
+        Arc::new(RwLock::new(AdaptiveFpsConfig::default())),
     )
     .await;
 
@@ -3,13 +3,14 @@ use base64::{engine::general_purpose, Engine as _};
 use clap::Parser;
 use futures_util::{SinkExt, StreamExt};
 use image::ImageEncoder;
-use screenpipe_vision::capture_screenshot_by_window::WindowFilters;
+use screenpipe_vision::capture_screenshot_by_window::{CaptureMode, WindowFilters};
+use screenpipe_vision::frame_rate::AdaptiveFpsConfig;
 use screenpipe_vision::{
-    continuous_capture, monitor::get_default_monitor, CaptureResult, OcrEngine,
+    continuous_capture, monitor::get_default_monitor, CaptureResult, OcrEngine, PrivacyBlocklist,
 };
 use serde::Serialize;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::mpsc::channel;
@@ -100,8 +101,14 @@ async fn main() -> Result<()> {
             },
             id,
             window_filters,
+            Arc::new(PrivacyBlocklist::default()),
             vec![],
             false,
+            false,
+            false,
+            Arc::new(vec![]),
+            CaptureMode::Monitor,
+            Arc::new(RwLock::new(AdaptiveFpsConfig::default())),
         )
         .await
     });
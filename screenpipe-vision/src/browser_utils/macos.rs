@@ -1,6 +1,7 @@
 use accessibility_sys::{
     kAXChildrenAttribute, kAXFocusedWindowAttribute, kAXRoleAttribute, kAXTextFieldRole,
-    kAXValueAttribute, AXUIElementCopyAttributeValue, AXUIElementCreateApplication, AXUIElementRef,
+    kAXTitleAttribute, kAXValueAttribute, AXUIElementCopyAttributeValue,
+    AXUIElementCreateApplication, AXUIElementRef,
 };
 use anyhow::Result;
 use core_foundation::{
@@ -10,7 +11,11 @@ use core_foundation::{
 };
 use url::Url;
 
-use super::BrowserUrlDetector;
+use super::{is_private_window_title, BrowserUrlDetector};
+
+/// Browsers whose AppleScript dictionary reports the `mode` of a window,
+/// `incognito` for private ones.
+const CHROMIUM_BROWSERS: [&str; 4] = ["Google Chrome", "Brave Browser", "Microsoft Edge", "Chromium"];
 
 pub struct MacOSUrlDetector;
 
@@ -136,6 +141,41 @@ impl MacOSUrlDetector {
             result
         }
     }
+
+    fn get_focused_window_title(&self, process_id: i32) -> Option<String> {
+        unsafe {
+            let app_element = AXUIElementCreateApplication(process_id);
+
+            let mut focused_window: CFTypeRef = std::ptr::null_mut();
+            let status = AXUIElementCopyAttributeValue(
+                app_element,
+                CFString::from_static_string(kAXFocusedWindowAttribute).as_concrete_TypeRef(),
+                &mut focused_window,
+            );
+            if status != accessibility_sys::kAXErrorSuccess {
+                CFRelease(app_element as CFTypeRef);
+                return None;
+            }
+
+            let mut title: CFTypeRef = std::ptr::null_mut();
+            let status = AXUIElementCopyAttributeValue(
+                focused_window as AXUIElementRef,
+                CFString::from_static_string(kAXTitleAttribute).as_concrete_TypeRef(),
+                &mut title,
+            );
+            let result = if status == accessibility_sys::kAXErrorSuccess {
+                let title_str = CFString::wrap_under_create_rule(title as _);
+                Some(title_str.to_string())
+            } else {
+                None
+            };
+
+            CFRelease(focused_window as CFTypeRef);
+            CFRelease(app_element as CFTypeRef);
+
+            result
+        }
+    }
 }
 
 impl BrowserUrlDetector for MacOSUrlDetector {
@@ -147,4 +187,19 @@ impl BrowserUrlDetector for MacOSUrlDetector {
             self.get_url_via_accessibility(process_id)
         }
     }
+
+    fn is_private_window(&self, app_name: &str, process_id: i32, window_title: &str) -> Result<bool> {
+        if CHROMIUM_BROWSERS.contains(&app_name) {
+            let script = format!(r#"tell application "{}" to return mode of front window"#, app_name);
+            if let Some(mode) = self.get_url_via_applescript(&script)? {
+                return Ok(mode == "incognito");
+            }
+        }
+
+        // other browsers only tell in the window title
+        let title = self
+            .get_focused_window_title(process_id)
+            .unwrap_or_else(|| window_title.to_string());
+        Ok(is_private_window_title(app_name, &title))
+    }
 }
@@ -3,6 +3,28 @@ use anyhow::Result;
 // Trait definition
 pub trait BrowserUrlDetector {
     fn get_active_url(&self, app_name: &str, process_id: i32, window_title: &str) -> Result<Option<String>>;
+
+    /// Whether the focused window of the browser is a private (incognito) one.
+    fn is_private_window(&self, app_name: &str, _process_id: i32, window_title: &str) -> Result<bool> {
+        Ok(is_private_window_title(app_name, window_title))
+    }
+}
+
+/// Title markers of private windows: Chrome/Chromium "(Incognito)", Edge
+/// "InPrivate", Firefox/Safari "Private Browsing", Brave "(Private)".
+const PRIVATE_WINDOW_MARKERS: [&str; 4] = ["incognito", "inprivate", "private browsing", "(private)"];
+
+/// Whether a browser window title marks it as private. Titles of other apps
+/// never do.
+pub fn is_private_window_title(app_name: &str, window_title: &str) -> bool {
+    let app_name = app_name.to_lowercase();
+    if !crate::core::BROWSER_NAMES.iter().any(|browser| app_name.contains(browser)) {
+        return false;
+    }
+    let window_title = window_title.to_lowercase();
+    PRIVATE_WINDOW_MARKERS
+        .iter()
+        .any(|marker| window_title.contains(marker))
 }
 
 // Factory function
@@ -33,3 +55,26 @@ mod linux;
 #[cfg(target_os = "linux")]
 pub use linux::LinuxUrlDetector;
 
+
+/// Title of the active tab, the window title without the browser name
+/// that Windows and Linux browsers append, e.g. "Inbox - Google Chrome".
+pub fn tab_title_from_window_title(app_name: &str, window_title: &str) -> Option<String> {
+    let app_name = app_name.to_lowercase();
+    let names: Vec<&str> = crate::core::BROWSER_NAMES
+        .iter()
+        .copied()
+        .filter(|name| app_name.contains(name))
+        .collect();
+    let title = [" - ", " \u{2014} "]
+        .iter()
+        .filter_map(|separator| window_title.rsplit_once(separator))
+        .find(|(_, suffix)| {
+            suffix
+                .to_lowercase()
+                .split_whitespace()
+                .any(|word| names.contains(&word))
+        })
+        .map_or(window_title, |(title, _)| title)
+        .trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
@@ -1,9 +1,10 @@
-use image::DynamicImage;
+use image::{DynamicImage, Rgba, RgbaImage};
 use once_cell::sync::Lazy;
+use regex::{Regex, RegexBuilder};
 use std::collections::HashSet;
 use std::error::Error;
 use std::fmt;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 use xcap::{Window, XCapError};
 
@@ -141,162 +142,378 @@ pub struct CapturedWindow {
     pub window_name: String,
     pub process_id: i32,
     pub is_focused: bool,
+    /// Where the window is in the stored frame, as `(x, y, width, height)`
+    pub frame_rect: Option<(u32, u32, u32, u32)>,
+}
+
+/// Screen-space rectangle of a window, in the same coordinate space as the
+/// monitor position reported by the OS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// How the frames written to the video chunks are produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptureMode {
+    /// Record the whole monitor (excluded windows are still blanked out)
+    #[default]
+    Monitor,
+    /// Record only the windows that pass the filters, everything else is blanked
+    Window,
+}
+
+/// A single include/exclude rule.
+///
+/// - `process:<name>` matches the app/process name (case-insensitive, exact)
+/// - `title:<regex>` matches the window title with a case-insensitive regex
+/// - anything else keeps the historical behaviour: case-insensitive substring
+///   match against the app name or the window title
+#[derive(Debug, Clone)]
+pub enum WindowPattern {
+    Contains(String),
+    Process(String),
+    Title(Regex),
+}
+
+impl WindowPattern {
+    pub fn parse(pattern: &str) -> Self {
+        if let Some(process) = pattern.strip_prefix("process:") {
+            return WindowPattern::Process(process.trim().to_lowercase());
+        }
+
+        if let Some(title) = pattern.strip_prefix("title:") {
+            match RegexBuilder::new(title).case_insensitive(true).build() {
+                Ok(regex) => return WindowPattern::Title(regex),
+                Err(e) => {
+                    warn!(
+                        "invalid window title regex '{}': {}, falling back to substring match",
+                        title, e
+                    );
+                    return WindowPattern::Contains(title.to_lowercase());
+                }
+            }
+        }
+
+        WindowPattern::Contains(pattern.to_lowercase())
+    }
+
+    fn matches(&self, app_name_lower: &str, title: &str, title_lower: &str) -> bool {
+        match self {
+            WindowPattern::Contains(needle) => {
+                app_name_lower.contains(needle) || title_lower.contains(needle)
+            }
+            WindowPattern::Process(process) => app_name_lower == process,
+            WindowPattern::Title(regex) => regex.is_match(title),
+        }
+    }
 }
 
 pub struct WindowFilters {
-    ignore_set: HashSet<String>,
-    include_set: HashSet<String>,
+    ignore_set: Vec<WindowPattern>,
+    include_set: Vec<WindowPattern>,
 }
 
 impl WindowFilters {
     pub fn new(ignore_list: &[String], include_list: &[String]) -> Self {
         Self {
-            ignore_set: ignore_list.iter().map(|s| s.to_lowercase()).collect(),
-            include_set: include_list.iter().map(|s| s.to_lowercase()).collect(),
+            ignore_set: ignore_list.iter().map(|s| WindowPattern::parse(s)).collect(),
+            include_set: include_list.iter().map(|s| WindowPattern::parse(s)).collect(),
         }
     }
 
-    // O(n) - we could figure out a better way to do this
-    pub fn is_valid(&self, app_name: &str, title: &str) -> bool {
+    /// Whether the window matches the ignore list. Excluded windows never
+    /// reach OCR and are blanked out of the stored frames.
+    pub fn is_excluded(&self, app_name: &str, title: &str) -> bool {
         let app_name_lower = app_name.to_lowercase();
         let title_lower = title.to_lowercase();
 
+        self.ignore_set
+            .iter()
+            .any(|ignore| ignore.matches(&app_name_lower, title, &title_lower))
+    }
+
+    // O(n) - we could figure out a better way to do this
+    pub fn is_valid(&self, app_name: &str, title: &str) -> bool {
+        // Exclusions always win over inclusions
+        if self.is_excluded(app_name, title) {
+            return false;
+        }
+
         // If include list is empty, we're done
         if self.include_set.is_empty() {
             return true;
         }
 
-        // Check include list
-        if self
-            .include_set
+        let app_name_lower = app_name.to_lowercase();
+        let title_lower = title.to_lowercase();
+
+        self.include_set
             .iter()
-            .any(|include| app_name_lower.contains(include) || title_lower.contains(include))
-        {
-            return true;
-        }
+            .any(|include| include.matches(&app_name_lower, title, &title_lower))
+    }
+}
 
-        // Check ignore list first (usually smaller)
-        if !self.ignore_set.is_empty()
-            && self
-                .ignore_set
-                .iter()
-                .any(|ignore| app_name_lower.contains(ignore) || title_lower.contains(ignore))
-        {
-            return false;
-        }
+/// The window that has the focus, whether it is captured or not.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FocusedWindow {
+    pub app_name: String,
+    pub window_name: String,
+    pub process_id: i32,
+}
 
-        false
-    }
+/// Result of enumerating the windows of a monitor.
+pub struct VisibleWindows {
+    /// Windows that passed the filters, ready for OCR
+    pub captured: Vec<CapturedWindow>,
+    /// Screen bounds of `captured`, in the same order
+    pub captured_bounds: Vec<WindowBounds>,
+    /// Screen bounds of windows matching the ignore list
+    pub excluded_bounds: Vec<WindowBounds>,
+    pub focused: Option<FocusedWindow>,
 }
 
 pub async fn capture_all_visible_windows(
-    monitor: &SafeMonitor,
+    _monitor: &SafeMonitor,
     window_filters: &WindowFilters,
     capture_unfocused_windows: bool,
-) -> Result<Vec<CapturedWindow>, Box<dyn Error>> {
-    let mut all_captured_images = Vec::new();
+) -> Result<VisibleWindows, Box<dyn Error>> {
+    let mut visible = VisibleWindows {
+        captured: Vec::new(),
+        captured_bounds: Vec::new(),
+        excluded_bounds: Vec::new(),
+        focused: None,
+    };
+    let mut found_any = false;
+
+    for window in Window::all()? {
+        let app_name = match window.app_name() {
+            Ok(name) => name.to_string(),
+            Err(e) => {
+                // mostly noise
+                debug!("Failed to get app_name for window: {}", e);
+                continue;
+            }
+        };
 
-    // Get windows and immediately extract the data we need
-    let windows_data = Window::all()?
-        .into_iter()
-        .filter_map(|window| {
-            // Extract all necessary data from the window while in the main thread
-            let app_name = match window.app_name() {
-                Ok(name) => name.to_string(),
-                Err(e) => {
-                    // Log warning and skip this window
-                    // mostly noise
-                    debug!("Failed to get app_name for window: {}", e);
-                    return None;
-                }
-            };
+        let title = match window.title() {
+            Ok(title) => title.to_string(),
+            Err(e) => {
+                error!("Failed to get title for window {}: {}", app_name, e);
+                continue;
+            }
+        };
 
-            let title = match window.title() {
-                Ok(title) => title.to_string(),
-                Err(e) => {
-                    error!("Failed to get title for window {}: {}", app_name, e);
-                    return None;
-                }
-            };
-
-            match window.is_minimized() {
-                Ok(is_minimized) => {
-                    if is_minimized {
-                        debug!("Window {} ({}) is_minimized", app_name, title);
-                        return None;
-                    }
-                },
-                Err(e) => {
-                    // Log warning and skip this window
-                    // mostly noise
-                    error!("Failed to get is_minimized for window {}: {}", app_name, e);
-                }
-            };
+        match window.is_minimized() {
+            Ok(true) => {
+                debug!("Window {} ({}) is_minimized", app_name, title);
+                continue;
+            }
+            Ok(false) => {}
+            Err(e) => {
+                error!("Failed to get is_minimized for window {}: {}", app_name, e);
+            }
+        };
 
-            let is_focused = match window.is_focused() {
-                Ok(focused) => focused,
-                Err(e) => {
-                    error!(
-                        "Failed to get focus state for window {} ({}): {}",
-                        app_name, title, e
-                    );
-                    return None;
-                }
-            };
+        found_any = true;
 
-            let process_id = match window.pid() {
-                Ok(pid) => pid as i32,
-                Err(e) => {
-                    error!(
-                        "Failed to get process ID for window {} ({}): {}",
-                        app_name, title, e
-                    );
-                    -1
-                }
-            };
+        if SKIP_APPS.contains(app_name.as_str()) || SKIP_TITLES.contains(title.as_str()) {
+            continue;
+        }
 
-            // Capture image immediately while we have access to the window
-            match window.capture_image() {
-                Ok(buffer) => Some((app_name, title, is_focused, buffer, process_id)),
-                Err(e) => {
-                    error!(
-                        "Failed to capture image for window {} ({}): {}",
-                        app_name, title, e
-                    );
-                    None
-                }
+        let bounds = window_bounds(&window);
+
+        // the privacy blocklist also applies to ignored and filtered windows
+        let focus = window.is_focused();
+        if matches!(focus, Ok(true)) && visible.focused.is_none() {
+            visible.focused = Some(FocusedWindow {
+                app_name: app_name.clone(),
+                window_name: title.clone(),
+                process_id: window.pid().map(|pid| pid as i32).unwrap_or(-1),
+            });
+        }
+
+        // Check exclusions before grabbing any pixels so excluded apps never
+        // leave the capture layer
+        if window_filters.is_excluded(&app_name, &title) {
+            debug!("Window {} ({}) is excluded", app_name, title);
+            if let Some(bounds) = bounds {
+                visible.excluded_bounds.push(bounds);
             }
-        })
-        .collect::<Vec<_>>();
+            continue;
+        }
 
-    if windows_data.is_empty() {
-        return Err(Box::new(CaptureError::NoWindows));
-    }
+        let is_focused = match focus {
+            Ok(focused) => focused,
+            Err(e) => {
+                error!(
+                    "Failed to get focus state for window {} ({}): {}",
+                    app_name, title, e
+                );
+                continue;
+            }
+        };
+
+        if !(capture_unfocused_windows || is_focused)
+            || !window_filters.is_valid(&app_name, &title)
+        {
+            continue;
+        }
+
+        let process_id = match window.pid() {
+            Ok(pid) => pid as i32,
+            Err(e) => {
+                error!(
+                    "Failed to get process ID for window {} ({}): {}",
+                    app_name, title, e
+                );
+                -1
+            }
+        };
+
+        let buffer = match window.capture_image() {
+            Ok(buffer) => buffer,
+            Err(e) => {
+                error!(
+                    "Failed to capture image for window {} ({}): {}",
+                    app_name, title, e
+                );
+                continue;
+            }
+        };
 
-    // Process the captured data
-    for (app_name, window_name, is_focused, buffer, process_id) in windows_data {
         // Convert to DynamicImage
         let image = DynamicImage::ImageRgba8(
             image::ImageBuffer::from_raw(buffer.width(), buffer.height(), buffer.into_raw())
                 .unwrap(),
         );
 
-        // Apply filters
-        let is_valid = !SKIP_APPS.contains(app_name.as_str())
-            && !SKIP_TITLES.contains(window_name.as_str())
-            && (capture_unfocused_windows || (is_focused && monitor.id() == monitor.id()))
-            && window_filters.is_valid(&app_name, &window_name);
-
-        if is_valid {
-            all_captured_images.push(CapturedWindow {
-                image,
-                app_name,
-                window_name,
-                process_id: process_id as i32,
-                is_focused,
-            });
+        visible.captured.push(CapturedWindow {
+            image,
+            app_name,
+            window_name: title,
+            process_id,
+            is_focused,
+            // filled in once the monitor frame is known
+            frame_rect: None,
+        });
+        visible.captured_bounds.push(bounds.unwrap_or(WindowBounds {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        }));
+    }
+
+    if !found_any {
+        return Err(Box::new(CaptureError::NoWindows));
+    }
+
+    Ok(visible)
+}
+
+/// The focused window, without capturing anything.
+pub fn focused_window() -> Option<FocusedWindow> {
+    let windows = match Window::all() {
+        Ok(windows) => windows,
+        Err(e) => {
+            debug!("Failed to list windows: {}", e);
+            return None;
         }
+    };
+    windows
+        .into_iter()
+        .find(|w| w.is_focused().unwrap_or(false) && !w.is_minimized().unwrap_or(false))
+        .and_then(|w| {
+            Some(FocusedWindow {
+                app_name: w.app_name().ok()?.to_string(),
+                window_name: w.title().ok()?.to_string(),
+                process_id: w.pid().map(|pid| pid as i32).unwrap_or(-1),
+            })
+        })
+}
+
+fn window_bounds(window: &Window) -> Option<WindowBounds> {
+    Some(WindowBounds {
+        x: window.x().ok()?,
+        y: window.y().ok()?,
+        width: window.width().ok()?,
+        height: window.height().ok()?,
+    })
+}
+
+/// Maps screen-space window bounds to pixel coordinates in the captured
+/// monitor image (accounts for the monitor offset and HiDPI scaling).
+pub(crate) fn to_image_rect(
+    bounds: &WindowBounds,
+    monitor: &SafeMonitor,
+    image: &DynamicImage,
+) -> Option<(u32, u32, u32, u32)> {
+    if monitor.width() == 0 || monitor.height() == 0 {
+        return None;
     }
+    let scale_x = image.width() as f64 / monitor.width() as f64;
+    let scale_y = image.height() as f64 / monitor.height() as f64;
+
+    let left = ((bounds.x - monitor.x()) as f64 * scale_x).floor();
+    let top = ((bounds.y - monitor.y()) as f64 * scale_y).floor();
+    let right = left + (bounds.width as f64 * scale_x).ceil();
+    let bottom = top + (bounds.height as f64 * scale_y).ceil();
+
+    let left = left.max(0.0) as u32;
+    let top = top.max(0.0) as u32;
+    let right = (right.max(0.0) as u32).min(image.width());
+    let bottom = (bottom.max(0.0) as u32).min(image.height());
 
-    Ok(all_captured_images)
+    if right <= left || bottom <= top {
+        return None;
+    }
+    Some((left, top, right - left, bottom - top))
+}
+
+/// Paints the given windows black in the monitor frame.
+pub fn blank_windows(image: &mut DynamicImage, bounds: &[WindowBounds], monitor: &SafeMonitor) {
+    let rects: Vec<_> = bounds
+        .iter()
+        .filter_map(|b| to_image_rect(b, monitor, image))
+        .collect();
+    if rects.is_empty() {
+        return;
+    }
+
+    let mut rgba = image.to_rgba8();
+    for (x, y, width, height) in rects {
+        for py in y..y + height {
+            for px in x..x + width {
+                rgba.put_pixel(px, py, Rgba([0, 0, 0, 255]));
+            }
+        }
+    }
+    *image = DynamicImage::ImageRgba8(rgba);
+}
+
+/// Keeps only the pixels covered by the given windows, everything else is
+/// painted black.
+pub fn keep_only_windows(
+    image: &DynamicImage,
+    bounds: &[WindowBounds],
+    monitor: &SafeMonitor,
+) -> DynamicImage {
+    let source = image.to_rgba8();
+    let mut canvas =
+        RgbaImage::from_pixel(image.width(), image.height(), Rgba([0, 0, 0, 255]));
+    for (x, y, width, height) in bounds
+        .iter()
+        .filter_map(|b| to_image_rect(b, monitor, image))
+    {
+        for py in y..y + height {
+            for px in x..x + width {
+                canvas.put_pixel(px, py, *source.get_pixel(px, py));
+            }
+        }
+    }
+    DynamicImage::ImageRgba8(canvas)
 }
@@ -1,26 +1,48 @@
 #[cfg(target_os = "macos")]
 use crate::apple::perform_ocr_apple;
+use crate::capture_screenshot_by_window::CaptureMode;
 use crate::capture_screenshot_by_window::CapturedWindow;
 use crate::capture_screenshot_by_window::WindowFilters;
+use crate::capture_screenshot_by_window::{focused_window, FocusedWindow};
 use crate::custom_ocr::perform_ocr_custom;
+use crate::frame_rate::{AdaptiveFrameRate, SharedAdaptiveFpsConfig};
+use crate::layout::{build_layout, layout_text, lines_from_ocr_json, merge_accessibility_lines};
+use crate::table::detect_tables;
 #[cfg(target_os = "windows")]
 use crate::microsoft::perform_ocr_windows;
 use crate::monitor::get_monitor_by_id;
+use crate::paddle::perform_ocr_paddle;
+use crate::privacy::{PrivacyBlocklist, PRIVATE_BROWSING_PATTERN};
+use crate::region::CaptureRegion;
 use crate::tesseract::perform_ocr_tesseract;
+use crate::ui_tree::focused_window_tree;
+use crate::uia::window_text_lines;
 use crate::utils::OcrEngine;
-use crate::utils::{capture_screenshot, compare_with_previous_image};
+use crate::utils::{
+    capture_screenshot, compare_with_previous_image, hash_distance, perceptual_hash,
+};
 use anyhow::Result;
 use base64::{engine::general_purpose, Engine as _};
 use image::codecs::jpeg::JpegEncoder;
 use image::DynamicImage;
+use once_cell::sync::Lazy;
+use screenpipe_core::idle::is_idle;
+use screenpipe_core::metrics;
+use screenpipe_core::ocr_mode::should_defer_ocr;
+use screenpipe_core::power::is_low_power;
+use screenpipe_core::privacy::{set_capture_blocked, PrivacyBlockEvent, PRIVACY_BLOCK_EVENT};
 use screenpipe_core::Language;
+use screenpipe_db::{
+    AccessibilityNode, BrowserContext, ExtractedTable, OcrLayoutBlock, OcrLayoutLine,
+};
+use screenpipe_events::send_event;
 use screenpipe_integrations::unstructured_ocr::perform_ocr_cloud;
 use serde::Deserialize;
 use serde::Deserializer;
 use serde::Serialize;
 use serde::Serializer;
 use serde_json;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::{
     collections::HashMap,
     time::{Duration, Instant, UNIX_EPOCH},
@@ -28,9 +50,42 @@ use std::{
 use tokio::fs::File;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::sync::mpsc::Sender;
-use tracing::{debug, error, warn};
+use tracing::{debug, error, info, info_span, warn, Instrument, Span};
+
+use crate::browser_utils::{
+    create_url_detector, is_private_window_title, tab_title_from_window_title,
+};
 
-use crate::browser_utils::create_url_detector;
+/// How much less often the screen is captured with the low power profile.
+const LOW_POWER_INTERVAL_FACTOR: u32 = 4;
+/// Frames whose perceptual hash is at most this many bits away from the
+/// previous one's are stored as repeats of it.
+const DUPLICATE_HASH_DISTANCE: u32 = 2;
+
+/// Unix time in seconds of the latest screenshot of each monitor being
+/// captured, the start of its capture until the first one.
+static MONITOR_CAPTURES: Lazy<Mutex<HashMap<u32, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn update_monitor_capture_time(monitor_id: u32) {
+    let now = UNIX_EPOCH.elapsed().unwrap_or_default().as_secs();
+    MONITOR_CAPTURES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(monitor_id, now);
+}
+
+/// Monitors being captured with the unix time in seconds of their latest
+/// screenshot, ordered by id.
+pub fn monitor_capture_times() -> Vec<(u32, u64)> {
+    let mut times: Vec<(u32, u64)> = MONITOR_CAPTURES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(monitor_id, time)| (*monitor_id, *time))
+        .collect();
+    times.sort();
+    times
+}
 
 fn serialize_image<S>(image: &Option<DynamicImage>, serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -105,6 +160,14 @@ pub struct CaptureResult {
     pub frame_number: u64,
     pub timestamp: Instant,
     pub window_ocr_results: Vec<WindowOcrResult>,
+    /// Perceptual hash of `image`, see [`perceptual_hash`]
+    pub phash: u64,
+    /// The screen looks the same as in the previous result, nothing was
+    /// OCR'd and the frame isn't meant to be stored again
+    pub repeat_of_previous: bool,
+    /// Span of the OCR of the frame, or of its capture for repeats, which
+    /// the writes to the database continue
+    pub span: Span,
 }
 
 pub struct WindowOcrResult {
@@ -113,20 +176,31 @@ pub struct WindowOcrResult {
     pub app_name: String,
     pub text: String,
     pub text_json: Vec<HashMap<String, String>>, // Change this line
+    pub layout: Vec<OcrLayoutBlock>,
+    pub tables: Vec<ExtractedTable>,
     pub focused: bool,
     pub confidence: f64,
-    pub browser_url: Option<String>,
+    /// Active tab, for focused browser windows
+    pub browser_context: Option<BrowserContext>,
+    /// Accessibility tree, for the focused window when UI monitoring is on
+    pub accessibility_tree: Option<AccessibilityNode>,
+    /// Where the window is in the stored frame, see `CapturedWindow`
+    pub frame_rect: Option<(u32, u32, u32, u32)>,
+    /// OCR was skipped, in deferred mode or to save power. `image` can be
+    /// passed to [`ocr_deferred_image`] later
+    pub ocr_deferred: bool,
 }
 
 pub struct OcrTaskData {
     pub image: DynamicImage,
+    pub phash: u64,
     pub window_images: Vec<CapturedWindow>,
     pub frame_number: u64,
     pub timestamp: Instant,
     pub result_tx: Sender<CaptureResult>,
 }
 
-const BROWSER_NAMES: [&str; 9] = [
+pub(crate) const BROWSER_NAMES: [&str; 9] = [
     "chrome", "firefox", "safari", "edge", "brave", "arc", "chromium", "vivaldi", "opera",
 ];
 
@@ -150,13 +224,23 @@ pub async fn continuous_capture(
     ocr_engine: OcrEngine,
     monitor_id: u32,
     window_filters: Arc<WindowFilters>,
+    privacy_blocklist: Arc<PrivacyBlocklist>,
     languages: Vec<Language>,
     capture_unfocused_windows: bool,
+    capture_ui_tree: bool,
+    accessibility_text: bool,
+    capture_regions: Arc<Vec<CaptureRegion>>,
+    capture_mode: CaptureMode,
+    adaptive_fps: SharedAdaptiveFpsConfig,
 ) -> Result<(), ContinuousCaptureError> {
     let mut frame_counter: u64 = 0;
+    let mut frame_rate = AdaptiveFrameRate::new(interval, adaptive_fps);
     let mut previous_image: Option<DynamicImage> = None;
+    let mut previous_phash: Option<u64> = None;
     let mut max_average: Option<MaxAverageFrame> = None;
     let mut max_avg_value = 0.0;
+    // blocklist pattern and app that stopped the capture, if any
+    let mut active_block: Option<(String, String)> = None;
 
     debug!(
         "continuous_capture: Starting using monitor: {:?}",
@@ -167,27 +251,88 @@ pub async fn continuous_capture(
         Some(m) => m,
         None => {
             error!("Monitor not found");
+            MONITOR_CAPTURES
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&monitor_id);
             return Err(ContinuousCaptureError::MonitorNotFound);
         }
     };
+    update_monitor_capture_time(monitor_id);
 
     loop {
+        // nothing is captured while the user is away
+        if is_idle() {
+            tokio::time::sleep(interval).await;
+            continue;
+        }
+        let frame_span = info_span!("capture_frame", monitor_id, frame_number = frame_counter);
+
         // 3. Capture screenshot
-        let capture_result =
-            match capture_screenshot(&monitor, &window_filters, capture_unfocused_windows).await {
-                Ok(result) => result,
-                Err(e) => {
-                    debug!("error capturing screenshot: {}", e);
-                    return Err(ContinuousCaptureError::ErrorCapturingScreenshot(
-                        e.to_string(),
-                    ));
+        let capture_result = match capture_screenshot(
+            &monitor,
+            &window_filters,
+            capture_unfocused_windows,
+            &capture_regions,
+            capture_mode,
+        )
+        .instrument(info_span!(parent: &frame_span, "capture_screenshot"))
+        .await
+        {
+            Ok(result) => {
+                update_monitor_capture_time(monitor_id);
+                result
+            }
+            Err(e) => {
+                debug!("error capturing screenshot: {}", e);
+                // don't keep audio paused while capture restarts
+                if let Some((pattern, app_name)) = active_block.take() {
+                    report_privacy_block(monitor_id, false, pattern, app_name);
                 }
-            };
+                return Err(ContinuousCaptureError::ErrorCapturingScreenshot(
+                    e.to_string(),
+                ));
+            }
+        };
 
         // 4. Process captured image
-        let (image, window_images, image_hash, _capture_duration) = capture_result;
+        let (image, mut window_images, image_hash, _capture_duration, focused) = capture_result;
+
+        // Frames of blocked apps and sites are dropped before any OCR
+        if !privacy_blocklist.is_empty() {
+            // windows are not enumerated when capturing regions
+            let focused = match focused {
+                None if !capture_regions.is_empty() => focused_window(),
+                focused => focused,
+            };
+            let block = blocklist_match(&privacy_blocklist, focused.as_ref()).await;
+            if block.as_ref().map(|(pattern, _)| pattern)
+                != active_block.as_ref().map(|(pattern, _)| pattern)
+            {
+                if let Some((pattern, app_name)) = active_block.take() {
+                    report_privacy_block(monitor_id, false, pattern, app_name);
+                }
+                if let Some((pattern, app_name)) = block.clone() {
+                    report_privacy_block(monitor_id, true, pattern, app_name);
+                }
+                active_block = block;
+            }
+
+            if active_block.is_some() {
+                tokio::time::sleep(interval).await;
+                continue;
+            }
 
-        let should_skip = should_skip_frame(
+            // private windows in the background are left out of OCR
+            if privacy_blocklist.blocks_private_browsing() {
+                window_images.retain(|window| {
+                    !is_private_window_title(&window.app_name, &window.window_name)
+                });
+            }
+        }
+
+        let phash = perceptual_hash(&image);
+        let (should_skip, frame_difference) = should_skip_frame(
             &previous_image,
             &image,
             &mut max_average,
@@ -195,22 +340,62 @@ pub async fn continuous_capture(
             &mut max_avg_value,
             &window_images,
             image_hash,
+            phash,
             result_tx.clone(),
+            &frame_span,
         )
         .await;
+        // a near-identical screen is only counted as a repeat of the last frame
+        let is_repeat = previous_phash.is_some_and(|previous| {
+            should_skip || hash_distance(previous, phash) <= DUPLICATE_HASH_DISTANCE
+        });
+
+        let mut next_interval = frame_rate.next_interval(frame_difference);
+        if is_low_power() {
+            next_interval *= LOW_POWER_INTERVAL_FACTOR;
+        }
+        if next_interval != interval {
+            debug!(
+                "adaptive fps: next capture for monitor {} in {:?} (difference {:.3})",
+                monitor_id, next_interval, frame_difference
+            );
+        }
 
-        if should_skip {
+        if should_skip || is_repeat {
+            if is_repeat {
+                max_average = None;
+                max_avg_value = 0.0;
+                let repeat = CaptureResult {
+                    image,
+                    frame_number: frame_counter,
+                    timestamp: Instant::now(),
+                    window_ocr_results: Vec::new(),
+                    phash,
+                    repeat_of_previous: true,
+                    span: frame_span.clone(),
+                };
+                if let Err(e) = result_tx.send(repeat).await {
+                    error!("Failed to send repeated frame: {}", e);
+                }
+            }
             frame_counter += 1;
-            tokio::time::sleep(interval).await;
+            tokio::time::sleep(next_interval).await;
             continue;
         }
 
         previous_image = Some(image);
+        previous_phash = Some(phash);
 
         // 5. Process max average frame if available
         if let Some(max_avg_frame) = max_average.take() {
-            if let Err(e) =
-                process_max_average_frame(max_avg_frame, &ocr_engine, languages.clone()).await
+            if let Err(e) = process_max_average_frame(
+                max_avg_frame,
+                &ocr_engine,
+                languages.clone(),
+                capture_ui_tree,
+                accessibility_text,
+            )
+            .await
             {
                 error!("Error processing max average frame: {}", e);
             }
@@ -219,10 +404,102 @@ pub async fn continuous_capture(
         }
 
         frame_counter += 1;
-        tokio::time::sleep(interval).await;
+        tokio::time::sleep(next_interval).await;
+    }
+}
+
+/// The blocklist pattern matching the focused window, with the app name.
+async fn blocklist_match(
+    blocklist: &PrivacyBlocklist,
+    focused: Option<&FocusedWindow>,
+) -> Option<(String, String)> {
+    let focused = focused?;
+    if blocklist.blocks_private_browsing() && is_private_browsing(focused).await {
+        return Some((
+            PRIVATE_BROWSING_PATTERN.to_string(),
+            focused.app_name.clone(),
+        ));
+    }
+
+    let url = if blocklist.matches_urls() {
+        get_browser_url_if_needed(
+            &focused.app_name,
+            true,
+            focused.process_id,
+            &focused.window_name,
+        )
+        .await
+    } else {
+        None
+    };
+    blocklist
+        .matching_pattern(&focused.app_name, url.as_deref())
+        .map(|pattern| (pattern.to_string(), focused.app_name.clone()))
+}
+
+/// Whether the focused window is a private browser window, asking the
+/// browser where possible as titles don't always tell.
+async fn is_private_browsing(focused: &FocusedWindow) -> bool {
+    if !BROWSER_NAMES
+        .iter()
+        .any(|&browser| focused.app_name.to_lowercase().contains(browser))
+    {
+        return false;
+    }
+
+    let FocusedWindow {
+        app_name,
+        window_name,
+        process_id,
+    } = focused.clone();
+    match tokio::task::spawn_blocking(move || {
+        create_url_detector().is_private_window(&app_name, process_id, &window_name)
+    })
+    .await
+    {
+        Ok(Ok(private)) => private,
+        Ok(Err(e)) => {
+            debug!("failed to check for private browsing: {}", e);
+            is_private_window_title(&focused.app_name, &focused.window_name)
+        }
+        Err(e) => {
+            error!("Failed to spawn blocking task: {}", e);
+            is_private_window_title(&focused.app_name, &focused.window_name)
+        }
+    }
+}
+
+/// Logs when the blocklist stops or resumes capture, pauses audio while it
+/// is stopped and sends an event so the change can be audited.
+fn report_privacy_block(monitor_id: u32, blocked: bool, pattern: String, app_name: String) {
+    if blocked {
+        info!(
+            "privacy blocklist: stopped capture on monitor {}, {} matches '{}'",
+            monitor_id, app_name, pattern
+        );
+    } else {
+        info!(
+            "privacy blocklist: resumed capture on monitor {}",
+            monitor_id
+        );
+    }
+    set_capture_blocked(monitor_id, blocked.then(|| pattern.clone()));
+
+    if let Err(e) = send_event(
+        PRIVACY_BLOCK_EVENT,
+        PrivacyBlockEvent {
+            monitor_id,
+            blocked,
+            app_name,
+            pattern,
+            timestamp: chrono::Utc::now(),
+        },
+    ) {
+        error!("Failed to send privacy block event: {}", e);
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn should_skip_frame(
     previous_image: &Option<DynamicImage>,
     current_image: &DynamicImage,
@@ -231,8 +508,10 @@ async fn should_skip_frame(
     max_avg_value: &mut f64,
     window_images: &Vec<CapturedWindow>,
     image_hash: u64,
+    phash: u64,
     result_tx: Sender<CaptureResult>,
-) -> bool {
+    frame_span: &Span,
+) -> (bool, f64) {
     let current_average = match compare_with_previous_image(
         previous_image.as_ref(),
         current_image,
@@ -260,21 +539,23 @@ async fn should_skip_frame(
             "Skipping frame {} due to low average difference: {:.3}",
             frame_counter, current_average
         );
-        true
+        (true, current_average)
     } else {
         if current_average > *max_avg_value {
             *max_average = Some(MaxAverageFrame {
                 image: current_image.clone(),
+                phash,
                 window_images: window_images.clone(),
                 image_hash,
                 frame_number: frame_counter,
                 timestamp: Instant::now(),
                 result_tx: result_tx.clone(),
                 average: current_average,
+                span: frame_span.clone(),
             });
             *max_avg_value = current_average;
         }
-        false
+        (false, current_average)
     }
 }
 
@@ -282,16 +563,34 @@ async fn process_max_average_frame(
     max_avg_frame: MaxAverageFrame,
     ocr_engine: &OcrEngine,
     languages: Vec<Language>,
+    capture_ui_tree: bool,
+    accessibility_text: bool,
 ) -> Result<(), ContinuousCaptureError> {
+    let span = info_span!(
+        parent: &max_avg_frame.span,
+        "ocr",
+        engine = ocr_engine.name(),
+        windows = max_avg_frame.window_images.len()
+    );
     let ocr_task_data = OcrTaskData {
         image: max_avg_frame.image,
+        phash: max_avg_frame.phash,
         window_images: max_avg_frame.window_images,
         frame_number: max_avg_frame.frame_number,
         timestamp: max_avg_frame.timestamp,
         result_tx: max_avg_frame.result_tx,
     };
 
-    if let Err(e) = process_ocr_task(ocr_task_data, ocr_engine, languages).await {
+    if let Err(e) = process_ocr_task(
+        ocr_task_data,
+        ocr_engine,
+        languages,
+        capture_ui_tree,
+        accessibility_text,
+    )
+    .instrument(span)
+    .await
+    {
         error!("Error processing OCR task: {}", e);
         return Err(ContinuousCaptureError::ErrorProcessingOcr(e.to_string()));
     }
@@ -301,21 +600,27 @@ async fn process_max_average_frame(
 
 pub struct MaxAverageFrame {
     pub image: DynamicImage,
+    pub phash: u64,
     pub window_images: Vec<CapturedWindow>,
     pub image_hash: u64,
     pub frame_number: u64,
     pub timestamp: Instant,
     pub result_tx: Sender<CaptureResult>,
     pub average: f64,
+    /// Span of the capture of the frame
+    pub span: Span,
 }
 
 pub async fn process_ocr_task(
     ocr_task_data: OcrTaskData,
     ocr_engine: &OcrEngine,
     languages: Vec<Language>,
+    capture_ui_tree: bool,
+    accessibility_text: bool,
 ) -> Result<(), ContinuousCaptureError> {
     let OcrTaskData {
         image,
+        phash,
         window_images,
         frame_number,
         timestamp,
@@ -337,6 +642,8 @@ pub async fn process_ocr_task(
             captured_window,
             ocr_engine,
             &languages,
+            capture_ui_tree,
+            accessibility_text,
             &mut total_confidence,
             &mut window_count,
         )
@@ -352,6 +659,9 @@ pub async fn process_ocr_task(
         frame_number,
         timestamp,
         window_ocr_results,
+        phash,
+        repeat_of_previous: false,
+        span: Span::current(),
     };
 
     send_ocr_result(&result_tx, capture_result)
@@ -368,25 +678,59 @@ async fn process_window_ocr(
     captured_window: CapturedWindow,
     ocr_engine: &OcrEngine,
     languages: &[Language],
+    capture_ui_tree: bool,
+    accessibility_text: bool,
     total_confidence: &mut f64,
     window_count: &mut u32,
 ) -> Result<WindowOcrResult, ContinuousCaptureError> {
     let app_name = captured_window.app_name.clone();
 
-    // Get browser URL if applicable
-    let browser_url = get_browser_url_if_needed(
+    // Get browser URL and tab title if applicable
+    let browser_context = get_browser_url_if_needed(
         &app_name,
         captured_window.is_focused,
         captured_window.process_id,
         &captured_window.window_name,
     )
-    .await;
+    .await
+    .map(|url| BrowserContext {
+        url,
+        title: tab_title_from_window_title(&app_name, &captured_window.window_name),
+        browser: app_name.clone(),
+    });
+
+    let accessibility_tree = if capture_ui_tree && captured_window.is_focused {
+        get_accessibility_tree(captured_window.process_id).await
+    } else {
+        None
+    };
+
+    let accessibility_lines = if accessibility_text {
+        get_accessibility_text(captured_window.process_id, &captured_window.window_name).await
+    } else {
+        Vec::new()
+    };
 
-    // Perform OCR based on the selected engine
-    let (window_text, window_json_output, confidence) =
-        perform_ocr_with_engine(ocr_engine, &captured_window.image, languages.to_vec())
+    // Perform OCR based on the selected engine, unless it's queued for later
+    let ocr_deferred = should_defer_ocr();
+    let (mut window_text, window_json_output, confidence) = if ocr_deferred {
+        (String::new(), String::new(), None)
+    } else {
+        let started = Instant::now();
+        let ocr = perform_ocr_with_engine(ocr_engine, &captured_window.image, languages.to_vec())
+            .instrument(info_span!(
+                "ocr_window",
+                app_name = %app_name,
+                width = captured_window.image.width(),
+                height = captured_window.image.height()
+            ))
             .await
             .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string()))?;
+        metrics::OCR_DURATION
+            .with_labels(&[ocr_engine.name()])
+            .observe(started.elapsed().as_secs_f64());
+        ocr
+    };
 
     // Update confidence metrics
     if let Some(conf) = confidence {
@@ -394,15 +738,74 @@ async fn process_window_ocr(
         *window_count += 1;
     }
 
+    let mut text_json = parse_json_output(&window_json_output);
+    let mut lines = lines_from_ocr_json(
+        ocr_engine,
+        &text_json,
+        captured_window.image.width(),
+        captured_window.image.height(),
+    );
+    // OCR'd text without positions can't be merged and is kept as is
+    let replace_text =
+        !accessibility_lines.is_empty() && (!lines.is_empty() || window_text.trim().is_empty());
+    if !accessibility_lines.is_empty() {
+        text_json.extend(accessibility_lines.iter().map(|line| {
+            HashMap::from([
+                ("text".to_string(), line.text.clone()),
+                ("confidence".to_string(), line.confidence.to_string()),
+                ("source".to_string(), line.source.to_string()),
+            ])
+        }));
+        lines = merge_accessibility_lines(lines, accessibility_lines);
+    }
+    let tables = detect_tables(&lines);
+    let layout = build_layout(lines);
+    if replace_text {
+        window_text = layout_text(&layout);
+    }
+
     Ok(WindowOcrResult {
         image: captured_window.image,
         window_name: captured_window.window_name,
         app_name: captured_window.app_name,
         text: window_text,
-        text_json: parse_json_output(&window_json_output),
+        text_json,
+        layout,
+        tables,
         focused: captured_window.is_focused,
         confidence: confidence.unwrap_or(0.0),
-        browser_url,
+        browser_context,
+        accessibility_tree,
+        frame_rect: captured_window.frame_rect,
+        ocr_deferred,
+    })
+}
+
+/// Text read from a window image whose OCR was deferred.
+#[derive(Debug, Clone)]
+pub struct DeferredOcr {
+    pub text: String,
+    pub text_json: Vec<HashMap<String, String>>,
+    pub layout: Vec<OcrLayoutBlock>,
+    pub tables: Vec<ExtractedTable>,
+}
+
+/// Runs the OCR skipped while capturing, see
+/// [`WindowOcrResult::ocr_deferred`].
+pub async fn ocr_deferred_image(
+    image: &DynamicImage,
+    ocr_engine: &OcrEngine,
+    languages: Vec<Language>,
+) -> Result<DeferredOcr, ContinuousCaptureError> {
+    let (text, json_output, _) = perform_ocr_with_engine(ocr_engine, image, languages).await?;
+    let text_json = parse_json_output(&json_output);
+    let lines = lines_from_ocr_json(ocr_engine, &text_json, image.width(), image.height());
+    let tables = detect_tables(&lines);
+    Ok(DeferredOcr {
+        text,
+        text_json,
+        layout: build_layout(lines),
+        tables,
     })
 }
 
@@ -436,6 +839,27 @@ async fn get_browser_url_if_needed(
     }
 }
 
+async fn get_accessibility_text(process_id: i32, window_name: &str) -> Vec<OcrLayoutLine> {
+    let window_name = window_name.to_string();
+    match tokio::task::spawn_blocking(move || window_text_lines(process_id, &window_name)).await {
+        Ok(lines) => lines,
+        Err(e) => {
+            error!("Failed to spawn blocking task: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+async fn get_accessibility_tree(process_id: i32) -> Option<AccessibilityNode> {
+    match tokio::task::spawn_blocking(move || focused_window_tree(process_id)).await {
+        Ok(tree) => tree,
+        Err(e) => {
+            error!("Failed to spawn blocking task: {}", e);
+            None
+        }
+    }
+}
+
 async fn perform_ocr_with_engine(
     ocr_engine: &OcrEngine,
     image: &DynamicImage,
@@ -452,6 +876,9 @@ async fn perform_ocr_with_engine(
             .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string())),
         #[cfg(target_os = "macos")]
         OcrEngine::AppleNative => Ok(perform_ocr_apple(image, &languages)),
+        OcrEngine::Paddle => perform_ocr_paddle(image)
+            .await
+            .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string())),
         OcrEngine::Custom(config) => perform_ocr_custom(image, languages, config)
             .await
             .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string())),
@@ -533,6 +960,10 @@ pub struct WindowOcr {
     pub app_name: String,
     pub text: String,
     pub text_json: Vec<HashMap<String, String>>, // Change this line
+    #[serde(default)]
+    pub layout: Vec<OcrLayoutBlock>,
+    #[serde(default)]
+    pub tables: Vec<ExtractedTable>,
     pub focused: bool,
     pub confidence: f64,
     #[serde(
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Tuning for the adaptive capture interval. Shared behind a lock so it can be
+/// changed at runtime through the server settings API.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveFpsConfig {
+    pub enabled: bool,
+    /// Lowest capture rate used while the screen does not change
+    pub min_fps: f64,
+    /// Frames whose difference to the previous frame is below this value are
+    /// considered unchanged
+    pub idle_threshold: f64,
+    /// Differences above this value jump straight back to the configured fps
+    pub activity_threshold: f64,
+    /// Number of consecutive unchanged frames before slowing down
+    pub idle_frames_before_slowdown: u32,
+}
+
+impl Default for AdaptiveFpsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_fps: 0.2,
+            idle_threshold: 0.006,
+            activity_threshold: 0.05,
+            idle_frames_before_slowdown: 3,
+        }
+    }
+}
+
+impl AdaptiveFpsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.min_fps.is_finite() || self.min_fps <= 0.0 {
+            return Err("min_fps must be a positive number".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.idle_threshold)
+            || !(0.0..=1.0).contains(&self.activity_threshold)
+        {
+            return Err("thresholds must be between 0 and 1".to_string());
+        }
+        if self.idle_threshold > self.activity_threshold {
+            return Err("idle_threshold must not exceed activity_threshold".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub type SharedAdaptiveFpsConfig = Arc<RwLock<AdaptiveFpsConfig>>;
+
+/// Computes the delay before the next capture from how much the screen
+/// changed. Backs off exponentially towards `min_fps` while idle, and ramps
+/// back up to the configured interval on activity.
+pub struct AdaptiveFrameRate {
+    base_interval: Duration,
+    current_interval: Duration,
+    idle_frames: u32,
+    config: SharedAdaptiveFpsConfig,
+}
+
+impl AdaptiveFrameRate {
+    pub fn new(base_interval: Duration, config: SharedAdaptiveFpsConfig) -> Self {
+        Self {
+            base_interval,
+            current_interval: base_interval,
+            idle_frames: 0,
+            config,
+        }
+    }
+
+    pub fn current_interval(&self) -> Duration {
+        self.current_interval
+    }
+
+    /// Records the difference between the last two frames (0.0 = identical,
+    /// 1.0 = completely different) and returns how long to wait before the
+    /// next capture.
+    pub fn next_interval(&mut self, frame_difference: f64) -> Duration {
+        let config = *self.config.read().unwrap_or_else(|e| e.into_inner());
+
+        if !config.enabled {
+            self.idle_frames = 0;
+            self.current_interval = self.base_interval;
+            return self.current_interval;
+        }
+
+        let max_interval = Duration::from_secs_f64(1.0 / config.min_fps).max(self.base_interval);
+
+        if frame_difference >= config.activity_threshold {
+            self.idle_frames = 0;
+            self.current_interval = self.base_interval;
+        } else if frame_difference < config.idle_threshold {
+            self.idle_frames = self.idle_frames.saturating_add(1);
+            if self.idle_frames >= config.idle_frames_before_slowdown {
+                self.current_interval = (self.current_interval * 2).min(max_interval);
+            }
+        } else {
+            // some activity: halve the interval back towards the base rate
+            self.idle_frames = 0;
+            self.current_interval = (self.current_interval / 2).max(self.base_interval);
+        }
+
+        self.current_interval
+    }
+}
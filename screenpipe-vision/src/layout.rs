@@ -0,0 +1,287 @@
+//! Groups OCR lines into paragraphs and blocks and infers their reading order,
+//! so multi-column content can be reconstructed instead of read row by row.
+
+use crate::utils::OcrEngine;
+use screenpipe_db::{OcrLayoutBlock, OcrLayoutLine, OcrLayoutParagraph, TextBounds, TextSource};
+use std::collections::HashMap;
+
+/// Lines further apart than this many line heights start a new block.
+const BLOCK_GAP_RATIO: f32 = 1.2;
+/// Lines further apart than this many line heights start a new paragraph.
+const PARAGRAPH_GAP_RATIO: f32 = 0.6;
+/// Minimum horizontal overlap, relative to the narrower line, for two lines to
+/// be part of the same block.
+const MIN_HORIZONTAL_OVERLAP: f32 = 0.3;
+/// Column gutters are usually narrower than the gap between paragraphs, so a
+/// vertical cut wins over a horizontal one unless the latter is much wider.
+const VERTICAL_CUT_PREFERENCE: f32 = 2.0;
+
+/// Extracts positioned lines from an engine's JSON output. Entries without
+/// bounds are skipped, so engines that do not report positions produce no
+/// layout.
+pub fn lines_from_ocr_json(
+    engine: &OcrEngine,
+    text_json: &[HashMap<String, String>],
+    image_width: u32,
+    image_height: u32,
+) -> Vec<OcrLayoutLine> {
+    if image_width == 0 || image_height == 0 {
+        return Vec::new();
+    }
+
+    text_json
+        .iter()
+        .filter_map(|entry| {
+            let text = entry.get("text")?.trim();
+            if text.is_empty() {
+                return None;
+            }
+            let value = |key: &str| entry.get(key).and_then(|v| v.parse::<f32>().ok());
+            let (left, top, width, height) = (
+                value("left")?,
+                value("top")?,
+                value("width")?,
+                value("height")?,
+            );
+            if width <= 0.0 || height <= 0.0 {
+                return None;
+            }
+
+            let bounds = match engine {
+                // vision reports normalized coordinates with the origin at the bottom left
+                OcrEngine::AppleNative => TextBounds {
+                    left,
+                    top: 1.0 - top - height,
+                    width,
+                    height,
+                },
+                _ => TextBounds {
+                    left: left / image_width as f32,
+                    top: top / image_height as f32,
+                    width: width / image_width as f32,
+                    height: height / image_height as f32,
+                },
+            };
+
+            // engines report confidence either from 0 to 1 or from 0 to 100
+            let confidence = value("confidence")
+                .or_else(|| value("conf"))
+                .map(|c| if c > 1.0 { c / 100.0 } else { c })
+                .unwrap_or(0.0)
+                .clamp(0.0, 1.0);
+
+            Some(OcrLayoutLine {
+                text: text.to_string(),
+                confidence,
+                bounds,
+                source: TextSource::Ocr,
+            })
+        })
+        .collect()
+}
+
+fn horizontal_overlap(a: &TextBounds, b: &TextBounds) -> f32 {
+    let overlap = a.right().min(b.right()) - a.left.max(b.left);
+    let narrower = a.width.min(b.width);
+    if narrower <= 0.0 {
+        return 0.0;
+    }
+    (overlap / narrower).max(0.0)
+}
+
+/// Builds blocks from lines and numbers them in reading order.
+pub fn build_layout(mut lines: Vec<OcrLayoutLine>) -> Vec<OcrLayoutBlock> {
+    lines.sort_by(|a, b| {
+        a.bounds
+            .top
+            .total_cmp(&b.bounds.top)
+            .then(a.bounds.left.total_cmp(&b.bounds.left))
+    });
+
+    // a line continues the block whose last line sits right above it
+    let mut blocks: Vec<Vec<OcrLayoutLine>> = Vec::new();
+    for line in lines {
+        let target = blocks.iter_mut().rev().find(|block| {
+            let last = block.last().expect("blocks are never empty");
+            let gap = line.bounds.top - last.bounds.bottom();
+            let line_height = last.bounds.height.max(line.bounds.height);
+            gap <= line_height * BLOCK_GAP_RATIO
+                && gap >= -line_height / 2.0
+                && horizontal_overlap(&last.bounds, &line.bounds) >= MIN_HORIZONTAL_OVERLAP
+        });
+        match target {
+            Some(block) => block.push(line),
+            None => blocks.push(vec![line]),
+        }
+    }
+
+    let blocks: Vec<OcrLayoutBlock> = blocks
+        .into_iter()
+        .map(|lines| {
+            let paragraphs = split_paragraphs(lines);
+            let bounds = union_all(paragraphs.iter().map(|p| &p.bounds));
+            OcrLayoutBlock {
+                reading_order: 0,
+                bounds,
+                paragraphs,
+            }
+        })
+        .collect();
+
+    let bounds: Vec<TextBounds> = blocks.iter().map(|b| b.bounds).collect();
+    let mut order = Vec::with_capacity(blocks.len());
+    xy_cut((0..blocks.len()).collect(), &bounds, &mut order);
+
+    let mut blocks: Vec<Option<OcrLayoutBlock>> = blocks.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .enumerate()
+        .filter_map(|(position, index)| {
+            blocks[index].take().map(|mut block| {
+                block.reading_order = position as u32;
+                block
+            })
+        })
+        .collect()
+}
+
+fn split_paragraphs(lines: Vec<OcrLayoutLine>) -> Vec<OcrLayoutParagraph> {
+    let mut paragraphs: Vec<Vec<OcrLayoutLine>> = Vec::new();
+    for line in lines {
+        let new_paragraph = match paragraphs.last().and_then(|p| p.last()) {
+            Some(previous) => {
+                let gap = line.bounds.top - previous.bounds.bottom();
+                gap > previous.bounds.height.max(line.bounds.height) * PARAGRAPH_GAP_RATIO
+            }
+            None => true,
+        };
+        if new_paragraph {
+            paragraphs.push(vec![line]);
+        } else if let Some(paragraph) = paragraphs.last_mut() {
+            paragraph.push(line);
+        }
+    }
+
+    paragraphs
+        .into_iter()
+        .map(|lines| OcrLayoutParagraph {
+            bounds: union_all(lines.iter().map(|l| &l.bounds)),
+            lines,
+        })
+        .collect()
+}
+
+fn union_all<'a>(mut bounds: impl Iterator<Item = &'a TextBounds>) -> TextBounds {
+    let first = bounds.next().copied().unwrap_or_default();
+    bounds.fold(first, |acc, b| acc.union(b))
+}
+
+/// Finds the widest gap splitting the blocks along one axis. Returns the gap
+/// width and the two halves.
+fn best_cut(
+    indices: &[usize],
+    bounds: &[TextBounds],
+    range: impl Fn(&TextBounds) -> (f32, f32),
+) -> Option<(f32, Vec<usize>, Vec<usize>)> {
+    let mut sorted = indices.to_vec();
+    sorted.sort_by(|a, b| range(&bounds[*a]).0.total_cmp(&range(&bounds[*b]).0));
+
+    let mut best: Option<(f32, usize)> = None;
+    let mut max_end = range(&bounds[sorted[0]]).1;
+    for (i, index) in sorted.iter().enumerate().skip(1) {
+        let (start, end) = range(&bounds[*index]);
+        let gap = start - max_end;
+        if gap > 0.0 && best.is_none_or(|(best_gap, _)| gap > best_gap) {
+            best = Some((gap, i));
+        }
+        max_end = max_end.max(end);
+    }
+
+    best.map(|(gap, split)| {
+        let after = sorted.split_off(split);
+        (gap, sorted, after)
+    })
+}
+
+/// Recursive XY-cut: split the page at the widest empty horizontal or
+/// vertical band, read the halves top-to-bottom or left-to-right, and repeat.
+fn xy_cut(indices: Vec<usize>, bounds: &[TextBounds], order: &mut Vec<usize>) {
+    if indices.len() <= 1 {
+        order.extend(indices);
+        return;
+    }
+
+    let horizontal = best_cut(&indices, bounds, |b| (b.top, b.bottom()));
+    let vertical = best_cut(&indices, bounds, |b| (b.left, b.right()));
+
+    let cut = match (horizontal, vertical) {
+        (Some(h), Some(v)) => {
+            if v.0 * VERTICAL_CUT_PREFERENCE >= h.0 {
+                Some(v)
+            } else {
+                Some(h)
+            }
+        }
+        (h, v) => h.or(v),
+    };
+
+    match cut {
+        Some((_, first, second)) => {
+            xy_cut(first, bounds, order);
+            xy_cut(second, bounds, order);
+        }
+        None => {
+            // overlapping blocks, fall back to top-to-bottom order
+            let mut indices = indices;
+            indices.sort_by(|a, b| {
+                bounds[*a]
+                    .top
+                    .total_cmp(&bounds[*b].top)
+                    .then(bounds[*a].left.total_cmp(&bounds[*b].left))
+            });
+            order.extend(indices);
+        }
+    }
+}
+
+/// Text of all blocks in reading order, one paragraph per line.
+pub fn layout_text(blocks: &[OcrLayoutBlock]) -> String {
+    blocks
+        .iter()
+        .map(|b| b.text())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Share of an OCR'd line that accessibility text must cover to replace it.
+const MIN_ACCESSIBILITY_COVERAGE: f32 = 0.5;
+
+/// Merges text read through the accessibility API with OCR'd lines. The
+/// accessibility text is exact, so OCR'd lines it covers are dropped, the
+/// others are kept, e.g. text in images or apps that expose no text.
+pub fn merge_accessibility_lines(
+    ocr_lines: Vec<OcrLayoutLine>,
+    accessibility_lines: Vec<OcrLayoutLine>,
+) -> Vec<OcrLayoutLine> {
+    let mut lines: Vec<OcrLayoutLine> = ocr_lines
+        .into_iter()
+        .filter(|line| {
+            !accessibility_lines.iter().any(|accessible| {
+                coverage(&line.bounds, &accessible.bounds) >= MIN_ACCESSIBILITY_COVERAGE
+            })
+        })
+        .collect();
+    lines.extend(accessibility_lines);
+    lines
+}
+
+/// Share of the area of `a` that lies within `b`.
+fn coverage(a: &TextBounds, b: &TextBounds) -> f32 {
+    let width = a.right().min(b.right()) - a.left.max(b.left);
+    let height = a.bottom().min(b.bottom()) - a.top.max(b.top);
+    let area = a.width * a.height;
+    if width <= 0.0 || height <= 0.0 || area <= 0.0 {
+        return 0.0;
+    }
+    width * height / area
+}
@@ -2,20 +2,33 @@
 pub mod apple;
 pub mod core;
 pub mod custom_ocr;
+pub mod frame_rate;
+pub mod layout;
 #[cfg(target_os = "windows")]
 pub mod microsoft;
 pub mod monitor;
+pub mod paddle;
+pub mod privacy;
+pub mod region;
 #[cfg(target_os = "macos")]
 pub mod run_ui_monitoring_macos;
+pub mod table;
 pub mod tesseract;
+pub mod ui_tree;
+pub mod uia;
 pub mod utils;
+#[cfg(target_os = "linux")]
+pub mod wayland;
 #[cfg(target_os = "macos")]
 pub use apple::perform_ocr_apple;
 pub use core::{continuous_capture, process_ocr_task, CaptureResult, RealtimeVisionEvent, UIFrame};
 // pub use types::CaptureResult;
+pub use privacy::PrivacyBlocklist;
+pub use region::CaptureRegion;
 pub use utils::OcrEngine;
 pub mod capture_screenshot_by_window;
 pub use custom_ocr::perform_ocr_custom;
+pub use paddle::perform_ocr_paddle;
 #[cfg(target_os = "windows")]
 pub use microsoft::perform_ocr_windows;
 #[cfg(target_os = "macos")]
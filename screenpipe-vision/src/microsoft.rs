@@ -39,11 +39,32 @@ pub async fn perform_ocr_windows(image: &DynamicImage) -> Result<(String, String
 
     let text = result.Text()?.to_string();
 
-    let json_output = serde_json::json!([{
-        "text": text,
-        "confidence": "1.0" // Windows OCR doesn't provide confidence scores
-    }])
-    .to_string();
+    // one entry per line with the union of its word boxes, in pixels
+    let mut lines = Vec::new();
+    for line in result.Lines()? {
+        let mut bounds: Option<(f32, f32, f32, f32)> = None;
+        for word in line.Words()? {
+            let rect = word.BoundingRect()?;
+            let (right, bottom) = (rect.X + rect.Width, rect.Y + rect.Height);
+            bounds = Some(match bounds {
+                Some((l, t, r, b)) => (l.min(rect.X), t.min(rect.Y), r.max(right), b.max(bottom)),
+                None => (rect.X, rect.Y, right, bottom),
+            });
+        }
+        let mut entry = serde_json::json!({
+            "text": line.Text()?.to_string(),
+            "confidence": "1.0" // Windows OCR doesn't provide confidence scores
+        });
+        if let Some((left, top, right, bottom)) = bounds {
+            entry["left"] = left.to_string().into();
+            entry["top"] = top.to_string().into();
+            entry["width"] = (right - left).to_string().into();
+            entry["height"] = (bottom - top).to_string().into();
+        }
+        lines.push(entry);
+    }
+
+    let json_output = serde_json::Value::Array(lines).to_string();
 
     Ok((text, json_output, Some(1.0)))
 }
@@ -12,6 +12,8 @@ pub struct SafeMonitor {
 
 #[derive(Clone)]
 pub struct MonitorData {
+    pub x: i32,
+    pub y: i32,
     pub width: u32,
     pub height: u32,
     pub name: String,
@@ -22,6 +24,8 @@ impl SafeMonitor {
     pub fn new(monitor: Monitor) -> Self {
         let monitor_id = monitor.id().unwrap();
         let monitor_data = Arc::new(MonitorData {
+            x: monitor.x().unwrap_or(0),
+            y: monitor.y().unwrap_or(0),
             width: monitor.width().unwrap(),
             height: monitor.height().unwrap(),
             name: monitor.name().unwrap().to_string(),
@@ -37,6 +41,25 @@ impl SafeMonitor {
     pub async fn capture_image(&self) -> Result<DynamicImage> {
         let monitor_id = self.monitor_id;
 
+        #[cfg(target_os = "linux")]
+        if crate::wayland::is_wayland_session() {
+            let index = list_monitors()
+                .await
+                .iter()
+                .position(|m| m.id() == monitor_id)
+                .unwrap_or(0);
+            match crate::wayland::capture_monitor(self.x(), self.y(), index).await {
+                Ok(image) => return Ok(image),
+                Err(e) => {
+                    tracing::debug!(
+                        "wayland portal capture failed for monitor {}, falling back to xcap: {}",
+                        monitor_id,
+                        e
+                    );
+                }
+            }
+        }
+
         let image = std::thread::spawn(move || -> Result<DynamicImage> {
             let monitor = Monitor::all()
                 .map_err(Error::from)?
@@ -71,6 +94,14 @@ impl SafeMonitor {
         &self.monitor_data.name
     }
 
+    pub fn x(&self) -> i32 {
+        self.monitor_data.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.monitor_data.y
+    }
+
     pub fn width(&self) -> u32 {
         self.monitor_data.width
     }
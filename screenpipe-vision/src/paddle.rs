@@ -0,0 +1,535 @@
+//! PaddleOCR (PP-OCRv4) running on ONNX Runtime.
+//!
+//! Text detection uses the DB model to find text boxes, each box is then
+//! recognized with the CRNN model and decoded with CTC. Works noticeably
+//! better than the native engines on dense monospace text like terminals.
+//!
+//! The models run on the GPU when one is available, see [`OcrDevice`].
+
+use anyhow::{anyhow, Result};
+use image::{imageops::FilterType, DynamicImage, GenericImageView, GrayImage, Luma};
+use ndarray::{Array4, ArrayViewD, Axis};
+use once_cell::sync::Lazy;
+use ort::{
+    CPUExecutionProvider, CUDAExecutionProvider, CoreMLExecutionProvider,
+    DirectMLExecutionProvider, ExecutionProvider, ExecutionProviderDispatch,
+    GraphOptimizationLevel, Session,
+};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::{Mutex, OnceCell};
+use tracing::{debug, info, warn};
+
+const DET_MODEL: &str = "ch_PP-OCRv4_det.onnx";
+const REC_MODEL: &str = "ch_PP-OCRv4_rec.onnx";
+const REC_DICT: &str = "ppocr_keys_v1.txt";
+const DEFAULT_MODELS_URL: &str =
+    "https://github.com/mediar-ai/screenpipe/raw/refs/heads/main/screenpipe-vision/models/paddle";
+
+/// Longest side of the image fed to the detection model.
+const DET_MAX_SIDE: u32 = 1280;
+/// Pixels above this probability are considered text.
+const DET_THRESHOLD: f32 = 0.3;
+/// Boxes whose mean probability is below this are dropped.
+const DET_BOX_THRESHOLD: f32 = 0.6;
+/// Boxes are grown by this ratio of their height, DB shrinks text regions.
+const DET_UNCLIP_RATIO: f32 = 0.5;
+const DET_MIN_BOX_SIZE: u32 = 3;
+const REC_HEIGHT: u32 = 48;
+const REC_MAX_WIDTH: u32 = 1280;
+
+static PADDLE_OCR: OnceCell<Arc<PaddleOcr>> = OnceCell::const_new();
+static DOWNLOAD_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+static OCR_DEVICE: Lazy<std::sync::Mutex<OcrDevice>> =
+    Lazy::new(|| std::sync::Mutex::new(OcrDevice::default()));
+
+/// Hardware the OCR models run on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OcrDevice {
+    /// The first available GPU, otherwise the CPU
+    #[default]
+    Auto,
+    Cpu,
+    /// NVIDIA GPUs, on Linux and Windows
+    Cuda,
+    /// Apple Neural Engine and GPU, on macOS
+    CoreMl,
+    /// Any DirectX 12 GPU, on Windows
+    DirectMl,
+}
+
+impl fmt::Display for OcrDevice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrDevice::Auto => write!(f, "auto"),
+            OcrDevice::Cpu => write!(f, "cpu"),
+            OcrDevice::Cuda => write!(f, "cuda"),
+            OcrDevice::CoreMl => write!(f, "coreml"),
+            OcrDevice::DirectMl => write!(f, "directml"),
+        }
+    }
+}
+
+impl OcrDevice {
+    fn execution_provider(&self) -> Option<ExecutionProviderDispatch> {
+        match self {
+            OcrDevice::Auto => None,
+            OcrDevice::Cpu => Some(CPUExecutionProvider::default().build()),
+            OcrDevice::Cuda => Some(CUDAExecutionProvider::default().build()),
+            OcrDevice::CoreMl => Some(CoreMLExecutionProvider::default().build()),
+            OcrDevice::DirectMl => Some(DirectMLExecutionProvider::default().build()),
+        }
+    }
+
+    /// Whether ONNX Runtime was built with this device and can use it here.
+    fn is_available(&self) -> bool {
+        match self {
+            OcrDevice::Auto | OcrDevice::Cpu => Ok(true),
+            OcrDevice::Cuda => CUDAExecutionProvider::default().is_available(),
+            OcrDevice::CoreMl => CoreMLExecutionProvider::default().is_available(),
+            OcrDevice::DirectMl => DirectMLExecutionProvider::default().is_available(),
+        }
+        .unwrap_or(false)
+    }
+}
+
+/// Sets the device the OCR models run on. Only applies before the models are
+/// first loaded.
+pub fn set_ocr_device(device: OcrDevice) {
+    *OCR_DEVICE.lock().unwrap_or_else(|e| e.into_inner()) = device;
+}
+
+/// Device the OCR models run on, once they are loaded.
+pub fn active_ocr_device() -> Option<OcrDevice> {
+    PADDLE_OCR.get().map(|ocr| ocr.device)
+}
+
+/// Picks the device for `device`: the first available GPU for
+/// [`OcrDevice::Auto`], or the CPU if the requested one isn't available.
+pub fn resolve_ocr_device(device: OcrDevice) -> OcrDevice {
+    match device {
+        OcrDevice::Auto => [OcrDevice::Cuda, OcrDevice::CoreMl, OcrDevice::DirectMl]
+            .into_iter()
+            .find(OcrDevice::is_available)
+            .unwrap_or(OcrDevice::Cpu),
+        device if device.is_available() => device,
+        device => {
+            warn!(
+                "{} isn't available for ocr, falling back to the cpu",
+                device
+            );
+            OcrDevice::Cpu
+        }
+    }
+}
+
+/// A recognized line of text with its bounding box in image coordinates.
+#[derive(Debug, Clone)]
+pub struct PaddleTextBox {
+    pub text: String,
+    pub confidence: f32,
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct PaddleOcr {
+    detection: Session,
+    recognition: Session,
+    dictionary: Vec<String>,
+    device: OcrDevice,
+}
+
+impl PaddleOcr {
+    /// Loads the models on `device`, which should come from
+    /// [`resolve_ocr_device`].
+    pub fn load(model_dir: &std::path::Path, device: OcrDevice) -> Result<Self> {
+        let dictionary = std::fs::read_to_string(model_dir.join(REC_DICT))?;
+        Ok(Self {
+            detection: create_session(model_dir.join(DET_MODEL), device)?,
+            recognition: create_session(model_dir.join(REC_MODEL), device)?,
+            dictionary: load_dictionary(&dictionary),
+            device,
+        })
+    }
+
+    pub fn device(&self) -> OcrDevice {
+        self.device
+    }
+
+    pub fn recognize(&self, image: &DynamicImage) -> Result<Vec<PaddleTextBox>> {
+        let boxes = self.detect(image)?;
+        let mut results = Vec::with_capacity(boxes.len());
+        for (left, top, width, height) in boxes {
+            let crop = image.crop_imm(left, top, width, height);
+            let (text, confidence) = self.recognize_line(&crop)?;
+            if text.trim().is_empty() {
+                continue;
+            }
+            results.push(PaddleTextBox {
+                text,
+                confidence,
+                left,
+                top,
+                width,
+                height,
+            });
+        }
+        Ok(sort_reading_order(results))
+    }
+
+    fn detect(&self, image: &DynamicImage) -> Result<Vec<(u32, u32, u32, u32)>> {
+        let (width, height) = image.dimensions();
+        let scale = (DET_MAX_SIDE as f32 / width.max(height) as f32).min(1.0);
+        let resized_w = round_to_32((width as f32 * scale) as u32);
+        let resized_h = round_to_32((height as f32 * scale) as u32);
+        let resized = image
+            .resize_exact(resized_w, resized_h, FilterType::Triangle)
+            .to_rgb8();
+
+        const MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+        const STD: [f32; 3] = [0.229, 0.224, 0.225];
+        let mut input = Array4::<f32>::zeros((1, 3, resized_h as usize, resized_w as usize));
+        for (x, y, pixel) in resized.enumerate_pixels() {
+            for c in 0..3 {
+                input[[0, c, y as usize, x as usize]] =
+                    (pixel[c] as f32 / 255.0 - MEAN[c]) / STD[c];
+            }
+        }
+
+        let outputs = self.detection.run(ort::inputs![input.view()]?)?;
+        let probabilities = outputs[0].try_extract_tensor::<f32>()?;
+        let probability_map = probability_map(&probabilities, resized_w, resized_h)?;
+
+        let scale_x = width as f32 / resized_w as f32;
+        let scale_y = height as f32 / resized_h as f32;
+
+        Ok(find_text_boxes(&probability_map)
+            .into_iter()
+            .filter_map(|(x0, y0, x1, y1)| {
+                let pad = ((y1 - y0 + 1) as f32 * DET_UNCLIP_RATIO).round() as u32;
+                let left = ((x0.saturating_sub(pad)) as f32 * scale_x) as u32;
+                let top = ((y0.saturating_sub(pad)) as f32 * scale_y) as u32;
+                let right = (((x1 + pad + 1) as f32 * scale_x) as u32).min(width);
+                let bottom = (((y1 + pad + 1) as f32 * scale_y) as u32).min(height);
+                if right > left + DET_MIN_BOX_SIZE && bottom > top + DET_MIN_BOX_SIZE {
+                    Some((left, top, right - left, bottom - top))
+                } else {
+                    None
+                }
+            })
+            .collect())
+    }
+
+    fn recognize_line(&self, crop: &DynamicImage) -> Result<(String, f32)> {
+        let (width, height) = crop.dimensions();
+        let target_w = ((width as f32 * REC_HEIGHT as f32 / height as f32).ceil() as u32)
+            .clamp(REC_HEIGHT, REC_MAX_WIDTH);
+        let resized = crop
+            .resize_exact(target_w, REC_HEIGHT, FilterType::Triangle)
+            .to_rgb8();
+
+        let mut input = Array4::<f32>::zeros((1, 3, REC_HEIGHT as usize, target_w as usize));
+        for (x, y, pixel) in resized.enumerate_pixels() {
+            for c in 0..3 {
+                input[[0, c, y as usize, x as usize]] = (pixel[c] as f32 / 255.0 - 0.5) / 0.5;
+            }
+        }
+
+        let outputs = self.recognition.run(ort::inputs![input.view()]?)?;
+        let logits = outputs[0].try_extract_tensor::<f32>()?;
+        Ok(ctc_decode(&logits, &self.dictionary))
+    }
+}
+
+fn create_session(path: PathBuf, device: OcrDevice) -> Result<Session> {
+    let builder = Session::builder()?
+        .with_optimization_level(GraphOptimizationLevel::Level3)?
+        .with_intra_threads(2)?;
+
+    // the cpu provider is always registered last, anything not supported by
+    // the device runs there
+    let builder = match device.execution_provider() {
+        Some(provider) => builder.with_execution_providers([provider.error_on_failure()])?,
+        None => builder,
+    };
+
+    Ok(builder.commit_from_file(path)?)
+}
+
+fn round_to_32(value: u32) -> u32 {
+    (value.div_ceil(32) * 32).max(32)
+}
+
+/// Paddle dictionaries list one character per line. Index 0 of the model
+/// output is the CTC blank and the last index is a space.
+pub fn load_dictionary(contents: &str) -> Vec<String> {
+    let mut dictionary = vec![String::new()];
+    dictionary.extend(
+        contents
+            .lines()
+            .map(|line| line.trim_end_matches('\r').to_string()),
+    );
+    dictionary.push(" ".to_string());
+    dictionary
+}
+
+fn probability_map(tensor: &ArrayViewD<f32>, width: u32, height: u32) -> Result<GrayImage> {
+    let shape = tensor.shape();
+    if shape.len() != 4 || shape[2] != height as usize || shape[3] != width as usize {
+        return Err(anyhow!("unexpected detection output shape {:?}", shape));
+    }
+    let mut map = GrayImage::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let p = tensor[[0, 0, y as usize, x as usize]];
+            map.put_pixel(x, y, Luma([(p.clamp(0.0, 1.0) * 255.0) as u8]));
+        }
+    }
+    Ok(map)
+}
+
+/// Finds connected text regions in the probability map and returns their
+/// bounding boxes as `(x0, y0, x1, y1)`, inclusive.
+pub fn find_text_boxes(map: &GrayImage) -> Vec<(u32, u32, u32, u32)> {
+    let (width, height) = map.dimensions();
+    let threshold = (DET_THRESHOLD * 255.0) as u8;
+    let mut visited = vec![false; (width * height) as usize];
+    let mut boxes = Vec::new();
+    let mut stack = Vec::new();
+
+    for start_y in 0..height {
+        for start_x in 0..width {
+            let start = (start_y * width + start_x) as usize;
+            if visited[start] || map.get_pixel(start_x, start_y)[0] <= threshold {
+                continue;
+            }
+
+            let (mut x0, mut y0, mut x1, mut y1) = (start_x, start_y, start_x, start_y);
+            let mut score_sum = 0u64;
+            let mut count = 0u64;
+            visited[start] = true;
+            stack.push((start_x, start_y));
+
+            while let Some((x, y)) = stack.pop() {
+                score_sum += map.get_pixel(x, y)[0] as u64;
+                count += 1;
+                x0 = x0.min(x);
+                y0 = y0.min(y);
+                x1 = x1.max(x);
+                y1 = y1.max(y);
+
+                let neighbours = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+                for (nx, ny) in neighbours {
+                    if nx >= width || ny >= height {
+                        continue;
+                    }
+                    let index = (ny * width + nx) as usize;
+                    if !visited[index] && map.get_pixel(nx, ny)[0] > threshold {
+                        visited[index] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+
+            let mean_score = score_sum as f32 / count as f32 / 255.0;
+            if mean_score >= DET_BOX_THRESHOLD
+                && x1 - x0 + 1 >= DET_MIN_BOX_SIZE
+                && y1 - y0 + 1 >= DET_MIN_BOX_SIZE
+            {
+                boxes.push((x0, y0, x1, y1));
+            }
+        }
+    }
+
+    boxes
+}
+
+/// Greedy CTC decoding: take the best class per timestep, collapse repeats
+/// and drop blanks.
+pub fn ctc_decode(logits: &ArrayViewD<f32>, dictionary: &[String]) -> (String, f32) {
+    if logits.ndim() != 3 || logits.shape()[0] == 0 {
+        return (String::new(), 0.0);
+    }
+    let Ok(steps) = logits
+        .index_axis(Axis(0), 0)
+        .into_dimensionality::<ndarray::Ix2>()
+    else {
+        return (String::new(), 0.0);
+    };
+
+    let mut text = String::new();
+    let mut confidence_sum = 0.0;
+    let mut kept = 0;
+    let mut previous = 0usize;
+
+    for row in steps.rows() {
+        let (index, score) = row
+            .iter()
+            .enumerate()
+            .fold((0usize, f32::MIN), |best, (i, &v)| {
+                if v > best.1 {
+                    (i, v)
+                } else {
+                    best
+                }
+            });
+        if index != 0 && index != previous {
+            if let Some(symbol) = dictionary.get(index) {
+                text.push_str(symbol);
+                confidence_sum += score;
+                kept += 1;
+            }
+        }
+        previous = index;
+    }
+
+    let confidence = if kept > 0 {
+        confidence_sum / kept as f32
+    } else {
+        0.0
+    };
+    (text, confidence)
+}
+
+/// Orders boxes top to bottom, and left to right within a line. Boxes whose
+/// vertical centers are within half a line height belong to the same line.
+pub fn sort_reading_order(mut boxes: Vec<PaddleTextBox>) -> Vec<PaddleTextBox> {
+    boxes.sort_by_key(|b| (b.top, b.left));
+    let mut lines: Vec<Vec<PaddleTextBox>> = Vec::new();
+    for b in boxes {
+        let center = b.top + b.height / 2;
+        match lines.last_mut() {
+            Some(line)
+                if line.first().is_some_and(|first| {
+                    center.abs_diff(first.top + first.height / 2) <= first.height / 2
+                }) =>
+            {
+                line.push(b)
+            }
+            _ => lines.push(vec![b]),
+        }
+    }
+    lines
+        .into_iter()
+        .flat_map(|mut line| {
+            line.sort_by_key(|b| b.left);
+            line
+        })
+        .collect()
+}
+
+fn models_dir() -> Result<PathBuf> {
+    let cache_dir = dirs::cache_dir().ok_or_else(|| anyhow!("failed to get cache dir"))?;
+    Ok(cache_dir
+        .join("screenpipe")
+        .join("models")
+        .join("paddle-ocr"))
+}
+
+async fn download_models(model_dir: &std::path::Path) -> Result<()> {
+    let _guard = DOWNLOAD_LOCK.lock().await;
+    tokio::fs::create_dir_all(model_dir).await?;
+
+    let base_url = std::env::var("SCREENPIPE_PADDLE_OCR_MODELS_URL")
+        .unwrap_or_else(|_| DEFAULT_MODELS_URL.to_string());
+
+    for filename in [DET_MODEL, REC_MODEL, REC_DICT] {
+        let path = model_dir.join(filename);
+        if path.exists() {
+            continue;
+        }
+        let url = format!("{}/{}", base_url.trim_end_matches('/'), filename);
+        info!("downloading paddle ocr model {} from {}", filename, url);
+        let response = reqwest::get(&url).await?.error_for_status()?;
+        let data = response.bytes().await?;
+
+        // write to a temp file first so a partial download is never loaded
+        let tmp_path = path.with_extension("download");
+        tokio::fs::write(&tmp_path, &data).await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+        debug!("saved {} ({} bytes)", filename, data.len());
+    }
+
+    Ok(())
+}
+
+async fn get_or_load() -> Result<Arc<PaddleOcr>> {
+    PADDLE_OCR
+        .get_or_try_init(|| async {
+            let model_dir = models_dir()?;
+            download_models(&model_dir).await?;
+            let device = *OCR_DEVICE.lock().unwrap_or_else(|e| e.into_inner());
+            let ocr = tokio::task::spawn_blocking(move || {
+                let device = resolve_ocr_device(device);
+                match PaddleOcr::load(&model_dir, device) {
+                    Err(e) if device != OcrDevice::Cpu => {
+                        warn!(
+                            "failed to run ocr on {}, falling back to the cpu: {}",
+                            device, e
+                        );
+                        PaddleOcr::load(&model_dir, OcrDevice::Cpu)
+                    }
+                    result => result,
+                }
+            })
+            .await??;
+            info!("paddle ocr models loaded on {}", ocr.device());
+            Ok(Arc::new(ocr))
+        })
+        .await
+        .cloned()
+}
+
+pub async fn perform_ocr_paddle(image: &DynamicImage) -> Result<(String, String, Option<f64>)> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return Ok(("".to_string(), "[]".to_string(), None));
+    }
+
+    let ocr = get_or_load().await.map_err(|e| {
+        warn!("failed to load paddle ocr: {}", e);
+        e
+    })?;
+
+    let image = image.clone();
+    let boxes = tokio::task::spawn_blocking(move || ocr.recognize(&image)).await??;
+
+    let text = boxes
+        .iter()
+        .map(|b| b.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let json_output = serde_json::Value::Array(
+        boxes
+            .iter()
+            .map(|b| {
+                serde_json::json!({
+                    "text": b.text,
+                    "confidence": format!("{:.2}", b.confidence * 100.0),
+                    "left": b.left.to_string(),
+                    "top": b.top.to_string(),
+                    "width": b.width.to_string(),
+                    "height": b.height.to_string(),
+                })
+            })
+            .collect(),
+    )
+    .to_string();
+
+    let overall_confidence = if boxes.is_empty() {
+        None
+    } else {
+        Some(boxes.iter().map(|b| b.confidence as f64).sum::<f64>() / boxes.len() as f64)
+    };
+
+    Ok((text, json_output, overall_confidence))
+}
@@ -0,0 +1,102 @@
+use regex::{Regex, RegexBuilder};
+use tracing::warn;
+
+/// A single privacy blocklist rule, checked against the focused window.
+///
+/// - `app:<name>` matches the app name (case-insensitive, exact)
+/// - `url:<regex>` matches the browser URL with a case-insensitive regex
+/// - anything else is a case-insensitive substring of the app name or URL
+#[derive(Debug, Clone)]
+pub enum BlocklistPattern {
+    App(String),
+    Url(Regex),
+    Contains(String),
+}
+
+impl BlocklistPattern {
+    pub fn parse(pattern: &str) -> Self {
+        if let Some(app) = pattern.strip_prefix("app:") {
+            return BlocklistPattern::App(app.trim().to_lowercase());
+        }
+
+        if let Some(url) = pattern.strip_prefix("url:") {
+            match RegexBuilder::new(url).case_insensitive(true).build() {
+                Ok(regex) => return BlocklistPattern::Url(regex),
+                Err(e) => {
+                    warn!(
+                        "invalid blocklist url regex '{}': {}, falling back to substring match",
+                        url, e
+                    );
+                    return BlocklistPattern::Contains(url.to_lowercase());
+                }
+            }
+        }
+
+        BlocklistPattern::Contains(pattern.to_lowercase())
+    }
+
+    fn matches(&self, app_name_lower: &str, url: Option<&str>) -> bool {
+        match self {
+            BlocklistPattern::App(app) => app_name_lower == app,
+            BlocklistPattern::Url(regex) => url.is_some_and(|url| regex.is_match(url)),
+            BlocklistPattern::Contains(needle) => {
+                app_name_lower.contains(needle)
+                    || url.is_some_and(|url| url.to_lowercase().contains(needle))
+            }
+        }
+    }
+}
+
+/// Pattern reported when capture stops for a private browsing window.
+pub const PRIVATE_BROWSING_PATTERN: &str = "private browsing";
+
+/// Apps and sites that must never be recorded. While one of them is focused
+/// frames are dropped before OCR and audio is not recorded.
+#[derive(Debug, Clone, Default)]
+pub struct PrivacyBlocklist {
+    patterns: Vec<(String, BlocklistPattern)>,
+    /// Also block private and incognito browser windows
+    private_browsing: bool,
+}
+
+impl PrivacyBlocklist {
+    pub fn new(patterns: &[String]) -> Self {
+        Self {
+            patterns: patterns
+                .iter()
+                .map(|p| (p.clone(), BlocklistPattern::parse(p)))
+                .collect(),
+            private_browsing: false,
+        }
+    }
+
+    pub fn block_private_browsing(mut self, enabled: bool) -> Self {
+        self.private_browsing = enabled;
+        self
+    }
+
+    pub fn blocks_private_browsing(&self) -> bool {
+        self.private_browsing
+    }
+
+    /// Whether nothing is blocked.
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty() && !self.private_browsing
+    }
+
+    /// Whether any rule looks at the browser URL, which is only read if so.
+    pub fn matches_urls(&self) -> bool {
+        self.patterns
+            .iter()
+            .any(|(_, p)| !matches!(p, BlocklistPattern::App(_)))
+    }
+
+    /// The first rule matching the focused window, as it was written.
+    pub fn matching_pattern(&self, app_name: &str, url: Option<&str>) -> Option<&str> {
+        let app_name_lower = app_name.to_lowercase();
+        self.patterns
+            .iter()
+            .find(|(_, p)| p.matches(&app_name_lower, url))
+            .map(|(pattern, _)| pattern.as_str())
+    }
+}
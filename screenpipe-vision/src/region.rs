@@ -0,0 +1,175 @@
+use image::{DynamicImage, GenericImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::capture_screenshot_by_window::CapturedWindow;
+
+/// App name stored for frames produced from a capture region.
+pub const REGION_APP_NAME: &str = "screen region";
+
+/// A rectangular area of a monitor, in physical pixels relative to the
+/// monitor's top-left corner. When regions are configured for a monitor only
+/// those areas are captured, OCR'd and written to the video chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureRegion {
+    pub monitor_id: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CaptureRegion {
+    /// Clamps the region to the given image dimensions. Returns `None` if
+    /// nothing of the region is left on screen.
+    pub fn clamp_to(&self, width: u32, height: u32) -> Option<CaptureRegion> {
+        if self.x >= width || self.y >= height {
+            return None;
+        }
+        let clamped_width = self.width.min(width - self.x);
+        let clamped_height = self.height.min(height - self.y);
+        if clamped_width == 0 || clamped_height == 0 {
+            return None;
+        }
+        Some(CaptureRegion {
+            width: clamped_width,
+            height: clamped_height,
+            ..*self
+        })
+    }
+
+    pub fn crop(&self, image: &DynamicImage) -> Option<DynamicImage> {
+        let region = self.clamp_to(image.width(), image.height())?;
+        Some(image.crop_imm(region.x, region.y, region.width, region.height))
+    }
+}
+
+impl fmt::Display for CaptureRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{},{},{},{}",
+            self.monitor_id, self.x, self.y, self.width, self.height
+        )
+    }
+}
+
+/// Parses `<monitor_id>:<x>,<y>,<width>,<height>`, e.g. `1:0,0,1280,720`.
+impl FromStr for CaptureRegion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (monitor_id, rect) = s.split_once(':').ok_or_else(|| {
+            format!(
+                "invalid region '{}', expected <monitor_id>:<x>,<y>,<width>,<height>",
+                s
+            )
+        })?;
+
+        let monitor_id = monitor_id
+            .trim()
+            .parse::<u32>()
+            .map_err(|e| format!("invalid monitor id in region '{}': {}", s, e))?;
+
+        let values = rect
+            .split(',')
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid coordinates in region '{}': {}", s, e))?;
+
+        let [x, y, width, height] = values[..] else {
+            return Err(format!(
+                "invalid region '{}', expected 4 values <x>,<y>,<width>,<height>",
+                s
+            ));
+        };
+
+        if width == 0 || height == 0 {
+            return Err(format!("region '{}' must have a non-zero size", s));
+        }
+
+        Ok(CaptureRegion {
+            monitor_id,
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+/// Returns the regions that apply to the given monitor.
+pub fn regions_for_monitor(regions: &[CaptureRegion], monitor_id: u32) -> Vec<CaptureRegion> {
+    regions
+        .iter()
+        .filter(|r| r.monitor_id == monitor_id)
+        .copied()
+        .collect()
+}
+
+/// Crops the frame to the bounding box of all regions and blanks everything
+/// that is not covered by a region, so nothing outside the configured areas
+/// ends up in the stored video.
+pub fn mask_to_regions(image: &DynamicImage, regions: &[CaptureRegion]) -> DynamicImage {
+    let regions: Vec<CaptureRegion> = regions
+        .iter()
+        .filter_map(|r| r.clamp_to(image.width(), image.height()))
+        .collect();
+
+    if regions.is_empty() {
+        return image.clone();
+    }
+
+    let min_x = regions.iter().map(|r| r.x).min().unwrap();
+    let min_y = regions.iter().map(|r| r.y).min().unwrap();
+    let max_x = regions.iter().map(|r| r.x + r.width).max().unwrap();
+    let max_y = regions.iter().map(|r| r.y + r.height).max().unwrap();
+
+    let mut canvas =
+        RgbaImage::from_pixel(max_x - min_x, max_y - min_y, Rgba([0, 0, 0, 255]));
+    for region in &regions {
+        let cropped = image
+            .crop_imm(region.x, region.y, region.width, region.height)
+            .to_rgba8();
+        // regions are clamped to the source and the canvas covers their bounding box
+        let _ = canvas.copy_from(&cropped, region.x - min_x, region.y - min_y);
+    }
+
+    DynamicImage::ImageRgba8(canvas)
+}
+
+/// Turns each region into a pseudo-window so the regular per-window OCR path
+/// handles it, instead of OCR'ing the real windows on screen.
+pub fn capture_regions_as_windows(
+    image: &DynamicImage,
+    regions: &[CaptureRegion],
+) -> Vec<CapturedWindow> {
+    let clamped: Vec<CaptureRegion> = regions
+        .iter()
+        .filter_map(|r| r.clamp_to(image.width(), image.height()))
+        .collect();
+    // the stored frame starts at the top-left corner of all regions
+    let min_x = clamped.iter().map(|r| r.x).min().unwrap_or(0);
+    let min_y = clamped.iter().map(|r| r.y).min().unwrap_or(0);
+
+    regions
+        .iter()
+        .filter_map(|region| {
+            let clamped = region.clamp_to(image.width(), image.height())?;
+            region.crop(image).map(|cropped| CapturedWindow {
+                image: cropped,
+                app_name: REGION_APP_NAME.to_string(),
+                window_name: format!("region {}", region),
+                process_id: -1,
+                is_focused: true,
+                frame_rect: Some((
+                    clamped.x - min_x,
+                    clamped.y - min_y,
+                    clamped.width,
+                    clamped.height,
+                )),
+            })
+        })
+        .collect()
+}
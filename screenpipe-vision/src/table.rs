@@ -0,0 +1,153 @@
+//! Detects tables in OCR output by looking for runs of rows whose cells line
+//! up in the same columns, e.g. spreadsheets and dashboards.
+
+use screenpipe_db::{ExtractedTable, OcrLayoutLine, TextBounds};
+
+/// Vertical overlap, relative to the shorter line, for two lines to share a row.
+const MIN_ROW_OVERLAP: f32 = 0.5;
+/// Rows further apart than this many line heights end a table.
+const MAX_ROW_GAP_RATIO: f32 = 1.5;
+const MIN_ROWS: usize = 2;
+const MIN_COLUMNS: usize = 2;
+/// Share of cells that must hold text, sparse grids are usually not tables.
+const MIN_FILL_RATIO: f32 = 0.5;
+/// Cells are short, side by side lines of prose (two column layouts) are not.
+const MAX_AVG_CELL_CHARS: usize = 40;
+
+struct Row<'a> {
+    bounds: TextBounds,
+    cells: Vec<&'a OcrLayoutLine>,
+}
+
+fn vertical_overlap(a: &TextBounds, b: &TextBounds) -> f32 {
+    let overlap = a.bottom().min(b.bottom()) - a.top.max(b.top);
+    let shorter = a.height.min(b.height);
+    if shorter <= 0.0 {
+        return 0.0;
+    }
+    (overlap / shorter).max(0.0)
+}
+
+fn group_rows(lines: &[OcrLayoutLine]) -> Vec<Row<'_>> {
+    let mut sorted: Vec<&OcrLayoutLine> = lines.iter().collect();
+    sorted.sort_by(|a, b| {
+        (a.bounds.top + a.bounds.height / 2.0).total_cmp(&(b.bounds.top + b.bounds.height / 2.0))
+    });
+
+    let mut rows: Vec<Row> = Vec::new();
+    for line in sorted {
+        match rows.last_mut() {
+            Some(row) if vertical_overlap(&row.bounds, &line.bounds) >= MIN_ROW_OVERLAP => {
+                row.bounds = row.bounds.union(&line.bounds);
+                row.cells.push(line);
+            }
+            _ => rows.push(Row {
+                bounds: line.bounds,
+                cells: vec![line],
+            }),
+        }
+    }
+
+    for row in rows.iter_mut() {
+        row.cells
+            .sort_by(|a, b| a.bounds.left.total_cmp(&b.bounds.left));
+    }
+    rows
+}
+
+/// Merges the horizontal extents of all cells into column ranges.
+fn find_columns(rows: &[Row]) -> Vec<(f32, f32)> {
+    let mut ranges: Vec<(f32, f32)> = rows
+        .iter()
+        .flat_map(|r| r.cells.iter().map(|c| (c.bounds.left, c.bounds.right())))
+        .collect();
+    ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut columns: Vec<(f32, f32)> = Vec::new();
+    for (start, end) in ranges {
+        match columns.last_mut() {
+            Some(column) if start <= column.1 => column.1 = column.1.max(end),
+            _ => columns.push((start, end)),
+        }
+    }
+    columns
+}
+
+fn build_table(rows: &[Row]) -> Option<ExtractedTable> {
+    if rows.len() < MIN_ROWS {
+        return None;
+    }
+
+    let columns = find_columns(rows);
+    if columns.len() < MIN_COLUMNS {
+        return None;
+    }
+
+    let cell_count = rows.iter().map(|r| r.cells.len()).sum::<usize>();
+    let char_count = rows
+        .iter()
+        .flat_map(|r| r.cells.iter())
+        .map(|c| c.text.chars().count())
+        .sum::<usize>();
+    if char_count / cell_count > MAX_AVG_CELL_CHARS {
+        return None;
+    }
+
+    let mut filled = 0;
+    let grid: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            let mut cells = vec![String::new(); columns.len()];
+            for cell in &row.cells {
+                let column = columns
+                    .iter()
+                    .position(|(start, end)| cell.bounds.left >= *start && cell.bounds.left <= *end)
+                    .unwrap_or(columns.len() - 1);
+                if cells[column].is_empty() {
+                    filled += 1;
+                } else {
+                    cells[column].push(' ');
+                }
+                cells[column].push_str(&cell.text);
+            }
+            cells
+        })
+        .collect();
+
+    if (filled as f32) < (rows.len() * columns.len()) as f32 * MIN_FILL_RATIO {
+        return None;
+    }
+
+    let bounds = rows
+        .iter()
+        .skip(1)
+        .fold(rows[0].bounds, |acc, r| acc.union(&r.bounds));
+    Some(ExtractedTable { bounds, rows: grid })
+}
+
+/// Finds tables among positioned OCR lines. Each line is treated as a cell,
+/// so this works best with engines that report one box per cell.
+pub fn detect_tables(lines: &[OcrLayoutLine]) -> Vec<ExtractedTable> {
+    let rows = group_rows(lines);
+
+    let mut tables = Vec::new();
+    let mut run: Vec<Row> = Vec::new();
+    for row in rows {
+        let continues = row.cells.len() >= MIN_COLUMNS
+            && run.last().is_none_or(|previous| {
+                let gap = row.bounds.top - previous.bounds.bottom();
+                gap <= previous.bounds.height.max(row.bounds.height) * MAX_ROW_GAP_RATIO
+            });
+
+        if !continues {
+            tables.extend(build_table(&run));
+            run.clear();
+        }
+        if row.cells.len() >= MIN_COLUMNS {
+            run.push(row);
+        }
+    }
+    tables.extend(build_table(&run));
+
+    tables
+}
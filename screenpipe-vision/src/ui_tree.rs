@@ -0,0 +1,142 @@
+//! Snapshots of the accessibility tree of the focused window, so that the
+//! buttons and fields on screen, and the one with the focus, are known.
+
+use screenpipe_db::AccessibilityNode;
+
+/// Deepest level of the tree that is read.
+#[cfg(target_os = "macos")]
+const MAX_DEPTH: usize = 30;
+/// Most elements read from one window, huge trees are cut.
+#[cfg(target_os = "macos")]
+const MAX_NODES: usize = 2000;
+/// Longest value kept for one element.
+#[cfg(target_os = "macos")]
+const MAX_VALUE_CHARS: usize = 1000;
+
+/// Accessibility tree of the focused window of a process. `None` if it can't
+/// be read, or on platforms other than macOS.
+pub fn focused_window_tree(process_id: i32) -> Option<AccessibilityNode> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::focused_window_tree(process_id)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = process_id;
+        None
+    }
+}
+
+/// Drops empty strings and cuts long ones to `MAX_VALUE_CHARS`.
+#[cfg(target_os = "macos")]
+fn clean_text(text: String) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    Some(text.chars().take(MAX_VALUE_CHARS).collect())
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::{clean_text, MAX_DEPTH, MAX_NODES};
+    use accessibility_sys::{
+        kAXChildrenAttribute, kAXDescriptionAttribute, kAXErrorSuccess, kAXFocusedAttribute,
+        kAXFocusedWindowAttribute, kAXRoleAttribute, kAXSubroleAttribute, kAXTitleAttribute,
+        kAXValueAttribute, AXUIElementCopyAttributeValue, AXUIElementCreateApplication,
+        AXUIElementRef,
+    };
+    use core_foundation::{
+        array::{CFArray, CFArrayRef},
+        base::{CFRelease, CFType, CFTypeRef, TCFType},
+        boolean::CFBoolean,
+        number::CFNumber,
+        string::CFString,
+    };
+    use screenpipe_db::AccessibilityNode;
+
+    pub fn focused_window_tree(process_id: i32) -> Option<AccessibilityNode> {
+        unsafe {
+            let app_element = AXUIElementCreateApplication(process_id);
+            let window = copy_attribute(app_element, kAXFocusedWindowAttribute);
+            CFRelease(app_element as CFTypeRef);
+
+            let window = window?;
+            let mut remaining = MAX_NODES;
+            read_element(window.as_CFTypeRef() as AXUIElementRef, 0, &mut remaining)
+        }
+    }
+
+    unsafe fn read_element(
+        element: AXUIElementRef,
+        depth: usize,
+        remaining: &mut usize,
+    ) -> Option<AccessibilityNode> {
+        if *remaining == 0 {
+            return None;
+        }
+        *remaining -= 1;
+
+        let role = copy_attribute(element, kAXRoleAttribute).and_then(|v| to_string(&v))?;
+        let label = copy_attribute(element, kAXTitleAttribute)
+            .and_then(|v| to_string(&v))
+            .and_then(clean_text)
+            .or_else(|| {
+                copy_attribute(element, kAXDescriptionAttribute)
+                    .and_then(|v| to_string(&v))
+                    .and_then(clean_text)
+            });
+
+        let mut children = Vec::new();
+        if depth < MAX_DEPTH {
+            if let Some(value) = copy_attribute(element, kAXChildrenAttribute) {
+                let array = CFArray::<*const std::ffi::c_void>::wrap_under_get_rule(
+                    value.as_CFTypeRef() as CFArrayRef,
+                );
+                for child in array.iter() {
+                    if let Some(node) = read_element(*child as AXUIElementRef, depth + 1, remaining)
+                    {
+                        children.push(node);
+                    }
+                }
+            }
+        }
+
+        Some(AccessibilityNode {
+            role,
+            subrole: copy_attribute(element, kAXSubroleAttribute).and_then(|v| to_string(&v)),
+            label,
+            value: copy_attribute(element, kAXValueAttribute)
+                .and_then(|v| to_string(&v))
+                .and_then(clean_text),
+            focused: copy_attribute(element, kAXFocusedAttribute)
+                .and_then(|v| v.downcast::<CFBoolean>())
+                .is_some_and(bool::from),
+            children,
+        })
+    }
+
+    /// Copies an attribute of an element, released when dropped.
+    unsafe fn copy_attribute(element: AXUIElementRef, attribute: &'static str) -> Option<CFType> {
+        let mut value: CFTypeRef = std::ptr::null();
+        let status = AXUIElementCopyAttributeValue(
+            element,
+            CFString::from_static_string(attribute).as_concrete_TypeRef(),
+            &mut value,
+        );
+        (status == kAXErrorSuccess && !value.is_null())
+            .then(|| CFType::wrap_under_create_rule(value))
+    }
+
+    /// Text of string and number values, e.g. of text fields and sliders.
+    fn to_string(value: &CFType) -> Option<String> {
+        if let Some(string) = value.downcast::<CFString>() {
+            return Some(string.to_string());
+        }
+        value
+            .downcast::<CFNumber>()
+            .and_then(|number| number.to_f64())
+            .map(|number| number.to_string())
+    }
+}
@@ -0,0 +1,218 @@
+//! Text of a window read through Windows UI Automation. Most native and
+//! browser apps expose their text this way, which is exact where OCR guesses,
+//! so it replaces the OCR'd lines it covers, see
+//! [`merge_accessibility_lines`](crate::layout::merge_accessibility_lines).
+
+use screenpipe_db::OcrLayoutLine;
+
+/// Lines of text shown in a window of a process, with bounds relative to the
+/// window. Empty if the window exposes no text, or on platforms other than
+/// Windows.
+pub fn window_text_lines(process_id: i32, window_name: &str) -> Vec<OcrLayoutLine> {
+    #[cfg(target_os = "windows")]
+    {
+        automation::window_text_lines(process_id, window_name).unwrap_or_default()
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = (process_id, window_name);
+        Vec::new()
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod automation {
+    use screenpipe_db::{OcrLayoutLine, TextBounds, TextSource};
+    use uiautomation::controls::ControlType;
+    use uiautomation::patterns::UITextPattern;
+    use uiautomation::types::{Rect, TreeScope, UIProperty};
+    use uiautomation::variants::Variant;
+    use uiautomation::{UIAutomation, UIElement, UITreeWalker};
+
+    /// Deepest level of the element tree that is read.
+    const MAX_DEPTH: usize = 50;
+    /// Most elements visited in one window.
+    const MAX_ELEMENTS: usize = 3000;
+
+    pub fn window_text_lines(process_id: i32, window_name: &str) -> Option<Vec<OcrLayoutLine>> {
+        let automation = UIAutomation::new().ok()?;
+        let condition = automation
+            .create_property_condition(UIProperty::ProcessId, Variant::from(process_id), None)
+            .ok()?;
+        let windows = automation
+            .get_root_element()
+            .ok()?
+            .find_all(TreeScope::Children, &condition)
+            .ok()?;
+        let window = windows
+            .iter()
+            .find(|w| w.get_name().is_ok_and(|name| name == window_name))
+            .or(windows.first())?;
+
+        let window_rect = window.get_bounding_rectangle().ok()?;
+        if window_rect.get_width() <= 0 || window_rect.get_height() <= 0 {
+            return None;
+        }
+
+        let mut reader = TextReader {
+            walker: automation.get_control_view_walker().ok()?,
+            window_rect,
+            lines: Vec::new(),
+            remaining: MAX_ELEMENTS,
+        };
+        reader.read(window, 0);
+        Some(reader.lines)
+    }
+
+    struct TextReader {
+        walker: UITreeWalker,
+        window_rect: Rect,
+        lines: Vec<OcrLayoutLine>,
+        remaining: usize,
+    }
+
+    impl TextReader {
+        fn read(&mut self, element: &UIElement, depth: usize) {
+            if self.remaining == 0 || depth > MAX_DEPTH {
+                return;
+            }
+            self.remaining -= 1;
+            if element.is_offscreen().unwrap_or(true) {
+                return;
+            }
+
+            let control_type = element.get_control_type().ok();
+            match control_type {
+                Some(ControlType::Document | ControlType::Edit) => {
+                    // the text pattern covers the whole content of the element
+                    if self.read_text_pattern(element) {
+                        return;
+                    }
+                    if let Some(value) = string_property(element, UIProperty::ValueValue) {
+                        self.push(&value, element.get_bounding_rectangle().ok());
+                        return;
+                    }
+                }
+                Some(ControlType::Text) => {
+                    if let Some(name) = string_property(element, UIProperty::Name) {
+                        self.push(&name, element.get_bounding_rectangle().ok());
+                    }
+                }
+                _ => {}
+            }
+
+            let mut child = self.walker.get_first_child(element).ok();
+            let has_children = child.is_some();
+            while let Some(current) = child {
+                self.read(&current, depth + 1);
+                child = self.walker.get_next_sibling(&current).ok();
+            }
+
+            // labels of items are usually repeated by a text child
+            let labeled = matches!(
+                control_type,
+                Some(
+                    ControlType::Button
+                        | ControlType::Hyperlink
+                        | ControlType::ListItem
+                        | ControlType::MenuItem
+                        | ControlType::TabItem
+                        | ControlType::TreeItem
+                        | ControlType::DataItem
+                        | ControlType::HeaderItem
+                        | ControlType::CheckBox
+                        | ControlType::RadioButton
+                )
+            );
+            if labeled && !has_children {
+                if let Some(name) = string_property(element, UIProperty::Name) {
+                    self.push(&name, element.get_bounding_rectangle().ok());
+                }
+            }
+        }
+
+        /// Reads the visible text of a document or text field, one line per
+        /// rectangle when the line breaks match the reported rectangles.
+        fn read_text_pattern(&mut self, element: &UIElement) -> bool {
+            let Ok(pattern) = element.get_pattern::<UITextPattern>() else {
+                return false;
+            };
+            let Ok(ranges) = pattern.get_visible_ranges() else {
+                return false;
+            };
+
+            for range in ranges {
+                let Ok(text) = range.get_text(-1) else {
+                    continue;
+                };
+                let rects = range.get_bounding_rectangles().unwrap_or_default();
+                let lines: Vec<&str> = text
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .collect();
+
+                if lines.len() == rects.len() {
+                    for (line, rect) in lines.into_iter().zip(rects) {
+                        self.push(line, Some(rect));
+                    }
+                } else if let Some(rect) = rects.into_iter().reduce(union) {
+                    self.push(&lines.join(" "), Some(rect));
+                }
+            }
+            true
+        }
+
+        fn push(&mut self, text: &str, rect: Option<Rect>) {
+            let text = text.trim();
+            let Some(rect) = rect else {
+                return;
+            };
+            if text.is_empty() {
+                return;
+            }
+
+            let window = &self.window_rect;
+            let (width, height) = (window.get_width() as f32, window.get_height() as f32);
+            let left = ((rect.get_left() - window.get_left()) as f32 / width).clamp(0.0, 1.0);
+            let top = ((rect.get_top() - window.get_top()) as f32 / height).clamp(0.0, 1.0);
+            let right = ((rect.get_right() - window.get_left()) as f32 / width).clamp(0.0, 1.0);
+            let bottom = ((rect.get_bottom() - window.get_top()) as f32 / height).clamp(0.0, 1.0);
+            // scrolled out of the window
+            if right <= left || bottom <= top {
+                return;
+            }
+
+            self.lines.push(OcrLayoutLine {
+                text: text.to_string(),
+                confidence: 1.0,
+                bounds: TextBounds {
+                    left,
+                    top,
+                    width: right - left,
+                    height: bottom - top,
+                },
+                source: TextSource::Accessibility,
+            });
+        }
+    }
+
+    fn string_property(element: &UIElement, property: UIProperty) -> Option<String> {
+        let value = element
+            .get_property_value(property)
+            .ok()?
+            .get_string()
+            .ok()?;
+        (!value.trim().is_empty()).then_some(value)
+    }
+
+    fn union(a: Rect, b: Rect) -> Rect {
+        Rect::new(
+            a.get_left().min(b.get_left()),
+            a.get_top().min(b.get_top()),
+            a.get_right().max(b.get_right()),
+            a.get_bottom().max(b.get_bottom()),
+        )
+    }
+}
@@ -1,9 +1,11 @@
 use crate::capture_screenshot_by_window::{
-    capture_all_visible_windows, CapturedWindow, WindowFilters,
+    blank_windows, capture_all_visible_windows, keep_only_windows, to_image_rect, CaptureMode,
+    CapturedWindow, FocusedWindow, WindowFilters,
 };
 use crate::core::MaxAverageFrame;
 use crate::custom_ocr::CustomOcrConfig;
 use crate::monitor::SafeMonitor;
+use crate::region::{capture_regions_as_windows, mask_to_regions, CaptureRegion};
 use image::DynamicImage;
 use image_compare::{Algorithm, Metric, Similarity};
 use tracing::{debug, warn};
@@ -18,9 +20,24 @@ pub enum OcrEngine {
     Tesseract,
     WindowsNative,
     AppleNative,
+    Paddle,
     Custom(CustomOcrConfig),
 }
 
+impl OcrEngine {
+    /// Short name of the engine, e.g. in metrics.
+    pub fn name(&self) -> &'static str {
+        match self {
+            OcrEngine::Unstructured => "unstructured",
+            OcrEngine::Tesseract => "tesseract",
+            OcrEngine::WindowsNative => "windows-native",
+            OcrEngine::AppleNative => "apple-native",
+            OcrEngine::Paddle => "paddle",
+            OcrEngine::Custom(_) => "custom",
+        }
+    }
+}
+
 impl From<OcrEngine> for screenpipe_db::OcrEngine {
     fn from(val: OcrEngine) -> Self {
         match val {
@@ -28,6 +45,7 @@ impl From<OcrEngine> for screenpipe_db::OcrEngine {
             OcrEngine::Tesseract => screenpipe_db::OcrEngine::Tesseract,
             OcrEngine::WindowsNative => screenpipe_db::OcrEngine::WindowsNative,
             OcrEngine::AppleNative => screenpipe_db::OcrEngine::AppleNative,
+            OcrEngine::Paddle => screenpipe_db::OcrEngine::Paddle,
             OcrEngine::Custom(config) => {
                 screenpipe_db::OcrEngine::Custom(DBCustomOcrConfig::from(config))
             }
@@ -42,6 +60,7 @@ impl From<screenpipe_db::OcrEngine> for OcrEngine {
             screenpipe_db::OcrEngine::Tesseract => OcrEngine::Tesseract,
             screenpipe_db::OcrEngine::WindowsNative => OcrEngine::WindowsNative,
             screenpipe_db::OcrEngine::AppleNative => OcrEngine::AppleNative,
+            screenpipe_db::OcrEngine::Paddle => OcrEngine::Paddle,
             screenpipe_db::OcrEngine::Custom(config) => OcrEngine::Custom(config.into()),
         }
     }
@@ -53,6 +72,30 @@ pub fn calculate_hash(image: &DynamicImage) -> u64 {
     hasher.finish()
 }
 
+/// Difference hash of the image: one bit per pair of neighbouring pixels of
+/// a 9x8 grayscale thumbnail. Near-identical images have hashes a few bits
+/// apart, see [`hash_distance`].
+pub fn perceptual_hash(image: &DynamicImage) -> u64 {
+    let thumbnail = image
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if thumbnail.get_pixel(x, y)[0] < thumbnail.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Number of bits that differ between two perceptual hashes.
+pub fn hash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
 pub fn compare_images_histogram(
     image1: &DynamicImage,
     image2: &DynamicImage,
@@ -76,30 +119,71 @@ pub async fn capture_screenshot(
     monitor: &SafeMonitor,
     window_filters: &WindowFilters,
     capture_unfocused_windows: bool,
-) -> Result<(DynamicImage, Vec<CapturedWindow>, u64, Duration), anyhow::Error> {
+    capture_regions: &[CaptureRegion],
+    capture_mode: CaptureMode,
+) -> Result<
+    (
+        DynamicImage,
+        Vec<CapturedWindow>,
+        u64,
+        Duration,
+        Option<FocusedWindow>,
+    ),
+    anyhow::Error,
+> {
     // info!("Starting screenshot capture for monitor: {:?}", monitor);
     let capture_start = Instant::now();
     let image = monitor.capture_image().await.map_err(|e| {
         debug!("failed to capture monitor image: {}", e);
         anyhow::anyhow!("monitor capture failed")
     })?;
-    let image_hash = calculate_hash(&image);
+
+    // region-of-interest mode: only the configured areas are kept and OCR'd
+    if !capture_regions.is_empty() {
+        let window_images = capture_regions_as_windows(&image, capture_regions);
+        let image = mask_to_regions(&image, capture_regions);
+        let image_hash = calculate_hash(&image);
+        return Ok((
+            image,
+            window_images,
+            image_hash,
+            capture_start.elapsed(),
+            // windows are not enumerated in this mode
+            None,
+        ));
+    }
+
     let capture_duration = capture_start.elapsed();
 
-    let window_images =
+    let (image, window_images, focused) =
         match capture_all_visible_windows(monitor, window_filters, capture_unfocused_windows).await
         {
-            Ok(images) => images,
+            Ok(visible) => {
+                let mut image = match capture_mode {
+                    CaptureMode::Monitor => image,
+                    CaptureMode::Window => {
+                        keep_only_windows(&image, &visible.captured_bounds, monitor)
+                    }
+                };
+                // excluded apps must never end up in the stored frames
+                blank_windows(&mut image, &visible.excluded_bounds, monitor);
+                let mut captured = visible.captured;
+                for (window, bounds) in captured.iter_mut().zip(&visible.captured_bounds) {
+                    window.frame_rect = to_image_rect(bounds, monitor, &image);
+                }
+                (image, captured, visible.focused)
+            }
             Err(e) => {
                 warn!(
                     "Failed to capture window images: {}. Continuing with empty result.",
                     e
                 );
-                Vec::new()
+                (image, Vec::new(), None)
             }
         };
+    let image_hash = calculate_hash(&image);
 
-    Ok((image, window_images, image_hash, capture_duration))
+    Ok((image, window_images, image_hash, capture_duration, focused))
 }
 
 pub async fn compare_with_previous_image(
@@ -0,0 +1,388 @@
+//! Screen capture on Wayland compositors through the xdg-desktop-portal
+//! ScreenCast interface and PipeWire.
+//!
+//! The portal asks the user which monitors to share the first time. The
+//! returned restore token is stored on disk and handed back on the next start
+//! so the permission dialog does not show up again until the user revokes it.
+
+use anyhow::{anyhow, Result};
+use ashpd::desktop::screencast::{CursorMode, Screencast, SourceType};
+use ashpd::desktop::PersistMode;
+use image::{DynamicImage, RgbaImage};
+use pipewire as pw;
+use pw::spa;
+use pw::spa::param::format::{FormatProperties, MediaSubtype, MediaType};
+use pw::spa::param::format_utils;
+use pw::spa::param::video::{VideoFormat, VideoInfoRaw};
+use pw::spa::pod::Pod;
+use std::collections::HashMap;
+use std::os::fd::OwnedFd;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::{debug, error, info, warn};
+
+const RESTORE_TOKEN_FILE: &str = "wayland_restore_token";
+/// How long to wait for the first frame of a freshly started stream.
+const FIRST_FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+static WAYLAND_CAPTURE: OnceCell<Arc<WaylandCapture>> = OnceCell::const_new();
+
+/// Returns true when running inside a Wayland session, where X11 screen
+/// grabbing only sees XWayland windows.
+pub fn is_wayland_session() -> bool {
+    let session_type = std::env::var("XDG_SESSION_TYPE").unwrap_or_default();
+    if session_type.eq_ignore_ascii_case("x11") {
+        return false;
+    }
+    session_type.eq_ignore_ascii_case("wayland")
+        || std::env::var("WAYLAND_DISPLAY").is_ok_and(|v| !v.is_empty())
+}
+
+fn restore_token_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".screenpipe").join(RESTORE_TOKEN_FILE))
+}
+
+fn load_restore_token() -> Option<String> {
+    let path = restore_token_path()?;
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+fn save_restore_token(token: &str) {
+    let Some(path) = restore_token_path() else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Err(e) = std::fs::write(&path, token) {
+        warn!("failed to save wayland restore token: {}", e);
+    }
+}
+
+/// One monitor shared through the portal.
+#[derive(Debug, Clone)]
+pub struct PortalStream {
+    pub node_id: u32,
+    pub position: Option<(i32, i32)>,
+    pub size: Option<(i32, i32)>,
+}
+
+/// An active screencast session. Frames are written by the PipeWire thread
+/// and read by the capture loop, only the latest frame of each stream is kept.
+pub struct WaylandCapture {
+    streams: Vec<PortalStream>,
+    frames: Arc<Mutex<HashMap<u32, (RgbaImage, Instant)>>>,
+}
+
+impl WaylandCapture {
+    async fn start() -> Result<Self> {
+        let proxy = Screencast::new().await?;
+        let session = proxy.create_session().await?;
+
+        let restore_token = load_restore_token();
+        if restore_token.is_some() {
+            debug!("restoring previous wayland screencast session");
+        } else {
+            info!("requesting screen capture permission through xdg-desktop-portal");
+        }
+
+        proxy
+            .select_sources(
+                &session,
+                CursorMode::Embedded,
+                SourceType::Monitor.into(),
+                true,
+                restore_token.as_deref(),
+                PersistMode::ExplicitlyRevoked,
+            )
+            .await?;
+
+        let response = proxy.start(&session, None).await?.response()?;
+
+        if let Some(token) = response.restore_token() {
+            save_restore_token(token);
+        }
+
+        let streams: Vec<PortalStream> = response
+            .streams()
+            .iter()
+            .map(|stream| PortalStream {
+                node_id: stream.pipe_wire_node_id(),
+                position: stream.position(),
+                size: stream.size(),
+            })
+            .collect();
+
+        if streams.is_empty() {
+            return Err(anyhow!("no monitor was shared through the screencast portal"));
+        }
+
+        info!("wayland screencast started with {} stream(s)", streams.len());
+
+        let fd = proxy.open_pipe_wire_remote(&session).await?;
+        let frames = Arc::new(Mutex::new(HashMap::new()));
+
+        let thread_streams = streams.clone();
+        let thread_frames = frames.clone();
+        std::thread::Builder::new()
+            .name("screenpipe-pipewire".to_string())
+            .spawn(move || {
+                // the portal session must outlive the pipewire streams
+                let _session = session;
+                if let Err(e) = run_pipewire_loop(fd, &thread_streams, thread_frames) {
+                    error!("pipewire capture loop stopped: {}", e);
+                }
+            })?;
+
+        Ok(Self { streams, frames })
+    }
+
+    pub fn streams(&self) -> &[PortalStream] {
+        &self.streams
+    }
+
+    /// Picks the stream showing the monitor at the given position. Falls back
+    /// to the stream at the same index when the portal reports no positions.
+    fn stream_for_monitor(&self, x: i32, y: i32, index: usize) -> Option<&PortalStream> {
+        self.streams
+            .iter()
+            .find(|s| s.position == Some((x, y)))
+            .or_else(|| self.streams.get(index))
+            .or_else(|| self.streams.first().filter(|_| self.streams.len() == 1))
+    }
+
+    async fn latest_frame(&self, node_id: u32) -> Result<DynamicImage> {
+        let deadline = Instant::now() + FIRST_FRAME_TIMEOUT;
+        loop {
+            if let Some((frame, _)) = self
+                .frames
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(&node_id)
+            {
+                return Ok(DynamicImage::ImageRgba8(frame.clone()));
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow!("no frame received from pipewire node {}", node_id));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+async fn get_or_start() -> Result<Arc<WaylandCapture>> {
+    WAYLAND_CAPTURE
+        .get_or_try_init(|| async { WaylandCapture::start().await.map(Arc::new) })
+        .await
+        .cloned()
+}
+
+/// Captures the monitor at the given position. `index` is the monitor's index
+/// in the monitor list and is used when the compositor does not report
+/// stream positions.
+pub async fn capture_monitor(x: i32, y: i32, index: usize) -> Result<DynamicImage> {
+    let capture = get_or_start().await?;
+    let stream = capture
+        .stream_for_monitor(x, y, index)
+        .ok_or_else(|| anyhow!("monitor at {},{} was not shared through the portal", x, y))?;
+    capture.latest_frame(stream.node_id).await
+}
+
+struct StreamState {
+    node_id: u32,
+    format: VideoInfoRaw,
+    frames: Arc<Mutex<HashMap<u32, (RgbaImage, Instant)>>>,
+}
+
+fn run_pipewire_loop(
+    fd: OwnedFd,
+    streams: &[PortalStream],
+    frames: Arc<Mutex<HashMap<u32, (RgbaImage, Instant)>>>,
+) -> Result<()> {
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect_fd(fd, None)?;
+
+    // keep streams and listeners alive for as long as the loop runs
+    let mut active = Vec::new();
+
+    for portal_stream in streams {
+        let stream = pw::stream::Stream::new(
+            &core,
+            "screenpipe",
+            pw::properties::properties! {
+                *pw::keys::MEDIA_TYPE => "Video",
+                *pw::keys::MEDIA_CATEGORY => "Capture",
+                *pw::keys::MEDIA_ROLE => "Screen",
+            },
+        )?;
+
+        let state = StreamState {
+            node_id: portal_stream.node_id,
+            format: VideoInfoRaw::default(),
+            frames: frames.clone(),
+        };
+
+        let listener = stream
+            .add_local_listener_with_user_data(state)
+            .param_changed(|_, state, id, param| {
+                let Some(param) = param else {
+                    return;
+                };
+                if id != spa::param::ParamType::Format.as_raw() {
+                    return;
+                }
+                let Ok((media_type, media_subtype)) = format_utils::parse_format(param) else {
+                    return;
+                };
+                if media_type != MediaType::Video || media_subtype != MediaSubtype::Raw {
+                    return;
+                }
+                if let Err(e) = state.format.parse(param) {
+                    warn!("failed to parse pipewire video format: {:?}", e);
+                    return;
+                }
+                debug!(
+                    "pipewire node {} negotiated {:?} {}x{}",
+                    state.node_id,
+                    state.format.format(),
+                    state.format.size().width,
+                    state.format.size().height
+                );
+            })
+            .process(|stream, state| {
+                let Some(mut buffer) = stream.dequeue_buffer() else {
+                    return;
+                };
+                let datas = buffer.datas_mut();
+                let Some(data) = datas.first_mut() else {
+                    return;
+                };
+
+                let size = state.format.size();
+                let stride = data.chunk().stride().max(0) as usize;
+                let format = state.format.format();
+                let Some(bytes) = data.data() else {
+                    return;
+                };
+
+                if let Some(image) = frame_to_rgba(bytes, size.width, size.height, stride, format)
+                {
+                    state
+                        .frames
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(state.node_id, (image, Instant::now()));
+                }
+            })
+            .register()?;
+
+        let format_pod = video_format_pod()?;
+        let mut params = [Pod::from_bytes(&format_pod)
+            .ok_or_else(|| anyhow!("invalid pipewire format pod"))?];
+
+        stream.connect(
+            spa::utils::Direction::Input,
+            Some(portal_stream.node_id),
+            pw::stream::StreamFlags::AUTOCONNECT | pw::stream::StreamFlags::MAP_BUFFERS,
+            &mut params,
+        )?;
+
+        active.push((stream, listener));
+    }
+
+    mainloop.run();
+    Ok(())
+}
+
+/// Formats we accept from the compositor, all 32-bit packed so converting to
+/// RGBA is a cheap per-pixel swizzle.
+fn video_format_pod() -> Result<Vec<u8>> {
+    let object = spa::pod::object!(
+        spa::utils::SpaTypes::ObjectParamFormat,
+        spa::param::ParamType::EnumFormat,
+        spa::pod::property!(FormatProperties::MediaType, Id, MediaType::Video),
+        spa::pod::property!(FormatProperties::MediaSubtype, Id, MediaSubtype::Raw),
+        spa::pod::property!(
+            FormatProperties::VideoFormat,
+            Choice,
+            Enum,
+            Id,
+            VideoFormat::BGRx,
+            VideoFormat::BGRx,
+            VideoFormat::BGRA,
+            VideoFormat::RGBx,
+            VideoFormat::RGBA
+        ),
+        spa::pod::property!(
+            FormatProperties::VideoSize,
+            Choice,
+            Range,
+            Rectangle,
+            spa::utils::Rectangle {
+                width: 1920,
+                height: 1080
+            },
+            spa::utils::Rectangle {
+                width: 1,
+                height: 1
+            },
+            spa::utils::Rectangle {
+                width: 8192,
+                height: 8192
+            }
+        ),
+    );
+
+    let (cursor, _) = spa::pod::serialize::PodSerializer::serialize(
+        std::io::Cursor::new(Vec::new()),
+        &spa::pod::Value::Object(object),
+    )
+    .map_err(|e| anyhow!("failed to serialize pipewire format: {:?}", e))?;
+
+    Ok(cursor.into_inner())
+}
+
+/// Converts a packed 32-bit PipeWire frame into an RGBA image.
+pub fn frame_to_rgba(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    format: VideoFormat,
+) -> Option<RgbaImage> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let row_len = width as usize * 4;
+    let stride = if stride == 0 { row_len } else { stride };
+    if stride < row_len || bytes.len() < stride * (height as usize - 1) + row_len {
+        return None;
+    }
+
+    let (swap_rb, has_alpha) = match format {
+        VideoFormat::BGRx => (true, false),
+        VideoFormat::BGRA => (true, true),
+        VideoFormat::RGBx => (false, false),
+        VideoFormat::RGBA => (false, true),
+        _ => return None,
+    };
+
+    let mut pixels = Vec::with_capacity(row_len * height as usize);
+    for row in bytes.chunks(stride).take(height as usize) {
+        for px in row[..row_len].chunks_exact(4) {
+            let (r, b) = if swap_rb { (px[2], px[0]) } else { (px[0], px[2]) };
+            let a = if has_alpha { px[3] } else { 255 };
+            pixels.extend_from_slice(&[r, px[1], b, a]);
+        }
+    }
+
+    RgbaImage::from_raw(width, height, pixels)
+}
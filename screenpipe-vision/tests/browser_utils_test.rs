@@ -0,0 +1,35 @@
+use screenpipe_vision::browser_utils::tab_title_from_window_title;
+
+#[test]
+fn test_tab_title_strips_browser_name() {
+    assert_eq!(
+        tab_title_from_window_title("Google Chrome", "Inbox (3) - Gmail - Google Chrome"),
+        Some("Inbox (3) - Gmail".to_string())
+    );
+    assert_eq!(
+        tab_title_from_window_title("firefox", "Rust Docs \u{2014} Mozilla Firefox"),
+        Some("Rust Docs".to_string())
+    );
+    assert_eq!(
+        tab_title_from_window_title("Microsoft Edge", "New tab - Profile 1 - Microsoft Edge"),
+        Some("New tab - Profile 1".to_string())
+    );
+}
+
+#[test]
+fn test_tab_title_without_browser_suffix() {
+    // macOS window titles are the tab title alone
+    assert_eq!(
+        tab_title_from_window_title("Safari", "Apple - Start"),
+        Some("Apple - Start".to_string())
+    );
+    assert_eq!(tab_title_from_window_title("Arc", "  "), None);
+}
+
+#[test]
+fn test_tab_title_keeps_words_that_contain_a_browser_name() {
+    assert_eq!(
+        tab_title_from_window_title("Arc", "Papers - Research"),
+        Some("Papers - Research".to_string())
+    );
+}
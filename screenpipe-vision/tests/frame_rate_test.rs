@@ -0,0 +1,94 @@
+use screenpipe_vision::frame_rate::{AdaptiveFpsConfig, AdaptiveFrameRate};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+fn enabled_config() -> AdaptiveFpsConfig {
+    AdaptiveFpsConfig {
+        enabled: true,
+        min_fps: 0.25,
+        idle_threshold: 0.01,
+        activity_threshold: 0.1,
+        idle_frames_before_slowdown: 2,
+    }
+}
+
+#[test]
+fn test_disabled_keeps_base_interval() {
+    let config = Arc::new(RwLock::new(AdaptiveFpsConfig::default()));
+    let mut rate = AdaptiveFrameRate::new(Duration::from_secs(1), config);
+
+    for _ in 0..10 {
+        assert_eq!(rate.next_interval(0.0), Duration::from_secs(1));
+    }
+}
+
+#[test]
+fn test_idle_backs_off_to_min_fps() {
+    let config = Arc::new(RwLock::new(enabled_config()));
+    let mut rate = AdaptiveFrameRate::new(Duration::from_secs(1), config);
+
+    assert_eq!(rate.next_interval(0.0), Duration::from_secs(1));
+    assert_eq!(rate.next_interval(0.0), Duration::from_secs(2));
+    assert_eq!(rate.next_interval(0.0), Duration::from_secs(4));
+    // capped at 1 / min_fps
+    assert_eq!(rate.next_interval(0.0), Duration::from_secs(4));
+}
+
+#[test]
+fn test_activity_resets_interval() {
+    let config = Arc::new(RwLock::new(enabled_config()));
+    let mut rate = AdaptiveFrameRate::new(Duration::from_secs(1), config);
+
+    for _ in 0..5 {
+        rate.next_interval(0.0);
+    }
+    assert_eq!(rate.current_interval(), Duration::from_secs(4));
+
+    assert_eq!(rate.next_interval(0.5), Duration::from_secs(1));
+}
+
+#[test]
+fn test_small_changes_ramp_up_gradually() {
+    let config = Arc::new(RwLock::new(enabled_config()));
+    let mut rate = AdaptiveFrameRate::new(Duration::from_secs(1), config);
+
+    for _ in 0..5 {
+        rate.next_interval(0.0);
+    }
+
+    assert_eq!(rate.next_interval(0.05), Duration::from_secs(2));
+    assert_eq!(rate.next_interval(0.05), Duration::from_secs(1));
+    assert_eq!(rate.next_interval(0.05), Duration::from_secs(1));
+}
+
+#[test]
+fn test_config_changes_apply_at_runtime() {
+    let config = Arc::new(RwLock::new(enabled_config()));
+    let mut rate = AdaptiveFrameRate::new(Duration::from_secs(1), config.clone());
+
+    for _ in 0..5 {
+        rate.next_interval(0.0);
+    }
+    assert_eq!(rate.current_interval(), Duration::from_secs(4));
+
+    config.write().unwrap().enabled = false;
+    assert_eq!(rate.next_interval(0.0), Duration::from_secs(1));
+}
+
+#[test]
+fn test_validate_config() {
+    assert!(AdaptiveFpsConfig::default().validate().is_ok());
+
+    let invalid = AdaptiveFpsConfig {
+        min_fps: 0.0,
+        ..Default::default()
+    };
+    assert!(invalid.validate().is_err());
+
+    let invalid = AdaptiveFpsConfig {
+        idle_threshold: 0.5,
+        activity_threshold: 0.1,
+        ..Default::default()
+    };
+    assert!(invalid.validate().is_err());
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use screenpipe_db::{OcrLayoutLine, TextBounds, TextSource};
+use screenpipe_vision::layout::{
+    build_layout, layout_text, lines_from_ocr_json, merge_accessibility_lines,
+};
+use screenpipe_vision::OcrEngine;
+
+fn line(text: &str, left: f32, top: f32, width: f32) -> OcrLayoutLine {
+    OcrLayoutLine {
+        text: text.to_string(),
+        confidence: 0.9,
+        bounds: TextBounds {
+            left,
+            top,
+            width,
+            height: 0.02,
+        },
+        source: TextSource::Ocr,
+    }
+}
+
+fn entry(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_two_columns_are_read_column_by_column() {
+    // rows interleave, but each column should be read to the end first
+    let lines = vec![
+        line("left one", 0.05, 0.10, 0.4),
+        line("right one", 0.55, 0.10, 0.4),
+        line("left two", 0.05, 0.125, 0.4),
+        line("right two", 0.55, 0.125, 0.4),
+    ];
+
+    let blocks = build_layout(lines);
+    assert_eq!(blocks.len(), 2);
+    assert_eq!(blocks[0].text(), "left one left two");
+    assert_eq!(blocks[1].text(), "right one right two");
+    assert_eq!(blocks[0].reading_order, 0);
+    assert_eq!(blocks[1].reading_order, 1);
+}
+
+#[test]
+fn test_header_spanning_columns_comes_first() {
+    let lines = vec![
+        line("right column", 0.55, 0.30, 0.4),
+        line("left column", 0.05, 0.30, 0.4),
+        line("page title", 0.05, 0.05, 0.9),
+        line("footer", 0.05, 0.90, 0.9),
+    ];
+
+    let text = layout_text(&build_layout(lines));
+    assert_eq!(text, "page title\nleft column\nright column\nfooter");
+}
+
+#[test]
+fn test_paragraph_gaps_split_a_block() {
+    let lines = vec![
+        line("first paragraph", 0.1, 0.100, 0.5),
+        line("still first", 0.1, 0.122, 0.5),
+        line("second paragraph", 0.1, 0.160, 0.5),
+    ];
+
+    let blocks = build_layout(lines);
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].paragraphs.len(), 2);
+    assert_eq!(
+        blocks[0].paragraphs[0].text(),
+        "first paragraph still first"
+    );
+    assert_eq!(
+        blocks[0].text(),
+        "first paragraph still first\nsecond paragraph"
+    );
+}
+
+#[test]
+fn test_pixel_coordinates_are_normalized() {
+    let json = vec![entry(&[
+        ("text", "hello"),
+        ("confidence", "87.5"),
+        ("left", "100"),
+        ("top", "50"),
+        ("width", "200"),
+        ("height", "20"),
+    ])];
+
+    let lines = lines_from_ocr_json(&OcrEngine::Tesseract, &json, 1000, 500);
+    assert_eq!(lines.len(), 1);
+    assert_eq!(
+        lines[0].bounds,
+        TextBounds {
+            left: 0.1,
+            top: 0.1,
+            width: 0.2,
+            height: 0.04,
+        }
+    );
+    assert!((lines[0].confidence - 0.875).abs() < 1e-6);
+}
+
+#[test]
+fn test_apple_coordinates_are_flipped() {
+    let json = vec![entry(&[
+        ("text", "hello"),
+        ("confidence", "0.5"),
+        ("left", "0.1"),
+        ("top", "0.7"),
+        ("width", "0.2"),
+        ("height", "0.1"),
+    ])];
+
+    let lines = lines_from_ocr_json(&OcrEngine::AppleNative, &json, 1000, 500);
+    assert_eq!(lines.len(), 1);
+    assert!((lines[0].bounds.top - 0.2).abs() < 1e-6);
+    assert_eq!(lines[0].confidence, 0.5);
+}
+
+#[test]
+fn test_entries_without_bounds_are_skipped() {
+    let json = vec![
+        entry(&[("text", "no position"), ("confidence", "1.0")]),
+        entry(&[
+            ("text", "  "),
+            ("left", "0"),
+            ("top", "0"),
+            ("width", "10"),
+            ("height", "10"),
+        ]),
+    ];
+
+    assert!(lines_from_ocr_json(&OcrEngine::Tesseract, &json, 100, 100).is_empty());
+    assert!(build_layout(Vec::new()).is_empty());
+}
+
+#[test]
+fn test_accessibility_text_replaces_covered_ocr_lines() {
+    let ocr = vec![
+        line("Helo wrld", 0.1, 0.10, 0.4),
+        line("text in an image", 0.1, 0.50, 0.4),
+    ];
+    let accessibility = vec![OcrLayoutLine {
+        text: "Hello world".to_string(),
+        confidence: 1.0,
+        source: TextSource::Accessibility,
+        ..line("", 0.1, 0.095, 0.45)
+    }];
+
+    let lines = merge_accessibility_lines(ocr, accessibility);
+    assert_eq!(lines.len(), 2);
+    assert!(lines
+        .iter()
+        .any(|l| l.text == "Hello world" && l.source == TextSource::Accessibility));
+    assert!(lines
+        .iter()
+        .any(|l| l.text == "text in an image" && l.source == TextSource::Ocr));
+    assert!(!lines.iter().any(|l| l.text == "Helo wrld"));
+
+    let text = layout_text(&build_layout(lines));
+    assert_eq!(text, "Hello world\ntext in an image");
+}
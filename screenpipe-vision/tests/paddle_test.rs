@@ -0,0 +1,99 @@
+use image::{GrayImage, Luma};
+use ndarray::Array3;
+use screenpipe_vision::paddle::{
+    ctc_decode, find_text_boxes, load_dictionary, resolve_ocr_device, sort_reading_order,
+    OcrDevice, PaddleTextBox,
+};
+
+fn text_box(text: &str, left: u32, top: u32) -> PaddleTextBox {
+    PaddleTextBox {
+        text: text.to_string(),
+        confidence: 0.9,
+        left,
+        top,
+        width: 40,
+        height: 20,
+    }
+}
+
+#[test]
+fn test_dictionary_has_blank_and_space() {
+    let dictionary = load_dictionary("a\r\nb\nc");
+    assert_eq!(dictionary, vec!["", "a", "b", "c", " "]);
+}
+
+#[test]
+fn test_ctc_decode_collapses_repeats_and_blanks() {
+    let dictionary = load_dictionary("a\nb");
+    // timesteps: a, a, blank, a, b, b -> "aab"
+    let best = [1, 1, 0, 1, 2, 2];
+    let mut logits = Array3::<f32>::zeros((1, best.len(), dictionary.len()));
+    for (t, &class) in best.iter().enumerate() {
+        logits[[0, t, class]] = 0.8;
+    }
+
+    let (text, confidence) = ctc_decode(&logits.into_dyn().view(), &dictionary);
+    assert_eq!(text, "aab");
+    assert!((confidence - 0.8).abs() < 1e-6);
+}
+
+#[test]
+fn test_ctc_decode_empty_output() {
+    let dictionary = load_dictionary("a");
+    let logits = Array3::<f32>::zeros((1, 0, dictionary.len()));
+    let (text, confidence) = ctc_decode(&logits.into_dyn().view(), &dictionary);
+    assert!(text.is_empty());
+    assert_eq!(confidence, 0.0);
+}
+
+#[test]
+fn test_find_text_boxes_separates_regions() {
+    let mut map = GrayImage::new(100, 50);
+    for y in 10..20 {
+        for x in 5..40 {
+            map.put_pixel(x, y, Luma([230]));
+        }
+        for x in 60..90 {
+            map.put_pixel(x, y, Luma([230]));
+        }
+    }
+    // single noisy pixel is ignored
+    map.put_pixel(50, 40, Luma([255]));
+
+    let mut boxes = find_text_boxes(&map);
+    boxes.sort();
+    assert_eq!(boxes, vec![(5, 10, 39, 19), (60, 10, 89, 19)]);
+}
+
+#[test]
+fn test_find_text_boxes_drops_low_confidence_regions() {
+    let mut map = GrayImage::new(50, 50);
+    for y in 10..20 {
+        for x in 10..40 {
+            // above the pixel threshold but below the box threshold
+            map.put_pixel(x, y, Luma([100]));
+        }
+    }
+    assert!(find_text_boxes(&map).is_empty());
+}
+
+#[test]
+fn test_reading_order_is_line_by_line() {
+    let boxes = vec![
+        text_box("second line", 10, 50),
+        text_box("world", 100, 12),
+        text_box("hello", 10, 10),
+    ];
+
+    let ordered: Vec<String> = sort_reading_order(boxes)
+        .into_iter()
+        .map(|b| b.text)
+        .collect();
+    assert_eq!(ordered, vec!["hello", "world", "second line"]);
+}
+
+#[test]
+fn test_cpu_ocr_device_is_always_available() {
+    assert_eq!(resolve_ocr_device(OcrDevice::Cpu), OcrDevice::Cpu);
+    assert_eq!(OcrDevice::CoreMl.to_string(), "coreml");
+}
@@ -0,0 +1,44 @@
+use image::{DynamicImage, Rgb, RgbImage};
+use screenpipe_vision::utils::{hash_distance, perceptual_hash};
+
+/// Vertical gradient with a dark block at `block_x`.
+fn screen(block_x: u32) -> DynamicImage {
+    let mut image = RgbImage::from_fn(320, 200, |x, _| {
+        let v = (x * 255 / 320) as u8;
+        Rgb([v, v, v])
+    });
+    for y in 40..160 {
+        for x in block_x..block_x + 80 {
+            image.put_pixel(x, y, Rgb([0, 0, 0]));
+        }
+    }
+    DynamicImage::ImageRgb8(image)
+}
+
+#[test]
+fn test_near_identical_screens_have_close_hashes() {
+    let original = screen(40);
+    let mut cursor_moved = original.to_rgb8();
+    cursor_moved.put_pixel(300, 10, Rgb([255, 0, 0]));
+
+    let hash = perceptual_hash(&original);
+    assert_eq!(hash, perceptual_hash(&original));
+    assert!(
+        hash_distance(
+            hash,
+            perceptual_hash(&DynamicImage::ImageRgb8(cursor_moved))
+        ) <= 2
+    );
+}
+
+#[test]
+fn test_different_screens_have_distant_hashes() {
+    let distance = hash_distance(perceptual_hash(&screen(40)), perceptual_hash(&screen(200)));
+    assert!(distance > 2, "distance {}", distance);
+}
+
+#[test]
+fn test_hash_distance_counts_differing_bits() {
+    assert_eq!(hash_distance(0b1011, 0b0001), 2);
+    assert_eq!(hash_distance(u64::MAX, 0), 64);
+}
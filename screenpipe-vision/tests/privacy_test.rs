@@ -0,0 +1,99 @@
+use screenpipe_vision::browser_utils::is_private_window_title;
+use screenpipe_vision::PrivacyBlocklist;
+
+fn blocklist(patterns: &[&str]) -> PrivacyBlocklist {
+    PrivacyBlocklist::new(&patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>())
+}
+
+#[test]
+fn test_app_pattern_matches_exact_app_name() {
+    let blocklist = blocklist(&["app:1Password"]);
+
+    assert_eq!(
+        blocklist.matching_pattern("1password", None),
+        Some("app:1Password")
+    );
+    assert_eq!(blocklist.matching_pattern("1Password Helper", None), None);
+    assert!(!blocklist.matches_urls());
+}
+
+#[test]
+fn test_url_pattern_matches_browser_url() {
+    let blocklist = blocklist(&[r"url:^https://([a-z]+\.)?mybank\.com"]);
+
+    assert_eq!(
+        blocklist.matching_pattern("Arc", Some("https://online.MyBank.com/accounts")),
+        Some(r"url:^https://([a-z]+\.)?mybank\.com")
+    );
+    assert_eq!(
+        blocklist.matching_pattern("Arc", Some("https://example.com/?q=mybank.com")),
+        None
+    );
+    // the app name alone never matches a url rule
+    assert_eq!(blocklist.matching_pattern("mybank.com", None), None);
+    assert!(blocklist.matches_urls());
+}
+
+#[test]
+fn test_plain_pattern_matches_app_or_url() {
+    let blocklist = blocklist(&["signal"]);
+
+    assert!(blocklist.matching_pattern("Signal", None).is_some());
+    assert!(blocklist
+        .matching_pattern("Firefox", Some("https://signal.org"))
+        .is_some());
+    assert!(blocklist.matching_pattern("Firefox", None).is_none());
+}
+
+#[test]
+fn test_invalid_url_regex_falls_back_to_substring() {
+    let blocklist = blocklist(&["url:bank[("]);
+
+    assert!(blocklist
+        .matching_pattern("Safari", Some("https://bank[(.com"))
+        .is_some());
+    assert!(blocklist
+        .matching_pattern("Safari", Some("https://bank.com"))
+        .is_none());
+}
+
+#[test]
+fn test_empty_blocklist_matches_nothing() {
+    let blocklist = PrivacyBlocklist::default();
+
+    assert!(blocklist.is_empty());
+    assert!(blocklist
+        .matching_pattern("1Password", Some("https://mybank.com"))
+        .is_none());
+}
+
+#[test]
+fn test_private_browsing_blocks_without_patterns() {
+    let blocklist = PrivacyBlocklist::default().block_private_browsing(true);
+
+    assert!(!blocklist.is_empty());
+    assert!(blocklist.blocks_private_browsing());
+    assert!(blocklist.matching_pattern("Firefox", None).is_none());
+}
+
+#[test]
+fn test_private_window_titles() {
+    assert!(is_private_window_title(
+        "Google Chrome",
+        "New Tab - Google Chrome (Incognito)"
+    ));
+    assert!(is_private_window_title(
+        "Microsoft Edge",
+        "[InPrivate] New tab - Microsoft Edge"
+    ));
+    assert!(is_private_window_title(
+        "firefox",
+        "Mozilla Firefox Private Browsing"
+    ));
+    assert!(!is_private_window_title("Firefox", "Mozilla Firefox"));
+    // only browser titles count
+    assert!(!is_private_window_title(
+        "Notes",
+        "Private Browsing checklist"
+    ));
+}
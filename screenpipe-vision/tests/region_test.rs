@@ -0,0 +1,85 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use screenpipe_vision::region::{capture_regions_as_windows, mask_to_regions, regions_for_monitor};
+use screenpipe_vision::CaptureRegion;
+
+fn test_image() -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_pixel(200, 100, Rgba([255, 255, 255, 255])))
+}
+
+#[test]
+fn test_parse_capture_region() {
+    let region: CaptureRegion = "2:10,20,300,400".parse().unwrap();
+    assert_eq!(
+        region,
+        CaptureRegion {
+            monitor_id: 2,
+            x: 10,
+            y: 20,
+            width: 300,
+            height: 400,
+        }
+    );
+    assert_eq!(region.to_string(), "2:10,20,300,400");
+}
+
+#[test]
+fn test_parse_capture_region_rejects_invalid_input() {
+    assert!("10,20,300,400".parse::<CaptureRegion>().is_err());
+    assert!("1:10,20,300".parse::<CaptureRegion>().is_err());
+    assert!("1:10,20,0,400".parse::<CaptureRegion>().is_err());
+    assert!("a:10,20,300,400".parse::<CaptureRegion>().is_err());
+}
+
+#[test]
+fn test_regions_for_monitor() {
+    let regions: Vec<CaptureRegion> = ["1:0,0,10,10", "2:0,0,10,10", "1:20,20,10,10"]
+        .iter()
+        .map(|r| r.parse().unwrap())
+        .collect();
+
+    assert_eq!(regions_for_monitor(&regions, 1).len(), 2);
+    assert_eq!(regions_for_monitor(&regions, 3).len(), 0);
+}
+
+#[test]
+fn test_mask_to_regions_crops_to_bounding_box() {
+    let regions: Vec<CaptureRegion> = ["1:0,0,50,50", "1:100,50,50,50"]
+        .iter()
+        .map(|r| r.parse().unwrap())
+        .collect();
+
+    let masked = mask_to_regions(&test_image(), &regions).to_rgba8();
+    assert_eq!(masked.dimensions(), (150, 100));
+    // inside a region keeps the captured pixels
+    assert_eq!(masked.get_pixel(10, 10), &Rgba([255, 255, 255, 255]));
+    // between regions is blanked out
+    assert_eq!(masked.get_pixel(75, 25), &Rgba([0, 0, 0, 255]));
+}
+
+#[test]
+fn test_regions_are_clamped_to_screen() {
+    let regions: Vec<CaptureRegion> = ["1:150,50,500,500", "1:500,500,10,10"]
+        .iter()
+        .map(|r| r.parse().unwrap())
+        .collect();
+
+    let windows = capture_regions_as_windows(&test_image(), &regions);
+    assert_eq!(windows.len(), 1);
+    assert_eq!(windows[0].image.width(), 50);
+    assert_eq!(windows[0].image.height(), 50);
+}
+
+#[test]
+fn test_region_windows_know_their_place_in_the_frame() {
+    let regions: Vec<CaptureRegion> = ["1:20,10,30,30", "1:100,40,50,50"]
+        .iter()
+        .map(|r| r.parse().unwrap())
+        .collect();
+
+    let windows = capture_regions_as_windows(&test_image(), &regions);
+    assert_eq!(windows[0].frame_rect, Some((0, 0, 30, 30)));
+    assert_eq!(windows[1].frame_rect, Some((80, 30, 50, 50)));
+
+    let frame = mask_to_regions(&test_image(), &regions);
+    assert_eq!((frame.width(), frame.height()), (130, 80));
+}
@@ -0,0 +1,99 @@
+use screenpipe_db::{OcrLayoutLine, TextBounds, TextSource};
+use screenpipe_vision::table::detect_tables;
+
+fn cell(text: &str, left: f32, top: f32) -> OcrLayoutLine {
+    OcrLayoutLine {
+        text: text.to_string(),
+        confidence: 0.9,
+        bounds: TextBounds {
+            left,
+            top,
+            width: 0.1,
+            height: 0.02,
+        },
+        source: TextSource::Ocr,
+    }
+}
+
+#[test]
+fn test_grid_is_reconstructed() {
+    let lines = vec![
+        cell("name", 0.1, 0.10),
+        cell("price", 0.3, 0.10),
+        cell("qty", 0.5, 0.10),
+        cell("apple", 0.1, 0.13),
+        cell("1.20", 0.3, 0.13),
+        cell("4", 0.5, 0.13),
+        cell("pear", 0.1, 0.16),
+        // missing price
+        cell("7", 0.5, 0.16),
+    ];
+
+    let tables = detect_tables(&lines);
+    assert_eq!(tables.len(), 1);
+    assert_eq!(
+        tables[0].rows,
+        vec![
+            vec!["name", "price", "qty"],
+            vec!["apple", "1.20", "4"],
+            vec!["pear", "", "7"],
+        ]
+    );
+    assert_eq!(tables[0].column_count(), 3);
+    assert!((tables[0].bounds.left - 0.1).abs() < 1e-6);
+    assert!((tables[0].bounds.bottom() - 0.18).abs() < 1e-6);
+}
+
+#[test]
+fn test_slightly_misaligned_cells_share_a_row() {
+    let lines = vec![
+        cell("a", 0.1, 0.100),
+        cell("b", 0.3, 0.105),
+        cell("c", 0.1, 0.130),
+        cell("d", 0.3, 0.128),
+    ];
+
+    let tables = detect_tables(&lines);
+    assert_eq!(tables.len(), 1);
+    assert_eq!(tables[0].rows, vec![vec!["a", "b"], vec!["c", "d"]]);
+}
+
+#[test]
+fn test_distant_grids_are_separate_tables() {
+    let lines = vec![
+        cell("a", 0.1, 0.10),
+        cell("b", 0.3, 0.10),
+        cell("c", 0.1, 0.13),
+        cell("d", 0.3, 0.13),
+        cell("e", 0.1, 0.60),
+        cell("f", 0.3, 0.60),
+        cell("g", 0.1, 0.63),
+        cell("h", 0.3, 0.63),
+    ];
+
+    let tables = detect_tables(&lines);
+    assert_eq!(tables.len(), 2);
+    assert_eq!(tables[1].rows, vec![vec!["e", "f"], vec!["g", "h"]]);
+}
+
+#[test]
+fn test_prose_is_not_a_table() {
+    // a single column of text
+    let column = vec![
+        cell("first line", 0.1, 0.10),
+        cell("second line", 0.1, 0.13),
+    ];
+    assert!(detect_tables(&column).is_empty());
+
+    // two columns of long lines
+    let sentence = "the quick brown fox jumps over the lazy dog again and again";
+    let columns = vec![
+        cell(sentence, 0.05, 0.10),
+        cell(sentence, 0.55, 0.10),
+        cell(sentence, 0.05, 0.13),
+        cell(sentence, 0.55, 0.13),
+    ];
+    assert!(detect_tables(&columns).is_empty());
+
+    assert!(detect_tables(&[]).is_empty());
+}
@@ -0,0 +1,41 @@
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod tests {
+    use pipewire::spa::param::video::VideoFormat;
+    use screenpipe_vision::wayland::frame_to_rgba;
+
+    #[test]
+    fn test_bgrx_frame_is_swizzled_to_rgba() {
+        // 2x1 frame: one blue pixel, one red pixel, padding byte ignored
+        let bytes = [255, 0, 0, 0, 0, 0, 255, 0];
+        let image = frame_to_rgba(&bytes, 2, 1, 8, VideoFormat::BGRx).unwrap();
+
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_rgba_frame_keeps_alpha() {
+        let bytes = [10, 20, 30, 40];
+        let image = frame_to_rgba(&bytes, 1, 1, 4, VideoFormat::RGBA).unwrap();
+
+        assert_eq!(image.get_pixel(0, 0).0, [10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn test_stride_padding_is_skipped() {
+        // 1x2 frame with 8 byte stride, second half of each row is padding
+        let bytes = [1, 2, 3, 0, 9, 9, 9, 9, 4, 5, 6, 0];
+        let image = frame_to_rgba(&bytes, 1, 2, 8, VideoFormat::RGBx).unwrap();
+
+        assert_eq!(image.get_pixel(0, 0).0, [1, 2, 3, 255]);
+        assert_eq!(image.get_pixel(0, 1).0, [4, 5, 6, 255]);
+    }
+
+    #[test]
+    fn test_truncated_or_unsupported_frames_are_rejected() {
+        assert!(frame_to_rgba(&[0; 4], 2, 1, 8, VideoFormat::BGRx).is_none());
+        assert!(frame_to_rgba(&[0; 4], 0, 1, 4, VideoFormat::BGRx).is_none());
+        assert!(frame_to_rgba(&[0; 4], 1, 1, 4, VideoFormat::I420).is_none());
+    }
+}
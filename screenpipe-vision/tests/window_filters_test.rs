@@ -0,0 +1,51 @@
+use screenpipe_vision::capture_screenshot_by_window::WindowFilters;
+
+fn filters(ignore: &[&str], include: &[&str]) -> WindowFilters {
+    let ignore: Vec<String> = ignore.iter().map(|s| s.to_string()).collect();
+    let include: Vec<String> = include.iter().map(|s| s.to_string()).collect();
+    WindowFilters::new(&ignore, &include)
+}
+
+#[test]
+fn test_empty_filters_allow_everything() {
+    let filters = filters(&[], &[]);
+    assert!(filters.is_valid("Google Chrome", "Inbox"));
+}
+
+#[test]
+fn test_ignore_list_applies_without_include_list() {
+    let filters = filters(&["bit"], &[]);
+    assert!(!filters.is_valid("Bitwarden", "Vault"));
+    assert!(filters.is_valid("Code", "main.rs"));
+}
+
+#[test]
+fn test_exclusion_wins_over_inclusion() {
+    let filters = filters(&["private"], &["chrome"]);
+    assert!(filters.is_valid("Google Chrome", "Inbox"));
+    assert!(!filters.is_valid("Google Chrome", "Private Browsing"));
+    assert!(!filters.is_valid("Slack", "general"));
+}
+
+#[test]
+fn test_process_pattern_matches_exact_process_name() {
+    let filters = filters(&["process:1password"], &[]);
+    assert!(filters.is_excluded("1Password", "Vault"));
+    assert!(!filters.is_excluded("1Password Helper", "Vault"));
+    assert!(!filters.is_excluded("Notes", "my 1password recovery"));
+}
+
+#[test]
+fn test_title_pattern_uses_regex() {
+    let filters = filters(&["title:^(chase|paypal) - .*$"], &["title:\\.rs$"]);
+    assert!(filters.is_excluded("Safari", "PayPal - Summary"));
+    assert!(!filters.is_excluded("Safari", "Pay with PayPal"));
+    assert!(filters.is_valid("Code", "core.rs"));
+    assert!(!filters.is_valid("Code", "core.ts"));
+}
+
+#[test]
+fn test_invalid_regex_falls_back_to_substring() {
+    let filters = filters(&["title:bank("], &[]);
+    assert!(filters.is_excluded("Safari", "my bank( account"));
+}
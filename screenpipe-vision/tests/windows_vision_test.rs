@@ -1,11 +1,14 @@
 #[cfg(target_os = "windows")]
 #[cfg(test)]
 mod tests {
-    use screenpipe_vision::capture_screenshot_by_window::{CapturedWindow, WindowFilters};
+    use screenpipe_vision::capture_screenshot_by_window::{
+        CaptureMode, CapturedWindow, WindowFilters,
+    };
     use screenpipe_vision::core::OcrTaskData;
     use screenpipe_vision::monitor::get_default_monitor;
-    use screenpipe_vision::{process_ocr_task, OcrEngine};
-    use std::sync::Arc;
+    use screenpipe_vision::{process_ocr_task, OcrEngine, PrivacyBlocklist};
+    use screenpipe_vision::frame_rate::AdaptiveFpsConfig;
+    use std::sync::{Arc, RwLock};
     use std::{path::PathBuf, time::Instant};
     use tokio::sync::mpsc;
 
@@ -35,11 +38,13 @@ mod tests {
             image,
             is_focused: true,
             process_id: 1234,
+            frame_rect: None,
         }];
 
         let result = process_ocr_task(
             OcrTaskData {
                 image: image_arc,
+                phash: 0,
                 window_images,
                 frame_number,
                 timestamp,
@@ -47,6 +52,8 @@ mod tests {
             },
             &ocr_engine,
             vec![],
+            false,
+            false,
         )
         .await;
 
@@ -76,8 +83,14 @@ mod tests {
             ocr_engine,
             monitor,
             window_filters, // window filters as empty vec
+            Arc::new(PrivacyBlocklist::default()),
             vec![],         // languages as empty vec
             save_text_files_flag,
+            false,
+            false,
+            Arc::new(vec![]),
+            CaptureMode::Monitor,
+            Arc::new(RwLock::new(AdaptiveFpsConfig::default())),
         ));
 
         // Wait for a short duration to allow some captures to occur